@@ -27,6 +27,7 @@ const DEFAULT_BROKER_URL = "ws://localhost:9001";
 
 const DEFAULT_TOPICS = [
     MQTT_TOPICS.TELEMETRY_ALL,
+    MQTT_TOPICS.TELEMETRY_BATCH,
     MQTT_TOPICS.HEARTBEAT_ALL,
     MQTT_TOPICS.ALERTS,
 ];
@@ -5,6 +5,8 @@ import type {
     Heartbeat,
     PipeEnvironment,
     MqttMessage,
+    TelemetryBatch,
+    TelemetryPayload,
 } from "@/types/aetheris";
 
 export type ConnectionStatus = "connecting" | "connected" | "disconnected" | "error";
@@ -100,8 +102,22 @@ export const useTelemetryStore = create<TelemetryState>((set, get) => ({
 
             // Handle telemetry: aetheris/telemetry/{robot_id}
             if (topic.startsWith("aetheris/telemetry/")) {
-                const msg = data as MqttMessage<RobotState>;
-                get().updateRobot(msg.payload);
+                const msg = data as MqttMessage<TelemetryPayload>;
+                if (msg.payload.kind !== "delta") {
+                    get().updateRobot(msg.payload);
+                } else {
+                    // A delta needs the robot's previous state
+                    const { kind: _, ...delta } = msg.payload;
+                    const previous = get().robots[delta.id];
+                    if (previous) {
+                        get().updateRobot({ ...previous, ...delta });
+                    }
+                }
+            }
+            // Handle batched telemetry: aetheris/telemetry_batch
+            else if (topic === "aetheris/telemetry_batch") {
+                const msg = data as MqttMessage<TelemetryBatch>;
+                msg.payload.states.forEach((state) => get().updateRobot(state));
             }
             // Handle heartbeat: aetheris/heartbeat/{robot_id}
             else if (topic.startsWith("aetheris/heartbeat/")) {
@@ -23,6 +23,21 @@ export interface Velocity {
     vz: number;
 }
 
+/** Attitude in radians (yaw about +z from +x, pitch nose-up, roll) */
+export interface Orientation {
+    yaw: number;
+    pitch: number;
+    roll: number;
+}
+
+/** Unit quaternion w + xi + yj + zk; orientations stay Euler on the wire */
+export interface Quaternion {
+    w: number;
+    x: number;
+    y: number;
+    z: number;
+}
+
 // ============================================================================
 // ROBOT TYPES & STATE
 // ============================================================================
@@ -60,6 +75,8 @@ export interface RobotState {
     position: Position;
     /** Current velocity vector */
     velocity: Velocity;
+    /** Current attitude (missing from older engines) */
+    orientation?: Orientation;
     /** Battery level (0.0 - 100.0) */
     battery: number;
     /** Signal strength (0.0 - 100.0) */
@@ -74,6 +91,104 @@ export interface RobotState {
     timestamp: number;
 }
 
+/** Fields of a RobotState that changed since the previous report */
+export type RobotStateDelta = Pick<RobotState, "id" | "timestamp"> &
+    Partial<Omit<RobotState, "id" | "timestamp">>;
+
+/** Telemetry topic payload: a full state unless tagged as a delta */
+export type TelemetryPayload =
+    | (RobotState & { kind?: "full" })
+    | (RobotStateDelta & { kind: "delta" });
+
+/** Telemetry of several robots in one message, applied in order */
+export interface TelemetryBatch {
+    /** Component that assembled the batch */
+    source: string;
+    /** Unix timestamp the batch was assembled (milliseconds) */
+    timestamp: number;
+    states: RobotState[];
+}
+
+// ============================================================================
+// PATROL ROUTES
+// ============================================================================
+
+/** One stop on a patrol route */
+export interface Waypoint {
+    position: Position;
+    /** How long to hold position on arrival (seconds) */
+    dwell_secs?: number;
+    /** Scan to perform while dwelling */
+    scan?: ScanType;
+}
+
+/** What a robot does after the last waypoint */
+export type RouteMode = "loop" | "one_shot";
+
+/** A named, ordered patrol path */
+export interface PatrolRoute {
+    /** Unique identifier (e.g., "ROUTE-A1") */
+    id: string;
+    /** Human-readable name */
+    name: string;
+    waypoints: Waypoint[];
+    mode: RouteMode;
+}
+
+/** Every known route, sorted by id, retained on `aetheris/routes` */
+export interface RouteLibrary {
+    routes: PatrolRoute[];
+}
+
+/** Ask for routes to be republished; no ids asks for the whole library */
+export interface RouteRequest {
+    route_ids?: string[];
+    /** Who is asking, for the per-requester rate limit */
+    requester?: string;
+}
+
+// ============================================================================
+// CHARGING STATIONS
+// ============================================================================
+
+/** A dock where robots recharge */
+export interface ChargingStation {
+    /** Unique identifier (e.g., "CHG-01") */
+    id: string;
+    position: Position;
+    /** Robots that can charge at the same time */
+    capacity: number;
+}
+
+/** How a robot's battery drains, for predicting how far it can still go */
+export interface BatteryProfile {
+    /** Battery used per meter travelled (percent) */
+    drain_per_meter: number;
+    /** Battery used per second while powered (percent) */
+    idle_drain_per_sec: number;
+    /** Speed the robot returns to a station at (m/s) */
+    return_speed: number;
+}
+
+// ============================================================================
+// GEOFENCES
+// ============================================================================
+
+/**
+ * A volume robots must keep out of: a polygon on the ground plane (x and z),
+ * between two heights (y) when they are given
+ */
+export interface Geofence {
+    /** Unique identifier (e.g., "FENCE-COMPRESSOR") */
+    id: string;
+    /** Corners of the footprint in order; their y is ignored */
+    polygon: Position[];
+    /** Heights the fence covers; every height when absent */
+    altitude?: AltitudeRange;
+    /** Robot types kept out; every type when absent */
+    robot_types?: RobotType[];
+}
+
 // ============================================================================
 // PIPELINE ENVIRONMENT
 // ============================================================================
@@ -100,6 +215,41 @@ export interface PipeEnvironment {
     timestamp: number;
 }
 
+// ============================================================================
+// PIPELINE TOPOLOGY
+// ============================================================================
+
+/** Pipe wall material */
+export type PipeMaterial = "carbon_steel" | "stainless_steel" | "polyethylene" | "composite";
+
+/** A straight run of pipe between two junctions or ends */
+export interface PipelineSection {
+    id: string;
+    start: Position;
+    end: Position;
+    /** Inner diameter in millimeters */
+    diameter_mm: number;
+    material: PipeMaterial;
+    /** Design pressure in bar */
+    design_pressure_bar: number;
+    /** Ids of the sections joined to this one */
+    neighbors: string[];
+}
+
+/** Fitted wall-thickness trend of a section (`GET /sections/wall-thickness`) */
+export interface SectionWallTrend {
+    section_id: string;
+    /** Latest fitted thickness in millimeters */
+    thickness_mm: number;
+    /** Loss rate in millimeters per year; negative when the fit thickens */
+    loss_rate_mm_per_year: number;
+    /** Whether the loss rate stands clear of the sample noise */
+    significant: boolean;
+    /** Unix timestamp (milliseconds) the wall is projected to reach the minimum */
+    breach_at?: number;
+    samples: number;
+}
+
 // ============================================================================
 // ANOMALY DETECTION
 // ============================================================================
@@ -138,8 +288,74 @@ export interface AnomalyReport {
     description: string;
     /** Unix timestamp (milliseconds) */
     timestamp: number;
-    /** Whether the anomaly has been acknowledged */
+    /** Whether the anomaly has been acknowledged (derived from `status`) */
     acknowledged: boolean;
+    /** Lifecycle status; absent while new */
+    status?: AnomalyStatus;
+    /** Lifecycle transitions, oldest first */
+    status_history?: StatusChange[];
+    /** Operator who acknowledged the anomaly */
+    acknowledged_by?: string;
+    /** Operator who resolved the anomaly or marked it a false positive */
+    resolved_by?: string;
+    /** Detections of the same event folded into this report; absent when 1 */
+    occurrence_count?: number;
+    /** Unix timestamp (milliseconds) of the latest folded detection */
+    last_seen?: number;
+    /** Times the severity was raised because nobody acknowledged the report */
+    escalation_count?: number;
+}
+
+/** Where an anomaly is in its operator lifecycle */
+export type AnomalyStatus =
+    | "new"
+    | "acknowledged"
+    | "investigating"
+    | "resolved"
+    | "false_positive";
+
+/** One lifecycle transition of an anomaly */
+export interface StatusChange {
+    status: AnomalyStatus;
+    /** Operator (or command source) that made the change */
+    by: string;
+    /** Unix timestamp (milliseconds) */
+    at: number;
+}
+
+/** How an operator closed an anomaly */
+export type Resolution = "fixed" | "false_positive";
+
+// ============================================================================
+// SECTION HEALTH
+// ============================================================================
+
+/** What lowered a section's health score */
+export type HealthFactorKind = "open_anomalies" | "hazard_margin" | "stale_readings";
+
+/** One deduction from a section's health score */
+export interface HealthFactor {
+    kind: HealthFactorKind;
+    /** Points taken off the score */
+    penalty: number;
+    detail: string;
+}
+
+/** Rolled-up health of one section, retained on `aetheris/health/{section_id}` */
+export interface SectionHealthReport {
+    section_id: string;
+    /** 0 (failing) to 100 (nothing wrong) */
+    score: number;
+    /** Unresolved anomalies on the section, every severity listed */
+    open_anomalies: Record<SeverityLevel, number>;
+    /** Latest environment reading of the section */
+    environment?: PipeEnvironment;
+    /** Unix timestamp (milliseconds) of the latest reading or robot report */
+    last_inspection?: number;
+    /** What lowered the score, largest deduction first */
+    factors?: HealthFactor[];
+    /** Unix timestamp (milliseconds) */
+    timestamp: number;
 }
 
 // ============================================================================
@@ -154,12 +370,22 @@ export type FaultType =
     | "motor_failure"
     | "gps_drift";
 
+/** Heights a robot operates at, as position y in meters */
+export interface AltitudeRange {
+    min: number;
+    max: number;
+}
+
 /** Robot configuration parameters */
 export interface RobotConfig {
     max_speed?: number;
     scan_interval?: number;
     heartbeat_interval?: number;
     low_battery_threshold?: number;
+    /** Replaces the scans of the robot's type */
+    supported_scans?: ScanType[];
+    /** Replaces the operating altitude of the robot's type */
+    operating_altitude?: AltitudeRange;
 }
 
 /** Commands that can be sent to robots */
@@ -172,7 +398,81 @@ export type Command =
     | { command: "investigate"; params: { anomaly_id: string } }
     | { command: "emergency_stop" }
     | { command: "inject_fault"; params: { fault_type: FaultType } }
-    | { command: "configure"; params: { config: RobotConfig } };
+    | { command: "clear_fault"; params: { fault_type: FaultType | null } }
+    | { command: "inject_leak"; params: { section_id: string; severity: SeverityLevel } }
+    | { command: "clear_leak"; params: { section_id: string | null } }
+    | { command: "configure"; params: { config: RobotConfig } }
+    | { command: "get_config" }
+    | { command: "acknowledge_anomaly"; params: { anomaly_id: string } }
+    | {
+          command: "resolve_anomaly";
+          params: { anomaly_id: string; resolution?: Resolution; force?: boolean };
+      }
+    | { command: "request_keyframe" }
+    | { command: "start_mission"; params: { plan: MissionPlan } }
+    | { command: "abort_mission"; params: { mission_id: string } }
+    | { command: "correct_position" };
+
+/** Privileges of whoever issues a command, lowest first */
+export type OperatorRole = "viewer" | "operator" | "admin";
+
+/** Identity a command is issued under */
+export interface Operator {
+    id: string;
+    role: OperatorRole;
+}
+
+// ============================================================================
+// MISSIONS
+// ============================================================================
+
+/** What a mission does when a step fails or times out (default "abort") */
+export type FailurePolicy = "abort" | "continue" | { retry: number };
+
+/** Robot condition a mission step waits for before its command is sent */
+export type Precondition =
+    | { min_battery: number }
+    | { status: RobotStatus }
+    | { near: { position: Position; radius: number } };
+
+/** One command of a mission */
+export interface MissionStep {
+    command: Command;
+    /** How long the robot has to answer the command, and to meet the preconditions */
+    timeout_secs: number;
+    on_failure?: FailurePolicy;
+    /** Conditions the robot must meet before the command is sent */
+    preconditions?: Precondition[];
+}
+
+/** An ordered list of commands for one robot */
+export interface MissionPlan {
+    id: string;
+    robot_id: string;
+    steps: MissionStep[];
+}
+
+/** Where a mission is */
+export type MissionState = "running" | "completed" | "failed" | "aborted";
+
+/** Mission progress, retained on `aetheris/missions/{robot_id}` */
+export interface MissionStatus {
+    mission_id: string;
+    robot_id: string;
+    state: MissionState;
+    /** Index of the current step, or of the last one run once ended */
+    step: number;
+    /** Number of steps in the plan */
+    steps: number;
+    /** Attempt of the current step, from 1 */
+    attempt: number;
+    /** Steps that failed and were skipped */
+    skipped?: number[];
+    /** Why the latest attempt failed */
+    error?: string;
+    /** Unix timestamp (milliseconds) */
+    timestamp: number;
+}
 
 // ============================================================================
 // MQTT MESSAGES
@@ -188,6 +488,21 @@ export interface MqttMessage<T> {
     timestamp: number;
     /** Message sequence number */
     seq: number;
+    /** Envelope schema version; absent on payloads from before versioning (1) */
+    version?: number;
+    /** Id of the command carried, echoed in its CommandResponse */
+    command_id?: string;
+    /** Operator who issued the command carried */
+    operator?: Operator;
+    /**
+     * Hex HMAC-SHA256. Commands: under the operator's key, of the compact
+     * JSON array [payload, operator, timestamp, command_id, expires_at].
+     * Everything else: under the source's key, of the canonical JSON (keys
+     * of every object sorted, no whitespace) of [payload, source, timestamp, seq]
+     */
+    signature?: string;
+    /** Unix timestamp after which the command carried must not be acted on (milliseconds) */
+    expires_at?: number;
 }
 
 /** Heartbeat message for connectivity monitoring */
@@ -209,19 +524,149 @@ export interface Heartbeat {
 }
 
 /** Command response from robot */
+export type CommandStatus = "accepted" | "in_progress" | "completed" | "failed";
+
 export interface CommandResponse {
     /** ID of the command being responded to */
     command_id: string;
     /** Robot ID */
     robot_id: string;
-    /** Whether command was accepted */
+    /** Whether command was accepted; false only with a failed status */
     success: boolean;
     /** Error message if failed */
     error?: string;
+    /** The robot's effective configuration, answering a get_config */
+    config?: RobotConfig;
+    /** Progress of a long-running command; absent means completed or failed per `success` */
+    status?: CommandStatus;
     /** Unix timestamp (milliseconds) */
     timestamp: number;
 }
 
+/** A command sent through the engine moved to another status */
+export interface CommandUpdate {
+    command_id: string;
+    robot_id: string;
+    /** Command variant name */
+    variant: string;
+    status: CommandStatus;
+    error?: string;
+    /** Unix timestamp of the change (milliseconds) */
+    at: number;
+}
+
+/** How the robots online when a command was broadcast answered it */
+export interface BroadcastResult {
+    /** ID of the broadcast command */
+    command_id: string;
+    /** Command variant name */
+    command: string;
+    /** Robots that carried the command out */
+    acked: string[];
+    /** Robots that refused it, or that it could not be sent to */
+    failed: string[];
+    /** Robots that never answered, including those that went offline */
+    non_responding: string[];
+    /** Unix timestamp the command was broadcast (milliseconds) */
+    started_at: number;
+    /** Unix timestamp the result was settled (milliseconds) */
+    completed_at: number;
+}
+
+/** Whether the engine is running */
+export type EngineState = "online" | "offline";
+
+/** Robots of one type by connectivity */
+export interface FleetCount {
+    connected: number;
+    offline: number;
+}
+
+/** A robot's link delay and clock skew, estimated from its heartbeats */
+export interface LinkQuality {
+    /**
+     * Median of engine receive time minus heartbeat timestamp (milliseconds):
+     * the one-way delay plus how far the robot's clock lags; negative when it runs ahead
+     */
+    offset_ms: number;
+    /** Median deviation from the offset (milliseconds) */
+    jitter_ms: number;
+    /** Heartbeats the estimate is drawn from */
+    samples: number;
+    /** The offset is beyond the engine's skew bound */
+    drifted: boolean;
+}
+
+/** An engine instance's view of the leader election */
+export interface LeaderStatus {
+    /** Instance id of the engine reporting */
+    instance_id: string;
+    /** Instance holding the lease, if any is known */
+    leader: string | null;
+    /** Times the leader has changed since the engine started */
+    changes: number;
+    /** Unix timestamp (milliseconds) of the last change */
+    since: number;
+}
+
+/**
+ * Engine liveness and fleet summary, retained on `aetheris/system/status` and
+ * republished periodically. The offline form is the engine's MQTT Last Will,
+ * published by the broker if the engine dies; its summary is empty.
+ */
+export interface SystemStatus {
+    /** MQTT client id of the engine */
+    engine_id: string;
+    state: EngineState;
+    /** Robots not offline */
+    connected_robots: number;
+    /** Seconds since the engine started */
+    uptime_secs?: number;
+    /** Connected and offline robots per type, every type listed */
+    fleet?: Partial<Record<RobotType, FleetCount>>;
+    /** Active anomalies nobody has acknowledged yet, per severity */
+    unacknowledged_anomalies?: Partial<Record<SeverityLevel, number>>;
+    /** Messages received per second since the previous status */
+    messages_per_sec?: number;
+    /** Messages missed per source, from gaps in envelope sequence numbers */
+    missed_messages?: Record<string, number>;
+    /** Periodic engine messages superseded before the engine could process them, per class */
+    dropped_messages?: Record<string, number>;
+    /** Link delay and clock skew per robot with enough heartbeats */
+    link_quality?: Record<string, LinkQuality>;
+    /** Present when the engine runs on a scaled or virtual clock: timestamps are simulated time */
+    simulated?: boolean;
+    /** Which engine instance leads, for engines running leader election */
+    leadership?: LeaderStatus;
+    /** Unix timestamp (milliseconds); for a Last Will, when the engine connected */
+    timestamp: number;
+}
+
+// ============================================================================
+// ENGINE HTTP BRIDGE
+// ============================================================================
+
+/**
+ * Event on the engine bridge's `/ws` websocket. A client that fell behind
+ * gets a `lagged` event with the number of events it missed.
+ */
+export type StreamEvent =
+    | { type: "telemetry"; data: RobotState }
+    | { type: "heartbeat"; data: Heartbeat }
+    | { type: "alert"; data: AnomalyReport }
+    | { type: "broadcast_result"; data: BroadcastResult }
+    | { type: "command_status"; data: CommandUpdate }
+    | { type: "lagged"; data: { skipped: number } };
+
+/** What a stored history record holds */
+export type HistoryKind = "telemetry" | "environment" | "anomaly";
+
+/** A record from the engine bridge's `/history` endpoint, oldest first */
+export type HistoryRecord =
+    | { kind: "telemetry"; data: RobotState }
+    | { kind: "environment"; data: PipeEnvironment }
+    | { kind: "anomaly"; data: AnomalyReport };
+
 // ============================================================================
 // MQTT TOPICS
 // ============================================================================
@@ -237,6 +682,9 @@ export const MQTT_TOPICS = {
     /** Telemetry wildcard subscription */
     TELEMETRY_ALL: "aetheris/telemetry/+",
 
+    /** Telemetry of many robots in one message */
+    TELEMETRY_BATCH: "aetheris/telemetry_batch",
+
     /** Robot heartbeat: aetheris/heartbeat/{robot_id} */
     heartbeat: (robotId: string) => `aetheris/heartbeat/${robotId}`,
 
@@ -255,6 +703,12 @@ export const MQTT_TOPICS = {
     /** Anomaly alerts */
     ALERTS: "aetheris/alerts",
 
+    /** Alerts of one severity: aetheris/alerts/{severity} */
+    alertsSeverity: (severity: SeverityLevel) => `aetheris/alerts/${severity}`,
+
+    /** Alerts raised a severity level for going unacknowledged */
+    ALERT_ESCALATIONS: "aetheris/alerts/escalations",
+
     /** Environment readings */
     environment: (sectionId: string) => `aetheris/environment/${sectionId}`,
 
@@ -266,6 +720,33 @@ export const MQTT_TOPICS = {
 
     /** System status */
     SYSTEM_STATUS: "aetheris/system/status",
+
+    /** Patrol route definitions (retained) */
+    routes: (routeId: string) => `aetheris/routes/${routeId}`,
+
+    /** Route definition wildcard */
+    ROUTES_ALL: "aetheris/routes/+",
+
+    /** Every route definition at once (retained) */
+    ROUTE_LIBRARY: "aetheris/routes",
+
+    /** Requests to republish route definitions */
+    ROUTE_REQUESTS: "aetheris/route_requests",
+
+    /** Section health rollups (retained) */
+    health: (sectionId: string) => `aetheris/health/${sectionId}`,
+
+    /** Section health wildcard */
+    HEALTH_ALL: "aetheris/health/+",
+
+    /** Progress of a robot's mission (retained) */
+    missions: (robotId: string) => `aetheris/missions/${robotId}`,
+
+    /** Mission progress wildcard */
+    MISSIONS_ALL: "aetheris/missions/+",
+
+    /** Outcomes of broadcast commands */
+    BROADCAST_RESULTS: "aetheris/broadcast_results",
 } as const;
 
 // ============================================================================
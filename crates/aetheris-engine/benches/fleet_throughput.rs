@@ -0,0 +1,97 @@
+//! Telemetry ingestion into the fleet from concurrent handlers
+//!
+//! 60 robots reporting at 10 Hz is 600 updates a second. Writers, each
+//! owning a slice of the robots, push a burst of updates while a reader
+//! scans the fleet the way the heartbeat monitor and dashboards do. The
+//! sharded fleet is compared with the same fleet behind one lock, which is
+//! how every update was serialized before.
+//!
+//! Run with `cargo bench -p aetheris-engine --bench fleet_throughput`.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use aetheris_engine::FleetManager;
+use aetheris_shared::{Position, RobotState, RobotType};
+
+const ROBOTS: usize = 60;
+const WRITERS: usize = 6;
+/// Ten seconds of the fleet at 10 Hz
+const UPDATES: usize = ROBOTS * 10 * 10;
+const TARGET_PER_SEC: f64 = 600.0;
+
+fn state(robot: usize, step: usize) -> RobotState {
+    let id = format!("RV-{robot:03}");
+    RobotState {
+        position: Position::new(step as f64, 0.0, robot as f64),
+        battery: 100.0 - (step % 100) as f64,
+        ..RobotState::new(id.parse().unwrap(), &id, RobotType::Rover)
+    }
+}
+
+/// Updates per second with `update` and `scan` racing from several threads
+fn run(update: impl Fn(RobotState) + Sync, scan: impl Fn() + Sync) -> f64 {
+    let done = AtomicBool::new(false);
+    let start = Instant::now();
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            while !done.load(Ordering::Relaxed) {
+                scan();
+            }
+        });
+        let writers: Vec<_> = (0..WRITERS)
+            .map(|writer| {
+                let update = &update;
+                scope.spawn(move || {
+                    let robots = (writer..ROBOTS).step_by(WRITERS).collect::<Vec<_>>();
+                    for step in 0..UPDATES / WRITERS {
+                        update(state(robots[step % robots.len()], step));
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        done.store(true, Ordering::Relaxed);
+    });
+    UPDATES as f64 / start.elapsed().as_secs_f64()
+}
+
+fn main() {
+    let timeout = Duration::from_secs(15);
+
+    let sharded = Arc::new(FleetManager::new(timeout));
+    let sharded_rate = run(
+        |state| {
+            sharded.update_robot(state);
+        },
+        || {
+            sharded.get_timed_out_robots();
+            sharded.summary();
+        },
+    );
+
+    let single = Mutex::new(FleetManager::new(timeout));
+    let single_rate = run(
+        |state| {
+            single.lock().unwrap().update_robot(state);
+        },
+        || {
+            let fleet = single.lock().unwrap();
+            fleet.get_timed_out_robots();
+            fleet.summary();
+        },
+    );
+
+    println!("fleet_throughput: {UPDATES} updates of {ROBOTS} robots from {WRITERS} writers");
+    for (name, rate) in [("single lock", single_rate), ("sharded", sharded_rate)] {
+        println!(
+            "  {name:<12} {rate:>12.0} updates/s  ({:.0}x the {TARGET_PER_SEC} updates/s of the fleet)",
+            rate / TARGET_PER_SEC
+        );
+    }
+    println!("  speedup      {:>12.2}x", sharded_rate / single_rate);
+}
@@ -0,0 +1,397 @@
+//! Command acknowledgment tracking
+//!
+//! Every command the engine publishes carries a `command_id` in its envelope,
+//! and robots echo it in their [`CommandResponse`]. Sent commands wait here
+//! until the response with their id arrives or the timeout passes. Matching
+//! is by id only: two commands sent back-to-back to the same robot are told
+//! apart even when their responses arrive out of order. A caller may wait on
+//! a command's outcome; the wait ends with the response, a timeout, or the
+//! command being dropped before it was ever delivered.
+//!
+//! Robots running long commands may answer more than once: `Accepted` and
+//! `InProgress` responses record progress and restart the timeout, and only
+//! a `Completed` or `Failed` one ends the wait.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::Serialize;
+use thiserror::Error;
+use tokio::sync::oneshot;
+
+use aetheris_shared::{CommandResponse, CommandStatus};
+
+use crate::config::{CheckConfig, ConfigChecker};
+use crate::fanout::PublishError;
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Acknowledgment tracking behavior
+#[derive(Debug, Clone, PartialEq)]
+pub struct AckConfig {
+    /// How long a sent command waits for its response
+    pub timeout: Duration,
+}
+
+impl Default for AckConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl CheckConfig for AckConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        checker.positive("timeout", self.timeout);
+    }
+}
+
+// ============================================================================
+// TRACKING
+// ============================================================================
+
+/// New id for a command the engine publishes
+pub fn new_command_id() -> String {
+    format!("CMD-{}", uuid::Uuid::new_v4().simple())
+}
+
+/// Why waiting on a command ended without a response
+#[derive(Debug, Error)]
+pub enum AckError {
+    #[error("no response to {command_id} from {robot_id} within {timeout:?}")]
+    Timeout {
+        command_id: String,
+        robot_id: String,
+        timeout: Duration,
+    },
+    #[error("{command_id} to {robot_id} was dropped before delivery")]
+    Dropped {
+        command_id: String,
+        robot_id: String,
+    },
+    #[error(transparent)]
+    Publish(#[from] PublishError),
+}
+
+/// A command sent and not yet answered
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AwaitingAck {
+    pub command_id: String,
+    pub robot_id: String,
+    /// Command variant name (see [`aetheris_shared::Command::name`])
+    pub variant: &'static str,
+    /// Unix timestamp the command was published (milliseconds)
+    pub sent_at: u64,
+    /// Unix timestamp after which the command times out (milliseconds)
+    pub deadline: u64,
+    /// Last progress the robot reported; `None` until it reports any
+    pub status: Option<CommandStatus>,
+}
+
+/// A tracked command moved to another status
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommandUpdate {
+    pub command_id: String,
+    pub robot_id: String,
+    /// Command variant name (see [`aetheris_shared::Command::name`])
+    pub variant: &'static str,
+    pub status: CommandStatus,
+    /// Why the command failed, from the robot or the timeout
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Unix timestamp of the change (milliseconds)
+    pub at: u64,
+}
+
+impl CommandUpdate {
+    /// The status a response to a `variant` command reports
+    pub fn reported(response: &CommandResponse, variant: &'static str, now: u64) -> Self {
+        Self {
+            command_id: response.command_id.clone(),
+            robot_id: response.robot_id.to_string(),
+            variant,
+            status: response.status(),
+            error: response.error.clone(),
+            at: now,
+        }
+    }
+
+    /// A command that reached its deadline without a final response
+    pub fn timed_out(awaiting: &AwaitingAck, now: u64) -> Self {
+        Self {
+            command_id: awaiting.command_id.clone(),
+            robot_id: awaiting.robot_id.clone(),
+            variant: awaiting.variant,
+            status: CommandStatus::Failed,
+            error: Some("no response before the acknowledgment timeout".into()),
+            at: now,
+        }
+    }
+}
+
+/// How a response related to the tracked commands
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResponseMatch {
+    /// Answered a tracked command for good, `latency_ms` after it was sent
+    Matched {
+        variant: &'static str,
+        latency_ms: u64,
+    },
+    /// Reported progress on a tracked command, which keeps waiting
+    Progress {
+        variant: &'static str,
+        status: CommandStatus,
+    },
+    /// The id belongs to a command sent to a different robot; ignored
+    WrongRobot { expected: String },
+    /// Not a tracked command: already answered, timed out, or not ours
+    Unknown,
+}
+
+type Waiter = oneshot::Sender<Result<CommandResponse, AckError>>;
+
+/// Pending acknowledgments and the callers waiting on them
+#[derive(Debug, Default)]
+pub struct CommandAcks {
+    config: AckConfig,
+    awaiting: HashMap<String, AwaitingAck>,
+    waiters: HashMap<String, Waiter>,
+}
+
+impl CommandAcks {
+    pub fn new(config: AckConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Register interest in the outcome of `command_id` before it is sent
+    pub fn wait_for(
+        &mut self,
+        command_id: &str,
+    ) -> oneshot::Receiver<Result<CommandResponse, AckError>> {
+        let (tx, rx) = oneshot::channel();
+        self.waiters.insert(command_id.to_string(), tx);
+        rx
+    }
+
+    /// Start the timeout of a command that was just published
+    pub fn sent(&mut self, command_id: &str, robot_id: &str, variant: &'static str, now: u64) {
+        self.awaiting.insert(
+            command_id.to_string(),
+            AwaitingAck {
+                command_id: command_id.to_string(),
+                robot_id: robot_id.to_string(),
+                variant,
+                sent_at: now,
+                deadline: now + self.config.timeout.as_millis() as u64,
+                status: None,
+            },
+        );
+    }
+
+    /// Match a response to the command it answers
+    pub fn on_response(&mut self, response: &CommandResponse, now: u64) -> ResponseMatch {
+        let Some(awaiting) = self.awaiting.get_mut(&response.command_id) else {
+            return ResponseMatch::Unknown;
+        };
+        if awaiting.robot_id != response.robot_id {
+            return ResponseMatch::WrongRobot {
+                expected: awaiting.robot_id.clone(),
+            };
+        }
+        let status = response.status();
+        if !status.is_final() {
+            awaiting.status = Some(status);
+            awaiting.deadline = now + self.config.timeout.as_millis() as u64;
+            return ResponseMatch::Progress {
+                variant: awaiting.variant,
+                status,
+            };
+        }
+        let awaiting = self
+            .awaiting
+            .remove(&response.command_id)
+            .expect("found above");
+        if let Some(waiter) = self.waiters.remove(&response.command_id) {
+            let _ = waiter.send(Ok(response.clone()));
+        }
+        ResponseMatch::Matched {
+            variant: awaiting.variant,
+            latency_ms: now.saturating_sub(awaiting.sent_at),
+        }
+    }
+
+    /// The command will never be sent (held and then expired or discarded,
+    /// or refused); its waiter gets `error`
+    pub fn abandon(&mut self, command_id: &str, error: AckError) {
+        self.awaiting.remove(command_id);
+        if let Some(waiter) = self.waiters.remove(command_id) {
+            let _ = waiter.send(Err(error));
+        }
+    }
+
+    /// Drop sent commands whose deadline passed, failing their waiters
+    pub fn expire(&mut self, now: u64) -> Vec<AwaitingAck> {
+        let expired: Vec<String> = self
+            .awaiting
+            .values()
+            .filter(|a| now > a.deadline)
+            .map(|a| a.command_id.clone())
+            .collect();
+        let timeout = self.config.timeout;
+        expired
+            .into_iter()
+            .filter_map(|command_id| {
+                let awaiting = self.awaiting.remove(&command_id)?;
+                if let Some(waiter) = self.waiters.remove(&command_id) {
+                    let _ = waiter.send(Err(AckError::Timeout {
+                        command_id,
+                        robot_id: awaiting.robot_id.clone(),
+                        timeout,
+                    }));
+                }
+                Some(awaiting)
+            })
+            .collect()
+    }
+
+    /// Sent commands still waiting for a response, oldest first
+    pub fn awaiting(&self) -> Vec<AwaitingAck> {
+        let mut awaiting: Vec<_> = self.awaiting.values().cloned().collect();
+        awaiting.sort_by(|a, b| {
+            a.sent_at
+                .cmp(&b.sent_at)
+                .then_with(|| a.command_id.cmp(&b.command_id))
+        });
+        awaiting
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const T0: u64 = 1_000_000;
+
+    fn response(command_id: &str, robot_id: &str, success: bool) -> CommandResponse {
+        CommandResponse {
+            command_id: command_id.into(),
+            robot_id: robot_id.parse().unwrap(),
+            success,
+            error: (!success).then(|| "busy".into()),
+            config: None,
+            status: None,
+            timestamp: T0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_back_to_back_commands_match_by_id() {
+        let mut acks = CommandAcks::default();
+        let first = acks.wait_for("CMD-1");
+        let second = acks.wait_for("CMD-2");
+        acks.sent("CMD-1", "RV-001", "move_to", T0);
+        acks.sent("CMD-2", "RV-001", "stop", T0 + 5);
+
+        // Answered in reverse order
+        assert_eq!(
+            acks.on_response(&response("CMD-2", "RV-001", false), T0 + 40),
+            ResponseMatch::Matched {
+                variant: "stop",
+                latency_ms: 35
+            }
+        );
+        assert_eq!(acks.awaiting().len(), 1);
+        acks.on_response(&response("CMD-1", "RV-001", true), T0 + 90);
+
+        assert!(first.await.unwrap().unwrap().success);
+        assert!(!second.await.unwrap().unwrap().success);
+        assert_eq!(
+            acks.on_response(&response("CMD-1", "RV-001", true), T0 + 95),
+            ResponseMatch::Unknown
+        );
+    }
+
+    #[tokio::test]
+    async fn test_response_from_other_robot_is_ignored() {
+        let mut acks = CommandAcks::default();
+        acks.sent("CMD-1", "RV-001", "stop", T0);
+        assert_eq!(
+            acks.on_response(&response("CMD-1", "RV-002", true), T0 + 10),
+            ResponseMatch::WrongRobot {
+                expected: "RV-001".into()
+            }
+        );
+        assert_eq!(acks.awaiting().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_commands_time_out() {
+        let mut acks = CommandAcks::new(AckConfig {
+            timeout: Duration::from_secs(10),
+        });
+        let waiter = acks.wait_for("CMD-1");
+        acks.sent("CMD-1", "CR-001", "perform_scan", T0);
+        acks.sent("CMD-2", "CR-001", "stop", T0 + 5_000);
+
+        assert!(acks.expire(T0 + 10_000).is_empty());
+        let expired = acks.expire(T0 + 10_001);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].command_id, "CMD-1");
+        assert!(matches!(
+            waiter.await.unwrap(),
+            Err(AckError::Timeout { command_id, .. }) if command_id == "CMD-1"
+        ));
+        // A late response no longer matches
+        assert_eq!(
+            acks.on_response(&response("CMD-1", "CR-001", true), T0 + 12_000),
+            ResponseMatch::Unknown
+        );
+    }
+
+    #[tokio::test]
+    async fn test_progress_keeps_the_command_waiting_and_restarts_its_timeout() {
+        let mut acks = CommandAcks::new(AckConfig {
+            timeout: Duration::from_secs(10),
+        });
+        let mut waiter = acks.wait_for("CMD-1");
+        acks.sent("CMD-1", "CR-001", "perform_scan", T0);
+
+        let mut progress = response("CMD-1", "CR-001", true);
+        progress.status = Some(CommandStatus::InProgress);
+        assert_eq!(
+            acks.on_response(&progress, T0 + 8_000),
+            ResponseMatch::Progress {
+                variant: "perform_scan",
+                status: CommandStatus::InProgress
+            }
+        );
+        assert!(waiter.try_recv().is_err());
+        assert!(acks.expire(T0 + 12_000).is_empty());
+        let awaiting = acks.awaiting();
+        assert_eq!(awaiting[0].status, Some(CommandStatus::InProgress));
+        assert_eq!(awaiting[0].deadline, T0 + 18_000);
+
+        let mut failed = response("CMD-1", "CR-001", false);
+        failed.status = Some(CommandStatus::Failed);
+        assert!(matches!(
+            acks.on_response(&failed, T0 + 15_000),
+            ResponseMatch::Matched {
+                latency_ms: 15_000,
+                ..
+            }
+        ));
+        let answer = waiter.await.unwrap().unwrap();
+        assert_eq!(answer.status(), CommandStatus::Failed);
+        assert!(acks.awaiting().is_empty());
+    }
+}
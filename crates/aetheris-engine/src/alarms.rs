@@ -0,0 +1,548 @@
+//! Section-scoped environment alarms
+//!
+//! Hazard assessment of environment readings is stateful: each
+//! (section, hazard) pair is an alarm that is Raised once, can be
+//! Acknowledged by an operator, and only Clears after readings stay below
+//! the threshold minus a hysteresis band for a hold time. A new
+//! [`AnomalyReport`] is created only on the Cleared → Raised transition;
+//! readings that keep exceeding the threshold are counted as occurrences of
+//! the existing alarm instead of re-alerting.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use aetheris_shared::{
+    AnomalyReport, AnomalyType, HazardThresholds, PipeEnvironment, SeverityLevel,
+};
+
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Environment hazard categories tracked per section
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HazardKind {
+    /// Hydrogen concentration above the alert level
+    H2Concentration,
+    /// Internal pressure above the safe limit
+    Overpressure,
+    /// Temperature above the safe limit
+    HighTemperature,
+}
+
+impl HazardKind {
+    pub const ALL: [HazardKind; 3] = [
+        HazardKind::H2Concentration,
+        HazardKind::Overpressure,
+        HazardKind::HighTemperature,
+    ];
+
+    /// The reading's value on this hazard's sensor channel
+    pub fn reading(&self, env: &PipeEnvironment) -> f64 {
+        match self {
+            HazardKind::H2Concentration => env.h2_concentration,
+            HazardKind::Overpressure => env.pressure,
+            HazardKind::HighTemperature => env.temperature,
+        }
+    }
+}
+
+/// Threshold with a hysteresis band below it that must be crossed to clear
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AlarmThreshold {
+    /// Value above which the alarm raises
+    pub raise_above: f64,
+    /// Width of the band below `raise_above` that does not clear the alarm
+    pub hysteresis: f64,
+}
+
+impl AlarmThreshold {
+    fn clear_below(&self) -> f64 {
+        self.raise_above - self.hysteresis
+    }
+}
+
+/// Alarm thresholds and clearing behavior
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlarmConfig {
+    /// H2 concentration in ppm (10% of the 40,000 ppm LEL)
+    pub h2_concentration: AlarmThreshold,
+    /// Pressure in bar
+    pub pressure: AlarmThreshold,
+    /// Temperature in Celsius
+    pub temperature: AlarmThreshold,
+    /// How long readings must stay below the clear level before clearing
+    #[serde(with = "crate::config::duration_secs")]
+    pub clear_hold: Duration,
+}
+
+impl Default for AlarmConfig {
+    fn default() -> Self {
+        // Raise levels match PipeEnvironment::is_hazardous
+        let hazard = HazardThresholds::default();
+        Self {
+            h2_concentration: AlarmThreshold {
+                raise_above: hazard.h2_ppm,
+                hysteresis: 400.0,
+            },
+            pressure: AlarmThreshold {
+                raise_above: hazard.pressure_bar,
+                hysteresis: 5.0,
+            },
+            temperature: AlarmThreshold {
+                raise_above: hazard.temperature_celsius,
+                hysteresis: 5.0,
+            },
+            clear_hold: Duration::from_secs(60),
+        }
+    }
+}
+
+impl AlarmConfig {
+    /// Raise levels of the three hazards
+    pub fn thresholds(&self) -> HazardThresholds {
+        HazardThresholds {
+            h2_ppm: self.h2_concentration.raise_above,
+            pressure_bar: self.pressure.raise_above,
+            temperature_celsius: self.temperature.raise_above,
+        }
+    }
+}
+
+impl CheckConfig for AlarmThreshold {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if !self.raise_above.is_finite() {
+            checker.error("raise_above", "must be a finite number", None);
+        }
+        // The clear level must sit strictly below the raise level
+        if !(self.hysteresis > 0.0 && self.hysteresis < self.raise_above) {
+            checker.error(
+                "hysteresis",
+                format!(
+                    "must be positive and below raise_above ({}), got {}",
+                    self.raise_above, self.hysteresis
+                ),
+                Some(format!("try {}", self.raise_above * 0.1)),
+            );
+        }
+    }
+}
+
+impl CheckConfig for AlarmConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        checker.check_section("h2_concentration", &self.h2_concentration);
+        checker.check_section("pressure", &self.pressure);
+        checker.check_section("temperature", &self.temperature);
+        checker.positive("clear_hold", self.clear_hold);
+    }
+}
+
+impl AlarmConfig {
+    fn threshold(&self, kind: HazardKind) -> AlarmThreshold {
+        match kind {
+            HazardKind::H2Concentration => self.h2_concentration,
+            HazardKind::Overpressure => self.pressure,
+            HazardKind::HighTemperature => self.temperature,
+        }
+    }
+}
+
+// ============================================================================
+// ALARM STATE
+// ============================================================================
+
+/// Lifecycle state of a section alarm
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlarmStatus {
+    Raised,
+    Acknowledged,
+    Cleared,
+}
+
+/// Current state of one (section, hazard) alarm
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlarmState {
+    pub section_id: String,
+    pub hazard: HazardKind,
+    pub status: AlarmStatus,
+    /// Report created when the alarm last raised
+    pub report_id: Option<String>,
+    /// Readings above the threshold since the alarm raised (including the first)
+    pub occurrences: u32,
+    /// Latest reading value for this hazard
+    pub last_value: f64,
+    /// Unix timestamp of the last exceeding reading (milliseconds)
+    pub last_exceeded: u64,
+    /// Unix timestamp readings first dropped below the clear level (milliseconds)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub below_since: Option<u64>,
+}
+
+/// Outcome of assessing a reading against one alarm
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlarmEvent {
+    /// Alarm transitioned Cleared → Raised; the report should be published
+    Raised(Box<AnomalyReport>),
+    /// Reading still exceeds the threshold of an active alarm
+    Occurrence {
+        report_id: String,
+        hazard: HazardKind,
+        occurrences: u32,
+    },
+    /// Alarm cleared after the hold time below the hysteresis band
+    Cleared {
+        report_id: String,
+        hazard: HazardKind,
+    },
+}
+
+/// Per-section alarm states for all environment hazards
+#[derive(Debug, Default)]
+pub struct EnvironmentAlarms {
+    config: AlarmConfig,
+    alarms: HashMap<(String, HazardKind), AlarmState>,
+}
+
+impl EnvironmentAlarms {
+    pub fn new(config: AlarmConfig) -> Self {
+        Self {
+            config,
+            alarms: HashMap::new(),
+        }
+    }
+
+    /// Assess a reading taken at `now_ms` and advance each hazard's alarm
+    pub fn assess(&mut self, reading: &PipeEnvironment, now_ms: u64) -> Vec<AlarmEvent> {
+        self.assess_excluding(reading, now_ms, &[])
+    }
+
+    /// Assess a reading, skipping the `excluded` hazards (e.g., channels
+    /// whose sensor is suspect). Their alarms keep their state untouched.
+    pub fn assess_excluding(
+        &mut self,
+        reading: &PipeEnvironment,
+        now_ms: u64,
+        excluded: &[HazardKind],
+    ) -> Vec<AlarmEvent> {
+        let mut events = Vec::new();
+        for kind in HazardKind::ALL {
+            if excluded.contains(&kind) {
+                continue;
+            }
+            let threshold = self.config.threshold(kind);
+            let value = kind.reading(reading);
+            let key = (reading.section_id.clone(), kind);
+            let hold_ms = self.config.clear_hold.as_millis() as u64;
+
+            let alarm = self.alarms.entry(key).or_insert_with(|| AlarmState {
+                section_id: reading.section_id.clone(),
+                hazard: kind,
+                status: AlarmStatus::Cleared,
+                report_id: None,
+                occurrences: 0,
+                last_value: value,
+                last_exceeded: 0,
+                below_since: None,
+            });
+            alarm.last_value = value;
+
+            if value > threshold.raise_above {
+                alarm.below_since = None;
+                alarm.last_exceeded = now_ms;
+                if alarm.status == AlarmStatus::Cleared {
+                    let report = build_report(reading, kind, value, threshold.raise_above);
+                    alarm.status = AlarmStatus::Raised;
+                    alarm.report_id = Some(report.id.clone());
+                    alarm.occurrences = 1;
+                    events.push(AlarmEvent::Raised(Box::new(report)));
+                } else {
+                    alarm.occurrences += 1;
+                    events.push(AlarmEvent::Occurrence {
+                        report_id: alarm.report_id.clone().unwrap_or_default(),
+                        hazard: kind,
+                        occurrences: alarm.occurrences,
+                    });
+                }
+                continue;
+            }
+
+            if alarm.status == AlarmStatus::Cleared {
+                continue;
+            }
+
+            if value >= threshold.clear_below() {
+                // Inside the hysteresis band: not clearing, restart the hold
+                alarm.below_since = None;
+                continue;
+            }
+
+            let below_since = *alarm.below_since.get_or_insert(now_ms);
+            if now_ms.saturating_sub(below_since) >= hold_ms {
+                alarm.status = AlarmStatus::Cleared;
+                alarm.below_since = None;
+                events.push(AlarmEvent::Cleared {
+                    report_id: alarm.report_id.clone().unwrap_or_default(),
+                    hazard: kind,
+                });
+            }
+        }
+        events
+    }
+
+    /// Acknowledge the active alarm that raised `report_id`.
+    ///
+    /// Returns false if no active alarm owns that report.
+    pub fn acknowledge(&mut self, report_id: &str) -> bool {
+        let alarm = self.alarms.values_mut().find(|alarm| {
+            alarm.status == AlarmStatus::Raised && alarm.report_id.as_deref() == Some(report_id)
+        });
+        match alarm {
+            Some(alarm) => {
+                alarm.status = AlarmStatus::Acknowledged;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move the alarms of section `from` to section `into`, after a
+    /// provisional section was merged. Where both have an alarm for a
+    /// hazard, an active one wins over a cleared one, then the one that
+    /// exceeded last.
+    pub fn rename_section(&mut self, from: &str, into: &str) {
+        for kind in HazardKind::ALL {
+            let Some(mut moved) = self.alarms.remove(&(from.to_string(), kind)) else {
+                continue;
+            };
+            moved.section_id = into.to_string();
+            let key = (into.to_string(), kind);
+            let keep_existing = self.alarms.get(&key).is_some_and(|existing| {
+                let active = |alarm: &AlarmState| alarm.status != AlarmStatus::Cleared;
+                (active(existing), existing.last_exceeded) >= (active(&moved), moved.last_exceeded)
+            });
+            if !keep_existing {
+                self.alarms.insert(key, moved);
+            }
+        }
+    }
+
+    pub fn get(&self, section_id: &str, hazard: HazardKind) -> Option<&AlarmState> {
+        self.alarms.get(&(section_id.to_string(), hazard))
+    }
+
+    /// All alarm states, ordered by section then hazard
+    pub fn snapshot(&self) -> Vec<AlarmState> {
+        let mut states: Vec<_> = self.alarms.values().cloned().collect();
+        states.sort_by(|a, b| {
+            a.section_id
+                .cmp(&b.section_id)
+                .then_with(|| a.hazard.cmp(&b.hazard))
+        });
+        states
+    }
+}
+
+fn build_report(
+    reading: &PipeEnvironment,
+    kind: HazardKind,
+    value: f64,
+    threshold: f64,
+) -> AnomalyReport {
+    let (anomaly_type, severity, description) = match kind {
+        HazardKind::H2Concentration => (
+            AnomalyType::Leak,
+            SeverityLevel::High,
+            format!(
+                "H2 concentration {:.0} ppm exceeds {:.0} ppm in {}",
+                value, threshold, reading.section_id
+            ),
+        ),
+        HazardKind::Overpressure => (
+            AnomalyType::Unknown,
+            SeverityLevel::High,
+            format!(
+                "Pressure {:.1} bar exceeds {:.1} bar in {}",
+                value, threshold, reading.section_id
+            ),
+        ),
+        HazardKind::HighTemperature => (
+            AnomalyType::TemperatureAnomaly,
+            SeverityLevel::Medium,
+            format!(
+                "Temperature {:.1} °C exceeds {:.1} °C in {}",
+                value, threshold, reading.section_id
+            ),
+        ),
+    };
+
+    AnomalyReport::new(
+        anomaly_type,
+        severity,
+        reading.position,
+        &reading.section_id,
+        "engine",
+        1.0,
+        description,
+    )
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetheris_shared::{Position, Timestamp};
+
+    fn reading(temperature: f64) -> PipeEnvironment {
+        PipeEnvironment {
+            section_id: "PIPE-002".into(),
+            pressure: 50.0,
+            temperature,
+            h2_concentration: 100.0,
+            wall_thickness: 10.0,
+            flow_rate: 500.0,
+            humidity: 45.0,
+            position: Position::origin(),
+            timestamp: Timestamp::from_millis(0),
+        }
+    }
+
+    fn raised(events: &[AlarmEvent]) -> Vec<&AnomalyReport> {
+        events
+            .iter()
+            .filter_map(|e| match e {
+                AlarmEvent::Raised(report) => Some(report.as_ref()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_default_raise_levels_match_hazard_thresholds() {
+        let config = AlarmConfig::default();
+        let hazard = HazardThresholds::default();
+        assert_eq!(config.h2_concentration.raise_above, hazard.h2_ppm);
+        assert_eq!(config.pressure.raise_above, hazard.pressure_bar);
+        assert_eq!(config.temperature.raise_above, hazard.temperature_celsius);
+    }
+
+    #[test]
+    fn test_alarm_lifecycle_creates_exactly_two_reports() {
+        let mut alarms = EnvironmentAlarms::default();
+        let mut reports = Vec::new();
+        let mut at = |alarms: &mut EnvironmentAlarms, secs: u64, temperature: f64| {
+            let events = alarms.assess(&reading(temperature), secs * 1000);
+            reports.extend(raised(&events).into_iter().cloned());
+            events
+        };
+
+        // Raise
+        at(&mut alarms, 0, 85.0);
+        let state = alarms.get("PIPE-002", HazardKind::HighTemperature).unwrap();
+        assert_eq!(state.status, AlarmStatus::Raised);
+        let first_id = state.report_id.clone().unwrap();
+
+        // Acknowledge, then keep exceeding: occurrences, no new report
+        assert!(alarms.acknowledge(&first_id));
+        let events = at(&mut alarms, 10, 90.0);
+        assert!(matches!(
+            events[0],
+            AlarmEvent::Occurrence { occurrences: 2, .. }
+        ));
+        at(&mut alarms, 20, 86.0);
+        let state = alarms.get("PIPE-002", HazardKind::HighTemperature).unwrap();
+        assert_eq!(state.status, AlarmStatus::Acknowledged);
+        assert_eq!(state.occurrences, 3);
+
+        // Inside the hysteresis band (75..80): never clears
+        at(&mut alarms, 30, 78.0);
+        at(&mut alarms, 200, 77.0);
+        assert_eq!(
+            alarms
+                .get("PIPE-002", HazardKind::HighTemperature)
+                .unwrap()
+                .status,
+            AlarmStatus::Acknowledged
+        );
+
+        // Below the band but not for the full hold time
+        at(&mut alarms, 210, 70.0);
+        at(&mut alarms, 260, 70.0);
+        // A band reading restarts the hold
+        at(&mut alarms, 265, 76.0);
+        at(&mut alarms, 270, 70.0);
+        let events = at(&mut alarms, 320, 70.0);
+        assert!(events.is_empty());
+
+        // Held below the band for 60 s: clears
+        let events = at(&mut alarms, 330, 70.0);
+        assert!(matches!(events[0], AlarmEvent::Cleared { .. }));
+
+        // Re-raise creates the second report
+        at(&mut alarms, 400, 82.0);
+        assert_eq!(reports.len(), 2);
+        assert_ne!(reports[0].id, reports[1].id);
+        assert_eq!(reports[0].anomaly_type, AnomalyType::TemperatureAnomaly);
+    }
+
+    #[test]
+    fn test_acknowledge_requires_raised_alarm() {
+        let mut alarms = EnvironmentAlarms::default();
+        assert!(!alarms.acknowledge("ANM-UNKNOWN"));
+
+        let events = alarms.assess(&reading(95.0), 0);
+        let id = raised(&events)[0].id.clone();
+        assert!(alarms.acknowledge(&id));
+        // Already acknowledged
+        assert!(!alarms.acknowledge(&id));
+    }
+
+    #[test]
+    fn test_alarms_are_scoped_per_section_and_hazard() {
+        let mut alarms = EnvironmentAlarms::default();
+        let mut hot_and_leaking = reading(95.0);
+        hot_and_leaking.h2_concentration = 5000.0;
+        let other_section = PipeEnvironment {
+            section_id: "PIPE-003".into(),
+            ..reading(95.0)
+        };
+
+        assert_eq!(raised(&alarms.assess(&hot_and_leaking, 0)).len(), 2);
+        assert_eq!(raised(&alarms.assess(&other_section, 0)).len(), 1);
+        assert_eq!(alarms.snapshot().len(), 6);
+        assert_eq!(alarms.snapshot()[0].section_id, "PIPE-002");
+    }
+
+    #[test]
+    fn test_excluded_hazard_keeps_its_alarm_state() {
+        let mut alarms = EnvironmentAlarms::default();
+        let excluded = [HazardKind::HighTemperature];
+        assert!(
+            alarms
+                .assess_excluding(&reading(95.0), 0, &excluded)
+                .is_empty()
+        );
+        assert_eq!(raised(&alarms.assess(&reading(95.0), 1_000)).len(), 1);
+
+        // Cool readings from a suspect sensor neither clear nor re-raise it
+        for secs in 2..200 {
+            assert!(
+                alarms
+                    .assess_excluding(&reading(20.0), secs * 1000, &excluded)
+                    .is_empty()
+            );
+        }
+        let state = alarms.get("PIPE-002", HazardKind::HighTemperature).unwrap();
+        assert_eq!(state.status, AlarmStatus::Raised);
+        assert_eq!(state.last_value, 95.0);
+    }
+}
@@ -0,0 +1,278 @@
+//! Folding of repeated engine alerts before they are published
+//!
+//! The engine raises a fresh report, with a fresh id, for every chaos command
+//! and every hazardous reading, so one leaking joint would flood the alerts
+//! topic with near-identical reports. Before publishing, a new report is
+//! compared with the unresolved reports published recently: one of the same
+//! type on the same section, within `radius` meters and seen within
+//! `window`, absorbs it by bumping its `occurrence_count` and `last_seen`
+//! instead. Only a rise in severity republishes the absorbing report, so an
+//! escalation reaches operators immediately.
+//!
+//! Fleet-health alerts on the system section are never folded: their
+//! positions are not where anything happened. Republished reports (same id)
+//! pass through and refresh the stored copy; a closed report forgets it.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use aetheris_shared::AnomalyReport;
+
+use crate::anomalies::SYSTEM_SECTION;
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// When a new engine alert is folded into a published one
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertDedupConfig {
+    /// How long a published report keeps absorbing repeats after it was last
+    /// seen
+    pub window: Duration,
+    /// Maximum straight-line distance in meters between the two reports
+    pub radius: f64,
+}
+
+impl Default for AlertDedupConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(300),
+            radius: 10.0,
+        }
+    }
+}
+
+impl CheckConfig for AlertDedupConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        checker.positive("window", self.window);
+        if !(self.radius >= 0.0 && self.radius.is_finite()) {
+            checker.error(
+                "radius",
+                format!(
+                    "must be a finite distance of at least 0, got {}",
+                    self.radius
+                ),
+                None,
+            );
+        }
+    }
+}
+
+// ============================================================================
+// ALERT DEDUP
+// ============================================================================
+
+/// What to do with an alert about to be published
+#[derive(Debug, Clone, PartialEq)]
+pub enum DedupVerdict {
+    /// Publish the report as it is
+    Publish(AnomalyReport),
+    /// The report raised the severity of `report`, which absorbed it and is
+    /// published in its place
+    Escalated(AnomalyReport),
+    /// Absorbed by an already published report; nothing to publish
+    Folded {
+        primary_id: String,
+        occurrence_count: u32,
+    },
+}
+
+/// Unresolved reports published recently, by id
+#[derive(Debug, Default)]
+pub struct AlertDedup {
+    config: AlertDedupConfig,
+    published: HashMap<String, AnomalyReport>,
+}
+
+/// Unix timestamp of the latest detection folded into `report`
+fn last_activity(report: &AnomalyReport) -> u64 {
+    report.last_seen.unwrap_or(report.timestamp.as_millis())
+}
+
+impl AlertDedup {
+    pub fn new(config: AlertDedupConfig) -> Self {
+        Self {
+            config,
+            published: HashMap::new(),
+        }
+    }
+
+    /// Number of reports that can still absorb repeats
+    pub fn len(&self) -> usize {
+        self.published.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.published.is_empty()
+    }
+
+    /// Decide whether `report` is published, timed by its `timestamp`
+    pub fn admit(&mut self, report: AnomalyReport) -> DedupVerdict {
+        let now = report.timestamp.as_millis();
+        let window_ms = self.config.window.as_millis() as u64;
+        self.published
+            .retain(|_, r| now.saturating_sub(last_activity(r)) <= window_ms);
+
+        if report.status.is_closed() {
+            self.published.remove(&report.id);
+            return DedupVerdict::Publish(report);
+        }
+        if let Some(stored) = self.published.get_mut(&report.id) {
+            let occurrence_count = stored.occurrence_count.max(report.occurrence_count);
+            let last_seen = stored.last_seen.max(report.last_seen);
+            *stored = AnomalyReport {
+                occurrence_count,
+                last_seen,
+                ..report
+            };
+            return DedupVerdict::Publish(stored.clone());
+        }
+        if report.section_id == SYSTEM_SECTION {
+            return DedupVerdict::Publish(report);
+        }
+
+        let Some(stored) = self.find_match(&report) else {
+            self.published.insert(report.id.clone(), report.clone());
+            return DedupVerdict::Publish(report);
+        };
+        stored.occurrence_count += report.occurrence_count;
+        stored.last_seen = Some(last_activity(stored).max(now));
+        if report.severity > stored.severity {
+            stored.severity = report.severity;
+            stored.confidence = report.confidence;
+            stored.description = report.description;
+            return DedupVerdict::Escalated(stored.clone());
+        }
+        DedupVerdict::Folded {
+            primary_id: stored.id.clone(),
+            occurrence_count: stored.occurrence_count,
+        }
+    }
+
+    /// Nearest published report that absorbs `report`
+    fn find_match(&mut self, report: &AnomalyReport) -> Option<&mut AnomalyReport> {
+        self.published
+            .values_mut()
+            .filter(|r| r.anomaly_type == report.anomaly_type)
+            .filter(|r| r.section_id == report.section_id)
+            .map(|r| (r.position.distance_to(&report.position), r))
+            .filter(|(distance, _)| *distance <= self.config.radius)
+            .min_by(|x, y| x.0.total_cmp(&y.0))
+            .map(|(_, r)| r)
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetheris_shared::{AnomalyStatus, AnomalyType, Position, SeverityLevel, Timestamp};
+
+    const T0: u64 = 1_000_000;
+
+    fn leak(x: f64, severity: SeverityLevel, at: u64) -> AnomalyReport {
+        AnomalyReport {
+            timestamp: Timestamp::from_millis(at),
+            ..AnomalyReport::new(
+                AnomalyType::Leak,
+                severity,
+                Position::new(x, 0.0, 0.0),
+                "PIPE-001",
+                "engine",
+                0.9,
+                "Potential leak signature detected",
+            )
+        }
+    }
+
+    fn published(verdict: DedupVerdict) -> AnomalyReport {
+        match verdict {
+            DedupVerdict::Publish(report) | DedupVerdict::Escalated(report) => report,
+            folded => panic!("expected a publish, got {folded:?}"),
+        }
+    }
+
+    #[test]
+    fn test_repeats_within_the_radius_are_folded() {
+        let mut dedup = AlertDedup::new(AlertDedupConfig::default());
+        let first = published(dedup.admit(leak(0.0, SeverityLevel::Medium, T0)));
+
+        // Exactly on the radius still counts as the same event
+        assert_eq!(
+            dedup.admit(leak(10.0, SeverityLevel::Medium, T0 + 1_000)),
+            DedupVerdict::Folded {
+                primary_id: first.id.clone(),
+                occurrence_count: 2,
+            }
+        );
+        // Just past it is a new event
+        let beyond = published(dedup.admit(leak(10.01, SeverityLevel::Medium, T0 + 2_000)));
+        assert_ne!(beyond.id, first.id);
+        assert_eq!(dedup.len(), 2);
+
+        // Another type, or another section, is never folded
+        let mut other_type = leak(0.0, SeverityLevel::Medium, T0 + 3_000);
+        other_type.anomaly_type = AnomalyType::Corrosion;
+        assert!(matches!(dedup.admit(other_type), DedupVerdict::Publish(_)));
+        let mut other_section = leak(0.0, SeverityLevel::Medium, T0 + 3_000);
+        other_section.section_id = "PIPE-002".into();
+        assert!(matches!(
+            dedup.admit(other_section),
+            DedupVerdict::Publish(_)
+        ));
+    }
+
+    #[test]
+    fn test_escalation_republishes_the_folded_report() {
+        let mut dedup = AlertDedup::new(AlertDedupConfig::default());
+        let first = published(dedup.admit(leak(0.0, SeverityLevel::Medium, T0)));
+        dedup.admit(leak(2.0, SeverityLevel::Low, T0 + 1_000));
+
+        let DedupVerdict::Escalated(escalated) =
+            dedup.admit(leak(3.0, SeverityLevel::Critical, T0 + 2_000))
+        else {
+            panic!("a rise in severity must republish");
+        };
+        assert_eq!(escalated.id, first.id);
+        assert_eq!(escalated.severity, SeverityLevel::Critical);
+        assert_eq!(escalated.occurrence_count, 3);
+        assert_eq!(escalated.last_seen, Some(T0 + 2_000));
+
+        // Back to the same severity: folded again
+        assert!(matches!(
+            dedup.admit(leak(1.0, SeverityLevel::Critical, T0 + 3_000)),
+            DedupVerdict::Folded {
+                occurrence_count: 4,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_window_and_resolution_end_folding() {
+        let mut dedup = AlertDedup::new(AlertDedupConfig::default());
+        let first = published(dedup.admit(leak(0.0, SeverityLevel::High, T0)));
+        dedup.admit(leak(0.0, SeverityLevel::High, T0 + 200_000));
+
+        // The window slides with the last repeat
+        assert!(matches!(
+            dedup.admit(leak(0.0, SeverityLevel::High, T0 + 450_000)),
+            DedupVerdict::Folded { .. }
+        ));
+        let late = published(dedup.admit(leak(0.0, SeverityLevel::High, T0 + 800_000)));
+        assert_ne!(late.id, first.id);
+
+        // A resolved report no longer absorbs repeats
+        let mut resolved = late.clone();
+        resolved.set_status(AnomalyStatus::Resolved, "j.ortega", T0 + 801_000);
+        assert!(matches!(dedup.admit(resolved), DedupVerdict::Publish(_)));
+        assert!(dedup.is_empty());
+        let after = published(dedup.admit(leak(0.0, SeverityLevel::High, T0 + 802_000)));
+        assert_ne!(after.id, late.id);
+    }
+}
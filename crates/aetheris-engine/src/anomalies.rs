@@ -0,0 +1,1184 @@
+//! Active anomaly set with duplicate and cross-origin merging
+//!
+//! The same physical event often arrives as several reports: a robot
+//! republishes its finding, or a robot reports a leak and the engine's hazard
+//! assessment raises its own alert for the same reading seconds later.
+//! Reports are folded together when they share a fingerprint (type and
+//! spatial cell), and a robot report and an engine report of compatible type
+//! on the same section within a short window are merged with the robot's
+//! report as primary, since it carries the sensor evidence.
+//!
+//! On sections with a known axis, distances are measured along the section
+//! (chainage) instead of straight-line, so two reports a few meters apart
+//! laterally but on the same stretch of pipe still match.
+//!
+//! Operators assign significant (Medium+) anomalies to a named responder
+//! with a due time. The assignment rides on the primary report, survives
+//! merges and republished reports, keeps the anomaly from aging out, and
+//! must be closed (or overridden with `force`) before the anomaly is
+//! resolved. Assignments not done by their due time raise one alert each.
+//!
+//! Every anomaly moves through a forward-only lifecycle: new, acknowledged,
+//! investigating, then resolved or false positive. A republished report
+//! keeps the lifecycle it reached, and a closed report arriving on the
+//! alerts topic (the engine republishes resolutions) drops its anomaly.
+//!
+//! New anomalies nobody acknowledges in time are escalated: raised one
+//! severity level (Critical stays Critical) and republished, at most once per
+//! threshold of the severity they reached.
+//!
+//! Every anomaly created, changed or dropped is remembered until
+//! [`ActiveAnomalies::take_changes`], so the engine can write it through to
+//! an [`AnomalyStore`](crate::anomaly_store::AnomalyStore) and
+//! [`ActiveAnomalies::restore`] it after a restart.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::time::Duration;
+
+use aetheris_shared::{
+    AnomalyReport, AnomalyStatus, AnomalyType, Assignment, AssignmentState, Position, Resolution,
+    SeverityLevel, Timestamp,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::config::{CheckConfig, ConfigChecker};
+use crate::sections::SectionRegistry;
+
+/// `detected_by` of reports raised by the engine itself
+pub const ENGINE_ORIGIN: &str = "engine";
+
+/// Section of fleet-health alerts, which are never merged by location
+pub const SYSTEM_SECTION: &str = "SYSTEM";
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Duplicate and merge matching behavior
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeConfig {
+    /// Fingerprint cell size in meters (along the section axis when known)
+    pub grid_cell: f64,
+    /// How long a fingerprint keeps absorbing duplicates after last being seen
+    pub dedup_window: Duration,
+    /// Maximum time between a robot report and an engine report to merge them
+    pub cross_origin_window: Duration,
+    /// Maximum distance between a robot report and an engine report to merge them
+    pub cross_origin_distance: f64,
+    /// Anomalies with no new report for this long are dropped
+    pub retention: Duration,
+}
+
+impl Default for MergeConfig {
+    fn default() -> Self {
+        Self {
+            grid_cell: 10.0,
+            dedup_window: Duration::from_secs(300),
+            cross_origin_window: Duration::from_secs(30),
+            // Environment readings are per section; a section spans ~100 m
+            cross_origin_distance: 50.0,
+            retention: Duration::from_secs(6 * 60 * 60),
+        }
+    }
+}
+
+impl CheckConfig for MergeConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if self.grid_cell.is_nan() || self.grid_cell <= 0.0 {
+            checker.error("grid_cell", "must be greater than zero", None);
+        }
+        checker.positive("dedup_window", self.dedup_window);
+        checker.positive("cross_origin_window", self.cross_origin_window);
+        if self.cross_origin_distance.is_nan() || self.cross_origin_distance < 0.0 {
+            checker.error("cross_origin_distance", "must not be negative", None);
+        }
+        if self.retention < self.dedup_window {
+            checker.error(
+                "retention",
+                "must be at least dedup_window",
+                Some(format!("raise it to {:?} or more", self.dedup_window)),
+            );
+        }
+    }
+}
+
+/// When unacknowledged anomalies are raised a severity level
+#[derive(Debug, Clone, PartialEq)]
+pub struct EscalationConfig {
+    /// How long an anomaly of each severity may stay unacknowledged, from
+    /// detection or its previous escalation. Severities left out never
+    /// escalate.
+    pub after: BTreeMap<SeverityLevel, Duration>,
+    /// Also publish escalated reports on the escalations topic
+    pub publish_escalations: bool,
+}
+
+impl Default for EscalationConfig {
+    fn default() -> Self {
+        Self {
+            after: BTreeMap::from([
+                (SeverityLevel::Critical, Duration::from_secs(2 * 60)),
+                (SeverityLevel::High, Duration::from_secs(10 * 60)),
+                (SeverityLevel::Medium, Duration::from_secs(60 * 60)),
+            ]),
+            publish_escalations: true,
+        }
+    }
+}
+
+impl CheckConfig for EscalationConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        for (severity, after) in &self.after {
+            if after.is_zero() {
+                checker.error(
+                    "after",
+                    format!("{:?} threshold must be greater than zero", severity),
+                    Some("leave the severity out to never escalate it".into()),
+                );
+            }
+        }
+    }
+}
+
+// ============================================================================
+// ACTIVE ANOMALIES
+// ============================================================================
+
+/// One physical event and every report describing it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActiveAnomaly {
+    pub primary: AnomalyReport,
+    /// Reports folded into the primary (e.g., the engine's alert for a leak a
+    /// robot also reported)
+    pub supporting: Vec<AnomalyReport>,
+    /// Number of duplicate reports absorbed
+    pub duplicates: u32,
+    /// Unix timestamp of the most recent report (milliseconds)
+    pub last_seen: u64,
+}
+
+impl ActiveAnomaly {
+    /// Whether `id` is the primary or a supporting report
+    pub fn contains(&self, id: &str) -> bool {
+        self.primary.id == id || self.supporting.iter().any(|r| r.id == id)
+    }
+}
+
+/// An anomaly with the bookkeeping that must survive a restart
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredAnomaly {
+    pub anomaly: ActiveAnomaly,
+    /// When it was last escalated (milliseconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escalated_at: Option<u64>,
+    /// Its current assignment already raised an overdue alert
+    #[serde(default)]
+    pub overdue_alerted: bool,
+}
+
+/// An anomaly created or changed, or one no longer active
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnomalyChange {
+    Saved(Box<StoredAnomaly>),
+    Removed(String),
+}
+
+/// What happened to an ingested report
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeOutcome {
+    /// First report of a new event
+    New,
+    /// A republished report (same id) replaced the stored copy
+    Updated { primary_id: String },
+    /// Same fingerprint as an active anomaly
+    Duplicate { primary_id: String },
+    /// Folded into an existing anomaly as supporting evidence
+    Supporting { primary_id: String },
+    /// Became the primary of an existing anomaly, demoting `demoted_id`
+    Promoted { demoted_id: String },
+    /// A resolved or false-positive report; its anomaly, if still active,
+    /// was dropped
+    Closed { primary_id: Option<String> },
+}
+
+fn is_engine(report: &AnomalyReport) -> bool {
+    report.detected_by == ENGINE_ORIGIN
+}
+
+/// Whether a robot finding and an engine hazard can describe the same event
+fn compatible(a: AnomalyType, b: AnomalyType) -> bool {
+    use AnomalyType::*;
+    a == b
+        || matches!(
+            (a, b),
+            // The engine reports overpressure as Unknown
+            (Leak, PressureDrop)
+                | (PressureDrop, Leak)
+                | (PressureDrop, Unknown)
+                | (Unknown, PressureDrop)
+        )
+}
+
+/// Keep the assignment, lifecycle, and escalation of `previous` on a report
+/// replacing it as primary
+fn carry_over(previous: &mut AnomalyReport, report: &mut AnomalyReport) {
+    if report.assignment.is_none() {
+        report.assignment = previous.assignment.take();
+    }
+    if report.status.is_new() && !previous.status.is_new() {
+        report.status = previous.status;
+        report.acknowledged = previous.acknowledged;
+        report.acknowledged_by = previous.acknowledged_by.take();
+        report.status_history = std::mem::take(&mut previous.status_history);
+    }
+    if previous.escalation_count > report.escalation_count {
+        report.escalation_count = previous.escalation_count;
+        report.severity = report.severity.max(previous.severity);
+    }
+}
+
+/// Least severe anomaly that can be assigned to a responder
+pub const MIN_ASSIGNABLE_SEVERITY: SeverityLevel = SeverityLevel::Medium;
+
+/// Refused assignment or lifecycle change
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum AssignmentError {
+    #[error("no active anomaly \"{0}\"")]
+    UnknownAnomaly(String),
+    #[error("{severity:?} anomalies cannot be assigned (Medium or higher required)")]
+    SeverityTooLow { severity: SeverityLevel },
+    #[error("anomaly {0} is not assigned")]
+    NotAssigned(String),
+    #[error("anomaly {anomaly_id} is still assigned to {assignee}; resolve with force")]
+    OpenAssignment {
+        anomaly_id: String,
+        assignee: String,
+    },
+    #[error("anomaly {anomaly_id} is {from:?} and cannot become {to:?}")]
+    InvalidTransition {
+        anomaly_id: String,
+        from: AnomalyStatus,
+        to: AnomalyStatus,
+    },
+}
+
+/// An accepted assignment change
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssignmentChange {
+    pub primary_id: String,
+    pub previous: Option<Assignment>,
+    pub current: Assignment,
+}
+
+/// Active anomalies keyed by primary report id
+#[derive(Debug, Default)]
+pub struct ActiveAnomalies {
+    config: MergeConfig,
+    active: HashMap<String, ActiveAnomaly>,
+    /// Primary ids whose current assignment already raised an overdue alert
+    overdue_alerted: HashSet<String>,
+    /// When each anomaly was last escalated (milliseconds)
+    escalated_at: HashMap<String, u64>,
+    /// Primary ids created, changed or dropped since the last
+    /// `take_changes`
+    changed: BTreeSet<String>,
+}
+
+impl ActiveAnomalies {
+    pub fn new(config: MergeConfig) -> Self {
+        Self {
+            config,
+            active: HashMap::new(),
+            overdue_alerted: HashSet::new(),
+            escalated_at: HashMap::new(),
+            changed: BTreeSet::new(),
+        }
+    }
+
+    /// Put anomalies kept from a previous run back, as they were stored
+    pub fn restore(&mut self, stored: impl IntoIterator<Item = StoredAnomaly>) {
+        for stored in stored {
+            let primary_id = stored.anomaly.primary.id.clone();
+            if let Some(at) = stored.escalated_at {
+                self.escalated_at.insert(primary_id.clone(), at);
+            }
+            if stored.overdue_alerted {
+                self.overdue_alerted.insert(primary_id.clone());
+            }
+            self.active.insert(primary_id, stored.anomaly);
+        }
+    }
+
+    /// Anomalies created, changed or dropped since the previous call, by
+    /// primary id
+    pub fn take_changes(&mut self) -> Vec<AnomalyChange> {
+        std::mem::take(&mut self.changed)
+            .into_iter()
+            .map(|primary_id| match self.active.get(&primary_id) {
+                Some(anomaly) => AnomalyChange::Saved(Box::new(StoredAnomaly {
+                    anomaly: anomaly.clone(),
+                    escalated_at: self.escalated_at.get(&primary_id).copied(),
+                    overdue_alerted: self.overdue_alerted.contains(&primary_id),
+                })),
+                None => AnomalyChange::Removed(primary_id),
+            })
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.active.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.active.is_empty()
+    }
+
+    pub fn get(&self, primary_id: &str) -> Option<&ActiveAnomaly> {
+        self.active.get(primary_id)
+    }
+
+    /// Active anomalies, most recently seen first
+    pub fn all(&self) -> Vec<&ActiveAnomaly> {
+        let mut all: Vec<_> = self.active.values().collect();
+        all.sort_by(|a, b| {
+            b.last_seen
+                .cmp(&a.last_seen)
+                .then_with(|| a.primary.id.cmp(&b.primary.id))
+        });
+        all
+    }
+
+    /// Remove an anomaly once it is resolved
+    pub fn remove(&mut self, primary_id: &str) -> Option<ActiveAnomaly> {
+        self.changed.insert(primary_id.to_string());
+        self.overdue_alerted.remove(primary_id);
+        self.escalated_at.remove(primary_id);
+        self.active.remove(primary_id)
+    }
+
+    /// Move every report on section `from` to section `into`, after a
+    /// provisional section was merged. Returns the primary ids changed.
+    pub fn rename_section(&mut self, from: &str, into: &str) -> Vec<String> {
+        let mut renamed = Vec::new();
+        for (primary_id, anomaly) in &mut self.active {
+            let mut changed = false;
+            for report in std::iter::once(&mut anomaly.primary).chain(&mut anomaly.supporting) {
+                if report.section_id == from {
+                    report.section_id = into.to_string();
+                    changed = true;
+                }
+            }
+            if changed {
+                renamed.push(primary_id.clone());
+            }
+        }
+        renamed.sort();
+        self.changed.extend(renamed.iter().cloned());
+        renamed
+    }
+
+    /// Anomaly containing report `id`, as primary or supporting
+    fn find_mut(&mut self, id: &str) -> Result<&mut ActiveAnomaly, AssignmentError> {
+        self.active
+            .values_mut()
+            .find(|a| a.contains(id))
+            .ok_or_else(|| AssignmentError::UnknownAnomaly(id.to_string()))
+    }
+
+    /// Assign the anomaly containing report `anomaly_id` to `assignee`,
+    /// replacing any current assignment
+    pub fn assign(
+        &mut self,
+        anomaly_id: &str,
+        assignee: &str,
+        assigned_by: &str,
+        due_at: u64,
+        now: u64,
+    ) -> Result<AssignmentChange, AssignmentError> {
+        let anomaly = self.find_mut(anomaly_id)?;
+        if anomaly.primary.severity < MIN_ASSIGNABLE_SEVERITY {
+            return Err(AssignmentError::SeverityTooLow {
+                severity: anomaly.primary.severity,
+            });
+        }
+        let current = Assignment {
+            assignee: assignee.to_string(),
+            assigned_by: assigned_by.to_string(),
+            assigned_at: now,
+            due_at,
+            state: AssignmentState::Assigned,
+        };
+        let previous = anomaly.primary.assignment.replace(current.clone());
+        let primary_id = anomaly.primary.id.clone();
+        self.overdue_alerted.remove(&primary_id);
+        self.changed.insert(primary_id.clone());
+        Ok(AssignmentChange {
+            primary_id,
+            previous,
+            current,
+        })
+    }
+
+    /// Record the responder's progress on an assigned anomaly
+    pub fn update_assignment(
+        &mut self,
+        anomaly_id: &str,
+        state: AssignmentState,
+    ) -> Result<AssignmentChange, AssignmentError> {
+        let anomaly = self.find_mut(anomaly_id)?;
+        let primary_id = anomaly.primary.id.clone();
+        let Some(assignment) = anomaly.primary.assignment.as_mut() else {
+            return Err(AssignmentError::NotAssigned(primary_id));
+        };
+        let previous = assignment.clone();
+        assignment.state = state;
+        let current = assignment.clone();
+        self.changed.insert(primary_id.clone());
+        Ok(AssignmentChange {
+            primary_id,
+            previous: Some(previous),
+            current,
+        })
+    }
+
+    /// Move the anomaly containing report `anomaly_id` to `status`,
+    /// returning the updated primary report
+    pub fn set_status(
+        &mut self,
+        anomaly_id: &str,
+        status: AnomalyStatus,
+        by: &str,
+        now: u64,
+    ) -> Result<AnomalyReport, AssignmentError> {
+        let anomaly = self.find_mut(anomaly_id)?;
+        let primary = &mut anomaly.primary;
+        if !primary.status.can_become(status) {
+            return Err(AssignmentError::InvalidTransition {
+                anomaly_id: primary.id.clone(),
+                from: primary.status,
+                to: status,
+            });
+        }
+        primary.set_status(status, by, now);
+        let report = primary.clone();
+        self.changed.insert(report.id.clone());
+        Ok(report)
+    }
+
+    /// Fold later occurrences of the event behind report `anomaly_id` into
+    /// its primary report, returning the updated report
+    pub fn record_occurrences(
+        &mut self,
+        anomaly_id: &str,
+        occurrence_count: u32,
+        now: u64,
+    ) -> Result<AnomalyReport, AssignmentError> {
+        let anomaly = self.find_mut(anomaly_id)?;
+        anomaly.last_seen = anomaly.last_seen.max(now);
+        let primary = &mut anomaly.primary;
+        primary.occurrence_count = primary.occurrence_count.max(occurrence_count);
+        primary.last_seen = Some(primary.last_seen.unwrap_or(0).max(now));
+        let report = primary.clone();
+        self.changed.insert(report.id.clone());
+        Ok(report)
+    }
+
+    /// Resolve and remove the anomaly containing report `anomaly_id`. An open
+    /// assignment blocks this unless `force` is set.
+    pub fn resolve(
+        &mut self,
+        anomaly_id: &str,
+        resolution: Resolution,
+        force: bool,
+        by: &str,
+        now: u64,
+    ) -> Result<ActiveAnomaly, AssignmentError> {
+        let anomaly = self.find_mut(anomaly_id)?;
+        if !force
+            && let Some(assignment) = &anomaly.primary.assignment
+            && assignment.state.is_open()
+        {
+            return Err(AssignmentError::OpenAssignment {
+                anomaly_id: anomaly.primary.id.clone(),
+                assignee: assignment.assignee.clone(),
+            });
+        }
+        let primary_id = anomaly.primary.id.clone();
+        self.set_status(&primary_id, resolution.status(), by, now)?;
+        Ok(self.remove(&primary_id).expect("found above"))
+    }
+
+    /// Open (not done) assignments per assignee
+    pub fn open_assignments(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for assignment in self
+            .active
+            .values()
+            .filter_map(|a| a.primary.assignment.as_ref())
+        {
+            if assignment.state.is_open() {
+                *counts.entry(assignment.assignee.clone()).or_default() += 1;
+            }
+        }
+        counts
+    }
+
+    /// Active anomalies still New, per severity (every severity listed)
+    pub fn unacknowledged_by_severity(&self) -> BTreeMap<SeverityLevel, usize> {
+        let mut counts: BTreeMap<_, _> = SeverityLevel::ALL.map(|level| (level, 0)).into();
+        for anomaly in self.active.values() {
+            if anomaly.primary.status.is_new() {
+                *counts.entry(anomaly.primary.severity).or_default() += 1;
+            }
+        }
+        counts
+    }
+
+    /// Unresolved anomalies on `section_id`, per severity (every severity
+    /// listed)
+    pub fn open_in_section(&self, section_id: &str) -> BTreeMap<SeverityLevel, usize> {
+        let mut counts: BTreeMap<_, _> = SeverityLevel::ALL.map(|level| (level, 0)).into();
+        for anomaly in self.active.values() {
+            if anomaly.primary.section_id == section_id && !anomaly.primary.status.is_closed() {
+                *counts.entry(anomaly.primary.severity).or_default() += 1;
+            }
+        }
+        counts
+    }
+
+    /// Alerts for assignments that passed their due time without being done,
+    /// once per assignment
+    pub fn overdue_assignments(&mut self, now: u64) -> Vec<AnomalyReport> {
+        let mut alerts = Vec::new();
+        for anomaly in self.active.values() {
+            let Some(assignment) = &anomaly.primary.assignment else {
+                continue;
+            };
+            if !assignment.state.is_open()
+                || now <= assignment.due_at
+                || !self.overdue_alerted.insert(anomaly.primary.id.clone())
+            {
+                continue;
+            }
+            self.changed.insert(anomaly.primary.id.clone());
+            alerts.push(AnomalyReport {
+                timestamp: Timestamp::from_millis(now),
+                ..AnomalyReport::new(
+                    AnomalyType::Unknown,
+                    anomaly.primary.severity,
+                    anomaly.primary.position,
+                    SYSTEM_SECTION,
+                    ENGINE_ORIGIN,
+                    1.0,
+                    format!(
+                        "Assignment of {} to {} is {} min overdue",
+                        anomaly.primary.id,
+                        assignment.assignee,
+                        (now - assignment.due_at) / 60_000
+                    ),
+                )
+            });
+        }
+        alerts
+    }
+
+    /// Raise every new anomaly left unacknowledged past the threshold of its
+    /// severity by one level, counting the escalation. Returns the escalated
+    /// reports, by id.
+    pub fn escalate_stale(&mut self, config: &EscalationConfig, now: u64) -> Vec<AnomalyReport> {
+        let mut escalated = Vec::new();
+        for anomaly in self.active.values_mut() {
+            let report = &mut anomaly.primary;
+            if !report.status.is_new() || report.section_id == SYSTEM_SECTION {
+                continue;
+            }
+            let Some(after) = config.after.get(&report.severity) else {
+                continue;
+            };
+            let since = self
+                .escalated_at
+                .get(&report.id)
+                .copied()
+                .unwrap_or(report.timestamp.as_millis());
+            if now.saturating_sub(since) < after.as_millis() as u64 {
+                continue;
+            }
+            report.severity = report.severity.raised();
+            report.escalation_count += 1;
+            self.escalated_at.insert(report.id.clone(), now);
+            self.changed.insert(report.id.clone());
+            escalated.push(report.clone());
+        }
+        escalated.sort_by(|a, b| a.id.cmp(&b.id));
+        escalated
+    }
+
+    /// Fold `report` into the active set. Its section should already be
+    /// canonical.
+    pub fn ingest(&mut self, report: AnomalyReport, sections: &SectionRegistry) -> MergeOutcome {
+        let now = report.timestamp.as_millis();
+        let retention_ms = self.config.retention.as_millis() as u64;
+        // Assigned anomalies stay until the responder is done with them
+        let changed = &mut self.changed;
+        self.active.retain(|primary_id, a| {
+            let kept = now.saturating_sub(a.last_seen) <= retention_ms
+                || a.primary
+                    .assignment
+                    .as_ref()
+                    .is_some_and(|x| x.state.is_open());
+            if !kept {
+                changed.insert(primary_id.clone());
+            }
+            kept
+        });
+        let active = &self.active;
+        self.overdue_alerted.retain(|id| active.contains_key(id));
+        self.escalated_at.retain(|id, _| active.contains_key(id));
+
+        if report.status.is_closed() {
+            let primary_id = self
+                .active
+                .values()
+                .find(|a| a.contains(&report.id))
+                .map(|a| a.primary.id.clone());
+            if let Some(primary_id) = &primary_id {
+                self.remove(primary_id);
+            }
+            return MergeOutcome::Closed { primary_id };
+        }
+
+        let mut report = report;
+        if let Some(anomaly) = self.active.values_mut().find(|a| a.contains(&report.id)) {
+            anomaly.last_seen = anomaly.last_seen.max(now);
+            let primary_id = anomaly.primary.id.clone();
+            if anomaly.primary.id == report.id {
+                carry_over(&mut anomaly.primary, &mut report);
+                anomaly.primary = report;
+            } else if let Some(slot) = anomaly.supporting.iter_mut().find(|r| r.id == report.id) {
+                *slot = report;
+            }
+            self.changed.insert(primary_id.clone());
+            return MergeOutcome::Updated { primary_id };
+        }
+
+        if report.section_id == SYSTEM_SECTION {
+            self.insert_new(report);
+            return MergeOutcome::New;
+        }
+
+        if let Some(anomaly) = self.find_duplicate(&report, sections) {
+            anomaly.duplicates += 1;
+            anomaly.last_seen = anomaly.last_seen.max(now);
+            let primary_id = anomaly.primary.id.clone();
+            self.changed.insert(primary_id.clone());
+            return MergeOutcome::Duplicate { primary_id };
+        }
+
+        if let Some(primary_id) = self.find_cross_origin(&report, sections) {
+            let mut anomaly = self.active.remove(&primary_id).expect("found above");
+            anomaly.last_seen = anomaly.last_seen.max(now);
+            self.changed.insert(primary_id.clone());
+            if is_engine(&report) {
+                anomaly.supporting.push(report);
+                self.active.insert(primary_id.clone(), anomaly);
+                return MergeOutcome::Supporting { primary_id };
+            }
+            // The robot's report has the sensor evidence: it becomes primary
+            let mut demoted = std::mem::replace(&mut anomaly.primary, report);
+            carry_over(&mut demoted, &mut anomaly.primary);
+            let demoted_id = demoted.id.clone();
+            if self.overdue_alerted.remove(&demoted_id) {
+                self.overdue_alerted.insert(anomaly.primary.id.clone());
+            }
+            if let Some(at) = self.escalated_at.remove(&demoted_id) {
+                self.escalated_at.insert(anomaly.primary.id.clone(), at);
+            }
+            anomaly.supporting.insert(0, demoted);
+            self.changed.insert(anomaly.primary.id.clone());
+            self.active.insert(anomaly.primary.id.clone(), anomaly);
+            return MergeOutcome::Promoted { demoted_id };
+        }
+
+        self.insert_new(report);
+        MergeOutcome::New
+    }
+
+    fn insert_new(&mut self, report: AnomalyReport) {
+        self.changed.insert(report.id.clone());
+        self.active.insert(
+            report.id.clone(),
+            ActiveAnomaly {
+                last_seen: report.timestamp.as_millis(),
+                primary: report,
+                supporting: Vec::new(),
+                duplicates: 0,
+            },
+        );
+    }
+
+    fn find_duplicate(
+        &mut self,
+        report: &AnomalyReport,
+        sections: &SectionRegistry,
+    ) -> Option<&mut ActiveAnomaly> {
+        let window_ms = self.config.dedup_window.as_millis() as u64;
+        let cell = |section_id: &str, position: &Position| -> (i64, i64, i64) {
+            debug_assert!(
+                position.is_finite(),
+                "positions are bounds-checked on ingest"
+            );
+            let size = self.config.grid_cell;
+            match sections.get(section_id).and_then(|s| s.chainage(position)) {
+                Some(chainage) => ((chainage / size).floor() as i64, i64::MIN, i64::MIN),
+                None => (
+                    (position.x / size).floor() as i64,
+                    (position.y / size).floor() as i64,
+                    (position.z / size).floor() as i64,
+                ),
+            }
+        };
+        let fingerprint = (
+            report.anomaly_type,
+            report.section_id.as_str(),
+            cell(&report.section_id, &report.position),
+        );
+        let matching = self
+            .active
+            .values()
+            .filter(|a| report.timestamp.as_millis().abs_diff(a.last_seen) <= window_ms)
+            .find(|a| {
+                (
+                    a.primary.anomaly_type,
+                    a.primary.section_id.as_str(),
+                    cell(&a.primary.section_id, &a.primary.position),
+                ) == fingerprint
+            })
+            .map(|a| a.primary.id.clone())?;
+        self.active.get_mut(&matching)
+    }
+
+    fn find_cross_origin(
+        &self,
+        report: &AnomalyReport,
+        sections: &SectionRegistry,
+    ) -> Option<String> {
+        let window_ms = self.config.cross_origin_window.as_millis() as u64;
+        self.active
+            .values()
+            .filter(|a| is_engine(&a.primary) != is_engine(report))
+            .filter(|a| a.primary.section_id == report.section_id)
+            .filter(|a| compatible(a.primary.anomaly_type, report.anomaly_type))
+            .filter(|a| {
+                a.primary
+                    .timestamp
+                    .as_millis()
+                    .abs_diff(report.timestamp.as_millis())
+                    <= window_ms
+            })
+            .map(|a| {
+                let distance =
+                    sections.separation(&report.section_id, &a.primary.position, &report.position);
+                (a, distance)
+            })
+            .filter(|(_, distance)| *distance <= self.config.cross_origin_distance)
+            .min_by(|x, y| x.1.total_cmp(&y.1))
+            .map(|(a, _)| a.primary.id.clone())
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sections::{SectionInfo, UnknownSectionPolicy};
+
+    const T0: u64 = 1_000_000;
+
+    fn sections() -> SectionRegistry {
+        SectionRegistry::with_sections(
+            UnknownSectionPolicy::Provisional,
+            [
+                SectionInfo::new("PIPE-001", Position::new(0.0, 0.0, 0.0))
+                    .with_end(Position::new(100.0, 0.0, 0.0)),
+                SectionInfo::new("PIPE-002", Position::new(100.0, 0.0, 0.0))
+                    .with_end(Position::new(200.0, 0.0, 0.0)),
+            ],
+        )
+    }
+
+    fn leak(section: &str, x: f64, y: f64, by: &str, at: u64) -> AnomalyReport {
+        AnomalyReport {
+            timestamp: Timestamp::from_millis(at),
+            ..AnomalyReport::new(
+                AnomalyType::Leak,
+                SeverityLevel::High,
+                Position::new(x, y, 0.0),
+                section,
+                by,
+                0.9,
+                "leak",
+            )
+        }
+    }
+
+    #[test]
+    fn test_robot_and_engine_reports_of_one_leak_merge() {
+        let sections = sections();
+        let mut active = ActiveAnomalies::default();
+
+        // Engine alarm first, robot finding 20 m further along the section
+        let engine = leak("PIPE-001", 30.0, 0.0, ENGINE_ORIGIN, T0);
+        let robot = leak("PIPE-001", 50.0, 3.0, "CR-001", T0 + 4_000);
+        assert_eq!(active.ingest(engine.clone(), &sections), MergeOutcome::New);
+        assert_eq!(
+            active.ingest(robot.clone(), &sections),
+            MergeOutcome::Promoted {
+                demoted_id: engine.id.clone()
+            }
+        );
+
+        assert_eq!(active.len(), 1);
+        let anomaly = active.get(&robot.id).unwrap();
+        assert_eq!(anomaly.primary.detected_by, "CR-001");
+        assert_eq!(anomaly.supporting[0].id, engine.id);
+
+        // The other order keeps the robot as primary too
+        let mut active = ActiveAnomalies::default();
+        active.ingest(robot.clone(), &sections);
+        assert_eq!(
+            active.ingest(engine.clone(), &sections),
+            MergeOutcome::Supporting {
+                primary_id: robot.id.clone()
+            }
+        );
+        assert_eq!(active.len(), 1);
+    }
+
+    #[test]
+    fn test_unacknowledged_counts_follow_status() {
+        let sections = sections();
+        let mut active = ActiveAnomalies::default();
+        let first = leak("PIPE-001", 10.0, 0.0, "CR-001", T0);
+        let second = leak("PIPE-002", 150.0, 0.0, "CR-002", T0);
+        active.ingest(first.clone(), &sections);
+        active.ingest(second, &sections);
+        assert_eq!(active.unacknowledged_by_severity()[&SeverityLevel::High], 2);
+
+        active
+            .set_status(&first.id, AnomalyStatus::Acknowledged, "operator", T0 + 1)
+            .unwrap();
+        let counts = active.unacknowledged_by_severity();
+        assert_eq!(counts[&SeverityLevel::High], 1);
+        assert_eq!(counts.len(), SeverityLevel::ALL.len());
+        assert_eq!(counts.values().sum::<usize>(), 1);
+    }
+
+    #[test]
+    fn test_reports_on_different_sections_stay_separate() {
+        let sections = sections();
+        let mut active = ActiveAnomalies::default();
+        active.ingest(leak("PIPE-001", 95.0, 0.0, ENGINE_ORIGIN, T0), &sections);
+        let outcome = active.ingest(
+            leak("PIPE-002", 105.0, 0.0, "CR-001", T0 + 1_000),
+            &sections,
+        );
+        assert_eq!(outcome, MergeOutcome::New);
+        assert_eq!(active.len(), 2);
+
+        // Same section but outside the window, or an incompatible type
+        active.ingest(
+            leak("PIPE-001", 60.0, 0.0, "RV-001", T0 + 60_000),
+            &sections,
+        );
+        let mut crack = leak("PIPE-002", 110.0, 0.0, ENGINE_ORIGIN, T0 + 2_000);
+        crack.anomaly_type = AnomalyType::Crack;
+        active.ingest(crack, &sections);
+        assert_eq!(active.len(), 4);
+    }
+
+    #[test]
+    fn test_fingerprint_uses_chainage_on_known_sections() {
+        let sections = sections();
+        let mut active = ActiveAnomalies::default();
+        let first = leak("PIPE-001", 42.0, 0.0, "RV-001", T0);
+        active.ingest(first.clone(), &sections);
+
+        // 6 m off the axis but in the same 10 m chainage cell
+        let again = leak("PIPE-001", 44.0, 6.0, "RV-002", T0 + 30_000);
+        assert_eq!(
+            active.ingest(again, &sections),
+            MergeOutcome::Duplicate {
+                primary_id: first.id.clone()
+            }
+        );
+        // Republishing under the same id updates in place
+        let mut updated = first.clone();
+        updated.severity = SeverityLevel::Critical;
+        active.ingest(updated, &sections);
+        let anomaly = active.get(&first.id).unwrap();
+        assert_eq!(anomaly.duplicates, 1);
+        assert_eq!(anomaly.primary.severity, SeverityLevel::Critical);
+
+        // Off-topology positions fall back to a 3D grid
+        let mut loose = ActiveAnomalies::default();
+        loose.ingest(leak("SEC-X", 42.0, 0.0, "RV-001", T0), &sections);
+        let outcome = loose.ingest(leak("SEC-X", 44.0, 16.0, "RV-002", T0 + 1), &sections);
+        assert_eq!(outcome, MergeOutcome::New);
+
+        // Fleet-health alerts are never merged, and idle anomalies expire
+        let mut system = leak(SYSTEM_SECTION, 0.0, 0.0, "RV-001", T0);
+        system.anomaly_type = AnomalyType::Unknown;
+        loose.ingest(system.clone(), &sections);
+        system.id = "ANO-other".into();
+        assert_eq!(loose.ingest(system, &sections), MergeOutcome::New);
+        assert_eq!(loose.len(), 4);
+        loose.ingest(
+            leak("SEC-Y", 0.0, 0.0, "RV-003", T0 + 7 * 3_600_000),
+            &sections,
+        );
+        assert_eq!(loose.len(), 1);
+    }
+
+    #[test]
+    fn test_assignment_lifecycle() {
+        let sections = sections();
+        let mut active = ActiveAnomalies::default();
+        let report = leak("PIPE-001", 30.0, 0.0, "CR-001", T0);
+        active.ingest(report.clone(), &sections);
+
+        let change = active
+            .assign(&report.id, "j.ortega", "dashboard", T0 + 60_000, T0)
+            .unwrap();
+        assert_eq!(change.previous, None);
+        assert_eq!(active.open_assignments()["j.ortega"], 1);
+
+        // Republishing the report keeps the assignment
+        active.ingest(report.clone(), &sections);
+        let change = active
+            .update_assignment(&report.id, AssignmentState::InProgress)
+            .unwrap();
+        assert_eq!(change.current.state, AssignmentState::InProgress);
+
+        let change = active
+            .assign(&report.id, "a.kim", "supervisor", T0 + 120_000, T0 + 1_000)
+            .unwrap();
+        assert_eq!(change.previous.unwrap().assignee, "j.ortega");
+        assert_eq!(change.current.state, AssignmentState::Assigned);
+        assert_eq!(
+            active.open_assignments(),
+            BTreeMap::from([("a.kim".to_string(), 1)])
+        );
+
+        // An engine alarm merged into the anomaly does not disturb it
+        active.ingest(
+            leak("PIPE-001", 35.0, 0.0, ENGINE_ORIGIN, T0 + 2_000),
+            &sections,
+        );
+        active
+            .update_assignment(&report.id, AssignmentState::Done)
+            .unwrap();
+        assert!(active.open_assignments().is_empty());
+        assert_eq!(
+            active
+                .resolve(&report.id, Resolution::Fixed, false, "dashboard", T0)
+                .unwrap()
+                .primary
+                .id,
+            report.id
+        );
+        assert!(active.is_empty());
+    }
+
+    #[test]
+    fn test_only_medium_and_above_can_be_assigned() {
+        let sections = sections();
+        let mut active = ActiveAnomalies::default();
+        let minor = AnomalyReport {
+            severity: SeverityLevel::Low,
+            ..leak("PIPE-001", 30.0, 0.0, "CR-001", T0)
+        };
+        active.ingest(minor.clone(), &sections);
+        assert_eq!(
+            active.assign(&minor.id, "j.ortega", "dashboard", T0 + 60_000, T0),
+            Err(AssignmentError::SeverityTooLow {
+                severity: SeverityLevel::Low
+            })
+        );
+        assert_eq!(
+            active.update_assignment(&minor.id, AssignmentState::Done),
+            Err(AssignmentError::NotAssigned(minor.id.clone()))
+        );
+        assert_eq!(
+            active.assign("ANM-UNKNOWN", "j.ortega", "dashboard", T0, T0),
+            Err(AssignmentError::UnknownAnomaly("ANM-UNKNOWN".into()))
+        );
+    }
+
+    #[test]
+    fn test_resolving_open_assignment_requires_force() {
+        let sections = sections();
+        let mut active = ActiveAnomalies::default();
+        let report = leak("PIPE-001", 30.0, 0.0, "CR-001", T0);
+        active.ingest(report.clone(), &sections);
+        active
+            .assign(&report.id, "j.ortega", "dashboard", T0 + 60_000, T0)
+            .unwrap();
+
+        assert_eq!(
+            active.resolve(&report.id, Resolution::Fixed, false, "dashboard", T0),
+            Err(AssignmentError::OpenAssignment {
+                anomaly_id: report.id.clone(),
+                assignee: "j.ortega".into()
+            })
+        );
+        assert_eq!(active.len(), 1);
+        assert!(
+            active
+                .resolve(&report.id, Resolution::Fixed, true, "dashboard", T0)
+                .is_ok()
+        );
+        assert!(active.is_empty());
+    }
+
+    #[test]
+    fn test_status_lifecycle_survives_republish_and_closes() {
+        let sections = sections();
+        let mut active = ActiveAnomalies::default();
+        let report = leak("PIPE-001", 30.0, 0.0, "CR-001", T0);
+        active.ingest(report.clone(), &sections);
+
+        let acked = active
+            .set_status(
+                &report.id,
+                AnomalyStatus::Acknowledged,
+                "j.ortega",
+                T0 + 1_000,
+            )
+            .unwrap();
+        assert!(acked.acknowledged);
+        assert_eq!(acked.acknowledged_by.as_deref(), Some("j.ortega"));
+        assert_eq!(
+            active.set_status(&report.id, AnomalyStatus::Acknowledged, "a.kim", T0 + 2_000),
+            Err(AssignmentError::InvalidTransition {
+                anomaly_id: report.id.clone(),
+                from: AnomalyStatus::Acknowledged,
+                to: AnomalyStatus::Acknowledged,
+            })
+        );
+
+        // The robot republishing its report does not reset the lifecycle
+        active.ingest(report.clone(), &sections);
+        let primary = &active.get(&report.id).unwrap().primary;
+        assert_eq!(primary.status, AnomalyStatus::Acknowledged);
+        assert_eq!(primary.status_history.len(), 1);
+
+        let closed = active
+            .resolve(
+                &report.id,
+                Resolution::FalsePositive,
+                false,
+                "j.ortega",
+                T0 + 3_000,
+            )
+            .unwrap();
+        assert_eq!(closed.primary.status, AnomalyStatus::FalsePositive);
+        assert_eq!(closed.primary.resolved_by.as_deref(), Some("j.ortega"));
+
+        // The engine's republished resolution does not bring it back
+        assert_eq!(
+            active.ingest(closed.primary, &sections),
+            MergeOutcome::Closed { primary_id: None }
+        );
+        assert!(active.is_empty());
+        assert_eq!(
+            active.set_status("ANM-UNKNOWN", AnomalyStatus::Acknowledged, "a.kim", T0),
+            Err(AssignmentError::UnknownAnomaly("ANM-UNKNOWN".into()))
+        );
+    }
+
+    #[test]
+    fn test_overdue_assignment_alerts_once() {
+        let sections = sections();
+        let mut active = ActiveAnomalies::default();
+        let report = leak("PIPE-001", 30.0, 0.0, "CR-001", T0);
+        active.ingest(report.clone(), &sections);
+        let due = T0 + 60_000;
+        active
+            .assign(&report.id, "j.ortega", "dashboard", due, T0)
+            .unwrap();
+
+        assert!(active.overdue_assignments(due).is_empty());
+        let alerts = active.overdue_assignments(due + 5 * 60_000);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].section_id, SYSTEM_SECTION);
+        assert_eq!(alerts[0].severity, SeverityLevel::High);
+        assert!(alerts[0].description.contains("j.ortega is 5 min overdue"));
+        assert!(active.overdue_assignments(due + 10 * 60_000).is_empty());
+
+        // Reassigning starts a new deadline, and done work is never overdue
+        active
+            .assign(&report.id, "a.kim", "dashboard", due + 20 * 60_000, due)
+            .unwrap();
+        assert_eq!(active.overdue_assignments(due + 21 * 60_000).len(), 1);
+        active
+            .assign(&report.id, "a.kim", "dashboard", due + 30 * 60_000, due)
+            .unwrap();
+        active
+            .update_assignment(&report.id, AssignmentState::Done)
+            .unwrap();
+        assert!(active.overdue_assignments(due + 60 * 60_000).is_empty());
+    }
+
+    #[test]
+    fn test_unacknowledged_anomalies_escalate_once_per_window() {
+        let sections = sections();
+        let mut active = ActiveAnomalies::default();
+        let config = EscalationConfig::default();
+        let high = leak("PIPE-001", 30.0, 0.0, "CR-001", T0);
+        let acknowledged = leak("PIPE-002", 150.0, 0.0, "RV-001", T0);
+        active.ingest(high.clone(), &sections);
+        active.ingest(acknowledged.clone(), &sections);
+        active
+            .set_status(&acknowledged.id, AnomalyStatus::Acknowledged, "op", T0)
+            .unwrap();
+
+        assert!(active.escalate_stale(&config, T0 + 9 * 60_000).is_empty());
+        let escalated = active.escalate_stale(&config, T0 + 10 * 60_000);
+        assert_eq!(escalated.len(), 1);
+        assert_eq!(escalated[0].id, high.id);
+        assert_eq!(escalated[0].severity, SeverityLevel::Critical);
+        assert_eq!(escalated[0].escalation_count, 1);
+        let at = T0 + 10 * 60_000;
+        // Not again within the Critical window, then capped at Critical
+        assert!(active.escalate_stale(&config, at + 60_000).is_empty());
+        let escalated = active.escalate_stale(&config, at + 2 * 60_000);
+        assert_eq!(escalated[0].severity, SeverityLevel::Critical);
+        assert_eq!(escalated[0].escalation_count, 2);
+
+        // A robot republishing its finding keeps the escalation
+        active.ingest(high.clone(), &sections);
+        let primary = &active.get(&high.id).unwrap().primary;
+        assert_eq!(primary.severity, SeverityLevel::Critical);
+        assert_eq!(primary.escalation_count, 2);
+        assert!(active.escalate_stale(&config, at + 3 * 60_000).is_empty());
+
+        // Acknowledging stops it; severities without a threshold never escalate
+        active
+            .set_status(&high.id, AnomalyStatus::Acknowledged, "op", at)
+            .unwrap();
+        let low = AnomalyReport {
+            severity: SeverityLevel::Low,
+            ..leak("PIPE-001", 90.0, 0.0, "DR-001", T0)
+        };
+        active.ingest(low, &sections);
+        assert!(
+            active
+                .escalate_stale(&config, T0 + 24 * 60 * 60_000)
+                .is_empty()
+        );
+    }
+}
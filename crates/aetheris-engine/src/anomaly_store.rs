@@ -0,0 +1,437 @@
+//! Active anomalies kept across engine restarts
+//!
+//! An engine restarted mid-incident must not come back with a clean slate
+//! while the pipeline is still leaking. Every anomaly created, acknowledged,
+//! escalated, assigned or resolved is written through to an
+//! [`AnomalyStore`], and the open ones are loaded back at start.
+//!
+//! The file store is an append-only journal: one JSON record per line, each
+//! the full anomaly after a change or the removal of one. Replaying it in
+//! order gives the open set. It is compacted to one record per open anomaly
+//! when loaded and after `compact_after` appended records, written beside
+//! itself and renamed into place.
+//!
+//! A crash mid-append leaves a partial last line, and disks corrupt lines
+//! in the middle too. Records that cannot be read are moved to a
+//! `.quarantine` file beside the journal, logged and skipped; the rest still
+//! load.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::anomalies::{AnomalyChange, StoredAnomaly};
+use crate::config::{CheckConfig, ConfigChecker};
+use crate::robot_config::partial_path;
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Where active anomalies are kept between runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnomalyStoreConfig {
+    /// Journal file of anomaly changes; memory only when unset
+    pub path: Option<PathBuf>,
+    /// Records appended before the journal is compacted
+    pub compact_after: usize,
+}
+
+impl Default for AnomalyStoreConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            compact_after: 1000,
+        }
+    }
+}
+
+impl CheckConfig for AnomalyStoreConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if self
+            .path
+            .as_ref()
+            .is_some_and(|path| path.as_os_str().is_empty())
+        {
+            checker.error("path", "must not be empty", Some("leave it unset".into()));
+        }
+        if self.compact_after == 0 {
+            checker.error("compact_after", "must be greater than zero", None);
+        }
+    }
+}
+
+impl AnomalyStoreConfig {
+    /// The store this configuration describes
+    pub fn open(&self) -> Box<dyn AnomalyStore> {
+        match &self.path {
+            Some(path) => Box::new(JournalAnomalyStore::new(path, self.compact_after)),
+            None => Box::<MemoryAnomalyStore>::default(),
+        }
+    }
+}
+
+// ============================================================================
+// STORES
+// ============================================================================
+
+/// Storage of the active anomaly set
+pub trait AnomalyStore: fmt::Debug + Send {
+    /// Every anomaly still open when the store was last written. Records
+    /// that cannot be read are set aside, not returned as an error.
+    fn load(&mut self) -> io::Result<Vec<StoredAnomaly>>;
+
+    /// Record anomalies created, changed or removed
+    fn write(&mut self, changes: &[AnomalyChange]) -> io::Result<()>;
+}
+
+/// Anomalies kept for the life of the process only
+#[derive(Debug, Default)]
+pub struct MemoryAnomalyStore {
+    anomalies: BTreeMap<String, StoredAnomaly>,
+}
+
+impl AnomalyStore for MemoryAnomalyStore {
+    fn load(&mut self) -> io::Result<Vec<StoredAnomaly>> {
+        Ok(self.anomalies.values().cloned().collect())
+    }
+
+    fn write(&mut self, changes: &[AnomalyChange]) -> io::Result<()> {
+        apply(&mut self.anomalies, changes);
+        Ok(())
+    }
+}
+
+fn apply(anomalies: &mut BTreeMap<String, StoredAnomaly>, changes: &[AnomalyChange]) {
+    for change in changes {
+        match change {
+            AnomalyChange::Saved(stored) => {
+                anomalies.insert(stored.anomaly.primary.id.clone(), (**stored).clone());
+            }
+            AnomalyChange::Removed(primary_id) => {
+                anomalies.remove(primary_id);
+            }
+        }
+    }
+}
+
+/// One line of the journal
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum Record {
+    Save(Box<StoredAnomaly>),
+    Remove { id: String },
+}
+
+impl From<&AnomalyChange> for Record {
+    fn from(change: &AnomalyChange) -> Self {
+        match change {
+            AnomalyChange::Saved(stored) => Record::Save(stored.clone()),
+            AnomalyChange::Removed(primary_id) => Record::Remove {
+                id: primary_id.clone(),
+            },
+        }
+    }
+}
+
+/// Append-only JSONL journal of anomaly changes
+#[derive(Debug)]
+pub struct JournalAnomalyStore {
+    path: PathBuf,
+    compact_after: usize,
+    /// Open anomalies, as the journal replays
+    anomalies: BTreeMap<String, StoredAnomaly>,
+    /// Records appended since the last compaction
+    appended: usize,
+}
+
+impl JournalAnomalyStore {
+    pub fn new(path: impl Into<PathBuf>, compact_after: usize) -> Self {
+        Self {
+            path: path.into(),
+            compact_after,
+            anomalies: BTreeMap::new(),
+            appended: 0,
+        }
+    }
+
+    /// The journal file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Where unreadable records are moved
+    pub fn quarantine_path(&self) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(".quarantine");
+        self.path.with_file_name(name)
+    }
+
+    /// Rewrite the journal as one record per open anomaly
+    fn compact(&mut self) -> io::Result<()> {
+        let partial = partial_path(&self.path);
+        let mut journal = io::BufWriter::new(File::create(&partial)?);
+        for stored in self.anomalies.values() {
+            write_record(&mut journal, &Record::Save(Box::new(stored.clone())))?;
+        }
+        journal.into_inner().map_err(|e| e.into_error())?;
+        std::fs::rename(&partial, &self.path)?;
+        self.appended = 0;
+        Ok(())
+    }
+
+    fn quarantine(&self, bad: &[&[u8]]) -> io::Result<()> {
+        let mut quarantine = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.quarantine_path())?;
+        for line in bad {
+            quarantine.write_all(line)?;
+            quarantine.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+}
+
+fn write_record(out: &mut impl Write, record: &Record) -> io::Result<()> {
+    serde_json::to_writer(&mut *out, record)?;
+    out.write_all(b"\n")
+}
+
+impl AnomalyStore for JournalAnomalyStore {
+    fn load(&mut self) -> io::Result<Vec<StoredAnomaly>> {
+        let journal = match std::fs::read(&self.path) {
+            Ok(journal) => journal,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        self.anomalies.clear();
+        let mut bad = Vec::new();
+        let lines = journal.split(|&b| b == b'\n').enumerate();
+        for (index, line) in lines.filter(|(_, line)| !line.trim_ascii().is_empty()) {
+            match serde_json::from_slice(line) {
+                Ok(Record::Save(stored)) => {
+                    self.anomalies
+                        .insert(stored.anomaly.primary.id.clone(), *stored);
+                }
+                Ok(Record::Remove { id }) => {
+                    self.anomalies.remove(&id);
+                }
+                Err(e) => {
+                    warn!(path = %self.path.display(), line = index + 1, "Unreadable anomaly record quarantined: {}", e);
+                    bad.push(line);
+                }
+            }
+        }
+        // What was read is kept even if the cleanup cannot be written
+        if !bad.is_empty()
+            && let Err(e) = self.quarantine(&bad)
+        {
+            warn!(path = %self.quarantine_path().display(), "Failed to quarantine anomaly records: {}", e);
+        }
+        if !journal.is_empty() {
+            if let Err(e) = self.compact() {
+                warn!(path = %self.path.display(), "Failed to compact anomaly journal: {}", e);
+            }
+            info!(path = %self.path.display(), open = self.anomalies.len(), quarantined = bad.len(), "Anomaly journal loaded");
+        }
+        Ok(self.anomalies.values().cloned().collect())
+    }
+
+    fn write(&mut self, changes: &[AnomalyChange]) -> io::Result<()> {
+        apply(&mut self.anomalies, changes);
+        if self.appended + changes.len() >= self.compact_after {
+            return self.compact();
+        }
+        let mut records = Vec::new();
+        for change in changes {
+            write_record(&mut records, &Record::from(change))?;
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(&records)?;
+        self.appended += changes.len();
+        Ok(())
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anomalies::{ActiveAnomalies, ActiveAnomaly, EscalationConfig};
+    use crate::sections::SectionRegistry;
+    use aetheris_shared::{
+        AnomalyReport, AnomalyStatus, AnomalyType, Position, Resolution, SeverityLevel, Timestamp,
+    };
+
+    const T0: u64 = 1_767_225_600_000;
+
+    fn journal() -> JournalAnomalyStore {
+        let dir = std::env::temp_dir().join(format!("aetheris-anomalies-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        JournalAnomalyStore::new(dir.join("anomalies.jsonl"), 1000)
+    }
+
+    fn report(anomaly_type: AnomalyType, severity: SeverityLevel, x: f64) -> AnomalyReport {
+        AnomalyReport {
+            timestamp: Timestamp::from_millis(T0),
+            ..AnomalyReport::new(
+                anomaly_type,
+                severity,
+                Position::new(x, 0.0, 0.0),
+                "PIPE-001",
+                "RV-001",
+                0.9,
+                "found on patrol",
+            )
+        }
+    }
+
+    /// Write what changed in `active` through to `store`
+    fn record(active: &mut ActiveAnomalies, store: &mut dyn AnomalyStore) {
+        store.write(&active.take_changes()).unwrap();
+    }
+
+    #[test]
+    fn test_anomalies_survive_a_restart_with_ids_and_lifecycle() {
+        let sections = SectionRegistry::default();
+        let mut store = journal();
+        let mut active = ActiveAnomalies::default();
+
+        let leak = report(AnomalyType::Leak, SeverityLevel::High, 10.0);
+        let crack = report(AnomalyType::Crack, SeverityLevel::Medium, 40.0);
+        let dent = report(AnomalyType::Corrosion, SeverityLevel::Low, 80.0);
+        for report in [&leak, &crack, &dent] {
+            active.ingest(report.clone(), &sections);
+        }
+        record(&mut active, &mut store);
+        active
+            .set_status(
+                &leak.id,
+                AnomalyStatus::Acknowledged,
+                "ops-alice",
+                T0 + 1_000,
+            )
+            .unwrap();
+        active
+            .assign(&crack.id, "field-team", "ops-alice", T0 + 3_600_000, T0)
+            .unwrap();
+        let escalated = active.escalate_stale(&EscalationConfig::default(), T0 + 86_400_000);
+        assert!(!escalated.is_empty());
+        active
+            .resolve(&dent.id, Resolution::Fixed, false, "ops-alice", T0 + 2_000)
+            .unwrap();
+        record(&mut active, &mut store);
+        let before: Vec<ActiveAnomaly> = active.all().into_iter().cloned().collect();
+        let path = store.path().to_path_buf();
+        drop(active);
+        drop(store);
+
+        let mut store = JournalAnomalyStore::new(path, 1000);
+        let mut restored = ActiveAnomalies::default();
+        restored.restore(store.load().unwrap());
+        let after: Vec<ActiveAnomaly> = restored.all().into_iter().cloned().collect();
+        assert_eq!(after, before);
+        assert!(restored.get(&dent.id).is_none());
+        let leak_after = &restored.get(&leak.id).unwrap().primary;
+        assert_eq!(leak_after.status, AnomalyStatus::Acknowledged);
+        assert_eq!(leak_after.acknowledged_by.as_deref(), Some("ops-alice"));
+        assert_eq!(
+            restored
+                .get(&crack.id)
+                .unwrap()
+                .primary
+                .assignment
+                .as_ref()
+                .unwrap()
+                .assignee,
+            "field-team"
+        );
+        // Escalation is not repeated for the threshold it already passed
+        assert!(
+            restored
+                .escalate_stale(&EscalationConfig::default(), T0 + 86_400_000)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_unreadable_records_are_quarantined_and_the_rest_load() {
+        let sections = SectionRegistry::default();
+        let mut store = journal();
+        let mut active = ActiveAnomalies::default();
+        let leak = report(AnomalyType::Leak, SeverityLevel::Critical, 10.0);
+        let crack = report(AnomalyType::Crack, SeverityLevel::High, 40.0);
+        active.ingest(leak.clone(), &sections);
+        record(&mut active, &mut store);
+        // Corruption mid-file, then a crash halfway through an append
+        let mut journal = OpenOptions::new().append(true).open(store.path()).unwrap();
+        journal
+            .write_all(b"{\"op\":\"save\",\"anomaly\":\xff\xfe}\n")
+            .unwrap();
+        active.ingest(crack.clone(), &sections);
+        record(&mut active, &mut store);
+        journal
+            .write_all(b"{\"op\":\"remove\",\"id\":\"ANM-")
+            .unwrap();
+        drop(journal);
+
+        let mut reopened = JournalAnomalyStore::new(store.path(), 1000);
+        let mut restored = ActiveAnomalies::default();
+        restored.restore(reopened.load().unwrap());
+        assert!(restored.get(&leak.id).is_some());
+        assert!(restored.get(&crack.id).is_some());
+        let quarantined = std::fs::read(reopened.quarantine_path()).unwrap();
+        assert_eq!(
+            quarantined
+                .split(|&b| b == b'\n')
+                .filter(|l| !l.is_empty())
+                .count(),
+            2
+        );
+
+        // Compacted on load: the bad records are gone from the journal
+        let mut again = JournalAnomalyStore::new(store.path(), 1000);
+        assert_eq!(again.load().unwrap().len(), 2);
+        assert_eq!(std::fs::read(again.quarantine_path()).unwrap(), quarantined);
+    }
+
+    #[test]
+    fn test_journal_is_compacted_after_enough_records() {
+        let sections = SectionRegistry::default();
+        let mut store = journal();
+        store.compact_after = 4;
+        let mut active = ActiveAnomalies::default();
+        let leak = report(AnomalyType::Leak, SeverityLevel::High, 10.0);
+        active.ingest(leak.clone(), &sections);
+        record(&mut active, &mut store);
+        for at in 1..=5 {
+            active.ingest(
+                AnomalyReport {
+                    timestamp: Timestamp::from_millis(T0 + at),
+                    ..leak.clone()
+                },
+                &sections,
+            );
+            record(&mut active, &mut store);
+        }
+        let lines = std::fs::read_to_string(store.path())
+            .unwrap()
+            .lines()
+            .count();
+        assert!(lines < 4, "{lines} records after compaction");
+        let mut reopened = JournalAnomalyStore::new(store.path(), 4);
+        assert_eq!(reopened.load().unwrap()[0].anomaly.last_seen, T0 + 5);
+    }
+}
@@ -0,0 +1,461 @@
+//! Operator authorization of incoming commands
+//!
+//! Anyone with broker access can publish on the command topics, so when
+//! authorization is on every command must name the [`Operator`] issuing it,
+//! and the role configured for that operator must be allowed to send that
+//! command variant. The role an envelope declares is never trusted: an
+//! operator missing from the configuration is refused, and so is one
+//! claiming a role other than their own.
+//! Once any signing key is configured, commands must also carry an
+//! HMAC-SHA256 signature of [`MqttMessage::signing_input`] under their
+//! operator's key, so an operator can't be impersonated. Signatures are
+//! verified in constant time.
+//!
+//! The engine signs the commands it issues itself with its own key, taken
+//! from the `engine` entry of the keys or generated at startup, and they
+//! pass the role policy once the signature verifies.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use aetheris_shared::{Command, MqttMessage, Operator, OperatorRole};
+use ring::hmac;
+use thiserror::Error;
+
+use crate::anomalies::ENGINE_ORIGIN;
+use crate::config::{CheckConfig, ConfigChecker};
+use crate::transport::Secret;
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Who may send which commands
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthorizationConfig {
+    /// Whether commands are checked at all
+    pub enabled: bool,
+    /// Command variants each role may send (see [`Command::NAMES`]); a
+    /// role left out may send nothing
+    pub policy: BTreeMap<OperatorRole, BTreeSet<String>>,
+    /// Role of each operator id allowed to send commands
+    pub operators: BTreeMap<String, OperatorRole>,
+    /// Shared signing keys by operator id. While any is set, every command
+    /// must be signed by its operator.
+    pub keys: BTreeMap<String, Secret>,
+}
+
+impl Default for AuthorizationConfig {
+    fn default() -> Self {
+        let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        Self {
+            enabled: false,
+            policy: BTreeMap::from([
+                (OperatorRole::Viewer, BTreeSet::new()),
+                (
+                    OperatorRole::Operator,
+                    names(&[
+                        "move_to",
+                        "stop",
+                        "perform_scan",
+                        "start_patrol",
+                        "return_to_base",
+                        "investigate",
+                        "acknowledge_anomaly",
+                        "update_assignment",
+                        "request_keyframe",
+                        "correct_position",
+                    ]),
+                ),
+                (OperatorRole::Admin, names(&Command::NAMES)),
+            ]),
+            operators: BTreeMap::new(),
+            keys: BTreeMap::new(),
+        }
+    }
+}
+
+impl CheckConfig for AuthorizationConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        for (role, names) in &self.policy {
+            for name in names {
+                if !Command::NAMES.contains(&name.as_str()) {
+                    checker.error(
+                        &format!("policy.{}", role_name(*role)),
+                        format!("unknown command \"{name}\""),
+                        Some(format!("expected one of: {}", Command::NAMES.join(", "))),
+                    );
+                }
+            }
+        }
+        for (operator, key) in &self.keys {
+            if key.expose().is_empty() {
+                checker.error(&format!("keys.{operator}"), "must not be empty", None);
+            }
+        }
+    }
+}
+
+fn role_name(role: OperatorRole) -> &'static str {
+    match role {
+        OperatorRole::Viewer => "viewer",
+        OperatorRole::Operator => "operator",
+        OperatorRole::Admin => "admin",
+    }
+}
+
+// ============================================================================
+// AUTHORIZATION
+// ============================================================================
+
+/// Why a command was refused
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum Rejection {
+    #[error("command names no operator")]
+    Anonymous,
+    #[error("operator \"{operator}\" is not known")]
+    UnknownOperator { operator: String },
+    #[error("operator \"{operator}\" claims role {declared:?} but has {assigned:?}")]
+    RoleMismatch {
+        operator: String,
+        declared: OperatorRole,
+        assigned: OperatorRole,
+    },
+    #[error("operator \"{operator}\" has no signing key")]
+    UnknownSigner { operator: String },
+    #[error("command from \"{operator}\" is not signed")]
+    Unsigned { operator: String },
+    #[error("signature does not match operator \"{operator}\"")]
+    BadSignature { operator: String },
+    #[error("role {role:?} of \"{operator}\" may not send {command}")]
+    NotPermitted {
+        operator: String,
+        role: OperatorRole,
+        command: &'static str,
+    },
+}
+
+/// Checks the operator, signature and role of incoming commands, and signs
+/// the engine's own
+pub struct CommandAuthorizer {
+    config: AuthorizationConfig,
+    keys: HashMap<String, hmac::Key>,
+    engine_key: hmac::Key,
+}
+
+impl std::fmt::Debug for CommandAuthorizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CommandAuthorizer")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CommandAuthorizer {
+    pub fn new(config: AuthorizationConfig) -> Self {
+        let keys: HashMap<_, _> = config
+            .keys
+            .iter()
+            .map(|(operator, key)| {
+                let key = hmac::Key::new(hmac::HMAC_SHA256, key.expose().as_bytes());
+                (operator.clone(), key)
+            })
+            .collect();
+        let engine_key = keys
+            .get(ENGINE_ORIGIN)
+            .cloned()
+            .unwrap_or_else(|| hmac::Key::new(hmac::HMAC_SHA256, &rand::random::<[u8; 32]>()));
+        Self {
+            config,
+            keys,
+            engine_key,
+        }
+    }
+
+    pub fn config(&self) -> &AuthorizationConfig {
+        &self.config
+    }
+
+    /// Tag a command the engine issues with the engine's identity and sign
+    /// it; left as is while authorization is off
+    pub fn sign_own(&self, msg: MqttMessage<Command>) -> MqttMessage<Command> {
+        if !self.config.enabled {
+            return msg;
+        }
+        let mut msg = msg.with_operator(Operator::new(ENGINE_ORIGIN, OperatorRole::Admin));
+        msg.signature = Some(sign(&self.engine_key, &msg));
+        msg
+    }
+
+    /// Whether the command may be carried out
+    pub fn check(&self, msg: &MqttMessage<Command>) -> Result<(), Rejection> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+        let operator = msg.operator.as_ref().ok_or(Rejection::Anonymous)?;
+        let is_engine = operator.id == ENGINE_ORIGIN;
+        if is_engine || !self.keys.is_empty() {
+            let key = if is_engine {
+                &self.engine_key
+            } else {
+                self.keys
+                    .get(&operator.id)
+                    .ok_or_else(|| Rejection::UnknownSigner {
+                        operator: operator.id.clone(),
+                    })?
+            };
+            let signature = msg
+                .signature
+                .as_deref()
+                .ok_or_else(|| Rejection::Unsigned {
+                    operator: operator.id.clone(),
+                })?;
+            let verified = decode_hex(signature)
+                .is_some_and(|tag| hmac::verify(key, &msg.signing_input(), &tag).is_ok());
+            if !verified {
+                return Err(Rejection::BadSignature {
+                    operator: operator.id.clone(),
+                });
+            }
+        }
+        if is_engine {
+            return Ok(());
+        }
+        let role =
+            *self
+                .config
+                .operators
+                .get(&operator.id)
+                .ok_or_else(|| Rejection::UnknownOperator {
+                    operator: operator.id.clone(),
+                })?;
+        if operator.role != role {
+            return Err(Rejection::RoleMismatch {
+                operator: operator.id.clone(),
+                declared: operator.role,
+                assigned: role,
+            });
+        }
+        let command = msg.payload.name();
+        let permitted = self
+            .config
+            .policy
+            .get(&role)
+            .is_some_and(|names| names.contains(command));
+        if !permitted {
+            return Err(Rejection::NotPermitted {
+                operator: operator.id.clone(),
+                role,
+                command,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Hex HMAC-SHA256 of the command's signing input
+pub fn sign(key: &hmac::Key, msg: &MqttMessage<Command>) -> String {
+    hmac::sign(key, &msg.signing_input())
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect()
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(command: Command, operator: Option<Operator>) -> MqttMessage<Command> {
+        let msg = MqttMessage::new(command, "dashboard", 1).with_command_id("CMD-1");
+        match operator {
+            Some(operator) => msg.with_operator(operator),
+            None => msg,
+        }
+    }
+
+    fn enabled(keys: &[(&str, &str)]) -> CommandAuthorizer {
+        CommandAuthorizer::new(AuthorizationConfig {
+            enabled: true,
+            operators: BTreeMap::from([
+                ("watcher".to_string(), OperatorRole::Viewer),
+                ("ops-bob".to_string(), OperatorRole::Operator),
+                ("ops-alice".to_string(), OperatorRole::Admin),
+            ]),
+            keys: keys
+                .iter()
+                .map(|(operator, key)| (operator.to_string(), Secret::new(*key)))
+                .collect(),
+            ..AuthorizationConfig::default()
+        })
+    }
+
+    #[test]
+    fn test_roles_limit_the_commands_they_may_send() {
+        let authorizer = enabled(&[]);
+        let viewer = Operator::new("watcher", OperatorRole::Viewer);
+        let operator = Operator::new("ops-bob", OperatorRole::Operator);
+        let admin = Operator::new("ops-alice", OperatorRole::Admin);
+
+        assert_eq!(
+            authorizer.check(&command(Command::Stop, None)),
+            Err(Rejection::Anonymous)
+        );
+        assert!(matches!(
+            authorizer.check(&command(Command::Stop, Some(viewer))),
+            Err(Rejection::NotPermitted {
+                command: "stop",
+                ..
+            })
+        ));
+        assert!(
+            authorizer
+                .check(&command(Command::ReturnToBase, Some(operator.clone())))
+                .is_ok()
+        );
+        assert!(matches!(
+            authorizer.check(&command(Command::EmergencyStop, Some(operator))),
+            Err(Rejection::NotPermitted {
+                role: OperatorRole::Operator,
+                ..
+            })
+        ));
+        assert!(
+            authorizer
+                .check(&command(Command::EmergencyStop, Some(admin)))
+                .is_ok()
+        );
+
+        let off = CommandAuthorizer::new(AuthorizationConfig::default());
+        assert!(off.check(&command(Command::EmergencyStop, None)).is_ok());
+    }
+
+    #[test]
+    fn test_signatures_are_required_once_keys_are_configured() {
+        let authorizer = enabled(&[("ops-alice", "alice-key")]);
+        let alice = Operator::new("ops-alice", OperatorRole::Admin);
+        let key = hmac::Key::new(hmac::HMAC_SHA256, b"alice-key");
+
+        let mut msg = command(Command::EmergencyStop, Some(alice.clone()));
+        assert!(matches!(
+            authorizer.check(&msg),
+            Err(Rejection::Unsigned { .. })
+        ));
+        msg.signature = Some(sign(&key, &msg));
+        assert!(authorizer.check(&msg).is_ok());
+
+        // The signature covers the command and the claimed role
+        let mut tampered = msg.clone();
+        tampered.payload = Command::InjectFault {
+            fault_type: aetheris_shared::FaultType::MotorFailure,
+        };
+        assert!(matches!(
+            authorizer.check(&tampered),
+            Err(Rejection::BadSignature { .. })
+        ));
+        msg.signature = Some("zz".into());
+        assert!(matches!(
+            authorizer.check(&msg),
+            Err(Rejection::BadSignature { .. })
+        ));
+
+        let mut stranger = command(
+            Command::Stop,
+            Some(Operator::new("stranger", OperatorRole::Admin)),
+        );
+        stranger.signature = Some(sign(&key, &stranger));
+        assert!(matches!(
+            authorizer.check(&stranger),
+            Err(Rejection::UnknownSigner { .. })
+        ));
+    }
+
+    #[test]
+    fn test_declared_role_must_match_the_configured_one() {
+        // A validly signed low-role operator claiming Admin gets nothing
+        let authorizer = enabled(&[("ops-bob", "bob-key")]);
+        let key = hmac::Key::new(hmac::HMAC_SHA256, b"bob-key");
+        let mut escalated = command(
+            Command::EmergencyStop,
+            Some(Operator::new("ops-bob", OperatorRole::Admin)),
+        );
+        escalated.signature = Some(sign(&key, &escalated));
+        assert_eq!(
+            authorizer.check(&escalated),
+            Err(Rejection::RoleMismatch {
+                operator: "ops-bob".into(),
+                declared: OperatorRole::Admin,
+                assigned: OperatorRole::Operator,
+            })
+        );
+        let mut honest = command(
+            Command::EmergencyStop,
+            Some(Operator::new("ops-bob", OperatorRole::Operator)),
+        );
+        honest.signature = Some(sign(&key, &honest));
+        assert!(matches!(
+            authorizer.check(&honest),
+            Err(Rejection::NotPermitted { .. })
+        ));
+
+        // Without keys, neither an unknown id nor a raised role gets through
+        let unsigned = enabled(&[]);
+        assert_eq!(
+            unsigned.check(&command(
+                Command::EmergencyStop,
+                Some(Operator::new("anyone", OperatorRole::Admin)),
+            )),
+            Err(Rejection::UnknownOperator {
+                operator: "anyone".into()
+            })
+        );
+        assert!(matches!(
+            unsigned.check(&command(
+                Command::EmergencyStop,
+                Some(Operator::new("watcher", OperatorRole::Admin)),
+            )),
+            Err(Rejection::RoleMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_engine_identity_cannot_be_claimed_without_its_key() {
+        let authorizer = enabled(&[]);
+        let own = authorizer.sign_own(command(
+            Command::InjectLeak {
+                section_id: "PIPE-001".into(),
+                severity: aetheris_shared::SeverityLevel::High,
+            },
+            None,
+        ));
+        assert!(authorizer.check(&own).is_ok());
+
+        let mut forged = command(
+            Command::EmergencyStop,
+            Some(Operator::new(ENGINE_ORIGIN, OperatorRole::Admin)),
+        );
+        assert!(matches!(
+            authorizer.check(&forged),
+            Err(Rejection::Unsigned { .. })
+        ));
+        let guessed = hmac::Key::new(hmac::HMAC_SHA256, b"engine");
+        forged.signature = Some(sign(&guessed, &forged));
+        assert!(matches!(
+            authorizer.check(&forged),
+            Err(Rejection::BadSignature { .. })
+        ));
+    }
+}
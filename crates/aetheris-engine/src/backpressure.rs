@@ -0,0 +1,253 @@
+//! Backpressure on the engine message channel
+//!
+//! The event loop hands every message it handled to the engine's consumer
+//! over a bounded channel. When the consumer falls behind, the messages
+//! split by what losing one would cost:
+//!
+//! - alerts, command responses and everything else the consumer acts on
+//!   wait for room, holding up the event loop rather than being lost
+//! - telemetry, heartbeats and environment readings are periodic, so each
+//!   robot (or section) gets a coalescing slot instead: a message that finds
+//!   the channel full waits in its slot, and a newer one for the same robot
+//!   replaces it, counted as dropped
+//!
+//! Slots are drained oldest first, ahead of any newer periodic message, so
+//! a robot's states still reach the consumer in order.
+
+use std::collections::VecDeque;
+
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+use crate::EngineMessage;
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// The channel from the event loop to the engine's consumer
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageChannelConfig {
+    /// Messages the channel holds before periodic ones are coalesced and
+    /// the rest wait
+    pub capacity: usize,
+}
+
+impl Default for MessageChannelConfig {
+    fn default() -> Self {
+        Self { capacity: 100 }
+    }
+}
+
+impl CheckConfig for MessageChannelConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if self.capacity == 0 {
+            checker.error("capacity", "must be at least 1", None);
+        }
+    }
+}
+
+// ============================================================================
+// CLASSES
+// ============================================================================
+
+/// Periodic messages, superseded by the next one from the same source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LossyClass {
+    Telemetry,
+    Heartbeat,
+    Environment,
+}
+
+impl LossyClass {
+    pub const ALL: [LossyClass; 3] = [
+        LossyClass::Telemetry,
+        LossyClass::Heartbeat,
+        LossyClass::Environment,
+    ];
+
+    /// Class and source (robot or section) of a periodic message; `None`
+    /// for the ones that must not be lost
+    pub fn of(message: &EngineMessage) -> Option<(Self, &str)> {
+        match message {
+            EngineMessage::TelemetryReceived(state) => Some((Self::Telemetry, &state.id)),
+            EngineMessage::HeartbeatReceived(heartbeat) => {
+                Some((Self::Heartbeat, &heartbeat.robot_id))
+            }
+            EngineMessage::EnvironmentReceived(reading) => {
+                Some((Self::Environment, &reading.section_id))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Telemetry => "telemetry",
+            Self::Heartbeat => "heartbeat",
+            Self::Environment => "environment",
+        }
+    }
+}
+
+// ============================================================================
+// SLOTS
+// ============================================================================
+
+/// The channel was closed: the consumer is gone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Closed;
+
+/// What became of a periodic message offered to the channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Handoff {
+    Sent,
+    /// Waiting in its source's slot for room in the channel
+    Slotted,
+    /// Waiting in its source's slot in place of an older message, dropped
+    Superseded,
+}
+
+/// Periodic messages waiting for room in the channel, one per source,
+/// oldest first
+#[derive(Debug, Default)]
+pub struct CoalescingSlots {
+    slots: VecDeque<(LossyClass, String, EngineMessage)>,
+}
+
+impl CoalescingSlots {
+    /// Send a periodic message without waiting, slotting it if the channel
+    /// is full or older messages are still slotted
+    pub fn offer(
+        &mut self,
+        tx: &mpsc::Sender<EngineMessage>,
+        message: EngineMessage,
+    ) -> Result<Handoff, Closed> {
+        self.flush(tx)?;
+        if self.slots.is_empty() {
+            match tx.try_send(message) {
+                Ok(()) => return Ok(Handoff::Sent),
+                Err(TrySendError::Closed(_)) => return Err(Closed),
+                Err(TrySendError::Full(message)) => return Ok(self.slot(message)),
+            }
+        }
+        Ok(self.slot(message))
+    }
+
+    /// Move slotted messages into the channel, oldest first, until it is
+    /// full
+    pub fn flush(&mut self, tx: &mpsc::Sender<EngineMessage>) -> Result<(), Closed> {
+        while let Some((class, source, message)) = self.slots.pop_front() {
+            match tx.try_send(message) {
+                Ok(()) => {}
+                Err(TrySendError::Full(message)) => {
+                    self.slots.push_front((class, source, message));
+                    return Ok(());
+                }
+                Err(TrySendError::Closed(_)) => return Err(Closed),
+            }
+        }
+        Ok(())
+    }
+
+    fn slot(&mut self, message: EngineMessage) -> Handoff {
+        let (class, source) =
+            LossyClass::of(&message).expect("only periodic messages are coalesced");
+        // A replaced message keeps its place: the source's next state is
+        // still due before those of sources slotted later
+        let previous = self
+            .slots
+            .iter_mut()
+            .find(|(c, s, _)| *c == class && s == source);
+        if let Some(previous) = previous {
+            previous.2 = message;
+            return Handoff::Superseded;
+        }
+        let source = source.to_string();
+        self.slots.push_back((class, source, message));
+        Handoff::Slotted
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetheris_shared::{RobotState, RobotType};
+    use std::time::Duration;
+
+    fn telemetry(robot_id: &str, x: f64) -> EngineMessage {
+        let mut state = RobotState::new(robot_id.parse().unwrap(), robot_id, RobotType::Rover);
+        state.position.x = x;
+        EngineMessage::TelemetryReceived(state)
+    }
+
+    fn received(rx: &mut mpsc::Receiver<EngineMessage>) -> Vec<(String, f64)> {
+        std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|message| match message {
+                EngineMessage::TelemetryReceived(state) => (state.id.to_string(), state.position.x),
+                other => panic!("expected telemetry, got {other:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_full_channel_keeps_latest_per_robot_in_order() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let mut slots = CoalescingSlots::default();
+        assert_eq!(
+            slots.offer(&tx, telemetry("RV-001", 1.0)),
+            Ok(Handoff::Sent)
+        );
+        assert_eq!(
+            slots.offer(&tx, telemetry("RV-002", 2.0)),
+            Ok(Handoff::Slotted)
+        );
+        assert_eq!(
+            slots.offer(&tx, telemetry("RV-001", 3.0)),
+            Ok(Handoff::Slotted)
+        );
+        assert_eq!(
+            slots.offer(&tx, telemetry("RV-002", 4.0)),
+            Ok(Handoff::Superseded)
+        );
+        assert_eq!(slots.len(), 2);
+
+        assert_eq!(received(&mut rx), [("RV-001".to_string(), 1.0)]);
+        // Room for one: the oldest slot goes first, the new message waits
+        assert_eq!(
+            slots.offer(&tx, telemetry("RV-001", 5.0)),
+            Ok(Handoff::Superseded)
+        );
+        assert_eq!(received(&mut rx), [("RV-002".to_string(), 4.0)]);
+        slots.flush(&tx).unwrap();
+        assert_eq!(received(&mut rx), [("RV-001".to_string(), 5.0)]);
+        assert!(slots.is_empty());
+    }
+
+    #[test]
+    fn test_only_periodic_messages_are_lossy() {
+        assert_eq!(
+            LossyClass::of(&telemetry("RV-001", 0.0)),
+            Some((LossyClass::Telemetry, "RV-001"))
+        );
+        let reconnected = EngineMessage::RobotReconnected("RV-001".into(), Duration::ZERO);
+        assert_eq!(LossyClass::of(&reconnected), None);
+
+        let (tx, rx) = mpsc::channel(1);
+        drop(rx);
+        let mut slots = CoalescingSlots::default();
+        assert_eq!(slots.offer(&tx, telemetry("RV-001", 0.0)), Err(Closed));
+    }
+}
@@ -0,0 +1,162 @@
+//! Simulated battery drain and charging
+//!
+//! A simulated robot's battery drains with the distance it covers, slowly
+//! while it sits powered, and faster while its sensors scan. Below its low
+//! battery threshold the robot heads for a charging station, charges, and
+//! resumes what it was doing (see [`crate::simulation`]). Rates are per
+//! simulated second, the same clock motion runs on.
+
+use aetheris_shared::HealthStatus;
+
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Battery drain and charging rates of the simulated fleet
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatteryConfig {
+    /// Battery used per meter travelled (percent)
+    pub drain_per_meter: f64,
+    /// Battery used per second while powered (percent)
+    pub idle_drain_per_sec: f64,
+    /// Extra battery used per second while scanning (percent)
+    pub scan_drain_per_sec: f64,
+    /// Battery gained per second on a charger (percent)
+    pub charge_per_sec: f64,
+    /// Level at which a charging robot unplugs and resumes (percent)
+    pub resume_level: f64,
+    /// Return-to-charge level for robots whose configuration sets none
+    /// (see `RobotConfig::low_battery_threshold`)
+    pub low_threshold: f64,
+    /// Health degrades to Warning below this level (percent)
+    pub warning_level: f64,
+    /// Health degrades to Critical below this level (percent)
+    pub critical_level: f64,
+}
+
+impl Default for BatteryConfig {
+    fn default() -> Self {
+        Self {
+            drain_per_meter: 0.05,
+            idle_drain_per_sec: 0.005,
+            scan_drain_per_sec: 0.05,
+            charge_per_sec: 2.0,
+            resume_level: 90.0,
+            low_threshold: 25.0,
+            warning_level: 20.0,
+            critical_level: 5.0,
+        }
+    }
+}
+
+impl CheckConfig for BatteryConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        for (field, rate) in [
+            ("drain_per_meter", self.drain_per_meter),
+            ("idle_drain_per_sec", self.idle_drain_per_sec),
+            ("scan_drain_per_sec", self.scan_drain_per_sec),
+        ] {
+            if !(rate >= 0.0 && rate.is_finite()) {
+                checker.error(field, format!("must be non-negative, got {rate}"), None);
+            }
+        }
+        if !(self.charge_per_sec > 0.0 && self.charge_per_sec.is_finite()) {
+            checker.error(
+                "charge_per_sec",
+                format!("must be positive, got {}", self.charge_per_sec),
+                None,
+            );
+        }
+        if !(self.low_threshold < self.resume_level && self.resume_level <= 100.0) {
+            checker.error(
+                "resume_level",
+                format!(
+                    "must be above low_threshold ({}) and at most 100, got {}",
+                    self.low_threshold, self.resume_level
+                ),
+                None,
+            );
+        }
+        if self.critical_level > self.warning_level {
+            checker.error(
+                "critical_level",
+                format!(
+                    "must not exceed warning_level ({}), got {}",
+                    self.warning_level, self.critical_level
+                ),
+                None,
+            );
+        }
+    }
+}
+
+// ============================================================================
+// MODEL
+// ============================================================================
+
+impl BatteryConfig {
+    /// Battery used over `secs` simulated seconds covering `distance` meters
+    pub fn drain(&self, distance: f64, scanning: bool, secs: f64) -> f64 {
+        let scan = if scanning {
+            self.scan_drain_per_sec
+        } else {
+            0.0
+        };
+        distance * self.drain_per_meter + (self.idle_drain_per_sec + scan) * secs
+    }
+
+    /// Health implied by the battery level alone
+    pub fn health(&self, level: f64) -> HealthStatus {
+        if level < self.critical_level {
+            HealthStatus::Critical
+        } else if level < self.warning_level {
+            HealthStatus::Warning
+        } else {
+            HealthStatus::Optimal
+        }
+    }
+}
+
+/// The worse of two health states
+pub fn worse(a: HealthStatus, b: HealthStatus) -> HealthStatus {
+    let rank = |health: HealthStatus| match health {
+        HealthStatus::Optimal => 0,
+        HealthStatus::Warning => 1,
+        HealthStatus::Critical => 2,
+    };
+    if rank(b) > rank(a) { b } else { a }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drain_follows_distance_and_scanning() {
+        let config = BatteryConfig::default();
+        let parked = config.drain(0.0, false, 10.0);
+        let driving = config.drain(10.0, false, 10.0);
+        let scanning = config.drain(0.0, true, 10.0);
+        assert!((parked - 0.05).abs() < 1e-12);
+        assert!((driving - parked - 0.5).abs() < 1e-12);
+        assert!(scanning > parked);
+    }
+
+    #[test]
+    fn test_health_degrades_at_battery_levels() {
+        let config = BatteryConfig::default();
+        assert_eq!(config.health(20.0), HealthStatus::Optimal);
+        assert_eq!(config.health(19.9), HealthStatus::Warning);
+        assert_eq!(config.health(4.9), HealthStatus::Critical);
+        assert_eq!(
+            worse(HealthStatus::Critical, HealthStatus::Warning),
+            HealthStatus::Critical
+        );
+    }
+}
@@ -0,0 +1,379 @@
+//! Runtime predictions and automatic recall to a charging station
+//!
+//! Each robot's discharge rate is smoothed from successive telemetry samples
+//! and turned into a runtime estimate. The battery a robot needs to reach its
+//! nearest station is the larger of what its type nominally uses on the way
+//! and what the observed rate would use over the trip, plus a reserve. Once
+//! the battery falls to that level the robot is sent back, once per
+//! discharge. A rising battery (charging) or a data gap restarts the rate.
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use aetheris_shared::{
+    BatteryProfile, ChargingStation, CurrentTask, RobotState, RobotStatus, RobotType,
+};
+
+use crate::config::{CheckConfig, ConfigChecker};
+use crate::create_mock_stations;
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Battery management behavior
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatteryManagerConfig {
+    /// Send robots back on their own once they can only just reach a station
+    pub auto_return: bool,
+    /// Stations robots return to; the demo stations when empty
+    pub stations: Vec<ChargingStation>,
+    /// Drain overrides by robot type (see `RobotType::battery_profile`)
+    pub profiles: BTreeMap<RobotType, BatteryProfile>,
+    /// Battery a robot should still have on reaching the station (percent)
+    pub reserve: f64,
+    /// Weight of the newest sample in the smoothed discharge rate
+    pub smoothing: f64,
+    /// Silence after which the discharge rate restarts
+    pub max_gap: Duration,
+}
+
+impl Default for BatteryManagerConfig {
+    fn default() -> Self {
+        Self {
+            auto_return: true,
+            stations: Vec::new(),
+            profiles: BTreeMap::new(),
+            reserve: 5.0,
+            smoothing: 0.2,
+            max_gap: Duration::from_secs(30),
+        }
+    }
+}
+
+impl BatteryManagerConfig {
+    /// The configured stations, or the demo ones
+    pub fn stations(&self) -> Vec<ChargingStation> {
+        if self.stations.is_empty() {
+            return create_mock_stations();
+        }
+        self.stations.clone()
+    }
+
+    /// Drain of a robot type, with its override if one is configured
+    pub fn profile(&self, robot_type: RobotType) -> BatteryProfile {
+        self.profiles
+            .get(&robot_type)
+            .copied()
+            .unwrap_or_else(|| robot_type.battery_profile())
+    }
+}
+
+impl CheckConfig for BatteryManagerConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        for (i, station) in self.stations.iter().enumerate() {
+            let field = format!("stations[{i}]");
+            if station.id.is_empty() {
+                checker.error(&field, "needs an id", None);
+            } else if self.stations[..i].iter().any(|s| s.id == station.id) {
+                checker.error(&field, format!("duplicate station {}", station.id), None);
+            }
+            if station.capacity == 0 {
+                checker.error(&format!("{field}.capacity"), "must be at least 1", None);
+            }
+        }
+        checker.section("profiles", |checker| {
+            for (robot_type, profile) in &self.profiles {
+                checker.check_section(robot_type.as_str(), profile);
+            }
+        });
+        if !(0.0..100.0).contains(&self.reserve) {
+            checker.error(
+                "reserve",
+                format!("must be between 0 and 100, got {}", self.reserve),
+                None,
+            );
+        }
+        if !(self.smoothing > 0.0 && self.smoothing <= 1.0) {
+            checker.error(
+                "smoothing",
+                format!("must be above 0 and at most 1, got {}", self.smoothing),
+                None,
+            );
+        }
+        checker.positive("max_gap", self.max_gap);
+    }
+}
+
+impl CheckConfig for BatteryProfile {
+    fn check(&self, checker: &mut ConfigChecker) {
+        for (field, rate) in [
+            ("drain_per_meter", self.drain_per_meter),
+            ("idle_drain_per_sec", self.idle_drain_per_sec),
+        ] {
+            if !(rate >= 0.0 && rate.is_finite()) {
+                checker.error(field, format!("must be non-negative, got {rate}"), None);
+            }
+        }
+        if !(self.return_speed > 0.0 && self.return_speed.is_finite()) {
+            checker.error(
+                "return_speed",
+                format!("must be positive, got {}", self.return_speed),
+                None,
+            );
+        }
+    }
+}
+
+// ============================================================================
+// MANAGER
+// ============================================================================
+
+/// Battery prediction for one robot, as of its latest telemetry
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BatteryOutlook {
+    pub robot_id: String,
+    /// Percent
+    pub battery: f64,
+    /// Smoothed discharge in percent per minute, once two samples are in
+    pub discharge_per_min: Option<f64>,
+    /// Seconds until empty at the current discharge rate
+    pub runtime_secs: Option<f64>,
+    /// Nearest charging station
+    pub station_id: Option<String>,
+    /// Battery needed to reach it with the reserve left (percent)
+    pub return_needed: Option<f64>,
+    /// Sent back to charge during the current discharge
+    pub recalled: bool,
+}
+
+/// A robot that has to head for a station now
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recall {
+    pub robot_id: String,
+    pub station_id: String,
+    /// Percent
+    pub battery: f64,
+    /// Battery the way back needs, reserve included (percent)
+    pub needed: f64,
+}
+
+#[derive(Debug, Default)]
+struct RobotBattery {
+    /// Timestamp and level of the previous sample
+    last: Option<(u64, f64)>,
+    /// Smoothed discharge in percent per second
+    rate_per_sec: Option<f64>,
+    recalled: bool,
+    outlook: Option<BatteryOutlook>,
+}
+
+/// Per-robot discharge tracking and return decisions
+#[derive(Debug)]
+pub struct BatteryManager {
+    config: BatteryManagerConfig,
+    stations: Vec<ChargingStation>,
+    robots: HashMap<String, RobotBattery>,
+}
+
+impl BatteryManager {
+    pub fn new(config: BatteryManagerConfig) -> Self {
+        Self {
+            stations: config.stations(),
+            config,
+            robots: HashMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> &BatteryManagerConfig {
+        &self.config
+    }
+
+    pub fn stations(&self) -> &[ChargingStation] {
+        &self.stations
+    }
+
+    /// Latest prediction for a robot
+    pub fn outlook(&self, robot_id: &str) -> Option<&BatteryOutlook> {
+        self.robots.get(robot_id)?.outlook.as_ref()
+    }
+
+    /// Latest prediction for every robot, by id
+    pub fn snapshot(&self) -> Vec<BatteryOutlook> {
+        let mut outlooks: Vec<BatteryOutlook> = self
+            .robots
+            .values()
+            .filter_map(|robot| robot.outlook.clone())
+            .collect();
+        outlooks.sort_by(|a, b| a.robot_id.cmp(&b.robot_id));
+        outlooks
+    }
+
+    /// Record a robot's state, returning a recall if its battery only just
+    /// covers the way back to the nearest station
+    pub fn observe(&mut self, state: &RobotState) -> Option<Recall> {
+        let timestamp = state.timestamp.as_millis();
+        let robot = self.robots.entry(state.id.to_string()).or_default();
+        match robot.last {
+            // Late samples would turn the rate around
+            Some((last, _)) if timestamp <= last => return None,
+            Some((last, level)) if timestamp - last <= self.config.max_gap.as_millis() as u64 => {
+                if state.battery > level {
+                    robot.rate_per_sec = None;
+                } else {
+                    let secs = (timestamp - last) as f64 / 1000.0;
+                    let rate = (level - state.battery) / secs;
+                    robot.rate_per_sec = Some(match robot.rate_per_sec {
+                        Some(smoothed) => smoothed + self.config.smoothing * (rate - smoothed),
+                        None => rate,
+                    });
+                }
+            }
+            _ => robot.rate_per_sec = None,
+        }
+        robot.last = Some((timestamp, state.battery));
+
+        let profile = self.config.profile(state.robot_type);
+        let station = ChargingStation::nearest(&self.stations, &state.position);
+        let needed = station.map(|station| {
+            let distance = station.position.distance_to(&state.position);
+            let observed = robot.rate_per_sec.unwrap_or(0.0) * profile.return_secs(distance);
+            profile.return_cost(distance).max(observed) + self.config.reserve
+        });
+
+        let mut recall = None;
+        match (station, needed) {
+            (Some(station), Some(needed)) if state.battery <= needed => {
+                let returning = state.current_task == CurrentTask::ReturningToBase
+                    || !matches!(state.status, RobotStatus::Active | RobotStatus::Idle);
+                if self.config.auto_return && !robot.recalled && !returning {
+                    robot.recalled = true;
+                    recall = Some(Recall {
+                        robot_id: state.id.to_string(),
+                        station_id: station.id.clone(),
+                        battery: state.battery,
+                        needed,
+                    });
+                }
+            }
+            // Charged well past the point of return
+            (_, Some(needed)) if state.battery > needed + self.config.reserve => {
+                robot.recalled = false;
+            }
+            _ => {}
+        }
+
+        let rate = robot.rate_per_sec;
+        robot.outlook = Some(BatteryOutlook {
+            robot_id: state.id.to_string(),
+            battery: state.battery,
+            discharge_per_min: rate.map(|rate| rate * 60.0),
+            runtime_secs: rate
+                .filter(|rate| *rate > 0.0)
+                .map(|rate| state.battery / rate),
+            station_id: station.map(|station| station.id.clone()),
+            return_needed: needed,
+            recalled: robot.recalled,
+        });
+        recall
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetheris_shared::{Position, RobotId, Timestamp};
+
+    const T0: u64 = 1_700_000_000_000;
+
+    fn rover(battery: f64, x: f64, at: u64) -> RobotState {
+        let mut state = RobotState::new(
+            RobotId::parse("RV-001").unwrap(),
+            "Rover Alpha",
+            RobotType::Rover,
+        );
+        state.status = RobotStatus::Active;
+        state.battery = battery;
+        state.position = Position::new(x, 0.0, 0.0);
+        state.timestamp = Timestamp::from_millis(at);
+        state
+    }
+
+    fn manager() -> BatteryManager {
+        BatteryManager::new(BatteryManagerConfig {
+            stations: vec![ChargingStation::new("CHG-01", Position::origin(), 1)],
+            ..BatteryManagerConfig::default()
+        })
+    }
+
+    #[test]
+    fn test_discharge_rate_predicts_runtime() {
+        let mut manager = manager();
+        assert!(manager.observe(&rover(80.0, 10.0, T0)).is_none());
+        let first = manager.outlook("RV-001").unwrap();
+        assert_eq!(first.discharge_per_min, None);
+        assert_eq!(first.station_id.as_deref(), Some("CHG-01"));
+
+        // 0.5% per second, steady
+        for i in 1..=5 {
+            manager.observe(&rover(80.0 - 0.5 * i as f64, 10.0, T0 + i * 1000));
+        }
+        let outlook = manager.outlook("RV-001").unwrap();
+        assert!((outlook.discharge_per_min.unwrap() - 30.0).abs() < 1e-9);
+        assert!((outlook.runtime_secs.unwrap() - 155.0).abs() < 1e-9);
+        // The observed rate over the 6.7 s trip outweighs the nominal cost
+        let needed = outlook.return_needed.unwrap();
+        assert!((needed - (0.5 * 10.0 / 1.5 + 5.0)).abs() < 1e-9);
+
+        // Charging and gaps restart the rate
+        manager.observe(&rover(78.0, 10.0, T0 + 6000));
+        assert_eq!(manager.outlook("RV-001").unwrap().discharge_per_min, None);
+        manager.observe(&rover(77.0, 10.0, T0 + 7000));
+        manager.observe(&rover(76.0, 10.0, T0 + 60_000));
+        assert_eq!(manager.outlook("RV-001").unwrap().discharge_per_min, None);
+    }
+
+    #[test]
+    fn test_robot_is_recalled_once_when_it_can_just_make_it_back() {
+        let mut manager = manager();
+        // 100 m out a rover needs 5% + 0.33% for the trip and 5% in reserve
+        assert!(manager.observe(&rover(12.0, 100.0, T0)).is_none());
+        let recall = manager.observe(&rover(10.3, 100.0, T0 + 1000)).unwrap();
+        assert_eq!(recall.robot_id, "RV-001");
+        assert_eq!(recall.station_id, "CHG-01");
+        assert!(recall.needed >= recall.battery);
+        assert!(manager.outlook("RV-001").unwrap().recalled);
+        assert!(manager.observe(&rover(10.2, 90.0, T0 + 2000)).is_none());
+
+        // Recharged at the station, then run down again far out
+        manager.observe(&rover(95.0, 0.0, T0 + 600_000));
+        assert!(!manager.outlook("RV-001").unwrap().recalled);
+        manager.observe(&rover(11.0, 100.0, T0 + 1_200_000));
+        assert!(
+            manager
+                .observe(&rover(10.0, 100.0, T0 + 1_201_000))
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_robots_already_heading_back_are_left_alone() {
+        let mut manager = manager();
+        let mut returning = rover(8.0, 100.0, T0);
+        returning.current_task = CurrentTask::ReturningToBase;
+        assert!(manager.observe(&returning).is_none());
+
+        let mut charging = rover(4.0, 0.0, T0 + 1000);
+        charging.status = RobotStatus::Maintenance;
+        assert!(manager.observe(&charging).is_none());
+
+        let mut manual = BatteryManager::new(BatteryManagerConfig {
+            auto_return: false,
+            ..BatteryManagerConfig::default()
+        });
+        assert!(manual.observe(&rover(1.0, 100.0, T0)).is_none());
+        assert!(manual.outlook("RV-001").unwrap().return_needed.is_some());
+    }
+}
@@ -0,0 +1,187 @@
+//! World bounds for robot and report positions
+//!
+//! Every position the engine computes with (distances, fingerprint cells,
+//! chainage) is expected to be finite and inside one global bounding volume.
+//! A simulated robot whose true position leaves the volume is halted, and
+//! telemetry, alerts, and environment readings reporting a position outside
+//! it are rejected before they reach any downstream computation, with the
+//! rejections counted per publisher.
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use aetheris_shared::Position;
+
+use crate::config::{CheckConfig, ConfigChecker};
+use crate::sections::SectionInfo;
+
+// ============================================================================
+// BOUNDS
+// ============================================================================
+
+/// Axis-aligned bounding volume of the world in meters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorldBounds {
+    pub min: Position,
+    pub max: Position,
+}
+
+impl Default for WorldBounds {
+    /// A 20 km square, 2 km deep, around the origin. Use
+    /// [`WorldBounds::around_sections`] once the topology is known.
+    fn default() -> Self {
+        Self {
+            min: Position::new(-10_000.0, -10_000.0, -1_000.0),
+            max: Position::new(10_000.0, 10_000.0, 1_000.0),
+        }
+    }
+}
+
+impl WorldBounds {
+    pub fn new(min: Position, max: Position) -> Self {
+        Self { min, max }
+    }
+
+    /// Bounding box of the section topology, padded by `padding` meters on
+    /// every side; `None` without sections
+    pub fn around_sections<'a>(
+        sections: impl IntoIterator<Item = &'a SectionInfo>,
+        padding: f64,
+    ) -> Option<Self> {
+        let mut points = sections
+            .into_iter()
+            .flat_map(|s| std::iter::once(s.position).chain(s.end));
+        let first = points.next()?;
+        let (mut min, mut max) = (first, first);
+        for p in points {
+            min = Position::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+            max = Position::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+        }
+        Some(Self {
+            min: Position::new(min.x - padding, min.y - padding, min.z - padding),
+            max: Position::new(max.x + padding, max.y + padding, max.z + padding),
+        })
+    }
+
+    /// Whether `position` is finite and inside the bounds
+    pub fn contains(&self, position: &Position) -> bool {
+        position.is_finite()
+            && (self.min.x..=self.max.x).contains(&position.x)
+            && (self.min.y..=self.max.y).contains(&position.y)
+            && (self.min.z..=self.max.z).contains(&position.z)
+    }
+}
+
+impl CheckConfig for WorldBounds {
+    fn check(&self, checker: &mut ConfigChecker) {
+        let axes = [
+            ("x", self.min.x, self.max.x),
+            ("y", self.min.y, self.max.y),
+            ("z", self.min.z, self.max.z),
+        ];
+        for (axis, min, max) in axes {
+            if !min.is_finite() || !max.is_finite() {
+                checker.error(axis, "bounds must be finite", None);
+            } else if min >= max {
+                checker.error(axis, format!("min {} must be below max {}", min, max), None);
+            }
+        }
+    }
+}
+
+/// A position outside the world bounds
+#[derive(Debug, Clone, Copy, PartialEq, Error)]
+#[error("position ({}, {}, {}) is outside the world bounds", .position.x, .position.y, .position.z)]
+pub struct OutOfBounds {
+    pub position: Position,
+}
+
+// ============================================================================
+// GUARD
+// ============================================================================
+
+/// Applies [`WorldBounds`] to incoming positions and counts violations
+#[derive(Debug, Default)]
+pub struct BoundsGuard {
+    bounds: WorldBounds,
+    violations: HashMap<String, u64>,
+}
+
+impl BoundsGuard {
+    pub fn new(bounds: WorldBounds) -> Self {
+        Self {
+            bounds,
+            violations: HashMap::new(),
+        }
+    }
+
+    pub fn bounds(&self) -> &WorldBounds {
+        &self.bounds
+    }
+
+    /// Check a position reported by `source`, counting it if out of bounds
+    pub fn check(&mut self, source: &str, position: &Position) -> Result<(), OutOfBounds> {
+        if self.bounds.contains(position) {
+            return Ok(());
+        }
+        *self.violations.entry(source.to_string()).or_default() += 1;
+        Err(OutOfBounds {
+            position: *position,
+        })
+    }
+
+    /// Out-of-bounds positions reported by `source`
+    pub fn violations(&self, source: &str) -> u64 {
+        self.violations.get(source).copied().unwrap_or(0)
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bounds_reject_escaped_and_non_finite_positions() {
+        let mut guard = BoundsGuard::default();
+        assert!(guard.check("DR-001", &Position::new(1.0, 3.0, 0.0)).is_ok());
+        let escaped = Position::new(4.7e12, 3.0, 0.0);
+        assert_eq!(
+            guard.check("DR-001", &escaped),
+            Err(OutOfBounds { position: escaped })
+        );
+        assert!(
+            guard
+                .check("DR-001", &Position::new(f64::NAN, 0.0, 0.0))
+                .is_err()
+        );
+        assert!(
+            guard
+                .check("RV-001", &Position::new(0.0, 0.0, -1_000.0))
+                .is_ok()
+        );
+        assert_eq!(guard.violations("DR-001"), 2);
+        assert_eq!(guard.violations("RV-001"), 0);
+    }
+
+    #[test]
+    fn test_bounds_derived_from_topology() {
+        let sections = [
+            SectionInfo::new("PIPE-001", Position::new(0.0, 0.0, 0.0))
+                .with_end(Position::new(500.0, 20.0, -5.0)),
+            SectionInfo::new("PIPE-002", Position::new(-100.0, 40.0, 0.0)),
+        ];
+        let bounds = WorldBounds::around_sections(&sections, 100.0).unwrap();
+        assert_eq!(bounds.min, Position::new(-200.0, -100.0, -105.0));
+        assert_eq!(bounds.max, Position::new(600.0, 140.0, 100.0));
+        assert!(WorldBounds::around_sections([], 100.0).is_none());
+
+        let mut checker = ConfigChecker::default();
+        WorldBounds::new(bounds.max, bounds.min).check(&mut checker);
+        assert_eq!(checker.finish().unwrap_err().issues.len(), 3);
+    }
+}
@@ -0,0 +1,349 @@
+//! Tracking of broadcast commands to completion
+//!
+//! A broadcast goes to the robots online when it is sent; that snapshot is
+//! what completion is measured against, so a robot joining mid-window
+//! neither counts nor is waited for. Responses are matched by robot and
+//! command id. The broadcast settles once every robot has answered or gone
+//! offline, or when the deadline passes. A robot that never answered,
+//! whether silent or offline, is reported as non-responding rather than
+//! failed: it may well have stopped.
+//!
+//! A broadcast narrowed to the robots able to carry it out is sent to each
+//! of them under its own id, derived with [`share_id`].
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::time::Duration;
+
+use aetheris_shared::{BroadcastResult, CommandResponse};
+use tokio::sync::oneshot;
+
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Broadcast tracking behavior
+#[derive(Debug, Clone, PartialEq)]
+pub struct BroadcastConfig {
+    /// How long robots have to answer a broadcast
+    pub deadline: Duration,
+}
+
+impl Default for BroadcastConfig {
+    fn default() -> Self {
+        Self {
+            deadline: Duration::from_secs(10),
+        }
+    }
+}
+
+impl CheckConfig for BroadcastConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        checker.positive("deadline", self.deadline);
+    }
+}
+
+// ============================================================================
+// TRACKING
+// ============================================================================
+
+/// Id of the command a narrowed broadcast sends to `robot_id`
+pub fn share_id(command_id: &str, robot_id: &str) -> String {
+    format!("{command_id}.{robot_id}")
+}
+
+/// A broadcast waiting for its robots
+#[derive(Debug)]
+struct PendingBroadcast {
+    command: &'static str,
+    /// Id each robot answers with, by robot
+    expected: BTreeMap<String, String>,
+    acked: BTreeSet<String>,
+    failed: BTreeSet<String>,
+    started_at: u64,
+    deadline: u64,
+    waiter: Option<oneshot::Sender<BroadcastResult>>,
+}
+
+impl PendingBroadcast {
+    fn answered(&self, robot_id: &str) -> bool {
+        self.acked.contains(robot_id) || self.failed.contains(robot_id)
+    }
+
+    /// Whether no robot is left that could still answer
+    fn settled(&self, offline: &HashSet<String>) -> bool {
+        self.expected
+            .keys()
+            .all(|robot_id| self.answered(robot_id) || offline.contains(robot_id))
+    }
+}
+
+/// Broadcasts in flight and the callers waiting on them
+#[derive(Debug, Default)]
+pub struct BroadcastTracker {
+    config: BroadcastConfig,
+    pending: HashMap<String, PendingBroadcast>,
+}
+
+impl BroadcastTracker {
+    pub fn new(config: BroadcastConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    pub fn config(&self) -> &BroadcastConfig {
+        &self.config
+    }
+
+    /// Start tracking broadcast `command_id` of a `command` variant, sent at
+    /// `now` to the robots in `expected` (robot to the id it answers with)
+    pub fn start(
+        &mut self,
+        command_id: &str,
+        command: &'static str,
+        expected: BTreeMap<String, String>,
+        now: u64,
+    ) -> oneshot::Receiver<BroadcastResult> {
+        let (tx, rx) = oneshot::channel();
+        self.pending.insert(
+            command_id.to_string(),
+            PendingBroadcast {
+                command,
+                expected,
+                acked: BTreeSet::new(),
+                failed: BTreeSet::new(),
+                started_at: now,
+                deadline: now + self.config.deadline.as_millis() as u64,
+                waiter: Some(tx),
+            },
+        );
+        rx
+    }
+
+    /// Record that a robot's share of a broadcast could not be sent
+    pub fn fail(&mut self, command_id: &str, robot_id: &str) {
+        if let Some(pending) = self.pending.get_mut(command_id)
+            && pending.expected.contains_key(robot_id)
+        {
+            pending.failed.insert(robot_id.to_string());
+        }
+    }
+
+    /// Record a robot's answer. Returns the result if it was the last one
+    /// the broadcast was waiting for.
+    pub fn on_response(
+        &mut self,
+        response: &CommandResponse,
+        offline: &HashSet<String>,
+        now: u64,
+    ) -> Option<BroadcastResult> {
+        let command_id = self.pending.iter().find_map(|(command_id, pending)| {
+            let expected = pending.expected.get(response.robot_id.as_str())?;
+            (*expected == response.command_id && !pending.answered(&response.robot_id))
+                .then(|| command_id.clone())
+        })?;
+        let pending = self.pending.get_mut(&command_id)?;
+        if response.success {
+            pending.acked.insert(response.robot_id.to_string());
+        } else {
+            pending.failed.insert(response.robot_id.to_string());
+        }
+        if pending.settled(offline) {
+            return self.finish(&command_id, now);
+        }
+        None
+    }
+
+    /// Settle a broadcast now, whatever is still outstanding
+    pub fn finish(&mut self, command_id: &str, now: u64) -> Option<BroadcastResult> {
+        let mut pending = self.pending.remove(command_id)?;
+        let non_responding = pending
+            .expected
+            .keys()
+            .filter(|robot_id| !pending.answered(robot_id))
+            .cloned()
+            .collect();
+        let result = BroadcastResult {
+            command_id: command_id.to_string(),
+            command: pending.command.to_string(),
+            acked: pending.acked.into_iter().collect(),
+            failed: pending.failed.into_iter().collect(),
+            non_responding,
+            started_at: pending.started_at,
+            completed_at: now,
+        };
+        if let Some(waiter) = pending.waiter.take() {
+            // The caller may have stopped waiting
+            let _ = waiter.send(result.clone());
+        }
+        Some(result)
+    }
+
+    /// Settle the broadcasts past their deadline, or with no robot left
+    /// online to answer
+    pub fn finish_due(&mut self, offline: &HashSet<String>, now: u64) -> Vec<BroadcastResult> {
+        let mut due: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| now >= pending.deadline || pending.settled(offline))
+            .map(|(command_id, _)| command_id.clone())
+            .collect();
+        due.sort();
+        due.iter()
+            .filter_map(|command_id| self.finish(command_id, now))
+            .collect()
+    }
+
+    /// Ids of the broadcasts still waiting for robots
+    pub fn in_flight(&self) -> Vec<&str> {
+        let mut ids: Vec<&str> = self.pending.keys().map(String::as_str).collect();
+        ids.sort();
+        ids
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const T0: u64 = 1_767_225_600_000;
+
+    fn expected(robots: &[&str], command_id: &str) -> BTreeMap<String, String> {
+        robots
+            .iter()
+            .map(|robot_id| (robot_id.to_string(), command_id.to_string()))
+            .collect()
+    }
+
+    fn response(command_id: &str, robot_id: &str, success: bool) -> CommandResponse {
+        CommandResponse {
+            command_id: command_id.into(),
+            robot_id: robot_id.parse().unwrap(),
+            success,
+            error: (!success).then(|| "motor fault".into()),
+            config: None,
+            status: None,
+            timestamp: T0,
+        }
+    }
+
+    #[test]
+    fn test_broadcast_settles_once_every_robot_answered() {
+        let mut tracker = BroadcastTracker::default();
+        let offline = HashSet::new();
+        let mut rx = tracker.start(
+            "CMD-1",
+            "emergency_stop",
+            expected(&["RV-001", "DR-001", "CR-001"], "CMD-1"),
+            T0,
+        );
+
+        assert_eq!(
+            tracker.on_response(&response("CMD-1", "RV-001", true), &offline, T0 + 100),
+            None
+        );
+        // A robot that joined after the broadcast is not waited for
+        assert_eq!(
+            tracker.on_response(&response("CMD-1", "RV-009", true), &offline, T0 + 150),
+            None
+        );
+        // Nor is an answer to some other command
+        assert_eq!(
+            tracker.on_response(&response("CMD-2", "DR-001", true), &offline, T0 + 180),
+            None
+        );
+        assert!(rx.try_recv().is_err());
+        assert_eq!(
+            tracker.on_response(&response("CMD-1", "DR-001", true), &offline, T0 + 200),
+            None
+        );
+        let result = tracker
+            .on_response(&response("CMD-1", "CR-001", false), &offline, T0 + 300)
+            .unwrap();
+        assert_eq!(result.acked, ["DR-001", "RV-001"]);
+        assert_eq!(result.failed, ["CR-001"]);
+        assert!(result.non_responding.is_empty());
+        assert_eq!(result.summary(), "2/3 robots confirmed emergency_stop");
+        assert_eq!(result.completed_at, T0 + 300);
+        assert_eq!(rx.try_recv().unwrap(), result);
+        assert!(tracker.in_flight().is_empty());
+    }
+
+    #[test]
+    fn test_silent_and_offline_robots_are_non_responding() {
+        let mut tracker = BroadcastTracker::default();
+        let mut offline = HashSet::new();
+        tracker.start(
+            "CMD-1",
+            "stop",
+            expected(&["RV-001", "RV-002"], "CMD-1"),
+            T0,
+        );
+        tracker.start(
+            "CMD-2",
+            "stop",
+            expected(&["RV-001", "RV-002"], "CMD-2"),
+            T0,
+        );
+        tracker.on_response(&response("CMD-1", "RV-001", true), &offline, T0 + 100);
+        tracker.on_response(&response("CMD-2", "RV-001", true), &offline, T0 + 100);
+        assert!(tracker.finish_due(&offline, T0 + 9_999).is_empty());
+
+        // RV-002 drops off: nobody is left to answer CMD-1 or CMD-2
+        offline.insert("RV-002".to_string());
+        let results = tracker.finish_due(&offline, T0 + 5_000);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].command_id, "CMD-1");
+        assert_eq!(results[0].acked, ["RV-001"]);
+        assert!(results[0].failed.is_empty());
+        assert_eq!(results[0].non_responding, ["RV-002"]);
+
+        // A robot that stays online but silent is given up on at the deadline
+        tracker.start("CMD-3", "stop", expected(&["RV-001"], "CMD-3"), T0);
+        assert!(tracker.finish_due(&HashSet::new(), T0 + 9_999).is_empty());
+        let results = tracker.finish_due(&HashSet::new(), T0 + 10_000);
+        assert_eq!(results[0].non_responding, ["RV-001"]);
+        assert_eq!(results[0].summary(), "0/1 robots confirmed stop");
+    }
+
+    #[test]
+    fn test_narrowed_broadcasts_match_each_robots_share() {
+        let mut tracker = BroadcastTracker::default();
+        let offline = HashSet::new();
+        let shares = ["RV-001", "DR-001"]
+            .iter()
+            .map(|robot_id| (robot_id.to_string(), share_id("CMD-1", robot_id)))
+            .collect();
+        let mut rx = tracker.start("CMD-1", "perform_scan", shares, T0);
+
+        // The broadcast id itself is not what the robots answer
+        assert_eq!(
+            tracker.on_response(&response("CMD-1", "RV-001", true), &offline, T0 + 100),
+            None
+        );
+        tracker.fail("CMD-1", "DR-001");
+        let result = tracker
+            .on_response(
+                &response(&share_id("CMD-1", "RV-001"), "RV-001", true),
+                &offline,
+                T0 + 200,
+            )
+            .unwrap();
+        assert_eq!(result.acked, ["RV-001"]);
+        assert_eq!(result.failed, ["DR-001"]);
+        assert!(!result.is_complete());
+        assert_eq!(rx.try_recv().unwrap(), result);
+
+        // Nobody online: settled at the next check
+        tracker.start("CMD-2", "stop", BTreeMap::new(), T0);
+        let results = tracker.finish_due(&offline, T0);
+        assert_eq!(results[0].expected(), 0);
+    }
+}
@@ -0,0 +1,75 @@
+//! What each robot can be commanded to do
+//!
+//! A robot's [`Capabilities`] are those of its type unless a `Configure`
+//! command gave it overrides, as for a rover fitted with an ultrasonic
+//! probe. The engine learns overrides from the command topics, so a config
+//! sent by the dashboard counts as well as its own rollouts.
+
+use std::collections::HashMap;
+
+use aetheris_shared::{Capabilities, RobotConfig, RobotType};
+
+/// Capability overrides per robot
+#[derive(Debug, Default)]
+pub struct CapabilityRegistry {
+    overrides: HashMap<String, RobotConfig>,
+}
+
+impl CapabilityRegistry {
+    /// Record the capability overrides in a config sent to `robot_id`.
+    /// Settings left unset keep the robot's previous overrides.
+    pub fn configure(&mut self, robot_id: &str, config: &RobotConfig) {
+        self.overrides
+            .entry(robot_id.to_string())
+            .or_default()
+            .merge(config);
+    }
+
+    /// Capabilities of `robot_id`, a robot of type `robot_type`
+    pub fn of(&self, robot_id: &str, robot_type: RobotType) -> Capabilities {
+        let capabilities = robot_type.capabilities();
+        match self.overrides.get(robot_id) {
+            Some(config) => capabilities.configured(config),
+            None => capabilities,
+        }
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetheris_shared::{AltitudeRange, ScanType};
+
+    #[test]
+    fn test_overrides_accumulate_per_robot() {
+        let mut registry = CapabilityRegistry::default();
+        registry.configure(
+            "RV-002",
+            &RobotConfig {
+                supported_scans: Some(vec![ScanType::Ultrasonic]),
+                ..RobotConfig::default()
+            },
+        );
+        registry.configure(
+            "RV-002",
+            &RobotConfig {
+                operating_altitude: Some(AltitudeRange::new(-3.0, 1.0)),
+                low_battery_threshold: Some(15.0),
+                ..RobotConfig::default()
+            },
+        );
+
+        let special = registry.of("RV-002", RobotType::Rover);
+        assert_eq!(special.scans, [ScanType::Ultrasonic]);
+        assert_eq!(special.altitude, AltitudeRange::new(-3.0, 1.0));
+        assert_eq!(special.max_speed, RobotType::Rover.max_speed());
+        assert_eq!(
+            registry.of("RV-001", RobotType::Rover),
+            RobotType::Rover.capabilities()
+        );
+    }
+}
@@ -0,0 +1,236 @@
+//! Simulation clock
+//!
+//! The fleet and sensor simulations, the battery model they drive, heartbeat
+//! timeouts, and the engine's escalation and expiry timers read time from a
+//! [`SimClock`] rather than the system clock. The real clock is the default.
+//! A [`ScaledClock`] runs simulated time faster than wall-clock time, so at
+//! `time_scale = 60` a battery cycle of an hour plays out in a minute; a
+//! [`VirtualClock`] moves only when told to, for tests.
+//!
+//! Periods of the simulation tasks are simulated time as well: at scale 60 a
+//! robot heartbeating every 5 simulated seconds does so every 83 real
+//! milliseconds. The heartbeat timeout is measured on the same clock, so
+//! robots do not time out just because time runs faster.
+//!
+//! Published timestamps and command expiry are simulated time too, and
+//! [`aetheris_shared::SystemStatus::simulated`] says so. A scaled engine is
+//! for demos: it judges expiry stamped by other processes against its own
+//! time.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use aetheris_shared::Timestamp;
+use tokio::time::{Instant, Interval};
+
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// How fast simulated time runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClockConfig {
+    /// Simulated seconds per real second; 1 runs on the system clock
+    pub time_scale: f64,
+}
+
+impl Default for ClockConfig {
+    fn default() -> Self {
+        Self { time_scale: 1.0 }
+    }
+}
+
+impl CheckConfig for ClockConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if !(self.time_scale.is_finite() && self.time_scale > 0.0) {
+            checker.error(
+                "time_scale",
+                format!("must be a positive number, got {}", self.time_scale),
+                None,
+            );
+        }
+    }
+}
+
+impl ClockConfig {
+    /// The system clock at scale 1, a scaled clock starting now otherwise
+    pub fn clock(&self) -> SharedClock {
+        if self.time_scale == 1.0 {
+            Arc::new(RealClock)
+        } else {
+            Arc::new(ScaledClock::new(self.time_scale))
+        }
+    }
+}
+
+// ============================================================================
+// CLOCKS
+// ============================================================================
+
+/// Source of simulated time
+pub trait SimClock: fmt::Debug + Send + Sync {
+    /// Current simulated wall-clock time
+    fn now(&self) -> Timestamp;
+
+    /// Current simulated monotonic time, for measuring simulated durations
+    fn instant(&self) -> Instant;
+
+    /// Simulated seconds per real second
+    fn scale(&self) -> f64;
+
+    /// Whether time is not the system clock's, so published timestamps
+    /// must not be taken for real ones
+    fn is_simulated(&self) -> bool;
+
+    /// Real time it takes for `simulated` time to pass
+    fn real(&self, simulated: Duration) -> Duration {
+        simulated.div_f64(self.scale())
+    }
+
+    /// A ticker firing every `period` of simulated time
+    fn interval(&self, period: Duration) -> Interval {
+        tokio::time::interval(self.real(period))
+    }
+}
+
+/// A clock shared by the engine and its tasks
+pub type SharedClock = Arc<dyn SimClock>;
+
+/// The system clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RealClock;
+
+impl SimClock for RealClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::now()
+    }
+
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn scale(&self) -> f64 {
+        1.0
+    }
+
+    fn is_simulated(&self) -> bool {
+        false
+    }
+}
+
+/// Simulated time starting at the real time it was created and running
+/// `scale` times as fast
+#[derive(Debug, Clone)]
+pub struct ScaledClock {
+    scale: f64,
+    started: Instant,
+    started_at: Timestamp,
+}
+
+impl ScaledClock {
+    pub fn new(scale: f64) -> Self {
+        Self {
+            scale,
+            started: Instant::now(),
+            started_at: Timestamp::now(),
+        }
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.started.elapsed().mul_f64(self.scale)
+    }
+}
+
+impl SimClock for ScaledClock {
+    fn now(&self) -> Timestamp {
+        self.started_at.saturating_add(self.elapsed())
+    }
+
+    fn instant(&self) -> Instant {
+        self.started + self.elapsed()
+    }
+
+    fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    fn is_simulated(&self) -> bool {
+        true
+    }
+}
+
+/// Simulated time that stands still until advanced. Tasks on it still
+/// tick in real time; tests drive what they call instead.
+#[derive(Debug)]
+pub struct VirtualClock {
+    started: Instant,
+    started_at: Timestamp,
+    elapsed: Mutex<Duration>,
+}
+
+impl VirtualClock {
+    pub fn new(started_at: Timestamp) -> Self {
+        Self {
+            started: Instant::now(),
+            started_at,
+            elapsed: Mutex::default(),
+        }
+    }
+
+    /// Move simulated time forward
+    pub fn advance(&self, by: Duration) {
+        *self.elapsed.lock().unwrap_or_else(|e| e.into_inner()) += by;
+    }
+
+    fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl SimClock for VirtualClock {
+    fn now(&self) -> Timestamp {
+        self.started_at.saturating_add(self.elapsed())
+    }
+
+    fn instant(&self) -> Instant {
+        self.started + self.elapsed()
+    }
+
+    fn scale(&self) -> f64 {
+        1.0
+    }
+
+    fn is_simulated(&self) -> bool {
+        true
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scaled_periods_and_virtual_time() {
+        let scaled = ClockConfig { time_scale: 60.0 }.clock();
+        assert!(scaled.is_simulated());
+        assert_eq!(scaled.real(Duration::from_secs(60)), Duration::from_secs(1));
+
+        let real = ClockConfig::default().clock();
+        assert!(!real.is_simulated());
+        assert_eq!(real.real(Duration::from_secs(5)), Duration::from_secs(5));
+
+        let clock = VirtualClock::new(Timestamp::from_millis(1_000));
+        let start = clock.instant();
+        assert_eq!(clock.now(), Timestamp::from_millis(1_000));
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(clock.now(), Timestamp::from_millis(91_000));
+        assert_eq!(clock.instant() - start, Duration::from_secs(90));
+    }
+}
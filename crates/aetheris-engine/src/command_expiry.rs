@@ -0,0 +1,182 @@
+//! Expiry of commands delivered late
+//!
+//! A command the broker held while the engine or a robot was offline can
+//! arrive minutes after it was sent, when acting on it is no longer safe.
+//! The engine stamps every command it sends with an `expires_at` from the
+//! time to live of its variant, and receivers refuse commands past it,
+//! allowing for clock skew between the sender and themselves. Stops never
+//! expire.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use aetheris_shared::Command;
+use thiserror::Error;
+
+use crate::config::{CheckConfig, ConfigChecker};
+
+/// Time to live of sent commands
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandExpiryConfig {
+    /// Time to live by command variant (see [`Command::NAMES`]); variants
+    /// left out never expire
+    pub ttl: BTreeMap<String, Duration>,
+    /// How far the receiver's clock may run ahead of the sender's
+    pub clock_slack: Duration,
+}
+
+impl Default for CommandExpiryConfig {
+    fn default() -> Self {
+        let ttl = [
+            ("move_to", 30),
+            ("perform_scan", 60),
+            ("start_patrol", 60),
+            ("return_to_base", 120),
+            ("investigate", 120),
+            ("inject_fault", 30),
+            ("inject_leak", 30),
+            ("configure", 300),
+        ];
+        Self {
+            ttl: ttl
+                .into_iter()
+                .map(|(name, secs)| (name.to_string(), Duration::from_secs(secs)))
+                .collect(),
+            clock_slack: Duration::from_secs(5),
+        }
+    }
+}
+
+impl CheckConfig for CommandExpiryConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        for (name, ttl) in &self.ttl {
+            let path = format!("ttl.{name}");
+            if !Command::NAMES.contains(&name.as_str()) {
+                checker.error(
+                    &path,
+                    format!("unknown command \"{name}\""),
+                    Some(format!("expected one of: {}", Command::NAMES.join(", "))),
+                );
+            } else if name == "stop" || name == "emergency_stop" {
+                checker.error(&path, "stops never expire", None);
+            } else {
+                checker.positive(&path, *ttl);
+            }
+        }
+    }
+}
+
+/// A command received after it expired
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("expired {late_ms} ms before delivery")]
+pub struct Expired {
+    /// Unix timestamp the command expired (milliseconds)
+    pub expires_at: u64,
+    pub late_ms: u64,
+}
+
+impl CommandExpiryConfig {
+    /// When a command sent at `now` (Unix ms) expires, or `None` if it never
+    /// does
+    pub fn expires_at(&self, command: &Command, now: u64) -> Option<u64> {
+        if !command.expires() {
+            return None;
+        }
+        let ttl = self.ttl.get(command.name())?;
+        Some(now + ttl.as_millis() as u64)
+    }
+
+    /// Whether a command received at `now` (Unix ms) may still be acted on.
+    /// Commands without an expiry, and stops, always may.
+    pub fn verify(
+        &self,
+        command: &Command,
+        expires_at: Option<u64>,
+        now: u64,
+    ) -> Result<(), Expired> {
+        let Some(expires_at) = expires_at.filter(|_| command.expires()) else {
+            return Ok(());
+        };
+        if now <= expires_at + self.clock_slack.as_millis() as u64 {
+            return Ok(());
+        }
+        Err(Expired {
+            expires_at,
+            late_ms: now - expires_at,
+        })
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetheris_shared::Position;
+
+    const NOW: u64 = 1_767_225_600_000;
+
+    fn move_to() -> Command {
+        Command::MoveTo {
+            target: Position::new(10.0, 0.0, 5.0),
+            speed: None,
+        }
+    }
+
+    #[test]
+    fn test_commands_expire_after_their_ttl_and_the_clock_slack() {
+        let config = CommandExpiryConfig::default();
+        let expires_at = config.expires_at(&move_to(), NOW).unwrap();
+        assert_eq!(expires_at, NOW + 30_000);
+
+        // Within the slack a receiver ahead of the sender still accepts it
+        assert!(config.verify(&move_to(), Some(expires_at), NOW).is_ok());
+        assert!(
+            config
+                .verify(&move_to(), Some(expires_at), expires_at + 5_000)
+                .is_ok()
+        );
+        assert_eq!(
+            config.verify(&move_to(), Some(expires_at), expires_at + 5_001),
+            Err(Expired {
+                expires_at,
+                late_ms: 5_001
+            })
+        );
+        // Ten minutes late
+        assert!(config.verify(&move_to(), Some(NOW), NOW + 600_000).is_err());
+    }
+
+    #[test]
+    fn test_far_future_and_missing_expiry_are_accepted() {
+        let config = CommandExpiryConfig::default();
+        assert!(
+            config
+                .verify(&move_to(), Some(NOW + 86_400_000), NOW)
+                .is_ok()
+        );
+        assert!(config.verify(&move_to(), None, NOW + 600_000).is_ok());
+        // Variants without a TTL are sent without an expiry
+        assert_eq!(config.expires_at(&Command::RequestKeyframe, NOW), None);
+    }
+
+    #[test]
+    fn test_stops_never_expire() {
+        let mut config = CommandExpiryConfig::default();
+        for stop in [Command::Stop, Command::EmergencyStop] {
+            assert_eq!(config.expires_at(&stop, NOW), None);
+            assert!(config.verify(&stop, Some(NOW), NOW + 600_000).is_ok());
+        }
+
+        config
+            .ttl
+            .insert("emergency_stop".into(), Duration::from_secs(1));
+        config.ttl.insert("launch".into(), Duration::from_secs(1));
+        config.ttl.insert("move_to".into(), Duration::ZERO);
+        let mut checker = ConfigChecker::default();
+        config.check(&mut checker);
+        assert_eq!(checker.finish().unwrap_err().issues.len(), 3);
+    }
+}
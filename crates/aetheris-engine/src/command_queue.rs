@@ -0,0 +1,340 @@
+//! Prioritized command dispatch
+//!
+//! Commands the engine sends go through a queue drained by a dispatch task,
+//! highest [`CommandPriority`] first and in call order within a priority,
+//! so routine commands issued in bursts cannot delay the ones that matter.
+//! Emergency commands never wait: they are published on the caller's task,
+//! ahead of everything queued. A command identical to one already queued
+//! for the same robot is dropped, and a full queue makes room by evicting
+//! its oldest low-priority command.
+
+use std::collections::VecDeque;
+use std::fmt;
+
+use aetheris_shared::Command;
+use thiserror::Error;
+
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// PRIORITY
+// ============================================================================
+
+/// How urgently a command must reach its robot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CommandPriority {
+    Low,
+    Normal,
+    High,
+    /// Published at once, never queued
+    Emergency,
+}
+
+impl CommandPriority {
+    /// Default priority of a command variant
+    pub fn of(command: &Command) -> Self {
+        match command {
+            Command::EmergencyStop | Command::Stop => Self::Emergency,
+            Command::Investigate { .. } | Command::ReturnToBase | Command::AbortMission { .. } => {
+                Self::High
+            }
+            Command::MoveTo { .. }
+            | Command::StartPatrol { .. }
+            | Command::Configure { .. }
+            | Command::RegisterSection { .. }
+            | Command::SetZoneMode { .. }
+            | Command::AssignAnomaly { .. }
+            | Command::UpdateAssignment { .. }
+            | Command::AcknowledgeAnomaly { .. }
+            | Command::ResolveAnomaly { .. }
+            | Command::RequestKeyframe
+            | Command::CorrectPosition
+            | Command::GetConfig
+            | Command::StartMission { .. } => Self::Normal,
+            Command::PerformScan { .. }
+            | Command::InjectFault { .. }
+            | Command::ClearFault { .. }
+            | Command::InjectLeak { .. }
+            | Command::ClearLeak { .. } => Self::Low,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Normal => "normal",
+            Self::High => "high",
+            Self::Emergency => "emergency",
+        }
+    }
+}
+
+impl fmt::Display for CommandPriority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Command queue behavior
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandQueueConfig {
+    /// Commands waiting for dispatch at most
+    pub max_depth: usize,
+}
+
+impl Default for CommandQueueConfig {
+    fn default() -> Self {
+        Self { max_depth: 256 }
+    }
+}
+
+impl CheckConfig for CommandQueueConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if self.max_depth == 0 {
+            checker.error(
+                "max_depth",
+                "must be greater than zero",
+                Some("the default is 256".into()),
+            );
+        }
+    }
+}
+
+// ============================================================================
+// QUEUE
+// ============================================================================
+
+/// A command waiting for dispatch
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueuedCommand {
+    pub robot_id: String,
+    /// Id the command is published under, known to the caller from the
+    /// moment it is queued
+    pub command_id: String,
+    pub command: Command,
+    pub priority: CommandPriority,
+}
+
+/// What the queue did with a command
+#[derive(Debug, Clone, PartialEq)]
+pub enum Enqueued {
+    Queued,
+    /// An emergency command, published without queueing
+    Dispatched,
+    /// The same command is already queued for the robot, under this id
+    Duplicate(String),
+    /// Queued in place of the oldest low-priority command
+    Evicted(QueuedCommand),
+}
+
+/// The queue is full of commands that may not be evicted for this one
+#[derive(Debug, Error)]
+#[error("command queue full ({depth} commands), {priority} {command} to {robot_id} dropped")]
+pub struct QueueFull {
+    pub robot_id: String,
+    pub command: &'static str,
+    pub priority: CommandPriority,
+    pub depth: usize,
+}
+
+/// Commands waiting for dispatch, one FIFO per queued priority
+#[derive(Debug)]
+pub struct CommandQueue {
+    max_depth: usize,
+    /// Indexed by priority, `Low` first
+    lanes: [VecDeque<QueuedCommand>; 3],
+}
+
+impl Default for CommandQueue {
+    fn default() -> Self {
+        Self::new(&CommandQueueConfig::default())
+    }
+}
+
+impl CommandQueue {
+    pub fn new(config: &CommandQueueConfig) -> Self {
+        Self {
+            max_depth: config.max_depth,
+            lanes: Default::default(),
+        }
+    }
+
+    /// Queue a command. Emergency commands are queued as high ones; callers
+    /// publish them directly instead.
+    pub fn push(
+        &mut self,
+        robot_id: &str,
+        command_id: &str,
+        command: Command,
+        priority: CommandPriority,
+    ) -> Result<Enqueued, QueueFull> {
+        let priority = priority.min(CommandPriority::High);
+        let duplicate = self
+            .lanes
+            .iter()
+            .flatten()
+            .find(|queued| queued.robot_id == robot_id && queued.command == command);
+        if let Some(queued) = duplicate {
+            return Ok(Enqueued::Duplicate(queued.command_id.clone()));
+        }
+
+        let evicted = if self.len() >= self.max_depth {
+            match self.lanes[CommandPriority::Low as usize].pop_front() {
+                Some(oldest) => Some(oldest),
+                None => {
+                    return Err(QueueFull {
+                        robot_id: robot_id.to_string(),
+                        command: command.name(),
+                        priority,
+                        depth: self.len(),
+                    });
+                }
+            }
+        } else {
+            None
+        };
+        self.lanes[priority as usize].push_back(QueuedCommand {
+            robot_id: robot_id.to_string(),
+            command_id: command_id.to_string(),
+            command,
+            priority,
+        });
+        Ok(evicted.map_or(Enqueued::Queued, Enqueued::Evicted))
+    }
+
+    /// The next command to dispatch: the oldest of the highest priority
+    pub fn pop(&mut self) -> Option<QueuedCommand> {
+        self.lanes.iter_mut().rev().find_map(VecDeque::pop_front)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lanes.iter().map(VecDeque::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lanes.iter().all(VecDeque::is_empty)
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetheris_shared::{Position, ScanType};
+
+    fn scan() -> Command {
+        Command::PerformScan {
+            scan_type: ScanType::Thermal,
+        }
+    }
+
+    fn queue(max_depth: usize) -> CommandQueue {
+        CommandQueue::new(&CommandQueueConfig { max_depth })
+    }
+
+    fn push(queue: &mut CommandQueue, robot_id: &str, command: Command) -> Enqueued {
+        let priority = CommandPriority::of(&command);
+        let command_id = format!("CMD-{}", queue.len() + 1);
+        queue
+            .push(robot_id, &command_id, command, priority)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_higher_priorities_preempt_in_call_order() {
+        let mut queue = queue(16);
+        for robot in ["RV-001", "RV-002", "RV-003"] {
+            push(&mut queue, robot, scan());
+        }
+        push(
+            &mut queue,
+            "RV-001",
+            Command::MoveTo {
+                target: Position::new(1.0, 0.0, 0.0),
+                speed: None,
+            },
+        );
+        push(&mut queue, "RV-002", Command::ReturnToBase);
+        push(
+            &mut queue,
+            "RV-003",
+            Command::Investigate {
+                anomaly_id: "ANM-1".into(),
+            },
+        );
+
+        let order: Vec<_> = std::iter::from_fn(|| queue.pop())
+            .map(|queued| (queued.robot_id, queued.command.name()))
+            .collect();
+        assert_eq!(
+            order,
+            [
+                ("RV-002".to_string(), "return_to_base"),
+                ("RV-003".to_string(), "investigate"),
+                ("RV-001".to_string(), "move_to"),
+                ("RV-001".to_string(), "perform_scan"),
+                ("RV-002".to_string(), "perform_scan"),
+                ("RV-003".to_string(), "perform_scan"),
+            ]
+        );
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_identical_commands_are_queued_once_per_robot() {
+        let mut queue = queue(16);
+        let move_to = Command::MoveTo {
+            target: Position::new(5.0, 0.0, 0.0),
+            speed: Some(1.0),
+        };
+        assert_eq!(
+            push(&mut queue, "RV-001", move_to.clone()),
+            Enqueued::Queued
+        );
+        assert_eq!(
+            push(&mut queue, "RV-001", move_to.clone()),
+            Enqueued::Duplicate("CMD-1".into())
+        );
+        assert_eq!(push(&mut queue, "RV-002", move_to), Enqueued::Queued);
+        assert_eq!(queue.len(), 2);
+    }
+
+    #[test]
+    fn test_full_queue_evicts_the_oldest_low_command() {
+        let mut queue = queue(2);
+        push(&mut queue, "RV-001", scan());
+        push(&mut queue, "RV-002", scan());
+
+        let evicted = push(&mut queue, "RV-003", Command::ReturnToBase);
+        assert!(matches!(evicted, Enqueued::Evicted(old) if old.robot_id == "RV-001"));
+        let evicted = push(&mut queue, "RV-001", Command::ReturnToBase);
+        assert!(matches!(evicted, Enqueued::Evicted(old) if old.robot_id == "RV-002"));
+
+        // Nothing low left to evict
+        let full = queue
+            .push("RV-004", "CMD-9", scan(), CommandPriority::Low)
+            .unwrap_err();
+        assert_eq!(full.depth, 2);
+        assert_eq!(queue.len(), 2);
+    }
+
+    #[test]
+    fn test_priority_of_stop_commands_is_emergency() {
+        assert_eq!(
+            CommandPriority::of(&Command::EmergencyStop),
+            CommandPriority::Emergency
+        );
+        assert_eq!(
+            CommandPriority::of(&Command::Stop),
+            CommandPriority::Emergency
+        );
+        assert_eq!(CommandPriority::of(&scan()), CommandPriority::Low);
+    }
+}
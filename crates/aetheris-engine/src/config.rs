@@ -0,0 +1,1991 @@
+//! Engine configuration and startup validation
+//!
+//! [`EngineConfig`] gathers every subsystem's configuration. Each subsystem
+//! implements [`CheckConfig`] for its own section, and
+//! [`EngineConfig::validate`] runs all of them plus the cross-section checks
+//! before anything connects, collecting every problem into one
+//! [`ConfigReport`] instead of stopping at the first.
+//!
+//! A deployment overrides the defaults with a TOML or JSON [`ConfigFile`]
+//! (broker, timing, metrics endpoint, and the simulated fleet); the binary applies its command
+//! line and environment overrides on top before validating.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use aetheris_shared::{
+    BatteryProfile, ChargingStation, Encoding, Geofence, OperatorRole, PipelineSection, RobotType,
+    SeverityLevel,
+};
+
+use crate::acks::AckConfig;
+use crate::alarms::AlarmConfig;
+use crate::alert_dedup::AlertDedupConfig;
+use crate::anomalies::{EscalationConfig, MergeConfig};
+use crate::anomaly_store::AnomalyStoreConfig;
+use crate::authorization::AuthorizationConfig;
+use crate::backpressure::MessageChannelConfig;
+use crate::battery::BatteryConfig;
+use crate::battery_manager::BatteryManagerConfig;
+use crate::bounds::WorldBounds;
+use crate::broadcast::BroadcastConfig;
+use crate::clock::ClockConfig;
+use crate::command_expiry::CommandExpiryConfig;
+use crate::command_queue::CommandQueueConfig;
+use crate::correlation::CorrelationConfig;
+use crate::dead_letters::DeadLetterConfig;
+use crate::delta::DeltaConfig;
+use crate::detectors::{ThresholdConfig, ThresholdRule};
+use crate::dispatch::DispatchConfig;
+use crate::environment::EnvironmentSimConfig;
+use crate::event_log::EventLogConfig;
+use crate::expected_fleet::ExpectedFleetConfig;
+use crate::failover::BrokerEndpoint;
+use crate::fanout::FanoutConfig;
+use crate::faults::RecoveryConfig;
+use crate::flapping::FlapConfig;
+use crate::geofences::{FenceAction, GeofenceConfig};
+#[cfg(feature = "http")]
+use crate::http_bridge::HttpConfig;
+use crate::leader::LeaderConfig;
+use crate::link_quality::LinkQualityConfig;
+use crate::metrics::MetricsConfig;
+use crate::position_filter::PositionFilterConfig;
+use crate::robot_config::RobotConfigStorage;
+use crate::rollout::RolloutConfig;
+use crate::route_requests::RouteRequestConfig;
+use crate::section_health::SectionHealthConfig;
+use crate::sections::{PipelineConfig, UnknownSectionPolicy};
+use crate::sensor_health::SensorHealthConfig;
+use crate::sequence::SequenceConfig;
+use crate::simulation::{FleetConfig, RobotSpec, SimulationTiming};
+use crate::source_binding::SourceBindings;
+use crate::source_signing::SourceSigningConfig;
+use crate::store_forward::StoreForwardConfig;
+use crate::telemetry_store::TelemetryStoreConfig;
+use crate::timeline::TimelineConfig;
+use crate::transport::{Secret, TlsConfig};
+use crate::trends::TrendConfig;
+use crate::triage::TriageConfig;
+use crate::wall_thickness::WallThicknessConfig;
+use crate::zones::ZoneConfig;
+use crate::{DEFAULT_HEARTBEAT_TIMEOUT, MqttConfig};
+
+/// Serde adapter for durations written as (fractional) seconds
+pub mod duration_secs {
+    use std::time::Duration;
+
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(duration.as_secs_f64())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let secs = f64::deserialize(deserializer)?;
+        Duration::try_from_secs_f64(secs).map_err(D::Error::custom)
+    }
+
+    /// The same for optional durations
+    pub mod option {
+        use std::time::Duration;
+
+        use serde::{Deserialize, Deserializer};
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Duration>, D::Error> {
+            #[derive(Deserialize)]
+            struct Secs(#[serde(with = "super")] Duration);
+            Ok(Option::<Secs>::deserialize(deserializer)?.map(|Secs(duration)| duration))
+        }
+    }
+
+    /// The same for maps of durations
+    pub mod map {
+        use std::collections::BTreeMap;
+        use std::time::Duration;
+
+        use serde::{Deserialize, Deserializer};
+
+        pub fn deserialize<'de, D, K>(deserializer: D) -> Result<BTreeMap<K, Duration>, D::Error>
+        where
+            D: Deserializer<'de>,
+            K: Deserialize<'de> + Ord,
+        {
+            #[derive(Deserialize)]
+            struct Secs(#[serde(with = "super")] Duration);
+            Ok(BTreeMap::<K, Secs>::deserialize(deserializer)?
+                .into_iter()
+                .map(|(key, Secs(duration))| (key, duration))
+                .collect())
+        }
+    }
+}
+
+/// Exit code for an invalid configuration (sysexits `EX_CONFIG`)
+pub const EXIT_INVALID_CONFIG: i32 = 78;
+
+// ============================================================================
+// VALIDATION FRAMEWORK
+// ============================================================================
+
+/// One configuration problem
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigIssue {
+    /// Dotted path into the configuration (e.g., "alarms.pressure.hysteresis")
+    pub path: String,
+    pub message: String,
+    /// How to fix it, when there is an obvious fix
+    pub suggestion: Option<String>,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " ({})", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// Every problem found in a configuration
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ConfigReport {
+    pub issues: Vec<ConfigIssue>,
+}
+
+impl fmt::Display for ConfigReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "configuration has {} problem{}:",
+            self.issues.len(),
+            if self.issues.len() == 1 { "" } else { "s" }
+        )?;
+        for issue in &self.issues {
+            write!(f, "\n  - {}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigReport {}
+
+/// Collects issues while walking configuration sections
+#[derive(Debug, Default)]
+pub struct ConfigChecker {
+    path: Vec<String>,
+    issues: Vec<ConfigIssue>,
+}
+
+impl ConfigChecker {
+    /// Run `check` with `name` appended to the current path
+    pub fn section(&mut self, name: &str, check: impl FnOnce(&mut Self)) {
+        self.path.push(name.to_string());
+        check(self);
+        self.path.pop();
+    }
+
+    /// Check a subsystem's configuration under `name`
+    pub fn check_section(&mut self, name: &str, config: &impl CheckConfig) {
+        self.section(name, |checker| config.check(checker));
+    }
+
+    /// Record a problem with `field` in the current section
+    pub fn error(&mut self, field: &str, message: impl Into<String>, suggestion: Option<String>) {
+        let path = self
+            .path
+            .iter()
+            .map(String::as_str)
+            .chain((!field.is_empty()).then_some(field))
+            .collect::<Vec<_>>()
+            .join(".");
+        self.issues.push(ConfigIssue {
+            path,
+            message: message.into(),
+            suggestion,
+        });
+    }
+
+    /// Require a non-zero duration
+    pub fn positive(&mut self, field: &str, value: Duration) {
+        if value.is_zero() {
+            self.error(field, "must be greater than zero", None);
+        }
+    }
+
+    /// Require a value within `0.0..=1.0`
+    pub fn fraction(&mut self, field: &str, value: f64) {
+        if !(0.0..=1.0).contains(&value) {
+            self.error(
+                field,
+                format!("must be between 0 and 1, got {}", value),
+                None,
+            );
+        }
+    }
+
+    pub fn finish(self) -> Result<(), ConfigReport> {
+        if self.issues.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigReport {
+                issues: self.issues,
+            })
+        }
+    }
+}
+
+/// Implemented by each subsystem for its own configuration section
+pub trait CheckConfig {
+    fn check(&self, checker: &mut ConfigChecker);
+}
+
+// ============================================================================
+// ENGINE CONFIGURATION
+// ============================================================================
+
+/// Complete engine configuration
+#[derive(Debug, Clone)]
+pub struct EngineConfig {
+    pub mqtt: MqttConfig,
+    /// Channel from the event loop to the engine's consumer
+    pub message_channel: MessageChannelConfig,
+    /// Time without a heartbeat after which a robot is marked offline
+    pub heartbeat_timeout: Duration,
+    /// How fast simulated time runs
+    pub clock: ClockConfig,
+    /// Robots whose connection keeps dropping
+    pub flapping: FlapConfig,
+    /// Clock skew estimation from heartbeat timestamps
+    pub link_quality: LinkQualityConfig,
+    pub simulation: SimulationTiming,
+    pub alarms: AlarmConfig,
+    pub triage: TriageConfig,
+    /// Robots sent to new anomalies without an operator
+    pub dispatch: DispatchConfig,
+    /// Operators and signatures incoming commands must carry
+    pub authorization: AuthorizationConfig,
+    pub correlation: CorrelationConfig,
+    pub recovery: RecoveryConfig,
+    /// Battery drain and charging of the simulated fleet
+    pub battery: BatteryConfig,
+    /// Runtime predictions and automatic return to charge
+    pub battery_manager: BatteryManagerConfig,
+    /// Volumes robots' moves must keep out of
+    pub geofences: GeofenceConfig,
+    pub trends: TrendConfig,
+    /// Wall-thinning projections from ultrasonic readings
+    pub wall_thickness: WallThicknessConfig,
+    /// Rules of the built-in threshold detector
+    pub thresholds: ThresholdConfig,
+    /// Scoring of the per-section health rollup
+    pub section_health: SectionHealthConfig,
+    /// Topics each envelope source may publish on
+    pub source_bindings: SourceBindings,
+    /// Keys envelope sources must sign with
+    pub source_signing: SourceSigningConfig,
+    pub rollout: RolloutConfig,
+    /// Duplicate and cross-origin anomaly matching
+    pub merging: MergeConfig,
+    /// Severity escalation of anomalies nobody acknowledges
+    pub escalation: EscalationConfig,
+    /// Folding of repeated engine alerts before they are published
+    pub alert_dedup: AlertDedupConfig,
+    /// Volume every robot and reported position must stay inside
+    pub world_bounds: WorldBounds,
+    /// Retention and per-source rate limit of quarantined messages
+    pub dead_letters: DeadLetterConfig,
+    /// Rate limit of route requests
+    pub route_requests: RouteRequestConfig,
+    /// File keeping every robot's configuration across restarts
+    pub robot_configs: RobotConfigStorage,
+    /// Journal keeping open anomalies across restarts
+    pub anomaly_store: AnomalyStoreConfig,
+    /// Telemetry history kept for replay
+    pub telemetry_store: TelemetryStoreConfig,
+    /// Instance identity and leader election between engines
+    pub leader: LeaderConfig,
+    /// Position history kept for incident timelines
+    pub timeline: TimelineConfig,
+    /// Zone footprints whose operational mode can be changed
+    pub zones: ZoneConfig,
+    /// Concurrency of per-robot command batches
+    pub fanout: FanoutConfig,
+    /// Prioritized dispatch of the engine's commands
+    pub command_queue: CommandQueueConfig,
+    /// Command holding for robots with a weak link
+    pub store_forward: StoreForwardConfig,
+    /// Environment sensor fault detection
+    pub sensor_health: SensorHealthConfig,
+    /// Smoothing of telemetry positions
+    pub position_filter: PositionFilterConfig,
+    /// Robots operations expects on shift
+    pub expected_fleet: ExpectedFleetConfig,
+    /// How long sent commands wait for their response
+    pub acks: AckConfig,
+    /// How long robots have to answer a broadcast
+    pub broadcast: BroadcastConfig,
+    /// How long sent commands may still be acted on
+    pub command_expiry: CommandExpiryConfig,
+    /// Robots of the simulated fleet
+    pub fleet: FleetConfig,
+    /// Gaps in incoming envelope sequence numbers
+    pub sequence: SequenceConfig,
+    /// Prometheus scrape endpoint
+    pub metrics: MetricsConfig,
+    /// JSONL trail of every engine message
+    pub event_log: EventLogConfig,
+    /// Keyframes and deltas in published telemetry
+    pub delta: DeltaConfig,
+    /// Sections of the monitored pipeline
+    pub pipeline: PipelineConfig,
+    /// Simulated section sensors and leak scenarios
+    pub environment: EnvironmentSimConfig,
+    /// Dashboard HTTP and WebSocket bridge
+    #[cfg(feature = "http")]
+    pub http: HttpConfig,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            mqtt: MqttConfig::default(),
+            message_channel: MessageChannelConfig::default(),
+            heartbeat_timeout: DEFAULT_HEARTBEAT_TIMEOUT,
+            clock: ClockConfig::default(),
+            flapping: FlapConfig::default(),
+            link_quality: LinkQualityConfig::default(),
+            simulation: SimulationTiming::default(),
+            alarms: AlarmConfig::default(),
+            triage: TriageConfig::default(),
+            dispatch: DispatchConfig::default(),
+            authorization: AuthorizationConfig::default(),
+            correlation: CorrelationConfig::default(),
+            recovery: RecoveryConfig::default(),
+            battery: BatteryConfig::default(),
+            battery_manager: BatteryManagerConfig::default(),
+            geofences: GeofenceConfig::default(),
+            trends: TrendConfig::default(),
+            wall_thickness: WallThicknessConfig::default(),
+            thresholds: ThresholdConfig::default(),
+            section_health: SectionHealthConfig::default(),
+            source_bindings: SourceBindings::default(),
+            source_signing: SourceSigningConfig::default(),
+            rollout: RolloutConfig::default(),
+            merging: MergeConfig::default(),
+            escalation: EscalationConfig::default(),
+            alert_dedup: AlertDedupConfig::default(),
+            world_bounds: WorldBounds::default(),
+            dead_letters: DeadLetterConfig::default(),
+            route_requests: RouteRequestConfig::default(),
+            robot_configs: RobotConfigStorage::default(),
+            anomaly_store: AnomalyStoreConfig::default(),
+            telemetry_store: TelemetryStoreConfig::default(),
+            leader: LeaderConfig::default(),
+            timeline: TimelineConfig::default(),
+            zones: ZoneConfig::default(),
+            fanout: FanoutConfig::default(),
+            command_queue: CommandQueueConfig::default(),
+            store_forward: StoreForwardConfig::default(),
+            sensor_health: SensorHealthConfig::default(),
+            position_filter: PositionFilterConfig::default(),
+            expected_fleet: ExpectedFleetConfig::default(),
+            acks: AckConfig::default(),
+            broadcast: BroadcastConfig::default(),
+            command_expiry: CommandExpiryConfig::default(),
+            fleet: FleetConfig::default(),
+            sequence: SequenceConfig::default(),
+            metrics: MetricsConfig::default(),
+            event_log: EventLogConfig::default(),
+            delta: DeltaConfig::default(),
+            pipeline: PipelineConfig::default(),
+            environment: EnvironmentSimConfig::default(),
+            #[cfg(feature = "http")]
+            http: HttpConfig::default(),
+        }
+    }
+}
+
+impl EngineConfig {
+    /// Validate every section and the references between them
+    pub fn validate(&self) -> Result<(), ConfigReport> {
+        let mut checker = ConfigChecker::default();
+        checker.check_section("mqtt", &self.mqtt);
+        checker.check_section("message_channel", &self.message_channel);
+        checker.positive("heartbeat_timeout", self.heartbeat_timeout);
+        checker.check_section("clock", &self.clock);
+        checker.check_section("flapping", &self.flapping);
+        checker.check_section("link_quality", &self.link_quality);
+        checker.check_section("simulation", &self.simulation);
+        checker.check_section("alarms", &self.alarms);
+        checker.check_section("triage", &self.triage);
+        checker.check_section("dispatch", &self.dispatch);
+        checker.check_section("authorization", &self.authorization);
+        checker.check_section("correlation", &self.correlation);
+        checker.check_section("recovery", &self.recovery);
+        checker.check_section("battery", &self.battery);
+        checker.check_section("battery_manager", &self.battery_manager);
+        checker.check_section("geofences", &self.geofences);
+        checker.check_section("trends", &self.trends);
+        checker.check_section("wall_thickness", &self.wall_thickness);
+        checker.check_section("thresholds", &self.thresholds);
+        checker.check_section("section_health", &self.section_health);
+        checker.check_section("source_bindings", &self.source_bindings);
+        checker.check_section("source_signing", &self.source_signing);
+        checker.check_section("rollout", &self.rollout);
+        checker.check_section("merging", &self.merging);
+        checker.check_section("escalation", &self.escalation);
+        checker.check_section("alert_dedup", &self.alert_dedup);
+        checker.check_section("world_bounds", &self.world_bounds);
+        checker.check_section("dead_letters", &self.dead_letters);
+        checker.check_section("route_requests", &self.route_requests);
+        checker.check_section("robot_configs", &self.robot_configs);
+        checker.check_section("anomaly_store", &self.anomaly_store);
+        checker.check_section("telemetry_store", &self.telemetry_store);
+        checker.check_section("leader", &self.leader);
+        checker.check_section("timeline", &self.timeline);
+        checker.check_section("zones", &self.zones);
+        checker.check_section("fanout", &self.fanout);
+        checker.check_section("command_queue", &self.command_queue);
+        checker.check_section("store_forward", &self.store_forward);
+        checker.check_section("sensor_health", &self.sensor_health);
+        checker.check_section("position_filter", &self.position_filter);
+        checker.check_section("expected_fleet", &self.expected_fleet);
+        checker.check_section("acks", &self.acks);
+        checker.check_section("broadcast", &self.broadcast);
+        checker.check_section("command_expiry", &self.command_expiry);
+        checker.check_section("fleet", &self.fleet);
+        checker.check_section("sequence", &self.sequence);
+        checker.check_section("metrics", &self.metrics);
+        checker.check_section("event_log", &self.event_log);
+        checker.check_section("delta", &self.delta);
+        checker.check_section("pipeline", &self.pipeline);
+        checker.check_section("environment", &self.environment);
+        #[cfg(feature = "http")]
+        checker.check_section("http", &self.http);
+
+        // Cross-section: simulated robots must start inside the world
+        for (i, robot) in self.fleet.robots.iter().enumerate() {
+            if robot.position.is_finite() && !self.world_bounds.contains(&robot.position) {
+                checker.error(
+                    &format!("fleet.robots[{i}].position"),
+                    "is outside world_bounds",
+                    None,
+                );
+            }
+        }
+
+        // Cross-section: jittered heartbeats must fit the offline timeout
+        if !self.heartbeat_timeout.is_zero()
+            && let Err(violation) = self
+                .simulation
+                .validate_against_timeout(self.heartbeat_timeout)
+        {
+            checker.error(
+                "simulation.heartbeat_interval",
+                violation.to_string(),
+                Some(format!(
+                    "keep heartbeat_interval at or below {:?} or raise heartbeat_timeout",
+                    self.heartbeat_timeout
+                        .div_f64(2.0 * (1.0 + self.simulation.jitter_fraction.clamp(0.0, 1.0)))
+                )),
+            );
+        }
+        checker.finish()
+    }
+}
+
+/// Validate `config` for `--check-config`, writing the outcome to `out`.
+///
+/// Returns the process exit code: 0 when valid, [`EXIT_INVALID_CONFIG`]
+/// otherwise.
+pub fn run_config_check(config: &EngineConfig, out: &mut impl Write) -> i32 {
+    match config.validate() {
+        Ok(()) => {
+            let _ = writeln!(out, "configuration OK");
+            0
+        }
+        Err(report) => {
+            let _ = writeln!(out, "{}", report);
+            EXIT_INVALID_CONFIG
+        }
+    }
+}
+
+// ============================================================================
+// CONFIGURATION FILE
+// ============================================================================
+
+/// A configuration file that could not be used
+#[derive(Debug, Error)]
+pub enum ConfigFileError {
+    #[error("cannot read {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("{}: {source}", path.display())]
+    Toml {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("{}: {source}", path.display())]
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+}
+
+/// Settings a configuration file may override; everything left out keeps
+/// its default. Unknown keys are rejected so typos do not pass silently.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    #[serde(default)]
+    pub mqtt: BrokerSettings,
+    #[serde(default)]
+    pub message_channel: MessageChannelSettings,
+    #[serde(default, with = "duration_secs::option")]
+    pub heartbeat_timeout: Option<Duration>,
+    #[serde(default)]
+    pub clock: ClockSettings,
+    #[serde(default)]
+    pub link_quality: LinkQualitySettings,
+    #[serde(default)]
+    pub simulation: TimingSettings,
+    #[serde(default)]
+    pub dispatch: DispatchSettings,
+    #[serde(default)]
+    pub authorization: AuthorizationSettings,
+    #[serde(default)]
+    pub source_signing: SourceSigningSettings,
+    #[serde(default)]
+    pub command_expiry: CommandExpirySettings,
+    #[serde(default)]
+    pub broadcast: BroadcastSettings,
+    #[serde(default)]
+    pub metrics: MetricsSettings,
+    #[serde(default)]
+    pub event_log: EventLogSettings,
+    #[serde(default)]
+    pub dead_letters: DeadLetterSettings,
+    #[serde(default)]
+    pub route_requests: RouteRequestSettings,
+    #[serde(default)]
+    pub robot_configs: RobotConfigSettings,
+    #[serde(default)]
+    pub anomaly_store: AnomalyStoreSettings,
+    #[serde(default)]
+    pub telemetry_store: TelemetryStoreSettings,
+    #[serde(default)]
+    pub thresholds: ThresholdSettings,
+    #[serde(default)]
+    pub battery_manager: BatteryManagerSettings,
+    #[serde(default)]
+    pub geofences: GeofenceSettings,
+    #[serde(default)]
+    pub leader: LeaderSettings,
+    #[serde(default)]
+    pub delta: DeltaSettings,
+    #[serde(default)]
+    pub pipeline: PipelineSettings,
+    #[serde(default)]
+    pub environment: EnvironmentSettings,
+    #[cfg(feature = "http")]
+    #[serde(default)]
+    pub http: HttpSettings,
+    /// Replaces the demo fleet when not empty
+    #[serde(default)]
+    pub robots: Vec<RobotSpec>,
+}
+
+/// Broker connection overrides
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BrokerSettings {
+    pub broker_host: Option<String>,
+    pub broker_port: Option<u16>,
+    pub client_id: Option<String>,
+    pub keep_alive_secs: Option<u64>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Connect over TLS
+    pub tls: Option<TlsSettings>,
+    /// Format of published payloads: "json", "cbor" or "msgpack"
+    pub encoding: Option<Encoding>,
+    /// Answer each robot in the format it publishes in
+    pub negotiate_encoding: Option<bool>,
+    /// Keep publishing alerts on the flat `aetheris/alerts` topic
+    pub legacy_alerts: Option<bool>,
+    /// Subscribe only to alerts at or above this severity
+    pub min_alert_severity: Option<SeverityLevel>,
+    /// Replaces the configured backup brokers, in order of preference
+    #[serde(default)]
+    pub backup_brokers: Vec<BackupBrokerSettings>,
+    /// Consecutive failed connection attempts before trying the next broker
+    pub failover_after: Option<u32>,
+    #[serde(default, with = "duration_secs::option")]
+    pub failback_interval: Option<Duration>,
+    #[serde(default)]
+    pub offline_buffer: OfflineBufferSettings,
+}
+
+/// Bounds of what is held while no broker is reachable
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OfflineBufferSettings {
+    pub alerts: Option<usize>,
+    /// Robots whose latest telemetry is held
+    pub telemetry: Option<usize>,
+    pub other: Option<usize>,
+}
+
+/// A backup broker; credentials and TLS left out are those of the primary
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BackupBrokerSettings {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub tls: Option<TlsSettings>,
+}
+
+/// TLS to a broker
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsSettings {
+    /// Required unless `insecure_skip_verify` is set
+    #[serde(default)]
+    pub ca_path: PathBuf,
+    pub client_cert_path: Option<PathBuf>,
+    pub client_key_path: Option<PathBuf>,
+    #[serde(default)]
+    pub insecure_skip_verify: bool,
+    /// ALPN protocols, most preferred first
+    #[serde(default)]
+    pub alpn: Vec<String>,
+}
+
+impl From<TlsSettings> for TlsConfig {
+    fn from(settings: TlsSettings) -> Self {
+        let TlsSettings {
+            ca_path,
+            client_cert_path,
+            client_key_path,
+            insecure_skip_verify,
+            alpn,
+        } = settings;
+        Self {
+            ca_path,
+            client_cert_path,
+            client_key_path,
+            insecure_skip_verify,
+            alpn,
+        }
+    }
+}
+
+/// Engine message channel overrides
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MessageChannelSettings {
+    pub capacity: Option<usize>,
+}
+
+/// Simulation clock overrides
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClockSettings {
+    /// Simulated seconds per real second
+    pub time_scale: Option<f64>,
+}
+
+/// Clock skew estimation overrides
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LinkQualitySettings {
+    /// Heartbeats the median is taken over
+    pub window: Option<usize>,
+    #[serde(default, with = "duration_secs::option")]
+    pub max_skew: Option<Duration>,
+}
+
+/// Auto-dispatch overrides
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DispatchSettings {
+    pub enabled: Option<bool>,
+    pub min_severity: Option<SeverityLevel>,
+    /// Percent
+    pub min_battery: Option<f64>,
+}
+
+/// Command authorization overrides
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuthorizationSettings {
+    pub enabled: Option<bool>,
+    /// Replaces the commands of each role listed
+    #[serde(default)]
+    pub policy: BTreeMap<OperatorRole, BTreeSet<String>>,
+    /// Roles by operator id, added to the configured ones
+    #[serde(default)]
+    pub operators: BTreeMap<String, OperatorRole>,
+    /// Signing keys by operator id, added to the configured ones
+    #[serde(default)]
+    pub keys: BTreeMap<String, String>,
+}
+
+/// Envelope signing overrides
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SourceSigningSettings {
+    /// Signing keys by source id, added to the configured ones
+    #[serde(default)]
+    pub keys: BTreeMap<String, String>,
+}
+
+/// Command expiry overrides
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CommandExpirySettings {
+    /// Seconds by command variant, added to the configured ones
+    #[serde(default, with = "duration_secs::map")]
+    pub ttl: BTreeMap<String, Duration>,
+    #[serde(default, with = "duration_secs::option")]
+    pub clock_slack: Option<Duration>,
+}
+
+/// Broadcast tracking overrides
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BroadcastSettings {
+    #[serde(default, with = "duration_secs::option")]
+    pub deadline: Option<Duration>,
+}
+
+/// Metrics endpoint overrides
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetricsSettings {
+    /// Serve `/metrics` on this port, on every interface
+    pub port: Option<u16>,
+}
+
+/// Dashboard bridge overrides
+#[cfg(feature = "http")]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HttpSettings {
+    /// Serve the bridge on this port, on every interface
+    pub port: Option<u16>,
+    pub allowed_origins: Option<Vec<String>>,
+    pub command_token: Option<String>,
+    pub query_database: Option<PathBuf>,
+}
+
+/// Event log overrides
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EventLogSettings {
+    /// Write the log into this directory
+    pub directory: Option<PathBuf>,
+    pub rotate_bytes: Option<u64>,
+    /// Seconds
+    #[serde(default, with = "duration_secs::option")]
+    pub rotate_interval: Option<Duration>,
+    pub keep_files: Option<usize>,
+}
+
+/// Dead-letter overrides
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeadLetterSettings {
+    pub retained: Option<usize>,
+    pub burst: Option<u32>,
+    /// Seconds
+    #[serde(default, with = "duration_secs::option")]
+    pub window: Option<Duration>,
+    pub sample_every: Option<u32>,
+}
+
+/// Route request rate limit overrides
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RouteRequestSettings {
+    pub burst: Option<u32>,
+    pub total: Option<u32>,
+    /// Seconds
+    #[serde(default, with = "duration_secs::option")]
+    pub window: Option<Duration>,
+}
+
+/// Robot configuration storage overrides
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RobotConfigSettings {
+    pub path: Option<PathBuf>,
+}
+
+/// Anomaly journal overrides
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AnomalyStoreSettings {
+    pub path: Option<PathBuf>,
+    pub compact_after: Option<usize>,
+}
+
+/// Telemetry history overrides
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TelemetryStoreSettings {
+    pub path: Option<PathBuf>,
+    /// Seconds
+    #[serde(default, with = "duration_secs::option")]
+    pub retention: Option<Duration>,
+    pub max_records: Option<usize>,
+}
+
+/// Threshold detector overrides
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ThresholdSettings {
+    /// Replaces the configured rules
+    pub rules: Option<Vec<ThresholdRule>>,
+}
+
+/// Battery management overrides
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatteryManagerSettings {
+    pub auto_return: Option<bool>,
+    /// Replaces the demo stations
+    pub stations: Option<Vec<ChargingStation>>,
+    /// Drain by robot type, added to the configured profiles
+    #[serde(default)]
+    pub profiles: BTreeMap<RobotType, BatteryProfile>,
+    /// Percent
+    pub reserve: Option<f64>,
+    pub smoothing: Option<f64>,
+    /// Seconds
+    #[serde(default, with = "duration_secs::option")]
+    pub max_gap: Option<Duration>,
+}
+
+/// Geofence overrides
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GeofenceSettings {
+    /// Replaces the configured fences
+    pub fences: Option<Vec<Geofence>>,
+    pub action: Option<FenceAction>,
+    /// Meters
+    pub margin: Option<f64>,
+}
+
+/// Instance identity and leader election overrides
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LeaderSettings {
+    pub enabled: Option<bool>,
+    pub instance_id: Option<String>,
+    /// Seconds
+    #[serde(default, with = "duration_secs::option")]
+    pub lease: Option<Duration>,
+    /// Seconds
+    #[serde(default, with = "duration_secs::option")]
+    pub renew_interval: Option<Duration>,
+}
+
+/// Delta telemetry overrides
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeltaSettings {
+    /// Turns deltas on, with a full state every this many reports
+    pub keyframe_interval: Option<u32>,
+    /// Seconds
+    #[serde(default, with = "duration_secs::option")]
+    pub keyframe_retry: Option<Duration>,
+}
+
+/// Pipeline overrides
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineSettings {
+    pub tolerance: Option<f64>,
+    pub unknown_sections: Option<UnknownSectionPolicy>,
+    /// Replaces the demo pipeline when not empty
+    #[serde(default)]
+    pub sections: Vec<PipelineSection>,
+}
+
+/// Simulated environment overrides
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnvironmentSettings {
+    /// Seconds
+    #[serde(default, with = "duration_secs::option")]
+    pub interval: Option<Duration>,
+    /// ppm per second
+    pub h2_ramp_rate: Option<f64>,
+    /// Bar per second
+    pub pressure_ramp_rate: Option<f64>,
+    pub neighbor_share: Option<f64>,
+    pub seed: Option<u64>,
+}
+
+/// Simulated publish timing overrides (seconds)
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TimingSettings {
+    #[serde(default, with = "duration_secs::option")]
+    pub telemetry_interval: Option<Duration>,
+    #[serde(default, with = "duration_secs::option")]
+    pub heartbeat_interval: Option<Duration>,
+    pub jitter_fraction: Option<f64>,
+    pub seed: Option<u64>,
+    pub batch_telemetry: Option<bool>,
+}
+
+impl ConfigFile {
+    /// Read a `.toml` file, or JSON for any other extension
+    pub fn load(path: &Path) -> Result<Self, ConfigFileError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigFileError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        if path.extension().is_some_and(|ext| ext == "toml") {
+            toml::from_str(&text).map_err(|source| ConfigFileError::Toml {
+                path: path.to_path_buf(),
+                source,
+            })
+        } else {
+            serde_json::from_str(&text).map_err(|source| ConfigFileError::Json {
+                path: path.to_path_buf(),
+                source,
+            })
+        }
+    }
+
+    /// Override `config` with every setting present in the file
+    pub fn apply(self, config: &mut EngineConfig) {
+        let Self {
+            mqtt,
+            message_channel,
+            heartbeat_timeout,
+            clock,
+            link_quality,
+            simulation,
+            dispatch,
+            authorization,
+            source_signing,
+            command_expiry,
+            broadcast,
+            metrics,
+            event_log,
+            dead_letters,
+            route_requests,
+            robot_configs,
+            anomaly_store,
+            telemetry_store,
+            thresholds,
+            battery_manager,
+            geofences,
+            leader,
+            delta,
+            pipeline,
+            environment,
+            #[cfg(feature = "http")]
+            http,
+            robots,
+        } = self;
+        let broker = &mut config.mqtt;
+        if let Some(host) = mqtt.broker_host {
+            broker.broker_host = host;
+        }
+        if let Some(port) = mqtt.broker_port {
+            broker.broker_port = port;
+        }
+        if let Some(client_id) = mqtt.client_id {
+            broker.client_id = client_id;
+        }
+        if let Some(keep_alive) = mqtt.keep_alive_secs {
+            broker.keep_alive_secs = keep_alive;
+        }
+        broker.username = mqtt.username.or(broker.username.take());
+        if let Some(password) = mqtt.password {
+            broker.password = Some(Secret::new(password));
+        }
+        if let Some(tls) = mqtt.tls {
+            broker.tls = Some(tls.into());
+        }
+        if let Some(encoding) = mqtt.encoding {
+            broker.encoding = encoding;
+        }
+        if let Some(negotiate) = mqtt.negotiate_encoding {
+            broker.negotiate_encoding = negotiate;
+        }
+        if let Some(legacy) = mqtt.legacy_alerts {
+            broker.alert_topics.legacy = legacy;
+        }
+        broker.alert_topics.min_severity =
+            mqtt.min_alert_severity.or(broker.alert_topics.min_severity);
+        if !mqtt.backup_brokers.is_empty() {
+            broker.backup_brokers = mqtt
+                .backup_brokers
+                .into_iter()
+                .map(|backup| BrokerEndpoint {
+                    username: backup.username,
+                    password: backup.password.map(Secret::new),
+                    tls: backup.tls.map(TlsConfig::from),
+                    ..BrokerEndpoint::new(backup.host, backup.port)
+                })
+                .collect();
+        }
+        if let Some(max_failures) = mqtt.failover_after {
+            broker.failover.max_failures = max_failures;
+        }
+        if let Some(interval) = mqtt.failback_interval {
+            broker.failover.failback_interval = interval;
+        }
+        let offline = &mut broker.offline_buffer;
+        if let Some(alerts) = mqtt.offline_buffer.alerts {
+            offline.alerts = alerts;
+        }
+        if let Some(telemetry) = mqtt.offline_buffer.telemetry {
+            offline.telemetry = telemetry;
+        }
+        if let Some(other) = mqtt.offline_buffer.other {
+            offline.other = other;
+        }
+        if let Some(capacity) = message_channel.capacity {
+            config.message_channel.capacity = capacity;
+        }
+        if let Some(timeout) = heartbeat_timeout {
+            config.heartbeat_timeout = timeout;
+        }
+        if let Some(scale) = clock.time_scale {
+            config.clock.time_scale = scale;
+        }
+        if let Some(window) = link_quality.window {
+            config.link_quality.window = window;
+        }
+        if let Some(max_skew) = link_quality.max_skew {
+            config.link_quality.max_skew = max_skew;
+        }
+        let timing = &mut config.simulation;
+        if let Some(interval) = simulation.telemetry_interval {
+            timing.telemetry_interval = interval;
+        }
+        if let Some(interval) = simulation.heartbeat_interval {
+            timing.heartbeat_interval = interval;
+        }
+        if let Some(jitter) = simulation.jitter_fraction {
+            timing.jitter_fraction = jitter;
+        }
+        if let Some(seed) = simulation.seed {
+            timing.seed = seed;
+        }
+        if let Some(batch) = simulation.batch_telemetry {
+            timing.batch_telemetry = batch;
+        }
+        if let Some(enabled) = dispatch.enabled {
+            config.dispatch.enabled = enabled;
+        }
+        if let Some(severity) = dispatch.min_severity {
+            config.dispatch.min_severity = severity;
+        }
+        if let Some(battery) = dispatch.min_battery {
+            config.dispatch.min_battery = battery;
+        }
+        if let Some(enabled) = authorization.enabled {
+            config.authorization.enabled = enabled;
+        }
+        config.authorization.policy.extend(authorization.policy);
+        config
+            .authorization
+            .operators
+            .extend(authorization.operators);
+        config.authorization.keys.extend(
+            authorization
+                .keys
+                .into_iter()
+                .map(|(operator, key)| (operator, Secret::new(key))),
+        );
+        config.source_signing.keys.extend(
+            source_signing
+                .keys
+                .into_iter()
+                .map(|(source, key)| (source, Secret::new(key))),
+        );
+        config.command_expiry.ttl.extend(command_expiry.ttl);
+        if let Some(slack) = command_expiry.clock_slack {
+            config.command_expiry.clock_slack = slack;
+        }
+        if let Some(deadline) = broadcast.deadline {
+            config.broadcast.deadline = deadline;
+        }
+        if let Some(port) = metrics.port {
+            config.metrics.listen = Some(SocketAddr::from(([0, 0, 0, 0], port)));
+        }
+        let log = &mut config.event_log;
+        log.directory = event_log.directory.or(log.directory.take());
+        if let Some(bytes) = event_log.rotate_bytes {
+            log.rotate_bytes = bytes;
+        }
+        log.rotate_interval = event_log.rotate_interval.or(log.rotate_interval);
+        if let Some(keep) = event_log.keep_files {
+            log.keep_files = keep;
+        }
+        let quarantine = &mut config.dead_letters;
+        if let Some(retained) = dead_letters.retained {
+            quarantine.retained = retained;
+        }
+        if let Some(burst) = dead_letters.burst {
+            quarantine.burst = burst;
+        }
+        if let Some(window) = dead_letters.window {
+            quarantine.window = window;
+        }
+        if let Some(every) = dead_letters.sample_every {
+            quarantine.sample_every = every;
+        }
+        let requests = &mut config.route_requests;
+        if let Some(burst) = route_requests.burst {
+            requests.burst = burst;
+        }
+        if let Some(total) = route_requests.total {
+            requests.total = total;
+        }
+        if let Some(window) = route_requests.window {
+            requests.window = window;
+        }
+        config.robot_configs.path = robot_configs.path.or(config.robot_configs.path.take());
+        config.anomaly_store.path = anomaly_store.path.or(config.anomaly_store.path.take());
+        if let Some(records) = anomaly_store.compact_after {
+            config.anomaly_store.compact_after = records;
+        }
+        config.telemetry_store.path = telemetry_store.path.or(config.telemetry_store.path.take());
+        if let Some(retention) = telemetry_store.retention {
+            config.telemetry_store.retention = retention;
+        }
+        if let Some(records) = telemetry_store.max_records {
+            config.telemetry_store.max_records = records;
+        }
+        if let Some(rules) = thresholds.rules {
+            config.thresholds.rules = rules;
+        }
+        if let Some(auto_return) = battery_manager.auto_return {
+            config.battery_manager.auto_return = auto_return;
+        }
+        if let Some(stations) = battery_manager.stations {
+            config.battery_manager.stations = stations;
+        }
+        config
+            .battery_manager
+            .profiles
+            .extend(battery_manager.profiles);
+        if let Some(reserve) = battery_manager.reserve {
+            config.battery_manager.reserve = reserve;
+        }
+        if let Some(smoothing) = battery_manager.smoothing {
+            config.battery_manager.smoothing = smoothing;
+        }
+        if let Some(max_gap) = battery_manager.max_gap {
+            config.battery_manager.max_gap = max_gap;
+        }
+        if let Some(fences) = geofences.fences {
+            config.geofences.fences = fences;
+        }
+        if let Some(action) = geofences.action {
+            config.geofences.action = action;
+        }
+        if let Some(margin) = geofences.margin {
+            config.geofences.margin = margin;
+        }
+        if let Some(enabled) = leader.enabled {
+            config.leader.enabled = enabled;
+        }
+        config.leader.instance_id = leader.instance_id.or(config.leader.instance_id.take());
+        if let Some(lease) = leader.lease {
+            config.leader.lease = lease;
+        }
+        if let Some(interval) = leader.renew_interval {
+            config.leader.renew_interval = interval;
+        }
+        config.delta.keyframe_interval = delta.keyframe_interval.or(config.delta.keyframe_interval);
+        if let Some(retry) = delta.keyframe_retry {
+            config.delta.keyframe_retry = retry;
+        }
+        if let Some(tolerance) = pipeline.tolerance {
+            config.pipeline.tolerance = tolerance;
+        }
+        if let Some(policy) = pipeline.unknown_sections {
+            config.pipeline.unknown_sections = policy;
+        }
+        if !pipeline.sections.is_empty() {
+            config.pipeline.sections = pipeline.sections;
+        }
+        let sensors = &mut config.environment;
+        if let Some(interval) = environment.interval {
+            sensors.interval = interval;
+        }
+        if let Some(rate) = environment.h2_ramp_rate {
+            sensors.h2_ramp_rate = rate;
+        }
+        if let Some(rate) = environment.pressure_ramp_rate {
+            sensors.pressure_ramp_rate = rate;
+        }
+        if let Some(share) = environment.neighbor_share {
+            sensors.neighbor_share = share;
+        }
+        if let Some(seed) = environment.seed {
+            sensors.seed = seed;
+        }
+        #[cfg(feature = "http")]
+        {
+            if let Some(port) = http.port {
+                config.http.listen = Some(SocketAddr::from(([0, 0, 0, 0], port)));
+            }
+            if let Some(origins) = http.allowed_origins {
+                config.http.allowed_origins = origins;
+            }
+            if let Some(token) = http.command_token {
+                config.http.command_token = Some(Secret::new(token));
+            }
+            if let Some(path) = http.query_database {
+                config.http.query_database = Some(path);
+            }
+        }
+        if !robots.is_empty() {
+            config.fleet.robots = robots;
+        }
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alarms::AlarmThreshold;
+    use crate::correlation::KnownCause;
+    use crate::detectors::ThresholdMetric;
+    use aetheris_shared::{CurrentTask, Position, RobotType};
+
+    fn paths(config: &EngineConfig) -> Vec<String> {
+        config
+            .validate()
+            .unwrap_err()
+            .issues
+            .into_iter()
+            .map(|issue| issue.path)
+            .collect()
+    }
+
+    #[test]
+    fn test_default_config_is_valid() {
+        let mut out = Vec::new();
+        assert_eq!(run_config_check(&EngineConfig::default(), &mut out), 0);
+        assert_eq!(String::from_utf8(out).unwrap(), "configuration OK\n");
+    }
+
+    #[test]
+    fn test_each_rule_reports_its_path() {
+        type Breakage = fn(&mut EngineConfig);
+        let cases: Vec<(Breakage, &str)> = vec![
+            (|c| c.mqtt.broker_host.clear(), "mqtt.broker_host"),
+            (|c| c.mqtt.broker_port = 0, "mqtt.broker_port"),
+            (
+                |c| c.mqtt.status_interval = Duration::ZERO,
+                "mqtt.status_interval",
+            ),
+            (
+                |c| c.mqtt.reconnect.max_delay = Duration::ZERO,
+                "mqtt.reconnect.max_delay",
+            ),
+            (
+                |c| c.mqtt.failover.max_failures = 0,
+                "mqtt.failover.max_failures",
+            ),
+            (
+                |c| c.mqtt.offline_buffer.alerts = 0,
+                "mqtt.offline_buffer.alerts",
+            ),
+            (
+                |c| c.message_channel.capacity = 0,
+                "message_channel.capacity",
+            ),
+            (
+                |c| c.mqtt.backup_brokers = vec![BrokerEndpoint::new("", 1883)],
+                "mqtt.backup_brokers.0.host",
+            ),
+            (
+                |c| c.mqtt.password = Some(crate::transport::Secret::new("pw")),
+                "mqtt.password",
+            ),
+            (
+                |c| c.mqtt.tls = Some(crate::transport::TlsConfig::new("/nonexistent/ca.pem")),
+                "mqtt.tls.ca_path",
+            ),
+            (
+                |c| c.mqtt.parse_limits.max_depth = 0,
+                "mqtt.parse_limits.max_depth",
+            ),
+            (
+                |c| c.heartbeat_timeout = Duration::ZERO,
+                "heartbeat_timeout",
+            ),
+            (|c| c.clock.time_scale = 0.0, "clock.time_scale"),
+            (
+                |c| c.link_quality.min_samples = 0,
+                "link_quality.min_samples",
+            ),
+            (|c| c.flapping.max_reconnects = 0, "flapping.max_reconnects"),
+            (
+                |c| c.simulation.jitter_fraction = -0.1,
+                "simulation.jitter_fraction",
+            ),
+            (
+                |c| c.alarms.pressure.hysteresis = 0.0,
+                "alarms.pressure.hysteresis",
+            ),
+            (
+                |c| c.alarms.clear_hold = Duration::ZERO,
+                "alarms.clear_hold",
+            ),
+            (
+                |c| c.triage.max_nearby_robots = 0,
+                "triage.max_nearby_robots",
+            ),
+            (
+                |c| c.battery_manager.reserve = 100.0,
+                "battery_manager.reserve",
+            ),
+            (
+                |c| {
+                    c.battery_manager.profiles.insert(
+                        RobotType::Crawler,
+                        BatteryProfile {
+                            return_speed: 0.0,
+                            ..RobotType::Crawler.battery_profile()
+                        },
+                    );
+                },
+                "battery_manager.profiles.crawler.return_speed",
+            ),
+            (|c| c.geofences.margin = -1.0, "geofences.margin"),
+            (
+                |c| c.correlation.known_causes[0].variant = "inject_faults",
+                "correlation.known_causes[0].variant",
+            ),
+            (
+                |c| c.recovery.low_battery_clear_level = 5.0,
+                "recovery.low_battery_clear_level",
+            ),
+            (|c| c.battery.charge_per_sec = 0.0, "battery.charge_per_sec"),
+            (
+                |c| c.trends.battery_discharge.medium = 1.0,
+                "trends.battery_discharge.medium",
+            ),
+            (
+                |c| c.source_bindings.rules[0].topics.clear(),
+                "source_bindings.rules[0].topics",
+            ),
+            (
+                |c| c.rollout.ack_timeout = Duration::ZERO,
+                "rollout.ack_timeout",
+            ),
+            (|c| c.alert_dedup.radius = -1.0, "alert_dedup.radius"),
+            (|c| c.world_bounds.max.z = -2_000.0, "world_bounds.z"),
+            (|c| c.timeline.min_movement = 0.0, "timeline.min_movement"),
+            (|c| c.zones.exit_margin = 0.0, "zones.exit_margin"),
+            (|c| c.fanout.concurrency = 0, "fanout.concurrency"),
+            (
+                |c| c.store_forward.retain_after = Some(Duration::from_secs(600)),
+                "store_forward.retain_after",
+            ),
+            (|c| c.sensor_health.window = 3, "sensor_health.window"),
+            (
+                |c| c.position_filter.drone.beta = 3.0,
+                "position_filter.drone.beta",
+            ),
+            (
+                |c| c.expected_fleet.utc_offset_minutes = 900,
+                "expected_fleet.utc_offset_minutes",
+            ),
+            (|c| c.acks.timeout = Duration::ZERO, "acks.timeout"),
+            (
+                |c| c.broadcast.deadline = Duration::ZERO,
+                "broadcast.deadline",
+            ),
+            (
+                |c| {
+                    let spec = RobotSpec {
+                        id: "RV-001".parse().unwrap(),
+                        name: "Rover".into(),
+                        robot_type: RobotType::Rover,
+                        position: Position::origin(),
+                        battery: 80.0,
+                        route: None,
+                    };
+                    c.fleet.robots = vec![spec.clone(), spec];
+                },
+                "fleet.robots[1].id",
+            ),
+            (|c| c.sequence.large_gap = 0, "sequence.large_gap"),
+            (
+                |c| c.robot_configs.path = Some(PathBuf::new()),
+                "robot_configs.path",
+            ),
+            (
+                |c| c.anomaly_store.compact_after = 0,
+                "anomaly_store.compact_after",
+            ),
+            (
+                |c| c.telemetry_store.retention = Duration::ZERO,
+                "telemetry_store.retention",
+            ),
+            (
+                |c| c.leader.renew_interval = c.leader.lease,
+                "leader.renew_interval",
+            ),
+            (
+                |c| c.leader.instance_id = Some("engine/a".into()),
+                "leader.instance_id",
+            ),
+            (
+                |c| c.metrics.listen = Some(SocketAddr::from(([0, 0, 0, 0], 0))),
+                "metrics.listen",
+            ),
+            (|c| c.event_log.keep_files = 0, "event_log.keep_files"),
+            (
+                |c| c.dead_letters.sample_every = 0,
+                "dead_letters.sample_every",
+            ),
+            (|c| c.route_requests.total = 1, "route_requests.total"),
+            (|c| c.command_queue.max_depth = 0, "command_queue.max_depth"),
+            (
+                |c| c.wall_thickness.min_samples = 2,
+                "wall_thickness.min_samples",
+            ),
+            (
+                |c| {
+                    c.thresholds.rules.push(ThresholdRule {
+                        metric: ThresholdMetric::Pressure,
+                        above: None,
+                        below: None,
+                        severity: SeverityLevel::High,
+                        anomaly_type: None,
+                        sections: Vec::new(),
+                    })
+                },
+                "thresholds.rules[0].above",
+            ),
+            (|c| c.dispatch.min_battery = 120.0, "dispatch.min_battery"),
+            (
+                |c| {
+                    c.authorization
+                        .policy
+                        .entry(OperatorRole::Viewer)
+                        .or_default()
+                        .insert("launch".into());
+                },
+                "authorization.policy.viewer",
+            ),
+            (
+                |c| {
+                    c.escalation
+                        .after
+                        .insert(SeverityLevel::Low, Duration::ZERO);
+                },
+                "escalation.after",
+            ),
+            (
+                |c| c.section_health.warning_band = 1.5,
+                "section_health.warning_band",
+            ),
+            (
+                |c| c.delta.keyframe_interval = Some(0),
+                "delta.keyframe_interval",
+            ),
+            (
+                |c| {
+                    let mut section = crate::create_mock_pipeline().remove(0);
+                    section.neighbors.push("PIPE-404".into());
+                    c.pipeline.sections = vec![section];
+                },
+                "pipeline.sections[0].neighbors[0]",
+            ),
+            (
+                |c| c.environment.h2_ramp_rate = 0.0,
+                "environment.h2_ramp_rate",
+            ),
+            #[cfg(feature = "http")]
+            (
+                |c| c.http.allowed_origins = vec!["dashboard.plant.local".into()],
+                "http.allowed_origins[0]",
+            ),
+            #[cfg(feature = "http")]
+            (
+                |c| c.http.query_database = Some(PathBuf::new()),
+                "http.query_database",
+            ),
+        ];
+
+        for (break_config, expected) in cases {
+            let mut config = EngineConfig::default();
+            break_config(&mut config);
+            assert_eq!(paths(&config), [expected]);
+        }
+    }
+
+    #[test]
+    fn test_all_violations_are_collected_together() {
+        let mut config = EngineConfig::default();
+        config.mqtt.broker_port = 0;
+        config.alarms.h2_concentration = AlarmThreshold {
+            raise_above: 4000.0,
+            hysteresis: 5000.0,
+        };
+        config.triage.enabled = true;
+        config.triage.timeout = Duration::ZERO;
+        config.correlation.known_causes.push(KnownCause {
+            variant: "teleport",
+            anomaly_types: Vec::new(),
+            max_severity: SeverityLevel::Info,
+        });
+        config.simulation.heartbeat_interval = Duration::from_secs(10);
+
+        let report = config.validate().unwrap_err();
+        let found: Vec<&str> = report.issues.iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(
+            found,
+            [
+                "mqtt.broker_port",
+                "alarms.h2_concentration.hysteresis",
+                "triage.timeout",
+                "correlation.known_causes[3].variant",
+                "simulation.heartbeat_interval",
+            ]
+        );
+        assert!(report.issues[3].suggestion.is_some());
+
+        let mut out = Vec::new();
+        assert_eq!(run_config_check(&config, &mut out), EXIT_INVALID_CONFIG);
+        let printed = String::from_utf8(out).unwrap();
+        assert!(printed.starts_with("configuration has 5 problems:"));
+        assert_eq!(printed.matches("\n  - ").count(), 5);
+    }
+
+    fn write_temp(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("aetheris-{}-{name}", std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_broker_tls_from_the_file() {
+        let ca = write_temp("ca.pem", "");
+        let cert = write_temp("engine.pem", "");
+        let key = write_temp("engine.key", "");
+        let path = write_temp(
+            "tls.toml",
+            &format!(
+                r#"
+                    [mqtt]
+                    backup_brokers = [
+                        {{ host = "broker-b.plant.local", port = 8883 }},
+                        {{ host = "broker-c.plant.local", port = 8883, tls = {{ insecure_skip_verify = true }} }},
+                    ]
+
+                    [mqtt.tls]
+                    ca_path = {ca:?}
+                    client_cert_path = {cert:?}
+                    client_key_path = {key:?}
+                    alpn = ["mqtt"]
+                "#
+            ),
+        );
+        let mut config = EngineConfig::default();
+        ConfigFile::load(&path).unwrap().apply(&mut config);
+        assert_eq!(config.validate(), Ok(()));
+        for file in [path, ca.clone(), cert, key.clone()] {
+            std::fs::remove_file(file).unwrap();
+        }
+
+        let tls = config.mqtt.tls.as_ref().unwrap();
+        assert_eq!(tls.ca_path, ca);
+        assert_eq!(tls.client_key_path, Some(key));
+        assert_eq!(tls.alpn, ["mqtt"]);
+        let endpoints = config.mqtt.endpoints();
+        // Backups without their own TLS use the primary's
+        assert_eq!(endpoints[1].tls.as_ref(), Some(tls));
+        let own = endpoints[2].tls.as_ref().unwrap();
+        assert!(own.insecure_skip_verify && own.alpn.is_empty());
+    }
+
+    #[test]
+    fn test_toml_file_overrides_defaults_and_fleet() {
+        let path = write_temp(
+            "engine.toml",
+            r#"
+                heartbeat_timeout = 30
+
+                [mqtt]
+                broker_host = "broker.plant.local"
+                broker_port = 8883
+                min_alert_severity = "high"
+                encoding = "msgpack"
+                failover_after = 5
+                failback_interval = 30
+                backup_brokers = [
+                    { host = "broker-b.plant.local", port = 8883 },
+                    { host = "10.0.4.2", port = 1883, username = "field", password = "field-pass" },
+                ]
+
+                [mqtt.offline_buffer]
+                telemetry = 20
+
+                [message_channel]
+                capacity = 1000
+
+                [clock]
+                time_scale = 60
+
+                [link_quality]
+                window = 30
+                max_skew = 2.5
+
+                [simulation]
+                telemetry_interval = 0.5
+
+                [dispatch]
+                enabled = false
+                min_severity = "critical"
+
+                [authorization]
+                enabled = true
+                policy = { operator = ["move_to", "return_to_base"] }
+                operators = { ops-alice = "admin", ops-bob = "operator" }
+                keys = { ops-alice = "alice-key" }
+
+                [source_signing]
+                keys = { RV-001 = "rv-001-key" }
+
+                [command_expiry]
+                ttl = { move_to = 10, request_keyframe = 2.5 }
+
+                [broadcast]
+                deadline = 3
+
+                [metrics]
+                port = 9464
+
+                [robot_configs]
+                path = "/var/lib/aetheris/robots.json"
+
+                [anomaly_store]
+                path = "/var/lib/aetheris/anomalies.jsonl"
+
+                [telemetry_store]
+                retention = 7200
+                max_records = 50000
+
+                [[thresholds.rules]]
+                metric = "h2_concentration"
+                above = 5000.0
+                severity = "critical"
+
+                [[thresholds.rules]]
+                metric = "wall_loss_rate"
+                above = 0.5
+                severity = "medium"
+                sections = ["PIPE-003"]
+
+                [battery_manager]
+                reserve = 8.0
+
+                [[battery_manager.stations]]
+                id = "DOCK-A"
+                position = { x = 40.0, y = 0.0, z = 0.0 }
+                capacity = 3
+
+                [battery_manager.profiles.drone]
+                drain_per_meter = 0.03
+                idle_drain_per_sec = 0.08
+                return_speed = 6.0
+
+                [geofences]
+                action = "clamp"
+
+                [[geofences.fences]]
+                id = "FENCE-COMPRESSOR"
+                polygon = [
+                    { x = 40.0, y = 0.0, z = -10.0 },
+                    { x = 60.0, y = 0.0, z = -10.0 },
+                    { x = 60.0, y = 0.0, z = 10.0 },
+                ]
+                altitude = { min = 0.0, max = 30.0 }
+                robot_types = ["drone"]
+
+                [leader]
+                enabled = true
+                instance_id = "engine-north"
+                lease = 6
+
+                [dead_letters]
+                burst = 5
+                sample_every = 20
+
+                [route_requests]
+                burst = 2
+                window = 30
+
+                [delta]
+                keyframe_interval = 10
+
+                [pipeline]
+                unknown_sections = "provisional"
+
+                [[pipeline.sections]]
+                id = "MAIN-1"
+                start = { x = 0.0, y = 0.0, z = 0.0 }
+                end = { x = 100.0, y = 0.0, z = 0.0 }
+                diameter_mm = 610.0
+                material = "carbon_steel"
+                design_pressure_bar = 80.0
+
+                [[pipeline.sections]]
+                id = "MAIN-2"
+                start = { x = 100.0, y = 0.0, z = 0.0 }
+                end = { x = 200.0, y = 0.0, z = 0.0 }
+                diameter_mm = 610.0
+                material = "carbon_steel"
+                design_pressure_bar = 80.0
+                neighbors = ["MAIN-1"]
+
+                [environment]
+                h2_ramp_rate = 2000.0
+
+                [[robots]]
+                id = "RV-101"
+                name = "Rover One"
+                type = "rover"
+                position = { x = 5.0, y = 0.0, z = -2.0 }
+                battery = 64.0
+                route = "ROUTE-A1"
+
+                [[robots]]
+                id = "DR-101"
+                name = "Drone One"
+                type = "drone"
+            "#,
+        );
+        let mut config = EngineConfig::default();
+        ConfigFile::load(&path).unwrap().apply(&mut config);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(config.mqtt.broker_host, "broker.plant.local");
+        assert_eq!(config.mqtt.broker_port, 8883);
+        assert_eq!(config.mqtt.encoding, Encoding::MessagePack);
+        assert!(config.mqtt.negotiate_encoding);
+        assert_eq!(
+            config.mqtt.alert_topics.subscriptions(),
+            ["aetheris/alerts/high", "aetheris/alerts/critical"]
+        );
+        assert!(config.mqtt.alert_topics.legacy);
+        let endpoints = config.mqtt.endpoints();
+        let addresses: Vec<_> = endpoints.iter().map(|e| e.address()).collect();
+        assert_eq!(
+            addresses,
+            [
+                "broker.plant.local:8883",
+                "broker-b.plant.local:8883",
+                "10.0.4.2:1883"
+            ]
+        );
+        assert_eq!(endpoints[2].username.as_deref(), Some("field"));
+        assert_eq!(config.mqtt.failover.max_failures, 5);
+        assert_eq!(config.mqtt.offline_buffer.telemetry, 20);
+        assert_eq!(config.mqtt.offline_buffer.alerts, 10_000);
+        assert_eq!(config.message_channel.capacity, 1000);
+        assert_eq!(
+            config.mqtt.failover.failback_interval,
+            Duration::from_secs(30)
+        );
+        assert_eq!(config.heartbeat_timeout, Duration::from_secs(30));
+        assert_eq!(config.clock.time_scale, 60.0);
+        assert_eq!(config.link_quality.window, 30);
+        assert_eq!(config.link_quality.max_skew, Duration::from_millis(2500));
+        assert_eq!(
+            config.simulation.telemetry_interval,
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            config.metrics.listen,
+            Some(SocketAddr::from(([0, 0, 0, 0], 9464)))
+        );
+        assert_eq!(config.dead_letters.burst, 5);
+        assert_eq!(config.dead_letters.sample_every, 20);
+        assert_eq!(config.route_requests.burst, 2);
+        assert_eq!(config.route_requests.total, 50);
+        assert_eq!(config.route_requests.window, Duration::from_secs(30));
+        assert_eq!(
+            config.robot_configs.path,
+            Some(PathBuf::from("/var/lib/aetheris/robots.json"))
+        );
+        assert_eq!(
+            config.anomaly_store.path,
+            Some(PathBuf::from("/var/lib/aetheris/anomalies.jsonl"))
+        );
+        assert_eq!(config.anomaly_store.compact_after, 1000);
+        assert_eq!(config.telemetry_store.retention, Duration::from_secs(7200));
+        assert_eq!(config.telemetry_store.max_records, 50_000);
+        let rules: Vec<_> = config
+            .thresholds
+            .rules
+            .iter()
+            .map(|rule| (rule.metric, rule.above, rule.severity))
+            .collect();
+        assert_eq!(
+            rules,
+            [
+                (
+                    ThresholdMetric::H2Concentration,
+                    Some(5000.0),
+                    SeverityLevel::Critical
+                ),
+                (
+                    ThresholdMetric::WallLossRate,
+                    Some(0.5),
+                    SeverityLevel::Medium
+                ),
+            ]
+        );
+        assert_eq!(config.thresholds.rules[1].sections, ["PIPE-003"]);
+        assert_eq!(config.battery_manager.reserve, 8.0);
+        assert!(config.battery_manager.auto_return);
+        assert_eq!(
+            config.battery_manager.stations(),
+            [ChargingStation::new(
+                "DOCK-A",
+                Position::new(40.0, 0.0, 0.0),
+                3
+            )]
+        );
+        assert_eq!(
+            config
+                .battery_manager
+                .profile(RobotType::Drone)
+                .return_speed,
+            6.0
+        );
+        assert_eq!(
+            config.battery_manager.profile(RobotType::Rover),
+            RobotType::Rover.battery_profile()
+        );
+        assert_eq!(config.geofences.action, FenceAction::Clamp);
+        assert_eq!(config.geofences.margin, 5.0);
+        assert_eq!(config.geofences.fences[0].id, "FENCE-COMPRESSOR");
+        assert_eq!(config.geofences.fences[0].polygon.len(), 3);
+        assert!(config.geofences.fences[0].applies_to(RobotType::Drone));
+        assert!(!config.geofences.fences[0].applies_to(RobotType::Rover));
+        assert!(config.leader.enabled);
+        assert_eq!(config.leader.instance_id.as_deref(), Some("engine-north"));
+        assert_eq!(config.leader.lease, Duration::from_secs(6));
+        assert_eq!(config.leader.renew_interval, Duration::from_secs(3));
+        assert!(!config.dispatch.enabled);
+        assert_eq!(config.dispatch.min_severity, SeverityLevel::Critical);
+        assert_eq!(config.dispatch.min_battery, 30.0);
+        let authorization = &config.authorization;
+        assert!(authorization.enabled);
+        assert_eq!(authorization.policy[&OperatorRole::Operator].len(), 2);
+        assert!(authorization.policy[&OperatorRole::Admin].contains("emergency_stop"));
+        assert_eq!(authorization.operators["ops-bob"], OperatorRole::Operator);
+        assert_eq!(authorization.keys["ops-alice"].expose(), "alice-key");
+        assert_eq!(config.source_signing.keys["RV-001"].expose(), "rv-001-key");
+        let expiry = &config.command_expiry;
+        assert_eq!(expiry.ttl["move_to"], Duration::from_secs(10));
+        assert_eq!(expiry.ttl["request_keyframe"], Duration::from_millis(2500));
+        assert_eq!(expiry.ttl["configure"], Duration::from_secs(300));
+        assert_eq!(expiry.clock_slack, Duration::from_secs(5));
+        assert_eq!(config.broadcast.deadline, Duration::from_secs(3));
+        assert_eq!(config.delta.keyframe_interval, Some(10));
+        assert_eq!(
+            config.pipeline.unknown_sections,
+            UnknownSectionPolicy::Provisional
+        );
+        assert_eq!(config.environment.h2_ramp_rate, 2000.0);
+        let map = config.pipeline.map();
+        assert_eq!(map.neighbors("MAIN-1")[0].id, "MAIN-2");
+        // Left out of the file
+        assert_eq!(config.simulation.heartbeat_interval, Duration::from_secs(5));
+        assert_eq!(config.validate(), Ok(()));
+
+        let states = config.fleet.states();
+        assert_eq!(states.len(), 2);
+        assert_eq!(states[0].battery, 64.0);
+        assert_eq!(
+            states[0].current_task,
+            CurrentTask::Patrolling {
+                route_id: "ROUTE-A1".into()
+            }
+        );
+        assert_eq!(states[1].battery, 100.0);
+        assert_eq!(EngineConfig::default().fleet.states().len(), 5);
+    }
+
+    #[test]
+    fn test_bad_fleet_files_fail_with_their_location() {
+        let path = write_temp(
+            "fleet.json",
+            r#"{"robots": [{"id": "BT-001", "name": "Boat", "type": "boat"}]}"#,
+        );
+        let error = ConfigFile::load(&path).unwrap_err().to_string();
+        std::fs::remove_file(&path).unwrap();
+        assert!(error.contains("unknown variant `boat`"), "{error}");
+
+        let path = write_temp("typo.toml", "[mqtt]\nbroker = \"localhost\"\n");
+        let error = ConfigFile::load(&path).unwrap_err().to_string();
+        std::fs::remove_file(&path).unwrap();
+        assert!(error.contains("unknown field `broker`"), "{error}");
+
+        let file: ConfigFile = toml::from_str(
+            r#"
+                [[robots]]
+                id = "CR-001"
+                name = "Crawler"
+                type = "crawler"
+                battery = 140.0
+                route = "ROUTE-Z9"
+                position = { x = 0.0, y = 0.0, z = 5000.0 }
+            "#,
+        )
+        .unwrap();
+        let mut config = EngineConfig::default();
+        file.apply(&mut config);
+        assert_eq!(
+            paths(&config),
+            [
+                "fleet.robots[0].battery",
+                "fleet.robots[0].route",
+                "fleet.robots[0].position"
+            ]
+        );
+    }
+}
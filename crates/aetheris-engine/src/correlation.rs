@@ -0,0 +1,413 @@
+//! Correlation of alerts with our own recent commands
+//!
+//! Every command seen on the command topics is recorded in a bounded
+//! [`CommandAudit`]. When an alert arrives, commands that targeted the same
+//! robot or section within the look-back window are attached to the report.
+//! If one of them is a known cause of that kind of alert (injecting a fault
+//! produces comm/battery alerts, a routine scan produces Info reports), the
+//! report's notification urgency is reduced. Severity is never touched.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use aetheris_shared::{
+    AnomalyReport, AnomalyType, Command, CorrelatedCommand, NotificationUrgency, SeverityLevel,
+};
+
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// COMMAND AUDIT
+// ============================================================================
+
+/// A command observed on the command topics
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandAuditEntry {
+    pub command_id: String,
+    /// Command variant name (see [`Command::name`])
+    pub variant: &'static str,
+    /// Target robot, or `None` for broadcasts
+    pub robot_id: Option<String>,
+    /// Section the command refers to, if any
+    pub section_id: Option<String>,
+    /// Anomaly the command refers to, if any
+    pub anomaly_id: Option<String>,
+    /// Operator, or envelope source for commands that name none
+    pub issued_by: String,
+    /// Unix timestamp the command was issued (milliseconds)
+    pub timestamp: u64,
+    /// Why the command was refused, if it was
+    pub rejected: Option<String>,
+}
+
+impl CommandAuditEntry {
+    pub fn new(
+        command: &Command,
+        robot_id: Option<&str>,
+        issued_by: impl Into<String>,
+        timestamp: u64,
+    ) -> Self {
+        let section_id = match command {
+            Command::RegisterSection { section_id, .. }
+            | Command::InjectLeak { section_id, .. }
+            | Command::ClearLeak {
+                section_id: Some(section_id),
+            } => Some(section_id.clone()),
+            _ => None,
+        };
+        let anomaly_id = match command {
+            Command::Investigate { anomaly_id } => Some(anomaly_id.clone()),
+            _ => None,
+        };
+        Self {
+            command_id: format!("CMD-{}", uuid::Uuid::new_v4().simple()),
+            variant: command.name(),
+            robot_id: robot_id.map(String::from),
+            section_id,
+            anomaly_id,
+            issued_by: issued_by.into(),
+            timestamp,
+            rejected: None,
+        }
+    }
+
+    /// Whether the command targeted the robot or section of `report`
+    fn targets(&self, report: &AnomalyReport) -> bool {
+        let robot_match = match &self.robot_id {
+            Some(robot_id) => *robot_id == report.detected_by,
+            None => true,
+        };
+        robot_match || self.section_id.as_deref() == Some(report.section_id.as_str())
+    }
+}
+
+/// Bounded, time-ordered log of recent commands
+#[derive(Debug)]
+pub struct CommandAudit {
+    entries: VecDeque<CommandAuditEntry>,
+    capacity: usize,
+}
+
+impl Default for CommandAudit {
+    fn default() -> Self {
+        Self::new(1000)
+    }
+}
+
+impl CommandAudit {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    pub fn record(&mut self, entry: CommandAuditEntry) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &CommandAuditEntry> {
+        self.entries.iter()
+    }
+}
+
+// ============================================================================
+// CORRELATION
+// ============================================================================
+
+/// A command variant known to cause certain alerts
+#[derive(Debug, Clone, PartialEq)]
+pub struct KnownCause {
+    /// Command variant name (e.g., "inject_fault")
+    pub variant: &'static str,
+    /// Anomaly types the command causes; empty matches any type
+    pub anomaly_types: Vec<AnomalyType>,
+    /// Highest severity the command plausibly causes
+    pub max_severity: SeverityLevel,
+}
+
+impl KnownCause {
+    fn explains(&self, variant: &str, report: &AnomalyReport) -> bool {
+        self.variant == variant
+            && (self.anomaly_types.is_empty() || self.anomaly_types.contains(&report.anomaly_type))
+            && report.severity <= self.max_severity
+    }
+}
+
+/// Correlation behavior
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorrelationConfig {
+    /// How far before an alert to look for commands
+    pub lookback: Duration,
+    /// Command/alert pairs that reduce notification urgency
+    pub known_causes: Vec<KnownCause>,
+}
+
+impl Default for CorrelationConfig {
+    fn default() -> Self {
+        Self {
+            lookback: Duration::from_secs(60),
+            known_causes: vec![
+                // Fault injection raises comm-loss and battery alerts
+                KnownCause {
+                    variant: "inject_fault",
+                    anomaly_types: vec![AnomalyType::Unknown],
+                    max_severity: SeverityLevel::Critical,
+                },
+                // A simulated leak raises the section's leak alarm
+                KnownCause {
+                    variant: "inject_leak",
+                    anomaly_types: vec![AnomalyType::Leak],
+                    max_severity: SeverityLevel::Critical,
+                },
+                // A routine scan reports its own completion
+                KnownCause {
+                    variant: "perform_scan",
+                    anomaly_types: Vec::new(),
+                    max_severity: SeverityLevel::Info,
+                },
+            ],
+        }
+    }
+}
+
+impl CheckConfig for CorrelationConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        checker.positive("lookback", self.lookback);
+        for (i, cause) in self.known_causes.iter().enumerate() {
+            if !Command::NAMES.contains(&cause.variant) {
+                checker.error(
+                    &format!("known_causes[{i}].variant"),
+                    format!("unknown command \"{}\"", cause.variant),
+                    Some(format!("expected one of: {}", Command::NAMES.join(", "))),
+                );
+            }
+        }
+    }
+}
+
+/// Attaches recent commands to incoming alerts
+#[derive(Debug, Default)]
+pub struct AlertCorrelator {
+    config: CorrelationConfig,
+    audit: CommandAudit,
+}
+
+impl AlertCorrelator {
+    pub fn new(config: CorrelationConfig) -> Self {
+        Self {
+            config,
+            audit: CommandAudit::default(),
+        }
+    }
+
+    pub fn config(&self) -> &CorrelationConfig {
+        &self.config
+    }
+
+    pub fn audit(&self) -> &CommandAudit {
+        &self.audit
+    }
+
+    pub fn record_command(&mut self, entry: CommandAuditEntry) {
+        self.audit.record(entry);
+    }
+
+    /// Attach correlated commands to `report`, most recent first, and reduce
+    /// its urgency if one of them is a known cause
+    pub fn correlate(&self, report: &mut AnomalyReport) {
+        let lookback_ms = self.config.lookback.as_millis() as u64;
+        let reported_at = report.timestamp.as_millis();
+        let mut correlated: Vec<(&CommandAuditEntry, u64)> = self
+            .audit
+            .entries()
+            .filter(|entry| entry.rejected.is_none() && entry.timestamp <= reported_at)
+            .map(|entry| (entry, reported_at - entry.timestamp))
+            .filter(|(entry, before_ms)| *before_ms <= lookback_ms && entry.targets(report))
+            .collect();
+        correlated.sort_by_key(|(_, before_ms)| *before_ms);
+
+        let known_cause = correlated.iter().any(|(entry, _)| {
+            self.config
+                .known_causes
+                .iter()
+                .any(|cause| cause.explains(entry.variant, report))
+        });
+        if known_cause {
+            report.urgency = NotificationUrgency::Reduced;
+        }
+
+        report.correlated_commands = correlated
+            .into_iter()
+            .map(|(entry, before_ms)| CorrelatedCommand {
+                command_id: entry.command_id.clone(),
+                variant: entry.variant.into(),
+                issued_by: entry.issued_by.clone(),
+                seconds_before: before_ms as f64 / 1000.0,
+            })
+            .collect();
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetheris_shared::{FaultType, Position, ScanType, Timestamp};
+
+    const T0: u64 = 1_000_000;
+
+    fn alert(anomaly_type: AnomalyType, severity: SeverityLevel, robot: &str) -> AnomalyReport {
+        AnomalyReport {
+            timestamp: Timestamp::from_millis(T0),
+            ..AnomalyReport::new(
+                anomaly_type,
+                severity,
+                Position::origin(),
+                "PIPE-001",
+                robot,
+                0.9,
+                "test",
+            )
+        }
+    }
+
+    fn command(correlator: &mut AlertCorrelator, command: Command, robot: &str, at: u64) {
+        correlator.record_command(CommandAuditEntry::new(
+            &command,
+            Some(robot),
+            "dashboard",
+            at,
+        ));
+    }
+
+    fn inject(fault_type: FaultType) -> Command {
+        Command::InjectFault { fault_type }
+    }
+
+    #[test]
+    fn test_commands_to_same_robot_are_attached_newest_first() {
+        let mut correlator = AlertCorrelator::default();
+        command(&mut correlator, Command::Stop, "RV-001", T0 - 30_000);
+        command(
+            &mut correlator,
+            inject(FaultType::CommDropout),
+            "RV-001",
+            T0 - 2_500,
+        );
+        command(&mut correlator, Command::Stop, "RV-002", T0 - 1_000);
+        command(&mut correlator, Command::Stop, "RV-001", T0 + 1_000);
+
+        let mut report = alert(AnomalyType::Unknown, SeverityLevel::Critical, "RV-001");
+        correlator.correlate(&mut report);
+
+        let attached: Vec<_> = report
+            .correlated_commands
+            .iter()
+            .map(|c| (c.variant.as_str(), c.seconds_before))
+            .collect();
+        assert_eq!(attached, [("inject_fault", 2.5), ("stop", 30.0)]);
+        assert_eq!(report.correlated_commands[0].issued_by, "dashboard");
+        // Known cause: urgency drops, severity does not
+        assert_eq!(report.urgency, NotificationUrgency::Reduced);
+        assert_eq!(report.severity, SeverityLevel::Critical);
+    }
+
+    #[test]
+    fn test_lookback_window_boundary_is_inclusive() {
+        let mut correlator = AlertCorrelator::default();
+        command(&mut correlator, Command::Stop, "RV-001", T0 - 60_000);
+        command(
+            &mut correlator,
+            Command::ReturnToBase,
+            "RV-001",
+            T0 - 60_001,
+        );
+
+        let mut report = alert(AnomalyType::Leak, SeverityLevel::High, "RV-001");
+        correlator.correlate(&mut report);
+        assert_eq!(report.correlated_commands.len(), 1);
+        assert_eq!(report.correlated_commands[0].variant, "stop");
+        assert_eq!(report.urgency, NotificationUrgency::Normal);
+    }
+
+    #[test]
+    fn test_known_cause_table_controls_urgency() {
+        let mut correlator = AlertCorrelator::default();
+        let scan = Command::PerformScan {
+            scan_type: ScanType::Visual,
+        };
+        command(&mut correlator, scan, "CR-001", T0 - 5_000);
+
+        // A scan explains its own Info report...
+        let mut info = alert(AnomalyType::Unknown, SeverityLevel::Info, "CR-001");
+        correlator.correlate(&mut info);
+        assert_eq!(info.urgency, NotificationUrgency::Reduced);
+
+        // ...but not a real finding it made
+        let mut finding = alert(AnomalyType::WallThinning, SeverityLevel::High, "CR-001");
+        correlator.correlate(&mut finding);
+        assert_eq!(finding.correlated_commands.len(), 1);
+        assert_eq!(finding.urgency, NotificationUrgency::Normal);
+
+        // Without the pair in the table nothing is downgraded
+        let mut strict = AlertCorrelator::new(CorrelationConfig {
+            known_causes: Vec::new(),
+            ..CorrelationConfig::default()
+        });
+        command(
+            &mut strict,
+            inject(FaultType::LowBattery),
+            "RV-001",
+            T0 - 1_000,
+        );
+        let mut battery = alert(AnomalyType::Unknown, SeverityLevel::Medium, "RV-001");
+        strict.correlate(&mut battery);
+        assert_eq!(battery.correlated_commands.len(), 1);
+        assert_eq!(battery.urgency, NotificationUrgency::Normal);
+    }
+
+    #[test]
+    fn test_broadcast_and_section_commands_correlate() {
+        let mut correlator = AlertCorrelator::default();
+        correlator.record_command(CommandAuditEntry::new(
+            &Command::EmergencyStop,
+            None,
+            "dashboard",
+            T0 - 1_000,
+        ));
+        correlator.record_command(CommandAuditEntry::new(
+            &Command::RegisterSection {
+                section_id: "PIPE-001".into(),
+                position: Position::origin(),
+                merge_from: None,
+            },
+            Some("engine"),
+            "dashboard",
+            T0 - 2_000,
+        ));
+
+        let mut report = alert(AnomalyType::Leak, SeverityLevel::High, "DR-001");
+        correlator.correlate(&mut report);
+        let variants: Vec<_> = report
+            .correlated_commands
+            .iter()
+            .map(|c| c.variant.as_str())
+            .collect();
+        assert_eq!(variants, ["emergency_stop", "register_section"]);
+    }
+}
@@ -0,0 +1,192 @@
+//! Quarantine of refused messages
+//!
+//! Messages the engine refuses to process (unparseable, invalid, from the
+//! wrong source or badly signed) are published as a
+//! [`DeadLetter`] on `aetheris/deadletter`, and the latest are kept in
+//! memory for the status surface.
+//!
+//! A robot whose firmware emits garbage at 10 Hz must not double the
+//! broker traffic, so dead letters are rate limited per source: the first
+//! `burst` in a window are published, after that one in `sample_every`, and
+//! the rest are only counted. A published letter carries the number
+//! suppressed since the previous one.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use aetheris_shared::DeadLetter;
+
+use crate::config::{CheckConfig, ConfigChecker};
+
+/// Sources tracked before those with an expired window and nothing
+/// suppressed are forgotten
+const MAX_TRACKED_SOURCES: usize = 1024;
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// How many dead letters are kept and published
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeadLetterConfig {
+    /// Latest dead letters kept in memory
+    pub retained: usize,
+    /// Dead letters per source per window published in full
+    pub burst: u32,
+    /// Length of a rate-limiting window
+    pub window: Duration,
+    /// Beyond the burst, one dead letter in this many is published
+    pub sample_every: u32,
+}
+
+impl Default for DeadLetterConfig {
+    fn default() -> Self {
+        Self {
+            retained: 100,
+            burst: 10,
+            window: Duration::from_secs(60),
+            sample_every: 10,
+        }
+    }
+}
+
+impl CheckConfig for DeadLetterConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        checker.positive("window", self.window);
+        if self.sample_every == 0 {
+            checker.error(
+                "sample_every",
+                "must be at least 1",
+                Some("1 publishes every dead letter".into()),
+            );
+        }
+    }
+}
+
+// ============================================================================
+// QUEUE
+// ============================================================================
+
+/// Rate-limiting state of one source
+#[derive(Debug, Default)]
+struct SourceRate {
+    /// Start of the current window (Unix ms)
+    window_start: u64,
+    /// Dead letters in the current window, published or not
+    in_window: u32,
+    /// Dead letters suppressed since the last published one
+    suppressed: u32,
+    /// Dead letters suppressed since start
+    suppressed_total: u64,
+}
+
+/// The latest dead letters and the per-source rate limit
+#[derive(Debug, Default)]
+pub struct DeadLetterQueue {
+    config: DeadLetterConfig,
+    recent: VecDeque<DeadLetter>,
+    sources: HashMap<String, SourceRate>,
+}
+
+impl DeadLetterQueue {
+    pub fn new(config: DeadLetterConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Count a dead letter from `source`. Returns it, with the number
+    /// suppressed since the previous one, when it is to be published; it is
+    /// then kept among the latest too.
+    pub fn admit(&mut self, source: &str, mut letter: DeadLetter) -> Option<DeadLetter> {
+        let now = letter.received_at;
+        let window = self.config.window.as_millis() as u64;
+        if self.sources.len() >= MAX_TRACKED_SOURCES && !self.sources.contains_key(source) {
+            self.sources.retain(|_, rate| {
+                rate.suppressed > 0 || now.saturating_sub(rate.window_start) < window
+            });
+        }
+        let rate = self.sources.entry(source.to_string()).or_default();
+        if rate.in_window == 0 || now.saturating_sub(rate.window_start) >= window {
+            rate.window_start = now;
+            rate.in_window = 0;
+        }
+        rate.in_window = rate.in_window.saturating_add(1);
+
+        let beyond_burst = rate.in_window.saturating_sub(self.config.burst);
+        if beyond_burst > 0 && !beyond_burst.is_multiple_of(self.config.sample_every.max(1)) {
+            rate.suppressed += 1;
+            rate.suppressed_total += 1;
+            return None;
+        }
+        letter.suppressed = std::mem::take(&mut rate.suppressed);
+        self.recent.push_back(letter.clone());
+        while self.recent.len() > self.config.retained {
+            self.recent.pop_front();
+        }
+        Some(letter)
+    }
+
+    /// The latest published dead letters, oldest first
+    pub fn recent(&self) -> Vec<DeadLetter> {
+        self.recent.iter().cloned().collect()
+    }
+
+    /// Dead letters from `source` suppressed since start
+    pub fn suppressed(&self, source: &str) -> u64 {
+        self.sources
+            .get(source)
+            .map_or(0, |rate| rate.suppressed_total)
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetheris_shared::DeadLetterReason;
+
+    fn letter(received_at: u64) -> DeadLetter {
+        DeadLetter {
+            topic: "aetheris/telemetry/RV-001".into(),
+            reason: DeadLetterReason::Malformed,
+            detail: "expected value at line 1 column 1".into(),
+            claimed_source: None,
+            payload: "garbage".into(),
+            payload_base64: false,
+            received_at,
+            suppressed: 0,
+        }
+    }
+
+    #[test]
+    fn test_noisy_source_is_sampled_after_its_burst() {
+        let mut queue = DeadLetterQueue::new(DeadLetterConfig {
+            retained: 8,
+            burst: 3,
+            window: Duration::from_secs(60),
+            sample_every: 4,
+        });
+        // 10 Hz for 3 s
+        let published: Vec<DeadLetter> = (0..30)
+            .filter_map(|n| queue.admit("RV-001", letter(n * 100)))
+            .collect();
+        let suppressed: Vec<u32> = published.iter().map(|l| l.suppressed).collect();
+        assert_eq!(suppressed, [0, 0, 0, 3, 3, 3, 3, 3, 3]);
+        assert_eq!(queue.suppressed("RV-001"), 27 - 6);
+        assert_eq!(queue.recent().len(), 8);
+        assert_eq!(queue.recent()[0].received_at, 100);
+
+        // Other sources have their own burst
+        assert!(queue.admit("RV-002", letter(3_000)).is_some());
+
+        // A new window starts with a full burst, reporting what was held back
+        let next = queue.admit("RV-001", letter(63_000)).unwrap();
+        assert_eq!(next.suppressed, 3);
+        assert!(queue.admit("RV-001", letter(63_100)).is_some());
+    }
+}
@@ -0,0 +1,280 @@
+//! Decision trace for engine policies
+//!
+//! Policies that act on the fleet on their own (auto-dispatch, patrol
+//! scheduling, hazard response, ...) record a [`Decision`] describing what
+//! triggered them, every candidate they considered, and what they did. The
+//! candidate list comes straight from the policy's evaluation step, so the
+//! trace shows what the policy actually saw rather than a reconstruction.
+
+use std::collections::VecDeque;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+// ============================================================================
+// DECISION TYPES
+// ============================================================================
+
+/// Engine policies that make autonomous decisions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyKind {
+    AutoDispatch,
+    PatrolScheduler,
+    HazardResponder,
+    LowBattery,
+    WeatherHold,
+}
+
+/// Why a candidate was ruled out before scoring
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExclusionReason {
+    Offline,
+    InError,
+    InMaintenance,
+    LowBattery,
+    WrongType,
+    InvalidPosition,
+    AlreadyAssigned,
+    ZoneExcluded,
+    Busy,
+}
+
+impl fmt::Display for ExclusionReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            ExclusionReason::Offline => "robot is offline",
+            ExclusionReason::InError => "robot is in error state",
+            ExclusionReason::InMaintenance => "robot is in maintenance",
+            ExclusionReason::LowBattery => "battery below dispatch floor",
+            ExclusionReason::WrongType => "robot type cannot handle this task",
+            ExclusionReason::InvalidPosition => "robot position is not finite",
+            ExclusionReason::AlreadyAssigned => "robot is already assigned",
+            ExclusionReason::ZoneExcluded => "robot is in or must cross an excluded zone",
+            ExclusionReason::Busy => "robot is on a task that may not be interrupted",
+        };
+        f.write_str(text)
+    }
+}
+
+/// One candidate a policy considered
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CandidateEvaluation {
+    /// Robot being considered
+    pub robot_id: String,
+    /// Policy-specific score, higher is better (None when excluded)
+    pub score: Option<f64>,
+    /// Why the candidate was ruled out, if it was
+    pub excluded: Option<ExclusionReason>,
+}
+
+impl CandidateEvaluation {
+    pub fn scored(robot_id: impl Into<String>, score: f64) -> Self {
+        Self {
+            robot_id: robot_id.into(),
+            score: Some(score),
+            excluded: None,
+        }
+    }
+
+    pub fn excluded(robot_id: impl Into<String>, reason: ExclusionReason) -> Self {
+        Self {
+            robot_id: robot_id.into(),
+            score: None,
+            excluded: Some(reason),
+        }
+    }
+
+    pub fn is_eligible(&self) -> bool {
+        self.excluded.is_none()
+    }
+}
+
+/// Structured record of a single policy decision
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Decision {
+    /// Unique decision identifier, referenced from emitted events
+    pub id: String,
+    /// Unix timestamp of the decision (milliseconds)
+    pub timestamp: u64,
+    /// Policy that made the decision
+    pub policy: PolicyKind,
+    /// What triggered the evaluation (e.g. an anomaly id)
+    pub trigger: String,
+    /// Every candidate considered, eligible ones first in ranked order
+    pub candidates: Vec<CandidateEvaluation>,
+    /// Human-readable description of the chosen action, None if nothing was done
+    pub chosen_action: Option<String>,
+    /// IDs of the commands issued as a result
+    pub command_ids: Vec<String>,
+}
+
+impl Decision {
+    /// A decision taken at `now` on the engine's clock, which the events
+    /// it leads to share
+    pub fn new(
+        policy: PolicyKind,
+        trigger: impl Into<String>,
+        candidates: Vec<CandidateEvaluation>,
+        now: u64,
+    ) -> Self {
+        Self {
+            id: generate_decision_id(now),
+            timestamp: now,
+            policy,
+            trigger: trigger.into(),
+            candidates,
+            chosen_action: None,
+            command_ids: Vec::new(),
+        }
+    }
+
+    /// Record the action taken and the commands it produced
+    pub fn with_action(mut self, action: impl Into<String>, command_ids: Vec<String>) -> Self {
+        self.chosen_action = Some(action.into());
+        self.command_ids = command_ids;
+        self
+    }
+
+    /// Highest-scoring eligible candidate
+    pub fn winner(&self) -> Option<&CandidateEvaluation> {
+        self.candidates
+            .iter()
+            .filter(|c| c.is_eligible())
+            .max_by(|a, b| {
+                a.score
+                    .unwrap_or(f64::MIN)
+                    .total_cmp(&b.score.unwrap_or(f64::MIN))
+            })
+    }
+}
+
+/// Order candidates eligible first, best to worst, then the excluded ones.
+/// Robot ID breaks ties deterministically.
+pub fn rank(candidates: &mut [CandidateEvaluation]) {
+    candidates.sort_by(|a, b| {
+        let score = |c: &CandidateEvaluation| c.score.unwrap_or(f64::NEG_INFINITY);
+        score(b)
+            .total_cmp(&score(a))
+            .then_with(|| a.robot_id.cmp(&b.robot_id))
+    });
+}
+
+// ============================================================================
+// DECISION LOG
+// ============================================================================
+
+/// Bounded in-memory store of recent decisions, oldest evicted first
+#[derive(Debug)]
+pub struct DecisionLog {
+    decisions: VecDeque<Decision>,
+    capacity: usize,
+}
+
+impl Default for DecisionLog {
+    fn default() -> Self {
+        Self::new(1000)
+    }
+}
+
+impl DecisionLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            decisions: VecDeque::with_capacity(capacity.min(1024)),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Store a decision and return its id for attaching to events
+    pub fn record(&mut self, decision: Decision) -> String {
+        if self.decisions.len() == self.capacity {
+            self.decisions.pop_front();
+        }
+        let id = decision.id.clone();
+        self.decisions.push_back(decision);
+        id
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Decision> {
+        self.decisions.iter().find(|d| d.id == id)
+    }
+
+    /// Decisions at or after `since` (ms), optionally limited to one policy
+    pub fn query(&self, since: Option<u64>, policy: Option<PolicyKind>) -> Vec<&Decision> {
+        self.decisions
+            .iter()
+            .filter(|d| since.is_none_or(|since| d.timestamp >= since))
+            .filter(|d| policy.is_none_or(|policy| d.policy == policy))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.decisions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.decisions.is_empty()
+    }
+}
+
+/// Generate a unique decision ID
+fn generate_decision_id(now: u64) -> String {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let count = COUNTER.fetch_add(1, Ordering::SeqCst);
+    format!("DEC-{:X}-{:04X}", now, count)
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decision(policy: PolicyKind, timestamp: u64) -> Decision {
+        Decision::new(policy, "ANM-1", Vec::new(), timestamp)
+    }
+
+    #[test]
+    fn test_log_evicts_oldest() {
+        let mut log = DecisionLog::new(2);
+        let first = log.record(decision(PolicyKind::AutoDispatch, 1));
+        log.record(decision(PolicyKind::AutoDispatch, 2));
+        log.record(decision(PolicyKind::LowBattery, 3));
+        assert_eq!(log.len(), 2);
+        assert!(log.get(&first).is_none());
+    }
+
+    #[test]
+    fn test_query_filters_by_since_and_policy() {
+        let mut log = DecisionLog::default();
+        log.record(decision(PolicyKind::AutoDispatch, 100));
+        log.record(decision(PolicyKind::LowBattery, 200));
+        log.record(decision(PolicyKind::AutoDispatch, 300));
+
+        assert_eq!(log.query(None, None).len(), 3);
+        assert_eq!(log.query(Some(200), None).len(), 2);
+        assert_eq!(log.query(None, Some(PolicyKind::AutoDispatch)).len(), 2);
+        let recent = log.query(Some(150), Some(PolicyKind::AutoDispatch));
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].timestamp, 300);
+    }
+
+    #[test]
+    fn test_winner_ignores_excluded_candidates() {
+        let decision = Decision::new(
+            PolicyKind::AutoDispatch,
+            "ANM-1",
+            vec![
+                CandidateEvaluation::excluded("RV-002", ExclusionReason::LowBattery),
+                CandidateEvaluation::scored("CR-001", 0.4),
+                CandidateEvaluation::scored("DR-001", 0.2),
+            ],
+            0,
+        );
+        assert_eq!(decision.winner().unwrap().robot_id, "CR-001");
+    }
+}
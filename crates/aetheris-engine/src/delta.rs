@@ -0,0 +1,210 @@
+//! Delta telemetry for bandwidth-constrained links
+//!
+//! Most of a [`RobotState`] is the same from one report to the next. With a
+//! keyframe interval configured, the engine publishes a full state for a
+//! robot every N reports and only the changed fields in between, on the
+//! same topic (see [`TelemetryPayload`]). Receivers rebuild full states from
+//! the last state they hold. One that gets a delta for a robot it holds no
+//! state for cannot use it: it asks the robot for a keyframe with
+//! [`Command::RequestKeyframe`](aetheris_shared::Command::RequestKeyframe)
+//! and drops that robot's deltas until one arrives. A lost delta skews the
+//! rebuilt state until the next keyframe at the latest.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use aetheris_shared::{RobotId, RobotState, TelemetryPayload};
+
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Delta telemetry behavior
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeltaConfig {
+    /// Publish a full state every this many reports per robot and deltas in
+    /// between; full states only when unset
+    pub keyframe_interval: Option<u32>,
+    /// Time before a keyframe request that went unanswered is repeated
+    pub keyframe_retry: Duration,
+}
+
+impl Default for DeltaConfig {
+    fn default() -> Self {
+        Self {
+            keyframe_interval: None,
+            keyframe_retry: Duration::from_secs(5),
+        }
+    }
+}
+
+impl CheckConfig for DeltaConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if self.keyframe_interval == Some(0) {
+            checker.error(
+                "keyframe_interval",
+                "must be at least 1",
+                Some("leave it unset to publish full states only".into()),
+            );
+        }
+        checker.positive("keyframe_retry", self.keyframe_retry);
+    }
+}
+
+// ============================================================================
+// ENCODER
+// ============================================================================
+
+/// Chooses between a keyframe and a delta for each outgoing report
+#[derive(Debug, Default)]
+pub struct DeltaEncoder {
+    keyframe_interval: Option<u32>,
+    /// Last state sent per robot, and reports since its keyframe
+    sent: HashMap<RobotId, (RobotState, u32)>,
+}
+
+impl DeltaEncoder {
+    pub fn new(config: &DeltaConfig) -> Self {
+        Self {
+            keyframe_interval: config.keyframe_interval,
+            sent: HashMap::new(),
+        }
+    }
+
+    /// The payload to report `state` with; `None` when deltas are off and
+    /// the state goes out as is
+    pub fn encode(&mut self, state: &RobotState) -> Option<TelemetryPayload> {
+        let interval = self.keyframe_interval?;
+        let payload = match self.sent.get_mut(&state.id) {
+            Some((previous, since_keyframe)) if *since_keyframe + 1 < interval => {
+                *since_keyframe += 1;
+                let delta = state.diff(previous);
+                *previous = state.clone();
+                return Some(TelemetryPayload::Delta(delta));
+            }
+            _ => TelemetryPayload::Full(state.clone()),
+        };
+        self.sent.insert(state.id.clone(), (state.clone(), 0));
+        Some(payload)
+    }
+
+    /// Make the next report of `robot_id` (of every robot when `None`) a
+    /// keyframe
+    pub fn force_keyframe(&mut self, robot_id: Option<&str>) {
+        match robot_id {
+            Some(robot_id) => {
+                self.sent.remove(robot_id);
+            }
+            None => self.sent.clear(),
+        }
+    }
+}
+
+// ============================================================================
+// KEYFRAME REQUESTS
+// ============================================================================
+
+/// Robots the engine is waiting on a keyframe from
+#[derive(Debug)]
+pub struct KeyframeRequests {
+    retry_ms: u64,
+    /// When the keyframe was last requested, per robot
+    awaiting: HashMap<String, u64>,
+}
+
+impl KeyframeRequests {
+    pub fn new(config: &DeltaConfig) -> Self {
+        Self {
+            retry_ms: config.keyframe_retry.as_millis() as u64,
+            awaiting: HashMap::new(),
+        }
+    }
+
+    /// Note a delta that could not be applied. Returns whether a keyframe
+    /// should be requested now: on the first such delta, and again once the
+    /// previous request went unanswered for the retry interval.
+    pub fn missing_keyframe(&mut self, robot_id: &str, now: u64) -> bool {
+        match self.awaiting.get_mut(robot_id) {
+            Some(requested_at) if now.saturating_sub(*requested_at) < self.retry_ms => false,
+            Some(requested_at) => {
+                *requested_at = now;
+                true
+            }
+            None => {
+                self.awaiting.insert(robot_id.to_string(), now);
+                true
+            }
+        }
+    }
+
+    pub fn received_keyframe(&mut self, robot_id: &str) {
+        self.awaiting.remove(robot_id);
+    }
+
+    /// Whether deltas of `robot_id` are being dropped
+    pub fn is_awaiting(&self, robot_id: &str) -> bool {
+        self.awaiting.contains_key(robot_id)
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetheris_shared::{Position, RobotType};
+
+    fn keyframes_every(interval: u32) -> DeltaEncoder {
+        DeltaEncoder::new(&DeltaConfig {
+            keyframe_interval: Some(interval),
+            ..DeltaConfig::default()
+        })
+    }
+
+    #[test]
+    fn test_keyframe_every_n_reports_and_on_request() {
+        let mut encoder = keyframes_every(3);
+        let mut state = RobotState::new("RV-001".parse().unwrap(), "Rover Alpha", RobotType::Rover);
+        let mut receiver = state.clone();
+        let mut kinds = Vec::new();
+        for tick in 0..7 {
+            state.position = Position::new(tick as f64 * 0.1, 0.0, 0.0);
+            state.timestamp += Duration::from_secs(1);
+            if tick == 5 {
+                encoder.force_keyframe(Some("RV-001"));
+            }
+            match encoder.encode(&state).unwrap() {
+                TelemetryPayload::Full(full) => {
+                    kinds.push('F');
+                    receiver = full;
+                }
+                TelemetryPayload::Delta(delta) => {
+                    kinds.push('d');
+                    receiver.apply(&delta);
+                }
+            }
+            assert_eq!(receiver, state);
+        }
+        assert_eq!(kinds.iter().collect::<String>(), "FddFdFd");
+
+        assert!(DeltaEncoder::default().encode(&state).is_none());
+    }
+
+    #[test]
+    fn test_keyframe_requests_are_throttled() {
+        let mut requests = KeyframeRequests::new(&DeltaConfig::default());
+        assert!(requests.missing_keyframe("RV-001", 1_000));
+        assert!(!requests.missing_keyframe("RV-001", 2_000));
+        assert!(requests.is_awaiting("RV-001"));
+        // Unanswered: asked again after the retry interval
+        assert!(requests.missing_keyframe("RV-001", 6_000));
+
+        requests.received_keyframe("RV-001");
+        assert!(!requests.is_awaiting("RV-001"));
+        assert!(requests.missing_keyframe("RV-001", 6_500));
+    }
+}
@@ -0,0 +1,798 @@
+//! Replay-driven comparison of detector configurations
+//!
+//! Before new detector thresholds are deployed, a recording of environment
+//! and telemetry traffic is replayed through the detector pipeline twice,
+//! once per configuration, and the resulting alerts are compared. Only the
+//! detectors run: there is no MQTT, no fleet state, and no dispatch policy.
+//! A guardrail fails the evaluation when the candidate raises too many more
+//! severe alerts than the baseline.
+//!
+//! A recording is a directory holding `environment.jsonl` and/or
+//! `telemetry.jsonl`, one [`MqttMessage`] envelope per line as published on
+//! the corresponding topics. Configuration files hold the detector sections
+//! of the engine configuration: `{"alarms": {...}, "trends": {...}}`, with
+//! missing fields at their defaults.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use aetheris_shared::{
+    AnomalyReport, AnomalyType, MqttMessage, PipeEnvironment, RobotState, SeverityLevel,
+};
+
+use crate::alarms::{AlarmConfig, AlarmEvent, EnvironmentAlarms};
+use crate::config::{CheckConfig, ConfigChecker, ConfigReport, EXIT_INVALID_CONFIG, EngineConfig};
+use crate::sensor_health::{SensorHealth, SensorHealthConfig};
+use crate::trends::{TrendConfig, TrendDetector};
+
+/// Exit code when the candidate fails the guardrail
+pub const EXIT_REGRESSION: i32 = 1;
+
+/// Exit code for malformed arguments (sysexits `EX_USAGE`)
+pub const EXIT_USAGE: i32 = 64;
+
+/// Exit code for an unreadable recording (sysexits `EX_NOINPUT`)
+pub const EXIT_NO_INPUT: i32 = 66;
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// The detector sections of the engine configuration
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DetectorConfig {
+    pub alarms: AlarmConfig,
+    pub trends: TrendConfig,
+    pub sensor_health: SensorHealthConfig,
+}
+
+impl From<&EngineConfig> for DetectorConfig {
+    fn from(config: &EngineConfig) -> Self {
+        Self {
+            alarms: config.alarms.clone(),
+            trends: config.trends.clone(),
+            sensor_health: config.sensor_health.clone(),
+        }
+    }
+}
+
+impl CheckConfig for DetectorConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        checker.check_section("alarms", &self.alarms);
+        checker.check_section("trends", &self.trends);
+        checker.check_section("sensor_health", &self.sensor_health);
+    }
+}
+
+impl DetectorConfig {
+    /// Load and validate a JSON configuration file
+    pub fn load(path: &Path) -> Result<Self, EvalError> {
+        let text = std::fs::read_to_string(path).map_err(|source| EvalError::Io {
+            path: path.into(),
+            source,
+        })?;
+        let config: Self = serde_json::from_str(&text).map_err(|source| EvalError::Parse {
+            path: path.into(),
+            line: None,
+            source,
+        })?;
+        let mut checker = ConfigChecker::default();
+        config.check(&mut checker);
+        checker.finish().map_err(|report| EvalError::Invalid {
+            path: path.into(),
+            report,
+        })?;
+        Ok(config)
+    }
+}
+
+/// Failure to load a recording or configuration
+#[derive(Debug, Error)]
+pub enum EvalError {
+    #[error("{}: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("{}{}: {source}", .path.display(), .line.map(|l| format!(":{l}")).unwrap_or_default())]
+    Parse {
+        path: PathBuf,
+        line: Option<usize>,
+        source: serde_json::Error,
+    },
+    #[error("{}:\n{report}", .path.display())]
+    Invalid { path: PathBuf, report: ConfigReport },
+    #[error("{}: no environment.jsonl or telemetry.jsonl", .0.display())]
+    EmptyRecording(PathBuf),
+}
+
+// ============================================================================
+// RECORDING
+// ============================================================================
+
+/// One recorded message
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordedSample {
+    Environment(PipeEnvironment),
+    Telemetry(RobotState),
+}
+
+impl RecordedSample {
+    /// Unix timestamp of the reading (milliseconds)
+    pub fn timestamp(&self) -> u64 {
+        match self {
+            RecordedSample::Environment(reading) => reading.timestamp.as_millis(),
+            RecordedSample::Telemetry(state) => state.timestamp.as_millis(),
+        }
+    }
+}
+
+/// Recorded samples in time order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Recording {
+    pub samples: Vec<RecordedSample>,
+}
+
+impl Recording {
+    pub fn new(mut samples: Vec<RecordedSample>) -> Self {
+        samples.sort_by_key(RecordedSample::timestamp);
+        Self { samples }
+    }
+
+    /// Load the streams of a recording directory
+    pub fn load(dir: &Path) -> Result<Self, EvalError> {
+        let environment = read_stream(&dir.join("environment.jsonl"))?;
+        let telemetry = read_stream(&dir.join("telemetry.jsonl"))?;
+        if environment.is_none() && telemetry.is_none() {
+            return Err(EvalError::EmptyRecording(dir.into()));
+        }
+        let samples = environment
+            .into_iter()
+            .flatten()
+            .map(RecordedSample::Environment)
+            .chain(
+                telemetry
+                    .into_iter()
+                    .flatten()
+                    .map(RecordedSample::Telemetry),
+            )
+            .collect();
+        Ok(Self::new(samples))
+    }
+}
+
+/// Payloads of a JSONL envelope stream; `None` if the file does not exist
+fn read_stream<T: DeserializeOwned>(path: &Path) -> Result<Option<Vec<T>>, EvalError> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(source) => {
+            return Err(EvalError::Io {
+                path: path.into(),
+                source,
+            });
+        }
+    };
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str::<MqttMessage<T>>(line)
+                .map(|msg| msg.payload)
+                .map_err(|source| EvalError::Parse {
+                    path: path.into(),
+                    line: Some(i + 1),
+                    source,
+                })
+        })
+        .collect::<Result<_, _>>()
+        .map(Some)
+}
+
+// ============================================================================
+// PIPELINE
+// ============================================================================
+
+/// Detector that raised an alert
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DetectorKind {
+    /// Environment threshold alarms
+    Alarms,
+    /// Battery and signal trends
+    Trends,
+}
+
+impl DetectorKind {
+    fn name(&self) -> &'static str {
+        match self {
+            DetectorKind::Alarms => "alarms",
+            DetectorKind::Trends => "trends",
+        }
+    }
+}
+
+/// An alert raised during replay
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DetectedAlert {
+    /// `{detector}:{subject}:{type}#{n}`, n counting raises of that kind
+    /// for that subject; stable across configurations for the same episode
+    pub fingerprint: String,
+    pub detector: DetectorKind,
+    /// Section (alarms) or robot (trends) the alert is about
+    pub subject: String,
+    pub section_id: String,
+    pub anomaly_type: AnomalyType,
+    pub severity: SeverityLevel,
+    /// Unix timestamp of the sample that raised it (milliseconds)
+    pub timestamp: u64,
+    pub description: String,
+}
+
+/// The engine's detectors, fed from recorded samples instead of live traffic
+#[derive(Debug)]
+pub struct DetectorPipeline {
+    alarms: EnvironmentAlarms,
+    trends: TrendDetector,
+    sensor_health: SensorHealth,
+    raises: HashMap<(DetectorKind, String, String), u32>,
+}
+
+impl DetectorPipeline {
+    pub fn new(config: &DetectorConfig) -> Self {
+        Self {
+            alarms: EnvironmentAlarms::new(config.alarms.clone()),
+            trends: TrendDetector::new(config.trends.clone()),
+            sensor_health: SensorHealth::new(config.sensor_health.clone()),
+            raises: HashMap::new(),
+        }
+    }
+
+    /// Feed one sample, returning the alerts it raised
+    pub fn feed(&mut self, sample: &RecordedSample) -> Vec<DetectedAlert> {
+        let reports: Vec<(DetectorKind, String, AnomalyReport)> = match sample {
+            RecordedSample::Environment(reading) => {
+                let excluded = self
+                    .sensor_health
+                    .observe(reading, reading.timestamp.as_millis())
+                    .excluded;
+                self.alarms
+                    .assess_excluding(reading, reading.timestamp.as_millis(), &excluded)
+                    .into_iter()
+                    .filter_map(|event| match event {
+                        AlarmEvent::Raised(report) => {
+                            Some((DetectorKind::Alarms, reading.section_id.clone(), *report))
+                        }
+                        _ => None,
+                    })
+                    .collect()
+            }
+            RecordedSample::Telemetry(state) => self
+                .trends
+                .observe(
+                    &state.id,
+                    state.battery,
+                    state.signal,
+                    state.position,
+                    state.timestamp.as_millis(),
+                )
+                .into_iter()
+                .map(|report| (DetectorKind::Trends, state.id.to_string(), report))
+                .collect(),
+        };
+        reports
+            .into_iter()
+            .map(|(detector, subject, report)| {
+                let kind = label(&report.anomaly_type);
+                let n = self
+                    .raises
+                    .entry((detector, subject.clone(), kind.clone()))
+                    .or_default();
+                *n += 1;
+                DetectedAlert {
+                    fingerprint: format!("{}:{}:{}#{}", detector.name(), subject, kind, n),
+                    detector,
+                    subject,
+                    section_id: report.section_id,
+                    anomaly_type: report.anomaly_type,
+                    severity: report.severity,
+                    timestamp: sample.timestamp(),
+                    description: report.description,
+                }
+            })
+            .collect()
+    }
+
+    /// Replay a whole recording
+    pub fn run(config: &DetectorConfig, recording: &Recording) -> Vec<DetectedAlert> {
+        let mut pipeline = Self::new(config);
+        recording
+            .samples
+            .iter()
+            .flat_map(|sample| pipeline.feed(sample))
+            .collect()
+    }
+}
+
+/// Wire name of a serde enum (e.g. `AnomalyType::Leak` -> "leak")
+fn label(value: &impl Serialize) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(s)) => s,
+        Ok(other) => other.to_string(),
+        Err(_) => String::new(),
+    }
+}
+
+// ============================================================================
+// COMPARISON
+// ============================================================================
+
+/// Guardrail applied to the candidate
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EvalOptions {
+    /// Alerts at or above this severity are counted by the guardrail
+    pub guard_severity: SeverityLevel,
+    /// Largest tolerated increase in guarded alerts
+    pub max_guarded_increase: u32,
+}
+
+impl Default for EvalOptions {
+    fn default() -> Self {
+        Self {
+            guard_severity: SeverityLevel::Critical,
+            max_guarded_increase: 0,
+        }
+    }
+}
+
+/// Alert counts under each configuration
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct AlertTotals {
+    pub baseline: usize,
+    pub candidate: usize,
+}
+
+/// An episode both configurations alerted on, at different severities
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SeverityChange {
+    pub fingerprint: String,
+    pub baseline: SeverityLevel,
+    pub candidate: SeverityLevel,
+}
+
+/// Differences between the baseline and candidate alerts
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EvalReport {
+    pub only_in_baseline: Vec<DetectedAlert>,
+    pub only_in_candidate: Vec<DetectedAlert>,
+    pub changed_severity: Vec<SeverityChange>,
+    pub per_detector: BTreeMap<DetectorKind, AlertTotals>,
+    pub per_section: BTreeMap<String, AlertTotals>,
+    pub guard_severity: SeverityLevel,
+    /// Change in alerts at or above `guard_severity`, candidate minus baseline
+    pub guarded_increase: i64,
+    pub max_guarded_increase: u32,
+}
+
+impl EvalReport {
+    pub fn compare(
+        baseline: Vec<DetectedAlert>,
+        candidate: Vec<DetectedAlert>,
+        options: EvalOptions,
+    ) -> Self {
+        let mut per_detector: BTreeMap<DetectorKind, AlertTotals> = BTreeMap::new();
+        let mut per_section: BTreeMap<String, AlertTotals> = BTreeMap::new();
+        for alert in &baseline {
+            per_detector.entry(alert.detector).or_default().baseline += 1;
+            per_section
+                .entry(alert.section_id.clone())
+                .or_default()
+                .baseline += 1;
+        }
+        for alert in &candidate {
+            per_detector.entry(alert.detector).or_default().candidate += 1;
+            per_section
+                .entry(alert.section_id.clone())
+                .or_default()
+                .candidate += 1;
+        }
+        let guarded = |alerts: &[DetectedAlert]| {
+            alerts
+                .iter()
+                .filter(|a| a.severity >= options.guard_severity)
+                .count() as i64
+        };
+        let guarded_increase = guarded(&candidate) - guarded(&baseline);
+
+        let candidate_by_key: HashMap<&str, &DetectedAlert> = candidate
+            .iter()
+            .map(|a| (a.fingerprint.as_str(), a))
+            .collect();
+        let baseline_keys: HashMap<&str, &DetectedAlert> = baseline
+            .iter()
+            .map(|a| (a.fingerprint.as_str(), a))
+            .collect();
+        let changed_severity = baseline
+            .iter()
+            .filter_map(|b| {
+                let c = candidate_by_key.get(b.fingerprint.as_str())?;
+                (c.severity != b.severity).then(|| SeverityChange {
+                    fingerprint: b.fingerprint.clone(),
+                    baseline: b.severity,
+                    candidate: c.severity,
+                })
+            })
+            .collect();
+        let only_in_baseline = baseline
+            .iter()
+            .filter(|a| !candidate_by_key.contains_key(a.fingerprint.as_str()))
+            .cloned()
+            .collect();
+        let only_in_candidate = candidate
+            .iter()
+            .filter(|a| !baseline_keys.contains_key(a.fingerprint.as_str()))
+            .cloned()
+            .collect();
+
+        Self {
+            only_in_baseline,
+            only_in_candidate,
+            changed_severity,
+            per_detector,
+            per_section,
+            guard_severity: options.guard_severity,
+            guarded_increase,
+            max_guarded_increase: options.max_guarded_increase,
+        }
+    }
+
+    /// Whether the candidate stays within the guardrail
+    pub fn passes(&self) -> bool {
+        self.guarded_increase <= i64::from(self.max_guarded_increase)
+    }
+
+    /// Human-readable summary for a terminal
+    pub fn render_table(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "{:<44} {:>9} {:>9}", "TOTALS", "BASELINE", "CANDIDATE");
+        for (detector, totals) in &self.per_detector {
+            let _ = writeln!(
+                out,
+                "{:<44} {:>9} {:>9}",
+                format!("detector {}", detector.name()),
+                totals.baseline,
+                totals.candidate
+            );
+        }
+        for (section, totals) in &self.per_section {
+            let _ = writeln!(
+                out,
+                "{:<44} {:>9} {:>9}",
+                format!("section {}", section),
+                totals.baseline,
+                totals.candidate
+            );
+        }
+        let _ = writeln!(out);
+        for (title, alerts) in [
+            ("ONLY IN BASELINE", &self.only_in_baseline),
+            ("ONLY IN CANDIDATE", &self.only_in_candidate),
+        ] {
+            let _ = writeln!(out, "{} ({})", title, alerts.len());
+            for alert in alerts {
+                let _ = writeln!(
+                    out,
+                    "  {:<42} {:<9} {}",
+                    alert.fingerprint,
+                    label(&alert.severity),
+                    alert.description
+                );
+            }
+        }
+        let _ = writeln!(out, "CHANGED SEVERITY ({})", self.changed_severity.len());
+        for change in &self.changed_severity {
+            let _ = writeln!(
+                out,
+                "  {:<42} {} -> {}",
+                change.fingerprint,
+                label(&change.baseline),
+                label(&change.candidate)
+            );
+        }
+        let _ = writeln!(
+            out,
+            "\n{} alerts at {}+: {:+} (limit +{}) {}",
+            label(&self.guard_severity),
+            label(&self.guard_severity),
+            self.guarded_increase,
+            self.max_guarded_increase,
+            if self.passes() { "PASS" } else { "FAIL" }
+        );
+        out
+    }
+}
+
+/// Replay `recording` under both configurations and compare the alerts
+pub fn evaluate(
+    recording: &Recording,
+    baseline: &DetectorConfig,
+    candidate: &DetectorConfig,
+    options: EvalOptions,
+) -> EvalReport {
+    EvalReport::compare(
+        DetectorPipeline::run(baseline, recording),
+        DetectorPipeline::run(candidate, recording),
+        options,
+    )
+}
+
+// ============================================================================
+// COMMAND LINE
+// ============================================================================
+
+const USAGE: &str = "usage: --detector-eval <recording-dir> <baseline.json> <candidate.json> \
+                     [--guard-severity <level>] [--max-increase <n>] [--json <path>]";
+
+/// Run `--detector-eval` with the arguments following the flag, printing the
+/// table to `out`.
+///
+/// Returns the process exit code: 0 when the candidate passes the guardrail,
+/// [`EXIT_REGRESSION`] when it does not, and a sysexits code for bad input.
+pub fn run_detector_eval(args: &[String], out: &mut impl Write) -> i32 {
+    let mut positional = Vec::new();
+    let mut options = EvalOptions::default();
+    let mut json_path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let parsed = match arg.as_str() {
+            "--guard-severity" => args
+                .next()
+                .and_then(|v| serde_json::from_value(serde_json::Value::String(v.clone())).ok())
+                .map(|severity| options.guard_severity = severity),
+            "--max-increase" => args
+                .next()
+                .and_then(|v| v.parse().ok())
+                .map(|n| options.max_guarded_increase = n),
+            "--json" => args
+                .next()
+                .map(|path| json_path = Some(PathBuf::from(path))),
+            _ if !arg.starts_with("--") => {
+                positional.push(PathBuf::from(arg));
+                Some(())
+            }
+            _ => None,
+        };
+        if parsed.is_none() {
+            let _ = writeln!(out, "invalid argument {arg:?}\n{USAGE}");
+            return EXIT_USAGE;
+        }
+    }
+    let [recording, baseline, candidate] = positional.as_slice() else {
+        let _ = writeln!(out, "{USAGE}");
+        return EXIT_USAGE;
+    };
+
+    let recording = match Recording::load(recording) {
+        Ok(recording) => recording,
+        Err(e) => {
+            let _ = writeln!(out, "{e}");
+            return EXIT_NO_INPUT;
+        }
+    };
+    let configs = DetectorConfig::load(baseline)
+        .and_then(|baseline| Ok((baseline, DetectorConfig::load(candidate)?)));
+    let (baseline, candidate) = match configs {
+        Ok(configs) => configs,
+        Err(e) => {
+            let _ = writeln!(out, "{e}");
+            return EXIT_INVALID_CONFIG;
+        }
+    };
+
+    let report = evaluate(&recording, &baseline, &candidate, options);
+    let _ = write!(out, "{}", report.render_table());
+    if let Some(path) = json_path {
+        let json = serde_json::to_string_pretty(&report).unwrap_or_default();
+        if let Err(e) = std::fs::write(&path, json) {
+            let _ = writeln!(out, "{}: {}", path.display(), e);
+        }
+    }
+    if report.passes() { 0 } else { EXIT_REGRESSION }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alarms::AlarmThreshold;
+    use aetheris_shared::{Position, Timestamp};
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("aetheris-eval-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn write(&self, name: &str, contents: &str) -> String {
+            let path = self.0.join(name);
+            std::fs::write(&path, contents).unwrap();
+            path.display().to_string()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn reading(section: &str, h2: f64, temperature: f64, at: u64) -> PipeEnvironment {
+        PipeEnvironment {
+            section_id: section.into(),
+            pressure: 50.0,
+            temperature,
+            h2_concentration: h2,
+            wall_thickness: 10.0,
+            flow_rate: 500.0,
+            humidity: 45.0,
+            position: Position::origin(),
+            timestamp: Timestamp::from_millis(at),
+        }
+    }
+
+    /// PIPE-001 peaks at 3500 ppm H2, PIPE-002 at 5000 ppm and 85 °C; a
+    /// rover drains steadily
+    fn write_recording(dir: &TempDir) {
+        let mut environment = String::new();
+        for (i, (h2_1, h2_2, t_2)) in [
+            (100.0, 100.0, 20.0),
+            (3500.0, 5000.0, 85.0),
+            (100.0, 100.0, 20.0),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let at = i as u64 * 10_000;
+            for r in [
+                reading("PIPE-001", h2_1, 20.0, at),
+                reading("PIPE-002", h2_2, t_2, at),
+            ] {
+                let line = serde_json::to_string(&MqttMessage::new(r, "PIPE", 0)).unwrap();
+                environment.push_str(&line);
+                environment.push('\n');
+            }
+        }
+        dir.write("environment.jsonl", &environment);
+
+        let mut telemetry = String::new();
+        for i in 0..10u64 {
+            let mut state = RobotState::new(
+                "RV-001".parse().unwrap(),
+                "Rover",
+                aetheris_shared::RobotType::Rover,
+            );
+            state.battery = 90.0 - i as f64 * 0.1;
+            state.timestamp = Timestamp::from_millis(i * 5_000);
+            let line = serde_json::to_string(&MqttMessage::new(state, "RV-001", i)).unwrap();
+            telemetry.push_str(&line);
+            telemetry.push('\n');
+        }
+        dir.write("telemetry.jsonl", &telemetry);
+    }
+
+    fn candidate() -> DetectorConfig {
+        DetectorConfig {
+            alarms: AlarmConfig {
+                h2_concentration: AlarmThreshold {
+                    raise_above: 3000.0,
+                    hysteresis: 300.0,
+                },
+                ..AlarmConfig::default()
+            },
+            ..DetectorConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_lowered_threshold_shows_up_in_diff() {
+        let dir = TempDir::new();
+        write_recording(&dir);
+        let recording = Recording::load(&dir.0).unwrap();
+        assert_eq!(recording.samples.len(), 16);
+
+        let report = evaluate(
+            &recording,
+            &DetectorConfig::default(),
+            &candidate(),
+            EvalOptions::default(),
+        );
+        assert!(report.only_in_baseline.is_empty());
+        assert!(report.changed_severity.is_empty());
+        let extra: Vec<_> = report
+            .only_in_candidate
+            .iter()
+            .map(|a| a.fingerprint.as_str())
+            .collect();
+        assert_eq!(extra, vec!["alarms:PIPE-001:leak#1"]);
+        assert_eq!(
+            report.per_detector[&DetectorKind::Alarms],
+            AlertTotals {
+                baseline: 2,
+                candidate: 3
+            }
+        );
+        assert_eq!(
+            report.per_section["PIPE-002"],
+            AlertTotals {
+                baseline: 2,
+                candidate: 2
+            }
+        );
+        assert!(!report.per_detector.contains_key(&DetectorKind::Trends));
+        // No detector raises Critical, so the default guardrail holds
+        assert_eq!(report.guarded_increase, 0);
+        assert!(report.passes());
+    }
+
+    #[test]
+    fn test_guardrail_sets_exit_code() {
+        let dir = TempDir::new();
+        write_recording(&dir);
+        let baseline = dir.write("baseline.json", "{}");
+        let candidate = dir.write(
+            "candidate.json",
+            &serde_json::to_string(&candidate()).unwrap(),
+        );
+        let json = dir.0.join("report.json").display().to_string();
+        let args = |extra: &[&str]| {
+            [
+                dir.0.display().to_string(),
+                baseline.clone(),
+                candidate.clone(),
+            ]
+            .into_iter()
+            .chain(extra.iter().map(|s| s.to_string()))
+            .collect::<Vec<_>>()
+        };
+
+        let mut out = Vec::new();
+        let code = run_detector_eval(
+            &args(&["--guard-severity", "high", "--json", &json]),
+            &mut out,
+        );
+        assert_eq!(code, EXIT_REGRESSION);
+        let table = String::from_utf8(out).unwrap();
+        assert!(table.contains("alarms:PIPE-001:leak#1"));
+        assert!(table.contains("high alerts at high+: +1 (limit +0) FAIL"));
+        let written: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&json).unwrap()).unwrap();
+        assert_eq!(written["guarded_increase"], 1);
+
+        let tolerant = args(&["--guard-severity", "high", "--max-increase", "1"]);
+        assert_eq!(run_detector_eval(&tolerant, &mut Vec::new()), 0);
+
+        let invalid = dir.write("invalid.json", r#"{"trends": {"warmup_samples": 1}}"#);
+        let bad = [dir.0.display().to_string(), baseline.clone(), invalid];
+        assert_eq!(
+            run_detector_eval(&bad, &mut Vec::new()),
+            EXIT_INVALID_CONFIG
+        );
+        assert_eq!(
+            run_detector_eval(&args(&["--max-increase"]), &mut Vec::new()),
+            EXIT_USAGE
+        );
+    }
+}
@@ -0,0 +1,453 @@
+//! Pluggable anomaly detectors
+//!
+//! Besides its built-in hazard alarms and wall-thickness projections the
+//! engine runs every registered [`AnomalyDetector`] on each environment
+//! reading and publishes the reports they return like any other alert.
+//! Detectors are registered at startup with
+//! [`AetherisMqtt::register_detector`](crate::AetherisMqtt::register_detector).
+//!
+//! The built-in [`ThresholdDetector`] checks readings against configured
+//! rules: a metric of the reading, or the section's wall loss rate, kept
+//! within bounds. A rule reports once when a section breaks it and again
+//! only after the section has come back within bounds.
+
+use std::collections::HashSet;
+use std::fmt::Debug;
+
+use serde::{Deserialize, Serialize};
+
+use aetheris_shared::{AnomalyReport, AnomalyType, Measurement, PipeEnvironment, SeverityLevel};
+
+use crate::alarms::HazardKind;
+use crate::anomalies::ENGINE_ORIGIN;
+use crate::config::{CheckConfig, ConfigChecker};
+use crate::wall_thickness::SectionWallTrend;
+
+// ============================================================================
+// DETECTOR TRAIT
+// ============================================================================
+
+/// What a detector sees of one environment reading
+#[derive(Debug, Clone, Copy)]
+pub struct Observation<'a> {
+    pub reading: &'a PipeEnvironment,
+    /// The section's wall-thickness trend, once it has one
+    pub wall_trend: Option<&'a SectionWallTrend>,
+    /// Channels whose sensor is suspect; their values should not be trusted
+    pub suspect: &'a [HazardKind],
+}
+
+/// Source of anomaly reports from environment readings
+pub trait AnomalyDetector: Debug + Send {
+    /// Name the detector is logged under
+    fn name(&self) -> &str;
+
+    /// Reports raised by `observation`, if any
+    fn observe(&mut self, observation: &Observation<'_>) -> Vec<AnomalyReport>;
+}
+
+// ============================================================================
+// THRESHOLD RULES
+// ============================================================================
+
+/// Value a threshold rule watches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThresholdMetric {
+    /// H2 concentration in ppm
+    H2Concentration,
+    /// Pressure in bar
+    Pressure,
+    /// Temperature in Celsius
+    Temperature,
+    /// Humidity in percent
+    Humidity,
+    /// Flow rate in m³/h
+    FlowRate,
+    /// Wall thickness in millimeters
+    WallThickness,
+    /// Wall loss rate in mm/year, from the section's trend once it is
+    /// significant
+    WallLossRate,
+}
+
+impl ThresholdMetric {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ThresholdMetric::H2Concentration => "h2_concentration",
+            ThresholdMetric::Pressure => "pressure",
+            ThresholdMetric::Temperature => "temperature",
+            ThresholdMetric::Humidity => "humidity",
+            ThresholdMetric::FlowRate => "flow_rate",
+            ThresholdMetric::WallThickness => "wall_thickness",
+            ThresholdMetric::WallLossRate => "wall_loss_rate",
+        }
+    }
+
+    pub fn unit(&self) -> &'static str {
+        match self {
+            ThresholdMetric::H2Concentration => "ppm",
+            ThresholdMetric::Pressure => "bar",
+            ThresholdMetric::Temperature => "°C",
+            ThresholdMetric::Humidity => "%",
+            ThresholdMetric::FlowRate => "m³/h",
+            ThresholdMetric::WallThickness => "mm",
+            ThresholdMetric::WallLossRate => "mm/year",
+        }
+    }
+
+    /// What breaking a rule on this metric is reported as
+    pub fn anomaly_type(&self) -> AnomalyType {
+        match self {
+            ThresholdMetric::H2Concentration => AnomalyType::Leak,
+            ThresholdMetric::Pressure => AnomalyType::PressureDrop,
+            ThresholdMetric::Temperature => AnomalyType::TemperatureAnomaly,
+            ThresholdMetric::WallThickness | ThresholdMetric::WallLossRate => {
+                AnomalyType::WallThinning
+            }
+            ThresholdMetric::Humidity | ThresholdMetric::FlowRate => AnomalyType::Unknown,
+        }
+    }
+
+    /// The metric's value in `observation`, if known and trusted
+    pub fn value(&self, observation: &Observation<'_>) -> Option<f64> {
+        let reading = observation.reading;
+        let hazard = match self {
+            ThresholdMetric::H2Concentration => Some(HazardKind::H2Concentration),
+            ThresholdMetric::Pressure => Some(HazardKind::Overpressure),
+            ThresholdMetric::Temperature => Some(HazardKind::HighTemperature),
+            _ => None,
+        };
+        if let Some(hazard) = hazard {
+            if observation.suspect.contains(&hazard) {
+                return None;
+            }
+            return Some(hazard.reading(reading));
+        }
+        let value = match self {
+            ThresholdMetric::Humidity => reading.humidity,
+            ThresholdMetric::FlowRate => reading.flow_rate,
+            ThresholdMetric::WallThickness => reading.wall_thickness,
+            ThresholdMetric::WallLossRate => {
+                observation
+                    .wall_trend
+                    .filter(|trend| trend.significant)?
+                    .loss_rate_mm_per_year
+            }
+            _ => return None,
+        };
+        value.is_finite().then_some(value)
+    }
+}
+
+/// Bounds a metric must stay within
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ThresholdRule {
+    pub metric: ThresholdMetric,
+    /// Values above this break the rule
+    #[serde(default)]
+    pub above: Option<f64>,
+    /// Values below this break the rule
+    #[serde(default)]
+    pub below: Option<f64>,
+    pub severity: SeverityLevel,
+    /// What the report is filed as; by default the metric's usual type
+    #[serde(default)]
+    pub anomaly_type: Option<AnomalyType>,
+    /// Only sections listed here, or every section when empty
+    #[serde(default)]
+    pub sections: Vec<String>,
+}
+
+impl ThresholdRule {
+    /// The bound `value` breaks, if any
+    fn broken(&self, value: f64) -> Option<(&'static str, f64)> {
+        match (self.above, self.below) {
+            (Some(above), _) if value > above => Some(("above", above)),
+            (_, Some(below)) if value < below => Some(("below", below)),
+            _ => None,
+        }
+    }
+
+    fn applies_to(&self, section_id: &str) -> bool {
+        self.sections.is_empty() || self.sections.iter().any(|s| s == section_id)
+    }
+}
+
+impl CheckConfig for ThresholdRule {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if self.above.is_none() && self.below.is_none() {
+            checker.error("above", "a rule needs `above`, `below` or both", None);
+        }
+        for (field, bound) in [("above", self.above), ("below", self.below)] {
+            if bound.is_some_and(|bound| !bound.is_finite()) {
+                checker.error(field, "must be a finite number", None);
+            }
+        }
+        if let (Some(above), Some(below)) = (self.above, self.below)
+            && below > above
+        {
+            checker.error(
+                "below",
+                format!("must not exceed above ({above}), got {below}"),
+                None,
+            );
+        }
+    }
+}
+
+/// Rules of the built-in threshold detector
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ThresholdConfig {
+    /// No rules means no threshold detector
+    pub rules: Vec<ThresholdRule>,
+}
+
+impl CheckConfig for ThresholdConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        for (index, rule) in self.rules.iter().enumerate() {
+            checker.check_section(&format!("rules[{index}]"), rule);
+        }
+    }
+}
+
+// ============================================================================
+// THRESHOLD DETECTOR
+// ============================================================================
+
+/// Reports sections whose readings break a [`ThresholdRule`]
+#[derive(Debug, Default)]
+pub struct ThresholdDetector {
+    rules: Vec<ThresholdRule>,
+    /// (rule index, section) pairs currently breaking their rule
+    broken: HashSet<(usize, String)>,
+}
+
+impl ThresholdDetector {
+    pub fn new(config: ThresholdConfig) -> Self {
+        Self {
+            rules: config.rules,
+            broken: HashSet::new(),
+        }
+    }
+
+    pub fn rules(&self) -> &[ThresholdRule] {
+        &self.rules
+    }
+}
+
+impl AnomalyDetector for ThresholdDetector {
+    fn name(&self) -> &str {
+        "thresholds"
+    }
+
+    fn observe(&mut self, observation: &Observation<'_>) -> Vec<AnomalyReport> {
+        let reading = observation.reading;
+        let mut reports = Vec::new();
+        for (index, rule) in self.rules.iter().enumerate() {
+            if !rule.applies_to(&reading.section_id) {
+                continue;
+            }
+            // An unknown value leaves the rule where it was
+            let Some(value) = rule.metric.value(observation) else {
+                continue;
+            };
+            let key = (index, reading.section_id.clone());
+            let Some((side, bound)) = rule.broken(value) else {
+                self.broken.remove(&key);
+                continue;
+            };
+            if !self.broken.insert(key) {
+                continue;
+            }
+            let metric = rule.metric;
+            reports.push(AnomalyReport {
+                timestamp: reading.timestamp,
+                measurement: Some(Measurement {
+                    name: metric.as_str().into(),
+                    value,
+                    unit: metric.unit().into(),
+                }),
+                ..AnomalyReport::new(
+                    rule.anomaly_type.unwrap_or(metric.anomaly_type()),
+                    rule.severity,
+                    reading.position,
+                    &reading.section_id,
+                    ENGINE_ORIGIN,
+                    1.0,
+                    format!(
+                        "{} {:.2} {} is {} the {:.2} {} limit in {}",
+                        metric.as_str(),
+                        value,
+                        metric.unit(),
+                        side,
+                        bound,
+                        metric.unit(),
+                        reading.section_id
+                    ),
+                )
+            });
+        }
+        reports
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetheris_shared::{Position, Timestamp};
+
+    fn reading(section_id: &str, h2: f64, wall_thickness: f64) -> PipeEnvironment {
+        PipeEnvironment {
+            section_id: section_id.into(),
+            pressure: 50.0,
+            temperature: 25.0,
+            h2_concentration: h2,
+            wall_thickness,
+            flow_rate: 500.0,
+            humidity: 45.0,
+            position: Position::new(5.0, -0.5, 8.0),
+            timestamp: Timestamp::from_millis(1_000),
+        }
+    }
+
+    fn observe(detector: &mut ThresholdDetector, reading: &PipeEnvironment) -> Vec<AnomalyReport> {
+        detector.observe(&Observation {
+            reading,
+            wall_trend: None,
+            suspect: &[],
+        })
+    }
+
+    fn rule(metric: ThresholdMetric, above: Option<f64>, below: Option<f64>) -> ThresholdRule {
+        ThresholdRule {
+            metric,
+            above,
+            below,
+            severity: SeverityLevel::High,
+            anomaly_type: None,
+            sections: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_rules_report_once_per_breach() {
+        let mut detector = ThresholdDetector::new(ThresholdConfig {
+            rules: vec![
+                rule(ThresholdMetric::H2Concentration, Some(1_000.0), None),
+                rule(ThresholdMetric::WallThickness, None, Some(8.0)),
+            ],
+        });
+
+        assert!(observe(&mut detector, &reading("PIPE-001", 200.0, 10.0)).is_empty());
+        let reports = observe(&mut detector, &reading("PIPE-001", 1_500.0, 7.5));
+        let found: Vec<_> = reports.iter().map(|r| r.anomaly_type).collect();
+        assert_eq!(found, [AnomalyType::Leak, AnomalyType::WallThinning]);
+        assert_eq!(
+            reports[0].description,
+            "h2_concentration 1500.00 ppm is above the 1000.00 ppm limit in PIPE-001"
+        );
+        assert_eq!(reports[1].measurement.as_ref().unwrap().value, 7.5);
+        assert_eq!(reports[0].detected_by, ENGINE_ORIGIN);
+
+        // Still broken: nothing new, and other sections are tracked apart
+        assert!(observe(&mut detector, &reading("PIPE-001", 1_600.0, 7.4)).is_empty());
+        assert_eq!(
+            observe(&mut detector, &reading("PIPE-002", 1_600.0, 10.0)).len(),
+            1
+        );
+
+        // Back within bounds re-arms the rule
+        assert!(observe(&mut detector, &reading("PIPE-001", 200.0, 7.4)).is_empty());
+        assert_eq!(
+            observe(&mut detector, &reading("PIPE-001", 1_500.0, 7.4)).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_untrusted_or_unknown_values_are_skipped() {
+        let mut detector = ThresholdDetector::new(ThresholdConfig {
+            rules: vec![
+                rule(ThresholdMetric::H2Concentration, Some(1_000.0), None),
+                ThresholdRule {
+                    severity: SeverityLevel::Medium,
+                    sections: vec!["PIPE-003".into()],
+                    ..rule(ThresholdMetric::WallLossRate, Some(0.5), None)
+                },
+            ],
+        });
+        let leaking = reading("PIPE-003", 5_000.0, 9.0);
+        let mut trend = SectionWallTrend {
+            section_id: "PIPE-003".into(),
+            thickness_mm: 9.0,
+            loss_rate_mm_per_year: 0.8,
+            significant: false,
+            breach_at: None,
+            samples: 30,
+        };
+
+        let reports = detector.observe(&Observation {
+            reading: &leaking,
+            wall_trend: Some(&trend),
+            suspect: &[HazardKind::H2Concentration],
+        });
+        assert!(reports.is_empty());
+
+        trend.significant = true;
+        let reports = detector.observe(&Observation {
+            reading: &leaking,
+            wall_trend: Some(&trend),
+            suspect: &[],
+        });
+        let found: Vec<_> = reports
+            .iter()
+            .map(|r| (r.anomaly_type, r.severity))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (AnomalyType::Leak, SeverityLevel::High),
+                (AnomalyType::WallThinning, SeverityLevel::Medium)
+            ]
+        );
+
+        // The loss-rate rule is limited to PIPE-003
+        let other = reading("PIPE-004", 100.0, 9.0);
+        let reports = detector.observe(&Observation {
+            reading: &other,
+            wall_trend: Some(&trend),
+            suspect: &[],
+        });
+        assert!(reports.is_empty());
+    }
+
+    #[test]
+    fn test_rules_need_a_bound() {
+        let config = ThresholdConfig {
+            rules: vec![
+                rule(ThresholdMetric::Pressure, None, None),
+                rule(ThresholdMetric::Pressure, Some(60.0), Some(70.0)),
+                rule(ThresholdMetric::Pressure, Some(f64::NAN), None),
+            ],
+        };
+        let mut checker = ConfigChecker::default();
+        config.check(&mut checker);
+        let paths: Vec<_> = checker
+            .finish()
+            .unwrap_err()
+            .issues
+            .into_iter()
+            .map(|issue| issue.path)
+            .collect();
+        assert_eq!(
+            paths,
+            ["rules[0].above", "rules[1].below", "rules[2].above"]
+        );
+    }
+}
@@ -0,0 +1,511 @@
+//! Automatic dispatch of robots to new anomalies
+//!
+//! When an unacknowledged alert at or above the configured severity is
+//! released for dispatch, the [`AutoDispatcher`] picks the nearest robot
+//! suited to the anomaly that is free to go: of the right type, online, not
+//! on a task that may not be interrupted, above the battery floor, and not
+//! already assigned to another anomaly. The engine sends it `Investigate`
+//! followed by a `MoveTo` toward the anomaly. When no robot can go, the
+//! engine raises an escalation alert instead. Every evaluation is recorded
+//! as a [`Decision`].
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use aetheris_shared::{
+    AnomalyReport, AnomalyType, CurrentTask, RobotStatus, RobotType, SeverityLevel,
+};
+
+use crate::FleetManager;
+use crate::anomalies::{ActiveAnomalies, ENGINE_ORIGIN, SYSTEM_SECTION};
+use crate::config::{CheckConfig, ConfigChecker};
+use crate::decision::{self, CandidateEvaluation, Decision, ExclusionReason, PolicyKind};
+use crate::zones::ZoneRegistry;
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Auto-dispatch policy settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct DispatchConfig {
+    /// Whether robots are sent to new anomalies without an operator
+    pub enabled: bool,
+    /// Least severe alert a robot is sent to
+    pub min_severity: SeverityLevel,
+    /// Battery percentage a robot needs to be sent
+    pub min_battery: f64,
+}
+
+impl Default for DispatchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_severity: SeverityLevel::High,
+            min_battery: 30.0,
+        }
+    }
+}
+
+impl CheckConfig for DispatchConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if !(0.0..=100.0).contains(&self.min_battery) {
+            checker.error(
+                "min_battery",
+                format!("must be between 0 and 100, got {}", self.min_battery),
+                Some("a percentage of full charge; the default is 30".into()),
+            );
+        }
+    }
+}
+
+/// Robot types that can investigate an anomaly: crawlers inside the pipe,
+/// drones and rovers for surveys along it
+pub fn suited_types(anomaly_type: AnomalyType) -> &'static [RobotType] {
+    match anomaly_type {
+        AnomalyType::WallThinning => &[RobotType::Crawler],
+        AnomalyType::Corrosion | AnomalyType::Crack => &[RobotType::Crawler, RobotType::Rover],
+        AnomalyType::Leak | AnomalyType::PressureDrop | AnomalyType::TemperatureAnomaly => {
+            &[RobotType::Drone, RobotType::Rover]
+        }
+        AnomalyType::StructuralDamage => &[RobotType::Rover, RobotType::Drone],
+        AnomalyType::Unknown => &RobotType::ALL,
+    }
+}
+
+/// Whether a robot may be pulled off its task to investigate: idle robots
+/// and patrols may, errands of their own may not
+fn interruptible(task: &CurrentTask) -> bool {
+    matches!(task, CurrentTask::None | CurrentTask::Patrolling { .. })
+}
+
+// ============================================================================
+// DISPATCHER
+// ============================================================================
+
+/// What to do about an alert released for dispatch
+#[derive(Debug, Clone, PartialEq)]
+pub enum DispatchPlan {
+    /// Not for auto-dispatch, or already handled
+    Skip,
+    /// Send the robot, now assigned to the anomaly
+    Send {
+        robot_id: String,
+        decision: Decision,
+    },
+    /// No robot can go; raise an escalation alert
+    Escalate { decision: Decision },
+}
+
+/// Picks robots for new anomalies and remembers which robot went where
+#[derive(Debug, Default)]
+pub struct AutoDispatcher {
+    config: DispatchConfig,
+    /// Anomaly each dispatched robot is investigating
+    assignments: HashMap<String, String>,
+    /// Anomalies no robot could be found for
+    escalated: HashSet<String>,
+}
+
+impl AutoDispatcher {
+    pub fn new(config: DispatchConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    pub fn config(&self) -> &DispatchConfig {
+        &self.config
+    }
+
+    /// Anomaly the robot was sent to, if any
+    pub fn assignment(&self, robot_id: &str) -> Option<&str> {
+        self.assignments.get(robot_id).map(String::as_str)
+    }
+
+    /// Robot sent to the anomaly, if any
+    pub fn assigned_robot(&self, anomaly_id: &str) -> Option<&str> {
+        self.assignments
+            .iter()
+            .find(|(_, anomaly)| *anomaly == anomaly_id)
+            .map(|(robot_id, _)| robot_id.as_str())
+    }
+
+    /// Free the robot, e.g. when its commands could not be sent
+    pub fn release(&mut self, robot_id: &str) -> Option<String> {
+        self.assignments.remove(robot_id)
+    }
+
+    /// Free robots whose anomaly left the active set or that dropped out
+    /// (offline, in error, or in maintenance), and forget escalations of
+    /// closed anomalies
+    pub fn release_finished(&mut self, fleet: &FleetManager, anomalies: &ActiveAnomalies) {
+        self.assignments.retain(|robot_id, anomaly_id| {
+            anomalies.get(anomaly_id).is_some()
+                && fleet.get_robot(robot_id).is_some_and(|robot| {
+                    matches!(robot.status, RobotStatus::Active | RobotStatus::Idle)
+                })
+        });
+        self.escalated
+            .retain(|anomaly_id| anomalies.get(anomaly_id).is_some());
+    }
+
+    /// Whether the alert calls for a robot: enabled, severe enough, not yet
+    /// acknowledged, about a place on the pipeline, and not already handled
+    fn wants(&self, report: &AnomalyReport) -> bool {
+        self.config.enabled
+            && report.severity >= self.config.min_severity
+            && !report.acknowledged
+            && report.section_id != SYSTEM_SECTION
+            && !self.escalated.contains(&report.id)
+            && self.assigned_robot(&report.id).is_none()
+    }
+
+    /// Score every robot for the anomaly, eligible ones first, best to
+    /// worst. On top of the fleet's own exclusions, robots of the wrong type,
+    /// already assigned, or on a task that may not be interrupted are ruled
+    /// out.
+    pub fn evaluate(
+        &self,
+        report: &AnomalyReport,
+        fleet: &FleetManager,
+        zones: &ZoneRegistry,
+    ) -> Vec<CandidateEvaluation> {
+        let suited = suited_types(report.anomaly_type);
+        let mut candidates: Vec<_> = fleet
+            .evaluate_dispatch_candidates(&report.position, self.config.min_battery, zones)
+            .into_iter()
+            .map(|candidate| {
+                let Some(robot) = fleet.get_robot(&candidate.robot_id) else {
+                    return candidate;
+                };
+                let exclusion = if !suited.contains(&robot.robot_type) {
+                    ExclusionReason::WrongType
+                } else if !candidate.is_eligible() {
+                    return candidate;
+                } else if self.assignments.contains_key(robot.id.as_str()) {
+                    ExclusionReason::AlreadyAssigned
+                } else if !interruptible(&robot.current_task) {
+                    ExclusionReason::Busy
+                } else {
+                    return candidate;
+                };
+                CandidateEvaluation::excluded(robot.id.as_str(), exclusion)
+            })
+            .collect();
+        decision::rank(&mut candidates);
+        candidates
+    }
+
+    /// Pick a robot for the alert and assign it, or tell the engine to
+    /// escalate when none can go
+    pub fn plan(
+        &mut self,
+        report: &AnomalyReport,
+        fleet: &FleetManager,
+        zones: &ZoneRegistry,
+        now: u64,
+    ) -> DispatchPlan {
+        if !self.wants(report) {
+            return DispatchPlan::Skip;
+        }
+        let candidates = self.evaluate(report, fleet, zones);
+        let decision = Decision::new(PolicyKind::AutoDispatch, &report.id, candidates, now);
+        match decision.winner().map(|winner| winner.robot_id.clone()) {
+            Some(robot_id) => {
+                self.assignments.insert(robot_id.clone(), report.id.clone());
+                DispatchPlan::Send { robot_id, decision }
+            }
+            None => {
+                self.escalated.insert(report.id.clone());
+                DispatchPlan::Escalate { decision }
+            }
+        }
+    }
+}
+
+/// Alert raised when no robot could be sent to `report`, with why each
+/// candidate was ruled out
+pub fn escalation(report: &AnomalyReport, decision: &Decision) -> AnomalyReport {
+    let mut reasons: BTreeMap<String, usize> = BTreeMap::new();
+    for reason in decision.candidates.iter().filter_map(|c| c.excluded) {
+        *reasons.entry(reason.to_string()).or_default() += 1;
+    }
+    let why = if reasons.is_empty() {
+        "no robots known".to_string()
+    } else {
+        reasons
+            .iter()
+            .map(|(reason, count)| format!("{} {}", count, reason))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    AnomalyReport::new(
+        AnomalyType::Unknown,
+        report.severity,
+        report.position,
+        SYSTEM_SECTION,
+        ENGINE_ORIGIN,
+        1.0,
+        format!(
+            "No robot available to investigate {} ({:?} in {}): {}",
+            report.id, report.anomaly_type, report.section_id, why
+        ),
+    )
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use aetheris_shared::{Position, RobotState};
+
+    use super::*;
+    use crate::sections::{SectionRegistry, UnknownSectionPolicy};
+
+    const T0: u64 = 1_700_000_000_000;
+
+    fn robot(id: &str, robot_type: RobotType, x: f64) -> RobotState {
+        RobotState {
+            position: Position::new(x, 0.0, 0.0),
+            ..RobotState::new(id.parse().unwrap(), id, robot_type)
+        }
+    }
+
+    fn fleet(robots: impl IntoIterator<Item = RobotState>) -> FleetManager {
+        let fleet = FleetManager::new(Duration::from_secs(15));
+        for robot in robots {
+            fleet.update_robot(robot);
+        }
+        fleet
+    }
+
+    fn alert(anomaly_type: AnomalyType, severity: SeverityLevel) -> AnomalyReport {
+        AnomalyReport::new(
+            anomaly_type,
+            severity,
+            Position::origin(),
+            "PIPE-001",
+            "RV-001",
+            0.9,
+            "test",
+        )
+    }
+
+    fn exclusion(decision: &Decision, robot_id: &str) -> Option<ExclusionReason> {
+        decision
+            .candidates
+            .iter()
+            .find(|c| c.robot_id == robot_id)
+            .unwrap()
+            .excluded
+    }
+
+    fn sent(plan: DispatchPlan) -> (String, Decision) {
+        match plan {
+            DispatchPlan::Send { robot_id, decision } => (robot_id, decision),
+            other => panic!("expected a robot to be sent, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_nearest_robot_of_a_suited_type_is_sent() {
+        let fleet = fleet([
+            robot("RV-001", RobotType::Rover, 1.0),
+            robot("DR-001", RobotType::Drone, 2.0),
+            robot("CR-002", RobotType::Crawler, 9.0),
+            robot("CR-001", RobotType::Crawler, 4.0),
+        ]);
+        let mut dispatcher = AutoDispatcher::default();
+        let report = alert(AnomalyType::WallThinning, SeverityLevel::High);
+
+        let (robot_id, decision) =
+            sent(dispatcher.plan(&report, &fleet, &ZoneRegistry::default(), T0));
+        assert_eq!(robot_id, "CR-001");
+        assert_eq!(decision.trigger, report.id);
+        assert_eq!(decision.candidates[1].robot_id, "CR-002");
+        assert_eq!(
+            exclusion(&decision, "RV-001"),
+            Some(ExclusionReason::WrongType)
+        );
+        assert_eq!(
+            exclusion(&decision, "DR-001"),
+            Some(ExclusionReason::WrongType)
+        );
+        assert_eq!(dispatcher.assignment("CR-001"), Some(report.id.as_str()));
+
+        // Leaks go to the nearest surveyor, drone or rover
+        let report = alert(AnomalyType::Leak, SeverityLevel::Critical);
+        let (robot_id, _) = sent(dispatcher.plan(&report, &fleet, &ZoneRegistry::default(), T0));
+        assert_eq!(robot_id, "RV-001");
+    }
+
+    #[test]
+    fn test_unavailable_busy_and_drained_robots_are_passed_over() {
+        let mut offline = robot("DR-001", RobotType::Drone, 1.0);
+        offline.status = RobotStatus::Offline;
+        let mut repairing = robot("DR-002", RobotType::Drone, 2.0);
+        repairing.status = RobotStatus::Maintenance;
+        let mut drained = robot("DR-003", RobotType::Drone, 3.0);
+        drained.battery = 12.0;
+        let mut errand = robot("DR-004", RobotType::Drone, 4.0);
+        errand.status = RobotStatus::Active;
+        errand.current_task = CurrentTask::ReturningToBase;
+        let mut patrolling = robot("DR-005", RobotType::Drone, 20.0);
+        patrolling.status = RobotStatus::Active;
+        patrolling.current_task = CurrentTask::Patrolling {
+            route_id: "ROUTE-D1".into(),
+        };
+        let fleet = fleet([offline, repairing, drained, errand, patrolling]);
+        let mut dispatcher = AutoDispatcher::default();
+
+        let (robot_id, decision) = sent(dispatcher.plan(
+            &alert(AnomalyType::Leak, SeverityLevel::High),
+            &fleet,
+            &ZoneRegistry::default(),
+            T0,
+        ));
+        assert_eq!(robot_id, "DR-005");
+        assert_eq!(
+            exclusion(&decision, "DR-001"),
+            Some(ExclusionReason::Offline)
+        );
+        assert_eq!(
+            exclusion(&decision, "DR-002"),
+            Some(ExclusionReason::InMaintenance)
+        );
+        assert_eq!(
+            exclusion(&decision, "DR-003"),
+            Some(ExclusionReason::LowBattery)
+        );
+        assert_eq!(exclusion(&decision, "DR-004"), Some(ExclusionReason::Busy));
+        assert_eq!(decision.candidates[0].robot_id, "DR-005");
+    }
+
+    #[test]
+    fn test_anomalies_do_not_share_a_robot_and_escalate_when_none_is_left() {
+        let fleet = fleet([
+            robot("DR-001", RobotType::Drone, 1.0),
+            robot("DR-002", RobotType::Drone, 5.0),
+            robot("CR-001", RobotType::Crawler, 0.0),
+        ]);
+        let zones = ZoneRegistry::default();
+        let mut dispatcher = AutoDispatcher::default();
+
+        let first = alert(AnomalyType::Leak, SeverityLevel::High);
+        let second = alert(AnomalyType::Leak, SeverityLevel::High);
+        let third = alert(AnomalyType::Leak, SeverityLevel::Critical);
+        assert_eq!(
+            sent(dispatcher.plan(&first, &fleet, &zones, T0)).0,
+            "DR-001"
+        );
+        let (robot_id, decision) = sent(dispatcher.plan(&second, &fleet, &zones, T0));
+        assert_eq!(robot_id, "DR-002");
+        assert_eq!(
+            exclusion(&decision, "DR-001"),
+            Some(ExclusionReason::AlreadyAssigned)
+        );
+
+        let DispatchPlan::Escalate { decision } = dispatcher.plan(&third, &fleet, &zones, T0)
+        else {
+            panic!("expected an escalation");
+        };
+        assert!(decision.winner().is_none());
+        let escalation = escalation(&third, &decision);
+        assert_eq!(escalation.severity, SeverityLevel::Critical);
+        assert_eq!(escalation.section_id, SYSTEM_SECTION);
+        assert!(
+            escalation
+                .description
+                .ends_with("2 robot is already assigned, 1 robot type cannot handle this task"),
+            "{}",
+            escalation.description
+        );
+        // Escalated once; the escalation alert itself is never dispatched
+        assert_eq!(
+            dispatcher.plan(&third, &fleet, &zones, T0),
+            DispatchPlan::Skip
+        );
+        assert_eq!(
+            dispatcher.plan(&escalation, &fleet, &zones, T0),
+            DispatchPlan::Skip
+        );
+    }
+
+    #[test]
+    fn test_disabled_minor_and_handled_alerts_are_skipped() {
+        let fleet = fleet([robot("RV-001", RobotType::Rover, 1.0)]);
+        let zones = ZoneRegistry::default();
+        let mut disabled = AutoDispatcher::new(DispatchConfig {
+            enabled: false,
+            ..DispatchConfig::default()
+        });
+        let report = alert(AnomalyType::Crack, SeverityLevel::Critical);
+        assert_eq!(
+            disabled.plan(&report, &fleet, &zones, T0),
+            DispatchPlan::Skip
+        );
+
+        let mut dispatcher = AutoDispatcher::default();
+        let minor = alert(AnomalyType::Crack, SeverityLevel::Medium);
+        assert_eq!(
+            dispatcher.plan(&minor, &fleet, &zones, T0),
+            DispatchPlan::Skip
+        );
+        let mut acknowledged = report.clone();
+        acknowledged.acknowledged = true;
+        assert_eq!(
+            dispatcher.plan(&acknowledged, &fleet, &zones, T0),
+            DispatchPlan::Skip
+        );
+
+        assert_eq!(
+            sent(dispatcher.plan(&report, &fleet, &zones, T0)).0,
+            "RV-001"
+        );
+        // Re-released with a new severity or status, still the same anomaly
+        assert_eq!(
+            dispatcher.plan(&report, &fleet, &zones, T0),
+            DispatchPlan::Skip
+        );
+        assert_eq!(dispatcher.assigned_robot(&report.id), Some("RV-001"));
+    }
+
+    #[test]
+    fn test_robots_are_freed_when_their_anomaly_closes_or_they_drop_out() {
+        let fleet = fleet([
+            robot("RV-001", RobotType::Rover, 1.0),
+            robot("RV-002", RobotType::Rover, 2.0),
+        ]);
+        let zones = ZoneRegistry::default();
+        let sections = SectionRegistry::new(UnknownSectionPolicy::Provisional);
+        let mut anomalies = ActiveAnomalies::default();
+        let mut dispatcher = AutoDispatcher::default();
+
+        let closed = alert(AnomalyType::Crack, SeverityLevel::High);
+        let open = AnomalyReport {
+            section_id: "PIPE-002".into(),
+            ..alert(AnomalyType::Crack, SeverityLevel::High)
+        };
+        anomalies.ingest(open.clone(), &sections);
+        assert_eq!(
+            sent(dispatcher.plan(&closed, &fleet, &zones, T0)).0,
+            "RV-001"
+        );
+        assert_eq!(sent(dispatcher.plan(&open, &fleet, &zones, T0)).0, "RV-002");
+
+        dispatcher.release_finished(&fleet, &anomalies);
+        assert_eq!(dispatcher.assignment("RV-001"), None);
+        assert_eq!(dispatcher.assignment("RV-002"), Some(open.id.as_str()));
+
+        fleet.mark_offline("RV-002");
+        dispatcher.release_finished(&fleet, &anomalies);
+        assert_eq!(dispatcher.assigned_robot(&open.id), None);
+        // The anomaly can be dispatched again, to the robot still online
+        assert_eq!(sent(dispatcher.plan(&open, &fleet, &zones, T0)).0, "RV-001");
+    }
+}
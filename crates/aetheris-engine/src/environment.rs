@@ -0,0 +1,489 @@
+//! Simulated pipeline environment sensors
+//!
+//! Without real section sensors nothing publishes on the environment topics,
+//! so alongside the mock fleet the engine simulates one sensor station per
+//! pipeline section. Readings wander slowly around each section's operating
+//! point: pressure and flow move together on one slowly reverting
+//! disturbance and temperature on another, with measurement noise on every
+//! channel.
+//!
+//! [`Command::InjectLeak`] starts a hydrogen leak in a section. Its H2
+//! concentration ramps up and its pressure falls toward levels set by the
+//! leak's severity, and neighboring sections see a share of both. Once the
+//! concentration passes the alert level the engine's environment alarms
+//! raise a Leak report for the section. [`Command::ClearLeak`] seals the leak
+//! and the readings ramp back to normal at the same rates.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+use aetheris_shared::{
+    Command, PipeEnvironment, PipelineMap, Position, SeverityLevel, Timestamp, limits,
+};
+
+use crate::config::{CheckConfig, ConfigChecker};
+use crate::shutdown::Shutdown;
+use crate::simulation::publishing;
+use crate::{AetherisMqtt, ReceivedCommand};
+
+/// Operating pressure as a fraction of the design pressure
+const OPERATING_PRESSURE_FRACTION: f64 = 0.7;
+/// Mean gas velocity the nominal flow is derived from (m/s)
+const FLOW_VELOCITY: f64 = 1.0;
+/// Ambient temperature (Celsius)
+const AMBIENT_CELSIUS: f64 = 15.0;
+/// Trace hydrogen present without a leak (ppm)
+const BACKGROUND_H2_PPM: f64 = 40.0;
+const AMBIENT_HUMIDITY: f64 = 45.0;
+/// Time constant of the slow disturbances
+const DISTURBANCE_TAU_SECS: f64 = 300.0;
+/// Typical size of the pressure and flow disturbance, as a fraction of the
+/// nominal values
+const PROCESS_SPREAD: f64 = 0.01;
+/// Typical size of the temperature disturbance (Celsius)
+const THERMAL_SPREAD: f64 = 0.3;
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Simulated environment sensors and leak scenarios
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvironmentSimConfig {
+    /// Interval between readings of each section
+    pub interval: Duration,
+    /// How fast the H2 concentration of a leaking section changes (ppm per
+    /// second). The alert level must be reached within about a dozen
+    /// readings: a slower steady rise looks like a drifting sensor to the
+    /// engine's sensor health checks, which then hold the channel out of
+    /// hazard assessment.
+    pub h2_ramp_rate: f64,
+    /// How fast the pressure of a leaking section changes (bar per second)
+    pub pressure_ramp_rate: f64,
+    /// Share of a leak's effect seen by the neighboring sections
+    pub neighbor_share: f64,
+    /// Seed for the noise RNG so runs are reproducible
+    pub seed: u64,
+}
+
+impl Default for EnvironmentSimConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            h2_ramp_rate: 500.0,
+            pressure_ramp_rate: 2.0,
+            neighbor_share: 0.2,
+            seed: 0x0E4F_1EA4,
+        }
+    }
+}
+
+impl CheckConfig for EnvironmentSimConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        checker.positive("interval", self.interval);
+        for (path, rate) in [
+            ("h2_ramp_rate", self.h2_ramp_rate),
+            ("pressure_ramp_rate", self.pressure_ramp_rate),
+        ] {
+            if !(rate > 0.0 && rate.is_finite()) {
+                checker.error(path, format!("must be greater than zero, got {rate}"), None);
+            }
+        }
+        checker.fraction("neighbor_share", self.neighbor_share);
+    }
+}
+
+/// Where a leak of `severity` settles: H2 concentration above background as
+/// a multiple of the alert level, and pressure lost as a fraction of the
+/// operating pressure. Only an Info leak stays below the alert level.
+fn leak_levels(severity: SeverityLevel) -> (f64, f64) {
+    match severity {
+        SeverityLevel::Info => (0.5, 0.02),
+        SeverityLevel::Low => (1.25, 0.05),
+        SeverityLevel::Medium => (2.0, 0.1),
+        SeverityLevel::High => (4.0, 0.2),
+        SeverityLevel::Critical => (8.0, 0.4),
+    }
+}
+
+// ============================================================================
+// SIMULATOR
+// ============================================================================
+
+/// One section's sensor station
+#[derive(Debug)]
+struct SimSection {
+    id: String,
+    position: Position,
+    /// Indices of the neighboring sections
+    neighbors: Vec<usize>,
+    pressure: f64,
+    flow_rate: f64,
+    wall_thickness: f64,
+    /// Slow pressure and flow disturbance, as a fraction of nominal
+    process: f64,
+    /// Slow temperature disturbance (Celsius)
+    thermal: f64,
+    /// Hydrogen from leaks (ppm)
+    leaked_h2: f64,
+    /// Pressure lost to leaks (bar)
+    pressure_loss: f64,
+}
+
+/// Environment readings of every pipeline section, with leaks on demand
+#[derive(Debug)]
+pub struct EnvironmentSimulator {
+    config: EnvironmentSimConfig,
+    sections: Vec<SimSection>,
+    /// Severity of the active leak per section index
+    leaks: BTreeMap<usize, SeverityLevel>,
+    rng: StdRng,
+}
+
+impl EnvironmentSimulator {
+    pub fn new(pipeline: &PipelineMap, config: EnvironmentSimConfig) -> Self {
+        let ids: Vec<&str> = pipeline.sections().iter().map(|s| s.id.as_str()).collect();
+        let sections = pipeline
+            .sections()
+            .iter()
+            .map(|section| {
+                let radius_m = section.diameter_mm / 2000.0;
+                SimSection {
+                    id: section.id.clone(),
+                    position: section.point_at(0.5),
+                    neighbors: pipeline
+                        .neighbors(&section.id)
+                        .iter()
+                        .filter_map(|n| ids.iter().position(|id| *id == n.id))
+                        .collect(),
+                    pressure: section.design_pressure_bar * OPERATING_PRESSURE_FRACTION,
+                    flow_rate: std::f64::consts::PI * radius_m * radius_m * FLOW_VELOCITY * 3600.0,
+                    wall_thickness: section.diameter_mm / 50.0,
+                    process: 0.0,
+                    thermal: 0.0,
+                    leaked_h2: 0.0,
+                    pressure_loss: 0.0,
+                }
+            })
+            .collect();
+        Self {
+            rng: StdRng::seed_from_u64(config.seed),
+            config,
+            sections,
+            leaks: BTreeMap::new(),
+        }
+    }
+
+    fn index(&self, section_id: &str) -> Result<usize, String> {
+        self.sections
+            .iter()
+            .position(|s| s.id == section_id)
+            .ok_or_else(|| format!("unknown section {section_id}"))
+    }
+
+    /// Start or seal leaks; `Err` is the reason the command was ignored.
+    /// Other commands are not for the environment and are ignored quietly.
+    pub fn apply(&mut self, command: &Command) -> Result<(), String> {
+        match command {
+            Command::InjectLeak {
+                section_id,
+                severity,
+            } => {
+                let index = self.index(section_id)?;
+                self.leaks.insert(index, *severity);
+                info!(section_id = %section_id, severity = ?severity, "Simulated leak started");
+            }
+            Command::ClearLeak {
+                section_id: Some(section_id),
+            } => {
+                let index = self.index(section_id)?;
+                if self.leaks.remove(&index).is_none() {
+                    return Err(format!("no leak in {section_id}"));
+                }
+                info!(section_id = %section_id, "Simulated leak sealed");
+            }
+            Command::ClearLeak { section_id: None } => {
+                info!(leaks = self.leaks.len(), "Simulated leaks sealed");
+                self.leaks.clear();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Sections with an active leak, and its severity
+    pub fn leaks(&self) -> impl Iterator<Item = (&str, SeverityLevel)> {
+        self.leaks
+            .iter()
+            .map(|(index, severity)| (self.sections[*index].id.as_str(), *severity))
+    }
+
+    /// Hydrogen and pressure loss each section is heading for: the worst of
+    /// the leaks in it or next to it
+    fn leak_targets(&self) -> Vec<(f64, f64)> {
+        let mut targets = vec![(0.0, 0.0); self.sections.len()];
+        for (&index, &severity) in &self.leaks {
+            let (h2_multiple, loss_fraction) = leak_levels(severity);
+            let h2 = h2_multiple * limits::H2_ALERT_PPM;
+            let mut affect = |at: usize, share: f64| {
+                let target = &mut targets[at];
+                target.0 = f64::max(target.0, h2 * share);
+                target.1 = f64::max(target.1, loss_fraction * share);
+            };
+            affect(index, 1.0);
+            for &neighbor in &self.sections[index].neighbors {
+                affect(neighbor, self.config.neighbor_share);
+            }
+        }
+        targets
+    }
+
+    /// Advance every section by `dt` and read its sensors at `now` (Unix ms)
+    pub fn step(&mut self, dt: Duration, now: u64) -> Vec<PipeEnvironment> {
+        let secs = dt.as_secs_f64();
+        let targets = self.leak_targets();
+        // Exact decay of the disturbances over `dt`, whatever its length
+        let keep = (-secs / DISTURBANCE_TAU_SECS).exp();
+        let renew = (1.0 - keep * keep).sqrt();
+        let rng = &mut self.rng;
+        self.sections
+            .iter_mut()
+            .zip(targets)
+            .map(|(section, (h2_target, loss_fraction))| {
+                section.process = section.process * keep + PROCESS_SPREAD * renew * gaussian(rng);
+                section.thermal = section.thermal * keep + THERMAL_SPREAD * renew * gaussian(rng);
+                section.leaked_h2 = ramp(
+                    section.leaked_h2,
+                    h2_target,
+                    self.config.h2_ramp_rate * secs,
+                );
+                section.pressure_loss = ramp(
+                    section.pressure_loss,
+                    loss_fraction * section.pressure,
+                    self.config.pressure_ramp_rate * secs,
+                );
+
+                let pressure = section.pressure * (1.0 + section.process) - section.pressure_loss;
+                // Flow follows pressure, and drops with what leaks out
+                let flow = section.flow_rate * (1.0 + section.process)
+                    - section.flow_rate * section.pressure_loss / section.pressure;
+                PipeEnvironment {
+                    section_id: section.id.clone(),
+                    pressure: pressure + 0.05 * gaussian(rng),
+                    temperature: AMBIENT_CELSIUS + section.thermal + 0.05 * gaussian(rng),
+                    h2_concentration: (BACKGROUND_H2_PPM + section.leaked_h2 + 2.0 * gaussian(rng))
+                        .max(0.0),
+                    wall_thickness: section.wall_thickness + 0.01 * gaussian(rng),
+                    flow_rate: flow + 2.0 * gaussian(rng),
+                    humidity: (AMBIENT_HUMIDITY + 0.5 * gaussian(rng)).clamp(0.0, 100.0),
+                    position: section.position,
+                    timestamp: Timestamp::from_millis(now),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Move `value` toward `target` by at most `step`
+fn ramp(value: f64, target: f64, step: f64) -> f64 {
+    if value < target {
+        (value + step).min(target)
+    } else {
+        (value - step).max(target)
+    }
+}
+
+/// Standard normal sample (Box-Muller)
+fn gaussian(rng: &mut StdRng) -> f64 {
+    let u: f64 = rng.random_range(f64::EPSILON..1.0);
+    let v: f64 = rng.random();
+    (-2.0 * u.ln()).sqrt() * (std::f64::consts::TAU * v).cos()
+}
+
+// ============================================================================
+// SIMULATION TASK
+// ============================================================================
+
+/// Spawns the environment sensor publisher.
+///
+/// Leak commands sent on the returned channel take effect at the next
+/// reading; other commands are ignored. Readings are not published while
+/// the broker connection is down.
+pub fn spawn_environment_simulation(
+    mqtt: Arc<AetherisMqtt>,
+    mut simulator: EnvironmentSimulator,
+    mut shutdown: Shutdown,
+) -> (mpsc::Sender<ReceivedCommand>, JoinHandle<()>) {
+    let (command_tx, mut commands) = mpsc::channel::<ReceivedCommand>(16);
+    let task = tokio::spawn(async move {
+        let clock = mqtt.clock();
+        let period = simulator.config.interval;
+        let mut ticker = clock.interval(period);
+        loop {
+            tokio::select! {
+                Some(received) = commands.recv() => {
+                    if let Err(reason) = simulator.apply(&received.command) {
+                        warn!(command_id = %received.command_id, "Leak command ignored: {}", reason);
+                    }
+                    continue;
+                }
+                _ = ticker.tick() => {}
+                _ = shutdown.wait() => return,
+            }
+            let readings = simulator.step(period, clock.now().as_millis());
+            if !publishing(&mqtt) {
+                continue;
+            }
+            for reading in readings {
+                if let Err(e) = mqtt.publish_environment(&reading).await {
+                    error!(section_id = %reading.section_id, "Failed to publish environment data: {}", e);
+                }
+            }
+        }
+    });
+    (command_tx, task)
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_mock_pipeline;
+
+    fn simulator() -> EnvironmentSimulator {
+        let pipeline = PipelineMap::new(create_mock_pipeline(), 2.0);
+        EnvironmentSimulator::new(&pipeline, EnvironmentSimConfig::default())
+    }
+
+    fn leak(section_id: &str, severity: SeverityLevel) -> Command {
+        Command::InjectLeak {
+            section_id: section_id.into(),
+            severity,
+        }
+    }
+
+    fn reading<'a>(readings: &'a [PipeEnvironment], section_id: &str) -> &'a PipeEnvironment {
+        readings
+            .iter()
+            .find(|r| r.section_id == section_id)
+            .unwrap()
+    }
+
+    /// Every severity but Info raises the engine's leak alarm before its
+    /// sensor health checks could take the ramp for a drifting sensor
+    #[tokio::test]
+    async fn test_injected_leaks_raise_the_engine_leak_alarm() {
+        use aetheris_shared::{MqttMessage, topics};
+
+        use crate::MqttConfig;
+        use crate::alarms::{AlarmStatus, HazardKind};
+
+        for severity in [
+            SeverityLevel::Info,
+            SeverityLevel::Low,
+            SeverityLevel::Critical,
+        ] {
+            let (tx, mut rx) = mpsc::channel(16);
+            let (mqtt, _eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+            let mut simulator = simulator();
+            for tick in 0..120u64 {
+                if tick == 60 {
+                    simulator.apply(&leak("PIPE-003", severity)).unwrap();
+                }
+                let now = 1_700_000_000_000 + tick * 1_000;
+                for reading in simulator.step(Duration::from_secs(1), now) {
+                    let msg = MqttMessage::new(reading.clone(), &reading.section_id, tick);
+                    let payload = serde_json::to_vec(&msg).unwrap();
+                    mqtt.handle_incoming(&topics::environment(&reading.section_id), &payload)
+                        .await
+                        .unwrap();
+                    while rx.try_recv().is_ok() {}
+                }
+            }
+
+            let alarms = mqtt.alarms();
+            let alarms = alarms.read().await;
+            let alarm = alarms.get("PIPE-003", HazardKind::H2Concentration).unwrap();
+            if severity == SeverityLevel::Info {
+                assert_eq!(alarm.status, AlarmStatus::Cleared);
+                continue;
+            }
+            assert_eq!(alarm.status, AlarmStatus::Raised, "{severity:?} leak");
+        }
+    }
+
+    #[test]
+    fn test_quiet_pipeline_drifts_without_hazards() {
+        let mut simulator = simulator();
+        let mut pressures = Vec::new();
+        for tick in 0..600 {
+            let readings = simulator.step(Duration::from_secs(1), tick * 1_000);
+            assert_eq!(readings.len(), 4);
+            assert!(readings.iter().all(|r| !r.is_hazardous()));
+            pressures.push(reading(&readings, "PIPE-001").pressure);
+        }
+        // Around the 49 bar operating point, never stuck
+        let mean = pressures.iter().sum::<f64>() / pressures.len() as f64;
+        assert!((mean - 49.0).abs() < 2.0, "mean pressure {mean}");
+        assert!(pressures.windows(2).all(|w| w[0] != w[1]));
+    }
+
+    #[test]
+    fn test_leak_ramps_up_spreads_to_neighbors_and_recovers() {
+        let mut simulator = simulator();
+        simulator
+            .apply(&leak("PIPE-001", SeverityLevel::High))
+            .unwrap();
+        assert!(
+            simulator
+                .apply(&leak("PIPE-404", SeverityLevel::High))
+                .is_err()
+        );
+
+        let mut readings = Vec::new();
+        let mut hazardous_after = None;
+        for tick in 0..60 {
+            readings = simulator.step(Duration::from_secs(1), tick * 1_000);
+            if hazardous_after.is_none() && reading(&readings, "PIPE-001").is_hazardous() {
+                hazardous_after = Some(tick);
+            }
+        }
+        assert_eq!(hazardous_after, Some(7));
+        let source = reading(&readings, "PIPE-001");
+        assert!(source.h2_concentration > 15_000.0);
+        assert!(source.pressure < 42.0);
+        // PIPE-002 and PIPE-003 join PIPE-001; PIPE-004 does not
+        for neighbor in ["PIPE-002", "PIPE-003"] {
+            let h2 = reading(&readings, neighbor).h2_concentration;
+            assert!((3_000.0..3_500.0).contains(&h2), "{neighbor}: {h2} ppm");
+        }
+        assert!(reading(&readings, "PIPE-004").h2_concentration < 100.0);
+
+        simulator
+            .apply(&Command::ClearLeak { section_id: None })
+            .unwrap();
+        assert_eq!(simulator.leaks().count(), 0);
+        for tick in 60..120 {
+            readings = simulator.step(Duration::from_secs(1), tick * 1_000);
+        }
+        assert!(readings.iter().all(|r| !r.is_hazardous()));
+        assert!(reading(&readings, "PIPE-001").pressure > 46.0);
+        assert!(
+            simulator
+                .apply(&Command::ClearLeak {
+                    section_id: Some("PIPE-001".into())
+                })
+                .is_err()
+        );
+    }
+}
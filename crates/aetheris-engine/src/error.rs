@@ -0,0 +1,183 @@
+//! Engine errors
+//!
+//! Handling and publishing messages fails with an [`AetherisError`], whose
+//! category tells the event loop what to do: drop the message, reconnect,
+//! or stop. [`EngineError`] adds the startup and connection failures no
+//! retry can fix. The subsystems keep their own error types, which convert
+//! into the category that fits them here.
+
+use aetheris_shared::{AetherisError, EncodingError, ErrorKind, Recovery, ValidationError};
+use rumqttc::ClientError;
+use thiserror::Error;
+
+use crate::acks::AckError;
+use crate::anomalies::AssignmentError;
+use crate::bounds::OutOfBounds;
+use crate::command_queue::QueueFull;
+use crate::fanout::PublishError;
+use crate::ingest::ParseRejection;
+use crate::robot_config::RobotConfigLoadError;
+use crate::rollout::RolloutError;
+use crate::sections::SectionError;
+use crate::transport::{FatalConnectError, TlsFileError};
+use crate::zones::ZoneError;
+
+// ============================================================================
+// ENGINE ERROR
+// ============================================================================
+
+/// A failure of the engine's public operations
+#[derive(Debug, Error)]
+pub enum EngineError {
+    #[error(transparent)]
+    Message(#[from] AetherisError),
+    /// The broker refused us for a reason reconnecting will not change
+    #[error(transparent)]
+    Connect(#[from] FatalConnectError),
+    #[error(transparent)]
+    TlsFile(#[from] TlsFileError),
+    #[error(transparent)]
+    RobotConfigs(#[from] RobotConfigLoadError),
+}
+
+pub type Result<T, E = EngineError> = std::result::Result<T, E>;
+
+impl EngineError {
+    /// Category of a message failure; `None` for startup and connection
+    /// failures
+    pub fn kind(&self) -> Option<ErrorKind> {
+        match self {
+            Self::Message(e) => Some(e.kind()),
+            Self::Connect(_) | Self::TlsFile(_) | Self::RobotConfigs(_) => None,
+        }
+    }
+
+    pub fn recovery(&self) -> Recovery {
+        match self {
+            Self::Message(e) => e.recovery(),
+            Self::Connect(_) | Self::TlsFile(_) | Self::RobotConfigs(_) => Recovery::Shutdown,
+        }
+    }
+}
+
+/// Tags a failed MQTT client request with what it was for
+pub(crate) trait TransportContext<T> {
+    /// `action` completes "failed to ...", e.g. "publish telemetry"
+    fn transport(self, action: &str) -> Result<T>;
+}
+
+impl<T> TransportContext<T> for Result<T, ClientError> {
+    fn transport(self, action: &str) -> Result<T> {
+        self.map_err(|e| AetherisError::transport(action, e).into())
+    }
+}
+
+// ============================================================================
+// CATEGORIES
+// ============================================================================
+
+impl From<ParseRejection> for AetherisError {
+    fn from(rejection: ParseRejection) -> Self {
+        match rejection {
+            ParseRejection::Invalid(e) => Self::Validation(e),
+            rejection => Self::Serialization(rejection.to_string()),
+        }
+    }
+}
+
+impl From<PublishError> for AetherisError {
+    fn from(e: PublishError) -> Self {
+        match e {
+            PublishError::Failed(reason) => Self::transport("publish command", reason),
+            PublishError::Rejected(_)
+            | PublishError::Fenced(_)
+            | PublishError::Unsupported(_)
+            | PublishError::InvalidRobot(_)
+            | PublishError::RateLimited(_) => Self::Rejected(e.to_string()),
+        }
+    }
+}
+
+impl From<AckError> for AetherisError {
+    fn from(e: AckError) -> Self {
+        match e {
+            AckError::Publish(e) => e.into(),
+            e => Self::Rejected(e.to_string()),
+        }
+    }
+}
+
+/// Refusals by the engine's own state
+macro_rules! rejected {
+    ($($error:ty),* $(,)?) => {$(
+        impl From<$error> for AetherisError {
+            fn from(e: $error) -> Self {
+                Self::Rejected(e.to_string())
+            }
+        }
+    )*};
+}
+
+rejected!(
+    AssignmentError,
+    OutOfBounds,
+    QueueFull,
+    RolloutError,
+    SectionError,
+    ZoneError
+);
+
+/// Errors that reach [`EngineError`] through their category
+macro_rules! message_error {
+    ($($error:ty),* $(,)?) => {$(
+        impl From<$error> for EngineError {
+            fn from(e: $error) -> Self {
+                Self::Message(e.into())
+            }
+        }
+    )*};
+}
+
+message_error!(
+    AckError,
+    AssignmentError,
+    EncodingError,
+    OutOfBounds,
+    ParseRejection,
+    PublishError,
+    QueueFull,
+    RolloutError,
+    SectionError,
+    ValidationError,
+    ZoneError,
+    serde_json::Error,
+);
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subsystem_errors_land_in_their_category() {
+        let oversized: EngineError = ParseRejection::Oversized { size: 10, limit: 5 }.into();
+        assert_eq!(oversized.kind(), Some(ErrorKind::Serialization));
+        assert_eq!(oversized.recovery(), Recovery::Skip);
+
+        let invalid: EngineError =
+            ParseRejection::Invalid(ValidationError::Empty { field: "id".into() }).into();
+        assert_eq!(invalid.kind(), Some(ErrorKind::Validation));
+
+        let lost: EngineError = PublishError::Failed("connection reset".into()).into();
+        assert_eq!(lost.recovery(), Recovery::Reconnect);
+        let limited: EngineError = PublishError::RateLimited("RV-001".into()).into();
+        assert_eq!(limited.kind(), Some(ErrorKind::Rejected));
+
+        let refused: EngineError = FatalConnectError::Tls("bad certificate".into()).into();
+        assert_eq!(refused.kind(), None);
+        assert_eq!(refused.recovery(), Recovery::Shutdown);
+    }
+}
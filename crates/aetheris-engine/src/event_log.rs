@@ -0,0 +1,512 @@
+//! Forensic event log: every engine message appended to JSONL files
+//!
+//! Unlike the bounded in-memory [`EventLog`](crate::events::EventLog), this
+//! keeps the full trail on disk for after an incident: each
+//! [`EngineMessage`] becomes one [`LoggedEvent`] line, tagged with its
+//! receive time and the topic it arrived on. Files rotate by size and age,
+//! and only the newest `keep_files` are kept.
+//!
+//! Writing happens on a dedicated blocking thread fed by a bounded channel.
+//! [`EventLogger::log`] never waits: when the disk cannot keep up, events
+//! are dropped and counted instead of slowing the message processor.
+//!
+//! [`EventLogReader`] reads a file or a whole log directory back, skipping
+//! the truncated final line a crash can leave behind.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use aetheris_shared::topics::{self, Topic};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{error, warn};
+
+use crate::EngineMessage;
+use crate::config::{CheckConfig, ConfigChecker};
+use crate::reconnect::ConnectionState;
+
+/// File name prefix and extension of log files
+const FILE_PREFIX: &str = "events-";
+const FILE_EXTENSION: &str = ".jsonl";
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Where and how the event log is written
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventLogConfig {
+    /// Directory of the log files; no log when unset
+    pub directory: Option<PathBuf>,
+    /// A file is rotated once it grows past this size
+    pub rotate_bytes: u64,
+    /// A file is rotated once it has been written to this long
+    pub rotate_interval: Option<Duration>,
+    /// Files kept, the current one included; older ones are deleted
+    pub keep_files: usize,
+    /// Events waiting for the disk before new ones are dropped
+    pub queue: usize,
+}
+
+impl Default for EventLogConfig {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            rotate_bytes: 64 * 1024 * 1024,
+            rotate_interval: Some(Duration::from_secs(60 * 60)),
+            keep_files: 48,
+            queue: 4096,
+        }
+    }
+}
+
+impl CheckConfig for EventLogConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if self.rotate_bytes == 0 {
+            checker.error("rotate_bytes", "must be at least 1", None);
+        }
+        if let Some(interval) = self.rotate_interval {
+            checker.positive("rotate_interval", interval);
+        }
+        if self.keep_files == 0 {
+            checker.error(
+                "keep_files",
+                "must be at least 1",
+                Some("the file being written counts".into()),
+            );
+        }
+        if self.queue == 0 {
+            checker.error("queue", "must be at least 1", None);
+        }
+    }
+}
+
+// ============================================================================
+// LOGGED EVENTS
+// ============================================================================
+
+/// One line of the event log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoggedEvent {
+    /// Unix timestamp the engine handled the message (milliseconds)
+    #[serde(alias = "timestamp")]
+    pub received_at: u64,
+    /// Topic the message arrived on; unset for the engine's own events
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topic: Option<String>,
+    /// Kind of message, e.g. `telemetry` or `command`
+    #[serde(default)]
+    pub kind: String,
+    pub payload: Value,
+}
+
+impl LoggedEvent {
+    /// The log line for a message handled at `received_at`
+    pub fn from_message(message: &EngineMessage, received_at: u64) -> Self {
+        let (topic, kind, payload) = match message {
+            EngineMessage::TelemetryReceived(state) => (
+                Some(topics::telemetry(&state.id)),
+                "telemetry",
+                to_value(state),
+            ),
+            EngineMessage::HeartbeatReceived(heartbeat) => (
+                Some(topics::heartbeat(&heartbeat.robot_id)),
+                "heartbeat",
+                to_value(heartbeat),
+            ),
+            EngineMessage::AlertReceived(report) => {
+                (Some(topics::ALERTS.to_string()), "alert", to_value(report))
+            }
+            EngineMessage::AlertTriaged(report) => (None, "alert_triaged", to_value(report)),
+            EngineMessage::EnvironmentReceived(env) => (
+                Some(topics::environment(&env.section_id)),
+                "environment",
+                to_value(env),
+            ),
+            EngineMessage::CommandResponseReceived(response) => (
+                Some(topics::responses(&response.robot_id)),
+                "command_response",
+                to_value(response),
+            ),
+            EngineMessage::CommandReceived(received) => (
+                Some(
+                    Topic::Commands {
+                        target: received.target.clone(),
+                    }
+                    .to_string(),
+                ),
+                "command",
+                json!({
+                    "command_id": received.command_id,
+                    "source": received.source,
+                    "command": received.command,
+                }),
+            ),
+            EngineMessage::ConnectionStateChanged(state, broker) => (
+                None,
+                "connection_state",
+                json!({
+                    "connected": *state == ConnectionState::Connected,
+                    "broker": broker,
+                }),
+            ),
+            EngineMessage::BrokerSwitched(switch) => (
+                None,
+                "broker_switched",
+                json!({
+                    "from": switch.from,
+                    "to": switch.to,
+                    "reason": switch.reason.as_str(),
+                }),
+            ),
+            EngineMessage::RobotReconnected(robot_id, offline_for) => (
+                None,
+                "robot_reconnected",
+                json!({
+                    "robot_id": robot_id,
+                    "offline_for_ms": offline_for.as_millis() as u64,
+                }),
+            ),
+            EngineMessage::BroadcastCompleted(result) => (
+                Some(topics::BROADCAST_RESULTS.to_string()),
+                "broadcast_result",
+                to_value(result),
+            ),
+            EngineMessage::CommandStatusChanged(update) => {
+                (None, "command_status", to_value(update))
+            }
+        };
+        Self {
+            received_at,
+            topic,
+            kind: kind.to_string(),
+            payload,
+        }
+    }
+}
+
+fn to_value<T: Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+// ============================================================================
+// LOGGER
+// ============================================================================
+
+/// Handle the message processor logs through
+#[derive(Debug, Clone)]
+pub struct EventLogger {
+    tx: mpsc::Sender<LoggedEvent>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl EventLogger {
+    /// Queue a message for the disk; dropped and counted when the queue is
+    /// full
+    pub fn log(&self, message: &EngineMessage) {
+        let event = LoggedEvent::from_message(message, aetheris_shared::current_timestamp_ms());
+        if self.tx.try_send(event).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Events dropped because the writer fell behind or stopped
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// Starts the writer for `config.directory`. It runs until every
+/// [`EventLogger`] is dropped, then flushes and returns.
+pub fn spawn_event_log(config: EventLogConfig) -> io::Result<(EventLogger, JoinHandle<()>)> {
+    let directory = config
+        .directory
+        .clone()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no log directory"))?;
+    let mut writer = RotatingWriter::open(directory, &config)?;
+    let (tx, mut rx) = mpsc::channel::<LoggedEvent>(config.queue);
+    let task = tokio::task::spawn_blocking(move || {
+        while let Some(event) = rx.blocking_recv() {
+            let mut written = writer.write(&event);
+            // Flush once the burst is written, not after every line
+            while written.is_ok()
+                && let Ok(event) = rx.try_recv()
+            {
+                written = writer.write(&event);
+            }
+            if let Err(e) = written.and_then(|()| writer.flush()) {
+                error!("Event log write failed, stopping the log: {}", e);
+                return;
+            }
+        }
+        if let Err(e) = writer.flush() {
+            error!("Event log flush failed: {}", e);
+        }
+    });
+    let logger = EventLogger {
+        tx,
+        dropped: Arc::new(AtomicU64::new(0)),
+    };
+    Ok((logger, task))
+}
+
+/// The file being written, rotated by size and age
+struct RotatingWriter {
+    directory: PathBuf,
+    rotate_bytes: u64,
+    rotate_interval: Option<Duration>,
+    keep_files: usize,
+    /// Run id and index of the next file, which keep file names in order
+    run: u64,
+    index: u32,
+    file: BufWriter<File>,
+    written: u64,
+    opened: Instant,
+}
+
+impl RotatingWriter {
+    fn open(directory: PathBuf, config: &EventLogConfig) -> io::Result<Self> {
+        fs::create_dir_all(&directory)?;
+        let run = aetheris_shared::current_timestamp_ms();
+        let file = create_file(&directory, run, 0)?;
+        let writer = Self {
+            directory,
+            rotate_bytes: config.rotate_bytes,
+            rotate_interval: config.rotate_interval,
+            keep_files: config.keep_files,
+            run,
+            index: 1,
+            file,
+            written: 0,
+            opened: Instant::now(),
+        };
+        writer.prune()?;
+        Ok(writer)
+    }
+
+    fn write(&mut self, event: &LoggedEvent) -> io::Result<()> {
+        if self.written >= self.rotate_bytes
+            || self
+                .rotate_interval
+                .is_some_and(|interval| self.opened.elapsed() >= interval)
+        {
+            self.rotate()?;
+        }
+        let mut line = serde_json::to_vec(event).map_err(io::Error::other)?;
+        line.push(b'\n');
+        self.file.write_all(&line)?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        self.file = create_file(&self.directory, self.run, self.index)?;
+        self.index += 1;
+        self.written = 0;
+        self.opened = Instant::now();
+        self.prune()
+    }
+
+    /// Delete the oldest files beyond `keep_files`
+    fn prune(&self) -> io::Result<()> {
+        let files = log_files(&self.directory)?;
+        for old in files
+            .iter()
+            .take(files.len().saturating_sub(self.keep_files))
+        {
+            if let Err(e) = fs::remove_file(old) {
+                warn!(path = %old.display(), "Failed to delete old event log: {}", e);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn create_file(directory: &Path, run: u64, index: u32) -> io::Result<BufWriter<File>> {
+    let path = directory.join(format!("{FILE_PREFIX}{run:013}-{index:05}{FILE_EXTENSION}"));
+    let file = OpenOptions::new()
+        .create_new(true)
+        .append(true)
+        .open(path)?;
+    Ok(BufWriter::new(file))
+}
+
+/// Log files in a directory, oldest first
+fn log_files(directory: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(FILE_PREFIX) && name.ends_with(FILE_EXTENSION))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+// ============================================================================
+// READER
+// ============================================================================
+
+/// Events read back from a log file, or from every file of a log directory
+/// in order
+pub struct EventLogReader {
+    files: std::vec::IntoIter<PathBuf>,
+    lines: Option<std::iter::Peekable<io::Lines<BufReader<File>>>>,
+    path: PathBuf,
+}
+
+impl EventLogReader {
+    pub fn iter(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let files = if path.is_dir() {
+            log_files(path)?
+        } else {
+            vec![path.to_path_buf()]
+        };
+        Ok(Self {
+            files: files.into_iter(),
+            lines: None,
+            path: PathBuf::new(),
+        })
+    }
+}
+
+impl Iterator for EventLogReader {
+    type Item = LoggedEvent;
+
+    fn next(&mut self) -> Option<LoggedEvent> {
+        loop {
+            let Some(lines) = &mut self.lines else {
+                self.path = self.files.next()?;
+                match File::open(&self.path) {
+                    Ok(file) => self.lines = Some(BufReader::new(file).lines().peekable()),
+                    Err(e) => warn!(path = %self.path.display(), "Cannot read event log: {}", e),
+                }
+                continue;
+            };
+            let line = match lines.next() {
+                Some(Ok(line)) => line,
+                Some(Err(e)) => {
+                    warn!(path = %self.path.display(), "Event log read failed: {}", e);
+                    self.lines = None;
+                    continue;
+                }
+                None => {
+                    self.lines = None;
+                    continue;
+                }
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(event) => return Some(event),
+                // A crash mid-write leaves a partial last line
+                Err(_) if lines.peek().is_none() => {}
+                Err(e) => {
+                    warn!(path = %self.path.display(), "Skipping corrupt event log line: {}", e)
+                }
+            }
+        }
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetheris_shared::{Command, RobotState, RobotType, topics::CommandTarget};
+
+    use crate::ReceivedCommand;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "aetheris-event-log-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn telemetry(battery: f64) -> EngineMessage {
+        let mut state = RobotState::new("RV-001".parse().unwrap(), "Rover", RobotType::Rover);
+        state.battery = battery;
+        EngineMessage::TelemetryReceived(state)
+    }
+
+    #[tokio::test]
+    async fn test_log_rotates_and_keeps_the_newest_files() {
+        let dir = temp_dir("rotate");
+        let config = EventLogConfig {
+            directory: Some(dir.clone()),
+            rotate_bytes: 1,
+            keep_files: 3,
+            ..EventLogConfig::default()
+        };
+        let (logger, writer) = spawn_event_log(config).unwrap();
+        for battery in 0..5 {
+            logger.log(&telemetry(battery as f64));
+        }
+        logger.log(&EngineMessage::CommandReceived(ReceivedCommand {
+            command: Command::EmergencyStop,
+            source: "dashboard".into(),
+            target: CommandTarget::Robot("RV-001".parse().unwrap()),
+            command_id: "cmd-1".into(),
+            expires_at: None,
+        }));
+        drop(logger);
+        writer.await.unwrap();
+
+        // One event per file: the oldest three were rotated away
+        assert_eq!(log_files(&dir).unwrap().len(), 3);
+        let events: Vec<_> = EventLogReader::iter(&dir).unwrap().collect();
+        let kinds: Vec<_> = events.iter().map(|e| e.kind.as_str()).collect();
+        assert_eq!(kinds, ["telemetry", "telemetry", "command"]);
+        assert_eq!(events[1].payload["battery"], 4.0);
+        assert_eq!(events[2].topic.as_deref(), Some("aetheris/commands/RV-001"));
+        assert_eq!(events[2].payload["command"]["command"], "emergency_stop");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reader_skips_a_truncated_final_line() {
+        let dir = temp_dir("truncated");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("crashed.jsonl");
+        let line = |battery| {
+            serde_json::to_string(&LoggedEvent::from_message(&telemetry(battery), 1_000)).unwrap()
+        };
+        let complete = format!("{}\n{{\"oops\n{}\n", line(1.0), line(2.0));
+        let truncated = &line(3.0)[..40];
+        fs::write(&path, format!("{complete}{truncated}")).unwrap();
+
+        let events: Vec<_> = EventLogReader::iter(&path).unwrap().collect();
+        let batteries: Vec<_> = events
+            .iter()
+            .map(|e| e.payload["battery"].clone())
+            .collect();
+        assert_eq!(batteries, [json!(1.0), json!(2.0)]);
+        assert_eq!(
+            events[0].topic.as_deref(),
+            Some("aetheris/telemetry/RV-001")
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
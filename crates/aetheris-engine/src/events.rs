@@ -0,0 +1,178 @@
+//! Engine event log
+//!
+//! Bounded, time-ordered log of what the engine did or noticed beyond the
+//! telemetry, alerts, and commands it relays: a command passing the command
+//! topics, a robot going silent, a payload being quarantined, an anomaly
+//! changing hands. Events that
+//! stem from a command carry its audit id so consumers such as the
+//! [`timeline`](crate::timeline) can fold the two records together.
+
+use std::collections::VecDeque;
+
+use serde::Serialize;
+
+// ============================================================================
+// EVENTS
+// ============================================================================
+
+/// What a [`SystemEvent`] records
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SystemEventKind {
+    /// A command was seen on the command topics
+    CommandIssued,
+    /// A robot missed its heartbeat deadline
+    RobotOffline,
+    /// A robot marked offline was heard from again
+    RobotReconnected,
+    /// A message was refused and published on the dead-letter topic
+    DeadLetter,
+    /// A zone's operational mode was set or expired
+    ZoneModeChanged,
+    /// A command was refused by a zone mode or a geofence
+    CommandRejected,
+    /// A move was cut short before a geofence
+    CommandClamped,
+    /// An anomaly was assigned or reassigned, or its assignment progressed
+    AssignmentChanged,
+    /// An assignment passed its due time without being done
+    AssignmentOverdue,
+    /// An anomaly was acknowledged or sent for investigation
+    AnomalyStatusChanged,
+    /// An anomaly was resolved and left the active set
+    AnomalyResolved,
+    /// An anomaly nobody acknowledged was raised a severity level
+    AlertEscalated,
+    /// A command held for a weak-link robot expired or was discarded
+    CommandDropped,
+    /// A sent command got no response within the acknowledgment timeout
+    CommandUnacknowledged,
+    /// A section sensor channel became suspect or was restored
+    SensorHealthChanged,
+    /// A declared robot was not heard from within its shift's grace period
+    ExpectedRobotMissing,
+    /// A declared robot that was missing was heard from
+    ExpectedRobotArrived,
+    /// A robot not declared in the expected fleet was heard from
+    UnexpectedRobot,
+    /// The auto-dispatch policy sent a robot to investigate an anomaly
+    RobotDispatched,
+    /// The battery manager sent a robot back to charge
+    RobotRecalled,
+    /// Another engine instance took the lead, or this one did
+    LeadershipChanged,
+    /// A simulated robot left the world bounds and was halted
+    RobotHalted,
+    /// A fault was injected into a simulated robot
+    FaultStarted,
+    /// A simulated fault moved on: a sensor reboot began, or a motor came
+    /// back at reduced speed
+    FaultProgressed,
+    /// A simulated fault recovered or was cleared
+    FaultCleared,
+    /// A configuration rollout started
+    RolloutStarted,
+    /// A rollout batch survived its soak period
+    RolloutBatchPassed,
+    /// A rollout exceeded its failure threshold and stopped
+    RolloutHalted,
+    /// A robot was sent back the configuration it had before a rollout
+    RolloutReverted,
+    /// Every batch of a rollout passed
+    RolloutCompleted,
+}
+
+/// One engine event
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SystemEvent {
+    pub id: String,
+    pub kind: SystemEventKind,
+    /// Robot, section, anomaly, or source the event is about
+    pub subject: Option<String>,
+    /// Audit id of the command the event stems from
+    pub command_id: Option<String>,
+    /// Id of the policy [`Decision`](crate::decision::Decision) behind the
+    /// event
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decision_id: Option<String>,
+    /// Human-readable detail
+    pub detail: String,
+    /// Unix timestamp (milliseconds)
+    pub timestamp: u64,
+}
+
+impl SystemEvent {
+    pub fn new(
+        kind: SystemEventKind,
+        subject: Option<&str>,
+        detail: impl Into<String>,
+        timestamp: u64,
+    ) -> Self {
+        Self {
+            id: format!("EVT-{}", uuid::Uuid::new_v4().simple()),
+            kind,
+            subject: subject.map(String::from),
+            command_id: None,
+            decision_id: None,
+            detail: detail.into(),
+            timestamp,
+        }
+    }
+
+    /// Link the event to the command it stems from
+    pub fn for_command(mut self, command_id: impl Into<String>) -> Self {
+        self.command_id = Some(command_id.into());
+        self
+    }
+
+    /// Link the event to the policy decision that caused it
+    pub fn for_decision(mut self, decision_id: impl Into<String>) -> Self {
+        self.decision_id = Some(decision_id.into());
+        self
+    }
+}
+
+// ============================================================================
+// LOG
+// ============================================================================
+
+/// Bounded log of recent events, oldest first
+#[derive(Debug)]
+pub struct EventLog {
+    events: VecDeque<SystemEvent>,
+    capacity: usize,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        Self::new(1000)
+    }
+}
+
+impl EventLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            events: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    pub fn record(&mut self, event: SystemEvent) {
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &SystemEvent> {
+        self.events.iter()
+    }
+}
@@ -0,0 +1,422 @@
+//! Expected fleet declaration and missing-robot alarms
+//!
+//! The fleet manager only knows robots it has heard from, so a rover that
+//! never powers on is invisible. Operations declares which robots should be
+//! on shift; each is pre-registered as offline, and a Medium alert is raised
+//! when one has neither sent a heartbeat nor published telemetry within the
+//! grace period after its shift starts. The alert is cleared when the robot
+//! arrives. Robots heard from but not declared are reported once, since they
+//! are usually a typo in the declaration or in the robot's own config.
+//!
+//! Shift windows are local times of day, converted with a fixed UTC offset;
+//! a window whose end is before its start runs past midnight. A robot with
+//! no windows is expected from engine startup.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use aetheris_shared::{
+    AnomalyReport, AnomalyType, Position, RobotId, RobotType, SeverityLevel, Timestamp,
+};
+
+use crate::anomalies::{ENGINE_ORIGIN, SYSTEM_SECTION};
+use crate::config::{CheckConfig, ConfigChecker};
+
+const MINUTE_MS: u64 = 60_000;
+const DAY_MINUTES: u16 = 24 * 60;
+const DAY_MS: u64 = DAY_MINUTES as u64 * MINUTE_MS;
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Local time of day, written "HH:MM"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeOfDay {
+    minutes: u16,
+}
+
+impl TimeOfDay {
+    pub fn new(hour: u16, minute: u16) -> Option<Self> {
+        (hour < 24 && minute < 60).then_some(Self {
+            minutes: hour * 60 + minute,
+        })
+    }
+
+    /// Minutes after local midnight
+    pub fn minutes(&self) -> u16 {
+        self.minutes
+    }
+}
+
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.minutes / 60, self.minutes % 60)
+    }
+}
+
+impl FromStr for TimeOfDay {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (hour, minute) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected HH:MM, got \"{s}\""))?;
+        let parse = |part: &str| part.parse::<u16>().map_err(|_| format!("bad time \"{s}\""));
+        Self::new(parse(hour)?, parse(minute)?).ok_or_else(|| format!("no such time \"{s}\""))
+    }
+}
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<TimeOfDay> for String {
+    fn from(time: TimeOfDay) -> Self {
+        time.to_string()
+    }
+}
+
+/// Daily window a robot is on shift
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ShiftWindow {
+    pub start: TimeOfDay,
+    /// Before `start` for a shift running past midnight
+    pub end: TimeOfDay,
+}
+
+impl ShiftWindow {
+    /// Start of the occurrence of this window containing local time
+    /// `local_ms`, if any
+    fn start_containing(&self, local_ms: u64) -> Option<u64> {
+        let midnight = local_ms - local_ms % DAY_MS;
+        let minute = ((local_ms % DAY_MS) / MINUTE_MS) as u16;
+        let (start, end) = (self.start.minutes, self.end.minutes);
+        let start_ms = start as u64 * MINUTE_MS;
+        if start < end {
+            (start..end)
+                .contains(&minute)
+                .then_some(midnight + start_ms)
+        } else if minute >= start {
+            Some(midnight + start_ms)
+        } else if minute < end {
+            // Started yesterday evening
+            (midnight + start_ms).checked_sub(DAY_MS)
+        } else {
+            None
+        }
+    }
+}
+
+/// A robot operations expects to be running
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExpectedRobot {
+    pub id: RobotId,
+    pub robot_type: RobotType,
+    /// Windows the robot is on shift; empty means always
+    #[serde(default)]
+    pub shifts: Vec<ShiftWindow>,
+}
+
+/// The declared fleet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExpectedFleetConfig {
+    pub robots: Vec<ExpectedRobot>,
+    /// Time after a shift starts for a robot to be heard from
+    #[serde(with = "crate::config::duration_secs")]
+    pub grace: Duration,
+    /// Offset of the shift windows' local time from UTC, in minutes
+    pub utc_offset_minutes: i32,
+}
+
+impl Default for ExpectedFleetConfig {
+    fn default() -> Self {
+        Self {
+            robots: Vec::new(),
+            grace: Duration::from_secs(300),
+            utc_offset_minutes: 0,
+        }
+    }
+}
+
+impl CheckConfig for ExpectedFleetConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        let mut seen = HashSet::new();
+        for (i, robot) in self.robots.iter().enumerate() {
+            if robot.id.trim().is_empty() {
+                checker.error(&format!("robots[{i}].id"), "must not be empty", None);
+            } else if !seen.insert(robot.id.as_str()) {
+                checker.error(
+                    &format!("robots[{i}].id"),
+                    format!("{} is declared more than once", robot.id),
+                    None,
+                );
+            }
+            for (j, shift) in robot.shifts.iter().enumerate() {
+                if shift.start == shift.end {
+                    checker.error(
+                        &format!("robots[{i}].shifts[{j}]"),
+                        "start and end are the same time",
+                        Some("omit shifts for a robot that is always expected".into()),
+                    );
+                }
+            }
+        }
+        checker.positive("grace", self.grace);
+        if self.utc_offset_minutes.abs() > 14 * 60 {
+            checker.error(
+                "utc_offset_minutes",
+                format!("{} is not a UTC offset", self.utc_offset_minutes),
+                Some("offsets range from -840 to +840 minutes".into()),
+            );
+        }
+    }
+}
+
+// ============================================================================
+// TRACKING
+// ============================================================================
+
+/// What hearing from a robot meant
+#[derive(Debug, Clone, PartialEq)]
+pub enum Arrival {
+    /// A declared robot; `cleared` is its missing alert, if one was raised
+    Expected { cleared: Option<String> },
+    /// Not declared; `first` is set the first time it is heard from
+    Unexpected { first: bool },
+}
+
+/// Arrival tracking for the declared fleet
+#[derive(Debug, Default)]
+pub struct ExpectedFleet {
+    config: ExpectedFleetConfig,
+    started_at: u64,
+    last_heard: HashMap<String, u64>,
+    /// Open missing alert id per robot
+    missing: HashMap<String, String>,
+    /// Shift start already alerted per robot, kept after the alert clears
+    alerted: HashMap<String, u64>,
+    unexpected: HashSet<String>,
+}
+
+impl ExpectedFleet {
+    /// Track `config` for an engine started at `started_at` (Unix ms)
+    pub fn new(config: ExpectedFleetConfig, started_at: u64) -> Self {
+        Self {
+            config,
+            started_at,
+            ..Self::default()
+        }
+    }
+
+    pub fn robots(&self) -> &[ExpectedRobot] {
+        &self.config.robots
+    }
+
+    pub fn is_expected(&self, robot_id: &str) -> bool {
+        self.config.robots.iter().any(|r| r.id == robot_id)
+    }
+
+    /// Start of the robot's current shift at `now`, `None` when off shift
+    pub fn shift_start(&self, robot: &ExpectedRobot, now: u64) -> Option<u64> {
+        if robot.shifts.is_empty() {
+            return Some(self.started_at);
+        }
+        let offset_ms = self.config.utc_offset_minutes as i64 * MINUTE_MS as i64;
+        let local = now.checked_add_signed(offset_ms)?;
+        robot
+            .shifts
+            .iter()
+            .filter_map(|shift| shift.start_containing(local))
+            .max()
+            .and_then(|start| start.checked_add_signed(-offset_ms))
+    }
+
+    /// Record a heartbeat or telemetry from `robot_id` at `now`
+    pub fn observe(&mut self, robot_id: &str, now: u64) -> Arrival {
+        if !self.is_expected(robot_id) {
+            return Arrival::Unexpected {
+                first: !self.config.robots.is_empty()
+                    && self.unexpected.insert(robot_id.to_string()),
+            };
+        }
+        self.last_heard.insert(robot_id.to_string(), now);
+        Arrival::Expected {
+            cleared: self.missing.remove(robot_id),
+        }
+    }
+
+    /// Alerts for robots not heard from within the grace period of their
+    /// current shift, once per shift
+    pub fn check(&mut self, now: u64) -> Vec<AnomalyReport> {
+        let grace = self.config.grace.as_millis() as u64;
+        let mut alerts = Vec::new();
+        for robot in &self.config.robots {
+            let Some(start) = self.shift_start(robot, now) else {
+                continue;
+            };
+            let heard = self
+                .last_heard
+                .get(robot.id.as_str())
+                .is_some_and(|&at| at >= start);
+            if heard || now < start + grace || self.alerted.get(robot.id.as_str()) == Some(&start) {
+                continue;
+            }
+            let report = AnomalyReport {
+                timestamp: Timestamp::from_millis(now),
+                ..AnomalyReport::new(
+                    AnomalyType::Unknown,
+                    SeverityLevel::Medium,
+                    Position::origin(),
+                    SYSTEM_SECTION,
+                    ENGINE_ORIGIN,
+                    1.0,
+                    format!(
+                        "Expected {} {} has not reported {} min into its shift",
+                        robot.robot_type.as_str(),
+                        robot.id,
+                        (now - start) / MINUTE_MS
+                    ),
+                )
+            };
+            self.alerted.insert(robot.id.to_string(), start);
+            self.missing.insert(robot.id.to_string(), report.id.clone());
+            alerts.push(report);
+        }
+        alerts
+    }
+
+    /// Robots with an open missing alert
+    pub fn missing(&self) -> Vec<&str> {
+        let mut ids: Vec<&str> = self.missing.keys().map(String::as_str).collect();
+        ids.sort_unstable();
+        ids
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2026-01-01T00:00:00Z
+    const MIDNIGHT: u64 = 1_767_225_600_000;
+    const HOUR: u64 = 60 * MINUTE_MS;
+
+    fn shift(start: &str, end: &str) -> ShiftWindow {
+        ShiftWindow {
+            start: start.parse().unwrap(),
+            end: end.parse().unwrap(),
+        }
+    }
+
+    fn fleet(utc_offset_minutes: i32) -> ExpectedFleet {
+        ExpectedFleet::new(
+            ExpectedFleetConfig {
+                robots: vec![
+                    ExpectedRobot {
+                        id: "RV-001".parse().unwrap(),
+                        robot_type: RobotType::Rover,
+                        shifts: vec![shift("08:00", "16:00")],
+                    },
+                    ExpectedRobot {
+                        id: "CR-001".parse().unwrap(),
+                        robot_type: RobotType::Crawler,
+                        shifts: vec![shift("22:00", "06:00")],
+                    },
+                ],
+                utc_offset_minutes,
+                ..ExpectedFleetConfig::default()
+            },
+            MIDNIGHT,
+        )
+    }
+
+    #[test]
+    fn test_missing_alarm_after_grace_and_cleared_on_arrival() {
+        let mut expected = fleet(0);
+        // Off shift: nothing expected of the rover yet
+        assert!(expected.check(MIDNIGHT + 7 * HOUR).is_empty());
+        // Within the grace period
+        assert!(
+            expected
+                .check(MIDNIGHT + 8 * HOUR + 4 * MINUTE_MS)
+                .is_empty()
+        );
+
+        let alerts = expected.check(MIDNIGHT + 8 * HOUR + 5 * MINUTE_MS);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].severity, SeverityLevel::Medium);
+        assert!(alerts[0].description.contains("RV-001"));
+        // Raised once per shift
+        assert!(expected.check(MIDNIGHT + 9 * HOUR).is_empty());
+        assert_eq!(expected.missing(), ["RV-001"]);
+
+        let arrival = expected.observe("RV-001", MIDNIGHT + 9 * HOUR);
+        assert_eq!(
+            arrival,
+            Arrival::Expected {
+                cleared: Some(alerts[0].id.clone())
+            }
+        );
+        assert!(expected.missing().is_empty());
+        assert!(expected.check(MIDNIGHT + 10 * HOUR).is_empty());
+
+        // Heard from yesterday does not count for today's shift
+        let next_day = MIDNIGHT + 24 * HOUR + 8 * HOUR + 6 * MINUTE_MS;
+        assert_eq!(expected.check(next_day).len(), 1);
+    }
+
+    #[test]
+    fn test_overnight_shift_and_utc_offset() {
+        let expected = fleet(0);
+        let crawler = &expected.robots()[1];
+        // 03:00 belongs to the shift that started at 22:00 the day before
+        assert_eq!(
+            expected.shift_start(crawler, MIDNIGHT + 3 * HOUR),
+            Some(MIDNIGHT - 2 * HOUR)
+        );
+        assert_eq!(expected.shift_start(crawler, MIDNIGHT + 12 * HOUR), None);
+
+        // At UTC+2, the 08:00 local shift starts at 06:00 UTC
+        let expected = fleet(120);
+        let rover = &expected.robots()[0];
+        assert_eq!(
+            expected.shift_start(rover, MIDNIGHT + 7 * HOUR),
+            Some(MIDNIGHT + 6 * HOUR)
+        );
+    }
+
+    #[test]
+    fn test_unexpected_robot_reported_once() {
+        let mut expected = fleet(0);
+        assert_eq!(
+            expected.observe("RV-01", MIDNIGHT),
+            Arrival::Unexpected { first: true }
+        );
+        assert_eq!(
+            expected.observe("RV-01", MIDNIGHT + 1),
+            Arrival::Unexpected { first: false }
+        );
+
+        // Without a declaration nobody is unexpected
+        let mut undeclared = ExpectedFleet::default();
+        assert_eq!(
+            undeclared.observe("RV-001", MIDNIGHT),
+            Arrival::Unexpected { first: false }
+        );
+    }
+}
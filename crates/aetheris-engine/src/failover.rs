@@ -0,0 +1,305 @@
+//! Failover between brokers
+//!
+//! A site may run a backup broker next to the primary one. The engine
+//! connects to the endpoints in order of preference: after
+//! `max_failures` consecutive failed attempts on one it moves to the next,
+//! wrapping around after the last. While on a backup it probes the
+//! preferred endpoints every `failback_interval` with a plain TCP connect,
+//! and moves back to the first that answers. A clean session on the new
+//! broker holds none of our subscriptions, so the usual resubscribe on
+//! ConnAck covers a switch. Publishes made meanwhile wait in the
+//! [`OfflineBuffer`](crate::offline_buffer::OfflineBuffer).
+
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::config::{CheckConfig, ConfigChecker};
+use crate::transport::{Secret, TlsConfig};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// A broker the engine may connect to. Credentials and TLS left out are
+/// those of the primary broker.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrokerEndpoint {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<Secret>,
+    pub tls: Option<TlsConfig>,
+}
+
+impl BrokerEndpoint {
+    pub fn new(host: impl Into<String>, port: u16) -> Self {
+        Self {
+            host: host.into(),
+            port,
+            username: None,
+            password: None,
+            tls: None,
+        }
+    }
+
+    /// `host:port`, as shown in logs and messages
+    pub fn address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+impl CheckConfig for BrokerEndpoint {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if self.host.trim().is_empty() {
+            checker.error("host", "must not be empty", None);
+        }
+        if self.port == 0 {
+            checker.error("port", "must be a valid port", None);
+        }
+        if self.password.is_some() && self.username.is_none() {
+            checker.error("password", "is set without a username", None);
+        }
+        if let Some(tls) = &self.tls {
+            checker.check_section("tls", tls);
+        }
+    }
+}
+
+/// When to switch brokers
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailoverConfig {
+    /// Consecutive failed connection attempts before moving to the next
+    /// endpoint
+    pub max_failures: u32,
+    /// How often a preferred endpoint is probed while on a backup
+    pub failback_interval: Duration,
+    /// How long a probe waits for the TCP connection
+    pub probe_timeout: Duration,
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        Self {
+            max_failures: 3,
+            failback_interval: Duration::from_secs(60),
+            probe_timeout: Duration::from_secs(2),
+        }
+    }
+}
+
+impl CheckConfig for FailoverConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if self.max_failures == 0 {
+            checker.error("max_failures", "must be at least 1", None);
+        }
+        checker.positive("failback_interval", self.failback_interval);
+        checker.positive("probe_timeout", self.probe_timeout);
+    }
+}
+
+// ============================================================================
+// ROTATION
+// ============================================================================
+
+/// Why the engine moved to another broker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwitchReason {
+    /// The active broker kept refusing or timing out
+    Failover,
+    /// A preferred broker answered a probe
+    Failback,
+}
+
+impl SwitchReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Failover => "failover",
+            Self::Failback => "failback",
+        }
+    }
+}
+
+/// A move from one broker to another
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokerSwitch {
+    /// `host:port` of the broker left
+    pub from: String,
+    /// `host:port` of the broker connected to next
+    pub to: String,
+    pub reason: SwitchReason,
+}
+
+/// Which endpoint the engine is on, and when to leave it
+#[derive(Debug)]
+pub struct BrokerRotation {
+    config: FailoverConfig,
+    endpoints: Vec<BrokerEndpoint>,
+    active: usize,
+    failures: u32,
+    last_probe: Option<Instant>,
+}
+
+impl BrokerRotation {
+    /// Rotation over `endpoints`, most preferred first; there must be at
+    /// least one
+    pub fn new(endpoints: Vec<BrokerEndpoint>, config: FailoverConfig) -> Self {
+        assert!(!endpoints.is_empty(), "no broker endpoint");
+        Self {
+            config,
+            endpoints,
+            active: 0,
+            failures: 0,
+            last_probe: None,
+        }
+    }
+
+    pub fn config(&self) -> &FailoverConfig {
+        &self.config
+    }
+
+    /// Position of the active endpoint in order of preference
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    pub fn active(&self) -> &BrokerEndpoint {
+        &self.endpoints[self.active]
+    }
+
+    /// The active broker accepted the connection
+    pub fn on_connected(&mut self) {
+        self.failures = 0;
+    }
+
+    /// A connection attempt failed. Moves to the next endpoint once
+    /// `max_failures` attempts in a row did, or at once when `fatal`
+    /// (rejected credentials or certificates).
+    pub fn on_failure(&mut self, fatal: bool) -> Option<BrokerSwitch> {
+        if self.endpoints.len() < 2 {
+            return None;
+        }
+        self.failures += 1;
+        if !fatal && self.failures < self.config.max_failures {
+            return None;
+        }
+        Some(self.switch_to(
+            (self.active + 1) % self.endpoints.len(),
+            SwitchReason::Failover,
+        ))
+    }
+
+    /// Preferred endpoints to probe now, while on a backup and at most once
+    /// per `failback_interval`
+    pub fn probe_due(&mut self, now: Instant) -> Option<Vec<(usize, BrokerEndpoint)>> {
+        if self.active == 0 {
+            return None;
+        }
+        if let Some(last) = self.last_probe
+            && now.duration_since(last) < self.config.failback_interval
+        {
+            return None;
+        }
+        self.last_probe = Some(now);
+        Some(
+            self.endpoints[..self.active]
+                .iter()
+                .cloned()
+                .enumerate()
+                .collect(),
+        )
+    }
+
+    /// Move back to a preferred endpoint that answered a probe
+    pub fn fail_back(&mut self, index: usize) -> Option<BrokerSwitch> {
+        (index < self.active).then(|| self.switch_to(index, SwitchReason::Failback))
+    }
+
+    fn switch_to(&mut self, index: usize, reason: SwitchReason) -> BrokerSwitch {
+        let from = self.active().address();
+        self.active = index;
+        self.failures = 0;
+        self.last_probe = None;
+        BrokerSwitch {
+            from,
+            to: self.active().address(),
+            reason,
+        }
+    }
+}
+
+/// Whether `endpoint` accepts a TCP connection within `timeout`
+pub async fn probe(endpoint: &BrokerEndpoint, timeout: Duration) -> bool {
+    let connect = tokio::net::TcpStream::connect((endpoint.host.as_str(), endpoint.port));
+    matches!(tokio::time::timeout(timeout, connect).await, Ok(Ok(_)))
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rotation() -> BrokerRotation {
+        BrokerRotation::new(
+            vec![
+                BrokerEndpoint::new("control.plant", 8883),
+                BrokerEndpoint::new("field.plant", 1883),
+            ],
+            FailoverConfig::default(),
+        )
+    }
+
+    #[test]
+    fn test_rotates_after_consecutive_failures_and_fails_back() {
+        let mut rotation = rotation();
+        assert_eq!(rotation.on_failure(false), None);
+        rotation.on_connected();
+        assert_eq!(rotation.on_failure(false), None);
+        assert_eq!(rotation.on_failure(false), None);
+        let switch = rotation.on_failure(false).unwrap();
+        assert_eq!(
+            switch,
+            BrokerSwitch {
+                from: "control.plant:8883".into(),
+                to: "field.plant:1883".into(),
+                reason: SwitchReason::Failover,
+            }
+        );
+        assert_eq!(rotation.active_index(), 1);
+
+        // Probed at once on the backup, then once per interval
+        let now = Instant::now();
+        let targets = rotation.probe_due(now).unwrap();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].1.host, "control.plant");
+        assert!(rotation.probe_due(now + Duration::from_secs(59)).is_none());
+        assert!(rotation.probe_due(now + Duration::from_secs(60)).is_some());
+
+        let switch = rotation.fail_back(0).unwrap();
+        assert_eq!(switch.reason, SwitchReason::Failback);
+        assert_eq!(rotation.active().address(), "control.plant:8883");
+        assert!(rotation.probe_due(now).is_none());
+        assert_eq!(rotation.fail_back(0), None);
+    }
+
+    #[test]
+    fn test_fatal_failures_rotate_at_once_unless_alone() {
+        let mut rotation = rotation();
+        assert_eq!(rotation.on_failure(true).unwrap().to, "field.plant:1883");
+        // Wraps around to the primary
+        assert_eq!(rotation.on_failure(true).unwrap().to, "control.plant:8883");
+
+        let mut single = BrokerRotation::new(
+            vec![BrokerEndpoint::new("localhost", 1883)],
+            FailoverConfig::default(),
+        );
+        for _ in 0..5 {
+            assert_eq!(single.on_failure(false), None);
+        }
+        assert_eq!(single.on_failure(true), None);
+        assert!(single.probe_due(Instant::now()).is_none());
+    }
+}
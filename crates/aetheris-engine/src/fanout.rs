@@ -0,0 +1,332 @@
+//! Bounded-concurrency command fan-out
+//!
+//! Rollouts and zone evacuations send one command to each of many robots.
+//! Publishing them one at a time serializes on the MQTT client, so
+//! [`fan_out`] keeps up to `concurrency` robots in flight at once. Commands
+//! to the same robot go out one after another in batch order, never two at a
+//! time, and each command passes the publisher's checks (zone modes) as it is
+//! sent rather than once for the whole batch, so a mode change mid-batch
+//! takes effect for the robots not yet reached. A publisher may also hold a
+//! command for later delivery, which the report records as queued.
+
+use std::collections::HashMap;
+use std::future::Future;
+
+use aetheris_shared::{Command, InvalidRobotId, Unsupported};
+use futures_util::stream::{self, StreamExt};
+use thiserror::Error;
+
+use crate::config::{CheckConfig, ConfigChecker};
+use crate::geofences::GeofenceViolation;
+use crate::zones::ZoneViolation;
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Fan-out behavior
+#[derive(Debug, Clone, PartialEq)]
+pub struct FanoutConfig {
+    /// Robots with a command in flight at once
+    pub concurrency: usize,
+}
+
+impl Default for FanoutConfig {
+    fn default() -> Self {
+        Self { concurrency: 16 }
+    }
+}
+
+impl CheckConfig for FanoutConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if self.concurrency == 0 {
+            checker.error(
+                "concurrency",
+                "must be greater than zero",
+                Some("use 1 to publish sequentially; the default is 16".into()),
+            );
+        }
+    }
+}
+
+// ============================================================================
+// PUBLISHING
+// ============================================================================
+
+/// Why a single command was not published
+#[derive(Debug, Error)]
+pub enum PublishError {
+    #[error(transparent)]
+    Rejected(#[from] ZoneViolation),
+    #[error(transparent)]
+    Fenced(#[from] GeofenceViolation),
+    #[error(transparent)]
+    Unsupported(#[from] Unsupported),
+    #[error(transparent)]
+    InvalidRobot(#[from] InvalidRobotId),
+    #[error("rate limit reached for {0}")]
+    RateLimited(String),
+    #[error("publish failed: {0}")]
+    Failed(String),
+}
+
+/// How an accepted command was handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    Published,
+    /// Held until the robot's link is back
+    Queued,
+}
+
+/// Sends one command to one robot
+pub trait CommandPublisher: Sync {
+    fn publish(
+        &self,
+        robot_id: &str,
+        command: Command,
+    ) -> impl Future<Output = Result<Delivery, PublishError>> + Send;
+}
+
+/// What happened to one command of a batch
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandOutcome {
+    Published,
+    /// Held for store-and-forward delivery
+    Queued,
+    /// Refused by a zone mode or a geofence, beyond the robot's
+    /// capabilities, or addressed to a malformed robot id
+    Rejected(String),
+    RateLimited,
+    /// The broker client failed to take the message
+    Failed(String),
+}
+
+/// Per-command outcomes of a batch
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FanoutReport {
+    /// Robot and outcome of every command, in batch order
+    pub outcomes: Vec<(String, CommandOutcome)>,
+}
+
+impl FanoutReport {
+    /// Robots whose command was published (once per command)
+    pub fn published(&self) -> Vec<&str> {
+        self.matching(|o| matches!(o, CommandOutcome::Published))
+    }
+
+    /// Robots whose command was held for later delivery
+    pub fn queued(&self) -> Vec<&str> {
+        self.matching(|o| matches!(o, CommandOutcome::Queued))
+    }
+
+    /// Robots whose command a zone mode or their capabilities refused
+    pub fn rejected(&self) -> Vec<&str> {
+        self.matching(|o| matches!(o, CommandOutcome::Rejected(_)))
+    }
+
+    /// Robots whose command hit a rate limit
+    pub fn rate_limited(&self) -> Vec<&str> {
+        self.matching(|o| matches!(o, CommandOutcome::RateLimited))
+    }
+
+    /// Robots whose command failed to publish
+    pub fn failed(&self) -> Vec<&str> {
+        self.matching(|o| matches!(o, CommandOutcome::Failed(_)))
+    }
+
+    /// Whether every command was published or queued
+    pub fn all_accepted(&self) -> bool {
+        self.outcomes
+            .iter()
+            .all(|(_, o)| matches!(o, CommandOutcome::Published | CommandOutcome::Queued))
+    }
+
+    fn matching(&self, f: impl Fn(&CommandOutcome) -> bool) -> Vec<&str> {
+        self.outcomes
+            .iter()
+            .filter(|(_, o)| f(o))
+            .map(|(robot_id, _)| robot_id.as_str())
+            .collect()
+    }
+}
+
+/// Publish `batch` with at most `config.concurrency` robots in flight.
+///
+/// Commands to one robot are sent sequentially in batch order; a failed
+/// command does not stop the robot's later ones.
+pub async fn fan_out<P: CommandPublisher>(
+    publisher: &P,
+    batch: Vec<(String, Command)>,
+    config: &FanoutConfig,
+) -> FanoutReport {
+    let total = batch.len();
+    let mut queues: Vec<(String, Vec<(usize, Command)>)> = Vec::new();
+    let mut queue_of: HashMap<String, usize> = HashMap::new();
+    for (index, (robot_id, command)) in batch.into_iter().enumerate() {
+        let queue = *queue_of.entry(robot_id.clone()).or_insert_with(|| {
+            queues.push((robot_id, Vec::new()));
+            queues.len() - 1
+        });
+        queues[queue].1.push((index, command));
+    }
+
+    let sent: Vec<(String, Vec<(usize, CommandOutcome)>)> =
+        stream::iter(queues.into_iter().map(|(robot_id, commands)| async move {
+            let mut outcomes = Vec::with_capacity(commands.len());
+            for (index, command) in commands {
+                let outcome = match publisher.publish(&robot_id, command).await {
+                    Ok(Delivery::Published) => CommandOutcome::Published,
+                    Ok(Delivery::Queued) => CommandOutcome::Queued,
+                    Err(PublishError::Rejected(violation)) => {
+                        CommandOutcome::Rejected(violation.to_string())
+                    }
+                    Err(PublishError::Fenced(violation)) => {
+                        CommandOutcome::Rejected(violation.to_string())
+                    }
+                    Err(PublishError::Unsupported(unsupported)) => {
+                        CommandOutcome::Rejected(unsupported.to_string())
+                    }
+                    Err(PublishError::InvalidRobot(invalid)) => {
+                        CommandOutcome::Rejected(invalid.to_string())
+                    }
+                    Err(PublishError::RateLimited(_)) => CommandOutcome::RateLimited,
+                    Err(PublishError::Failed(reason)) => CommandOutcome::Failed(reason),
+                };
+                outcomes.push((index, outcome));
+            }
+            (robot_id, outcomes)
+        }))
+        .buffer_unordered(config.concurrency.max(1))
+        .collect()
+        .await;
+
+    let mut ordered: Vec<Option<(String, CommandOutcome)>> = vec![None; total];
+    for (robot_id, outcomes) in sent {
+        for (index, outcome) in outcomes {
+            ordered[index] = Some((robot_id.clone(), outcome));
+        }
+    }
+    FanoutReport {
+        outcomes: ordered.into_iter().flatten().collect(),
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    /// Records every publish, tracking how many are in flight
+    #[derive(Default)]
+    struct RecordingBus {
+        in_flight: AtomicUsize,
+        peak: AtomicUsize,
+        busy_robots: Mutex<HashSet<String>>,
+        overlapping: AtomicUsize,
+        published: Mutex<Vec<(String, Command)>>,
+        fail: HashSet<String>,
+        limited: HashSet<String>,
+    }
+
+    impl CommandPublisher for RecordingBus {
+        async fn publish(
+            &self,
+            robot_id: &str,
+            command: Command,
+        ) -> Result<Delivery, PublishError> {
+            if !self
+                .busy_robots
+                .lock()
+                .unwrap()
+                .insert(robot_id.to_string())
+            {
+                self.overlapping.fetch_add(1, Ordering::SeqCst);
+            }
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(2)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            self.busy_robots.lock().unwrap().remove(robot_id);
+
+            if self.fail.contains(robot_id) {
+                return Err(PublishError::Failed("connection reset".into()));
+            }
+            if self.limited.contains(robot_id) {
+                return Err(PublishError::RateLimited(robot_id.into()));
+            }
+            self.published
+                .lock()
+                .unwrap()
+                .push((robot_id.to_string(), command));
+            Ok(Delivery::Published)
+        }
+    }
+
+    fn robot(n: usize) -> String {
+        format!("RV-{n:03}")
+    }
+
+    #[tokio::test]
+    async fn test_large_batch_respects_bound_and_partitions_outcomes() {
+        let bus = RecordingBus {
+            fail: (0..200).step_by(25).map(robot).collect(),
+            limited: [robot(7), robot(8)].into(),
+            ..RecordingBus::default()
+        };
+        let batch: Vec<_> = (0..200).map(|n| (robot(n), Command::Stop)).collect();
+
+        let report = fan_out(&bus, batch, &FanoutConfig { concurrency: 16 }).await;
+
+        assert_eq!(bus.peak.load(Ordering::SeqCst), 16);
+        assert_eq!(report.outcomes.len(), 200);
+        let ids: Vec<String> = report.outcomes.iter().map(|(id, _)| id.clone()).collect();
+        assert_eq!(ids, (0..200).map(robot).collect::<Vec<_>>());
+        assert_eq!(report.failed().len(), 8);
+        assert_eq!(report.rate_limited(), ["RV-007", "RV-008"]);
+        assert_eq!(report.published().len(), 190);
+        assert!(report.rejected().is_empty());
+        assert!(!report.all_accepted());
+    }
+
+    #[tokio::test]
+    async fn test_commands_to_one_robot_go_out_in_order() {
+        let bus = RecordingBus::default();
+        let mut batch = Vec::new();
+        for step in 0..5 {
+            for n in 0..40 {
+                let command = Command::StartPatrol {
+                    route_id: format!("ROUTE-{step}"),
+                };
+                batch.push((robot(n), command));
+            }
+        }
+
+        let report = fan_out(&bus, batch, &FanoutConfig { concurrency: 8 }).await;
+
+        assert!(report.all_accepted());
+        assert_eq!(bus.overlapping.load(Ordering::SeqCst), 0);
+        assert!(bus.peak.load(Ordering::SeqCst) <= 8);
+        let published = bus.published.lock().unwrap();
+        for n in 0..40 {
+            let routes: Vec<_> = published
+                .iter()
+                .filter(|(id, _)| *id == robot(n))
+                .map(|(_, command)| match command {
+                    Command::StartPatrol { route_id } => route_id.clone(),
+                    other => panic!("unexpected {other:?}"),
+                })
+                .collect();
+            assert_eq!(
+                routes,
+                (0..5).map(|s| format!("ROUTE-{s}")).collect::<Vec<_>>()
+            );
+        }
+    }
+}
@@ -0,0 +1,510 @@
+//! Simulated fault lifecycles with realistic recovery
+//!
+//! Real robots don't stay broken until someone clears the fault: a comm
+//! dropout ends, a motor controller resets, a sensor reboots. Each injected
+//! fault follows its own recovery model here, driven by the simulation clock
+//! and a seeded RNG, and every transition is reported as a [`FaultEvent`]
+//! alongside the status/health change applied to the robot.
+
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use aetheris_shared::{FaultType, HealthStatus, RobotState, RobotStatus, Velocity};
+
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Parameters of the per-fault recovery models
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveryConfig {
+    /// Shortest comm dropout
+    pub comm_dropout_min: Duration,
+    /// Longest comm dropout
+    pub comm_dropout_max: Duration,
+    /// Probability per minute that a failed motor controller resets
+    pub motor_recovery_per_minute: f64,
+    /// Fraction of normal max speed available after a motor reset
+    pub degraded_speed_factor: f64,
+    /// Time a failed sensor stays in Error before the robot reboots it
+    pub sensor_reboot_delay: Duration,
+    /// Time the robot is offline while rebooting
+    pub sensor_reboot_duration: Duration,
+    /// Battery level forced by a LowBattery fault (percent)
+    pub low_battery_level: f64,
+    /// Battery level at which a LowBattery fault counts as recovered
+    pub low_battery_clear_level: f64,
+    /// Maximum GPS drift applied per tick (meters)
+    pub gps_drift_per_tick: f64,
+    /// RNG seed for sampled durations and recovery rolls
+    pub seed: u64,
+}
+
+impl Default for RecoveryConfig {
+    fn default() -> Self {
+        Self {
+            comm_dropout_min: Duration::from_secs(10),
+            comm_dropout_max: Duration::from_secs(60),
+            motor_recovery_per_minute: 0.2,
+            degraded_speed_factor: 0.5,
+            sensor_reboot_delay: Duration::from_secs(10),
+            sensor_reboot_duration: Duration::from_secs(20),
+            low_battery_level: 12.0,
+            low_battery_clear_level: 30.0,
+            gps_drift_per_tick: 0.5,
+            seed: 0xFA17,
+        }
+    }
+}
+
+impl CheckConfig for RecoveryConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if self.comm_dropout_min > self.comm_dropout_max {
+            checker.error(
+                "comm_dropout_min",
+                format!(
+                    "{:?} exceeds comm_dropout_max {:?}",
+                    self.comm_dropout_min, self.comm_dropout_max
+                ),
+                None,
+            );
+        }
+        checker.fraction("motor_recovery_per_minute", self.motor_recovery_per_minute);
+        if !(self.degraded_speed_factor > 0.0 && self.degraded_speed_factor <= 1.0) {
+            checker.error(
+                "degraded_speed_factor",
+                format!("must be in (0, 1], got {}", self.degraded_speed_factor),
+                None,
+            );
+        }
+        checker.positive("sensor_reboot_duration", self.sensor_reboot_duration);
+        if self.low_battery_clear_level <= self.low_battery_level {
+            checker.error(
+                "low_battery_clear_level",
+                format!(
+                    "must be above low_battery_level ({}), got {}",
+                    self.low_battery_level, self.low_battery_clear_level
+                ),
+                None,
+            );
+        }
+    }
+}
+
+// ============================================================================
+// FAULT STATE
+// ============================================================================
+
+/// Where a fault is in its lifecycle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FaultPhase {
+    /// Fault is in effect
+    Active,
+    /// Sensor reboot in progress; robot is offline until `until_ms`
+    Rebooting { until_ms: u64 },
+}
+
+/// A fault currently affecting a simulated robot
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActiveFault {
+    pub fault_type: FaultType,
+    pub injected_at_ms: u64,
+    pub phase: FaultPhase,
+    /// Sampled end time for faults that end on their own
+    pub ends_at_ms: Option<u64>,
+}
+
+/// Fault lifecycle transitions
+#[derive(Debug, Clone, PartialEq)]
+pub enum FaultEvent {
+    Injected(FaultType),
+    /// Sensor reboot started; robot goes offline
+    RebootStarted,
+    /// Motor controller reset; robot runs at reduced max speed
+    MotorDegraded {
+        speed_factor: f64,
+    },
+    Recovered(FaultType),
+}
+
+/// Fault state and recovery for one simulated robot
+#[derive(Debug)]
+pub struct RobotFaults {
+    config: RecoveryConfig,
+    faults: Vec<ActiveFault>,
+    /// Status before the first fault, restored once all faults recover
+    status_before: Option<RobotStatus>,
+    /// Max speed multiplier left by a degraded motor (1.0 = healthy)
+    speed_factor: f64,
+    rng: StdRng,
+}
+
+impl RobotFaults {
+    pub fn new(config: RecoveryConfig) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(config.seed),
+            config,
+            faults: Vec::new(),
+            status_before: None,
+            speed_factor: 1.0,
+        }
+    }
+
+    pub fn active(&self) -> &[ActiveFault] {
+        &self.faults
+    }
+
+    pub fn has(&self, fault_type: FaultType) -> bool {
+        self.faults.iter().any(|f| f.fault_type == fault_type)
+    }
+
+    /// Whether the robot's link is down (dropout or sensor reboot)
+    pub fn is_comm_suppressed(&self) -> bool {
+        self.faults.iter().any(|f| {
+            f.fault_type == FaultType::CommDropout
+                || matches!(f.phase, FaultPhase::Rebooting { .. })
+        })
+    }
+
+    /// Multiplier on the robot's max speed after motor degradation
+    pub fn speed_factor(&self) -> f64 {
+        self.speed_factor
+    }
+
+    /// Apply a fault to the robot
+    pub fn inject(
+        &mut self,
+        fault_type: FaultType,
+        robot: &mut RobotState,
+        now_ms: u64,
+    ) -> Vec<FaultEvent> {
+        if self.has(fault_type) {
+            return Vec::new();
+        }
+        if self.faults.is_empty() {
+            self.status_before = Some(robot.status);
+        }
+
+        let ends_at_ms = match fault_type {
+            FaultType::CommDropout => {
+                let min = self.config.comm_dropout_min.as_millis() as u64;
+                let max = self.config.comm_dropout_max.as_millis() as u64;
+                Some(now_ms + self.rng.random_range(min..=max.max(min)))
+            }
+            _ => None,
+        };
+
+        match fault_type {
+            FaultType::LowBattery => {
+                robot.battery = robot.battery.min(self.config.low_battery_level);
+            }
+            FaultType::SensorFailure => robot.status = RobotStatus::Error,
+            FaultType::MotorFailure => {
+                robot.velocity = Velocity::zero();
+                robot.status = RobotStatus::Maintenance;
+            }
+            FaultType::CommDropout | FaultType::GpsDrift => {}
+        }
+
+        self.faults.push(ActiveFault {
+            fault_type,
+            injected_at_ms: now_ms,
+            phase: FaultPhase::Active,
+            ends_at_ms,
+        });
+        robot.health = self.health();
+        vec![FaultEvent::Injected(fault_type)]
+    }
+
+    /// Advance every fault's recovery model by one simulation tick
+    pub fn tick(&mut self, robot: &mut RobotState, now_ms: u64, dt: Duration) -> Vec<FaultEvent> {
+        let mut events = Vec::new();
+        let mut recovered = Vec::new();
+        let reboot_delay = self.config.sensor_reboot_delay.as_millis() as u64;
+        let reboot_duration = self.config.sensor_reboot_duration.as_millis() as u64;
+        // Chance of at least one motor reset within dt given the per-minute rate
+        let motor_roll = 1.0
+            - (1.0 - self.config.motor_recovery_per_minute.clamp(0.0, 1.0))
+                .powf(dt.as_secs_f64() / 60.0);
+
+        for fault in &mut self.faults {
+            match (fault.fault_type, fault.phase) {
+                (FaultType::CommDropout, _) => {
+                    if fault.ends_at_ms.is_some_and(|end| now_ms >= end) {
+                        recovered.push(fault.fault_type);
+                    }
+                }
+                (FaultType::MotorFailure, _) => {
+                    robot.velocity = Velocity::zero();
+                    if self.rng.random_bool(motor_roll) {
+                        self.speed_factor = self.config.degraded_speed_factor;
+                        events.push(FaultEvent::MotorDegraded {
+                            speed_factor: self.speed_factor,
+                        });
+                        recovered.push(fault.fault_type);
+                    }
+                }
+                (FaultType::SensorFailure, FaultPhase::Active) => {
+                    if now_ms >= fault.injected_at_ms + reboot_delay {
+                        fault.phase = FaultPhase::Rebooting {
+                            until_ms: now_ms + reboot_duration,
+                        };
+                        robot.status = RobotStatus::Offline;
+                        events.push(FaultEvent::RebootStarted);
+                    }
+                }
+                (FaultType::SensorFailure, FaultPhase::Rebooting { until_ms }) => {
+                    if now_ms >= until_ms {
+                        recovered.push(fault.fault_type);
+                    }
+                }
+                (FaultType::LowBattery, _) => {
+                    if robot.battery >= self.config.low_battery_clear_level {
+                        recovered.push(fault.fault_type);
+                    }
+                }
+                (FaultType::GpsDrift, _) => {
+                    let drift = self.config.gps_drift_per_tick;
+                    robot.position.x += self.rng.random_range(-drift..=drift);
+                    robot.position.z += self.rng.random_range(-drift..=drift);
+                }
+            }
+        }
+
+        for fault_type in recovered {
+            events.extend(self.recover(fault_type, robot));
+        }
+        events
+    }
+
+    /// A position fix or docking clears GPS drift
+    pub fn correct_position(&mut self, robot: &mut RobotState) -> Vec<FaultEvent> {
+        self.recover(FaultType::GpsDrift, robot)
+    }
+
+    /// Remove one fault immediately (operator override). Clearing a motor
+    /// failure also undoes any lasting speed degradation.
+    pub fn clear(&mut self, fault_type: FaultType, robot: &mut RobotState) -> Vec<FaultEvent> {
+        if fault_type == FaultType::MotorFailure {
+            self.speed_factor = 1.0;
+        }
+        let events = self.recover(fault_type, robot);
+        robot.health = self.health();
+        events
+    }
+
+    /// Remove every fault immediately (operator override)
+    pub fn clear_all(&mut self, robot: &mut RobotState) -> Vec<FaultEvent> {
+        let types: Vec<_> = self.faults.iter().map(|f| f.fault_type).collect();
+        self.speed_factor = 1.0;
+        types
+            .into_iter()
+            .flat_map(|fault_type| self.recover(fault_type, robot))
+            .collect()
+    }
+
+    fn recover(&mut self, fault_type: FaultType, robot: &mut RobotState) -> Vec<FaultEvent> {
+        let before = self.faults.len();
+        self.faults.retain(|f| f.fault_type != fault_type);
+        if self.faults.len() == before {
+            return Vec::new();
+        }
+
+        if fault_type == FaultType::SensorFailure {
+            // A rebooted robot comes back idle rather than resuming its task
+            robot.status = RobotStatus::Idle;
+            self.status_before = Some(RobotStatus::Idle);
+        }
+        if self.faults.is_empty() {
+            if let Some(status) = self.status_before.take() {
+                robot.status = status;
+            }
+        } else if fault_type == FaultType::MotorFailure && robot.status == RobotStatus::Maintenance
+        {
+            robot.status = self.status_before.unwrap_or(RobotStatus::Idle);
+        }
+        robot.health = self.health();
+        vec![FaultEvent::Recovered(fault_type)]
+    }
+
+    /// Health implied by the active faults and any lasting degradation
+    pub fn health(&self) -> HealthStatus {
+        let critical = self.faults.iter().any(|f| {
+            matches!(
+                f.fault_type,
+                FaultType::SensorFailure | FaultType::MotorFailure
+            )
+        });
+        if critical {
+            HealthStatus::Critical
+        } else if !self.faults.is_empty() || self.speed_factor < 1.0 {
+            HealthStatus::Warning
+        } else {
+            HealthStatus::Optimal
+        }
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetheris_shared::{Orientation, RobotType};
+
+    const TICK: Duration = Duration::from_secs(1);
+
+    fn robot() -> RobotState {
+        RobotState {
+            status: RobotStatus::Active,
+            velocity: Velocity::new(1.0, 0.0, 0.0),
+            orientation: Orientation::identity(),
+            battery: 80.0,
+            ..RobotState::new("RV-001".parse().unwrap(), "Rover Alpha", RobotType::Rover)
+        }
+    }
+
+    /// Tick once per simulated second from `from_s` to `to_s`, collecting events
+    fn run(
+        faults: &mut RobotFaults,
+        robot: &mut RobotState,
+        from_s: u64,
+        to_s: u64,
+    ) -> Vec<(u64, FaultEvent)> {
+        (from_s..=to_s)
+            .flat_map(|s| {
+                faults
+                    .tick(robot, s * 1000, TICK)
+                    .into_iter()
+                    .map(move |e| (s, e))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_comm_dropout_ends_after_sampled_duration() {
+        let mut faults = RobotFaults::new(RecoveryConfig::default());
+        let mut robot = robot();
+        faults.inject(FaultType::CommDropout, &mut robot, 0);
+        assert!(faults.is_comm_suppressed());
+        assert_eq!(robot.health, HealthStatus::Warning);
+
+        let events = run(&mut faults, &mut robot, 1, 120);
+        assert_eq!(events.len(), 1);
+        let (at, event) = &events[0];
+        assert_eq!(*event, FaultEvent::Recovered(FaultType::CommDropout));
+        assert!((10..=60).contains(at));
+        assert!(!faults.is_comm_suppressed());
+        assert_eq!(robot.status, RobotStatus::Active);
+        assert_eq!(robot.health, HealthStatus::Optimal);
+    }
+
+    #[test]
+    fn test_motor_failure_self_recovers_into_degraded_mode() {
+        let config = RecoveryConfig {
+            motor_recovery_per_minute: 0.9,
+            ..RecoveryConfig::default()
+        };
+        let mut faults = RobotFaults::new(config);
+        let mut robot = robot();
+        faults.inject(FaultType::MotorFailure, &mut robot, 0);
+        assert_eq!(robot.status, RobotStatus::Maintenance);
+        assert_eq!(robot.health, HealthStatus::Critical);
+        assert_eq!(robot.velocity, Velocity::zero());
+
+        let events: Vec<_> = run(&mut faults, &mut robot, 1, 600)
+            .into_iter()
+            .map(|(_, e)| e)
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                FaultEvent::MotorDegraded { speed_factor: 0.5 },
+                FaultEvent::Recovered(FaultType::MotorFailure),
+            ]
+        );
+        assert_eq!(faults.speed_factor(), 0.5);
+        assert_eq!(robot.status, RobotStatus::Active);
+        assert_eq!(robot.health, HealthStatus::Warning);
+    }
+
+    #[test]
+    fn test_sensor_failure_reboots_offline_then_returns_idle() {
+        let mut faults = RobotFaults::new(RecoveryConfig::default());
+        let mut robot = robot();
+        faults.inject(FaultType::SensorFailure, &mut robot, 0);
+        assert_eq!(robot.status, RobotStatus::Error);
+
+        let events = run(&mut faults, &mut robot, 1, 15);
+        assert_eq!(events, vec![(10, FaultEvent::RebootStarted)]);
+        assert_eq!(robot.status, RobotStatus::Offline);
+        assert!(faults.is_comm_suppressed());
+
+        let events = run(&mut faults, &mut robot, 16, 40);
+        assert_eq!(
+            events,
+            vec![(30, FaultEvent::Recovered(FaultType::SensorFailure))]
+        );
+        assert_eq!(robot.status, RobotStatus::Idle);
+        assert_eq!(robot.health, HealthStatus::Optimal);
+    }
+
+    #[test]
+    fn test_low_battery_only_clears_by_charging() {
+        let mut faults = RobotFaults::new(RecoveryConfig::default());
+        let mut robot = robot();
+        faults.inject(FaultType::LowBattery, &mut robot, 0);
+        assert_eq!(robot.battery, 12.0);
+        assert_eq!(robot.health, HealthStatus::Warning);
+
+        assert!(run(&mut faults, &mut robot, 1, 3600).is_empty());
+        assert!(faults.has(FaultType::LowBattery));
+
+        robot.battery = 30.0;
+        let events = run(&mut faults, &mut robot, 3601, 3601);
+        assert_eq!(
+            events,
+            vec![(3601, FaultEvent::Recovered(FaultType::LowBattery))]
+        );
+        assert_eq!(robot.health, HealthStatus::Optimal);
+    }
+
+    #[test]
+    fn test_gps_drift_persists_until_position_correction() {
+        let mut faults = RobotFaults::new(RecoveryConfig::default());
+        let mut robot = robot();
+        let start = robot.position;
+        faults.inject(FaultType::GpsDrift, &mut robot, 0);
+
+        assert!(run(&mut faults, &mut robot, 1, 30).is_empty());
+        assert_ne!(robot.position, start);
+
+        assert_eq!(
+            faults.correct_position(&mut robot),
+            vec![FaultEvent::Recovered(FaultType::GpsDrift)]
+        );
+        assert!(faults.active().is_empty());
+        assert_eq!(robot.health, HealthStatus::Optimal);
+    }
+
+    #[test]
+    fn test_overlapping_faults_restore_original_status() {
+        let mut faults = RobotFaults::new(RecoveryConfig::default());
+        let mut robot = robot();
+        faults.inject(FaultType::GpsDrift, &mut robot, 0);
+        faults.inject(FaultType::MotorFailure, &mut robot, 0);
+        assert_eq!(robot.health, HealthStatus::Critical);
+
+        let events = faults.clear_all(&mut robot);
+        assert_eq!(events.len(), 2);
+        assert_eq!(robot.status, RobotStatus::Active);
+        assert_eq!(robot.health, HealthStatus::Optimal);
+    }
+}
@@ -0,0 +1,139 @@
+//! Flapping detection for robot connections
+//!
+//! A robot whose heartbeats keep lapsing and resuming is worse off than its
+//! status shows: each time it comes back it looks healthy again. Every
+//! return from offline is counted per robot; more than `max_reconnects`
+//! within `window` makes the robot flapping. Its health is held at Warning
+//! while it flaps, and one alert is raised per flapping episode.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// When a robot's connection counts as flapping
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlapConfig {
+    /// Returns from offline tolerated within `window`
+    pub max_reconnects: usize,
+    pub window: Duration,
+}
+
+impl Default for FlapConfig {
+    fn default() -> Self {
+        Self {
+            max_reconnects: 3,
+            window: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+impl CheckConfig for FlapConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if self.max_reconnects == 0 {
+            checker.error(
+                "max_reconnects",
+                "must be at least 1",
+                Some("a single reconnect is not flapping".into()),
+            );
+        }
+        checker.positive("window", self.window);
+    }
+}
+
+// ============================================================================
+// FLAP DETECTOR
+// ============================================================================
+
+/// Recent returns from offline, per robot
+#[derive(Debug, Default)]
+pub struct FlapDetector {
+    config: FlapConfig,
+    reconnects: HashMap<String, VecDeque<Instant>>,
+    /// Robots whose current flapping episode was already reported
+    reported: HashSet<String>,
+}
+
+impl FlapDetector {
+    pub fn new(config: FlapConfig) -> Self {
+        Self {
+            config,
+            reconnects: HashMap::new(),
+            reported: HashSet::new(),
+        }
+    }
+
+    pub fn config(&self) -> &FlapConfig {
+        &self.config
+    }
+
+    /// Count a return from offline at `now`. Returns the number of reconnects
+    /// within the window when this one started a flapping episode.
+    pub fn on_reconnect(&mut self, robot_id: &str, now: Instant) -> Option<usize> {
+        let reconnects = self.reconnects.entry(robot_id.to_string()).or_default();
+        reconnects.push_back(now);
+        while reconnects
+            .front()
+            .is_some_and(|at| now.duration_since(*at) > self.config.window)
+        {
+            reconnects.pop_front();
+        }
+        let count = reconnects.len();
+        if count <= self.config.max_reconnects {
+            self.reported.remove(robot_id);
+            return None;
+        }
+        self.reported.insert(robot_id.to_string()).then_some(count)
+    }
+
+    /// Whether the robot reconnected too often within the window up to `now`
+    pub fn is_flapping(&self, robot_id: &str, now: Instant) -> bool {
+        self.reconnects.get(robot_id).is_some_and(|reconnects| {
+            reconnects
+                .iter()
+                .filter(|at| now.duration_since(**at) <= self.config.window)
+                .count()
+                > self.config.max_reconnects
+        })
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flapping_is_reported_once_per_episode() {
+        let mut flaps = FlapDetector::new(FlapConfig {
+            max_reconnects: 2,
+            window: Duration::from_secs(60),
+        });
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert_eq!(flaps.on_reconnect("RV-001", at(0)), None);
+        assert_eq!(flaps.on_reconnect("RV-001", at(10)), None);
+        assert!(!flaps.is_flapping("RV-001", at(10)));
+        assert_eq!(flaps.on_reconnect("RV-001", at(20)), Some(3));
+        assert!(flaps.is_flapping("RV-001", at(20)));
+        assert_eq!(flaps.on_reconnect("RV-001", at(30)), None);
+        // Other robots are counted on their own
+        assert_eq!(flaps.on_reconnect("RV-002", at(30)), None);
+
+        // Calm for a window: the episode is over and a new one is reported
+        assert!(!flaps.is_flapping("RV-001", at(100)));
+        assert_eq!(flaps.on_reconnect("RV-001", at(200)), None);
+        assert_eq!(flaps.on_reconnect("RV-001", at(210)), None);
+        assert_eq!(flaps.on_reconnect("RV-001", at(220)), Some(3));
+    }
+}
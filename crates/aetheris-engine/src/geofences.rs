@@ -0,0 +1,316 @@
+//! Geofences enforced on robot movement
+//!
+//! Fences are volumes robots must keep out of, such as the air over the
+//! compressor station for drones. They come from the configuration and are
+//! checked when a `MoveTo` is dispatched: a move whose straight path enters
+//! a fence that applies to the robot's type is refused, or cut short a
+//! margin before the fence, as configured. A robot already inside a fence
+//! may still be moved out of it.
+
+use std::collections::HashSet;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use aetheris_shared::{Command, Geofence, Position, RobotState};
+
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// What happens to a move into a fence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FenceAction {
+    /// Refuse the command
+    #[default]
+    Reject,
+    /// Send the robot as far as it may go towards the target
+    Clamp,
+}
+
+/// Geofences and how they are enforced
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeofenceConfig {
+    pub fences: Vec<Geofence>,
+    pub action: FenceAction,
+    /// Distance short of the fence a clamped move stops at (meters)
+    pub margin: f64,
+}
+
+impl Default for GeofenceConfig {
+    fn default() -> Self {
+        Self {
+            fences: Vec::new(),
+            action: FenceAction::default(),
+            margin: 5.0,
+        }
+    }
+}
+
+impl CheckConfig for GeofenceConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        let mut seen = HashSet::new();
+        for (i, fence) in self.fences.iter().enumerate() {
+            let field = format!("fences[{i}]");
+            if fence.id.is_empty() || !seen.insert(fence.id.as_str()) {
+                checker.error(
+                    &format!("{field}.id"),
+                    format!("fence id \"{}\" is empty or duplicated", fence.id),
+                    None,
+                );
+            }
+            if fence.polygon.len() < 3 {
+                checker.error(
+                    &format!("{field}.polygon"),
+                    format!("needs at least 3 corners, got {}", fence.polygon.len()),
+                    None,
+                );
+            } else if !fence.polygon.iter().all(Position::is_finite) {
+                checker.error(&format!("{field}.polygon"), "corners must be finite", None);
+            }
+            if let Some(range) = fence.altitude
+                && !(range.min.is_finite() && range.max.is_finite() && range.min < range.max)
+            {
+                checker.error(
+                    &format!("{field}.altitude"),
+                    format!("min {} must be below max {}", range.min, range.max),
+                    None,
+                );
+            }
+        }
+        if !(self.margin >= 0.0 && self.margin.is_finite()) {
+            checker.error("margin", "must be a non-negative distance", None);
+        }
+    }
+}
+
+// ============================================================================
+// ENFORCEMENT
+// ============================================================================
+
+/// A move refused because its path enters a fence
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("path to ({}, {}, {}) enters geofence {fence_id}", .target.x, .target.y, .target.z)]
+pub struct GeofenceViolation {
+    pub fence_id: String,
+    pub target: Position,
+}
+
+/// What the fences make of a command
+#[derive(Debug, Clone, PartialEq)]
+pub enum Enforcement {
+    /// Not a move into a fence
+    Allowed,
+    /// A move cut short before the fence; `target` is where the robot
+    /// should go instead
+    Clamped {
+        fence_id: String,
+        requested: Position,
+        target: Position,
+    },
+    Rejected(GeofenceViolation),
+}
+
+/// The configured fences
+#[derive(Debug, Default)]
+pub struct Geofences {
+    config: GeofenceConfig,
+}
+
+impl Geofences {
+    pub fn new(config: GeofenceConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn fences(&self) -> &[Geofence] {
+        &self.config.fences
+    }
+
+    /// The fence the straight path of `robot` to `target` enters first,
+    /// with the fraction of the path covered before it
+    fn first_entry(&self, robot: &RobotState, target: &Position) -> Option<(&Geofence, f64)> {
+        self.config
+            .fences
+            .iter()
+            .filter(|fence| fence.applies_to(robot.robot_type))
+            // Leaving a fence the robot is in is allowed
+            .filter(|fence| !fence.contains(&robot.position) || fence.contains(target))
+            .filter_map(|fence| Some((fence, fence.entry(&robot.position, target)?)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// Check `command` for `robot` against the fences
+    pub fn check_command(&self, command: &Command, robot: &RobotState) -> Enforcement {
+        let Command::MoveTo { target, .. } = command else {
+            return Enforcement::Allowed;
+        };
+        let Some((fence, entry)) = self.first_entry(robot, target) else {
+            return Enforcement::Allowed;
+        };
+        let violation = GeofenceViolation {
+            fence_id: fence.id.clone(),
+            target: *target,
+        };
+        if self.config.action == FenceAction::Reject {
+            return Enforcement::Rejected(violation);
+        }
+        let length = robot.position.distance_to(target);
+        let stop = entry - self.config.margin / length;
+        if stop <= 0.0 {
+            // Already at the fence
+            return Enforcement::Rejected(violation);
+        }
+        Enforcement::Clamped {
+            fence_id: violation.fence_id,
+            requested: *target,
+            target: robot.position.lerp(target, stop),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetheris_shared::{AltitudeRange, RobotId, RobotType, Timestamp};
+
+    fn drone_at(position: Position) -> RobotState {
+        let mut state = RobotState::new_at(
+            RobotId::parse("DR-001").unwrap(),
+            "Drone Alpha",
+            RobotType::Drone,
+            Timestamp::from_millis(0),
+        );
+        state.position = position;
+        state
+    }
+
+    fn fences(action: FenceAction) -> Geofences {
+        Geofences::new(GeofenceConfig {
+            fences: vec![Geofence {
+                altitude: Some(AltitudeRange::new(0.0, 30.0)),
+                robot_types: vec![RobotType::Drone],
+                ..Geofence::new(
+                    "FENCE-COMPRESSOR",
+                    vec![
+                        Position::new(40.0, 0.0, -10.0),
+                        Position::new(60.0, 0.0, -10.0),
+                        Position::new(60.0, 0.0, 10.0),
+                        Position::new(40.0, 0.0, 10.0),
+                    ],
+                )
+            }],
+            action,
+            margin: 5.0,
+        })
+    }
+
+    fn move_to(x: f64, y: f64, z: f64) -> Command {
+        Command::MoveTo {
+            target: Position::new(x, y, z),
+            speed: None,
+        }
+    }
+
+    #[test]
+    fn test_moves_into_or_across_a_fence_are_rejected() {
+        let fences = fences(FenceAction::Reject);
+        let drone = drone_at(Position::new(0.0, 10.0, 0.0));
+        assert_eq!(
+            fences.check_command(&move_to(50.0, 10.0, 0.0), &drone),
+            Enforcement::Rejected(GeofenceViolation {
+                fence_id: "FENCE-COMPRESSOR".into(),
+                target: Position::new(50.0, 10.0, 0.0),
+            })
+        );
+        // Straight across the station
+        assert!(matches!(
+            fences.check_command(&move_to(100.0, 10.0, 0.0), &drone),
+            Enforcement::Rejected(_)
+        ));
+        // Over it, around it, or by a robot it doesn't apply to
+        let high = drone_at(Position::new(0.0, 40.0, 0.0));
+        for (command, drone) in [
+            (move_to(100.0, 40.0, 0.0), &high),
+            (move_to(100.0, 10.0, 30.0), &drone),
+            (Command::ReturnToBase, &drone),
+        ] {
+            assert_eq!(fences.check_command(&command, drone), Enforcement::Allowed);
+        }
+        let mut rover = drone_at(Position::new(0.0, 0.0, 0.0));
+        rover.robot_type = RobotType::Rover;
+        assert_eq!(
+            fences.check_command(&move_to(50.0, 0.0, 0.0), &rover),
+            Enforcement::Allowed
+        );
+        // Out of the fence, not deeper into it
+        let inside = drone_at(Position::new(50.0, 10.0, 0.0));
+        assert_eq!(
+            fences.check_command(&move_to(0.0, 10.0, 0.0), &inside),
+            Enforcement::Allowed
+        );
+        assert!(matches!(
+            fences.check_command(&move_to(55.0, 10.0, 5.0), &inside),
+            Enforcement::Rejected(_)
+        ));
+    }
+
+    #[test]
+    fn test_clamped_moves_stop_short_of_the_fence() {
+        let fences = fences(FenceAction::Clamp);
+        let drone = drone_at(Position::new(0.0, 10.0, 0.0));
+        let Enforcement::Clamped {
+            fence_id,
+            requested,
+            target,
+        } = fences.check_command(&move_to(50.0, 10.0, 0.0), &drone)
+        else {
+            panic!("move should be clamped");
+        };
+        assert_eq!(fence_id, "FENCE-COMPRESSOR");
+        assert_eq!(requested, Position::new(50.0, 10.0, 0.0));
+        assert!(target.distance_to(&Position::new(35.0, 10.0, 0.0)) < 1e-9);
+
+        // Nothing left to clamp to
+        let close = drone_at(Position::new(37.0, 10.0, 0.0));
+        assert!(matches!(
+            fences.check_command(&move_to(50.0, 10.0, 0.0), &close),
+            Enforcement::Rejected(_)
+        ));
+    }
+
+    #[test]
+    fn test_config_check_catches_degenerate_fences() {
+        let mut config = GeofenceConfig::default();
+        config.fences.push(Geofence::new(
+            "FENCE-A",
+            vec![Position::origin(), Position::new(1.0, 0.0, 0.0)],
+        ));
+        config.fences.push(Geofence {
+            altitude: Some(AltitudeRange::new(30.0, 0.0)),
+            ..Geofence::new(
+                "FENCE-A",
+                vec![
+                    Position::origin(),
+                    Position::new(1.0, 0.0, 0.0),
+                    Position::new(0.0, 0.0, 1.0),
+                ],
+            )
+        });
+        let mut checker = ConfigChecker::default();
+        config.check(&mut checker);
+        let fields: Vec<String> = checker
+            .finish()
+            .unwrap_err()
+            .issues
+            .into_iter()
+            .map(|issue| issue.path)
+            .collect();
+        assert_eq!(
+            fields,
+            ["fences[0].polygon", "fences[1].id", "fences[1].altitude"]
+        );
+    }
+}
@@ -0,0 +1,1710 @@
+//! HTTP and WebSocket bridge for dashboards
+//!
+//! Browsers cannot speak MQTT without a websocket plugin on the broker, so
+//! the engine serves what the dashboard needs over HTTP:
+//!
+//! - `GET /fleet` and `GET /fleet/{robot_id}`: the latest robot states
+//! - `GET /api/robots/{robot_id}/trends`: the smoothed battery and signal
+//!   rates of a robot, `null` until enough samples came in
+//! - `GET /api/robots/{robot_id}/pending`: the commands held for a
+//!   weak-link robot, oldest first
+//! - `GET /api/fleet/summary`: robot counts, the open assignments of every
+//!   responder and the mode of every zone
+//! - `GET /anomalies?status=..&severity=..`: active anomalies
+//! - `GET /sections/health`: the latest health rollup per section
+//! - `GET /sections/wall-thickness`: the fitted wall-loss trend per section
+//! - `GET /deadletters`: the latest quarantined messages, oldest first
+//! - `GET /history?hours=..&robot_id=..&section_id=..&kind=..&limit=..`:
+//!   stored telemetry, environment readings and anomalies to replay, oldest
+//!   first; at most 10 000 records, and a larger `limit` is refused
+//! - `GET /api/environment/alarms`: the state of every section alarm
+//! - `GET /api/environment/sensor-health`: the health of every section
+//!   sensor channel, suspect ones with the fault that made them so
+//! - `GET /api/decisions?since=..&policy=..`: the decision trace of the
+//!   engine's policies, oldest first
+//! - `GET /api/rollouts/{rollout_id}`: the progress of a configuration
+//!   rollout
+//! - `GET /api/timeline?from=..&to=..&anomaly=..`: the incident timeline
+//!   of an anomaly, or of a `section_id` or `robot_id` instead, oldest first
+//! - `GET /api/zones`: every zone with its mode and when the mode expires
+//! - `POST /api/query`: a `{"sql": ..}` body run through a read-only
+//!   [`QueryEngine`](crate::query::QueryEngine) over the configured history
+//!   database (`sqlite` feature)
+//! - `POST /api/query/export?format=csv|json_lines`: the same body, with
+//!   every row streamed back as it is read instead of collected first
+//!   (`sqlite` feature)
+//! - `POST /commands/{robot_id}`: a [`Command`] body, forwarded through
+//!   [`AetherisMqtt::send_command`]; requires the configured bearer token
+//! - `POST /api/sections`: registers a section or merges a provisional one
+//!   into it through [`AetherisMqtt::register_section`]; requires the
+//!   bearer token too
+//! - `POST /api/anomalies/{anomaly_id}/assign` and `.../reassign`: an
+//!   `{"assignee": .., "due_at": ..}` body, through
+//!   [`AetherisMqtt::assign_anomaly`]; assigning refuses an assigned anomaly
+//!   and reassigning an unassigned one. `POST .../assignment` takes a
+//!   `{"state": ..}` body through [`AetherisMqtt::update_assignment`]. All
+//!   three require the bearer token.
+//! - `POST /api/zones/{zone_id}`: a `{"mode": .., "until": ..}` body, through
+//!   [`AetherisMqtt::set_zone_mode`]; requires the bearer token
+//! - `GET /ws`: telemetry, heartbeats and alerts as they arrive
+//!
+//! The websocket fan-out goes through a broadcast channel: forwarding never
+//! waits, and a client that falls behind skips events (and is told how
+//! many) instead of stalling the MQTT loop.
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use aetheris_shared::{
+    AnomalyReport, AnomalyStatus, AssignmentState, BroadcastResult, Command, DeadLetter, ErrorKind,
+    Heartbeat, Position, RobotState, SectionHealthReport, SeverityLevel, TimelineEntry, ZoneMode,
+};
+use axum::Json;
+use axum::Router;
+use axum::body::Bytes;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, State};
+use axum::http::header::{AUTHORIZATION, CONTENT_TYPE};
+use axum::http::{HeaderMap, HeaderValue, Method, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tracing::{debug, error, info};
+
+use crate::acks::CommandUpdate;
+use crate::alarms::AlarmState;
+use crate::config::{CheckConfig, ConfigChecker};
+use crate::decision::{Decision, PolicyKind};
+#[cfg(feature = "sqlite")]
+use crate::query::{ExportFormat, QueryEngine, QueryError, QueryLimits};
+use crate::rollout::RolloutProgress;
+use crate::sections::SectionError;
+use crate::sensor_health::ChannelHealth;
+use crate::shutdown::Shutdown;
+use crate::store_forward::PendingCommand;
+use crate::telemetry_store::{DEFAULT_HISTORY_LIMIT, HistoryKind, HistoryQuery, HistoryRecord};
+use crate::timeline::TimelineFocus;
+use crate::transport::Secret;
+use crate::trends::RobotTrends;
+use crate::wall_thickness::SectionWallTrend;
+use crate::zones::ZoneStatus;
+use crate::{AetherisMqtt, EngineMessage, FleetSummary};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Dashboard bridge settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpConfig {
+    /// Address to serve on; no bridge when unset
+    pub listen: Option<SocketAddr>,
+    /// Origins browsers may call the bridge from; `*` allows any
+    pub allowed_origins: Vec<String>,
+    /// Bearer token `POST /commands` requires; commands are refused without
+    /// one
+    pub command_token: Option<Secret>,
+    /// Events buffered per websocket client before it starts skipping
+    pub stream_capacity: usize,
+    /// History database `POST /api/query` reads (`sqlite` feature); no
+    /// queries when unset
+    pub query_database: Option<PathBuf>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            listen: None,
+            allowed_origins: Vec::new(),
+            command_token: None,
+            stream_capacity: 256,
+            query_database: None,
+        }
+    }
+}
+
+impl CheckConfig for HttpConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if self.listen.is_some_and(|addr| addr.port() == 0) {
+            checker.error("listen", "needs a port", None);
+        }
+        for (i, origin) in self.allowed_origins.iter().enumerate() {
+            let is_url = origin.starts_with("http://") || origin.starts_with("https://");
+            if origin != "*" && (!is_url || HeaderValue::from_str(origin).is_err()) {
+                checker.error(
+                    &format!("allowed_origins[{i}]"),
+                    format!("\"{origin}\" is not an origin"),
+                    Some("use scheme://host[:port], or * for any".into()),
+                );
+            }
+        }
+        if self
+            .command_token
+            .as_ref()
+            .is_some_and(|token| token.expose().is_empty())
+        {
+            checker.error("command_token", "must not be empty", None);
+        }
+        if self.stream_capacity == 0 {
+            checker.error("stream_capacity", "must be at least 1", None);
+        }
+        match &self.query_database {
+            Some(path) if path.as_os_str().is_empty() => {
+                checker.error(
+                    "query_database",
+                    "must not be empty",
+                    Some("leave it unset".into()),
+                );
+            }
+            Some(_) if cfg!(not(feature = "sqlite")) => {
+                checker.error(
+                    "query_database",
+                    "needs the engine built with the `sqlite` feature",
+                    Some("leave it unset to disable queries".into()),
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+// ============================================================================
+// EVENT STREAM
+// ============================================================================
+
+/// An event sent to websocket clients
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "type", content = "data")]
+pub enum StreamEvent {
+    Telemetry(RobotState),
+    Heartbeat(Heartbeat),
+    Alert(Box<AnomalyReport>),
+    BroadcastResult(BroadcastResult),
+    CommandStatus(CommandUpdate),
+    /// The client fell behind and `skipped` events were dropped for it
+    Lagged {
+        skipped: u64,
+    },
+}
+
+impl StreamEvent {
+    /// The event a message is streamed as, if it is streamed at all
+    pub fn from_message(message: &EngineMessage) -> Option<Self> {
+        match message {
+            EngineMessage::TelemetryReceived(state) => Some(Self::Telemetry(state.clone())),
+            EngineMessage::HeartbeatReceived(heartbeat) => Some(Self::Heartbeat(heartbeat.clone())),
+            EngineMessage::AlertReceived(report) => Some(Self::Alert(Box::new(report.clone()))),
+            EngineMessage::BroadcastCompleted(result) => {
+                Some(Self::BroadcastResult(result.clone()))
+            }
+            EngineMessage::CommandStatusChanged(update) => {
+                Some(Self::CommandStatus(update.clone()))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Fan-out of engine messages to every websocket client
+#[derive(Debug, Clone)]
+pub struct EventStream(broadcast::Sender<StreamEvent>);
+
+impl EventStream {
+    pub fn new(capacity: usize) -> Self {
+        Self(broadcast::channel(capacity).0)
+    }
+
+    /// Hand a message to the connected clients; never waits
+    pub fn forward(&self, message: &EngineMessage) {
+        if self.0.receiver_count() == 0 {
+            return;
+        }
+        if let Some(event) = StreamEvent::from_message(message) {
+            let _ = self.0.send(event);
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<StreamEvent> {
+        self.0.subscribe()
+    }
+}
+
+// ============================================================================
+// ROUTES
+// ============================================================================
+
+#[derive(Clone)]
+struct BridgeState {
+    mqtt: Arc<AetherisMqtt>,
+    stream: EventStream,
+    command_token: Option<Secret>,
+    #[cfg(feature = "sqlite")]
+    queries: Option<Arc<std::sync::Mutex<QueryEngine>>>,
+    shutdown: Shutdown,
+}
+
+/// The bridge's routes, with CORS for the configured origins
+pub fn router(
+    mqtt: Arc<AetherisMqtt>,
+    stream: EventStream,
+    config: &HttpConfig,
+    shutdown: Shutdown,
+) -> Router {
+    let origins = if config.allowed_origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(
+            config
+                .allowed_origins
+                .iter()
+                .filter_map(|origin| HeaderValue::from_str(origin).ok()),
+        )
+    };
+    let cors = CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods([Method::GET, Method::POST])
+        .allow_headers([AUTHORIZATION, CONTENT_TYPE]);
+
+    #[cfg(feature = "sqlite")]
+    let queries = config.query_database.as_ref().and_then(|path| {
+        match QueryEngine::open(path, QueryLimits::default()) {
+            Ok(engine) => Some(Arc::new(std::sync::Mutex::new(engine))),
+            Err(e) => {
+                error!(path = %path.display(), "Failed to open the query database: {}", e);
+                None
+            }
+        }
+    });
+
+    let router = Router::new()
+        .route("/fleet", get(fleet))
+        .route("/fleet/{robot_id}", get(robot))
+        .route("/api/robots/{robot_id}/trends", get(robot_trends))
+        .route("/api/robots/{robot_id}/pending", get(pending_commands))
+        .route("/api/fleet/summary", get(fleet_summary))
+        .route("/anomalies", get(anomalies))
+        .route("/sections/health", get(section_health))
+        .route("/sections/wall-thickness", get(wall_thickness))
+        .route("/deadletters", get(dead_letters))
+        .route("/history", get(history))
+        .route("/api/environment/alarms", get(environment_alarms))
+        .route("/api/environment/sensor-health", get(sensor_health))
+        .route("/api/decisions", get(decisions))
+        .route("/api/rollouts/{rollout_id}", get(rollout))
+        .route("/api/timeline", get(timeline))
+        .route("/api/zones", get(zones))
+        .route("/commands/{robot_id}", post(command))
+        .route("/api/sections", post(register_section))
+        .route("/api/anomalies/{anomaly_id}/assign", post(assign))
+        .route("/api/anomalies/{anomaly_id}/reassign", post(reassign))
+        .route(
+            "/api/anomalies/{anomaly_id}/assignment",
+            post(update_assignment),
+        )
+        .route("/api/zones/{zone_id}", post(set_zone_mode))
+        .route("/ws", get(websocket));
+    #[cfg(feature = "sqlite")]
+    let router = router
+        .route("/api/query", post(query))
+        .route("/api/query/export", post(export));
+    router.layer(cors).with_state(BridgeState {
+        mqtt,
+        stream,
+        command_token: config.command_token.clone(),
+        #[cfg(feature = "sqlite")]
+        queries,
+        shutdown,
+    })
+}
+
+async fn fleet(State(state): State<BridgeState>) -> Json<Vec<RobotState>> {
+    let mut robots = state.mqtt.fleet().get_all_robots();
+    robots.sort_by(|a, b| a.id.cmp(&b.id));
+    Json(robots)
+}
+
+async fn robot(
+    State(state): State<BridgeState>,
+    Path(robot_id): Path<String>,
+) -> Result<Json<RobotState>, StatusCode> {
+    let robot = state.mqtt.fleet().get_robot(&robot_id);
+    robot.map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn robot_trends(
+    State(state): State<BridgeState>,
+    Path(robot_id): Path<String>,
+) -> Result<Json<Option<RobotTrends>>, StatusCode> {
+    if state.mqtt.fleet().get_robot(&robot_id).is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(Json(state.mqtt.trends().read().await.trends(&robot_id)))
+}
+
+async fn pending_commands(
+    State(state): State<BridgeState>,
+    Path(robot_id): Path<String>,
+) -> Result<Json<Vec<PendingCommand>>, StatusCode> {
+    if state.mqtt.fleet().get_robot(&robot_id).is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(Json(state.mqtt.pending_commands(&robot_id).await))
+}
+
+async fn fleet_summary(State(state): State<BridgeState>) -> Json<FleetSummary> {
+    Json(state.mqtt.summary().await)
+}
+
+/// Filters of `GET /anomalies`; each one left out matches everything
+#[derive(Debug, Deserialize)]
+struct AnomalyFilter {
+    status: Option<AnomalyStatus>,
+    severity: Option<SeverityLevel>,
+}
+
+async fn anomalies(
+    State(state): State<BridgeState>,
+    Query(filter): Query<AnomalyFilter>,
+) -> Json<Vec<AnomalyReport>> {
+    let anomalies = state.mqtt.anomalies();
+    let anomalies = anomalies.read().await;
+    Json(
+        anomalies
+            .all()
+            .into_iter()
+            .map(|anomaly| &anomaly.primary)
+            .filter(|report| filter.status.is_none_or(|status| report.status == status))
+            .filter(|report| {
+                filter
+                    .severity
+                    .is_none_or(|severity| report.severity == severity)
+            })
+            .cloned()
+            .collect(),
+    )
+}
+
+/// Window and filters of `GET /history`: the last `hours` (one by
+/// default) unless `from` and `to` are given
+#[derive(Debug, Deserialize)]
+struct HistoryFilter {
+    hours: Option<f64>,
+    /// Unix timestamp (milliseconds)
+    from: Option<u64>,
+    /// Unix timestamp (milliseconds)
+    to: Option<u64>,
+    robot_id: Option<String>,
+    section_id: Option<String>,
+    kind: Option<HistoryKind>,
+    limit: Option<usize>,
+}
+
+async fn history(
+    State(state): State<BridgeState>,
+    Query(filter): Query<HistoryFilter>,
+) -> Result<Json<Vec<HistoryRecord>>, Response> {
+    let now = state.mqtt.now_ms();
+    let hours = filter.hours.unwrap_or(1.0);
+    if !(hours.is_finite() && hours > 0.0) {
+        return Err((StatusCode::BAD_REQUEST, "hours must be positive").into_response());
+    }
+    let to = filter.to.unwrap_or(now);
+    let from = filter
+        .from
+        .unwrap_or_else(|| to.saturating_sub((hours * 3_600_000.0) as u64));
+    let mut query = HistoryQuery::range(from, to);
+    query.robot_id = filter.robot_id;
+    query.section_id = filter.section_id;
+    query.kinds.extend(filter.kind);
+    if let Some(limit) = filter.limit {
+        // Results are buffered whole, so a client may not ask for more
+        if limit > DEFAULT_HISTORY_LIMIT {
+            let reason = format!("limit must be at most {DEFAULT_HISTORY_LIMIT}");
+            return Err((StatusCode::BAD_REQUEST, reason).into_response());
+        }
+        query.limit = limit;
+    }
+    state
+        .mqtt
+        .telemetry_history(&query)
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response())
+}
+
+async fn section_health(State(state): State<BridgeState>) -> Json<Vec<SectionHealthReport>> {
+    let health = state.mqtt.section_health();
+    let health = health.read().await;
+    Json(health.all().into_iter().cloned().collect())
+}
+
+async fn wall_thickness(State(state): State<BridgeState>) -> Json<Vec<SectionWallTrend>> {
+    Json(state.mqtt.wall_trends().read().await.snapshot())
+}
+
+async fn environment_alarms(State(state): State<BridgeState>) -> Json<Vec<AlarmState>> {
+    Json(state.mqtt.alarms().read().await.snapshot())
+}
+
+async fn sensor_health(State(state): State<BridgeState>) -> Json<Vec<ChannelHealth>> {
+    Json(state.mqtt.sensor_health().read().await.snapshot())
+}
+
+/// Filters of `GET /api/decisions`; each one left out matches everything
+#[derive(Debug, Deserialize)]
+struct DecisionFilter {
+    /// Unix timestamp (milliseconds)
+    since: Option<u64>,
+    policy: Option<PolicyKind>,
+}
+
+async fn decisions(
+    State(state): State<BridgeState>,
+    Query(filter): Query<DecisionFilter>,
+) -> Json<Vec<Decision>> {
+    let decisions = state.mqtt.decisions();
+    let decisions = decisions.read().await;
+    Json(
+        decisions
+            .query(filter.since, filter.policy)
+            .into_iter()
+            .cloned()
+            .collect(),
+    )
+}
+
+async fn rollout(
+    State(state): State<BridgeState>,
+    Path(rollout_id): Path<String>,
+) -> Result<Json<RolloutProgress>, StatusCode> {
+    let progress = state.mqtt.rollouts().read().await.progress(&rollout_id);
+    progress.map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Window and focus of `GET /api/timeline`: exactly one of `anomaly`,
+/// `section_id` and `robot_id`
+#[derive(Debug, Deserialize)]
+struct TimelineFilter {
+    /// Unix timestamp (milliseconds)
+    from: u64,
+    /// Unix timestamp (milliseconds)
+    to: u64,
+    anomaly: Option<String>,
+    section_id: Option<String>,
+    robot_id: Option<String>,
+}
+
+async fn timeline(
+    State(state): State<BridgeState>,
+    Query(filter): Query<TimelineFilter>,
+) -> Result<Json<Vec<TimelineEntry>>, Response> {
+    let focus = match (filter.anomaly, filter.section_id, filter.robot_id) {
+        (Some(anomaly), None, None) => TimelineFocus::Anomaly(anomaly),
+        (None, Some(section_id), None) => TimelineFocus::Section(section_id),
+        (None, None, Some(robot_id)) => TimelineFocus::Robot(robot_id),
+        _ => {
+            let message = "give one of anomaly, section_id and robot_id";
+            return Err((StatusCode::BAD_REQUEST, message).into_response());
+        }
+    };
+    if filter.from > filter.to {
+        return Err((StatusCode::BAD_REQUEST, "from must not be after to").into_response());
+    }
+    Ok(Json(
+        state.mqtt.timeline(filter.from, filter.to, &focus).await,
+    ))
+}
+
+async fn zones(State(state): State<BridgeState>) -> Json<Vec<ZoneStatus>> {
+    Json(state.mqtt.zones().read().await.status())
+}
+
+/// Body of `POST /api/query`
+#[cfg(feature = "sqlite")]
+#[derive(Debug, Deserialize)]
+struct QueryRequest {
+    sql: String,
+}
+
+#[cfg(feature = "sqlite")]
+async fn query(State(state): State<BridgeState>, body: Bytes) -> Response {
+    let Some(queries) = state.queries else {
+        return (StatusCode::SERVICE_UNAVAILABLE, "queries are disabled").into_response();
+    };
+    let request: QueryRequest = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(e) => return (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()).into_response(),
+    };
+
+    // SQLite blocks; the statement timeout bounds how long
+    let result = tokio::task::spawn_blocking(move || {
+        let engine = queries.lock().unwrap_or_else(|e| e.into_inner());
+        engine.run(&request.sql)
+    })
+    .await;
+    match result {
+        Ok(Ok(result)) => Json(result).into_response(),
+        Ok(Err(e)) => query_failure(e),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn query_failure(e: QueryError) -> Response {
+    let status = match e {
+        QueryError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+        QueryError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        _ => StatusCode::BAD_REQUEST,
+    };
+    (status, e.to_string()).into_response()
+}
+
+/// Encoding of `POST /api/query/export`, CSV by default
+#[cfg(feature = "sqlite")]
+#[derive(Debug, Deserialize)]
+struct ExportParams {
+    format: Option<ExportFormat>,
+}
+
+/// Export chunks waiting for a client; the export blocks beyond that
+#[cfg(feature = "sqlite")]
+const EXPORT_CHUNKS_IN_FLIGHT: usize = 4;
+
+#[cfg(feature = "sqlite")]
+async fn export(
+    State(state): State<BridgeState>,
+    Query(params): Query<ExportParams>,
+    body: Bytes,
+) -> Response {
+    let Some(queries) = state.queries else {
+        return (StatusCode::SERVICE_UNAVAILABLE, "queries are disabled").into_response();
+    };
+    let request: QueryRequest = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(e) => return (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()).into_response(),
+    };
+    let format = params.format.unwrap_or(ExportFormat::Csv);
+
+    let (chunks, mut sent) = tokio::sync::mpsc::channel(EXPORT_CHUNKS_IN_FLIGHT);
+    let runtime = tokio::runtime::Handle::current();
+    let exported = tokio::task::spawn_blocking(move || {
+        let engine = queries.lock().unwrap_or_else(|e| e.into_inner());
+        let mut sink = ChunkSink {
+            chunks,
+            runtime,
+            timeout: engine.limits().timeout,
+            started: false,
+        };
+        let exported = engine.export(&request.sql, format, &mut sink);
+        if let Err(e) = &exported {
+            sink.abort(e);
+        }
+        exported
+    });
+
+    // Until the first chunk the export can still be refused with a status
+    let Some(first) = sent.recv().await else {
+        return match exported.await {
+            Ok(Ok(_)) => ([(CONTENT_TYPE, format.content_type())], "").into_response(),
+            Ok(Err(e)) => query_failure(e),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        };
+    };
+    let rest = futures_util::stream::unfold(sent, |mut sent| async move {
+        sent.recv().await.map(|chunk| (chunk, sent))
+    });
+    let body = axum::body::Body::from_stream(futures_util::StreamExt::chain(
+        futures_util::stream::iter([first]),
+        rest,
+    ));
+    ([(CONTENT_TYPE, format.content_type())], body).into_response()
+}
+
+/// Hands export chunks to the response body, waiting at most `timeout` for
+/// the client to make room for each
+#[cfg(feature = "sqlite")]
+struct ChunkSink {
+    chunks: tokio::sync::mpsc::Sender<std::io::Result<Bytes>>,
+    runtime: tokio::runtime::Handle,
+    timeout: std::time::Duration,
+    /// Whether the response has begun
+    started: bool,
+}
+
+#[cfg(feature = "sqlite")]
+impl ChunkSink {
+    fn send(&mut self, chunk: std::io::Result<Bytes>) -> std::io::Result<()> {
+        let send = tokio::time::timeout(self.timeout, self.chunks.send(chunk));
+        match self.runtime.block_on(send) {
+            Ok(Ok(())) => {
+                self.started = true;
+                Ok(())
+            }
+            Ok(Err(_)) => Err(std::io::Error::new(
+                std::io::ErrorKind::BrokenPipe,
+                "the client went away",
+            )),
+            Err(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "the client stopped reading",
+            )),
+        }
+    }
+
+    /// Cut a begun response short instead of ending it as if complete
+    fn abort(&mut self, e: &QueryError) {
+        if self.started {
+            let _ = self.send(Err(std::io::Error::other(e.to_string())));
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl std::io::Write for ChunkSink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.send(Ok(Bytes::copy_from_slice(buf)))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+async fn dead_letters(State(state): State<BridgeState>) -> Json<Vec<DeadLetter>> {
+    Json(state.mqtt.dead_letters())
+}
+
+/// The refusal of a request that may change engine state without the command token
+fn refusal(state: &BridgeState, headers: &HeaderMap) -> Option<Response> {
+    let Some(token) = &state.command_token else {
+        return Some((StatusCode::FORBIDDEN, "commands are disabled").into_response());
+    };
+    (!authorized(headers, token)).then(|| StatusCode::UNAUTHORIZED.into_response())
+}
+
+/// Whether the request carries the command token, compared in constant time
+fn authorized(headers: &HeaderMap, token: &Secret) -> bool {
+    let Some(presented) = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+    let (presented, token) = (presented.as_bytes(), token.expose().as_bytes());
+    presented.len() == token.len()
+        && presented
+            .iter()
+            .zip(token)
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+async fn command(
+    State(state): State<BridgeState>,
+    Path(robot_id): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if let Some(refused) = refusal(&state, &headers) {
+        return refused;
+    }
+    let command: Command = match serde_json::from_slice(&body) {
+        Ok(command) => command,
+        Err(e) => return (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()).into_response(),
+    };
+
+    info!(robot_id = %robot_id, command = command.name(), "Command from the dashboard bridge");
+    match state.mqtt.send_command(&robot_id, command).await {
+        Ok(command_id) => (StatusCode::ACCEPTED, command_id).into_response(),
+        Err(e) => {
+            let status = match e.kind() {
+                Some(ErrorKind::Rejected) => StatusCode::CONFLICT,
+                Some(ErrorKind::Transport) => StatusCode::BAD_GATEWAY,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            (status, e.to_string()).into_response()
+        }
+    }
+}
+
+/// Body of `POST /api/sections`, as in [`Command::RegisterSection`]
+#[derive(Debug, Deserialize)]
+struct SectionRegistration {
+    section_id: String,
+    position: Position,
+    merge_from: Option<String>,
+}
+
+async fn register_section(
+    State(state): State<BridgeState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if let Some(refused) = refusal(&state, &headers) {
+        return refused;
+    }
+    let registration: SectionRegistration = match serde_json::from_slice(&body) {
+        Ok(registration) => registration,
+        Err(e) => return (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()).into_response(),
+    };
+
+    let registered = state
+        .mqtt
+        .register_section(
+            &registration.section_id,
+            registration.position,
+            registration.merge_from.as_deref(),
+        )
+        .await;
+    match registered {
+        Ok(section) => (StatusCode::CREATED, Json(section)).into_response(),
+        Err(e) => {
+            let status = match e {
+                SectionError::Unknown(_) => StatusCode::NOT_FOUND,
+                SectionError::NotProvisional(_) | SectionError::SelfMerge(_) => {
+                    StatusCode::CONFLICT
+                }
+            };
+            (status, e.to_string()).into_response()
+        }
+    }
+}
+
+/// Body of `POST /api/anomalies/{anomaly_id}/assign` and `.../reassign`, as
+/// in [`Command::AssignAnomaly`]
+#[derive(Debug, Deserialize)]
+struct AssignmentRequest {
+    assignee: String,
+    /// Unix timestamp (milliseconds)
+    due_at: u64,
+}
+
+/// Body of `POST /api/anomalies/{anomaly_id}/assignment`, as in
+/// [`Command::UpdateAssignment`]
+#[derive(Debug, Deserialize)]
+struct AssignmentUpdate {
+    state: AssignmentState,
+}
+
+/// Who the bridge records as making assignments
+const BRIDGE_SOURCE: &str = "dashboard";
+
+async fn assign(
+    State(state): State<BridgeState>,
+    Path(anomaly_id): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    change_assignment(state, anomaly_id, headers, body, false).await
+}
+
+async fn reassign(
+    State(state): State<BridgeState>,
+    Path(anomaly_id): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    change_assignment(state, anomaly_id, headers, body, true).await
+}
+
+async fn change_assignment(
+    state: BridgeState,
+    anomaly_id: String,
+    headers: HeaderMap,
+    body: Bytes,
+    reassigning: bool,
+) -> Response {
+    if let Some(refused) = refusal(&state, &headers) {
+        return refused;
+    }
+    let request: AssignmentRequest = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(e) => return (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()).into_response(),
+    };
+    let assigned = {
+        let anomalies = state.mqtt.anomalies();
+        let anomalies = anomalies.read().await;
+        anomalies
+            .all()
+            .into_iter()
+            .find(|anomaly| anomaly.contains(&anomaly_id))
+            .map(|anomaly| anomaly.primary.assignment.is_some())
+    };
+    match assigned {
+        None => return StatusCode::NOT_FOUND.into_response(),
+        Some(true) if !reassigning => {
+            let message = "already assigned; reassign it instead";
+            return (StatusCode::CONFLICT, message).into_response();
+        }
+        Some(false) if reassigning => {
+            return (StatusCode::CONFLICT, "not assigned").into_response();
+        }
+        Some(_) => {}
+    }
+
+    let changed = state
+        .mqtt
+        .assign_anomaly(
+            &anomaly_id,
+            &request.assignee,
+            BRIDGE_SOURCE,
+            request.due_at,
+        )
+        .await;
+    respond_to_change(changed)
+}
+
+async fn update_assignment(
+    State(state): State<BridgeState>,
+    Path(anomaly_id): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if let Some(refused) = refusal(&state, &headers) {
+        return refused;
+    }
+    let update: AssignmentUpdate = match serde_json::from_slice(&body) {
+        Ok(update) => update,
+        Err(e) => return (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()).into_response(),
+    };
+    let changed = state
+        .mqtt
+        .update_assignment(&anomaly_id, update.state)
+        .await;
+    respond_to_change(changed)
+}
+
+/// No content for an accepted change, or why it was refused
+fn respond_to_change(changed: crate::Result<()>) -> Response {
+    match changed {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) if e.kind() == Some(ErrorKind::Rejected) => {
+            (StatusCode::CONFLICT, e.to_string()).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// Body of `POST /api/zones/{zone_id}`, as in [`Command::SetZoneMode`]
+#[derive(Debug, Deserialize)]
+struct ZoneModeChange {
+    mode: ZoneMode,
+    /// Unix timestamp the mode reverts to Normal (milliseconds)
+    until: Option<u64>,
+}
+
+async fn set_zone_mode(
+    State(state): State<BridgeState>,
+    Path(zone_id): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if let Some(refused) = refusal(&state, &headers) {
+        return refused;
+    }
+    let change: ZoneModeChange = match serde_json::from_slice(&body) {
+        Ok(change) => change,
+        Err(e) => return (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()).into_response(),
+    };
+    let known = {
+        let zones = state.mqtt.zones();
+        let zones = zones.read().await;
+        zones.zones().iter().any(|zone| zone.id == zone_id)
+    };
+    if !known {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let changed = state
+        .mqtt
+        .set_zone_mode(&zone_id, change.mode, change.until)
+        .await;
+    respond_to_change(changed)
+}
+
+async fn websocket(State(state): State<BridgeState>, upgrade: WebSocketUpgrade) -> Response {
+    let events = state.stream.subscribe();
+    upgrade.on_upgrade(move |socket| stream_events(socket, events, state.shutdown))
+}
+
+/// Send events to one client until it leaves or the engine shuts down
+async fn stream_events(
+    mut socket: WebSocket,
+    mut events: broadcast::Receiver<StreamEvent>,
+    mut shutdown: Shutdown,
+) {
+    loop {
+        let event = tokio::select! {
+            event = events.recv() => event,
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_)) | Err(_)) | None => return,
+                Some(Ok(_)) => continue,
+            },
+            _ = shutdown.wait() => {
+                let _ = socket.send(Message::Close(None)).await;
+                return;
+            }
+        };
+        let event = match event {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                debug!(skipped, "Dashboard client fell behind");
+                StreamEvent::Lagged { skipped }
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+        let Ok(text) = serde_json::to_string(&event) else {
+            continue;
+        };
+        if socket.send(Message::Text(text.into())).await.is_err() {
+            return;
+        }
+    }
+}
+
+// ============================================================================
+// SERVER
+// ============================================================================
+
+/// Binds the bridge on `addr` and spawns the task serving it until the
+/// shutdown
+pub async fn spawn_bridge(
+    mqtt: Arc<AetherisMqtt>,
+    stream: EventStream,
+    config: &HttpConfig,
+    addr: SocketAddr,
+    shutdown: Shutdown,
+) -> std::io::Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(addr).await?;
+    let app = router(mqtt, stream, config, shutdown.clone());
+    let mut stop = shutdown;
+    Ok(tokio::spawn(async move {
+        let server =
+            axum::serve(listener, app).with_graceful_shutdown(async move { stop.wait().await });
+        if let Err(e) = server.await {
+            error!("Dashboard bridge failed: {}", e);
+        }
+    }))
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MqttConfig;
+    use crate::sections::{SectionInfo, SectionRegistry, UnknownSectionPolicy};
+    use crate::zones::{Zone, ZoneConfig, ZoneRegistry};
+    use aetheris_shared::{PipeEnvironment, RobotStatus, RobotType, Timestamp};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+    use tokio::sync::mpsc;
+
+    /// Status line and body of a request sent over a fresh connection
+    async fn request(addr: SocketAddr, head: &str, body: &str) -> (String, String) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "{head}\r\nHost: localhost\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        (head.lines().next().unwrap().to_string(), body.to_string())
+    }
+
+    /// Serve the bridge on an ephemeral port until `trigger` fires
+    async fn serve(
+        mqtt: Arc<AetherisMqtt>,
+        config: &HttpConfig,
+    ) -> (SocketAddr, crate::shutdown::ShutdownTrigger, JoinHandle<()>) {
+        let (trigger, shutdown) = crate::shutdown::channel();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = router(mqtt, EventStream::new(1), config, shutdown.clone());
+        let mut stop = shutdown;
+        let server = tokio::spawn(async move {
+            axum::serve(listener, app)
+                .with_graceful_shutdown(async move { stop.wait().await })
+                .await
+                .unwrap();
+        });
+        (addr, trigger, server)
+    }
+
+    /// An engine without a broker, for handlers to read from
+    async fn engine() -> Arc<AetherisMqtt> {
+        let (tx, _rx) = mpsc::channel(10);
+        let (mqtt, _eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+        Arc::new(mqtt)
+    }
+
+    #[tokio::test]
+    async fn test_bridge_serves_fleet_anomalies_and_guarded_commands() {
+        let (tx, _rx) = mpsc::channel(10);
+        let (mqtt, _eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+        let mqtt = Arc::new(mqtt);
+        for id in ["RV-002", "RV-001"] {
+            mqtt.fleet()
+                .update_robot(RobotState::new(id.parse().unwrap(), id, RobotType::Rover));
+        }
+        let config = HttpConfig {
+            command_token: Some(Secret::new("s3cret")),
+            ..HttpConfig::default()
+        };
+        let (addr, trigger, server) = serve(mqtt, &config).await;
+
+        let (status, body) = request(addr, "GET /fleet HTTP/1.1", "").await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        let robots: Vec<RobotState> = serde_json::from_str(&body).unwrap();
+        let ids: Vec<_> = robots.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, ["RV-001", "RV-002"]);
+        let (status, _) = request(addr, "GET /fleet/RV-009 HTTP/1.1", "").await;
+        assert_eq!(status, "HTTP/1.1 404 Not Found");
+
+        let (status, body) = request(addr, "GET /anomalies?severity=high HTTP/1.1", "").await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(body, "[]");
+        for path in ["sections/health", "sections/wall-thickness", "deadletters"] {
+            let line = format!("GET /{path} HTTP/1.1");
+            let (status, body) = request(addr, &line, "").await;
+            assert_eq!(status, "HTTP/1.1 200 OK");
+            assert_eq!(body, "[]");
+        }
+        let (status, _) = request(addr, "GET /anomalies?severity=loud HTTP/1.1", "").await;
+        assert_eq!(status, "HTTP/1.1 400 Bad Request");
+
+        let line = "GET /history?hours=2&robot_id=RV-001&kind=telemetry HTTP/1.1";
+        let (status, body) = request(addr, line, "").await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(body, "[]");
+        let (status, _) = request(addr, "GET /history?hours=-1 HTTP/1.1", "").await;
+        assert_eq!(status, "HTTP/1.1 400 Bad Request");
+        let (status, _) = request(addr, "GET /history?limit=10000 HTTP/1.1", "").await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        let (status, body) = request(addr, "GET /history?limit=100000000 HTTP/1.1", "").await;
+        assert_eq!(status, "HTTP/1.1 400 Bad Request");
+        assert!(body.contains("at most 10000"));
+
+        let stop = r#"{"command":"emergency_stop"}"#;
+        let (status, _) = request(addr, "POST /commands/RV-001 HTTP/1.1", stop).await;
+        assert_eq!(status, "HTTP/1.1 401 Unauthorized");
+        let wrong = "POST /commands/RV-001 HTTP/1.1\r\nAuthorization: Bearer s3cre7";
+        assert_eq!(
+            request(addr, wrong, stop).await.0,
+            "HTTP/1.1 401 Unauthorized"
+        );
+        let authorized = "POST /commands/RV-001 HTTP/1.1\r\nAuthorization: Bearer s3cret";
+        assert_eq!(
+            request(addr, authorized, "{}").await.0,
+            "HTTP/1.1 422 Unprocessable Entity"
+        );
+        assert_eq!(
+            request(addr, authorized, stop).await.0,
+            "HTTP/1.1 202 Accepted"
+        );
+
+        trigger.trigger();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_environment_alarms_are_listed() {
+        let mqtt = engine().await;
+        let hot = PipeEnvironment {
+            section_id: "PIPE-002".into(),
+            pressure: 50.0,
+            temperature: aetheris_shared::limits::TEMPERATURE_ALERT_CELSIUS + 10.0,
+            h2_concentration: 100.0,
+            wall_thickness: 10.0,
+            flow_rate: 500.0,
+            humidity: 45.0,
+            position: Position::origin(),
+            timestamp: Timestamp::from_millis(1_000),
+        };
+        mqtt.alarms().write().await.assess(&hot, 1_000);
+        let (addr, trigger, server) = serve(mqtt, &HttpConfig::default()).await;
+
+        let (status, body) = request(addr, "GET /api/environment/alarms HTTP/1.1", "").await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        let alarms: Vec<serde_json::Value> = serde_json::from_str(&body).unwrap();
+        let raised: Vec<_> = alarms
+            .iter()
+            .filter(|alarm| alarm["status"] == "raised")
+            .map(|alarm| (&alarm["section_id"], &alarm["hazard"]))
+            .collect();
+        assert_eq!(raised, [(&"PIPE-002".into(), &"high_temperature".into())]);
+
+        trigger.trigger();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sensor_health_is_listed_by_section() {
+        let mqtt = engine().await;
+        for section_id in ["PIPE-003", "PIPE-002"] {
+            let reading = PipeEnvironment {
+                section_id: section_id.into(),
+                pressure: 50.0,
+                temperature: 25.0,
+                h2_concentration: 100.0,
+                wall_thickness: 10.0,
+                flow_rate: 500.0,
+                humidity: 45.0,
+                position: Position::origin(),
+                timestamp: Timestamp::from_millis(1_000),
+            };
+            mqtt.sensor_health().write().await.observe(&reading, 1_000);
+        }
+        let (addr, trigger, server) = serve(mqtt, &HttpConfig::default()).await;
+
+        let line = "GET /api/environment/sensor-health HTTP/1.1";
+        let (status, body) = request(addr, line, "").await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        let channels: Vec<serde_json::Value> = serde_json::from_str(&body).unwrap();
+        assert!(!channels.is_empty());
+        assert!(
+            channels
+                .iter()
+                .all(|channel| channel["status"] == "healthy")
+        );
+        let sections: Vec<_> = channels
+            .iter()
+            .map(|channel| channel["section_id"].as_str().unwrap())
+            .collect();
+        assert!(sections.is_sorted());
+        assert_eq!(sections.first(), Some(&"PIPE-002"));
+        assert_eq!(sections.last(), Some(&"PIPE-003"));
+
+        trigger.trigger();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_decisions_are_filtered_by_time_and_policy() {
+        use crate::decision::CandidateEvaluation;
+
+        let mqtt = engine().await;
+        for (policy, timestamp) in [
+            (PolicyKind::AutoDispatch, 100),
+            (PolicyKind::LowBattery, 200),
+            (PolicyKind::AutoDispatch, 300),
+        ] {
+            let candidates = vec![CandidateEvaluation::scored("RV-001", 0.5)];
+            mqtt.decisions()
+                .write()
+                .await
+                .record(Decision::new(policy, "ANM-1", candidates, timestamp));
+        }
+        let (addr, trigger, server) = serve(mqtt, &HttpConfig::default()).await;
+
+        let line = "GET /api/decisions?since=150&policy=auto_dispatch HTTP/1.1";
+        let (status, body) = request(addr, line, "").await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        let decisions: Vec<Decision> = serde_json::from_str(&body).unwrap();
+        let times: Vec<_> = decisions.iter().map(|d| d.timestamp).collect();
+        assert_eq!(times, [300]);
+        assert_eq!(decisions[0].candidates[0].robot_id, "RV-001");
+        let (_, body) = request(addr, "GET /api/decisions HTTP/1.1", "").await;
+        assert_eq!(
+            serde_json::from_str::<Vec<Decision>>(&body).unwrap().len(),
+            3
+        );
+        let line = "GET /api/decisions?policy=coin_flip HTTP/1.1";
+        assert_eq!(request(addr, line, "").await.0, "HTTP/1.1 400 Bad Request");
+
+        trigger.trigger();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_robot_trends_are_served_for_known_robots() {
+        let mqtt = engine().await;
+        mqtt.fleet().update_robot(RobotState::new(
+            "RV-001".parse().unwrap(),
+            "Rover",
+            RobotType::Rover,
+        ));
+        let (addr, trigger, server) = serve(mqtt.clone(), &HttpConfig::default()).await;
+
+        let line = "GET /api/robots/RV-001/trends HTTP/1.1";
+        let (status, body) = request(addr, line, "").await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(body, "null");
+
+        // 4%/min, over more than half the battery window
+        for s in 0..90u64 {
+            let battery = 90.0 - s as f64 * 4.0 / 60.0;
+            mqtt.trends().write().await.observe(
+                "RV-001",
+                battery,
+                80.0,
+                Position::origin(),
+                s * 1000,
+            );
+        }
+        let (status, body) = request(addr, line, "").await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        let trends: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let rate = trends["battery_rate_per_min"].as_f64().unwrap();
+        assert!((rate + 4.0).abs() < 1e-6);
+        assert_eq!(trends["battery_degrading"], true);
+
+        let line = "GET /api/robots/RV-404/trends HTTP/1.1";
+        assert_eq!(request(addr, line, "").await.0, "HTTP/1.1 404 Not Found");
+
+        trigger.trigger();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pending_commands_are_served_oldest_first() {
+        let mqtt = engine().await;
+        let robot_id: aetheris_shared::RobotId = "CR-001".parse().unwrap();
+        mqtt.fleet().update_robot(RobotState::new(
+            robot_id.clone(),
+            "Crawler",
+            RobotType::Crawler,
+        ));
+        {
+            let now = mqtt.now_ms();
+            let store_forward = mqtt.store_forward();
+            let mut store_forward = store_forward.write().await;
+            store_forward.observe_link(&robot_id, 5.0, now);
+            for route in ["A", "B"] {
+                let command = Command::StartPatrol {
+                    route_id: route.into(),
+                };
+                let offered =
+                    store_forward.offer(&robot_id, &format!("CMD-{route}"), command, None, now);
+                assert!(offered.is_ok());
+            }
+        }
+        let (addr, trigger, server) = serve(mqtt, &HttpConfig::default()).await;
+
+        let (status, body) = request(addr, "GET /api/robots/CR-001/pending HTTP/1.1", "").await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        let pending: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let ids: Vec<_> = pending
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|command| command["command_id"].as_str().unwrap())
+            .collect();
+        assert_eq!(ids, ["CMD-A", "CMD-B"]);
+        let line = "GET /api/robots/CR-404/pending HTTP/1.1";
+        assert_eq!(request(addr, line, "").await.0, "HTTP/1.1 404 Not Found");
+
+        trigger.trigger();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_timeline_is_focused_on_the_requested_anomaly() {
+        let mqtt = engine().await;
+        let reports: Vec<_> = ["PIPE-002", "PIPE-007"]
+            .into_iter()
+            .map(|section_id| {
+                AnomalyReport::new(
+                    aetheris_shared::AnomalyType::Leak,
+                    SeverityLevel::High,
+                    Position::origin(),
+                    section_id,
+                    "CR-001",
+                    0.9,
+                    "H2 above threshold",
+                )
+            })
+            .collect();
+        let anomaly_id = reports[0].id.clone();
+        let at = reports[0].timestamp.as_millis();
+        for report in reports {
+            mqtt.anomalies()
+                .write()
+                .await
+                .ingest(report, &SectionRegistry::default());
+        }
+        let (addr, trigger, server) = serve(mqtt, &HttpConfig::default()).await;
+
+        let line = format!(
+            "GET /api/timeline?from={}&to={}&anomaly={anomaly_id} HTTP/1.1",
+            at - 1000,
+            at + 1000
+        );
+        let (status, body) = request(addr, &line, "").await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        let entries: Vec<TimelineEntry> = serde_json::from_str(&body).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].subject, anomaly_id);
+        assert_eq!(entries[0].kind, aetheris_shared::TimelineEntryKind::Anomaly);
+
+        // Outside the window there is nothing to tell
+        let line = format!(
+            "GET /api/timeline?from=0&to={}&anomaly={anomaly_id} HTTP/1.1",
+            at - 1000
+        );
+        let (_, body) = request(addr, &line, "").await;
+        assert_eq!(body, "[]");
+        let line = "GET /api/timeline?from=0&to=1 HTTP/1.1";
+        assert_eq!(request(addr, line, "").await.0, "HTTP/1.1 400 Bad Request");
+        let line = "GET /api/timeline?from=0&to=1&anomaly=a&robot_id=RV-001 HTTP/1.1";
+        assert_eq!(request(addr, line, "").await.0, "HTTP/1.1 400 Bad Request");
+        let line = "GET /api/timeline?from=2&to=1&robot_id=RV-001 HTTP/1.1";
+        assert_eq!(request(addr, line, "").await.0, "HTTP/1.1 400 Bad Request");
+
+        trigger.trigger();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rollout_progress_is_served() {
+        use crate::rollout::{RobotSelector, RolloutPlan};
+
+        let mqtt = engine().await;
+        let fleet = [RobotState::new(
+            "RV-001".parse().unwrap(),
+            "Rover",
+            RobotType::Rover,
+        )];
+        let plan = RolloutPlan {
+            config: aetheris_shared::RobotConfig::default(),
+            selector: RobotSelector::All,
+            batch_size: 1,
+            soak_duration: std::time::Duration::from_secs(60),
+            abort_on_failures: 0,
+        };
+        let (id, _) = mqtt
+            .rollouts()
+            .write()
+            .await
+            .start(plan, &fleet, 1_000)
+            .unwrap();
+        let (addr, trigger, server) = serve(mqtt, &HttpConfig::default()).await;
+
+        let (status, body) = request(addr, &format!("GET /api/rollouts/{id} HTTP/1.1"), "").await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        let progress: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(progress["phase"], "awaiting_ack");
+        assert_eq!(progress["batches"], serde_json::json!([["RV-001"]]));
+        let (status, _) = request(addr, "GET /api/rollouts/ROLL-0 HTTP/1.1", "").await;
+        assert_eq!(status, "HTTP/1.1 404 Not Found");
+
+        trigger.trigger();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_assignments_change_over_rest_and_are_counted() {
+        let mqtt = engine().await;
+        let report = AnomalyReport::new(
+            aetheris_shared::AnomalyType::Leak,
+            SeverityLevel::High,
+            Position::origin(),
+            "PIPE-002",
+            "CR-001",
+            0.9,
+            "H2 above threshold",
+        );
+        let anomaly_id = report.id.clone();
+        mqtt.anomalies()
+            .write()
+            .await
+            .ingest(report, &SectionRegistry::default());
+        let config = HttpConfig {
+            command_token: Some(Secret::new("s3cret")),
+            ..HttpConfig::default()
+        };
+        let (addr, trigger, server) = serve(mqtt.clone(), &config).await;
+        let post = |action: &str| {
+            format!(
+                "POST /api/anomalies/{anomaly_id}/{action} HTTP/1.1\r\nAuthorization: Bearer s3cret"
+            )
+        };
+        let to = |assignee: &str| format!(r#"{{"assignee":"{assignee}","due_at":4102444800000}}"#);
+
+        let line = format!("POST /api/anomalies/{anomaly_id}/assign HTTP/1.1");
+        let (status, _) = request(addr, &line, &to("j.ortega")).await;
+        assert_eq!(status, "HTTP/1.1 401 Unauthorized");
+        let (status, _) = request(addr, &post("reassign"), &to("a.kim")).await;
+        assert_eq!(status, "HTTP/1.1 409 Conflict");
+        let (status, _) = request(addr, &post("assign"), &to("j.ortega")).await;
+        assert_eq!(status, "HTTP/1.1 204 No Content");
+        let (status, _) = request(addr, &post("assign"), &to("a.kim")).await;
+        assert_eq!(status, "HTTP/1.1 409 Conflict");
+        let (status, _) = request(addr, &post("reassign"), &to("a.kim")).await;
+        assert_eq!(status, "HTTP/1.1 204 No Content");
+        let line = "POST /api/anomalies/ANM-0/assign HTTP/1.1\r\nAuthorization: Bearer s3cret";
+        let (status, _) = request(addr, line, &to("a.kim")).await;
+        assert_eq!(status, "HTTP/1.1 404 Not Found");
+
+        let (status, body) = request(addr, "GET /api/fleet/summary HTTP/1.1", "").await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        let summary: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(summary["open_assignments"], serde_json::json!({"a.kim": 1}));
+
+        let (status, _) = request(addr, &post("assignment"), r#"{"state":"done"}"#).await;
+        assert_eq!(status, "HTTP/1.1 204 No Content");
+        assert!(mqtt.summary().await.open_assignments.is_empty());
+        assert!(mqtt.system_status().await.open_assignments.is_empty());
+
+        trigger.trigger();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_zone_modes_are_listed_and_changed_over_rest() {
+        let mqtt = engine().await;
+        *mqtt.zones().write().await = ZoneRegistry::new(ZoneConfig {
+            zones: vec![Zone::new(
+                "HOT-1",
+                Position::new(0.0, 0.0, 0.0),
+                Position::new(10.0, 10.0, 0.0),
+            )],
+            ..ZoneConfig::default()
+        });
+        let config = HttpConfig {
+            command_token: Some(Secret::new("s3cret")),
+            ..HttpConfig::default()
+        };
+        let (addr, trigger, server) = serve(mqtt.clone(), &config).await;
+        let authorized = |zone_id: &str| {
+            format!("POST /api/zones/{zone_id} HTTP/1.1\r\nAuthorization: Bearer s3cret")
+        };
+        let excluded = r#"{"mode":{"mode":"excluded"},"until":4102444800000}"#;
+
+        let (status, body) = request(addr, "GET /api/zones HTTP/1.1", "").await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        let zones: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            zones,
+            serde_json::json!([{"zone_id": "HOT-1", "mode": {"mode": "normal"}, "until": null}])
+        );
+
+        let (status, _) = request(addr, "POST /api/zones/HOT-1 HTTP/1.1", excluded).await;
+        assert_eq!(status, "HTTP/1.1 401 Unauthorized");
+        let (status, _) = request(addr, &authorized("HOT-9"), excluded).await;
+        assert_eq!(status, "HTTP/1.1 404 Not Found");
+        // Validated like a SetZoneMode command: the expiry must lie ahead
+        let past = r#"{"mode":{"mode":"excluded"},"until":1}"#;
+        let (status, _) = request(addr, &authorized("HOT-1"), past).await;
+        assert_eq!(status, "HTTP/1.1 409 Conflict");
+        let (status, _) = request(addr, &authorized("HOT-1"), excluded).await;
+        assert_eq!(status, "HTTP/1.1 204 No Content");
+
+        let (_, body) = request(addr, "GET /api/zones HTTP/1.1", "").await;
+        let zones: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(zones[0]["mode"], serde_json::json!({"mode": "excluded"}));
+        assert_eq!(zones[0]["until"], 4102444800000u64);
+        let (_, body) = request(addr, "GET /api/fleet/summary HTTP/1.1", "").await;
+        let summary: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(summary["zones"], zones);
+
+        trigger.trigger();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sections_are_registered_and_merged() {
+        let mqtt = engine().await;
+        {
+            let sections = mqtt.sections();
+            let mut sections = sections.write().await;
+            *sections = SectionRegistry::new(UnknownSectionPolicy::Provisional);
+            sections
+                .resolve("PIPE-0O3", Position::new(5.0, 0.0, 0.0))
+                .unwrap();
+        }
+        let config = HttpConfig {
+            command_token: Some(Secret::new("s3cret")),
+            ..HttpConfig::default()
+        };
+        let (addr, trigger, server) = serve(mqtt.clone(), &config).await;
+
+        let merge = r#"{"section_id":"PIPE-003","position":{"x":5.0,"y":0.0,"z":0.0},"merge_from":"PIPE-0O3"}"#;
+        let (status, _) = request(addr, "POST /api/sections HTTP/1.1", merge).await;
+        assert_eq!(status, "HTTP/1.1 401 Unauthorized");
+        let authorized = "POST /api/sections HTTP/1.1\r\nAuthorization: Bearer s3cret";
+        let (status, body) = request(addr, authorized, merge).await;
+        assert_eq!(status, "HTTP/1.1 201 Created");
+        let section: SectionInfo = serde_json::from_str(&body).unwrap();
+        assert_eq!(section.id, "PIPE-003");
+        assert!(!section.provisional);
+        assert_eq!(
+            mqtt.sections().read().await.canonical_id("PIPE-0O3"),
+            "PIPE-003"
+        );
+
+        // The provisional section is gone, and a canonical one cannot be merged
+        let (status, _) = request(addr, authorized, merge).await;
+        assert_eq!(status, "HTTP/1.1 404 Not Found");
+        let again = r#"{"section_id":"PIPE-004","position":{"x":9.0,"y":0.0,"z":0.0},"merge_from":"PIPE-003"}"#;
+        let (status, _) = request(addr, authorized, again).await;
+        assert_eq!(status, "HTTP/1.1 409 Conflict");
+        assert!(mqtt.sections().read().await.get("PIPE-004").is_none());
+        let (status, _) = request(addr, authorized, r#"{"section_id":"PIPE-005"}"#).await;
+        assert_eq!(status, "HTTP/1.1 422 Unprocessable Entity");
+
+        trigger.trigger();
+        server.await.unwrap();
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_queries_run_read_only_against_the_history_database() {
+        use crate::persistence::FleetStore;
+
+        let path =
+            std::env::temp_dir().join(format!("aetheris-bridge-{}.db", uuid::Uuid::new_v4()));
+        let store = FleetStore::open(&path).unwrap();
+        for (id, battery) in [("RV-001", 80.0), ("RV-002", 60.0)] {
+            let mut state = RobotState::new(id.parse().unwrap(), id, RobotType::Rover);
+            state.battery = battery;
+            store.record_robot_state(&state).unwrap();
+        }
+        let config = HttpConfig {
+            query_database: Some(path.clone()),
+            ..HttpConfig::default()
+        };
+        let (addr, trigger, server) = serve(engine().await, &config).await;
+
+        let sql =
+            serde_json::json!({"sql": "SELECT AVG(battery) AS avg_battery FROM v_robots_history"});
+        let (status, body) = request(addr, "POST /api/query HTTP/1.1", &sql.to_string()).await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        let result: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            result,
+            serde_json::json!({"columns": ["avg_battery"], "rows": [[70.0]], "truncated": false})
+        );
+        let update = serde_json::json!({"sql": "UPDATE robot_states SET battery = 0"});
+        let (status, _) = request(addr, "POST /api/query HTTP/1.1", &update.to_string()).await;
+        assert_eq!(status, "HTTP/1.1 400 Bad Request");
+        trigger.trigger();
+        server.await.unwrap();
+
+        // Without a database the endpoint answers but runs nothing
+        let (addr, trigger, server) = serve(engine().await, &HttpConfig::default()).await;
+        let (status, _) = request(addr, "POST /api/query HTTP/1.1", &sql.to_string()).await;
+        assert_eq!(status, "HTTP/1.1 503 Service Unavailable");
+        trigger.trigger();
+        server.await.unwrap();
+
+        drop(store);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+        }
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_exports_stream_the_rows_of_a_query() {
+        use crate::persistence::FleetStore;
+
+        /// Body of a chunked response, reassembled
+        fn dechunk(mut body: &str) -> String {
+            let mut joined = String::new();
+            loop {
+                let (size, rest) = body.split_once("\r\n").unwrap();
+                let size = usize::from_str_radix(size, 16).unwrap();
+                if size == 0 {
+                    return joined;
+                }
+                joined.push_str(&rest[..size]);
+                body = &rest[size + 2..];
+            }
+        }
+
+        let path =
+            std::env::temp_dir().join(format!("aetheris-export-{}.db", uuid::Uuid::new_v4()));
+        let store = FleetStore::open(&path).unwrap();
+        for i in 0..1_200 {
+            let mut state = RobotState::new("RV-001".parse().unwrap(), "Rover", RobotType::Rover);
+            state.battery = (i % 100) as f64;
+            store.record_robot_state(&state).unwrap();
+        }
+        let config = HttpConfig {
+            query_database: Some(path.clone()),
+            ..HttpConfig::default()
+        };
+        let (addr, trigger, server) = serve(engine().await, &config).await;
+
+        let sql = "SELECT battery FROM v_robots_history";
+        let request_body = serde_json::json!({ "sql": sql }).to_string();
+        let line = "POST /api/query/export?format=json_lines HTTP/1.1";
+        let (status, body) = request(addr, line, &request_body).await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        // The same bytes as an export into memory, sent over several chunks
+        let mut expected = Vec::new();
+        QueryEngine::open(&path, QueryLimits::default())
+            .unwrap()
+            .export(sql, ExportFormat::JsonLines, &mut expected)
+            .unwrap();
+        assert_eq!(dechunk(&body), String::from_utf8(expected).unwrap());
+        assert_eq!(dechunk(&body).lines().count(), 1_201);
+
+        let (status, body) = request(addr, "POST /api/query/export HTTP/1.1", &request_body).await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert!(dechunk(&body).starts_with("battery"));
+
+        // Refused before anything was sent, so with a status
+        let update = serde_json::json!({"sql": "UPDATE robot_states SET battery = 0"});
+        let line = "POST /api/query/export HTTP/1.1";
+        let (status, _) = request(addr, line, &update.to_string()).await;
+        assert_eq!(status, "HTTP/1.1 400 Bad Request");
+        trigger.trigger();
+        server.await.unwrap();
+
+        drop(store);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_client_skips_instead_of_blocking() {
+        let stream = EventStream::new(2);
+        // Nobody listening: nothing is built or buffered
+        stream.forward(&EngineMessage::HeartbeatReceived(Heartbeat::new(
+            "RV-001".parse().unwrap(),
+            RobotType::Rover,
+            RobotStatus::Active,
+            90.0,
+            90.0,
+            1,
+        )));
+
+        let mut client = stream.subscribe();
+        for i in 0..5 {
+            let mut state = RobotState::new("RV-001".parse().unwrap(), "Rover", RobotType::Rover);
+            state.battery = i as f64;
+            stream.forward(&EngineMessage::TelemetryReceived(state));
+        }
+        stream.forward(&EngineMessage::RobotReconnected(
+            "RV-001".into(),
+            std::time::Duration::ZERO,
+        ));
+
+        assert!(matches!(
+            client.recv().await,
+            Err(broadcast::error::RecvError::Lagged(3))
+        ));
+        let Ok(StreamEvent::Telemetry(state)) = client.recv().await else {
+            panic!("expected telemetry");
+        };
+        assert_eq!(state.battery, 3.0);
+        let event = serde_json::to_value(StreamEvent::Lagged { skipped: 3 }).unwrap();
+        assert_eq!(
+            event,
+            serde_json::json!({"type": "lagged", "data": {"skipped": 3}})
+        );
+    }
+}
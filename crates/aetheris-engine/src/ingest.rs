@@ -0,0 +1,806 @@
+//! Resource-bounded payload parsing
+//!
+//! Every subscribed topic is reachable by any client on the broker, so the
+//! engine never hands an untrusted payload straight to a decoder. Documents
+//! are checked against a size cap before parsing. JSON is then deserialized
+//! through a wrapper that counts nesting and checks the per-document time
+//! budget as it goes, so a document is abandoned as soon as it nests too
+//! deep or runs out of time. Payloads may be JSON, CBOR, or MessagePack,
+//! told apart by their first byte; binary payloads are held to the size cap
+//! and to the nesting limit of their decoders. Violations are counted per
+//! source so noisy publishers stand out.
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, Deserializer, EnumAccess, MapAccess, SeqAccess,
+    VariantAccess, Visitor,
+};
+use thiserror::Error;
+
+use aetheris_shared::{
+    CURRENT_VERSION, Encoding, EncodingError, EnvelopeVersion, MIN_SUPPORTED_VERSION, MqttMessage,
+    Validate, ValidationError, is_supported_version, limits,
+};
+
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// LIMITS
+// ============================================================================
+
+/// Limits applied to every incoming payload before and during parsing
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseLimits {
+    /// Maximum payload size in bytes, checked before any parsing
+    pub max_payload_bytes: usize,
+    /// Maximum nesting depth of JSON objects and arrays (CBOR and
+    /// MessagePack are held to [`limits::MAX_PAYLOAD_DEPTH`] by their
+    /// decoders)
+    pub max_depth: usize,
+    /// Maximum wall-clock time a single JSON document may take to
+    /// deserialize, checked at every nested value and element
+    pub parse_budget: Duration,
+    /// Publish messages that parse but fail validation on the dead-letter
+    /// topic; they are dropped either way
+    pub quarantine_invalid: bool,
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        Self {
+            // A full RobotState envelope is well under 1 KiB; leave generous headroom
+            // for long anomaly descriptions without admitting megabyte documents.
+            max_payload_bytes: limits::MAX_PAYLOAD_BYTES,
+            max_depth: limits::MAX_PAYLOAD_DEPTH,
+            parse_budget: Duration::from_millis(50),
+            quarantine_invalid: true,
+        }
+    }
+}
+
+impl CheckConfig for ParseLimits {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if self.max_payload_bytes == 0 {
+            checker.error("max_payload_bytes", "must be greater than zero", None);
+        }
+        if self.max_depth == 0 {
+            checker.error(
+                "max_depth",
+                "must be greater than zero",
+                Some("messages nest up to 4 levels; the default is 16".into()),
+            );
+        }
+        checker.positive("parse_budget", self.parse_budget);
+    }
+}
+
+// ============================================================================
+// REJECTIONS
+// ============================================================================
+
+/// Reason a payload was rejected by the bounded parser
+#[derive(Debug, Error)]
+pub enum ParseRejection {
+    #[error("payload of {size} bytes exceeds the {limit} byte limit")]
+    Oversized { size: usize, limit: usize },
+    #[error("payload nesting exceeds the depth limit of {limit}")]
+    TooDeep { limit: usize },
+    #[error("parse took {elapsed:?}, over the {budget:?} budget")]
+    OverBudget { elapsed: Duration, budget: Duration },
+    #[error("malformed payload: {0}")]
+    Malformed(EncodingError),
+    #[error(
+        "envelope schema version {version} is not supported (accepted: {}..={})",
+        MIN_SUPPORTED_VERSION,
+        CURRENT_VERSION
+    )]
+    UnsupportedVersion { version: u16 },
+    #[error("invalid value: {0}")]
+    Invalid(#[from] ValidationError),
+}
+
+/// Per-source counts of rejected payloads
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SourceViolations {
+    pub oversized: u64,
+    pub too_deep: u64,
+    pub over_budget: u64,
+    pub malformed: u64,
+    pub unsupported_version: u64,
+    pub invalid: u64,
+}
+
+impl SourceViolations {
+    /// Total number of rejected payloads from this source
+    pub fn total(&self) -> u64 {
+        self.oversized
+            + self.too_deep
+            + self.over_budget
+            + self.malformed
+            + self.unsupported_version
+            + self.invalid
+    }
+
+    fn record(&mut self, rejection: &ParseRejection) {
+        match rejection {
+            ParseRejection::Oversized { .. } => self.oversized += 1,
+            ParseRejection::TooDeep { .. } => self.too_deep += 1,
+            ParseRejection::OverBudget { .. } => self.over_budget += 1,
+            ParseRejection::Malformed(_) => self.malformed += 1,
+            ParseRejection::UnsupportedVersion { .. } => self.unsupported_version += 1,
+            ParseRejection::Invalid(_) => self.invalid += 1,
+        }
+    }
+}
+
+// ============================================================================
+// BOUNDED PARSER
+// ============================================================================
+
+/// Parses payloads under [`ParseLimits`] and tracks violations per source
+#[derive(Debug, Default)]
+pub struct PayloadGuard {
+    limits: ParseLimits,
+    violations: HashMap<String, SourceViolations>,
+}
+
+impl PayloadGuard {
+    pub fn new(limits: ParseLimits) -> Self {
+        Self {
+            limits,
+            violations: HashMap::new(),
+        }
+    }
+
+    pub fn limits(&self) -> &ParseLimits {
+        &self.limits
+    }
+
+    /// Parse a payload from `source`, recording any violation against it
+    pub fn parse<T: DeserializeOwned>(
+        &mut self,
+        source: &str,
+        payload: &[u8],
+    ) -> Result<T, ParseRejection> {
+        let result = parse_bounded(payload, &self.limits);
+        if let Err(rejection) = &result {
+            self.violations
+                .entry(source.to_string())
+                .or_default()
+                .record(rejection);
+        }
+        result
+    }
+
+    /// Parse an [`MqttMessage`] from `source`, checking its schema version
+    /// before the payload so a newer publisher is reported as such rather
+    /// than as malformed. Older versions parse with defaults filled in.
+    pub fn parse_envelope<T: DeserializeOwned>(
+        &mut self,
+        source: &str,
+        payload: &[u8],
+    ) -> Result<MqttMessage<T>, ParseRejection> {
+        let result = parse_bounded::<EnvelopeVersion>(payload, &self.limits).and_then(|probe| {
+            if is_supported_version(probe.version) {
+                parse_bounded(payload, &self.limits)
+            } else {
+                Err(ParseRejection::UnsupportedVersion {
+                    version: probe.version,
+                })
+            }
+        });
+        if let Err(rejection) = &result {
+            self.violations
+                .entry(source.to_string())
+                .or_default()
+                .record(rejection);
+        }
+        result
+    }
+
+    /// Check the values of a parsed message from `source`, counting a
+    /// failure against it like a parse rejection
+    pub fn validate<T: Validate>(&mut self, source: &str, value: &T) -> Result<(), ParseRejection> {
+        value.validate().map_err(|error| {
+            let rejection = ParseRejection::from(error);
+            self.violations
+                .entry(source.to_string())
+                .or_default()
+                .record(&rejection);
+            rejection
+        })
+    }
+
+    /// Violation counts for a single source
+    pub fn violations(&self, source: &str) -> SourceViolations {
+        self.violations.get(source).copied().unwrap_or_default()
+    }
+
+    /// Sources with at least `threshold` rejected payloads, worst first
+    pub fn noisy_sources(&self, threshold: u64) -> Vec<(String, SourceViolations)> {
+        let mut noisy: Vec<_> = self
+            .violations
+            .iter()
+            .filter(|(_, v)| v.total() >= threshold)
+            .map(|(source, v)| (source.clone(), *v))
+            .collect();
+        noisy.sort_by(|a, b| b.1.total().cmp(&a.1.total()).then_with(|| a.0.cmp(&b.0)));
+        noisy
+    }
+}
+
+/// Parse a JSON, CBOR, or MessagePack payload, enforcing size and depth
+/// limits, and the time budget on JSON
+pub fn parse_bounded<T: DeserializeOwned>(
+    payload: &[u8],
+    limits: &ParseLimits,
+) -> Result<T, ParseRejection> {
+    if payload.len() > limits.max_payload_bytes {
+        return Err(ParseRejection::Oversized {
+            size: payload.len(),
+            limit: limits.max_payload_bytes,
+        });
+    }
+
+    match Encoding::detect(payload) {
+        Encoding::Json => parse_json(payload, limits),
+        encoding => encoding.decode(payload).map_err(|e| match e {
+            EncodingError::TooDeep => ParseRejection::TooDeep {
+                limit: limits::MAX_PAYLOAD_DEPTH,
+            },
+            e => ParseRejection::Malformed(e),
+        }),
+    }
+}
+
+/// Deserialize a JSON document through [`Bounded`], telling a limit that
+/// stopped it apart from a malformed document
+fn parse_json<T: DeserializeOwned>(
+    payload: &[u8],
+    limits: &ParseLimits,
+) -> Result<T, ParseRejection> {
+    let budget = Budget::new(limits);
+    let mut deserializer = serde_json::Deserializer::from_slice(payload);
+    let result = T::deserialize(Bounded {
+        inner: &mut deserializer,
+        budget: &budget,
+    })
+    .and_then(|value| deserializer.end().map(|()| value));
+    match (result, budget.tripped.get()) {
+        (Ok(value), _) => Ok(value),
+        (Err(_), Some(Tripped::Depth)) => Err(ParseRejection::TooDeep {
+            limit: limits.max_depth,
+        }),
+        (Err(_), Some(Tripped::Time)) => Err(ParseRejection::OverBudget {
+            elapsed: budget.started.elapsed(),
+            budget: limits.parse_budget,
+        }),
+        (Err(e), None) => Err(ParseRejection::Malformed(EncodingError::Json(e))),
+    }
+}
+
+// ============================================================================
+// BOUNDED DESERIALIZATION
+// ============================================================================
+
+/// Limit that stopped a parse
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tripped {
+    Depth,
+    Time,
+}
+
+/// Nesting and time used so far by one parse, shared by every layer of
+/// the wrappers below
+struct Budget {
+    max_depth: usize,
+    limit: Duration,
+    started: Instant,
+    depth: Cell<usize>,
+    tripped: Cell<Option<Tripped>>,
+}
+
+impl Budget {
+    fn new(limits: &ParseLimits) -> Self {
+        Self {
+            max_depth: limits.max_depth,
+            limit: limits.parse_budget,
+            started: Instant::now(),
+            depth: Cell::new(0),
+            tripped: Cell::new(None),
+        }
+    }
+
+    fn trip<E: de::Error>(&self, tripped: Tripped) -> E {
+        self.tripped.set(Some(tripped));
+        match tripped {
+            Tripped::Depth => E::custom("nesting exceeds the depth limit"),
+            Tripped::Time => E::custom("parse exceeds the time budget"),
+        }
+    }
+
+    fn check_time<E: de::Error>(&self) -> Result<(), E> {
+        if self.started.elapsed() > self.limit {
+            return Err(self.trip(Tripped::Time));
+        }
+        Ok(())
+    }
+
+    /// Run `visit` one object or array deeper
+    fn nested<T, E: de::Error>(&self, visit: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+        let depth = self.depth.get() + 1;
+        if depth > self.max_depth {
+            return Err(self.trip(Tripped::Depth));
+        }
+        self.check_time()?;
+        self.depth.set(depth);
+        let result = visit();
+        self.depth.set(depth - 1);
+        result
+    }
+}
+
+/// Deserializer that hands every visitor, element and value it produces
+/// to [`Budget`] first
+struct Bounded<'b, D> {
+    inner: D,
+    budget: &'b Budget,
+}
+
+impl<'b, D> Bounded<'b, D> {
+    fn visitor<V>(&self, visitor: V) -> BoundedVisitor<'b, V> {
+        BoundedVisitor {
+            inner: visitor,
+            budget: self.budget,
+        }
+    }
+}
+
+macro_rules! forward_deserialize {
+    ($($method:ident($($arg:ident: $ty:ty),*)),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, D::Error> {
+                let visitor = self.visitor(visitor);
+                self.inner.$method($($arg,)* visitor)
+            }
+        )*
+    };
+}
+
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for Bounded<'_, D> {
+    type Error = D::Error;
+
+    forward_deserialize! {
+        deserialize_any(),
+        deserialize_bool(),
+        deserialize_i8(),
+        deserialize_i16(),
+        deserialize_i32(),
+        deserialize_i64(),
+        deserialize_i128(),
+        deserialize_u8(),
+        deserialize_u16(),
+        deserialize_u32(),
+        deserialize_u64(),
+        deserialize_u128(),
+        deserialize_f32(),
+        deserialize_f64(),
+        deserialize_char(),
+        deserialize_str(),
+        deserialize_string(),
+        deserialize_bytes(),
+        deserialize_byte_buf(),
+        deserialize_option(),
+        deserialize_unit(),
+        deserialize_unit_struct(name: &'static str),
+        deserialize_newtype_struct(name: &'static str),
+        deserialize_seq(),
+        deserialize_tuple(len: usize),
+        deserialize_tuple_struct(name: &'static str, len: usize),
+        deserialize_map(),
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]),
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]),
+        deserialize_identifier(),
+    }
+
+    // Walked rather than skipped, so unknown fields count against the limits
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, D::Error> {
+        let visitor = self.visitor(visitor);
+        self.inner.deserialize_any(visitor)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+struct BoundedVisitor<'b, V> {
+    inner: V,
+    budget: &'b Budget,
+}
+
+macro_rules! forward_visit {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $method<E: de::Error>(self, v: $ty) -> Result<Self::Value, E> {
+                self.inner.$method(v)
+            }
+        )*
+    };
+}
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for BoundedVisitor<'_, V> {
+    type Value = V::Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.expecting(f)
+    }
+
+    forward_visit! {
+        visit_bool(bool),
+        visit_i8(i8),
+        visit_i16(i16),
+        visit_i32(i32),
+        visit_i64(i64),
+        visit_i128(i128),
+        visit_u8(u8),
+        visit_u16(u16),
+        visit_u32(u32),
+        visit_u64(u64),
+        visit_u128(u128),
+        visit_f32(f32),
+        visit_f64(f64),
+        visit_char(char),
+        visit_str(&str),
+        visit_borrowed_str(&'de str),
+        visit_string(String),
+        visit_bytes(&[u8]),
+        visit_borrowed_bytes(&'de [u8]),
+        visit_byte_buf(Vec<u8>),
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.inner.visit_none()
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.inner.visit_unit()
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        self.inner.visit_some(Bounded {
+            inner: deserializer,
+            budget: self.budget,
+        })
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        self.inner.visit_newtype_struct(Bounded {
+            inner: deserializer,
+            budget: self.budget,
+        })
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        let budget = self.budget;
+        budget.nested(|| self.inner.visit_seq(BoundedAccess { inner: seq, budget }))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        let budget = self.budget;
+        budget.nested(|| self.inner.visit_map(BoundedAccess { inner: map, budget }))
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        self.inner.visit_enum(BoundedAccess {
+            inner: data,
+            budget: self.budget,
+        })
+    }
+}
+
+/// Sequence, map, enum or variant access handing out bounded values
+struct BoundedAccess<'b, A> {
+    inner: A,
+    budget: &'b Budget,
+}
+
+impl<'b, A> BoundedAccess<'b, A> {
+    fn seed<S>(&self, seed: S) -> BoundedSeed<'b, S> {
+        BoundedSeed {
+            inner: seed,
+            budget: self.budget,
+        }
+    }
+}
+
+impl<'de, A: SeqAccess<'de>> SeqAccess<'de> for BoundedAccess<'_, A> {
+    type Error = A::Error;
+
+    fn next_element_seed<S: DeserializeSeed<'de>>(
+        &mut self,
+        seed: S,
+    ) -> Result<Option<S::Value>, A::Error> {
+        self.budget.check_time()?;
+        let seed = self.seed(seed);
+        self.inner.next_element_seed(seed)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for BoundedAccess<'_, A> {
+    type Error = A::Error;
+
+    fn next_key_seed<S: DeserializeSeed<'de>>(
+        &mut self,
+        seed: S,
+    ) -> Result<Option<S::Value>, A::Error> {
+        self.budget.check_time()?;
+        let seed = self.seed(seed);
+        self.inner.next_key_seed(seed)
+    }
+
+    fn next_value_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<S::Value, A::Error> {
+        let seed = self.seed(seed);
+        self.inner.next_value_seed(seed)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'de, 'b, A: EnumAccess<'de>> EnumAccess<'de> for BoundedAccess<'b, A> {
+    type Error = A::Error;
+    type Variant = BoundedAccess<'b, A::Variant>;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<(S::Value, Self::Variant), A::Error> {
+        let seed = self.seed(seed);
+        let (value, variant) = self.inner.variant_seed(seed)?;
+        Ok((
+            value,
+            BoundedAccess {
+                inner: variant,
+                budget: self.budget,
+            },
+        ))
+    }
+}
+
+impl<'de, A: VariantAccess<'de>> VariantAccess<'de> for BoundedAccess<'_, A> {
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), A::Error> {
+        self.inner.unit_variant()
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<S::Value, A::Error> {
+        let seed = self.seed(seed);
+        self.inner.newtype_variant_seed(seed)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, A::Error> {
+        let visitor = BoundedVisitor {
+            inner: visitor,
+            budget: self.budget,
+        };
+        self.inner.tuple_variant(len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, A::Error> {
+        let visitor = BoundedVisitor {
+            inner: visitor,
+            budget: self.budget,
+        };
+        self.inner.struct_variant(fields, visitor)
+    }
+}
+
+struct BoundedSeed<'b, S> {
+    inner: S,
+    budget: &'b Budget,
+}
+
+impl<'de, S: DeserializeSeed<'de>> DeserializeSeed<'de> for BoundedSeed<'_, S> {
+    type Value = S::Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<S::Value, D::Error> {
+        self.inner.deserialize(Bounded {
+            inner: deserializer,
+            budget: self.budget,
+        })
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetheris_shared::{
+        AnomalyReport, AnomalyType, Position, RobotState, RobotType, SeverityLevel,
+    };
+
+    fn nested(depth: usize) -> String {
+        format!("{}{}", "[".repeat(depth), "]".repeat(depth))
+    }
+
+    #[test]
+    fn test_rejects_deeply_nested_document() {
+        let mut guard = PayloadGuard::default();
+        let payload = nested(10_000);
+        let result = guard.parse::<serde_json::Value>("RV-001", payload.as_bytes());
+        assert!(matches!(result, Err(ParseRejection::TooDeep { limit: 16 })));
+        assert_eq!(guard.violations("RV-001").too_deep, 1);
+    }
+
+    #[test]
+    fn test_brackets_inside_strings_do_not_count() {
+        let limits = ParseLimits {
+            max_depth: 2,
+            ..ParseLimits::default()
+        };
+        let payload = format!(r#"{{"description": "{}\"{}"}}"#, nested(50), "[[[");
+        let value: serde_json::Value = parse_bounded(payload.as_bytes(), &limits).unwrap();
+        assert!(value["description"].as_str().unwrap().starts_with("[[["));
+    }
+
+    #[test]
+    fn test_nesting_in_unknown_fields_counts_too() {
+        let state = RobotState::new("RV-001".parse().unwrap(), "Rover", RobotType::Rover);
+        let mut value = serde_json::to_value(&state).unwrap();
+        value["extra"] = serde_json::from_str(&nested(20)).unwrap();
+        let payload = serde_json::to_vec(&value).unwrap();
+        let result = parse_bounded::<RobotState>(&payload, &ParseLimits::default());
+        assert!(matches!(result, Err(ParseRejection::TooDeep { limit: 16 })));
+
+        // Just inside the limit, with the document itself a level deep
+        value["extra"] = serde_json::from_str(&nested(15)).unwrap();
+        let payload = serde_json::to_vec(&value).unwrap();
+        assert!(parse_bounded::<RobotState>(&payload, &ParseLimits::default()).is_ok());
+    }
+
+    #[test]
+    fn test_rejects_oversized_document_before_parsing() {
+        let mut guard = PayloadGuard::default();
+        // Not even valid JSON: the size cap must fire before serde sees it
+        let payload = vec![b'x'; guard.limits().max_payload_bytes + 1];
+        let result = guard.parse::<serde_json::Value>("DR-001", &payload);
+        assert!(matches!(result, Err(ParseRejection::Oversized { .. })));
+        assert_eq!(guard.violations("DR-001").oversized, 1);
+        assert_eq!(guard.violations("DR-001").malformed, 0);
+    }
+
+    #[test]
+    fn test_accepts_large_valid_report_under_limits() {
+        let mut guard = PayloadGuard::default();
+        let report = AnomalyReport::new(
+            AnomalyType::Corrosion,
+            SeverityLevel::Medium,
+            Position::new(1.0, 2.0, 3.0),
+            "PIPE-001",
+            "CR-001",
+            0.9,
+            "corrosion pitting ".repeat(12_000),
+        );
+        let payload = serde_json::to_vec(&MqttMessage::new(report.clone(), "CR-001", 7)).unwrap();
+        assert!(payload.len() > 200 * 1024);
+        assert!(payload.len() < guard.limits().max_payload_bytes);
+
+        let msg: MqttMessage<AnomalyReport> = guard.parse("CR-001", &payload).unwrap();
+        assert_eq!(msg.payload, report);
+        assert_eq!(guard.violations("CR-001").total(), 0);
+    }
+
+    #[test]
+    fn test_mixed_encodings_parse_alike() {
+        let mut guard = PayloadGuard::default();
+        let msg = MqttMessage::new(
+            AnomalyReport::new(
+                AnomalyType::Leak,
+                SeverityLevel::High,
+                Position::new(1.0, 2.0, 3.0),
+                "PIPE-001",
+                "RV-001",
+                0.8,
+                "leak",
+            ),
+            "RV-001",
+            3,
+        );
+        for encoding in Encoding::ALL {
+            let payload = encoding.encode(&msg).unwrap();
+            let parsed: MqttMessage<AnomalyReport> = guard.parse("RV-001", &payload).unwrap();
+            assert_eq!(parsed, msg);
+        }
+
+        // Maps of one, nested 10,000 deep: {"a": {"a": ... {}}}
+        for (level, empty) in [([0xa1, 0x61, b'a'], 0xa0), ([0x81, 0xa1, b'a'], 0x80)] {
+            let mut deep = level.repeat(10_000);
+            deep.push(empty);
+            let result = guard.parse::<serde_json::Value>("RV-001", &deep);
+            assert!(matches!(result, Err(ParseRejection::TooDeep { .. })));
+        }
+        assert_eq!(guard.violations("RV-001").too_deep, 2);
+    }
+
+    #[test]
+    fn test_newer_envelope_version_is_rejected_as_such() {
+        let mut guard = PayloadGuard::default();
+        // The payload would not parse either; the version is reported first
+        let newer = br#"{"payload": {"renamed": 1}, "source": "RV-001", "timestamp": 1, "seq": 1, "version": 2}"#;
+        let result = guard.parse_envelope::<AnomalyReport>("RV-001", newer);
+        assert!(matches!(
+            result,
+            Err(ParseRejection::UnsupportedVersion { version: 2 })
+        ));
+        assert_eq!(guard.violations("RV-001").unsupported_version, 1);
+        assert_eq!(guard.violations("RV-001").malformed, 0);
+
+        // Unversioned envelopes are version 1
+        let older = br#"{"payload": 7, "source": "RV-001", "timestamp": 1, "seq": 1}"#;
+        let msg = guard.parse_envelope::<u32>("RV-001", older).unwrap();
+        assert_eq!((msg.payload, msg.version), (7, 1));
+    }
+
+    #[test]
+    fn test_invalid_values_are_counted_per_source() {
+        let mut guard = PayloadGuard::default();
+        let mut state = RobotState::new("RV-001".parse().unwrap(), "Rover Alpha", RobotType::Rover);
+        assert!(guard.validate("RV-001", &state).is_ok());
+        state.battery = -3.0;
+        let rejection = guard.validate("RV-001", &state).unwrap_err();
+        assert_eq!(
+            rejection.to_string(),
+            "invalid value: battery must be within 0..=100, got -3"
+        );
+        assert_eq!(guard.violations("RV-001").invalid, 1);
+        assert_eq!(guard.noisy_sources(1)[0].0, "RV-001");
+    }
+
+    #[test]
+    fn test_parse_budget_violation() {
+        let limits = ParseLimits {
+            parse_budget: Duration::ZERO,
+            ..ParseLimits::default()
+        };
+        let payload = serde_json::to_vec(&vec![1.5f64; 20_000]).unwrap();
+        let result = parse_bounded::<Vec<f64>>(&payload, &limits);
+        assert!(matches!(result, Err(ParseRejection::OverBudget { .. })));
+    }
+
+    #[test]
+    fn test_noisy_sources_ranked_by_violations() {
+        let mut guard = PayloadGuard::default();
+        for _ in 0..3 {
+            let _ = guard.parse::<serde_json::Value>("RV-002", b"{not json");
+        }
+        let _ = guard.parse::<serde_json::Value>("RV-001", b"{not json");
+        let _ = guard.parse::<serde_json::Value>("RV-003", b"{}");
+
+        let noisy = guard.noisy_sources(1);
+        assert_eq!(noisy.len(), 2);
+        assert_eq!(noisy[0].0, "RV-002");
+        assert_eq!(noisy[0].1.malformed, 3);
+        assert!(guard.noisy_sources(4).is_empty());
+    }
+}
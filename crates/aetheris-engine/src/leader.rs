@@ -0,0 +1,560 @@
+//! Leader election between engine instances
+//!
+//! Two engines may run against one broker for redundancy. Both keep their
+//! fleet, anomalies and sections current from the same subscriptions, but
+//! only one may act on the fleet's behalf: publish the simulated fleet and
+//! environment, mark robots offline on heartbeat timeout, auto-dispatch
+//! robots and escalate unacknowledged alerts. That instance is the leader.
+//!
+//! The leader keeps a retained [`LeaderClaim`] on `aetheris/system/leader`
+//! and renews it every `renew_interval`. Any instance that has not seen a
+//! live claim for `lease` after it connected claims the lead itself, so a
+//! dead leader is replaced within `lease` plus one election tick; one that
+//! shuts down releases its claim and is replaced at the next tick. Two
+//! leaders, e.g. after a broker partition, see each other's renewals and
+//! keep the earlier claim, the higher instance id on a tie.
+//!
+//! [`LeaderElection`] is the state machine alone: it is told what arrived
+//! and what time it is, and answers with the claims to publish.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use aetheris_shared::{LeaderClaim, LeaderStatus};
+
+use crate::anomalies::ENGINE_ORIGIN;
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Identity of this instance and its part in the election
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeaderConfig {
+    /// Elect a leader among engine instances; an engine not electing one
+    /// always leads
+    pub enabled: bool,
+    /// Source of everything this instance publishes. Unset, it is "engine"
+    /// for a lone engine and generated ("engine-…") for an electing one;
+    /// the default source bindings only know ids starting with "engine".
+    pub instance_id: Option<String>,
+    /// How long a claim holds without a renewal
+    pub lease: Duration,
+    /// How often the leader renews its claim
+    pub renew_interval: Duration,
+}
+
+impl Default for LeaderConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            instance_id: None,
+            lease: Duration::from_secs(10),
+            renew_interval: Duration::from_secs(3),
+        }
+    }
+}
+
+impl LeaderConfig {
+    /// The configured instance id, or the default one
+    pub fn resolve_instance_id(&self) -> String {
+        match &self.instance_id {
+            Some(id) => id.clone(),
+            None if self.enabled => {
+                let uuid = uuid::Uuid::new_v4().simple().to_string();
+                format!("{ENGINE_ORIGIN}-{}", &uuid[..8])
+            }
+            None => ENGINE_ORIGIN.to_string(),
+        }
+    }
+}
+
+impl CheckConfig for LeaderConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if let Some(id) = &self.instance_id
+            && (id.is_empty() || id.contains(|c: char| c.is_whitespace() || "/+#".contains(c)))
+        {
+            checker.error(
+                "instance_id",
+                format!("\"{id}\" is not a usable source id"),
+                Some("use letters, digits, '-' or '_'".into()),
+            );
+        }
+        checker.positive("lease", self.lease);
+        checker.positive("renew_interval", self.renew_interval);
+        if self.renew_interval >= self.lease {
+            checker.error(
+                "renew_interval",
+                "must be shorter than the lease",
+                Some("a third of the lease leaves room for two lost renewals".into()),
+            );
+        }
+    }
+}
+
+// ============================================================================
+// ELECTION
+// ============================================================================
+
+/// Shared view of whether this instance leads, for tasks that only the
+/// leader runs
+#[derive(Debug, Clone)]
+pub struct Leadership(Arc<AtomicBool>);
+
+impl Leadership {
+    pub fn new(leader: bool) -> Self {
+        Self(Arc::new(AtomicBool::new(leader)))
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn set(&self, leader: bool) {
+        self.0.store(leader, Ordering::Relaxed);
+    }
+}
+
+/// What a claim or tick did to the leadership
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LeaderChange {
+    /// This instance took the lead
+    Elected,
+    /// Another instance leads now
+    Following { leader: String },
+    /// This instance stopped leading, with no successor known yet
+    SteppedDown,
+}
+
+/// Another instance's claim and when it lapses on our clock
+#[derive(Debug, Clone)]
+struct HeldClaim {
+    claim: LeaderClaim,
+    expires_at: u64,
+}
+
+/// Election state of one instance. Times are engine clock milliseconds.
+#[derive(Debug)]
+pub struct LeaderElection {
+    instance_id: String,
+    lease_ms: u64,
+    renew_ms: u64,
+    leadership: Leadership,
+    /// Our claim while we lead
+    own: Option<LeaderClaim>,
+    /// When our claim was last published
+    renewed_at: u64,
+    /// Renew at the next tick, whatever the interval says
+    renew_now: bool,
+    /// The leader we follow
+    other: Option<HeldClaim>,
+    /// No claim is made before this, so a live leader's retained claim
+    /// has time to arrive; unset until connected
+    settle_until: u64,
+    changes: u64,
+    changed_at: u64,
+}
+
+impl LeaderElection {
+    pub fn new(instance_id: impl Into<String>, config: &LeaderConfig, now: u64) -> Self {
+        Self {
+            instance_id: instance_id.into(),
+            lease_ms: config.lease.as_millis() as u64,
+            renew_ms: config.renew_interval.as_millis() as u64,
+            leadership: Leadership::new(false),
+            own: None,
+            renewed_at: 0,
+            renew_now: false,
+            other: None,
+            settle_until: u64::MAX,
+            changes: 0,
+            changed_at: now,
+        }
+    }
+
+    pub fn instance_id(&self) -> &str {
+        &self.instance_id
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.own.is_some()
+    }
+
+    /// Handle kept current as the leadership changes
+    pub fn leadership(&self) -> Leadership {
+        self.leadership.clone()
+    }
+
+    /// The instance known to lead, if any
+    pub fn leader(&self) -> Option<&str> {
+        match (&self.own, &self.other) {
+            (Some(own), _) => Some(&own.instance_id),
+            (None, Some(other)) => Some(&other.claim.instance_id),
+            (None, None) => None,
+        }
+    }
+
+    pub fn status(&self) -> LeaderStatus {
+        LeaderStatus {
+            instance_id: self.instance_id.clone(),
+            leader: self.leader().map(str::to_string),
+            changes: self.changes,
+            since: self.changed_at,
+        }
+    }
+
+    /// Take in a claim from the leader topic
+    pub fn observe(&mut self, claim: LeaderClaim, now: u64) -> Option<LeaderChange> {
+        // Our own renewals, or our claim retained from before a restart
+        if claim.instance_id == self.instance_id {
+            return None;
+        }
+        if claim.released {
+            if self
+                .other
+                .as_ref()
+                .is_some_and(|held| held.claim.instance_id == claim.instance_id)
+            {
+                self.other = None;
+                // Nobody else will hold a retained claim back now
+                self.settle_until = now;
+            }
+            return None;
+        }
+        if let Some(own) = &self.own {
+            if own.outranks(&claim) {
+                // Make sure the other leader hears it lost
+                self.renew_now = true;
+                return None;
+            }
+            self.own = None;
+            return self.follow(claim, now);
+        }
+        let replaces = match &self.other {
+            Some(held) if held.claim.instance_id == claim.instance_id => true,
+            Some(held) => held.expires_at <= now || claim.outranks(&held.claim),
+            None => true,
+        };
+        if !replaces {
+            return None;
+        }
+        let changed = self
+            .other
+            .as_ref()
+            .is_none_or(|held| held.claim.instance_id != claim.instance_id);
+        if changed {
+            self.follow(claim, now)
+        } else {
+            self.other = Some(self.hold(claim, now));
+            None
+        }
+    }
+
+    /// Advance to `now`: the claim to publish, if one is due, and whether
+    /// this instance just took the lead
+    pub fn tick(&mut self, now: u64) -> (Option<LeaderClaim>, Option<LeaderChange>) {
+        if self.own.is_none() {
+            let leader_alive = self
+                .other
+                .as_ref()
+                .is_some_and(|held| held.expires_at > now);
+            if leader_alive || now < self.settle_until {
+                return (None, None);
+            }
+            self.other = None;
+            self.own = Some(LeaderClaim {
+                instance_id: self.instance_id.clone(),
+                claimed_at: now,
+                renewed_at: now,
+                lease_ms: self.lease_ms,
+                released: false,
+            });
+            self.record_change(now);
+            self.renewed_at = now;
+            self.renew_now = false;
+            return (self.own.clone(), Some(LeaderChange::Elected));
+        }
+        if !self.renew_now && now < self.renewed_at + self.renew_ms {
+            return (None, None);
+        }
+        self.renew_now = false;
+        self.renewed_at = now;
+        let claim = self.own.as_mut().map(|own| {
+            own.renewed_at = now;
+            own.clone()
+        });
+        (claim, None)
+    }
+
+    /// The broker connection dropped: whatever we claimed may be taken
+    /// over meanwhile, so stop acting as leader until the election runs
+    /// again after reconnecting
+    pub fn disconnected(&mut self, now: u64) -> Option<LeaderChange> {
+        self.other = None;
+        self.settle_until = u64::MAX;
+        self.own.take()?;
+        self.record_change(now);
+        Some(LeaderChange::SteppedDown)
+    }
+
+    /// The broker connection is up: give a live leader's retained claim a
+    /// renewal interval to arrive before claiming
+    pub fn connected(&mut self, now: u64) {
+        self.settle_until = now + self.renew_ms;
+    }
+
+    /// The claim that hands the lead over on shutdown, if we hold it
+    pub fn release(&mut self, now: u64) -> Option<LeaderClaim> {
+        let mut claim = self.own.take()?;
+        self.leadership.set(false);
+        claim.renewed_at = now;
+        claim.released = true;
+        Some(claim)
+    }
+
+    fn hold(&self, claim: LeaderClaim, now: u64) -> HeldClaim {
+        HeldClaim {
+            expires_at: now + claim.lease_ms,
+            claim,
+        }
+    }
+
+    fn follow(&mut self, claim: LeaderClaim, now: u64) -> Option<LeaderChange> {
+        let leader = claim.instance_id.clone();
+        self.other = Some(self.hold(claim, now));
+        self.record_change(now);
+        Some(LeaderChange::Following { leader })
+    }
+
+    fn record_change(&mut self, now: u64) {
+        self.changes += 1;
+        self.changed_at = now;
+        self.leadership.set(self.own.is_some());
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const T0: u64 = 1_767_225_600_000;
+    const SECOND: u64 = 1_000;
+
+    fn config() -> LeaderConfig {
+        LeaderConfig {
+            enabled: true,
+            ..LeaderConfig::default()
+        }
+    }
+
+    /// An instance connected at `now`
+    fn instance(id: &str, now: u64) -> LeaderElection {
+        let mut election = LeaderElection::new(id, &config(), now);
+        election.connected(now);
+        election
+    }
+
+    fn claim(id: &str, claimed_at: u64, renewed_at: u64) -> LeaderClaim {
+        LeaderClaim {
+            instance_id: id.into(),
+            claimed_at,
+            renewed_at,
+            lease_ms: 10 * SECOND,
+            released: false,
+        }
+    }
+
+    /// Tick until `id` leads and return its first claim
+    fn elect(election: &mut LeaderElection, now: u64) -> LeaderClaim {
+        let (claim, change) = election.tick(now);
+        assert_eq!(change, Some(LeaderChange::Elected));
+        claim.unwrap()
+    }
+
+    #[test]
+    fn test_lone_instance_claims_after_settling() {
+        let mut a = LeaderElection::new("engine-a", &config(), T0);
+        // Not connected: never claims
+        assert_eq!(a.tick(T0 + 60 * SECOND), (None, None));
+
+        a.connected(T0 + 60 * SECOND);
+        assert_eq!(a.tick(T0 + 62 * SECOND), (None, None));
+        assert!(!a.leadership().is_leader());
+
+        let claim = elect(&mut a, T0 + 63 * SECOND);
+        assert_eq!(claim.instance_id, "engine-a");
+        assert_eq!(claim.claimed_at, T0 + 63 * SECOND);
+        assert!(a.is_leader());
+        assert!(a.leadership().is_leader());
+        assert_eq!(a.status().leader.as_deref(), Some("engine-a"));
+        assert_eq!(a.status().changes, 1);
+    }
+
+    #[test]
+    fn test_leader_renews_every_interval() {
+        let mut a = instance("engine-a", T0);
+        let first = elect(&mut a, T0 + 3 * SECOND);
+
+        assert_eq!(a.tick(T0 + 5 * SECOND), (None, None));
+        let (renewal, change) = a.tick(T0 + 6 * SECOND);
+        let renewal = renewal.unwrap();
+        assert_eq!(change, None);
+        assert_eq!(renewal.claimed_at, first.claimed_at);
+        assert_eq!(renewal.renewed_at, T0 + 6 * SECOND);
+
+        // Its own renewal echoed back changes nothing
+        assert_eq!(a.observe(renewal, T0 + 6 * SECOND), None);
+        assert!(a.is_leader());
+    }
+
+    #[test]
+    fn test_follower_waits_while_the_leader_renews() {
+        let mut b = instance("engine-b", T0);
+        let change = b.observe(claim("engine-a", T0 - 60 * SECOND, T0), T0);
+        assert_eq!(
+            change,
+            Some(LeaderChange::Following {
+                leader: "engine-a".into()
+            })
+        );
+        for second in 1..30 {
+            let now = T0 + second * SECOND;
+            if second % 3 == 0 {
+                assert_eq!(
+                    b.observe(claim("engine-a", T0 - 60 * SECOND, now), now),
+                    None
+                );
+            }
+            assert_eq!(b.tick(now), (None, None));
+        }
+        assert_eq!(b.leader(), Some("engine-a"));
+        assert_eq!(b.status().changes, 1);
+    }
+
+    #[test]
+    fn test_follower_takes_over_after_the_lease_expires() {
+        let mut b = instance("engine-b", T0);
+        b.observe(claim("engine-a", T0 - 60 * SECOND, T0), T0);
+
+        assert_eq!(b.tick(T0 + 9 * SECOND), (None, None));
+        let claim = elect(&mut b, T0 + 10 * SECOND);
+        assert_eq!(claim.instance_id, "engine-b");
+        assert_eq!(b.leader(), Some("engine-b"));
+        assert_eq!(b.status().since, T0 + 10 * SECOND);
+    }
+
+    #[test]
+    fn test_released_claim_hands_over_at_the_next_tick() {
+        let mut a = instance("engine-a", T0);
+        let mut b = instance("engine-b", T0);
+        let claim = elect(&mut a, T0 + 3 * SECOND);
+        b.observe(claim, T0 + 3 * SECOND);
+
+        let released = a.release(T0 + 4 * SECOND).unwrap();
+        assert!(released.released);
+        assert!(!a.leadership().is_leader());
+        assert_eq!(a.release(T0 + 4 * SECOND), None);
+
+        assert_eq!(b.observe(released, T0 + 4 * SECOND), None);
+        elect(&mut b, T0 + 5 * SECOND);
+    }
+
+    #[test]
+    fn test_split_brain_keeps_the_earlier_claim() {
+        let mut a = instance("engine-a", T0);
+        let mut b = instance("engine-b", T0);
+        let a_claim = elect(&mut a, T0 + 3 * SECOND);
+        let b_claim = elect(&mut b, T0 + 4 * SECOND);
+
+        // The partition heals and each sees the other's claim
+        assert_eq!(
+            b.observe(a_claim.clone(), T0 + 20 * SECOND),
+            Some(LeaderChange::Following {
+                leader: "engine-a".into()
+            })
+        );
+        assert_eq!(a.observe(b_claim, T0 + 20 * SECOND), None);
+        assert!(a.is_leader());
+        assert!(!b.is_leader());
+        assert!(!b.leadership().is_leader());
+
+        // The winner answers at once rather than at its next renewal
+        let (renewal, _) = a.tick(T0 + 20 * SECOND);
+        assert_eq!(renewal.unwrap().claimed_at, a_claim.claimed_at);
+    }
+
+    #[test]
+    fn test_split_brain_tie_goes_to_the_higher_instance_id() {
+        let mut a = instance("engine-a", T0);
+        let mut b = instance("engine-b", T0);
+        let a_claim = elect(&mut a, T0 + 3 * SECOND);
+        let b_claim = elect(&mut b, T0 + 3 * SECOND);
+
+        assert_eq!(
+            a.observe(b_claim, T0 + 4 * SECOND),
+            Some(LeaderChange::Following {
+                leader: "engine-b".into()
+            })
+        );
+        assert_eq!(b.observe(a_claim, T0 + 4 * SECOND), None);
+        assert!(b.is_leader());
+        assert!(!a.is_leader());
+    }
+
+    #[test]
+    fn test_follower_prefers_the_outranking_of_two_leaders() {
+        let mut c = instance("engine-c", T0);
+        c.observe(claim("engine-b", T0 - 5 * SECOND, T0), T0);
+        assert!(
+            c.observe(claim("engine-a", T0 - 9 * SECOND, T0), T0)
+                .is_some()
+        );
+        assert_eq!(
+            c.observe(claim("engine-b", T0 - 5 * SECOND, T0 + SECOND), T0 + SECOND),
+            None
+        );
+        assert_eq!(c.leader(), Some("engine-a"));
+    }
+
+    #[test]
+    fn test_disconnected_leader_steps_down_until_elected_again() {
+        let mut a = instance("engine-a", T0);
+        elect(&mut a, T0 + 3 * SECOND);
+
+        assert_eq!(
+            a.disconnected(T0 + 5 * SECOND),
+            Some(LeaderChange::SteppedDown)
+        );
+        assert!(!a.leadership().is_leader());
+        assert_eq!(a.tick(T0 + 60 * SECOND), (None, None));
+
+        // Someone else took over meanwhile
+        a.connected(T0 + 60 * SECOND);
+        a.observe(
+            claim("engine-b", T0 + 20 * SECOND, T0 + 60 * SECOND),
+            T0 + 60 * SECOND,
+        );
+        assert_eq!(a.tick(T0 + 63 * SECOND), (None, None));
+        assert_eq!(a.leader(), Some("engine-b"));
+        assert_eq!(a.status().changes, 3);
+    }
+
+    #[test]
+    fn test_instance_id_defaults() {
+        assert_eq!(LeaderConfig::default().resolve_instance_id(), "engine");
+        let generated = config().resolve_instance_id();
+        assert!(generated.starts_with("engine-"), "{generated}");
+        assert_ne!(generated, config().resolve_instance_id());
+        let named = LeaderConfig {
+            instance_id: Some("engine-north".into()),
+            ..config()
+        };
+        assert_eq!(named.resolve_instance_id(), "engine-north");
+    }
+}
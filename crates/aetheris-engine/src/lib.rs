@@ -0,0 +1,7968 @@
+//! AETHERIS Engine - Robotics Simulation and MQTT Communication Hub
+//!
+//! This crate provides:
+//! - Async MQTT client for robot coordination
+//! - Heartbeat mechanism for connectivity monitoring
+//! - Multi-robot telemetry broadcasting
+//! - Command dispatch and response handling
+
+pub mod acks;
+pub mod alarms;
+pub mod alert_dedup;
+pub mod anomalies;
+pub mod anomaly_store;
+pub mod authorization;
+pub mod backpressure;
+pub mod battery;
+pub mod battery_manager;
+pub mod bounds;
+pub mod broadcast;
+pub mod capabilities;
+pub mod clock;
+pub mod command_expiry;
+pub mod command_queue;
+pub mod config;
+pub mod correlation;
+pub mod dead_letters;
+pub mod decision;
+pub mod delta;
+pub mod detector_eval;
+pub mod detectors;
+pub mod dispatch;
+pub mod environment;
+pub mod error;
+pub mod event_log;
+pub mod events;
+pub mod expected_fleet;
+pub mod failover;
+pub mod fanout;
+pub mod faults;
+pub mod flapping;
+pub mod geofences;
+#[cfg(feature = "http")]
+pub mod http_bridge;
+pub mod ingest;
+pub mod leader;
+pub mod link_quality;
+pub mod metrics;
+pub mod missions;
+pub mod offline_buffer;
+pub mod operator_cli;
+#[cfg(feature = "sqlite")]
+pub mod persistence;
+pub mod position_filter;
+#[cfg(feature = "sqlite")]
+pub mod query;
+pub mod reconnect;
+pub mod replay;
+pub mod robot_config;
+pub mod rollout;
+pub mod route_requests;
+pub mod section_health;
+pub mod sections;
+pub mod sensor_health;
+pub mod sequence;
+pub mod shutdown;
+pub mod simulation;
+pub mod source_binding;
+pub mod source_signing;
+pub mod store_forward;
+pub mod telemetry_store;
+pub mod timeline;
+pub mod transport;
+pub mod trends;
+pub mod triage;
+pub mod wall_thickness;
+pub mod zones;
+
+use std::collections::hash_map::{Entry, RandomState};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::hash::BuildHasher;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock as StdRwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Duration;
+
+use rumqttc::{
+    AsyncClient, ClientError, Event, EventLoop, LastWill, MqttOptions, Outgoing, Packet, Publish,
+    QoS, Request,
+};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tokio::sync::{Notify, RwLock, mpsc, watch};
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, interval};
+use tracing::{debug, error, info, warn};
+
+use aetheris_shared::topics::{CommandTarget, Topic};
+use aetheris_shared::{
+    AetherisError, AnomalyReport, AnomalyStatus, AnomalyType, BroadcastResult, ChargingStation,
+    Command, CommandResponse, CurrentTask, DeadLetter, DeadLetterReason, Encoding, EncodingError,
+    EngineState, ErrorKind, FaultType, FilteredTelemetry, FleetCount, HealthStatus, Heartbeat,
+    LeaderClaim, LinkQuality, MissionStatus, MqttMessage, NearbyRobot, Orientation, PatrolRoute,
+    PipeEnvironment, PipeMaterial, PipelineMap, PipelineSection, Position, Recovery, Resolution,
+    RobotConfig, RobotId, RobotState, RobotStatus, RobotType, RobotView, RouteLibrary, RouteMode,
+    RouteRequest, SectionHealthReport, SeverityLevel, SignatureError, SystemStatus, TelemetryBatch,
+    TelemetryPayload, TimelineEntry, Timestamp, TriageRequest, TriageResult, Validate, Velocity,
+    Waypoint, limits, topics,
+};
+
+use crate::acks::{AckError, CommandAcks, CommandUpdate, ResponseMatch};
+use crate::alarms::{AlarmEvent, EnvironmentAlarms};
+use crate::alert_dedup::{AlertDedup, DedupVerdict};
+use crate::anomalies::{
+    ActiveAnomalies, ENGINE_ORIGIN, EscalationConfig, MergeOutcome, SYSTEM_SECTION,
+};
+use crate::anomaly_store::AnomalyStore;
+use crate::authorization::CommandAuthorizer;
+use crate::backpressure::{Closed, CoalescingSlots, Handoff, LossyClass};
+use crate::battery::worse;
+use crate::battery_manager::BatteryManager;
+use crate::bounds::BoundsGuard;
+use crate::broadcast::BroadcastTracker;
+use crate::capabilities::CapabilityRegistry;
+use crate::clock::{RealClock, SharedClock};
+use crate::command_expiry::CommandExpiryConfig;
+use crate::command_queue::{CommandPriority, CommandQueue, Enqueued};
+use crate::config::{CheckConfig, ConfigChecker, EngineConfig};
+use crate::correlation::{AlertCorrelator, CommandAuditEntry};
+use crate::dead_letters::DeadLetterQueue;
+use crate::decision::{CandidateEvaluation, Decision, DecisionLog, ExclusionReason, PolicyKind};
+use crate::delta::{DeltaEncoder, KeyframeRequests};
+use crate::detectors::{AnomalyDetector, Observation, ThresholdDetector};
+use crate::dispatch::{AutoDispatcher, DispatchPlan};
+use crate::error::{Result, TransportContext};
+use crate::events::{EventLog, SystemEvent, SystemEventKind};
+use crate::expected_fleet::{Arrival, ExpectedFleet};
+use crate::failover::{BrokerEndpoint, BrokerRotation, BrokerSwitch, FailoverConfig, SwitchReason};
+use crate::fanout::{
+    CommandOutcome, CommandPublisher, Delivery, FanoutConfig, FanoutReport, PublishError,
+};
+use crate::flapping::{FlapConfig, FlapDetector};
+use crate::geofences::{Enforcement, Geofences};
+use crate::ingest::{ParseLimits, ParseRejection, PayloadGuard, SourceViolations};
+use crate::leader::{LeaderChange, LeaderElection, Leadership};
+use crate::link_quality::{DriftChange, LinkEstimator, LinkQualityConfig};
+use crate::metrics::{Metrics, TopicClass};
+use crate::missions::MissionDriver;
+use crate::offline_buffer::{HeldPublish, Hold, HoldClass, OfflineBuffer, OfflineBufferConfig};
+use crate::position_filter::PositionFilter;
+use crate::reconnect::{Backoff, ConnectionMonitor, ConnectionState, ReconnectConfig};
+use crate::robot_config::RobotConfigRegistry;
+use crate::rollout::{ConfigPush, RolloutController, RolloutEventKind, RolloutPlan};
+use crate::route_requests::{ANONYMOUS_REQUESTER, RouteRequestLimiter};
+use crate::section_health::SectionHealth;
+use crate::sections::{SectionError, SectionInfo, SectionRegistry};
+use crate::sensor_health::{SensorHealth, SensorHealthEvent};
+use crate::sequence::{MessageClass, SeqVerdict, SequenceCounter, SequenceTracker};
+use crate::shutdown::Shutdown;
+use crate::source_binding::{SourceGuard, SourceVerdict};
+use crate::source_signing::SourceSigner;
+use crate::store_forward::{Offer, PendingCommand, Release, StoreAndForward};
+use crate::telemetry_store::{HistoryQuery, HistoryRecord, TelemetryStore};
+use crate::timeline::{RobotHistory, TimelineConfig, TimelineFocus, TimelineSources};
+use crate::transport::{FatalConnectError, Secret, TlsConfig};
+use crate::trends::TrendDetector;
+use crate::triage::{TriageCoordinator, TriageDecision};
+use crate::wall_thickness::WallThicknessTrends;
+use crate::zones::{ZoneRegistry, ZoneStatus};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Time without a heartbeat after which a robot is marked offline
+pub const DEFAULT_HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(limits::HEARTBEAT_TIMEOUT_SECS);
+
+/// MQTT client configuration
+#[derive(Debug, Clone)]
+pub struct MqttConfig {
+    pub broker_host: String,
+    pub broker_port: u16,
+    pub client_id: String,
+    pub keep_alive_secs: u64,
+    pub clean_session: bool,
+    /// Size, depth, and time limits for incoming payloads
+    pub parse_limits: ParseLimits,
+    /// Message classes the broker keeps for late subscribers
+    pub retain: RetainPolicy,
+    /// Alert topics published to and subscribed to
+    pub alert_topics: AlertTopics,
+    /// Format of published payloads; incoming ones are accepted in any
+    /// [`Encoding`]. The dashboard reads JSON only.
+    pub encoding: Encoding,
+    /// Send each robot its commands in the format it last published in,
+    /// rather than in `encoding`
+    pub negotiate_encoding: bool,
+    /// Delays between attempts after the connection drops
+    pub reconnect: ReconnectConfig,
+    /// Broker credentials; the password is never logged
+    pub username: Option<String>,
+    pub password: Option<Secret>,
+    /// Connect over TLS instead of plain TCP
+    pub tls: Option<TlsConfig>,
+    /// Brokers to fail over to, most preferred first, after the one above
+    pub backup_brokers: Vec<BrokerEndpoint>,
+    /// When to switch brokers
+    pub failover: FailoverConfig,
+    /// What is held while no broker is reachable
+    pub offline_buffer: OfflineBufferConfig,
+    /// Period of the system status and fleet summary
+    pub status_interval: Duration,
+}
+
+impl MqttConfig {
+    /// Every broker to connect to, most preferred first: the primary one,
+    /// then the backups with the primary's credentials and TLS where they
+    /// set none of their own
+    pub fn endpoints(&self) -> Vec<BrokerEndpoint> {
+        let primary = BrokerEndpoint {
+            host: self.broker_host.clone(),
+            port: self.broker_port,
+            username: self.username.clone(),
+            password: self.password.clone(),
+            tls: self.tls.clone(),
+        };
+        let backups = self.backup_brokers.iter().map(|backup| {
+            let own_credentials = backup.username.is_some();
+            BrokerEndpoint {
+                host: backup.host.clone(),
+                port: backup.port,
+                username: if own_credentials {
+                    backup.username.clone()
+                } else {
+                    self.username.clone()
+                },
+                password: if own_credentials {
+                    backup.password.clone()
+                } else {
+                    self.password.clone()
+                },
+                tls: backup.tls.clone().or_else(|| self.tls.clone()),
+            }
+        });
+        std::iter::once(primary).chain(backups).collect()
+    }
+}
+
+/// Which published message classes are retained, so a dashboard that
+/// connects late sees the latest one at once
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetainPolicy {
+    /// Latest telemetry of each robot
+    pub telemetry: bool,
+    /// Engine status, including the Last Will
+    pub system_status: bool,
+}
+
+/// Every alert goes to the subtopic of its severity
+/// (`aetheris/alerts/{severity}`) and, unless turned off, the flat
+/// `aetheris/alerts` topic older consumers read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlertTopics {
+    /// Also publish on the flat topic. Robots that publish only there are
+    /// not heard once it is off.
+    pub legacy: bool,
+    /// Subscribe to the severity subtopics at or above this level instead
+    /// of the flat topic
+    pub min_severity: Option<SeverityLevel>,
+}
+
+impl Default for AlertTopics {
+    fn default() -> Self {
+        Self {
+            legacy: true,
+            min_severity: None,
+        }
+    }
+}
+
+impl AlertTopics {
+    /// Topics to subscribe to: the flat topic while it is published and no
+    /// minimum severity is set, otherwise the severity subtopics
+    pub fn subscriptions(&self) -> Vec<String> {
+        if self.legacy && self.min_severity.is_none() {
+            return vec![topics::ALERTS.to_string()];
+        }
+        let min_severity = self.min_severity.unwrap_or(SeverityLevel::Info);
+        SeverityLevel::ALL
+            .into_iter()
+            .filter(|level| *level >= min_severity)
+            .map(topics::alerts_severity)
+            .collect()
+    }
+}
+
+impl Default for RetainPolicy {
+    fn default() -> Self {
+        Self {
+            telemetry: true,
+            system_status: true,
+        }
+    }
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            broker_host: "localhost".into(),
+            broker_port: limits::MQTT_PORT,
+            client_id: format!("aetheris-engine-{}", uuid::Uuid::new_v4()),
+            keep_alive_secs: limits::MQTT_KEEP_ALIVE_SECS,
+            clean_session: true,
+            parse_limits: ParseLimits::default(),
+            retain: RetainPolicy::default(),
+            alert_topics: AlertTopics::default(),
+            encoding: Encoding::Json,
+            negotiate_encoding: true,
+            reconnect: ReconnectConfig::default(),
+            username: None,
+            password: None,
+            tls: None,
+            backup_brokers: Vec::new(),
+            failover: FailoverConfig::default(),
+            offline_buffer: OfflineBufferConfig::default(),
+            status_interval: Duration::from_secs(10),
+        }
+    }
+}
+
+impl CheckConfig for MqttConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if self.broker_host.trim().is_empty() {
+            checker.error(
+                "broker_host",
+                "must not be empty",
+                Some("e.g. \"localhost\"".into()),
+            );
+        }
+        if self.broker_port == 0 {
+            checker.error(
+                "broker_port",
+                "must be a valid port",
+                Some(format!("the MQTT default is {}", limits::MQTT_PORT)),
+            );
+        }
+        if self.client_id.is_empty() {
+            checker.error("client_id", "must not be empty", None);
+        }
+        if self.keep_alive_secs == 0 {
+            checker.error("keep_alive_secs", "must be greater than zero", None);
+        }
+        if self.password.is_some() && self.username.is_none() {
+            checker.error(
+                "password",
+                "is set without a username",
+                Some("set mqtt.username as well".into()),
+            );
+        }
+        checker.positive("status_interval", self.status_interval);
+        checker.check_section("parse_limits", &self.parse_limits);
+        checker.check_section("reconnect", &self.reconnect);
+        if let Some(tls) = &self.tls {
+            checker.check_section("tls", tls);
+        }
+        for (i, backup) in self.backup_brokers.iter().enumerate() {
+            checker.check_section(&format!("backup_brokers.{i}"), backup);
+        }
+        checker.check_section("failover", &self.failover);
+        checker.check_section("offline_buffer", &self.offline_buffer);
+    }
+}
+
+// ============================================================================
+// ROBOT FLEET MANAGER
+// ============================================================================
+
+/// Shards of the robot map; robots in different shards update in parallel
+const FLEET_SHARDS: usize = 16;
+
+/// Manages the state of all robots in the fleet.
+///
+/// Robots are spread over shards, each behind its own lock, so telemetry
+/// from different robots rarely contends and every method takes `&self`:
+/// the fleet is shared as a plain `Arc`. Queries return owned snapshots
+/// rather than borrowing from a guard, so they may be held across an
+/// `.await`; a query over the whole fleet reads it shard by shard and may
+/// see an update that lands mid-scan.
+#[derive(Debug)]
+pub struct FleetManager {
+    shards: Vec<StdRwLock<HashMap<RobotId, FleetEntry>>>,
+    hasher: RandomState,
+    /// Heartbeat timeout duration
+    heartbeat_timeout: Duration,
+    /// Robots declared in the expected fleet
+    expected: StdRwLock<HashSet<RobotId>>,
+    flaps: StdRwLock<FlapDetector>,
+    /// Clock skew estimation from heartbeat timestamps
+    link_quality: LinkQualityConfig,
+    /// Time heartbeat deadlines are measured in
+    clock: SharedClock,
+}
+
+/// A robot's state and connection bookkeeping
+#[derive(Debug)]
+struct FleetEntry {
+    state: RobotState,
+    /// Last heartbeat or telemetry; none for a declared robot never heard from
+    last_seen: Option<Instant>,
+    /// What the robot was doing when it went offline
+    offline: Option<OfflineRecord>,
+    /// Delay and skew of its heartbeats
+    link: LinkEstimator,
+}
+
+fn read<T>(lock: &StdRwLock<T>) -> RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(|e| e.into_inner())
+}
+
+fn write<T>(lock: &StdRwLock<T>) -> RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A robot's state before it was marked offline, restored when it returns
+#[derive(Debug, Clone, Copy)]
+struct OfflineRecord {
+    status: RobotStatus,
+    health: HealthStatus,
+    since: Instant,
+}
+
+/// A robot heard from again after being marked offline
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reconnection {
+    pub robot_id: String,
+    pub offline_for: Duration,
+    /// Reconnects within the flapping window, when this one started a
+    /// flapping episode
+    pub flapping: Option<usize>,
+}
+
+/// Robot counts for dashboards
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct FleetSummary {
+    pub total: usize,
+    pub active: usize,
+    pub idle: usize,
+    pub maintenance: usize,
+    pub error: usize,
+    pub offline: usize,
+    /// Robots declared in the expected fleet
+    pub expected: usize,
+    /// Declared robots currently offline, including ones never heard from
+    pub expected_missing: usize,
+    /// Robots heard from but not declared (zero without a declaration)
+    pub unexpected: usize,
+    /// Open anomaly assignments per assignee; filled in by
+    /// [`AetherisMqtt::summary`]
+    pub open_assignments: BTreeMap<String, usize>,
+    /// Every zone with its mode and expiry; filled in by
+    /// [`AetherisMqtt::summary`]
+    pub zones: Vec<ZoneStatus>,
+}
+
+impl Default for FleetManager {
+    fn default() -> Self {
+        Self::new(Duration::ZERO)
+    }
+}
+
+impl FleetManager {
+    pub fn new(heartbeat_timeout: Duration) -> Self {
+        Self {
+            shards: (0..FLEET_SHARDS).map(|_| StdRwLock::default()).collect(),
+            hasher: RandomState::new(),
+            heartbeat_timeout,
+            expected: StdRwLock::default(),
+            flaps: StdRwLock::new(FlapDetector::default()),
+            link_quality: LinkQualityConfig::default(),
+            clock: Arc::new(RealClock),
+        }
+    }
+
+    /// Detect robots whose connection keeps dropping
+    pub fn with_flap_detection(mut self, config: FlapConfig) -> Self {
+        self.flaps = StdRwLock::new(FlapDetector::new(config));
+        self
+    }
+
+    /// Estimate clock skew from heartbeat timestamps under `config`
+    pub fn with_link_quality(mut self, config: LinkQualityConfig) -> Self {
+        self.link_quality = config;
+        self
+    }
+
+    /// Measure heartbeat deadlines and offline spells on `clock`
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The clock heartbeat deadlines are measured on
+    pub fn clock(&self) -> SharedClock {
+        self.clock.clone()
+    }
+
+    fn shard(&self, robot_id: &str) -> &StdRwLock<HashMap<RobotId, FleetEntry>> {
+        &self.shards[self.hasher.hash_one(robot_id) as usize % self.shards.len()]
+    }
+
+    /// Visit every robot, shard by shard
+    fn for_each(&self, mut visit: impl FnMut(&RobotState)) {
+        for shard in &self.shards {
+            read(shard).values().for_each(|entry| visit(&entry.state));
+        }
+    }
+
+    /// Snapshots of the robots passing `keep`
+    fn snapshot(&self, keep: impl Fn(&RobotState) -> bool) -> Vec<RobotState> {
+        let mut robots = Vec::new();
+        self.for_each(|robot| {
+            if keep(robot) {
+                robots.push(robot.clone());
+            }
+        });
+        robots
+    }
+
+    /// Pre-register a declared robot as offline until it is heard from
+    pub fn register_expected(&self, id: &RobotId, robot_type: RobotType) {
+        write(&self.expected).insert(id.clone());
+        write(self.shard(id))
+            .entry(id.clone())
+            .or_insert_with(|| FleetEntry {
+                state: RobotState {
+                    status: RobotStatus::Offline,
+                    ..RobotState::new(id.clone(), id.as_str(), robot_type)
+                },
+                last_seen: None,
+                offline: None,
+                link: LinkEstimator::default(),
+            });
+    }
+
+    /// Whether the robot is declared in the expected fleet
+    pub fn is_expected(&self, robot_id: &str) -> bool {
+        read(&self.expected).contains(robot_id)
+    }
+
+    /// Counts by status and against the expected fleet
+    pub fn summary(&self) -> FleetSummary {
+        let expected = read(&self.expected);
+        let mut summary = FleetSummary {
+            expected: expected.len(),
+            ..FleetSummary::default()
+        };
+        self.for_each(|robot| {
+            summary.total += 1;
+            match robot.status {
+                RobotStatus::Active => summary.active += 1,
+                RobotStatus::Idle => summary.idle += 1,
+                RobotStatus::Maintenance => summary.maintenance += 1,
+                RobotStatus::Error => summary.error += 1,
+                RobotStatus::Offline => summary.offline += 1,
+            }
+            let is_expected = expected.contains(&robot.id);
+            if is_expected && robot.status == RobotStatus::Offline {
+                summary.expected_missing += 1;
+            } else if !is_expected && !expected.is_empty() {
+                summary.unexpected += 1;
+            }
+        });
+        summary
+    }
+
+    /// Heartbeat timeout after which robots are marked offline
+    pub fn heartbeat_timeout(&self) -> Duration {
+        self.heartbeat_timeout
+    }
+
+    /// Register a new robot or update existing. Telemetry from a robot
+    /// marked offline brings it back with the status it reports.
+    pub fn update_robot(&self, mut state: RobotState) -> Option<Reconnection> {
+        let now = self.clock.instant();
+        if read(&self.flaps).is_flapping(&state.id, now) {
+            state.health = worse(state.health, HealthStatus::Warning);
+        }
+        let reports_offline = state.status == RobotStatus::Offline;
+        let robot_id = state.id.clone();
+        let mut shard = write(self.shard(&robot_id));
+        let entry = match shard.entry(robot_id) {
+            Entry::Occupied(occupied) => {
+                let entry = occupied.into_mut();
+                entry.state = state;
+                entry
+            }
+            Entry::Vacant(vacant) => vacant.insert(FleetEntry {
+                state,
+                last_seen: None,
+                offline: None,
+                link: LinkEstimator::default(),
+            }),
+        };
+        entry.last_seen = Some(now);
+        if reports_offline {
+            return None;
+        }
+        self.reconnect(entry, now)
+    }
+
+    /// Record heartbeat from a robot. A robot marked offline gets back the
+    /// status and health it had before.
+    pub fn record_heartbeat(&self, robot_id: &str) -> Option<Reconnection> {
+        let now = self.clock.instant();
+        let mut shard = write(self.shard(robot_id));
+        let entry = shard.get_mut(robot_id)?;
+        entry.last_seen = Some(now);
+        self.reconnect(entry, now)
+    }
+
+    /// Count a heartbeat the robot stamped `sent`, arriving now, towards its
+    /// skew estimate. Returns the change when its clock drifts beyond the
+    /// bound or comes back within it.
+    pub fn record_link(&self, robot_id: &str, sent: Timestamp) -> Option<DriftChange> {
+        let received = self.clock.now();
+        let mut shard = write(self.shard(robot_id));
+        let entry = shard.get_mut(robot_id)?;
+        entry.link.observe(&self.link_quality, sent, received)
+    }
+
+    /// Link delay and clock skew of a robot, once enough heartbeats arrived
+    pub fn link_quality(&self, robot_id: &str) -> Option<LinkQuality> {
+        read(self.shard(robot_id))
+            .get(robot_id)?
+            .link
+            .estimate(&self.link_quality)
+    }
+
+    /// Link delay and clock skew of every robot with an estimate
+    pub fn link_qualities(&self) -> BTreeMap<String, LinkQuality> {
+        let mut qualities = BTreeMap::new();
+        for shard in &self.shards {
+            for (id, entry) in read(shard).iter() {
+                if let Some(quality) = entry.link.estimate(&self.link_quality) {
+                    qualities.insert(id.to_string(), quality);
+                }
+            }
+        }
+        qualities
+    }
+
+    /// A timestamp from the robot's clock moved onto the engine's by its
+    /// skew estimate; unchanged without one
+    pub fn corrected_timestamp(&self, robot_id: &str, sent: Timestamp) -> Timestamp {
+        read(self.shard(robot_id))
+            .get(robot_id)
+            .map_or(sent, |entry| entry.link.correct(&self.link_quality, sent))
+    }
+
+    fn reconnect(&self, entry: &mut FleetEntry, now: Instant) -> Option<Reconnection> {
+        let record = entry.offline.take()?;
+        let robot = &mut entry.state;
+        let flapping = write(&self.flaps).on_reconnect(&robot.id, now);
+        // Telemetry already replaced the state we marked offline
+        if robot.status == RobotStatus::Offline {
+            robot.status = record.status;
+            robot.health = record.health;
+        }
+        if read(&self.flaps).is_flapping(&robot.id, now) {
+            robot.health = worse(robot.health, HealthStatus::Warning);
+        }
+        Some(Reconnection {
+            robot_id: robot.id.to_string(),
+            offline_for: now.duration_since(record.since),
+            flapping,
+        })
+    }
+
+    fn timed_out(&self, entry: &FleetEntry, now: Instant) -> bool {
+        entry.state.status != RobotStatus::Offline
+            && entry
+                .last_seen
+                .is_some_and(|seen| now.duration_since(seen) > self.heartbeat_timeout)
+    }
+
+    /// Get the robots that missed their heartbeat deadline and are not yet
+    /// marked offline
+    pub fn get_timed_out_robots(&self) -> Vec<RobotId> {
+        let now = self.clock.instant();
+        let mut timed_out = Vec::new();
+        for shard in &self.shards {
+            let shard = read(shard);
+            timed_out.extend(
+                shard
+                    .iter()
+                    .filter(|(_, entry)| self.timed_out(entry, now))
+                    .map(|(id, _)| id.clone()),
+            );
+        }
+        timed_out
+    }
+
+    /// Mark a robot as offline, remembering its status and health for when
+    /// it returns. Returns whether the robot was online.
+    pub fn mark_offline(&self, robot_id: &str) -> bool {
+        let mut shard = write(self.shard(robot_id));
+        shard
+            .get_mut(robot_id)
+            .is_some_and(|entry| Self::take_offline(entry, self.clock.instant()))
+    }
+
+    /// Mark offline the robots that missed their heartbeat deadline, checked
+    /// under the same lock so telemetry arriving meanwhile is not overruled.
+    /// Returns the robots marked.
+    pub fn mark_timed_out(&self) -> Vec<RobotId> {
+        let now = self.clock.instant();
+        let mut marked = Vec::new();
+        for shard in &self.shards {
+            for (id, entry) in write(shard).iter_mut() {
+                if self.timed_out(entry, now) && Self::take_offline(entry, now) {
+                    marked.push(id.clone());
+                }
+            }
+        }
+        marked
+    }
+
+    fn take_offline(entry: &mut FleetEntry, now: Instant) -> bool {
+        let robot = &mut entry.state;
+        if robot.status == RobotStatus::Offline {
+            return false;
+        }
+        entry.offline = Some(OfflineRecord {
+            status: robot.status,
+            health: robot.health,
+            since: now,
+        });
+        robot.status = RobotStatus::Offline;
+        robot.health = HealthStatus::Critical;
+        true
+    }
+
+    /// Settings of the flapping detection
+    pub fn flap_config(&self) -> FlapConfig {
+        read(&self.flaps).config().clone()
+    }
+
+    /// Snapshots of every known robot
+    pub fn get_all_robots(&self) -> Vec<RobotState> {
+        self.snapshot(|_| true)
+    }
+
+    /// Snapshot of a specific robot by ID
+    pub fn get_robot(&self, id: &str) -> Option<RobotState> {
+        read(self.shard(id))
+            .get(id)
+            .map(|entry| entry.state.clone())
+    }
+
+    /// Robots of one type, by ID
+    pub fn robots_by_type(&self, robot_type: RobotType) -> Vec<RobotState> {
+        self.sorted_by_id(|robot| robot.robot_type == robot_type)
+    }
+
+    /// Robots in one status, by ID
+    pub fn robots_with_status(&self, status: RobotStatus) -> Vec<RobotState> {
+        self.sorted_by_id(|robot| robot.status == status)
+    }
+
+    fn sorted_by_id(&self, keep: impl Fn(&RobotState) -> bool) -> Vec<RobotState> {
+        let mut robots = self.snapshot(keep);
+        robots.sort_by(|a, b| a.id.cmp(&b.id));
+        robots
+    }
+
+    /// Nearest robot to `target`, of `robot_type` if given. Robots without a
+    /// finite position are skipped; robot ID breaks ties.
+    pub fn nearest_robot(
+        &self,
+        target: &Position,
+        robot_type: Option<RobotType>,
+    ) -> Option<RobotState> {
+        self.by_distance(target, |robot| {
+            robot_type.is_none_or(|robot_type| robot.robot_type == robot_type)
+        })
+        .into_iter()
+        .next()
+    }
+
+    /// Robots of `robot_type` that can take on work at `target`: not
+    /// offline, in error, or in maintenance, and with at least `min_battery`.
+    /// Nearest first, robot ID breaking ties; robots without a finite
+    /// position are skipped.
+    pub fn find_candidates(
+        &self,
+        target: &Position,
+        robot_type: RobotType,
+        min_battery: f64,
+    ) -> Vec<RobotState> {
+        self.by_distance(target, |robot| {
+            robot.robot_type == robot_type
+                && matches!(robot.status, RobotStatus::Active | RobotStatus::Idle)
+                && robot.battery >= min_battery
+        })
+    }
+
+    /// Robots with a finite position passing `keep`, nearest to `target`
+    /// first; none if `target` itself is not finite
+    fn by_distance(
+        &self,
+        target: &Position,
+        keep: impl Fn(&RobotState) -> bool,
+    ) -> Vec<RobotState> {
+        if !target.is_finite() {
+            return Vec::new();
+        }
+        let mut robots: Vec<_> = self
+            .snapshot(|robot| robot.position.is_finite() && keep(robot))
+            .into_iter()
+            .map(|robot| (robot.position.distance_to(target), robot))
+            .collect();
+        robots.sort_by(|(a_distance, a), (b_distance, b)| {
+            a_distance
+                .total_cmp(b_distance)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        robots.into_iter().map(|(_, robot)| robot).collect()
+    }
+
+    /// Number of robots in each status, including statuses with none
+    pub fn robot_count_by_status(&self) -> HashMap<RobotStatus, usize> {
+        let mut counts: HashMap<RobotStatus, usize> = [
+            RobotStatus::Active,
+            RobotStatus::Idle,
+            RobotStatus::Maintenance,
+            RobotStatus::Error,
+            RobotStatus::Offline,
+        ]
+        .into_iter()
+        .map(|status| (status, 0))
+        .collect();
+        self.for_each(|robot| *counts.entry(robot.status).or_default() += 1);
+        counts
+    }
+
+    /// Connected and offline robots per type, including types with none
+    pub fn fleet_summary(&self) -> BTreeMap<RobotType, FleetCount> {
+        let mut counts: BTreeMap<_, FleetCount> = RobotType::ALL
+            .map(|robot_type| (robot_type, FleetCount::default()))
+            .into();
+        self.for_each(|robot| {
+            let count = counts.entry(robot.robot_type).or_default();
+            if robot.status == RobotStatus::Offline {
+                count.offline += 1;
+            } else {
+                count.connected += 1;
+            }
+        });
+        counts
+    }
+
+    /// Robots with a finite position, nearest to `target` first
+    pub fn nearby_robots(&self, target: &Position, limit: usize) -> Vec<NearbyRobot> {
+        debug_assert!(target.is_finite(), "positions are bounds-checked on ingest");
+        let mut nearby = Vec::new();
+        self.for_each(|robot| {
+            if robot.position.is_finite() {
+                nearby.push(NearbyRobot {
+                    robot_id: robot.id.to_string(),
+                    robot_type: robot.robot_type,
+                    status: robot.status,
+                    battery: robot.battery,
+                    distance: robot.position.distance_to(target),
+                });
+            }
+        });
+        nearby.sort_by(|a, b| {
+            a.distance
+                .total_cmp(&b.distance)
+                .then_with(|| a.robot_id.cmp(&b.robot_id))
+        });
+        nearby.truncate(limit);
+        nearby
+    }
+
+    /// Score every robot as a dispatch candidate for `target`.
+    ///
+    /// Eligible robots are scored by proximity (`1 / (1 + distance)`, higher is
+    /// better) and listed first, best to worst; excluded robots follow with the
+    /// reason they were ruled out. Robot ID breaks ties deterministically.
+    /// Excluded zones are obstacles: robots inside one, or whose straight
+    /// route to `target` crosses one, are ruled out.
+    pub fn evaluate_dispatch_candidates(
+        &self,
+        target: &Position,
+        min_battery: f64,
+        zones: &ZoneRegistry,
+    ) -> Vec<CandidateEvaluation> {
+        let mut candidates = Vec::new();
+        self.for_each(|robot| {
+            candidates.push({
+                let exclusion = match robot.status {
+                    RobotStatus::Offline => Some(ExclusionReason::Offline),
+                    RobotStatus::Error => Some(ExclusionReason::InError),
+                    RobotStatus::Maintenance => Some(ExclusionReason::InMaintenance),
+                    _ if robot.battery < min_battery => Some(ExclusionReason::LowBattery),
+                    _ if !robot.position.is_finite() => Some(ExclusionReason::InvalidPosition),
+                    _ if zones.excluded_at(&robot.position).is_some()
+                        || zones.blocked_path(&robot.position, target).is_some() =>
+                    {
+                        Some(ExclusionReason::ZoneExcluded)
+                    }
+                    _ => None,
+                };
+                match exclusion {
+                    Some(reason) => CandidateEvaluation::excluded(&robot.id, reason),
+                    None => {
+                        let distance = robot.position.distance_to(target);
+                        CandidateEvaluation::scored(&robot.id, 1.0 / (1.0 + distance))
+                    }
+                }
+            })
+        });
+        decision::rank(&mut candidates);
+        candidates
+    }
+}
+
+// ============================================================================
+// MQTT MESSAGE HANDLER
+// ============================================================================
+
+/// Internal message types for the engine
+#[derive(Debug)]
+pub enum EngineMessage {
+    TelemetryReceived(RobotState),
+    HeartbeatReceived(Heartbeat),
+    AlertReceived(AnomalyReport),
+    /// Alert released for dispatch, after triage or bypassing it
+    AlertTriaged(AnomalyReport),
+    EnvironmentReceived(PipeEnvironment),
+    CommandResponseReceived(CommandResponse),
+    CommandReceived(ReceivedCommand),
+    /// The connection to the given broker (`host:port`) was lost or
+    /// (re)established
+    ConnectionStateChanged(ConnectionState, String),
+    /// The engine moved to another broker
+    BrokerSwitched(BrokerSwitch),
+    /// A robot marked offline was heard from again, after being offline for
+    /// the given time
+    RobotReconnected(String, Duration),
+    /// A broadcast command was answered by every robot, or its deadline passed
+    BroadcastCompleted(BroadcastResult),
+    /// A command the engine sent was accepted, progressed, completed, failed
+    /// or timed out
+    CommandStatusChanged(CommandUpdate),
+}
+
+/// A command seen on the command topics
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceivedCommand {
+    pub command: Command,
+    pub source: String,
+    pub target: CommandTarget,
+    /// Id the command was sent with, or the one assigned when it arrived untagged
+    pub command_id: String,
+    /// Unix timestamp after which the command must not be acted on (milliseconds)
+    pub expires_at: Option<u64>,
+}
+
+/// Generate random coordinate for simulated positions
+fn rand_coord() -> f64 {
+    (rand::random::<f64>() - 0.5) * 200.0 // Range: -100 to 100
+}
+
+// ============================================================================
+// AETHERIS MQTT CLIENT
+// ============================================================================
+
+/// Main MQTT communication hub for the AETHERIS system
+pub struct AetherisMqtt {
+    client: AsyncClient,
+    /// Endpoint connected to, and when to move to another
+    brokers: Mutex<BrokerRotation>,
+    /// Client options of each endpoint, in the rotation's order
+    broker_options: Vec<MqttOptions>,
+    /// Publishes made while no broker was reachable
+    offline_buffer: Mutex<OfflineBuffer>,
+    /// Topics subscribed one at a time, restored with the rest after the
+    /// broker lost our session
+    extra_subscriptions: Mutex<BTreeSet<String>>,
+    /// Format each robot last published in
+    robot_encodings: Mutex<HashMap<String, Encoding>>,
+    config: MqttConfig,
+    fleet: Arc<FleetManager>,
+    message_tx: mpsc::Sender<EngineMessage>,
+    /// Periodic messages waiting for room in the channel
+    coalesced: Mutex<CoalescingSlots>,
+    outgoing_seq: Mutex<SequenceCounter>,
+    sequences: Arc<RwLock<SequenceTracker>>,
+    payload_guard: Mutex<PayloadGuard>,
+    decisions: Arc<RwLock<DecisionLog>>,
+    sections: Arc<RwLock<SectionRegistry>>,
+    pipeline: PipelineMap,
+    alarms: Arc<RwLock<EnvironmentAlarms>>,
+    triage: Arc<RwLock<TriageCoordinator>>,
+    dispatcher: Arc<RwLock<AutoDispatcher>>,
+    authorizer: CommandAuthorizer,
+    correlator: Arc<RwLock<AlertCorrelator>>,
+    trends: Arc<RwLock<TrendDetector>>,
+    battery_manager: Arc<RwLock<BatteryManager>>,
+    wall_trends: Arc<RwLock<WallThicknessTrends>>,
+    /// Run on every environment reading, in the order registered
+    detectors: Mutex<Vec<Box<dyn AnomalyDetector>>>,
+    section_health: Arc<RwLock<SectionHealth>>,
+    sources: Arc<RwLock<SourceGuard>>,
+    signer: SourceSigner,
+    rollouts: Arc<RwLock<RolloutController>>,
+    anomalies: Arc<RwLock<ActiveAnomalies>>,
+    /// Where every anomaly change is written through
+    anomaly_store: Mutex<Box<dyn AnomalyStore>>,
+    telemetry_store: Mutex<Box<dyn TelemetryStore>>,
+    history_retention: Duration,
+    escalation: EscalationConfig,
+    alert_dedup: Arc<RwLock<AlertDedup>>,
+    bounds: Mutex<BoundsGuard>,
+    dead_letters: Mutex<DeadLetterQueue>,
+    events: Arc<RwLock<EventLog>>,
+    history: Arc<RwLock<RobotHistory>>,
+    timeline: TimelineConfig,
+    zones: Arc<RwLock<ZoneRegistry>>,
+    geofences: Geofences,
+    capabilities: Arc<RwLock<CapabilityRegistry>>,
+    /// Effective configuration of every configured robot
+    robot_configs: RwLock<RobotConfigRegistry>,
+    fanout: FanoutConfig,
+    command_queue: Mutex<CommandQueue>,
+    /// Wakes the command dispatcher
+    commands_queued: Notify,
+    store_forward: Arc<RwLock<StoreAndForward>>,
+    sensor_health: Arc<RwLock<SensorHealth>>,
+    position_filter: Arc<RwLock<PositionFilter>>,
+    delta_encoder: Mutex<DeltaEncoder>,
+    keyframes: Mutex<KeyframeRequests>,
+    expected_fleet: Arc<RwLock<ExpectedFleet>>,
+    acks: Arc<RwLock<CommandAcks>>,
+    broadcasts: Arc<RwLock<BroadcastTracker>>,
+    command_expiry: CommandExpiryConfig,
+    /// Every route published so far, republished on request
+    routes: Mutex<RouteLibrary>,
+    route_requests: Mutex<RouteRequestLimiter>,
+    /// Source of everything this instance publishes
+    instance_id: String,
+    /// Leader election with the other instances, when enabled
+    election: Option<Mutex<LeaderElection>>,
+    leadership: Leadership,
+    connection: watch::Sender<ConnectionState>,
+    error_counts: Mutex<HashMap<ErrorKind, u64>>,
+    metrics: Arc<Metrics>,
+    /// Simulated time, the system clock unless scaled
+    clock: SharedClock,
+    /// Unix timestamp the engine started (milliseconds)
+    started_at: u64,
+    /// Messages received from the broker since start
+    received: std::sync::atomic::AtomicU64,
+    /// Received count and time at the previous system status
+    last_status: Mutex<(u64, u64)>,
+}
+
+impl AetherisMqtt {
+    /// Create a new MQTT client with default configuration
+    pub async fn new(
+        config: MqttConfig,
+        message_tx: mpsc::Sender<EngineMessage>,
+    ) -> Result<(Self, EventLoop)> {
+        Self::from_engine_config(
+            EngineConfig {
+                mqtt: config,
+                ..EngineConfig::default()
+            },
+            message_tx,
+        )
+        .await
+    }
+
+    /// Create a new MQTT client with every subsystem configured from `config`
+    pub async fn from_engine_config(
+        config: EngineConfig,
+        message_tx: mpsc::Sender<EngineMessage>,
+    ) -> Result<(Self, EventLoop)> {
+        let EngineConfig {
+            mqtt: config,
+            heartbeat_timeout,
+            clock,
+            flapping,
+            link_quality,
+            alarms,
+            triage,
+            dispatch,
+            authorization,
+            correlation,
+            battery_manager,
+            trends,
+            wall_thickness,
+            thresholds,
+            section_health,
+            source_bindings,
+            source_signing,
+            rollout,
+            merging,
+            escalation,
+            alert_dedup,
+            world_bounds,
+            dead_letters,
+            route_requests,
+            robot_configs,
+            anomaly_store,
+            telemetry_store,
+            timeline,
+            zones,
+            geofences,
+            fanout,
+            command_queue,
+            store_forward,
+            sensor_health,
+            position_filter,
+            expected_fleet,
+            acks,
+            broadcast,
+            command_expiry,
+            sequence,
+            delta,
+            pipeline,
+            leader,
+            ..
+        } = config;
+        let clock = clock.clock();
+        // The broker announces an ungraceful disconnect on our behalf
+        let last_will = SystemStatus {
+            simulated: clock.is_simulated(),
+            ..SystemStatus::offline(&config.client_id, clock.now().as_millis())
+        };
+        let last_will = LastWill::new(
+            topics::SYSTEM_STATUS,
+            config.encoding.encode(&last_will)?,
+            QoS::AtLeastOnce,
+            config.retain.system_status,
+        );
+        // Certificates of every endpoint are read now, not at failover
+        let endpoints = config.endpoints();
+        let broker_options = endpoints
+            .iter()
+            .map(|endpoint| {
+                let mut mqtt_opts =
+                    MqttOptions::new(&config.client_id, &endpoint.host, endpoint.port);
+                mqtt_opts.set_keep_alive(Duration::from_secs(config.keep_alive_secs));
+                mqtt_opts.set_clean_session(config.clean_session);
+                mqtt_opts.set_last_will(last_will.clone());
+                transport::configure(
+                    &mut mqtt_opts,
+                    endpoint.username.as_deref(),
+                    endpoint.password.as_ref(),
+                    endpoint.tls.as_ref(),
+                )?;
+                Ok(mqtt_opts)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let brokers = BrokerRotation::new(endpoints, config.failover.clone());
+        let offline_buffer = OfflineBuffer::new(config.offline_buffer.clone());
+
+        let (client, eventloop) = AsyncClient::new(broker_options[0].clone(), 100);
+        let started_at = clock.now().as_millis();
+
+        let payload_guard = Mutex::new(PayloadGuard::new(config.parse_limits.clone()));
+        let fleet = FleetManager::new(heartbeat_timeout)
+            .with_flap_detection(flapping)
+            .with_link_quality(link_quality)
+            .with_clock(clock.clone());
+        for robot in &expected_fleet.robots {
+            fleet.register_expected(&robot.id, robot.robot_type);
+        }
+        let expected_fleet = ExpectedFleet::new(expected_fleet, clock.now().as_millis());
+        let map = pipeline.map();
+        let hazard_thresholds = alarms.thresholds();
+        let mut detectors: Vec<Box<dyn AnomalyDetector>> = Vec::new();
+        if !thresholds.rules.is_empty() {
+            detectors.push(Box::new(ThresholdDetector::new(thresholds)));
+        }
+        // Unreadable storage loses the previous anomalies, not the engine
+        let mut anomaly_store = anomaly_store.open();
+        let mut anomalies = ActiveAnomalies::new(merging);
+        match anomaly_store.load() {
+            Ok(stored) => {
+                if !stored.is_empty() {
+                    info!(
+                        open = stored.len(),
+                        "Restored anomalies from before the restart"
+                    );
+                }
+                anomalies.restore(stored);
+            }
+            Err(e) => error!(
+                "Failed to load stored anomalies, starting without them: {}",
+                e
+            ),
+        }
+        let instance_id = leader.resolve_instance_id();
+        let election = leader
+            .enabled
+            .then(|| LeaderElection::new(&instance_id, &leader, clock.now().as_millis()));
+        let leadership = match &election {
+            Some(election) => election.leadership(),
+            None => Leadership::new(true),
+        };
+        let metrics = Arc::new(Metrics::default());
+        metrics.set_leader(leadership.is_leader());
+        let mqtt = Self {
+            client,
+            brokers: Mutex::new(brokers),
+            broker_options,
+            offline_buffer: Mutex::new(offline_buffer),
+            extra_subscriptions: Mutex::new(BTreeSet::new()),
+            robot_encodings: Mutex::new(HashMap::new()),
+            config,
+            fleet: Arc::new(fleet),
+            message_tx,
+            coalesced: Mutex::new(CoalescingSlots::default()),
+            outgoing_seq: Mutex::default(),
+            sequences: Arc::new(RwLock::new(SequenceTracker::new(sequence))),
+            payload_guard,
+            decisions: Arc::new(RwLock::new(DecisionLog::default())),
+            sections: Arc::new(RwLock::new(SectionRegistry::with_sections(
+                pipeline.unknown_sections,
+                map.sections().iter().map(SectionInfo::from),
+            ))),
+            pipeline: map,
+            alarms: Arc::new(RwLock::new(EnvironmentAlarms::new(alarms))),
+            triage: Arc::new(RwLock::new(TriageCoordinator::new(triage))),
+            dispatcher: Arc::new(RwLock::new(AutoDispatcher::new(dispatch))),
+            authorizer: CommandAuthorizer::new(authorization),
+            correlator: Arc::new(RwLock::new(AlertCorrelator::new(correlation))),
+            trends: Arc::new(RwLock::new(TrendDetector::new(trends))),
+            battery_manager: Arc::new(RwLock::new(BatteryManager::new(battery_manager))),
+            wall_trends: Arc::new(RwLock::new(WallThicknessTrends::new(wall_thickness))),
+            detectors: Mutex::new(detectors),
+            section_health: Arc::new(RwLock::new(SectionHealth::new(
+                section_health,
+                hazard_thresholds,
+            ))),
+            sources: Arc::new(RwLock::new(SourceGuard::new(source_bindings))),
+            signer: SourceSigner::new(&source_signing),
+            rollouts: Arc::new(RwLock::new(RolloutController::new(rollout))),
+            anomalies: Arc::new(RwLock::new(anomalies)),
+            anomaly_store: Mutex::new(anomaly_store),
+            telemetry_store: Mutex::new(telemetry_store.open()),
+            history_retention: telemetry_store.retention,
+            escalation,
+            alert_dedup: Arc::new(RwLock::new(AlertDedup::new(alert_dedup))),
+            bounds: Mutex::new(BoundsGuard::new(world_bounds)),
+            dead_letters: Mutex::new(DeadLetterQueue::new(dead_letters)),
+            events: Arc::new(RwLock::new(EventLog::default())),
+            history: Arc::new(RwLock::new(RobotHistory::new(timeline.samples_per_robot))),
+            timeline,
+            zones: Arc::new(RwLock::new(ZoneRegistry::new(zones))),
+            geofences: Geofences::new(geofences),
+            capabilities: Arc::new(RwLock::new(CapabilityRegistry::default())),
+            robot_configs: RwLock::new(RobotConfigRegistry::load(&robot_configs)?),
+            fanout,
+            command_queue: Mutex::new(CommandQueue::new(&command_queue)),
+            commands_queued: Notify::new(),
+            store_forward: Arc::new(RwLock::new(StoreAndForward::new(store_forward))),
+            sensor_health: Arc::new(RwLock::new(SensorHealth::new(sensor_health))),
+            position_filter: Arc::new(RwLock::new(PositionFilter::new(position_filter))),
+            delta_encoder: Mutex::new(DeltaEncoder::new(&delta)),
+            keyframes: Mutex::new(KeyframeRequests::new(&delta)),
+            expected_fleet: Arc::new(RwLock::new(expected_fleet)),
+            acks: Arc::new(RwLock::new(CommandAcks::new(acks))),
+            broadcasts: Arc::new(RwLock::new(BroadcastTracker::new(broadcast))),
+            command_expiry,
+            routes: Mutex::default(),
+            route_requests: Mutex::new(RouteRequestLimiter::new(route_requests)),
+            instance_id,
+            election: election.map(Mutex::new),
+            leadership,
+            connection: watch::Sender::new(ConnectionState::Disconnected),
+            error_counts: Mutex::default(),
+            metrics,
+            clock,
+            started_at,
+            received: std::sync::atomic::AtomicU64::new(0),
+            last_status: Mutex::new((0, started_at)),
+        };
+
+        Ok((mqtt, eventloop))
+    }
+
+    /// Subscribe to all relevant AETHERIS topics
+    pub async fn subscribe_all(&self) -> Result<()> {
+        info!("Subscribing to AETHERIS MQTT topics...");
+
+        // Subscribe to telemetry from all robots
+        self.client
+            .subscribe(topics::TELEMETRY_ALL, QoS::AtLeastOnce)
+            .await
+            .transport("subscribe to telemetry")?;
+
+        // Subscribe to batched telemetry
+        self.client
+            .subscribe(topics::TELEMETRY_BATCH, QoS::AtLeastOnce)
+            .await
+            .transport("subscribe to telemetry batches")?;
+
+        // Subscribe to heartbeats
+        self.client
+            .subscribe(topics::HEARTBEAT_ALL, QoS::AtLeastOnce)
+            .await
+            .transport("subscribe to heartbeats")?;
+
+        // Subscribe to alerts, all of them or only the severe ones
+        for topic in self.config.alert_topics.subscriptions() {
+            self.client
+                .subscribe(topic, QoS::AtLeastOnce)
+                .await
+                .transport("subscribe to alerts")?;
+        }
+
+        // Subscribe to environment readings
+        self.client
+            .subscribe(topics::ENVIRONMENT_ALL, QoS::AtLeastOnce)
+            .await
+            .transport("subscribe to environment")?;
+
+        // Subscribe to command responses (for dashboard)
+        self.client
+            .subscribe("aetheris/responses/+", QoS::AtLeastOnce)
+            .await
+            .transport("subscribe to responses")?;
+
+        // Subscribe to triage results from the Brain
+        self.client
+            .subscribe(topics::triage_results(), QoS::AtLeastOnce)
+            .await
+            .transport("subscribe to triage results")?;
+
+        // Subscribe to commands (to handle chaos scenarios)
+        self.client
+            .subscribe(topics::COMMANDS_ALL, QoS::AtLeastOnce)
+            .await
+            .transport("subscribe to commands")?;
+
+        // Subscribe to requests to republish route definitions
+        self.client
+            .subscribe(topics::ROUTE_REQUESTS, QoS::AtLeastOnce)
+            .await
+            .transport("subscribe to route requests")?;
+
+        // Subscribe to the other instances' leader claims
+        if self.election.is_some() {
+            self.client
+                .subscribe(topics::SYSTEM_LEADER, QoS::AtLeastOnce)
+                .await
+                .transport("subscribe to leader claims")?;
+        }
+
+        // Restore the topics subscribed one at a time
+        let extra = self
+            .extra_subscriptions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        for topic in extra {
+            self.client
+                .subscribe(topic, QoS::AtLeastOnce)
+                .await
+                .transport("resubscribe")?;
+        }
+
+        info!("Successfully subscribed to all AETHERIS topics");
+        Ok(())
+    }
+
+    /// Subscribe to a single topic, for clients that need less than
+    /// [`Self::subscribe_all`], or more. The subscription is restored with
+    /// the others after a reconnect that lost the session.
+    pub async fn subscribe(&self, topic: &str) -> Result<()> {
+        self.client
+            .subscribe(topic, QoS::AtLeastOnce)
+            .await
+            .transport("subscribe")?;
+        self.extra_subscriptions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(topic.to_string());
+        Ok(())
+    }
+
+    /// Send a command to a specific robot, unless a zone mode forbids it or
+    /// the robot is not built for it. Commands to a weak-link robot are held until its link is back.
+    /// Queued behind more urgent commands, see [`Self::queue_command`].
+    ///
+    /// Returns the id the robot will answer under, to follow the command
+    /// through [`EngineMessage::CommandStatusChanged`]; for a command
+    /// already queued, the id of the queued one.
+    pub async fn send_command(&self, robot_id: &str, command: Command) -> Result<String> {
+        let command_id = acks::new_command_id();
+        match self.enqueue(robot_id, &command_id, command, None).await? {
+            Enqueued::Duplicate(queued) => Ok(queued),
+            _ => Ok(command_id),
+        }
+    }
+
+    /// Send a command at `priority`, by default the one of its variant.
+    /// Emergency commands are dispatched at once; the others wait for the
+    /// command dispatcher, which publishes the most urgent ones first.
+    pub async fn queue_command(
+        &self,
+        robot_id: &str,
+        command: Command,
+        priority: Option<CommandPriority>,
+    ) -> Result<Enqueued> {
+        self.enqueue(robot_id, &acks::new_command_id(), command, priority)
+            .await
+    }
+
+    async fn enqueue(
+        &self,
+        robot_id: &str,
+        command_id: &str,
+        command: Command,
+        priority: Option<CommandPriority>,
+    ) -> Result<Enqueued> {
+        // Refused before it takes a place in the queue
+        let id = RobotId::parse(robot_id).map_err(PublishError::from)?;
+        if let Some(robot) = self.fleet.get_robot(&id) {
+            self.check_capabilities(&robot, command_id, &command)
+                .await?;
+        }
+        let priority = priority.unwrap_or_else(|| CommandPriority::of(&command));
+        if priority == CommandPriority::Emergency {
+            self.dispatch(robot_id, command_id, command).await?;
+            return Ok(Enqueued::Dispatched);
+        }
+
+        let variant = command.name();
+        let (enqueued, depth) = {
+            let mut queue = self.command_queue.lock().unwrap_or_else(|e| e.into_inner());
+            let enqueued = queue.push(robot_id, command_id, command, priority);
+            (enqueued, queue.len())
+        };
+        self.metrics.set_command_queue_depth(depth);
+        match enqueued? {
+            Enqueued::Duplicate(queued) => {
+                debug!(robot_id = %robot_id, command = variant, command_id = %queued, "Identical command already queued");
+                Ok(Enqueued::Duplicate(queued))
+            }
+            enqueued => {
+                if let Enqueued::Evicted(oldest) = &enqueued {
+                    warn!(
+                        robot_id = %oldest.robot_id,
+                        command = oldest.command.name(),
+                        "Command queue full, oldest low-priority command dropped"
+                    );
+                }
+                self.commands_queued.notify_one();
+                Ok(enqueued)
+            }
+        }
+    }
+
+    /// Dispatch queued commands until the queue is empty, returning how
+    /// many were taken from it. Commands queued meanwhile are included, in
+    /// priority order.
+    pub async fn drain_command_queue(&self) -> usize {
+        let mut drained = 0;
+        loop {
+            let (next, depth) = {
+                let mut queue = self.command_queue.lock().unwrap_or_else(|e| e.into_inner());
+                (queue.pop(), queue.len())
+            };
+            let Some(queued) = next else {
+                return drained;
+            };
+            self.metrics.set_command_queue_depth(depth);
+            drained += 1;
+            if let Err(e) = self
+                .dispatch(&queued.robot_id, &queued.command_id, queued.command)
+                .await
+            {
+                warn!(robot_id = %queued.robot_id, priority = %queued.priority, "Queued command not sent: {}", e);
+            }
+        }
+    }
+
+    /// Commands waiting for the command dispatcher
+    pub fn command_queue_depth(&self) -> usize {
+        self.command_queue
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .len()
+    }
+
+    /// Send a command and wait for the robot's response to it. Ends with an
+    /// error when the command is refused, dropped while held, or not
+    /// answered within the acknowledgment timeout after it was published.
+    pub async fn send_command_and_wait(
+        &self,
+        robot_id: &str,
+        command: Command,
+    ) -> Result<CommandResponse, AckError> {
+        let command_id = acks::new_command_id();
+        let response = self.acks.write().await.wait_for(&command_id);
+        if let Err(e) = self.dispatch(robot_id, &command_id, command).await {
+            self.acks.write().await.abandon(&command_id, e.into());
+        }
+        response.await.unwrap_or_else(|_| {
+            Err(AckError::Dropped {
+                command_id,
+                robot_id: robot_id.to_string(),
+            })
+        })
+    }
+
+    /// Check a command against zone modes, geofences and the robot's
+    /// capabilities, then publish it or hold it for a weak link
+    async fn dispatch(
+        &self,
+        robot_id: &str,
+        command_id: &str,
+        mut command: Command,
+    ) -> Result<Delivery, PublishError> {
+        let robot_id = &RobotId::parse(robot_id)?;
+        let robot = self.fleet.get_robot(robot_id);
+        if let Some(robot) = &robot
+            && let Err(violation) = self.zones.read().await.check_command(&command, robot)
+        {
+            warn!(robot_id = %robot_id, command = command.name(), "Command rejected: {}", violation);
+            self.events.write().await.record(
+                SystemEvent::new(
+                    SystemEventKind::CommandRejected,
+                    Some(robot_id),
+                    format!("{}: {}", command.name(), violation),
+                    self.now_ms(),
+                )
+                .for_command(command_id),
+            );
+            return Err(violation.into());
+        }
+        if let Some(robot) = &robot {
+            self.enforce_geofences(robot, command_id, &mut command)
+                .await?;
+        }
+        if let Some(robot) = &robot {
+            // Checked again: a Configure may have landed while it was queued
+            self.check_capabilities(robot, command_id, &command).await?;
+        }
+        let now = self.now_ms();
+        let offer = self
+            .store_forward
+            .write()
+            .await
+            .offer(robot_id, command_id, command, None, now)
+            .map_err(|e| PublishError::Failed(e.to_string()))?;
+        match offer {
+            Offer::Held => {
+                info!(robot_id = %robot_id, command_id = %command_id, "Weak link, command held");
+                Ok(Delivery::Queued)
+            }
+            Offer::Publish { command, discarded } => {
+                for pending in &discarded {
+                    self.record_dropped(robot_id, pending, "discarded by emergency stop", now)
+                        .await;
+                }
+                self.publish_now(robot_id, command_id, command, false)
+                    .await?;
+                Ok(Delivery::Published)
+            }
+        }
+    }
+
+    /// Refuse a command `robot` cannot carry out, answering it with a
+    /// failed response as the robot would
+    async fn check_capabilities(
+        &self,
+        robot: &RobotState,
+        command_id: &str,
+        command: &Command,
+    ) -> Result<(), PublishError> {
+        let robot_id = &robot.id;
+        let Err(unsupported) = self
+            .capabilities
+            .read()
+            .await
+            .of(robot_id, robot.robot_type)
+            .check(command)
+        else {
+            return Ok(());
+        };
+        warn!(robot_id = %robot_id, command = command.name(), "Command rejected: {}", unsupported);
+        let now = self.now_ms();
+        self.events.write().await.record(
+            SystemEvent::new(
+                SystemEventKind::CommandRejected,
+                Some(robot_id),
+                format!("{}: {}", command.name(), unsupported),
+                now,
+            )
+            .for_command(command_id),
+        );
+        // Answered as the robot would, for senders watching responses
+        let response = CommandResponse {
+            command_id: command_id.to_string(),
+            robot_id: robot_id.clone(),
+            success: false,
+            error: Some(unsupported.to_string()),
+            config: None,
+            status: None,
+            timestamp: now,
+        };
+        if let Err(e) = self.publish_response(&response).await {
+            warn!(robot_id = %robot_id, "Failed to answer unsupported command: {}", e);
+        }
+        Err(unsupported.into())
+    }
+
+    /// Hold a move to the geofences: refuse it, or cut it short before the
+    /// fence, and alert either way
+    async fn enforce_geofences(
+        &self,
+        robot: &RobotState,
+        command_id: &str,
+        command: &mut Command,
+    ) -> Result<(), PublishError> {
+        let (kind, detail, requested, result) = match self.geofences.check_command(command, robot) {
+            Enforcement::Allowed => return Ok(()),
+            Enforcement::Clamped {
+                fence_id,
+                requested,
+                target,
+            } => {
+                if let Command::MoveTo { target: sent, .. } = command {
+                    *sent = target;
+                }
+                let detail = format!(
+                    "move_to ({:.1}, {:.1}, {:.1}) stopped at ({:.1}, {:.1}, {:.1}), short of geofence {}",
+                    requested.x, requested.y, requested.z, target.x, target.y, target.z, fence_id
+                );
+                (SystemEventKind::CommandClamped, detail, requested, Ok(()))
+            }
+            Enforcement::Rejected(violation) => (
+                SystemEventKind::CommandRejected,
+                format!("{}: {}", command.name(), violation),
+                violation.target,
+                Err(violation.into()),
+            ),
+        };
+        warn!(robot_id = %robot.id, command_id = %command_id, "Geofence violation: {}", detail);
+        let now = self.clock.now();
+        self.events.write().await.record(
+            SystemEvent::new(kind, Some(&robot.id), detail.clone(), now.as_millis())
+                .for_command(command_id),
+        );
+        let report = AnomalyReport {
+            timestamp: now,
+            ..AnomalyReport::new(
+                AnomalyType::Unknown,
+                if result.is_ok() {
+                    SeverityLevel::Low
+                } else {
+                    SeverityLevel::Medium
+                },
+                requested,
+                SYSTEM_SECTION,
+                ENGINE_ORIGIN,
+                1.0,
+                format!("Geofence violation by {}: {}", robot.id, detail),
+            )
+        };
+        if let Err(e) = self.publish_alert(&report).await {
+            warn!(robot_id = %robot.id, "Failed to publish geofence alert: {}", e);
+        }
+        result
+    }
+
+    /// Publish a command on the robot's topic without any checks and start
+    /// waiting for its acknowledgment
+    async fn publish_now(
+        &self,
+        robot_id: &RobotId,
+        command_id: &str,
+        command: Command,
+        retain: bool,
+    ) -> Result<(), PublishError> {
+        let topic = topics::commands(robot_id);
+        let seq = self.next_sequence(&self.instance_id, MessageClass::Command);
+        let variant = command.name();
+        let expires_at = self.command_expiry.expires_at(&command, self.now_ms());
+        let mut msg = MqttMessage::new(command, &self.instance_id, seq).with_command_id(command_id);
+        msg.expires_at = expires_at;
+        let msg = self.authorizer.sign_own(msg);
+        let payload = self
+            .command_encoding(robot_id)
+            .encode(&msg)
+            .map_err(|e| PublishError::Failed(e.to_string()))?;
+
+        self.publish_payload(&topic, QoS::AtLeastOnce, retain, payload)
+            .await
+            .map_err(|e| PublishError::Failed(e.to_string()))?;
+        self.acks
+            .write()
+            .await
+            .sent(command_id, robot_id, variant, self.now_ms());
+
+        self.metrics.record_command_sent();
+        info!(robot_id = %robot_id, command_id = %command_id, retain, "Command sent");
+        Ok(())
+    }
+
+    /// Get the commands waiting for a response
+    pub fn acks(&self) -> Arc<RwLock<CommandAcks>> {
+        self.acks.clone()
+    }
+
+    /// Give up on sent commands that were never answered
+    pub async fn expire_command_acks(&self) {
+        let now = self.now_ms();
+        let expired = self.acks.write().await.expire(now);
+        for awaiting in expired {
+            warn!(
+                robot_id = %awaiting.robot_id,
+                command_id = %awaiting.command_id,
+                command = awaiting.variant,
+                "Command was never acknowledged"
+            );
+            self.events.write().await.record(
+                SystemEvent::new(
+                    SystemEventKind::CommandUnacknowledged,
+                    Some(&awaiting.robot_id),
+                    format!(
+                        "{} sent at {} got no response",
+                        awaiting.variant, awaiting.sent_at
+                    ),
+                    now,
+                )
+                .for_command(&awaiting.command_id),
+            );
+            self.command_status_changed(CommandUpdate::timed_out(&awaiting, now))
+                .await;
+        }
+    }
+
+    async fn command_status_changed(&self, update: CommandUpdate) {
+        if let Err(e) = self
+            .notify(EngineMessage::CommandStatusChanged(update))
+            .await
+        {
+            debug!("Command status not delivered: {}", e);
+        }
+    }
+
+    /// Commands held for a weak-link robot, oldest first
+    pub async fn pending_commands(&self, robot_id: &str) -> Vec<PendingCommand> {
+        self.store_forward.read().await.pending(robot_id)
+    }
+
+    /// Get the store-and-forward queues and link states
+    pub fn store_forward(&self) -> Arc<RwLock<StoreAndForward>> {
+        self.store_forward.clone()
+    }
+
+    /// Note that a robot was heard from and deliver whatever its returning
+    /// link releases
+    async fn observe_link(&self, robot_id: &RobotId, signal: f64) -> Result<()> {
+        let now = self.now_ms();
+        self.note_arrival(robot_id, now).await;
+        let release = self
+            .store_forward
+            .write()
+            .await
+            .observe_link(robot_id, signal, now);
+        self.deliver_release(robot_id, release, now).await
+    }
+
+    /// Clear a declared robot's missing alert, or report an undeclared one
+    async fn note_arrival(&self, robot_id: &str, now: u64) {
+        let arrival = self.expected_fleet.write().await.observe(robot_id, now);
+        let event = match arrival {
+            Arrival::Expected {
+                cleared: Some(anomaly_id),
+            } => {
+                info!(robot_id = %robot_id, anomaly_id = %anomaly_id, "Missing robot arrived");
+                // Already gone if an operator resolved it
+                {
+                    let mut anomalies = self.anomalies.write().await;
+                    let _ =
+                        anomalies.resolve(&anomaly_id, Resolution::Fixed, true, ENGINE_ORIGIN, now);
+                    self.persist_anomalies(&mut anomalies);
+                }
+                SystemEvent::new(
+                    SystemEventKind::ExpectedRobotArrived,
+                    Some(robot_id),
+                    format!("arrived, missing alert {} cleared", anomaly_id),
+                    now,
+                )
+            }
+            Arrival::Unexpected { first: true } => {
+                info!(robot_id = %robot_id, "Robot is not in the expected fleet");
+                SystemEvent::new(
+                    SystemEventKind::UnexpectedRobot,
+                    Some(robot_id),
+                    "heard from but not declared in the expected fleet",
+                    now,
+                )
+            }
+            _ => return,
+        };
+        self.events.write().await.record(event);
+    }
+
+    async fn deliver_release(&self, robot_id: &RobotId, release: Release, now: u64) -> Result<()> {
+        let Release {
+            commands,
+            expired,
+            clear_retained,
+        } = release;
+        if clear_retained {
+            // An empty retained payload clears the broker's slot
+            self.publish_payload(topics::commands(robot_id), QoS::AtLeastOnce, true, "")
+                .await
+                .transport("clear retained command")?;
+        }
+        for pending in expired {
+            self.record_dropped(robot_id, &pending, "expired", now)
+                .await;
+        }
+        if !commands.is_empty() {
+            info!(robot_id = %robot_id, count = commands.len(), "Link back, releasing held commands");
+        }
+        for pending in commands {
+            self.publish_now(robot_id, &pending.command_id, pending.command, false)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn record_dropped(&self, robot_id: &str, pending: &PendingCommand, why: &str, now: u64) {
+        warn!(robot_id = %robot_id, command = pending.command.name(), "Held command {}", why);
+        self.acks.write().await.abandon(
+            &pending.command_id,
+            AckError::Dropped {
+                command_id: pending.command_id.clone(),
+                robot_id: robot_id.to_string(),
+            },
+        );
+        self.events.write().await.record(
+            SystemEvent::new(
+                SystemEventKind::CommandDropped,
+                Some(robot_id),
+                format!(
+                    "{} held since {} {}",
+                    pending.command.name(),
+                    pending.queued_at,
+                    why
+                ),
+                now,
+            )
+            .for_command(&pending.command_id),
+        );
+    }
+
+    /// Expire stale held commands and fall back to retained publishing for
+    /// robots that stay away
+    pub async fn drive_store_forward(&self) -> Result<()> {
+        let now = self.now_ms();
+        let (expired, retained) = {
+            let mut store_forward = self.store_forward.write().await;
+            (
+                store_forward.prune(now),
+                store_forward.take_retain_fallbacks(now),
+            )
+        };
+        for (robot_id, pending) in expired {
+            self.record_dropped(&robot_id, &pending, "expired", now)
+                .await;
+        }
+        for (robot_id, pending) in retained {
+            warn!(robot_id = %robot_id, command = pending.command.name(), "Robot still unreachable, publishing command retained");
+            self.publish_now(&robot_id, &pending.command_id, pending.command, true)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Send per-robot commands with bounded concurrency, reporting each
+    /// command's outcome instead of stopping at the first failure
+    pub async fn send_commands(&self, batch: Vec<(String, Command)>) -> FanoutReport {
+        let report = fanout::fan_out(self, batch, &self.fanout).await;
+        if !report.all_accepted() {
+            warn!(
+                published = report.published().len(),
+                queued = report.queued().len(),
+                rejected = report.rejected().len(),
+                rate_limited = report.rate_limited().len(),
+                failed = report.failed().len(),
+                "Command batch partially sent"
+            );
+        }
+        report
+    }
+
+    /// Broadcast a command to all robots and track how the ones online
+    /// answer it. A command some known robots cannot carry out is sent to
+    /// each of the others instead.
+    pub async fn broadcast_command(&self, command: Command) -> Result<()> {
+        self.start_broadcast(command).await?;
+        Ok(())
+    }
+
+    /// Broadcast a command and wait until every robot online when it was
+    /// sent has answered it, or the broadcast deadline passes
+    pub async fn broadcast_and_collect(&self, command: Command) -> Result<BroadcastResult> {
+        let (command_id, mut settled) = self.start_broadcast(command).await?;
+        let deadline = self.broadcasts.read().await.config().deadline;
+        if let Ok(Ok(result)) = tokio::time::timeout(deadline, &mut settled).await {
+            return Ok(result);
+        }
+        match self.finish_broadcast(&command_id).await? {
+            Some(result) => Ok(result),
+            // Settled by the deadline check in the meantime
+            None => settled
+                .await
+                .map_err(|_| AetherisError::ChannelClosed.into()),
+        }
+    }
+
+    async fn start_broadcast(
+        &self,
+        command: Command,
+    ) -> Result<(String, tokio::sync::oneshot::Receiver<BroadcastResult>)> {
+        let robots: Vec<(String, RobotType, bool)> = self
+            .fleet
+            .get_all_robots()
+            .into_iter()
+            .map(|robot| {
+                let online = robot.status != RobotStatus::Offline;
+                (robot.id.to_string(), robot.robot_type, online)
+            })
+            .collect();
+        let (capable, incapable): (Vec<_>, Vec<_>) = {
+            let capabilities = self.capabilities.read().await;
+            robots.into_iter().partition(|(robot_id, robot_type, _)| {
+                capabilities
+                    .of(robot_id, *robot_type)
+                    .check(&command)
+                    .is_ok()
+            })
+        };
+        let command_id = acks::new_command_id();
+        let variant = command.name();
+        let narrowed = !incapable.is_empty();
+        // Robots that come online later are not waited for
+        let expected = capable
+            .iter()
+            .filter(|(_, _, online)| *online)
+            .map(|(robot_id, _, _)| {
+                let answers_with = if narrowed {
+                    broadcast::share_id(&command_id, robot_id)
+                } else {
+                    command_id.clone()
+                };
+                (robot_id.clone(), answers_with)
+            })
+            .collect();
+        let settled =
+            self.broadcasts
+                .write()
+                .await
+                .start(&command_id, variant, expected, self.now_ms());
+
+        if narrowed {
+            info!(
+                command = variant,
+                robots = capable.len(),
+                skipped = incapable.len(),
+                "Broadcast narrowed to the robots that support it"
+            );
+            let batch = capable
+                .into_iter()
+                .map(|(robot_id, _, _)| (robot_id, command.clone()))
+                .collect();
+            let share = BroadcastShare {
+                mqtt: self,
+                command_id: &command_id,
+            };
+            let report = fanout::fan_out(&share, batch, &self.fanout).await;
+            let mut broadcasts = self.broadcasts.write().await;
+            for (robot_id, outcome) in &report.outcomes {
+                if !matches!(outcome, CommandOutcome::Published | CommandOutcome::Queued) {
+                    broadcasts.fail(&command_id, robot_id);
+                }
+            }
+        } else {
+            let seq = self.next_sequence(&self.instance_id, MessageClass::Command);
+            let expires_at = self.command_expiry.expires_at(&command, self.now_ms());
+            let mut msg =
+                MqttMessage::new(command, &self.instance_id, seq).with_command_id(&command_id);
+            msg.expires_at = expires_at;
+            let msg = self.authorizer.sign_own(msg);
+            let payload = self.encode(&msg)?;
+
+            if let Err(e) = self
+                .publish_payload(topics::COMMANDS_BROADCAST, QoS::AtLeastOnce, false, payload)
+                .await
+                .transport("broadcast command")
+            {
+                self.broadcasts
+                    .write()
+                    .await
+                    .finish(&command_id, self.now_ms());
+                return Err(e);
+            }
+
+            self.metrics.record_command_sent();
+            info!(command_id = %command_id, "Command broadcast to all robots");
+        }
+        // Nobody online to answer
+        self.finish_due_broadcasts().await?;
+        Ok((command_id, settled))
+    }
+
+    /// Get the broadcasts waiting for their robots
+    pub fn broadcasts(&self) -> Arc<RwLock<BroadcastTracker>> {
+        self.broadcasts.clone()
+    }
+
+    /// Settle the broadcasts past their deadline or with every robot
+    /// answered or offline, and report them
+    pub async fn finish_due_broadcasts(&self) -> Result<()> {
+        let offline = self.offline_robots().await;
+        let settled = self
+            .broadcasts
+            .write()
+            .await
+            .finish_due(&offline, self.now_ms());
+        for result in settled {
+            self.report_broadcast(result).await?;
+        }
+        Ok(())
+    }
+
+    /// Settle a broadcast now and report it. `None` if it already was.
+    async fn finish_broadcast(&self, command_id: &str) -> Result<Option<BroadcastResult>> {
+        let result = self
+            .broadcasts
+            .write()
+            .await
+            .finish(command_id, self.now_ms());
+        if let Some(result) = &result {
+            self.report_broadcast(result.clone()).await?;
+        }
+        Ok(result)
+    }
+
+    async fn offline_robots(&self) -> HashSet<String> {
+        self.fleet
+            .get_all_robots()
+            .into_iter()
+            .filter(|robot| robot.status == RobotStatus::Offline)
+            .map(|robot| robot.id.to_string())
+            .collect()
+    }
+
+    /// Publish a settled broadcast and hand it to the engine
+    async fn report_broadcast(&self, result: BroadcastResult) -> Result<()> {
+        if result.is_complete() {
+            info!(command_id = %result.command_id, "Broadcast {}", result.summary());
+        } else {
+            warn!(
+                command_id = %result.command_id,
+                failed = ?result.failed,
+                non_responding = ?result.non_responding,
+                "Broadcast {}",
+                result.summary()
+            );
+        }
+        let payload = self.encode(&result)?;
+        self.publish_payload(topics::BROADCAST_RESULTS, QoS::AtLeastOnce, false, payload)
+            .await
+            .transport("publish broadcast result")?;
+        self.notify(EngineMessage::BroadcastCompleted(result)).await
+    }
+
+    /// Publish robot telemetry (used by simulated robots), as a keyframe or
+    /// delta when delta telemetry is on. Only full states are retained.
+    pub async fn publish_telemetry(&self, state: &RobotState) -> Result<()> {
+        let topic = topics::telemetry(&state.id);
+        let seq = self.next_sequence(&state.id, MessageClass::Telemetry);
+        let encoded = self
+            .delta_encoder
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .encode(state);
+        let mut retain = self.config.retain.telemetry;
+        let payload = match encoded {
+            None => {
+                self.encode_envelope(MqttMessage::new(state.clone(), state.id.as_str(), seq))?
+            }
+            Some(telemetry) => {
+                retain &= matches!(telemetry, TelemetryPayload::Full(_));
+                self.encode_envelope(MqttMessage::new(telemetry, state.id.as_str(), seq))?
+            }
+        };
+
+        self.publish_payload(&topic, QoS::AtLeastOnce, retain, payload)
+            .await
+            .transport("publish telemetry")?;
+
+        debug!(robot_id = %state.id, "Telemetry published");
+        Ok(())
+    }
+
+    /// Publish the telemetry of many robots as one message (used by the
+    /// simulated fleet). Never retained: late subscribers pick the robots
+    /// up from the next batch.
+    pub async fn publish_telemetry_batch(&self, batch: &TelemetryBatch) -> Result<()> {
+        let seq = self.next_sequence(&batch.source, MessageClass::TelemetryBatch);
+        let payload = self.encode_envelope(MqttMessage::new(batch.clone(), &batch.source, seq))?;
+
+        self.publish_payload(topics::TELEMETRY_BATCH, QoS::AtLeastOnce, false, payload)
+            .await
+            .transport("publish telemetry batch")?;
+
+        debug!(robots = batch.states.len(), "Telemetry batch published");
+        Ok(())
+    }
+
+    /// Publish an engine-filtered position estimate. Sent at most once; a
+    /// lost estimate is superseded by the next.
+    pub async fn publish_filtered_telemetry(&self, filtered: &FilteredTelemetry) -> Result<()> {
+        let topic = topics::telemetry_filtered(&filtered.robot_id);
+        let seq = self.next_sequence(&self.instance_id, MessageClass::FilteredTelemetry);
+        let payload =
+            self.encode_envelope(MqttMessage::new(filtered.clone(), &self.instance_id, seq))?;
+
+        self.publish_payload(&topic, QoS::AtMostOnce, false, payload)
+            .await
+            .transport("publish filtered telemetry")?;
+        Ok(())
+    }
+
+    /// Serialize a payload in the configured encoding
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, EncodingError> {
+        self.config.encoding.encode(value)
+    }
+
+    /// Remember the format a robot publishes in, to answer it in kind
+    fn note_encoding(&self, robot_id: &str, payload: &[u8]) {
+        if self.config.negotiate_encoding {
+            self.robot_encodings
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(robot_id.to_string(), Encoding::detect(payload));
+        }
+    }
+
+    /// Format commands to a robot go out in: the one it last published in,
+    /// or the configured one until it has been heard from
+    pub fn command_encoding(&self, robot_id: &str) -> Encoding {
+        self.robot_encodings
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(robot_id)
+            .copied()
+            .unwrap_or(self.config.encoding)
+    }
+
+    /// Stamp an envelope with the simulated time, sign it with its
+    /// source's key, if it has one, and serialize it in the configured
+    /// encoding
+    fn encode_envelope<T: Serialize>(
+        &self,
+        mut msg: MqttMessage<T>,
+    ) -> Result<Vec<u8>, EncodingError> {
+        msg.timestamp = self.clock.now();
+        self.signer.sign(&mut msg);
+        self.encode(&msg)
+    }
+
+    /// Hand a payload to the client, counted and timed per topic class.
+    /// Once the connection dropped, or while disconnected the client has no
+    /// room left, payloads are held in the offline buffer until it is
+    /// flushed.
+    async fn publish_payload(
+        &self,
+        topic: impl Into<String>,
+        qos: QoS,
+        retain: bool,
+        payload: impl Into<Vec<u8>>,
+    ) -> Result<(), ClientError> {
+        let topic = topic.into();
+        let payload = payload.into();
+        {
+            let mut buffer = self.lock_offline_buffer();
+            if buffer.is_holding() {
+                return self.hold(
+                    &mut buffer,
+                    HeldPublish {
+                        topic,
+                        qos,
+                        retain,
+                        payload,
+                    },
+                );
+            }
+        }
+        let class = TopicClass::of(&topic);
+        let start = Instant::now();
+        let result = if self.connection_state() == ConnectionState::Connected {
+            self.client.publish(topic, qos, retain, payload).await
+        } else {
+            match self.client.try_publish(topic, qos, retain, payload) {
+                Err(ClientError::TryRequest(Request::Publish(publish))) => {
+                    return self.hold(&mut self.lock_offline_buffer(), publish.into());
+                }
+                result => result,
+            }
+        };
+        self.metrics
+            .record_publish(class, start.elapsed(), result.is_ok());
+        result
+    }
+
+    fn lock_offline_buffer(&self) -> std::sync::MutexGuard<'_, OfflineBuffer> {
+        self.offline_buffer
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Hold a publish until a broker is reachable. Only a refused alert is
+    /// an error: the other drops are by design, and counted.
+    fn hold(&self, buffer: &mut OfflineBuffer, publish: HeldPublish) -> Result<(), ClientError> {
+        let class = HoldClass::of(&publish.topic);
+        let result = match buffer.push(publish) {
+            Hold::Held | Hold::Coalesced => Ok(()),
+            Hold::DroppedOldest(dropped) => {
+                self.metrics.record_offline_drop(class);
+                debug!(topic = %dropped.topic, "Offline buffer full, dropped the oldest publish");
+                Ok(())
+            }
+            Hold::Discarded => {
+                self.metrics.record_offline_drop(class);
+                Ok(())
+            }
+            Hold::Refused(refused) => {
+                self.metrics.record_offline_drop(class);
+                warn!(topic = %refused.topic, "Offline buffer full of alerts, alert refused");
+                let mut publish = Publish::new(refused.topic, refused.qos, refused.payload);
+                publish.retain = refused.retain;
+                Err(ClientError::TryRequest(Request::Publish(publish)))
+            }
+        };
+        self.metrics.set_offline_held(class, buffer.held(class));
+        result
+    }
+
+    /// Hand held publishes to the client, in priority order and as many as
+    /// it has room for; holding stops once they all went
+    fn flush_offline_buffer(&self) {
+        let mut buffer = self.lock_offline_buffer();
+        if !buffer.is_holding() {
+            return;
+        }
+        let held = buffer.len();
+        while let Some(publish) = buffer.pop() {
+            let class = TopicClass::of(&publish.topic);
+            let HeldPublish {
+                topic,
+                qos,
+                retain,
+                payload,
+            } = publish;
+            match self.client.try_publish(topic, qos, retain, payload) {
+                Ok(()) => self.metrics.record_publish(class, Duration::ZERO, true),
+                Err(e) => {
+                    if let ClientError::TryRequest(Request::Publish(publish)) = e {
+                        buffer.unpop(publish.into());
+                    }
+                    break;
+                }
+            }
+        }
+        debug!(
+            sent = held - buffer.len(),
+            held = buffer.len(),
+            "Offline buffer flushed"
+        );
+        for class in HoldClass::ALL {
+            self.metrics.set_offline_held(class, buffer.held(class));
+        }
+    }
+
+    /// Publish an already-built payload on `topic`, e.g. a replayed one
+    pub async fn publish_value<T: Serialize>(&self, topic: &str, value: &T) -> Result<()> {
+        let payload = self.encode(value)?;
+
+        self.publish_payload(topic, QoS::AtLeastOnce, false, payload)
+            .await
+            .transport("publish replayed message")?;
+        Ok(())
+    }
+
+    /// Publish a command response (used by simulated robots)
+    pub async fn publish_response(&self, response: &CommandResponse) -> Result<()> {
+        let topic = topics::responses(&response.robot_id);
+        let payload = self.encode(response)?;
+
+        self.publish_payload(&topic, QoS::AtLeastOnce, false, payload)
+            .await
+            .transport("publish command response")?;
+
+        debug!(robot_id = %response.robot_id, command_id = %response.command_id, success = response.success, "Command response published");
+        Ok(())
+    }
+
+    /// Robot counts with the open assignments of every responder and the
+    /// mode of every zone
+    pub async fn summary(&self) -> FleetSummary {
+        FleetSummary {
+            open_assignments: self.anomalies.read().await.open_assignments(),
+            zones: self.zones.read().await.status(),
+            ..self.fleet.summary()
+        }
+    }
+
+    /// Current engine status, online, with the fleet summary. The message
+    /// rate covers the time since the previous call.
+    pub async fn system_status(&self) -> SystemStatus {
+        let now = self.now_ms();
+        let fleet = self.fleet.fleet_summary();
+        let (unacknowledged_anomalies, open_assignments) = {
+            let anomalies = self.anomalies.read().await;
+            (
+                anomalies.unacknowledged_by_severity(),
+                anomalies.open_assignments(),
+            )
+        };
+        let received = self.received.load(std::sync::atomic::Ordering::Relaxed);
+        let (previous, since) = std::mem::replace(
+            &mut *self.last_status.lock().unwrap_or_else(|e| e.into_inner()),
+            (received, now),
+        );
+        let elapsed_secs = now.saturating_sub(since) as f64 / 1000.0;
+        let messages_per_sec = if elapsed_secs > 0.0 {
+            received.saturating_sub(previous) as f64 / elapsed_secs
+        } else {
+            0.0
+        };
+        let missed_messages = self.sequences.read().await.missed().clone();
+        let dropped_messages = LossyClass::ALL
+            .into_iter()
+            .map(|class| {
+                (
+                    class.as_str().to_string(),
+                    self.metrics.channel_dropped(class),
+                )
+            })
+            .filter(|(_, dropped)| *dropped > 0)
+            .collect();
+        let connected = fleet.values().map(|count| count.connected).sum();
+        SystemStatus {
+            uptime_secs: now.saturating_sub(self.started_at) / 1000,
+            fleet,
+            unacknowledged_anomalies,
+            open_assignments,
+            messages_per_sec,
+            missed_messages,
+            dropped_messages,
+            link_quality: self.fleet.link_qualities(),
+            simulated: self.clock.is_simulated(),
+            leadership: self
+                .election
+                .as_ref()
+                .map(|election| self.lock_election(election).status()),
+            ..SystemStatus::online(&self.config.client_id, connected, now)
+        }
+    }
+
+    /// Announce a graceful shutdown and disconnect. The broker discards the
+    /// Last Will on a clean disconnect, so the offline status is published
+    /// here; [`AetherisMqtt::run`] returns once the disconnect is sent.
+    pub async fn shutdown(&self) -> Result<()> {
+        let released = self
+            .election
+            .as_ref()
+            .and_then(|election| self.lock_election(election).release(self.now_ms()));
+        if let Some(claim) = released {
+            self.metrics.set_leader(false);
+            self.publish_leader_claim(&claim).await?;
+        }
+        let status = SystemStatus {
+            state: EngineState::Offline,
+            ..self.system_status().await
+        };
+        self.publish_system_status(&status).await?;
+        self.client.disconnect().await.transport("disconnect")
+    }
+
+    /// Publish the engine status, replacing the Last Will's offline status
+    /// after a (re)connect
+    pub async fn publish_system_status(&self, status: &SystemStatus) -> Result<()> {
+        let payload = self.encode(status)?;
+
+        self.publish_payload(
+            topics::SYSTEM_STATUS,
+            QoS::AtLeastOnce,
+            self.config.retain.system_status,
+            payload,
+        )
+        .await
+        .transport("publish system status")?;
+
+        debug!(state = ?status.state, connected_robots = status.connected_robots, "System status published");
+        Ok(())
+    }
+
+    /// Publish a heartbeat for a robot
+    pub async fn publish_heartbeat(&self, heartbeat: &Heartbeat) -> Result<()> {
+        let topic = topics::heartbeat(&heartbeat.robot_id);
+        let payload = self.encode(heartbeat)?;
+
+        self.publish_payload(&topic, QoS::AtLeastOnce, false, payload)
+            .await
+            .transport("publish heartbeat")?;
+
+        debug!(robot_id = %heartbeat.robot_id, "Heartbeat published");
+        Ok(())
+    }
+
+    /// Publish an anomaly alert
+    pub async fn publish_alert(&self, report: &AnomalyReport) -> Result<()> {
+        let report = match self.alert_dedup.write().await.admit(report.clone()) {
+            DedupVerdict::Publish(report) => report,
+            DedupVerdict::Escalated(report) => {
+                info!(anomaly_id = %report.id, severity = ?report.severity, occurrences = report.occurrence_count, "Repeated anomaly escalated");
+                report
+            }
+            DedupVerdict::Folded {
+                primary_id,
+                occurrence_count,
+            } => {
+                debug!(anomaly_id = %primary_id, occurrences = occurrence_count, "Repeated anomaly folded");
+                return Ok(());
+            }
+        };
+        let source = self.source_of(&report.detected_by);
+        let seq = self.next_sequence(source, MessageClass::Alert);
+        let payload = self.encode_envelope(MqttMessage::new(report.clone(), source, seq))?;
+        // Replaces the open report retained after a restart
+        let retain = report.severity >= SeverityLevel::High && report.status.is_closed();
+
+        if self.config.alert_topics.legacy {
+            self.publish_payload(topics::ALERTS, QoS::AtLeastOnce, retain, payload.clone())
+                .await
+                .transport("publish alert")?;
+        }
+        self.publish_payload(
+            topics::alerts_severity(report.severity),
+            QoS::AtLeastOnce,
+            retain,
+            payload,
+        )
+        .await
+        .transport("publish alert")?;
+        self.metrics.record_alert_published();
+
+        warn!(
+            anomaly_id = %report.id,
+            severity = ?report.severity,
+            "Anomaly alert published"
+        );
+        Ok(())
+    }
+
+    /// Publish a triage request for the Brain
+    pub async fn publish_triage_request(&self, request: &TriageRequest) -> Result<()> {
+        let seq = self.next_sequence(&self.instance_id, MessageClass::TriageRequest);
+        let payload =
+            self.encode_envelope(MqttMessage::new(request.clone(), &self.instance_id, seq))?;
+
+        self.publish_payload(topics::triage_requests(), QoS::AtLeastOnce, false, payload)
+            .await
+            .transport("publish triage request")?;
+
+        debug!(anomaly_id = %request.report.id, "Triage request published");
+        Ok(())
+    }
+
+    /// Publish environment sensor data
+    pub async fn publish_environment(&self, env: &PipeEnvironment) -> Result<()> {
+        let topic = topics::environment(&env.section_id);
+        let seq = self.next_sequence(&env.section_id, MessageClass::Environment);
+        let payload = self.encode_envelope(MqttMessage::new(env.clone(), &env.section_id, seq))?;
+
+        self.publish_payload(&topic, QoS::AtLeastOnce, false, payload)
+            .await
+            .transport("publish environment data")?;
+
+        debug!(section_id = %env.section_id, "Environment data published");
+        Ok(())
+    }
+
+    /// Publish a patrol route definition, retained so late subscribers get
+    /// the route geometry without waiting for a republish. The route joins
+    /// the library; see [`Self::publish_route_library`].
+    pub async fn publish_route(&self, route: &PatrolRoute) -> Result<()> {
+        self.lock_routes().upsert(route.clone());
+        let topic = topics::routes(&route.id);
+        let seq = self.next_sequence(&self.instance_id, MessageClass::Route);
+        let payload =
+            self.encode_envelope(MqttMessage::new(route.clone(), &self.instance_id, seq))?;
+
+        self.publish_payload(&topic, QoS::AtLeastOnce, true, payload)
+            .await
+            .transport("publish route")?;
+
+        debug!(route_id = %route.id, "Route published");
+        Ok(())
+    }
+
+    /// Publish every route published so far as one retained message
+    pub async fn publish_route_library(&self) -> Result<()> {
+        let library = self.routes();
+        let seq = self.next_sequence(&self.instance_id, MessageClass::Route);
+        let count = library.len();
+        let payload = self.encode_envelope(MqttMessage::new(library, &self.instance_id, seq))?;
+
+        self.publish_payload(topics::ROUTE_LIBRARY, QoS::AtLeastOnce, true, payload)
+            .await
+            .transport("publish route library")?;
+
+        debug!(routes = count, "Route library published");
+        Ok(())
+    }
+
+    /// Snapshot of the routes published so far
+    pub fn routes(&self) -> RouteLibrary {
+        self.lock_routes().clone()
+    }
+
+    /// Republish what a route request asks for: the named routes, or the
+    /// whole library when it names none. Only the leader answers, so a
+    /// request to several instances gets one reply, and only as often as
+    /// the [route request limit](crate::route_requests) allows.
+    async fn answer_route_request(&self, request: RouteRequest) -> Result<()> {
+        if !self.is_leader() {
+            return Ok(());
+        }
+        let requester = request.requester.as_deref().unwrap_or(ANONYMOUS_REQUESTER);
+        let admitted = self
+            .route_requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .admit(requester, self.now_ms());
+        if !admitted {
+            debug!(%requester, "Route request dropped by rate limit");
+            return Ok(());
+        }
+        if request.route_ids.is_empty() {
+            return self.publish_route_library().await;
+        }
+        for route_id in &request.route_ids {
+            let route = self.lock_routes().get(route_id).cloned();
+            match route {
+                Some(route) => self.publish_route(&route).await?,
+                None => warn!(%route_id, "Route requested but not known"),
+            }
+        }
+        Ok(())
+    }
+
+    fn lock_routes(&self) -> std::sync::MutexGuard<'_, RouteLibrary> {
+        self.routes.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Publish a section's health rollup. Retained so a late subscriber
+    /// sees every section's latest score; sent at most once since the next
+    /// publish supersedes it.
+    pub async fn publish_section_health(&self, report: &SectionHealthReport) -> Result<()> {
+        let topic = topics::health(&report.section_id);
+        let seq = self.next_sequence(&self.instance_id, MessageClass::SectionHealth);
+        let payload =
+            self.encode_envelope(MqttMessage::new(report.clone(), &self.instance_id, seq))?;
+
+        self.publish_payload(&topic, QoS::AtMostOnce, true, payload)
+            .await
+            .transport("publish section health")?;
+
+        debug!(section_id = %report.section_id, score = report.score, "Section health published");
+        Ok(())
+    }
+
+    /// Publish the health reports of the sections rescored since the last
+    /// call, or gone stale meanwhile
+    pub async fn publish_section_health_changes(&self) -> Result<()> {
+        let reports = {
+            let anomalies = self.anomalies.read().await;
+            self.section_health
+                .write()
+                .await
+                .take_changed(&anomalies, self.now_ms())
+        };
+        for report in &reports {
+            self.publish_section_health(report).await?;
+        }
+        Ok(())
+    }
+
+    /// Publish a mission's progress on its robot's topic. Retained so a
+    /// dashboard opened mid-mission sees where it is; a robot's next
+    /// mission replaces it.
+    pub async fn publish_mission_status(&self, status: &MissionStatus) -> Result<()> {
+        let topic = topics::missions(&status.robot_id);
+        let seq = self.next_sequence(&self.instance_id, MessageClass::Mission);
+        let payload =
+            self.encode_envelope(MqttMessage::new(status.clone(), &self.instance_id, seq))?;
+
+        self.publish_payload(&topic, QoS::AtLeastOnce, true, payload)
+            .await
+            .transport("publish mission status")?;
+
+        debug!(mission_id = %status.mission_id, state = ?status.state, step = status.step, "Mission status published");
+        Ok(())
+    }
+
+    /// Publish a quarantined message
+    pub async fn publish_dead_letter(&self, letter: &DeadLetter) -> Result<()> {
+        let payload = self.encode(letter)?;
+        self.publish_payload(topics::DEADLETTER, QoS::AtLeastOnce, false, payload)
+            .await
+            .transport("publish dead letter")?;
+
+        debug!(topic = %letter.topic, reason = ?letter.reason, "Dead letter published");
+        Ok(())
+    }
+
+    /// Get the active client configuration
+    pub fn config(&self) -> &MqttConfig {
+        &self.config
+    }
+
+    /// Rejected payload counts for a source (robot, section, or flat topic)
+    pub fn parse_violations(&self, source: &str) -> SourceViolations {
+        self.payload_guard
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .violations(source)
+    }
+
+    /// Sources with at least `threshold` rejected payloads, worst first
+    pub fn noisy_sources(&self, threshold: u64) -> Vec<(String, SourceViolations)> {
+        self.payload_guard
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .noisy_sources(threshold)
+    }
+
+    /// Messages that failed handling or were dead-lettered, by category
+    pub fn error_counts(&self) -> HashMap<ErrorKind, u64> {
+        self.error_counts
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// The latest published dead letters, oldest first
+    pub fn dead_letters(&self) -> Vec<DeadLetter> {
+        self.dead_letters
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .recent()
+    }
+
+    /// Every configured robot with its effective configuration
+    pub async fn robot_configs(&self) -> Vec<(String, RobotConfig)> {
+        self.robot_configs.read().await.all()
+    }
+
+    /// Counters and latencies of the hub
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.metrics.clone()
+    }
+
+    /// Metrics in the Prometheus text format, with the fleet's robots per
+    /// status
+    pub async fn render_metrics(&self) -> String {
+        let robots = self.fleet.robot_count_by_status();
+        let mut out = self.metrics.render(&robots);
+        metrics::render_wall_trends(&mut out, &self.wall_trends.read().await.snapshot());
+        out
+    }
+
+    fn count_error(&self, kind: ErrorKind) {
+        *self
+            .error_counts
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(kind)
+            .or_default() += 1;
+    }
+
+    /// Out-of-bounds positions rejected from a publisher
+    pub fn bounds_violations(&self, source: &str) -> u64 {
+        self.bounds
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .violations(source)
+    }
+
+    /// Get the decision trace shared by the engine's policies
+    pub fn decisions(&self) -> Arc<RwLock<DecisionLog>> {
+        self.decisions.clone()
+    }
+
+    /// Get the pipeline section registry
+    pub fn sections(&self) -> Arc<RwLock<SectionRegistry>> {
+        self.sections.clone()
+    }
+
+    /// Register a canonical pipeline section, optionally folding a
+    /// provisional one into it; see [`SectionRegistry::register`]. A merge
+    /// also moves the anomalies, history, alarms, health and wall trend
+    /// kept under the provisional id.
+    pub async fn register_section(
+        &self,
+        section_id: &str,
+        position: Position,
+        merge_from: Option<&str>,
+    ) -> Result<SectionInfo, SectionError> {
+        let mut sections = self.sections.write().await;
+        sections.register(section_id, position, merge_from)?;
+        info!(section_id = %section_id, merged = ?merge_from, "Pipeline section registered");
+        if let Some(from) = merge_from {
+            self.merge_section_state(from, section_id).await;
+        }
+        Ok(sections.get(section_id).cloned().expect("just registered"))
+    }
+
+    /// Move everything kept under section `from` to section `into`
+    async fn merge_section_state(&self, from: &str, into: &str) {
+        let now = self.now_ms();
+        {
+            let mut anomalies = self.anomalies.write().await;
+            let renamed = anomalies.rename_section(from, into);
+            debug!(from = %from, into = %into, anomalies = renamed.len(), "Anomalies moved to the merged section");
+            self.persist_anomalies(&mut anomalies);
+        }
+        match self.lock_telemetry_store().rename_section(from, into) {
+            Ok(moved) => {
+                debug!(from = %from, into = %into, records = moved, "History moved to the merged section")
+            }
+            Err(e) => {
+                error!(from = %from, into = %into, "Failed to move history to the merged section: {}", e)
+            }
+        }
+        self.alarms.write().await.rename_section(from, into);
+        self.wall_trends.write().await.merge_section(from, into);
+        let anomalies = self.anomalies.read().await;
+        self.section_health
+            .write()
+            .await
+            .merge_section(from, into, &anomalies, now);
+    }
+
+    /// Sections of the monitored pipeline and how they connect
+    pub fn pipeline(&self) -> &PipelineMap {
+        &self.pipeline
+    }
+
+    /// A random point on the pipeline and the section it lies on, for
+    /// generated anomalies; anywhere, on no section, without a pipeline
+    fn random_pipeline_point(&self) -> (Position, &str) {
+        let sections = self.pipeline.sections();
+        if sections.is_empty() {
+            return (
+                Position::new(rand_coord(), 0.0, rand_coord()),
+                SYSTEM_SECTION,
+            );
+        }
+        let section = &sections[rand::random::<u64>() as usize % sections.len()];
+        let position = section.point_at(rand::random::<f64>());
+        let section_id = self
+            .pipeline
+            .section_at(&position)
+            .map_or(SYSTEM_SECTION, |section| section.id.as_str());
+        (position, section_id)
+    }
+
+    /// Get the per-section environment alarm states
+    pub fn alarms(&self) -> Arc<RwLock<EnvironmentAlarms>> {
+        self.alarms.clone()
+    }
+
+    /// Get the inferred health of section sensor channels
+    pub fn sensor_health(&self) -> Arc<RwLock<SensorHealth>> {
+        self.sensor_health.clone()
+    }
+
+    /// Get the triage coordinator
+    pub fn triage(&self) -> Arc<RwLock<TriageCoordinator>> {
+        self.triage.clone()
+    }
+
+    /// Get the auto-dispatch policy and its robot assignments
+    pub fn dispatcher(&self) -> Arc<RwLock<AutoDispatcher>> {
+        self.dispatcher.clone()
+    }
+
+    /// Get the operator and signature checks of incoming commands
+    pub fn authorizer(&self) -> &CommandAuthorizer {
+        &self.authorizer
+    }
+
+    /// Get the alert correlator and its command audit log
+    pub fn correlator(&self) -> Arc<RwLock<AlertCorrelator>> {
+        self.correlator.clone()
+    }
+
+    /// Get the battery/signal trend detector
+    pub fn trends(&self) -> Arc<RwLock<TrendDetector>> {
+        self.trends.clone()
+    }
+
+    /// Get the per-robot discharge rates and runtime predictions
+    pub fn battery_manager(&self) -> Arc<RwLock<BatteryManager>> {
+        self.battery_manager.clone()
+    }
+
+    /// Get the per-section wall-thickness trends
+    pub fn wall_trends(&self) -> Arc<RwLock<WallThicknessTrends>> {
+        self.wall_trends.clone()
+    }
+
+    /// Get the latest health rollup of each section
+    pub fn section_health(&self) -> Arc<RwLock<SectionHealth>> {
+        self.section_health.clone()
+    }
+
+    /// Get the source-binding guard and its per-source mismatch counts
+    pub fn sources(&self) -> Arc<RwLock<SourceGuard>> {
+        self.sources.clone()
+    }
+
+    /// Get the configuration rollout controller
+    pub fn rollouts(&self) -> Arc<RwLock<RolloutController>> {
+        self.rollouts.clone()
+    }
+
+    /// Get the active anomalies with their merged reports
+    pub fn anomalies(&self) -> Arc<RwLock<ActiveAnomalies>> {
+        self.anomalies.clone()
+    }
+
+    /// Get the recently published engine alerts that absorb repeats
+    pub fn alert_dedup(&self) -> Arc<RwLock<AlertDedup>> {
+        self.alert_dedup.clone()
+    }
+
+    /// Get the per-source sequence tracking of incoming envelopes
+    pub fn sequences(&self) -> Arc<RwLock<SequenceTracker>> {
+        self.sequences.clone()
+    }
+
+    /// Get the engine event log
+    pub fn events(&self) -> Arc<RwLock<EventLog>> {
+        self.events.clone()
+    }
+
+    /// Get the per-robot position history
+    pub fn robot_history(&self) -> Arc<RwLock<RobotHistory>> {
+        self.history.clone()
+    }
+
+    /// Incident timeline of `focus` between `from` and `to` (Unix milliseconds)
+    pub async fn timeline(&self, from: u64, to: u64, focus: &TimelineFocus) -> Vec<TimelineEntry> {
+        let anomalies = self.anomalies.read().await;
+        let correlator = self.correlator.read().await;
+        let events = self.events.read().await;
+        let history = self.history.read().await;
+        let anomalies: Vec<_> = anomalies.all();
+        let commands: Vec<_> = correlator.audit().entries().collect();
+        let events: Vec<_> = events.entries().collect();
+        let sources = TimelineSources {
+            anomalies: &anomalies,
+            commands: &commands,
+            events: &events,
+            history: &history,
+        };
+        timeline::build_timeline(&sources, from, to, focus, &self.timeline)
+    }
+
+    /// Get the zone registry and current zone modes
+    pub fn zones(&self) -> Arc<RwLock<ZoneRegistry>> {
+        self.zones.clone()
+    }
+
+    /// Get the configured geofences
+    pub fn geofences(&self) -> &Geofences {
+        &self.geofences
+    }
+
+    /// Get the per-robot position filters
+    pub fn position_filter(&self) -> Arc<RwLock<PositionFilter>> {
+        self.position_filter.clone()
+    }
+
+    /// Every known robot with its filtered estimate at `now`, for dashboards
+    pub async fn robot_views(&self, now: u64) -> Vec<RobotView> {
+        let fleet = &self.fleet;
+        let filter = self.position_filter.read().await;
+        fleet
+            .get_all_robots()
+            .into_iter()
+            .map(|state| {
+                let estimate = filter.estimate(&state.id, now);
+                RobotView {
+                    filtered_position: estimate.map(|e| e.position),
+                    filtered_velocity: estimate.map(|e| e.velocity),
+                    expected: fleet.is_expected(&state.id),
+                    ..RobotView::from(state.clone())
+                }
+            })
+            .collect()
+    }
+
+    /// Get the declared fleet and its arrival tracking
+    pub fn expected_fleet(&self) -> Arc<RwLock<ExpectedFleet>> {
+        self.expected_fleet.clone()
+    }
+
+    /// Get the fleet manager for reading robot states
+    pub fn fleet(&self) -> Arc<FleetManager> {
+        self.fleet.clone()
+    }
+
+    /// The clock simulations and timers run on
+    pub fn clock(&self) -> SharedClock {
+        self.clock.clone()
+    }
+
+    /// Source of everything this instance publishes
+    pub fn instance_id(&self) -> &str {
+        &self.instance_id
+    }
+
+    /// Whether this instance runs the duties only one engine may: the
+    /// simulated fleet, heartbeat timeouts, auto-dispatch and escalation.
+    /// Always true unless leader election is enabled.
+    pub fn is_leader(&self) -> bool {
+        self.leadership.is_leader()
+    }
+
+    /// Handle on [`Self::is_leader`] for tasks that outlive a borrow
+    pub fn leadership(&self) -> Leadership {
+        self.leadership.clone()
+    }
+
+    /// Envelope source of a report detected by `origin`: this instance
+    /// for the engine's own detections
+    fn source_of<'a>(&'a self, origin: &'a str) -> &'a str {
+        if origin == ENGINE_ORIGIN {
+            &self.instance_id
+        } else {
+            origin
+        }
+    }
+
+    fn lock_election<'a>(
+        &self,
+        election: &'a Mutex<LeaderElection>,
+    ) -> std::sync::MutexGuard<'a, LeaderElection> {
+        election.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Claim, renew or take over the lead as the election calls for.
+    /// Only runs while connected: a claim nobody can see is no claim.
+    pub async fn drive_election(&self) -> Result<()> {
+        let Some(election) = &self.election else {
+            return Ok(());
+        };
+        if self.connection_state() != ConnectionState::Connected {
+            return Ok(());
+        }
+        let (claim, change) = self.lock_election(election).tick(self.now_ms());
+        if let Some(change) = change {
+            self.leadership_changed(change).await;
+        }
+        if let Some(claim) = claim {
+            self.publish_leader_claim(&claim).await?;
+        }
+        Ok(())
+    }
+
+    /// Publish this instance's claim, retained for instances yet to connect
+    async fn publish_leader_claim(&self, claim: &LeaderClaim) -> Result<()> {
+        let payload = self.encode(claim)?;
+        self.publish_payload(topics::SYSTEM_LEADER, QoS::AtLeastOnce, true, payload)
+            .await
+            .transport("publish leader claim")?;
+        debug!(released = claim.released, "Leader claim published");
+        Ok(())
+    }
+
+    async fn leadership_changed(&self, change: LeaderChange) {
+        self.metrics.record_leadership_change();
+        self.metrics.set_leader(self.is_leader());
+        let (leader, detail) = match &change {
+            LeaderChange::Elected => {
+                info!(instance_id = %self.instance_id, "Took the lead of the engine instances");
+                (Some(self.instance_id.as_str()), "elected")
+            }
+            LeaderChange::Following { leader } => {
+                info!(leader = %leader, "Following another engine instance");
+                (Some(leader.as_str()), "following")
+            }
+            LeaderChange::SteppedDown => {
+                warn!("Lost the broker connection, stepped down as leader");
+                (None, "stepped down")
+            }
+        };
+        self.events.write().await.record(SystemEvent::new(
+            SystemEventKind::LeadershipChanged,
+            leader,
+            detail,
+            self.now_ms(),
+        ));
+    }
+
+    /// Current simulated time (Unix ms)
+    fn now_ms(&self) -> u64 {
+        self.clock.now().as_millis()
+    }
+
+    /// Generate the next sequence number of `source`'s envelopes in `class`
+    pub fn next_sequence(&self, source: &str, class: MessageClass) -> u64 {
+        self.outgoing_seq
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .next(source, class)
+    }
+
+    /// Parse an incoming payload under the configured limits.
+    ///
+    /// The last topic segment identifies the publisher for violation
+    /// accounting. Rejected payloads are dead-lettered.
+    async fn parse_payload<T: DeserializeOwned>(&self, topic: &str, payload: &[u8]) -> Result<T> {
+        let source = topic.rsplit('/').next().unwrap_or(topic);
+        let result = self
+            .payload_guard
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .parse(source, payload);
+        match result {
+            Ok(value) => Ok(value),
+            Err(rejection) => {
+                self.metrics.record_deserialization_failure();
+                warn!(topic = %topic, source = %source, "Payload rejected: {}", rejection);
+                self.reject_payload(topic, rejection, payload).await
+            }
+        }
+    }
+
+    /// Parse an incoming envelope under the configured limits, refusing
+    /// schema versions this build does not understand. Rejected envelopes
+    /// are dead-lettered.
+    async fn parse_envelope<T: DeserializeOwned>(
+        &self,
+        topic: &str,
+        payload: &[u8],
+    ) -> Result<MqttMessage<T>> {
+        let source = topic.rsplit('/').next().unwrap_or(topic);
+        let result = self
+            .payload_guard
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .parse_envelope(source, payload);
+        match result {
+            Ok(msg) => Ok(msg),
+            Err(rejection) => {
+                self.metrics.record_deserialization_failure();
+                if let ParseRejection::UnsupportedVersion { .. } = rejection {
+                    warn!(topic = %topic, source = %source, "Envelope from a newer publisher: {}", rejection);
+                } else {
+                    warn!(topic = %topic, source = %source, "Payload rejected: {}", rejection);
+                }
+                self.reject_payload(topic, rejection, payload).await
+            }
+        }
+    }
+
+    /// Dead-letter a payload the parser refused and return the refusal as
+    /// the error; it is counted where the error is handled
+    async fn reject_payload<T>(
+        &self,
+        topic: &str,
+        rejection: ParseRejection,
+        payload: &[u8],
+    ) -> Result<T> {
+        let reason = match rejection {
+            ParseRejection::Invalid(_) => DeadLetterReason::Invalid,
+            _ => DeadLetterReason::Malformed,
+        };
+        self.quarantine(topic, reason, rejection.to_string(), None, payload)
+            .await?;
+        Err(AetherisError::from(rejection).into())
+    }
+
+    /// Validate a parsed message's values.
+    ///
+    /// Invalid messages are counted against their source, dead-lettered
+    /// unless quarantine is turned off, and `false` is returned.
+    async fn check_valid<T: Validate>(
+        &self,
+        topic: &str,
+        source: &str,
+        value: &T,
+        payload: &[u8],
+    ) -> Result<bool> {
+        let (result, quarantine) = {
+            let mut guard = self.payload_guard.lock().unwrap_or_else(|e| e.into_inner());
+            (
+                guard.validate(source, value),
+                guard.limits().quarantine_invalid,
+            )
+        };
+        let Err(rejection) = result else {
+            return Ok(true);
+        };
+        self.metrics.record_validation_rejection();
+        warn!(topic = %topic, source = %source, "Dropping message: {}", rejection);
+        if quarantine {
+            self.dead_letter(
+                topic,
+                DeadLetterReason::Invalid,
+                rejection.to_string(),
+                source,
+                payload,
+            )
+            .await?;
+        }
+        Ok(false)
+    }
+
+    /// [`Self::check_valid`] for a command, also answering an invalid one
+    /// with a failed [`CommandResponse`] so its sender learns why
+    async fn check_command_valid(
+        &self,
+        topic: &str,
+        target: &CommandTarget,
+        msg: &MqttMessage<Command>,
+        payload: &[u8],
+    ) -> Result<bool> {
+        if self
+            .check_valid(topic, &msg.source, &msg.payload, payload)
+            .await?
+        {
+            return Ok(true);
+        }
+        let reason = match msg.payload.validate() {
+            Err(e) => e.to_string(),
+            Ok(()) => "invalid command".into(),
+        };
+        self.refuse_command(target, msg, reason).await?;
+        Ok(false)
+    }
+
+    /// Check an envelope's claimed source against its topic.
+    ///
+    /// Mismatched messages are dead-lettered and `false` is returned; the
+    /// first mismatch per source per day also raises an alert.
+    async fn bind_source(&self, topic: &str, source: &str, payload: &[u8]) -> Result<bool> {
+        self.bind_source_on(topic, topic, source, payload).await
+    }
+
+    /// [`bind_source`](Self::bind_source) for content that stands for
+    /// `claimed`, e.g. one robot's telemetry inside a batch, arriving on
+    /// `topic`
+    async fn bind_source_on(
+        &self,
+        topic: &str,
+        claimed: &str,
+        source: &str,
+        payload: &[u8],
+    ) -> Result<bool> {
+        let verdict = self
+            .sources
+            .write()
+            .await
+            .check(source, claimed, self.now_ms());
+        let SourceVerdict::Mismatch { alert } = verdict else {
+            return Ok(true);
+        };
+
+        warn!(topic = %topic, source = %source, "Envelope source does not match topic");
+        self.dead_letter(
+            topic,
+            DeadLetterReason::SourceMismatch,
+            format!("source \"{}\" may not publish on {}", source, claimed),
+            source,
+            payload,
+        )
+        .await?;
+        if let Some(report) = alert {
+            self.publish_alert(&report).await?;
+        }
+        Ok(false)
+    }
+
+    /// Check that a payload about robot `claimed` arrived on the topic of
+    /// robot `expected`; otherwise it is dead-lettered and `false` returned
+    async fn check_robot_topic(
+        &self,
+        topic: &str,
+        source: &str,
+        claimed: &RobotId,
+        expected: &RobotId,
+        payload: &[u8],
+    ) -> Result<bool> {
+        if claimed == expected {
+            return Ok(true);
+        }
+        warn!(topic = %topic, source = %source, robot_id = %claimed, "Payload names another robot than its topic");
+        self.dead_letter(
+            topic,
+            DeadLetterReason::SourceMismatch,
+            format!(
+                "payload for {} arrived on the topic of {}",
+                claimed, expected
+            ),
+            source,
+            payload,
+        )
+        .await?;
+        Ok(false)
+    }
+
+    /// Check the signature of an envelope whose source has a signing key.
+    ///
+    /// The signature is checked over the envelope as received, fields this
+    /// build does not know included. Envelopes that fail are counted,
+    /// dead-lettered and `false` is returned.
+    async fn check_signature(&self, topic: &str, source: &str, payload: &[u8]) -> Result<bool> {
+        if !self.signer.requires_signature(source) {
+            return Ok(true);
+        }
+        let verified = Encoding::decode_detected::<MqttMessage<serde_json::Value>>(payload)
+            .map_err(|_| SignatureError::Malformed)
+            .and_then(|received| self.signer.verify(&received));
+        let Err(error) = verified else {
+            return Ok(true);
+        };
+        self.metrics.record_signature_rejection();
+        warn!(topic = %topic, source = %source, "Dropping envelope: {}", error);
+        self.dead_letter(
+            topic,
+            DeadLetterReason::BadSignature,
+            error.to_string(),
+            source,
+            payload,
+        )
+        .await?;
+        Ok(false)
+    }
+
+    /// Check a command's operator, signature and role.
+    ///
+    /// Refused commands are answered and audited (see
+    /// [`Self::refuse_command`]) and `false` is returned.
+    async fn authorize(&self, target: &CommandTarget, msg: &MqttMessage<Command>) -> Result<bool> {
+        let Err(rejection) = self.authorizer.check(msg) else {
+            return Ok(true);
+        };
+        warn!(source = %msg.source, command = msg.payload.name(), "Command refused: {}", rejection);
+        self.metrics.record_command_unauthorized();
+        self.refuse_command(target, msg, rejection.to_string())
+            .await?;
+        Ok(false)
+    }
+
+    /// Check that a command has not expired, allowing for clock skew.
+    ///
+    /// Expired commands are refused like unauthorized ones and `false` is
+    /// returned.
+    async fn check_expiry(
+        &self,
+        target: &CommandTarget,
+        msg: &MqttMessage<Command>,
+    ) -> Result<bool> {
+        let now = self.now_ms();
+        let Err(expired) = self
+            .command_expiry
+            .verify(&msg.payload, msg.expires_at, now)
+        else {
+            return Ok(true);
+        };
+        warn!(source = %msg.source, command = msg.payload.name(), late_ms = expired.late_ms, "Expired command dropped");
+        self.metrics.record_command_expired();
+        self.refuse_command(target, msg, expired.to_string())
+            .await?;
+        Ok(false)
+    }
+
+    /// Answer a refused command with a failed [`CommandResponse`] and keep
+    /// it in the command audit log, marked as rejected
+    async fn refuse_command(
+        &self,
+        target: &CommandTarget,
+        msg: &MqttMessage<Command>,
+        reason: String,
+    ) -> Result<()> {
+        let robot_id = match target {
+            CommandTarget::Robot(robot_id) => Some(robot_id.as_str()),
+            CommandTarget::Broadcast => None,
+        };
+        let issued_by = msg
+            .operator
+            .as_ref()
+            .map_or(&msg.source, |operator| &operator.id);
+        let now = self.now_ms();
+        let mut entry =
+            CommandAuditEntry::new(&msg.payload, robot_id, issued_by, msg.timestamp.as_millis());
+        if let Some(command_id) = &msg.command_id {
+            entry.command_id = command_id.clone();
+        }
+        let command_id = entry.command_id.clone();
+        self.events.write().await.record(
+            SystemEvent::new(
+                SystemEventKind::CommandRejected,
+                robot_id,
+                format!("{} from {}: {}", entry.variant, issued_by, reason),
+                now,
+            )
+            .for_command(&command_id),
+        );
+        entry.rejected = Some(reason.clone());
+        self.correlator.write().await.record_command(entry);
+        self.publish_response(&CommandResponse {
+            command_id,
+            robot_id: match target {
+                CommandTarget::Robot(robot_id) => robot_id.clone(),
+                CommandTarget::Broadcast => RobotId::engine(),
+            },
+            success: false,
+            error: Some(reason),
+            config: None,
+            status: None,
+            timestamp: now,
+        })
+        .await
+    }
+
+    /// Place an envelope in its sequence stream. Returns whether it may be
+    /// processed: duplicates never are, and telemetry older than the state
+    /// already applied is not. A large gap is reported as degraded
+    /// communication.
+    async fn check_sequence<T>(&self, class: MessageClass, msg: &MqttMessage<T>) -> Result<bool> {
+        let (verdict, alert_on_large_gap) = {
+            let mut sequences = self.sequences.write().await;
+            let verdict = sequences.observe(&msg.source, class, msg.seq, msg.timestamp.as_millis());
+            (verdict, sequences.config().alert_on_large_gap)
+        };
+        match verdict {
+            SeqVerdict::InOrder => Ok(true),
+            SeqVerdict::Restarted => {
+                info!(source = %msg.source, class = ?class, seq = msg.seq, "Publisher restarted, new sequence session");
+                Ok(true)
+            }
+            SeqVerdict::Duplicate => {
+                debug!(source = %msg.source, class = ?class, seq = msg.seq, "Duplicate delivery dropped");
+                Ok(false)
+            }
+            SeqVerdict::Stale { latest } => {
+                debug!(source = %msg.source, class = ?class, seq = msg.seq, latest, "Out-of-order delivery");
+                Ok(class != MessageClass::Telemetry)
+            }
+            SeqVerdict::Gap {
+                missed,
+                large: false,
+            } => {
+                debug!(source = %msg.source, class = ?class, missed, "Sequence gap");
+                Ok(true)
+            }
+            SeqVerdict::Gap {
+                missed,
+                large: true,
+            } => {
+                warn!(source = %msg.source, class = ?class, missed, "Large sequence gap, communication degraded");
+                if alert_on_large_gap {
+                    let position = self
+                        .fleet
+                        .get_robot(&msg.source)
+                        .map_or_else(Position::origin, |robot| robot.position);
+                    let report = AnomalyReport {
+                        timestamp: self.clock.now(),
+                        ..AnomalyReport::new(
+                            AnomalyType::Unknown,
+                            SeverityLevel::Low,
+                            position,
+                            SYSTEM_SECTION,
+                            ENGINE_ORIGIN,
+                            1.0,
+                            format!(
+                                "Communication degraded: {} {:?} messages from {} missing",
+                                missed, class, msg.source
+                            ),
+                        )
+                    };
+                    self.publish_alert(&report).await?;
+                }
+                Ok(true)
+            }
+        }
+    }
+
+    /// Check that a reported position is inside the world bounds,
+    /// dead-lettering the message if not. Returns whether it may be processed.
+    async fn check_bounds(
+        &self,
+        topic: &str,
+        source: &str,
+        position: &Position,
+        payload: &[u8],
+    ) -> Result<bool> {
+        let checked = self
+            .bounds
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .check(source, position);
+        let Err(violation) = checked else {
+            return Ok(true);
+        };
+
+        self.metrics.record_validation_rejection();
+        warn!(topic = %topic, source = %source, "Rejected position: {}", violation);
+        self.dead_letter(
+            topic,
+            DeadLetterReason::OutOfBounds,
+            violation.to_string(),
+            source,
+            payload,
+        )
+        .await?;
+        Ok(false)
+    }
+
+    async fn dead_letter(
+        &self,
+        topic: &str,
+        reason: DeadLetterReason,
+        detail: String,
+        source: &str,
+        payload: &[u8],
+    ) -> Result<()> {
+        self.count_error(reason.kind());
+        self.quarantine(topic, reason, detail, Some(source), payload)
+            .await
+    }
+
+    /// Keep and publish a refused message as far as its source's rate limit
+    /// allows. A message without a claimed source counts against the
+    /// publisher its topic names.
+    async fn quarantine(
+        &self,
+        topic: &str,
+        reason: DeadLetterReason,
+        detail: String,
+        claimed_source: Option<&str>,
+        payload: &[u8],
+    ) -> Result<()> {
+        let mut letter = DeadLetter {
+            topic: topic.into(),
+            reason,
+            detail,
+            claimed_source: claimed_source.map(Into::into),
+            payload: String::new(),
+            payload_base64: false,
+            received_at: self.now_ms(),
+            suppressed: 0,
+        };
+        letter.set_payload(payload);
+        let source = claimed_source.unwrap_or_else(|| topic.rsplit('/').next().unwrap_or(topic));
+        let admitted = self
+            .dead_letters
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .admit(source, letter);
+        self.metrics.record_dead_letter(reason, admitted.is_some());
+        let Some(letter) = admitted else {
+            debug!(topic = %topic, source = %source, "Dead letter suppressed by rate limit");
+            return Ok(());
+        };
+        self.events.write().await.record(SystemEvent::new(
+            SystemEventKind::DeadLetter,
+            Some(source),
+            format!("{:?} on {}: {}", letter.reason, letter.topic, letter.detail),
+            letter.received_at,
+        ));
+        self.publish_dead_letter(&letter).await
+    }
+
+    /// Hand a response to the caller waiting for the command it answers,
+    /// and count it towards the broadcast it belongs to. Returns the
+    /// broadcast when it was the last answer it waited for; progress
+    /// responses only report the command's new status.
+    pub async fn settle_response(&self, response: &CommandResponse) -> Option<BroadcastResult> {
+        let now = self.now_ms();
+        let matched = self.acks.write().await.on_response(response, now);
+        let variant = match matched {
+            ResponseMatch::Matched {
+                variant,
+                latency_ms,
+            } => {
+                debug!(robot_id = %response.robot_id, command_id = %response.command_id, command = variant, latency_ms, "Command acknowledged");
+                Some(variant)
+            }
+            ResponseMatch::Progress { variant, status } => {
+                debug!(robot_id = %response.robot_id, command_id = %response.command_id, command = variant, status = %status, "Command progressed");
+                Some(variant)
+            }
+            ResponseMatch::WrongRobot { expected } => {
+                warn!(robot_id = %response.robot_id, command_id = %response.command_id, expected = %expected, "Response names a command sent to another robot");
+                None
+            }
+            ResponseMatch::Unknown => None,
+        };
+        if let Some(variant) = variant {
+            self.command_status_changed(CommandUpdate::reported(response, variant, now))
+                .await;
+        }
+        if !response.is_final() {
+            return None;
+        }
+        let offline = self.offline_robots().await;
+        self.broadcasts
+            .write()
+            .await
+            .on_response(response, &offline, self.now_ms())
+    }
+
+    /// Drive the MQTT event loop until a failure no retry can fix
+    ///
+    /// Subscribes whenever the broker did not keep our session (the first
+    /// connection, and reconnects with a clean session), republishes the
+    /// engine status, and waits out connection errors with backoff.
+    pub async fn run(&self, mut eventloop: EventLoop) -> Result<()> {
+        let mut monitor = ConnectionMonitor::default();
+        let mut backoff = Backoff::new(self.config.reconnect.clone());
+        let mut probe = None;
+        loop {
+            self.flush_coalesced();
+            if monitor.is_connected() {
+                self.flush_offline_buffer();
+                if let Some(switch) = self.check_failback(&mut probe).await {
+                    self.switch_broker(&mut eventloop, switch).await;
+                    self.reconnect(&mut eventloop, &mut monitor).await;
+                }
+            }
+            match eventloop.poll().await {
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    let Err(e) = self.handle_publish(&publish).await else {
+                        continue;
+                    };
+                    if let Some(kind) = e.kind() {
+                        self.count_error(kind);
+                    }
+                    match e.recovery() {
+                        Recovery::Skip if e.kind() == Some(ErrorKind::Topic) => {
+                            debug!("Ignoring message: {}", e);
+                        }
+                        Recovery::Skip => {
+                            warn!("Dropped message on {}: {}", publish.topic, e);
+                        }
+                        Recovery::Reconnect => {
+                            error!("Failed to handle message on {}: {}", publish.topic, e);
+                            self.reconnect(&mut eventloop, &mut monitor).await;
+                        }
+                        Recovery::Shutdown => {
+                            error!("Stopping the event loop: {}", e);
+                            return Err(e);
+                        }
+                    }
+                }
+                Ok(Event::Incoming(Packet::SubAck(_))) => {
+                    debug!("Subscription acknowledged");
+                }
+                Ok(Event::Outgoing(Outgoing::Disconnect)) => {
+                    info!("Disconnected from MQTT broker");
+                    self.set_connection_state(ConnectionState::Disconnected)
+                        .await;
+                    return Ok(());
+                }
+                Ok(Event::Incoming(Packet::ConnAck(connack))) => {
+                    let outcome = monitor.on_connack(connack.session_present);
+                    backoff.reset();
+                    let broker = {
+                        let mut brokers = self.lock_brokers();
+                        brokers.on_connected();
+                        brokers.active().address()
+                    };
+                    if outcome.reconnected {
+                        self.metrics.record_reconnect();
+                        info!(broker = %broker, "Reconnected to MQTT broker");
+                    } else {
+                        info!(broker = %broker, "Connected to MQTT broker");
+                    }
+                    if outcome.resubscribe
+                        && let Err(e) = self.subscribe_all().await
+                    {
+                        error!("{}", e);
+                        self.count_error(ErrorKind::Transport);
+                        self.reconnect(&mut eventloop, &mut monitor).await;
+                        continue;
+                    }
+                    // Replace the offline status a previous Last Will left behind
+                    let status = self.system_status().await;
+                    if let Err(e) = self.publish_system_status(&status).await {
+                        error!("Failed to publish system status: {}", e);
+                    }
+                    self.set_connection_state(ConnectionState::Connected).await;
+                    if !outcome.reconnected
+                        && let Err(e) = self.republish_open_anomalies().await
+                    {
+                        error!("Failed to republish open anomalies: {}", e);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    let fatal = FatalConnectError::classify(&e);
+                    let switch = self.lock_brokers().on_failure(fatal.is_some());
+                    // Retrying cannot fix rejected credentials or
+                    // certificates, unless another broker is left to try
+                    if switch.is_none()
+                        && let Some(fatal) = fatal
+                    {
+                        error!("MQTT connection failed: {}", fatal);
+                        return Err(fatal.into());
+                    }
+                    if monitor.on_error() {
+                        self.set_connection_state(ConnectionState::Disconnected)
+                            .await;
+                    }
+                    if let Some(switch) = switch {
+                        self.switch_broker(&mut eventloop, switch).await;
+                        backoff.reset();
+                    }
+                    let delay = backoff.next_delay();
+                    error!("MQTT connection error: {}. Retrying in {:?}", e, delay);
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    /// Drop the broker connection; the next poll connects afresh and
+    /// resubscribes, with unacknowledged publishes kept for resending
+    async fn reconnect(&self, eventloop: &mut EventLoop, monitor: &mut ConnectionMonitor) {
+        eventloop.clean();
+        if monitor.on_error() {
+            self.set_connection_state(ConnectionState::Disconnected)
+                .await;
+        }
+    }
+
+    fn lock_brokers(&self) -> std::sync::MutexGuard<'_, BrokerRotation> {
+        self.brokers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// `host:port` of the broker the engine is on, or trying to reach
+    pub fn active_broker(&self) -> String {
+        self.lock_brokers().active().address()
+    }
+
+    /// Collect the failback probe once it finished, or start it when due.
+    /// Returns the switch to a preferred broker that answered.
+    async fn check_failback(
+        &self,
+        probe: &mut Option<JoinHandle<Option<usize>>>,
+    ) -> Option<BrokerSwitch> {
+        if let Some(running) = probe.take() {
+            if !running.is_finished() {
+                *probe = Some(running);
+                return None;
+            }
+            let answered = running.await.ok().flatten()?;
+            return self.lock_brokers().fail_back(answered);
+        }
+        let (targets, timeout) = {
+            let mut brokers = self.lock_brokers();
+            let targets = brokers.probe_due(Instant::now())?;
+            (targets, brokers.config().probe_timeout)
+        };
+        *probe = Some(tokio::spawn(async move {
+            for (index, endpoint) in targets {
+                if failover::probe(&endpoint, timeout).await {
+                    return Some(index);
+                }
+            }
+            None
+        }));
+        None
+    }
+
+    /// Point the event loop at the broker the rotation moved to; the next
+    /// poll connects there
+    async fn switch_broker(&self, eventloop: &mut EventLoop, switch: BrokerSwitch) {
+        let index = self.lock_brokers().active_index();
+        eventloop.mqtt_options = self.broker_options[index].clone();
+        match switch.reason {
+            SwitchReason::Failover => {
+                self.metrics.record_broker_failover();
+                warn!(from = %switch.from, to = %switch.to, "Failing over to the next MQTT broker");
+            }
+            SwitchReason::Failback => {
+                self.metrics.record_broker_failback();
+                info!(from = %switch.from, to = %switch.to, "Preferred MQTT broker is back, failing back");
+            }
+        }
+        self.metrics.set_active_broker(index);
+        let _ = self
+            .message_tx
+            .send(EngineMessage::BrokerSwitched(switch))
+            .await;
+    }
+
+    /// Hand a message to the engine's consumer. Periodic messages never wait
+    /// for room in the channel, the rest do (see [`backpressure`]).
+    async fn notify(&self, message: EngineMessage) -> Result<()> {
+        if let Some((class, _)) = LossyClass::of(&message) {
+            let offer = self.lock_coalesced().offer(&self.message_tx, message);
+            return match offer {
+                Ok(Handoff::Superseded) => {
+                    self.metrics.record_channel_drop(class);
+                    Ok(())
+                }
+                Ok(Handoff::Sent | Handoff::Slotted) => Ok(()),
+                Err(Closed) => Err(AetherisError::ChannelClosed.into()),
+            };
+        }
+        self.flush_coalesced();
+        self.message_tx
+            .send(message)
+            .await
+            .map_err(|_| AetherisError::ChannelClosed.into())
+    }
+
+    fn lock_coalesced(&self) -> std::sync::MutexGuard<'_, CoalescingSlots> {
+        self.coalesced.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Move periodic messages that found the channel full into it, as far
+    /// as there is room. A closed channel is reported by the next notify.
+    fn flush_coalesced(&self) {
+        let _ = self.lock_coalesced().flush(&self.message_tx);
+    }
+
+    async fn set_connection_state(&self, state: ConnectionState) {
+        if state == ConnectionState::Disconnected {
+            self.lock_offline_buffer().start_holding();
+        }
+        if let Some(election) = &self.election {
+            let now = self.now_ms();
+            let change = match state {
+                ConnectionState::Connected => {
+                    self.lock_election(election).connected(now);
+                    None
+                }
+                ConnectionState::Disconnected => self.lock_election(election).disconnected(now),
+            };
+            if let Some(change) = change {
+                self.leadership_changed(change).await;
+            }
+        }
+        self.connection.send_replace(state);
+        let broker = self.active_broker();
+        let _ = self
+            .message_tx
+            .send(EngineMessage::ConnectionStateChanged(state, broker))
+            .await;
+    }
+
+    /// Whether the broker connection is currently up
+    pub fn connection_state(&self) -> ConnectionState {
+        *self.connection.borrow()
+    }
+
+    /// Handle a publish from the event loop. Retained telemetry older than
+    /// the heartbeat timeout is from a robot that may be long gone, and is
+    /// dropped rather than bringing it back online.
+    pub async fn handle_publish(&self, publish: &Publish) -> Result<()> {
+        if publish.retain
+            && self
+                .is_stale_telemetry(&publish.topic, &publish.payload)
+                .await
+        {
+            debug!(topic = %publish.topic, "Ignoring stale retained telemetry");
+            return Ok(());
+        }
+        self.received
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let start = Instant::now();
+        let result = self.handle_incoming(&publish.topic, &publish.payload).await;
+        self.metrics
+            .record_received(TopicClass::of(&publish.topic), start.elapsed());
+        result
+    }
+
+    /// The full state a telemetry payload reports. A delta applies to the
+    /// robot's last known state; without one it is dropped and a keyframe
+    /// requested.
+    async fn rebuild_state(&self, telemetry: &TelemetryPayload) -> Result<Option<RobotState>> {
+        let delta = match telemetry {
+            TelemetryPayload::Full(state) => {
+                self.keyframes
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .received_keyframe(&state.id);
+                return Ok(Some(state.clone()));
+            }
+            TelemetryPayload::Delta(delta) => delta,
+        };
+        let known = self.fleet.get_robot(&delta.id);
+        let awaiting = self
+            .keyframes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_awaiting(&delta.id);
+        if let Some(mut state) = known
+            && !awaiting
+        {
+            state.apply(delta);
+            return Ok(Some(state));
+        }
+        let request = self
+            .keyframes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .missing_keyframe(&delta.id, self.now_ms());
+        if request {
+            info!(robot_id = %delta.id, "Delta telemetry without a keyframe, requesting one");
+            self.send_command(&delta.id, Command::RequestKeyframe)
+                .await?;
+        } else {
+            debug!(robot_id = %delta.id, "Dropping delta telemetry until the keyframe arrives");
+        }
+        Ok(None)
+    }
+
+    /// Fold an accepted robot state into the fleet and everything that
+    /// watches it, then hand it to the consumer
+    async fn apply_telemetry(&self, state: RobotState) -> Result<()> {
+        let reconnection = self.fleet.update_robot(state.clone());
+        if let Some(reconnection) = reconnection {
+            self.robot_reconnected(reconnection).await?;
+        }
+        self.history.write().await.record(&state);
+        self.record_history(HistoryRecord::Telemetry(state.clone()));
+        let filtered = self.position_filter.write().await.observe(&state);
+        if let Some(filtered) = filtered {
+            self.publish_filtered_telemetry(&filtered).await?;
+        }
+        self.observe_link(&state.id, state.signal).await?;
+        let trend_alerts = self.trends.write().await.observe(
+            &state.id,
+            state.battery,
+            state.signal,
+            state.position,
+            state.timestamp.as_millis(),
+        );
+        for report in trend_alerts {
+            self.publish_alert(&report).await?;
+        }
+        self.check_battery(&state).await?;
+        self.notify(EngineMessage::TelemetryReceived(state)).await
+    }
+
+    /// Send a robot back to charge once its battery only just covers the
+    /// way to the nearest station
+    async fn check_battery(&self, state: &RobotState) -> Result<()> {
+        let recall = self.battery_manager.write().await.observe(state);
+        let Some(recall) = recall else {
+            return Ok(());
+        };
+        if !self.is_leader() {
+            return Ok(());
+        }
+        info!(
+            robot_id = %recall.robot_id,
+            station_id = %recall.station_id,
+            battery = recall.battery,
+            needed = recall.needed,
+            "Battery only just covers the way back, returning the robot to charge"
+        );
+        // The robot is the only candidate, scored by the margin it has left
+        let candidates = vec![CandidateEvaluation::scored(
+            &recall.robot_id,
+            recall.battery - recall.needed,
+        )];
+        let now = self.now_ms();
+        let decision = Decision::new(PolicyKind::LowBattery, &recall.robot_id, candidates, now);
+        let command_id = match self
+            .send_command(&recall.robot_id, Command::ReturnToBase)
+            .await
+        {
+            Ok(command_id) => command_id,
+            Err(e) => {
+                self.decisions.write().await.record(decision);
+                return Err(e);
+            }
+        };
+        self.events.write().await.record(
+            SystemEvent::new(
+                SystemEventKind::RobotRecalled,
+                Some(&recall.robot_id),
+                format!(
+                    "sent back to {} at {:.1}% battery ({:.1}% needed)",
+                    recall.station_id, recall.battery, recall.needed
+                ),
+                now,
+            )
+            .for_command(&command_id)
+            .for_decision(&decision.id),
+        );
+        self.decisions.write().await.record(decision.with_action(
+            format!("send {} back to {}", recall.robot_id, recall.station_id),
+            vec![command_id],
+        ));
+        Ok(())
+    }
+
+    /// Whether telemetry is older than the heartbeat timeout, judged on the
+    /// engine's clock after correcting for the robot's skew
+    async fn is_stale_telemetry(&self, topic: &str, payload: &[u8]) -> bool {
+        let Some(Topic::Telemetry { robot_id }) = topics::parse(topic) else {
+            return false;
+        };
+        let Ok(msg) = Encoding::decode_detected::<MqttMessage<TelemetryPayload>>(payload) else {
+            return false;
+        };
+        let sent = self
+            .fleet
+            .corrected_timestamp(&robot_id, msg.payload.timestamp());
+        self.clock.now().duration_since(sent) > self.fleet.heartbeat_timeout()
+    }
+
+    /// Process incoming MQTT messages
+    pub async fn handle_incoming(&self, topic: &str, payload: &[u8]) -> Result<()> {
+        let Some(parsed) = topics::parse(topic) else {
+            return Err(AetherisError::Topic(topic.into()).into());
+        };
+        match parsed {
+            Topic::Telemetry { robot_id } => {
+                let msg: MqttMessage<TelemetryPayload> =
+                    self.parse_envelope(topic, payload).await?;
+                if !self.bind_source(topic, &msg.source, payload).await?
+                    || !self.check_signature(topic, &msg.source, payload).await?
+                {
+                    return Ok(());
+                }
+                let Some(state) = self.rebuild_state(&msg.payload).await? else {
+                    return Ok(());
+                };
+                if !self
+                    .check_robot_topic(topic, &msg.source, &state.id, &robot_id, payload)
+                    .await?
+                    || !self
+                        .check_valid(topic, &msg.source, &state, payload)
+                        .await?
+                    || !self
+                        .check_bounds(topic, &msg.source, &state.position, payload)
+                        .await?
+                    || !self.check_sequence(MessageClass::Telemetry, &msg).await?
+                {
+                    return Ok(());
+                }
+                self.note_encoding(&state.id, payload);
+                self.apply_telemetry(state).await?;
+            }
+            Topic::TelemetryBatch => {
+                let msg: MqttMessage<TelemetryBatch> = self.parse_envelope(topic, payload).await?;
+                if !self.bind_source(topic, &msg.source, payload).await?
+                    || !self.check_signature(topic, &msg.source, payload).await?
+                    || !self
+                        .check_sequence(MessageClass::TelemetryBatch, &msg)
+                        .await?
+                {
+                    return Ok(());
+                }
+                // A bad state costs only its own robot's update; the rest
+                // are applied in the order they were batched
+                for state in msg.payload.states {
+                    // The batch's source must be one that may publish each
+                    // robot's own telemetry
+                    let claimed = topics::telemetry(&state.id);
+                    if self
+                        .bind_source_on(topic, &claimed, &msg.source, payload)
+                        .await?
+                        && self.check_valid(topic, &state.id, &state, payload).await?
+                        && self
+                            .check_bounds(topic, &state.id, &state.position, payload)
+                            .await?
+                    {
+                        self.apply_telemetry(state).await?;
+                    }
+                }
+            }
+            Topic::Heartbeat { robot_id } => {
+                let heartbeat: Heartbeat = self.parse_payload(topic, payload).await?;
+                // Heartbeats travel bare: the robot they name is the source
+                let source = heartbeat.robot_id.as_str();
+                if !self.bind_source(topic, source, payload).await?
+                    || !self
+                        .check_robot_topic(topic, source, &heartbeat.robot_id, &robot_id, payload)
+                        .await?
+                    || !self
+                        .check_valid(topic, &robot_id, &heartbeat, payload)
+                        .await?
+                {
+                    return Ok(());
+                }
+                self.note_encoding(&heartbeat.robot_id, payload);
+                let reconnection = self.fleet.record_heartbeat(&heartbeat.robot_id);
+                if let Some(reconnection) = reconnection {
+                    self.robot_reconnected(reconnection).await?;
+                }
+                let drift = self
+                    .fleet
+                    .record_link(&heartbeat.robot_id, heartbeat.timestamp);
+                if let Some(drift) = drift {
+                    self.clock_drift_changed(&heartbeat.robot_id, drift).await?;
+                }
+                self.observe_link(&heartbeat.robot_id, heartbeat.signal)
+                    .await?;
+                self.notify(EngineMessage::HeartbeatReceived(heartbeat))
+                    .await?;
+            }
+            Topic::Alerts | Topic::AlertSeverity { .. } => {
+                let mut msg: MqttMessage<AnomalyReport> =
+                    self.parse_envelope(topic, payload).await?;
+                if !self.bind_source(topic, &msg.source, payload).await?
+                    || !self.check_signature(topic, &msg.source, payload).await?
+                    || !self
+                        .check_valid(topic, &msg.source, &msg.payload, payload)
+                        .await?
+                    || !self
+                        .check_bounds(topic, &msg.source, &msg.payload.position, payload)
+                        .await?
+                    || !self.check_sequence(MessageClass::Alert, &msg).await?
+                {
+                    return Ok(());
+                }
+                let report = &mut msg.payload;
+                report.section_id = self.sections.write().await.record_anomaly(
+                    &report.section_id,
+                    report.position,
+                    &report.id,
+                );
+                self.correlator.read().await.correlate(report);
+                let outcome = {
+                    let sections = self.sections.read().await;
+                    let mut anomalies = self.anomalies.write().await;
+                    let outcome = anomalies.ingest(report.clone(), &sections);
+                    self.persist_anomalies(&mut anomalies);
+                    outcome
+                };
+                {
+                    let anomalies = self.anomalies.read().await;
+                    self.section_health.write().await.observe_anomaly(
+                        report,
+                        &anomalies,
+                        self.now_ms(),
+                    );
+                }
+                match &outcome {
+                    MergeOutcome::Duplicate { primary_id }
+                    | MergeOutcome::Supporting { primary_id } => {
+                        debug!(anomaly_id = %report.id, primary_id = %primary_id, "Report merged into active anomaly");
+                        return Ok(());
+                    }
+                    MergeOutcome::Promoted { demoted_id } => {
+                        info!(anomaly_id = %report.id, demoted_id = %demoted_id, "Robot report supersedes engine alert");
+                    }
+                    MergeOutcome::Closed { primary_id } => {
+                        debug!(anomaly_id = %report.id, dropped = primary_id.is_some(), "Closed anomaly report");
+                        return Ok(());
+                    }
+                    MergeOutcome::New | MergeOutcome::Updated { .. } => {}
+                }
+                if !report.correlated_commands.is_empty() {
+                    info!(
+                        anomaly_id = %report.id,
+                        commands = report.correlated_commands.len(),
+                        urgency = ?report.urgency,
+                        "Alert follows recent commands"
+                    );
+                }
+                self.record_history(HistoryRecord::Anomaly(Box::new(msg.payload.clone())));
+                self.notify(EngineMessage::AlertReceived(msg.payload.clone()))
+                    .await?;
+                self.triage_alert(msg.payload).await?;
+            }
+            Topic::TriageResults => {
+                let msg: MqttMessage<TriageResult> = self.parse_envelope(topic, payload).await?;
+                if !self.bind_source(topic, &msg.source, payload).await?
+                    || !self.check_signature(topic, &msg.source, payload).await?
+                {
+                    return Ok(());
+                }
+                let triaged = self
+                    .triage
+                    .write()
+                    .await
+                    .on_result(&msg.payload, self.now_ms());
+                match triaged {
+                    Some(report) => self.release_triaged(report).await?,
+                    None => {
+                        debug!(anomaly_id = %msg.payload.anomaly_id, "Ignoring stale triage result")
+                    }
+                }
+            }
+            Topic::Environment { .. } => {
+                let msg: MqttMessage<PipeEnvironment> = self.parse_envelope(topic, payload).await?;
+                if !self.bind_source(topic, &msg.source, payload).await?
+                    || !self.check_signature(topic, &msg.source, payload).await?
+                    || !self
+                        .check_valid(topic, &msg.source, &msg.payload, payload)
+                        .await?
+                    || !self
+                        .check_bounds(topic, &msg.source, &msg.payload.position, payload)
+                        .await?
+                    || !self.check_sequence(MessageClass::Environment, &msg).await?
+                {
+                    return Ok(());
+                }
+                let reading = self.sections.write().await.record_reading(msg.payload)?;
+                let now = self.now_ms();
+                let assessment = self.sensor_health.write().await.observe(&reading, now);
+                for event in assessment.events {
+                    let detail = match event {
+                        SensorHealthEvent::Suspected {
+                            channel,
+                            fault,
+                            report,
+                        } => {
+                            warn!(section_id = %reading.section_id, channel = ?channel, fault = ?fault, "Sensor channel suspect");
+                            self.publish_alert(&report).await?;
+                            format!("{:?} sensor suspect ({:?})", channel, fault)
+                        }
+                        SensorHealthEvent::Restored { channel } => {
+                            info!(section_id = %reading.section_id, channel = ?channel, "Sensor channel restored");
+                            format!("{:?} sensor restored", channel)
+                        }
+                    };
+                    self.events.write().await.record(SystemEvent::new(
+                        SystemEventKind::SensorHealthChanged,
+                        Some(&reading.section_id),
+                        detail,
+                        now,
+                    ));
+                }
+                let events =
+                    self.alarms
+                        .write()
+                        .await
+                        .assess_excluding(&reading, now, &assessment.excluded);
+                for event in events {
+                    match event {
+                        AlarmEvent::Raised(report) => self.publish_alert(&report).await?,
+                        AlarmEvent::Occurrence {
+                            report_id,
+                            occurrences,
+                            ..
+                        } => self.alarm_occurred(&report_id, occurrences, now).await,
+                        AlarmEvent::Cleared { report_id, hazard } => {
+                            info!(anomaly_id = %report_id, hazard = ?hazard, "Environment alarm cleared")
+                        }
+                    }
+                }
+                let (thinning, wall_trend) = {
+                    let mut trends = self.wall_trends.write().await;
+                    let thinning = trends.observe(&reading);
+                    (thinning, trends.trend(&reading.section_id).cloned())
+                };
+                if let Some(report) = thinning {
+                    warn!(section_id = %reading.section_id, severity = ?report.severity, "Wall thinning projected");
+                    self.publish_alert(&report).await?;
+                }
+                let detected = self.run_detectors(&Observation {
+                    reading: &reading,
+                    wall_trend: wall_trend.as_ref(),
+                    suspect: &assessment.excluded,
+                });
+                for (detector, report) in detected {
+                    warn!(section_id = %reading.section_id, detector = %detector, anomaly_type = ?report.anomaly_type, "Detector raised an anomaly");
+                    self.publish_alert(&report).await?;
+                }
+                {
+                    let anomalies = self.anomalies.read().await;
+                    self.section_health
+                        .write()
+                        .await
+                        .observe_reading(&reading, &anomalies, now);
+                }
+                self.record_history(HistoryRecord::Environment(reading.clone()));
+                self.notify(EngineMessage::EnvironmentReceived(reading))
+                    .await?;
+            }
+            Topic::Responses { .. } => {
+                let response: CommandResponse = self.parse_payload(topic, payload).await?;
+                let settled = self.settle_response(&response).await;
+                if response.is_final() {
+                    let reverts = self.rollouts.write().await.on_ack(
+                        &response.robot_id,
+                        &response.command_id,
+                        response.success,
+                        self.now_ms(),
+                    );
+                    self.push_configs(reverts).await?;
+                }
+                if let Some(result) = settled {
+                    self.report_broadcast(result).await?;
+                }
+                self.notify(EngineMessage::CommandResponseReceived(response))
+                    .await?;
+            }
+            Topic::Commands { target } => {
+                // Handle incoming commands from dashboard (chaos scenarios). An
+                // empty payload only clears a retained held command.
+                if !payload.is_empty()
+                    && let Ok(msg) = self.parse_envelope::<Command>(topic, payload).await
+                    && self.bind_source(topic, &msg.source, payload).await?
+                    && self
+                        .check_command_valid(topic, &target, &msg, payload)
+                        .await?
+                    && self.authorize(&target, &msg).await?
+                    && self.check_expiry(&target, &msg).await?
+                {
+                    let robot_id = match &target {
+                        CommandTarget::Robot(robot_id) => Some(robot_id.as_str()),
+                        CommandTarget::Broadcast => None,
+                    };
+                    let issued_by = msg
+                        .operator
+                        .as_ref()
+                        .map_or(&msg.source, |operator| &operator.id);
+                    let mut entry = CommandAuditEntry::new(
+                        &msg.payload,
+                        robot_id,
+                        issued_by,
+                        msg.timestamp.as_millis(),
+                    );
+                    if let Some(command_id) = &msg.command_id {
+                        entry.command_id = command_id.clone();
+                    }
+                    let command_id = entry.command_id.clone();
+                    self.events.write().await.record(
+                        SystemEvent::new(
+                            SystemEventKind::CommandIssued,
+                            robot_id,
+                            format!("{} from {}", entry.variant, msg.source),
+                            msg.timestamp.as_millis(),
+                        )
+                        .for_command(&entry.command_id),
+                    );
+                    self.correlator.write().await.record_command(entry);
+                    if let (Command::Configure { config }, Some(robot_id)) =
+                        (&msg.payload, robot_id)
+                    {
+                        self.capabilities.write().await.configure(robot_id, config);
+                        let mut configs = self.robot_configs.write().await;
+                        configs.configure(robot_id, config);
+                        if let Err(e) = configs.save() {
+                            error!(robot_id = %robot_id, "Failed to save robot configuration: {}", e);
+                        }
+                    }
+                    // The engine answers for the configuration it keeps
+                    if let (Command::GetConfig, CommandTarget::Robot(robot_id)) =
+                        (&msg.payload, &target)
+                    {
+                        let config = self.robot_configs.read().await.get(robot_id);
+                        self.publish_response(&CommandResponse {
+                            command_id: command_id.clone(),
+                            robot_id: robot_id.clone(),
+                            success: true,
+                            error: None,
+                            config: Some(config),
+                            status: None,
+                            timestamp: self.now_ms(),
+                        })
+                        .await?;
+                    }
+                    // The engine publishes for the simulated robots
+                    if msg.payload == Command::RequestKeyframe {
+                        self.delta_encoder
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .force_keyframe(robot_id);
+                    }
+                    if let Command::SetZoneMode {
+                        zone_id,
+                        mode,
+                        until,
+                    } = &msg.payload
+                        && let Err(e) = self.set_zone_mode(zone_id, mode.clone(), *until).await
+                    {
+                        warn!(zone_id = %zone_id, "Zone mode change failed: {}", e);
+                    }
+                    let change = match &msg.payload {
+                        Command::AssignAnomaly {
+                            anomaly_id,
+                            assignee,
+                            due_at,
+                        } => Some((
+                            anomaly_id,
+                            self.assign_anomaly(anomaly_id, assignee, &msg.source, *due_at)
+                                .await,
+                        )),
+                        Command::UpdateAssignment { anomaly_id, state } => {
+                            Some((anomaly_id, self.update_assignment(anomaly_id, *state).await))
+                        }
+                        Command::AcknowledgeAnomaly { anomaly_id } => Some((
+                            anomaly_id,
+                            self.set_anomaly_status(
+                                anomaly_id,
+                                AnomalyStatus::Acknowledged,
+                                &msg.source,
+                            )
+                            .await,
+                        )),
+                        Command::ResolveAnomaly {
+                            anomaly_id,
+                            resolution,
+                            force,
+                        } => Some((
+                            anomaly_id,
+                            self.resolve_anomaly(anomaly_id, *resolution, *force, &msg.source)
+                                .await,
+                        )),
+                        _ => None,
+                    };
+                    if let Some((anomaly_id, outcome)) = change {
+                        if let Err(e) = &outcome {
+                            warn!(anomaly_id = %anomaly_id, "Anomaly change refused: {}", e);
+                        }
+                        // The engine answers anomaly commands as robots answer theirs
+                        self.publish_response(&CommandResponse {
+                            command_id: command_id.clone(),
+                            robot_id: RobotId::engine(),
+                            success: outcome.is_ok(),
+                            error: outcome.err().map(|e| e.to_string()),
+                            config: None,
+                            status: None,
+                            timestamp: self.now_ms(),
+                        })
+                        .await?;
+                    }
+                    if let Command::Investigate { anomaly_id } = &msg.payload
+                        && let Err(e) = self
+                            .set_anomaly_status(
+                                anomaly_id,
+                                AnomalyStatus::Investigating,
+                                &msg.source,
+                            )
+                            .await
+                    {
+                        debug!(anomaly_id = %anomaly_id, "Investigation not tracked: {}", e);
+                    }
+                    if let Command::RegisterSection {
+                        section_id,
+                        position,
+                        merge_from,
+                    } = &msg.payload
+                        && let Err(e) = self
+                            .register_section(section_id, *position, merge_from.as_deref())
+                            .await
+                    {
+                        warn!(section_id = %section_id, "Section registration failed: {}", e);
+                    }
+                    // Generate alert for chaos scenarios
+                    if let Err(e) = self
+                        .generate_alert_for_command(&msg.payload, &msg.source)
+                        .await
+                    {
+                        error!("Failed to generate alert for command: {}", e);
+                    }
+                    self.notify(EngineMessage::CommandReceived(ReceivedCommand {
+                        command: msg.payload,
+                        source: msg.source,
+                        target,
+                        command_id,
+                        expires_at: msg.expires_at,
+                    }))
+                    .await?;
+                }
+            }
+            Topic::RouteRequests => {
+                let request: RouteRequest = self.parse_payload(topic, payload).await?;
+                self.answer_route_request(request).await?;
+            }
+            Topic::SystemLeader => {
+                let claim: LeaderClaim = self.parse_payload(topic, payload).await?;
+                let Some(election) = &self.election else {
+                    return Ok(());
+                };
+                let change = self.lock_election(election).observe(claim, self.now_ms());
+                if let Some(change) = change {
+                    self.leadership_changed(change).await;
+                }
+            }
+            // Our own publications, or not consumed by the engine
+            Topic::TelemetryFiltered { .. }
+            | Topic::AlertEscalations
+            | Topic::SystemStatus
+            | Topic::TriageRequests
+            | Topic::DeadLetter
+            | Topic::Routes { .. }
+            | Topic::RouteLibrary
+            | Topic::Health { .. }
+            | Topic::Missions { .. }
+            | Topic::BroadcastResults => {}
+        }
+
+        Ok(())
+    }
+
+    /// Route a new alert through triage, deferring it or releasing it directly
+    async fn triage_alert(&self, report: AnomalyReport) -> Result<()> {
+        let (nearby, environment) = {
+            let limit = self.triage.read().await.config().max_nearby_robots;
+            let nearby = self.fleet.nearby_robots(&report.position, limit);
+            let environment = self
+                .sections
+                .read()
+                .await
+                .latest_reading(&report.section_id)
+                .cloned();
+            (nearby, environment)
+        };
+
+        let decision =
+            self.triage
+                .write()
+                .await
+                .on_alert(report, nearby, environment, self.now_ms());
+        match decision {
+            TriageDecision::Bypass(report) => {
+                self.auto_dispatch(&report).await?;
+                self.notify(EngineMessage::AlertTriaged(report)).await?;
+            }
+            TriageDecision::Deferred(request) => self.publish_triage_request(&request).await?,
+            TriageDecision::Ignore => {}
+        }
+        Ok(())
+    }
+
+    /// Record a robot's return and raise an alert when its connection flaps
+    async fn robot_reconnected(&self, reconnection: Reconnection) -> Result<()> {
+        let Reconnection {
+            robot_id,
+            offline_for,
+            flapping,
+        } = reconnection;
+        let now = self.now_ms();
+        info!(robot_id = %robot_id, offline_for = ?offline_for, "Robot back online");
+        self.events.write().await.record(SystemEvent::new(
+            SystemEventKind::RobotReconnected,
+            Some(&robot_id),
+            format!("back after {}s offline", offline_for.as_secs()),
+            now,
+        ));
+
+        if let Some(reconnects) = flapping {
+            let (position, window) = {
+                let fleet = &self.fleet;
+                let position = fleet
+                    .get_robot(&robot_id)
+                    .map_or_else(Position::origin, |robot| robot.position);
+                (position, fleet.flap_config().window)
+            };
+            warn!(robot_id = %robot_id, reconnects, "Robot connection flapping");
+            let report = AnomalyReport {
+                timestamp: Timestamp::from_millis(now),
+                ..AnomalyReport::new(
+                    AnomalyType::Unknown,
+                    SeverityLevel::Medium,
+                    position,
+                    SYSTEM_SECTION,
+                    ENGINE_ORIGIN,
+                    1.0,
+                    format!(
+                        "Intermittent connectivity: robot {} came back online {} times within {} min",
+                        robot_id,
+                        reconnects,
+                        window.as_secs() / 60
+                    ),
+                )
+            };
+            self.publish_alert(&report).await?;
+        }
+
+        self.notify(EngineMessage::RobotReconnected(robot_id, offline_for))
+            .await
+    }
+
+    /// Alert on a robot whose clock drifted beyond the skew bound; note its
+    /// return within it
+    async fn clock_drift_changed(&self, robot_id: &RobotId, drift: DriftChange) -> Result<()> {
+        let estimate = match drift {
+            DriftChange::Recovered(estimate) => {
+                info!(robot_id = %robot_id, offset_ms = estimate.offset_ms, "Robot clock back within the skew bound");
+                return Ok(());
+            }
+            DriftChange::Drifted(estimate) => estimate,
+        };
+        warn!(robot_id = %robot_id, offset_ms = estimate.offset_ms, "Robot clock drifted");
+        let position = self
+            .fleet
+            .get_robot(robot_id)
+            .map_or_else(Position::origin, |robot| robot.position);
+        let (direction, skew) = if estimate.offset_ms >= 0 {
+            ("behind", estimate.offset_ms)
+        } else {
+            ("ahead of", -estimate.offset_ms)
+        };
+        let report = AnomalyReport {
+            timestamp: self.clock.now(),
+            ..AnomalyReport::new(
+                AnomalyType::Unknown,
+                SeverityLevel::Medium,
+                position,
+                SYSTEM_SECTION,
+                ENGINE_ORIGIN,
+                1.0,
+                format!(
+                    "Clock drift: robot {} runs {:.1}s {} the engine, its timestamps are corrected",
+                    robot_id,
+                    skew as f64 / 1000.0,
+                    direction
+                ),
+            )
+        };
+        self.publish_alert(&report).await
+    }
+
+    /// Republish a triaged report and hand it on for dispatch
+    async fn release_triaged(&self, report: AnomalyReport) -> Result<()> {
+        self.publish_alert(&report).await?;
+        self.auto_dispatch(&report).await?;
+        self.notify(EngineMessage::AlertTriaged(report)).await?;
+        Ok(())
+    }
+
+    /// Send the best robot to investigate an alert released for dispatch,
+    /// or raise an escalation alert when none can go
+    async fn auto_dispatch(&self, report: &AnomalyReport) -> Result<()> {
+        if !self.is_leader() {
+            return Ok(());
+        }
+        let now = self.now_ms();
+        let plan = {
+            let zones = self.zones.read().await;
+            let mut dispatcher = self.dispatcher.write().await;
+            dispatcher.release_finished(&self.fleet, &*self.anomalies.read().await);
+            dispatcher.plan(report, &self.fleet, &zones, now)
+        };
+        let decision = match plan {
+            DispatchPlan::Skip => return Ok(()),
+            DispatchPlan::Send { robot_id, decision } => {
+                let commands = [
+                    Command::Investigate {
+                        anomaly_id: report.id.clone(),
+                    },
+                    Command::MoveTo {
+                        target: report.position,
+                        speed: None,
+                    },
+                ];
+                let mut command_ids = Vec::with_capacity(commands.len());
+                for command in commands {
+                    let command_id = acks::new_command_id();
+                    if let Err(e) = self.dispatch(&robot_id, &command_id, command).await {
+                        warn!(anomaly_id = %report.id, robot_id = %robot_id, "Auto-dispatch not sent: {}", e);
+                        break;
+                    }
+                    command_ids.push(command_id);
+                }
+                if command_ids.is_empty() {
+                    self.dispatcher.write().await.release(&robot_id);
+                    decision
+                } else {
+                    info!(anomaly_id = %report.id, robot_id = %robot_id, decision_id = %decision.id, "Robot dispatched to anomaly");
+                    self.events.write().await.record(
+                        SystemEvent::new(
+                            SystemEventKind::RobotDispatched,
+                            Some(&robot_id),
+                            format!("sent to investigate {}", report.id),
+                            now,
+                        )
+                        .for_command(&command_ids[0])
+                        .for_decision(&decision.id),
+                    );
+                    decision.with_action(
+                        format!("send {} to investigate {}", robot_id, report.id),
+                        command_ids,
+                    )
+                }
+            }
+            DispatchPlan::Escalate { decision } => {
+                warn!(anomaly_id = %report.id, decision_id = %decision.id, "No robot available for anomaly, escalating");
+                self.publish_alert(&dispatch::escalation(report, &decision))
+                    .await?;
+                decision.with_action(
+                    format!("escalate {}, no robot available", report.id),
+                    Vec::new(),
+                )
+            }
+        };
+        self.decisions.write().await.record(decision);
+        Ok(())
+    }
+
+    /// Release alerts whose triage timed out, untriaged
+    pub async fn expire_triage(&self) -> Result<()> {
+        let expired = self.triage.write().await.expire(self.now_ms());
+        for report in expired {
+            warn!(anomaly_id = %report.id, "Triage timed out, releasing alert untriaged");
+            self.release_triaged(report).await?;
+        }
+        Ok(())
+    }
+
+    /// Start rolling `config` out to the robots matching `selector`, one
+    /// batch at a time. Returns the rollout id.
+    pub async fn start_config_rollout(
+        &self,
+        config: aetheris_shared::RobotConfig,
+        selector: rollout::RobotSelector,
+        batch_size: usize,
+        soak_duration: Duration,
+        abort_on_failures: usize,
+    ) -> Result<String> {
+        let fleet = self.fleet.get_all_robots();
+        let plan = RolloutPlan {
+            config,
+            selector,
+            batch_size,
+            soak_duration,
+            abort_on_failures,
+        };
+        let (id, pushes) = self
+            .rollouts
+            .write()
+            .await
+            .start(plan, &fleet, self.now_ms())?;
+        info!(rollout_id = %id, "Configuration rollout started");
+        self.push_configs(pushes).await?;
+        Ok(id)
+    }
+
+    /// Change a zone's mode and move robots out of it if it is now Excluded
+    pub async fn set_zone_mode(
+        &self,
+        zone_id: &str,
+        mode: aetheris_shared::ZoneMode,
+        until: Option<u64>,
+    ) -> Result<()> {
+        let now = self.now_ms();
+        self.zones
+            .write()
+            .await
+            .set_mode(zone_id, mode.clone(), until, now)?;
+        info!(zone_id = %zone_id, mode = ?mode, until = ?until, "Zone mode changed");
+        self.events.write().await.record(SystemEvent::new(
+            SystemEventKind::ZoneModeChanged,
+            Some(zone_id),
+            format!("{:?} until {:?}", mode, until),
+            now,
+        ));
+        let robots = self.fleet.get_all_robots();
+        let evacuations = self.zones.read().await.evacuations(&robots);
+        let mut batch = Vec::with_capacity(evacuations.len());
+        for evacuation in evacuations {
+            warn!(robot_id = %evacuation.robot_id, zone_id = %evacuation.zone_id, "Evacuating robot from excluded zone");
+            let command = Command::MoveTo {
+                target: evacuation.target,
+                speed: None,
+            };
+            batch.push((evacuation.robot_id, command));
+        }
+        self.send_commands(batch).await;
+        Ok(())
+    }
+
+    /// Assign (or reassign) an anomaly to a human responder
+    pub async fn assign_anomaly(
+        &self,
+        anomaly_id: &str,
+        assignee: &str,
+        assigned_by: &str,
+        due_at: u64,
+    ) -> Result<()> {
+        let now = self.now_ms();
+        let change = {
+            let mut anomalies = self.anomalies.write().await;
+            let change = anomalies.assign(anomaly_id, assignee, assigned_by, due_at, now)?;
+            self.persist_anomalies(&mut anomalies);
+            change
+        };
+        let detail = match &change.previous {
+            Some(previous) => format!(
+                "reassigned from {} to {} by {}",
+                previous.assignee, assignee, assigned_by
+            ),
+            None => format!("assigned to {} by {}", assignee, assigned_by),
+        };
+        info!(anomaly_id = %change.primary_id, assignee = %assignee, due_at, "Anomaly assigned");
+        self.events.write().await.record(SystemEvent::new(
+            SystemEventKind::AssignmentChanged,
+            Some(&change.primary_id),
+            detail,
+            now,
+        ));
+        Ok(())
+    }
+
+    /// Record a responder's progress on an assigned anomaly
+    pub async fn update_assignment(
+        &self,
+        anomaly_id: &str,
+        state: aetheris_shared::AssignmentState,
+    ) -> Result<()> {
+        let now = self.now_ms();
+        let change = {
+            let mut anomalies = self.anomalies.write().await;
+            let change = anomalies.update_assignment(anomaly_id, state)?;
+            self.persist_anomalies(&mut anomalies);
+            change
+        };
+        info!(anomaly_id = %change.primary_id, state = ?state, "Assignment updated");
+        self.events.write().await.record(SystemEvent::new(
+            SystemEventKind::AssignmentChanged,
+            Some(&change.primary_id),
+            format!("{} marked {:?}", change.current.assignee, state),
+            now,
+        ));
+        Ok(())
+    }
+
+    /// Move an anomaly along its lifecycle and republish the updated report
+    pub async fn set_anomaly_status(
+        &self,
+        anomaly_id: &str,
+        status: AnomalyStatus,
+        by: &str,
+    ) -> Result<()> {
+        let now = self.now_ms();
+        let report = {
+            let mut anomalies = self.anomalies.write().await;
+            let report = anomalies.set_status(anomaly_id, status, by, now)?;
+            self.persist_anomalies(&mut anomalies);
+            report
+        };
+        info!(anomaly_id = %report.id, status = ?status, by = %by, "Anomaly status changed");
+        if status == AnomalyStatus::Acknowledged {
+            // The environment alarm behind it stops counting as unhandled
+            let mut alarms = self.alarms.write().await;
+            if !alarms.acknowledge(anomaly_id) {
+                alarms.acknowledge(&report.id);
+            }
+        }
+        self.events.write().await.record(SystemEvent::new(
+            SystemEventKind::AnomalyStatusChanged,
+            Some(&report.id),
+            format!("{:?} by {}", status, by),
+            now,
+        ));
+        self.publish_alert(&report).await
+    }
+
+    /// Count a reading that kept an environment alarm active against the
+    /// report it raised, instead of alerting again
+    async fn alarm_occurred(&self, report_id: &str, occurrences: u32, now: u64) {
+        let mut anomalies = self.anomalies.write().await;
+        match anomalies.record_occurrences(report_id, occurrences, now) {
+            Ok(report) => {
+                debug!(anomaly_id = %report.id, occurrences, "Alarm occurrence folded");
+                self.persist_anomalies(&mut anomalies);
+            }
+            // Its report has not come back from the broker yet
+            Err(_) => debug!(anomaly_id = %report_id, occurrences, "Alarm still active"),
+        }
+    }
+
+    /// Resolve an anomaly and republish the closed report; an open
+    /// assignment requires `force`
+    pub async fn resolve_anomaly(
+        &self,
+        anomaly_id: &str,
+        resolution: Resolution,
+        force: bool,
+        by: &str,
+    ) -> Result<()> {
+        let now = self.now_ms();
+        let resolved = {
+            let mut anomalies = self.anomalies.write().await;
+            let resolved = anomalies.resolve(anomaly_id, resolution, force, by, now)?;
+            self.persist_anomalies(&mut anomalies);
+            resolved
+        };
+        let open = resolved
+            .primary
+            .assignment
+            .as_ref()
+            .filter(|a| a.state.is_open());
+        let detail = match &open {
+            Some(assignment) => format!(
+                "{:?} by {}, forced while assigned to {}",
+                resolution, by, assignment.assignee
+            ),
+            None => format!("{:?} by {}", resolution, by),
+        };
+        info!(anomaly_id = %resolved.primary.id, resolution = ?resolution, forced = open.is_some(), "Anomaly resolved");
+        self.events.write().await.record(SystemEvent::new(
+            SystemEventKind::AnomalyResolved,
+            Some(&resolved.primary.id),
+            detail,
+            now,
+        ));
+        self.publish_alert(&resolved.primary).await
+    }
+
+    /// Add a detector to run on every environment reading after those
+    /// already registered
+    pub fn register_detector(&self, detector: Box<dyn AnomalyDetector>) {
+        info!(detector = detector.name(), "Anomaly detector registered");
+        self.lock_detectors().push(detector);
+    }
+
+    /// Names of the registered detectors, in the order they run
+    pub fn detector_names(&self) -> Vec<String> {
+        self.lock_detectors()
+            .iter()
+            .map(|detector| detector.name().to_string())
+            .collect()
+    }
+
+    /// Reports every detector raises on `observation`, with the name of the
+    /// detector that raised each
+    fn run_detectors(&self, observation: &Observation<'_>) -> Vec<(String, AnomalyReport)> {
+        let mut detectors = self.lock_detectors();
+        let mut detected = Vec::new();
+        for detector in detectors.iter_mut() {
+            let reports = detector.observe(observation);
+            let name = detector.name();
+            detected.extend(reports.into_iter().map(|report| (name.to_string(), report)));
+        }
+        detected
+    }
+
+    fn lock_detectors(&self) -> std::sync::MutexGuard<'_, Vec<Box<dyn AnomalyDetector>>> {
+        self.detectors.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn record_history(&self, record: HistoryRecord) {
+        if let Err(e) = self.lock_telemetry_store().record(record) {
+            error!("Failed to store telemetry history: {}", e);
+        }
+    }
+
+    /// Stored telemetry, environment readings and anomalies matching
+    /// `query`, for replaying a time window
+    pub fn telemetry_history(&self, query: &HistoryQuery) -> std::io::Result<Vec<HistoryRecord>> {
+        self.lock_telemetry_store().query(query)
+    }
+
+    /// Drop history older than the retention, returning how many records
+    /// went
+    pub fn prune_telemetry_history(&self) -> usize {
+        let before = self
+            .now_ms()
+            .saturating_sub(self.history_retention.as_millis() as u64);
+        match self.lock_telemetry_store().prune(before) {
+            Ok(pruned) => pruned,
+            Err(e) => {
+                error!("Failed to prune telemetry history: {}", e);
+                0
+            }
+        }
+    }
+
+    fn lock_telemetry_store(&self) -> std::sync::MutexGuard<'_, Box<dyn TelemetryStore>> {
+        self.telemetry_store
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Write the anomalies changed since the last call through to the
+    /// store. Called under the write lock that changed them, so records are
+    /// stored in the order the changes happened.
+    fn persist_anomalies(&self, anomalies: &mut ActiveAnomalies) {
+        let changes = anomalies.take_changes();
+        if changes.is_empty() {
+            return;
+        }
+        let mut store = self.anomaly_store.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = store.write(&changes) {
+            error!("Failed to store anomalies: {}", e);
+        }
+    }
+
+    /// Republish open High and Critical anomalies retained, so a dashboard
+    /// connecting after a restart sees the incident still in progress
+    async fn republish_open_anomalies(&self) -> Result<()> {
+        let open: Vec<AnomalyReport> = self
+            .anomalies
+            .read()
+            .await
+            .all()
+            .into_iter()
+            .map(|anomaly| &anomaly.primary)
+            .filter(|report| report.severity >= SeverityLevel::High && !report.status.is_closed())
+            .cloned()
+            .collect();
+        // Oldest first: the newest of each severity keeps its topic's slot
+        for report in open.iter().rev() {
+            let seq = self.next_sequence(&report.detected_by, MessageClass::Alert);
+            let payload =
+                self.encode_envelope(MqttMessage::new(report.clone(), &report.detected_by, seq))?;
+            if self.config.alert_topics.legacy {
+                self.publish_payload(topics::ALERTS, QoS::AtLeastOnce, true, payload.clone())
+                    .await
+                    .transport("republish open anomaly")?;
+            }
+            self.publish_payload(
+                topics::alerts_severity(report.severity),
+                QoS::AtLeastOnce,
+                true,
+                payload,
+            )
+            .await
+            .transport("republish open anomaly")?;
+        }
+        if !open.is_empty() {
+            info!(count = open.len(), "Open anomalies republished");
+        }
+        Ok(())
+    }
+
+    /// Alert on assignments that passed their due time without being done
+    pub async fn check_overdue_assignments(&self) -> Result<()> {
+        let now = self.now_ms();
+        let alerts = {
+            let mut anomalies = self.anomalies.write().await;
+            let alerts = anomalies.overdue_assignments(now);
+            self.persist_anomalies(&mut anomalies);
+            alerts
+        };
+        for alert in alerts {
+            self.events.write().await.record(SystemEvent::new(
+                SystemEventKind::AssignmentOverdue,
+                Some(&alert.id),
+                alert.description.clone(),
+                now,
+            ));
+            self.publish_alert(&alert).await?;
+        }
+        Ok(())
+    }
+
+    /// Raise the severity of anomalies nobody acknowledged in time and
+    /// republish them. Left to the leader when instances elect one.
+    pub async fn escalate_stale_alerts(&self) -> Result<()> {
+        if !self.is_leader() {
+            return Ok(());
+        }
+        let now = self.now_ms();
+        let escalated = {
+            let mut anomalies = self.anomalies.write().await;
+            let escalated = anomalies.escalate_stale(&self.escalation, now);
+            self.persist_anomalies(&mut anomalies);
+            escalated
+        };
+        for report in escalated {
+            warn!(anomaly_id = %report.id, severity = ?report.severity, escalations = report.escalation_count, "Unacknowledged anomaly escalated");
+            self.metrics.record_alert_escalated();
+            self.events.write().await.record(SystemEvent::new(
+                SystemEventKind::AlertEscalated,
+                Some(&report.id),
+                format!(
+                    "raised to {:?}, escalation {}",
+                    report.severity, report.escalation_count
+                ),
+                now,
+            ));
+            self.publish_alert(&report).await?;
+            if self.escalation.publish_escalations {
+                let seq = self.next_sequence(&self.instance_id, MessageClass::Alert);
+                let payload =
+                    self.encode_envelope(MqttMessage::new(report, &self.instance_id, seq))?;
+                self.publish_payload(topics::ALERT_ESCALATIONS, QoS::AtLeastOnce, false, payload)
+                    .await
+                    .transport("publish alert escalation")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Raise alerts for declared robots not heard from within the grace
+    /// period of their shift
+    pub async fn check_expected_fleet(&self) -> Result<()> {
+        let now = self.now_ms();
+        let alerts = self.expected_fleet.write().await.check(now);
+        for alert in alerts {
+            warn!(anomaly_id = %alert.id, "{}", alert.description);
+            self.events.write().await.record(SystemEvent::new(
+                SystemEventKind::ExpectedRobotMissing,
+                Some(&alert.id),
+                alert.description.clone(),
+                now,
+            ));
+            self.publish_alert(&alert).await?;
+        }
+        Ok(())
+    }
+
+    /// Restore Normal on zones whose mode expired
+    pub async fn expire_zone_modes(&self) {
+        let now = self.now_ms();
+        let expired = self.zones.write().await.expire(now);
+        for zone_id in expired {
+            info!(zone_id = %zone_id, "Zone mode expired, back to normal");
+            self.events.write().await.record(SystemEvent::new(
+                SystemEventKind::ZoneModeChanged,
+                Some(&zone_id),
+                "expired, back to Normal",
+                now,
+            ));
+        }
+    }
+
+    /// Watch the fleet for regressions and advance the active rollout
+    pub async fn drive_rollouts(&self) -> Result<()> {
+        let now = self.now_ms();
+        let mut rollouts = self.rollouts.write().await;
+        if rollouts.active().is_none() {
+            return Ok(());
+        }
+        let mut pushes = Vec::new();
+        for robot in self.fleet.get_all_robots() {
+            pushes.extend(rollouts.observe(&robot, now));
+        }
+        pushes.extend(rollouts.tick(now));
+        drop(rollouts);
+        self.push_configs(pushes).await
+    }
+
+    /// Send rollout pushes under their command ids. A push that cannot be
+    /// sent fails its robot at once, which may bring reverts to send in
+    /// turn. The rollout's new steps are then recorded as events.
+    async fn push_configs(&self, mut pushes: Vec<ConfigPush>) -> Result<()> {
+        while !pushes.is_empty() {
+            let command_ids: HashMap<String, String> = pushes
+                .iter()
+                .map(|push| (push.robot_id.clone(), push.command_id.clone()))
+                .collect();
+            let batch = pushes
+                .into_iter()
+                .map(|push| {
+                    let command = Command::Configure {
+                        config: push.config,
+                    };
+                    (push.robot_id, command)
+                })
+                .collect();
+            let publisher = ConfigPushes {
+                mqtt: self,
+                command_ids: &command_ids,
+            };
+            let report = fanout::fan_out(&publisher, batch, &self.fanout).await;
+
+            let now = self.now_ms();
+            let mut rollouts = self.rollouts.write().await;
+            pushes = Vec::new();
+            for (robot_id, outcome) in &report.outcomes {
+                let reason = match outcome {
+                    CommandOutcome::Published | CommandOutcome::Queued => continue,
+                    CommandOutcome::Rejected(reason) | CommandOutcome::Failed(reason) => {
+                        reason.as_str()
+                    }
+                    CommandOutcome::RateLimited => "rate limited",
+                };
+                warn!(robot_id = %robot_id, reason, "Rollout configuration not delivered");
+                pushes.extend(rollouts.on_undelivered(
+                    robot_id,
+                    &command_ids[robot_id],
+                    reason,
+                    now,
+                ));
+            }
+        }
+        self.record_rollout_events().await;
+        Ok(())
+    }
+
+    async fn record_rollout_events(&self) {
+        let steps = self.rollouts.write().await.take_events();
+        let mut events = self.events.write().await;
+        for (rollout_id, step) in steps {
+            let (kind, subject, detail) = match step.kind {
+                RolloutEventKind::Started { robots, batches } => (
+                    SystemEventKind::RolloutStarted,
+                    rollout_id,
+                    format!("{robots} robots in {batches} batches"),
+                ),
+                RolloutEventKind::BatchPassed { batch } => (
+                    SystemEventKind::RolloutBatchPassed,
+                    rollout_id,
+                    format!("batch {} passed its soak", batch + 1),
+                ),
+                RolloutEventKind::Halted { failures } => (
+                    SystemEventKind::RolloutHalted,
+                    rollout_id,
+                    format!("halted after {failures} failures"),
+                ),
+                RolloutEventKind::RevertPushed { robot_id } => (
+                    SystemEventKind::RolloutReverted,
+                    robot_id,
+                    format!("configuration of rollout {rollout_id} reverted"),
+                ),
+                RolloutEventKind::Completed => (
+                    SystemEventKind::RolloutCompleted,
+                    rollout_id,
+                    "every batch passed".to_string(),
+                ),
+                _ => continue,
+            };
+            events.record(SystemEvent::new(
+                kind,
+                Some(&subject),
+                detail,
+                step.timestamp,
+            ));
+        }
+    }
+
+    /// Generate an alert based on a command, at a random point of the
+    /// pipeline
+    pub async fn generate_alert_for_command(&self, command: &Command, source: &str) -> Result<()> {
+        let (position, section_id) = self.random_pipeline_point();
+        let alert = match command {
+            Command::EmergencyStop => Some(AnomalyReport::new(
+                AnomalyType::Leak,
+                SeverityLevel::Critical,
+                position,
+                section_id,
+                source,
+                0.96,
+                "EMERGENCY: Hydrogen leak detected! All units halted.",
+            )),
+            Command::Investigate { anomaly_id } => Some(AnomalyReport::new(
+                AnomalyType::PressureDrop,
+                SeverityLevel::High,
+                position,
+                section_id,
+                source,
+                0.89,
+                format!("Pressure anomaly {} under investigation", anomaly_id),
+            )),
+            Command::PerformScan { scan_type } => {
+                let (anomaly_type, severity, desc) = match scan_type {
+                    aetheris_shared::ScanType::Thermal => (
+                        AnomalyType::TemperatureAnomaly,
+                        SeverityLevel::Medium,
+                        "Temperature spike detected during thermal scan",
+                    ),
+                    aetheris_shared::ScanType::Ultrasonic => (
+                        AnomalyType::WallThinning,
+                        SeverityLevel::High,
+                        "Wall thickness below threshold detected",
+                    ),
+                    aetheris_shared::ScanType::LeakDetection => (
+                        AnomalyType::Leak,
+                        SeverityLevel::High,
+                        "Potential leak signature detected",
+                    ),
+                    _ => (
+                        AnomalyType::Unknown,
+                        SeverityLevel::Info,
+                        "Scan completed - no anomalies",
+                    ),
+                };
+                Some(AnomalyReport::new(
+                    anomaly_type,
+                    severity,
+                    position,
+                    section_id,
+                    source,
+                    0.85 + (rand::random::<f64>() * 0.1),
+                    desc,
+                ))
+            }
+            Command::InjectFault { fault_type } => {
+                let (anomaly_type, severity, desc) = match fault_type {
+                    FaultType::LowBattery => (
+                        AnomalyType::Unknown,
+                        SeverityLevel::Medium,
+                        format!("Robot {} reporting critical battery level", source),
+                    ),
+                    FaultType::SensorFailure => (
+                        AnomalyType::Unknown,
+                        SeverityLevel::High,
+                        format!("Sensor malfunction detected on {}", source),
+                    ),
+                    FaultType::CommDropout => (
+                        AnomalyType::Unknown,
+                        SeverityLevel::Critical,
+                        format!("Communication lost with {}", source),
+                    ),
+                    FaultType::MotorFailure => (
+                        AnomalyType::StructuralDamage,
+                        SeverityLevel::High,
+                        format!("Motor failure reported by {}", source),
+                    ),
+                    FaultType::GpsDrift => (
+                        AnomalyType::Unknown,
+                        SeverityLevel::Low,
+                        format!("GPS accuracy degraded on {}", source),
+                    ),
+                };
+                Some(AnomalyReport::new(
+                    anomaly_type,
+                    severity,
+                    Position::new(rand_coord(), 0.0, rand_coord()),
+                    SYSTEM_SECTION,
+                    source,
+                    0.99,
+                    desc,
+                ))
+            }
+            _ => None,
+        };
+
+        if let Some(report) = alert {
+            self.publish_alert(&report).await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl CommandPublisher for AetherisMqtt {
+    async fn publish(&self, robot_id: &str, command: Command) -> Result<Delivery, PublishError> {
+        self.dispatch(robot_id, &acks::new_command_id(), command)
+            .await
+    }
+}
+
+/// Sends each robot its rollout configuration under the id the rollout
+/// expects the answer to
+struct ConfigPushes<'a> {
+    mqtt: &'a AetherisMqtt,
+    command_ids: &'a HashMap<String, String>,
+}
+
+impl CommandPublisher for ConfigPushes<'_> {
+    async fn publish(&self, robot_id: &str, command: Command) -> Result<Delivery, PublishError> {
+        let command_id = self.command_ids[robot_id].as_str();
+        self.mqtt.dispatch(robot_id, command_id, command).await
+    }
+}
+
+/// Sends each robot its share of a narrowed broadcast, under an id derived
+/// from the broadcast's
+struct BroadcastShare<'a> {
+    mqtt: &'a AetherisMqtt,
+    command_id: &'a str,
+}
+
+impl CommandPublisher for BroadcastShare<'_> {
+    async fn publish(&self, robot_id: &str, command: Command) -> Result<Delivery, PublishError> {
+        let command_id = broadcast::share_id(self.command_id, robot_id);
+        self.mqtt.dispatch(robot_id, &command_id, command).await
+    }
+}
+
+impl MissionDriver for AetherisMqtt {
+    async fn execute(&self, robot_id: &str, command: Command) -> Result<CommandResponse, AckError> {
+        self.send_command_and_wait(robot_id, command).await
+    }
+
+    fn robot_state(&self, robot_id: &str) -> Option<RobotState> {
+        self.fleet.get_robot(robot_id)
+    }
+
+    async fn stop(&self, robot_id: &str) {
+        if let Err(e) = self.send_command(robot_id, Command::Stop).await {
+            error!(robot_id = %robot_id, "Failed to stop the robot of an aborted mission: {}", e);
+        }
+    }
+
+    async fn report(&self, status: &MissionStatus) {
+        if let Err(e) = self.publish_mission_status(status).await {
+            error!(mission_id = %status.mission_id, "Failed to publish mission status: {}", e);
+        }
+    }
+
+    async fn answer(&self, response: &CommandResponse) {
+        if let Err(e) = self.publish_response(response).await {
+            error!(command_id = %response.command_id, "Failed to answer mission command: {}", e);
+        }
+    }
+}
+
+// ============================================================================
+// SIMULATION: MOCK ROBOT FLEET
+// ============================================================================
+
+/// Creates a set of simulated robots for testing
+pub fn create_mock_fleet() -> Vec<RobotState> {
+    vec![
+        RobotState {
+            id: "RV-001".parse().unwrap(),
+            name: "Rover Alpha".into(),
+            robot_type: RobotType::Rover,
+            position: Position::new(-2.0, 0.0, 1.0),
+            velocity: Velocity::new(1.2, 0.0, 0.0),
+            orientation: Orientation::identity(),
+            battery: 87.0,
+            signal: 95.0,
+            health: HealthStatus::Optimal,
+            status: RobotStatus::Active,
+            current_task: CurrentTask::Patrolling {
+                route_id: "ROUTE-A1".into(),
+            },
+            timestamp: Timestamp::now(),
+        },
+        RobotState {
+            id: "RV-002".parse().unwrap(),
+            name: "Rover Beta".into(),
+            robot_type: RobotType::Rover,
+            position: Position::new(2.0, 0.0, -1.0),
+            velocity: Velocity::new(0.8, 0.0, 0.0),
+            orientation: Orientation::identity(),
+            battery: 62.0,
+            signal: 78.0,
+            health: HealthStatus::Warning,
+            status: RobotStatus::Active,
+            current_task: CurrentTask::Scanning {
+                scan_type: aetheris_shared::ScanType::LeakDetection,
+            },
+            timestamp: Timestamp::now(),
+        },
+        RobotState {
+            id: "DR-001".parse().unwrap(),
+            name: "Drone Hawk".into(),
+            robot_type: RobotType::Drone,
+            position: Position::new(1.0, 3.0, 0.0),
+            velocity: Velocity::new(8.5, 0.0, 0.0),
+            orientation: Orientation::identity(),
+            battery: 94.0,
+            signal: 99.0,
+            health: HealthStatus::Optimal,
+            status: RobotStatus::Active,
+            current_task: CurrentTask::Patrolling {
+                route_id: "ROUTE-AIR-1".into(),
+            },
+            timestamp: Timestamp::now(),
+        },
+        RobotState {
+            id: "CR-001".parse().unwrap(),
+            name: "Crawler Alpha".into(),
+            robot_type: RobotType::Crawler,
+            position: Position::new(0.0, -0.5, 5.0), // Inside pipeline
+            velocity: Velocity::new(0.3, 0.0, 0.0),
+            orientation: Orientation::identity(),
+            battery: 71.0,
+            signal: 65.0, // Lower signal inside pipe
+            health: HealthStatus::Optimal,
+            status: RobotStatus::Active,
+            current_task: CurrentTask::Scanning {
+                scan_type: aetheris_shared::ScanType::Ultrasonic,
+            },
+            timestamp: Timestamp::now(),
+        },
+        RobotState {
+            id: "CR-002".parse().unwrap(),
+            name: "Crawler Beta".into(),
+            robot_type: RobotType::Crawler,
+            position: Position::new(3.0, -0.5, 8.0),
+            velocity: Velocity::zero(),
+            orientation: Orientation::identity(),
+            battery: 23.0,
+            signal: 45.0,
+            health: HealthStatus::Critical,
+            status: RobotStatus::Maintenance,
+            current_task: CurrentTask::ReturningToBase,
+            timestamp: Timestamp::now(),
+        },
+    ]
+}
+
+/// Patrol routes driven by the simulated fleet's patrolling robots
+pub fn create_mock_routes() -> Vec<PatrolRoute> {
+    vec![
+        PatrolRoute::new(
+            "ROUTE-A1",
+            "East perimeter",
+            RouteMode::Loop,
+            vec![
+                Waypoint::at(Position::new(-2.0, 0.0, 1.0)),
+                Waypoint {
+                    position: Position::new(6.0, 0.0, 1.0),
+                    dwell_secs: Some(3.0),
+                    scan: Some(aetheris_shared::ScanType::LeakDetection),
+                },
+                Waypoint::at(Position::new(6.0, 4.0, 1.0)),
+                Waypoint::at(Position::new(-2.0, 4.0, 1.0)),
+            ],
+        ),
+        PatrolRoute::new(
+            "ROUTE-AIR-1",
+            "Overhead sweep",
+            RouteMode::Loop,
+            vec![
+                Waypoint::at(Position::new(1.0, 3.0, 0.0)),
+                Waypoint::at(Position::new(1.0, 3.0, 12.0)),
+                Waypoint {
+                    position: Position::new(30.0, 3.0, 12.0),
+                    dwell_secs: Some(2.0),
+                    scan: Some(aetheris_shared::ScanType::Thermal),
+                },
+                Waypoint::at(Position::new(30.0, -10.0, 12.0)),
+                Waypoint::at(Position::new(1.0, -10.0, 12.0)),
+            ],
+        ),
+    ]
+}
+
+/// The demo pipeline under the simulated site: a trunk with a branch at
+/// its junction, running past the crawlers and their charging station
+pub fn create_mock_pipeline() -> Vec<PipelineSection> {
+    let section = |id: &str, start: Position, end: Position, neighbors: &[&str]| PipelineSection {
+        id: id.into(),
+        start,
+        end,
+        diameter_mm: 508.0,
+        material: PipeMaterial::CarbonSteel,
+        design_pressure_bar: 70.0,
+        neighbors: neighbors.iter().map(|n| n.to_string()).collect(),
+    };
+    vec![
+        section(
+            "PIPE-001",
+            Position::new(-30.0, -0.5, 5.0),
+            Position::new(0.0, -0.5, 5.0),
+            &[],
+        ),
+        section(
+            "PIPE-002",
+            Position::new(0.0, -0.5, 5.0),
+            Position::new(30.0, -0.5, 5.0),
+            &["PIPE-001"],
+        ),
+        section(
+            "PIPE-003",
+            Position::new(0.0, -0.5, 5.0),
+            Position::new(0.0, -0.5, 8.0),
+            &["PIPE-001", "PIPE-002"],
+        ),
+        section(
+            "PIPE-004",
+            Position::new(0.0, -0.5, 8.0),
+            Position::new(12.0, -0.5, 8.0),
+            &["PIPE-003"],
+        ),
+    ]
+}
+
+/// Charging stations the simulated robots return to when their battery runs
+/// low
+pub fn create_mock_stations() -> Vec<ChargingStation> {
+    vec![
+        ChargingStation::new("CHG-01", Position::new(-5.0, 0.0, 1.0), 2),
+        ChargingStation::new("CHG-02", Position::new(5.0, -0.5, 8.0), 1),
+    ]
+}
+
+// ============================================================================
+// HEARTBEAT MONITOR TASK
+// ============================================================================
+
+/// Spawns a background task to monitor robot heartbeats. Only the leader
+/// marks robots offline; followers learn of it from the leader's effects.
+pub async fn spawn_heartbeat_monitor(
+    fleet: Arc<FleetManager>,
+    events: Arc<RwLock<EventLog>>,
+    leadership: Leadership,
+    mut shutdown: Shutdown,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let clock = fleet.clock();
+        let mut check_interval = clock.interval(Duration::from_secs(5));
+
+        loop {
+            tokio::select! {
+                _ = check_interval.tick() => {}
+                _ = shutdown.wait() => return,
+            }
+            if !leadership.is_leader() {
+                continue;
+            }
+
+            for robot_id in fleet.mark_timed_out() {
+                warn!(robot_id = %robot_id, "Robot heartbeat timeout - marking offline");
+                events.write().await.record(SystemEvent::new(
+                    SystemEventKind::RobotOffline,
+                    Some(&robot_id),
+                    "heartbeat timeout",
+                    clock.now().as_millis(),
+                ));
+            }
+        }
+    })
+}
+
+/// Spawns a background task that publishes the system status every
+/// `mqtt.status_interval` while the broker connection is up. The final
+/// offline status is left to [`AetherisMqtt::shutdown`].
+pub async fn spawn_status_publisher(
+    mqtt: Arc<AetherisMqtt>,
+    mut shutdown: Shutdown,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut status_interval = interval(mqtt.config().status_interval);
+
+        loop {
+            tokio::select! {
+                _ = status_interval.tick() => {}
+                _ = shutdown.wait() => return,
+            }
+            if mqtt.connection_state() != ConnectionState::Connected {
+                continue;
+            }
+
+            let status = mqtt.system_status().await;
+            if let Err(e) = mqtt.publish_system_status(&status).await {
+                error!("Failed to publish system status: {}", e);
+            }
+        }
+    })
+}
+
+/// Spawns a background task that publishes changed section health reports
+/// every `section_health.publish_interval` while the broker connection is up
+pub async fn spawn_section_health_publisher(
+    mqtt: Arc<AetherisMqtt>,
+    mut shutdown: Shutdown,
+) -> JoinHandle<()> {
+    let period = mqtt.section_health.read().await.config().publish_interval;
+    tokio::spawn(async move {
+        let mut publish_interval = interval(period);
+
+        loop {
+            tokio::select! {
+                _ = publish_interval.tick() => {}
+                _ = shutdown.wait() => return,
+            }
+            if mqtt.connection_state() != ConnectionState::Connected {
+                continue;
+            }
+
+            if let Err(e) = mqtt.publish_section_health_changes().await {
+                error!("Failed to publish section health: {}", e);
+            }
+        }
+    })
+}
+
+/// Spawn the task publishing queued commands, most urgent first
+pub fn spawn_command_dispatcher(mqtt: Arc<AetherisMqtt>, mut shutdown: Shutdown) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = mqtt.commands_queued.notified() => {}
+                _ = shutdown.wait() => return,
+            }
+            mqtt.drain_command_queue().await;
+        }
+    })
+}
+
+/// Binds the metrics endpoint on `addr` and spawns the task serving it
+pub async fn spawn_metrics_endpoint(
+    mqtt: Arc<AetherisMqtt>,
+    addr: SocketAddr,
+    shutdown: Shutdown,
+) -> std::io::Result<JoinHandle<()>> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    let scrape = move || {
+        let mqtt = mqtt.clone();
+        async move { mqtt.render_metrics().await }
+    };
+    Ok(tokio::spawn(metrics::serve(listener, scrape, shutdown)))
+}
+
+/// Spawns a background task that periodically lists provisional sections
+pub async fn spawn_section_report(
+    sections: Arc<RwLock<SectionRegistry>>,
+    period: Duration,
+    mut shutdown: Shutdown,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut report_interval = interval(period);
+
+        loop {
+            tokio::select! {
+                _ = report_interval.tick() => {}
+                _ = shutdown.wait() => return,
+            }
+
+            let registry = sections.read().await;
+            let provisional = registry.provisional_report();
+            if provisional.is_empty() {
+                continue;
+            }
+
+            let ids: Vec<&str> = provisional.iter().map(|s| s.id.as_str()).collect();
+            warn!(
+                count = ids.len(),
+                "Provisional pipeline sections need topology review: {}",
+                ids.join(", ")
+            );
+        }
+    })
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alarms::{AlarmStatus, HazardKind};
+    use crate::anomaly_store::AnomalyStoreConfig;
+    use crate::authorization::AuthorizationConfig;
+    use crate::battery_manager::BatteryManagerConfig;
+    use crate::clock::{ClockConfig, VirtualClock};
+    use crate::dead_letters::DeadLetterConfig;
+    use crate::decision::{Decision, PolicyKind};
+    use crate::geofences::{FenceAction, GeofenceConfig};
+    use crate::leader::LeaderConfig;
+    use crate::source_binding::{SourceBindings, SourceRule};
+    use crate::source_signing::SourceSigningConfig;
+    use aetheris_shared::{Geofence, Operator, OperatorRole, SigningKey};
+    use std::collections::BTreeMap;
+
+    fn robot(id: &str, position: Position, battery: f64) -> RobotState {
+        RobotState {
+            position,
+            battery,
+            ..RobotState::new(id.parse().unwrap(), id, RobotType::Rover)
+        }
+    }
+
+    #[test]
+    fn test_dispatch_trace_lists_excluded_nearest_robot() {
+        let fleet = FleetManager::new(Duration::from_secs(15));
+        fleet.update_robot(robot("RV-002", Position::new(1.0, 0.0, 0.0), 8.0));
+        fleet.update_robot(robot("CR-001", Position::new(4.0, 0.0, 0.0), 70.0));
+        fleet.update_robot(robot("DR-001", Position::new(9.0, 0.0, 0.0), 90.0));
+
+        let candidates =
+            fleet.evaluate_dispatch_candidates(&Position::origin(), 20.0, &ZoneRegistry::default());
+        let decision = Decision::new(PolicyKind::AutoDispatch, "ANM-1", candidates, 0);
+
+        let nearest = decision
+            .candidates
+            .iter()
+            .find(|c| c.robot_id == "RV-002")
+            .unwrap();
+        assert_eq!(nearest.excluded, Some(ExclusionReason::LowBattery));
+        assert_eq!(nearest.score, None);
+
+        let winner = decision.winner().unwrap();
+        assert_eq!(winner.robot_id, "CR-001");
+        assert!((winner.score.unwrap() - 0.2).abs() < 1e-9);
+        assert_eq!(decision.candidates[0].robot_id, "CR-001");
+        assert_eq!(decision.candidates[1].robot_id, "DR-001");
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_event_links_its_decision() {
+        let (tx, _rx) = mpsc::channel(10);
+        let (mqtt, _eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+        mqtt.fleet
+            .update_robot(robot("RV-001", Position::new(3.0, 0.0, 0.0), 90.0));
+        let report = AnomalyReport::new(
+            AnomalyType::Leak,
+            SeverityLevel::High,
+            Position::origin(),
+            "PIPE-001",
+            "RV-002",
+            0.9,
+            "Leak",
+        );
+
+        mqtt.auto_dispatch(&report).await.unwrap();
+        let events = mqtt.events();
+        let events = events.read().await;
+        let dispatched = events
+            .entries()
+            .find(|event| event.kind == SystemEventKind::RobotDispatched)
+            .unwrap();
+        let decision_id = dispatched.decision_id.as_deref().unwrap();
+        let decisions = mqtt.decisions();
+        let decisions = decisions.read().await;
+        let decision = decisions.get(decision_id).unwrap();
+        assert_eq!(decision.policy, PolicyKind::AutoDispatch);
+        assert_eq!(decision.trigger, report.id);
+        assert_eq!(decision.command_ids.first(), dispatched.command_id.as_ref());
+    }
+
+    #[tokio::test]
+    async fn test_decisions_are_stamped_on_the_engine_clock() {
+        let (tx, _rx) = mpsc::channel(10);
+        let config = EngineConfig {
+            clock: ClockConfig {
+                time_scale: 3_600.0,
+            },
+            ..EngineConfig::default()
+        };
+        let (mqtt, _eventloop) = AetherisMqtt::from_engine_config(config, tx).await.unwrap();
+        mqtt.fleet
+            .update_robot(robot("RV-001", Position::new(3.0, 0.0, 0.0), 90.0));
+        let report = AnomalyReport::new(
+            AnomalyType::Leak,
+            SeverityLevel::High,
+            Position::origin(),
+            "PIPE-001",
+            "RV-002",
+            0.9,
+            "Leak",
+        );
+        // Ten real milliseconds are 36 simulated seconds
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        mqtt.auto_dispatch(&report).await.unwrap();
+        let events = mqtt.events();
+        let events = events.read().await;
+        let dispatched = events
+            .entries()
+            .find(|event| event.kind == SystemEventKind::RobotDispatched)
+            .unwrap();
+        let decisions = mqtt.decisions();
+        let decisions = decisions.read().await;
+        let decision = decisions
+            .get(dispatched.decision_id.as_deref().unwrap())
+            .unwrap();
+        assert_eq!(decision.timestamp, dispatched.timestamp);
+        assert!(decision.timestamp > aetheris_shared::current_timestamp_ms() + 30_000);
+        assert!(
+            decision
+                .id
+                .starts_with(&format!("DEC-{:X}-", decision.timestamp))
+        );
+    }
+
+    #[test]
+    fn test_summary_counts_expected_missing_and_unexpected() {
+        let fleet = FleetManager::new(Duration::from_secs(30));
+        fleet.register_expected(&"RV-001".parse().unwrap(), RobotType::Rover);
+        fleet.register_expected(&"CR-001".parse().unwrap(), RobotType::Crawler);
+        assert_eq!(
+            fleet.get_robot("CR-001").unwrap().status,
+            RobotStatus::Offline
+        );
+        assert!(fleet.get_timed_out_robots().is_empty());
+
+        fleet.update_robot(robot("RV-001", Position::origin(), 90.0));
+        fleet.update_robot(robot("RV-002", Position::origin(), 90.0));
+        // Re-declaring keeps the live state
+        fleet.register_expected(&"RV-001".parse().unwrap(), RobotType::Rover);
+
+        assert_eq!(
+            fleet.summary(),
+            FleetSummary {
+                total: 3,
+                idle: 2,
+                offline: 1,
+                expected: 2,
+                expected_missing: 1,
+                unexpected: 1,
+                ..FleetSummary::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_fleet_queries_on_empty_and_offline_fleets() {
+        let fleet = FleetManager::new(Duration::from_secs(30));
+        let target = Position::origin();
+        assert!(fleet.nearest_robot(&target, None).is_none());
+        assert!(
+            fleet
+                .find_candidates(&target, RobotType::Rover, 0.0)
+                .is_empty()
+        );
+        assert!(fleet.robot_count_by_status().values().all(|&n| n == 0));
+
+        fleet.register_expected(&"RV-001".parse().unwrap(), RobotType::Rover);
+        fleet.register_expected(&"RV-002".parse().unwrap(), RobotType::Rover);
+        assert_eq!(
+            fleet
+                .nearest_robot(&target, Some(RobotType::Rover))
+                .unwrap()
+                .id,
+            "RV-001"
+        );
+        assert!(
+            fleet
+                .find_candidates(&target, RobotType::Rover, 0.0)
+                .is_empty()
+        );
+        assert_eq!(fleet.robot_count_by_status()[&RobotStatus::Offline], 2);
+        assert_eq!(fleet.robot_count_by_status()[&RobotStatus::Idle], 0);
+        assert_eq!(fleet.robots_with_status(RobotStatus::Offline).len(), 2);
+        assert!(fleet.robots_by_type(RobotType::Drone).is_empty());
+    }
+
+    #[test]
+    fn test_candidates_sorted_by_distance_with_ties_by_id() {
+        let fleet = FleetManager::new(Duration::from_secs(30));
+        fleet.update_robot(robot("RV-003", Position::new(0.0, 3.0, 0.0), 90.0));
+        fleet.update_robot(robot("RV-002", Position::new(-3.0, 0.0, 0.0), 90.0));
+        fleet.update_robot(robot("RV-001", Position::new(5.0, 0.0, 0.0), 90.0));
+        fleet.update_robot(robot("RV-004", Position::new(1.0, 0.0, 0.0), 10.0));
+        fleet.update_robot(robot("RV-005", Position::new(f64::NAN, 0.0, 0.0), 90.0));
+        let mut busy = robot("RV-006", Position::new(0.5, 0.0, 0.0), 90.0);
+        busy.status = RobotStatus::Maintenance;
+        fleet.update_robot(busy);
+        fleet.update_robot(RobotState {
+            position: Position::new(0.1, 0.0, 0.0),
+            ..RobotState::new("DR-001".parse().unwrap(), "DR-001", RobotType::Drone)
+        });
+
+        let ids = |robots: Vec<RobotState>| -> Vec<String> {
+            robots.into_iter().map(|r| r.id.to_string()).collect()
+        };
+        assert_eq!(
+            ids(fleet.find_candidates(&Position::origin(), RobotType::Rover, 20.0)),
+            ["RV-002", "RV-003", "RV-001"]
+        );
+        assert_eq!(
+            fleet.nearest_robot(&Position::origin(), None).unwrap().id,
+            "DR-001"
+        );
+        assert_eq!(
+            fleet
+                .nearest_robot(&Position::origin(), Some(RobotType::Rover))
+                .unwrap()
+                .id,
+            "RV-006"
+        );
+        assert!(
+            fleet
+                .nearest_robot(&Position::new(f64::NAN, 0.0, 0.0), None)
+                .is_none()
+        );
+        assert_eq!(fleet.robots_by_type(RobotType::Rover).len(), 6);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_robot_back_from_timeout_recovers_and_flapping_is_flagged() {
+        let fleet = FleetManager::new(Duration::from_secs(15)).with_flap_detection(FlapConfig {
+            max_reconnects: 2,
+            window: Duration::from_secs(600),
+        });
+        fleet.update_robot(RobotState {
+            status: RobotStatus::Maintenance,
+            ..RobotState::new("RV-001".parse().unwrap(), "Rover", RobotType::Rover)
+        });
+
+        tokio::time::advance(Duration::from_secs(16)).await;
+        assert_eq!(fleet.get_timed_out_robots(), ["RV-001"]);
+        assert!(fleet.mark_offline("RV-001"));
+        // Marked once, not on every check
+        assert!(fleet.get_timed_out_robots().is_empty());
+        assert!(!fleet.mark_offline("RV-001"));
+
+        tokio::time::advance(Duration::from_secs(4)).await;
+        let back = fleet.record_heartbeat("RV-001").unwrap();
+        assert_eq!(back.offline_for, Duration::from_secs(4));
+        assert_eq!(back.flapping, None);
+        let robot = fleet.get_robot("RV-001").unwrap();
+        // Back to what it was doing, not to Active
+        assert_eq!(robot.status, RobotStatus::Maintenance);
+        assert_eq!(robot.health, HealthStatus::Optimal);
+        assert!(fleet.record_heartbeat("RV-001").is_none());
+
+        // Two more drops within the window: flapping
+        let mut flapping = None;
+        for _ in 0..2 {
+            tokio::time::advance(Duration::from_secs(16)).await;
+            assert!(fleet.mark_offline("RV-001"));
+            tokio::time::advance(Duration::from_secs(1)).await;
+            flapping = fleet.record_heartbeat("RV-001").unwrap().flapping;
+        }
+        assert_eq!(flapping, Some(3));
+        let robot = fleet.get_robot("RV-001").unwrap();
+        assert_eq!(robot.status, RobotStatus::Maintenance);
+        assert_eq!(robot.health, HealthStatus::Warning);
+
+        // Telemetry does not clear the warning while the robot flaps
+        fleet.update_robot(RobotState::new(
+            "RV-001".parse().unwrap(),
+            "Rover",
+            RobotType::Rover,
+        ));
+        assert_eq!(
+            fleet.get_robot("RV-001").unwrap().health,
+            HealthStatus::Warning
+        );
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_deadlines_run_on_the_engine_clock() {
+        let clock = Arc::new(VirtualClock::new(Timestamp::from_millis(1_000)));
+        let fleet = FleetManager::new(Duration::from_secs(15)).with_clock(clock.clone());
+        fleet.update_robot(RobotState::new(
+            "RV-001".parse().unwrap(),
+            "Rover",
+            RobotType::Rover,
+        ));
+
+        // A heartbeat every 10 simulated seconds keeps the robot online, however
+        // little real time passes
+        for _ in 0..6 {
+            clock.advance(Duration::from_secs(10));
+            assert!(fleet.mark_timed_out().is_empty());
+            fleet.record_heartbeat("RV-001");
+        }
+        clock.advance(Duration::from_secs(16));
+        assert_eq!(fleet.mark_timed_out(), ["RV-001"]);
+        clock.advance(Duration::from_secs(60));
+        let back = fleet.record_heartbeat("RV-001").unwrap();
+        assert_eq!(back.offline_for, Duration::from_secs(60));
+
+        // A scaled engine stamps what it publishes as simulated
+        let (tx, _rx) = mpsc::channel(10);
+        let config = EngineConfig {
+            clock: ClockConfig { time_scale: 60.0 },
+            ..EngineConfig::default()
+        };
+        let (mqtt, _eventloop) = AetherisMqtt::from_engine_config(config, tx).await.unwrap();
+        assert!(mqtt.clock().is_simulated());
+        assert!(mqtt.system_status().await.simulated);
+        let (tx, _rx) = mpsc::channel(10);
+        let (real, _eventloop) = AetherisMqtt::from_engine_config(EngineConfig::default(), tx)
+            .await
+            .unwrap();
+        assert!(!real.system_status().await.simulated);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_fleet_takes_concurrent_updates_while_scanned() {
+        let fleet = Arc::new(FleetManager::new(Duration::from_secs(15)));
+        let start = std::time::Instant::now();
+        // A second of 60 robots at 10 Hz, from six handlers
+        let writers: Vec<_> = (0..6)
+            .map(|writer| {
+                let fleet = fleet.clone();
+                tokio::spawn(async move {
+                    for step in 0..100 {
+                        let id = format!("RV-{:03}", writer * 10 + step % 10);
+                        fleet.update_robot(RobotState {
+                            battery: (100 - step) as f64,
+                            ..RobotState::new(id.parse().unwrap(), &id, RobotType::Rover)
+                        });
+                        tokio::task::yield_now().await;
+                    }
+                })
+            })
+            .collect();
+        let scanner = {
+            let fleet = fleet.clone();
+            tokio::spawn(async move {
+                for _ in 0..50 {
+                    assert!(fleet.get_timed_out_robots().is_empty());
+                    assert!(fleet.get_all_robots().len() <= 60);
+                    tokio::task::yield_now().await;
+                }
+            })
+        };
+        for writer in writers {
+            writer.await.unwrap();
+        }
+        scanner.await.unwrap();
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        let robots = fleet.get_all_robots();
+        assert_eq!(robots.len(), 60);
+        // Each robot's last update is the one that stuck
+        assert!(robots.iter().all(|robot| robot.battery <= 10.0));
+        assert_eq!(fleet.summary().idle, 60);
+    }
+
+    #[test]
+    fn test_fleet_summary_counts_each_type() {
+        let fleet = FleetManager::new(Duration::from_secs(15));
+        for (id, robot_type) in [
+            ("RV-001", RobotType::Rover),
+            ("RV-002", RobotType::Rover),
+            ("RV-003", RobotType::Rover),
+            ("DR-001", RobotType::Drone),
+        ] {
+            fleet.update_robot(RobotState::new(id.parse().unwrap(), id, robot_type));
+        }
+        fleet.mark_offline("RV-002");
+        fleet.mark_offline("DR-001");
+
+        let summary = fleet.fleet_summary();
+        assert_eq!(
+            summary[&RobotType::Rover],
+            FleetCount {
+                connected: 2,
+                offline: 1
+            }
+        );
+        assert_eq!(
+            summary[&RobotType::Drone],
+            FleetCount {
+                connected: 0,
+                offline: 1
+            }
+        );
+        // Types without robots are listed too
+        assert_eq!(summary[&RobotType::Crawler], FleetCount::default());
+    }
+
+    #[tokio::test]
+    async fn test_system_status_summarizes_fleet_and_traffic() {
+        let (tx, _rx) = mpsc::channel(10);
+        let (mqtt, _eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+        for id in ["RV-001", "CR-001"] {
+            let robot_type = if id.starts_with("RV") {
+                RobotType::Rover
+            } else {
+                RobotType::Crawler
+            };
+            let state = RobotState::new(id.parse().unwrap(), id, robot_type);
+            let payload = serde_json::to_vec(&MqttMessage::new(state, id, 1)).unwrap();
+            let publish = Publish::new(
+                topics::telemetry(&id.parse().unwrap()),
+                QoS::AtLeastOnce,
+                payload,
+            );
+            mqtt.handle_publish(&publish).await.unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let status = mqtt.system_status().await;
+        assert_eq!(status.state, EngineState::Online);
+        assert_eq!(status.connected_robots, 2);
+        assert_eq!(status.fleet[&RobotType::Crawler].connected, 1);
+        assert_eq!(status.unacknowledged_anomalies.values().sum::<usize>(), 0);
+        assert!(status.messages_per_sec > 0.0);
+        // Nothing arrived since
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(mqtt.system_status().await.messages_per_sec, 0.0);
+    }
+
+    #[test]
+    fn test_dispatch_candidates_exclude_unavailable_robots() {
+        let fleet = FleetManager::new(Duration::from_secs(15));
+        let mut offline = robot("RV-001", Position::origin(), 90.0);
+        offline.status = RobotStatus::Offline;
+        fleet.update_robot(offline);
+        fleet.update_robot(robot("RV-003", Position::new(f64::NAN, 0.0, 0.0), 90.0));
+
+        let candidates =
+            fleet.evaluate_dispatch_candidates(&Position::origin(), 20.0, &ZoneRegistry::default());
+        assert!(candidates.iter().all(|c| !c.is_eligible()));
+        assert_eq!(candidates[0].excluded, Some(ExclusionReason::Offline));
+        assert_eq!(
+            candidates[1].excluded,
+            Some(ExclusionReason::InvalidPosition)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_out_of_bounds_telemetry_is_rejected() {
+        let (tx, mut rx) = mpsc::channel(10);
+        let (mqtt, _eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+
+        let mut state = RobotState::new("DR-001".parse().unwrap(), "Drone Hawk", RobotType::Drone);
+        state.position = Position::new(4.7e12, 3.0, 0.0);
+        let payload = serde_json::to_vec(&MqttMessage::new(state, "DR-001", 1)).unwrap();
+        mqtt.handle_incoming(&topics::telemetry(&"DR-001".parse().unwrap()), &payload)
+            .await
+            .unwrap();
+
+        assert_eq!(mqtt.bounds_violations("DR-001"), 1);
+        assert!(mqtt.fleet().get_robot("DR-001").is_none());
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_telemetry_is_dropped_and_quarantined() {
+        let (tx, mut rx) = mpsc::channel(10);
+        let (mqtt, _eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+
+        let mut state = RobotState::new("RV-001".parse().unwrap(), "Rover Alpha", RobotType::Rover);
+        state.battery = -3.0;
+        let payload = serde_json::to_vec(&MqttMessage::new(state, "RV-001", 1)).unwrap();
+        mqtt.handle_incoming(&topics::telemetry(&"RV-001".parse().unwrap()), &payload)
+            .await
+            .unwrap();
+
+        assert_eq!(mqtt.parse_violations("RV-001").invalid, 1);
+        assert!(mqtt.fleet().get_robot("RV-001").is_none());
+        assert!(rx.try_recv().is_err());
+        let events = mqtt.events();
+        let events = events.read().await;
+        let quarantined = events.entries().last().unwrap();
+        assert_eq!(quarantined.kind, SystemEventKind::DeadLetter);
+        assert!(
+            quarantined
+                .detail
+                .contains("battery must be within 0..=100")
+        );
+        assert_eq!(mqtt.error_counts()[&ErrorKind::Validation], 1);
+    }
+
+    #[tokio::test]
+    async fn test_unparseable_payloads_are_quarantined_and_rate_limited() {
+        let (tx, _rx) = mpsc::channel(10);
+        let config = EngineConfig {
+            dead_letters: DeadLetterConfig {
+                burst: 2,
+                sample_every: 5,
+                ..DeadLetterConfig::default()
+            },
+            ..EngineConfig::default()
+        };
+        let (mqtt, _eventloop) = AetherisMqtt::from_engine_config(config, tx).await.unwrap();
+        let rover = topics::telemetry(&"RV-001".parse().unwrap());
+
+        // Firmware emitting garbage
+        for _ in 0..12 {
+            let e = mqtt
+                .handle_incoming(&rover, &[0xff, 0x00, 0x13, 0x37])
+                .await
+                .unwrap_err();
+            assert_eq!(e.kind(), Some(ErrorKind::Serialization));
+        }
+        let letters = mqtt.dead_letters();
+        let suppressed: Vec<u32> = letters.iter().map(|letter| letter.suppressed).collect();
+        assert_eq!(suppressed, [0, 0, 4, 4]);
+        assert_eq!(letters[0].reason, DeadLetterReason::Malformed);
+        assert_eq!(letters[0].claimed_source, None);
+        assert!(letters[0].payload_base64);
+        assert_eq!(letters[0].payload, "/wATNw==");
+        let rendered = mqtt.metrics().render(&HashMap::new());
+        assert!(rendered.contains("aetheris_dead_letters_total{reason=\"malformed\"} 12"));
+        assert!(rendered.contains("aetheris_dead_letters_suppressed_total 8"));
+        assert!(rendered.contains("aetheris_deserialization_failures_total 12"));
+    }
+
+    #[tokio::test]
+    async fn test_sources_with_a_key_must_sign_their_envelopes() {
+        let (tx, _rx) = mpsc::channel(10);
+        let config = EngineConfig {
+            source_signing: SourceSigningConfig {
+                keys: BTreeMap::from([("RV-001".to_string(), Secret::new("rv-001-key"))]),
+            },
+            ..EngineConfig::default()
+        };
+        let (mqtt, _eventloop) = AetherisMqtt::from_engine_config(config, tx).await.unwrap();
+        let telemetry = |robot_id: &str, key: Option<&str>| {
+            let state = RobotState::new(robot_id.parse().unwrap(), robot_id, RobotType::Rover);
+            let mut msg = MqttMessage::new(state, robot_id, 1);
+            if let Some(key) = key {
+                msg.sign(&SigningKey::new(key.as_bytes()));
+            }
+            serde_json::to_vec(&msg).unwrap()
+        };
+        let rover = topics::telemetry(&"RV-001".parse().unwrap());
+
+        for forged in [
+            telemetry("RV-001", None),
+            telemetry("RV-001", Some("guess")),
+        ] {
+            mqtt.handle_incoming(&rover, &forged).await.unwrap();
+        }
+        assert!(mqtt.fleet().get_robot("RV-001").is_none());
+        assert_eq!(mqtt.error_counts()[&ErrorKind::Rejected], 2);
+        let rendered = mqtt.metrics().render(&HashMap::new());
+        assert!(rendered.contains("aetheris_signature_rejections_total 2"));
+        let events = mqtt.events();
+        let quarantined = events.read().await.entries().last().unwrap().clone();
+        assert_eq!(quarantined.kind, SystemEventKind::DeadLetter);
+        assert!(quarantined.detail.contains("does not match"));
+
+        mqtt.handle_incoming(&rover, &telemetry("RV-001", Some("rv-001-key")))
+            .await
+            .unwrap();
+        assert!(mqtt.fleet().get_robot("RV-001").is_some());
+        // No key configured: unsigned envelopes still pass
+        mqtt.handle_incoming(
+            &topics::telemetry(&"RV-002".parse().unwrap()),
+            &telemetry("RV-002", None),
+        )
+        .await
+        .unwrap();
+        assert!(mqtt.fleet().get_robot("RV-002").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_handling_errors_tell_drop_from_shutdown() {
+        let (tx, rx) = mpsc::channel(10);
+        let (mqtt, _eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+
+        let unknown = mqtt
+            .handle_incoming("weather/today", b"{}")
+            .await
+            .unwrap_err();
+        assert_eq!(unknown.kind(), Some(ErrorKind::Topic));
+        let malformed = mqtt
+            .handle_incoming(
+                &topics::telemetry(&"RV-001".parse().unwrap()),
+                b"{\"payload\":",
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(malformed.kind(), Some(ErrorKind::Serialization));
+        assert_eq!(malformed.recovery(), Recovery::Skip);
+
+        // Nobody is left to consume what the engine hears
+        drop(rx);
+        let state = RobotState::new("RV-001".parse().unwrap(), "Rover Alpha", RobotType::Rover);
+        let payload = serde_json::to_vec(&MqttMessage::new(state, "RV-001", 1)).unwrap();
+        let closed = mqtt
+            .handle_incoming(&topics::telemetry(&"RV-001".parse().unwrap()), &payload)
+            .await
+            .unwrap_err();
+        assert_eq!(closed.kind(), Some(ErrorKind::ChannelClosed));
+        assert_eq!(closed.recovery(), Recovery::Shutdown);
+    }
+
+    #[tokio::test]
+    async fn test_stale_retained_telemetry_is_ignored() {
+        let (tx, _rx) = mpsc::channel(10);
+        let (mqtt, _eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+        let retained = |id: &str, age: Duration| {
+            let mut state = RobotState::new(id.parse().unwrap(), id, RobotType::Rover);
+            state.timestamp -= age;
+            let payload = serde_json::to_vec(&MqttMessage::new(state, id, 1)).unwrap();
+            let mut publish = Publish::new(
+                topics::telemetry(&id.parse().unwrap()),
+                QoS::AtLeastOnce,
+                payload,
+            );
+            publish.retain = true;
+            publish
+        };
+
+        let stale = retained("RV-001", DEFAULT_HEARTBEAT_TIMEOUT * 2);
+        mqtt.handle_publish(&stale).await.unwrap();
+        assert!(mqtt.fleet().get_robot("RV-001").is_none());
+
+        mqtt.handle_publish(&retained("RV-002", Duration::from_secs(1)))
+            .await
+            .unwrap();
+        assert!(mqtt.fleet().get_robot("RV-002").is_some());
+        assert_eq!(mqtt.system_status().await.connected_robots, 1);
+
+        // Live delivery of the same message is processed as usual
+        let mut live = stale;
+        live.retain = false;
+        mqtt.handle_publish(&live).await.unwrap();
+        assert!(mqtt.fleet().get_robot("RV-001").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_drifted_robot_clock_is_estimated_and_corrected() {
+        let (tx, _rx) = mpsc::channel(10);
+        let (mqtt, _eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+        let robot_id: RobotId = "RV-001".parse().unwrap();
+        // The robot's clock is 20 s slow: everything it sends looks old
+        let lag = Duration::from_secs(20);
+        let telemetry = |seq: u64, battery: f64, retain: bool| {
+            let mut state = robot("RV-001", Position::origin(), battery);
+            state.timestamp -= lag;
+            let payload = serde_json::to_vec(&MqttMessage::new(state, "RV-001", seq)).unwrap();
+            let mut publish = Publish::new(topics::telemetry(&robot_id), QoS::AtLeastOnce, payload);
+            publish.retain = retain;
+            publish
+        };
+        mqtt.handle_publish(&telemetry(1, 90.0, false))
+            .await
+            .unwrap();
+        mqtt.handle_publish(&telemetry(2, 80.0, true))
+            .await
+            .unwrap();
+        assert_eq!(mqtt.fleet().get_robot("RV-001").unwrap().battery, 90.0);
+
+        for _ in 0..5 {
+            let heartbeat = Heartbeat {
+                timestamp: Timestamp::now() - lag,
+                ..Heartbeat::new(
+                    robot_id.clone(),
+                    RobotType::Rover,
+                    RobotStatus::Active,
+                    90.0,
+                    90.0,
+                    60,
+                )
+            };
+            let payload = serde_json::to_vec(&heartbeat).unwrap();
+            mqtt.handle_incoming(&topics::heartbeat(&robot_id), &payload)
+                .await
+                .unwrap();
+        }
+        let quality = mqtt.fleet().link_quality("RV-001").unwrap();
+        assert!(quality.drifted);
+        assert!((20_000..21_000).contains(&quality.offset_ms), "{quality:?}");
+        assert_eq!(mqtt.system_status().await.link_quality["RV-001"], quality);
+
+        // Corrected for the skew, the retained state is current
+        mqtt.handle_publish(&telemetry(3, 80.0, true))
+            .await
+            .unwrap();
+        assert_eq!(mqtt.fleet().get_robot("RV-001").unwrap().battery, 80.0);
+    }
+
+    #[tokio::test]
+    async fn test_filtered_view_leaves_raw_telemetry_untouched() {
+        let (tx, mut rx) = mpsc::channel(10);
+        let (mqtt, _eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+
+        let mut sent = Vec::new();
+        for (i, x) in [0.0, 1.3, 1.8, 3.4].into_iter().enumerate() {
+            let mut state =
+                RobotState::new("RV-001".parse().unwrap(), "Rover Alpha", RobotType::Rover);
+            state.position = Position::new(x, 0.0, 0.0);
+            state.timestamp = Timestamp::from_millis(1_000_000 + i as u64 * 500);
+            let payload =
+                serde_json::to_vec(&MqttMessage::new(state.clone(), "RV-001", i as u64)).unwrap();
+            mqtt.handle_incoming(&topics::telemetry(&"RV-001".parse().unwrap()), &payload)
+                .await
+                .unwrap();
+            sent.push(state);
+        }
+
+        let last = sent.last().unwrap();
+        let views = mqtt.robot_views(last.timestamp.as_millis()).await;
+        assert_eq!(views[0].state, *last);
+        let filtered = views[0].filtered_position.unwrap();
+        assert_ne!(filtered, last.position);
+        for state in sent {
+            match rx.try_recv() {
+                Ok(EngineMessage::TelemetryReceived(received)) => assert_eq!(received, state),
+                other => panic!("expected telemetry, got {other:?}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_telemetry_batch_fans_out_in_order() {
+        let (tx, mut rx) = mpsc::channel(10);
+        let (mqtt, _eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+
+        let robot = |id: &str, x: f64, timestamp: u64| {
+            let mut state = RobotState::new(id.parse().unwrap(), id, RobotType::Rover);
+            state.position = Position::new(x, 0.0, 0.0);
+            state.timestamp = Timestamp::from_millis(timestamp);
+            state
+        };
+        let mut broken = robot("CR-001", 0.0, 1_000_000);
+        broken.battery = 150.0;
+        let states = vec![
+            robot("RV-001", 1.0, 1_000_000),
+            broken,
+            robot("RV-002", 5.0, 1_000_000),
+            robot("RV-001", 2.0, 1_000_500),
+        ];
+        let batch = TelemetryBatch::new("engine", states.clone());
+        let payload = serde_json::to_vec(&MqttMessage::new(batch, "engine", 0)).unwrap();
+        mqtt.handle_incoming(topics::TELEMETRY_BATCH, &payload)
+            .await
+            .unwrap();
+
+        // The invalid state is dropped alone; RV-001 ends in its later state
+        for expected in [&states[0], &states[2], &states[3]] {
+            match rx.try_recv() {
+                Ok(EngineMessage::TelemetryReceived(received)) => assert_eq!(received, *expected),
+                other => panic!("expected telemetry, got {other:?}"),
+            }
+        }
+        let fleet = mqtt.fleet();
+        assert_eq!(fleet.get_robot("RV-001").unwrap().position.x, 2.0);
+        assert!(fleet.get_robot("CR-001").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_payloads_naming_another_robot_than_their_topic_are_dropped() {
+        let (tx, mut rx) = mpsc::channel(10);
+        let mut source_bindings = SourceBindings::default();
+        source_bindings.rules.push(SourceRule::new(
+            "gateway-north",
+            &[topics::TELEMETRY_BATCH, "aetheris/telemetry/RV-001"],
+        ));
+        let config = EngineConfig {
+            source_bindings,
+            ..EngineConfig::default()
+        };
+        let (mqtt, _eventloop) = AetherisMqtt::from_engine_config(config, tx).await.unwrap();
+        let rover = |id: &str| RobotState::new(id.parse().unwrap(), id, RobotType::Rover);
+        let rv_001: RobotId = "RV-001".parse().unwrap();
+        let mismatches = |mqtt: &AetherisMqtt| {
+            mqtt.dead_letters()
+                .iter()
+                .filter(|letter| letter.reason == DeadLetterReason::SourceMismatch)
+                .count()
+        };
+
+        // RV-001 speaking for RV-002 on its own topic
+        let spoofed = serde_json::to_vec(&MqttMessage::new(rover("RV-002"), "RV-001", 0)).unwrap();
+        mqtt.handle_incoming(&topics::telemetry(&rv_001), &spoofed)
+            .await
+            .unwrap();
+        assert!(mqtt.fleet().get_robot("RV-002").is_none());
+        assert_eq!(mismatches(&mqtt), 1);
+
+        // A batch only carries the robots its source may speak for
+        let batch = TelemetryBatch::new("gateway-north", vec![rover("RV-001"), rover("RV-002")]);
+        let payload = serde_json::to_vec(&MqttMessage::new(batch, "gateway-north", 0)).unwrap();
+        mqtt.handle_incoming(topics::TELEMETRY_BATCH, &payload)
+            .await
+            .unwrap();
+        assert!(mqtt.fleet().get_robot("RV-001").is_some());
+        assert!(mqtt.fleet().get_robot("RV-002").is_none());
+        assert_eq!(mismatches(&mqtt), 2);
+        while rx.try_recv().is_ok() {}
+
+        // A heartbeat for RV-002 on RV-001's topic keeps nobody online
+        let heartbeat = Heartbeat::new(
+            "RV-002".parse().unwrap(),
+            RobotType::Rover,
+            RobotStatus::Active,
+            80.0,
+            90.0,
+            60,
+        );
+        mqtt.handle_incoming(
+            &topics::heartbeat(&rv_001),
+            &serde_json::to_vec(&heartbeat).unwrap(),
+        )
+        .await
+        .unwrap();
+        assert!(rx.try_recv().is_err());
+        assert_eq!(mismatches(&mqtt), 3);
+    }
+
+    #[tokio::test]
+    async fn test_robot_that_can_just_make_it_back_is_returned_to_charge() {
+        let (tx, _rx) = mpsc::channel(10);
+        let config = EngineConfig {
+            battery_manager: BatteryManagerConfig {
+                stations: vec![ChargingStation::new("CHG-01", Position::origin(), 1)],
+                ..BatteryManagerConfig::default()
+            },
+            ..EngineConfig::default()
+        };
+        let (mqtt, _eventloop) = AetherisMqtt::from_engine_config(config, tx).await.unwrap();
+        let topic = topics::telemetry(&"RV-001".parse().unwrap());
+        let mut state = RobotState::new("RV-001".parse().unwrap(), "Rover Alpha", RobotType::Rover);
+        state.status = RobotStatus::Active;
+        state.position = Position::new(100.0, 0.0, 0.0);
+
+        // 100 m out a rover needs 5.33% for the way back and 5% in reserve
+        for (seq, battery) in [(0, 10.5), (1, 10.49), (2, 10.4), (3, 10.3), (4, 10.2)] {
+            state.battery = battery;
+            state.timestamp += Duration::from_secs(1);
+            let payload =
+                serde_json::to_vec(&MqttMessage::new(state.clone(), "RV-001", seq)).unwrap();
+            mqtt.handle_incoming(&topic, &payload).await.unwrap();
+        }
+
+        // Recalled once, not again on every later sample
+        assert_eq!(mqtt.drain_command_queue().await, 1);
+        let awaiting = mqtt.acks().read().await.awaiting();
+        assert_eq!(awaiting[0].variant, "return_to_base");
+        let outlook = mqtt
+            .battery_manager()
+            .read()
+            .await
+            .outlook("RV-001")
+            .cloned()
+            .unwrap();
+        assert!(outlook.recalled);
+        assert_eq!(outlook.station_id.as_deref(), Some("CHG-01"));
+        assert!(outlook.runtime_secs.is_some());
+        let events = mqtt.events();
+        let recalled = events
+            .read()
+            .await
+            .entries()
+            .find(|event| event.kind == SystemEventKind::RobotRecalled)
+            .cloned()
+            .unwrap();
+        assert_eq!(recalled.subject.as_deref(), Some("RV-001"));
+        assert!(recalled.detail.contains("CHG-01"));
+
+        // Traced like a dispatch, and the event points at the trace
+        let decisions = mqtt.decisions();
+        let decisions = decisions.read().await;
+        let recalls = decisions.query(None, Some(PolicyKind::LowBattery));
+        assert_eq!(recalls.len(), 1);
+        assert_eq!(
+            recalled.decision_id.as_deref(),
+            Some(recalls[0].id.as_str())
+        );
+        assert_eq!(recalls[0].trigger, "RV-001");
+        assert_eq!(recalls[0].candidates[0].robot_id, "RV-001");
+        assert_eq!(recalls[0].command_ids, [awaiting[0].command_id.clone()]);
+        assert_eq!(
+            recalls[0].chosen_action.as_deref(),
+            Some("send RV-001 back to CHG-01")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_commands_follow_the_encoding_each_robot_publishes_in() {
+        let (tx, _rx) = mpsc::channel(10);
+        let (mqtt, mut eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+        for (robot_id, encoding) in [
+            ("RV-001", Encoding::MessagePack),
+            ("RV-002", Encoding::Cbor),
+        ] {
+            let state = RobotState::new(robot_id.parse().unwrap(), robot_id, RobotType::Rover);
+            let payload = encoding
+                .encode(&MqttMessage::new(state, robot_id, 1))
+                .unwrap();
+            mqtt.handle_incoming(&topics::telemetry(&robot_id.parse().unwrap()), &payload)
+                .await
+                .unwrap();
+        }
+        assert_eq!(mqtt.command_encoding("RV-001"), Encoding::MessagePack);
+        assert_eq!(mqtt.command_encoding("DR-001"), Encoding::Json);
+
+        for robot_id in ["RV-001", "RV-002", "DR-001"] {
+            mqtt.send_command(robot_id, Command::Stop).await.unwrap();
+        }
+        mqtt.drain_command_queue().await;
+        eventloop.clean();
+        let sent: Vec<(String, Encoding)> = eventloop
+            .pending
+            .drain(..)
+            .filter_map(|request| match request {
+                Request::Publish(publish) if publish.topic.starts_with("aetheris/commands/") => {
+                    let msg: MqttMessage<Command> =
+                        Encoding::decode_detected(&publish.payload).unwrap();
+                    assert_eq!(msg.payload, Command::Stop);
+                    Some((publish.topic, Encoding::detect(&publish.payload)))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            sent,
+            [
+                (
+                    "aetheris/commands/RV-001".to_string(),
+                    Encoding::MessagePack
+                ),
+                ("aetheris/commands/RV-002".to_string(), Encoding::Cbor),
+                ("aetheris/commands/DR-001".to_string(), Encoding::Json),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delta_without_keyframe_requests_one() {
+        let (tx, mut rx) = mpsc::channel(10);
+        let (mqtt, _eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+        let topic = topics::telemetry(&"RV-009".parse().unwrap());
+        let send = |seq: u64, telemetry: TelemetryPayload| {
+            serde_json::to_vec(&MqttMessage::new(telemetry, "RV-009", seq)).unwrap()
+        };
+
+        let keyframe = RobotState::new("RV-009".parse().unwrap(), "Rover Nine", RobotType::Rover);
+        let mut moved = keyframe.clone();
+        moved.position = Position::new(1.5, 0.0, -0.5);
+        moved.timestamp += Duration::from_secs(1);
+        let delta = TelemetryPayload::Delta(moved.diff(&keyframe));
+
+        // Never seen: the deltas are dropped and one keyframe is requested
+        for seq in 0..2 {
+            mqtt.handle_incoming(&topic, &send(seq, delta.clone()))
+                .await
+                .unwrap();
+        }
+        assert!(rx.try_recv().is_err());
+        assert_eq!(mqtt.drain_command_queue().await, 1);
+        let awaiting = mqtt.acks().read().await.awaiting();
+        assert_eq!(awaiting.len(), 1);
+        assert_eq!(awaiting[0].variant, "request_keyframe");
+
+        mqtt.handle_incoming(&topic, &send(2, TelemetryPayload::Full(keyframe.clone())))
+            .await
+            .unwrap();
+        mqtt.handle_incoming(&topic, &send(3, delta)).await.unwrap();
+        for expected in [keyframe, moved] {
+            match rx.try_recv() {
+                Ok(EngineMessage::TelemetryReceived(received)) => assert_eq!(received, expected),
+                other => panic!("expected telemetry, got {other:?}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_commands_are_refused_and_broadcasts_narrowed() {
+        let (tx, _rx) = mpsc::channel(10);
+        let (mqtt, _eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+        for (id, robot_type) in [
+            ("RV-001", RobotType::Rover),
+            ("CR-001", RobotType::Crawler),
+            ("CR-002", RobotType::Crawler),
+        ] {
+            mqtt.fleet
+                .update_robot(RobotState::new(id.parse().unwrap(), id, robot_type));
+        }
+        let ultrasonic = Command::PerformScan {
+            scan_type: aetheris_shared::ScanType::Ultrasonic,
+        };
+
+        let refused = mqtt.publish("RV-001", ultrasonic.clone()).await;
+        assert!(matches!(refused, Err(PublishError::Unsupported(_))));
+        assert!(mqtt.acks().read().await.awaiting().is_empty());
+
+        mqtt.broadcast_command(ultrasonic).await.unwrap();
+        let mut addressed: Vec<_> = mqtt
+            .acks()
+            .read()
+            .await
+            .awaiting()
+            .into_iter()
+            .map(|awaiting| awaiting.robot_id)
+            .collect();
+        addressed.sort();
+        assert_eq!(addressed, ["CR-001", "CR-002"]);
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_commands_are_refused_before_they_are_queued() {
+        let (tx, _rx) = mpsc::channel(10);
+        let (mqtt, mut eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+        let robot_id: RobotId = "CR-001".parse().unwrap();
+        mqtt.fleet.update_robot(RobotState::new(
+            robot_id.clone(),
+            "Crawler",
+            RobotType::Crawler,
+        ));
+        let climb = Command::MoveTo {
+            target: Position::new(0.0, 20.0, 0.0),
+            speed: None,
+        };
+
+        let refused = mqtt.send_command("CR-001", climb).await.unwrap_err();
+        assert_eq!(refused.kind(), Some(ErrorKind::Rejected));
+        assert_eq!(mqtt.drain_command_queue().await, 0);
+        assert!(
+            mqtt.events()
+                .read()
+                .await
+                .entries()
+                .any(|event| event.kind == SystemEventKind::CommandRejected)
+        );
+
+        eventloop.clean();
+        let responses: Vec<CommandResponse> = eventloop
+            .pending
+            .drain(..)
+            .filter_map(|request| match request {
+                Request::Publish(publish) if publish.topic == topics::responses(&robot_id) => {
+                    Some(Encoding::decode_detected(&publish.payload).unwrap())
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(responses.len(), 1);
+        assert!(!responses[0].success);
+        assert!(responses[0].error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_moves_into_a_geofence_are_refused_or_clamped_with_an_alert() {
+        let fence = Geofence {
+            robot_types: vec![RobotType::Drone],
+            ..Geofence::new(
+                "FENCE-COMPRESSOR",
+                vec![
+                    Position::new(40.0, 0.0, -10.0),
+                    Position::new(60.0, 0.0, -10.0),
+                    Position::new(60.0, 0.0, 10.0),
+                    Position::new(40.0, 0.0, 10.0),
+                ],
+            )
+        };
+        for action in [FenceAction::Reject, FenceAction::Clamp] {
+            let (tx, _rx) = mpsc::channel(10);
+            let config = EngineConfig {
+                geofences: GeofenceConfig {
+                    fences: vec![fence.clone()],
+                    action,
+                    ..GeofenceConfig::default()
+                },
+                ..EngineConfig::default()
+            };
+            let (mqtt, mut eventloop) = AetherisMqtt::from_engine_config(config, tx).await.unwrap();
+            let mut drone =
+                RobotState::new("DR-001".parse().unwrap(), "Drone Alpha", RobotType::Drone);
+            drone.position = Position::new(0.0, 10.0, 0.0);
+            mqtt.fleet.update_robot(drone);
+            let into_fence = Command::MoveTo {
+                target: Position::new(50.0, 10.0, 0.0),
+                speed: None,
+            };
+
+            let result = mqtt.publish("DR-001", into_fence).await;
+            let kind = if action == FenceAction::Reject {
+                assert!(matches!(result, Err(PublishError::Fenced(_))));
+                assert!(mqtt.acks().read().await.awaiting().is_empty());
+                SystemEventKind::CommandRejected
+            } else {
+                assert!(result.is_ok());
+                SystemEventKind::CommandClamped
+            };
+            assert!(
+                mqtt.events()
+                    .read()
+                    .await
+                    .entries()
+                    .any(|event| event.kind == kind)
+            );
+
+            eventloop.clean();
+            let mut sent_target = None;
+            let mut alerted = false;
+            for request in eventloop.pending.drain(..) {
+                let Request::Publish(publish) = request else {
+                    continue;
+                };
+                if publish.topic.starts_with("aetheris/commands/") {
+                    let msg: MqttMessage<Command> =
+                        Encoding::decode_detected(&publish.payload).unwrap();
+                    if let Command::MoveTo { target, .. } = msg.payload {
+                        sent_target = Some(target);
+                    }
+                } else if publish.topic.starts_with(topics::ALERTS) {
+                    let msg: MqttMessage<AnomalyReport> =
+                        Encoding::decode_detected(&publish.payload).unwrap();
+                    assert!(msg.payload.description.contains("FENCE-COMPRESSOR"));
+                    alerted = true;
+                }
+            }
+            assert!(alerted);
+            match action {
+                FenceAction::Reject => assert_eq!(sent_target, None),
+                FenceAction::Clamp => {
+                    let target = sent_target.expect("clamped move should be sent");
+                    assert!(target.distance_to(&Position::new(35.0, 10.0, 0.0)) < 1e-9);
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unauthorized_commands_are_answered_and_audited() {
+        let (tx, mut rx) = mpsc::channel(10);
+        let config = EngineConfig {
+            authorization: AuthorizationConfig {
+                enabled: true,
+                operators: [("watcher".to_string(), OperatorRole::Viewer)].into(),
+                ..AuthorizationConfig::default()
+            },
+            ..EngineConfig::default()
+        };
+        let (mqtt, _eventloop) = AetherisMqtt::from_engine_config(config, tx).await.unwrap();
+        let topic = topics::commands(&"RV-001".parse().unwrap());
+
+        let msg = MqttMessage::new(Command::EmergencyStop, "dashboard", 1)
+            .with_command_id("CMD-viewer")
+            .with_operator(Operator::new("watcher", OperatorRole::Viewer));
+        mqtt.handle_incoming(&topic, &serde_json::to_vec(&msg).unwrap())
+            .await
+            .unwrap();
+        assert!(rx.try_recv().is_err());
+        {
+            let correlator = mqtt.correlator();
+            let correlator = correlator.read().await;
+            let entry = correlator.audit().entries().last().unwrap().clone();
+            assert_eq!(entry.command_id, "CMD-viewer");
+            assert_eq!(entry.issued_by, "watcher");
+            assert!(
+                entry
+                    .rejected
+                    .unwrap()
+                    .contains("may not send emergency_stop")
+            );
+        }
+
+        let own = mqtt
+            .authorizer()
+            .sign_own(MqttMessage::new(Command::Stop, "engine", 1));
+        mqtt.handle_incoming(&topic, &serde_json::to_vec(&own).unwrap())
+            .await
+            .unwrap();
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(EngineMessage::CommandReceived(ReceivedCommand {
+                command: Command::Stop,
+                ..
+            }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_expired_commands_are_dropped_with_an_error_response() {
+        let (tx, mut rx) = mpsc::channel(10);
+        let (mqtt, _eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+        let topic = topics::commands(&"RV-001".parse().unwrap());
+        let now = aetheris_shared::current_timestamp_ms();
+        let send = |seq, command_id: &str, expires_at| {
+            let mut msg = MqttMessage::new(Command::ReturnToBase, "dashboard", seq)
+                .with_command_id(command_id);
+            msg.expires_at = expires_at;
+            serde_json::to_vec(&msg).unwrap()
+        };
+
+        // Held by the broker for ten minutes
+        mqtt.handle_incoming(&topic, &send(1, "CMD-stale", Some(now - 600_000)))
+            .await
+            .unwrap();
+        assert!(rx.try_recv().is_err());
+        {
+            let correlator = mqtt.correlator();
+            let correlator = correlator.read().await;
+            let entry = correlator.audit().entries().last().unwrap();
+            assert_eq!(entry.command_id, "CMD-stale");
+            assert!(entry.rejected.as_ref().unwrap().starts_with("expired"));
+        }
+
+        for (seq, expires_at) in [(2, Some(now + 86_400_000)), (3, None)] {
+            mqtt.handle_incoming(&topic, &send(seq, "CMD-fresh", expires_at))
+                .await
+                .unwrap();
+            match rx.try_recv() {
+                Ok(EngineMessage::CommandReceived(received)) => {
+                    assert_eq!(received.expires_at, expires_at)
+                }
+                other => panic!("expected the command, got {other:?}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_configure_merges_and_get_config_answers_with_the_result() {
+        let (tx, _rx) = mpsc::channel(10);
+        let (mqtt, mut eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+        let topic = topics::commands(&"RV-001".parse().unwrap());
+        let send = |seq, command_id: &str, command| {
+            serde_json::to_vec(
+                &MqttMessage::new(command, "dashboard", seq).with_command_id(command_id),
+            )
+            .unwrap()
+        };
+        let configure = |config| Command::Configure { config };
+
+        for (seq, config) in [
+            (
+                1,
+                RobotConfig {
+                    max_speed: Some(1.5),
+                    low_battery_threshold: Some(30.0),
+                    ..RobotConfig::default()
+                },
+            ),
+            (
+                2,
+                RobotConfig {
+                    scan_interval: Some(2),
+                    ..RobotConfig::default()
+                },
+            ),
+            // A zero interval is refused and changes nothing
+            (
+                3,
+                RobotConfig {
+                    heartbeat_interval: Some(0),
+                    max_speed: Some(0.5),
+                    ..RobotConfig::default()
+                },
+            ),
+        ] {
+            let command_id = format!("CMD-{seq}");
+            mqtt.handle_incoming(&topic, &send(seq, &command_id, configure(config)))
+                .await
+                .unwrap();
+        }
+        mqtt.handle_incoming(&topic, &send(4, "CMD-get", Command::GetConfig))
+            .await
+            .unwrap();
+
+        let expected = RobotConfig {
+            max_speed: Some(1.5),
+            scan_interval: Some(2),
+            low_battery_threshold: Some(30.0),
+            ..RobotConfig::default()
+        };
+        assert_eq!(
+            mqtt.robot_configs().await,
+            [("RV-001".to_string(), expected.clone())]
+        );
+        eventloop.clean();
+        let responses: Vec<CommandResponse> = eventloop
+            .pending
+            .drain(..)
+            .filter_map(|request| match request {
+                Request::Publish(publish) if publish.topic.starts_with("aetheris/responses/") => {
+                    Some(Encoding::Json.decode(&publish.payload).unwrap())
+                }
+                _ => None,
+            })
+            .collect();
+        let [refused, answered] = &responses[..] else {
+            panic!("expected two responses, got {responses:?}");
+        };
+        assert_eq!(refused.command_id, "CMD-3");
+        assert!(!refused.success);
+        assert!(
+            refused
+                .error
+                .as_ref()
+                .unwrap()
+                .contains("heartbeat_interval")
+        );
+        assert_eq!(answered.command_id, "CMD-get");
+        assert!(answered.success);
+        assert_eq!(answered.config, Some(expected));
+    }
+
+    #[tokio::test]
+    async fn test_broadcasts_report_which_online_robots_confirmed() {
+        let (tx, mut rx) = mpsc::channel(10);
+        let config = EngineConfig {
+            broadcast: broadcast::BroadcastConfig {
+                deadline: Duration::from_millis(300),
+            },
+            ..EngineConfig::default()
+        };
+        let (mqtt, _eventloop) = AetherisMqtt::from_engine_config(config, tx).await.unwrap();
+        {
+            let fleet = mqtt.fleet();
+            for id in ["RV-001", "RV-002", "RV-003", "RV-004"] {
+                fleet.update_robot(RobotState::new(id.parse().unwrap(), id, RobotType::Rover));
+            }
+            fleet.mark_offline("RV-004");
+        }
+
+        let answer = |command_id: &str, robot_id: &str, success: bool| {
+            let response = CommandResponse {
+                command_id: command_id.into(),
+                robot_id: robot_id.parse().unwrap(),
+                success,
+                error: None,
+                config: None,
+                status: None,
+                timestamp: aetheris_shared::current_timestamp_ms(),
+            };
+            (
+                topics::responses(&robot_id.parse().unwrap()),
+                serde_json::to_vec(&response).unwrap(),
+            )
+        };
+        let robots = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            let command_id = mqtt.broadcasts().read().await.in_flight()[0].to_string();
+            // RV-005 joined after the broadcast; RV-003 drops off unanswered
+            for (robot_id, success) in [("RV-001", true), ("RV-002", false), ("RV-005", true)] {
+                let (topic, payload) = answer(&command_id, robot_id, success);
+                mqtt.handle_incoming(&topic, &payload).await.unwrap();
+            }
+            mqtt.fleet().mark_offline("RV-003");
+        };
+        let (result, ()) = tokio::join!(mqtt.broadcast_and_collect(Command::EmergencyStop), robots);
+        let result = result.unwrap();
+
+        assert_eq!(result.acked, ["RV-001"]);
+        assert_eq!(result.failed, ["RV-002"]);
+        assert_eq!(result.non_responding, ["RV-003"]);
+        assert_eq!(result.summary(), "1/3 robots confirmed emergency_stop");
+        assert!(mqtt.broadcasts().read().await.in_flight().is_empty());
+        assert_eq!(mqtt.metrics().published(TopicClass::BroadcastResult), 1);
+        let mut completed = None;
+        while let Ok(message) = rx.try_recv() {
+            if let EngineMessage::BroadcastCompleted(result) = message {
+                completed = Some(result);
+            }
+        }
+        assert_eq!(completed, Some(result));
+    }
+
+    #[tokio::test]
+    async fn test_readings_must_come_from_mapped_sections() {
+        let (tx, mut rx) = mpsc::channel(10);
+        let (mqtt, _eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+        let reading = |section_id: &str| PipeEnvironment {
+            section_id: section_id.into(),
+            pressure: 50.0,
+            temperature: 25.0,
+            h2_concentration: 100.0,
+            wall_thickness: 10.0,
+            flow_rate: 500.0,
+            humidity: 45.0,
+            position: Position::new(5.0, -0.5, 8.0),
+            timestamp: Timestamp::from_millis(aetheris_shared::current_timestamp_ms()),
+        };
+        let send = |section_id: &str, seq: u64| {
+            let msg = MqttMessage::new(reading(section_id), section_id, seq);
+            (
+                topics::environment(section_id),
+                serde_json::to_vec(&msg).unwrap(),
+            )
+        };
+
+        let (topic, payload) = send("PIPE-004", 0);
+        mqtt.handle_incoming(&topic, &payload).await.unwrap();
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(EngineMessage::EnvironmentReceived(_))
+        ));
+
+        let (topic, payload) = send("PIPE-H3", 0);
+        let err = mqtt.handle_incoming(&topic, &payload).await.unwrap_err();
+        assert_eq!(err.kind(), Some(ErrorKind::Rejected));
+        assert!(rx.try_recv().is_err());
+
+        // Generated anomalies land on the pipeline
+        for _ in 0..20 {
+            let (position, section_id) = mqtt.random_pipeline_point();
+            let section = mqtt.pipeline().get(section_id).unwrap();
+            assert!(section.distance_to(&position) < 1e-9);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_merged_section_takes_over_anomalies_health_and_history() {
+        let (tx, _rx) = mpsc::channel(100);
+        let (mqtt, mut eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+        *mqtt.sections().write().await =
+            SectionRegistry::new(sections::UnknownSectionPolicy::Provisional);
+        let hot = PipeEnvironment {
+            section_id: "PIPE-0O3".into(),
+            pressure: 50.0,
+            temperature: aetheris_shared::limits::TEMPERATURE_ALERT_CELSIUS + 10.0,
+            h2_concentration: 100.0,
+            wall_thickness: 10.0,
+            flow_rate: 500.0,
+            humidity: 45.0,
+            position: Position::new(5.0, 0.0, 0.0),
+            timestamp: Timestamp::from_millis(aetheris_shared::current_timestamp_ms()),
+        };
+        let msg = MqttMessage::new(hot, "PIPE-0O3", 0);
+        mqtt.handle_incoming(
+            &topics::environment("PIPE-0O3"),
+            &serde_json::to_vec(&msg).unwrap(),
+        )
+        .await
+        .unwrap();
+        // The raised report comes back from the broker like any alert
+        eventloop.clean();
+        let (alert_topic, alert) = eventloop
+            .pending
+            .drain(..)
+            .find_map(|request| match request {
+                Request::Publish(publish) if publish.topic.starts_with(topics::ALERTS) => {
+                    Some((publish.topic, publish.payload))
+                }
+                _ => None,
+            })
+            .expect("the alarm should raise an alert");
+        mqtt.handle_incoming(&alert_topic, &alert).await.unwrap();
+        assert!(mqtt.section_health().read().await.get("PIPE-0O3").is_some());
+
+        mqtt.register_section("PIPE-003", Position::new(5.0, 0.0, 0.0), Some("PIPE-0O3"))
+            .await
+            .unwrap();
+
+        let anomalies = mqtt.anomalies();
+        let anomalies = anomalies.read().await;
+        let sections: Vec<_> = anomalies
+            .all()
+            .into_iter()
+            .map(|anomaly| anomaly.primary.section_id.as_str())
+            .collect();
+        assert_eq!(sections, ["PIPE-003"]);
+        let health = mqtt.section_health();
+        let health = health.read().await;
+        assert!(health.get("PIPE-0O3").is_none());
+        let report = health.get("PIPE-003").unwrap();
+        assert_eq!(report.open_anomalies.values().sum::<usize>(), 1);
+        assert_eq!(report.environment.as_ref().unwrap().section_id, "PIPE-003");
+        let alarms = mqtt.alarms().read().await.snapshot();
+        assert!(alarms.iter().all(|alarm| alarm.section_id == "PIPE-003"));
+        let history = mqtt
+            .telemetry_history(&HistoryQuery::range(0, u64::MAX).for_section("PIPE-0O3"))
+            .unwrap();
+        assert!(history.is_empty());
+        let history = mqtt
+            .telemetry_history(&HistoryQuery::range(0, u64::MAX).for_section("PIPE-003"))
+            .unwrap();
+        assert_eq!(history.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_environment_alarms_fold_occurrences_and_follow_acknowledgement() {
+        let (tx, _rx) = mpsc::channel(10);
+        let (mqtt, mut eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+        let hot = |seq: u64| {
+            let reading = PipeEnvironment {
+                section_id: "PIPE-002".into(),
+                pressure: 50.0,
+                temperature: limits::TEMPERATURE_ALERT_CELSIUS + 10.0,
+                h2_concentration: 100.0,
+                wall_thickness: 10.0,
+                flow_rate: 500.0,
+                humidity: 45.0,
+                position: Position::new(5.0, -0.5, 8.0),
+                timestamp: Timestamp::from_millis(aetheris_shared::current_timestamp_ms()),
+            };
+            serde_json::to_vec(&MqttMessage::new(reading, "PIPE-002", seq)).unwrap()
+        };
+        let topic = topics::environment("PIPE-002");
+
+        mqtt.handle_incoming(&topic, &hot(0)).await.unwrap();
+        // The raised report comes back from the broker like any alert
+        eventloop.clean();
+        let (alert_topic, alert) = eventloop
+            .pending
+            .drain(..)
+            .find_map(|request| match request {
+                Request::Publish(publish) if publish.topic.starts_with(topics::ALERTS) => {
+                    Some((publish.topic, publish.payload))
+                }
+                _ => None,
+            })
+            .expect("the alarm should raise an alert");
+        mqtt.handle_incoming(&alert_topic, &alert).await.unwrap();
+        let report_id = {
+            let anomalies = mqtt.anomalies();
+            let anomalies = anomalies.read().await;
+            anomalies.all()[0].primary.id.clone()
+        };
+
+        for seq in 1..3 {
+            mqtt.handle_incoming(&topic, &hot(seq)).await.unwrap();
+        }
+        {
+            let anomalies = mqtt.anomalies();
+            let anomalies = anomalies.read().await;
+            assert_eq!(anomalies.len(), 1);
+            let primary = &anomalies.get(&report_id).unwrap().primary;
+            assert_eq!(primary.occurrence_count, 3);
+            assert!(primary.last_seen.is_some());
+        }
+
+        mqtt.set_anomaly_status(&report_id, AnomalyStatus::Acknowledged, "operator")
+            .await
+            .unwrap();
+        let alarms = mqtt.alarms();
+        let alarms = alarms.read().await;
+        let alarm = alarms.get("PIPE-002", HazardKind::HighTemperature).unwrap();
+        assert_eq!(alarm.status, AlarmStatus::Acknowledged);
+    }
+
+    #[tokio::test]
+    async fn test_registered_detectors_raise_alerts_from_readings() {
+        use crate::detectors::{ThresholdConfig, ThresholdMetric, ThresholdRule};
+
+        /// Flags every reading of a section it is told about
+        #[derive(Debug)]
+        struct Watchlist(&'static str);
+
+        impl AnomalyDetector for Watchlist {
+            fn name(&self) -> &str {
+                "watchlist"
+            }
+
+            fn observe(&mut self, observation: &Observation<'_>) -> Vec<AnomalyReport> {
+                let reading = observation.reading;
+                if reading.section_id != self.0 {
+                    return Vec::new();
+                }
+                vec![AnomalyReport::new(
+                    AnomalyType::Corrosion,
+                    SeverityLevel::Low,
+                    reading.position,
+                    &reading.section_id,
+                    "watchlist",
+                    0.5,
+                    "watched section",
+                )]
+            }
+        }
+
+        let (tx, _rx) = mpsc::channel(10);
+        let config = EngineConfig {
+            thresholds: ThresholdConfig {
+                rules: vec![ThresholdRule {
+                    metric: ThresholdMetric::FlowRate,
+                    above: None,
+                    below: Some(100.0),
+                    severity: SeverityLevel::Medium,
+                    anomaly_type: None,
+                    sections: Vec::new(),
+                }],
+            },
+            ..EngineConfig::default()
+        };
+        let (mqtt, _eventloop) = AetherisMqtt::from_engine_config(config, tx).await.unwrap();
+        mqtt.register_detector(Box::new(Watchlist("PIPE-002")));
+        assert_eq!(mqtt.detector_names(), ["thresholds", "watchlist"]);
+
+        let send = |section_id: &str, flow_rate: f64, seq: u64| {
+            let reading = PipeEnvironment {
+                section_id: section_id.into(),
+                pressure: 50.0,
+                temperature: 25.0,
+                h2_concentration: 100.0,
+                wall_thickness: 10.0,
+                flow_rate,
+                humidity: 45.0,
+                position: Position::new(5.0, -0.5, 8.0),
+                timestamp: Timestamp::from_millis(aetheris_shared::current_timestamp_ms()),
+            };
+            let msg = MqttMessage::new(reading, section_id, seq);
+            (
+                topics::environment(section_id),
+                serde_json::to_vec(&msg).unwrap(),
+            )
+        };
+        let alerts = || mqtt.metrics().published(TopicClass::Alert);
+
+        let (topic, payload) = send("PIPE-001", 500.0, 0);
+        mqtt.handle_incoming(&topic, &payload).await.unwrap();
+        assert_eq!(alerts(), 0);
+        // Flow collapses: the threshold rule reports once
+        for seq in 1..3 {
+            let (topic, payload) = send("PIPE-001", 20.0, seq);
+            mqtt.handle_incoming(&topic, &payload).await.unwrap();
+        }
+        let (topic, payload) = send("PIPE-002", 500.0, 0);
+        mqtt.handle_incoming(&topic, &payload).await.unwrap();
+        // Each alert goes to the flat and the severity topic
+        assert_eq!(alerts(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_single_subscriptions_are_restored_with_the_rest() {
+        let (tx, _rx) = mpsc::channel(10);
+        let (mqtt, mut eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+        mqtt.subscribe(topics::MISSIONS_ALL).await.unwrap();
+        eventloop.clean();
+        eventloop.pending.clear();
+
+        // What a reconnect without a session runs
+        mqtt.subscribe_all().await.unwrap();
+        eventloop.clean();
+        let subscribed: Vec<String> = eventloop
+            .pending
+            .drain(..)
+            .filter_map(|request| match request {
+                Request::Subscribe(subscribe) => Some(subscribe.filters),
+                _ => None,
+            })
+            .flatten()
+            .map(|filter| filter.path)
+            .collect();
+        assert!(subscribed.contains(&topics::TELEMETRY_ALL.to_string()));
+        assert_eq!(subscribed.last().unwrap(), topics::MISSIONS_ALL);
+    }
+
+    #[tokio::test]
+    async fn test_alerts_reach_the_flat_topic_until_it_is_turned_off() {
+        let report = AnomalyReport::new(
+            AnomalyType::Leak,
+            SeverityLevel::High,
+            Position::origin(),
+            "PIPE-001",
+            "RV-001",
+            0.9,
+            "test",
+        );
+        assert_eq!(
+            AlertTopics::default().subscriptions(),
+            [topics::ALERTS.to_string()]
+        );
+        let (tx, _rx) = mpsc::channel(10);
+        let (mqtt, _eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+        mqtt.publish_alert(&report).await.unwrap();
+        assert_eq!(mqtt.metrics().published(TopicClass::Alert), 2);
+
+        let alert_topics = AlertTopics {
+            legacy: false,
+            min_severity: None,
+        };
+        assert_eq!(alert_topics.subscriptions().len(), SeverityLevel::ALL.len());
+        let (tx, _rx) = mpsc::channel(10);
+        let config = MqttConfig {
+            alert_topics,
+            ..MqttConfig::default()
+        };
+        let (mqtt, _eventloop) = AetherisMqtt::new(config, tx).await.unwrap();
+        mqtt.publish_alert(&report).await.unwrap();
+        assert_eq!(mqtt.metrics().published(TopicClass::Alert), 1);
+    }
+
+    #[tokio::test]
+    async fn test_telemetry_flood_never_blocks_and_loses_no_alert() {
+        let (tx, mut rx) = mpsc::channel(16);
+        let (mqtt, _eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+        // A consumer far slower than the flood
+        let consumer = tokio::spawn(async move {
+            let mut received = Vec::new();
+            while let Some(message) = rx.recv().await {
+                received.push(message);
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+            received
+        });
+
+        const FLOOD: u64 = 10_000;
+        const ROBOTS: u64 = 50;
+        let mut alert_ids = Vec::new();
+        let mut slowest = Duration::ZERO;
+        for i in 0..FLOOD {
+            let robot_id = format!("RV-{:03}", i % ROBOTS);
+            let state = RobotState {
+                timestamp: Timestamp::from_millis(1_000_000 + i),
+                ..RobotState::new(robot_id.parse().unwrap(), &robot_id, RobotType::Rover)
+            };
+            let start = std::time::Instant::now();
+            mqtt.notify(EngineMessage::TelemetryReceived(state))
+                .await
+                .unwrap();
+            slowest = slowest.max(start.elapsed());
+            if i % 100 == 99 {
+                let report = AnomalyReport::new(
+                    AnomalyType::Leak,
+                    SeverityLevel::High,
+                    Position::origin(),
+                    "PIPE-001",
+                    &robot_id,
+                    0.9,
+                    "test",
+                );
+                alert_ids.push(report.id.clone());
+                mqtt.notify(EngineMessage::AlertReceived(report))
+                    .await
+                    .unwrap();
+            }
+        }
+        assert!(
+            slowest < Duration::from_millis(50),
+            "telemetry waited {slowest:?} for the consumer"
+        );
+
+        while !mqtt.lock_coalesced().is_empty() {
+            mqtt.flush_coalesced();
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        let dropped = mqtt.metrics().channel_dropped(LossyClass::Telemetry);
+        let status = mqtt.system_status().await;
+        drop(mqtt);
+        let received = consumer.await.unwrap();
+
+        let mut delivered_alerts = Vec::new();
+        let mut latest = HashMap::new();
+        let mut telemetry = 0;
+        for message in received {
+            match message {
+                EngineMessage::AlertReceived(report) => delivered_alerts.push(report.id),
+                EngineMessage::TelemetryReceived(state) => {
+                    telemetry += 1;
+                    let previous = latest.insert(state.id.clone(), state.timestamp);
+                    assert!(previous < Some(state.timestamp), "{} went back", state.id);
+                }
+                other => panic!("unexpected {other:?}"),
+            }
+        }
+        assert_eq!(delivered_alerts, alert_ids);
+        assert!(dropped > 0);
+        assert_eq!(telemetry + dropped, FLOOD);
+        assert_eq!(status.dropped_messages["telemetry"], dropped);
+        // The last state of every robot made it through
+        assert_eq!(latest.len(), ROBOTS as usize);
+        assert!(
+            latest
+                .values()
+                .all(|timestamp| *timestamp >= 1_000_000 + FLOOD - ROBOTS)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_open_anomalies_survive_a_restart_and_are_republished() {
+        let dir = std::env::temp_dir().join(format!("aetheris-anomalies-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = || EngineConfig {
+            anomaly_store: AnomalyStoreConfig {
+                path: Some(dir.join("anomalies.jsonl")),
+                ..AnomalyStoreConfig::default()
+            },
+            ..EngineConfig::default()
+        };
+        let report = |anomaly_type, severity, section_id| {
+            AnomalyReport::new(
+                anomaly_type,
+                severity,
+                Position::new(5.0, 0.0, 0.0),
+                section_id,
+                "RV-001",
+                0.9,
+                "found on patrol",
+            )
+        };
+        let leak = report(AnomalyType::Leak, SeverityLevel::Critical, "PIPE-001");
+        let crack = report(AnomalyType::Crack, SeverityLevel::Low, "PIPE-002");
+        {
+            let (tx, _rx) = mpsc::channel(10);
+            let (mqtt, _eventloop) = AetherisMqtt::from_engine_config(config(), tx)
+                .await
+                .unwrap();
+            for (seq, report) in [&leak, &crack].into_iter().enumerate() {
+                let msg = MqttMessage::new(report.clone(), "RV-001", seq as u64 + 1);
+                mqtt.handle_incoming(topics::ALERTS, &serde_json::to_vec(&msg).unwrap())
+                    .await
+                    .unwrap();
+            }
+            mqtt.set_anomaly_status(&leak.id, AnomalyStatus::Acknowledged, "ops-alice")
+                .await
+                .unwrap();
+        }
+
+        let (tx, _rx) = mpsc::channel(10);
+        let (mqtt, mut eventloop) = AetherisMqtt::from_engine_config(config(), tx)
+            .await
+            .unwrap();
+        {
+            let anomalies = mqtt.anomalies();
+            let anomalies = anomalies.read().await;
+            assert_eq!(anomalies.len(), 2);
+            let restored = &anomalies.get(&leak.id).unwrap().primary;
+            assert_eq!(restored.status, AnomalyStatus::Acknowledged);
+            assert_eq!(restored.acknowledged_by.as_deref(), Some("ops-alice"));
+            assert!(anomalies.get(&crack.id).is_some());
+        }
+
+        // Only the critical leak is put back for late-joining dashboards
+        mqtt.set_connection_state(ConnectionState::Connected).await;
+        mqtt.republish_open_anomalies().await.unwrap();
+        eventloop.clean();
+        let retained: Vec<(String, String)> = eventloop
+            .pending
+            .drain(..)
+            .filter_map(|request| match request {
+                Request::Publish(publish) if publish.retain => {
+                    let msg: MqttMessage<AnomalyReport> =
+                        serde_json::from_slice(&publish.payload).unwrap();
+                    Some((publish.topic, msg.payload.id))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            retained,
+            [
+                (topics::ALERTS.to_string(), leak.id.clone()),
+                (topics::alerts_severity(SeverityLevel::Critical), leak.id),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_window_holds_alerts_and_latest_telemetry() {
+        let (tx, _rx) = mpsc::channel(10);
+        let config = MqttConfig {
+            backup_brokers: vec![BrokerEndpoint::new("broker-b.plant.local", 1883)],
+            ..MqttConfig::default()
+        };
+        let (mqtt, mut eventloop) = AetherisMqtt::new(config, tx).await.unwrap();
+        assert_eq!(mqtt.active_broker(), "localhost:1883");
+
+        // The connection drops
+        mqtt.set_connection_state(ConnectionState::Disconnected)
+            .await;
+        for battery in [90.0, 80.0, 70.0] {
+            for id in ["RV-001", "DR-001"] {
+                let state = RobotState {
+                    battery,
+                    ..RobotState::new(id.parse().unwrap(), id, RobotType::Rover)
+                };
+                mqtt.publish_telemetry(&state).await.unwrap();
+            }
+        }
+        let heartbeat = Heartbeat::new(
+            "RV-001".parse().unwrap(),
+            RobotType::Rover,
+            RobotStatus::Active,
+            70.0,
+            90.0,
+            60,
+        );
+        mqtt.publish_heartbeat(&heartbeat).await.unwrap();
+        let mut alert_ids = Vec::new();
+        for (anomaly_type, section) in [
+            (AnomalyType::Leak, "PIPE-001"),
+            (AnomalyType::Corrosion, "PIPE-002"),
+            (AnomalyType::Crack, "PIPE-003"),
+        ] {
+            let report = AnomalyReport::new(
+                anomaly_type,
+                SeverityLevel::High,
+                Position::origin(),
+                section,
+                "RV-001",
+                0.9,
+                "test",
+            );
+            alert_ids.push(report.id.clone());
+            mqtt.publish_alert(&report).await.unwrap();
+        }
+        {
+            let buffer = mqtt.lock_offline_buffer();
+            assert_eq!(buffer.held(HoldClass::Alert), 6);
+            assert_eq!(buffer.held(HoldClass::Telemetry), 2);
+        }
+        let metrics = mqtt.render_metrics().await;
+        assert!(metrics.contains("aetheris_offline_publishes_held{class=\"alert\"} 6"));
+        assert!(
+            metrics.contains("aetheris_offline_publishes_dropped_total{class=\"heartbeat\"} 1")
+        );
+        assert_eq!(mqtt.metrics().published(TopicClass::Telemetry), 0);
+
+        // Back: the buffer goes out ahead of anything new, alerts first
+        mqtt.set_connection_state(ConnectionState::Connected).await;
+        mqtt.flush_offline_buffer();
+        assert!(!mqtt.lock_offline_buffer().is_holding());
+        eventloop.clean();
+        let published: Vec<Publish> = eventloop
+            .pending
+            .drain(..)
+            .filter_map(|request| match request {
+                Request::Publish(publish) => Some(publish),
+                _ => None,
+            })
+            .collect();
+        let alerts: Vec<String> = published[..6]
+            .iter()
+            .step_by(2)
+            .map(|publish| {
+                let msg: MqttMessage<AnomalyReport> =
+                    Encoding::Json.decode(&publish.payload).unwrap();
+                msg.payload.id
+            })
+            .collect();
+        assert_eq!(alerts, alert_ids);
+        let telemetry: Vec<(String, f64)> = published[6..]
+            .iter()
+            .map(|publish| {
+                let msg: MqttMessage<RobotState> = Encoding::Json.decode(&publish.payload).unwrap();
+                (msg.payload.id.to_string(), msg.payload.battery)
+            })
+            .collect();
+        assert_eq!(
+            telemetry,
+            [("RV-001".to_string(), 70.0), ("DR-001".to_string(), 70.0)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_emergency_stop_bypasses_queued_commands() {
+        let (tx, _rx) = mpsc::channel(10);
+        let (mqtt, _eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+        let scan = Command::PerformScan {
+            scan_type: aetheris_shared::ScanType::Thermal,
+        };
+        let mut ids = Vec::new();
+        for robot_id in ["RV-001", "RV-002", "RV-003"] {
+            ids.push(mqtt.send_command(robot_id, scan.clone()).await.unwrap());
+        }
+        let again = mqtt.queue_command("RV-001", scan, None).await.unwrap();
+        assert_eq!(again, Enqueued::Duplicate(ids[0].clone()));
+        assert_eq!(mqtt.command_queue_depth(), 3);
+
+        // Published at once, ahead of the queued scans
+        let stop = mqtt
+            .queue_command("RV-002", Command::EmergencyStop, None)
+            .await
+            .unwrap();
+        assert_eq!(stop, Enqueued::Dispatched);
+        let awaiting = mqtt.acks().read().await.awaiting();
+        assert_eq!(awaiting.len(), 1);
+        assert_eq!(awaiting[0].variant, "emergency_stop");
+
+        assert_eq!(mqtt.drain_command_queue().await, 3);
+        assert_eq!(mqtt.command_queue_depth(), 0);
+        let awaiting = mqtt.acks().read().await.awaiting();
+        assert_eq!(awaiting.len(), 4);
+        // Published under the ids handed out when they were queued
+        assert!(
+            ids.iter()
+                .all(|id| awaiting.iter().any(|a| &a.command_id == id))
+        );
+        assert!(
+            mqtt.render_metrics()
+                .await
+                .contains("aetheris_command_queue_depth 0")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_back_to_back_commands_resolve_by_id() {
+        let (tx, _rx) = mpsc::channel(10);
+        let (mqtt, _eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+
+        let respond = async {
+            let awaiting = loop {
+                let awaiting = mqtt.acks().read().await.awaiting();
+                if awaiting.len() == 2 {
+                    break awaiting;
+                }
+                tokio::task::yield_now().await;
+            };
+            // The robot answers the second command first
+            for (variant, success) in [("return_to_base", false), ("stop", true)] {
+                let sent = awaiting.iter().find(|a| a.variant == variant).unwrap();
+                let response = CommandResponse {
+                    command_id: sent.command_id.clone(),
+                    robot_id: "RV-001".parse().unwrap(),
+                    success,
+                    error: None,
+                    config: None,
+                    status: None,
+                    timestamp: aetheris_shared::current_timestamp_ms(),
+                };
+                let payload = serde_json::to_vec(&response).unwrap();
+                mqtt.handle_incoming(&topics::responses(&"RV-001".parse().unwrap()), &payload)
+                    .await
+                    .unwrap();
+            }
+        };
+        let (stop, return_to_base, ()) = tokio::join!(
+            mqtt.send_command_and_wait("RV-001", Command::Stop),
+            mqtt.send_command_and_wait("RV-001", Command::ReturnToBase),
+            respond
+        );
+
+        assert!(stop.unwrap().success);
+        assert!(!return_to_base.unwrap().success);
+        assert!(mqtt.acks().read().await.awaiting().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rollout_batches_settle_on_their_configure_commands_only() {
+        let (tx, _rx) = mpsc::channel(10);
+        let (mqtt, _eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+        for robot_id in ["RV-001", "RV-002"] {
+            let state = RobotState::new(robot_id.parse().unwrap(), robot_id, RobotType::Rover);
+            mqtt.fleet().update_robot(state);
+        }
+        let config = aetheris_shared::RobotConfig {
+            max_speed: Some(1.5),
+            ..Default::default()
+        };
+        let rollout_id = mqtt
+            .start_config_rollout(
+                config,
+                rollout::RobotSelector::All,
+                2,
+                Duration::from_secs(60),
+                0,
+            )
+            .await
+            .unwrap();
+        let respond = |command_id: String, robot_id: &str, success: bool| {
+            let response = CommandResponse {
+                command_id,
+                robot_id: robot_id.parse().unwrap(),
+                success,
+                error: None,
+                config: None,
+                status: None,
+                timestamp: aetheris_shared::current_timestamp_ms(),
+            };
+            (
+                topics::responses(&robot_id.parse().unwrap()),
+                serde_json::to_vec(&response).unwrap(),
+            )
+        };
+
+        // RV-001 fails a move it was sent in the middle of the batch
+        let (topic, payload) = respond("CMD-move".into(), "RV-001", false);
+        mqtt.handle_incoming(&topic, &payload).await.unwrap();
+        let progress = mqtt.rollouts().read().await.progress(&rollout_id).unwrap();
+        assert_eq!(progress.phase, rollout::RolloutPhase::AwaitingAck);
+        assert!(progress.failures.is_empty());
+
+        let configures: Vec<_> = mqtt
+            .acks()
+            .read()
+            .await
+            .awaiting()
+            .into_iter()
+            .filter(|a| a.variant == "configure")
+            .map(|a| (a.command_id, a.robot_id))
+            .collect();
+        assert_eq!(configures.len(), 2);
+        for (command_id, robot_id) in configures {
+            let (topic, payload) = respond(command_id, &robot_id, true);
+            mqtt.handle_incoming(&topic, &payload).await.unwrap();
+        }
+        let progress = mqtt.rollouts().read().await.progress(&rollout_id).unwrap();
+        assert!(matches!(
+            progress.phase,
+            rollout::RolloutPhase::Soaking { .. }
+        ));
+        assert!(progress.failures.is_empty());
+        let events = mqtt.events();
+        let started = events
+            .read()
+            .await
+            .entries()
+            .find(|event| event.kind == SystemEventKind::RolloutStarted)
+            .cloned()
+            .unwrap();
+        assert_eq!(started.subject.as_deref(), Some(rollout_id.as_str()));
+    }
+
+    /// Production code (everything before the test module) of the files
+    /// whose limits moved to `aetheris_shared::limits`
+    fn production_sources() -> Vec<(&'static str, &'static str)> {
+        let strip_tests = |src: &'static str| src.split("#[cfg(test)]\nmod tests").next().unwrap();
+        let shared = strip_tests(include_str!("../../aetheris-shared/src/lib.rs"));
+        let (before, rest) = shared.split_once("pub mod limits {").unwrap();
+        let after = rest.split_once("\n}\n").unwrap().1;
+        vec![
+            ("aetheris-shared/src/lib.rs (before limits)", before),
+            ("aetheris-shared/src/lib.rs (after limits)", after),
+            ("lib.rs", strip_tests(include_str!("lib.rs"))),
+            ("alarms.rs", strip_tests(include_str!("alarms.rs"))),
+            ("ingest.rs", strip_tests(include_str!("ingest.rs"))),
+        ]
+    }
+
+    #[test]
+    fn test_no_magic_limits_outside_limits_module() {
+        let forbidden = [
+            "4000.0",
+            "> 100.0",
+            "> 80.0",
+            "battery: 100.0",
+            "signal: 100.0",
+            "from_secs(15)",
+            "1883",
+            "keep_alive_secs: 30",
+            "256 * 1024",
+        ];
+        for (file, source) in production_sources() {
+            for literal in forbidden {
+                assert!(
+                    !source.contains(literal),
+                    "{file} contains `{literal}`; use aetheris_shared::limits instead"
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_follower_takes_the_lead_when_the_leader_releases_it() {
+        let config = EngineConfig {
+            leader: LeaderConfig {
+                enabled: true,
+                instance_id: Some("engine-b".into()),
+                lease: Duration::from_millis(500),
+                renew_interval: Duration::from_millis(20),
+            },
+            ..EngineConfig::default()
+        };
+        let (tx, _rx) = mpsc::channel(10);
+        let (mqtt, mut eventloop) = AetherisMqtt::from_engine_config(config, tx).await.unwrap();
+        assert!(!mqtt.is_leader());
+        assert!(!mqtt.leadership().is_leader());
+        mqtt.set_connection_state(ConnectionState::Connected).await;
+
+        let now = mqtt.now_ms();
+        let mut claim = LeaderClaim {
+            instance_id: "engine-a".into(),
+            claimed_at: now - 60_000,
+            renewed_at: now,
+            lease_ms: 500,
+            released: false,
+        };
+        let payload = serde_json::to_vec(&claim).unwrap();
+        mqtt.handle_incoming(topics::SYSTEM_LEADER, &payload)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        mqtt.drive_election().await.unwrap();
+        assert!(!mqtt.is_leader());
+        let leadership = mqtt.system_status().await.leadership.unwrap();
+        assert_eq!(leadership.instance_id, "engine-b");
+        assert_eq!(leadership.leader.as_deref(), Some("engine-a"));
+
+        // Followers leave escalation to the leader
+        let stale = AnomalyReport::new_at(
+            AnomalyType::Leak,
+            SeverityLevel::Medium,
+            Position::origin(),
+            "PIPE-001",
+            ENGINE_ORIGIN,
+            0.9,
+            "H2 above threshold",
+            Timestamp::from_millis(now - 2 * 3_600_000),
+        );
+        mqtt.anomalies()
+            .write()
+            .await
+            .ingest(stale, &SectionRegistry::default());
+        mqtt.escalate_stale_alerts().await.unwrap();
+        let metrics = mqtt.metrics().render(&HashMap::new());
+        assert!(
+            metrics
+                .lines()
+                .any(|l| l == "aetheris_alerts_escalated_total 0")
+        );
+        assert!(metrics.lines().any(|l| l == "aetheris_leader 0"));
+
+        claim.released = true;
+        let payload = serde_json::to_vec(&claim).unwrap();
+        mqtt.handle_incoming(topics::SYSTEM_LEADER, &payload)
+            .await
+            .unwrap();
+        mqtt.drive_election().await.unwrap();
+        assert!(mqtt.is_leader());
+        let leadership = mqtt.system_status().await.leadership.unwrap();
+        assert_eq!(leadership.leader.as_deref(), Some("engine-b"));
+        assert_eq!(leadership.changes, 2);
+        mqtt.escalate_stale_alerts().await.unwrap();
+        let metrics = mqtt.metrics().render(&HashMap::new());
+        assert!(
+            metrics
+                .lines()
+                .any(|l| l == "aetheris_alerts_escalated_total 1")
+        );
+        assert!(metrics.lines().any(|l| l == "aetheris_leader 1"));
+        assert!(
+            metrics
+                .lines()
+                .any(|l| l == "aetheris_leadership_changes_total 2")
+        );
+        assert!(
+            mqtt.events()
+                .read()
+                .await
+                .entries()
+                .any(|event| event.kind == SystemEventKind::LeadershipChanged)
+        );
+
+        eventloop.clean();
+        let claims: Vec<LeaderClaim> = eventloop
+            .pending
+            .drain(..)
+            .filter_map(|request| match request {
+                Request::Publish(publish)
+                    if publish.topic == topics::SYSTEM_LEADER && publish.retain =>
+                {
+                    Some(serde_json::from_slice(&publish.payload).unwrap())
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(claims.len(), 1);
+        assert_eq!(claims[0].instance_id, "engine-b");
+        assert!(!claims[0].released);
+    }
+
+    #[tokio::test]
+    async fn test_route_requests_republish_known_routes_or_the_library() {
+        let (tx, _rx) = mpsc::channel(10);
+        let (mqtt, mut eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+        mqtt.set_connection_state(ConnectionState::Connected).await;
+        for route in create_mock_routes() {
+            mqtt.publish_route(&route).await.unwrap();
+        }
+        let ids: Vec<String> = mqtt.routes().ids().map(String::from).collect();
+        assert_eq!(ids.len(), create_mock_routes().len());
+        eventloop.clean();
+        eventloop.pending.clear();
+
+        let published = |eventloop: &mut rumqttc::EventLoop| -> Vec<Publish> {
+            eventloop.clean();
+            eventloop
+                .pending
+                .drain(..)
+                .filter_map(|request| match request {
+                    Request::Publish(publish) => Some(publish),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let request = RouteRequest::routes([ids[0].as_str(), "ROUTE-NOPE"]);
+        mqtt.handle_incoming(
+            topics::ROUTE_REQUESTS,
+            &serde_json::to_vec(&request).unwrap(),
+        )
+        .await
+        .unwrap();
+        let routes = published(&mut eventloop);
+        assert_eq!(routes.len(), 1);
+        assert_eq!(routes[0].topic, topics::routes(&ids[0]));
+
+        let request = serde_json::to_vec(&RouteRequest::all()).unwrap();
+        mqtt.handle_incoming(topics::ROUTE_REQUESTS, &request)
+            .await
+            .unwrap();
+        let library = published(&mut eventloop);
+        assert_eq!(library.len(), 1);
+        assert_eq!(library[0].topic, topics::ROUTE_LIBRARY);
+        assert!(library[0].retain);
+        let msg: MqttMessage<RouteLibrary> = Encoding::Json.decode(&library[0].payload).unwrap();
+        assert_eq!(msg.payload.ids().collect::<Vec<_>>(), ids);
+
+        // A flood is answered only up to the requester's burst, and the
+        // anonymous allowance is already half used
+        let flood = serde_json::to_vec(&RouteRequest::all().with_requester("spammer")).unwrap();
+        for _ in 0..20 {
+            mqtt.handle_incoming(topics::ROUTE_REQUESTS, &flood)
+                .await
+                .unwrap();
+        }
+        assert_eq!(published(&mut eventloop).len(), 5);
+        for _ in 0..20 {
+            mqtt.handle_incoming(topics::ROUTE_REQUESTS, &request)
+                .await
+                .unwrap();
+        }
+        assert_eq!(published(&mut eventloop).len(), 3);
+    }
+}
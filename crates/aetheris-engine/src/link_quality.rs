@@ -0,0 +1,242 @@
+//! Link delay and clock skew from heartbeat arrivals
+//!
+//! Every heartbeat carries the robot's own timestamp. Its arrival time on
+//! the engine's clock minus that timestamp is the one-way delay plus how far
+//! the robot's clock lags the engine's; from one side the two cannot be told
+//! apart, so the estimate is their sum. It is the median over a window of
+//! heartbeats, so one that sat in a queue does not drag it, and the median
+//! absolute deviation stands for jitter.
+//!
+//! An estimate beyond `max_skew` either way marks the robot's clock as
+//! drifted, reported once until it comes back within bounds. Staleness
+//! checks of the robot's telemetry shift its timestamps by the estimate, so
+//! a robot with a slow clock is not taken for one sending old data.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use aetheris_shared::{LinkQuality, Timestamp};
+
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// How heartbeat arrivals are turned into a skew estimate
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkQualityConfig {
+    /// Latest heartbeats the median is taken over
+    pub window: usize,
+    /// Heartbeats needed before there is an estimate
+    pub min_samples: usize,
+    /// Estimated skew either way beyond which a robot's clock has drifted
+    pub max_skew: Duration,
+}
+
+impl Default for LinkQualityConfig {
+    fn default() -> Self {
+        Self {
+            window: 20,
+            min_samples: 5,
+            max_skew: Duration::from_secs(5),
+        }
+    }
+}
+
+impl CheckConfig for LinkQualityConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if self.window == 0 {
+            checker.error("window", "must be at least 1", None);
+        }
+        if self.min_samples == 0 || self.min_samples > self.window {
+            checker.error(
+                "min_samples",
+                format!("must be between 1 and the window of {}", self.window),
+                None,
+            );
+        }
+        checker.positive("max_skew", self.max_skew);
+    }
+}
+
+// ============================================================================
+// ESTIMATOR
+// ============================================================================
+
+/// A robot's clock crossing the skew bound
+#[derive(Debug, Clone, PartialEq)]
+pub enum DriftChange {
+    /// Now beyond `max_skew`
+    Drifted(LinkQuality),
+    /// Back within `max_skew`
+    Recovered(LinkQuality),
+}
+
+/// Recent heartbeat offsets of one robot
+#[derive(Debug, Clone, Default)]
+pub struct LinkEstimator {
+    /// Receive time minus heartbeat timestamp (ms), oldest first
+    offsets: VecDeque<i64>,
+    drifted: bool,
+}
+
+impl LinkEstimator {
+    /// Count a heartbeat stamped `sent` that arrived at `received`. Returns
+    /// the change when it moves the estimate across the skew bound.
+    pub fn observe(
+        &mut self,
+        config: &LinkQualityConfig,
+        sent: Timestamp,
+        received: Timestamp,
+    ) -> Option<DriftChange> {
+        let offset = received.as_millis() as i64 - sent.as_millis() as i64;
+        self.offsets.push_back(offset);
+        while self.offsets.len() > config.window {
+            self.offsets.pop_front();
+        }
+
+        let mut estimate = self.estimate(config)?;
+        let drifted = estimate.offset_ms.unsigned_abs() > config.max_skew.as_millis() as u64;
+        if drifted == self.drifted {
+            return None;
+        }
+        self.drifted = drifted;
+        estimate.drifted = drifted;
+        Some(if drifted {
+            DriftChange::Drifted(estimate)
+        } else {
+            DriftChange::Recovered(estimate)
+        })
+    }
+
+    /// The current estimate, once there are `min_samples` heartbeats
+    pub fn estimate(&self, config: &LinkQualityConfig) -> Option<LinkQuality> {
+        if self.offsets.len() < config.min_samples.max(1) {
+            return None;
+        }
+        let offset_ms = median(self.offsets.iter().copied());
+        let jitter_ms = median(self.offsets.iter().map(|o| (o - offset_ms).abs())) as u64;
+        Some(LinkQuality {
+            offset_ms,
+            jitter_ms,
+            samples: self.offsets.len(),
+            drifted: self.drifted,
+        })
+    }
+
+    /// A timestamp from the robot's clock moved onto the engine's, as far
+    /// as there is an estimate
+    pub fn correct(&self, config: &LinkQualityConfig, sent: Timestamp) -> Timestamp {
+        match self.estimate(config) {
+            Some(LinkQuality { offset_ms, .. }) if offset_ms >= 0 => {
+                sent.saturating_add(Duration::from_millis(offset_ms as u64))
+            }
+            Some(LinkQuality { offset_ms, .. }) => {
+                sent.saturating_sub(Duration::from_millis(offset_ms.unsigned_abs()))
+            }
+            None => sent,
+        }
+    }
+}
+
+/// Median of a non-empty sample, the mean of the middle two for an even
+/// count
+fn median(values: impl Iterator<Item = i64>) -> i64 {
+    let mut values: Vec<i64> = values.collect();
+    values.sort_unstable();
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]).div_euclid(2)
+    } else {
+        values[mid]
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: u64 = 1_767_225_600_000;
+
+    /// Heartbeats every second from a robot whose clock lags by `lag_ms`,
+    /// with `delay` giving each one's delay in transit
+    fn feed(
+        estimator: &mut LinkEstimator,
+        config: &LinkQualityConfig,
+        lag_ms: i64,
+        count: u64,
+        delay: impl Fn(u64) -> i64,
+    ) -> Vec<DriftChange> {
+        (0..count)
+            .filter_map(|n| {
+                let received = START + n * 1_000;
+                let sent = (received as i64 - lag_ms - delay(n)) as u64;
+                estimator.observe(
+                    config,
+                    Timestamp::from_millis(sent),
+                    Timestamp::from_millis(received),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_estimate_converges_despite_jitter_and_outliers() {
+        let config = LinkQualityConfig::default();
+        let mut estimator = LinkEstimator::default();
+        // 40-60 ms in transit, every seventh heartbeat stuck for 3 s
+        let delay = |n: u64| {
+            if n % 7 == 3 {
+                3_000
+            } else {
+                40 + (n * 13 % 21) as i64
+            }
+        };
+        let changes = feed(&mut estimator, &config, 0, 4, delay);
+        assert!(changes.is_empty());
+        assert_eq!(estimator.estimate(&config), None);
+
+        feed(&mut estimator, &config, 0, 40, delay);
+        let estimate = estimator.estimate(&config).unwrap();
+        assert!((40..=60).contains(&estimate.offset_ms), "{estimate:?}");
+        assert!(estimate.jitter_ms <= 10, "{estimate:?}");
+        assert_eq!(estimate.samples, 20);
+        assert!(!estimate.drifted);
+    }
+
+    #[test]
+    fn test_drifted_clock_is_reported_once_and_corrected() {
+        let config = LinkQualityConfig::default();
+        let mut estimator = LinkEstimator::default();
+        let delay = |n: u64| 50 + (n % 5) as i64;
+
+        // 8 s slow: drift is reported when the estimate first exists
+        let changes = feed(&mut estimator, &config, 8_000, 30, delay);
+        assert_eq!(changes.len(), 1);
+        let DriftChange::Drifted(estimate) = &changes[0] else {
+            panic!("expected drift, got {changes:?}");
+        };
+        assert!((8_050..=8_054).contains(&estimate.offset_ms));
+        assert!(estimate.drifted);
+        let sent = Timestamp::from_millis(START);
+        let corrected = estimator.correct(&config, sent);
+        assert_eq!(corrected.duration_since(sent).as_millis(), 8_052);
+
+        // Clock fixed: recovered once the window's median moves back
+        let changes = feed(&mut estimator, &config, 0, 30, delay);
+        assert!(matches!(changes[..], [DriftChange::Recovered(_)]));
+
+        // 6 s fast
+        let changes = feed(&mut estimator, &config, -6_000, 30, delay);
+        let [DriftChange::Drifted(estimate)] = &changes[..] else {
+            panic!("expected drift, got {changes:?}");
+        };
+        assert!((-5_950..=-5_946).contains(&estimate.offset_ms));
+        assert!(estimator.correct(&config, sent) < sent);
+    }
+}
@@ -1,603 +1,236 @@
-//! AETHERIS Engine - Robotics Simulation and MQTT Communication Hub
+//! AETHERIS Engine binary
 //!
-//! This module provides:
-//! - Async MQTT client for robot coordination
-//! - Heartbeat mechanism for connectivity monitoring
-//! - Multi-robot telemetry broadcasting
-//! - Command dispatch and response handling
-
-use std::collections::HashMap;
+//! Wires the engine library together: MQTT hub, heartbeat monitor, mock fleet
+//! and pipeline sensor simulation, and the message processor. Ctrl+C or
+//! SIGTERM stops them all, announces the engine and its simulated robots
+//! offline, and disconnects. With `--replay` a recorded session is
+//! republished instead of simulating the fleet, or with `--replay-local` fed
+//! straight to the message processor; since the engine then acts on the live
+//! broker, that needs `--allow-live-actions` too. `--cli` runs one operator
+//! command against a running fleet instead of the engine.
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::{Context, Result};
-use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Packet, QoS};
-use serde_json;
-use tokio::sync::{RwLock, mpsc};
-use tokio::time::{Instant, interval};
+use clap::Parser;
+use tokio::sync::mpsc;
 use tracing::{debug, error, info, warn};
 
-use aetheris_shared::{
-    AnomalyReport, AnomalyType, Command, CommandResponse, CurrentTask, FaultType, HealthStatus,
-    Heartbeat, MqttMessage, PipeEnvironment, Position, RobotState, RobotStatus, RobotType,
-    SeverityLevel, Velocity, topics,
+use aetheris_shared::Command;
+
+use aetheris_engine::config::{ConfigFile, EXIT_INVALID_CONFIG, EngineConfig, run_config_check};
+use aetheris_engine::detector_eval::run_detector_eval;
+use aetheris_engine::environment::{EnvironmentSimulator, spawn_environment_simulation};
+use aetheris_engine::event_log::spawn_event_log;
+#[cfg(feature = "http")]
+use aetheris_engine::http_bridge::{self, EventStream};
+use aetheris_engine::missions::spawn_mission_executor;
+use aetheris_engine::operator_cli::run_operator_cli;
+use aetheris_engine::replay::{Recording, ReplayOptions, run_replay};
+use aetheris_engine::shutdown::{self, EXIT_SHUTDOWN_TIMEOUT, GRACE_PERIOD, TaskSet};
+use aetheris_engine::simulation::{SimulatedFleet, spawn_fleet_simulation};
+use aetheris_engine::source_binding::{SourceBindings, spawn_binding_reload};
+use aetheris_engine::transport::{Secret, TlsConfig};
+use aetheris_engine::{
+    AetherisMqtt, EngineMessage, create_mock_routes, spawn_command_dispatcher,
+    spawn_heartbeat_monitor, spawn_metrics_endpoint, spawn_section_health_publisher,
+    spawn_section_report, spawn_status_publisher,
 };
 
 // ============================================================================
-// CONFIGURATION
+// COMMAND LINE
 // ============================================================================
 
-/// MQTT client configuration
-#[derive(Debug, Clone)]
-pub struct MqttConfig {
-    pub broker_host: String,
-    pub broker_port: u16,
-    pub client_id: String,
-    pub keep_alive_secs: u64,
-    pub clean_session: bool,
-}
-
-impl Default for MqttConfig {
-    fn default() -> Self {
-        Self {
-            broker_host: "localhost".into(),
-            broker_port: 1883,
-            client_id: format!("aetheris-engine-{}", uuid::Uuid::new_v4()),
-            keep_alive_secs: 30,
-            clean_session: true,
-        }
-    }
+/// AETHERIS Engine: MQTT hub, fleet coordination, and robot simulation.
+///
+/// Settings come from the defaults, then the configuration file, then the
+/// environment, then the flags.
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    after_help = "Offline detector comparison: --detector-eval <recording-dir> \
+                  <baseline.json> <candidate.json> [options]\n\
+                  Operator commands: --cli <command>, see --cli --help"
+)]
+struct Cli {
+    /// TOML or JSON configuration file (broker, timing, simulated fleet)
+    #[arg(long, value_name = "FILE", env = "AETHERIS_CONFIG")]
+    config: Option<PathBuf>,
+    /// MQTT broker host
+    #[arg(long, env = "AETHERIS_BROKER_HOST")]
+    broker_host: Option<String>,
+    /// MQTT broker port
+    #[arg(long, env = "AETHERIS_BROKER_PORT")]
+    broker_port: Option<u16>,
+    /// Username to log in to the broker with
+    #[arg(long, env = "AETHERIS_BROKER_USERNAME")]
+    broker_username: Option<String>,
+    /// Password to log in to the broker with
+    #[arg(long, env = "AETHERIS_BROKER_PASSWORD", hide_env_values = true)]
+    broker_password: Option<String>,
+    /// Connect to the broker over TLS, verified against this CA certificate
+    #[arg(long, value_name = "FILE", env = "AETHERIS_BROKER_CA")]
+    broker_ca: Option<PathBuf>,
+    /// Serve Prometheus metrics on this port at /metrics
+    #[arg(long, env = "AETHERIS_METRICS_PORT")]
+    metrics_port: Option<u16>,
+    /// Serve the dashboard HTTP and WebSocket bridge on this port
+    #[cfg(feature = "http")]
+    #[arg(long, env = "AETHERIS_HTTP_PORT")]
+    http_port: Option<u16>,
+    /// Bearer token the bridge requires for commands
+    #[cfg(feature = "http")]
+    #[arg(long, env = "AETHERIS_HTTP_TOKEN", hide_env_values = true)]
+    http_token: Option<String>,
+    /// Append every engine message to JSONL files in this directory
+    #[arg(long, value_name = "DIR", env = "AETHERIS_EVENT_LOG")]
+    event_log: Option<PathBuf>,
+    /// Source bindings file, reloaded when it changes
+    #[arg(long, value_name = "FILE", env = "AETHERIS_SOURCE_BINDINGS")]
+    source_bindings: Option<PathBuf>,
+    /// Simulated seconds per real second; 60 runs the simulation a minute
+    /// per second and flags its timestamps as simulated
+    #[arg(long, env = "AETHERIS_TIME_SCALE")]
+    time_scale: Option<f64>,
+    /// Republish a recorded session (event log file or directory) instead
+    /// of simulating the fleet
+    #[arg(long, value_name = "FILE")]
+    replay: Option<PathBuf>,
+    /// Replay speed factor; 2.0 plays twice as fast as recorded
+    #[arg(long, default_value_t = 1.0, requires = "replay")]
+    speed: f64,
+    /// Start the replay over when it ends
+    #[arg(long = "loop", requires = "replay")]
+    looped: bool,
+    /// Set the replayed payloads' timestamps to the time they are sent
+    #[arg(long, requires = "replay")]
+    rewrite_timestamps: bool,
+    /// Replay recorded commands too (skipped by default)
+    #[arg(long, requires = "replay")]
+    replay_commands: bool,
+    /// Feed the replay to this engine's message processor instead of
+    /// republishing it; timestamps are always rewritten
+    #[arg(long, requires = "replay")]
+    replay_local: bool,
+    /// Let a local replay act on the live broker: the engine publishes
+    /// alerts and sends recalls and dispatches as the replay unfolds
+    #[arg(long, requires = "replay_local")]
+    allow_live_actions: bool,
+    /// Validate the configuration and exit
+    #[arg(long)]
+    check_config: bool,
 }
 
 // ============================================================================
-// ROBOT FLEET MANAGER
+// MAIN ENTRY POINT
 // ============================================================================
 
-/// Manages the state of all robots in the fleet
-#[derive(Debug, Default)]
-pub struct FleetManager {
-    /// Map of robot ID to current state
-    robots: HashMap<String, RobotState>,
-    /// Last heartbeat received from each robot
-    last_heartbeat: HashMap<String, Instant>,
-    /// Heartbeat timeout duration
-    heartbeat_timeout: Duration,
-}
-
-impl FleetManager {
-    pub fn new(heartbeat_timeout: Duration) -> Self {
-        Self {
-            robots: HashMap::new(),
-            last_heartbeat: HashMap::new(),
-            heartbeat_timeout,
-        }
-    }
-
-    /// Register a new robot or update existing
-    pub fn update_robot(&mut self, state: RobotState) {
-        let robot_id = state.id.clone();
-        self.robots.insert(robot_id.clone(), state);
-        self.last_heartbeat.insert(robot_id, Instant::now());
-    }
-
-    /// Record heartbeat from a robot
-    pub fn record_heartbeat(&mut self, robot_id: &str) {
-        self.last_heartbeat
-            .insert(robot_id.to_string(), Instant::now());
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Offline detector comparison: no broker, no engine
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(at) = args.iter().position(|arg| arg == "--detector-eval") {
+        std::process::exit(run_detector_eval(&args[at + 1..], &mut std::io::stdout()));
     }
-
-    /// Get all robots that have timed out
-    pub fn get_timed_out_robots(&self) -> Vec<String> {
-        let now = Instant::now();
-        self.last_heartbeat
-            .iter()
-            .filter(|(_, last_seen)| now.duration_since(**last_seen) > self.heartbeat_timeout)
-            .map(|(id, _)| id.clone())
-            .collect()
+    // Operator command against a running fleet: a client, not the engine
+    if let Some(at) = args.iter().position(|arg| arg == "--cli") {
+        std::process::exit(run_operator_cli(&args[at + 1..], &mut std::io::stdout()).await);
     }
-
-    /// Mark a robot as offline
-    pub fn mark_offline(&mut self, robot_id: &str) {
-        if let Some(robot) = self.robots.get_mut(robot_id) {
-            robot.status = RobotStatus::Offline;
-            robot.health = HealthStatus::Critical;
+    let cli = Cli::parse();
+
+    // Defaults, overridden by the configuration file, then the environment
+    // and flags
+    let mut engine_config = EngineConfig::default();
+    if let Some(path) = &cli.config {
+        match ConfigFile::load(path) {
+            Ok(file) => file.apply(&mut engine_config),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(EXIT_INVALID_CONFIG);
+            }
         }
     }
-
-    /// Get all connected robots
-    pub fn get_all_robots(&self) -> Vec<&RobotState> {
-        self.robots.values().collect()
+    if let Some(host) = cli.broker_host {
+        engine_config.mqtt.broker_host = host;
     }
-
-    /// Get a specific robot by ID
-    pub fn get_robot(&self, id: &str) -> Option<&RobotState> {
-        self.robots.get(id)
+    if let Some(port) = cli.broker_port {
+        engine_config.mqtt.broker_port = port;
     }
-}
-
-// ============================================================================
-// MQTT MESSAGE HANDLER
-// ============================================================================
-
-/// Internal message types for the engine
-#[derive(Debug)]
-pub enum EngineMessage {
-    TelemetryReceived(RobotState),
-    HeartbeatReceived(Heartbeat),
-    AlertReceived(AnomalyReport),
-    EnvironmentReceived(PipeEnvironment),
-    CommandResponseReceived(CommandResponse),
-    CommandReceived(Command, String), // (command, source)
-}
-
-/// Generate random coordinate for simulated positions
-fn rand_coord() -> f64 {
-    (rand::random::<f64>() - 0.5) * 200.0 // Range: -100 to 100
-}
-
-// ============================================================================
-// AETHERIS MQTT CLIENT
-// ============================================================================
-
-/// Main MQTT communication hub for the AETHERIS system
-pub struct AetherisMqtt {
-    client: AsyncClient,
-    config: MqttConfig,
-    fleet: Arc<RwLock<FleetManager>>,
-    message_tx: mpsc::Sender<EngineMessage>,
-    sequence: Arc<std::sync::atomic::AtomicU64>,
-}
-
-impl AetherisMqtt {
-    /// Create a new MQTT client with default configuration
-    pub async fn new(
-        config: MqttConfig,
-        message_tx: mpsc::Sender<EngineMessage>,
-    ) -> Result<(Self, EventLoop)> {
-        let mut mqtt_opts =
-            MqttOptions::new(&config.client_id, &config.broker_host, config.broker_port);
-        mqtt_opts.set_keep_alive(Duration::from_secs(config.keep_alive_secs));
-        mqtt_opts.set_clean_session(config.clean_session);
-
-        let (client, eventloop) = AsyncClient::new(mqtt_opts, 100);
-
-        let mqtt = Self {
-            client,
-            config,
-            fleet: Arc::new(RwLock::new(FleetManager::new(Duration::from_secs(15)))),
-            message_tx,
-            sequence: Arc::new(std::sync::atomic::AtomicU64::new(0)),
-        };
-
-        Ok((mqtt, eventloop))
+    if let Some(username) = cli.broker_username {
+        engine_config.mqtt.username = Some(username);
     }
-
-    /// Subscribe to all relevant AETHERIS topics
-    pub async fn subscribe_all(&self) -> Result<()> {
-        info!("Subscribing to AETHERIS MQTT topics...");
-
-        // Subscribe to telemetry from all robots
-        self.client
-            .subscribe(topics::TELEMETRY_ALL, QoS::AtLeastOnce)
-            .await
-            .context("Failed to subscribe to telemetry")?;
-
-        // Subscribe to heartbeats
-        self.client
-            .subscribe(topics::HEARTBEAT_ALL, QoS::AtLeastOnce)
-            .await
-            .context("Failed to subscribe to heartbeats")?;
-
-        // Subscribe to alerts
-        self.client
-            .subscribe(topics::ALERTS, QoS::AtLeastOnce)
-            .await
-            .context("Failed to subscribe to alerts")?;
-
-        // Subscribe to environment readings
-        self.client
-            .subscribe(topics::ENVIRONMENT_ALL, QoS::AtLeastOnce)
-            .await
-            .context("Failed to subscribe to environment")?;
-
-        // Subscribe to command responses (for dashboard)
-        self.client
-            .subscribe("aetheris/responses/+", QoS::AtLeastOnce)
-            .await
-            .context("Failed to subscribe to responses")?;
-
-        // Subscribe to commands (to handle chaos scenarios)
-        self.client
-            .subscribe(topics::COMMANDS_ALL, QoS::AtLeastOnce)
-            .await
-            .context("Failed to subscribe to commands")?;
-
-        info!("Successfully subscribed to all AETHERIS topics");
-        Ok(())
+    if let Some(password) = cli.broker_password {
+        engine_config.mqtt.password = Some(Secret::new(password));
     }
-
-    /// Send a command to a specific robot
-    pub async fn send_command(&self, robot_id: &str, command: Command) -> Result<()> {
-        let topic = topics::commands(robot_id);
-        let seq = self.next_sequence();
-        let msg = MqttMessage::new(command, "engine", seq);
-        let payload = serde_json::to_string(&msg)?;
-
-        self.client
-            .publish(&topic, QoS::AtLeastOnce, false, payload)
-            .await
-            .context("Failed to publish command")?;
-
-        info!(robot_id = %robot_id, "Command sent");
-        Ok(())
+    if let Some(ca_path) = cli.broker_ca {
+        // Keeps the client certificate and ALPN of the configuration file
+        let tls = engine_config
+            .mqtt
+            .tls
+            .get_or_insert_with(|| TlsConfig::new(&ca_path));
+        tls.ca_path = ca_path;
     }
-
-    /// Broadcast a command to all robots
-    pub async fn broadcast_command(&self, command: Command) -> Result<()> {
-        let seq = self.next_sequence();
-        let msg = MqttMessage::new(command, "engine", seq);
-        let payload = serde_json::to_string(&msg)?;
-
-        self.client
-            .publish(topics::COMMANDS_BROADCAST, QoS::AtLeastOnce, false, payload)
-            .await
-            .context("Failed to broadcast command")?;
-
-        info!("Command broadcast to all robots");
-        Ok(())
+    if let Some(dir) = cli.event_log {
+        engine_config.event_log.directory = Some(dir);
     }
-
-    /// Publish robot telemetry (used by simulated robots)
-    pub async fn publish_telemetry(&self, state: &RobotState) -> Result<()> {
-        let topic = topics::telemetry(&state.id);
-        let seq = self.next_sequence();
-        let msg = MqttMessage::new(state.clone(), &state.id, seq);
-        let payload = serde_json::to_string(&msg)?;
-
-        self.client
-            .publish(&topic, QoS::AtLeastOnce, false, payload)
-            .await
-            .context("Failed to publish telemetry")?;
-
-        debug!(robot_id = %state.id, "Telemetry published");
-        Ok(())
+    if let Some(scale) = cli.time_scale {
+        engine_config.clock.time_scale = scale;
     }
-
-    /// Publish a heartbeat for a robot
-    pub async fn publish_heartbeat(&self, heartbeat: &Heartbeat) -> Result<()> {
-        let topic = topics::heartbeat(&heartbeat.robot_id);
-        let payload = serde_json::to_string(heartbeat)?;
-
-        self.client
-            .publish(&topic, QoS::AtLeastOnce, false, payload)
-            .await
-            .context("Failed to publish heartbeat")?;
-
-        debug!(robot_id = %heartbeat.robot_id, "Heartbeat published");
-        Ok(())
+    if let Some(port) = cli.metrics_port {
+        engine_config.metrics.listen = Some(SocketAddr::from(([0, 0, 0, 0], port)));
     }
-
-    /// Publish an anomaly alert
-    pub async fn publish_alert(&self, report: &AnomalyReport) -> Result<()> {
-        let seq = self.next_sequence();
-        let msg = MqttMessage::new(report.clone(), &report.detected_by, seq);
-        let payload = serde_json::to_string(&msg)?;
-
-        self.client
-            .publish(topics::ALERTS, QoS::AtLeastOnce, false, payload)
-            .await
-            .context("Failed to publish alert")?;
-
-        warn!(
-            anomaly_id = %report.id,
-            severity = ?report.severity,
-            "Anomaly alert published"
-        );
-        Ok(())
+    #[cfg(feature = "http")]
+    {
+        if let Some(port) = cli.http_port {
+            engine_config.http.listen = Some(SocketAddr::from(([0, 0, 0, 0], port)));
+        }
+        if let Some(token) = cli.http_token {
+            engine_config.http.command_token = Some(Secret::new(token));
+        }
     }
 
-    /// Publish environment sensor data
-    pub async fn publish_environment(&self, env: &PipeEnvironment) -> Result<()> {
-        let topic = topics::environment(&env.section_id);
-        let seq = self.next_sequence();
-        let msg = MqttMessage::new(env.clone(), &env.section_id, seq);
-        let payload = serde_json::to_string(&msg)?;
-
-        self.client
-            .publish(&topic, QoS::AtLeastOnce, false, payload)
-            .await
-            .context("Failed to publish environment data")?;
-
-        debug!(section_id = %env.section_id, "Environment data published");
-        Ok(())
+    // Source bindings may come from a file that is watched for changes
+    let bindings_path = cli.source_bindings;
+    if let Some(path) = &bindings_path {
+        match SourceBindings::load(path) {
+            Ok(bindings) => engine_config.source_bindings = bindings,
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                std::process::exit(EXIT_INVALID_CONFIG);
+            }
+        }
     }
 
-    /// Get the fleet manager for reading robot states
-    pub fn fleet(&self) -> Arc<RwLock<FleetManager>> {
-        self.fleet.clone()
+    // Validate the whole configuration before anything connects
+    if cli.check_config {
+        std::process::exit(run_config_check(&engine_config, &mut std::io::stdout()));
     }
-
-    /// Generate next sequence number
-    fn next_sequence(&self) -> u64 {
-        self.sequence
-            .fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+    if let Err(report) = engine_config.validate() {
+        eprintln!("{}", report);
+        std::process::exit(EXIT_INVALID_CONFIG);
     }
-
-    /// Process incoming MQTT messages
-    pub async fn handle_incoming(&self, topic: &str, payload: &[u8]) -> Result<()> {
-        let payload_str = std::str::from_utf8(payload)?;
-
-        // Route based on topic pattern
-        if topic.starts_with("aetheris/telemetry/") {
-            let msg: MqttMessage<RobotState> = serde_json::from_str(payload_str)?;
-            self.fleet.write().await.update_robot(msg.payload.clone());
-            let _ = self
-                .message_tx
-                .send(EngineMessage::TelemetryReceived(msg.payload))
-                .await;
-        } else if topic.starts_with("aetheris/heartbeat/") {
-            let heartbeat: Heartbeat = serde_json::from_str(payload_str)?;
-            self.fleet
-                .write()
-                .await
-                .record_heartbeat(&heartbeat.robot_id);
-            let _ = self
-                .message_tx
-                .send(EngineMessage::HeartbeatReceived(heartbeat))
-                .await;
-        } else if topic == topics::ALERTS {
-            let msg: MqttMessage<AnomalyReport> = serde_json::from_str(payload_str)?;
-            let _ = self
-                .message_tx
-                .send(EngineMessage::AlertReceived(msg.payload))
-                .await;
-        } else if topic.starts_with("aetheris/environment/") {
-            let msg: MqttMessage<PipeEnvironment> = serde_json::from_str(payload_str)?;
-            let _ = self
-                .message_tx
-                .send(EngineMessage::EnvironmentReceived(msg.payload))
-                .await;
-        } else if topic.starts_with("aetheris/responses/") {
-            let response: CommandResponse = serde_json::from_str(payload_str)?;
-            let _ = self
-                .message_tx
-                .send(EngineMessage::CommandResponseReceived(response))
-                .await;
-        } else if topic.starts_with("aetheris/commands/") {
-            // Handle incoming commands from dashboard (chaos scenarios)
-            if let Ok(msg) = serde_json::from_str::<MqttMessage<Command>>(payload_str) {
-                // Generate alert for chaos scenarios
-                if let Err(e) = self
-                    .generate_alert_for_command(&msg.payload, &msg.source)
-                    .await
-                {
-                    error!("Failed to generate alert for command: {}", e);
-                }
-                let _ = self
-                    .message_tx
-                    .send(EngineMessage::CommandReceived(msg.payload, msg.source))
-                    .await;
-            }
-        }
-
-        Ok(())
+    if !(cli.speed.is_finite() && cli.speed > 0.0) {
+        eprintln!("--speed must be a positive number, got {}", cli.speed);
+        std::process::exit(EXIT_INVALID_CONFIG);
     }
-
-    /// Generate an alert based on a command
-    pub async fn generate_alert_for_command(&self, command: &Command, source: &str) -> Result<()> {
-        let alert = match command {
-            Command::EmergencyStop => Some(AnomalyReport::new(
-                AnomalyType::Leak,
-                SeverityLevel::Critical,
-                Position::new(rand_coord(), 0.0, rand_coord()),
-                format!("PIPE-H{}", rand::random::<u8>() % 10),
-                source,
-                0.96,
-                "EMERGENCY: Hydrogen leak detected! All units halted.",
-            )),
-            Command::Investigate { anomaly_id } => Some(AnomalyReport::new(
-                AnomalyType::PressureDrop,
-                SeverityLevel::High,
-                Position::new(rand_coord(), 0.0, rand_coord()),
-                format!("PIPE-A{}", rand::random::<u8>() % 10),
-                source,
-                0.89,
-                format!("Pressure anomaly {} under investigation", anomaly_id),
-            )),
-            Command::PerformScan { scan_type } => {
-                let (anomaly_type, severity, desc) = match scan_type {
-                    aetheris_shared::ScanType::Thermal => (
-                        AnomalyType::TemperatureAnomaly,
-                        SeverityLevel::Medium,
-                        "Temperature spike detected during thermal scan",
-                    ),
-                    aetheris_shared::ScanType::Ultrasonic => (
-                        AnomalyType::WallThinning,
-                        SeverityLevel::High,
-                        "Wall thickness below threshold detected",
-                    ),
-                    aetheris_shared::ScanType::LeakDetection => (
-                        AnomalyType::Leak,
-                        SeverityLevel::High,
-                        "Potential leak signature detected",
-                    ),
-                    _ => (
-                        AnomalyType::Unknown,
-                        SeverityLevel::Info,
-                        "Scan completed - no anomalies",
-                    ),
-                };
-                Some(AnomalyReport::new(
-                    anomaly_type,
-                    severity,
-                    Position::new(rand_coord(), 0.0, rand_coord()),
-                    format!("PIPE-S{}", rand::random::<u8>() % 10),
-                    source,
-                    0.85 + (rand::random::<f64>() * 0.1),
-                    desc,
-                ))
-            }
-            Command::InjectFault { fault_type } => {
-                let (anomaly_type, severity, desc) = match fault_type {
-                    FaultType::LowBattery => (
-                        AnomalyType::Unknown,
-                        SeverityLevel::Medium,
-                        format!("Robot {} reporting critical battery level", source),
-                    ),
-                    FaultType::SensorFailure => (
-                        AnomalyType::Unknown,
-                        SeverityLevel::High,
-                        format!("Sensor malfunction detected on {}", source),
-                    ),
-                    FaultType::CommDropout => (
-                        AnomalyType::Unknown,
-                        SeverityLevel::Critical,
-                        format!("Communication lost with {}", source),
-                    ),
-                    FaultType::MotorFailure => (
-                        AnomalyType::StructuralDamage,
-                        SeverityLevel::High,
-                        format!("Motor failure reported by {}", source),
-                    ),
-                    FaultType::GpsDrift => (
-                        AnomalyType::Unknown,
-                        SeverityLevel::Low,
-                        format!("GPS accuracy degraded on {}", source),
-                    ),
-                };
-                Some(AnomalyReport::new(
-                    anomaly_type,
-                    severity,
-                    Position::new(rand_coord(), 0.0, rand_coord()),
-                    "SYSTEM",
-                    source,
-                    0.99,
-                    desc,
-                ))
-            }
-            _ => None,
-        };
-
-        if let Some(report) = alert {
-            self.publish_alert(&report).await?;
-        }
-
-        Ok(())
+    if cli.replay_local && !cli.allow_live_actions {
+        eprintln!(
+            "--replay-local publishes alerts and sends real commands as the engine reacts to the \
+             replay; pass --allow-live-actions to run it anyway"
+        );
+        std::process::exit(EXIT_INVALID_CONFIG);
     }
-}
-
-// ============================================================================
-// SIMULATION: MOCK ROBOT FLEET
-// ============================================================================
-
-/// Creates a set of simulated robots for testing
-pub fn create_mock_fleet() -> Vec<RobotState> {
-    vec![
-        RobotState {
-            id: "RV-001".into(),
-            name: "Rover Alpha".into(),
-            robot_type: RobotType::Rover,
-            position: Position::new(-2.0, 0.0, 1.0),
-            velocity: Velocity::new(1.2, 0.0, 0.0),
-            battery: 87.0,
-            signal: 95.0,
-            health: HealthStatus::Optimal,
-            status: RobotStatus::Active,
-            current_task: CurrentTask::Patrolling {
-                route_id: "ROUTE-A1".into(),
-            },
-            timestamp: aetheris_shared::current_timestamp_ms(),
-        },
-        RobotState {
-            id: "RV-002".into(),
-            name: "Rover Beta".into(),
-            robot_type: RobotType::Rover,
-            position: Position::new(2.0, 0.0, -1.0),
-            velocity: Velocity::new(0.8, 0.0, 0.0),
-            battery: 62.0,
-            signal: 78.0,
-            health: HealthStatus::Warning,
-            status: RobotStatus::Active,
-            current_task: CurrentTask::Scanning {
-                scan_type: aetheris_shared::ScanType::LeakDetection,
-            },
-            timestamp: aetheris_shared::current_timestamp_ms(),
-        },
-        RobotState {
-            id: "DR-001".into(),
-            name: "Drone Hawk".into(),
-            robot_type: RobotType::Drone,
-            position: Position::new(1.0, 3.0, 0.0),
-            velocity: Velocity::new(8.5, 0.0, 0.0),
-            battery: 94.0,
-            signal: 99.0,
-            health: HealthStatus::Optimal,
-            status: RobotStatus::Active,
-            current_task: CurrentTask::Patrolling {
-                route_id: "ROUTE-AIR-1".into(),
-            },
-            timestamp: aetheris_shared::current_timestamp_ms(),
-        },
-        RobotState {
-            id: "CR-001".into(),
-            name: "Crawler Alpha".into(),
-            robot_type: RobotType::Crawler,
-            position: Position::new(0.0, -0.5, 5.0), // Inside pipeline
-            velocity: Velocity::new(0.3, 0.0, 0.0),
-            battery: 71.0,
-            signal: 65.0, // Lower signal inside pipe
-            health: HealthStatus::Optimal,
-            status: RobotStatus::Active,
-            current_task: CurrentTask::Scanning {
-                scan_type: aetheris_shared::ScanType::Ultrasonic,
-            },
-            timestamp: aetheris_shared::current_timestamp_ms(),
-        },
-        RobotState {
-            id: "CR-002".into(),
-            name: "Crawler Beta".into(),
-            robot_type: RobotType::Crawler,
-            position: Position::new(3.0, -0.5, 8.0),
-            velocity: Velocity::zero(),
-            battery: 23.0,
-            signal: 45.0,
-            health: HealthStatus::Critical,
-            status: RobotStatus::Maintenance,
-            current_task: CurrentTask::ReturningToBase,
-            timestamp: aetheris_shared::current_timestamp_ms(),
-        },
-    ]
-}
-
-// ============================================================================
-// HEARTBEAT MONITOR TASK
-// ============================================================================
-
-/// Spawns a background task to monitor robot heartbeats
-pub async fn spawn_heartbeat_monitor(fleet: Arc<RwLock<FleetManager>>) {
-    tokio::spawn(async move {
-        let mut check_interval = interval(Duration::from_secs(5));
+    let replay_options = ReplayOptions {
+        speed: cli.speed,
+        looped: cli.looped,
+        include_commands: cli.replay_commands,
+        rewrite_timestamps: cli.rewrite_timestamps,
+        local: cli.replay_local,
+    };
 
-        loop {
-            check_interval.tick().await;
-
-            let mut fleet_guard = fleet.write().await;
-            let timed_out = fleet_guard.get_timed_out_robots();
-
-            for robot_id in timed_out {
-                warn!(robot_id = %robot_id, "Robot heartbeat timeout - marking offline");
-                fleet_guard.mark_offline(&robot_id);
-            }
-        }
-    });
-}
-
-// ============================================================================
-// MAIN ENTRY POINT
-// ============================================================================
-
-#[tokio::main]
-async fn main() -> Result<()> {
     // Initialize logging
     tracing_subscriber::fmt()
         .with_env_filter(
@@ -608,81 +241,271 @@ async fn main() -> Result<()> {
         .init();
 
     info!("🚀 AETHERIS Engine starting...");
+    if engine_config.clock.time_scale != 1.0 {
+        warn!(
+            time_scale = engine_config.clock.time_scale,
+            "Running on simulated time: published timestamps are not wall-clock time"
+        );
+    }
+
+    // Read the whole recording up front so a bad file fails fast
+    let recording = match &cli.replay {
+        Some(path) => {
+            let recording = Recording::load(path, replay_options.include_commands)
+                .with_context(|| format!("Failed to read the recording {}", path.display()))?;
+            if recording.out_of_order > 0 {
+                warn!(
+                    out_of_order = recording.out_of_order,
+                    "Recording is not in time order, replaying it sorted"
+                );
+            }
+            info!(
+                events = recording.len(),
+                skipped = recording.skipped,
+                "Loaded recording {}",
+                path.display()
+            );
+            Some(recording)
+        }
+        None => None,
+    };
 
     // Create message channel
-    let (message_tx, mut message_rx) = mpsc::channel::<EngineMessage>(100);
+    let (message_tx, mut message_rx) =
+        mpsc::channel::<EngineMessage>(engine_config.message_channel.capacity);
 
     // Initialize MQTT client
-    let config = MqttConfig::default();
     info!(
         "Connecting to MQTT broker at {}:{}",
-        config.broker_host, config.broker_port
+        engine_config.mqtt.broker_host, engine_config.mqtt.broker_port
     );
+    for backup in &engine_config.mqtt.backup_brokers {
+        info!("Backup MQTT broker at {}", backup.address());
+    }
 
-    let (mqtt, mut eventloop) = AetherisMqtt::new(config.clone(), message_tx)
+    let timing = engine_config.simulation.clone();
+    let world_bounds = engine_config.world_bounds;
+    let recovery = engine_config.recovery.clone();
+    let battery = engine_config.battery.clone();
+    let stations = engine_config.battery_manager.stations();
+    let command_expiry = engine_config.command_expiry.clone();
+    let fleet_states = engine_config.fleet.states();
+    let environment = engine_config.environment.clone();
+    let metrics_listen = engine_config.metrics.listen;
+    let event_log = engine_config.event_log.clone();
+    #[cfg(feature = "http")]
+    let http_config = engine_config.http.clone();
+    let (mqtt, eventloop) = AetherisMqtt::from_engine_config(engine_config, message_tx)
         .await
         .context("Failed to create MQTT client")?;
 
-    // Subscribe to topics
-    mqtt.subscribe_all().await?;
+    // Every background task stops when the shutdown is triggered
+    let (shutdown_trigger, shutdown) = shutdown::channel();
+    let mut tasks = TaskSet::default();
 
     // Start heartbeat monitor
-    spawn_heartbeat_monitor(mqtt.fleet()).await;
+    tasks.push(
+        "heartbeat monitor",
+        spawn_heartbeat_monitor(
+            mqtt.fleet(),
+            mqtt.events(),
+            mqtt.leadership(),
+            shutdown.clone(),
+        )
+        .await,
+    );
 
-    // Initialize mock fleet for simulation
-    let mock_robots = create_mock_fleet();
-    info!("Initialized {} simulated robots", mock_robots.len());
+    // Pick up edits to the source bindings without a restart
+    if let Some(path) = bindings_path {
+        tasks.push(
+            "source binding reload",
+            spawn_binding_reload(
+                mqtt.sources(),
+                path,
+                Duration::from_secs(10),
+                shutdown.clone(),
+            )
+            .await,
+        );
+    }
 
-    // Clone for the simulation task
-    let mqtt_sim = Arc::new(mqtt);
-    let mqtt_handler = mqtt_sim.clone();
+    // Report sections inferred from traffic so the topology can be fixed
+    tasks.push(
+        "section report",
+        spawn_section_report(mqtt.sections(), Duration::from_secs(300), shutdown.clone()).await,
+    );
 
-    // Spawn telemetry simulation task
-    let simulation_robots = mock_robots.clone();
-    tokio::spawn(async move {
-        let mut telemetry_interval = interval(Duration::from_secs(1));
-        let mut heartbeat_interval = interval(Duration::from_secs(5));
-        let mut uptime: u64 = 0;
+    let mqtt_handler = Arc::new(mqtt);
 
+    // Publish the engine's queued commands, most urgent first
+    tasks.push(
+        "command dispatcher",
+        spawn_command_dispatcher(mqtt_handler.clone(), shutdown.clone()),
+    );
+    // Run mission plans step by step
+    let (mission_commands, mission_executor) =
+        spawn_mission_executor(mqtt_handler.clone(), shutdown.clone());
+    tasks.push("mission executor", mission_executor);
+
+    // Simulations that act on received commands
+    let sim_commands = match recording {
+        // The recording stands in for the simulated fleet
+        Some(recording) => {
+            let replay = tokio::spawn(run_replay(
+                mqtt_handler.clone(),
+                recording,
+                replay_options,
+                shutdown.clone(),
+            ));
+            tasks.push("replay", replay);
+            Vec::new()
+        }
+        None => {
+            // Initialize the simulated fleet (the demo fleet unless configured)
+            let mock_robots = fleet_states;
+            info!("Initialized {} simulated robots", mock_robots.len());
+
+            // Share the patrol routes the simulated robots drive
+            let mock_routes = create_mock_routes();
+            for route in &mock_routes {
+                if let Err(e) = mqtt_handler.publish_route(route).await {
+                    error!("Failed to publish route {}: {}", route.id, e);
+                }
+            }
+            if let Err(e) = mqtt_handler.publish_route_library().await {
+                error!("Failed to publish route library: {}", e);
+            }
+
+            // Spawn telemetry simulation task (timing was validated with the config)
+            let mut fleet =
+                SimulatedFleet::new(mock_robots, mock_routes, world_bounds, 1.0, recovery)
+                    .with_charging(stations, battery)
+                    .with_command_expiry(command_expiry);
+            // Robots start as they were last configured
+            for (robot_id, config) in mqtt_handler.robot_configs().await {
+                fleet.configure(&robot_id, &config);
+            }
+            let (sim_commands, simulation) =
+                spawn_fleet_simulation(mqtt_handler.clone(), fleet, timing, shutdown.clone());
+            tasks.push("fleet simulation", simulation);
+
+            // Section sensors, leaking on command
+            let sensors = EnvironmentSimulator::new(mqtt_handler.pipeline(), environment);
+            let (leak_commands, sensor_simulation) =
+                spawn_environment_simulation(mqtt_handler.clone(), sensors, shutdown.clone());
+            tasks.push("environment simulation", sensor_simulation);
+            vec![
+                ("Fleet simulation", sim_commands),
+                ("Environment simulation", leak_commands),
+            ]
+        }
+    };
+
+    // Claim, renew or take over the lead when instances elect one
+    let mqtt_election = mqtt_handler.clone();
+    let mut election_shutdown = shutdown.clone();
+    let election = tokio::spawn(async move {
+        let mut election_interval = mqtt_election.clock().interval(Duration::from_secs(1));
         loop {
             tokio::select! {
-                _ = telemetry_interval.tick() => {
-                    // Publish telemetry for all robots
-                    for robot in &simulation_robots {
-                        let mut robot_state = robot.clone();
-                        // Simulate movement
-                        robot_state.position.x += robot_state.velocity.vx * 0.1;
-                        robot_state.timestamp = aetheris_shared::current_timestamp_ms();
-
-                        if let Err(e) = mqtt_sim.publish_telemetry(&robot_state).await {
-                            error!("Failed to publish telemetry: {}", e);
-                        }
-                    }
-                }
-                _ = heartbeat_interval.tick() => {
-                    uptime += 5;
-                    // Publish heartbeats
-                    for robot in &simulation_robots {
-                        let heartbeat = Heartbeat::new(
-                            &robot.id,
-                            robot.robot_type,
-                            robot.status,
-                            robot.battery,
-                            robot.signal,
-                            uptime,
-                        );
-                        if let Err(e) = mqtt_sim.publish_heartbeat(&heartbeat).await {
-                            error!("Failed to publish heartbeat: {}", e);
-                        }
-                    }
-                }
+                _ = election_interval.tick() => {}
+                _ = election_shutdown.wait() => return,
+            }
+            if let Err(e) = mqtt_election.drive_election().await {
+                error!("Failed to publish leader claim: {}", e);
+            }
+        }
+    });
+    tasks.push("leader election", election);
+
+    // Release alerts whose triage timed out
+    let mqtt_triage = mqtt_handler.clone();
+    let mut triage_shutdown = shutdown.clone();
+    let triage = tokio::spawn(async move {
+        let mut sweep_interval = mqtt_triage.clock().interval(Duration::from_secs(1));
+        loop {
+            tokio::select! {
+                _ = sweep_interval.tick() => {}
+                _ = triage_shutdown.wait() => return,
+            }
+            if let Err(e) = mqtt_triage.expire_triage().await {
+                error!("Failed to release timed-out triage: {}", e);
+            }
+        }
+    });
+    tasks.push("triage sweep", triage);
+
+    // Advance configuration rollouts, watch updated robots, expire zone modes,
+    // flag overdue assignments and missing robots, escalate unacknowledged
+    // alerts, expire or retain held commands, give up on unacknowledged ones,
+    // prune telemetry history past its retention
+    let mqtt_rollouts = mqtt_handler.clone();
+    let mut rollout_shutdown = shutdown.clone();
+    let rollouts = tokio::spawn(async move {
+        let mut rollout_interval = mqtt_rollouts.clock().interval(Duration::from_secs(1));
+        loop {
+            tokio::select! {
+                _ = rollout_interval.tick() => {}
+                _ = rollout_shutdown.wait() => return,
+            }
+            if let Err(e) = mqtt_rollouts.drive_rollouts().await {
+                error!("Failed to advance configuration rollout: {}", e);
+            }
+            mqtt_rollouts.expire_zone_modes().await;
+            if let Err(e) = mqtt_rollouts.drive_store_forward().await {
+                error!("Failed to drive held commands: {}", e);
+            }
+            if let Err(e) = mqtt_rollouts.check_overdue_assignments().await {
+                error!("Failed to raise overdue assignment alerts: {}", e);
+            }
+            if let Err(e) = mqtt_rollouts.escalate_stale_alerts().await {
+                error!("Failed to escalate unacknowledged alerts: {}", e);
+            }
+            if let Err(e) = mqtt_rollouts.check_expected_fleet().await {
+                error!("Failed to raise missing robot alerts: {}", e);
+            }
+            mqtt_rollouts.expire_command_acks().await;
+            mqtt_rollouts.prune_telemetry_history();
+            if let Err(e) = mqtt_rollouts.finish_due_broadcasts().await {
+                error!("Failed to report broadcast results: {}", e);
             }
         }
     });
+    tasks.push("rollout driver", rollouts);
+
+    // Dashboard clients see the messages the processor handles
+    #[cfg(feature = "http")]
+    let event_stream = EventStream::new(http_config.stream_capacity);
+    #[cfg(feature = "http")]
+    let bridge_stream = event_stream.clone();
+
+    // Keep the forensic trail, off the processor's path
+    let mut event_log_writer = None;
+    let event_logger = match &event_log.directory {
+        Some(dir) => {
+            let (logger, writer) = spawn_event_log(event_log.clone())
+                .with_context(|| format!("Failed to open the event log in {}", dir.display()))?;
+            info!("Logging engine messages to {}", dir.display());
+            event_log_writer = Some(writer);
+            Some(logger)
+        }
+        None => None,
+    };
 
     // Spawn message processor task
-    tokio::spawn(async move {
-        while let Some(msg) = message_rx.recv().await {
+    let mut processor_shutdown = shutdown.clone();
+    let processor = tokio::spawn(async move {
+        loop {
+            let msg = tokio::select! {
+                Some(msg) = message_rx.recv() => msg,
+                _ = processor_shutdown.wait() => break,
+                else => break,
+            };
+            if let Some(logger) = &event_logger {
+                logger.log(&msg);
+            }
+            #[cfg(feature = "http")]
+            bridge_stream.forward(&msg);
             match msg {
                 EngineMessage::TelemetryReceived(state) => {
                     debug!(robot_id = %state.id, "Telemetry received");
@@ -698,6 +521,13 @@ async fn main() -> Result<()> {
                         alert.description
                     );
                 }
+                EngineMessage::AlertTriaged(alert) => {
+                    info!(
+                        alert_id = %alert.id,
+                        severity = ?alert.severity,
+                        "Alert ready for dispatch"
+                    );
+                }
                 EngineMessage::EnvironmentReceived(env) => {
                     debug!(section_id = %env.section_id, pressure = env.pressure, "Environment data received");
                 }
@@ -709,41 +539,150 @@ async fn main() -> Result<()> {
                         "Command response received"
                     );
                 }
-                EngineMessage::CommandReceived(cmd, source) => {
+                EngineMessage::CommandReceived(received) => {
                     info!(
-                        source = %source,
-                        "Command received from dashboard: {:?}",
-                        cmd
+                        source = %received.source,
+                        command_id = %received.command_id,
+                        "Command received: {:?}",
+                        received.command
+                    );
+                    // The simulated robots act on it and respond; the
+                    // simulated sensors start and seal leaks
+                    for (simulation, commands) in &sim_commands {
+                        if commands.send(received.clone()).await.is_err() {
+                            warn!("{} stopped, command not delivered", simulation);
+                        }
+                    }
+                    if matches!(
+                        received.command,
+                        Command::StartMission { .. } | Command::AbortMission { .. }
+                    ) && mission_commands.send(received).await.is_err()
+                    {
+                        warn!("Mission executor stopped, command not delivered");
+                    }
+                }
+                EngineMessage::ConnectionStateChanged(state, broker) => {
+                    info!(?state, broker = %broker, "Broker connection state changed");
+                }
+                EngineMessage::BrokerSwitched(switch) => {
+                    debug!(from = %switch.from, to = %switch.to, reason = switch.reason.as_str(), "Broker switched");
+                }
+                EngineMessage::RobotReconnected(robot_id, offline_for) => {
+                    debug!(robot_id = %robot_id, offline_for = ?offline_for, "Robot reconnected");
+                }
+                EngineMessage::BroadcastCompleted(result) => {
+                    debug!(command_id = %result.command_id, "Broadcast settled: {}", result.summary());
+                }
+                EngineMessage::CommandStatusChanged(update) => {
+                    debug!(
+                        command_id = %update.command_id,
+                        robot_id = %update.robot_id,
+                        status = %update.status,
+                        "Command status changed"
                     );
                 }
             }
         }
+        if let Some(logger) = event_logger
+            && logger.dropped() > 0
+        {
+            warn!(
+                dropped = logger.dropped(),
+                "Event log could not keep up, events were dropped"
+            );
+        }
     });
+    tasks.push("message processor", processor);
+    if let Some(writer) = event_log_writer {
+        tasks.push("event log", writer);
+    }
+
+    // Serve the engine metrics for scraping
+    if let Some(addr) = metrics_listen {
+        let endpoint = spawn_metrics_endpoint(mqtt_handler.clone(), addr, shutdown.clone())
+            .await
+            .with_context(|| format!("Failed to serve metrics on {}", addr))?;
+        info!("Metrics served at http://{}/metrics", addr);
+        tasks.push("metrics endpoint", endpoint);
+    }
+
+    // Serve the dashboard bridge
+    #[cfg(feature = "http")]
+    if let Some(addr) = http_config.listen {
+        let bridge = http_bridge::spawn_bridge(
+            mqtt_handler.clone(),
+            event_stream,
+            &http_config,
+            addr,
+            shutdown.clone(),
+        )
+        .await
+        .with_context(|| format!("Failed to serve the dashboard bridge on {}", addr))?;
+        info!("Dashboard bridge served at http://{}", addr);
+        tasks.push("dashboard bridge", bridge);
+    }
+
+    // Publish the section health rollups that changed
+    tasks.push(
+        "section health publisher",
+        spawn_section_health_publisher(mqtt_handler.clone(), shutdown.clone()).await,
+    );
+
+    // Publish the system status and fleet summary periodically
+    tasks.push(
+        "status publisher",
+        spawn_status_publisher(mqtt_handler.clone(), shutdown).await,
+    );
 
     // Main event loop - process MQTT events
     info!("✅ AETHERIS Engine running. Press Ctrl+C to stop.");
 
-    loop {
-        match eventloop.poll().await {
-            Ok(Event::Incoming(Packet::Publish(publish))) => {
-                if let Err(e) = mqtt_handler
-                    .handle_incoming(&publish.topic, &publish.payload)
-                    .await
-                {
-                    error!("Failed to handle message on {}: {}", publish.topic, e);
-                }
-            }
-            Ok(Event::Incoming(Packet::SubAck(_))) => {
-                debug!("Subscription acknowledged");
-            }
-            Ok(Event::Incoming(Packet::ConnAck(_))) => {
-                info!("Connected to MQTT broker");
-            }
-            Ok(_) => {}
-            Err(e) => {
-                error!("MQTT connection error: {}. Retrying...", e);
-                tokio::time::sleep(Duration::from_secs(5)).await;
-            }
+    // Subscribes on every (re)connect and returns when the engine
+    // disconnected for shutdown or cannot go on: a fatal connection failure
+    // or nothing left listening
+    let run = mqtt_handler.run(eventloop);
+    tokio::pin!(run);
+    tokio::select! {
+        result = &mut run => {
+            result?;
+            info!("AETHERIS Engine stopped");
+            return Ok(());
+        }
+        signal = shutdown::signal() => info!(signal, "Shutting down..."),
+    }
+
+    // Stop the tasks, announce the engine offline, then disconnect; the
+    // event loop keeps running meanwhile to flush the pending publishes and
+    // returns once the disconnect went out
+    shutdown_trigger.trigger();
+    let deadline = tokio::time::Instant::now() + GRACE_PERIOD;
+    let stop = async {
+        let stragglers = tasks.join_until(deadline).await;
+        if let Err(e) = mqtt_handler.shutdown().await {
+            error!("Failed to announce the engine offline: {}", e);
+        }
+        stragglers
+    };
+    match tokio::time::timeout_at(deadline, async { tokio::join!(&mut run, stop) }).await {
+        Ok((Ok(()), stragglers)) if stragglers.is_empty() => {
+            info!("AETHERIS Engine stopped");
+            Ok(())
+        }
+        Ok((Ok(()), stragglers)) => {
+            error!(
+                "Grace period of {:?} exceeded, aborted: {}",
+                GRACE_PERIOD,
+                stragglers.join(", ")
+            );
+            std::process::exit(EXIT_SHUTDOWN_TIMEOUT);
+        }
+        Ok((Err(e), _)) => Err(e.into()),
+        Err(_) => {
+            error!(
+                "Grace period of {:?} exceeded before the broker disconnect",
+                GRACE_PERIOD
+            );
+            std::process::exit(EXIT_SHUTDOWN_TIMEOUT);
         }
     }
 }
@@ -0,0 +1,842 @@
+//! Engine metrics in the Prometheus text format
+//!
+//! Counters and latency histograms are plain atomics indexed by topic class,
+//! so recording on the hot path (every telemetry message of every robot) is
+//! a handful of relaxed increments with no lock and no allocation. Fleet
+//! gauges are not stored: they are read from the fleet when scraped.
+//!
+//! The scrape endpoint is a deliberately tiny HTTP/1.1 responder serving
+//! `GET /metrics` and nothing else; it is off unless an address is
+//! configured.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use aetheris_shared::topics::{self, Topic};
+use aetheris_shared::{DeadLetterReason, RobotStatus};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, warn};
+
+use crate::backpressure::LossyClass;
+use crate::config::{CheckConfig, ConfigChecker};
+use crate::offline_buffer::HoldClass;
+use crate::shutdown::Shutdown;
+use crate::wall_thickness::SectionWallTrend;
+
+/// Time a scraper gets to send its request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest request head read before answering anyway
+const MAX_REQUEST_HEAD: usize = 8 * 1024;
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Where the metrics are served
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetricsConfig {
+    /// Address of the `/metrics` endpoint; no endpoint when unset
+    pub listen: Option<SocketAddr>,
+}
+
+impl CheckConfig for MetricsConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if self.listen.is_some_and(|addr| addr.port() == 0) {
+            checker.error(
+                "listen",
+                "needs a port",
+                Some("scrapers need a fixed port to find the endpoint".into()),
+            );
+        }
+    }
+}
+
+// ============================================================================
+// TOPIC CLASSES
+// ============================================================================
+
+/// Kind of topic a message travelled on, the label of per-topic metrics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TopicClass {
+    Telemetry,
+    TelemetryBatch,
+    FilteredTelemetry,
+    Heartbeat,
+    Command,
+    Environment,
+    Response,
+    Alert,
+    SystemStatus,
+    LeaderClaim,
+    TriageRequest,
+    TriageResult,
+    DeadLetter,
+    Route,
+    RouteRequest,
+    SectionHealth,
+    Mission,
+    BroadcastResult,
+    Unknown,
+}
+
+const CLASSES: usize = 19;
+
+impl TopicClass {
+    pub const ALL: [TopicClass; CLASSES] = [
+        TopicClass::Telemetry,
+        TopicClass::TelemetryBatch,
+        TopicClass::FilteredTelemetry,
+        TopicClass::Heartbeat,
+        TopicClass::Command,
+        TopicClass::Environment,
+        TopicClass::Response,
+        TopicClass::Alert,
+        TopicClass::SystemStatus,
+        TopicClass::LeaderClaim,
+        TopicClass::TriageRequest,
+        TopicClass::TriageResult,
+        TopicClass::DeadLetter,
+        TopicClass::Route,
+        TopicClass::RouteRequest,
+        TopicClass::SectionHealth,
+        TopicClass::Mission,
+        TopicClass::BroadcastResult,
+        TopicClass::Unknown,
+    ];
+
+    /// Class of a topic string; topics outside the AETHERIS tree are
+    /// `Unknown`
+    pub fn of(topic: &str) -> Self {
+        match topics::parse(topic) {
+            Some(Topic::Telemetry { .. }) => Self::Telemetry,
+            Some(Topic::TelemetryBatch) => Self::TelemetryBatch,
+            Some(Topic::TelemetryFiltered { .. }) => Self::FilteredTelemetry,
+            Some(Topic::Heartbeat { .. }) => Self::Heartbeat,
+            Some(Topic::Commands { .. }) => Self::Command,
+            Some(Topic::Environment { .. }) => Self::Environment,
+            Some(Topic::Responses { .. }) => Self::Response,
+            Some(Topic::Alerts | Topic::AlertSeverity { .. } | Topic::AlertEscalations) => {
+                Self::Alert
+            }
+            Some(Topic::SystemStatus) => Self::SystemStatus,
+            Some(Topic::SystemLeader) => Self::LeaderClaim,
+            Some(Topic::TriageRequests) => Self::TriageRequest,
+            Some(Topic::TriageResults) => Self::TriageResult,
+            Some(Topic::DeadLetter) => Self::DeadLetter,
+            Some(Topic::Routes { .. } | Topic::RouteLibrary) => Self::Route,
+            Some(Topic::RouteRequests) => Self::RouteRequest,
+            Some(Topic::Health { .. }) => Self::SectionHealth,
+            Some(Topic::Missions { .. }) => Self::Mission,
+            Some(Topic::BroadcastResults) => Self::BroadcastResult,
+            None => Self::Unknown,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TopicClass::Telemetry => "telemetry",
+            TopicClass::TelemetryBatch => "telemetry_batch",
+            TopicClass::FilteredTelemetry => "telemetry_filtered",
+            TopicClass::Heartbeat => "heartbeat",
+            TopicClass::Command => "command",
+            TopicClass::Environment => "environment",
+            TopicClass::Response => "response",
+            TopicClass::Alert => "alert",
+            TopicClass::SystemStatus => "system_status",
+            TopicClass::LeaderClaim => "leader_claim",
+            TopicClass::TriageRequest => "triage_request",
+            TopicClass::TriageResult => "triage_result",
+            TopicClass::DeadLetter => "deadletter",
+            TopicClass::Route => "route",
+            TopicClass::RouteRequest => "route_request",
+            TopicClass::SectionHealth => "section_health",
+            TopicClass::Mission => "mission",
+            TopicClass::BroadcastResult => "broadcast_result",
+            TopicClass::Unknown => "unknown",
+        }
+    }
+}
+
+// ============================================================================
+// COUNTERS AND HISTOGRAMS
+// ============================================================================
+
+/// One counter per topic class
+#[derive(Debug, Default)]
+struct ClassCounters([AtomicU64; CLASSES]);
+
+impl ClassCounters {
+    fn add(&self, class: TopicClass) {
+        self.0[class as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn get(&self, class: TopicClass) -> u64 {
+        self.0[class as usize].load(Ordering::Relaxed)
+    }
+}
+
+/// Upper bounds (seconds) of the latency buckets, from an in-memory handoff
+/// to a stalled broker connection
+const LATENCY_BUCKETS: [f64; 12] = [
+    0.0001, 0.00025, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.5, 1.0,
+];
+
+/// Latency distribution over [`LATENCY_BUCKETS`]
+#[derive(Debug, Default)]
+struct Histogram {
+    /// Observations per bucket, not cumulative; the last one is `+Inf`
+    buckets: [AtomicU64; LATENCY_BUCKETS.len() + 1],
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    fn observe(&self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| secs <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} histogram");
+        let mut cumulative = 0;
+        for (i, count) in self.buckets.iter().enumerate() {
+            cumulative += count.load(Ordering::Relaxed);
+            match LATENCY_BUCKETS.get(i) {
+                Some(bound) => {
+                    let _ = writeln!(out, "{name}_bucket{{le=\"{bound}\"}} {cumulative}");
+                }
+                None => {
+                    let _ = writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {cumulative}");
+                }
+            }
+        }
+        let sum = self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        let _ = writeln!(out, "{name}_sum {sum}");
+        let _ = writeln!(out, "{name}_count {cumulative}");
+    }
+}
+
+// ============================================================================
+// METRICS
+// ============================================================================
+
+/// Counters and latencies of the MQTT hub
+#[derive(Debug, Default)]
+pub struct Metrics {
+    received: ClassCounters,
+    published: ClassCounters,
+    publish_failures: ClassCounters,
+    deserialization_failures: AtomicU64,
+    validation_rejections: AtomicU64,
+    signature_rejections: AtomicU64,
+    dead_letters: [AtomicU64; DeadLetterReason::ALL.len()],
+    dead_letters_suppressed: AtomicU64,
+    commands_sent: AtomicU64,
+    alerts_published: AtomicU64,
+    alerts_escalated: AtomicU64,
+    commands_unauthorized: AtomicU64,
+    commands_expired: AtomicU64,
+    reconnects: AtomicU64,
+    broker_failovers: AtomicU64,
+    broker_failbacks: AtomicU64,
+    active_broker: AtomicU64,
+    leadership_changes: AtomicU64,
+    leader: AtomicU64,
+    offline_held: [AtomicU64; HoldClass::ALL.len()],
+    offline_dropped: [AtomicU64; HoldClass::ALL.len()],
+    channel_dropped: [AtomicU64; LossyClass::ALL.len()],
+    command_queue_depth: AtomicU64,
+    handle_latency: Histogram,
+    publish_latency: Histogram,
+}
+
+impl Metrics {
+    /// A message received on `class` took `elapsed` to handle
+    pub fn record_received(&self, class: TopicClass, elapsed: Duration) {
+        self.received.add(class);
+        self.handle_latency.observe(elapsed);
+    }
+
+    /// A publish on `class` took `elapsed` to hand to the client
+    pub fn record_publish(&self, class: TopicClass, elapsed: Duration, succeeded: bool) {
+        if succeeded {
+            self.published.add(class);
+        } else {
+            self.publish_failures.add(class);
+        }
+        self.publish_latency.observe(elapsed);
+    }
+
+    pub fn record_deserialization_failure(&self) {
+        self.deserialization_failures
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_validation_rejection(&self) {
+        self.validation_rejections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_signature_rejection(&self) {
+        self.signature_rejections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A message was quarantined for `reason`; `published` is false when
+    /// its source's rate limit held the dead letter back
+    pub fn record_dead_letter(&self, reason: DeadLetterReason, published: bool) {
+        self.dead_letters[reason as usize].fetch_add(1, Ordering::Relaxed);
+        if !published {
+            self.dead_letters_suppressed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn record_command_sent(&self) {
+        self.commands_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_alert_published(&self) {
+        self.alerts_published.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_alert_escalated(&self) {
+        self.alerts_escalated.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_command_unauthorized(&self) {
+        self.commands_unauthorized.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_command_expired(&self) {
+        self.commands_expired.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_reconnect(&self) {
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_broker_failover(&self) {
+        self.broker_failovers.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_broker_failback(&self) {
+        self.broker_failbacks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Another instance took the lead, or this one did
+    pub fn record_leadership_change(&self) {
+        self.leadership_changes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A publish of `class` was not held, or dropped to make room, while
+    /// no broker was reachable
+    pub fn record_offline_drop(&self, class: HoldClass) {
+        self.offline_dropped[class as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A periodic message of `class` was superseded by a newer one before
+    /// the engine's consumer had room for it
+    pub fn record_channel_drop(&self, class: LossyClass) {
+        self.channel_dropped[class as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Periodic messages of `class` superseded before reaching the consumer
+    pub fn channel_dropped(&self, class: LossyClass) -> u64 {
+        self.channel_dropped[class as usize].load(Ordering::Relaxed)
+    }
+
+    /// Position of the broker in use among the configured ones, 0 for the
+    /// primary
+    pub fn set_active_broker(&self, index: usize) {
+        self.active_broker.store(index as u64, Ordering::Relaxed);
+    }
+
+    /// Whether this instance currently leads
+    pub fn set_leader(&self, leader: bool) {
+        self.leader.store(leader.into(), Ordering::Relaxed);
+    }
+
+    /// Publishes of `class` held until a broker is reachable
+    pub fn set_offline_held(&self, class: HoldClass, held: usize) {
+        self.offline_held[class as usize].store(held as u64, Ordering::Relaxed);
+    }
+
+    /// Commands waiting for the command dispatcher
+    pub fn set_command_queue_depth(&self, depth: usize) {
+        self.command_queue_depth
+            .store(depth as u64, Ordering::Relaxed);
+    }
+
+    /// Messages received on `class` so far
+    pub fn received(&self, class: TopicClass) -> u64 {
+        self.received.get(class)
+    }
+
+    /// Messages published on `class` so far
+    pub fn published(&self, class: TopicClass) -> u64 {
+        self.published.get(class)
+    }
+
+    /// Everything in the Prometheus text exposition format, with the robot
+    /// count per status as gauges
+    pub fn render(&self, robots: &HashMap<RobotStatus, usize>) -> String {
+        let mut out = String::new();
+        let per_class = [
+            (
+                "aetheris_messages_received_total",
+                "Messages received from the broker",
+                &self.received,
+            ),
+            (
+                "aetheris_messages_published_total",
+                "Messages handed to the broker client",
+                &self.published,
+            ),
+            (
+                "aetheris_publish_failures_total",
+                "Publishes the broker client refused",
+                &self.publish_failures,
+            ),
+        ];
+        for (name, help, counters) in per_class {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} counter");
+            for class in TopicClass::ALL {
+                let _ = writeln!(
+                    out,
+                    "{name}{{class=\"{}\"}} {}",
+                    class.as_str(),
+                    counters.get(class)
+                );
+            }
+        }
+
+        let totals = [
+            (
+                "aetheris_deserialization_failures_total",
+                "Payloads that could not be parsed",
+                &self.deserialization_failures,
+            ),
+            (
+                "aetheris_validation_rejections_total",
+                "Parsed messages dropped for invalid or out-of-bounds values",
+                &self.validation_rejections,
+            ),
+            (
+                "aetheris_signature_rejections_total",
+                "Envelopes dropped for a missing or bad source signature",
+                &self.signature_rejections,
+            ),
+            (
+                "aetheris_dead_letters_suppressed_total",
+                "Dead letters counted but not published, past their source's rate limit",
+                &self.dead_letters_suppressed,
+            ),
+            (
+                "aetheris_commands_sent_total",
+                "Commands published to robots, broadcasts included",
+                &self.commands_sent,
+            ),
+            (
+                "aetheris_alerts_published_total",
+                "Anomaly alerts published",
+                &self.alerts_published,
+            ),
+            (
+                "aetheris_alerts_escalated_total",
+                "Unacknowledged anomalies raised a severity level",
+                &self.alerts_escalated,
+            ),
+            (
+                "aetheris_commands_unauthorized_total",
+                "Commands refused for their operator, signature or role",
+                &self.commands_unauthorized,
+            ),
+            (
+                "aetheris_commands_expired_total",
+                "Commands received after they expired",
+                &self.commands_expired,
+            ),
+            (
+                "aetheris_reconnects_total",
+                "Reconnections to the broker",
+                &self.reconnects,
+            ),
+            (
+                "aetheris_broker_failovers_total",
+                "Moves to the next broker after repeated connection failures",
+                &self.broker_failovers,
+            ),
+            (
+                "aetheris_broker_failbacks_total",
+                "Moves back to a preferred broker that recovered",
+                &self.broker_failbacks,
+            ),
+            (
+                "aetheris_leadership_changes_total",
+                "Changes of the engine instance holding the lead",
+                &self.leadership_changes,
+            ),
+        ];
+        for (name, help, counter) in totals {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} counter");
+            let _ = writeln!(out, "{name} {}", counter.load(Ordering::Relaxed));
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP aetheris_dead_letters_total Messages quarantined instead of processed"
+        );
+        let _ = writeln!(out, "# TYPE aetheris_dead_letters_total counter");
+        for reason in DeadLetterReason::ALL {
+            let _ = writeln!(
+                out,
+                "aetheris_dead_letters_total{{reason=\"{}\"}} {}",
+                reason.as_str(),
+                self.dead_letters[reason as usize].load(Ordering::Relaxed)
+            );
+        }
+
+        self.handle_latency.render(
+            &mut out,
+            "aetheris_handle_incoming_seconds",
+            "Time to handle a received message",
+        );
+        self.publish_latency.render(
+            &mut out,
+            "aetheris_publish_seconds",
+            "Time to hand a message to the broker client",
+        );
+
+        let _ = writeln!(
+            out,
+            "# HELP aetheris_command_queue_depth Commands waiting for dispatch"
+        );
+        let _ = writeln!(out, "# TYPE aetheris_command_queue_depth gauge");
+        let _ = writeln!(
+            out,
+            "aetheris_command_queue_depth {}",
+            self.command_queue_depth.load(Ordering::Relaxed)
+        );
+        let _ = writeln!(
+            out,
+            "# HELP aetheris_active_broker Broker in use, 0 for the primary"
+        );
+        let _ = writeln!(out, "# TYPE aetheris_active_broker gauge");
+        let _ = writeln!(
+            out,
+            "aetheris_active_broker {}",
+            self.active_broker.load(Ordering::Relaxed)
+        );
+        let _ = writeln!(
+            out,
+            "# HELP aetheris_leader 1 while this instance leads the engines"
+        );
+        let _ = writeln!(out, "# TYPE aetheris_leader gauge");
+        let _ = writeln!(
+            out,
+            "aetheris_leader {}",
+            self.leader.load(Ordering::Relaxed)
+        );
+        let offline = [
+            (
+                "aetheris_offline_publishes_held",
+                "Publishes held until a broker is reachable",
+                "gauge",
+                &self.offline_held,
+            ),
+            (
+                "aetheris_offline_publishes_dropped_total",
+                "Publishes not held, or dropped from a full offline buffer",
+                "counter",
+                &self.offline_dropped,
+            ),
+        ];
+        for (name, help, kind, values) in offline {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} {kind}");
+            for class in HoldClass::ALL {
+                let _ = writeln!(
+                    out,
+                    "{name}{{class=\"{}\"}} {}",
+                    class.as_str(),
+                    values[class as usize].load(Ordering::Relaxed)
+                );
+            }
+        }
+        let _ = writeln!(
+            out,
+            "# HELP aetheris_channel_dropped_total Periodic messages superseded before the engine's consumer had room"
+        );
+        let _ = writeln!(out, "# TYPE aetheris_channel_dropped_total counter");
+        for class in LossyClass::ALL {
+            let _ = writeln!(
+                out,
+                "aetheris_channel_dropped_total{{class=\"{}\"}} {}",
+                class.as_str(),
+                self.channel_dropped(class)
+            );
+        }
+
+        let mut robots: Vec<_> = robots
+            .iter()
+            .map(|(status, count)| (status.as_str(), count))
+            .collect();
+        robots.sort();
+        let _ = writeln!(out, "# HELP aetheris_robots Robots known to the fleet");
+        let _ = writeln!(out, "# TYPE aetheris_robots gauge");
+        for (status, count) in robots {
+            let _ = writeln!(out, "aetheris_robots{{status=\"{status}\"}} {count}");
+        }
+        out
+    }
+}
+
+type WallGauge = fn(&SectionWallTrend) -> Option<f64>;
+
+/// Append the per-section wall-thickness gauges to a scrape
+pub fn render_wall_trends(out: &mut String, trends: &[SectionWallTrend]) {
+    let gauges: [(&str, &str, WallGauge); 3] = [
+        (
+            "aetheris_wall_thickness_mm",
+            "Fitted wall thickness per section",
+            |t| Some(t.thickness_mm),
+        ),
+        (
+            "aetheris_wall_loss_mm_per_year",
+            "Fitted wall loss rate per section",
+            |t| Some(t.loss_rate_mm_per_year),
+        ),
+        (
+            "aetheris_wall_breach_timestamp_seconds",
+            "When a section is projected to reach its minimum thickness",
+            |t| t.breach_at.map(|at| at as f64 / 1000.0),
+        ),
+    ];
+    for (name, help, value) in gauges {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} gauge");
+        for trend in trends {
+            if let Some(value) = value(trend) {
+                let _ = writeln!(out, "{name}{{section=\"{}\"}} {value}", trend.section_id);
+            }
+        }
+    }
+}
+
+// ============================================================================
+// SCRAPE ENDPOINT
+// ============================================================================
+
+/// Answer scrapes on `listener` with the body `scrape` renders, until the
+/// shutdown
+pub async fn serve<F, Fut>(listener: TcpListener, scrape: F, mut shutdown: Shutdown)
+where
+    F: Fn() -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = String> + Send,
+{
+    loop {
+        let (stream, peer) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!("Metrics endpoint failed to accept: {}", e);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            },
+            _ = shutdown.wait() => return,
+        };
+        let scrape = scrape.clone();
+        tokio::spawn(async move {
+            if let Err(e) = respond(stream, scrape).await {
+                debug!(%peer, "Metrics request failed: {}", e);
+            }
+        });
+    }
+}
+
+async fn respond<F, Fut>(mut stream: TcpStream, scrape: F) -> std::io::Result<()>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = String>,
+{
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    let read_head = async {
+        while !head.windows(4).any(|w| w == b"\r\n\r\n") && head.len() < MAX_REQUEST_HEAD {
+            let n = stream.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            head.extend_from_slice(&buf[..n]);
+        }
+        std::io::Result::Ok(())
+    };
+    tokio::time::timeout(REQUEST_TIMEOUT, read_head)
+        .await
+        .map_err(|_| std::io::ErrorKind::TimedOut)??;
+
+    let request_line = head.split(|b| *b == b'\n').next().unwrap_or_default();
+    let mut parts = std::str::from_utf8(request_line)
+        .unwrap_or_default()
+        .split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            let body = scrape().await;
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+        }
+        _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".into(),
+    };
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_counts_per_class_and_buckets_latency() {
+        let metrics = Metrics::default();
+        let telemetry = TopicClass::of(&topics::telemetry(&"RV-001".parse().unwrap()));
+        assert_eq!(telemetry, TopicClass::Telemetry);
+        metrics.record_received(telemetry, Duration::from_micros(80));
+        metrics.record_received(telemetry, Duration::from_millis(3));
+        metrics.record_publish(
+            TopicClass::of(topics::ALERTS),
+            Duration::from_secs(2),
+            false,
+        );
+        metrics.record_alert_published();
+        metrics.record_alert_escalated();
+        metrics.record_command_unauthorized();
+        metrics.record_signature_rejection();
+        metrics.record_command_expired();
+        metrics.record_reconnect();
+        metrics.set_command_queue_depth(3);
+        metrics.record_broker_failover();
+        metrics.set_active_broker(1);
+        metrics.record_leadership_change();
+        metrics.set_leader(true);
+        metrics.set_offline_held(HoldClass::Telemetry, 4);
+        metrics.record_offline_drop(HoldClass::Heartbeat);
+        metrics.record_channel_drop(LossyClass::Telemetry);
+
+        let robots = HashMap::from([(RobotStatus::Active, 2), (RobotStatus::Offline, 1)]);
+        let text = metrics.render(&robots);
+        for line in [
+            "aetheris_messages_received_total{class=\"telemetry\"} 2",
+            "aetheris_messages_received_total{class=\"alert\"} 0",
+            "aetheris_publish_failures_total{class=\"alert\"} 1",
+            "aetheris_messages_published_total{class=\"alert\"} 0",
+            "aetheris_alerts_published_total 1",
+            "aetheris_alerts_escalated_total 1",
+            "aetheris_commands_unauthorized_total 1",
+            "aetheris_signature_rejections_total 1",
+            "aetheris_commands_expired_total 1",
+            "aetheris_reconnects_total 1",
+            "aetheris_commands_sent_total 0",
+            "aetheris_command_queue_depth 3",
+            "aetheris_broker_failovers_total 1",
+            "aetheris_broker_failbacks_total 0",
+            "aetheris_active_broker 1",
+            "aetheris_leadership_changes_total 1",
+            "aetheris_leader 1",
+            "aetheris_offline_publishes_held{class=\"telemetry\"} 4",
+            "aetheris_offline_publishes_held{class=\"alert\"} 0",
+            "aetheris_offline_publishes_dropped_total{class=\"heartbeat\"} 1",
+            "aetheris_channel_dropped_total{class=\"telemetry\"} 1",
+            "aetheris_channel_dropped_total{class=\"environment\"} 0",
+            "aetheris_handle_incoming_seconds_bucket{le=\"0.0001\"} 1",
+            "aetheris_handle_incoming_seconds_bucket{le=\"0.0025\"} 1",
+            "aetheris_handle_incoming_seconds_bucket{le=\"0.005\"} 2",
+            "aetheris_handle_incoming_seconds_count 2",
+            "aetheris_handle_incoming_seconds_sum 0.00308",
+            "aetheris_publish_seconds_bucket{le=\"1\"} 0",
+            "aetheris_publish_seconds_bucket{le=\"+Inf\"} 1",
+            "aetheris_robots{status=\"active\"} 2",
+            "aetheris_robots{status=\"offline\"} 1",
+        ] {
+            assert!(
+                text.lines().any(|l| l == line),
+                "missing `{line}` in\n{text}"
+            );
+        }
+        assert_eq!(TopicClass::of("elsewhere/x"), TopicClass::Unknown);
+    }
+
+    #[test]
+    fn test_wall_trends_render_per_section() {
+        let mut text = String::new();
+        render_wall_trends(
+            &mut text,
+            &[SectionWallTrend {
+                section_id: "PIPE-002".to_string(),
+                thickness_mm: 11.5,
+                loss_rate_mm_per_year: 2.25,
+                significant: true,
+                breach_at: Some(1_800_000_000_000),
+                samples: 48,
+            }],
+        );
+        for line in [
+            "aetheris_wall_thickness_mm{section=\"PIPE-002\"} 11.5",
+            "aetheris_wall_loss_mm_per_year{section=\"PIPE-002\"} 2.25",
+            "aetheris_wall_breach_timestamp_seconds{section=\"PIPE-002\"} 1800000000",
+        ] {
+            assert!(
+                text.lines().any(|l| l == line),
+                "missing `{line}` in\n{text}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_endpoint_serves_metrics_only() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (trigger, shutdown) = crate::shutdown::channel();
+        let server = tokio::spawn(serve(
+            listener,
+            || async { "aetheris_reconnects_total 0\n".to_string() },
+            shutdown,
+        ));
+
+        let get = |path: &'static str| async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            let request = format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n");
+            stream.write_all(request.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        };
+        let metrics = get("/metrics").await;
+        assert!(metrics.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(metrics.ends_with("\r\n\r\naetheris_reconnects_total 0\n"));
+        assert!(get("/").await.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+        trigger.trigger();
+        server.await.unwrap();
+    }
+}
@@ -0,0 +1,652 @@
+//! Mission execution
+//!
+//! A [`MissionPlan`] is an ordered list of commands for one robot. The
+//! executor sends one step at a time and waits for the robot's
+//! [`CommandResponse`] to it (matched by command id, see [`crate::acks`]) or
+//! for the step's timeout. A step with [`Precondition`]s first waits, up to
+//! the same timeout, for the robot's latest telemetry to meet them. A
+//! refused or unanswered step, or one whose preconditions never held, is
+//! handled by its [`FailurePolicy`]: the mission fails, skips the step, or
+//! tries it again. Progress goes out as a [`MissionStatus`] at every
+//! attempt and once the mission ends. Aborting sends the robot a Stop,
+//! whatever step it is on.
+//!
+//! One robot runs at most one mission at a time; a second `StartMission`
+//! for it is refused until the first one ends or is aborted.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{mpsc, watch};
+use tokio::task::{JoinHandle, JoinSet};
+use tracing::{info, warn};
+
+use aetheris_shared::topics::CommandTarget;
+use aetheris_shared::{
+    Command, CommandResponse, FailurePolicy, MissionPlan, MissionState, MissionStatus, MissionStep,
+    Precondition, RobotId, RobotState,
+};
+
+use crate::ReceivedCommand;
+use crate::acks::AckError;
+use crate::shutdown::Shutdown;
+
+// ============================================================================
+// EXECUTION
+// ============================================================================
+
+/// How often a step waiting on its preconditions looks at the robot again
+const PRECONDITION_POLL: Duration = Duration::from_secs(1);
+
+/// What a mission needs from the engine
+pub trait MissionDriver: Sync {
+    /// Send `command` to `robot_id` and wait for the robot's response to it
+    fn execute(
+        &self,
+        robot_id: &str,
+        command: Command,
+    ) -> impl Future<Output = Result<CommandResponse, AckError>> + Send;
+
+    /// The robot's latest state, to check preconditions against
+    fn robot_state(&self, robot_id: &str) -> Option<RobotState>;
+
+    /// Halt the robot of an aborted mission
+    fn stop(&self, robot_id: &str) -> impl Future<Output = ()> + Send;
+
+    /// Publish a mission's progress
+    fn report(&self, status: &MissionStatus) -> impl Future<Output = ()> + Send;
+
+    /// Answer a mission command
+    fn answer(&self, response: &CommandResponse) -> impl Future<Output = ()> + Send;
+}
+
+/// Run `plan` to its end, or until `abort` turns true. Returns the final
+/// status, which was also reported.
+pub async fn run_mission<D: MissionDriver>(
+    driver: &D,
+    plan: &MissionPlan,
+    mut abort: watch::Receiver<bool>,
+) -> MissionStatus {
+    let mut status = MissionStatus {
+        mission_id: plan.id.clone(),
+        robot_id: plan.robot_id.clone(),
+        state: MissionState::Running,
+        step: 0,
+        steps: plan.steps.len(),
+        attempt: 0,
+        skipped: Vec::new(),
+        error: None,
+        timestamp: aetheris_shared::current_timestamp_ms(),
+    };
+
+    for (index, step) in plan.steps.iter().enumerate() {
+        let attempts = match step.on_failure {
+            FailurePolicy::Retry(retries) => retries.saturating_add(1),
+            FailurePolicy::Abort | FailurePolicy::Continue => 1,
+        };
+        status.step = index;
+        status.attempt = 0;
+        loop {
+            status.attempt += 1;
+            status.timestamp = aetheris_shared::current_timestamp_ms();
+            driver.report(&status).await;
+
+            status.error = tokio::select! {
+                outcome = attempt_step(driver, &plan.robot_id, step) => outcome.err(),
+                _ = aborted(&mut abort) => {
+                    info!(mission_id = %plan.id, step = index, "Mission aborted");
+                    driver.stop(&plan.robot_id).await;
+                    return finish(driver, status, MissionState::Aborted).await;
+                }
+            };
+            let Some(error) = &status.error else {
+                break;
+            };
+            warn!(
+                mission_id = %plan.id,
+                step = index,
+                attempt = status.attempt,
+                command = step.command.name(),
+                "Mission step failed: {}",
+                error
+            );
+            if status.attempt < attempts {
+                continue;
+            }
+            if step.on_failure == FailurePolicy::Continue {
+                status.skipped.push(index);
+                break;
+            }
+            return finish(driver, status, MissionState::Failed).await;
+        }
+    }
+    finish(driver, status, MissionState::Completed).await
+}
+
+/// Wait for the step's preconditions, then send its command and wait for
+/// the answer. Why the attempt failed, if it did.
+async fn attempt_step<D: MissionDriver>(
+    driver: &D,
+    robot_id: &str,
+    step: &MissionStep,
+) -> Result<(), String> {
+    let timeout = Duration::from_secs(step.timeout_secs);
+    if !step.preconditions.is_empty() {
+        let ready = async {
+            while unmet(driver, robot_id, &step.preconditions).is_some() {
+                tokio::time::sleep(PRECONDITION_POLL).await;
+            }
+        };
+        if tokio::time::timeout(timeout, ready).await.is_err()
+            && let Some(reason) = unmet(driver, robot_id, &step.preconditions)
+        {
+            return Err(format!(
+                "precondition not met within {}s: {}",
+                step.timeout_secs, reason
+            ));
+        }
+    }
+    match tokio::time::timeout(timeout, driver.execute(robot_id, step.command.clone())).await {
+        Ok(Ok(response)) if response.success => Ok(()),
+        Ok(Ok(response)) => Err(response
+            .error
+            .unwrap_or_else(|| "refused by the robot".to_string())),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err(format!("no response within {}s", step.timeout_secs)),
+    }
+}
+
+/// Why the robot does not meet `preconditions` yet, if it does not
+fn unmet<D: MissionDriver>(
+    driver: &D,
+    robot_id: &str,
+    preconditions: &[Precondition],
+) -> Option<String> {
+    let Some(state) = driver.robot_state(robot_id) else {
+        return Some("no telemetry from the robot".to_string());
+    };
+    preconditions
+        .iter()
+        .find(|p| !p.holds(&state))
+        .map(Precondition::to_string)
+}
+
+/// Resolves once the mission is aborted; never if its abort handle is
+/// dropped
+async fn aborted(abort: &mut watch::Receiver<bool>) {
+    if abort.wait_for(|aborted| *aborted).await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
+
+async fn finish<D: MissionDriver>(
+    driver: &D,
+    mut status: MissionStatus,
+    state: MissionState,
+) -> MissionStatus {
+    status.state = state;
+    status.timestamp = aetheris_shared::current_timestamp_ms();
+    driver.report(&status).await;
+    status
+}
+
+// ============================================================================
+// EXECUTOR TASK
+// ============================================================================
+
+struct RunningMission {
+    robot_id: String,
+    abort: watch::Sender<bool>,
+}
+
+/// Start a mission, or why not
+fn admit(
+    running: &HashMap<String, RunningMission>,
+    target: &CommandTarget,
+    plan: &MissionPlan,
+) -> Result<(), String> {
+    match target {
+        CommandTarget::Robot(robot_id) if *robot_id == plan.robot_id => {}
+        CommandTarget::Robot(robot_id) => {
+            return Err(format!(
+                "mission {} is planned for {}, not {}",
+                plan.id, plan.robot_id, robot_id
+            ));
+        }
+        CommandTarget::Broadcast => {
+            return Err("missions cannot be broadcast".to_string());
+        }
+    }
+    if running.contains_key(&plan.id) {
+        return Err(format!("mission {} is already running", plan.id));
+    }
+    if let Some((mission_id, _)) = running.iter().find(|(_, m)| m.robot_id == plan.robot_id) {
+        return Err(format!(
+            "{} is busy with mission {}",
+            plan.robot_id, mission_id
+        ));
+    }
+    Ok(())
+}
+
+/// Spawns the mission executor. Commands sent on the returned channel start
+/// and abort missions, and are answered on the engine's responses topic;
+/// other commands are ignored. Missions still running at shutdown are
+/// dropped mid-step.
+pub fn spawn_mission_executor<D>(
+    driver: Arc<D>,
+    mut shutdown: Shutdown,
+) -> (mpsc::Sender<ReceivedCommand>, JoinHandle<()>)
+where
+    D: MissionDriver + Send + 'static,
+{
+    let (command_tx, mut commands) = mpsc::channel::<ReceivedCommand>(64);
+    let task = tokio::spawn(async move {
+        let mut running: HashMap<String, RunningMission> = HashMap::new();
+        let mut missions: JoinSet<MissionStatus> = JoinSet::new();
+
+        loop {
+            let received = tokio::select! {
+                Some(received) = commands.recv() => received,
+                Some(Ok(status)) = missions.join_next() => {
+                    running.remove(&status.mission_id);
+                    info!(mission_id = %status.mission_id, state = ?status.state, "Mission ended");
+                    continue;
+                }
+                _ = shutdown.wait() => return,
+                else => return,
+            };
+            let outcome = match received.command {
+                Command::StartMission { plan } => {
+                    admit(&running, &received.target, &plan).map(|()| {
+                        let (abort, aborted) = watch::channel(false);
+                        running.insert(
+                            plan.id.clone(),
+                            RunningMission {
+                                robot_id: plan.robot_id.clone(),
+                                abort,
+                            },
+                        );
+                        info!(mission_id = %plan.id, robot_id = %plan.robot_id, steps = plan.steps.len(), "Mission started");
+                        let driver = driver.clone();
+                        missions.spawn(async move { run_mission(&*driver, &plan, aborted).await });
+                    })
+                }
+                Command::AbortMission { mission_id } => match running.get(&mission_id) {
+                    Some(mission) => {
+                        mission.abort.send_replace(true);
+                        Ok(())
+                    }
+                    None => Err(format!("no running mission {mission_id}")),
+                },
+                _ => continue,
+            };
+            if let Err(e) = &outcome {
+                warn!(command_id = %received.command_id, "Mission command refused: {}", e);
+            }
+            driver
+                .answer(&CommandResponse {
+                    command_id: received.command_id,
+                    robot_id: RobotId::engine(),
+                    success: outcome.is_ok(),
+                    error: outcome.err(),
+                    config: None,
+                    status: None,
+                    timestamp: aetheris_shared::current_timestamp_ms(),
+                })
+                .await;
+        }
+    });
+    (command_tx, task)
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    use aetheris_shared::{Position, RobotStatus, RobotType, ScanType, Timestamp};
+
+    use crate::shutdown;
+
+    /// How the fake robot answers one command
+    #[derive(Debug, Clone)]
+    enum Reply {
+        Done,
+        Refused(&'static str),
+        /// Never answers
+        Silent,
+    }
+
+    /// Answers commands from a script, recording everything the mission
+    /// does
+    #[derive(Default)]
+    struct ScriptedRobot {
+        script: Mutex<VecDeque<Reply>>,
+        executed: Mutex<Vec<&'static str>>,
+        state: Mutex<Option<RobotState>>,
+        stops: Mutex<Vec<String>>,
+        reports: Mutex<Vec<MissionStatus>>,
+        answers: Mutex<Vec<CommandResponse>>,
+    }
+
+    impl ScriptedRobot {
+        fn new(script: impl IntoIterator<Item = Reply>) -> Self {
+            Self {
+                script: Mutex::new(script.into_iter().collect()),
+                ..Self::default()
+            }
+        }
+
+        fn executed(&self) -> Vec<&'static str> {
+            self.executed.lock().unwrap().clone()
+        }
+
+        /// (state, step, attempt) of every report
+        fn progress(&self) -> Vec<(MissionState, usize, u32)> {
+            self.reports
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|s| (s.state, s.step, s.attempt))
+                .collect()
+        }
+    }
+
+    impl MissionDriver for ScriptedRobot {
+        async fn execute(
+            &self,
+            robot_id: &str,
+            command: Command,
+        ) -> Result<CommandResponse, AckError> {
+            self.executed.lock().unwrap().push(command.name());
+            let reply = self
+                .script
+                .lock()
+                .unwrap()
+                .pop_front()
+                .unwrap_or(Reply::Done);
+            let error = match reply {
+                Reply::Done => None,
+                Reply::Refused(why) => Some(why.to_string()),
+                Reply::Silent => std::future::pending().await,
+            };
+            Ok(CommandResponse {
+                command_id: "CMD-1".into(),
+                robot_id: robot_id.parse().unwrap(),
+                success: error.is_none(),
+                error,
+                config: None,
+                status: None,
+                timestamp: 0,
+            })
+        }
+
+        fn robot_state(&self, _robot_id: &str) -> Option<RobotState> {
+            self.state.lock().unwrap().clone()
+        }
+
+        async fn stop(&self, robot_id: &str) {
+            self.stops.lock().unwrap().push(robot_id.to_string());
+        }
+
+        async fn report(&self, status: &MissionStatus) {
+            self.reports.lock().unwrap().push(status.clone());
+        }
+
+        async fn answer(&self, response: &CommandResponse) {
+            self.answers.lock().unwrap().push(response.clone());
+        }
+    }
+
+    fn step(command: Command, on_failure: FailurePolicy) -> MissionStep {
+        MissionStep {
+            command,
+            timeout_secs: 10,
+            on_failure,
+            preconditions: Vec::new(),
+        }
+    }
+
+    fn inspection(scan_policy: FailurePolicy) -> MissionPlan {
+        MissionPlan {
+            id: "MSN-1".into(),
+            robot_id: "CR-001".into(),
+            steps: vec![
+                step(
+                    Command::MoveTo {
+                        target: Position::new(12.0, -0.5, 5.0),
+                        speed: None,
+                    },
+                    FailurePolicy::Abort,
+                ),
+                step(
+                    Command::PerformScan {
+                        scan_type: ScanType::Ultrasonic,
+                    },
+                    scan_policy,
+                ),
+                step(Command::ReturnToBase, FailurePolicy::Abort),
+            ],
+        }
+    }
+
+    fn not_aborted() -> watch::Receiver<bool> {
+        watch::channel(false).1
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_timed_out_step_is_skipped_under_continue() {
+        let robot = ScriptedRobot::new([Reply::Done, Reply::Silent]);
+
+        let status = run_mission(&robot, &inspection(FailurePolicy::Continue), not_aborted()).await;
+
+        assert_eq!(status.state, MissionState::Completed);
+        assert_eq!(status.skipped, [1]);
+        assert_eq!(
+            robot.executed(),
+            ["move_to", "perform_scan", "return_to_base"]
+        );
+        assert_eq!(
+            robot.progress(),
+            [
+                (MissionState::Running, 0, 1),
+                (MissionState::Running, 1, 1),
+                (MissionState::Running, 2, 1),
+                (MissionState::Completed, 2, 1),
+            ]
+        );
+        // The timeout is reported with the next step
+        let reports = robot.reports.lock().unwrap();
+        assert_eq!(reports[2].error.as_deref(), Some("no response within 10s"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retries_run_out_and_fail_the_mission() {
+        let robot = ScriptedRobot::new([
+            Reply::Done,
+            Reply::Refused("sensor offline"),
+            Reply::Silent,
+            Reply::Refused("sensor offline"),
+        ]);
+
+        let status = run_mission(&robot, &inspection(FailurePolicy::Retry(2)), not_aborted()).await;
+
+        assert_eq!(status.state, MissionState::Failed);
+        assert_eq!((status.step, status.attempt), (1, 3));
+        assert_eq!(status.error.as_deref(), Some("sensor offline"));
+        assert_eq!(
+            robot.executed(),
+            ["move_to", "perform_scan", "perform_scan", "perform_scan"]
+        );
+        assert!(robot.stops.lock().unwrap().is_empty());
+
+        // A retry that succeeds carries on
+        let robot = ScriptedRobot::new([Reply::Done, Reply::Silent]);
+        let status = run_mission(&robot, &inspection(FailurePolicy::Retry(1)), not_aborted()).await;
+        assert_eq!(status.state, MissionState::Completed);
+        assert_eq!(status.error, None);
+        assert_eq!(robot.executed().len(), 4);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_steps_wait_for_their_preconditions() {
+        let robot = ScriptedRobot::new([]);
+        let mut plan = inspection(FailurePolicy::Abort);
+        plan.steps[1].preconditions = vec![
+            Precondition::Status(RobotStatus::Idle),
+            Precondition::MinBattery(50.0),
+        ];
+
+        // Nothing known of the robot: the scan never starts
+        let status = run_mission(&robot, &plan, not_aborted()).await;
+        assert_eq!((status.state, status.step), (MissionState::Failed, 1));
+        assert_eq!(
+            status.error.as_deref(),
+            Some("precondition not met within 10s: no telemetry from the robot")
+        );
+        assert_eq!(robot.executed(), ["move_to"]);
+
+        let mut state = RobotState::new_at(
+            "CR-001".parse().unwrap(),
+            "Crawler",
+            RobotType::Crawler,
+            Timestamp::from_millis(0),
+        );
+        state.status = RobotStatus::Idle;
+        state.battery = 30.0;
+        *robot.state.lock().unwrap() = Some(state.clone());
+        let status = run_mission(&robot, &plan, not_aborted()).await;
+        assert_eq!(
+            status.error.as_deref(),
+            Some("precondition not met within 10s: battery at least 50%")
+        );
+
+        // The robot charges while the step waits
+        let robot = ScriptedRobot::new([]);
+        *robot.state.lock().unwrap() = Some(state.clone());
+        let charge = async {
+            tokio::time::sleep(Duration::from_secs(4)).await;
+            state.battery = 80.0;
+            *robot.state.lock().unwrap() = Some(state);
+        };
+        let (status, ()) = tokio::join!(run_mission(&robot, &plan, not_aborted()), charge);
+        assert_eq!(status.state, MissionState::Completed);
+        assert_eq!(
+            robot.executed(),
+            ["move_to", "perform_scan", "return_to_base"]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_abort_mid_step_stops_the_robot() {
+        let (shutdown_trigger, shutdown) = shutdown::channel();
+        let robot = Arc::new(ScriptedRobot::new([Reply::Done, Reply::Silent]));
+        let (commands, executor) = spawn_mission_executor(robot.clone(), shutdown);
+        let received = |command_id: &str, target: &str, command| ReceivedCommand {
+            command,
+            source: "dashboard".into(),
+            target: CommandTarget::Robot(target.parse().unwrap()),
+            command_id: command_id.into(),
+            expires_at: None,
+        };
+
+        let plan = inspection(FailurePolicy::Abort);
+        commands
+            .send(received("CMD-A", "CR-001", Command::StartMission { plan }))
+            .await
+            .unwrap();
+        // The robot is busy: a second mission for it is refused
+        let mut other = inspection(FailurePolicy::Abort);
+        other.id = "MSN-2".into();
+        commands
+            .send(received(
+                "CMD-B",
+                "CR-001",
+                Command::StartMission { plan: other },
+            ))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        assert_eq!(robot.executed(), ["move_to", "perform_scan"]);
+
+        commands
+            .send(received(
+                "CMD-C",
+                "CR-001",
+                Command::AbortMission {
+                    mission_id: "MSN-1".into(),
+                },
+            ))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+
+        assert_eq!(*robot.stops.lock().unwrap(), ["CR-001"]);
+        let last = robot.reports.lock().unwrap().last().cloned().unwrap();
+        assert_eq!((last.state, last.step), (MissionState::Aborted, 1));
+        assert_eq!(robot.executed().len(), 2);
+
+        // Aborting again finds nothing running
+        commands
+            .send(received(
+                "CMD-D",
+                "CR-001",
+                Command::AbortMission {
+                    mission_id: "MSN-1".into(),
+                },
+            ))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let answers: Vec<_> = robot
+            .answers
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|a| (a.command_id.clone(), a.success, a.robot_id.is_engine()))
+            .collect();
+        assert_eq!(
+            answers,
+            [
+                ("CMD-A".to_string(), true, true),
+                ("CMD-B".to_string(), false, true),
+                ("CMD-C".to_string(), true, true),
+                ("CMD-D".to_string(), false, true),
+            ]
+        );
+
+        shutdown_trigger.trigger();
+        executor.await.unwrap();
+    }
+
+    #[test]
+    fn test_missions_must_address_their_robot() {
+        let plan = inspection(FailurePolicy::Abort);
+        let running = HashMap::new();
+        assert_eq!(
+            admit(
+                &running,
+                &CommandTarget::Robot("CR-001".parse().unwrap()),
+                &plan
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            admit(
+                &running,
+                &CommandTarget::Robot("RV-001".parse().unwrap()),
+                &plan
+            ),
+            Err("mission MSN-1 is planned for CR-001, not RV-001".into())
+        );
+        assert!(admit(&running, &CommandTarget::Broadcast, &plan).is_err());
+    }
+}
@@ -0,0 +1,325 @@
+//! Publishes held while the broker is unreachable
+//!
+//! Once a live connection drops, publishes are held here instead of being
+//! handed to the client, and each class of message gets the treatment its
+//! value calls for:
+//!
+//! - alerts are kept in order up to a large bound; past it the new alert is
+//!   refused rather than an older one dropped, so the caller learns of it
+//! - telemetry keeps only the newest state of each robot (one per topic),
+//!   in a small ring that drops the robot updated least recently
+//! - heartbeats are not held at all: a fresh one follows shortly
+//! - everything else (commands, responses, status) is kept in order,
+//!   oldest dropped first
+//!
+//! On reconnect the buffer is flushed before any new traffic, alerts first,
+//! then everything else, then telemetry.
+
+use std::collections::VecDeque;
+
+use rumqttc::QoS;
+
+use crate::config::{CheckConfig, ConfigChecker};
+use crate::metrics::TopicClass;
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Bounds of the offline buffer, per class
+#[derive(Debug, Clone, PartialEq)]
+pub struct OfflineBufferConfig {
+    /// Alerts held; further ones are refused
+    pub alerts: usize,
+    /// Robots whose latest telemetry is held
+    pub telemetry: usize,
+    /// Other publishes held, oldest dropped first
+    pub other: usize,
+}
+
+impl Default for OfflineBufferConfig {
+    fn default() -> Self {
+        Self {
+            alerts: 10_000,
+            telemetry: 200,
+            other: 1_000,
+        }
+    }
+}
+
+impl CheckConfig for OfflineBufferConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        for (field, bound) in [
+            ("alerts", self.alerts),
+            ("telemetry", self.telemetry),
+            ("other", self.other),
+        ] {
+            if bound == 0 {
+                checker.error(field, "must be at least 1", None);
+            }
+        }
+    }
+}
+
+// ============================================================================
+// CLASSES
+// ============================================================================
+
+/// How a publish is held, in flush order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HoldClass {
+    Alert,
+    Other,
+    Telemetry,
+    Heartbeat,
+}
+
+impl HoldClass {
+    pub const ALL: [HoldClass; 4] = [
+        HoldClass::Alert,
+        HoldClass::Other,
+        HoldClass::Telemetry,
+        HoldClass::Heartbeat,
+    ];
+
+    pub fn of(topic: &str) -> Self {
+        match TopicClass::of(topic) {
+            TopicClass::Alert => Self::Alert,
+            TopicClass::Telemetry | TopicClass::FilteredTelemetry => Self::Telemetry,
+            TopicClass::Heartbeat => Self::Heartbeat,
+            _ => Self::Other,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Alert => "alert",
+            Self::Other => "other",
+            Self::Telemetry => "telemetry",
+            Self::Heartbeat => "heartbeat",
+        }
+    }
+}
+
+// ============================================================================
+// BUFFER
+// ============================================================================
+
+/// A publish held until a broker is reachable
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeldPublish {
+    pub topic: String,
+    pub qos: QoS,
+    pub retain: bool,
+    pub payload: Vec<u8>,
+}
+
+impl From<rumqttc::Publish> for HeldPublish {
+    fn from(publish: rumqttc::Publish) -> Self {
+        Self {
+            topic: publish.topic,
+            qos: publish.qos,
+            retain: publish.retain,
+            payload: publish.payload.to_vec(),
+        }
+    }
+}
+
+/// What became of a publish handed to the buffer
+#[derive(Debug, Clone, PartialEq)]
+pub enum Hold {
+    Held,
+    /// Held in place of the previous state of the same robot
+    Coalesced,
+    /// Held, and the oldest of its class dropped to make room
+    DroppedOldest(HeldPublish),
+    /// Not held: alerts are full
+    Refused(HeldPublish),
+    /// Not held: heartbeats are not worth holding
+    Discarded,
+}
+
+/// Held publishes by class, each oldest first
+#[derive(Debug)]
+pub struct OfflineBuffer {
+    config: OfflineBufferConfig,
+    /// Holding since the connection dropped, until the buffer is flushed
+    holding: bool,
+    alerts: VecDeque<HeldPublish>,
+    other: VecDeque<HeldPublish>,
+    /// One per topic, least recently updated first
+    telemetry: VecDeque<HeldPublish>,
+}
+
+impl OfflineBuffer {
+    pub fn new(config: OfflineBufferConfig) -> Self {
+        Self {
+            config,
+            holding: false,
+            alerts: VecDeque::new(),
+            other: VecDeque::new(),
+            telemetry: VecDeque::new(),
+        }
+    }
+
+    /// The connection dropped: hold publishes until the next flush
+    pub fn start_holding(&mut self) {
+        self.holding = true;
+    }
+
+    /// Whether publishes should be held rather than sent, so that they do
+    /// not overtake held ones
+    pub fn is_holding(&self) -> bool {
+        self.holding || !self.is_empty()
+    }
+
+    pub fn push(&mut self, publish: HeldPublish) -> Hold {
+        match HoldClass::of(&publish.topic) {
+            HoldClass::Alert => {
+                if self.alerts.len() >= self.config.alerts {
+                    return Hold::Refused(publish);
+                }
+                self.alerts.push_back(publish);
+                Hold::Held
+            }
+            HoldClass::Telemetry => {
+                let previous = self.telemetry.iter().position(|t| t.topic == publish.topic);
+                if let Some(previous) = previous {
+                    self.telemetry.remove(previous);
+                    self.telemetry.push_back(publish);
+                    return Hold::Coalesced;
+                }
+                push_bounded(&mut self.telemetry, self.config.telemetry, publish)
+            }
+            HoldClass::Other => push_bounded(&mut self.other, self.config.other, publish),
+            HoldClass::Heartbeat => Hold::Discarded,
+        }
+    }
+
+    /// The next publish to flush, in priority order. Holding stops once the
+    /// buffer is empty.
+    pub fn pop(&mut self) -> Option<HeldPublish> {
+        let next = self
+            .alerts
+            .pop_front()
+            .or_else(|| self.other.pop_front())
+            .or_else(|| self.telemetry.pop_front());
+        if next.is_none() {
+            self.holding = false;
+        }
+        next
+    }
+
+    /// Put back a publish that could not be sent after all, keeping its place
+    pub fn unpop(&mut self, publish: HeldPublish) {
+        match HoldClass::of(&publish.topic) {
+            HoldClass::Alert => self.alerts.push_front(publish),
+            HoldClass::Telemetry => self.telemetry.push_front(publish),
+            HoldClass::Other | HoldClass::Heartbeat => self.other.push_front(publish),
+        }
+    }
+
+    /// Publishes held of a class
+    pub fn held(&self, class: HoldClass) -> usize {
+        match class {
+            HoldClass::Alert => self.alerts.len(),
+            HoldClass::Other => self.other.len(),
+            HoldClass::Telemetry => self.telemetry.len(),
+            HoldClass::Heartbeat => 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.alerts.len() + self.other.len() + self.telemetry.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn push_bounded(queue: &mut VecDeque<HeldPublish>, bound: usize, publish: HeldPublish) -> Hold {
+    let dropped = (queue.len() >= bound).then(|| queue.pop_front()).flatten();
+    queue.push_back(publish);
+    match dropped {
+        Some(dropped) => Hold::DroppedOldest(dropped),
+        None => Hold::Held,
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetheris_shared::topics;
+
+    fn held(topic: String, n: u8) -> HeldPublish {
+        HeldPublish {
+            topic,
+            qos: QoS::AtLeastOnce,
+            retain: false,
+            payload: vec![n],
+        }
+    }
+
+    #[test]
+    fn test_flushes_alerts_first_and_keeps_latest_telemetry() {
+        let mut buffer = OfflineBuffer::new(OfflineBufferConfig::default());
+        assert!(!buffer.is_holding());
+        buffer.start_holding();
+
+        let telemetry = |robot_id: &str, n| held(topics::telemetry(&robot_id.parse().unwrap()), n);
+        assert_eq!(buffer.push(telemetry("RV-001", 1)), Hold::Held);
+        assert_eq!(buffer.push(telemetry("DR-001", 2)), Hold::Held);
+        assert_eq!(buffer.push(telemetry("RV-001", 3)), Hold::Coalesced);
+        assert_eq!(
+            buffer.push(held(topics::heartbeat(&"RV-001".parse().unwrap()), 4)),
+            Hold::Discarded
+        );
+        assert_eq!(buffer.push(held(topics::ALERTS.into(), 5)), Hold::Held);
+        assert_eq!(
+            buffer.push(held(topics::commands(&"RV-001".parse().unwrap()), 6)),
+            Hold::Held
+        );
+        assert_eq!(buffer.push(held(topics::ALERTS.into(), 7)), Hold::Held);
+        assert_eq!(buffer.held(HoldClass::Telemetry), 2);
+
+        let first = buffer.pop().unwrap();
+        buffer.unpop(first);
+        let order: Vec<u8> = std::iter::from_fn(|| buffer.pop())
+            .map(|p| p.payload[0])
+            .collect();
+        assert_eq!(order, [5, 7, 6, 2, 3]);
+        assert!(!buffer.is_holding());
+    }
+
+    #[test]
+    fn test_bounds_refuse_alerts_and_drop_the_oldest_of_the_rest() {
+        let mut buffer = OfflineBuffer::new(OfflineBufferConfig {
+            alerts: 1,
+            telemetry: 1,
+            other: 1,
+        });
+        assert_eq!(buffer.push(held(topics::ALERTS.into(), 1)), Hold::Held);
+        assert_eq!(
+            buffer.push(held(topics::ALERTS.into(), 2)),
+            Hold::Refused(held(topics::ALERTS.into(), 2))
+        );
+        buffer.push(held(topics::telemetry(&"RV-001".parse().unwrap()), 3));
+        assert_eq!(
+            buffer.push(held(topics::telemetry(&"RV-002".parse().unwrap()), 4)),
+            Hold::DroppedOldest(held(topics::telemetry(&"RV-001".parse().unwrap()), 3))
+        );
+        buffer.push(held(topics::SYSTEM_STATUS.into(), 5));
+        assert!(matches!(
+            buffer.push(held(topics::SYSTEM_STATUS.into(), 6)),
+            Hold::DroppedOldest(_)
+        ));
+        assert_eq!(buffer.len(), 3);
+        // Held publishes alone keep later ones from overtaking them
+        assert!(buffer.is_holding());
+    }
+}
@@ -0,0 +1,983 @@
+//! Operator commands against a running fleet
+//!
+//! `aetheris-engine --cli <command>` connects to the broker as a client of
+//! its own, sends one command or reads what the fleet reports, and exits:
+//!
+//! ```text
+//! aetheris-engine --cli fleet list
+//! aetheris-engine --cli robot show RV-001
+//! aetheris-engine --cli send RV-001 move-to --x 10 --y 0 --z 5 --speed 2
+//! aetheris-engine --cli send --broadcast emergency-stop
+//! aetheris-engine --cli alerts tail --min-severity high
+//! aetheris-engine --cli inject-fault DR-001 gps-drift
+//! ```
+//!
+//! A command is published like the engine publishes its own and the
+//! response it correlates to is awaited; the exit code says whether it was
+//! carried out. The fleet is what robots reported on their telemetry topics
+//! while the client listened, retained telemetry included. Enum values are
+//! the wire names with dashes for underscores, parsed by the shared
+//! `FromStr` impls. Output is an aligned table, or JSON with `--json`.
+
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::builder::{PossibleValue, PossibleValuesParser, TypedValueParser};
+use clap::{Args, Parser, Subcommand};
+use rumqttc::{ConnectionError, Event, EventLoop, MqttOptions, Packet, Publish};
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+use aetheris_shared::topics::{self, Topic};
+use aetheris_shared::{
+    AnomalyReport, BroadcastResult, Command, CommandResponse, CurrentTask, Encoding, FaultType,
+    MqttMessage, Position, RobotConfig, RobotId, RobotState, ScanType, SeverityLevel,
+    TelemetryPayload, Timestamp, UnknownName, Validate,
+};
+
+use crate::anomaly_store::AnomalyStoreConfig;
+use crate::config::{ConfigFile, EXIT_INVALID_CONFIG, EngineConfig};
+use crate::detector_eval::EXIT_USAGE;
+use crate::error::Result;
+use crate::robot_config::RobotConfigStorage;
+use crate::{AetherisMqtt, AlertTopics, EngineMessage, transport};
+
+/// Exit code when a command was refused or failed, or a robot is not
+/// reporting
+pub const EXIT_FAILED: i32 = 1;
+
+/// Exit code when the broker cannot be reached (sysexits `EX_UNAVAILABLE`)
+pub const EXIT_UNAVAILABLE: i32 = 69;
+
+/// Exit code when a response did not arrive in time (sysexits `EX_TEMPFAIL`)
+pub const EXIT_TIMEOUT: i32 = 75;
+
+// ============================================================================
+// COMMAND LINE
+// ============================================================================
+
+/// Send commands to the fleet and read what it reports
+#[derive(Debug, Parser)]
+#[command(no_binary_name = true, bin_name = "aetheris-engine --cli")]
+pub struct OperatorCli {
+    /// Engine configuration file, for the broker, credentials and TLS
+    #[arg(long, value_name = "FILE", env = "AETHERIS_CONFIG", global = true)]
+    pub config: Option<PathBuf>,
+    /// MQTT broker host
+    #[arg(long, env = "AETHERIS_BROKER_HOST", global = true)]
+    pub broker_host: Option<String>,
+    /// MQTT broker port
+    #[arg(long, env = "AETHERIS_BROKER_PORT", global = true)]
+    pub broker_port: Option<u16>,
+    /// Seconds to wait for the response to a command
+    #[arg(long, value_name = "SECS", default_value = "5", value_parser = seconds, global = true)]
+    pub timeout: Duration,
+    /// Seconds to listen for robot telemetry before using the fleet
+    #[arg(long, value_name = "SECS", default_value = "2", value_parser = seconds, global = true)]
+    pub listen: Duration,
+    /// Print JSON instead of tables
+    #[arg(long, global = true)]
+    pub json: bool,
+    #[command(subcommand)]
+    pub operation: Operation,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Operation {
+    /// The robots reporting telemetry
+    Fleet {
+        #[command(subcommand)]
+        action: FleetAction,
+    },
+    /// One robot
+    Robot {
+        #[command(subcommand)]
+        action: RobotAction,
+    },
+    /// Send a command and wait for its response
+    Send(SendArgs),
+    /// Alerts as they are raised
+    Alerts {
+        #[command(subcommand)]
+        action: AlertsAction,
+    },
+    /// Inject a simulated fault; short for `send <ROBOT_ID> inject-fault`
+    InjectFault {
+        robot_id: RobotId,
+        #[arg(value_parser = wire_name(&FaultType::ALL))]
+        fault_type: FaultType,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum FleetAction {
+    /// Every robot with its latest telemetry
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RobotAction {
+    /// Latest telemetry and effective configuration
+    Show { robot_id: RobotId },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AlertsAction {
+    /// Print alerts as they arrive, until interrupted
+    Tail {
+        /// Leave out alerts below this severity
+        #[arg(long, value_parser = wire_name(&SeverityLevel::ALL))]
+        min_severity: Option<SeverityLevel>,
+        /// Exit after this many alerts
+        #[arg(long)]
+        count: Option<usize>,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct SendArgs {
+    /// Robot to command
+    #[arg(required_unless_present = "broadcast")]
+    pub robot_id: Option<RobotId>,
+    /// Send to every robot, waiting for those reporting telemetry
+    #[arg(long, conflicts_with = "robot_id")]
+    pub broadcast: bool,
+    #[command(subcommand)]
+    pub command: CommandArgs,
+}
+
+/// The commands an operator sends, as subcommands
+#[derive(Debug, Clone, Subcommand)]
+pub enum CommandArgs {
+    /// Move to a position
+    MoveTo {
+        #[arg(long, allow_negative_numbers = true)]
+        x: f64,
+        #[arg(long, allow_negative_numbers = true)]
+        y: f64,
+        #[arg(long, allow_negative_numbers = true)]
+        z: f64,
+        /// Speed in m/s, the robot's own when unset
+        #[arg(long)]
+        speed: Option<f64>,
+    },
+    /// Stop all movement
+    Stop,
+    /// Stop at once, ahead of everything queued
+    EmergencyStop,
+    /// Return to the charging station
+    ReturnToBase,
+    /// Perform a sensor scan
+    PerformScan {
+        #[arg(value_parser = wire_name(&ScanType::ALL))]
+        scan_type: ScanType,
+    },
+    /// Start a patrol route
+    StartPatrol { route_id: String },
+    /// Investigate an anomaly
+    Investigate { anomaly_id: String },
+    /// Inject a simulated fault
+    InjectFault {
+        #[arg(value_parser = wire_name(&FaultType::ALL))]
+        fault_type: FaultType,
+    },
+    /// Clear an injected fault, or every fault when none is named
+    ClearFault {
+        #[arg(value_parser = wire_name(&FaultType::ALL))]
+        fault_type: Option<FaultType>,
+    },
+    /// Change settings; those left out keep their value
+    Configure {
+        /// Maximum speed in m/s
+        #[arg(long)]
+        max_speed: Option<f64>,
+        /// Scan interval in seconds
+        #[arg(long)]
+        scan_interval: Option<u32>,
+        /// Heartbeat interval in seconds
+        #[arg(long)]
+        heartbeat_interval: Option<u32>,
+        /// Low battery threshold percentage
+        #[arg(long)]
+        low_battery_threshold: Option<f64>,
+    },
+    /// Show the effective configuration
+    GetConfig,
+    /// Stop a running mission
+    AbortMission { mission_id: String },
+}
+
+impl From<CommandArgs> for Command {
+    fn from(args: CommandArgs) -> Self {
+        match args {
+            CommandArgs::MoveTo { x, y, z, speed } => Command::MoveTo {
+                target: Position::new(x, y, z),
+                speed,
+            },
+            CommandArgs::Stop => Command::Stop,
+            CommandArgs::EmergencyStop => Command::EmergencyStop,
+            CommandArgs::ReturnToBase => Command::ReturnToBase,
+            CommandArgs::PerformScan { scan_type } => Command::PerformScan { scan_type },
+            CommandArgs::StartPatrol { route_id } => Command::StartPatrol { route_id },
+            CommandArgs::Investigate { anomaly_id } => Command::Investigate { anomaly_id },
+            CommandArgs::InjectFault { fault_type } => Command::InjectFault { fault_type },
+            CommandArgs::ClearFault { fault_type } => Command::ClearFault { fault_type },
+            CommandArgs::Configure {
+                max_speed,
+                scan_interval,
+                heartbeat_interval,
+                low_battery_threshold,
+            } => Command::Configure {
+                config: RobotConfig {
+                    max_speed,
+                    scan_interval,
+                    heartbeat_interval,
+                    low_battery_threshold,
+                    ..RobotConfig::default()
+                },
+            },
+            CommandArgs::GetConfig => Command::GetConfig,
+            CommandArgs::AbortMission { mission_id } => Command::AbortMission { mission_id },
+        }
+    }
+}
+
+/// Who a command goes to
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recipient {
+    Robot(RobotId),
+    Broadcast,
+}
+
+impl Operation {
+    /// The command the operation sends, if it sends one
+    pub fn command(&self) -> Option<(Recipient, Command)> {
+        match self {
+            Operation::Send(SendArgs {
+                robot_id, command, ..
+            }) => {
+                let recipient = robot_id
+                    .clone()
+                    .map_or(Recipient::Broadcast, Recipient::Robot);
+                Some((recipient, command.clone().into()))
+            }
+            Operation::InjectFault {
+                robot_id,
+                fault_type,
+            } => Some((
+                Recipient::Robot(robot_id.clone()),
+                Command::InjectFault {
+                    fault_type: *fault_type,
+                },
+            )),
+            Operation::Fleet { .. } | Operation::Robot { .. } | Operation::Alerts { .. } => None,
+        }
+    }
+}
+
+/// Parser of a shared enum by its wire name, with dashes for underscores.
+/// The names are listed in help and for completion; the value comes from
+/// the enum's `FromStr`.
+fn wire_name<T>(all: &[T]) -> impl TypedValueParser<Value = T> + use<T>
+where
+    T: core::fmt::Display + core::str::FromStr<Err = UnknownName> + Clone + Send + Sync + 'static,
+{
+    let names = all
+        .iter()
+        .map(|variant| PossibleValue::new(variant.to_string().replace('_', "-")));
+    PossibleValuesParser::new(names).try_map(|name| name.replace('-', "_").parse::<T>())
+}
+
+/// A duration given in seconds
+fn seconds(value: &str) -> Result<Duration, String> {
+    let secs: f64 = value.parse().map_err(|e| format!("{e}"))?;
+    Duration::try_from_secs_f64(secs).map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// ENTRY POINT
+// ============================================================================
+
+/// Run the operator command in `args` (everything after `--cli`), writing
+/// its output to `out`. Returns the process exit code.
+pub async fn run_operator_cli(args: &[String], out: &mut impl Write) -> i32 {
+    let cli = match OperatorCli::try_parse_from(args) {
+        Ok(cli) => cli,
+        Err(e) => {
+            let _ = write!(out, "{}", e.render());
+            return if e.use_stderr() { EXIT_USAGE } else { 0 };
+        }
+    };
+    if let Some((_, command)) = cli.operation.command()
+        && let Err(e) = command.validate()
+    {
+        let _ = writeln!(out, "invalid {}: {}", command.name(), e);
+        return EXIT_USAGE;
+    }
+    let config = match cli.engine_config() {
+        Ok(config) => config,
+        Err(message) => {
+            let _ = writeln!(out, "{message}");
+            return EXIT_INVALID_CONFIG;
+        }
+    };
+
+    // Nothing reads the engine messages; held so sending them cannot fail
+    let (message_tx, _engine_messages) = mpsc::channel(16);
+    let (mqtt, eventloop) = match connect(config, message_tx).await {
+        Ok(connected) => connected,
+        Err(e) => {
+            let _ = writeln!(out, "{e}");
+            return EXIT_INVALID_CONFIG;
+        }
+    };
+    // Queued ahead of any command, so its response is not missed
+    for topic in subscriptions(&cli.operation) {
+        if let Err(e) = mqtt.subscribe(&topic).await {
+            let _ = writeln!(out, "{e}");
+            return EXIT_UNAVAILABLE;
+        }
+    }
+
+    let (alert_tx, alert_rx) = mpsc::channel(64);
+    let outcome = tokio::select! {
+        code = operate(&cli, &mqtt, alert_rx, out) => Ok(code),
+        e = pump(&mqtt, eventloop, alert_tx) => Err(e),
+    };
+    outcome.unwrap_or_else(|e| {
+        let _ = writeln!(
+            out,
+            "cannot reach the broker at {}: {}",
+            mqtt.active_broker(),
+            e
+        );
+        EXIT_UNAVAILABLE
+    })
+}
+
+impl OperatorCli {
+    /// The engine configuration the client connects with: the defaults,
+    /// then the configuration file, then the flags
+    fn engine_config(&self) -> Result<EngineConfig, String> {
+        let mut config = EngineConfig::default();
+        if let Some(path) = &self.config {
+            ConfigFile::load(path)
+                .map_err(|e| e.to_string())?
+                .apply(&mut config);
+        }
+        if let Some(host) = &self.broker_host {
+            config.mqtt.broker_host = host.clone();
+        }
+        if let Some(port) = self.broker_port {
+            config.mqtt.broker_port = port;
+        }
+        config.mqtt.client_id = format!("aetheris-cli-{}", uuid::Uuid::new_v4());
+        // The engine keeps the robot configuration and the anomalies; the
+        // operator only asks
+        config.robot_configs = RobotConfigStorage::default();
+        config.anomaly_store = AnomalyStoreConfig::default();
+        config.broadcast.deadline = self.timeout;
+        config.validate().map_err(|report| report.to_string())?;
+        Ok(config)
+    }
+}
+
+/// A client for the operator. It connects to the primary broker only, and
+/// without the engine's Last Will: an operator leaving must not announce
+/// the engine offline.
+async fn connect(
+    config: EngineConfig,
+    message_tx: mpsc::Sender<EngineMessage>,
+) -> Result<(AetherisMqtt, EventLoop)> {
+    let (mqtt, mut eventloop) = AetherisMqtt::from_engine_config(config, message_tx).await?;
+    let mqtt_config = mqtt.config();
+    let endpoint = &mqtt_config.endpoints()[0];
+    let mut options = MqttOptions::new(&mqtt_config.client_id, &endpoint.host, endpoint.port);
+    options.set_keep_alive(Duration::from_secs(mqtt_config.keep_alive_secs));
+    transport::configure(
+        &mut options,
+        endpoint.username.as_deref(),
+        endpoint.password.as_ref(),
+        endpoint.tls.as_ref(),
+    )?;
+    eventloop.mqtt_options = options;
+    Ok((mqtt, eventloop))
+}
+
+/// Topics an operation listens on
+fn subscriptions(operation: &Operation) -> Vec<String> {
+    match operation {
+        Operation::Fleet { .. } => vec![topics::TELEMETRY_ALL.to_string()],
+        Operation::Robot {
+            action: RobotAction::Show { robot_id },
+        } => vec![topics::telemetry(robot_id), topics::responses(robot_id)],
+        Operation::Send(SendArgs {
+            robot_id: Some(robot_id),
+            ..
+        })
+        | Operation::InjectFault { robot_id, .. } => vec![topics::responses(robot_id)],
+        Operation::Send(SendArgs { robot_id: None, .. }) => vec![
+            topics::TELEMETRY_ALL.to_string(),
+            "aetheris/responses/+".to_string(),
+        ],
+        Operation::Alerts {
+            action: AlertsAction::Tail { min_severity, .. },
+        } => AlertTopics {
+            legacy: false,
+            min_severity: *min_severity,
+        }
+        .subscriptions(),
+    }
+}
+
+/// Drive the event loop, feeding telemetry into the fleet, responses to
+/// the commands awaiting them, and alerts to `alerts`. Returns when the
+/// connection fails: the operator is not kept waiting on reconnects.
+async fn pump(
+    mqtt: &AetherisMqtt,
+    mut eventloop: EventLoop,
+    alerts: mpsc::Sender<AnomalyReport>,
+) -> ConnectionError {
+    loop {
+        match eventloop.poll().await {
+            Ok(Event::Incoming(Packet::Publish(publish))) => {
+                observe(mqtt, &publish, &alerts).await;
+            }
+            Ok(_) => {}
+            Err(e) => return e,
+        }
+    }
+}
+
+async fn observe(mqtt: &AetherisMqtt, publish: &Publish, alerts: &mpsc::Sender<AnomalyReport>) {
+    match topics::parse(&publish.topic) {
+        Some(Topic::Telemetry { .. }) => {
+            let Ok(msg) =
+                Encoding::decode_detected::<MqttMessage<TelemetryPayload>>(&publish.payload)
+            else {
+                return;
+            };
+            // Retained telemetry of a robot long gone is not the fleet
+            let fleet = mqtt.fleet();
+            if let TelemetryPayload::Full(state) = msg.payload
+                && mqtt.clock().now().duration_since(state.timestamp) <= fleet.heartbeat_timeout()
+            {
+                fleet.update_robot(state);
+            }
+        }
+        Some(Topic::Responses { .. }) => {
+            if let Ok(response) = Encoding::decode_detected::<CommandResponse>(&publish.payload) {
+                mqtt.settle_response(&response).await;
+            }
+        }
+        Some(Topic::AlertSeverity { .. }) => {
+            if let Ok(msg) =
+                Encoding::decode_detected::<MqttMessage<AnomalyReport>>(&publish.payload)
+            {
+                let _ = alerts.send(msg.payload).await;
+            }
+        }
+        _ => {}
+    }
+}
+
+// ============================================================================
+// OPERATIONS
+// ============================================================================
+
+async fn operate(
+    cli: &OperatorCli,
+    mqtt: &AetherisMqtt,
+    alerts: mpsc::Receiver<AnomalyReport>,
+    out: &mut impl Write,
+) -> i32 {
+    match &cli.operation {
+        Operation::Fleet { .. } => {
+            tokio::time::sleep(cli.listen).await;
+            let mut robots = mqtt.fleet().get_all_robots();
+            robots.sort_by(|a, b| a.id.cmp(&b.id));
+            if cli.json {
+                print_json(out, &robots);
+            } else {
+                let _ = write!(out, "{}", render_fleet(&robots, mqtt.clock().now()));
+            }
+            0
+        }
+        Operation::Robot {
+            action: RobotAction::Show { robot_id },
+        } => show_robot(cli, mqtt, robot_id, out).await,
+        Operation::Alerts {
+            action: AlertsAction::Tail { count, .. },
+        } => tail_alerts(cli, *count, alerts, out).await,
+        Operation::Send(_) | Operation::InjectFault { .. } => match cli.operation.command() {
+            Some((Recipient::Robot(robot_id), command)) => {
+                send(cli, mqtt, &robot_id, command, out).await
+            }
+            Some((Recipient::Broadcast, command)) => broadcast(cli, mqtt, command, out).await,
+            None => EXIT_USAGE,
+        },
+    }
+}
+
+/// Send `command` to one robot and report its response
+async fn send(
+    cli: &OperatorCli,
+    mqtt: &AetherisMqtt,
+    robot_id: &RobotId,
+    command: Command,
+    out: &mut impl Write,
+) -> i32 {
+    let variant = command.name();
+    let answer = tokio::time::timeout(
+        cli.timeout,
+        mqtt.send_command_and_wait(robot_id.as_str(), command),
+    )
+    .await;
+    let response = match answer {
+        Ok(Ok(response)) => response,
+        Ok(Err(e)) => {
+            let _ = writeln!(out, "{variant} to {robot_id} failed: {e}");
+            return EXIT_FAILED;
+        }
+        Err(_) => {
+            let _ = writeln!(
+                out,
+                "no response from {robot_id} to {variant} within {:?}",
+                cli.timeout
+            );
+            return EXIT_TIMEOUT;
+        }
+    };
+    if cli.json {
+        print_json(out, &response);
+    } else {
+        let _ = write!(out, "{}", render_response(variant, &response));
+    }
+    if response.success { 0 } else { EXIT_FAILED }
+}
+
+/// Broadcast `command` to the robots heard from while listening and report
+/// how each answered
+async fn broadcast(
+    cli: &OperatorCli,
+    mqtt: &AetherisMqtt,
+    command: Command,
+    out: &mut impl Write,
+) -> i32 {
+    tokio::time::sleep(cli.listen).await;
+    let variant = command.name();
+    let result = match mqtt.broadcast_and_collect(command).await {
+        Ok(result) => result,
+        Err(e) => {
+            let _ = writeln!(out, "{variant} broadcast failed: {e}");
+            return EXIT_FAILED;
+        }
+    };
+    if cli.json {
+        print_json(out, &result);
+    } else {
+        let _ = write!(out, "{}", render_broadcast(&result));
+    }
+    if !result.failed.is_empty() {
+        EXIT_FAILED
+    } else if !result.non_responding.is_empty() {
+        EXIT_TIMEOUT
+    } else {
+        0
+    }
+}
+
+/// The robot's latest telemetry and the configuration the engine keeps
+async fn show_robot(
+    cli: &OperatorCli,
+    mqtt: &AetherisMqtt,
+    robot_id: &RobotId,
+    out: &mut impl Write,
+) -> i32 {
+    let fleet = mqtt.fleet();
+    let reported = tokio::time::timeout(cli.listen, async {
+        loop {
+            if let Some(state) = fleet.get_robot(robot_id.as_str()) {
+                return state;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await;
+    let Ok(state) = reported else {
+        let _ = writeln!(
+            out,
+            "{robot_id} reported no telemetry within {:?}",
+            cli.listen
+        );
+        return EXIT_FAILED;
+    };
+    let config = tokio::time::timeout(
+        cli.timeout,
+        mqtt.send_command_and_wait(robot_id.as_str(), Command::GetConfig),
+    )
+    .await
+    .ok()
+    .and_then(Result::ok)
+    .and_then(|response| response.config);
+
+    if cli.json {
+        print_json(
+            out,
+            &serde_json::json!({ "state": state, "config": config }),
+        );
+    } else {
+        let _ = write!(
+            out,
+            "{}",
+            render_robot(&state, config.as_ref(), mqtt.clock().now())
+        );
+    }
+    0
+}
+
+/// Print alerts as they arrive until `count` were printed or the operator
+/// interrupts
+async fn tail_alerts(
+    cli: &OperatorCli,
+    count: Option<usize>,
+    mut alerts: mpsc::Receiver<AnomalyReport>,
+    out: &mut impl Write,
+) -> i32 {
+    if !cli.json {
+        let _ = writeln!(
+            out,
+            "{}",
+            alert_row([
+                "ID",
+                "SEVERITY",
+                "TYPE",
+                "SECTION",
+                "DETECTED BY",
+                "DESCRIPTION"
+            ])
+        );
+    }
+    let mut printed = 0;
+    while count.is_none_or(|count| printed < count) {
+        let alert = tokio::select! {
+            alert = alerts.recv() => alert,
+            _ = tokio::signal::ctrl_c() => None,
+        };
+        let Some(alert) = alert else {
+            break;
+        };
+        if cli.json {
+            let _ = writeln!(out, "{}", serde_json::to_string(&alert).unwrap_or_default());
+        } else {
+            let _ = writeln!(
+                out,
+                "{}",
+                alert_row([
+                    &alert.id,
+                    alert.severity.as_str(),
+                    alert.anomaly_type.as_str(),
+                    &alert.section_id,
+                    &alert.detected_by,
+                    &alert.description,
+                ])
+            );
+        }
+        let _ = out.flush();
+        printed += 1;
+    }
+    0
+}
+
+fn print_json(out: &mut impl Write, value: &impl Serialize) {
+    let _ = writeln!(
+        out,
+        "{}",
+        serde_json::to_string_pretty(value).unwrap_or_default()
+    );
+}
+
+// ============================================================================
+// RENDERING
+// ============================================================================
+
+/// Rows of cells, rendered with every column as wide as its widest cell
+#[derive(Debug, Default)]
+struct Table {
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    fn with_header(header: &[&str]) -> Self {
+        let mut table = Self::default();
+        table.row(header.iter().map(|cell| cell.to_string()).collect());
+        table
+    }
+
+    fn row(&mut self, cells: Vec<String>) {
+        self.rows.push(cells);
+    }
+
+    fn render(&self) -> String {
+        let mut widths = Vec::new();
+        for row in &self.rows {
+            widths.resize(widths.len().max(row.len()), 0);
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let mut out = String::new();
+        for row in &self.rows {
+            let mut line = String::new();
+            for (cell, width) in row.iter().zip(&widths) {
+                let _ = write!(line, "{:<width$}  ", cell);
+            }
+            let _ = writeln!(out, "{}", line.trim_end());
+        }
+        out
+    }
+}
+
+/// The fleet as of `now`, one robot per row
+fn render_fleet(robots: &[RobotState], now: Timestamp) -> String {
+    if robots.is_empty() {
+        return "no robots reporting\n".to_string();
+    }
+    let mut table = Table::with_header(&[
+        "ID", "NAME", "TYPE", "STATUS", "HEALTH", "BATTERY", "SIGNAL", "POSITION", "TASK", "AGE",
+    ]);
+    for robot in robots {
+        table.row(vec![
+            robot.id.to_string(),
+            robot.name.clone(),
+            robot.robot_type.to_string(),
+            robot.status.to_string(),
+            robot.health.to_string(),
+            format!("{:.0}%", robot.battery),
+            format!("{:.0}%", robot.signal),
+            position(&robot.position),
+            task(&robot.current_task),
+            age(now, robot.timestamp),
+        ]);
+    }
+    table.render()
+}
+
+/// One robot as of `now`, with its configuration if the engine answered
+fn render_robot(state: &RobotState, config: Option<&RobotConfig>, now: Timestamp) -> String {
+    let mut table = Table::default();
+    let mut field = |name: &str, value: String| table.row(vec![name.to_string(), value]);
+    field("id", state.id.to_string());
+    field("name", state.name.clone());
+    field("type", state.robot_type.to_string());
+    field("status", state.status.to_string());
+    field("health", state.health.to_string());
+    field("battery", format!("{:.1}%", state.battery));
+    field("signal", format!("{:.1}%", state.signal));
+    field("position", position(&state.position));
+    field("task", task(&state.current_task));
+    field("reported", format!("{} ago", age(now, state.timestamp)));
+    let mut out = table.render();
+    match config {
+        Some(config) => {
+            let _ = write!(out, "\n{}", render_config(config));
+        }
+        None => out.push_str("\nconfiguration unavailable\n"),
+    }
+    out
+}
+
+fn render_config(config: &RobotConfig) -> String {
+    fn setting<T: core::fmt::Display>(value: Option<T>, unit: &str) -> String {
+        value.map_or_else(|| "default".to_string(), |value| format!("{value}{unit}"))
+    }
+    let mut table = Table::default();
+    let mut field = |name: &str, value: String| table.row(vec![name.to_string(), value]);
+    field("max_speed", setting(config.max_speed, " m/s"));
+    field("scan_interval", setting(config.scan_interval, " s"));
+    field(
+        "heartbeat_interval",
+        setting(config.heartbeat_interval, " s"),
+    );
+    field(
+        "low_battery_threshold",
+        setting(config.low_battery_threshold, "%"),
+    );
+    table.render()
+}
+
+fn render_response(variant: &str, response: &CommandResponse) -> String {
+    let mut table = Table::with_header(&["ROBOT", "COMMAND", "RESULT", "ERROR"]);
+    table.row(vec![
+        response.robot_id.to_string(),
+        variant.to_string(),
+        if response.success { "ok" } else { "failed" }.to_string(),
+        response.error.clone().unwrap_or_default(),
+    ]);
+    let mut out = table.render();
+    if let Some(config) = &response.config {
+        let _ = write!(out, "\n{}", render_config(config));
+    }
+    out
+}
+
+fn render_broadcast(result: &BroadcastResult) -> String {
+    let mut table = Table::with_header(&["ROBOT", "RESULT"]);
+    for (robots, outcome) in [
+        (&result.acked, "ok"),
+        (&result.failed, "failed"),
+        (&result.non_responding, "no response"),
+    ] {
+        for robot_id in robots {
+            table.row(vec![robot_id.clone(), outcome.to_string()]);
+        }
+    }
+    format!(
+        "{} broadcast: {}\n{}",
+        result.command,
+        result.summary(),
+        table.render()
+    )
+}
+
+/// An alert as one line of fixed-width columns, so lines printed as they
+/// arrive line up
+fn alert_row([id, severity, kind, section, detected_by, description]: [&str; 6]) -> String {
+    format!("{id:<24} {severity:<8} {kind:<20} {section:<10} {detected_by:<11} {description}")
+}
+
+fn position(position: &Position) -> String {
+    format!("({:.1}, {:.1}, {:.1})", position.x, position.y, position.z)
+}
+
+fn task(task: &CurrentTask) -> String {
+    match task {
+        CurrentTask::None => "-".to_string(),
+        CurrentTask::Patrolling { route_id } => format!("patrolling {route_id}"),
+        CurrentTask::MovingTo { target } => format!("moving to {}", position(target)),
+        CurrentTask::Scanning { scan_type } => format!("{scan_type} scan"),
+        CurrentTask::ReturningToBase => "returning to base".to_string(),
+        CurrentTask::Investigating { anomaly_id } => format!("investigating {anomaly_id}"),
+    }
+}
+
+/// Time from `then` to `now` in its largest whole unit
+fn age(now: Timestamp, then: Timestamp) -> String {
+    let secs = now.duration_since(then).as_secs();
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3_600 => format!("{}m", secs / 60),
+        3_600..86_400 => format!("{}h", secs / 3_600),
+        _ => format!("{}d", secs / 86_400),
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> std::result::Result<OperatorCli, clap::Error> {
+        OperatorCli::try_parse_from(line.split_whitespace())
+    }
+
+    #[test]
+    fn test_arguments_map_onto_shared_commands() {
+        let cli = parse("send RV-001 move-to --x 10 --y 0 --z -5 --speed 2").unwrap();
+        let robot_id = RobotId::parse("RV-001").unwrap();
+        assert_eq!(
+            cli.operation.command(),
+            Some((
+                Recipient::Robot(robot_id),
+                Command::MoveTo {
+                    target: Position::new(10.0, 0.0, -5.0),
+                    speed: Some(2.0),
+                }
+            ))
+        );
+
+        let cli = parse("send --broadcast emergency-stop --json").unwrap();
+        assert!(cli.json);
+        assert_eq!(
+            cli.operation.command(),
+            Some((Recipient::Broadcast, Command::EmergencyStop))
+        );
+
+        // Wire names with dashes, through the shared FromStr
+        let cli = parse("inject-fault DR-001 gps-drift").unwrap();
+        assert_eq!(
+            cli.operation.command(),
+            Some((
+                Recipient::Robot(RobotId::parse("DR-001").unwrap()),
+                Command::InjectFault {
+                    fault_type: FaultType::GpsDrift
+                }
+            ))
+        );
+        let cli = parse("send CR-001 clear-fault").unwrap();
+        assert_eq!(
+            cli.operation.command().map(|(_, command)| command),
+            Some(Command::ClearFault { fault_type: None })
+        );
+        let cli = parse("alerts tail --min-severity high --timeout 0.5").unwrap();
+        assert_eq!(cli.timeout, Duration::from_millis(500));
+        assert!(matches!(
+            cli.operation,
+            Operation::Alerts {
+                action: AlertsAction::Tail {
+                    min_severity: Some(SeverityLevel::High),
+                    count: None,
+                }
+            }
+        ));
+        assert_eq!(
+            subscriptions(&cli.operation),
+            ["aetheris/alerts/high", "aetheris/alerts/critical"]
+        );
+
+        let err = parse("inject-fault DR-001 gps_wobble").unwrap_err();
+        assert!(err.to_string().contains("gps-drift"), "{err}");
+        assert!(parse("send emergency-stop").is_err());
+        assert!(parse("send RV-001 --broadcast stop").is_err());
+        assert!(parse("robot show rover-1").is_err());
+    }
+
+    #[test]
+    fn test_fleet_table_lines_up_columns() {
+        let now = Timestamp::from_millis(1_767_225_600_000);
+        let mut rover = RobotState::new_at(
+            RobotId::parse("RV-001").unwrap(),
+            "Rover Alpha",
+            aetheris_shared::RobotType::Rover,
+            Timestamp::from_millis(now.as_millis() - 3_000),
+        );
+        rover.battery = 87.4;
+        rover.current_task = CurrentTask::MovingTo {
+            target: Position::new(10.0, 0.0, 5.0),
+        };
+        let drone = RobotState::new_at(
+            RobotId::parse("DR-001").unwrap(),
+            "Drone Gamma With A Long Name",
+            aetheris_shared::RobotType::Drone,
+            Timestamp::from_millis(now.as_millis() - 125_000),
+        );
+
+        let table = render_fleet(&[drone, rover], now);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        let column = |line: &str, text: &str| line.find(text).unwrap();
+        assert_eq!(column(lines[0], "TYPE"), column(lines[1], "drone"));
+        assert_eq!(column(lines[0], "TYPE"), column(lines[2], "rover"));
+        assert_eq!(column(lines[0], "AGE"), column(lines[1], "2m"));
+        assert!(lines[2].contains("87%"));
+        assert!(lines[2].contains("moving to (10.0, 0.0, 5.0)"));
+        assert!(lines[2].ends_with("3s"));
+
+        assert_eq!(render_fleet(&[], now), "no robots reporting\n");
+    }
+}
@@ -0,0 +1,237 @@
+//! SQLite persistence of fleet history
+//!
+//! Raw tables hold one row per telemetry sample, anomaly, environment
+//! reading and engine event. Analysts never see them directly: the `v_*`
+//! views flatten positions and join section metadata, and are the only
+//! objects the read-only [`query`](crate::query) interface may touch.
+
+use std::path::Path;
+
+use rusqlite::{Connection, params};
+use serde::Serialize;
+
+use aetheris_shared::{AnomalyReport, PipeEnvironment, RobotState};
+
+use crate::sections::SectionInfo;
+
+/// Views exposed to the read-only query interface
+pub const QUERYABLE_VIEWS: &[&str] = &[
+    "v_robots_history",
+    "v_anomalies",
+    "v_environment",
+    "v_events",
+];
+
+/// Tables each of the [`QUERYABLE_VIEWS`] reads
+pub const VIEW_TABLES: &[(&str, &[&str])] = &[
+    ("v_robots_history", &["robot_history"]),
+    ("v_anomalies", &["anomalies", "sections"]),
+    ("v_environment", &["environment", "sections"]),
+    ("v_events", &["events"]),
+];
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS sections (
+    section_id  TEXT PRIMARY KEY,
+    x           REAL NOT NULL,
+    y           REAL NOT NULL,
+    z           REAL NOT NULL,
+    provisional INTEGER NOT NULL,
+    first_seen  INTEGER NOT NULL,
+    last_seen   INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS robot_history (
+    robot_id   TEXT NOT NULL,
+    robot_type TEXT NOT NULL,
+    status     TEXT NOT NULL,
+    health     TEXT NOT NULL,
+    battery    REAL NOT NULL,
+    signal     REAL NOT NULL,
+    position   TEXT NOT NULL,
+    timestamp  INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS robot_history_time ON robot_history (timestamp);
+
+CREATE TABLE IF NOT EXISTS anomalies (
+    id           TEXT PRIMARY KEY,
+    anomaly_type TEXT NOT NULL,
+    severity     TEXT NOT NULL,
+    section_id   TEXT NOT NULL,
+    detected_by  TEXT NOT NULL,
+    confidence   REAL NOT NULL,
+    description  TEXT NOT NULL,
+    position     TEXT NOT NULL,
+    acknowledged INTEGER NOT NULL,
+    timestamp    INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS environment (
+    section_id       TEXT NOT NULL,
+    pressure         REAL NOT NULL,
+    temperature      REAL NOT NULL,
+    h2_concentration REAL NOT NULL,
+    wall_thickness   REAL NOT NULL,
+    flow_rate        REAL NOT NULL,
+    humidity         REAL NOT NULL,
+    position         TEXT NOT NULL,
+    timestamp        INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS environment_time ON environment (timestamp);
+
+CREATE TABLE IF NOT EXISTS events (
+    kind      TEXT NOT NULL,
+    subject   TEXT,
+    payload   TEXT NOT NULL,
+    timestamp INTEGER NOT NULL
+);
+
+CREATE VIEW IF NOT EXISTS v_robots_history AS
+SELECT robot_id, robot_type, status, health, battery, signal,
+       json_extract(position, '$.x') AS x,
+       json_extract(position, '$.y') AS y,
+       json_extract(position, '$.z') AS z,
+       timestamp
+FROM robot_history;
+
+CREATE VIEW IF NOT EXISTS v_anomalies AS
+SELECT a.id, a.anomaly_type, a.severity, a.section_id, a.detected_by,
+       a.confidence, a.description, a.acknowledged,
+       json_extract(a.position, '$.x') AS x,
+       json_extract(a.position, '$.y') AS y,
+       json_extract(a.position, '$.z') AS z,
+       s.provisional AS section_provisional,
+       s.x AS section_x, s.y AS section_y, s.z AS section_z,
+       a.timestamp
+FROM anomalies a LEFT JOIN sections s ON s.section_id = a.section_id;
+
+CREATE VIEW IF NOT EXISTS v_environment AS
+SELECT e.section_id, e.pressure, e.temperature, e.h2_concentration,
+       e.wall_thickness, e.flow_rate, e.humidity,
+       json_extract(e.position, '$.x') AS x,
+       json_extract(e.position, '$.y') AS y,
+       json_extract(e.position, '$.z') AS z,
+       s.provisional AS section_provisional,
+       e.timestamp
+FROM environment e LEFT JOIN sections s ON s.section_id = e.section_id;
+
+CREATE VIEW IF NOT EXISTS v_events AS
+SELECT kind, subject, payload, timestamp FROM events;
+";
+
+/// Writable handle on the fleet history database
+pub struct FleetStore {
+    conn: Connection,
+}
+
+impl FleetStore {
+    /// Open (or create) the database at `path` and apply the schema
+    pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch("PRAGMA journal_mode = WAL;")?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    pub fn upsert_section(&self, section: &SectionInfo) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT INTO sections (section_id, x, y, z, provisional, first_seen, last_seen)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT (section_id) DO UPDATE SET
+                 x = excluded.x, y = excluded.y, z = excluded.z,
+                 provisional = excluded.provisional, last_seen = excluded.last_seen",
+            params![
+                section.id,
+                section.position.x,
+                section.position.y,
+                section.position.z,
+                section.provisional,
+                section.first_seen as i64,
+                section.last_seen as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn record_robot_state(&self, state: &RobotState) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT INTO robot_history
+                 (robot_id, robot_type, status, health, battery, signal, position, timestamp)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                state.id.as_str(),
+                state.robot_type.as_str(),
+                state.status.as_str(),
+                state.health.as_str(),
+                state.battery,
+                state.signal,
+                json(&state.position),
+                state.timestamp.as_millis() as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Insert or update an anomaly (reports are republished after triage)
+    pub fn record_anomaly(&self, report: &AnomalyReport) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO anomalies
+                 (id, anomaly_type, severity, section_id, detected_by, confidence,
+                  description, position, acknowledged, timestamp)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                report.id,
+                report.anomaly_type.as_str(),
+                report.severity.as_str(),
+                report.section_id,
+                report.detected_by,
+                report.confidence,
+                report.description,
+                json(&report.position),
+                report.acknowledged,
+                report.timestamp.as_millis() as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn record_environment(&self, reading: &PipeEnvironment) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT INTO environment
+                 (section_id, pressure, temperature, h2_concentration, wall_thickness,
+                  flow_rate, humidity, position, timestamp)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                reading.section_id,
+                reading.pressure,
+                reading.temperature,
+                reading.h2_concentration,
+                reading.wall_thickness,
+                reading.flow_rate,
+                reading.humidity,
+                json(&reading.position),
+                reading.timestamp.as_millis() as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Record an engine event (command sent, robot offline, decision, ...)
+    pub fn record_event(
+        &self,
+        kind: &str,
+        subject: Option<&str>,
+        payload: &impl Serialize,
+        timestamp: u64,
+    ) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT INTO events (kind, subject, payload, timestamp) VALUES (?1, ?2, ?3, ?4)",
+            params![kind, subject, json(payload), timestamp as i64],
+        )?;
+        Ok(())
+    }
+}
+
+fn json(value: &impl Serialize) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "null".into())
+}
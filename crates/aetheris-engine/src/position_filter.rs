@@ -0,0 +1,419 @@
+//! Smoothed robot positions from noisy telemetry
+//!
+//! Raw telemetry positions jitter with localization noise. Each robot gets a
+//! constant-velocity alpha-beta filter, with gains per [`RobotType`] since a
+//! drone changes course far faster than a crawler. The first fix (or the
+//! first after a long gap) starts the track from the raw position and
+//! reported velocity. Between fixes the estimate coasts along its velocity
+//! for up to `max_coast`; past that the track is dropped and the next fix
+//! starts a new one. Raw telemetry is never modified: estimates are kept
+//! here and published on their own topic at a decimated rate.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use aetheris_shared::{FilteredTelemetry, Position, RobotId, RobotState, RobotType, Velocity};
+
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Alpha-beta gains: how far a fix pulls the position and the velocity
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FilterGains {
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl CheckConfig for FilterGains {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if !(self.alpha > 0.0 && self.alpha <= 1.0) {
+            checker.error("alpha", "must be in (0, 1]", None);
+        }
+        // Stability of the alpha-beta filter needs 0 < beta < 4 - 2 alpha
+        if !(self.beta > 0.0 && self.beta < 4.0 - 2.0 * self.alpha) {
+            checker.error(
+                "beta",
+                format!(
+                    "must be in (0, {}) for alpha {}",
+                    4.0 - 2.0 * self.alpha,
+                    self.alpha
+                ),
+                None,
+            );
+        }
+    }
+}
+
+/// Position filter behavior
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PositionFilterConfig {
+    pub rover: FilterGains,
+    pub drone: FilterGains,
+    pub crawler: FilterGains,
+    /// Longest telemetry gap the estimate coasts across before the track resets
+    #[serde(with = "crate::config::duration_secs")]
+    pub max_coast: Duration,
+    /// Minimum time between filtered publications per robot
+    #[serde(with = "crate::config::duration_secs")]
+    pub publish_interval: Duration,
+    /// Innovations kept per robot for statistics
+    pub innovation_window: usize,
+}
+
+impl Default for PositionFilterConfig {
+    fn default() -> Self {
+        Self {
+            rover: FilterGains {
+                alpha: 0.5,
+                beta: 0.1,
+            },
+            // Drones turn and accelerate quickly; trust fixes more
+            drone: FilterGains {
+                alpha: 0.7,
+                beta: 0.3,
+            },
+            // Crawlers move slowly and steadily inside the pipe
+            crawler: FilterGains {
+                alpha: 0.35,
+                beta: 0.05,
+            },
+            max_coast: Duration::from_secs(3),
+            publish_interval: Duration::from_secs(1),
+            innovation_window: 50,
+        }
+    }
+}
+
+impl PositionFilterConfig {
+    pub fn gains(&self, robot_type: RobotType) -> FilterGains {
+        match robot_type {
+            RobotType::Rover => self.rover,
+            RobotType::Drone => self.drone,
+            RobotType::Crawler => self.crawler,
+        }
+    }
+}
+
+impl CheckConfig for PositionFilterConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        checker.check_section("rover", &self.rover);
+        checker.check_section("drone", &self.drone);
+        checker.check_section("crawler", &self.crawler);
+        checker.positive("max_coast", self.max_coast);
+        checker.positive("publish_interval", self.publish_interval);
+        if self.innovation_window == 0 {
+            checker.error("innovation_window", "must be greater than zero", None);
+        }
+    }
+}
+
+// ============================================================================
+// FILTER
+// ============================================================================
+
+/// A robot's filtered position at some instant
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    pub position: Position,
+    pub velocity: Velocity,
+    /// Predicted across a gap rather than corrected by a fix at `timestamp`
+    pub coasting: bool,
+    /// Unix timestamp (milliseconds)
+    pub timestamp: u64,
+}
+
+/// How far raw fixes land from the filter's prediction, in meters.
+/// Persistently large values mean the filter disagrees with the raw data.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct InnovationStats {
+    pub samples: usize,
+    pub mean: f64,
+    pub rms: f64,
+    pub max: f64,
+    pub last: f64,
+    /// Tracks restarted after a gap longer than `max_coast`
+    pub resets: u64,
+}
+
+#[derive(Debug)]
+struct Track {
+    position: Position,
+    velocity: Velocity,
+    last_fix: u64,
+    innovations: VecDeque<f64>,
+    last_published: Option<u64>,
+}
+
+/// Per-robot alpha-beta position filters
+#[derive(Debug, Default)]
+pub struct PositionFilter {
+    config: PositionFilterConfig,
+    tracks: HashMap<RobotId, Track>,
+    resets: HashMap<RobotId, u64>,
+}
+
+impl PositionFilter {
+    pub fn new(config: PositionFilterConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Fold a telemetry fix into the robot's track. Returns the estimate to
+    /// publish when the robot's publish interval has elapsed.
+    pub fn observe(&mut self, state: &RobotState) -> Option<FilteredTelemetry> {
+        let now = state.timestamp.as_millis();
+        let max_coast = self.config.max_coast.as_millis() as u64;
+        let gains = self.config.gains(state.robot_type);
+        let window = self.config.innovation_window;
+
+        let track = match self.tracks.get_mut(&state.id) {
+            // Stale or reordered fix: the track already moved past it
+            Some(track) if now <= track.last_fix => return None,
+            Some(track) if now - track.last_fix <= max_coast => {
+                let dt = (now - track.last_fix) as f64 / 1000.0;
+                let predicted = advance(track.position, track.velocity, dt);
+                let residual = [
+                    state.position.x - predicted.x,
+                    state.position.y - predicted.y,
+                    state.position.z - predicted.z,
+                ];
+                track.position = Position::new(
+                    predicted.x + gains.alpha * residual[0],
+                    predicted.y + gains.alpha * residual[1],
+                    predicted.z + gains.alpha * residual[2],
+                );
+                let k = gains.beta / dt;
+                track.velocity = Velocity::new(
+                    track.velocity.vx + k * residual[0],
+                    track.velocity.vy + k * residual[1],
+                    track.velocity.vz + k * residual[2],
+                );
+                track.last_fix = now;
+                if track.innovations.len() == window {
+                    track.innovations.pop_front();
+                }
+                track
+                    .innovations
+                    .push_back(predicted.distance_to(&state.position));
+                track
+            }
+            existing => {
+                if existing.is_some() {
+                    *self.resets.entry(state.id.clone()).or_default() += 1;
+                }
+                self.tracks.insert(state.id.clone(), cold_start(state));
+                self.tracks.get_mut(&state.id).expect("track just inserted")
+            }
+        };
+
+        let interval = self.config.publish_interval.as_millis() as u64;
+        if track
+            .last_published
+            .is_some_and(|at| now.saturating_sub(at) < interval)
+        {
+            return None;
+        }
+        track.last_published = Some(now);
+        Some(FilteredTelemetry {
+            robot_id: state.id.clone(),
+            position: track.position,
+            velocity: track.velocity,
+            coasting: false,
+            timestamp: now,
+        })
+    }
+
+    /// The robot's estimate at `now`, coasted from its last fix. `None` when
+    /// there is no track or the gap exceeds `max_coast`.
+    pub fn estimate(&self, robot_id: &str, now: u64) -> Option<Estimate> {
+        let track = self.tracks.get(robot_id)?;
+        let gap = now.saturating_sub(track.last_fix);
+        if gap > self.config.max_coast.as_millis() as u64 {
+            return None;
+        }
+        Some(Estimate {
+            position: advance(track.position, track.velocity, gap as f64 / 1000.0),
+            velocity: track.velocity,
+            coasting: gap > 0,
+            timestamp: now.max(track.last_fix),
+        })
+    }
+
+    /// Innovation statistics over the robot's recent fixes
+    pub fn innovation_stats(&self, robot_id: &str) -> Option<InnovationStats> {
+        let track = self.tracks.get(robot_id)?;
+        let resets = self.resets.get(robot_id).copied().unwrap_or(0);
+        let samples = track.innovations.len();
+        if samples == 0 {
+            return Some(InnovationStats {
+                resets,
+                ..InnovationStats::default()
+            });
+        }
+        let n = samples as f64;
+        Some(InnovationStats {
+            samples,
+            mean: track.innovations.iter().sum::<f64>() / n,
+            rms: (track.innovations.iter().map(|i| i * i).sum::<f64>() / n).sqrt(),
+            max: track.innovations.iter().copied().fold(0.0, f64::max),
+            last: track.innovations.back().copied().unwrap_or(0.0),
+            resets,
+        })
+    }
+
+    /// Drop the robot's track, e.g. when it goes offline
+    pub fn forget(&mut self, robot_id: &str) {
+        self.tracks.remove(robot_id);
+    }
+}
+
+fn cold_start(state: &RobotState) -> Track {
+    let velocity = if state.velocity.vx.is_finite()
+        && state.velocity.vy.is_finite()
+        && state.velocity.vz.is_finite()
+    {
+        state.velocity
+    } else {
+        Velocity::zero()
+    };
+    Track {
+        position: state.position,
+        velocity,
+        last_fix: state.timestamp.as_millis(),
+        innovations: VecDeque::new(),
+        last_published: None,
+    }
+}
+
+fn advance(position: Position, velocity: Velocity, dt: f64) -> Position {
+    Position::new(
+        position.x + velocity.vx * dt,
+        position.y + velocity.vy * dt,
+        position.z + velocity.vz * dt,
+    )
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetheris_shared::Timestamp;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    const T0: u64 = 1_000_000;
+    const PERIOD_MS: u64 = 200;
+
+    fn fix(robot_type: RobotType, position: Position, timestamp: u64) -> RobotState {
+        RobotState {
+            position,
+            timestamp: Timestamp::from_millis(timestamp),
+            ..RobotState::new("RV-001".parse().unwrap(), "Rover Alpha", robot_type)
+        }
+    }
+
+    /// Ground truth of a rover driving at 1.5 m/s along x with a slow weave in y
+    fn truth(i: u64) -> Position {
+        let t = (i * PERIOD_MS) as f64 / 1000.0;
+        Position::new(1.5 * t, 2.0 * (t / 20.0).sin(), 0.0)
+    }
+
+    #[test]
+    fn test_filter_reduces_error_against_ground_truth() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut filter = PositionFilter::default();
+        let (mut raw_err, mut filtered_err) = (0.0, 0.0);
+        for i in 0..300 {
+            let t = truth(i);
+            let noisy = Position::new(
+                t.x + rng.random_range(-0.8..0.8),
+                t.y + rng.random_range(-0.8..0.8),
+                0.0,
+            );
+            let now = T0 + i * PERIOD_MS;
+            filter.observe(&fix(RobotType::Rover, noisy, now));
+            // Skip the warm-up while velocity converges
+            if i >= 50 {
+                let estimate = filter.estimate("RV-001", now).unwrap();
+                assert!(!estimate.coasting);
+                raw_err += noisy.distance_to(&t).powi(2);
+                filtered_err += estimate.position.distance_to(&t).powi(2);
+            }
+        }
+        assert!(
+            filtered_err < 0.6 * raw_err,
+            "filtered {filtered_err:.1} vs raw {raw_err:.1}"
+        );
+        let stats = filter.innovation_stats("RV-001").unwrap();
+        assert_eq!(stats.samples, 50);
+        assert!(stats.rms < 1.5, "{stats:?}");
+    }
+
+    #[test]
+    fn test_gap_coasts_then_resets() {
+        let mut filter = PositionFilter::default();
+        for i in 0..50 {
+            filter.observe(&fix(RobotType::Rover, truth(i), T0 + i * PERIOD_MS));
+        }
+        let last = T0 + 49 * PERIOD_MS;
+
+        // Coasts along the track within the bound
+        let coasted = filter.estimate("RV-001", last + 2_000).unwrap();
+        assert!(coasted.coasting);
+        let expected_x = truth(49).x + 1.5 * 2.0;
+        assert!((coasted.position.x - expected_x).abs() < 0.2, "{coasted:?}");
+
+        // Past the bound there is no estimate
+        assert!(filter.estimate("RV-001", last + 3_001).is_none());
+
+        // The next fix restarts the track from the raw position
+        let restart = Position::new(200.0, 5.0, 0.0);
+        let published = filter
+            .observe(&fix(RobotType::Rover, restart, last + 10_000))
+            .unwrap();
+        assert_eq!(published.position, restart);
+        let stats = filter.innovation_stats("RV-001").unwrap();
+        assert_eq!((stats.samples, stats.resets), (0, 1));
+    }
+
+    #[test]
+    fn test_publications_are_decimated_and_stale_fixes_ignored() {
+        let mut filter = PositionFilter::default();
+        let published = (0..50)
+            .filter_map(|i| filter.observe(&fix(RobotType::Drone, truth(i), T0 + i * 100)))
+            .count();
+        // 5 s of 10 Hz telemetry at one publication per second
+        assert_eq!(published, 5);
+
+        let before = filter.estimate("RV-001", T0 + 4_900).unwrap();
+        filter.observe(&fix(RobotType::Drone, Position::new(99.0, 0.0, 0.0), T0));
+        assert_eq!(filter.estimate("RV-001", T0 + 4_900).unwrap(), before);
+    }
+
+    #[test]
+    fn test_innovation_stats_expose_disagreement() {
+        let mut filter = PositionFilter::default();
+        for i in 0..20 {
+            filter.observe(&fix(RobotType::Crawler, truth(i), T0 + i * PERIOD_MS));
+        }
+        let calm = filter.innovation_stats("RV-001").unwrap();
+        // Localization jumps 25 m
+        let mut jumped = truth(20);
+        jumped.x += 25.0;
+        filter.observe(&fix(RobotType::Crawler, jumped, T0 + 20 * PERIOD_MS));
+        let stats = filter.innovation_stats("RV-001").unwrap();
+        assert!(calm.max < 1.0, "{calm:?}");
+        assert!(stats.last > 24.0 && stats.max == stats.last, "{stats:?}");
+    }
+}
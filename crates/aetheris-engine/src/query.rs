@@ -0,0 +1,874 @@
+//! Read-only ad-hoc SQL over fleet history
+//!
+//! Analysts submit a single SELECT against the [`QUERYABLE_VIEWS`]. Queries
+//! are checked before execution (one statement, SELECT/WITH only) and then
+//! prepared on a read-only connection whose authorizer denies everything
+//! except reads through the whitelisted views and calls to the
+//! [`ALLOWED_FUNCTIONS`]. Execution is bounded by a statement timeout and a
+//! row cap.
+//!
+//! Large results are exported with [`QueryEngine::export`], which streams
+//! rows into a [`Write`] sink in bounded chunks instead of collecting them,
+//! so memory stays flat however many rows match. The timeout applies to
+//! each chunk, which also cuts off a consumer too slow to take one.
+
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use rusqlite::hooks::{AuthAction, AuthContext, Authorization};
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, ErrorCode, OpenFlags};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::persistence::{QUERYABLE_VIEWS, VIEW_TABLES};
+
+/// SQL functions a query may call: the core, date, math, JSON, aggregate
+/// and window functions. Anything touching files, extensions or large
+/// allocations (`load_extension`, `readfile`, `zeroblob`, ...) is left out.
+pub const ALLOWED_FUNCTIONS: &[&str] = &[
+    // Core scalar
+    "abs",
+    "char",
+    "coalesce",
+    "concat",
+    "concat_ws",
+    "format",
+    "glob",
+    "hex",
+    "ifnull",
+    "iif",
+    "instr",
+    "length",
+    "like",
+    "likelihood",
+    "likely",
+    "lower",
+    "ltrim",
+    "max",
+    "min",
+    "nullif",
+    "octet_length",
+    "printf",
+    "quote",
+    "random",
+    "replace",
+    "round",
+    "rtrim",
+    "sign",
+    "substr",
+    "substring",
+    "trim",
+    "typeof",
+    "unicode",
+    "unlikely",
+    "upper",
+    // Aggregate
+    "avg",
+    "count",
+    "group_concat",
+    "string_agg",
+    "sum",
+    "total",
+    // Date and time
+    "date",
+    "datetime",
+    "julianday",
+    "strftime",
+    "time",
+    "timediff",
+    "unixepoch",
+    // Math
+    "acos",
+    "asin",
+    "atan",
+    "atan2",
+    "ceil",
+    "ceiling",
+    "cos",
+    "degrees",
+    "exp",
+    "floor",
+    "ln",
+    "log",
+    "log10",
+    "log2",
+    "mod",
+    "pi",
+    "pow",
+    "power",
+    "radians",
+    "sin",
+    "sqrt",
+    "tan",
+    "trunc",
+    // JSON
+    "json",
+    "json_array",
+    "json_array_length",
+    "json_extract",
+    "json_group_array",
+    "json_group_object",
+    "json_object",
+    "json_type",
+    "json_valid",
+    "->",
+    "->>",
+    // Window
+    "cume_dist",
+    "dense_rank",
+    "first_value",
+    "lag",
+    "last_value",
+    "lead",
+    "nth_value",
+    "ntile",
+    "percent_rank",
+    "rank",
+    "row_number",
+];
+
+/// Bounds applied to every query
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryLimits {
+    /// Maximum rows returned; further rows are dropped and flagged
+    pub max_rows: usize,
+    /// Statement timeout; for exports, the time allowed per chunk
+    pub timeout: Duration,
+    /// Maximum accepted SQL length in bytes
+    pub max_sql_bytes: usize,
+    /// Rows encoded before an export chunk is written to the sink
+    pub chunk_rows: usize,
+}
+
+impl Default for QueryLimits {
+    fn default() -> Self {
+        Self {
+            max_rows: 10_000,
+            timeout: Duration::from_secs(5),
+            max_sql_bytes: 16 * 1024,
+            chunk_rows: 500,
+        }
+    }
+}
+
+/// Why a query was refused or failed
+#[derive(Debug, Error)]
+pub enum QueryError {
+    #[error("query is empty")]
+    Empty,
+    #[error("query of {0} bytes exceeds the limit")]
+    TooLong(usize),
+    #[error("only a single statement is allowed")]
+    MultipleStatements,
+    #[error("only SELECT queries are allowed")]
+    NotSelect,
+    #[error("query exceeded the {0:?} timeout")]
+    Timeout(Duration),
+    #[error("query rejected: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("export sink failed: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Encoding of exported rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// RFC 4180 CSV with a header row
+    Csv,
+    /// A `{"columns": [...]}` line, then one JSON array per row, then
+    /// `{"truncated": true}` if rows were dropped
+    JsonLines,
+}
+
+impl ExportFormat {
+    /// HTTP content type of the encoding
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::JsonLines => "application/x-ndjson",
+        }
+    }
+
+    fn write_header(&self, columns: &[String], out: &mut Vec<u8>) {
+        match self {
+            ExportFormat::Csv => {
+                let fields: Vec<String> = columns.iter().map(|c| csv_field(c)).collect();
+                out.extend_from_slice(fields.join(",").as_bytes());
+            }
+            ExportFormat::JsonLines => {
+                let header = serde_json::json!({ "columns": columns });
+                out.extend_from_slice(header.to_string().as_bytes());
+            }
+        }
+        out.extend_from_slice(b"\r\n");
+    }
+
+    fn write_row(&self, values: &[serde_json::Value], out: &mut Vec<u8>) {
+        match self {
+            ExportFormat::Csv => {
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        out.push(b',');
+                    }
+                    let field = match value {
+                        serde_json::Value::Null => String::new(),
+                        serde_json::Value::String(s) => csv_field(s),
+                        other => other.to_string(),
+                    };
+                    out.extend_from_slice(field.as_bytes());
+                }
+            }
+            ExportFormat::JsonLines => {
+                out.extend_from_slice(serde_json::Value::from(values).to_string().as_bytes());
+            }
+        }
+        out.extend_from_slice(b"\r\n");
+    }
+
+    fn write_trailer(&self, truncated: bool, out: &mut Vec<u8>) {
+        if truncated && *self == ExportFormat::JsonLines {
+            out.extend_from_slice(b"{\"truncated\":true}\r\n");
+        }
+    }
+}
+
+/// Quote a CSV field if it contains a delimiter, quote, or line break
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Rows written by an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExportSummary {
+    pub rows: usize,
+    /// True if rows beyond the cap were dropped
+    pub truncated: bool,
+}
+
+/// Column names and rows of a query result
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<serde_json::Value>>,
+    /// True if rows beyond the cap were dropped
+    pub truncated: bool,
+}
+
+impl QueryResult {
+    /// Encode the whole result at once; [`QueryEngine::export`] produces the
+    /// same bytes without holding the rows
+    pub fn write_to(&self, format: ExportFormat, sink: &mut impl Write) -> std::io::Result<()> {
+        let mut out = Vec::new();
+        format.write_header(&self.columns, &mut out);
+        for row in &self.rows {
+            format.write_row(row, &mut out);
+        }
+        format.write_trailer(self.truncated, &mut out);
+        sink.write_all(&out)
+    }
+}
+
+/// Read-only connection for analyst queries
+pub struct QueryEngine {
+    conn: Connection,
+    limits: QueryLimits,
+    /// Tables and views stored in the database, as opposed to CTEs
+    stored: Arc<HashSet<String>>,
+}
+
+impl QueryEngine {
+    /// Open the history database written by [`FleetStore`](crate::persistence::FleetStore)
+    pub fn open(path: impl AsRef<Path>, limits: QueryLimits) -> rusqlite::Result<Self> {
+        let conn = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        conn.execute_batch("PRAGMA query_only = ON;")?;
+        let mut stored: HashSet<String> = conn
+            .prepare("SELECT name FROM sqlite_schema WHERE type IN ('table', 'view')")?
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        stored.extend(["sqlite_schema", "sqlite_master"].map(String::from));
+        Ok(Self {
+            conn,
+            limits,
+            stored: Arc::new(stored),
+        })
+    }
+
+    pub fn limits(&self) -> &QueryLimits {
+        &self.limits
+    }
+
+    /// Validate a query and prepare it under a fresh authorizer
+    fn prepare(&self, sql: &str) -> Result<rusqlite::Statement<'_>, QueryError> {
+        let sql = validate(sql, &self.limits)?;
+        self.conn.authorizer(Some(authorizer(self.stored.clone())));
+        let stmt = self.conn.prepare(sql)?;
+        if !stmt.readonly() {
+            return Err(QueryError::NotSelect);
+        }
+        Ok(stmt)
+    }
+
+    /// Validate and run a query
+    pub fn run(&self, sql: &str) -> Result<QueryResult, QueryError> {
+        let mut stmt = self.prepare(sql)?;
+
+        let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+        let deadline = Instant::now() + self.limits.timeout;
+        self.conn
+            .progress_handler(1_000, Some(move || Instant::now() >= deadline));
+
+        let result = self.collect_rows(&mut stmt, columns.len());
+        self.conn.progress_handler(0, None::<fn() -> bool>);
+
+        let (rows, truncated) = result.map_err(|e| match e.sqlite_error_code() {
+            Some(ErrorCode::OperationInterrupted) => QueryError::Timeout(self.limits.timeout),
+            _ => QueryError::Sqlite(e),
+        })?;
+        Ok(QueryResult {
+            columns,
+            rows,
+            truncated,
+        })
+    }
+
+    /// Validate a query and stream its rows into `sink`, one chunk of
+    /// `chunk_rows` at a time. Each chunk, including writing it to the sink,
+    /// must complete within the timeout.
+    pub fn export(
+        &self,
+        sql: &str,
+        format: ExportFormat,
+        sink: &mut impl Write,
+    ) -> Result<ExportSummary, QueryError> {
+        let mut stmt = self.prepare(sql)?;
+        let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+
+        // Deadline of the current chunk, in milliseconds since `start`
+        let start = Instant::now();
+        let budget_ms = self.limits.timeout.as_millis() as u64;
+        let deadline = Arc::new(AtomicU64::new(budget_ms));
+        let handler_deadline = deadline.clone();
+        self.conn.progress_handler(
+            1_000,
+            Some(move || {
+                start.elapsed().as_millis() as u64 >= handler_deadline.load(Ordering::Relaxed)
+            }),
+        );
+
+        let mut chunk = Vec::new();
+        format.write_header(&columns, &mut chunk);
+        let result = self.stream_rows(&mut stmt, columns.len(), format, &mut chunk, |chunk| {
+            sink.write_all(chunk)?;
+            chunk.clear();
+            deadline.store(
+                start.elapsed().as_millis() as u64 + budget_ms,
+                Ordering::Relaxed,
+            );
+            Ok(())
+        });
+        self.conn.progress_handler(0, None::<fn() -> bool>);
+
+        let summary = result?;
+        format.write_trailer(summary.truncated, &mut chunk);
+        sink.write_all(&chunk)?;
+        sink.flush()?;
+        Ok(summary)
+    }
+
+    fn stream_rows(
+        &self,
+        stmt: &mut rusqlite::Statement<'_>,
+        column_count: usize,
+        format: ExportFormat,
+        chunk: &mut Vec<u8>,
+        mut flush: impl FnMut(&mut Vec<u8>) -> std::io::Result<()>,
+    ) -> Result<ExportSummary, QueryError> {
+        let interrupted = |e: rusqlite::Error| match e.sqlite_error_code() {
+            Some(ErrorCode::OperationInterrupted) => QueryError::Timeout(self.limits.timeout),
+            _ => QueryError::Sqlite(e),
+        };
+        let mut rows = 0;
+        let mut cursor = stmt.query([]).map_err(interrupted)?;
+        while let Some(row) = cursor.next().map_err(interrupted)? {
+            if rows == self.limits.max_rows {
+                return Ok(ExportSummary {
+                    rows,
+                    truncated: true,
+                });
+            }
+            let values = (0..column_count)
+                .map(|i| row.get_ref(i).map(to_json))
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            format.write_row(&values, chunk);
+            rows += 1;
+            if rows % self.limits.chunk_rows.max(1) == 0 {
+                flush(chunk)?;
+            }
+        }
+        Ok(ExportSummary {
+            rows,
+            truncated: false,
+        })
+    }
+
+    fn collect_rows(
+        &self,
+        stmt: &mut rusqlite::Statement<'_>,
+        column_count: usize,
+    ) -> rusqlite::Result<(Vec<Vec<serde_json::Value>>, bool)> {
+        let mut rows = Vec::new();
+        let mut cursor = stmt.query([])?;
+        while let Some(row) = cursor.next()? {
+            if rows.len() == self.limits.max_rows {
+                return Ok((rows, true));
+            }
+            let values = (0..column_count)
+                .map(|i| row.get_ref(i).map(to_json))
+                .collect::<rusqlite::Result<_>>()?;
+            rows.push(values);
+        }
+        Ok((rows, false))
+    }
+}
+
+/// Authorizer for one statement: SELECT machinery, the
+/// [`ALLOWED_FUNCTIONS`], and reads of the whitelisted views.
+///
+/// A view's own reads are allowed on the tables it is built on, so a CTE
+/// named like a view can't reach anything else. SQLite reports the
+/// column-less read of a flattened view (`count(*)`, `SELECT 1`) against
+/// its table and without the view, so those are allowed only on tables the
+/// statement has already read through a view. Column-less reads of names
+/// not `stored` in the database are CTE scans and allowed.
+fn authorizer(
+    stored: Arc<HashSet<String>>,
+) -> impl FnMut(AuthContext<'_>) -> Authorization + Send + 'static {
+    let mut read_through_views = HashSet::new();
+    move |ctx| {
+        let allowed = match ctx.action {
+            AuthAction::Select | AuthAction::Recursive => true,
+            AuthAction::Function { function_name } => ALLOWED_FUNCTIONS
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(function_name)),
+            AuthAction::Read {
+                table_name,
+                column_name,
+            } => {
+                let through_view = ctx.accessor.is_some_and(|view| {
+                    VIEW_TABLES
+                        .iter()
+                        .any(|(name, tables)| *name == view && tables.contains(&table_name))
+                });
+                if through_view {
+                    read_through_views.insert(table_name.to_string());
+                }
+                let column_less_ok =
+                    read_through_views.contains(table_name) || !stored.contains(table_name);
+                QUERYABLE_VIEWS.contains(&table_name)
+                    || (column_name.is_empty() && column_less_ok)
+                    || (!column_name.is_empty() && through_view)
+            }
+            _ => false,
+        };
+        if allowed {
+            Authorization::Allow
+        } else {
+            Authorization::Deny
+        }
+    }
+}
+
+/// Check the query is a single SELECT/WITH statement, returning it trimmed
+fn validate<'a>(sql: &'a str, limits: &QueryLimits) -> Result<&'a str, QueryError> {
+    if sql.len() > limits.max_sql_bytes {
+        return Err(QueryError::TooLong(sql.len()));
+    }
+
+    let body = match statement_end(sql) {
+        Some(end) if !is_blank(&sql[end + 1..]) => return Err(QueryError::MultipleStatements),
+        Some(end) => &sql[..end],
+        None => sql,
+    };
+    if is_blank(body) {
+        return Err(QueryError::Empty);
+    }
+
+    let keyword: String = skip_comments(body)
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .collect();
+    if !keyword.eq_ignore_ascii_case("select") && !keyword.eq_ignore_ascii_case("with") {
+        return Err(QueryError::NotSelect);
+    }
+    Ok(body)
+}
+
+/// Byte offset of the first `;` outside literals and comments
+fn statement_end(sql: &str) -> Option<usize> {
+    let bytes = sql.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            quote @ (b'\'' | b'"' | b'`') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    i += 1;
+                }
+            }
+            b'[' => {
+                while i < bytes.len() && bytes[i] != b']' {
+                    i += 1;
+                }
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i + 1 < bytes.len() && !(bytes[i] == b'*' && bytes[i + 1] == b'/') {
+                    i += 1;
+                }
+                i += 1;
+            }
+            b';' => return Some(i),
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Strip leading whitespace and comments
+fn skip_comments(mut sql: &str) -> &str {
+    loop {
+        sql = sql.trim_start();
+        if let Some(rest) = sql.strip_prefix("--") {
+            sql = rest.split_once('\n').map_or("", |(_, tail)| tail);
+        } else if let Some(rest) = sql.strip_prefix("/*") {
+            sql = rest.split_once("*/").map_or("", |(_, tail)| tail);
+        } else {
+            return sql;
+        }
+    }
+}
+
+fn is_blank(sql: &str) -> bool {
+    skip_comments(sql).is_empty()
+}
+
+fn to_json(value: ValueRef<'_>) -> serde_json::Value {
+    match value {
+        ValueRef::Null => serde_json::Value::Null,
+        ValueRef::Integer(i) => i.into(),
+        ValueRef::Real(f) => {
+            serde_json::Number::from_f64(f).map_or(serde_json::Value::Null, Into::into)
+        }
+        ValueRef::Text(t) => String::from_utf8_lossy(t).into(),
+        ValueRef::Blob(b) => b
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>()
+            .into(),
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::FleetStore;
+    use aetheris_shared::{
+        AnomalyReport, AnomalyType, Position, RobotState, RobotType, SeverityLevel, Timestamp,
+    };
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    const HOUR_MS: u64 = 3_600_000;
+
+    /// Temporary database removed on drop
+    struct TempDb(PathBuf);
+
+    impl TempDb {
+        fn new() -> Self {
+            Self(std::env::temp_dir().join(format!("aetheris-query-{}.db", uuid::Uuid::new_v4())))
+        }
+    }
+
+    impl Drop for TempDb {
+        fn drop(&mut self) {
+            for suffix in ["", "-wal", "-shm"] {
+                let _ = std::fs::remove_file(format!("{}{suffix}", self.0.display()));
+            }
+        }
+    }
+
+    fn state(id: &str, robot_type: RobotType, battery: f64, timestamp: u64) -> RobotState {
+        RobotState {
+            battery,
+            timestamp: Timestamp::from_millis(timestamp),
+            ..RobotState::new(id.parse().unwrap(), id, robot_type)
+        }
+    }
+
+    fn seeded(limits: QueryLimits) -> (TempDb, FleetStore, QueryEngine) {
+        let db = TempDb::new();
+        let store = FleetStore::open(&db.0).unwrap();
+        for robot in [
+            state("RV-001", RobotType::Rover, 80.0, 0),
+            state("RV-002", RobotType::Rover, 60.0, 10_000),
+            state("RV-001", RobotType::Rover, 40.0, HOUR_MS + 5_000),
+            state("DR-001", RobotType::Drone, 50.0, 20_000),
+        ] {
+            store.record_robot_state(&robot).unwrap();
+        }
+        store
+            .record_anomaly(&AnomalyReport::new(
+                AnomalyType::Leak,
+                SeverityLevel::High,
+                Position::new(1.0, 2.0, 3.0),
+                "PIPE-001",
+                "RV-001",
+                0.9,
+                "Leak",
+            ))
+            .unwrap();
+        let engine = QueryEngine::open(&db.0, limits).unwrap();
+        (db, store, engine)
+    }
+
+    #[test]
+    fn test_average_battery_by_type_and_hour() {
+        let (_db, _store, engine) = seeded(QueryLimits::default());
+        let result = engine
+            .run(
+                "SELECT robot_type, timestamp / 3600000 AS hour, AVG(battery) AS avg_battery \
+                 FROM v_robots_history GROUP BY 1, 2 ORDER BY 1, 2;",
+            )
+            .unwrap();
+
+        assert_eq!(result.columns, ["robot_type", "hour", "avg_battery"]);
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![json!("drone"), json!(0), json!(50.0)],
+                vec![json!("rover"), json!(0), json!(70.0)],
+                vec![json!("rover"), json!(1), json!(40.0)],
+            ]
+        );
+        assert!(!result.truncated);
+
+        let anomalies = engine
+            .run("SELECT section_id, x, y FROM v_anomalies")
+            .unwrap();
+        assert_eq!(
+            anomalies.rows,
+            vec![vec![json!("PIPE-001"), json!(1.0), json!(2.0)]]
+        );
+    }
+
+    #[test]
+    fn test_only_single_selects_on_views_are_allowed() {
+        let (_db, _store, engine) = seeded(QueryLimits::default());
+
+        assert!(matches!(
+            engine.run("UPDATE robot_history SET battery = 0"),
+            Err(QueryError::NotSelect)
+        ));
+        assert!(matches!(
+            engine.run("ATTACH DATABASE '/tmp/x.db' AS x"),
+            Err(QueryError::NotSelect)
+        ));
+        assert!(matches!(
+            engine.run("SELECT 1 FROM v_events; DELETE FROM events"),
+            Err(QueryError::MultipleStatements)
+        ));
+        assert!(matches!(
+            engine.run("  -- nothing\n"),
+            Err(QueryError::Empty)
+        ));
+
+        // Raw tables and the catalog are not whitelisted
+        assert!(matches!(
+            engine.run("SELECT * FROM robot_history"),
+            Err(QueryError::Sqlite(_))
+        ));
+        assert!(matches!(
+            engine.run("SELECT name FROM sqlite_master"),
+            Err(QueryError::Sqlite(_))
+        ));
+
+        // Nor are column-less reads of them, while those of views are fine
+        for sql in [
+            "SELECT count(*) FROM robot_history",
+            "SELECT 1 FROM anomalies",
+            "SELECT count(*) FROM sections",
+            "SELECT count(*) FROM sqlite_master",
+        ] {
+            assert!(
+                matches!(engine.run(sql), Err(QueryError::Sqlite(_))),
+                "{sql}"
+            );
+        }
+        for (sql, count) in [
+            ("SELECT count(*) FROM v_robots_history", 4),
+            ("SELECT count(*) FROM v_anomalies", 1),
+            (
+                "WITH r AS (SELECT robot_id FROM v_robots_history) SELECT count(*) FROM r",
+                4,
+            ),
+        ] {
+            assert_eq!(engine.run(sql).unwrap().rows, [[json!(count)]], "{sql}");
+        }
+
+        // A CTE named like a view still only reaches that view's tables
+        assert!(matches!(
+            engine.run(
+                "WITH v_events AS (SELECT robot_id FROM robot_history) SELECT * FROM v_events"
+            ),
+            Err(QueryError::Sqlite(_))
+        ));
+
+        // Only allowlisted functions may be called
+        for sql in [
+            "SELECT load_extension('/tmp/evil.so') FROM v_events",
+            "SELECT readfile('/etc/passwd')",
+            "SELECT zeroblob(1000000000)",
+            "SELECT sqlite_version()",
+        ] {
+            assert!(
+                matches!(engine.run(sql), Err(QueryError::Sqlite(_))),
+                "{sql}"
+            );
+        }
+        let result = engine
+            .run("SELECT upper(robot_id), round(avg(battery), 1) FROM v_robots_history GROUP BY 1 ORDER BY 1")
+            .unwrap();
+        assert_eq!(result.rows[0], [json!("DR-001"), json!(50.0)]);
+
+        // Semicolons inside literals and comments are not statement breaks
+        let result = engine
+            .run("SELECT ';' AS s FROM v_events /* ; */ -- ;\n;")
+            .unwrap();
+        assert!(result.rows.is_empty());
+    }
+
+    #[test]
+    fn test_row_cap_truncates_results() {
+        let (_db, _store, engine) = seeded(QueryLimits {
+            max_rows: 2,
+            ..QueryLimits::default()
+        });
+        let result = engine.run("SELECT robot_id FROM v_robots_history").unwrap();
+        assert_eq!(result.rows.len(), 2);
+        assert!(result.truncated);
+    }
+
+    #[test]
+    fn test_export_matches_buffered_result() {
+        let (_db, store, engine) = seeded(QueryLimits {
+            chunk_rows: 2,
+            ..QueryLimits::default()
+        });
+        store
+            .record_event("note", Some("RV-001"), &"a, \"quoted\"\nline", 30_000)
+            .unwrap();
+        let queries = [
+            "SELECT robot_id, battery, x, timestamp FROM v_robots_history ORDER BY timestamp",
+            "SELECT kind, subject, payload FROM v_events",
+        ];
+        for sql in queries {
+            for format in [ExportFormat::Csv, ExportFormat::JsonLines] {
+                let mut buffered = Vec::new();
+                engine
+                    .run(sql)
+                    .unwrap()
+                    .write_to(format, &mut buffered)
+                    .unwrap();
+                let mut streamed = Vec::new();
+                let summary = engine.export(sql, format, &mut streamed).unwrap();
+                assert_eq!(streamed, buffered, "{sql} as {format:?}");
+                assert!(!summary.truncated);
+            }
+        }
+
+        let mut csv = Vec::new();
+        engine
+            .export("SELECT payload FROM v_events", ExportFormat::Csv, &mut csv)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "payload\r\n\"\"\"a, \\\"\"quoted\\\"\"\\nline\"\"\"\r\n"
+        );
+    }
+
+    #[test]
+    fn test_export_applies_row_cap() {
+        let (_db, _store, engine) = seeded(QueryLimits {
+            max_rows: 3,
+            chunk_rows: 2,
+            ..QueryLimits::default()
+        });
+        let sql = "SELECT robot_id FROM v_robots_history ORDER BY timestamp";
+        let mut streamed = Vec::new();
+        let summary = engine
+            .export(sql, ExportFormat::JsonLines, &mut streamed)
+            .unwrap();
+        assert_eq!(
+            summary,
+            ExportSummary {
+                rows: 3,
+                truncated: true
+            }
+        );
+        let mut buffered = Vec::new();
+        engine
+            .run(sql)
+            .unwrap()
+            .write_to(ExportFormat::JsonLines, &mut buffered)
+            .unwrap();
+        assert_eq!(streamed, buffered);
+        assert!(
+            String::from_utf8(streamed)
+                .unwrap()
+                .ends_with("{\"truncated\":true}\r\n")
+        );
+    }
+
+    #[test]
+    fn test_long_running_query_times_out() {
+        let (_db, _store, engine) = seeded(QueryLimits {
+            timeout: Duration::from_millis(50),
+            ..QueryLimits::default()
+        });
+        let started = Instant::now();
+        let err = engine
+            .run(
+                "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n) \
+                 SELECT count(*) FROM n",
+            )
+            .unwrap_err();
+        assert!(matches!(err, QueryError::Timeout(_)));
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        // The connection is usable afterwards
+        assert!(engine.run("SELECT count(*) FROM v_anomalies").is_ok());
+    }
+}
@@ -0,0 +1,208 @@
+//! Broker reconnection: backoff and resubscription
+//!
+//! When the connection drops, the event loop retries with exponential
+//! backoff, each delay shortened by a random jitter so engines that lost
+//! the same broker do not reconnect in lockstep. With a clean session the
+//! broker forgets our subscriptions, so every ConnAck that did not resume a
+//! session triggers a resubscribe; without it the engine would stay
+//! connected and hear nothing.
+
+use std::time::Duration;
+
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Delays between reconnection attempts
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconnectConfig {
+    /// Delay before the first retry
+    pub initial_delay: Duration,
+    /// Upper bound on the delay, however many attempts failed
+    pub max_delay: Duration,
+    /// Growth of the delay per failed attempt
+    pub multiplier: f64,
+    /// Fraction of each delay that may be randomly cut off
+    pub jitter: f64,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl CheckConfig for ReconnectConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        checker.positive("initial_delay", self.initial_delay);
+        if self.max_delay < self.initial_delay {
+            checker.error(
+                "max_delay",
+                format!(
+                    "must be at least initial_delay ({:?}), got {:?}",
+                    self.initial_delay, self.max_delay
+                ),
+                None,
+            );
+        }
+        if !(self.multiplier >= 1.0 && self.multiplier.is_finite()) {
+            checker.error(
+                "multiplier",
+                format!("must be at least 1, got {}", self.multiplier),
+                None,
+            );
+        }
+        checker.fraction("jitter", self.jitter);
+    }
+}
+
+// ============================================================================
+// BACKOFF
+// ============================================================================
+
+/// Delay schedule of consecutive failed connection attempts
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    config: ReconnectConfig,
+    attempt: u32,
+}
+
+impl Backoff {
+    pub fn new(config: ReconnectConfig) -> Self {
+        Self { config, attempt: 0 }
+    }
+
+    /// Delay before the next attempt, with random jitter
+    pub fn next_delay(&mut self) -> Duration {
+        self.next_delay_with(rand::random::<f64>())
+    }
+
+    /// Delay before the next attempt; `sample` in [0, 1) picks how much of
+    /// the jitter is applied
+    pub fn next_delay_with(&mut self, sample: f64) -> Duration {
+        let base = self.config.initial_delay.as_secs_f64()
+            * self.config.multiplier.powi(self.attempt as i32);
+        let capped = base.min(self.config.max_delay.as_secs_f64());
+        self.attempt = self.attempt.saturating_add(1);
+        Duration::from_secs_f64(capped * (1.0 - self.config.jitter * sample))
+    }
+
+    /// The connection is back: the next failure starts from the initial delay
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+// ============================================================================
+// CONNECTION STATE
+// ============================================================================
+
+/// Whether the engine currently has a broker connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    Connected,
+    Disconnected,
+}
+
+/// What to do about a ConnAck
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnAckOutcome {
+    /// The connection had been lost before
+    pub reconnected: bool,
+    /// The broker holds none of our subscriptions
+    pub resubscribe: bool,
+}
+
+/// Connection transitions seen by the event loop
+#[derive(Debug, Default)]
+pub struct ConnectionMonitor {
+    connected: bool,
+    ever_connected: bool,
+}
+
+impl ConnectionMonitor {
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// The broker accepted the connection
+    pub fn on_connack(&mut self, session_present: bool) -> ConnAckOutcome {
+        let reconnected = self.ever_connected;
+        self.connected = true;
+        self.ever_connected = true;
+        ConnAckOutcome {
+            reconnected,
+            resubscribe: !session_present,
+        }
+    }
+
+    /// Polling failed; true if this ended a live connection
+    pub fn on_error(&mut self) -> bool {
+        std::mem::replace(&mut self.connected, false)
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_to_the_cap_and_resets() {
+        let mut backoff = Backoff::new(ReconnectConfig {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: 0.5,
+        });
+        let schedule: Vec<_> = (0..6).map(|_| backoff.next_delay_with(0.0)).collect();
+        assert_eq!(
+            schedule,
+            [1, 2, 4, 8, 10, 10].map(Duration::from_secs).to_vec()
+        );
+        // Jitter only ever shortens a delay
+        assert_eq!(backoff.next_delay_with(0.5), Duration::from_millis(7_500));
+        assert!(backoff.next_delay() <= Duration::from_secs(10));
+
+        backoff.reset();
+        assert_eq!(backoff.next_delay_with(0.0), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_resubscribe_after_lost_session() {
+        let mut monitor = ConnectionMonitor::default();
+        assert!(!monitor.on_error());
+        assert_eq!(
+            monitor.on_connack(false),
+            ConnAckOutcome {
+                reconnected: false,
+                resubscribe: true
+            }
+        );
+        assert!(monitor.on_error());
+        assert!(!monitor.on_error());
+
+        // Broker restarted with a clean session
+        assert_eq!(
+            monitor.on_connack(false),
+            ConnAckOutcome {
+                reconnected: true,
+                resubscribe: true
+            }
+        );
+        // Broker resumed our session and still has the subscriptions
+        monitor.on_error();
+        assert!(!monitor.on_connack(true).resubscribe);
+        assert!(monitor.is_connected());
+    }
+}
@@ -0,0 +1,449 @@
+//! Replay of a recorded session
+//!
+//! Republishes an [event log](crate::event_log) to the broker with the
+//! original spacing between messages, scaled by a speed factor, so the
+//! Brain and the dashboard can be demoed or regression-tested against a
+//! known session. Played locally, the events go straight into this
+//! engine's message processor instead, as if they had just arrived. The
+//! replayed messages themselves are not republished, but the engine reacts
+//! to them as it would live: filtered telemetry and alerts are published
+//! and recalls and dispatches send real commands to the robots. Local
+//! replay always restamps payloads, since the processor would otherwise
+//! treat the recorded ones as stale. Besides event log files, any JSONL
+//! file with one `{"timestamp": <unix ms>, "topic": "...", "payload":
+//! {...}}` object per line can be replayed.
+//!
+//! Payloads may be recorded bare (as the event log does) or in their
+//! [`MqttMessage`] envelope. Bare payloads on enveloped topics are wrapped
+//! in a fresh envelope, numbered by the engine's outgoing sequence counter
+//! and attributed to the publisher the source bindings expect there.
+//! Commands are skipped unless asked for: replaying a chaos command would
+//! trigger it again. Events without a topic (the engine's own) are never
+//! republished.
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use aetheris_shared::MqttMessage;
+use aetheris_shared::topics::{self, Topic};
+use serde_json::Value;
+use tokio::time::Instant;
+use tracing::{error, info, warn};
+
+use crate::AetherisMqtt;
+use crate::event_log::{EventLogReader, LoggedEvent};
+use crate::sequence::MessageClass;
+use crate::shutdown::Shutdown;
+
+// ============================================================================
+// OPTIONS
+// ============================================================================
+
+/// How a recording is played back
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayOptions {
+    /// Playback speed; 2.0 plays twice as fast as recorded
+    pub speed: f64,
+    /// Start over after the last event
+    pub looped: bool,
+    /// Republish recorded commands too
+    pub include_commands: bool,
+    /// Stamp payload timestamps with the time they are republished, so
+    /// staleness checks downstream do not fire
+    pub rewrite_timestamps: bool,
+    /// Feed the events to this engine's message processor rather than
+    /// publishing them. The engine still acts on them, and timestamps are
+    /// rewritten whatever `rewrite_timestamps` says.
+    pub local: bool,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            looped: false,
+            include_commands: false,
+            rewrite_timestamps: false,
+            local: false,
+        }
+    }
+}
+
+// ============================================================================
+// RECORDING
+// ============================================================================
+
+/// Events to replay, in time order
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recording {
+    events: Vec<LoggedEvent>,
+    /// Events that came earlier in the file than an older one
+    pub out_of_order: usize,
+    /// Commands and topic-less events left out
+    pub skipped: usize,
+}
+
+impl Recording {
+    /// Read a recording file or event log directory
+    pub fn load(path: &Path, include_commands: bool) -> std::io::Result<Self> {
+        Ok(Self::from_events(
+            EventLogReader::iter(path)?,
+            include_commands,
+        ))
+    }
+
+    /// Keep the republishable events and sort them by time
+    pub fn from_events(
+        events: impl IntoIterator<Item = LoggedEvent>,
+        include_commands: bool,
+    ) -> Self {
+        let mut kept = Vec::new();
+        let mut skipped = 0;
+        for event in events {
+            let republish = event
+                .topic
+                .as_deref()
+                .and_then(topics::parse)
+                .is_some_and(|topic| include_commands || !matches!(topic, Topic::Commands { .. }));
+            if republish {
+                kept.push(event);
+            } else {
+                skipped += 1;
+            }
+        }
+        let out_of_order = kept
+            .windows(2)
+            .filter(|pair| pair[1].received_at < pair[0].received_at)
+            .count();
+        kept.sort_by_key(|event| event.received_at);
+        Self {
+            events: kept,
+            out_of_order,
+            skipped,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Time from the first event to the last, as recorded
+    pub fn duration(&self) -> Duration {
+        match (self.events.first(), self.events.last()) {
+            (Some(first), Some(last)) => {
+                Duration::from_millis(last.received_at - first.received_at)
+            }
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+// ============================================================================
+// PAYLOADS
+// ============================================================================
+
+/// Envelope stream of an enveloped topic; `None` for bare payloads
+fn envelope_class(topic: &Topic) -> Option<MessageClass> {
+    match topic {
+        Topic::Telemetry { .. } => Some(MessageClass::Telemetry),
+        Topic::TelemetryBatch => Some(MessageClass::TelemetryBatch),
+        Topic::TelemetryFiltered { .. } => Some(MessageClass::FilteredTelemetry),
+        Topic::Alerts | Topic::AlertSeverity { .. } | Topic::AlertEscalations => {
+            Some(MessageClass::Alert)
+        }
+        Topic::Environment { .. } => Some(MessageClass::Environment),
+        Topic::Commands { .. } => Some(MessageClass::Command),
+        Topic::TriageRequests => Some(MessageClass::TriageRequest),
+        Topic::TriageResults => Some(MessageClass::TriageResult),
+        Topic::Routes { .. } | Topic::RouteLibrary => Some(MessageClass::Route),
+        Topic::Health { .. } => Some(MessageClass::SectionHealth),
+        Topic::Missions { .. } => Some(MessageClass::Mission),
+        Topic::Heartbeat { .. }
+        | Topic::Responses { .. }
+        | Topic::SystemStatus
+        | Topic::SystemLeader
+        | Topic::RouteRequests
+        | Topic::DeadLetter
+        | Topic::BroadcastResults => None,
+    }
+}
+
+/// Publisher the source bindings expect on a topic
+fn expected_source(topic: &Topic, payload: &Value) -> String {
+    let field = |name: &str| payload.get(name).and_then(Value::as_str).map(String::from);
+    match topic {
+        Topic::Telemetry { robot_id } => robot_id.to_string(),
+        Topic::TelemetryBatch => field("source").unwrap_or_else(|| "engine".into()),
+        Topic::Environment { section_id } => section_id.clone(),
+        Topic::Alerts | Topic::AlertSeverity { .. } => {
+            field("detected_by").unwrap_or_else(|| "engine".into())
+        }
+        Topic::Commands { .. } => field("source").unwrap_or_else(|| "dashboard".into()),
+        Topic::TriageResults => "brain".into(),
+        _ => "engine".into(),
+    }
+}
+
+fn is_envelope(payload: &Value) -> bool {
+    payload.get("payload").is_some() && payload.get("seq").is_some()
+}
+
+/// The message to publish for a recorded event, stamped `now` when
+/// timestamps are rewritten. `next_seq` numbers new envelopes.
+pub fn prepare(
+    event: &LoggedEvent,
+    rewrite_timestamps: bool,
+    now: u64,
+    mut next_seq: impl FnMut(&str, MessageClass) -> u64,
+) -> Option<(String, Value)> {
+    let topic_name = event.topic.clone()?;
+    let topic = topics::parse(&topic_name)?;
+    let mut payload = event.payload.clone();
+    let restamp = |value: &mut Value| {
+        if rewrite_timestamps && let Some(timestamp) = value.get_mut("timestamp") {
+            *timestamp = now.into();
+        }
+    };
+
+    let Some(class) = envelope_class(&topic) else {
+        restamp(&mut payload);
+        return Some((topic_name, payload));
+    };
+    if is_envelope(&payload) {
+        restamp(&mut payload);
+        if let Some(inner) = payload.get_mut("payload") {
+            restamp(inner);
+        }
+        return Some((topic_name, payload));
+    }
+
+    // A recorded command carries its id and issuer next to the command
+    let source = expected_source(&topic, &payload);
+    let mut command_id = None;
+    if matches!(topic, Topic::Commands { .. }) && payload.get("command").is_some() {
+        command_id = payload
+            .get("command_id")
+            .and_then(Value::as_str)
+            .map(String::from);
+        payload = payload["command"].take();
+    }
+    restamp(&mut payload);
+    let msg = MqttMessage::new(payload, &source, next_seq(&source, class));
+    let msg = match command_id {
+        Some(id) => msg.with_command_id(id),
+        None => msg,
+    };
+    Some((topic_name, serde_json::to_value(msg).ok()?))
+}
+
+// ============================================================================
+// PLAYBACK
+// ============================================================================
+
+/// Play `recording` to the broker, or locally, until it ends (or, looped,
+/// until the shutdown)
+pub async fn run_replay(
+    mqtt: Arc<AetherisMqtt>,
+    recording: Recording,
+    options: ReplayOptions,
+    mut shutdown: Shutdown,
+) {
+    let Some(first) = recording.events.first().map(|event| event.received_at) else {
+        warn!("Nothing to replay");
+        return;
+    };
+    let mut pass = 1;
+    loop {
+        info!(
+            pass,
+            events = recording.len(),
+            recorded = ?recording.duration(),
+            speed = options.speed,
+            local = options.local,
+            "Replaying recorded session"
+        );
+        let start = Instant::now();
+        let rewrite_timestamps = options.rewrite_timestamps || options.local;
+        for event in &recording.events {
+            let offset = Duration::from_millis(event.received_at - first);
+            let due = start + offset.div_f64(options.speed);
+            tokio::select! {
+                _ = tokio::time::sleep_until(due) => {}
+                _ = shutdown.wait() => return,
+            }
+            let now = aetheris_shared::current_timestamp_ms();
+            let prepared = prepare(event, rewrite_timestamps, now, |source, class| {
+                mqtt.next_sequence(source, class)
+            });
+            let Some((topic, payload)) = prepared else {
+                continue;
+            };
+            if options.local {
+                // Refusals are part of what a replay shows, not a failure
+                let bytes = serde_json::to_vec(&payload).unwrap_or_default();
+                if let Err(e) = mqtt.handle_incoming(&topic, &bytes).await {
+                    warn!("Replayed message on {} was refused: {}", topic, e);
+                }
+            } else if let Err(e) = mqtt.publish_value(&topic, &payload).await {
+                error!("Failed to replay a message on {}: {}", topic, e);
+            }
+        }
+        if !options.looped {
+            info!("Replay finished");
+            return;
+        }
+        pass += 1;
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetheris_shared::{RobotState, RobotType};
+    use serde_json::json;
+
+    use rumqttc::Request;
+    use tokio::sync::mpsc;
+
+    use crate::sequence::SequenceCounter;
+    use crate::{EngineMessage, MqttConfig, shutdown};
+
+    fn event(received_at: u64, topic: &str, payload: Value) -> LoggedEvent {
+        LoggedEvent {
+            received_at,
+            topic: Some(topic.into()),
+            kind: String::new(),
+            payload,
+        }
+    }
+
+    #[test]
+    fn test_recording_sorts_and_skips_commands() {
+        let telemetry = topics::telemetry(&"RV-001".parse().unwrap());
+        let events = vec![
+            event(2_000, &telemetry, json!({})),
+            event(1_000, &telemetry, json!({})),
+            event(
+                1_500,
+                &topics::commands(&"RV-001".parse().unwrap()),
+                json!({}),
+            ),
+            LoggedEvent {
+                topic: None,
+                ..event(1_700, "", json!({}))
+            },
+            event(3_000, &telemetry, json!({})),
+        ];
+
+        let recording = Recording::from_events(events.clone(), false);
+        let times: Vec<_> = recording.events.iter().map(|e| e.received_at).collect();
+        assert_eq!(times, [1_000, 2_000, 3_000]);
+        assert_eq!(recording.out_of_order, 1);
+        assert_eq!(recording.skipped, 2);
+        assert_eq!(recording.duration(), Duration::from_secs(2));
+        assert_eq!(Recording::from_events(events, true).len(), 4);
+
+        // The documented minimal format parses as a logged event
+        let line = r#"{"timestamp": 5, "topic": "aetheris/alerts", "payload": {}}"#;
+        let parsed: LoggedEvent = serde_json::from_str(line).unwrap();
+        assert_eq!(parsed.received_at, 5);
+    }
+
+    #[test]
+    fn test_bare_payloads_are_wrapped_and_restamped() {
+        let mut counter = SequenceCounter::default();
+        let mut seq = |source: &str, class| counter.next(source, class);
+        let state = RobotState::new("RV-001".parse().unwrap(), "Rover", RobotType::Rover);
+        let telemetry = event(
+            1_000,
+            &topics::telemetry(&"RV-001".parse().unwrap()),
+            json!(state),
+        );
+
+        let (topic, first) = prepare(&telemetry, true, 42, &mut seq).unwrap();
+        assert_eq!(topic, "aetheris/telemetry/RV-001");
+        let msg: MqttMessage<RobotState> = serde_json::from_value(first).unwrap();
+        assert_eq!((msg.source.as_str(), msg.seq), ("RV-001", 0));
+        assert_eq!(msg.payload.timestamp, 42);
+        let (_, second) = prepare(&telemetry, false, 43, &mut seq).unwrap();
+        assert_eq!(second["seq"], 1);
+        assert_eq!(second["payload"]["timestamp"], state.timestamp.as_millis());
+
+        // Heartbeats travel bare; recorded envelopes are kept as they are
+        let heartbeat = event(
+            1_000,
+            &topics::heartbeat(&"RV-001".parse().unwrap()),
+            json!({"robot_id": "RV-001", "timestamp": 7}),
+        );
+        let (_, bare) = prepare(&heartbeat, true, 42, &mut seq).unwrap();
+        assert_eq!(bare, json!({"robot_id": "RV-001", "timestamp": 42}));
+        let envelope =
+            json!({"payload": {"timestamp": 7}, "source": "PIPE-001", "seq": 9, "timestamp": 7});
+        let environment = event(1_000, &topics::environment("PIPE-001"), envelope);
+        let (_, kept) = prepare(&environment, false, 42, &mut seq).unwrap();
+        assert_eq!(kept["seq"], 9);
+
+        // A recorded command keeps its id and issuer
+        let command = event(
+            1_000,
+            &topics::commands(&"RV-001".parse().unwrap()),
+            json!({"command_id": "cmd-1", "source": "dashboard", "command": {"command": "emergency_stop"}}),
+        );
+        let (_, wrapped) = prepare(&command, false, 42, &mut seq).unwrap();
+        assert_eq!(wrapped["command_id"], "cmd-1");
+        assert_eq!(wrapped["source"], "dashboard");
+        assert_eq!(wrapped["payload"], json!({"command": "emergency_stop"}));
+    }
+
+    #[tokio::test]
+    async fn test_local_replay_feeds_the_message_processor() {
+        let (tx, mut rx) = mpsc::channel(10);
+        let (mqtt, mut eventloop) = AetherisMqtt::new(MqttConfig::default(), tx).await.unwrap();
+        let mqtt = Arc::new(mqtt);
+        let telemetry = topics::telemetry(&"RV-001".parse().unwrap());
+        let events: Vec<_> = (0..3u32)
+            .map(|i| {
+                let mut state =
+                    RobotState::new("RV-001".parse().unwrap(), "Rover", RobotType::Rover);
+                state.battery = 90.0 - f64::from(i);
+                event(1_000 + u64::from(i), &telemetry, json!(state))
+            })
+            .collect();
+        // Restamped without asking, or the old telemetry would be dropped
+        let options = ReplayOptions {
+            speed: 1_000.0,
+            local: true,
+            ..ReplayOptions::default()
+        };
+        let (_trigger, shutdown) = shutdown::channel();
+
+        run_replay(
+            mqtt.clone(),
+            Recording::from_events(events, false),
+            options,
+            shutdown,
+        )
+        .await;
+        for battery in [90.0, 89.0, 88.0] {
+            match rx.try_recv() {
+                Ok(EngineMessage::TelemetryReceived(state)) => assert_eq!(state.battery, battery),
+                other => panic!("expected telemetry, got {other:?}"),
+            }
+        }
+        // The replayed telemetry itself was not republished
+        eventloop.clean();
+        assert!(!eventloop.pending.iter().any(|request| matches!(
+            request,
+            Request::Publish(publish) if publish.topic == telemetry
+        )));
+    }
+}
@@ -0,0 +1,183 @@
+//! Effective configuration of each robot
+//!
+//! A `Configure` command carries only the settings it changes. The registry
+//! merges each into what the robot was configured with before, so setting
+//! the heartbeat interval keeps an earlier maximum speed, and answers
+//! `GetConfig` with the result.
+//!
+//! With a file configured, the registry is loaded from it at start and
+//! rewritten after every change, so robots keep their configuration across
+//! an engine restart. The file is written beside itself and renamed into
+//! place, so a crash mid-write leaves the previous version.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use aetheris_shared::RobotConfig;
+
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Where robot configuration is kept
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RobotConfigStorage {
+    /// JSON file of every robot's configuration; memory only when unset
+    pub path: Option<PathBuf>,
+}
+
+impl CheckConfig for RobotConfigStorage {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if self
+            .path
+            .as_ref()
+            .is_some_and(|path| path.as_os_str().is_empty())
+        {
+            checker.error("path", "must not be empty", Some("leave it unset".into()));
+        }
+    }
+}
+
+/// Failure to load the robot configuration file
+#[derive(Debug, Error)]
+pub enum RobotConfigLoadError {
+    #[error("cannot read robot configuration {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+    #[error("malformed robot configuration {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+}
+
+// ============================================================================
+// REGISTRY
+// ============================================================================
+
+/// Merged configuration of every configured robot
+#[derive(Debug, Default)]
+pub struct RobotConfigRegistry {
+    path: Option<PathBuf>,
+    configs: BTreeMap<String, RobotConfig>,
+}
+
+impl RobotConfigRegistry {
+    /// The registry kept in `storage`, empty when its file does not exist yet
+    pub fn load(storage: &RobotConfigStorage) -> Result<Self, RobotConfigLoadError> {
+        let Some(path) = &storage.path else {
+            return Ok(Self::default());
+        };
+        let configs = match std::fs::read_to_string(path) {
+            Ok(text) => {
+                serde_json::from_str(&text).map_err(|source| RobotConfigLoadError::Parse {
+                    path: path.clone(),
+                    source,
+                })?
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(source) => {
+                return Err(RobotConfigLoadError::Io {
+                    path: path.clone(),
+                    source,
+                });
+            }
+        };
+        Ok(Self {
+            path: Some(path.clone()),
+            configs,
+        })
+    }
+
+    /// Merge a `Configure` sent to `robot_id` into its configuration and
+    /// return the result
+    pub fn configure(&mut self, robot_id: &str, update: &RobotConfig) -> RobotConfig {
+        let config = self.configs.entry(robot_id.to_string()).or_default();
+        config.merge(update);
+        config.clone()
+    }
+
+    /// Configuration of `robot_id`; everything unset for a robot never
+    /// configured
+    pub fn get(&self, robot_id: &str) -> RobotConfig {
+        self.configs.get(robot_id).cloned().unwrap_or_default()
+    }
+
+    /// Every configured robot with its configuration, by robot ID
+    pub fn all(&self) -> Vec<(String, RobotConfig)> {
+        self.configs
+            .iter()
+            .map(|(robot_id, config)| (robot_id.clone(), config.clone()))
+            .collect()
+    }
+
+    /// Write every robot's configuration to the file, if there is one
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let json = serde_json::to_string_pretty(&self.configs)?;
+        let partial = partial_path(path);
+        std::fs::write(&partial, json)?;
+        std::fs::rename(&partial, path)
+    }
+}
+
+/// Where the file is written before being renamed over `path`
+pub(crate) fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".partial");
+    path.with_file_name(name)
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_updates_keep_earlier_settings_across_restart() {
+        let dir = std::env::temp_dir().join(format!("aetheris-robots-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let storage = RobotConfigStorage {
+            path: Some(dir.join("robots.json")),
+        };
+        let mut registry = RobotConfigRegistry::load(&storage).unwrap();
+        registry.configure(
+            "RV-001",
+            &RobotConfig {
+                max_speed: Some(1.5),
+                low_battery_threshold: Some(30.0),
+                ..RobotConfig::default()
+            },
+        );
+        let effective = registry.configure(
+            "RV-001",
+            &RobotConfig {
+                heartbeat_interval: Some(2),
+                max_speed: Some(1.2),
+                ..RobotConfig::default()
+            },
+        );
+        let expected = RobotConfig {
+            max_speed: Some(1.2),
+            heartbeat_interval: Some(2),
+            low_battery_threshold: Some(30.0),
+            ..RobotConfig::default()
+        };
+        assert_eq!(effective, expected);
+        assert_eq!(registry.get("DR-001"), RobotConfig::default());
+        registry.save().unwrap();
+
+        let reloaded = RobotConfigRegistry::load(&storage).unwrap();
+        assert_eq!(reloaded.get("RV-001"), expected);
+        assert!(!partial_path(storage.path.as_ref().unwrap()).exists());
+    }
+}
@@ -0,0 +1,902 @@
+//! Progressive rollout of robot configuration changes
+//!
+//! A new [`RobotConfig`] is pushed to one batch of robots at a time. Each
+//! batch must acknowledge the change and then survive a soak period in which
+//! every robot updated so far is watched for regressions: a new Error status,
+//! degraded health, or going offline. A clean soak moves on to the next
+//! batch. Once failures exceed the run's threshold the rollout halts and
+//! every updated robot is sent the configuration it had before, so the
+//! controller keeps a shadow of each robot's last acknowledged config.
+//!
+//! The controller only decides; the engine sends the [`ConfigPush`]es it
+//! returns and feeds it acknowledgements and fleet state. Each push carries
+//! the id of the command it goes out as, and only the response to that
+//! command settles the robot's acknowledgement.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use serde::Serialize;
+use thiserror::Error;
+
+use aetheris_shared::{HealthStatus, RobotConfig, RobotState, RobotStatus, RobotType};
+
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Rollout controller behavior
+#[derive(Debug, Clone, PartialEq)]
+pub struct RolloutConfig {
+    /// How long a batch has to acknowledge the new config
+    pub ack_timeout: Duration,
+    /// Finished rollouts kept for progress queries
+    pub history: usize,
+}
+
+impl Default for RolloutConfig {
+    fn default() -> Self {
+        Self {
+            ack_timeout: Duration::from_secs(30),
+            history: 50,
+        }
+    }
+}
+
+impl CheckConfig for RolloutConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        checker.positive("ack_timeout", self.ack_timeout);
+        if self.history == 0 {
+            checker.error("history", "must be greater than zero", None);
+        }
+    }
+}
+
+// ============================================================================
+// RUN STATE
+// ============================================================================
+
+/// Which robots a rollout targets
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "type", content = "value")]
+pub enum RobotSelector {
+    All,
+    Robots(Vec<String>),
+    Type(RobotType),
+    /// Robots whose id starts with the prefix (e.g., "RV-")
+    Prefix(String),
+}
+
+impl RobotSelector {
+    pub fn matches(&self, robot: &RobotState) -> bool {
+        match self {
+            RobotSelector::All => true,
+            RobotSelector::Robots(ids) => ids.iter().any(|id| robot.id == *id),
+            RobotSelector::Type(robot_type) => robot.robot_type == *robot_type,
+            RobotSelector::Prefix(prefix) => robot.id.starts_with(prefix.as_str()),
+        }
+    }
+}
+
+/// What to roll out, where, and how cautiously
+#[derive(Debug, Clone, PartialEq)]
+pub struct RolloutPlan {
+    pub config: RobotConfig,
+    pub selector: RobotSelector,
+    /// Robots updated at a time
+    pub batch_size: usize,
+    /// How long each batch is watched after acknowledging
+    pub soak_duration: Duration,
+    /// Failures tolerated before the run halts and reverts
+    pub abort_on_failures: usize,
+}
+
+/// Where a rollout is in its run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RolloutPhase {
+    /// Current batch has been pushed and not everyone has acknowledged
+    AwaitingAck,
+    /// Current batch acknowledged; watching for regressions
+    Soaking { until_ms: u64 },
+    /// Every batch passed its soak
+    Completed,
+    /// Failures exceeded the threshold and the change was reverted
+    Reverted,
+}
+
+impl RolloutPhase {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, RolloutPhase::Completed | RolloutPhase::Reverted)
+    }
+}
+
+/// Why a robot counted as a failure
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum Regression {
+    /// The robot refused the configuration
+    Rejected,
+    /// No acknowledgement within the timeout
+    AckTimeout,
+    /// The configure command could not be sent
+    Undelivered {
+        reason: String,
+    },
+    /// Status became Error
+    ErrorStatus,
+    HealthDegraded {
+        from: HealthStatus,
+        to: HealthStatus,
+    },
+    WentOffline,
+}
+
+/// A robot that failed during the rollout
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RolloutFailure {
+    pub robot_id: String,
+    /// Batch the robot belonged to (0-based)
+    pub batch: usize,
+    pub regression: Regression,
+    pub timestamp: u64,
+}
+
+/// Something that happened during a rollout
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "event")]
+pub enum RolloutEventKind {
+    Started {
+        robots: usize,
+        batches: usize,
+    },
+    BatchPushed {
+        batch: usize,
+        robots: Vec<String>,
+    },
+    BatchAcknowledged {
+        batch: usize,
+    },
+    BatchPassed {
+        batch: usize,
+    },
+    RobotFailed {
+        robot_id: String,
+        regression: Regression,
+    },
+    Halted {
+        failures: usize,
+    },
+    RevertPushed {
+        robot_id: String,
+    },
+    Completed,
+}
+
+/// Timestamped entry in a rollout's record
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RolloutEvent {
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub kind: RolloutEventKind,
+}
+
+/// A configuration to send to one robot
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigPush {
+    pub robot_id: String,
+    /// Id the configure command must be sent under
+    pub command_id: String,
+    pub config: RobotConfig,
+}
+
+impl ConfigPush {
+    fn new(robot_id: String, config: RobotConfig) -> Self {
+        Self {
+            robot_id,
+            command_id: crate::acks::new_command_id(),
+            config,
+        }
+    }
+}
+
+/// Snapshot of a rollout for operators
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RolloutProgress {
+    pub id: String,
+    pub phase: RolloutPhase,
+    pub config: RobotConfig,
+    /// Current batch (0-based)
+    pub batch: usize,
+    pub batches: Vec<Vec<String>>,
+    pub failures: Vec<RolloutFailure>,
+    pub events: Vec<RolloutEvent>,
+}
+
+/// Reason a rollout could not start
+#[derive(Debug, Error, PartialEq)]
+pub enum RolloutError {
+    #[error("batch size must be greater than zero")]
+    ZeroBatchSize,
+    #[error("no robots match the selector")]
+    NoRobots,
+    #[error("rollout {0} is still running")]
+    AlreadyRunning(String),
+}
+
+#[derive(Debug)]
+struct ConfigRollout {
+    id: String,
+    config: RobotConfig,
+    batches: Vec<Vec<String>>,
+    batch: usize,
+    soak: Duration,
+    abort_on_failures: usize,
+    phase: RolloutPhase,
+    /// Command id each robot of the current batch has yet to answer
+    pending_acks: HashMap<String, String>,
+    ack_deadline_ms: u64,
+    /// Status and health before the robot was updated
+    baseline: HashMap<String, (RobotStatus, HealthStatus)>,
+    /// Config each updated robot had before the rollout
+    prior: HashMap<String, RobotConfig>,
+    failures: Vec<RolloutFailure>,
+    events: Vec<RolloutEvent>,
+    /// Events already handed out by [`RolloutController::take_events`]
+    reported: usize,
+}
+
+impl ConfigRollout {
+    fn record(&mut self, timestamp: u64, kind: RolloutEventKind) {
+        self.events.push(RolloutEvent { timestamp, kind });
+    }
+
+    /// Robots that have been sent the new config
+    fn updated(&self) -> impl Iterator<Item = &String> {
+        self.batches[..=self.batch].iter().flatten()
+    }
+
+    fn batch_of(&self, robot_id: &str) -> Option<usize> {
+        self.batches
+            .iter()
+            .position(|batch| batch.iter().any(|id| id == robot_id))
+    }
+
+    fn has_failed(&self, robot_id: &str) -> bool {
+        self.failures.iter().any(|f| f.robot_id == robot_id)
+    }
+}
+
+fn health_rank(health: HealthStatus) -> u8 {
+    match health {
+        HealthStatus::Optimal => 0,
+        HealthStatus::Warning => 1,
+        HealthStatus::Critical => 2,
+    }
+}
+
+// ============================================================================
+// CONTROLLER
+// ============================================================================
+
+/// Runs configuration rollouts and remembers each robot's config
+#[derive(Debug, Default)]
+pub struct RolloutController {
+    config: RolloutConfig,
+    /// Last acknowledged (or reverted-to) config per robot
+    shadow: HashMap<String, RobotConfig>,
+    rollouts: HashMap<String, ConfigRollout>,
+    order: VecDeque<String>,
+    active: Option<String>,
+}
+
+impl RolloutController {
+    pub fn new(config: RolloutConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Last known config of a robot, if the engine ever set one
+    pub fn known_config(&self, robot_id: &str) -> Option<&RobotConfig> {
+        self.shadow.get(robot_id)
+    }
+
+    /// Record a robot's config when it was set outside a rollout
+    pub fn set_known_config(&mut self, robot_id: &str, config: RobotConfig) {
+        self.shadow.insert(robot_id.to_string(), config);
+    }
+
+    pub fn active(&self) -> Option<&str> {
+        self.active.as_deref()
+    }
+
+    pub fn progress(&self, id: &str) -> Option<RolloutProgress> {
+        self.rollouts.get(id).map(|run| RolloutProgress {
+            id: run.id.clone(),
+            phase: run.phase,
+            config: run.config.clone(),
+            batch: run.batch,
+            batches: run.batches.clone(),
+            failures: run.failures.clone(),
+            events: run.events.clone(),
+        })
+    }
+
+    /// Start a rollout over the robots in `fleet` matching the plan's selector.
+    ///
+    /// Returns the rollout id and the pushes for the first batch.
+    pub fn start(
+        &mut self,
+        plan: RolloutPlan,
+        fleet: &[RobotState],
+        now_ms: u64,
+    ) -> Result<(String, Vec<ConfigPush>), RolloutError> {
+        let RolloutPlan {
+            config,
+            selector,
+            batch_size,
+            soak_duration,
+            abort_on_failures,
+        } = plan;
+        if let Some(active) = &self.active {
+            return Err(RolloutError::AlreadyRunning(active.clone()));
+        }
+        if batch_size == 0 {
+            return Err(RolloutError::ZeroBatchSize);
+        }
+        let mut targets: Vec<&RobotState> = fleet.iter().filter(|r| selector.matches(r)).collect();
+        if targets.is_empty() {
+            return Err(RolloutError::NoRobots);
+        }
+        targets.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let mut run = ConfigRollout {
+            id: format!("ROLL-{}", uuid::Uuid::new_v4().simple()),
+            config,
+            batches: targets
+                .chunks(batch_size)
+                .map(|chunk| chunk.iter().map(|r| r.id.to_string()).collect())
+                .collect(),
+            batch: 0,
+            soak: soak_duration,
+            abort_on_failures,
+            phase: RolloutPhase::AwaitingAck,
+            pending_acks: HashMap::new(),
+            ack_deadline_ms: 0,
+            baseline: targets
+                .iter()
+                .map(|r| (r.id.to_string(), (r.status, r.health)))
+                .collect(),
+            prior: HashMap::new(),
+            failures: Vec::new(),
+            events: Vec::new(),
+            reported: 0,
+        };
+        run.record(
+            now_ms,
+            RolloutEventKind::Started {
+                robots: targets.len(),
+                batches: run.batches.len(),
+            },
+        );
+        let pushes = self.push_batch(&mut run, now_ms);
+
+        let id = run.id.clone();
+        self.active = Some(id.clone());
+        self.rollouts.insert(id.clone(), run);
+        self.order.push_back(id.clone());
+        self.prune();
+        Ok((id, pushes))
+    }
+
+    /// A robot gave the final answer to a command; only the configure
+    /// command the rollout sent it settles anything
+    pub fn on_ack(
+        &mut self,
+        robot_id: &str,
+        command_id: &str,
+        success: bool,
+        now_ms: u64,
+    ) -> Vec<ConfigPush> {
+        let Some(mut run) = self.take_active() else {
+            return Vec::new();
+        };
+        let mut pushes = Vec::new();
+        if run.pending_acks.get(robot_id).map(String::as_str) == Some(command_id) {
+            run.pending_acks.remove(robot_id);
+            if success {
+                self.shadow.insert(robot_id.to_string(), run.config.clone());
+            } else {
+                pushes = self.fail(&mut run, robot_id, Regression::Rejected, now_ms);
+            }
+            if run.phase == RolloutPhase::AwaitingAck && run.pending_acks.is_empty() {
+                self.begin_soak(&mut run, now_ms);
+            }
+        }
+        self.put_active(run);
+        pushes
+    }
+
+    /// A configure command could not be sent: the robot fails at once
+    /// rather than at the ack timeout
+    pub fn on_undelivered(
+        &mut self,
+        robot_id: &str,
+        command_id: &str,
+        reason: &str,
+        now_ms: u64,
+    ) -> Vec<ConfigPush> {
+        let Some(mut run) = self.take_active() else {
+            return Vec::new();
+        };
+        let mut pushes = Vec::new();
+        if run.pending_acks.get(robot_id).map(String::as_str) == Some(command_id) {
+            run.pending_acks.remove(robot_id);
+            let regression = Regression::Undelivered {
+                reason: reason.to_string(),
+            };
+            pushes = self.fail(&mut run, robot_id, regression, now_ms);
+            if run.phase == RolloutPhase::AwaitingAck && run.pending_acks.is_empty() {
+                self.begin_soak(&mut run, now_ms);
+            }
+        }
+        self.put_active(run);
+        pushes
+    }
+
+    /// Compare a robot's current state against its pre-rollout baseline
+    pub fn observe(&mut self, state: &RobotState, now_ms: u64) -> Vec<ConfigPush> {
+        let Some(mut run) = self.take_active() else {
+            return Vec::new();
+        };
+        let mut pushes = Vec::new();
+        match run.batch_of(&state.id) {
+            Some(batch) if batch > run.batch => {
+                // Not updated yet: keep the baseline current
+                run.baseline
+                    .insert(state.id.to_string(), (state.status, state.health));
+            }
+            Some(_) if !run.has_failed(&state.id) => {
+                let (status, health) = run.baseline[state.id.as_str()];
+                let regression =
+                    if state.status == RobotStatus::Offline && status != RobotStatus::Offline {
+                        Some(Regression::WentOffline)
+                    } else if state.status == RobotStatus::Error && status != RobotStatus::Error {
+                        Some(Regression::ErrorStatus)
+                    } else if health_rank(state.health) > health_rank(health) {
+                        Some(Regression::HealthDegraded {
+                            from: health,
+                            to: state.health,
+                        })
+                    } else {
+                        None
+                    };
+                if let Some(regression) = regression {
+                    pushes = self.fail(&mut run, &state.id, regression, now_ms);
+                }
+            }
+            _ => {}
+        }
+        self.put_active(run);
+        pushes
+    }
+
+    /// Advance timeouts and soak periods
+    pub fn tick(&mut self, now_ms: u64) -> Vec<ConfigPush> {
+        let Some(mut run) = self.take_active() else {
+            return Vec::new();
+        };
+        let mut pushes = Vec::new();
+        match run.phase {
+            RolloutPhase::AwaitingAck if now_ms >= run.ack_deadline_ms => {
+                let mut silent: Vec<String> = run.pending_acks.drain().map(|(id, _)| id).collect();
+                silent.sort();
+                for robot_id in silent {
+                    pushes.extend(self.fail(&mut run, &robot_id, Regression::AckTimeout, now_ms));
+                }
+                if run.phase == RolloutPhase::AwaitingAck {
+                    self.begin_soak(&mut run, now_ms);
+                }
+            }
+            RolloutPhase::Soaking { until_ms } if now_ms >= until_ms => {
+                let batch = run.batch;
+                run.record(now_ms, RolloutEventKind::BatchPassed { batch });
+                if batch + 1 < run.batches.len() {
+                    run.batch += 1;
+                    pushes = self.push_batch(&mut run, now_ms);
+                } else {
+                    run.phase = RolloutPhase::Completed;
+                    run.record(now_ms, RolloutEventKind::Completed);
+                }
+            }
+            _ => {}
+        }
+        self.put_active(run);
+        pushes
+    }
+
+    /// Events recorded since the last call, with the id of their rollout
+    pub fn take_events(&mut self) -> Vec<(String, RolloutEvent)> {
+        let mut events = Vec::new();
+        for id in &self.order {
+            if let Some(run) = self.rollouts.get_mut(id) {
+                events.extend(
+                    run.events[run.reported..]
+                        .iter()
+                        .map(|event| (run.id.clone(), event.clone())),
+                );
+                run.reported = run.events.len();
+            }
+        }
+        events
+    }
+
+    fn take_active(&mut self) -> Option<ConfigRollout> {
+        self.active.as_ref().and_then(|id| self.rollouts.remove(id))
+    }
+
+    fn put_active(&mut self, run: ConfigRollout) {
+        if run.phase.is_finished() {
+            self.active = None;
+        }
+        self.rollouts.insert(run.id.clone(), run);
+    }
+
+    fn push_batch(&self, run: &mut ConfigRollout, now_ms: u64) -> Vec<ConfigPush> {
+        let robots = run.batches[run.batch].clone();
+        for robot_id in &robots {
+            let prior = self.shadow.get(robot_id).cloned().unwrap_or_default();
+            run.prior.insert(robot_id.clone(), prior);
+        }
+        let pushes: Vec<ConfigPush> = robots
+            .iter()
+            .map(|robot_id| ConfigPush::new(robot_id.clone(), run.config.clone()))
+            .collect();
+        run.pending_acks = pushes
+            .iter()
+            .map(|push| (push.robot_id.clone(), push.command_id.clone()))
+            .collect();
+        run.ack_deadline_ms = now_ms + self.config.ack_timeout.as_millis() as u64;
+        run.phase = RolloutPhase::AwaitingAck;
+        run.record(
+            now_ms,
+            RolloutEventKind::BatchPushed {
+                batch: run.batch,
+                robots,
+            },
+        );
+        pushes
+    }
+
+    fn begin_soak(&self, run: &mut ConfigRollout, now_ms: u64) {
+        let batch = run.batch;
+        run.record(now_ms, RolloutEventKind::BatchAcknowledged { batch });
+        run.phase = RolloutPhase::Soaking {
+            until_ms: now_ms + run.soak.as_millis() as u64,
+        };
+    }
+
+    /// Record a failure, halting and reverting once the threshold is exceeded
+    fn fail(
+        &mut self,
+        run: &mut ConfigRollout,
+        robot_id: &str,
+        regression: Regression,
+        now_ms: u64,
+    ) -> Vec<ConfigPush> {
+        run.failures.push(RolloutFailure {
+            robot_id: robot_id.to_string(),
+            batch: run.batch_of(robot_id).unwrap_or(run.batch),
+            regression: regression.clone(),
+            timestamp: now_ms,
+        });
+        run.record(
+            now_ms,
+            RolloutEventKind::RobotFailed {
+                robot_id: robot_id.to_string(),
+                regression,
+            },
+        );
+        if run.failures.len() <= run.abort_on_failures {
+            return Vec::new();
+        }
+
+        run.record(
+            now_ms,
+            RolloutEventKind::Halted {
+                failures: run.failures.len(),
+            },
+        );
+        run.phase = RolloutPhase::Reverted;
+        run.pending_acks.clear();
+        let updated: Vec<String> = run.updated().cloned().collect();
+        let mut pushes = Vec::new();
+        for robot_id in updated {
+            let prior = run.prior[&robot_id].clone();
+            self.shadow.insert(robot_id.clone(), prior.clone());
+            run.record(
+                now_ms,
+                RolloutEventKind::RevertPushed {
+                    robot_id: robot_id.clone(),
+                },
+            );
+            pushes.push(ConfigPush::new(robot_id, prior));
+        }
+        pushes
+    }
+
+    fn prune(&mut self) {
+        while self.order.len() > self.config.history {
+            let Some(oldest) = self.order.front() else {
+                break;
+            };
+            if self.active.as_ref() == Some(oldest) {
+                break;
+            }
+            let oldest = self.order.pop_front().unwrap_or_default();
+            self.rollouts.remove(&oldest);
+        }
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOAK: Duration = Duration::from_secs(60);
+
+    fn fleet() -> Vec<RobotState> {
+        (1..=5)
+            .map(|i| {
+                RobotState::new(
+                    format!("RV-00{i}").parse().unwrap(),
+                    format!("Rover {i}"),
+                    RobotType::Rover,
+                )
+            })
+            .collect()
+    }
+
+    fn new_config() -> RobotConfig {
+        RobotConfig {
+            max_speed: Some(1.5),
+            ..RobotConfig::default()
+        }
+    }
+
+    fn plan(selector: RobotSelector, batch_size: usize, abort_on_failures: usize) -> RolloutPlan {
+        RolloutPlan {
+            config: new_config(),
+            selector,
+            batch_size,
+            soak_duration: SOAK,
+            abort_on_failures,
+        }
+    }
+
+    fn ids(pushes: &[ConfigPush]) -> Vec<&str> {
+        pushes.iter().map(|p| p.robot_id.as_str()).collect()
+    }
+
+    fn ack_all(controller: &mut RolloutController, pushes: &[ConfigPush], now: u64) {
+        for push in pushes {
+            let reverts = controller.on_ack(&push.robot_id, &push.command_id, true, now);
+            assert!(reverts.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_clean_rollout_proceeds_batch_by_batch() {
+        let mut controller = RolloutController::default();
+        let fleet = fleet();
+        let (id, first) = controller
+            .start(plan(RobotSelector::All, 2, 0), &fleet, 0)
+            .unwrap();
+        assert_eq!(ids(&first), ["RV-001", "RV-002"]);
+        assert!(matches!(
+            controller.start(plan(RobotSelector::All, 2, 0), &fleet, 0),
+            Err(RolloutError::AlreadyRunning(_))
+        ));
+
+        // Nothing moves until the batch acknowledges and soaks
+        assert!(controller.tick(20_000).is_empty());
+        ack_all(&mut controller, &first, 1_000);
+        assert!(controller.tick(60_999).is_empty());
+        let second = controller.tick(61_000);
+        assert_eq!(ids(&second), ["RV-003", "RV-004"]);
+
+        ack_all(&mut controller, &second, 62_000);
+        let third = controller.tick(122_000);
+        assert_eq!(ids(&third), ["RV-005"]);
+        ack_all(&mut controller, &third, 123_000);
+        assert!(controller.tick(183_000).is_empty());
+
+        let progress = controller.progress(&id).unwrap();
+        assert_eq!(progress.phase, RolloutPhase::Completed);
+        assert!(progress.failures.is_empty());
+        assert_eq!(
+            progress.events.last().unwrap().kind,
+            RolloutEventKind::Completed
+        );
+        assert_eq!(controller.active(), None);
+        assert_eq!(controller.known_config("RV-005"), Some(&new_config()));
+    }
+
+    #[test]
+    fn test_regression_in_second_batch_halts_and_reverts() {
+        let mut controller = RolloutController::default();
+        let old = RobotConfig {
+            max_speed: Some(1.0),
+            ..RobotConfig::default()
+        };
+        for robot in ["RV-001", "RV-002", "RV-003"] {
+            controller.set_known_config(robot, old.clone());
+        }
+        let mut fleet = fleet();
+        let (id, first) = controller
+            .start(plan(RobotSelector::All, 2, 0), &fleet, 0)
+            .unwrap();
+        ack_all(&mut controller, &first, 1_000);
+        let second = controller.tick(61_000);
+        ack_all(&mut controller, &second, 62_000);
+
+        // RV-004 errors during its soak; RV-005 was never touched
+        fleet[3].status = RobotStatus::Error;
+        let reverts = controller.observe(&fleet[3], 70_000);
+        assert_eq!(ids(&reverts), ["RV-001", "RV-002", "RV-003", "RV-004"]);
+        assert_eq!(reverts[0].config, old);
+        assert_eq!(reverts[3].config, RobotConfig::default());
+        assert!(controller.tick(200_000).is_empty());
+
+        let progress = controller.progress(&id).unwrap();
+        assert_eq!(progress.phase, RolloutPhase::Reverted);
+        assert_eq!(progress.batch, 1);
+        assert_eq!(progress.failures.len(), 1);
+        assert_eq!(progress.failures[0].regression, Regression::ErrorStatus);
+        let halted = progress
+            .events
+            .iter()
+            .position(|e| e.kind == RolloutEventKind::Halted { failures: 1 })
+            .unwrap();
+        assert_eq!(progress.events.len() - halted - 1, 4);
+        assert_eq!(controller.known_config("RV-002"), Some(&old));
+        assert_eq!(controller.active(), None);
+    }
+
+    #[test]
+    fn test_failure_threshold_and_ack_timeout() {
+        let mut controller = RolloutController::default();
+        let mut fleet = fleet();
+        fleet[1].health = HealthStatus::Warning;
+        let (id, first) = controller
+            .start(plan(RobotSelector::All, 3, 1), &fleet, 0)
+            .unwrap();
+        assert_eq!(first.len(), 3);
+
+        // RV-002 was already Warning: staying there is not a regression
+        assert!(controller.observe(&fleet[1], 500).is_empty());
+        ack_all(&mut controller, &first[..2], 1_000);
+        // RV-003 never answers: one failure, tolerated
+        assert!(controller.tick(30_000).is_empty());
+        assert!(matches!(
+            controller.progress(&id).unwrap().phase,
+            RolloutPhase::Soaking { .. }
+        ));
+
+        // A second failure exceeds the threshold
+        fleet[0].status = RobotStatus::Offline;
+        let reverts = controller.observe(&fleet[0], 31_000);
+        assert_eq!(ids(&reverts), ["RV-001", "RV-002", "RV-003"]);
+        let regressions: Vec<_> = controller
+            .progress(&id)
+            .unwrap()
+            .failures
+            .into_iter()
+            .map(|f| f.regression)
+            .collect();
+        assert_eq!(
+            regressions,
+            [Regression::AckTimeout, Regression::WentOffline]
+        );
+    }
+
+    #[test]
+    fn test_only_the_configure_command_settles_a_robot() {
+        let mut controller = RolloutController::default();
+        let fleet = fleet();
+        let (id, first) = controller
+            .start(plan(RobotSelector::All, 2, 0), &fleet, 0)
+            .unwrap();
+        assert_ne!(first[0].command_id, first[1].command_id);
+
+        // RV-001 turns down an unrelated move mid-batch: nothing settles
+        assert!(
+            controller
+                .on_ack("RV-001", "CMD-move", false, 500)
+                .is_empty()
+        );
+        ack_all(&mut controller, &first[1..], 1_000);
+        let progress = controller.progress(&id).unwrap();
+        assert_eq!(progress.phase, RolloutPhase::AwaitingAck);
+        assert!(progress.failures.is_empty());
+
+        // Its configure answer does, and a repeat of it is ignored
+        ack_all(&mut controller, &first[..1], 2_000);
+        assert!(
+            controller
+                .on_ack("RV-001", &first[0].command_id, false, 2_500)
+                .is_empty()
+        );
+        let progress = controller.progress(&id).unwrap();
+        assert_eq!(progress.phase, RolloutPhase::Soaking { until_ms: 62_000 });
+        assert!(progress.failures.is_empty());
+    }
+
+    #[test]
+    fn test_undelivered_push_fails_at_once_and_events_are_taken_once() {
+        let mut controller = RolloutController::default();
+        let fleet = fleet();
+        let (id, first) = controller
+            .start(plan(RobotSelector::All, 5, 0), &fleet, 0)
+            .unwrap();
+        let taken = controller.take_events();
+        assert_eq!(taken.len(), 2);
+        assert!(taken.iter().all(|(rollout, _)| *rollout == id));
+
+        let reverts =
+            controller.on_undelivered("RV-003", &first[2].command_id, "connection reset", 100);
+        assert_eq!(reverts.len(), 5);
+        assert_ne!(reverts[0].command_id, first[0].command_id);
+        let progress = controller.progress(&id).unwrap();
+        assert_eq!(progress.phase, RolloutPhase::Reverted);
+        assert_eq!(
+            progress.failures[0].regression,
+            Regression::Undelivered {
+                reason: "connection reset".into()
+            }
+        );
+
+        let kinds: Vec<_> = controller
+            .take_events()
+            .into_iter()
+            .map(|(_, event)| event.kind)
+            .collect();
+        assert!(matches!(kinds[0], RolloutEventKind::RobotFailed { .. }));
+        assert_eq!(kinds[1], RolloutEventKind::Halted { failures: 1 });
+        assert_eq!(kinds.len(), 7);
+        assert!(controller.take_events().is_empty());
+    }
+
+    #[test]
+    fn test_selector_and_start_errors() {
+        let mut controller = RolloutController::default();
+        let mut fleet = fleet();
+        fleet.push(RobotState::new(
+            "DR-001".parse().unwrap(),
+            "Drone 1",
+            RobotType::Drone,
+        ));
+
+        let drones = RobotSelector::Type(RobotType::Drone);
+        let (_, pushes) = controller.start(plan(drones, 10, 0), &fleet, 0).unwrap();
+        assert_eq!(ids(&pushes), ["DR-001"]);
+
+        let mut idle = RolloutController::default();
+        assert_eq!(
+            idle.start(plan(RobotSelector::All, 0, 0), &fleet, 0),
+            Err(RolloutError::ZeroBatchSize)
+        );
+        assert_eq!(
+            idle.start(plan(RobotSelector::Prefix("CR-".into()), 1, 0), &fleet, 0),
+            Err(RolloutError::NoRobots)
+        );
+    }
+}
@@ -0,0 +1,166 @@
+//! Rate limit on route requests
+//!
+//! Any client on the broker can publish on `aetheris/route_requests`, and a
+//! request naming no routes makes the leader republish the whole library.
+//! Requests are therefore limited like dead letters: each requester gets
+//! `burst` answered requests per window, requests naming no requester share
+//! one allowance, and at most `total` are answered per window across all
+//! requesters so made-up requester names can't get around the limit. The
+//! rest are dropped and counted.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::config::{CheckConfig, ConfigChecker};
+
+/// Requesters tracked before those with an expired window are forgotten
+const MAX_TRACKED_REQUESTERS: usize = 1024;
+
+/// Allowance of route requests naming no requester
+pub const ANONYMOUS_REQUESTER: &str = "anonymous";
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// How many route requests are answered
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteRequestConfig {
+    /// Requests per requester per window answered
+    pub burst: u32,
+    /// Requests per window answered across all requesters
+    pub total: u32,
+    /// Length of a rate-limiting window
+    pub window: Duration,
+}
+
+impl Default for RouteRequestConfig {
+    fn default() -> Self {
+        Self {
+            burst: 5,
+            total: 50,
+            window: Duration::from_secs(60),
+        }
+    }
+}
+
+impl CheckConfig for RouteRequestConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        checker.positive("window", self.window);
+        if self.burst == 0 {
+            checker.error("burst", "must be at least 1", None);
+        }
+        if self.total < self.burst {
+            checker.error(
+                "total",
+                format!("must be at least the burst of {}", self.burst),
+                None,
+            );
+        }
+    }
+}
+
+// ============================================================================
+// LIMITER
+// ============================================================================
+
+/// Requests answered in the current window of one requester, or of all
+#[derive(Debug, Default, Clone, Copy)]
+struct Window {
+    /// Start of the window (Unix ms)
+    start: u64,
+    answered: u32,
+}
+
+impl Window {
+    /// Count a request at `now` if the window has room for it
+    fn take(&mut self, now: u64, window: u64, limit: u32) -> bool {
+        if now.saturating_sub(self.start) >= window {
+            *self = Window {
+                start: now,
+                answered: 0,
+            };
+        }
+        if self.answered >= limit {
+            return false;
+        }
+        self.answered += 1;
+        true
+    }
+}
+
+/// Per-requester and overall allowance of route requests
+#[derive(Debug, Default)]
+pub struct RouteRequestLimiter {
+    config: RouteRequestConfig,
+    overall: Window,
+    requesters: HashMap<String, Window>,
+    dropped: u64,
+}
+
+impl RouteRequestLimiter {
+    pub fn new(config: RouteRequestConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Whether a request from `requester` at `now` (Unix ms) is answered
+    pub fn admit(&mut self, requester: &str, now: u64) -> bool {
+        let window = self.config.window.as_millis() as u64;
+        if self.requesters.len() >= MAX_TRACKED_REQUESTERS
+            && !self.requesters.contains_key(requester)
+        {
+            self.requesters
+                .retain(|_, requests| now.saturating_sub(requests.start) < window);
+        }
+        let mut requests = self.requesters.get(requester).copied().unwrap_or_default();
+        let mut overall = self.overall;
+        let admitted = requests.take(now, window, self.config.burst)
+            && overall.take(now, window, self.config.total);
+        if admitted {
+            self.requesters.insert(requester.to_string(), requests);
+            self.overall = overall;
+        } else {
+            self.dropped += 1;
+        }
+        admitted
+    }
+
+    /// Requests dropped since start
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requests_are_limited_per_requester_and_overall() {
+        let mut limiter = RouteRequestLimiter::new(RouteRequestConfig {
+            burst: 2,
+            total: 3,
+            window: Duration::from_secs(60),
+        });
+        let admitted = |limiter: &mut RouteRequestLimiter, requester, now| {
+            (0..5).filter(|_| limiter.admit(requester, now)).count()
+        };
+
+        assert_eq!(admitted(&mut limiter, "dashboard", 0), 2);
+        // A second requester only gets what is left overall
+        assert_eq!(admitted(&mut limiter, "brain", 1_000), 1);
+        assert_eq!(admitted(&mut limiter, "made-up", 2_000), 0);
+        assert_eq!(limiter.dropped(), 3 + 4 + 5);
+
+        // A new window starts both allowances over
+        assert_eq!(admitted(&mut limiter, "dashboard", 60_000), 2);
+        assert_eq!(admitted(&mut limiter, "brain", 61_000), 1);
+    }
+}
@@ -0,0 +1,567 @@
+//! Per-section health rollup
+//!
+//! The Brain plans around sections, not individual reports, so each section
+//! gets one [`SectionHealthReport`] recomputed whenever an anomaly or an
+//! environment reading for it arrives. The score starts at 100 and loses:
+//!
+//! - **Open anomalies**: 2 per open Low, 8 per Medium, 20 per High and 40
+//!   per Critical anomaly (Info is free), at most 60 in total.
+//! - **Hazard margin**: for the sensor channel closest to its hazard
+//!   threshold, nothing below `1 - warning_band` of the threshold, rising
+//!   linearly to 30 at the threshold and staying 30 above it.
+//! - **Staleness**: 10 when the section has no environment reading newer
+//!   than `stale_after`.
+//!
+//! The score is clamped to 0..=100 and rounded. With the caps, a section
+//! only reaches 0 when every deduction is at its maximum.
+//!
+//! Sensors report every second, so reports are not published per reading:
+//! every `publish_interval` the sections rescored since the last publish go
+//! out, along with those whose latest reading went stale meanwhile.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use aetheris_shared::{
+    AnomalyReport, HazardThresholds, HealthFactor, HealthFactorKind, PipeEnvironment,
+    SectionHealthReport, SeverityLevel, Timestamp,
+};
+
+use crate::alarms::HazardKind;
+use crate::anomalies::{ActiveAnomalies, ENGINE_ORIGIN, SYSTEM_SECTION};
+use crate::config::{CheckConfig, ConfigChecker};
+
+/// Cap of the open-anomaly deduction
+const MAX_ANOMALY_PENALTY: f64 = 60.0;
+/// Deduction of a channel at or above its hazard threshold
+const MAX_HAZARD_PENALTY: f64 = 30.0;
+/// Deduction of a section without a recent reading
+const STALE_PENALTY: f64 = 10.0;
+
+/// Deduction per open anomaly of `severity`
+fn anomaly_weight(severity: SeverityLevel) -> f64 {
+    match severity {
+        SeverityLevel::Info => 0.0,
+        SeverityLevel::Low => 2.0,
+        SeverityLevel::Medium => 8.0,
+        SeverityLevel::High => 20.0,
+        SeverityLevel::Critical => 40.0,
+    }
+}
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Section health scoring
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SectionHealthConfig {
+    /// Sections without a reading for this long lose the staleness points
+    #[serde(with = "crate::config::duration_secs")]
+    pub stale_after: Duration,
+    /// Fraction of a hazard threshold, just below it, over which the margin
+    /// deduction builds up
+    pub warning_band: f64,
+    /// How often changed reports are published
+    #[serde(with = "crate::config::duration_secs")]
+    pub publish_interval: Duration,
+}
+
+impl Default for SectionHealthConfig {
+    fn default() -> Self {
+        Self {
+            stale_after: Duration::from_secs(600),
+            warning_band: 0.2,
+            publish_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+impl CheckConfig for SectionHealthConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        checker.positive("stale_after", self.stale_after);
+        checker.positive("publish_interval", self.publish_interval);
+        if !(self.warning_band > 0.0 && self.warning_band <= 1.0) {
+            checker.error(
+                "warning_band",
+                format!("must be in (0, 1], got {}", self.warning_band),
+                Some("try 0.2".into()),
+            );
+        }
+    }
+}
+
+// ============================================================================
+// SCORING
+// ============================================================================
+
+fn threshold(kind: HazardKind, thresholds: &HazardThresholds) -> f64 {
+    match kind {
+        HazardKind::H2Concentration => thresholds.h2_ppm,
+        HazardKind::Overpressure => thresholds.pressure_bar,
+        HazardKind::HighTemperature => thresholds.temperature_celsius,
+    }
+}
+
+fn channel_name(kind: HazardKind) -> &'static str {
+    match kind {
+        HazardKind::H2Concentration => "h2_concentration",
+        HazardKind::Overpressure => "pressure",
+        HazardKind::HighTemperature => "temperature",
+    }
+}
+
+/// Score of a section with `open` anomalies and latest reading
+/// `environment`, at `now`, with the deductions that made it
+pub fn score(
+    config: &SectionHealthConfig,
+    thresholds: &HazardThresholds,
+    open: &BTreeMap<SeverityLevel, usize>,
+    environment: Option<&PipeEnvironment>,
+    now: u64,
+) -> (u8, Vec<HealthFactor>) {
+    let mut factors = Vec::new();
+
+    let weighted: f64 = open
+        .iter()
+        .map(|(severity, count)| anomaly_weight(*severity) * *count as f64)
+        .sum();
+    if weighted > 0.0 {
+        let counts: Vec<_> = open
+            .iter()
+            .rev()
+            .filter(|(_, count)| **count > 0)
+            .map(|(severity, count)| format!("{count} {severity}"))
+            .collect();
+        factors.push(HealthFactor {
+            kind: HealthFactorKind::OpenAnomalies,
+            penalty: weighted.min(MAX_ANOMALY_PENALTY),
+            detail: format!("{} open", counts.join(", ")),
+        });
+    }
+
+    if let Some(env) = environment {
+        let start = 1.0 - config.warning_band;
+        let worst = HazardKind::ALL
+            .into_iter()
+            .map(|kind| (kind, kind.reading(env) / threshold(kind, thresholds)))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((kind, ratio)) = worst
+            && ratio > start
+        {
+            let penalty = MAX_HAZARD_PENALTY * ((ratio - start) / config.warning_band).min(1.0);
+            factors.push(HealthFactor {
+                kind: HealthFactorKind::HazardMargin,
+                penalty,
+                detail: format!(
+                    "{} at {:.0}% of threshold",
+                    channel_name(kind),
+                    ratio * 100.0
+                ),
+            });
+        }
+    }
+
+    let age = environment.map(|env| Timestamp::from_millis(now).duration_since(env.timestamp));
+    let stale = match age {
+        None => Some("no environment reading yet".to_string()),
+        Some(age) if age > config.stale_after => {
+            Some(format!("no reading for {} min", age.as_secs() / 60))
+        }
+        Some(_) => None,
+    };
+    if let Some(detail) = stale {
+        factors.push(HealthFactor {
+            kind: HealthFactorKind::StaleReadings,
+            penalty: STALE_PENALTY,
+            detail,
+        });
+    }
+
+    factors.sort_by(|a, b| b.penalty.total_cmp(&a.penalty));
+    let deducted: f64 = factors.iter().map(|f| f.penalty).sum();
+    ((100.0 - deducted).clamp(0.0, 100.0).round() as u8, factors)
+}
+
+// ============================================================================
+// REGISTRY
+// ============================================================================
+
+#[derive(Debug, Default)]
+struct SectionState {
+    environment: Option<PipeEnvironment>,
+    last_inspection: Option<u64>,
+    report: Option<SectionHealthReport>,
+}
+
+/// Latest health report of every section heard from
+#[derive(Debug, Default)]
+pub struct SectionHealth {
+    config: SectionHealthConfig,
+    /// Levels the hazard margin is measured against (the alarms' raise levels)
+    thresholds: HazardThresholds,
+    sections: HashMap<String, SectionState>,
+    /// Sections rescored since the last [`SectionHealth::take_changed`]
+    changed: BTreeSet<String>,
+}
+
+impl SectionHealth {
+    pub fn new(config: SectionHealthConfig, thresholds: HazardThresholds) -> Self {
+        Self {
+            config,
+            thresholds,
+            sections: HashMap::new(),
+            changed: BTreeSet::new(),
+        }
+    }
+
+    pub fn config(&self) -> &SectionHealthConfig {
+        &self.config
+    }
+
+    /// Latest report of `section_id`
+    pub fn get(&self, section_id: &str) -> Option<&SectionHealthReport> {
+        self.sections.get(section_id)?.report.as_ref()
+    }
+
+    /// Latest report of every section, by section id
+    pub fn all(&self) -> Vec<&SectionHealthReport> {
+        let mut all: Vec<_> = self
+            .sections
+            .values()
+            .filter_map(|state| state.report.as_ref())
+            .collect();
+        all.sort_by(|a, b| a.section_id.cmp(&b.section_id));
+        all
+    }
+
+    /// Record an environment reading and rescore its section
+    pub fn observe_reading(
+        &mut self,
+        reading: &PipeEnvironment,
+        anomalies: &ActiveAnomalies,
+        now: u64,
+    ) -> SectionHealthReport {
+        let state = self.sections.entry(reading.section_id.clone()).or_default();
+        state.last_inspection = state
+            .last_inspection
+            .max(Some(reading.timestamp.as_millis()));
+        state.environment = Some(reading.clone());
+        self.rescore(&reading.section_id, anomalies, now)
+    }
+
+    /// Rescore the section of an anomaly report, after it went through
+    /// `anomalies`. Robot reports count as an inspection; fleet-health
+    /// alerts are not about a section and score nothing.
+    pub fn observe_anomaly(
+        &mut self,
+        report: &AnomalyReport,
+        anomalies: &ActiveAnomalies,
+        now: u64,
+    ) -> Option<SectionHealthReport> {
+        if report.section_id == SYSTEM_SECTION {
+            return None;
+        }
+        let state = self.sections.entry(report.section_id.clone()).or_default();
+        if report.detected_by != ENGINE_ORIGIN {
+            state.last_inspection = state
+                .last_inspection
+                .max(Some(report.timestamp.as_millis()));
+        }
+        Some(self.rescore(&report.section_id, anomalies, now))
+    }
+
+    /// Fold the state of section `from` into section `into`, after a
+    /// provisional section was merged, and rescore `into`. `anomalies` must
+    /// already be on `into`.
+    pub fn merge_section(
+        &mut self,
+        from: &str,
+        into: &str,
+        anomalies: &ActiveAnomalies,
+        now: u64,
+    ) -> Option<SectionHealthReport> {
+        let merged = self.sections.remove(from)?;
+        self.changed.remove(from);
+        let state = self.sections.entry(into.to_string()).or_default();
+        state.last_inspection = state.last_inspection.max(merged.last_inspection);
+        if let Some(mut environment) = merged.environment {
+            let newer = state
+                .environment
+                .as_ref()
+                .is_none_or(|existing| existing.timestamp < environment.timestamp);
+            if newer {
+                environment.section_id = into.to_string();
+                state.environment = Some(environment);
+            }
+        }
+        Some(self.rescore(into, anomalies, now))
+    }
+
+    /// Reports to publish, by section id: the sections rescored since the
+    /// last call, and those whose latest reading went stale meanwhile
+    /// (rescored now)
+    pub fn take_changed(
+        &mut self,
+        anomalies: &ActiveAnomalies,
+        now: u64,
+    ) -> Vec<SectionHealthReport> {
+        let now_at = Timestamp::from_millis(now);
+        let newly_stale: Vec<String> = self
+            .sections
+            .iter()
+            .filter(|(_, state)| {
+                let read_at = state.environment.as_ref().map(|env| env.timestamp);
+                let penalized = state.report.as_ref().is_some_and(|report| {
+                    report
+                        .factors
+                        .iter()
+                        .any(|f| f.kind == HealthFactorKind::StaleReadings)
+                });
+                read_at.is_some_and(|at| now_at.duration_since(at) > self.config.stale_after)
+                    && !penalized
+            })
+            .map(|(section_id, _)| section_id.clone())
+            .collect();
+        for section_id in newly_stale {
+            self.rescore(&section_id, anomalies, now);
+        }
+        std::mem::take(&mut self.changed)
+            .into_iter()
+            .filter_map(|section_id| self.get(&section_id).cloned())
+            .collect()
+    }
+
+    fn rescore(
+        &mut self,
+        section_id: &str,
+        anomalies: &ActiveAnomalies,
+        now: u64,
+    ) -> SectionHealthReport {
+        let state = self.sections.entry(section_id.to_string()).or_default();
+        let open = anomalies.open_in_section(section_id);
+        let (score, factors) = score(
+            &self.config,
+            &self.thresholds,
+            &open,
+            state.environment.as_ref(),
+            now,
+        );
+        let report = SectionHealthReport {
+            section_id: section_id.to_string(),
+            score,
+            open_anomalies: open,
+            environment: state.environment.clone(),
+            last_inspection: state.last_inspection,
+            factors,
+            timestamp: now,
+        };
+        state.report = Some(report.clone());
+        self.changed.insert(section_id.to_string());
+        report
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sections::{SectionRegistry, UnknownSectionPolicy};
+    use aetheris_shared::{AnomalyType, Position, Resolution};
+
+    const T0: u64 = 1_704_067_200_000;
+    const MINUTE: u64 = 60_000;
+
+    fn reading(h2: f64, timestamp: u64) -> PipeEnvironment {
+        PipeEnvironment {
+            section_id: "PIPE-002".into(),
+            pressure: 49.0,
+            temperature: 15.0,
+            h2_concentration: h2,
+            wall_thickness: 12.0,
+            flow_rate: 730.0,
+            humidity: 45.0,
+            position: Position::new(15.0, -0.5, 5.0),
+            timestamp: Timestamp::from_millis(timestamp),
+        }
+    }
+
+    fn report(
+        anomaly_type: AnomalyType,
+        severity: SeverityLevel,
+        x: f64,
+        by: &str,
+        at: u64,
+    ) -> AnomalyReport {
+        AnomalyReport {
+            timestamp: Timestamp::from_millis(at),
+            ..AnomalyReport::new(
+                anomaly_type,
+                severity,
+                Position::new(x, 0.0, 0.0),
+                "PIPE-002",
+                by,
+                0.9,
+                "finding",
+            )
+        }
+    }
+
+    /// Deductions rounded to a tenth of a point
+    fn penalties(report: &SectionHealthReport) -> Vec<(HealthFactorKind, f64)> {
+        report
+            .factors
+            .iter()
+            .map(|f| (f.kind, (f.penalty * 10.0).round() / 10.0))
+            .collect()
+    }
+
+    #[test]
+    fn test_scores_of_representative_sections() {
+        let sections = SectionRegistry::new(UnknownSectionPolicy::Provisional);
+        let mut anomalies = ActiveAnomalies::default();
+        let mut health = SectionHealth::default();
+
+        // Fresh, normal readings and nothing open
+        let healthy = health.observe_reading(&reading(40.0, T0), &anomalies, T0);
+        assert_eq!(healthy.score, 100);
+        assert!(healthy.factors.is_empty());
+
+        // One High and two Low anomalies: 100 - 20 - 2 * 2
+        for (anomaly_type, severity, x) in [
+            (AnomalyType::Corrosion, SeverityLevel::High, 10.0),
+            (AnomalyType::Crack, SeverityLevel::Low, 60.0),
+            (AnomalyType::Corrosion, SeverityLevel::Low, 120.0),
+        ] {
+            anomalies.ingest(report(anomaly_type, severity, x, "CR-001", T0), &sections);
+        }
+        let worn = health.observe_reading(&reading(40.0, T0), &anomalies, T0);
+        assert_eq!(worn.score, 76);
+        assert_eq!(worn.factors[0].detail, "1 high, 2 low open");
+        assert_eq!(worn.open_anomalies[&SeverityLevel::Low], 2);
+
+        // H2 at 90% of its 4000 ppm threshold: halfway through the 20%
+        // warning band, so 15 more
+        let leaking = health.observe_reading(&reading(3_600.0, T0), &anomalies, T0);
+        assert_eq!(leaking.score, 61);
+        assert_eq!(
+            penalties(&leaking),
+            [
+                (HealthFactorKind::OpenAnomalies, 24.0),
+                (HealthFactorKind::HazardMargin, 15.0)
+            ]
+        );
+        assert_eq!(
+            leaking.factors[1].detail,
+            "h2_concentration at 90% of threshold"
+        );
+
+        // Over the threshold, with two Critical findings on top: the
+        // anomaly deduction caps at 60 and the hazard at 30
+        for x in [200.0, 300.0] {
+            anomalies.ingest(
+                report(AnomalyType::Leak, SeverityLevel::Critical, x, "DR-001", T0),
+                &sections,
+            );
+        }
+        let critical = health.observe_reading(&reading(5_000.0, T0), &anomalies, T0);
+        assert_eq!(critical.score, 10);
+
+        // Sensors silent for 11 minutes: the last 10 points go
+        let silent = report(
+            AnomalyType::Leak,
+            SeverityLevel::Critical,
+            400.0,
+            "DR-001",
+            T0,
+        );
+        anomalies.ingest(silent.clone(), &sections);
+        let failing = health
+            .observe_anomaly(&silent, &anomalies, T0 + 11 * MINUTE)
+            .unwrap();
+        assert_eq!(failing.score, 0);
+        assert_eq!(failing.factors[2].detail, "no reading for 11 min");
+        assert_eq!(health.get("PIPE-002"), Some(&failing));
+    }
+
+    #[test]
+    fn test_inspections_and_resolutions_update_the_rollup() {
+        let sections = SectionRegistry::new(UnknownSectionPolicy::Provisional);
+        let mut anomalies = ActiveAnomalies::default();
+        let mut health = SectionHealth::default();
+
+        // An engine alarm is not an inspection, and a section never read
+        // from is stale
+        let alarm = report(
+            AnomalyType::Leak,
+            SeverityLevel::Medium,
+            10.0,
+            ENGINE_ORIGIN,
+            T0,
+        );
+        anomalies.ingest(alarm.clone(), &sections);
+        let unread = health.observe_anomaly(&alarm, &anomalies, T0).unwrap();
+        assert_eq!(unread.score, 82);
+        assert_eq!(unread.last_inspection, None);
+        assert_eq!(unread.factors[0].detail, "no environment reading yet");
+
+        let finding = report(
+            AnomalyType::Corrosion,
+            SeverityLevel::Info,
+            90.0,
+            "CR-001",
+            T0 + MINUTE,
+        );
+        anomalies.ingest(finding.clone(), &sections);
+        let inspected = health
+            .observe_anomaly(&finding, &anomalies, T0 + MINUTE)
+            .unwrap();
+        assert_eq!(inspected.last_inspection, Some(T0 + MINUTE));
+
+        anomalies
+            .resolve(&alarm.id, Resolution::Fixed, true, "op", T0 + 2 * MINUTE)
+            .unwrap();
+        let fixed =
+            health.observe_reading(&reading(40.0, T0 + 2 * MINUTE), &anomalies, T0 + 2 * MINUTE);
+        assert_eq!(fixed.score, 100);
+        assert_eq!(fixed.last_inspection, Some(T0 + 2 * MINUTE));
+
+        let fleet_alert = AnomalyReport::new(
+            AnomalyType::Unknown,
+            SeverityLevel::High,
+            Position::default(),
+            SYSTEM_SECTION,
+            ENGINE_ORIGIN,
+            1.0,
+            "robot missing",
+        );
+        assert_eq!(health.observe_anomaly(&fleet_alert, &anomalies, T0), None);
+        assert_eq!(health.all().len(), 1);
+    }
+
+    #[test]
+    fn test_changed_and_newly_stale_sections_are_published_once() {
+        let anomalies = ActiveAnomalies::default();
+        let mut health = SectionHealth::default();
+
+        for tick in 0..3 {
+            health.observe_reading(&reading(40.0, T0 + tick * 1_000), &anomalies, T0);
+        }
+        let published = health.take_changed(&anomalies, T0 + 2_000);
+        assert_eq!(published.len(), 1);
+        assert_eq!(published[0].score, 100);
+        assert!(health.take_changed(&anomalies, T0 + 5 * MINUTE).is_empty());
+
+        // Silent past `stale_after`: rescored without a new reading
+        let published = health.take_changed(&anomalies, T0 + 11 * MINUTE);
+        assert_eq!(published.len(), 1);
+        assert_eq!(published[0].score, 90);
+        assert!(health.take_changed(&anomalies, T0 + 12 * MINUTE).is_empty());
+    }
+}
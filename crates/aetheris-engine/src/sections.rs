@@ -0,0 +1,581 @@
+//! Pipeline section registry
+//!
+//! Environment readings and anomalies carry free-form `section_id` strings.
+//! The registry checks each one against the sections the engine knows about
+//! so typos and renamed sections don't silently become phantom sections.
+//! The configured [`PipelineMap`] seeds the known sections. Readings from
+//! unknown ids are either registered as provisional sections or rejected,
+//! depending on [`UnknownSectionPolicy`]; anomalies on unknown ids are
+//! always kept, as provisional sections. Provisional sections can later be
+//! promoted or merged into a canonical section by an operator.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use aetheris_shared::{
+    PipeEnvironment, PipelineMap, PipelineSection, Position, current_timestamp_ms,
+};
+
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// The pipeline the engine monitors
+#[derive(Debug, Clone, PartialEq)]
+pub struct PipelineConfig {
+    /// Sections of the pipeline; the demo pipeline when empty
+    pub sections: Vec<PipelineSection>,
+    /// How far from a section's axis a position still lies on it (meters)
+    pub tolerance: f64,
+    /// What to do with environment readings from sections not on the map
+    pub unknown_sections: UnknownSectionPolicy,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            sections: Vec::new(),
+            tolerance: 2.0,
+            unknown_sections: UnknownSectionPolicy::Reject,
+        }
+    }
+}
+
+impl PipelineConfig {
+    pub fn map(&self) -> PipelineMap {
+        let sections = if self.sections.is_empty() {
+            crate::create_mock_pipeline()
+        } else {
+            self.sections.clone()
+        };
+        PipelineMap::new(sections, self.tolerance)
+    }
+}
+
+impl CheckConfig for PipelineConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if !(self.tolerance.is_finite() && self.tolerance > 0.0) {
+            checker.error(
+                "tolerance",
+                format!("must be a positive distance, got {}", self.tolerance),
+                Some("the default is 2 m".into()),
+            );
+        }
+        let ids: HashSet<&str> = self.sections.iter().map(|s| s.id.as_str()).collect();
+        for (i, section) in self.sections.iter().enumerate() {
+            let base = format!("sections[{i}]");
+            if section.id.trim().is_empty() {
+                checker.error(&format!("{base}.id"), "must not be empty", None);
+            } else if let Some(first) = self.sections[..i].iter().position(|s| s.id == section.id) {
+                checker.error(
+                    &format!("{base}.id"),
+                    format!("duplicate section id \"{}\"", section.id),
+                    Some(format!("already used by sections[{first}]")),
+                );
+            }
+            for (field, end) in [("start", &section.start), ("end", &section.end)] {
+                if !end.is_finite() {
+                    checker.error(&format!("{base}.{field}"), "must be finite", None);
+                }
+            }
+            for (field, value) in [
+                ("diameter_mm", section.diameter_mm),
+                ("design_pressure_bar", section.design_pressure_bar),
+            ] {
+                if !(value.is_finite() && value > 0.0) {
+                    checker.error(
+                        &format!("{base}.{field}"),
+                        format!("must be positive, got {value}"),
+                        None,
+                    );
+                }
+            }
+            for (j, neighbor) in section.neighbors.iter().enumerate() {
+                if neighbor == &section.id || !ids.contains(neighbor.as_str()) {
+                    checker.error(
+                        &format!("{base}.neighbors[{j}]"),
+                        format!("\"{neighbor}\" is not another section of the pipeline"),
+                        None,
+                    );
+                }
+            }
+        }
+    }
+}
+
+// ============================================================================
+// TYPES
+// ============================================================================
+
+/// How the engine treats section ids it has never seen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnknownSectionPolicy {
+    /// Register the id as a provisional section positioned at the first reading
+    #[default]
+    Provisional,
+    /// Reject the message
+    Reject,
+}
+
+/// A pipeline section known to the engine
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SectionInfo {
+    /// Section identifier (e.g., "PIPE-001")
+    pub id: String,
+    /// Representative position of the section (its start, when `end` is set)
+    pub position: Position,
+    /// Far end of the section; with `position` it gives the section's axis
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<Position>,
+    /// True if the section was inferred from traffic rather than configured
+    pub provisional: bool,
+    /// Unix timestamp the section was first seen (milliseconds)
+    pub first_seen: u64,
+    /// Unix timestamp of the most recent message referencing it (milliseconds)
+    pub last_seen: u64,
+}
+
+impl SectionInfo {
+    pub fn new(id: impl Into<String>, position: Position) -> Self {
+        let now = current_timestamp_ms();
+        Self {
+            id: id.into(),
+            position,
+            end: None,
+            provisional: false,
+            first_seen: now,
+            last_seen: now,
+        }
+    }
+
+    /// Give the section an axis running from its position to `end`
+    pub fn with_end(mut self, end: Position) -> Self {
+        self.end = Some(end);
+        self
+    }
+
+    /// Distance in meters along the section axis to the projection of
+    /// `point`, or `None` when the section has no axis
+    pub fn chainage(&self, point: &Position) -> Option<f64> {
+        let end = self.end?;
+        let start = self.position;
+        let axis = (end.x - start.x, end.y - start.y, end.z - start.z);
+        let length = start.distance_to(&end);
+        if length == 0.0 {
+            return None;
+        }
+        let along = (point.x - start.x) * axis.0
+            + (point.y - start.y) * axis.1
+            + (point.z - start.z) * axis.2;
+        Some((along / length).clamp(0.0, length))
+    }
+}
+
+impl From<&PipelineSection> for SectionInfo {
+    fn from(section: &PipelineSection) -> Self {
+        SectionInfo::new(&section.id, section.start).with_end(section.end)
+    }
+}
+
+/// Errors from section resolution and registration
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum SectionError {
+    #[error("unknown pipeline section '{0}'")]
+    Unknown(String),
+    #[error("section '{0}' is not provisional and cannot be merged")]
+    NotProvisional(String),
+    #[error("cannot merge section '{0}' into itself")]
+    SelfMerge(String),
+}
+
+// ============================================================================
+// REGISTRY
+// ============================================================================
+
+/// Known pipeline sections plus the data the engine keeps per section
+#[derive(Debug, Default)]
+pub struct SectionRegistry {
+    sections: HashMap<String, SectionInfo>,
+    /// Merged provisional ids mapped to their canonical section
+    aliases: HashMap<String, String>,
+    /// Latest environment reading per section
+    latest_readings: HashMap<String, PipeEnvironment>,
+    /// Anomaly ids reported against each section
+    anomalies: HashMap<String, Vec<String>>,
+    policy: UnknownSectionPolicy,
+}
+
+impl SectionRegistry {
+    pub fn new(policy: UnknownSectionPolicy) -> Self {
+        Self {
+            policy,
+            ..Self::default()
+        }
+    }
+
+    /// Create a registry pre-populated with configured sections
+    pub fn with_sections(
+        policy: UnknownSectionPolicy,
+        sections: impl IntoIterator<Item = SectionInfo>,
+    ) -> Self {
+        let mut registry = Self::new(policy);
+        for section in sections {
+            registry.sections.insert(section.id.clone(), section);
+        }
+        registry
+    }
+
+    pub fn policy(&self) -> UnknownSectionPolicy {
+        self.policy
+    }
+
+    pub fn get(&self, section_id: &str) -> Option<&SectionInfo> {
+        self.sections.get(self.canonical_id(section_id))
+    }
+
+    /// Follow merge aliases to the canonical id
+    pub fn canonical_id<'a>(&'a self, section_id: &'a str) -> &'a str {
+        self.aliases
+            .get(section_id)
+            .map(String::as_str)
+            .unwrap_or(section_id)
+    }
+
+    /// Resolve an incoming section id to its canonical id.
+    ///
+    /// Unknown ids are registered as provisional at `position` or rejected,
+    /// depending on the registry's policy.
+    pub fn resolve(
+        &mut self,
+        section_id: &str,
+        position: Position,
+    ) -> Result<String, SectionError> {
+        match self.policy {
+            UnknownSectionPolicy::Reject if self.get(section_id).is_none() => {
+                Err(SectionError::Unknown(section_id.to_string()))
+            }
+            _ => Ok(self.register_unknown(section_id, position)),
+        }
+    }
+
+    /// The canonical id of a section, registering it as provisional at
+    /// `position` if it is unknown
+    fn register_unknown(&mut self, section_id: &str, position: Position) -> String {
+        let canonical = self.canonical_id(section_id).to_string();
+        if let Some(section) = self.sections.get_mut(&canonical) {
+            section.last_seen = current_timestamp_ms();
+            return canonical;
+        }
+        let section = SectionInfo {
+            provisional: true,
+            ..SectionInfo::new(section_id, position)
+        };
+        self.sections.insert(canonical.clone(), section);
+        canonical
+    }
+
+    /// Resolve an environment reading's section and remember it as the latest
+    pub fn record_reading(
+        &mut self,
+        mut reading: PipeEnvironment,
+    ) -> Result<PipeEnvironment, SectionError> {
+        reading.section_id = self.resolve(&reading.section_id, reading.position)?;
+        self.latest_readings
+            .insert(reading.section_id.clone(), reading.clone());
+        Ok(reading)
+    }
+
+    /// Resolve an anomaly's section and index the anomaly under it. An
+    /// unknown section is registered as provisional whatever the policy:
+    /// an anomaly is never dropped over topology.
+    pub fn record_anomaly(
+        &mut self,
+        section_id: &str,
+        position: Position,
+        anomaly_id: &str,
+    ) -> String {
+        let canonical = self.register_unknown(section_id, position);
+        let ids = self.anomalies.entry(canonical.clone()).or_default();
+        // Updated reports are republished under the same id
+        if !ids.iter().any(|id| id == anomaly_id) {
+            ids.push(anomaly_id.to_string());
+        }
+        canonical
+    }
+
+    /// Distance between two points on a section: along its axis when the
+    /// section has one, otherwise straight-line
+    pub fn separation(&self, section_id: &str, a: &Position, b: &Position) -> f64 {
+        debug_assert!(
+            a.is_finite() && b.is_finite(),
+            "positions are bounds-checked on ingest"
+        );
+        let section = self.get(section_id);
+        match section.and_then(|s| Some((s.chainage(a)?, s.chainage(b)?))) {
+            Some((ca, cb)) => (ca - cb).abs(),
+            None => a.distance_to(b),
+        }
+    }
+
+    pub fn latest_reading(&self, section_id: &str) -> Option<&PipeEnvironment> {
+        self.latest_readings.get(self.canonical_id(section_id))
+    }
+
+    pub fn anomaly_ids(&self, section_id: &str) -> &[String] {
+        self.anomalies
+            .get(self.canonical_id(section_id))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Provisional sections awaiting topology fixes, oldest first
+    pub fn provisional_report(&self) -> Vec<&SectionInfo> {
+        let mut provisional: Vec<_> = self.sections.values().filter(|s| s.provisional).collect();
+        provisional.sort_by(|a, b| {
+            a.first_seen
+                .cmp(&b.first_seen)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        provisional
+    }
+
+    /// Register a canonical section, promoting it if it was provisional.
+    ///
+    /// With `merge_from`, the provisional section is folded into `section_id`:
+    /// its stored reading and anomalies move over and its id becomes an alias.
+    pub fn register(
+        &mut self,
+        section_id: &str,
+        position: Position,
+        merge_from: Option<&str>,
+    ) -> Result<(), SectionError> {
+        // A refused merge must leave the registry as it was
+        if let Some(from) = merge_from {
+            self.check_merge(from, section_id)?;
+        }
+        let section = self
+            .sections
+            .entry(section_id.to_string())
+            .or_insert_with(|| SectionInfo::new(section_id, position));
+        section.provisional = false;
+        section.position = position;
+
+        if let Some(from) = merge_from {
+            self.merge(from, section_id);
+        }
+        Ok(())
+    }
+
+    fn check_merge(&self, from: &str, into: &str) -> Result<(), SectionError> {
+        if from == into {
+            return Err(SectionError::SelfMerge(from.to_string()));
+        }
+        match self.sections.get(from) {
+            None => Err(SectionError::Unknown(from.to_string())),
+            Some(section) if !section.provisional => {
+                Err(SectionError::NotProvisional(from.to_string()))
+            }
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Fold `from` into `into`; see [`Self::check_merge`]
+    fn merge(&mut self, from: &str, into: &str) {
+        let merged = self.sections.remove(from).expect("checked before merging");
+        if let Some(target) = self.sections.get_mut(into) {
+            target.first_seen = target.first_seen.min(merged.first_seen);
+            target.last_seen = target.last_seen.max(merged.last_seen);
+        }
+
+        if let Some(mut reading) = self.latest_readings.remove(from) {
+            reading.section_id = into.to_string();
+            let newer = self
+                .latest_readings
+                .get(into)
+                .is_none_or(|existing| existing.timestamp < reading.timestamp);
+            if newer {
+                self.latest_readings.insert(into.to_string(), reading);
+            }
+        }
+        if let Some(ids) = self.anomalies.remove(from) {
+            self.anomalies
+                .entry(into.to_string())
+                .or_default()
+                .extend(ids);
+        }
+
+        // Anything that pointed at the merged id now points at the canonical one
+        for target in self.aliases.values_mut() {
+            if target == from {
+                *target = into.to_string();
+            }
+        }
+        self.aliases.insert(from.to_string(), into.to_string());
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetheris_shared::Timestamp;
+
+    fn reading(section_id: &str, timestamp: u64) -> PipeEnvironment {
+        PipeEnvironment {
+            section_id: section_id.into(),
+            pressure: 50.0,
+            temperature: 25.0,
+            h2_concentration: 100.0,
+            wall_thickness: 10.0,
+            flow_rate: 500.0,
+            humidity: 45.0,
+            position: Position::new(12.0, 0.0, 3.0),
+            timestamp: Timestamp::from_millis(timestamp),
+        }
+    }
+
+    fn known() -> SectionInfo {
+        SectionInfo::new("PIPE-001", Position::origin())
+    }
+
+    #[test]
+    fn test_provisional_mode_registers_unknown_sections() {
+        let mut registry =
+            SectionRegistry::with_sections(UnknownSectionPolicy::Provisional, [known()]);
+        let stored = registry.record_reading(reading("PIPE-0O1", 1)).unwrap();
+        assert_eq!(stored.section_id, "PIPE-0O1");
+
+        let section = registry.get("PIPE-0O1").unwrap();
+        assert!(section.provisional);
+        assert_eq!(section.position, Position::new(12.0, 0.0, 3.0));
+        assert!(!registry.get("PIPE-001").unwrap().provisional);
+    }
+
+    #[test]
+    fn test_reject_mode_refuses_unknown_sections() {
+        let mut registry = SectionRegistry::with_sections(UnknownSectionPolicy::Reject, [known()]);
+        assert!(registry.record_reading(reading("PIPE-001", 1)).is_ok());
+        assert_eq!(
+            registry.record_reading(reading("PIPE-999", 1)),
+            Err(SectionError::Unknown("PIPE-999".into()))
+        );
+        assert!(registry.get("PIPE-999").is_none());
+        assert!(registry.provisional_report().is_empty());
+
+        // Anomalies are kept, pending topology review
+        let section = registry.record_anomaly("PIPE-999", Position::origin(), "ANM-1");
+        assert_eq!(section, "PIPE-999");
+        assert!(registry.get("PIPE-999").unwrap().provisional);
+    }
+
+    #[test]
+    fn test_provisional_report_lists_only_provisional_sections() {
+        let mut registry =
+            SectionRegistry::with_sections(UnknownSectionPolicy::Provisional, [known()]);
+        registry.resolve("PIPE-H3", Position::origin()).unwrap();
+        registry.resolve("PIPE-A1", Position::origin()).unwrap();
+        registry.resolve("PIPE-001", Position::origin()).unwrap();
+
+        let report: Vec<_> = registry
+            .provisional_report()
+            .iter()
+            .map(|s| s.id.clone())
+            .collect();
+        assert_eq!(report.len(), 2);
+        assert!(report.contains(&"PIPE-H3".to_string()));
+        assert!(report.contains(&"PIPE-A1".to_string()));
+    }
+
+    #[test]
+    fn test_merge_remaps_stored_readings_and_anomalies() {
+        let mut registry =
+            SectionRegistry::with_sections(UnknownSectionPolicy::Provisional, [known()]);
+        registry.record_reading(reading("PIPE-001", 10)).unwrap();
+        registry.record_reading(reading("PIPE-0O1", 20)).unwrap();
+        registry.record_anomaly("PIPE-0O1", Position::origin(), "ANM-1");
+        registry.record_anomaly("PIPE-001", Position::origin(), "ANM-2");
+
+        registry
+            .register("PIPE-001", Position::origin(), Some("PIPE-0O1"))
+            .unwrap();
+
+        assert!(registry.provisional_report().is_empty());
+        assert_eq!(registry.latest_reading("PIPE-001").unwrap().timestamp, 20);
+        assert_eq!(
+            registry.latest_reading("PIPE-001").unwrap().section_id,
+            "PIPE-001"
+        );
+        assert_eq!(registry.anomaly_ids("PIPE-001"), ["ANM-2", "ANM-1"]);
+
+        // Late traffic on the old id lands on the canonical section
+        let late = registry.record_reading(reading("PIPE-0O1", 30)).unwrap();
+        assert_eq!(late.section_id, "PIPE-001");
+        assert!(registry.get("PIPE-0O1").is_some_and(|s| s.id == "PIPE-001"));
+    }
+
+    #[test]
+    fn test_register_promotes_provisional_section() {
+        let mut registry = SectionRegistry::new(UnknownSectionPolicy::Provisional);
+        registry.resolve("PIPE-NEW", Position::origin()).unwrap();
+        registry
+            .register("PIPE-NEW", Position::new(1.0, 0.0, 0.0), None)
+            .unwrap();
+        let section = registry.get("PIPE-NEW").unwrap();
+        assert!(!section.provisional);
+        assert_eq!(section.position, Position::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_merge_rejects_canonical_source() {
+        let mut registry = SectionRegistry::with_sections(
+            UnknownSectionPolicy::Provisional,
+            [known(), SectionInfo::new("PIPE-002", Position::origin())],
+        );
+        assert_eq!(
+            registry.register("PIPE-001", Position::origin(), Some("PIPE-002")),
+            Err(SectionError::NotProvisional("PIPE-002".into()))
+        );
+        assert_eq!(
+            registry.register("PIPE-001", Position::origin(), Some("PIPE-001")),
+            Err(SectionError::SelfMerge("PIPE-001".into()))
+        );
+    }
+
+    #[test]
+    fn test_refused_registration_leaves_registry_unchanged() {
+        let mut registry = SectionRegistry::with_sections(
+            UnknownSectionPolicy::Provisional,
+            [known(), SectionInfo::new("PIPE-002", Position::origin())],
+        );
+        registry.record_reading(reading("PIPE-0O3", 1)).unwrap();
+
+        for (section_id, merge_from) in [
+            ("PIPE-003", "PIPE-002"),
+            ("PIPE-003", "PIPE-404"),
+            ("PIPE-0O3", "PIPE-0O3"),
+        ] {
+            let position = Position::new(30.0, 0.0, 0.0);
+            assert!(
+                registry
+                    .register(section_id, position, Some(merge_from))
+                    .is_err()
+            );
+        }
+        // No canonical PIPE-003 was created, and the provisional section is
+        // still provisional where it was
+        assert!(registry.get("PIPE-003").is_none());
+        let provisional = registry.get("PIPE-0O3").unwrap();
+        assert!(provisional.provisional);
+        assert_eq!(provisional.position, Position::new(12.0, 0.0, 3.0));
+        assert!(registry.latest_reading("PIPE-0O3").is_some());
+        assert!(!registry.get("PIPE-002").unwrap().provisional);
+    }
+}
@@ -0,0 +1,598 @@
+//! Environment sensor health inferred from reading plausibility
+//!
+//! Section sensors fail by sticking at one value, spiking for a single
+//! reading, or drifting away from their neighbors, and without a check those
+//! failures look like pipeline hazards. Each (section, channel) keeps a short
+//! window of readings and three detectors run over it:
+//!
+//! - **stuck-at**: a full window with no variance while the same channel in
+//!   other sections varies;
+//! - **spike**: a reading beyond a robust (median/MAD) z-score that reverts on
+//!   the next reading. The outlying reading is held out of hazard assessment
+//!   until the next one shows whether it persists, so a real step change is
+//!   assessed one reading late instead of being missed;
+//! - **drift**: a steady, near-linear change in the offset from most of the
+//!   other sections. Comparing against each neighbor, rather than against
+//!   their average, keeps the drifting sensor from dragging healthy
+//!   neighbors along with it.
+//!
+//! A channel with a detected fault becomes Suspect: it is excluded from
+//! hazard assessment and a Low "sensor suspect" alert is raised once. It is
+//! restored after a clean period with no fault detected.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use aetheris_shared::{AnomalyReport, AnomalyType, PipeEnvironment, SeverityLevel, Timestamp};
+
+use crate::alarms::HazardKind;
+use crate::anomalies::ENGINE_ORIGIN;
+use crate::config::{CheckConfig, ConfigChecker};
+
+/// Scales a median absolute deviation to a normal standard deviation
+const MAD_SCALE: f64 = 1.4826;
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Fault detector thresholds and restoration timing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SensorHealthConfig {
+    /// Readings per section channel the detectors look back over
+    pub window: usize,
+    /// Standard deviation at or below which a full window is stuck
+    pub stuck_std_floor: f64,
+    /// Robust z-score beyond which a lone reading may be a spike
+    pub spike_z: f64,
+    /// Smallest spread used for the z-score, as a fraction of the median
+    pub spike_min_spread: f64,
+    /// Change in offset from a neighbor section over one window, as a
+    /// fraction of the neighbor's level, that counts as diverging
+    pub drift_fraction: f64,
+    /// How linear (R²) the change in offset must be to count as drift
+    pub drift_min_r2: f64,
+    /// Time without a detected fault before a suspect channel is restored
+    #[serde(with = "crate::config::duration_secs")]
+    pub clean_period: Duration,
+}
+
+impl Default for SensorHealthConfig {
+    fn default() -> Self {
+        Self {
+            window: 30,
+            stuck_std_floor: 1e-6,
+            spike_z: 8.0,
+            spike_min_spread: 0.01,
+            drift_fraction: 0.1,
+            drift_min_r2: 0.8,
+            clean_period: Duration::from_secs(600),
+        }
+    }
+}
+
+impl CheckConfig for SensorHealthConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if self.window < 8 {
+            checker.error(
+                "window",
+                "must be at least 8 readings",
+                Some("the default is 30".into()),
+            );
+        }
+        if !(self.stuck_std_floor >= 0.0 && self.stuck_std_floor.is_finite()) {
+            checker.error("stuck_std_floor", "must not be negative", None);
+        }
+        if !(self.spike_z > 0.0 && self.spike_z.is_finite()) {
+            checker.error("spike_z", "must be greater than zero", None);
+        }
+        checker.fraction("spike_min_spread", self.spike_min_spread);
+        checker.fraction("drift_fraction", self.drift_fraction);
+        checker.fraction("drift_min_r2", self.drift_min_r2);
+        checker.positive("clean_period", self.clean_period);
+    }
+}
+
+// ============================================================================
+// HEALTH STATE
+// ============================================================================
+
+/// How a sensor channel is misbehaving
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SensorFault {
+    StuckAt,
+    Spike,
+    Drift,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SensorStatus {
+    Healthy,
+    /// Excluded from hazard assessment until restored
+    Suspect,
+}
+
+/// Health of one section's sensor channel
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChannelHealth {
+    pub section_id: String,
+    pub channel: HazardKind,
+    pub status: SensorStatus,
+    /// Fault that made the channel suspect
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fault: Option<SensorFault>,
+    /// Unix timestamp the channel became suspect (milliseconds)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suspect_since: Option<u64>,
+    /// Unix timestamp a fault was last detected (milliseconds)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_fault: Option<u64>,
+}
+
+/// A change of channel health
+#[derive(Debug, Clone, PartialEq)]
+pub enum SensorHealthEvent {
+    /// The channel became suspect; the Low alert should be published
+    Suspected {
+        channel: HazardKind,
+        fault: SensorFault,
+        report: Box<AnomalyReport>,
+    },
+    /// The channel stayed clean for the clean period
+    Restored { channel: HazardKind },
+}
+
+/// Outcome of observing one reading
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SensorAssessment {
+    /// Channels hazard assessment must skip for this reading
+    pub excluded: Vec<HazardKind>,
+    pub events: Vec<SensorHealthEvent>,
+}
+
+#[derive(Debug)]
+struct ChannelSeries {
+    values: VecDeque<f64>,
+    /// Offset from each neighbor section's latest value
+    offsets: HashMap<String, VecDeque<f64>>,
+    /// Outlying reading held back until the next one shows if it persists
+    held: Option<f64>,
+    health: ChannelHealth,
+}
+
+/// Result of feeding one value to a channel's detectors
+struct Step {
+    fault: Option<SensorFault>,
+    /// The value was held back as a possible spike
+    withheld: bool,
+}
+
+impl ChannelSeries {
+    fn new(section_id: &str, channel: HazardKind) -> Self {
+        Self {
+            values: VecDeque::new(),
+            offsets: HashMap::new(),
+            held: None,
+            health: ChannelHealth {
+                section_id: section_id.to_string(),
+                channel,
+                status: SensorStatus::Healthy,
+                fault: None,
+                suspect_since: None,
+                last_fault: None,
+            },
+        }
+    }
+
+    fn robust_z(&self, value: f64, config: &SensorHealthConfig) -> f64 {
+        let values: Vec<f64> = self.values.iter().copied().collect();
+        let center = median(&values);
+        let deviations: Vec<f64> = values.iter().map(|v| (v - center).abs()).collect();
+        let spread = (median(&deviations) * MAD_SCALE)
+            .max(config.spike_min_spread * center.abs())
+            .max(f64::EPSILON);
+        (value - center).abs() / spread
+    }
+
+    fn push(&mut self, value: f64, neighbors: &[(&str, f64)], config: &SensorHealthConfig) {
+        push_bounded(&mut self.values, value, config.window);
+        for (neighbor, level) in neighbors {
+            let offsets = self.offsets.entry(neighbor.to_string()).or_default();
+            push_bounded(offsets, value - level, config.window);
+        }
+    }
+
+    /// Whether the offset from more than half of the neighbors (at least two)
+    /// changed steadily by more than the drift fraction over the window
+    fn drifting(&self, neighbors: &[(&str, f64)], config: &SensorHealthConfig) -> bool {
+        let mut compared = 0;
+        let mut diverging = 0;
+        for (neighbor, level) in neighbors {
+            let Some(offsets) = self.offsets.get(*neighbor) else {
+                continue;
+            };
+            if offsets.len() < config.window {
+                continue;
+            }
+            compared += 1;
+            if let Some((change, r2)) = linear_change(offsets)
+                && r2 >= config.drift_min_r2
+                && change.abs() >= config.drift_fraction * level.abs().max(1.0)
+            {
+                diverging += 1;
+            }
+        }
+        compared >= 2 && diverging * 2 > compared
+    }
+
+    fn step(
+        &mut self,
+        value: f64,
+        neighbors: &[(&str, f64)],
+        others_vary: bool,
+        config: &SensorHealthConfig,
+    ) -> Step {
+        let mut fault = None;
+        let warm = self.values.len() >= config.window / 2;
+        match self.held.take() {
+            Some(held) => {
+                if self.robust_z(value, config) < config.spike_z {
+                    fault = Some(SensorFault::Spike);
+                } else {
+                    self.push(held, neighbors, config);
+                }
+            }
+            None if warm && self.robust_z(value, config) >= config.spike_z => {
+                self.held = Some(value);
+                return Step {
+                    fault: None,
+                    withheld: true,
+                };
+            }
+            None => {}
+        }
+        self.push(value, neighbors, config);
+
+        let full = self.values.len() == config.window;
+        if fault.is_none() && full && others_vary && std_dev(&self.values) <= config.stuck_std_floor
+        {
+            fault = Some(SensorFault::StuckAt);
+        }
+        if fault.is_none() && self.drifting(neighbors, config) {
+            fault = Some(SensorFault::Drift);
+        }
+        Step {
+            fault,
+            withheld: false,
+        }
+    }
+}
+
+fn push_bounded(series: &mut VecDeque<f64>, item: f64, capacity: usize) {
+    if series.len() == capacity {
+        series.pop_front();
+    }
+    series.push_back(item);
+}
+
+fn median(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    }
+}
+
+fn std_dev(values: &VecDeque<f64>) -> f64 {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt()
+}
+
+/// Change over the series of its least-squares line, and the fit's R²
+fn linear_change(series: &VecDeque<f64>) -> Option<(f64, f64)> {
+    let n = series.len() as f64;
+    let mean_x = (n - 1.0) / 2.0;
+    let mean_y = series.iter().sum::<f64>() / n;
+    let (mut sxy, mut sxx, mut syy) = (0.0, 0.0, 0.0);
+    for (i, y) in series.iter().enumerate() {
+        let (dx, dy) = (i as f64 - mean_x, y - mean_y);
+        sxy += dx * dy;
+        sxx += dx * dx;
+        syy += dy * dy;
+    }
+    if sxx == 0.0 || syy == 0.0 {
+        return None;
+    }
+    let slope = sxy / sxx;
+    Some((slope * (n - 1.0), sxy * sxy / (sxx * syy)))
+}
+
+// ============================================================================
+// STORE
+// ============================================================================
+
+/// Health of every section's environment sensor channels
+#[derive(Debug, Default)]
+pub struct SensorHealth {
+    config: SensorHealthConfig,
+    series: HashMap<(String, HazardKind), ChannelSeries>,
+    /// Latest value per section and channel, for neighbor comparison
+    latest: HashMap<(String, HazardKind), f64>,
+}
+
+impl SensorHealth {
+    pub fn new(config: SensorHealthConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Run the detectors over a reading taken at `now_ms`
+    pub fn observe(&mut self, reading: &PipeEnvironment, now_ms: u64) -> SensorAssessment {
+        let mut assessment = SensorAssessment::default();
+        let section = reading.section_id.as_str();
+        for channel in HazardKind::ALL {
+            let value = channel.reading(reading);
+            let neighbors: Vec<(&str, f64)> = self
+                .latest
+                .iter()
+                .filter(|((s, c), _)| *c == channel && s != section)
+                .map(|((s, _), v)| (s.as_str(), *v))
+                .collect();
+            let floor = self.config.stuck_std_floor;
+            let others_vary = self.series.iter().any(|((s, c), series)| {
+                *c == channel
+                    && s != section
+                    && series.values.len() >= 2
+                    && std_dev(&series.values) > floor
+            });
+            let series = self
+                .series
+                .entry((section.to_string(), channel))
+                .or_insert_with(|| ChannelSeries::new(section, channel));
+            let step = series.step(value, &neighbors, others_vary, &self.config);
+            self.latest.insert((section.to_string(), channel), value);
+            let health = &mut series.health;
+            if let Some(fault) = step.fault {
+                health.last_fault = Some(now_ms);
+                if health.status == SensorStatus::Healthy {
+                    health.status = SensorStatus::Suspect;
+                    health.fault = Some(fault);
+                    health.suspect_since = Some(now_ms);
+                    assessment.events.push(SensorHealthEvent::Suspected {
+                        channel,
+                        fault,
+                        report: Box::new(suspect_report(reading, channel, fault, value, now_ms)),
+                    });
+                }
+            } else if health.status == SensorStatus::Suspect
+                && health.last_fault.is_some_and(|at| {
+                    now_ms.saturating_sub(at) >= self.config.clean_period.as_millis() as u64
+                })
+            {
+                health.status = SensorStatus::Healthy;
+                health.fault = None;
+                health.suspect_since = None;
+                assessment
+                    .events
+                    .push(SensorHealthEvent::Restored { channel });
+            }
+            if step.withheld || health.status == SensorStatus::Suspect {
+                assessment.excluded.push(channel);
+            }
+        }
+        assessment
+    }
+
+    pub fn is_suspect(&self, section_id: &str, channel: HazardKind) -> bool {
+        self.series
+            .get(&(section_id.to_string(), channel))
+            .is_some_and(|s| s.health.status == SensorStatus::Suspect)
+    }
+
+    /// Health of every tracked channel, ordered by section then channel
+    pub fn snapshot(&self) -> Vec<ChannelHealth> {
+        let mut all: Vec<_> = self.series.values().map(|s| s.health.clone()).collect();
+        all.sort_by(|a, b| {
+            a.section_id
+                .cmp(&b.section_id)
+                .then_with(|| a.channel.cmp(&b.channel))
+        });
+        all
+    }
+}
+
+fn suspect_report(
+    reading: &PipeEnvironment,
+    channel: HazardKind,
+    fault: SensorFault,
+    value: f64,
+    now_ms: u64,
+) -> AnomalyReport {
+    let what = match fault {
+        SensorFault::StuckAt => format!("stuck at {value}"),
+        SensorFault::Spike => "spiking".to_string(),
+        SensorFault::Drift => "drifting from neighboring sections".to_string(),
+    };
+    AnomalyReport {
+        timestamp: Timestamp::from_millis(now_ms),
+        ..AnomalyReport::new(
+            AnomalyType::Unknown,
+            SeverityLevel::Low,
+            reading.position,
+            &reading.section_id,
+            ENGINE_ORIGIN,
+            1.0,
+            format!(
+                "Sensor suspect: {:?} channel in {} is {}; excluded from hazard assessment",
+                channel, reading.section_id, what
+            ),
+        )
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alarms::{AlarmEvent, EnvironmentAlarms};
+    use aetheris_shared::Position;
+
+    const SECOND: u64 = 1_000;
+
+    fn reading(section: &str, temperature: f64, at: u64) -> PipeEnvironment {
+        PipeEnvironment {
+            section_id: section.into(),
+            pressure: 50.0,
+            temperature,
+            h2_concentration: 100.0,
+            wall_thickness: 10.0,
+            flow_rate: 500.0,
+            humidity: 45.0,
+            position: Position::origin(),
+            timestamp: Timestamp::from_millis(at),
+        }
+    }
+
+    /// Deterministic small wobble around a level
+    fn noisy(level: f64, i: u64, phase: u64) -> f64 {
+        level + ((i * 7 + phase * 3) % 11) as f64 * 0.05 - 0.25
+    }
+
+    /// Feed PIPE-001 with `f(i)` alongside two healthy neighbors; returns the
+    /// assessments of PIPE-001's readings
+    fn run(
+        health: &mut SensorHealth,
+        from: u64,
+        to: u64,
+        f: impl Fn(u64) -> f64,
+    ) -> Vec<SensorAssessment> {
+        (from..to)
+            .map(|i| {
+                let at = i * SECOND;
+                health.observe(&reading("PIPE-002", noisy(25.0, i, 1), at), at);
+                health.observe(&reading("PIPE-003", noisy(25.0, i, 2), at), at);
+                health.observe(&reading("PIPE-001", f(i), at), at)
+            })
+            .collect()
+    }
+
+    fn suspected(assessments: &[SensorAssessment]) -> Vec<SensorFault> {
+        assessments
+            .iter()
+            .flat_map(|a| &a.events)
+            .filter_map(|e| match e {
+                SensorHealthEvent::Suspected {
+                    channel: HazardKind::HighTemperature,
+                    fault,
+                    ..
+                } => Some(*fault),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_healthy_series_stays_healthy() {
+        let mut health = SensorHealth::default();
+        let assessments = run(&mut health, 0, 200, |i| noisy(25.0, i, 0));
+        assert!(assessments.iter().all(|a| a.events.is_empty()));
+        assert!(assessments.iter().all(|a| a.excluded.is_empty()));
+    }
+
+    #[test]
+    fn test_stuck_spiking_and_drifting_sensors_are_classified() {
+        let mut stuck = SensorHealth::default();
+        let assessments = run(&mut stuck, 0, 40, |_| 25.0);
+        assert_eq!(suspected(&assessments), [SensorFault::StuckAt]);
+
+        let mut spiking = SensorHealth::default();
+        let assessments = run(&mut spiking, 0, 40, |i| {
+            if i == 25 { 250.0 } else { noisy(25.0, i, 0) }
+        });
+        assert_eq!(suspected(&assessments), [SensorFault::Spike]);
+        // The spike itself never reached hazard assessment
+        assert!(
+            assessments[25]
+                .excluded
+                .contains(&HazardKind::HighTemperature)
+        );
+
+        let mut drifting = SensorHealth::default();
+        let assessments = run(&mut drifting, 0, 40, |i| noisy(25.0, i, 0) + 0.5 * i as f64);
+        assert_eq!(suspected(&assessments), [SensorFault::Drift]);
+        assert!(drifting.is_suspect("PIPE-001", HazardKind::HighTemperature));
+        assert!(!drifting.is_suspect("PIPE-002", HazardKind::HighTemperature));
+    }
+
+    #[test]
+    fn test_suspect_channel_is_excluded_from_hazard_assessment() {
+        let mut health = SensorHealth::default();
+        let mut alarms = EnvironmentAlarms::default();
+        let mut raised = 0;
+        for i in 0..60 {
+            let at = i * SECOND;
+            health.observe(&reading("PIPE-002", noisy(25.0, i, 1), at), at);
+            health.observe(&reading("PIPE-003", noisy(25.0, i, 2), at), at);
+            // Drifts past the 80 °C alert level after ~37 readings
+            let drifting = reading("PIPE-001", 25.0 + 1.5 * i as f64, at);
+            let assessment = health.observe(&drifting, at);
+            raised += alarms
+                .assess_excluding(&drifting, at, &assessment.excluded)
+                .iter()
+                .filter(|e| matches!(e, AlarmEvent::Raised(_)))
+                .count();
+        }
+        assert!(health.is_suspect("PIPE-001", HazardKind::HighTemperature));
+        assert_eq!(raised, 0);
+
+        // The same readings without the exclusion would have alarmed
+        let mut unguarded = EnvironmentAlarms::default();
+        let events = unguarded.assess(&reading("PIPE-001", 25.0 + 1.5 * 59.0, 0), 0);
+        assert!(matches!(events[0], AlarmEvent::Raised(_)));
+    }
+
+    #[test]
+    fn test_channel_is_restored_after_clean_period() {
+        let mut health = SensorHealth::new(SensorHealthConfig {
+            clean_period: Duration::from_secs(60),
+            ..SensorHealthConfig::default()
+        });
+        run(&mut health, 0, 40, |_| 25.0);
+        assert!(health.is_suspect("PIPE-001", HazardKind::HighTemperature));
+
+        // Unstuck: varies like its neighbors again
+        let assessments = run(&mut health, 40, 200, |i| noisy(25.0, i, 0));
+        let restored: Vec<usize> = assessments
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| {
+                a.events.contains(&SensorHealthEvent::Restored {
+                    channel: HazardKind::HighTemperature,
+                })
+            })
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(restored.len(), 1);
+        assert!(!health.is_suspect("PIPE-001", HazardKind::HighTemperature));
+        let snapshot = health.snapshot();
+        assert_eq!(snapshot.len(), 9);
+        assert!(snapshot.iter().all(|h| h.status == SensorStatus::Healthy));
+    }
+}
@@ -0,0 +1,262 @@
+//! Envelope sequence numbers: numbering and gap detection
+//!
+//! Every publisher numbers its envelopes per message class, so the receiving
+//! side can tell a stream that lost messages (a gap) from one that delivers
+//! them late or twice (a regression, common under QoS 1). The engine numbers
+//! what it publishes the same way, one counter per (source, class), which
+//! keeps the simulated robots' streams contiguous.
+//!
+//! A sequence number that falls back while the envelope timestamp moves
+//! forward is a publisher that restarted, and starts a new session instead
+//! of counting as a huge gap.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Reaction to gaps in incoming sequence numbers
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequenceConfig {
+    /// Missed messages in one gap that count as degraded communication
+    pub large_gap: u64,
+    /// Raise an alert for a large gap, not only a warning in the log
+    pub alert_on_large_gap: bool,
+}
+
+impl Default for SequenceConfig {
+    fn default() -> Self {
+        Self {
+            large_gap: 50,
+            alert_on_large_gap: true,
+        }
+    }
+}
+
+impl CheckConfig for SequenceConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if self.large_gap == 0 {
+            checker.error(
+                "large_gap",
+                "must be at least 1",
+                Some("a gap of one message is already a miss".into()),
+            );
+        }
+    }
+}
+
+// ============================================================================
+// MESSAGE CLASSES
+// ============================================================================
+
+/// Envelope stream a sequence number counts within
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MessageClass {
+    Telemetry,
+    TelemetryBatch,
+    FilteredTelemetry,
+    Alert,
+    TriageRequest,
+    TriageResult,
+    Environment,
+    Command,
+    Route,
+    SectionHealth,
+    Mission,
+}
+
+/// Sequence numbers of outgoing envelopes, per (source, class)
+#[derive(Debug, Default)]
+pub struct SequenceCounter {
+    next: HashMap<(String, MessageClass), u64>,
+}
+
+impl SequenceCounter {
+    /// Number for the next envelope `source` publishes in `class`
+    pub fn next(&mut self, source: &str, class: MessageClass) -> u64 {
+        let next = self.next.entry((source.to_string(), class)).or_default();
+        let seq = *next;
+        *next += 1;
+        seq
+    }
+}
+
+// ============================================================================
+// SEQUENCE TRACKER
+// ============================================================================
+
+/// Where an incoming envelope falls in its stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeqVerdict {
+    /// The next number, or the first one heard
+    InOrder,
+    /// `missed` messages were skipped
+    Gap { missed: u64, large: bool },
+    /// The number that was just seen, delivered again
+    Duplicate,
+    /// Older than the latest seen: delivered out of order
+    Stale { latest: u64 },
+    /// The number fell back with a newer timestamp: the publisher restarted
+    Restarted,
+}
+
+impl SeqVerdict {
+    /// Whether the envelope carries news that should be applied over state
+    /// from the latest one
+    pub fn is_current(&self) -> bool {
+        !matches!(self, Self::Duplicate | Self::Stale { .. })
+    }
+}
+
+/// Latest sequence number per incoming (source, class) stream
+#[derive(Debug, Default)]
+pub struct SequenceTracker {
+    config: SequenceConfig,
+    /// Sequence number and envelope timestamp of the latest envelope
+    latest: HashMap<(String, MessageClass), (u64, u64)>,
+    /// Messages missed per source, across classes and sessions
+    missed: BTreeMap<String, u64>,
+}
+
+impl SequenceTracker {
+    pub fn new(config: SequenceConfig) -> Self {
+        Self {
+            config,
+            latest: HashMap::new(),
+            missed: BTreeMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> &SequenceConfig {
+        &self.config
+    }
+
+    /// Place an envelope numbered `seq` and stamped `timestamp` in its stream
+    pub fn observe(
+        &mut self,
+        source: &str,
+        class: MessageClass,
+        seq: u64,
+        timestamp: u64,
+    ) -> SeqVerdict {
+        let key = (source.to_string(), class);
+        let Some(&(latest, latest_timestamp)) = self.latest.get(&key) else {
+            self.latest.insert(key, (seq, timestamp));
+            return SeqVerdict::InOrder;
+        };
+        let verdict = if seq == latest {
+            SeqVerdict::Duplicate
+        } else if seq < latest && timestamp > latest_timestamp {
+            SeqVerdict::Restarted
+        } else if seq < latest {
+            SeqVerdict::Stale { latest }
+        } else if seq == latest + 1 {
+            SeqVerdict::InOrder
+        } else {
+            let missed = seq - latest - 1;
+            *self.missed.entry(source.to_string()).or_default() += missed;
+            SeqVerdict::Gap {
+                missed,
+                large: missed >= self.config.large_gap,
+            }
+        };
+        if verdict.is_current() {
+            self.latest.insert(key, (seq, timestamp));
+        }
+        verdict
+    }
+
+    /// Messages missed per source
+    pub fn missed(&self) -> &BTreeMap<String, u64> {
+        &self.missed
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const T0: u64 = 1_000_000;
+
+    #[test]
+    fn test_gaps_and_regressions_are_told_apart() {
+        let mut tracker = SequenceTracker::new(SequenceConfig {
+            large_gap: 10,
+            ..SequenceConfig::default()
+        });
+        let telemetry = MessageClass::Telemetry;
+
+        assert_eq!(
+            tracker.observe("RV-001", telemetry, 7, T0),
+            SeqVerdict::InOrder
+        );
+        assert_eq!(
+            tracker.observe("RV-001", telemetry, 8, T0 + 100),
+            SeqVerdict::InOrder
+        );
+        // QoS 1 redelivery, then a late message
+        assert_eq!(
+            tracker.observe("RV-001", telemetry, 8, T0 + 100),
+            SeqVerdict::Duplicate
+        );
+        assert_eq!(
+            tracker.observe("RV-001", telemetry, 11, T0 + 400),
+            SeqVerdict::Gap {
+                missed: 2,
+                large: false
+            }
+        );
+        assert_eq!(
+            tracker.observe("RV-001", telemetry, 9, T0 + 200),
+            SeqVerdict::Stale { latest: 11 }
+        );
+        assert_eq!(
+            tracker.observe("RV-001", telemetry, 30, T0 + 2_300),
+            SeqVerdict::Gap {
+                missed: 18,
+                large: true
+            }
+        );
+
+        // Each class and source counts on its own
+        assert_eq!(
+            tracker.observe("RV-001", MessageClass::Alert, 0, T0),
+            SeqVerdict::InOrder
+        );
+        assert_eq!(
+            tracker.observe("RV-002", telemetry, 500, T0),
+            SeqVerdict::InOrder
+        );
+        assert_eq!(tracker.missed(), &BTreeMap::from([("RV-001".into(), 20)]));
+    }
+
+    #[test]
+    fn test_restarted_publisher_starts_a_new_session() {
+        let mut tracker = SequenceTracker::default();
+        let telemetry = MessageClass::Telemetry;
+        tracker.observe("RV-001", telemetry, 1_000_000, T0);
+
+        assert_eq!(
+            tracker.observe("RV-001", telemetry, 0, T0 + 5_000),
+            SeqVerdict::Restarted
+        );
+        assert_eq!(
+            tracker.observe("RV-001", telemetry, 1, T0 + 5_100),
+            SeqVerdict::InOrder
+        );
+        assert!(tracker.missed().is_empty());
+
+        let mut counter = SequenceCounter::default();
+        assert_eq!(counter.next("RV-001", telemetry), 0);
+        assert_eq!(counter.next("RV-002", telemetry), 0);
+        assert_eq!(counter.next("RV-001", telemetry), 1);
+        assert_eq!(counter.next("RV-001", MessageClass::Alert), 0);
+    }
+}
@@ -0,0 +1,161 @@
+//! Graceful shutdown
+//!
+//! Ctrl+C or SIGTERM triggers a [`ShutdownTrigger`]; every background task
+//! holds a [`Shutdown`] and returns when it fires, the fleet simulation after
+//! announcing its robots offline. The tasks are then joined against a
+//! deadline: whatever has not finished by then is aborted and reported, so
+//! a stuck task cannot keep the process alive.
+
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, timeout_at};
+use tracing::warn;
+
+/// Time the background tasks and the final publishes get after a shutdown
+/// signal
+pub const GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Exit code when the grace period ran out before everything stopped
+pub const EXIT_SHUTDOWN_TIMEOUT: i32 = 1;
+
+// ============================================================================
+// SHUTDOWN SIGNAL
+// ============================================================================
+
+/// Fires the shutdown for every [`Shutdown`] subscribed to it
+#[derive(Debug)]
+pub struct ShutdownTrigger(watch::Sender<bool>);
+
+/// A task's view of the shutdown
+#[derive(Debug, Clone)]
+pub struct Shutdown(watch::Receiver<bool>);
+
+/// A trigger and a first subscriber; clone the subscriber for more tasks
+pub fn channel() -> (ShutdownTrigger, Shutdown) {
+    let (tx, rx) = watch::channel(false);
+    (ShutdownTrigger(tx), Shutdown(rx))
+}
+
+impl ShutdownTrigger {
+    pub fn trigger(&self) {
+        self.0.send_replace(true);
+    }
+
+    pub fn subscribe(&self) -> Shutdown {
+        Shutdown(self.0.subscribe())
+    }
+}
+
+impl Shutdown {
+    /// Resolves once the shutdown was triggered, or its trigger dropped
+    pub async fn wait(&mut self) {
+        let _ = self.0.wait_for(|triggered| *triggered).await;
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.0.borrow()
+    }
+}
+
+/// Waits for Ctrl+C, or SIGTERM on unix; returns the signal's name
+pub async fn signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => return "SIGINT",
+                    _ = terminate.recv() => return "SIGTERM",
+                }
+            }
+            Err(e) => warn!("Cannot listen for SIGTERM: {}", e),
+        }
+    }
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        warn!("Cannot listen for Ctrl+C: {}", e);
+        std::future::pending::<()>().await;
+    }
+    "SIGINT"
+}
+
+// ============================================================================
+// BACKGROUND TASKS
+// ============================================================================
+
+/// Background tasks joined at shutdown
+#[derive(Debug, Default)]
+pub struct TaskSet {
+    tasks: Vec<(&'static str, JoinHandle<()>)>,
+}
+
+impl TaskSet {
+    pub fn push(&mut self, name: &'static str, task: JoinHandle<()>) {
+        self.tasks.push((name, task));
+    }
+
+    /// Waits for every task until `deadline`, aborts the ones still running
+    /// and returns their names
+    pub async fn join_until(self, deadline: Instant) -> Vec<&'static str> {
+        let mut stragglers = Vec::new();
+        for (name, mut task) in self.tasks {
+            match timeout_at(deadline, &mut task).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => warn!(task = name, "Background task failed: {}", e),
+                Err(_) => {
+                    task.abort();
+                    stragglers.push(name);
+                }
+            }
+        }
+        stragglers
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn until_shutdown(mut shutdown: Shutdown) -> JoinHandle<()> {
+        tokio::spawn(async move { shutdown.wait().await })
+    }
+
+    #[tokio::test]
+    async fn test_tasks_stop_within_the_grace_period() {
+        let (trigger, shutdown) = channel();
+        let mut tasks = TaskSet::default();
+        tasks.push("monitor", until_shutdown(shutdown.clone()));
+        tasks.push("publisher", until_shutdown(trigger.subscribe()));
+        assert!(!shutdown.is_triggered());
+
+        trigger.trigger();
+        assert!(shutdown.is_triggered());
+        let stragglers = tasks.join_until(Instant::now() + GRACE_PERIOD).await;
+        assert!(stragglers.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stuck_task_is_aborted_at_the_deadline() {
+        let (trigger, shutdown) = channel();
+        let mut tasks = TaskSet::default();
+        tasks.push("monitor", until_shutdown(shutdown));
+        let stuck = tokio::spawn(std::future::pending::<()>());
+        let stuck_abort = stuck.abort_handle();
+        tasks.push("stuck", stuck);
+
+        trigger.trigger();
+        let deadline = Instant::now() + Duration::from_millis(50);
+        let stragglers = tasks.join_until(deadline).await;
+        assert_eq!(stragglers, ["stuck"]);
+        assert!(Instant::now() >= deadline);
+        tokio::task::yield_now().await;
+        assert!(stuck_abort.is_finished());
+    }
+}
@@ -0,0 +1,2302 @@
+//! Mock fleet publishing schedule
+//!
+//! Simulated robots publish telemetry and heartbeats on their own timers, as
+//! real robots do: each robot gets an evenly spread phase offset within the
+//! interval plus seeded per-publish jitter, so the fleet produces a smooth
+//! stream instead of synchronized bursts on a shared tick.
+//!
+//! A robot whose true position leaves the [`WorldBounds`] is halted with
+//! status Error instead of publishing a runaway position.
+//!
+//! Patrolling robots whose route is known follow it waypoint by waypoint,
+//! dwelling and scanning where the route says to; other robots keep their
+//! velocity. Commands addressed to the simulated robots redirect them, and
+//! every robot answers each command it receives with a [`CommandResponse`].
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, sleep_until};
+use tracing::{error, info, warn};
+
+use aetheris_shared::topics::CommandTarget;
+use aetheris_shared::{
+    AnomalyReport, AnomalyType, Capabilities, ChargingStation, Command, CommandResponse,
+    CurrentTask, FaultType, Heartbeat, Orientation, PatrolRoute, Position, RobotConfig, RobotId,
+    RobotState, RobotStatus, RobotType, SeverityLevel, TelemetryBatch, Timestamp, Velocity, limits,
+};
+
+use crate::anomalies::SYSTEM_SECTION;
+use crate::battery::{BatteryConfig, worse};
+use crate::bounds::WorldBounds;
+use crate::command_expiry::CommandExpiryConfig;
+use crate::config::{CheckConfig, ConfigChecker};
+use crate::events::{SystemEvent, SystemEventKind};
+use crate::faults::{FaultEvent, RecoveryConfig, RobotFaults};
+use crate::reconnect::ConnectionState;
+use crate::shutdown::Shutdown;
+use crate::{AetherisMqtt, ReceivedCommand, create_mock_fleet, create_mock_routes};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Publish timing for the simulated fleet
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationTiming {
+    /// Nominal interval between telemetry publishes per robot
+    pub telemetry_interval: Duration,
+    /// Nominal interval between heartbeats per robot
+    pub heartbeat_interval: Duration,
+    /// Maximum jitter as a fraction of the interval (0.1 = ±10%)
+    pub jitter_fraction: f64,
+    /// Seed for the jitter RNG so runs are reproducible
+    pub seed: u64,
+    /// Report the whole fleet in one telemetry batch per interval instead
+    /// of one message per robot
+    pub batch_telemetry: bool,
+}
+
+impl Default for SimulationTiming {
+    fn default() -> Self {
+        Self {
+            telemetry_interval: Duration::from_secs(1),
+            heartbeat_interval: Duration::from_secs(5),
+            jitter_fraction: 0.1,
+            seed: 0xAE7E_4215,
+            batch_telemetry: false,
+        }
+    }
+}
+
+/// Timing configuration that risks false heartbeat timeouts
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error(
+    "worst-case heartbeat gap of {worst_gap:?} leaves no margin for a lost heartbeat \
+     under the {timeout:?} timeout"
+)]
+pub struct TimingViolation {
+    pub worst_gap: Duration,
+    pub timeout: Duration,
+}
+
+impl CheckConfig for SimulationTiming {
+    fn check(&self, checker: &mut ConfigChecker) {
+        checker.positive("telemetry_interval", self.telemetry_interval);
+        checker.positive("heartbeat_interval", self.heartbeat_interval);
+        checker.fraction("jitter_fraction", self.jitter_fraction);
+    }
+}
+
+/// One simulated robot as written in a configuration file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RobotSpec {
+    pub id: RobotId,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub robot_type: RobotType,
+    /// Starting position
+    #[serde(default)]
+    pub position: Position,
+    /// Starting battery level (percent)
+    #[serde(default = "full_battery")]
+    pub battery: f64,
+    /// Patrol route the robot starts on; idle without one
+    #[serde(default)]
+    pub route: Option<String>,
+}
+
+fn full_battery() -> f64 {
+    limits::BATTERY_FULL_PERCENT
+}
+
+impl RobotSpec {
+    /// Initial state of the robot
+    pub fn state(&self) -> RobotState {
+        let mut state = RobotState::new(self.id.clone(), &self.name, self.robot_type);
+        state.position = self.position;
+        state.battery = self.battery;
+        if let Some(route_id) = &self.route {
+            state.status = RobotStatus::Active;
+            state.current_task = CurrentTask::Patrolling {
+                route_id: route_id.clone(),
+            };
+        }
+        state
+    }
+}
+
+/// The simulated fleet; without robots, the demo fleet of
+/// [`create_mock_fleet`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FleetConfig {
+    pub robots: Vec<RobotSpec>,
+}
+
+impl FleetConfig {
+    /// Initial states of the configured robots
+    pub fn states(&self) -> Vec<RobotState> {
+        if self.robots.is_empty() {
+            return create_mock_fleet();
+        }
+        self.robots.iter().map(RobotSpec::state).collect()
+    }
+}
+
+impl CheckConfig for FleetConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        let routes: Vec<String> = create_mock_routes().into_iter().map(|r| r.id).collect();
+        for (i, robot) in self.robots.iter().enumerate() {
+            let base = format!("robots[{i}]");
+            // Ids are well formed once parsed; only duplicates are left
+            if let Some(first) = self.robots[..i].iter().position(|r| r.id == robot.id) {
+                checker.error(
+                    &format!("{base}.id"),
+                    format!("duplicate robot id \"{}\"", robot.id),
+                    Some(format!("already used by robots[{first}]")),
+                );
+            }
+            if robot.name.trim().is_empty() {
+                checker.error(&format!("{base}.name"), "must not be empty", None);
+            }
+            if !robot.position.is_finite() {
+                checker.error(&format!("{base}.position"), "must be finite", None);
+            }
+            if !(0.0..=limits::BATTERY_FULL_PERCENT).contains(&robot.battery) {
+                checker.error(
+                    &format!("{base}.battery"),
+                    format!(
+                        "must be within 0..={}, got {}",
+                        limits::BATTERY_FULL_PERCENT,
+                        robot.battery
+                    ),
+                    None,
+                );
+            }
+            if let Some(route) = &robot.route
+                && !routes.contains(route)
+            {
+                checker.error(
+                    &format!("{base}.route"),
+                    format!("unknown route \"{route}\""),
+                    Some(format!("known routes: {}", routes.join(", "))),
+                );
+            }
+        }
+    }
+}
+
+impl SimulationTiming {
+    /// Longest possible gap between two heartbeats from one robot
+    pub fn worst_heartbeat_gap(&self) -> Duration {
+        self.heartbeat_interval
+            .mul_f64(1.0 + self.jitter_fraction.clamp(0.0, 1.0))
+    }
+
+    /// Check that jittered heartbeats stay safely under the timeout.
+    ///
+    /// "Safely" means a single lost heartbeat must not mark the robot offline,
+    /// so two worst-case gaps have to fit inside the timeout.
+    pub fn validate_against_timeout(&self, timeout: Duration) -> Result<(), TimingViolation> {
+        let worst_gap = self.worst_heartbeat_gap();
+        if worst_gap * 2 > timeout {
+            return Err(TimingViolation { worst_gap, timeout });
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// SCHEDULER
+// ============================================================================
+
+/// Kind of periodic publish a simulated robot makes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PublishKind {
+    Telemetry,
+    /// Telemetry of every robot at once; scheduled for robot 0 only
+    TelemetryBatch,
+    Heartbeat,
+}
+
+/// A publish due at `at` (offset from simulation start)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ScheduledPublish {
+    pub at: Duration,
+    pub robot_index: usize,
+    pub kind: PublishKind,
+}
+
+/// Deterministic per-robot publish schedule with phase spreading and jitter
+#[derive(Debug)]
+pub struct PublishScheduler {
+    queue: BinaryHeap<Reverse<ScheduledPublish>>,
+    timing: SimulationTiming,
+    /// Intervals of robots configured away from the fleet's timing
+    overrides: HashMap<(usize, PublishKind), Duration>,
+    rng: StdRng,
+}
+
+impl PublishScheduler {
+    pub fn new(robot_count: usize, timing: SimulationTiming) -> Self {
+        let mut queue = BinaryHeap::with_capacity(robot_count * 2);
+        if timing.batch_telemetry && robot_count > 0 {
+            queue.push(Reverse(ScheduledPublish {
+                at: Duration::ZERO,
+                robot_index: 0,
+                kind: PublishKind::TelemetryBatch,
+            }));
+        }
+        for robot_index in 0..robot_count {
+            for (kind, interval) in [
+                (PublishKind::Telemetry, timing.telemetry_interval),
+                (PublishKind::Heartbeat, timing.heartbeat_interval),
+            ] {
+                if kind == PublishKind::Telemetry && timing.batch_telemetry {
+                    continue;
+                }
+                // Spread robots evenly across one interval
+                let phase = interval.mul_f64(robot_index as f64 / robot_count as f64);
+                queue.push(Reverse(ScheduledPublish {
+                    at: phase,
+                    robot_index,
+                    kind,
+                }));
+            }
+        }
+
+        Self {
+            queue,
+            overrides: HashMap::new(),
+            rng: StdRng::seed_from_u64(timing.seed),
+            timing,
+        }
+    }
+
+    /// Publish `kind` for one robot every `interval` from its next publish
+    /// on; `None` goes back to the fleet's timing
+    pub fn set_interval(
+        &mut self,
+        robot_index: usize,
+        kind: PublishKind,
+        interval: Option<Duration>,
+    ) {
+        match interval {
+            Some(interval) => self.overrides.insert((robot_index, kind), interval),
+            None => self.overrides.remove(&(robot_index, kind)),
+        };
+    }
+
+    /// Pop the next due publish and schedule the following one for that robot
+    pub fn next_publish(&mut self) -> Option<ScheduledPublish> {
+        let Reverse(due) = self.queue.pop()?;
+        let interval = match (self.overrides.get(&(due.robot_index, due.kind)), due.kind) {
+            (Some(interval), _) => *interval,
+            (None, PublishKind::Telemetry | PublishKind::TelemetryBatch) => {
+                self.timing.telemetry_interval
+            }
+            (None, PublishKind::Heartbeat) => self.timing.heartbeat_interval,
+        };
+        let jitter = self.timing.jitter_fraction.clamp(0.0, 1.0);
+        let factor = 1.0 + self.rng.random_range(-jitter..=jitter);
+        self.queue.push(Reverse(ScheduledPublish {
+            at: due.at + interval.mul_f64(factor),
+            ..due
+        }));
+        Some(due)
+    }
+}
+
+// ============================================================================
+// MOTION
+// ============================================================================
+
+/// Simulated time one telemetry tick moves a robot by
+pub const MOTION_STEP_SECS: f64 = 0.1;
+
+/// A simulated robot that left the world and was halted
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoundsEscape {
+    pub robot_id: String,
+    /// Where the robot would have ended up
+    pub escaped_to: Position,
+    /// Task the robot was executing when it escaped
+    pub task: CurrentTask,
+    /// Id and name of the last command the robot accepted, if any
+    pub last_command: Option<(String, &'static str)>,
+}
+
+impl BoundsEscape {
+    /// Fleet-health alert announcing the halt
+    pub fn to_report(&self, halted_at: Position) -> AnomalyReport {
+        AnomalyReport::new(
+            AnomalyType::Unknown,
+            SeverityLevel::High,
+            halted_at,
+            SYSTEM_SECTION,
+            &self.robot_id,
+            1.0,
+            format!(
+                "{} halted: moving to ({:.3e}, {:.3e}, {:.3e}) would leave the world bounds while executing {:?}",
+                self.robot_id, self.escaped_to.x, self.escaped_to.y, self.escaped_to.z, self.task
+            ),
+        )
+    }
+
+    /// Event recording the halt, linked to the last command the robot took
+    pub fn to_event(&self, now: u64) -> SystemEvent {
+        let command = match &self.last_command {
+            Some((_, name)) => format!("after {name}"),
+            None => "before any command".into(),
+        };
+        let event = SystemEvent::new(
+            SystemEventKind::RobotHalted,
+            Some(&self.robot_id),
+            format!(
+                "Halted at the world bounds while executing {:?}, {command}",
+                self.task
+            ),
+            now,
+        );
+        match &self.last_command {
+            Some((command_id, _)) => event.for_command(command_id),
+            None => event,
+        }
+    }
+}
+
+/// Compute the position `robot` publishes on its next telemetry tick.
+///
+/// A moving robot turns to face its direction of travel; a stopped one keeps
+/// its last orientation. If the next position is outside `bounds`, the robot
+/// is halted in place with status Error and the escape is returned.
+pub fn advance_robot(
+    robot: &mut RobotState,
+    bounds: &WorldBounds,
+) -> (Position, Option<BoundsEscape>) {
+    let next = robot.position.translate(&robot.velocity, MOTION_STEP_SECS);
+    if bounds.contains(&next) {
+        if let Some(facing) = Orientation::facing(&robot.velocity) {
+            robot.orientation = facing;
+        }
+        return (next, None);
+    }
+
+    robot.velocity = Velocity::zero();
+    robot.status = RobotStatus::Error;
+    let escape = BoundsEscape {
+        robot_id: robot.id.to_string(),
+        escaped_to: next,
+        task: robot.current_task.clone(),
+        last_command: None,
+    };
+    (robot.position, Some(escape))
+}
+
+// ============================================================================
+// ROUTE FOLLOWING
+// ============================================================================
+
+/// Distance at which a robot counts as having reached a waypoint
+const ARRIVAL_RADIUS: f64 = 1e-6;
+
+/// Distance a charger couples from (meters), so a robot whose position
+/// drifts still docks
+const DOCKING_RADIUS: f64 = 1.0;
+
+/// Point `robot`'s velocity at `target`: full `speed`, or exactly onto the
+/// target when it is within one step. Returns `false`, leaving the robot
+/// stopped, once it has arrived.
+fn steer_toward(robot: &mut RobotState, target: &Position, speed: f64) -> bool {
+    let offset = *target - robot.position;
+    let distance = offset.length();
+    if distance <= ARRIVAL_RADIUS {
+        robot.velocity = Velocity::zero();
+        return false;
+    }
+    robot.velocity = Velocity::from_direction(&offset, speed.min(distance / MOTION_STEP_SECS));
+    true
+}
+
+/// Progress of one simulated robot along a patrol route
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteFollower {
+    route: PatrolRoute,
+    /// Waypoint being driven to or dwelt at; `None` once a one-shot route is done
+    target: Option<usize>,
+    /// Motion steps left to hold at the current waypoint
+    dwell_steps: u32,
+    dwelling: bool,
+    /// Cruise speed between waypoints (m/s)
+    speed: f64,
+}
+
+impl RouteFollower {
+    /// Join `route` at the waypoint nearest `position`
+    pub fn new(route: PatrolRoute, position: &Position, speed: f64) -> Self {
+        let target = route.nearest_waypoint(position);
+        Self {
+            route,
+            target,
+            dwell_steps: 0,
+            dwelling: false,
+            speed,
+        }
+    }
+
+    pub fn route(&self) -> &PatrolRoute {
+        &self.route
+    }
+
+    /// Index of the waypoint being driven to or dwelt at
+    pub fn target(&self) -> Option<usize> {
+        self.target
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.target.is_none()
+    }
+
+    /// Set `robot`'s velocity and task for the next motion step
+    pub fn steer(&mut self, robot: &mut RobotState) {
+        // Waypoints passed through without dwelling are skipped within one
+        // step; a route whose waypoints all coincide must not spin forever
+        for _ in 0..=self.route.waypoints.len() {
+            let Some(index) = self.target else {
+                robot.velocity = Velocity::zero();
+                robot.current_task = CurrentTask::None;
+                robot.status = RobotStatus::Idle;
+                return;
+            };
+            let waypoint = &self.route.waypoints[index];
+
+            if self.dwelling {
+                self.dwell_steps = self.dwell_steps.saturating_sub(1);
+                if self.dwell_steps > 0 {
+                    robot.velocity = Velocity::zero();
+                    return;
+                }
+                self.dwelling = false;
+                self.target = self.route.next_waypoint(index);
+                robot.current_task = self.patrolling();
+                continue;
+            }
+
+            if robot.position.distance_to(&waypoint.position) <= ARRIVAL_RADIUS {
+                if let Some(secs) = waypoint.dwell_secs.filter(|secs| *secs > 0.0) {
+                    self.dwelling = true;
+                    self.dwell_steps = (secs / MOTION_STEP_SECS).round() as u32;
+                    robot.velocity = Velocity::zero();
+                    if let Some(scan_type) = waypoint.scan {
+                        robot.current_task = CurrentTask::Scanning { scan_type };
+                    }
+                    return;
+                }
+                self.target = self.route.next_waypoint(index);
+                continue;
+            }
+
+            steer_toward(robot, &waypoint.position, self.speed);
+            robot.current_task = self.patrolling();
+            return;
+        }
+        robot.velocity = Velocity::zero();
+    }
+
+    fn patrolling(&self) -> CurrentTask {
+        CurrentTask::Patrolling {
+            route_id: self.route.id.clone(),
+        }
+    }
+}
+
+// ============================================================================
+// SIMULATED FLEET
+// ============================================================================
+
+/// Where simulated robots return to
+pub const BASE_POSITION: Position = Position {
+    x: 0.0,
+    y: 0.0,
+    z: 0.0,
+};
+
+/// Motion steps a commanded scan takes
+pub const SCAN_STEPS: u32 = 5;
+
+/// What a simulated robot does on its telemetry ticks
+#[derive(Debug, Clone, PartialEq)]
+enum Activity {
+    /// Keep the current velocity
+    Drifting,
+    Holding,
+    Patrolling(RouteFollower),
+    /// Drive to `target`, then go idle
+    MovingTo {
+        target: Position,
+        speed: f64,
+    },
+    Scanning {
+        steps_left: u32,
+    },
+    /// Drive to a charging station
+    Docking {
+        station: usize,
+    },
+    /// At a charging station: charging once `plugged`, otherwise waiting
+    /// for a free charger
+    Charging {
+        station: usize,
+        plugged: bool,
+    },
+}
+
+/// What a robot goes back to once charged
+#[derive(Debug, Clone, PartialEq)]
+struct Suspended {
+    activity: Activity,
+    task: CurrentTask,
+}
+
+/// What commands may refer to
+struct World<'a> {
+    routes: &'a [PatrolRoute],
+    stations: &'a [ChargingStation],
+    /// Robots docking at or charging at each station
+    load: Vec<u32>,
+}
+
+impl World<'_> {
+    /// Nearest station with a free charger, or the nearest one to queue at
+    fn station_for(&self, position: &Position) -> Option<usize> {
+        let distance = |i: &usize| self.stations[*i].position.distance_to(position);
+        let nearest = |candidates: &mut dyn Iterator<Item = usize>| {
+            candidates.min_by(|a, b| distance(a).total_cmp(&distance(b)))
+        };
+        nearest(&mut (0..self.stations.len()).filter(|&i| self.load[i] < self.stations[i].capacity))
+            .or_else(|| nearest(&mut (0..self.stations.len())))
+    }
+}
+
+#[derive(Debug)]
+struct SimRobot {
+    state: RobotState,
+    activity: Activity,
+    /// Speed used for commanded moves that name none (m/s)
+    cruise_speed: f64,
+    /// Commands the robot refuses as beyond it
+    capabilities: Capabilities,
+    faults: RobotFaults,
+    /// Unix timestamp of the previous motion step (milliseconds)
+    last_step: Option<u64>,
+    /// Settings configured so far, merged
+    config: RobotConfig,
+    /// Resumed after charging; `None` goes idle
+    resume: Option<Suspended>,
+    /// Id and name of the last command accepted
+    last_command: Option<(String, &'static str)>,
+    /// Fault events not yet moved into the engine's event log
+    events: Vec<SystemEvent>,
+}
+
+impl SimRobot {
+    fn new(
+        state: RobotState,
+        routes: &[PatrolRoute],
+        default_speed: f64,
+        recovery: RecoveryConfig,
+    ) -> Self {
+        let cruise_speed = match state.velocity.magnitude() {
+            speed if speed > 0.0 => speed,
+            _ => default_speed,
+        };
+        let route = match &state.current_task {
+            CurrentTask::Patrolling { route_id } => routes.iter().find(|r| &r.id == route_id),
+            _ => None,
+        };
+        let activity = match route {
+            Some(route) => Activity::Patrolling(RouteFollower::new(
+                route.clone(),
+                &state.position,
+                cruise_speed,
+            )),
+            None => Activity::Drifting,
+        };
+        Self {
+            capabilities: state.robot_type.capabilities(),
+            state,
+            activity,
+            cruise_speed,
+            faults: RobotFaults::new(recovery),
+            last_step: None,
+            config: RobotConfig::default(),
+            resume: None,
+            last_command: None,
+            events: Vec::new(),
+        }
+    }
+
+    /// Start a new activity, abandoning any resume after charging
+    fn start(&mut self, activity: Activity, task: CurrentTask, status: RobotStatus) {
+        self.activity = activity;
+        self.resume = None;
+        self.state.current_task = task;
+        self.state.status = status;
+    }
+
+    fn halt(&mut self, task: CurrentTask, status: RobotStatus) {
+        self.start(Activity::Holding, task, status);
+        self.state.velocity = Velocity::zero();
+    }
+
+    /// Head for a charging station, resuming `resume` once charged
+    fn dock(&mut self, station: usize, resume: Option<Suspended>) {
+        self.start(
+            Activity::Docking { station },
+            CurrentTask::ReturningToBase,
+            RobotStatus::Active,
+        );
+        self.resume = resume;
+    }
+
+    fn is_charging(&self) -> bool {
+        matches!(
+            self.activity,
+            Activity::Docking { .. } | Activity::Charging { .. }
+        )
+    }
+
+    /// Carry out a received command, remembering it once accepted
+    fn handle_received(
+        &mut self,
+        received: &ReceivedCommand,
+        world: &World,
+        now: u64,
+    ) -> Result<(), String> {
+        let recorded = self.events.len();
+        let outcome = self.handle(&received.command, world, now);
+        if outcome.is_ok() {
+            self.last_command = Some((received.command_id.clone(), received.command.name()));
+        }
+        for event in &mut self.events[recorded..] {
+            event.command_id = Some(received.command_id.clone());
+        }
+        outcome
+    }
+
+    /// Carry out `command`; `Err` is the reason reported to the sender
+    fn handle(&mut self, command: &Command, world: &World, now: u64) -> Result<(), String> {
+        let halted = matches!(self.state.status, RobotStatus::Error | RobotStatus::Offline);
+        self.capabilities
+            .check(command)
+            .map_err(|unsupported| unsupported.to_string())?;
+        match command {
+            Command::InjectFault { fault_type } => {
+                let events = self.faults.inject(*fault_type, &mut self.state, now);
+                self.log_fault_events(events, now);
+            }
+            Command::ClearFault { fault_type } => {
+                let events = match fault_type {
+                    Some(fault_type) => self.faults.clear(*fault_type, &mut self.state),
+                    None => self.faults.clear_all(&mut self.state),
+                };
+                self.log_fault_events(events, now);
+            }
+            Command::CorrectPosition => {
+                let events = self.faults.correct_position(&mut self.state);
+                self.log_fault_events(events, now);
+            }
+            Command::Stop => self.halt(CurrentTask::None, RobotStatus::Idle),
+            // Stays down until an operator commands it again
+            Command::EmergencyStop => self.halt(CurrentTask::None, RobotStatus::Maintenance),
+            _ if halted => {
+                return Err(format!(
+                    "{} is {:?} and cannot {}",
+                    self.state.id,
+                    self.state.status,
+                    command.name()
+                ));
+            }
+            Command::MoveTo { target, speed } => {
+                let speed = speed.unwrap_or(self.cruise_speed);
+                if !(target.is_finite() && speed.is_finite() && speed > 0.0) {
+                    return Err(format!("invalid move to {target:?} at {speed} m/s"));
+                }
+                self.start(
+                    Activity::MovingTo {
+                        target: *target,
+                        speed,
+                    },
+                    CurrentTask::MovingTo { target: *target },
+                    RobotStatus::Active,
+                );
+            }
+            // Charge at the nearest station, or drive to base without one
+            Command::ReturnToBase => match world.station_for(&self.state.position) {
+                Some(station) => self.dock(station, None),
+                None => self.start(
+                    Activity::MovingTo {
+                        target: BASE_POSITION,
+                        speed: self.cruise_speed,
+                    },
+                    CurrentTask::ReturningToBase,
+                    RobotStatus::Active,
+                ),
+            },
+            Command::PerformScan { scan_type } => {
+                self.halt(
+                    CurrentTask::Scanning {
+                        scan_type: *scan_type,
+                    },
+                    RobotStatus::Active,
+                );
+                self.activity = Activity::Scanning {
+                    steps_left: SCAN_STEPS,
+                };
+            }
+            Command::StartPatrol { route_id } => {
+                let route = world
+                    .routes
+                    .iter()
+                    .find(|route| &route.id == route_id)
+                    .ok_or_else(|| format!("unknown route {route_id}"))?;
+                self.capabilities
+                    .check_route(route)
+                    .map_err(|unsupported| format!("{route_id}: {unsupported}"))?;
+                self.start(
+                    Activity::Patrolling(RouteFollower::new(
+                        route.clone(),
+                        &self.state.position,
+                        self.cruise_speed,
+                    )),
+                    CurrentTask::Patrolling {
+                        route_id: route_id.clone(),
+                    },
+                    RobotStatus::Active,
+                );
+            }
+            Command::Investigate { anomaly_id } => self.halt(
+                CurrentTask::Investigating {
+                    anomaly_id: anomaly_id.clone(),
+                },
+                RobotStatus::Active,
+            ),
+            // Intervals are applied by the publish scheduler
+            Command::Configure { config } => self.configure(config),
+            // The engine publishes for the simulated robots and has already
+            // queued the keyframe
+            Command::RequestKeyframe => {}
+            // Addressed to the engine or the environment simulation, never
+            // answered by robots
+            Command::InjectLeak { .. }
+            | Command::ClearLeak { .. }
+            | Command::RegisterSection { .. }
+            | Command::SetZoneMode { .. }
+            | Command::AssignAnomaly { .. }
+            | Command::UpdateAssignment { .. }
+            | Command::AcknowledgeAnomaly { .. }
+            | Command::ResolveAnomaly { .. }
+            | Command::StartMission { .. }
+            | Command::AbortMission { .. }
+            | Command::GetConfig => {}
+        }
+        Ok(())
+    }
+
+    /// Take the settings `config` sets
+    fn configure(&mut self, config: &RobotConfig) {
+        self.config.merge(config);
+        self.capabilities = self.capabilities.clone().configured(config);
+    }
+
+    /// Whether the robot's link is down, so it neither publishes nor hears
+    /// commands
+    fn is_silent(&self) -> bool {
+        self.faults.is_comm_suppressed()
+    }
+
+    /// Act on fault events and keep them for the engine's event log
+    fn log_fault_events(&mut self, events: Vec<FaultEvent>, now: u64) {
+        for event in events {
+            if event == FaultEvent::Recovered(FaultType::SensorFailure) {
+                // A rebooted robot comes back idle rather than resuming its task
+                self.halt(CurrentTask::None, RobotStatus::Idle);
+            }
+            info!(robot_id = %self.state.id, event = ?event, "Simulated fault");
+            let (kind, detail) = match event {
+                FaultEvent::Injected(fault_type) => (
+                    SystemEventKind::FaultStarted,
+                    format!("{fault_type:?} injected"),
+                ),
+                FaultEvent::RebootStarted => (
+                    SystemEventKind::FaultProgressed,
+                    "SensorFailure reboot started".into(),
+                ),
+                FaultEvent::MotorDegraded { speed_factor } => (
+                    SystemEventKind::FaultProgressed,
+                    format!("MotorFailure reset, running at {speed_factor} of full speed"),
+                ),
+                FaultEvent::Recovered(fault_type) => (
+                    SystemEventKind::FaultCleared,
+                    format!("{fault_type:?} recovered"),
+                ),
+            };
+            let subject = self.state.id.to_string();
+            self.events
+                .push(SystemEvent::new(kind, Some(&subject), detail, now));
+        }
+    }
+
+    /// Advance the robot's faults to `now`
+    fn tick_faults(&mut self, now: u64) {
+        let dt = Duration::from_millis(now.saturating_sub(self.last_step.unwrap_or(now)));
+        self.last_step = Some(now);
+        let events = self.faults.tick(&mut self.state, now, dt);
+        self.log_fault_events(events, now);
+    }
+
+    /// Set the velocity for the next motion step from the current activity
+    fn steer(&mut self, stations: &[ChargingStation], now: u64) {
+        if matches!(self.state.status, RobotStatus::Error | RobotStatus::Offline) {
+            self.state.velocity = Velocity::zero();
+            return;
+        }
+        match &mut self.activity {
+            Activity::Drifting => {}
+            Activity::Holding | Activity::Charging { .. } => self.state.velocity = Velocity::zero(),
+            Activity::Patrolling(follower) => follower.steer(&mut self.state),
+            Activity::MovingTo { target, speed } => {
+                if !steer_toward(&mut self.state, target, *speed) {
+                    self.halt(CurrentTask::None, RobotStatus::Idle);
+                }
+            }
+            Activity::Scanning { steps_left } => {
+                *steps_left = steps_left.saturating_sub(1);
+                if *steps_left == 0 {
+                    self.halt(CurrentTask::None, RobotStatus::Idle);
+                }
+            }
+            Activity::Docking { station } => {
+                let station = *station;
+                let dock = stations[station].position;
+                let within_reach = self.state.position.distance_to(&dock) <= DOCKING_RADIUS;
+                if !steer_toward(&mut self.state, &dock, self.cruise_speed) || within_reach {
+                    self.activity = Activity::Charging {
+                        station,
+                        plugged: false,
+                    };
+                    self.state.status = RobotStatus::Maintenance;
+                    // The dock is a known position
+                    self.state.position = dock;
+                    self.state.velocity = Velocity::zero();
+                    let events = self.faults.correct_position(&mut self.state);
+                    self.log_fault_events(events, now);
+                }
+            }
+        }
+    }
+
+    /// Slow the robot to its configured maximum speed
+    fn limit_speed(&mut self) {
+        let speed = self.state.velocity.magnitude();
+        if let Some(max_speed) = self.config.max_speed
+            && speed > max_speed
+        {
+            self.state.velocity *= max_speed / speed;
+        }
+    }
+
+    /// Drain or charge the battery for one motion step that covered
+    /// `distance`. `free_charger` tells a waiting robot whether its station
+    /// has a charger free.
+    fn tick_battery(&mut self, config: &BatteryConfig, distance: f64, free_charger: bool) {
+        let battery = &mut self.state.battery;
+        match &mut self.activity {
+            Activity::Charging { plugged, .. } if *plugged || free_charger => {
+                *plugged = true;
+                *battery = (*battery + config.charge_per_sec * MOTION_STEP_SECS)
+                    .min(limits::BATTERY_FULL_PERCENT);
+                if *battery >= config.resume_level {
+                    match self.resume.take() {
+                        Some(suspended) => {
+                            self.start(suspended.activity, suspended.task, RobotStatus::Active)
+                        }
+                        None => self.halt(CurrentTask::None, RobotStatus::Idle),
+                    }
+                }
+            }
+            _ => {
+                let scanning = matches!(self.state.current_task, CurrentTask::Scanning { .. });
+                *battery = (*battery - config.drain(distance, scanning, MOTION_STEP_SECS)).max(0.0);
+                if *battery == 0.0
+                    && !matches!(self.activity, Activity::Holding | Activity::Charging { .. })
+                {
+                    warn!(robot_id = %self.state.id, "Simulated robot ran out of battery");
+                    self.halt(CurrentTask::None, RobotStatus::Maintenance);
+                }
+            }
+        }
+        self.state.health = worse(self.faults.health(), config.health(self.state.battery));
+    }
+
+    /// Whether the battery is low enough to leave for a charger
+    fn needs_charge(&self, config: &BatteryConfig) -> bool {
+        let threshold = self
+            .config
+            .low_battery_threshold
+            .unwrap_or(config.low_threshold);
+        // Stopped, faulted, and flat robots stay where they are
+        self.state.battery < threshold
+            && self.state.battery > 0.0
+            && !self.is_charging()
+            && matches!(self.state.status, RobotStatus::Active | RobotStatus::Idle)
+    }
+}
+
+/// Commands robots act on, as opposed to engine administration
+fn addresses_robots(command: &Command) -> bool {
+    !matches!(
+        command,
+        Command::InjectLeak { .. }
+            | Command::ClearLeak { .. }
+            | Command::RegisterSection { .. }
+            | Command::SetZoneMode { .. }
+            | Command::AssignAnomaly { .. }
+            | Command::UpdateAssignment { .. }
+            | Command::AcknowledgeAnomaly { .. }
+            | Command::ResolveAnomaly { .. }
+            | Command::StartMission { .. }
+            | Command::AbortMission { .. }
+            | Command::GetConfig
+    )
+}
+
+/// Fault injection and clearing drive the simulation itself and reach a
+/// robot even while its link is down
+fn controls_simulation(command: &Command) -> bool {
+    matches!(
+        command,
+        Command::InjectFault { .. } | Command::ClearFault { .. }
+    )
+}
+
+/// The mock robots, moving on their own, acting on received commands,
+/// suffering injected faults, and leaving to charge when their battery runs
+/// low
+#[derive(Debug)]
+pub struct SimulatedFleet {
+    robots: Vec<SimRobot>,
+    routes: Vec<PatrolRoute>,
+    stations: Vec<ChargingStation>,
+    battery: BatteryConfig,
+    bounds: WorldBounds,
+    expiry: CommandExpiryConfig,
+}
+
+impl SimulatedFleet {
+    /// Robots patrolling one of `routes` follow it; the others keep their
+    /// initial velocity until commanded. A robot's cruise speed is the speed
+    /// it starts with, or `default_speed` if it starts stopped. Each robot's
+    /// faults recover under `recovery`, seeded per robot.
+    pub fn new(
+        robots: Vec<RobotState>,
+        routes: Vec<PatrolRoute>,
+        bounds: WorldBounds,
+        default_speed: f64,
+        recovery: RecoveryConfig,
+    ) -> Self {
+        Self {
+            robots: robots
+                .into_iter()
+                .enumerate()
+                .map(|(index, state)| {
+                    let recovery = RecoveryConfig {
+                        seed: recovery.seed.wrapping_add(index as u64),
+                        ..recovery.clone()
+                    };
+                    SimRobot::new(state, &routes, default_speed, recovery)
+                })
+                .collect(),
+            routes,
+            stations: Vec::new(),
+            battery: BatteryConfig::default(),
+            bounds,
+            expiry: CommandExpiryConfig::default(),
+        }
+    }
+
+    /// Charging stations and the battery model. Without stations, robots
+    /// drain until they stop.
+    pub fn with_charging(mut self, stations: Vec<ChargingStation>, battery: BatteryConfig) -> Self {
+        self.stations = stations;
+        self.battery = battery;
+        self
+    }
+
+    /// How late commands may arrive and still be carried out
+    pub fn with_command_expiry(mut self, expiry: CommandExpiryConfig) -> Self {
+        self.expiry = expiry;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.robots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.robots.is_empty()
+    }
+
+    pub fn robot(&self, index: usize) -> &RobotState {
+        &self.robots[index].state
+    }
+
+    pub fn find(&self, robot_id: &str) -> Option<&RobotState> {
+        self.robots
+            .iter()
+            .map(|robot| &robot.state)
+            .find(|state| state.id == robot_id)
+    }
+
+    /// Whether a robot's link is down: it publishes nothing and ignores
+    /// commands other than fault injection and clearing
+    pub fn is_silent(&self, index: usize) -> bool {
+        self.robots[index].is_silent()
+    }
+
+    /// Settings a robot has been configured with, merged
+    pub fn config(&self, index: usize) -> &RobotConfig {
+        &self.robots[index].config
+    }
+
+    /// Index of the robot with the given ID
+    pub fn index_of(&self, robot_id: &str) -> Option<usize> {
+        self.robots
+            .iter()
+            .position(|robot| robot.state.id == robot_id)
+    }
+
+    /// Configure a robot as a `Configure` command would, without a
+    /// response; `false` if there is no such robot
+    pub fn configure(&mut self, robot_id: &str, config: &RobotConfig) -> bool {
+        match self.index_of(robot_id) {
+            Some(index) => {
+                self.robots[index].configure(config);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Faults currently affecting a robot
+    pub fn faults(&self, index: usize) -> &RobotFaults {
+        &self.robots[index].faults
+    }
+
+    /// Station a robot is docking at or charging at
+    pub fn charging_at(&self, index: usize) -> Option<&ChargingStation> {
+        match self.robots[index].activity {
+            Activity::Docking { station } | Activity::Charging { station, .. } => {
+                Some(&self.stations[station])
+            }
+            _ => None,
+        }
+    }
+
+    /// Robots docking at or charging at each station
+    fn station_load(&self) -> Vec<u32> {
+        let mut load = vec![0; self.stations.len()];
+        for robot in &self.robots {
+            if let Activity::Docking { station } | Activity::Charging { station, .. } =
+                robot.activity
+            {
+                load[station] += 1;
+            }
+        }
+        load
+    }
+
+    /// Move one robot by a motion step at `now` (Unix ms), halting it if it
+    /// would leave the world bounds, then drain or charge its battery
+    pub fn step(&mut self, index: usize, now: u64) -> Option<BoundsEscape> {
+        let free_charger = match self.robots[index].activity {
+            Activity::Charging { station, .. } => {
+                let plugged = self
+                    .robots
+                    .iter()
+                    .filter(|robot| {
+                        robot.activity
+                            == Activity::Charging {
+                                station,
+                                plugged: true,
+                            }
+                    })
+                    .count();
+                (plugged as u32) < self.stations[station].capacity
+            }
+            _ => false,
+        };
+        let load = self.station_load();
+
+        let robot = &mut self.robots[index];
+        robot.steer(&self.stations, now);
+        robot.tick_faults(now);
+        robot.limit_speed();
+        robot.state.velocity *= robot.faults.speed_factor();
+        let (position, mut escape) = advance_robot(&mut robot.state, &self.bounds);
+        let distance = robot.state.position.distance_to(&position);
+        robot.state.position = position;
+        if let Some(escape) = &mut escape {
+            robot.activity = Activity::Holding;
+            escape.last_command = robot.last_command.clone();
+        }
+        robot.tick_battery(&self.battery, distance, free_charger);
+
+        if robot.needs_charge(&self.battery) {
+            let world = World {
+                routes: &self.routes,
+                stations: &self.stations,
+                load,
+            };
+            if let Some(station) = world.station_for(&robot.state.position) {
+                info!(
+                    robot_id = %robot.state.id,
+                    battery = robot.state.battery,
+                    station = %self.stations[station].id,
+                    "Simulated robot leaving to charge"
+                );
+                let suspended = Suspended {
+                    activity: robot.activity.clone(),
+                    task: robot.state.current_task.clone(),
+                };
+                robot.dock(station, Some(suspended));
+            }
+        }
+        escape
+    }
+
+    /// Fault events since the last call, for the engine's event log
+    pub fn take_events(&mut self) -> Vec<SystemEvent> {
+        self.robots
+            .iter_mut()
+            .flat_map(|robot| std::mem::take(&mut robot.events))
+            .collect()
+    }
+
+    /// Apply a received command to the robots it addresses, one response
+    /// per robot. A command for an unknown robot is answered with an error;
+    /// engine administration commands, and robots whose link is down, are
+    /// not answered. A broadcast reaches only the robots able to carry it
+    /// out. An expired command is refused by every robot it reaches.
+    pub fn apply(&mut self, received: &ReceivedCommand, now: u64) -> Vec<CommandResponse> {
+        if !addresses_robots(&received.command) {
+            return Vec::new();
+        }
+        let expired = self
+            .expiry
+            .verify(&received.command, received.expires_at, now)
+            .err();
+        let respond = |robot_id: &RobotId, outcome: Result<(), String>| CommandResponse {
+            command_id: received.command_id.clone(),
+            robot_id: robot_id.clone(),
+            success: outcome.is_ok(),
+            error: outcome.err(),
+            config: None,
+            status: None,
+            timestamp: now,
+        };
+        let reachable =
+            |robot: &SimRobot| !robot.is_silent() || controls_simulation(&received.command);
+        let world = World {
+            routes: &self.routes,
+            stations: &self.stations,
+            load: self.station_load(),
+        };
+        let mut responses = Vec::new();
+        match &received.target {
+            CommandTarget::Broadcast => {
+                for robot in self.robots.iter_mut() {
+                    // A broadcast only addresses the robots built for it
+                    if !reachable(robot) || robot.capabilities.check(&received.command).is_err() {
+                        continue;
+                    }
+                    let outcome = match expired {
+                        Some(expired) => Err(expired.to_string()),
+                        None => robot.handle_received(received, &world, now),
+                    };
+                    // The link may have just gone down
+                    if !robot.is_silent() {
+                        responses.push(respond(&robot.state.id, outcome));
+                    }
+                }
+            }
+            CommandTarget::Robot(robot_id) => {
+                match self.robots.iter_mut().find(|r| &r.state.id == robot_id) {
+                    Some(robot) if reachable(robot) => {
+                        let outcome = match expired {
+                            Some(expired) => Err(expired.to_string()),
+                            None => robot.handle_received(received, &world, now),
+                        };
+                        if !robot.is_silent() {
+                            responses.push(respond(robot_id, outcome));
+                        }
+                    }
+                    Some(_) => {}
+                    None => {
+                        responses.push(respond(robot_id, Err(format!("unknown robot {robot_id}"))))
+                    }
+                }
+            }
+        }
+        responses
+    }
+}
+
+// ============================================================================
+// SIMULATION TASK
+// ============================================================================
+
+/// Whether the simulation publishes: only while the broker connection is
+/// up, and only on the instance leading when engines elect one
+pub(crate) fn publishing(mqtt: &AetherisMqtt) -> bool {
+    mqtt.connection_state() == ConnectionState::Connected && mqtt.is_leader()
+}
+
+/// Spawns the mock fleet publisher driven by a [`PublishScheduler`].
+///
+/// Commands sent on the returned channel are applied to the fleet between
+/// publishes and answered on the responses topic. Telemetry and heartbeats
+/// are not published while the broker connection is down, and nothing is
+/// published by an engine instance following another; its robots keep
+/// moving, ready for a takeover. On shutdown every robot reports itself offline on its
+/// telemetry topic before the task ends.
+pub fn spawn_fleet_simulation(
+    mqtt: Arc<AetherisMqtt>,
+    mut fleet: SimulatedFleet,
+    timing: SimulationTiming,
+    mut shutdown: Shutdown,
+) -> (mpsc::Sender<ReceivedCommand>, JoinHandle<()>) {
+    let (command_tx, mut commands) = mpsc::channel(64);
+    let task = tokio::spawn(async move {
+        let clock = mqtt.clock();
+        let mut scheduler = PublishScheduler::new(fleet.len(), timing);
+        for index in 0..fleet.len() {
+            schedule_configured(&mut scheduler, fleet.config(index), index);
+        }
+        // The schedule is in simulated time, slept through in real time
+        let start = Instant::now();
+        let started = clock.instant();
+        let mut next = scheduler.next_publish();
+
+        while let Some(publish) = next {
+            tokio::select! {
+                Some(received) = commands.recv() => {
+                    let responses = fleet.apply(&received, clock.now().as_millis());
+                    record_events(&mqtt, fleet.take_events()).await;
+                    if let Command::Configure { .. } = received.command {
+                        for response in responses.iter().filter(|r| r.success) {
+                            if let Some(index) = fleet.index_of(&response.robot_id) {
+                                schedule_configured(&mut scheduler, fleet.config(index), index);
+                            }
+                        }
+                    }
+                    // Followers apply the command too, but the leader answers
+                    if mqtt.is_leader() {
+                        for response in responses {
+                            if let Err(e) = mqtt.publish_response(&response).await {
+                                error!("Failed to publish command response: {}", e);
+                            }
+                        }
+                    }
+                    continue;
+                }
+                _ = sleep_until(start + clock.real(publish.at)) => {}
+                _ = shutdown.wait() => {
+                    announce_offline(&mqtt, &fleet).await;
+                    return;
+                }
+            }
+            next = scheduler.next_publish();
+
+            match publish.kind {
+                PublishKind::Telemetry => {
+                    let now = clock.now().as_millis();
+                    let Some(robot_state) =
+                        step_robot(&mqtt, &mut fleet, publish.robot_index, now).await
+                    else {
+                        continue;
+                    };
+                    if let Err(e) = mqtt.publish_telemetry(&robot_state).await {
+                        error!("Failed to publish telemetry: {}", e);
+                    }
+                }
+                PublishKind::TelemetryBatch => {
+                    let now = clock.now().as_millis();
+                    let mut states = Vec::with_capacity(fleet.len());
+                    for index in 0..fleet.len() {
+                        states.extend(step_robot(&mqtt, &mut fleet, index, now).await);
+                    }
+                    if states.is_empty() {
+                        continue;
+                    }
+                    let batch = TelemetryBatch::new(mqtt.instance_id(), states);
+                    if let Err(e) = mqtt.publish_telemetry_batch(&batch).await {
+                        error!("Failed to publish telemetry batch: {}", e);
+                    }
+                }
+                PublishKind::Heartbeat => {
+                    if fleet.is_silent(publish.robot_index) || !publishing(&mqtt) {
+                        continue;
+                    }
+                    let robot = fleet.robot(publish.robot_index);
+                    let heartbeat = Heartbeat {
+                        timestamp: clock.now(),
+                        ..Heartbeat::new(
+                            robot.id.clone(),
+                            robot.robot_type,
+                            robot.status,
+                            robot.battery,
+                            robot.signal,
+                            (clock.instant() - started).as_secs(),
+                        )
+                    };
+                    if let Err(e) = mqtt.publish_heartbeat(&heartbeat).await {
+                        error!("Failed to publish heartbeat: {}", e);
+                    }
+                }
+            }
+        }
+    });
+    (command_tx, task)
+}
+
+/// Publish a robot's telemetry every scan interval and its heartbeats
+/// every heartbeat interval it is configured with
+fn schedule_configured(scheduler: &mut PublishScheduler, config: &RobotConfig, index: usize) {
+    let interval = |secs: Option<u32>| secs.map(|secs| Duration::from_secs(secs.into()));
+    scheduler.set_interval(
+        index,
+        PublishKind::Telemetry,
+        interval(config.scan_interval),
+    );
+    scheduler.set_interval(
+        index,
+        PublishKind::Heartbeat,
+        interval(config.heartbeat_interval),
+    );
+}
+
+/// Move one simulated robot a tick and return the state it reports, if it
+/// reports at all. Robots keep moving while the broker is away or another
+/// instance leads; only their reports pause rather than queue up.
+async fn step_robot(
+    mqtt: &AetherisMqtt,
+    fleet: &mut SimulatedFleet,
+    index: usize,
+    now: u64,
+) -> Option<RobotState> {
+    let escape = fleet.step(index, now);
+    // Halts and faults are recorded even when nobody hears of them
+    let mut events = fleet.take_events();
+    if let Some(escape) = &escape {
+        warn!(robot_id = %escape.robot_id, task = ?escape.task, "Simulated robot left the world bounds, halting");
+        events.push(escape.to_event(now));
+    }
+    record_events(mqtt, events).await;
+    if fleet.is_silent(index) || !publishing(mqtt) {
+        return None;
+    }
+    let robot = fleet.robot(index);
+    if let Some(escape) = escape
+        && let Err(e) = mqtt.publish_alert(&escape.to_report(robot.position)).await
+    {
+        error!("Failed to publish bounds escape: {}", e);
+    }
+    let mut robot_state = robot.clone();
+    robot_state.timestamp = Timestamp::from_millis(now);
+    Some(robot_state)
+}
+
+/// Add events from the simulated fleet to the engine's event log
+async fn record_events(mqtt: &AetherisMqtt, events: Vec<SystemEvent>) {
+    if events.is_empty() {
+        return;
+    }
+    let log = mqtt.events();
+    let mut log = log.write().await;
+    for event in events {
+        log.record(event);
+    }
+}
+
+/// Publish every simulated robot as offline, so dashboards do not wait for
+/// the heartbeat timeout
+async fn announce_offline(mqtt: &AetherisMqtt, fleet: &SimulatedFleet) {
+    if !publishing(mqtt) {
+        return;
+    }
+    let now = mqtt.clock().now().as_millis();
+    for index in 0..fleet.len() {
+        let mut robot_state = fleet.robot(index).clone();
+        robot_state.status = RobotStatus::Offline;
+        robot_state.timestamp = Timestamp::from_millis(now);
+        if let Err(e) = mqtt.publish_telemetry(&robot_state).await {
+            error!(robot_id = %robot_state.id, "Failed to publish offline telemetry: {}", e);
+        }
+    }
+    info!(robots = fleet.len(), "Simulated robots announced offline");
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetheris_shared::HealthStatus;
+
+    use crate::DEFAULT_HEARTBEAT_TIMEOUT;
+
+    #[test]
+    fn test_publish_rate_is_smoothed_across_large_fleet() {
+        let robots = 100;
+        let mut scheduler = PublishScheduler::new(robots, SimulationTiming::default());
+        let horizon = Duration::from_secs(60);
+
+        let mut buckets = vec![0u32; 600];
+        let mut per_robot_heartbeats = vec![Vec::new(); robots];
+        while let Some(publish) = scheduler.next_publish() {
+            if publish.at >= horizon {
+                break;
+            }
+            buckets[(publish.at.as_millis() / 100) as usize] += 1;
+            if publish.kind == PublishKind::Heartbeat {
+                per_robot_heartbeats[publish.robot_index].push(publish.at);
+            }
+        }
+
+        // 100 robots at 1 Hz telemetry + 0.2 Hz heartbeat average 12 publishes
+        // per 100 ms; a synchronized tick would put 200 in a single window.
+        let peak = *buckets.iter().max().unwrap();
+        assert!(peak <= 30, "peak of {peak} publishes in one 100 ms window");
+
+        // Every robot still heartbeats at the nominal rate
+        for heartbeats in &per_robot_heartbeats {
+            assert!((11..=13).contains(&heartbeats.len()));
+        }
+    }
+
+    #[test]
+    fn test_heartbeat_gaps_never_exceed_worst_case() {
+        let timing = SimulationTiming {
+            jitter_fraction: 0.3,
+            ..SimulationTiming::default()
+        };
+        let worst_gap = timing.worst_heartbeat_gap();
+        let mut scheduler = PublishScheduler::new(10, timing);
+        let mut last_seen = [None; 10];
+
+        for _ in 0..5_000 {
+            let publish = scheduler.next_publish().unwrap();
+            if publish.kind != PublishKind::Heartbeat {
+                continue;
+            }
+            if let Some(previous) = last_seen[publish.robot_index] {
+                assert!(publish.at - previous <= worst_gap);
+            }
+            last_seen[publish.robot_index] = Some(publish.at);
+        }
+    }
+
+    #[test]
+    fn test_batched_telemetry_is_one_publish_per_interval() {
+        let timing = SimulationTiming {
+            batch_telemetry: true,
+            ..SimulationTiming::default()
+        };
+        let mut scheduler = PublishScheduler::new(60, timing);
+        let mut batches = 0;
+        loop {
+            let publish = scheduler.next_publish().unwrap();
+            if publish.at >= Duration::from_secs(10) {
+                break;
+            }
+            assert_ne!(publish.kind, PublishKind::Telemetry);
+            if publish.kind == PublishKind::TelemetryBatch {
+                batches += 1;
+            }
+        }
+        // Jitter moves ticks by at most 10%
+        assert!((10..=11).contains(&batches), "{batches} batches");
+    }
+
+    #[test]
+    fn test_schedule_is_reproducible_for_a_seed() {
+        let mut a = PublishScheduler::new(5, SimulationTiming::default());
+        let mut b = PublishScheduler::new(5, SimulationTiming::default());
+        for _ in 0..100 {
+            assert_eq!(a.next_publish(), b.next_publish());
+        }
+    }
+
+    /// One telemetry tick of a route-following robot
+    fn tick(follower: &mut RouteFollower, robot: &mut RobotState) {
+        follower.steer(robot);
+        let (position, escape) = advance_robot(robot, &WorldBounds::default());
+        assert!(escape.is_none());
+        robot.position = position;
+    }
+
+    #[test]
+    fn test_robot_drives_route_waypoint_by_waypoint() {
+        use aetheris_shared::{RouteMode, ScanType, Waypoint};
+
+        let route = PatrolRoute::new(
+            "ROUTE-T",
+            "Test",
+            RouteMode::OneShot,
+            vec![
+                Waypoint::at(Position::origin()),
+                Waypoint {
+                    position: Position::new(1.0, 0.0, 0.0),
+                    dwell_secs: Some(0.5),
+                    scan: Some(ScanType::Thermal),
+                },
+                Waypoint::at(Position::new(1.0, 2.0, 0.0)),
+            ],
+        );
+        let mut robot = RobotState {
+            velocity: Velocity::new(2.0, 0.0, 0.0),
+            current_task: CurrentTask::Patrolling {
+                route_id: "ROUTE-T".into(),
+            },
+            ..RobotState::new(
+                "RV-009".parse().unwrap(),
+                "Rover",
+                aetheris_shared::RobotType::Rover,
+            )
+        };
+        let mut follower = RouteFollower::new(route, &robot.position, 2.0);
+
+        // 2 m/s covers 0.2 m per tick: five ticks to the first stop
+        for _ in 0..5 {
+            tick(&mut follower, &mut robot);
+        }
+        assert!(robot.position.distance_to(&Position::new(1.0, 0.0, 0.0)) < 1e-9);
+        tick(&mut follower, &mut robot);
+        assert_eq!(
+            robot.current_task,
+            CurrentTask::Scanning {
+                scan_type: ScanType::Thermal
+            }
+        );
+        for _ in 0..5 {
+            tick(&mut follower, &mut robot);
+        }
+        assert!(matches!(robot.current_task, CurrentTask::Patrolling { .. }));
+        assert!((robot.orientation.yaw - std::f64::consts::FRAC_PI_2).abs() < 1e-9);
+
+        for _ in 0..20 {
+            tick(&mut follower, &mut robot);
+        }
+        assert!(follower.is_finished());
+        assert!(robot.position.distance_to(&Position::new(1.0, 2.0, 0.0)) < 1e-9);
+        assert_eq!(robot.current_task, CurrentTask::None);
+        assert_eq!(robot.status, RobotStatus::Idle);
+    }
+
+    #[test]
+    fn test_looping_route_wraps_and_degenerate_route_holds() {
+        use aetheris_shared::{RouteMode, Waypoint};
+
+        let triangle = PatrolRoute::new(
+            "ROUTE-L",
+            "Loop",
+            RouteMode::Loop,
+            [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]
+                .into_iter()
+                .map(|(x, y)| Waypoint::at(Position::new(x, y, 0.0)))
+                .collect(),
+        );
+        let mut robot = RobotState::new(
+            "DR-009".parse().unwrap(),
+            "Drone",
+            aetheris_shared::RobotType::Drone,
+        );
+        let mut follower = RouteFollower::new(triangle.clone(), &robot.position, 10.0);
+        let mut visited = Vec::new();
+        for _ in 0..12 {
+            tick(&mut follower, &mut robot);
+            if visited.last() != follower.target().as_ref() {
+                visited.extend(follower.target());
+            }
+        }
+        assert_eq!(visited[..4], [1, 2, 0, 1]);
+
+        // Every waypoint at the robot's position: hold instead of spinning
+        let point = PatrolRoute::new(
+            "ROUTE-P",
+            "Point",
+            RouteMode::Loop,
+            vec![Waypoint::at(Position::origin()); 2],
+        );
+        let mut follower = RouteFollower::new(point, &Position::origin(), 1.0);
+        tick(&mut follower, &mut robot);
+        robot.position = Position::origin();
+        tick(&mut follower, &mut robot);
+        assert_eq!(robot.velocity, Velocity::zero());
+    }
+
+    const T0: u64 = 1_000_000;
+
+    fn received(target: CommandTarget, command: Command) -> ReceivedCommand {
+        ReceivedCommand {
+            command,
+            source: "dashboard".into(),
+            target,
+            command_id: "CMD-1".into(),
+            expires_at: None,
+        }
+    }
+
+    fn to(robot_id: &str) -> CommandTarget {
+        CommandTarget::Robot(robot_id.parse().unwrap())
+    }
+
+    #[test]
+    fn test_expired_commands_are_refused_but_stops_are_not() {
+        let mut fleet = SimulatedFleet::new(
+            crate::create_mock_fleet(),
+            crate::create_mock_routes(),
+            WorldBounds::default(),
+            1.0,
+            RecoveryConfig::default(),
+        );
+        let rover = (0..fleet.len())
+            .find(|&i| fleet.robot(i).id == "RV-002")
+            .unwrap();
+        let task = fleet.robot(rover).current_task.clone();
+        let late = |command| ReceivedCommand {
+            expires_at: Some(T0),
+            ..received(to("RV-002"), command)
+        };
+
+        let move_to = Command::MoveTo {
+            target: Position::new(2.0, 1.0, -1.0),
+            speed: None,
+        };
+        let responses = fleet.apply(&late(move_to), T0 + 600_000);
+        assert!(!responses[0].success);
+        assert!(responses[0].error.as_ref().unwrap().starts_with("expired"));
+        assert_eq!(fleet.robot(rover).current_task, task);
+
+        let responses = fleet.apply(&late(Command::Stop), T0 + 600_000);
+        assert!(responses[0].success);
+    }
+
+    #[test]
+    fn test_simulated_robots_act_on_commands_and_respond() {
+        let mut fleet = SimulatedFleet::new(
+            crate::create_mock_fleet(),
+            crate::create_mock_routes(),
+            WorldBounds::default(),
+            1.0,
+            RecoveryConfig::default(),
+        );
+        let rover = (0..fleet.len())
+            .find(|&i| fleet.robot(i).id == "RV-002")
+            .unwrap();
+        let target = Position::new(2.0, 1.0, -1.0);
+
+        let responses = fleet.apply(
+            &received(
+                to("RV-002"),
+                Command::MoveTo {
+                    target,
+                    speed: Some(2.0),
+                },
+            ),
+            1_000,
+        );
+        assert_eq!(responses.len(), 1);
+        assert!(responses[0].success);
+        assert_eq!(responses[0].command_id, "CMD-1");
+        assert_eq!(
+            fleet.robot(rover).current_task,
+            CurrentTask::MovingTo { target }
+        );
+
+        // 1 m at 2 m/s: five steps there, one more to notice the arrival
+        for _ in 0..6 {
+            fleet.step(rover, 0);
+        }
+        assert!(fleet.robot(rover).position.distance_to(&target) < 1e-9);
+        assert_eq!(fleet.robot(rover).current_task, CurrentTask::None);
+        assert_eq!(fleet.robot(rover).status, RobotStatus::Idle);
+
+        fleet.apply(
+            &received(
+                to("RV-002"),
+                Command::PerformScan {
+                    scan_type: aetheris_shared::ScanType::Visual,
+                },
+            ),
+            2_000,
+        );
+        for _ in 0..SCAN_STEPS - 1 {
+            fleet.step(rover, 0);
+            assert!(matches!(
+                fleet.robot(rover).current_task,
+                CurrentTask::Scanning { .. }
+            ));
+        }
+        fleet.step(rover, 0);
+        assert_eq!(fleet.robot(rover).current_task, CurrentTask::None);
+
+        let unknown = fleet.apply(&received(to("RV-404"), Command::Stop), 3_000);
+        assert!(!unknown[0].success);
+        assert_eq!(unknown[0].robot_id, "RV-404");
+        assert!(
+            unknown[0]
+                .error
+                .as_deref()
+                .unwrap()
+                .contains("unknown robot")
+        );
+
+        let bad_route = fleet.apply(
+            &received(
+                to("RV-002"),
+                Command::StartPatrol {
+                    route_id: "ROUTE-NOPE".into(),
+                },
+            ),
+            3_000,
+        );
+        assert!(!bad_route[0].success);
+    }
+
+    #[test]
+    fn test_broadcast_emergency_stop_halts_every_robot() {
+        let mut fleet = SimulatedFleet::new(
+            crate::create_mock_fleet(),
+            crate::create_mock_routes(),
+            WorldBounds::default(),
+            1.0,
+            RecoveryConfig::default(),
+        );
+        let responses = fleet.apply(
+            &received(CommandTarget::Broadcast, Command::EmergencyStop),
+            1_000,
+        );
+        assert_eq!(responses.len(), fleet.len());
+        assert!(responses.iter().all(|r| r.success));
+
+        for index in 0..fleet.len() {
+            let before = fleet.robot(index).position;
+            fleet.step(index, 0);
+            let robot = fleet.robot(index);
+            assert_eq!(robot.position, before, "{} moved", robot.id);
+            assert_eq!(robot.status, RobotStatus::Maintenance);
+        }
+
+        // Engine administration is not for robots to answer
+        let admin = fleet.apply(
+            &received(
+                CommandTarget::Broadcast,
+                Command::ResolveAnomaly {
+                    anomaly_id: "ANM-1".into(),
+                    resolution: aetheris_shared::Resolution::Fixed,
+                    force: false,
+                },
+            ),
+            2_000,
+        );
+        assert!(admin.is_empty());
+    }
+
+    #[test]
+    fn test_commands_beyond_a_robot_are_refused_or_skipped() {
+        let mut fleet = SimulatedFleet::new(
+            crate::create_mock_fleet(),
+            crate::create_mock_routes(),
+            WorldBounds::default(),
+            1.0,
+            RecoveryConfig::default(),
+        );
+        let ultrasonic = Command::PerformScan {
+            scan_type: aetheris_shared::ScanType::Ultrasonic,
+        };
+
+        // Only the crawlers are addressed by the broadcast
+        let responses = fleet.apply(
+            &received(CommandTarget::Broadcast, ultrasonic.clone()),
+            1_000,
+        );
+        let answered: Vec<_> = responses.iter().map(|r| r.robot_id.as_str()).collect();
+        assert_eq!(answered, ["CR-001", "CR-002"]);
+
+        let refused = fleet.apply(&received(to("RV-001"), ultrasonic.clone()), 2_000);
+        assert!(!refused[0].success);
+        assert_eq!(
+            refused[0].error.as_deref(),
+            Some("rover cannot perform Ultrasonic scans")
+        );
+        let flight = fleet.apply(
+            &received(
+                to("CR-001"),
+                Command::StartPatrol {
+                    route_id: "ROUTE-AIR-1".into(),
+                },
+            ),
+            3_000,
+        );
+        assert!(!flight[0].success);
+
+        // A special unit configured with the probe
+        let configure = Command::Configure {
+            config: aetheris_shared::RobotConfig {
+                supported_scans: Some(vec![aetheris_shared::ScanType::Ultrasonic]),
+                ..Default::default()
+            },
+        };
+        assert!(fleet.apply(&received(to("RV-001"), configure), 4_000)[0].success);
+        assert!(fleet.apply(&received(to("RV-001"), ultrasonic), 5_000)[0].success);
+    }
+
+    #[test]
+    fn test_configured_speed_and_intervals_take_effect() {
+        let mut fleet = SimulatedFleet::new(
+            crate::create_mock_fleet(),
+            crate::create_mock_routes(),
+            WorldBounds::default(),
+            1.0,
+            RecoveryConfig::default(),
+        );
+        let rover = fleet.index_of("RV-001").unwrap();
+        let configure = |config| received(to("RV-001"), Command::Configure { config });
+        fleet.apply(
+            &configure(RobotConfig {
+                max_speed: Some(0.5),
+                ..RobotConfig::default()
+            }),
+            T0,
+        );
+        fleet.apply(
+            &configure(RobotConfig {
+                scan_interval: Some(3),
+                ..RobotConfig::default()
+            }),
+            T0,
+        );
+        // The second update keeps the speed limit of the first
+        assert_eq!(fleet.config(rover).max_speed, Some(0.5));
+
+        let move_to = Command::MoveTo {
+            target: Position::new(50.0, 0.0, 50.0),
+            speed: None,
+        };
+        assert!(fleet.apply(&received(to("RV-001"), move_to), T0)[0].success);
+        fleet.step(rover, T0 + 100);
+        let speed = fleet.robot(rover).velocity.magnitude();
+        assert!(speed > 0.0 && speed <= 0.5 + 1e-9, "moving at {speed} m/s");
+
+        let timing = SimulationTiming {
+            jitter_fraction: 0.0,
+            ..SimulationTiming::default()
+        };
+        let mut scheduler = PublishScheduler::new(fleet.len(), timing);
+        for index in 0..fleet.len() {
+            schedule_configured(&mut scheduler, fleet.config(index), index);
+        }
+        let mut telemetry = vec![0; fleet.len()];
+        while let Some(publish) = scheduler.next_publish() {
+            if publish.at >= Duration::from_secs(30) {
+                break;
+            }
+            if publish.kind == PublishKind::Telemetry {
+                telemetry[publish.robot_index] += 1;
+            }
+        }
+        assert_eq!(telemetry[rover], 10);
+        assert!(
+            (0..fleet.len())
+                .filter(|&i| i != rover)
+                .all(|i| telemetry[i] == 30)
+        );
+    }
+
+    fn inject(robot_id: &str, fault_type: FaultType) -> ReceivedCommand {
+        received(to(robot_id), Command::InjectFault { fault_type })
+    }
+
+    #[test]
+    fn test_injected_faults_show_in_published_state() {
+        let mut fleet = SimulatedFleet::new(
+            crate::create_mock_fleet(),
+            crate::create_mock_routes(),
+            WorldBounds::default(),
+            1.0,
+            RecoveryConfig::default(),
+        );
+        let index = |fleet: &SimulatedFleet, id: &str| {
+            (0..fleet.len()).find(|&i| fleet.robot(i).id == id).unwrap()
+        };
+        let (rover, drone, crawler) = (
+            index(&fleet, "RV-001"),
+            index(&fleet, "DR-001"),
+            index(&fleet, "CR-001"),
+        );
+
+        fleet.apply(&inject("RV-001", FaultType::LowBattery), T0);
+        fleet.apply(&inject("DR-001", FaultType::MotorFailure), T0);
+        fleet.apply(&inject("CR-001", FaultType::SensorFailure), T0);
+        let drone_at = fleet.robot(drone).position;
+        for i in [rover, drone, crawler] {
+            fleet.step(i, T0 + 100);
+        }
+
+        let low = fleet.robot(rover);
+        assert!(low.battery < 15.0);
+        assert_eq!(low.health, HealthStatus::Warning);
+        let stuck = fleet.robot(drone);
+        assert_eq!(stuck.position, drone_at);
+        assert_eq!(stuck.velocity, Velocity::zero());
+        assert_eq!(stuck.status, RobotStatus::Maintenance);
+        assert_eq!(fleet.robot(crawler).status, RobotStatus::Error);
+        let refused = fleet.apply(&received(to("CR-001"), Command::ReturnToBase), T0 + 200);
+        assert!(!refused[0].success);
+
+        // Clearing restores the demo
+        fleet.apply(
+            &received(to("DR-001"), Command::ClearFault { fault_type: None }),
+            T0 + 300,
+        );
+        assert!(fleet.faults(drone).active().is_empty());
+        assert_eq!(fleet.robot(drone).health, HealthStatus::Optimal);
+        assert_eq!(fleet.robot(drone).status, RobotStatus::Active);
+    }
+
+    #[test]
+    fn test_comm_dropout_silences_robot_until_it_ends() {
+        let mut fleet = SimulatedFleet::new(
+            crate::create_mock_fleet(),
+            Vec::new(),
+            WorldBounds::default(),
+            1.0,
+            RecoveryConfig::default(),
+        );
+        fleet.apply(&inject("RV-002", FaultType::CommDropout), T0);
+        let rover = (0..fleet.len())
+            .find(|&i| fleet.robot(i).id == "RV-002")
+            .unwrap();
+        assert!(fleet.is_silent(rover));
+        assert!(
+            fleet
+                .apply(&received(to("RV-002"), Command::Stop), T0 + 1_000)
+                .is_empty()
+        );
+        let broadcast = fleet.apply(&received(CommandTarget::Broadcast, Command::Stop), T0);
+        assert_eq!(broadcast.len(), fleet.len() - 1);
+
+        // The longest configured dropout has passed
+        fleet.step(rover, T0 + 60_000);
+        assert!(!fleet.is_silent(rover));
+    }
+
+    #[test]
+    fn test_gps_drift_jitters_position() {
+        let fleet = || {
+            SimulatedFleet::new(
+                crate::create_mock_fleet(),
+                Vec::new(),
+                WorldBounds::default(),
+                1.0,
+                RecoveryConfig::default(),
+            )
+        };
+        let (mut clean, mut drifting) = (fleet(), fleet());
+        drifting.apply(&inject("RV-001", FaultType::GpsDrift), T0);
+        for tick in 1..=5 {
+            clean.step(0, T0 + tick * 100);
+            drifting.step(0, T0 + tick * 100);
+        }
+        assert_ne!(clean.robot(0).position, drifting.robot(0).position);
+        assert!(
+            clean
+                .robot(0)
+                .position
+                .distance_to(&drifting.robot(0).position)
+                < 5.0 * 0.5 * 2.0
+        );
+
+        drifting.apply(
+            &received(
+                to("RV-001"),
+                Command::ClearFault {
+                    fault_type: Some(FaultType::GpsDrift),
+                },
+            ),
+            T0 + 600,
+        );
+        assert!(!drifting.faults(0).has(FaultType::GpsDrift));
+    }
+
+    #[test]
+    fn test_position_fix_ends_gps_drift() {
+        let mut fleet = SimulatedFleet::new(
+            crate::create_mock_fleet(),
+            Vec::new(),
+            WorldBounds::default(),
+            1.0,
+            RecoveryConfig::default(),
+        );
+        fleet.apply(&inject("RV-001", FaultType::GpsDrift), T0);
+        fleet.step(0, T0 + 100);
+        let responses = fleet.apply(&received(to("RV-001"), Command::CorrectPosition), T0 + 200);
+        assert!(responses[0].success);
+        assert!(!fleet.faults(0).has(FaultType::GpsDrift));
+
+        // Both ends of the fault are events, tied to the commands behind them
+        let events: Vec<_> = fleet
+            .take_events()
+            .into_iter()
+            .map(|event| (event.kind, event.subject.unwrap(), event.command_id))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                (
+                    SystemEventKind::FaultStarted,
+                    "RV-001".to_string(),
+                    Some("CMD-1".to_string())
+                ),
+                (
+                    SystemEventKind::FaultCleared,
+                    "RV-001".to_string(),
+                    Some("CMD-1".to_string())
+                ),
+            ]
+        );
+        assert!(fleet.take_events().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_docking_ends_gps_drift_and_is_recorded() {
+        let (tx, _rx) = mpsc::channel(10);
+        let (mqtt, _eventloop) = AetherisMqtt::new(crate::MqttConfig::default(), tx)
+            .await
+            .unwrap();
+        let rover = crate::create_mock_fleet()
+            .into_iter()
+            .find(|r| r.id == "RV-002")
+            .unwrap();
+        let dock = Position::new(rover.position.x - 2.0, rover.position.y, rover.position.z);
+        let mut fleet = SimulatedFleet::new(
+            vec![rover],
+            Vec::new(),
+            WorldBounds::default(),
+            1.0,
+            RecoveryConfig::default(),
+        )
+        .with_charging(
+            vec![ChargingStation::new("CHG-01", dock, 1)],
+            BatteryConfig::default(),
+        );
+        fleet.apply(&inject("RV-002", FaultType::GpsDrift), T0);
+        fleet.apply(&received(to("RV-002"), Command::ReturnToBase), T0);
+
+        assert_eq!(fleet.charging_at(0).unwrap().id, "CHG-01");
+        let mut steps = 0;
+        while fleet.charging_at(0).is_some() {
+            step_robot(&mqtt, &mut fleet, 0, T0 + steps * 100).await;
+            steps += 1;
+            assert!(steps < 1_000, "never docked");
+        }
+        assert!(fleet.robot(0).position.distance_to(&dock) < 1e-9);
+        assert!(!fleet.faults(0).has(FaultType::GpsDrift));
+        let events = mqtt.events();
+        let events = events.read().await;
+        let cleared: Vec<_> = events
+            .entries()
+            .filter(|event| event.kind == SystemEventKind::FaultCleared)
+            .collect();
+        assert_eq!(cleared.len(), 1);
+        assert_eq!(cleared[0].subject.as_deref(), Some("RV-002"));
+        assert!(cleared[0].detail.contains("GpsDrift"));
+    }
+
+    #[test]
+    fn test_low_battery_robot_charges_and_resumes() {
+        let rover = crate::create_mock_fleet()
+            .into_iter()
+            .find(|r| r.id == "RV-002")
+            .unwrap();
+        let start = rover.position;
+        let dock = Position::new(start.x - 2.0, start.y, start.z);
+        let target = Position::new(start.x + 6.0, start.y, start.z);
+        let battery = BatteryConfig {
+            drain_per_meter: 2.0,
+            charge_per_sec: 100.0,
+            ..BatteryConfig::default()
+        };
+        let mut fleet = SimulatedFleet::new(
+            vec![RobotState {
+                battery: 30.0,
+                ..rover
+            }],
+            Vec::new(),
+            WorldBounds::default(),
+            1.0,
+            RecoveryConfig::default(),
+        )
+        .with_charging(vec![ChargingStation::new("CHG-01", dock, 1)], battery);
+        fleet.apply(
+            &received(
+                to("RV-002"),
+                Command::MoveTo {
+                    target,
+                    speed: Some(2.0),
+                },
+            ),
+            T0,
+        );
+
+        let mut steps = 0;
+        while fleet.robot(0).current_task != CurrentTask::ReturningToBase {
+            fleet.step(0, T0);
+            steps += 1;
+            assert!(steps < 100, "never left to charge");
+        }
+        assert!(fleet.robot(0).battery < 25.0);
+        assert_eq!(fleet.charging_at(0).unwrap().id, "CHG-01");
+
+        while fleet.robot(0).status != RobotStatus::Maintenance {
+            fleet.step(0, T0);
+        }
+        assert!(fleet.robot(0).position.distance_to(&dock) < 1e-9);
+        let low = fleet.robot(0).battery;
+        fleet.step(0, T0);
+        assert!(fleet.robot(0).battery > low);
+
+        while fleet.robot(0).status == RobotStatus::Maintenance {
+            fleet.step(0, T0);
+        }
+        assert!(fleet.robot(0).battery >= 90.0);
+        assert_eq!(fleet.robot(0).status, RobotStatus::Active);
+        assert_eq!(
+            fleet.robot(0).current_task,
+            CurrentTask::MovingTo { target }
+        );
+        assert!(fleet.charging_at(0).is_none());
+
+        // Sent to charge by hand, it goes idle once full
+        fleet.apply(&received(to("RV-002"), Command::ReturnToBase), T0);
+        assert_eq!(fleet.robot(0).current_task, CurrentTask::ReturningToBase);
+        while fleet.charging_at(0).is_some() {
+            fleet.step(0, T0);
+        }
+        assert_eq!(fleet.robot(0).status, RobotStatus::Idle);
+        assert_eq!(fleet.robot(0).current_task, CurrentTask::None);
+    }
+
+    #[test]
+    fn test_runaway_robot_is_halted_at_the_bounds() {
+        let bounds = WorldBounds::default();
+        let mut drone = crate::create_mock_fleet()
+            .into_iter()
+            .find(|r| r.id == "DR-001")
+            .unwrap();
+        let start = drone.position;
+
+        let (position, escape) = advance_robot(&mut drone, &bounds);
+        assert!(escape.is_none());
+        assert_eq!(position.x, start.x + 0.85);
+        assert!(drone.orientation.yaw.abs() < 1e-12);
+
+        // Broken steering vector
+        drone.velocity = Velocity::new(4.7e13, 0.0, 0.0);
+        let (position, escape) = advance_robot(&mut drone, &bounds);
+        let escape = escape.expect("escape detected");
+        assert_eq!(position, start);
+        assert_eq!(drone.status, RobotStatus::Error);
+        assert_eq!(drone.velocity, Velocity::zero());
+        assert_eq!(escape.escaped_to.x, start.x + 4.7e12);
+        assert!(matches!(escape.task, CurrentTask::Patrolling { .. }));
+
+        let report = escape.to_report(position);
+        assert_eq!(report.detected_by, "DR-001");
+        assert!(report.description.contains("ROUTE-AIR-1"));
+
+        // Halted robots stay put
+        assert_eq!(advance_robot(&mut drone, &bounds), (start, None));
+    }
+
+    #[tokio::test]
+    async fn test_runaway_halt_is_recorded_while_not_publishing() {
+        let (tx, _rx) = mpsc::channel(10);
+        let (mqtt, _eventloop) = AetherisMqtt::new(crate::MqttConfig::default(), tx)
+            .await
+            .unwrap();
+        let mut drone = crate::create_mock_fleet()
+            .into_iter()
+            .find(|r| r.id == "DR-001")
+            .unwrap();
+        // Drifting on a broken steering vector
+        drone.current_task = CurrentTask::None;
+        drone.velocity = Velocity::new(4.7e13, 0.0, 0.0);
+        let mut fleet = SimulatedFleet::new(
+            vec![drone],
+            Vec::new(),
+            WorldBounds::default(),
+            1.0,
+            RecoveryConfig::default(),
+        );
+        let responses = fleet.apply(&received(to("DR-001"), Command::RequestKeyframe), T0);
+        assert!(responses[0].success);
+
+        // Never connected, so nothing is published, but the halt is on record
+        assert!(step_robot(&mqtt, &mut fleet, 0, T0).await.is_none());
+        assert_eq!(fleet.robot(0).status, RobotStatus::Error);
+        let events = mqtt.events();
+        let events = events.read().await;
+        let halts: Vec<_> = events
+            .entries()
+            .filter(|event| event.kind == SystemEventKind::RobotHalted)
+            .collect();
+        assert_eq!(halts.len(), 1);
+        assert_eq!(halts[0].subject.as_deref(), Some("DR-001"));
+        assert_eq!(halts[0].command_id.as_deref(), Some("CMD-1"));
+        assert!(halts[0].detail.contains("request_keyframe"));
+    }
+
+    #[test]
+    fn test_timing_validation_against_timeout() {
+        assert!(
+            SimulationTiming::default()
+                .validate_against_timeout(DEFAULT_HEARTBEAT_TIMEOUT)
+                .is_ok()
+        );
+
+        let jittery = SimulationTiming {
+            heartbeat_interval: Duration::from_secs(6),
+            jitter_fraction: 0.5,
+            ..SimulationTiming::default()
+        };
+        let violation = jittery
+            .validate_against_timeout(DEFAULT_HEARTBEAT_TIMEOUT)
+            .unwrap_err();
+        assert_eq!(violation.worst_gap, Duration::from_secs(9));
+        assert_eq!(violation.timeout, DEFAULT_HEARTBEAT_TIMEOUT);
+    }
+}
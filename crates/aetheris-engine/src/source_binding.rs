@@ -0,0 +1,508 @@
+//! Binding of envelope sources to the topics they may publish on
+//!
+//! The `source` field of an [`MqttMessage`](aetheris_shared::MqttMessage) is
+//! set by the publisher, so any client can claim to be "dashboard". Until the
+//! broker hands us authenticated identities, the engine checks the claim
+//! against the topic instead: a rule maps a source pattern ("RV-*") to the
+//! topic filters it may publish on ("aetheris/telemetry/{source}").
+//! Envelopes whose claimed source is inconsistent with their topic are
+//! dead-lettered, counted per claimed source, and the first mismatch per
+//! source per day raises a Medium alert. Rules can be reloaded from a JSON file while running.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tokio::time::interval;
+use tracing::{info, warn};
+
+use aetheris_shared::{AnomalyReport, AnomalyType, Position, SeverityLevel, Timestamp};
+
+use crate::anomalies::SYSTEM_SECTION;
+use crate::config::{CheckConfig, ConfigChecker, ConfigReport};
+use crate::shutdown::Shutdown;
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+// ============================================================================
+// RULES
+// ============================================================================
+
+/// Topics a group of sources may publish on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceRule {
+    /// Source id, or a prefix pattern ending in `*` ("RV-*", "*")
+    pub source: String,
+    /// MQTT topic filters (`+` and `#` wildcards); `{source}` is replaced by
+    /// the claimed source before matching
+    pub topics: Vec<String>,
+}
+
+impl SourceRule {
+    pub fn new(source: &str, topics: &[&str]) -> Self {
+        Self {
+            source: source.into(),
+            topics: topics.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    fn matches_source(&self, source: &str) -> bool {
+        match self.source.strip_suffix('*') {
+            Some(prefix) => source.starts_with(prefix),
+            None => self.source == source,
+        }
+    }
+
+    fn allows(&self, source: &str, topic: &str) -> bool {
+        self.topics
+            .iter()
+            .any(|filter| topic_matches(&filter.replace("{source}", source), topic))
+    }
+}
+
+/// What to do with sources no rule mentions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnknownSourcePolicy {
+    /// Accept them on any topic (the pre-binding behavior)
+    #[default]
+    Allow,
+    /// Treat them as mismatches
+    Reject,
+}
+
+/// The complete source-to-topic mapping
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceBindings {
+    pub rules: Vec<SourceRule>,
+    #[serde(default)]
+    pub unknown_sources: UnknownSourcePolicy,
+}
+
+impl Default for SourceBindings {
+    fn default() -> Self {
+        let robot_topics = [
+            "aetheris/telemetry/{source}",
+            "aetheris/heartbeat/{source}",
+            "aetheris/responses/{source}",
+            "aetheris/alerts",
+            "aetheris/alerts/+",
+        ];
+        Self {
+            rules: vec![
+                SourceRule::new("RV-*", &robot_topics),
+                SourceRule::new("DR-*", &robot_topics),
+                SourceRule::new("CR-*", &robot_topics),
+                SourceRule::new("PIPE-*", &["aetheris/environment/{source}"]),
+                // Chaos commands are echoed as alerts detected by the issuer
+                SourceRule::new(
+                    "dashboard",
+                    &[
+                        "aetheris/commands/#",
+                        "aetheris/alerts",
+                        "aetheris/alerts/+",
+                    ],
+                ),
+                SourceRule::new("brain", &["aetheris/triage/results"]),
+                // Every engine instance: "engine", or "engine-…" when electing
+                SourceRule::new("engine*", &["#"]),
+            ],
+            unknown_sources: UnknownSourcePolicy::Allow,
+        }
+    }
+}
+
+impl SourceBindings {
+    /// Whether `source` may publish on `topic`
+    pub fn permits(&self, source: &str, topic: &str) -> bool {
+        let mut applicable = self
+            .rules
+            .iter()
+            .filter(|rule| rule.matches_source(source))
+            .peekable();
+        if applicable.peek().is_none() {
+            return self.unknown_sources == UnknownSourcePolicy::Allow;
+        }
+        applicable.any(|rule| rule.allows(source, topic))
+    }
+
+    /// Load and validate bindings from a JSON file
+    pub fn load(path: &Path) -> Result<Self, BindingLoadError> {
+        let text = std::fs::read_to_string(path)?;
+        let bindings: Self = serde_json::from_str(&text)?;
+        let mut checker = ConfigChecker::default();
+        bindings.check(&mut checker);
+        checker.finish()?;
+        Ok(bindings)
+    }
+}
+
+impl CheckConfig for SourceBindings {
+    fn check(&self, checker: &mut ConfigChecker) {
+        for (i, rule) in self.rules.iter().enumerate() {
+            let base = format!("rules[{i}]");
+            let star = rule.source.find('*');
+            if rule.source.is_empty() || star.is_some_and(|at| at + 1 != rule.source.len()) {
+                checker.error(
+                    &format!("{base}.source"),
+                    format!("invalid source pattern \"{}\"", rule.source),
+                    Some("use an exact id or a prefix ending in '*'".into()),
+                );
+            }
+            if rule.topics.is_empty() {
+                checker.error(&format!("{base}.topics"), "must not be empty", None);
+            }
+            for (j, filter) in rule.topics.iter().enumerate() {
+                if !is_valid_filter(filter) {
+                    checker.error(
+                        &format!("{base}.topics[{j}]"),
+                        format!("invalid topic filter \"{}\"", filter),
+                        Some("'+' must fill a whole level and '#' must be last".into()),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Failure to load a bindings file
+#[derive(Debug, Error)]
+pub enum BindingLoadError {
+    #[error("cannot read bindings: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("malformed bindings: {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("{0}")]
+    Invalid(#[from] ConfigReport),
+}
+
+/// Match a topic against an MQTT filter
+fn topic_matches(filter: &str, topic: &str) -> bool {
+    let mut levels = topic.split('/');
+    for part in filter.split('/') {
+        match (part, levels.next()) {
+            ("#", _) => return true,
+            ("+", Some(_)) => {}
+            (expected, Some(level)) if expected == level => {}
+            _ => return false,
+        }
+    }
+    levels.next().is_none()
+}
+
+fn is_valid_filter(filter: &str) -> bool {
+    let parts: Vec<&str> = filter.split('/').collect();
+    !filter.is_empty()
+        && parts.iter().enumerate().all(|(i, part)| match *part {
+            "#" => i + 1 == parts.len(),
+            "+" => true,
+            other => !other.contains(['#', '+']),
+        })
+}
+
+// ============================================================================
+// GUARD
+// ============================================================================
+
+/// Outcome of checking an envelope's claimed source
+#[derive(Debug, Clone, PartialEq)]
+pub enum SourceVerdict {
+    Consistent,
+    /// The message must be dead-lettered; `alert` is set on the first
+    /// mismatch from this source today
+    Mismatch {
+        alert: Option<Box<AnomalyReport>>,
+    },
+}
+
+/// Applies [`SourceBindings`] and tracks mismatches per claimed source
+#[derive(Debug, Default)]
+pub struct SourceGuard {
+    bindings: SourceBindings,
+    mismatches: HashMap<String, u64>,
+    /// Day index (days since the epoch) of the last alert per source
+    alerted_on: HashMap<String, u64>,
+}
+
+impl SourceGuard {
+    pub fn new(bindings: SourceBindings) -> Self {
+        Self {
+            bindings,
+            ..Self::default()
+        }
+    }
+
+    pub fn bindings(&self) -> &SourceBindings {
+        &self.bindings
+    }
+
+    /// Replace the rules; counters and alert throttling carry over
+    pub fn reload(&mut self, bindings: SourceBindings) {
+        self.bindings = bindings;
+    }
+
+    /// Check that `source` may publish on `topic` at `now_ms`
+    pub fn check(&mut self, source: &str, topic: &str, now_ms: u64) -> SourceVerdict {
+        if self.bindings.permits(source, topic) {
+            return SourceVerdict::Consistent;
+        }
+
+        *self.mismatches.entry(source.to_string()).or_default() += 1;
+        let today = now_ms / DAY_MS;
+        let first_today = self.alerted_on.insert(source.to_string(), today) != Some(today);
+        let alert = first_today.then(|| {
+            Box::new(AnomalyReport {
+                timestamp: Timestamp::from_millis(now_ms),
+                ..AnomalyReport::new(
+                    AnomalyType::Unknown,
+                    SeverityLevel::Medium,
+                    Position::origin(),
+                    SYSTEM_SECTION,
+                    "engine",
+                    1.0,
+                    format!(
+                        "Envelope claiming source \"{}\" arrived on {}, which it may not publish on",
+                        source, topic
+                    ),
+                )
+            })
+        });
+        SourceVerdict::Mismatch { alert }
+    }
+
+    /// Number of mismatched envelopes that claimed `source`
+    pub fn mismatches(&self, source: &str) -> u64 {
+        self.mismatches.get(source).copied().unwrap_or(0)
+    }
+
+    /// Mismatch counts for every claimed source, highest first
+    pub fn mismatch_counts(&self) -> Vec<(String, u64)> {
+        let mut counts: Vec<_> = self
+            .mismatches
+            .iter()
+            .map(|(source, count)| (source.clone(), *count))
+            .collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts
+    }
+}
+
+// ============================================================================
+// HOT RELOAD TASK
+// ============================================================================
+
+/// Spawns a background task that reloads bindings when `path` changes.
+///
+/// A file that fails to load or validate is logged and the previous rules
+/// stay in force.
+pub async fn spawn_binding_reload(
+    guard: Arc<RwLock<SourceGuard>>,
+    path: PathBuf,
+    period: Duration,
+    mut shutdown: Shutdown,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut poll_interval = interval(period);
+        let mut loaded_at: Option<SystemTime> = None;
+
+        loop {
+            tokio::select! {
+                _ = poll_interval.tick() => {}
+                _ = shutdown.wait() => return,
+            }
+
+            let modified = match std::fs::metadata(&path).and_then(|m| m.modified()) {
+                Ok(modified) => modified,
+                Err(e) => {
+                    warn!(path = %path.display(), "Cannot stat source bindings: {}", e);
+                    continue;
+                }
+            };
+            if loaded_at == Some(modified) {
+                continue;
+            }
+            loaded_at = Some(modified);
+
+            match SourceBindings::load(&path) {
+                Ok(bindings) => {
+                    info!(rules = bindings.rules.len(), "Source bindings reloaded");
+                    guard.write().await.reload(bindings);
+                }
+                Err(e) => warn!(path = %path.display(), "Keeping previous source bindings: {}", e),
+            }
+        }
+    })
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 20_000 * DAY_MS + 1_000;
+
+    fn consistent(guard: &mut SourceGuard, source: &str, topic: &str) -> bool {
+        guard.check(source, topic, NOW) == SourceVerdict::Consistent
+    }
+
+    #[test]
+    fn test_consistent_and_spoofed_envelopes() {
+        let mut guard = SourceGuard::default();
+
+        // Robots publish on their own telemetry topic and the shared alert topic
+        assert!(consistent(
+            &mut guard,
+            "RV-001",
+            "aetheris/telemetry/RV-001"
+        ));
+        assert!(consistent(&mut guard, "RV-001", "aetheris/alerts"));
+        assert!(consistent(
+            &mut guard,
+            "dashboard",
+            "aetheris/commands/RV-001"
+        ));
+        assert!(consistent(
+            &mut guard,
+            "dashboard",
+            "aetheris/commands/broadcast"
+        ));
+        assert!(consistent(
+            &mut guard,
+            "engine",
+            "aetheris/telemetry/DR-002"
+        ));
+
+        // Another robot's telemetry, and commands claiming to be the dashboard
+        assert!(!consistent(
+            &mut guard,
+            "RV-001",
+            "aetheris/telemetry/RV-002"
+        ));
+        assert!(!consistent(
+            &mut guard,
+            "dashboard",
+            "aetheris/telemetry/RV-001"
+        ));
+        assert!(!consistent(
+            &mut guard,
+            "RV-001",
+            "aetheris/commands/RV-001"
+        ));
+
+        assert_eq!(guard.mismatches("RV-001"), 2);
+        assert_eq!(guard.mismatches("dashboard"), 1);
+        assert_eq!(guard.mismatches("engine"), 0);
+        assert_eq!(
+            guard.mismatch_counts(),
+            [("RV-001".to_string(), 2), ("dashboard".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_wildcard_rules() {
+        let bindings = SourceBindings {
+            rules: vec![
+                SourceRule::new("RV-*", &["aetheris/+/{source}"]),
+                SourceRule::new("ops-*", &["aetheris/commands/#"]),
+            ],
+            unknown_sources: UnknownSourcePolicy::Reject,
+        };
+        assert!(bindings.permits("RV-007", "aetheris/heartbeat/RV-007"));
+        assert!(!bindings.permits("RV-007", "aetheris/heartbeat/RV-008"));
+        // '+' fills exactly one level
+        assert!(!bindings.permits("RV-007", "aetheris/x/y/RV-007"));
+        assert!(bindings.permits("ops-alice", "aetheris/commands/RV-001"));
+        assert!(bindings.permits("ops-alice", "aetheris/commands"));
+        assert!(!bindings.permits("ops-alice", "aetheris/alerts"));
+        // Unmentioned sources follow the policy
+        assert!(!bindings.permits("stranger", "aetheris/alerts"));
+        let open = SourceBindings {
+            unknown_sources: UnknownSourcePolicy::Allow,
+            ..bindings
+        };
+        assert!(open.permits("stranger", "aetheris/alerts"));
+
+        let mut checker = ConfigChecker::default();
+        SourceBindings {
+            rules: vec![SourceRule::new("R*V", &["aetheris/#/x", "a/b+"])],
+            unknown_sources: UnknownSourcePolicy::Allow,
+        }
+        .check(&mut checker);
+        let paths: Vec<_> = checker
+            .finish()
+            .unwrap_err()
+            .issues
+            .into_iter()
+            .map(|issue| issue.path)
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "rules[0].source",
+                "rules[0].topics[0]",
+                "rules[0].topics[1]"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_alert_on_first_mismatch_per_source_per_day() {
+        let mut guard = SourceGuard::default();
+        let spoof = |guard: &mut SourceGuard, source: &str, at: u64| match guard.check(
+            source,
+            "aetheris/telemetry/RV-009",
+            at,
+        ) {
+            SourceVerdict::Mismatch { alert } => alert,
+            SourceVerdict::Consistent => panic!("expected a mismatch"),
+        };
+
+        let alert = spoof(&mut guard, "dashboard", NOW).expect("first mismatch alerts");
+        assert_eq!(alert.severity, SeverityLevel::Medium);
+        assert!(alert.description.contains("\"dashboard\""));
+        assert!(spoof(&mut guard, "dashboard", NOW + 60_000).is_none());
+        // A different source is throttled separately
+        assert!(spoof(&mut guard, "RV-001", NOW + 60_000).is_some());
+        // Next day alerts again
+        assert!(spoof(&mut guard, "dashboard", NOW + DAY_MS).is_some());
+        assert_eq!(guard.mismatches("dashboard"), 3);
+    }
+
+    #[test]
+    fn test_reload_keeps_counters_and_rejects_bad_files() {
+        let mut guard = SourceGuard::default();
+        assert!(!consistent(&mut guard, "dashboard", "aetheris/telemetry/x"));
+
+        let dir = std::env::temp_dir().join(format!("aetheris-bindings-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("bindings.json");
+        std::fs::write(
+            &path,
+            r#"{"rules": [{"source": "dashboard", "topics": ["aetheris/#"]}]}"#,
+        )
+        .unwrap();
+        guard.reload(SourceBindings::load(&path).unwrap());
+        assert!(consistent(&mut guard, "dashboard", "aetheris/telemetry/x"));
+        assert_eq!(guard.mismatches("dashboard"), 1);
+
+        std::fs::write(&path, r#"{"rules": [{"source": "", "topics": []}]}"#).unwrap();
+        assert!(matches!(
+            SourceBindings::load(&path),
+            Err(BindingLoadError::Invalid(report)) if report.issues.len() == 2
+        ));
+        std::fs::write(&path, "{").unwrap();
+        assert!(matches!(
+            SourceBindings::load(&path),
+            Err(BindingLoadError::Parse(_))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,121 @@
+//! Signed envelopes from sources with a key
+//!
+//! Source binding (see [`crate::source_binding`]) only checks that a claimed
+//! source fits its topic, so anyone on the broker can still publish as
+//! RV-001 on RV-001's topics. Sources given a key here must sign every
+//! envelope (see [`MqttMessage::sign`]); an envelope of theirs that is
+//! unsigned or whose signature does not verify is dead-lettered. Sources
+//! without a key are accepted unsigned, so publishers can be moved over one
+//! at a time.
+//!
+//! Commands are not covered: they carry their operator's signature instead
+//! (see [`crate::authorization`]). The engine signs the envelopes it
+//! publishes on behalf of a source with that source's key.
+
+use std::collections::{BTreeMap, HashMap};
+
+use aetheris_shared::{MqttMessage, SignatureError, SigningKey};
+use serde::Serialize;
+
+use crate::config::{CheckConfig, ConfigChecker};
+use crate::transport::Secret;
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Keys envelope sources sign with
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceSigningConfig {
+    /// Shared signing keys by source id. A source with a key must sign
+    /// every envelope; one without may send them unsigned.
+    pub keys: BTreeMap<String, Secret>,
+}
+
+impl CheckConfig for SourceSigningConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        for (source, key) in &self.keys {
+            if key.expose().is_empty() {
+                checker.error(&format!("keys.{source}"), "must not be empty", None);
+            }
+        }
+    }
+}
+
+// ============================================================================
+// SIGNING
+// ============================================================================
+
+/// Signs and verifies the envelopes of sources with a key
+#[derive(Debug, Default)]
+pub struct SourceSigner {
+    keys: HashMap<String, SigningKey>,
+}
+
+impl SourceSigner {
+    pub fn new(config: &SourceSigningConfig) -> Self {
+        let keys = config
+            .keys
+            .iter()
+            .map(|(source, key)| (source.clone(), SigningKey::new(key.expose().as_bytes())))
+            .collect();
+        Self { keys }
+    }
+
+    /// Whether envelopes from `source` must be signed
+    pub fn requires_signature(&self, source: &str) -> bool {
+        self.keys.contains_key(source)
+    }
+
+    /// Sign an envelope the engine publishes on behalf of its source; left
+    /// unsigned when the source has no key
+    pub fn sign<T: Serialize>(&self, msg: &mut MqttMessage<T>) {
+        if let Some(key) = self.keys.get(&msg.source) {
+            msg.sign(key);
+        }
+    }
+
+    /// Whether the envelope may be processed: always for a source without
+    /// a key, only with a valid signature for one with a key
+    pub fn verify<T: Serialize>(&self, msg: &MqttMessage<T>) -> Result<(), SignatureError> {
+        match self.keys.get(&msg.source) {
+            Some(key) => msg.verify(key),
+            None => Ok(()),
+        }
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetheris_shared::{RobotState, RobotType};
+
+    #[test]
+    fn test_only_sources_with_a_key_must_sign() {
+        let signer = SourceSigner::new(&SourceSigningConfig {
+            keys: BTreeMap::from([("RV-001".to_string(), Secret::new("rv-001-key"))]),
+        });
+        let telemetry = |robot_id: &str| {
+            let state = RobotState::new(robot_id.parse().unwrap(), robot_id, RobotType::Rover);
+            MqttMessage::new(state, robot_id, 3)
+        };
+
+        // Migration mode: no key, no signature needed
+        assert!(!signer.requires_signature("RV-002"));
+        assert_eq!(signer.verify(&telemetry("RV-002")), Ok(()));
+
+        let mut msg = telemetry("RV-001");
+        assert!(signer.requires_signature("RV-001"));
+        assert_eq!(signer.verify(&msg), Err(SignatureError::Unsigned));
+        signer.sign(&mut msg);
+        assert_eq!(signer.verify(&msg), Ok(()));
+
+        // Signed by someone else claiming the source
+        msg.sign(&SigningKey::new(b"stolen-topic-access"));
+        assert_eq!(signer.verify(&msg), Err(SignatureError::Mismatch));
+    }
+}
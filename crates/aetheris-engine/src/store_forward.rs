@@ -0,0 +1,421 @@
+//! Store-and-forward command delivery for weak-link robots
+//!
+//! Crawlers deep in a section lose their link for minutes at a time, and a
+//! command published while their client is disconnected is simply lost.
+//! Commands to a robot whose link is weak (low signal, or nothing heard from
+//! it for a while) are held per robot and released, in order, once a fresh
+//! heartbeat or telemetry shows the link is back. Held commands expire after
+//! their TTL. A robot that stays away with a single command pending gets it
+//! as a retained message, since its command topic holds one retained slot.
+//! Emergency stops are never held, and they discard whatever was pending so
+//! that stale movement commands are not released after the stop.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Duration;
+
+use serde::Serialize;
+use thiserror::Error;
+
+use aetheris_shared::{Command, RobotId};
+
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Weak-link detection and queue behavior
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoreForwardConfig {
+    /// Signal strength (percent) below which a link is weak
+    pub weak_signal: f64,
+    /// Time without heartbeat or telemetry after which a link is weak
+    pub silence: Duration,
+    /// How long a held command stays deliverable, unless given its own TTL
+    pub command_ttl: Duration,
+    /// Held commands per robot
+    pub max_pending: usize,
+    /// How long a lone held command waits before it is published retained;
+    /// `None` never falls back to retain
+    pub retain_after: Option<Duration>,
+}
+
+impl Default for StoreForwardConfig {
+    fn default() -> Self {
+        Self {
+            weak_signal: 20.0,
+            // Two heartbeat intervals, well inside the offline timeout
+            silence: Duration::from_secs(10),
+            command_ttl: Duration::from_secs(300),
+            max_pending: 32,
+            retain_after: Some(Duration::from_secs(60)),
+        }
+    }
+}
+
+impl CheckConfig for StoreForwardConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if !(0.0..=100.0).contains(&self.weak_signal) {
+            checker.error("weak_signal", "must be a percentage (0-100)", None);
+        }
+        checker.positive("silence", self.silence);
+        checker.positive("command_ttl", self.command_ttl);
+        if self.max_pending == 0 {
+            checker.error("max_pending", "must be greater than zero", None);
+        }
+        if let Some(retain_after) = self.retain_after
+            && retain_after >= self.command_ttl
+        {
+            checker.error(
+                "retain_after",
+                "must be shorter than command_ttl",
+                Some("commands expire before they would ever be retained".into()),
+            );
+        }
+    }
+}
+
+// ============================================================================
+// QUEUE
+// ============================================================================
+
+/// A command held for a weak-link robot
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PendingCommand {
+    /// Id the command is published with
+    pub command_id: String,
+    pub command: Command,
+    /// Unix timestamp the command was held (milliseconds)
+    pub queued_at: u64,
+    /// Unix timestamp after which the command is dropped (milliseconds)
+    pub expires_at: u64,
+}
+
+/// A robot's queue was full
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{pending} commands already pending for {robot_id}")]
+pub struct QueueFull {
+    pub robot_id: String,
+    pub pending: usize,
+}
+
+/// What to do with an offered command
+#[derive(Debug, Clone, PartialEq)]
+pub enum Offer {
+    /// Publish now; `discarded` held commands were dropped by an emergency stop
+    Publish {
+        command: Command,
+        discarded: Vec<PendingCommand>,
+    },
+    /// Held until the link is back
+    Held,
+}
+
+/// Commands released by a returning link
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Release {
+    /// Still-valid commands, in the order they were held
+    pub commands: Vec<PendingCommand>,
+    /// Commands that expired while the robot was away
+    pub expired: Vec<PendingCommand>,
+    /// A retained command was published earlier and its slot should be cleared
+    pub clear_retained: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LinkObservation {
+    signal: f64,
+    last_heard: u64,
+}
+
+/// Per-robot link state and held commands
+#[derive(Debug, Default)]
+pub struct StoreAndForward {
+    config: StoreForwardConfig,
+    links: HashMap<RobotId, LinkObservation>,
+    pending: HashMap<RobotId, VecDeque<PendingCommand>>,
+    /// Robots with a command published retained on their topic
+    retained: HashSet<RobotId>,
+}
+
+impl StoreAndForward {
+    pub fn new(config: StoreForwardConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Whether the robot's link is weak at `now`. Robots never heard from
+    /// are not: there is nothing to say their link is bad.
+    pub fn is_weak(&self, robot_id: &str, now: u64) -> bool {
+        self.links.get(robot_id).is_some_and(|link| {
+            link.signal < self.config.weak_signal
+                || now.saturating_sub(link.last_heard) > self.config.silence.as_millis() as u64
+        })
+    }
+
+    /// Decide whether `command` goes out now or waits for the link. Commands
+    /// queue behind already-held ones so delivery order is kept.
+    pub fn offer(
+        &mut self,
+        robot_id: &RobotId,
+        command_id: &str,
+        command: Command,
+        ttl: Option<Duration>,
+        now: u64,
+    ) -> Result<Offer, QueueFull> {
+        if command == Command::EmergencyStop {
+            let discarded = self
+                .pending
+                .remove(robot_id)
+                .map(Vec::from)
+                .unwrap_or_default();
+            return Ok(Offer::Publish { command, discarded });
+        }
+        let queued = self.pending.get(robot_id).is_some_and(|q| !q.is_empty());
+        if !queued && !self.is_weak(robot_id, now) {
+            return Ok(Offer::Publish {
+                command,
+                discarded: Vec::new(),
+            });
+        }
+        let queue = self.pending.entry(robot_id.clone()).or_default();
+        if queue.len() >= self.config.max_pending {
+            return Err(QueueFull {
+                robot_id: robot_id.to_string(),
+                pending: queue.len(),
+            });
+        }
+        let ttl = ttl.unwrap_or(self.config.command_ttl).as_millis() as u64;
+        queue.push_back(PendingCommand {
+            command_id: command_id.to_string(),
+            command,
+            queued_at: now,
+            expires_at: now + ttl,
+        });
+        Ok(Offer::Held)
+    }
+
+    /// Record that the robot was heard from with `signal` at `now`. If that
+    /// brings the link back, the robot's held commands are released.
+    pub fn observe_link(&mut self, robot_id: &RobotId, signal: f64, now: u64) -> Release {
+        self.links.insert(
+            robot_id.clone(),
+            LinkObservation {
+                signal,
+                last_heard: now,
+            },
+        );
+        if self.is_weak(robot_id, now) {
+            return Release::default();
+        }
+        let clear_retained = self.retained.remove(robot_id);
+        let (commands, expired) = self
+            .pending
+            .remove(robot_id)
+            .unwrap_or_default()
+            .into_iter()
+            .partition::<Vec<_>, _>(|p| p.expires_at > now);
+        Release {
+            commands,
+            expired,
+            clear_retained,
+        }
+    }
+
+    /// Drop held commands whose TTL passed
+    pub fn prune(&mut self, now: u64) -> Vec<(RobotId, PendingCommand)> {
+        let mut expired = Vec::new();
+        for (robot_id, queue) in &mut self.pending {
+            let (keep, gone): (VecDeque<_>, VecDeque<_>) =
+                queue.drain(..).partition(|p| p.expires_at > now);
+            *queue = keep;
+            expired.extend(gone.into_iter().map(|p| (robot_id.clone(), p)));
+        }
+        self.pending.retain(|_, queue| !queue.is_empty());
+        expired
+    }
+
+    /// Lone held commands that waited past `retain_after`, removed from the
+    /// queue to be published retained
+    pub fn take_retain_fallbacks(&mut self, now: u64) -> Vec<(RobotId, PendingCommand)> {
+        let Some(retain_after) = self.config.retain_after else {
+            return Vec::new();
+        };
+        let retain_after = retain_after.as_millis() as u64;
+        let due: Vec<RobotId> = self
+            .pending
+            .iter()
+            .filter(|(_, queue)| {
+                queue.len() == 1 && now.saturating_sub(queue[0].queued_at) >= retain_after
+            })
+            .map(|(robot_id, _)| robot_id.clone())
+            .collect();
+        due.into_iter()
+            .filter_map(|robot_id| {
+                let pending = self.pending.remove(&robot_id)?.pop_front()?;
+                self.retained.insert(robot_id.clone());
+                Some((robot_id, pending))
+            })
+            .collect()
+    }
+
+    /// Commands held for a robot, oldest first
+    pub fn pending(&self, robot_id: &str) -> Vec<PendingCommand> {
+        self.pending
+            .get(robot_id)
+            .map(|q| q.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const T0: u64 = 1_000_000;
+
+    fn patrol(route: &str) -> Command {
+        Command::StartPatrol {
+            route_id: route.into(),
+        }
+    }
+
+    #[test]
+    fn test_weak_link_commands_are_held_and_released_in_order() {
+        let mut sf = StoreAndForward::default();
+        sf.observe_link(&"CR-001".parse().unwrap(), 80.0, T0);
+        assert!(!sf.is_weak("CR-001", T0 + 1_000));
+
+        // Goes silent deep in the pipe
+        let away = T0 + 20_000;
+        assert!(sf.is_weak("CR-001", away));
+        for (i, route) in ["A", "B", "C"].iter().enumerate() {
+            let ttl = (i == 1).then(|| Duration::from_secs(30));
+            assert_eq!(
+                sf.offer(
+                    &"CR-001".parse().unwrap(),
+                    &format!("CMD-{route}"),
+                    patrol(route),
+                    ttl,
+                    away + i as u64
+                )
+                .unwrap(),
+                Offer::Held
+            );
+        }
+        assert_eq!(sf.pending("CR-001").len(), 3);
+
+        // Weak signal on return keeps everything held
+        let back = away + 60_000;
+        assert_eq!(
+            sf.observe_link(&"CR-001".parse().unwrap(), 10.0, back),
+            Release::default()
+        );
+
+        let release = sf.observe_link(&"CR-001".parse().unwrap(), 65.0, back + 1_000);
+        let released: Vec<_> = release.commands.iter().map(|p| &p.command_id).collect();
+        assert_eq!(released, ["CMD-A", "CMD-C"]);
+        assert_eq!(release.expired.len(), 1);
+        assert_eq!(release.expired[0].command, patrol("B"));
+        assert!(sf.pending("CR-001").is_empty());
+
+        // Healthy link: straight through
+        assert!(matches!(
+            sf.offer(
+                &"CR-001".parse().unwrap(),
+                "CMD-D",
+                patrol("D"),
+                None,
+                back + 2_000
+            ),
+            Ok(Offer::Publish { .. })
+        ));
+    }
+
+    #[test]
+    fn test_emergency_stop_bypasses_and_discards_queue() {
+        let mut sf = StoreAndForward::default();
+        sf.observe_link(&"CR-001".parse().unwrap(), 5.0, T0);
+        sf.offer(&"CR-001".parse().unwrap(), "CMD-A", patrol("A"), None, T0)
+            .unwrap();
+
+        let offer = sf
+            .offer(
+                &"CR-001".parse().unwrap(),
+                "CMD-S",
+                Command::EmergencyStop,
+                None,
+                T0 + 1,
+            )
+            .unwrap();
+        let Offer::Publish { command, discarded } = offer else {
+            panic!("emergency stop was held");
+        };
+        assert_eq!(command, Command::EmergencyStop);
+        assert_eq!(discarded.len(), 1);
+        assert!(sf.pending("CR-001").is_empty());
+    }
+
+    #[test]
+    fn test_ttl_pruning_and_retain_fallback() {
+        let mut sf = StoreAndForward::new(StoreForwardConfig {
+            max_pending: 2,
+            ..StoreForwardConfig::default()
+        });
+        sf.observe_link(&"CR-001".parse().unwrap(), 5.0, T0);
+        sf.observe_link(&"CR-002".parse().unwrap(), 5.0, T0);
+        sf.offer(
+            &"CR-001".parse().unwrap(),
+            "CMD-A",
+            patrol("A"),
+            Some(Duration::from_secs(10)),
+            T0,
+        )
+        .unwrap();
+        sf.offer(&"CR-001".parse().unwrap(), "CMD-B", patrol("B"), None, T0)
+            .unwrap();
+        assert_eq!(
+            sf.offer(&"CR-001".parse().unwrap(), "CMD-C", patrol("C"), None, T0),
+            Err(QueueFull {
+                robot_id: "CR-001".into(),
+                pending: 2
+            })
+        );
+        sf.offer(
+            &"CR-002".parse().unwrap(),
+            "CMD-R",
+            Command::ReturnToBase,
+            None,
+            T0,
+        )
+        .unwrap();
+
+        let expired = sf.prune(T0 + 11_000);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].0, "CR-001");
+
+        // Both now have a lone command; after a minute it goes out retained
+        assert!(sf.take_retain_fallbacks(T0 + 30_000).is_empty());
+        let mut retained: Vec<_> = sf
+            .take_retain_fallbacks(T0 + 60_000)
+            .into_iter()
+            .map(|(robot_id, pending)| (robot_id.to_string(), pending.command))
+            .collect();
+        retained.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            retained,
+            [
+                ("CR-001".to_string(), patrol("B")),
+                ("CR-002".to_string(), Command::ReturnToBase)
+            ]
+        );
+        let release = sf.observe_link(&"CR-001".parse().unwrap(), 70.0, T0 + 61_000);
+        assert!(release.clear_retained && release.commands.is_empty());
+    }
+}
@@ -0,0 +1,628 @@
+//! Telemetry history kept for replay
+//!
+//! Robot states, environment readings and anomaly reports are written to a
+//! [`TelemetryStore`] as the engine accepts them, so the dashboard can replay
+//! the last hours of an incident instead of only watching it live. Queries
+//! select a time range, optionally narrowed to one robot, one section or
+//! one kind of record, and return the most recent matches oldest first.
+//!
+//! The memory store keeps a bounded window for the life of the process.
+//! With the `sqlite` feature a database path keeps the history across
+//! restarts. Records older than the retention are pruned either way.
+//! Anomaly reports are kept once per id, as their latest version; the
+//! engine republishes a report when triage or an operator changes it.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "sqlite")]
+use tracing::error;
+
+use aetheris_shared::{AnomalyReport, PipeEnvironment, RobotState};
+
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Where telemetry history is kept and for how long
+#[derive(Debug, Clone, PartialEq)]
+pub struct TelemetryStoreConfig {
+    /// SQLite database of the history (`sqlite` feature); memory only when
+    /// unset
+    pub path: Option<PathBuf>,
+    /// How long records are kept
+    pub retention: Duration,
+    /// Records the memory store holds before dropping the oldest
+    pub max_records: usize,
+}
+
+impl Default for TelemetryStoreConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            retention: Duration::from_secs(24 * 3600),
+            max_records: 200_000,
+        }
+    }
+}
+
+impl CheckConfig for TelemetryStoreConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        match &self.path {
+            Some(path) if path.as_os_str().is_empty() => {
+                checker.error("path", "must not be empty", Some("leave it unset".into()));
+            }
+            Some(_) if cfg!(not(feature = "sqlite")) => {
+                checker.error(
+                    "path",
+                    "needs the engine built with the `sqlite` feature",
+                    Some("leave it unset to keep history in memory".into()),
+                );
+            }
+            _ => {}
+        }
+        checker.positive("retention", self.retention);
+        if self.max_records == 0 {
+            checker.error("max_records", "must be greater than zero", None);
+        }
+    }
+}
+
+impl TelemetryStoreConfig {
+    /// The store this configuration describes. A database that cannot be
+    /// opened loses the history, not the engine: memory is used instead.
+    pub fn open(&self) -> Box<dyn TelemetryStore> {
+        #[cfg(feature = "sqlite")]
+        if let Some(path) = &self.path {
+            match SqliteTelemetryStore::open(path) {
+                Ok(store) => return Box::new(store),
+                Err(e) => error!(
+                    path = %path.display(),
+                    "Failed to open the telemetry history, keeping it in memory: {}", e
+                ),
+            }
+        }
+        Box::new(MemoryTelemetryStore::new(self.max_records))
+    }
+}
+
+// ============================================================================
+// RECORDS AND QUERIES
+// ============================================================================
+
+/// Kind of history record
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryKind {
+    Telemetry,
+    Environment,
+    Anomaly,
+}
+
+impl HistoryKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HistoryKind::Telemetry => "telemetry",
+            HistoryKind::Environment => "environment",
+            HistoryKind::Anomaly => "anomaly",
+        }
+    }
+}
+
+/// One stored message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum HistoryRecord {
+    Telemetry(RobotState),
+    Environment(PipeEnvironment),
+    Anomaly(Box<AnomalyReport>),
+}
+
+impl HistoryRecord {
+    pub fn kind(&self) -> HistoryKind {
+        match self {
+            HistoryRecord::Telemetry(_) => HistoryKind::Telemetry,
+            HistoryRecord::Environment(_) => HistoryKind::Environment,
+            HistoryRecord::Anomaly(_) => HistoryKind::Anomaly,
+        }
+    }
+
+    /// Unix timestamp of the message (milliseconds)
+    pub fn timestamp(&self) -> u64 {
+        match self {
+            HistoryRecord::Telemetry(state) => state.timestamp.as_millis(),
+            HistoryRecord::Environment(reading) => reading.timestamp.as_millis(),
+            HistoryRecord::Anomaly(report) => report.timestamp.as_millis(),
+        }
+    }
+
+    /// Robot the record came from: the reporting robot of a state, the
+    /// detector of an anomaly
+    pub fn robot_id(&self) -> Option<&str> {
+        match self {
+            HistoryRecord::Telemetry(state) => Some(state.id.as_str()),
+            HistoryRecord::Environment(_) => None,
+            HistoryRecord::Anomaly(report) => Some(&report.detected_by),
+        }
+    }
+
+    pub fn section_id(&self) -> Option<&str> {
+        match self {
+            HistoryRecord::Telemetry(_) => None,
+            HistoryRecord::Environment(reading) => Some(&reading.section_id),
+            HistoryRecord::Anomaly(report) => Some(&report.section_id),
+        }
+    }
+
+    /// Move a reading or anomaly to another section
+    fn set_section_id(&mut self, section_id: &str) {
+        match self {
+            HistoryRecord::Telemetry(_) => {}
+            HistoryRecord::Environment(reading) => reading.section_id = section_id.to_string(),
+            HistoryRecord::Anomaly(report) => report.section_id = section_id.to_string(),
+        }
+    }
+
+    /// Id a later version of the record replaces it under
+    fn anomaly_id(&self) -> Option<&str> {
+        match self {
+            HistoryRecord::Anomaly(report) => Some(&report.id),
+            _ => None,
+        }
+    }
+}
+
+/// Most records a history query returns unless told otherwise, and the
+/// most the HTTP bridge lets a client ask for
+pub const DEFAULT_HISTORY_LIMIT: usize = 10_000;
+
+/// Which records to return
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryQuery {
+    /// Unix timestamp of the oldest record (milliseconds, inclusive)
+    pub from: u64,
+    /// Unix timestamp of the newest record (milliseconds, inclusive)
+    pub to: u64,
+    pub robot_id: Option<String>,
+    pub section_id: Option<String>,
+    /// Kinds returned; every kind when empty
+    pub kinds: Vec<HistoryKind>,
+    /// Most recent matches returned
+    pub limit: usize,
+}
+
+impl HistoryQuery {
+    /// Every record between `from` and `to`, up to
+    /// [`DEFAULT_HISTORY_LIMIT`]
+    pub fn range(from: u64, to: u64) -> Self {
+        Self {
+            from,
+            to,
+            robot_id: None,
+            section_id: None,
+            kinds: Vec::new(),
+            limit: DEFAULT_HISTORY_LIMIT,
+        }
+    }
+
+    pub fn for_robot(mut self, robot_id: impl Into<String>) -> Self {
+        self.robot_id = Some(robot_id.into());
+        self
+    }
+
+    pub fn for_section(mut self, section_id: impl Into<String>) -> Self {
+        self.section_id = Some(section_id.into());
+        self
+    }
+
+    pub fn of_kind(mut self, kind: HistoryKind) -> Self {
+        self.kinds.push(kind);
+        self
+    }
+
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn matches(&self, record: &HistoryRecord) -> bool {
+        let timestamp = record.timestamp();
+        (self.from..=self.to).contains(&timestamp)
+            && (self.kinds.is_empty() || self.kinds.contains(&record.kind()))
+            && self
+                .robot_id
+                .as_deref()
+                .is_none_or(|id| record.robot_id() == Some(id))
+            && self
+                .section_id
+                .as_deref()
+                .is_none_or(|id| record.section_id() == Some(id))
+    }
+}
+
+// ============================================================================
+// STORES
+// ============================================================================
+
+/// Storage of telemetry history
+pub trait TelemetryStore: fmt::Debug + Send {
+    /// Add a record; an anomaly replaces the stored report with its id
+    fn record(&mut self, record: HistoryRecord) -> io::Result<()>;
+
+    /// The most recent `query.limit` matches, oldest first
+    fn query(&self, query: &HistoryQuery) -> io::Result<Vec<HistoryRecord>>;
+
+    /// Drop records older than `before`, returning how many were dropped
+    fn prune(&mut self, before: u64) -> io::Result<usize>;
+
+    /// Move the records of section `from` to section `into`, after a
+    /// provisional section was merged; returns how many moved
+    fn rename_section(&mut self, from: &str, into: &str) -> io::Result<usize>;
+
+    /// States a robot reported between `from` and `to`, oldest first
+    fn robot_states(&self, robot_id: &str, from: u64, to: u64) -> io::Result<Vec<RobotState>> {
+        let query = HistoryQuery::range(from, to)
+            .for_robot(robot_id)
+            .of_kind(HistoryKind::Telemetry);
+        Ok(self
+            .query(&query)?
+            .into_iter()
+            .filter_map(|record| match record {
+                HistoryRecord::Telemetry(state) => Some(state),
+                _ => None,
+            })
+            .collect())
+    }
+}
+
+/// History kept for the life of the process, oldest dropped first
+#[derive(Debug)]
+pub struct MemoryTelemetryStore {
+    max_records: usize,
+    /// In arrival order, which is nearly timestamp order
+    records: VecDeque<HistoryRecord>,
+}
+
+impl Default for MemoryTelemetryStore {
+    fn default() -> Self {
+        Self::new(TelemetryStoreConfig::default().max_records)
+    }
+}
+
+impl MemoryTelemetryStore {
+    pub fn new(max_records: usize) -> Self {
+        Self {
+            max_records,
+            records: VecDeque::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+impl TelemetryStore for MemoryTelemetryStore {
+    fn record(&mut self, record: HistoryRecord) -> io::Result<()> {
+        if let Some(id) = record.anomaly_id() {
+            let stored = self
+                .records
+                .iter_mut()
+                .find(|stored| stored.anomaly_id() == Some(id));
+            if let Some(stored) = stored {
+                *stored = record;
+                return Ok(());
+            }
+        }
+        if self.records.len() >= self.max_records {
+            self.records.pop_front();
+        }
+        self.records.push_back(record);
+        Ok(())
+    }
+
+    fn query(&self, query: &HistoryQuery) -> io::Result<Vec<HistoryRecord>> {
+        let mut matches: Vec<HistoryRecord> = self
+            .records
+            .iter()
+            .filter(|record| query.matches(record))
+            .cloned()
+            .collect();
+        matches.sort_by_key(HistoryRecord::timestamp);
+        let skip = matches.len().saturating_sub(query.limit);
+        matches.drain(..skip);
+        Ok(matches)
+    }
+
+    fn prune(&mut self, before: u64) -> io::Result<usize> {
+        let len = self.records.len();
+        self.records.retain(|record| record.timestamp() >= before);
+        Ok(len - self.records.len())
+    }
+
+    fn rename_section(&mut self, from: &str, into: &str) -> io::Result<usize> {
+        let mut renamed = 0;
+        for record in &mut self.records {
+            if record.section_id() == Some(from) {
+                record.set_section_id(into);
+                renamed += 1;
+            }
+        }
+        Ok(renamed)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteTelemetryStore;
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use std::path::Path;
+
+    use rusqlite::{Connection, params};
+
+    use super::*;
+
+    const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS telemetry_history (
+    kind       TEXT NOT NULL,
+    robot_id   TEXT,
+    section_id TEXT,
+    anomaly_id TEXT UNIQUE,
+    timestamp  INTEGER NOT NULL,
+    record     TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS telemetry_history_time ON telemetry_history (timestamp);
+CREATE INDEX IF NOT EXISTS telemetry_history_robot ON telemetry_history (robot_id, timestamp);
+";
+
+    /// History in a SQLite database, kept across restarts
+    #[derive(Debug)]
+    pub struct SqliteTelemetryStore {
+        conn: Connection,
+    }
+
+    impl SqliteTelemetryStore {
+        /// Open (or create) the database at `path` and apply the schema
+        pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Self> {
+            Self::with_connection(Connection::open(path)?)
+        }
+
+        /// A database that lives as long as the store, for tests
+        pub fn in_memory() -> rusqlite::Result<Self> {
+            Self::with_connection(Connection::open_in_memory()?)
+        }
+
+        fn with_connection(conn: Connection) -> rusqlite::Result<Self> {
+            conn.execute_batch("PRAGMA journal_mode = WAL;")?;
+            conn.execute_batch(SCHEMA)?;
+            Ok(Self { conn })
+        }
+    }
+
+    impl TelemetryStore for SqliteTelemetryStore {
+        fn record(&mut self, record: HistoryRecord) -> io::Result<()> {
+            let json = serde_json::to_string(&record)?;
+            self.conn
+                .execute(
+                    "INSERT OR REPLACE INTO telemetry_history
+                         (kind, robot_id, section_id, anomaly_id, timestamp, record)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        record.kind().as_str(),
+                        record.robot_id(),
+                        record.section_id(),
+                        record.anomaly_id(),
+                        record.timestamp() as i64,
+                        json,
+                    ],
+                )
+                .map_err(io::Error::other)?;
+            Ok(())
+        }
+
+        fn query(&self, query: &HistoryQuery) -> io::Result<Vec<HistoryRecord>> {
+            let kinds: Vec<&str> = query.kinds.iter().map(|kind| kind.as_str()).collect();
+            let mut statement = self
+                .conn
+                .prepare_cached(
+                    "SELECT record FROM telemetry_history
+                     WHERE timestamp BETWEEN ?1 AND ?2
+                       AND (?3 IS NULL OR robot_id = ?3)
+                       AND (?4 IS NULL OR section_id = ?4)
+                       AND (?5 = '' OR instr(?5, ',' || kind || ',') > 0)
+                     ORDER BY timestamp DESC, rowid DESC
+                     LIMIT ?6",
+                )
+                .map_err(io::Error::other)?;
+            let kinds = if kinds.is_empty() {
+                String::new()
+            } else {
+                format!(",{},", kinds.join(","))
+            };
+            let rows = statement
+                .query_map(
+                    params![
+                        query.from.min(i64::MAX as u64) as i64,
+                        query.to.min(i64::MAX as u64) as i64,
+                        query.robot_id,
+                        query.section_id,
+                        kinds,
+                        query.limit.min(i64::MAX as usize) as i64,
+                    ],
+                    |row| row.get::<_, String>(0),
+                )
+                .map_err(io::Error::other)?;
+            let mut records = Vec::new();
+            for json in rows {
+                let json = json.map_err(io::Error::other)?;
+                records.push(serde_json::from_str(&json)?);
+            }
+            records.reverse();
+            Ok(records)
+        }
+
+        fn prune(&mut self, before: u64) -> io::Result<usize> {
+            self.conn
+                .execute(
+                    "DELETE FROM telemetry_history WHERE timestamp < ?1",
+                    params![before.min(i64::MAX as u64) as i64],
+                )
+                .map_err(io::Error::other)
+        }
+
+        fn rename_section(&mut self, from: &str, into: &str) -> io::Result<usize> {
+            let tx = self.conn.transaction().map_err(io::Error::other)?;
+            let rows: Vec<(i64, String)> = {
+                let mut statement = tx
+                    .prepare("SELECT rowid, record FROM telemetry_history WHERE section_id = ?1")
+                    .map_err(io::Error::other)?;
+                statement
+                    .query_map(params![from], |row| Ok((row.get(0)?, row.get(1)?)))
+                    .and_then(Iterator::collect)
+                    .map_err(io::Error::other)?
+            };
+            for (rowid, json) in &rows {
+                let mut record: HistoryRecord = serde_json::from_str(json)?;
+                record.set_section_id(into);
+                tx.execute(
+                    "UPDATE telemetry_history SET section_id = ?1, record = ?2 WHERE rowid = ?3",
+                    params![into, serde_json::to_string(&record)?, rowid],
+                )
+                .map_err(io::Error::other)?;
+            }
+            tx.commit().map_err(io::Error::other)?;
+            Ok(rows.len())
+        }
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetheris_shared::{AnomalyType, Position, RobotType, SeverityLevel, Timestamp};
+
+    const T0: u64 = 1_700_000_000_000;
+
+    fn state(robot_id: &str, at: u64) -> HistoryRecord {
+        HistoryRecord::Telemetry(RobotState::new_at(
+            robot_id.parse().unwrap(),
+            robot_id,
+            RobotType::Rover,
+            Timestamp::from_millis(at),
+        ))
+    }
+
+    fn anomaly(id: &str, severity: SeverityLevel, at: u64) -> HistoryRecord {
+        let mut report = AnomalyReport::new_at(
+            AnomalyType::Leak,
+            severity,
+            Position::origin(),
+            "PIPE-002",
+            "CR-001",
+            0.9,
+            "H2 above threshold",
+            Timestamp::from_millis(at),
+        );
+        report.id = id.into();
+        HistoryRecord::Anomaly(Box::new(report))
+    }
+
+    fn exercise(store: &mut dyn TelemetryStore) {
+        for i in 0..5 {
+            store.record(state("RV-001", T0 + i * 1_000)).unwrap();
+            store.record(state("DR-001", T0 + i * 1_000 + 500)).unwrap();
+        }
+        store
+            .record(anomaly("ANM-1", SeverityLevel::Medium, T0 + 2_200))
+            .unwrap();
+        store
+            .record(anomaly("ANM-1", SeverityLevel::High, T0 + 2_200))
+            .unwrap();
+
+        // One robot over a window, oldest first
+        let states = store
+            .robot_states("RV-001", T0 + 1_000, T0 + 3_000)
+            .unwrap();
+        let times: Vec<u64> = states.iter().map(|s| s.timestamp.as_millis()).collect();
+        assert_eq!(times, [T0 + 1_000, T0 + 2_000, T0 + 3_000]);
+
+        // The latest version of an anomaly, once
+        let query = HistoryQuery::range(T0, T0 + 10_000).of_kind(HistoryKind::Anomaly);
+        let anomalies = store.query(&query).unwrap();
+        assert_eq!(anomalies.len(), 1);
+        assert!(matches!(
+            &anomalies[0],
+            HistoryRecord::Anomaly(report) if report.severity == SeverityLevel::High
+        ));
+        let by_section = HistoryQuery::range(T0, T0 + 10_000).for_section("PIPE-002");
+        assert_eq!(store.query(&by_section).unwrap(), anomalies);
+
+        // The limit keeps the most recent matches
+        let recent = store
+            .query(&HistoryQuery::range(T0, T0 + 10_000).with_limit(3))
+            .unwrap();
+        let times: Vec<u64> = recent.iter().map(HistoryRecord::timestamp).collect();
+        assert_eq!(times, [T0 + 3_500, T0 + 4_000, T0 + 4_500]);
+
+        assert_eq!(store.prune(T0 + 2_000).unwrap(), 4);
+        let left = store.query(&HistoryQuery::range(0, u64::MAX)).unwrap();
+        assert_eq!(left.len(), 7);
+        assert!(left.iter().all(|record| record.timestamp() >= T0 + 2_000));
+
+        // A merged section takes its records along
+        assert_eq!(store.rename_section("PIPE-002", "PIPE-003").unwrap(), 1);
+        assert!(store.query(&by_section).unwrap().is_empty());
+        let merged = HistoryQuery::range(T0, T0 + 10_000).for_section("PIPE-003");
+        let moved = store.query(&merged).unwrap();
+        assert_eq!(moved.len(), 1);
+        assert_eq!(moved[0].section_id(), Some("PIPE-003"));
+    }
+
+    #[test]
+    fn test_memory_store_queries_by_robot_range_and_kind() {
+        exercise(&mut MemoryTelemetryStore::default());
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_store_queries_by_robot_range_and_kind() {
+        exercise(&mut SqliteTelemetryStore::in_memory().unwrap());
+    }
+
+    #[test]
+    fn test_memory_store_drops_the_oldest_when_full() {
+        let mut store = MemoryTelemetryStore::new(3);
+        for i in 0..5 {
+            store.record(state("RV-001", T0 + i)).unwrap();
+        }
+        assert_eq!(store.len(), 3);
+        let states = store.robot_states("RV-001", 0, u64::MAX).unwrap();
+        assert_eq!(states[0].timestamp.as_millis(), T0 + 2);
+    }
+
+    #[test]
+    fn test_path_needs_the_sqlite_feature() {
+        let config = TelemetryStoreConfig {
+            path: Some("history.db".into()),
+            ..TelemetryStoreConfig::default()
+        };
+        let mut checker = ConfigChecker::default();
+        config.check(&mut checker);
+        assert_eq!(checker.finish().is_ok(), cfg!(feature = "sqlite"));
+    }
+}
@@ -0,0 +1,623 @@
+//! Incident timeline assembly
+//!
+//! Reconstructs "what happened between 02:10 and 02:40" from the engine's
+//! stores: active anomalies, the command audit, the event log, and a bounded
+//! per-robot position history. Records are normalized into ordered
+//! [`TimelineEntry`] items linking back to their sources. One action that
+//! produced several records becomes one entry: an anomaly and the reports
+//! merged into it, a command and the event it raised. Robot movements are
+//! summarized as one entry per significant move instead of one per sample.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use aetheris_shared::{
+    Position, RecordRef, RecordStore, RobotState, RobotStatus, TimelineEntry, TimelineEntryKind,
+};
+
+use crate::anomalies::{ActiveAnomaly, ENGINE_ORIGIN};
+use crate::config::{CheckConfig, ConfigChecker};
+use crate::correlation::CommandAuditEntry;
+use crate::events::SystemEvent;
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Position history and movement summarization
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineConfig {
+    /// Telemetry samples kept per robot
+    pub samples_per_robot: usize,
+    /// Step between consecutive samples below which a robot counts as stationary (meters)
+    pub stationary_step: f64,
+    /// Net displacement below which a movement is not reported (meters)
+    pub min_movement: f64,
+}
+
+impl Default for TimelineConfig {
+    fn default() -> Self {
+        Self {
+            samples_per_robot: 3600,
+            stationary_step: 0.05,
+            min_movement: 1.0,
+        }
+    }
+}
+
+impl CheckConfig for TimelineConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if self.samples_per_robot < 2 {
+            checker.error(
+                "samples_per_robot",
+                "must be at least 2",
+                Some("movements need a start and an end sample".into()),
+            );
+        }
+        if !(self.stationary_step >= 0.0 && self.stationary_step.is_finite()) {
+            checker.error("stationary_step", "must be a finite distance", None);
+        }
+        if !(self.min_movement >= self.stationary_step && self.min_movement.is_finite()) {
+            checker.error(
+                "min_movement",
+                "must be finite and at least stationary_step",
+                None,
+            );
+        }
+    }
+}
+
+// ============================================================================
+// ROBOT HISTORY
+// ============================================================================
+
+/// One position sample of a robot
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackPoint {
+    pub position: Position,
+    pub status: RobotStatus,
+    /// Unix timestamp (milliseconds)
+    pub timestamp: u64,
+}
+
+/// Recent telemetry positions per robot, oldest first
+#[derive(Debug, Default)]
+pub struct RobotHistory {
+    capacity: usize,
+    tracks: HashMap<String, VecDeque<TrackPoint>>,
+}
+
+impl RobotHistory {
+    pub fn new(samples_per_robot: usize) -> Self {
+        Self {
+            capacity: samples_per_robot,
+            tracks: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, state: &RobotState) {
+        let track = self.tracks.entry(state.id.to_string()).or_default();
+        if track.len() >= self.capacity {
+            track.pop_front();
+        }
+        track.push_back(TrackPoint {
+            position: state.position,
+            status: state.status,
+            timestamp: state.timestamp.as_millis(),
+        });
+    }
+
+    /// Samples of `robot_id` within `[from, to]`
+    pub fn track(&self, robot_id: &str, from: u64, to: u64) -> Vec<&TrackPoint> {
+        self.tracks
+            .get(robot_id)
+            .into_iter()
+            .flatten()
+            .filter(|p| (from..=to).contains(&p.timestamp))
+            .collect()
+    }
+
+    pub fn robots(&self) -> impl Iterator<Item = &str> {
+        self.tracks.keys().map(String::as_str)
+    }
+}
+
+// ============================================================================
+// TIMELINE
+// ============================================================================
+
+/// What a timeline is about
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimelineFocus {
+    Section(String),
+    /// A group of sections
+    Zone(Vec<String>),
+    Robot(String),
+    /// An anomaly, by the id of any report merged into it
+    Anomaly(String),
+}
+
+/// Borrowed view of the stores a timeline is built from
+#[derive(Debug, Clone, Copy)]
+pub struct TimelineSources<'a> {
+    pub anomalies: &'a [&'a ActiveAnomaly],
+    pub commands: &'a [&'a CommandAuditEntry],
+    pub events: &'a [&'a SystemEvent],
+    pub history: &'a RobotHistory,
+}
+
+/// Anomalies, robots, and sections a focus resolves to
+#[derive(Debug, Default)]
+struct Scope {
+    anomaly_ids: HashSet<String>,
+    robots: HashSet<String>,
+    sections: HashSet<String>,
+}
+
+impl Scope {
+    fn resolve(focus: &TimelineFocus, sources: &TimelineSources) -> Self {
+        let mut scope = Scope::default();
+        let anomalies: Vec<&ActiveAnomaly> = sources
+            .anomalies
+            .iter()
+            .copied()
+            .filter(|a| match focus {
+                TimelineFocus::Section(id) => reports(a).any(|r| r.section_id == *id),
+                TimelineFocus::Zone(ids) => reports(a).any(|r| ids.contains(&r.section_id)),
+                TimelineFocus::Robot(id) => reports(a).any(|r| r.detected_by == *id),
+                TimelineFocus::Anomaly(id) => a.contains(id),
+            })
+            .collect();
+        match focus {
+            TimelineFocus::Section(id) => {
+                scope.sections.insert(id.clone());
+            }
+            TimelineFocus::Zone(ids) => scope.sections.extend(ids.iter().cloned()),
+            TimelineFocus::Robot(id) => {
+                scope.robots.insert(id.clone());
+            }
+            TimelineFocus::Anomaly(_) => {}
+        }
+        for report in anomalies.iter().flat_map(|a| reports(a)) {
+            scope.anomaly_ids.insert(report.id.clone());
+            if !matches!(focus, TimelineFocus::Robot(_)) && report.detected_by != ENGINE_ORIGIN {
+                scope.robots.insert(report.detected_by.clone());
+            }
+        }
+        // Robots sent to investigate are part of the incident
+        if !matches!(focus, TimelineFocus::Robot(_)) {
+            for command in sources.commands {
+                if let (Some(anomaly_id), Some(robot_id)) = (&command.anomaly_id, &command.robot_id)
+                    && scope.anomaly_ids.contains(anomaly_id)
+                {
+                    scope.robots.insert(robot_id.clone());
+                }
+            }
+        }
+        scope
+    }
+
+    fn includes_command(&self, command: &CommandAuditEntry) -> bool {
+        command
+            .robot_id
+            .as_ref()
+            .is_some_and(|r| self.robots.contains(r))
+            || command
+                .section_id
+                .as_ref()
+                .is_some_and(|s| self.sections.contains(s))
+            || command
+                .anomaly_id
+                .as_ref()
+                .is_some_and(|a| self.anomaly_ids.contains(a))
+    }
+
+    fn includes_subject(&self, subject: &str) -> bool {
+        self.robots.contains(subject)
+            || self.sections.contains(subject)
+            || self.anomaly_ids.contains(subject)
+    }
+}
+
+fn reports(anomaly: &ActiveAnomaly) -> impl Iterator<Item = &aetheris_shared::AnomalyReport> {
+    std::iter::once(&anomaly.primary).chain(&anomaly.supporting)
+}
+
+fn format_position(p: &Position) -> String {
+    format!("({:.1}, {:.1}, {:.1})", p.x, p.y, p.z)
+}
+
+/// Build the timeline of `focus` between `from` and `to` (Unix milliseconds,
+/// inclusive), ordered by time
+pub fn build_timeline(
+    sources: &TimelineSources,
+    from: u64,
+    to: u64,
+    focus: &TimelineFocus,
+    config: &TimelineConfig,
+) -> Vec<TimelineEntry> {
+    let scope = Scope::resolve(focus, sources);
+    let in_window = |t: u64| (from..=to).contains(&t);
+    let mut entries = Vec::new();
+
+    // One entry per anomaly, linking every report merged into it
+    for anomaly in sources.anomalies {
+        if !scope.anomaly_ids.contains(&anomaly.primary.id) {
+            continue;
+        }
+        let Some(first) = reports(anomaly)
+            .map(|r| r.timestamp)
+            .filter(|t| in_window((*t).as_millis()))
+            .min()
+        else {
+            continue;
+        };
+        let primary = &anomaly.primary;
+        let mut summary = format!(
+            "{:?} {:?} in {} reported by {}: {}",
+            primary.severity,
+            primary.anomaly_type,
+            primary.section_id,
+            primary.detected_by,
+            primary.description
+        );
+        if !anomaly.supporting.is_empty() {
+            summary.push_str(&format!(" (+{} supporting)", anomaly.supporting.len()));
+        }
+        entries.push(TimelineEntry {
+            timestamp: first.as_millis(),
+            kind: TimelineEntryKind::Anomaly,
+            subject: primary.id.clone(),
+            summary,
+            records: reports(anomaly)
+                .map(|r| RecordRef::new(RecordStore::Anomalies, &r.id))
+                .collect(),
+        });
+    }
+
+    // Commands, each absorbing the events it raised
+    let mut command_entries: HashMap<&str, usize> = HashMap::new();
+    for command in sources.commands {
+        if !in_window(command.timestamp) || !scope.includes_command(command) {
+            continue;
+        }
+        let target = command.robot_id.as_deref().unwrap_or("all robots");
+        command_entries.insert(&command.command_id, entries.len());
+        entries.push(TimelineEntry {
+            timestamp: command.timestamp,
+            kind: TimelineEntryKind::Command,
+            subject: target.to_string(),
+            summary: match &command.rejected {
+                None => format!(
+                    "{} sent to {} by {}",
+                    command.variant, target, command.issued_by
+                ),
+                Some(reason) => format!(
+                    "{} to {} by {} refused: {}",
+                    command.variant, target, command.issued_by, reason
+                ),
+            },
+            records: vec![RecordRef::new(RecordStore::Commands, &command.command_id)],
+        });
+    }
+
+    for event in sources.events {
+        if !in_window(event.timestamp) {
+            continue;
+        }
+        if let Some(&index) = event
+            .command_id
+            .as_deref()
+            .and_then(|id| command_entries.get(id))
+        {
+            entries[index]
+                .records
+                .push(RecordRef::new(RecordStore::Events, &event.id));
+            continue;
+        }
+        let Some(subject) = event
+            .subject
+            .as_deref()
+            .filter(|s| scope.includes_subject(s))
+        else {
+            continue;
+        };
+        entries.push(TimelineEntry {
+            timestamp: event.timestamp,
+            kind: TimelineEntryKind::Event,
+            subject: subject.to_string(),
+            summary: format!("{:?}: {}", event.kind, event.detail),
+            records: vec![RecordRef::new(RecordStore::Events, &event.id)],
+        });
+    }
+
+    for robot_id in &scope.robots {
+        let track = sources.history.track(robot_id, from, to);
+        entries.extend(summarize_movements(robot_id, &track, config));
+    }
+
+    entries.sort_by(|a, b| {
+        a.timestamp
+            .cmp(&b.timestamp)
+            .then(a.kind.cmp(&b.kind))
+            .then_with(|| a.subject.cmp(&b.subject))
+    });
+    entries
+}
+
+/// One entry per run of consecutive moving samples whose net displacement
+/// reaches `min_movement`
+fn summarize_movements(
+    robot_id: &str,
+    track: &[&TrackPoint],
+    config: &TimelineConfig,
+) -> Vec<TimelineEntry> {
+    let step = |i: usize| track[i].position.distance_to(&track[i + 1].position);
+    let sample = |p: &TrackPoint| {
+        RecordRef::new(
+            RecordStore::RobotHistory,
+            format!("{}@{}", robot_id, p.timestamp),
+        )
+    };
+    let mut movements = Vec::new();
+    let mut i = 0;
+    while i + 1 < track.len() {
+        if step(i) <= config.stationary_step {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        let mut end = i + 1;
+        while end + 1 < track.len() && step(end) > config.stationary_step {
+            end += 1;
+        }
+        let (a, b) = (track[start], track[end]);
+        let distance = a.position.distance_to(&b.position);
+        if distance >= config.min_movement {
+            movements.push(TimelineEntry {
+                timestamp: a.timestamp,
+                kind: TimelineEntryKind::Movement,
+                subject: robot_id.to_string(),
+                summary: format!(
+                    "{} moved {:.1} m from {} to {} in {:.0} s",
+                    robot_id,
+                    distance,
+                    format_position(&a.position),
+                    format_position(&b.position),
+                    b.timestamp.saturating_sub(a.timestamp) as f64 / 1000.0
+                ),
+                records: vec![sample(a), sample(b)],
+            });
+        }
+        i = end;
+    }
+    movements
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::SystemEventKind;
+    use aetheris_shared::{
+        AnomalyReport, AnomalyType, Command, RobotType, SeverityLevel, Timestamp,
+    };
+
+    const T0: u64 = 1_700_000_000_000;
+
+    fn report(id: &str, section: &str, by: &str, at: u64) -> AnomalyReport {
+        AnomalyReport {
+            id: id.into(),
+            timestamp: Timestamp::from_millis(at),
+            ..AnomalyReport::new(
+                AnomalyType::Leak,
+                SeverityLevel::High,
+                Position::new(100.0, 0.0, 0.0),
+                section,
+                by,
+                0.9,
+                "H2 leak",
+            )
+        }
+    }
+
+    fn anomaly(primary: AnomalyReport, supporting: Vec<AnomalyReport>) -> ActiveAnomaly {
+        ActiveAnomaly {
+            last_seen: primary.timestamp.as_millis(),
+            primary,
+            supporting,
+            duplicates: 0,
+        }
+    }
+
+    fn command(command: Command, robot: &str, at: u64) -> CommandAuditEntry {
+        CommandAuditEntry::new(&command, Some(robot), "engine", at)
+    }
+
+    fn sample(robot: &str, x: f64, at: u64) -> RobotState {
+        let mut state = RobotState::new(robot.parse().unwrap(), robot, RobotType::Rover);
+        state.position = Position::new(x, 0.0, 0.0);
+        state.timestamp = Timestamp::from_millis(at);
+        state
+    }
+
+    /// Leak in PIPE-002 found by RV-001 at T0+60 s, confirmed by the engine,
+    /// DR-001 sent to investigate and flying 40 m; RV-002 patrols PIPE-005
+    struct Incident {
+        anomalies: Vec<ActiveAnomaly>,
+        commands: Vec<CommandAuditEntry>,
+        events: Vec<SystemEvent>,
+        history: RobotHistory,
+    }
+
+    impl Incident {
+        fn new() -> Self {
+            let leak = anomaly(
+                report("ANM-1", "PIPE-002", "RV-001", T0 + 60_000),
+                vec![report("ANM-2", "PIPE-002", ENGINE_ORIGIN, T0 + 62_000)],
+            );
+            let other = anomaly(report("ANM-3", "PIPE-005", "RV-002", T0 + 30_000), vec![]);
+
+            let investigate = command(
+                Command::Investigate {
+                    anomaly_id: "ANM-1".into(),
+                },
+                "DR-001",
+                T0 + 65_000,
+            );
+            let patrol = command(
+                Command::StartPatrol {
+                    route_id: "R-5".into(),
+                },
+                "RV-002",
+                T0 + 10_000,
+            );
+            let events = vec![
+                SystemEvent::new(
+                    SystemEventKind::CommandIssued,
+                    Some("DR-001"),
+                    "investigate",
+                    T0 + 65_000,
+                )
+                .for_command(&investigate.command_id),
+                SystemEvent::new(
+                    SystemEventKind::RobotOffline,
+                    Some("RV-001"),
+                    "heartbeat timeout",
+                    T0 + 90_000,
+                ),
+                SystemEvent::new(
+                    SystemEventKind::RobotOffline,
+                    Some("RV-002"),
+                    "heartbeat timeout",
+                    T0 + 95_000,
+                ),
+            ];
+
+            let mut history = RobotHistory::new(100);
+            // DR-001 hovers, flies 40 m in 4 s, hovers, then jitters in place
+            let xs = [0.0, 0.0, 10.0, 20.0, 30.0, 40.0, 40.0, 40.02, 40.0];
+            for (i, x) in xs.into_iter().enumerate() {
+                history.record(&sample("DR-001", x, T0 + 66_000 + i as u64 * 1000));
+            }
+            for i in 0..5 {
+                history.record(&sample("RV-002", i as f64 * 5.0, T0 + 11_000 + i * 1000));
+            }
+
+            Self {
+                anomalies: vec![leak, other],
+                commands: vec![patrol, investigate],
+                events,
+                history,
+            }
+        }
+
+        fn timeline(&self, from: u64, to: u64, focus: TimelineFocus) -> Vec<TimelineEntry> {
+            let anomalies: Vec<_> = self.anomalies.iter().collect();
+            let commands: Vec<_> = self.commands.iter().collect();
+            let events: Vec<_> = self.events.iter().collect();
+            let sources = TimelineSources {
+                anomalies: &anomalies,
+                commands: &commands,
+                events: &events,
+                history: &self.history,
+            };
+            build_timeline(&sources, from, to, &focus, &TimelineConfig::default())
+        }
+    }
+
+    fn kinds(entries: &[TimelineEntry]) -> Vec<(TimelineEntryKind, &str)> {
+        entries
+            .iter()
+            .map(|e| (e.kind, e.subject.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_anomaly_timeline_is_ordered_and_focused() {
+        let incident = Incident::new();
+        let entries = incident.timeline(T0, T0 + 120_000, TimelineFocus::Anomaly("ANM-2".into()));
+        assert_eq!(
+            kinds(&entries),
+            vec![
+                (TimelineEntryKind::Anomaly, "ANM-1"),
+                (TimelineEntryKind::Command, "DR-001"),
+                (TimelineEntryKind::Movement, "DR-001"),
+                (TimelineEntryKind::Event, "RV-001"),
+            ]
+        );
+        assert!(entries.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+
+        // The other section's anomaly, patrol, and offline event stay out
+        let section =
+            incident.timeline(T0, T0 + 120_000, TimelineFocus::Section("PIPE-005".into()));
+        assert_eq!(
+            kinds(&section),
+            vec![
+                (TimelineEntryKind::Command, "RV-002"),
+                (TimelineEntryKind::Movement, "RV-002"),
+                (TimelineEntryKind::Anomaly, "ANM-3"),
+                (TimelineEntryKind::Event, "RV-002"),
+            ]
+        );
+
+        // The window clips entries
+        let early = incident.timeline(T0, T0 + 61_000, TimelineFocus::Robot("RV-001".into()));
+        assert_eq!(kinds(&early), vec![(TimelineEntryKind::Anomaly, "ANM-1")]);
+    }
+
+    #[test]
+    fn test_merged_records_share_one_entry() {
+        let incident = Incident::new();
+        let entries = incident.timeline(T0, T0 + 120_000, TimelineFocus::Anomaly("ANM-1".into()));
+
+        let anomaly = &entries[0];
+        assert_eq!(
+            anomaly.records,
+            vec![
+                RecordRef::new(RecordStore::Anomalies, "ANM-1"),
+                RecordRef::new(RecordStore::Anomalies, "ANM-2"),
+            ]
+        );
+        assert!(anomaly.summary.ends_with("(+1 supporting)"));
+
+        // The CommandIssued event is folded into its command
+        let command = &entries[1];
+        assert_eq!(command.kind, TimelineEntryKind::Command);
+        assert_eq!(command.records.len(), 2);
+        assert_eq!(command.records[1].store, RecordStore::Events);
+        assert!(
+            !entries
+                .iter()
+                .any(|e| e.kind == TimelineEntryKind::Event && e.subject == "DR-001")
+        );
+    }
+
+    #[test]
+    fn test_movements_are_summarized() {
+        let incident = Incident::new();
+        let entries = incident.timeline(T0, T0 + 120_000, TimelineFocus::Robot("DR-001".into()));
+        let movements: Vec<_> = entries
+            .iter()
+            .filter(|e| e.kind == TimelineEntryKind::Movement)
+            .collect();
+
+        // Nine samples, one move; the 2 cm jitter is not a movement
+        assert_eq!(movements.len(), 1);
+        let movement = movements[0];
+        assert_eq!(movement.timestamp, T0 + 67_000);
+        assert_eq!(
+            movement.summary,
+            "DR-001 moved 40.0 m from (0.0, 0.0, 0.0) to (40.0, 0.0, 0.0) in 4 s"
+        );
+        assert_eq!(
+            movement.records,
+            vec![
+                RecordRef::new(RecordStore::RobotHistory, format!("DR-001@{}", T0 + 67_000)),
+                RecordRef::new(RecordStore::RobotHistory, format!("DR-001@{}", T0 + 71_000)),
+            ]
+        );
+    }
+}
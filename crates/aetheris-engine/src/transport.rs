@@ -0,0 +1,342 @@
+//! Broker transport security: TLS and credentials
+//!
+//! A remote broker is reached over TLS, verified against a CA certificate
+//! (plus a client certificate when the broker asks for one), and usually
+//! wants a username and password. Certificate files are read once at
+//! startup, so a missing file stops the engine before it connects instead
+//! of surfacing in the event loop. Failures no retry will fix, such as
+//! rejected credentials or a failed handshake, are told apart from network
+//! hiccups so the engine can stop with a message saying what to change.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use rumqttc::tokio_rustls::rustls;
+use rumqttc::{ConnectReturnCode, ConnectionError, MqttOptions, TlsConfiguration, Transport};
+use thiserror::Error;
+
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// A value kept out of logs and debug output
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(***)")
+    }
+}
+
+/// TLS settings for the broker connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsConfig {
+    /// PEM file of the CA that signed the broker's certificate
+    pub ca_path: PathBuf,
+    /// PEM certificate and key for brokers that require client
+    /// authentication; both or neither
+    pub client_cert_path: Option<PathBuf>,
+    pub client_key_path: Option<PathBuf>,
+    /// Accept any broker certificate. For development brokers with
+    /// self-signed certificates only.
+    pub insecure_skip_verify: bool,
+    /// ALPN protocols offered in the handshake, most preferred first; a
+    /// broker sharing port 443 with other services routes on them (e.g.
+    /// "mqtt")
+    pub alpn: Vec<String>,
+}
+
+impl TlsConfig {
+    pub fn new(ca_path: impl Into<PathBuf>) -> Self {
+        Self {
+            ca_path: ca_path.into(),
+            client_cert_path: None,
+            client_key_path: None,
+            insecure_skip_verify: false,
+            alpn: Vec::new(),
+        }
+    }
+}
+
+impl CheckConfig for TlsConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        // Without verification the CA is never consulted
+        let ca = (!self.insecure_skip_verify).then_some(&self.ca_path);
+        for (field, path) in [
+            ("ca_path", ca),
+            ("client_cert_path", self.client_cert_path.as_ref()),
+            ("client_key_path", self.client_key_path.as_ref()),
+        ] {
+            if let Some(path) = path
+                && !path.is_file()
+            {
+                checker.error(
+                    field,
+                    format!("no file at {}", path.display()),
+                    Some("give the path of a PEM file readable by the engine".into()),
+                );
+            }
+        }
+        match (&self.client_cert_path, &self.client_key_path) {
+            (Some(_), None) => {
+                checker.error("client_key_path", "is required with client_cert_path", None)
+            }
+            (None, Some(_)) => {
+                checker.error("client_cert_path", "is required with client_key_path", None)
+            }
+            (Some(_), Some(_)) if self.insecure_skip_verify => checker.error(
+                "insecure_skip_verify",
+                "cannot be combined with a client certificate",
+                Some("brokers that authenticate clients should be verified too".into()),
+            ),
+            _ => {}
+        }
+        for protocol in &self.alpn {
+            if protocol.is_empty() || protocol.len() > 255 {
+                checker.error(
+                    "alpn",
+                    format!("protocol names must be 1 to 255 bytes, got {protocol:?}"),
+                    None,
+                );
+            }
+        }
+    }
+}
+
+// ============================================================================
+// TRANSPORT SETUP
+// ============================================================================
+
+/// A certificate file that could not be loaded at startup
+#[derive(Debug, Error)]
+#[error("cannot read TLS {what} at {}: {source}", path.display())]
+pub struct TlsFileError {
+    what: &'static str,
+    path: PathBuf,
+    source: std::io::Error,
+}
+
+fn read(what: &'static str, path: &Path) -> Result<Vec<u8>, TlsFileError> {
+    std::fs::read(path).map_err(|source| TlsFileError {
+        what,
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Set up TLS and credentials on `options`
+pub fn configure(
+    options: &mut MqttOptions,
+    username: Option<&str>,
+    password: Option<&Secret>,
+    tls: Option<&TlsConfig>,
+) -> Result<(), TlsFileError> {
+    if let Some(username) = username {
+        options.set_credentials(username, password.map_or("", Secret::expose));
+    }
+    let Some(tls) = tls else {
+        return Ok(());
+    };
+    let alpn: Vec<Vec<u8>> = tls.alpn.iter().map(|p| p.as_bytes().to_vec()).collect();
+    let transport = if tls.insecure_skip_verify {
+        let mut config = rustls::ClientConfig::builder()
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AcceptAnyCertificate))
+            .with_no_client_auth();
+        config.alpn_protocols = alpn;
+        TlsConfiguration::Rustls(Arc::new(config))
+    } else {
+        let client_auth = match (&tls.client_cert_path, &tls.client_key_path) {
+            (Some(cert), Some(key)) => {
+                Some((read("client certificate", cert)?, read("client key", key)?))
+            }
+            _ => None,
+        };
+        TlsConfiguration::Simple {
+            ca: read("CA certificate", &tls.ca_path)?,
+            alpn: (!alpn.is_empty()).then_some(alpn),
+            client_auth,
+        }
+    };
+    options.set_transport(Transport::tls_with_config(transport));
+    Ok(())
+}
+
+/// Verifier for `insecure_skip_verify`: trusts every certificate
+#[derive(Debug)]
+struct AcceptAnyCertificate;
+
+impl rustls::client::danger::ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &rustls::pki_types::CertificateDer<'_>,
+        _intermediates: &[rustls::pki_types::CertificateDer<'_>],
+        _server_name: &rustls::pki_types::ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: rustls::pki_types::UnixTime,
+    ) -> Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
+        Ok(rustls::client::danger::ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _message: &[u8],
+        _cert: &rustls::pki_types::CertificateDer<'_>,
+        _dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        Ok(rustls::client::danger::HandshakeSignatureValid::assertion())
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        _message: &[u8],
+        _cert: &rustls::pki_types::CertificateDer<'_>,
+        _dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        Ok(rustls::client::danger::HandshakeSignatureValid::assertion())
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        rustls::crypto::ring::default_provider()
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+// ============================================================================
+// CONNECTION FAILURES
+// ============================================================================
+
+/// A connection failure that retrying will not fix
+#[derive(Debug, Error)]
+pub enum FatalConnectError {
+    #[error("broker rejected the credentials ({0:?}); check mqtt.username and mqtt.password")]
+    Credentials(ConnectReturnCode),
+    #[error(
+        "TLS handshake with the broker failed: {0}; check mqtt.tls and that mqtt.broker_host matches the broker certificate"
+    )]
+    Tls(String),
+}
+
+impl FatalConnectError {
+    /// The fatal failure behind `error`, or `None` if reconnecting may help
+    pub fn classify(error: &ConnectionError) -> Option<Self> {
+        match error {
+            ConnectionError::ConnectionRefused(
+                code @ (ConnectReturnCode::BadUserNamePassword | ConnectReturnCode::NotAuthorized),
+            ) => Some(Self::Credentials(*code)),
+            // A network error during the handshake is worth another try
+            ConnectionError::Tls(rumqttc::TlsError::Io(_)) => None,
+            ConnectionError::Tls(e) => Some(Self::Tls(e.to_string())),
+            _ => None,
+        }
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_certificate_files_are_reported() {
+        let mut checker = ConfigChecker::default();
+        checker.check_section(
+            "tls",
+            &TlsConfig {
+                client_cert_path: Some("/nonexistent/client.pem".into()),
+                alpn: vec!["mqtt".into(), String::new()],
+                ..TlsConfig::new("/nonexistent/ca.pem")
+            },
+        );
+        let paths: Vec<_> = checker
+            .finish()
+            .unwrap_err()
+            .issues
+            .into_iter()
+            .map(|issue| issue.path)
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "tls.ca_path",
+                "tls.client_cert_path",
+                "tls.client_key_path",
+                "tls.alpn"
+            ]
+        );
+
+        let mut options = MqttOptions::new("engine", "broker", 8883);
+        let error = configure(
+            &mut options,
+            None,
+            None,
+            Some(&TlsConfig::new("/nonexistent/ca.pem")),
+        )
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("CA certificate at /nonexistent/ca.pem")
+        );
+    }
+
+    #[test]
+    fn test_credentials_and_tls_failures_are_fatal() {
+        let refused = ConnectionError::ConnectionRefused(ConnectReturnCode::BadUserNamePassword);
+        assert!(matches!(
+            FatalConnectError::classify(&refused),
+            Some(FatalConnectError::Credentials(_))
+        ));
+        let handshake = ConnectionError::Tls(rumqttc::TlsError::NoValidCertInChain);
+        assert!(matches!(
+            FatalConnectError::classify(&handshake),
+            Some(FatalConnectError::Tls(_))
+        ));
+        assert!(FatalConnectError::classify(&ConnectionError::NetworkTimeout).is_none());
+        assert!(
+            FatalConnectError::classify(&ConnectionError::ConnectionRefused(
+                ConnectReturnCode::ServiceUnavailable
+            ))
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_insecure_transport_builds_and_password_stays_hidden() {
+        let mut options = MqttOptions::new("engine", "broker", 8883);
+        let password = Secret::new("hunter2");
+        let tls = TlsConfig {
+            insecure_skip_verify: true,
+            alpn: vec!["mqtt".into()],
+            ..TlsConfig::new("/unused/ca.pem")
+        };
+        configure(&mut options, Some("engine"), Some(&password), Some(&tls)).unwrap();
+        assert_eq!(
+            options.credentials(),
+            Some(("engine".to_string(), "hunter2".to_string()))
+        );
+        let Transport::Tls(TlsConfiguration::Rustls(config)) = options.transport() else {
+            panic!("expected a rustls transport");
+        };
+        assert_eq!(config.alpn_protocols, [b"mqtt".to_vec()]);
+        assert!(!format!("{password:?}").contains("hunter2"));
+    }
+}
@@ -0,0 +1,454 @@
+//! Rate-of-change detection on battery and signal
+//!
+//! A battery that drains fast or a signal that declines steadily predicts a
+//! failure before any absolute threshold is crossed. Each robot keeps a short
+//! history of samples; the smoothed derivative is the least-squares slope
+//! over a trailing window. Rising values (charging) never alert, and after a
+//! data gap the history restarts and must warm up again before rates count.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use aetheris_shared::{
+    AnomalyReport, AnomalyType, Measurement, Position, SeverityLevel, Timestamp,
+};
+
+use crate::anomalies::SYSTEM_SECTION;
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Decline rates at which alerts are raised
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RateThresholds {
+    /// Decline rate raising a Low alert
+    pub low: f64,
+    /// Decline rate raising a Medium alert
+    pub medium: f64,
+}
+
+/// Trend detection behavior
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrendConfig {
+    /// Battery discharge thresholds in percent per minute
+    pub battery_discharge: RateThresholds,
+    /// Signal decline thresholds in percent per hour
+    pub signal_decline: RateThresholds,
+    /// Window the battery slope is fitted over
+    #[serde(with = "crate::config::duration_secs")]
+    pub battery_window: Duration,
+    /// Window the signal slope is fitted over
+    #[serde(with = "crate::config::duration_secs")]
+    pub signal_window: Duration,
+    /// Samples needed after a (re)start before rates are evaluated
+    pub warmup_samples: usize,
+    /// Silence after which the history is discarded
+    #[serde(with = "crate::config::duration_secs")]
+    pub max_gap: Duration,
+}
+
+impl Default for TrendConfig {
+    fn default() -> Self {
+        Self {
+            // Normal patrol drain is well under 1%/min; 15% in 2 min is 7.5%/min
+            battery_discharge: RateThresholds {
+                low: 3.0,
+                medium: 6.0,
+            },
+            signal_decline: RateThresholds {
+                low: 20.0,
+                medium: 40.0,
+            },
+            battery_window: Duration::from_secs(120),
+            signal_window: Duration::from_secs(600),
+            warmup_samples: 5,
+            max_gap: Duration::from_secs(30),
+        }
+    }
+}
+
+impl CheckConfig for RateThresholds {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if self.low.is_nan() || self.low <= 0.0 {
+            checker.error("low", "must be greater than zero", None);
+        }
+        if self.medium.is_nan() || self.medium <= self.low {
+            checker.error(
+                "medium",
+                format!("must be above low ({}), got {}", self.low, self.medium),
+                None,
+            );
+        }
+    }
+}
+
+impl CheckConfig for TrendConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        checker.check_section("battery_discharge", &self.battery_discharge);
+        checker.check_section("signal_decline", &self.signal_decline);
+        checker.positive("battery_window", self.battery_window);
+        checker.positive("signal_window", self.signal_window);
+        if self.warmup_samples < 2 {
+            checker.error("warmup_samples", "a slope needs at least 2 samples", None);
+        }
+        checker.positive("max_gap", self.max_gap);
+    }
+}
+
+// ============================================================================
+// DETECTOR
+// ============================================================================
+
+/// Current smoothed rates for one robot
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RobotTrends {
+    /// Battery change in percent per minute (negative = discharging)
+    pub battery_rate_per_min: Option<f64>,
+    /// Signal change in percent per hour (negative = declining)
+    pub signal_rate_per_hour: Option<f64>,
+    /// Battery is discharging faster than the Low threshold
+    pub battery_degrading: bool,
+    /// Signal is declining faster than the Low threshold
+    pub signal_degrading: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    timestamp: u64,
+    battery: f64,
+    signal: f64,
+}
+
+#[derive(Debug, Default)]
+struct RobotHistory {
+    samples: VecDeque<Sample>,
+    /// Samples received since the last (re)start
+    since_restart: usize,
+    /// Highest severity already alerted in the current degradation episode
+    battery_alerted: Option<SeverityLevel>,
+    signal_alerted: Option<SeverityLevel>,
+    trends: Option<RobotTrends>,
+}
+
+/// Per-robot battery and signal trend tracking
+#[derive(Debug, Default)]
+pub struct TrendDetector {
+    config: TrendConfig,
+    robots: HashMap<String, RobotHistory>,
+}
+
+impl TrendDetector {
+    pub fn new(config: TrendConfig) -> Self {
+        Self {
+            config,
+            robots: HashMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> &TrendConfig {
+        &self.config
+    }
+
+    /// Current rates for a robot, once warmed up
+    pub fn trends(&self, robot_id: &str) -> Option<RobotTrends> {
+        self.robots.get(robot_id)?.trends
+    }
+
+    /// Record a sample, returning any alerts for newly crossed thresholds
+    pub fn observe(
+        &mut self,
+        robot_id: &str,
+        battery: f64,
+        signal: f64,
+        position: Position,
+        timestamp: u64,
+    ) -> Vec<AnomalyReport> {
+        let config = &self.config;
+        let history = self.robots.entry(robot_id.to_string()).or_default();
+
+        let gap_ms = config.max_gap.as_millis() as u64;
+        let restarted = history
+            .samples
+            .back()
+            .is_some_and(|last| timestamp < last.timestamp || timestamp - last.timestamp > gap_ms);
+        if restarted {
+            history.samples.clear();
+            history.since_restart = 0;
+            history.trends = None;
+        }
+
+        history.samples.push_back(Sample {
+            timestamp,
+            battery,
+            signal,
+        });
+        history.since_restart += 1;
+        let longest = config.battery_window.max(config.signal_window).as_millis() as u64;
+        while history
+            .samples
+            .front()
+            .is_some_and(|s| timestamp - s.timestamp > longest)
+        {
+            history.samples.pop_front();
+        }
+
+        if history.since_restart < config.warmup_samples {
+            return Vec::new();
+        }
+
+        let battery_rate = slope_per_ms(&history.samples, config.battery_window, |s| s.battery)
+            .map(|slope| slope * 60_000.0);
+        let signal_rate = slope_per_ms(&history.samples, config.signal_window, |s| s.signal)
+            .map(|slope| slope * 3_600_000.0);
+
+        let battery_severity = decline_severity(battery_rate, &config.battery_discharge);
+        let signal_severity = decline_severity(signal_rate, &config.signal_decline);
+        history.trends = Some(RobotTrends {
+            battery_rate_per_min: battery_rate,
+            signal_rate_per_hour: signal_rate,
+            battery_degrading: battery_severity.is_some(),
+            signal_degrading: signal_severity.is_some(),
+        });
+
+        let mut alerts = Vec::new();
+        if escalated(&mut history.battery_alerted, battery_severity)
+            && let (Some(severity), Some(rate)) = (battery_severity, battery_rate)
+        {
+            alerts.push(trend_alert(
+                robot_id,
+                severity,
+                position,
+                timestamp,
+                "battery_discharge_rate",
+                -rate,
+                "%/min",
+                format!(
+                    "Robot {} battery discharging at {:.1}%/min",
+                    robot_id, -rate
+                ),
+            ));
+        }
+        if escalated(&mut history.signal_alerted, signal_severity)
+            && let (Some(severity), Some(rate)) = (signal_severity, signal_rate)
+        {
+            alerts.push(trend_alert(
+                robot_id,
+                severity,
+                position,
+                timestamp,
+                "signal_decline_rate",
+                -rate,
+                "%/h",
+                format!("Robot {} signal declining at {:.1}%/h", robot_id, -rate),
+            ));
+        }
+        alerts
+    }
+}
+
+/// Least-squares slope (units per millisecond) over the trailing `window`.
+///
+/// Returns `None` until the samples cover at least half the window.
+fn slope_per_ms(
+    samples: &VecDeque<Sample>,
+    window: Duration,
+    value: impl Fn(&Sample) -> f64,
+) -> Option<f64> {
+    let latest = samples.back()?.timestamp;
+    let window_ms = window.as_millis() as u64;
+    let points: Vec<(f64, f64)> = samples
+        .iter()
+        .filter(|s| latest - s.timestamp <= window_ms)
+        .map(|s| (s.timestamp as f64 - latest as f64, value(s)))
+        .collect();
+    // A fit over a sliver of the window is dominated by sample noise
+    let span = points.first().map_or(0.0, |(t, _)| -t);
+    if points.len() < 2 || span < window_ms as f64 / 2.0 {
+        return None;
+    }
+
+    let n = points.len() as f64;
+    let mean_t = points.iter().map(|(t, _)| t).sum::<f64>() / n;
+    let mean_v = points.iter().map(|(_, v)| v).sum::<f64>() / n;
+    let (cov, var) = points.iter().fold((0.0, 0.0), |(cov, var), (t, v)| {
+        (
+            cov + (t - mean_t) * (v - mean_v),
+            var + (t - mean_t).powi(2),
+        )
+    });
+    (var > 0.0).then(|| cov / var)
+}
+
+/// Severity for a rate of change; rising values never alert
+fn decline_severity(rate: Option<f64>, thresholds: &RateThresholds) -> Option<SeverityLevel> {
+    let decline = -rate?;
+    if decline >= thresholds.medium {
+        Some(SeverityLevel::Medium)
+    } else if decline >= thresholds.low {
+        Some(SeverityLevel::Low)
+    } else {
+        None
+    }
+}
+
+/// Track the alerted severity for an episode; true if `current` is new or higher
+pub(crate) fn escalated(
+    alerted: &mut Option<SeverityLevel>,
+    current: Option<SeverityLevel>,
+) -> bool {
+    match current {
+        None => {
+            *alerted = None;
+            false
+        }
+        Some(severity) if alerted.is_none_or(|previous| severity > previous) => {
+            *alerted = Some(severity);
+            true
+        }
+        Some(_) => false,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn trend_alert(
+    robot_id: &str,
+    severity: SeverityLevel,
+    position: Position,
+    timestamp: u64,
+    name: &str,
+    value: f64,
+    unit: &str,
+    description: String,
+) -> AnomalyReport {
+    AnomalyReport {
+        timestamp: Timestamp::from_millis(timestamp),
+        measurement: Some(Measurement {
+            name: name.into(),
+            value,
+            unit: unit.into(),
+        }),
+        ..AnomalyReport::new(
+            AnomalyType::Unknown,
+            severity,
+            position,
+            SYSTEM_SECTION,
+            robot_id,
+            0.8,
+            description,
+        )
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed one sample per second with battery/signal given by `f(second)`
+    fn ramp(
+        detector: &mut TrendDetector,
+        start_s: u64,
+        seconds: u64,
+        f: impl Fn(u64) -> (f64, f64),
+    ) -> Vec<AnomalyReport> {
+        (start_s..start_s + seconds)
+            .flat_map(|s| {
+                let (battery, signal) = f(s - start_s);
+                detector.observe("RV-001", battery, signal, Position::origin(), s * 1000)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_discharge_rates_straddling_thresholds() {
+        // 2.5%/min: below Low
+        let mut detector = TrendDetector::default();
+        let alerts = ramp(&mut detector, 0, 180, |s| {
+            (90.0 - s as f64 * 2.5 / 60.0, 80.0)
+        });
+        assert!(alerts.is_empty());
+        let trends = detector.trends("RV-001").unwrap();
+        assert!((trends.battery_rate_per_min.unwrap() + 2.5).abs() < 1e-6);
+        assert!(!trends.battery_degrading);
+
+        // 3.5%/min: one Low alert with the measured rate
+        let mut detector = TrendDetector::default();
+        let alerts = ramp(&mut detector, 0, 180, |s| {
+            (90.0 - s as f64 * 3.5 / 60.0, 80.0)
+        });
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].severity, SeverityLevel::Low);
+        let measurement = alerts[0].measurement.as_ref().unwrap();
+        assert_eq!(measurement.name, "battery_discharge_rate");
+        assert!((measurement.value - 3.5).abs() < 1e-6);
+        assert!(alerts[0].description.contains("3.5%/min"));
+        assert!(detector.trends("RV-001").unwrap().battery_degrading);
+
+        // 15% in two minutes: Medium
+        let mut detector = TrendDetector::default();
+        let alerts = ramp(&mut detector, 0, 120, |s| {
+            (90.0 - s as f64 * 7.5 / 60.0, 80.0)
+        });
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].severity, SeverityLevel::Medium);
+    }
+
+    #[test]
+    fn test_steady_signal_decline_alerts() {
+        let mut detector = TrendDetector::default();
+        // 30%/h over 20 minutes, with sampling noise
+        let alerts = ramp(&mut detector, 0, 1200, |s| {
+            let noise = if s % 2 == 0 { 0.3 } else { -0.3 };
+            (80.0, 95.0 - s as f64 * 30.0 / 3600.0 + noise)
+        });
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].severity, SeverityLevel::Low);
+        assert_eq!(
+            alerts[0].measurement.as_ref().unwrap().name,
+            "signal_decline_rate"
+        );
+        assert!(detector.trends("RV-001").unwrap().signal_degrading);
+    }
+
+    #[test]
+    fn test_charging_never_alerts() {
+        let mut detector = TrendDetector::default();
+        let alerts = ramp(&mut detector, 0, 300, |s| (20.0 + s as f64 * 0.2, 80.0));
+        assert!(alerts.is_empty());
+        assert!(
+            detector
+                .trends("RV-001")
+                .unwrap()
+                .battery_rate_per_min
+                .unwrap()
+                > 0.0
+        );
+    }
+
+    #[test]
+    fn test_restart_after_gap_waits_for_warmup() {
+        let mut detector = TrendDetector::default();
+        ramp(&mut detector, 0, 60, |_| (90.0, 80.0));
+
+        // After a 5-minute gap the battery reads 20% lower; the jump across the
+        // gap and the first few samples must not count as a discharge
+        let alerts = ramp(&mut detector, 360, 4, |_| (70.0, 80.0));
+        assert!(alerts.is_empty());
+        assert_eq!(detector.trends("RV-001"), None);
+
+        let alerts = ramp(&mut detector, 364, 60, |_| (70.0, 80.0));
+        assert!(alerts.is_empty());
+        assert_eq!(
+            detector.trends("RV-001").unwrap().battery_rate_per_min,
+            Some(0.0)
+        );
+    }
+}
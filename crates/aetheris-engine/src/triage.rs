@@ -0,0 +1,338 @@
+//! AI triage of anomalies by the Brain
+//!
+//! When triage is enabled, new non-Critical alerts are held back and sent to
+//! the Brain as a [`TriageRequest`]. The report is released for dispatch once
+//! a [`TriageResult`] arrives (with the Brain's severity and confidence
+//! applied and recorded in a [`TriageAudit`]) or the timeout elapses, in
+//! which case it goes out unchanged. Critical alerts never wait for triage.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Duration;
+
+use aetheris_shared::{
+    AnomalyReport, NearbyRobot, PipeEnvironment, SeverityLevel, TriageAudit, TriageRequest,
+    TriageResult,
+};
+
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Triage behavior
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriageConfig {
+    /// Whether alerts are sent to the Brain before dispatch
+    pub enabled: bool,
+    /// How long to wait for a result before releasing the report untriaged
+    pub timeout: Duration,
+    /// Maximum number of nearby robots included as context
+    pub max_nearby_robots: usize,
+}
+
+impl Default for TriageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout: Duration::from_secs(10),
+            max_nearby_robots: 5,
+        }
+    }
+}
+
+impl CheckConfig for TriageConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if self.enabled {
+            checker.positive("timeout", self.timeout);
+        }
+        if self.max_nearby_robots == 0 {
+            checker.error(
+                "max_nearby_robots",
+                "must be greater than zero",
+                Some("the Brain needs at least one candidate; the default is 5".into()),
+            );
+        }
+    }
+}
+
+// ============================================================================
+// COORDINATOR
+// ============================================================================
+
+/// What to do with a newly received alert
+#[derive(Debug, Clone, PartialEq)]
+pub enum TriageDecision {
+    /// Release for dispatch immediately (triage disabled or Critical)
+    Bypass(AnomalyReport),
+    /// Held until the Brain answers; publish this request
+    Deferred(TriageRequest),
+    /// Already triaged or awaiting triage; nothing to do
+    Ignore,
+}
+
+#[derive(Debug)]
+struct PendingTriage {
+    report: AnomalyReport,
+    deadline_ms: u64,
+}
+
+/// Tracks alerts awaiting triage and applies results
+#[derive(Debug, Default)]
+pub struct TriageCoordinator {
+    config: TriageConfig,
+    pending: HashMap<String, PendingTriage>,
+    /// Recently released report ids, so republished reports are not re-triaged
+    released: HashSet<String>,
+    released_order: VecDeque<String>,
+}
+
+const RELEASED_MEMORY: usize = 1024;
+
+impl TriageCoordinator {
+    pub fn new(config: TriageConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    pub fn config(&self) -> &TriageConfig {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: TriageConfig) {
+        self.config = config;
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Decide how to handle a newly received alert.
+    ///
+    /// `nearby_robots` should be sorted nearest first; it is truncated to the
+    /// configured maximum.
+    pub fn on_alert(
+        &mut self,
+        report: AnomalyReport,
+        mut nearby_robots: Vec<NearbyRobot>,
+        latest_environment: Option<PipeEnvironment>,
+        now_ms: u64,
+    ) -> TriageDecision {
+        if self.pending.contains_key(&report.id) || self.released.contains(&report.id) {
+            return TriageDecision::Ignore;
+        }
+
+        if !self.config.enabled || report.severity == SeverityLevel::Critical {
+            self.remember_released(&report.id);
+            return TriageDecision::Bypass(report);
+        }
+
+        nearby_robots.truncate(self.config.max_nearby_robots);
+        let request = TriageRequest {
+            report: report.clone(),
+            nearby_robots,
+            latest_environment,
+            timestamp: now_ms,
+        };
+        self.pending.insert(
+            report.id.clone(),
+            PendingTriage {
+                report,
+                deadline_ms: now_ms + self.config.timeout.as_millis() as u64,
+            },
+        );
+        TriageDecision::Deferred(request)
+    }
+
+    /// Apply a triage result, returning the adjusted report to release.
+    ///
+    /// Results for unknown or already timed-out anomalies are ignored.
+    pub fn on_result(&mut self, result: &TriageResult, now_ms: u64) -> Option<AnomalyReport> {
+        let PendingTriage { mut report, .. } = self.pending.remove(&result.anomaly_id)?;
+
+        report.triage = Some(TriageAudit {
+            original_severity: report.severity,
+            original_confidence: report.confidence,
+            recommended_action: Some(result.recommended_action),
+            rationale: result.rationale.clone(),
+            timed_out: false,
+            completed_at: now_ms,
+        });
+        if let Some(severity) = result.adjusted_severity {
+            report.severity = severity;
+        }
+        if let Some(confidence) = result.adjusted_confidence {
+            report.confidence = confidence.clamp(0.0, 1.0);
+        }
+
+        self.remember_released(&report.id);
+        Some(report)
+    }
+
+    /// Release every report whose triage deadline has passed, untriaged
+    pub fn expire(&mut self, now_ms: u64) -> Vec<AnomalyReport> {
+        let expired: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| pending.deadline_ms <= now_ms)
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        let mut released = Vec::with_capacity(expired.len());
+        for id in expired {
+            let Some(PendingTriage { mut report, .. }) = self.pending.remove(&id) else {
+                continue;
+            };
+            report.triage = Some(TriageAudit {
+                original_severity: report.severity,
+                original_confidence: report.confidence,
+                recommended_action: None,
+                rationale: "Triage timed out; released untriaged".into(),
+                timed_out: true,
+                completed_at: now_ms,
+            });
+            self.remember_released(&report.id);
+            released.push(report);
+        }
+        released.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then_with(|| a.id.cmp(&b.id)));
+        released
+    }
+
+    fn remember_released(&mut self, id: &str) {
+        if self.released.insert(id.to_string()) {
+            self.released_order.push_back(id.to_string());
+            if self.released_order.len() > RELEASED_MEMORY
+                && let Some(oldest) = self.released_order.pop_front()
+            {
+                self.released.remove(&oldest);
+            }
+        }
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetheris_shared::{AnomalyType, Position, TriageAction};
+
+    fn enabled() -> TriageCoordinator {
+        TriageCoordinator::new(TriageConfig {
+            enabled: true,
+            ..TriageConfig::default()
+        })
+    }
+
+    fn report(severity: SeverityLevel) -> AnomalyReport {
+        AnomalyReport::new(
+            AnomalyType::Leak,
+            severity,
+            Position::origin(),
+            "PIPE-001",
+            "RV-001",
+            0.8,
+            "Possible leak",
+        )
+    }
+
+    fn result(anomaly_id: &str) -> TriageResult {
+        TriageResult {
+            anomaly_id: anomaly_id.into(),
+            adjusted_severity: Some(SeverityLevel::Low),
+            adjusted_confidence: Some(0.35),
+            recommended_action: TriageAction::Monitor,
+            rationale: "Matches scheduled purge".into(),
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn test_defer_then_result_applies_adjustment_with_audit() {
+        let mut triage = enabled();
+        let report = report(SeverityLevel::High);
+        let id = report.id.clone();
+
+        let TriageDecision::Deferred(request) = triage.on_alert(report, Vec::new(), None, 1_000)
+        else {
+            panic!("expected deferral");
+        };
+        assert_eq!(request.report.id, id);
+        assert_eq!(triage.pending_count(), 1);
+
+        let triaged = triage.on_result(&result(&id), 3_000).unwrap();
+        assert_eq!(triaged.severity, SeverityLevel::Low);
+        assert_eq!(triaged.confidence, 0.35);
+        let audit = triaged.triage.unwrap();
+        assert_eq!(audit.original_severity, SeverityLevel::High);
+        assert_eq!(audit.original_confidence, 0.8);
+        assert_eq!(audit.recommended_action, Some(TriageAction::Monitor));
+        assert!(!audit.timed_out);
+        assert_eq!(audit.completed_at, 3_000);
+        assert_eq!(triage.pending_count(), 0);
+    }
+
+    #[test]
+    fn test_timeout_releases_untriaged_report() {
+        let mut triage = enabled();
+        let report = report(SeverityLevel::Medium);
+        let id = report.id.clone();
+        triage.on_alert(report, Vec::new(), None, 0);
+
+        assert!(triage.expire(9_999).is_empty());
+        let released = triage.expire(10_000);
+        assert_eq!(released.len(), 1);
+        assert_eq!(released[0].severity, SeverityLevel::Medium);
+        assert!(released[0].triage.as_ref().unwrap().timed_out);
+
+        // A late result is ignored
+        assert!(triage.on_result(&result(&id), 12_000).is_none());
+    }
+
+    #[test]
+    fn test_critical_alerts_bypass_triage() {
+        let mut triage = enabled();
+        let decision = triage.on_alert(report(SeverityLevel::Critical), Vec::new(), None, 0);
+        assert!(matches!(decision, TriageDecision::Bypass(_)));
+        assert_eq!(triage.pending_count(), 0);
+    }
+
+    #[test]
+    fn test_disabled_triage_bypasses_and_republished_reports_are_ignored() {
+        let mut triage = TriageCoordinator::default();
+        let report = report(SeverityLevel::High);
+        assert!(matches!(
+            triage.on_alert(report.clone(), Vec::new(), None, 0),
+            TriageDecision::Bypass(_)
+        ));
+        assert_eq!(
+            triage.on_alert(report, Vec::new(), None, 0),
+            TriageDecision::Ignore
+        );
+    }
+
+    #[test]
+    fn test_request_context_is_truncated() {
+        let mut triage = enabled();
+        let robots = (0..8)
+            .map(|i| NearbyRobot {
+                robot_id: format!("RV-00{i}"),
+                robot_type: aetheris_shared::RobotType::Rover,
+                status: aetheris_shared::RobotStatus::Idle,
+                battery: 80.0,
+                distance: i as f64,
+            })
+            .collect();
+        let TriageDecision::Deferred(request) =
+            triage.on_alert(report(SeverityLevel::Low), robots, None, 0)
+        else {
+            panic!("expected deferral");
+        };
+        assert_eq!(request.nearby_robots.len(), 5);
+        assert_eq!(request.nearby_robots[0].robot_id, "RV-000");
+    }
+}
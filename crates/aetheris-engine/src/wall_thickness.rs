@@ -0,0 +1,495 @@
+//! Wall-thickness trends and predictive wall-thinning alerts
+//!
+//! Corrosion thins a pipe wall over months, long before any single
+//! ultrasonic reading looks alarming. Each section averages its readings
+//! into one sample per sample interval and keeps a bounded history of them.
+//! A least-squares line through the history gives the loss rate and
+//! projects when the wall reaches the minimum allowed thickness; a breach
+//! projected within the horizon raises a WallThinning report, more severe
+//! the sooner the breach, and again only if the severity rises.
+//!
+//! A fit only counts once the history is long enough and the slope stands
+//! clear of the sample noise. Walls do not grow back, so a thickening fit
+//! is sensor noise and never alerts.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use aetheris_shared::{AnomalyReport, AnomalyType, Measurement, PipeEnvironment, SeverityLevel};
+
+use crate::anomalies::ENGINE_ORIGIN;
+use crate::config::{CheckConfig, ConfigChecker};
+use crate::trends::escalated;
+
+const MS_PER_DAY: f64 = 86_400_000.0;
+const DAYS_PER_YEAR: f64 = 365.25;
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Wall-thinning projection behavior
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WallThicknessConfig {
+    /// Thickness a wall must keep, in millimeters
+    pub minimum_mm: f64,
+    /// Breaches projected further out than this do not alert
+    #[serde(with = "crate::config::duration_secs")]
+    pub horizon: Duration,
+    /// Readings within one interval are averaged into one sample
+    #[serde(with = "crate::config::duration_secs")]
+    pub sample_interval: Duration,
+    /// Samples kept per section
+    pub max_samples: usize,
+    /// Samples needed before the fit counts
+    pub min_samples: usize,
+    /// How many standard errors the loss rate must stand above zero
+    pub min_significance: f64,
+}
+
+impl Default for WallThicknessConfig {
+    fn default() -> Self {
+        Self {
+            minimum_mm: 6.0,
+            horizon: Duration::from_secs(180 * 86_400),
+            sample_interval: Duration::from_secs(3_600),
+            // Ninety days of hourly samples
+            max_samples: 2_160,
+            min_samples: 24,
+            min_significance: 3.0,
+        }
+    }
+}
+
+impl CheckConfig for WallThicknessConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        if !(self.minimum_mm > 0.0 && self.minimum_mm.is_finite()) {
+            checker.error("minimum_mm", "must be greater than zero", None);
+        }
+        checker.positive("horizon", self.horizon);
+        checker.positive("sample_interval", self.sample_interval);
+        if self.min_samples < 3 {
+            checker.error(
+                "min_samples",
+                "a fit with a spread needs at least 3 samples",
+                None,
+            );
+        }
+        if self.max_samples < self.min_samples {
+            checker.error(
+                "max_samples",
+                format!(
+                    "must be at least min_samples ({}), got {}",
+                    self.min_samples, self.max_samples
+                ),
+                None,
+            );
+        }
+        if !(self.min_significance >= 0.0 && self.min_significance.is_finite()) {
+            checker.error("min_significance", "must not be negative", None);
+        }
+    }
+}
+
+// ============================================================================
+// ANALYZER
+// ============================================================================
+
+/// Current wall-thickness trend of one section
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SectionWallTrend {
+    pub section_id: String,
+    /// Latest fitted thickness in millimeters
+    pub thickness_mm: f64,
+    /// Loss rate in millimeters per year; negative when the fit thickens
+    pub loss_rate_mm_per_year: f64,
+    /// Whether the loss rate stands clear of the sample noise
+    pub significant: bool,
+    /// Unix timestamp the wall is projected to reach the minimum
+    /// (milliseconds), for a significant loss
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub breach_at: Option<u64>,
+    pub samples: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    timestamp: u64,
+    thickness: f64,
+}
+
+/// Readings averaged into the sample being built
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    started: u64,
+    sum: f64,
+    count: u32,
+}
+
+#[derive(Debug, Default)]
+struct SectionHistory {
+    samples: VecDeque<Sample>,
+    bucket: Option<Bucket>,
+    /// Highest severity already alerted while the projection stays in the
+    /// horizon
+    alerted: Option<SeverityLevel>,
+    trend: Option<SectionWallTrend>,
+}
+
+/// Per-section wall-thickness trend tracking
+#[derive(Debug, Default)]
+pub struct WallThicknessTrends {
+    config: WallThicknessConfig,
+    sections: HashMap<String, SectionHistory>,
+}
+
+impl WallThicknessTrends {
+    pub fn new(config: WallThicknessConfig) -> Self {
+        Self {
+            config,
+            sections: HashMap::new(),
+        }
+    }
+
+    /// Current trend of a section, once it has enough samples
+    pub fn trend(&self, section_id: &str) -> Option<&SectionWallTrend> {
+        self.sections.get(section_id)?.trend.as_ref()
+    }
+
+    /// Trends of every section with enough samples, ordered by section
+    pub fn snapshot(&self) -> Vec<SectionWallTrend> {
+        let mut trends: Vec<_> = self
+            .sections
+            .values()
+            .filter_map(|history| history.trend.clone())
+            .collect();
+        trends.sort_by(|a, b| a.section_id.cmp(&b.section_id));
+        trends
+    }
+
+    /// Fold the samples of section `from` into section `into`, after a
+    /// provisional section was merged, and refit `into`
+    pub fn merge_section(&mut self, from: &str, into: &str) {
+        let Some(merged) = self.sections.remove(from) else {
+            return;
+        };
+        let history = self.sections.entry(into.to_string()).or_default();
+        let mut samples: Vec<Sample> = history.samples.drain(..).chain(merged.samples).collect();
+        samples.sort_by_key(|sample| sample.timestamp);
+        let skip = samples.len().saturating_sub(self.config.max_samples);
+        history.samples = samples.into_iter().skip(skip).collect();
+        if history.bucket.is_none() {
+            history.bucket = merged.bucket;
+        }
+        history.alerted = history.alerted.max(merged.alerted);
+        history.trend = None;
+        history.refit(into, &self.config);
+    }
+
+    /// Record a reading, returning a WallThinning report when the projected
+    /// breach is newly within the horizon or more severe than last reported
+    pub fn observe(&mut self, reading: &PipeEnvironment) -> Option<AnomalyReport> {
+        if !reading.wall_thickness.is_finite() {
+            return None;
+        }
+        let config = &self.config;
+        let history = self.sections.entry(reading.section_id.clone()).or_default();
+
+        let interval_ms = config.sample_interval.as_millis() as u64;
+        let bucket = history.bucket.get_or_insert(Bucket {
+            started: reading.timestamp.as_millis(),
+            sum: 0.0,
+            count: 0,
+        });
+        if reading.timestamp.as_millis().saturating_sub(bucket.started) < interval_ms {
+            bucket.sum += reading.wall_thickness;
+            bucket.count += 1;
+            return None;
+        }
+        // The interval is over: its average becomes a sample and the
+        // reading starts the next one
+        let closed = std::mem::replace(
+            bucket,
+            Bucket {
+                started: reading.timestamp.as_millis(),
+                sum: reading.wall_thickness,
+                count: 1,
+            },
+        );
+        if closed.count > 0 {
+            if history.samples.len() == config.max_samples {
+                history.samples.pop_front();
+            }
+            history.samples.push_back(Sample {
+                timestamp: closed.started + interval_ms / 2,
+                thickness: closed.sum / f64::from(closed.count),
+            });
+        }
+        let fit = history.refit(&reading.section_id, config)?;
+        let latest = history.samples.back()?.timestamp;
+        let trend = history.trend.as_ref()?;
+        let (thickness, loss_rate, breach_at) = (
+            trend.thickness_mm,
+            trend.loss_rate_mm_per_year,
+            trend.breach_at,
+        );
+
+        let severity = breach_at.and_then(|at| breach_severity(at.saturating_sub(latest), config));
+        if !escalated(&mut history.alerted, severity) {
+            return None;
+        }
+        let (severity, breach_at) = (severity?, breach_at?);
+        let days = breach_at.saturating_sub(latest) as f64 / MS_PER_DAY;
+        Some(AnomalyReport {
+            timestamp: reading.timestamp,
+            measurement: Some(Measurement {
+                name: "wall_loss_rate".into(),
+                value: loss_rate,
+                unit: "mm/year".into(),
+            }),
+            ..AnomalyReport::new(
+                AnomalyType::WallThinning,
+                severity,
+                reading.position,
+                &reading.section_id,
+                ENGINE_ORIGIN,
+                fit.r2,
+                format!(
+                    "Wall of {} thinning at {:.2} mm/year from {:.2} mm; projected to reach the {:.1} mm minimum on {} (in {:.0} days)",
+                    reading.section_id,
+                    loss_rate,
+                    thickness,
+                    config.minimum_mm,
+                    civil_date(breach_at),
+                    days
+                ),
+            )
+        })
+    }
+}
+
+impl SectionHistory {
+    /// Fit the samples and update the trend, once there are enough of them
+    fn refit(&mut self, section_id: &str, config: &WallThicknessConfig) -> Option<Fit> {
+        if self.samples.len() < config.min_samples {
+            return None;
+        }
+        let fit = Fit::of(&self.samples)?;
+        let latest = self.samples.back()?.timestamp;
+        let thickness = fit.at(latest);
+        let loss_per_ms = -fit.slope;
+        let significant =
+            loss_per_ms > 0.0 && loss_per_ms >= config.min_significance * fit.slope_error;
+        let breach_at = significant.then(|| {
+            let remaining = (thickness - config.minimum_mm).max(0.0);
+            latest + (remaining / loss_per_ms) as u64
+        });
+        self.trend = Some(SectionWallTrend {
+            section_id: section_id.to_string(),
+            thickness_mm: thickness,
+            loss_rate_mm_per_year: loss_per_ms * MS_PER_DAY * DAYS_PER_YEAR,
+            significant,
+            breach_at,
+            samples: self.samples.len(),
+        });
+        Some(fit)
+    }
+}
+
+/// Severity of a breach `in_ms` away: Low within the horizon, rising to
+/// Critical within a sixth of it
+fn breach_severity(in_ms: u64, config: &WallThicknessConfig) -> Option<SeverityLevel> {
+    let fraction = in_ms as f64 / config.horizon.as_millis() as f64;
+    match fraction {
+        f if f <= 1.0 / 6.0 => Some(SeverityLevel::Critical),
+        f if f <= 1.0 / 3.0 => Some(SeverityLevel::High),
+        f if f <= 0.5 => Some(SeverityLevel::Medium),
+        f if f <= 1.0 => Some(SeverityLevel::Low),
+        _ => None,
+    }
+}
+
+/// Least-squares line through the samples, thickness against time
+#[derive(Debug, Clone, Copy)]
+struct Fit {
+    /// Millimeters per millisecond
+    slope: f64,
+    /// Standard error of the slope
+    slope_error: f64,
+    intercept: f64,
+    /// Time the intercept is taken at
+    origin: u64,
+    r2: f64,
+}
+
+impl Fit {
+    fn of(samples: &VecDeque<Sample>) -> Option<Self> {
+        let origin = samples.front()?.timestamp;
+        let n = samples.len() as f64;
+        let t = |s: &Sample| (s.timestamp - origin) as f64;
+        let mean_t = samples.iter().map(t).sum::<f64>() / n;
+        let mean_v = samples.iter().map(|s| s.thickness).sum::<f64>() / n;
+        let (mut stt, mut stv, mut svv) = (0.0, 0.0, 0.0);
+        for sample in samples {
+            let (dt, dv) = (t(sample) - mean_t, sample.thickness - mean_v);
+            stt += dt * dt;
+            stv += dt * dv;
+            svv += dv * dv;
+        }
+        if stt == 0.0 || n < 3.0 {
+            return None;
+        }
+        let slope = stv / stt;
+        let residual = (svv - slope * stv).max(0.0);
+        Some(Self {
+            slope,
+            slope_error: (residual / (n - 2.0) / stt).sqrt(),
+            intercept: mean_v - slope * mean_t,
+            origin,
+            r2: if svv > 0.0 {
+                stv * stv / (stt * svv)
+            } else {
+                0.0
+            },
+        })
+    }
+
+    fn at(&self, timestamp: u64) -> f64 {
+        self.intercept + self.slope * timestamp.saturating_sub(self.origin) as f64
+    }
+}
+
+/// `YYYY-MM-DD` (UTC) of a Unix timestamp in milliseconds
+fn civil_date(timestamp: u64) -> String {
+    // Days to civil date, from Howard Hinnant's `civil_from_days`
+    let days = (timestamp / 86_400_000) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aetheris_shared::{Position, Timestamp};
+
+    /// 2024-01-01T00:00:00Z
+    const T0: u64 = 1_704_067_200_000;
+    const HOUR: u64 = 3_600_000;
+
+    fn reading(thickness: f64, timestamp: u64) -> PipeEnvironment {
+        PipeEnvironment {
+            section_id: "PIPE-002".into(),
+            pressure: 49.0,
+            temperature: 15.0,
+            h2_concentration: 40.0,
+            wall_thickness: thickness,
+            flow_rate: 730.0,
+            humidity: 45.0,
+            position: Position::new(15.0, -0.5, 5.0),
+            timestamp: Timestamp::from_millis(timestamp),
+        }
+    }
+
+    /// Four readings an hour for `hours`, thickness given by `f(hour)`
+    fn feed(
+        trends: &mut WallThicknessTrends,
+        hours: u64,
+        f: impl Fn(u64) -> f64,
+    ) -> Vec<AnomalyReport> {
+        (0..hours * 4)
+            .filter_map(|i| trends.observe(&reading(f(i / 4), T0 + i * HOUR / 4)))
+            .collect()
+    }
+
+    /// Millimeters per hour for a loss of `mm_per_year`
+    fn per_hour(mm_per_year: f64) -> f64 {
+        mm_per_year / DAYS_PER_YEAR / 24.0
+    }
+
+    /// Alternating measurement noise
+    fn noise(hour: u64, size: f64) -> f64 {
+        if hour.is_multiple_of(2) { size } else { -size }
+    }
+
+    #[test]
+    fn test_fast_thinning_projects_a_breach_and_escalates() {
+        let mut trends = WallThicknessTrends::default();
+        // 11.0 mm losing 16 mm/year: the 6.0 mm minimum is almost four
+        // months out, and within three a month later
+        let reports = feed(&mut trends, 24 * 30, |h| {
+            11.0 - per_hour(16.0) * h as f64 + noise(h, 0.005)
+        });
+
+        let trend = trends.trend("PIPE-002").unwrap();
+        assert!((trend.loss_rate_mm_per_year - 16.0).abs() < 0.2);
+        assert!(trend.significant);
+        let severities: Vec<_> = reports.iter().map(|r| r.severity).collect();
+        assert_eq!(severities, [SeverityLevel::Low, SeverityLevel::Medium]);
+
+        let report = &reports[1];
+        assert_eq!(report.anomaly_type, AnomalyType::WallThinning);
+        assert_eq!(report.section_id, "PIPE-002");
+        let measurement = report.measurement.as_ref().unwrap();
+        assert_eq!(measurement.unit, "mm/year");
+        assert!(report.description.contains("mm/year"));
+        assert!(report.description.contains("on 2024-"));
+        assert_eq!(trends.snapshot().len(), 1);
+    }
+
+    #[test]
+    fn test_noise_sparse_and_thickening_data_never_alert() {
+        // A day of samples is not enough
+        let mut trends = WallThicknessTrends::default();
+        assert!(feed(&mut trends, 20, |h| 10.0 - per_hour(200.0) * h as f64).is_empty());
+        assert!(trends.trend("PIPE-002").is_none());
+
+        // Noise with no trend behind it
+        let mut trends = WallThicknessTrends::default();
+        let noisy = |h: u64| 10.0 + 0.05 * ((h * 7919) % 13) as f64 / 13.0;
+        assert!(feed(&mut trends, 24 * 30, noisy).is_empty());
+        assert!(!trends.trend("PIPE-002").unwrap().significant);
+
+        // A thickening wall is sensor trouble, not a reprieve
+        let mut trends = WallThicknessTrends::default();
+        assert!(feed(&mut trends, 24 * 30, |h| 8.0 + per_hour(20.0) * h as f64).is_empty());
+        let trend = trends.trend("PIPE-002").unwrap();
+        assert!(trend.loss_rate_mm_per_year < 0.0);
+        assert_eq!(trend.breach_at, None);
+    }
+
+    #[test]
+    fn test_slow_loss_stays_beyond_the_horizon() {
+        let mut trends = WallThicknessTrends::default();
+        // 0.5 mm/year leaves eight years to the minimum
+        let reports = feed(&mut trends, 24 * 30, |h| {
+            10.0 - per_hour(0.5) * h as f64 + noise(h, 0.001)
+        });
+        assert!(reports.is_empty());
+        let trend = trends.trend("PIPE-002").unwrap();
+        assert!(trend.significant);
+        assert!(trend.breach_at.unwrap() > T0 + 7 * 365 * 24 * HOUR);
+    }
+
+    #[test]
+    fn test_civil_dates() {
+        assert_eq!(civil_date(0), "1970-01-01");
+        assert_eq!(civil_date(T0), "2024-01-01");
+        assert_eq!(civil_date(T0 + 59 * 24 * HOUR), "2024-02-29");
+        assert_eq!(civil_date(T0 + 366 * 24 * HOUR), "2025-01-01");
+    }
+}
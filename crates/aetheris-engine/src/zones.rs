@@ -0,0 +1,509 @@
+//! Per-zone operational modes
+//!
+//! During hot work or third-party excavation a zone drops to restricted
+//! operations or full robot exclusion. The [`ZoneRegistry`] holds each
+//! zone's footprint and current [`ZoneMode`], validates commands against
+//! the zones their robot is in, treats Excluded zones as obstacles for
+//! dispatch, and works out where robots caught inside a newly Excluded zone
+//! should go. Modes with an expiry fall back to Normal once it passes.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use thiserror::Error;
+
+use aetheris_shared::{Command, OperationKind, Position, RobotState, ZoneMode};
+
+use crate::config::{CheckConfig, ConfigChecker};
+
+// ============================================================================
+// ZONES
+// ============================================================================
+
+/// A named region of the site; its footprint spans every altitude
+#[derive(Debug, Clone, PartialEq)]
+pub struct Zone {
+    pub id: String,
+    /// South-west corner (only x and y are used)
+    pub min: Position,
+    /// North-east corner (only x and y are used)
+    pub max: Position,
+}
+
+impl Zone {
+    pub fn new(id: impl Into<String>, min: Position, max: Position) -> Self {
+        Self {
+            id: id.into(),
+            min,
+            max,
+        }
+    }
+
+    pub fn contains(&self, position: &Position) -> bool {
+        (self.min.x..=self.max.x).contains(&position.x)
+            && (self.min.y..=self.max.y).contains(&position.y)
+    }
+
+    /// Whether the straight path from `a` to `b` enters the footprint
+    pub fn intersects_segment(&self, a: &Position, b: &Position) -> bool {
+        // Liang-Barsky clipping of the segment against the rectangle
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let (mut t0, mut t1) = (0.0_f64, 1.0_f64);
+        for (p, q) in [
+            (-dx, a.x - self.min.x),
+            (dx, self.max.x - a.x),
+            (-dy, a.y - self.min.y),
+            (dy, self.max.y - a.y),
+        ] {
+            if p == 0.0 {
+                if q < 0.0 {
+                    return false;
+                }
+                continue;
+            }
+            let t = q / p;
+            if p < 0.0 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+            if t0 > t1 {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Points just outside each edge, `margin` meters out, nearest first
+    fn exits(&self, position: &Position, margin: f64) -> Vec<Position> {
+        let mut exits = vec![
+            Position::new(self.min.x - margin, position.y, position.z),
+            Position::new(self.max.x + margin, position.y, position.z),
+            Position::new(position.x, self.min.y - margin, position.z),
+            Position::new(position.x, self.max.y + margin, position.z),
+        ];
+        exits.sort_by(|a, b| a.distance_to(position).total_cmp(&b.distance_to(position)));
+        exits
+    }
+}
+
+/// Zone footprints and evacuation behavior
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoneConfig {
+    pub zones: Vec<Zone>,
+    /// Distance beyond the zone edge robots are evacuated to (meters)
+    pub exit_margin: f64,
+}
+
+impl Default for ZoneConfig {
+    fn default() -> Self {
+        Self {
+            zones: Vec::new(),
+            exit_margin: 5.0,
+        }
+    }
+}
+
+impl CheckConfig for ZoneConfig {
+    fn check(&self, checker: &mut ConfigChecker) {
+        let mut seen = std::collections::HashSet::new();
+        for (i, zone) in self.zones.iter().enumerate() {
+            if zone.id.is_empty() || !seen.insert(zone.id.as_str()) {
+                checker.error(
+                    &format!("zones[{i}].id"),
+                    format!("zone id \"{}\" is empty or duplicated", zone.id),
+                    None,
+                );
+            }
+            if !(zone.min.x < zone.max.x && zone.min.y < zone.max.y) {
+                checker.error(
+                    &format!("zones[{i}]"),
+                    "min must be below max on x and y",
+                    None,
+                );
+            }
+        }
+        if !(self.exit_margin > 0.0 && self.exit_margin.is_finite()) {
+            checker.error("exit_margin", "must be a positive distance", None);
+        }
+    }
+}
+
+// ============================================================================
+// MODES
+// ============================================================================
+
+/// Current mode of a zone, for status reporting
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ZoneStatus {
+    pub zone_id: String,
+    pub mode: ZoneMode,
+    /// Unix timestamp the mode reverts to Normal (milliseconds)
+    pub until: Option<u64>,
+}
+
+/// Failure to change a zone's mode
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ZoneError {
+    #[error("unknown zone \"{0}\"")]
+    UnknownZone(String),
+    #[error("expiry {until} is not in the future")]
+    ExpiryInPast { until: u64 },
+}
+
+/// A command refused because of a zone mode
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ZoneViolation {
+    #[error("{operation:?} is not allowed in zone {zone_id}")]
+    Disallowed {
+        zone_id: String,
+        operation: OperationKind,
+    },
+    #[error("target lies in excluded zone {zone_id}")]
+    ExcludedTarget { zone_id: String },
+}
+
+/// A robot that must leave an Excluded zone
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evacuation {
+    pub robot_id: String,
+    pub zone_id: String,
+    /// Nearest point outside every Excluded zone
+    pub target: Position,
+}
+
+/// Zone footprints with their current modes
+#[derive(Debug, Default)]
+pub struct ZoneRegistry {
+    config: ZoneConfig,
+    modes: HashMap<String, (ZoneMode, Option<u64>)>,
+}
+
+impl ZoneRegistry {
+    pub fn new(config: ZoneConfig) -> Self {
+        Self {
+            config,
+            modes: HashMap::new(),
+        }
+    }
+
+    pub fn zones(&self) -> &[Zone] {
+        &self.config.zones
+    }
+
+    pub fn mode(&self, zone_id: &str) -> ZoneMode {
+        self.modes
+            .get(zone_id)
+            .map(|(mode, _)| mode.clone())
+            .unwrap_or_default()
+    }
+
+    /// Every zone with its mode and expiry
+    pub fn status(&self) -> Vec<ZoneStatus> {
+        self.config
+            .zones
+            .iter()
+            .map(|zone| {
+                let (mode, until) = self.modes.get(&zone.id).cloned().unwrap_or_default();
+                ZoneStatus {
+                    zone_id: zone.id.clone(),
+                    mode,
+                    until,
+                }
+            })
+            .collect()
+    }
+
+    /// Set a zone's mode, reverting to Normal at `until` if given
+    pub fn set_mode(
+        &mut self,
+        zone_id: &str,
+        mode: ZoneMode,
+        until: Option<u64>,
+        now_ms: u64,
+    ) -> Result<(), ZoneError> {
+        if !self.config.zones.iter().any(|z| z.id == zone_id) {
+            return Err(ZoneError::UnknownZone(zone_id.into()));
+        }
+        if let Some(until) = until
+            && until <= now_ms
+        {
+            return Err(ZoneError::ExpiryInPast { until });
+        }
+        if mode == ZoneMode::Normal {
+            self.modes.remove(zone_id);
+        } else {
+            self.modes.insert(zone_id.into(), (mode, until));
+        }
+        Ok(())
+    }
+
+    /// Restore Normal on every zone whose mode expired, returning their ids
+    pub fn expire(&mut self, now_ms: u64) -> Vec<String> {
+        let mut expired: Vec<String> = self
+            .modes
+            .iter()
+            .filter(|(_, (_, until))| until.is_some_and(|until| until <= now_ms))
+            .map(|(id, _)| id.clone())
+            .collect();
+        expired.sort();
+        for id in &expired {
+            self.modes.remove(id);
+        }
+        expired
+    }
+
+    fn with_mode<'a>(
+        &'a self,
+        position: &'a Position,
+    ) -> impl Iterator<Item = (&'a Zone, &'a ZoneMode)> {
+        self.config
+            .zones
+            .iter()
+            .filter(|zone| zone.contains(position))
+            .filter_map(|zone| self.modes.get(&zone.id).map(|(mode, _)| (zone, mode)))
+    }
+
+    fn excluded(&self) -> impl Iterator<Item = &Zone> {
+        self.config
+            .zones
+            .iter()
+            .filter(|zone| matches!(self.modes.get(&zone.id), Some((ZoneMode::Excluded, _))))
+    }
+
+    /// Excluded zone containing `position`, if any
+    pub fn excluded_at(&self, position: &Position) -> Option<&Zone> {
+        self.excluded().find(|zone| zone.contains(position))
+    }
+
+    /// First Excluded zone the straight path from `from` to `to` enters
+    pub fn blocked_path(&self, from: &Position, to: &Position) -> Option<&Zone> {
+        self.excluded()
+            .find(|zone| zone.intersects_segment(from, to))
+    }
+
+    /// Check `command` for `robot` against the zones it is in and targets.
+    ///
+    /// A robot inside an Excluded zone may still be moved out of it.
+    pub fn check_command(
+        &self,
+        command: &Command,
+        robot: &RobotState,
+    ) -> Result<(), ZoneViolation> {
+        let Some(operation) = command.operation() else {
+            return Ok(());
+        };
+        if let Command::MoveTo { target, .. } = command
+            && let Some(zone) = self.excluded_at(target)
+        {
+            return Err(ZoneViolation::ExcludedTarget {
+                zone_id: zone.id.clone(),
+            });
+        }
+        for (zone, mode) in self.with_mode(&robot.position) {
+            let leaving = *mode == ZoneMode::Excluded && operation == OperationKind::Movement;
+            if !leaving && !mode.permits(operation, robot.robot_type) {
+                return Err(ZoneViolation::Disallowed {
+                    zone_id: zone.id.clone(),
+                    operation,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Robots inside Excluded zones and the nearest allowed point to send
+    /// each to; robots with no reachable exit are left out
+    pub fn evacuations<'a>(
+        &self,
+        robots: impl IntoIterator<Item = &'a RobotState>,
+    ) -> Vec<Evacuation> {
+        let mut evacuations: Vec<Evacuation> = robots
+            .into_iter()
+            .filter_map(|robot| {
+                let zone = self.excluded_at(&robot.position)?;
+                let target = zone
+                    .exits(&robot.position, self.config.exit_margin)
+                    .into_iter()
+                    .find(|exit| self.excluded_at(exit).is_none())?;
+                Some(Evacuation {
+                    robot_id: robot.id.to_string(),
+                    zone_id: zone.id.clone(),
+                    target,
+                })
+            })
+            .collect();
+        evacuations.sort_by(|a, b| a.robot_id.cmp(&b.robot_id));
+        evacuations
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FleetManager;
+    use crate::decision::ExclusionReason;
+    use aetheris_shared::{RobotType, ScanType};
+    use std::time::Duration;
+
+    const NOW: u64 = 1_700_000_000_000;
+
+    /// NORTH spans x 0..100, y 50..100; SOUTH spans x 0..100, y -100..-50
+    fn registry() -> ZoneRegistry {
+        ZoneRegistry::new(ZoneConfig {
+            zones: vec![
+                Zone::new(
+                    "NORTH",
+                    Position::new(0.0, 50.0, 0.0),
+                    Position::new(100.0, 100.0, 0.0),
+                ),
+                Zone::new(
+                    "SOUTH",
+                    Position::new(0.0, -100.0, 0.0),
+                    Position::new(100.0, -50.0, 0.0),
+                ),
+            ],
+            ..ZoneConfig::default()
+        })
+    }
+
+    fn robot(id: &str, robot_type: RobotType, x: f64, y: f64) -> RobotState {
+        let mut state = RobotState::new(id.parse().unwrap(), id, robot_type);
+        state.position = Position::new(x, y, 0.0);
+        state
+    }
+
+    #[test]
+    fn test_restricted_zone_rejects_disallowed_operations() {
+        let mut zones = registry();
+        let disallowed = vec![OperationKind::Scan, OperationKind::DroneFlight];
+        zones
+            .set_mode("NORTH", ZoneMode::Restricted { disallowed }, None, NOW)
+            .unwrap();
+
+        let rover = robot("RV-001", RobotType::Rover, 50.0, 60.0);
+        let drone = robot("DR-001", RobotType::Drone, 50.0, 60.0);
+        let scan = Command::PerformScan {
+            scan_type: ScanType::Ultrasonic,
+        };
+        let patrol = Command::StartPatrol {
+            route_id: "R-1".into(),
+        };
+        assert_eq!(
+            zones.check_command(&scan, &rover),
+            Err(ZoneViolation::Disallowed {
+                zone_id: "NORTH".into(),
+                operation: OperationKind::Scan
+            })
+        );
+        assert!(zones.check_command(&patrol, &rover).is_ok());
+        assert!(zones.check_command(&patrol, &drone).is_err());
+        // Stops always go through
+        assert!(zones.check_command(&Command::EmergencyStop, &drone).is_ok());
+        // Outside the zone nothing is restricted
+        let outside = robot("RV-002", RobotType::Rover, 50.0, 0.0);
+        assert!(zones.check_command(&scan, &outside).is_ok());
+        assert_eq!(
+            zones.set_mode("WEST", ZoneMode::Excluded, None, NOW),
+            Err(ZoneError::UnknownZone("WEST".into()))
+        );
+    }
+
+    #[test]
+    fn test_excluded_zone_is_an_obstacle() {
+        let mut zones = registry();
+        zones
+            .set_mode("NORTH", ZoneMode::Excluded, None, NOW)
+            .unwrap();
+
+        let rover = robot("RV-001", RobotType::Rover, 50.0, 0.0);
+        let into_zone = Command::MoveTo {
+            target: Position::new(50.0, 75.0, 0.0),
+            speed: None,
+        };
+        assert_eq!(
+            zones.check_command(&into_zone, &rover),
+            Err(ZoneViolation::ExcludedTarget {
+                zone_id: "NORTH".into()
+            })
+        );
+        // A straight route across the zone is blocked, one beside it is not
+        let (from, beyond) = (
+            Position::new(50.0, 0.0, 0.0),
+            Position::new(50.0, 150.0, 0.0),
+        );
+        assert_eq!(zones.blocked_path(&from, &beyond).unwrap().id, "NORTH");
+        assert!(
+            zones
+                .blocked_path(&from, &Position::new(200.0, 100.0, 0.0))
+                .is_none()
+        );
+
+        // Dispatch rules out the robot whose route crosses the zone
+        let fleet = FleetManager::new(Duration::from_secs(15));
+        fleet.update_robot(robot("RV-001", RobotType::Rover, 50.0, 0.0));
+        fleet.update_robot(robot("RV-002", RobotType::Rover, 150.0, 150.0));
+        let candidates = fleet.evaluate_dispatch_candidates(&beyond, 20.0, &zones);
+        assert_eq!(candidates[0].robot_id, "RV-002");
+        assert_eq!(candidates[1].excluded, Some(ExclusionReason::ZoneExcluded));
+    }
+
+    #[test]
+    fn test_robots_inside_excluded_zone_are_evacuated() {
+        let mut zones = registry();
+        let inside = robot("RV-001", RobotType::Rover, 90.0, 58.0);
+        let outside = robot("RV-002", RobotType::Rover, 50.0, 0.0);
+        assert!(zones.evacuations([&inside, &outside]).is_empty());
+
+        zones
+            .set_mode("NORTH", ZoneMode::Excluded, None, NOW)
+            .unwrap();
+        let evacuations = zones.evacuations([&inside, &outside]);
+        assert_eq!(
+            evacuations,
+            vec![Evacuation {
+                robot_id: "RV-001".into(),
+                zone_id: "NORTH".into(),
+                target: Position::new(90.0, 45.0, 0.0),
+            }]
+        );
+        // The evacuation move itself is allowed
+        let out = Command::MoveTo {
+            target: evacuations[0].target,
+            speed: None,
+        };
+        assert!(zones.check_command(&out, &inside).is_ok());
+        let scan = Command::PerformScan {
+            scan_type: ScanType::Visual,
+        };
+        assert!(zones.check_command(&scan, &inside).is_err());
+    }
+
+    #[test]
+    fn test_mode_expiry_restores_normal() {
+        let mut zones = registry();
+        assert_eq!(
+            zones.set_mode("SOUTH", ZoneMode::Excluded, Some(NOW), NOW),
+            Err(ZoneError::ExpiryInPast { until: NOW })
+        );
+        zones
+            .set_mode("SOUTH", ZoneMode::Excluded, Some(NOW + 60_000), NOW)
+            .unwrap();
+        zones
+            .set_mode("NORTH", ZoneMode::Excluded, None, NOW)
+            .unwrap();
+
+        assert!(zones.expire(NOW + 59_999).is_empty());
+        assert_eq!(zones.mode("SOUTH"), ZoneMode::Excluded);
+        assert_eq!(zones.expire(NOW + 60_000), vec!["SOUTH".to_string()]);
+        assert_eq!(zones.mode("SOUTH"), ZoneMode::Normal);
+        assert_eq!(zones.mode("NORTH"), ZoneMode::Excluded);
+        let status = zones.status();
+        assert_eq!(status[1].mode, ZoneMode::Normal);
+        assert_eq!(status[1].until, None);
+    }
+}
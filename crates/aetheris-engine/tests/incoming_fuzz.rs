@@ -0,0 +1,247 @@
+//! Whatever arrives off the network, `handle_incoming` returns.
+//!
+//! Arbitrary bytes and truncated or mutated wire payloads are fed to every
+//! topic the engine subscribes to; a panic anywhere in parsing or routing
+//! fails the run. Valid telemetry must still come out the other side, and
+//! the payloads collected from the field under `tests/fixtures/field`, one
+//! directory per topic, must be dead-lettered without reaching the consumer.
+//!
+//! No broker is needed: publishing while disconnected only fills the
+//! offline buffer.
+
+use std::cell::RefCell;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use proptest::prelude::*;
+use proptest::sample::select;
+use proptest::test_runner::{Config, TestRunner};
+use tokio::runtime::Runtime;
+use tokio::sync::mpsc;
+
+use aetheris_engine::{AetherisMqtt, EngineMessage, MqttConfig};
+use aetheris_shared::{
+    MqttMessage, Position, RobotId, RobotState, RobotType, SeverityLevel, topics,
+};
+
+/// Frozen v1 payloads, the starting point for mutation
+const WIRE_SAMPLES: &[&[u8]] = &[
+    include_bytes!("../../aetheris-shared/tests/fixtures/v1/envelope_robot_state.json"),
+    include_bytes!("../../aetheris-shared/tests/fixtures/v1/envelope_anomaly_report.json"),
+    include_bytes!("../../aetheris-shared/tests/fixtures/v1/envelope_command.json"),
+    include_bytes!("../../aetheris-shared/tests/fixtures/v1/envelope_pipe_environment.json"),
+    include_bytes!("../../aetheris-shared/tests/fixtures/v1/heartbeat.json"),
+    include_bytes!("../../aetheris-shared/tests/fixtures/v1/command_response.json"),
+];
+
+/// Engine with no broker behind it, on a runtime of its own
+struct Harness {
+    runtime: Runtime,
+    mqtt: AetherisMqtt,
+    rx: RefCell<mpsc::Receiver<EngineMessage>>,
+}
+
+impl Harness {
+    fn new() -> Self {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let (tx, rx) = mpsc::channel(1024);
+        let (mqtt, _eventloop) = runtime
+            .block_on(AetherisMqtt::new(MqttConfig::default(), tx))
+            .unwrap();
+        Self {
+            runtime,
+            mqtt,
+            rx: RefCell::new(rx),
+        }
+    }
+
+    /// Hand `payload` to the engine and collect what it passed on
+    fn feed(&self, topic: &str, payload: &[u8]) -> Vec<EngineMessage> {
+        // Refusal is an error or a silent drop; either is fine here
+        let _ = self
+            .runtime
+            .block_on(self.mqtt.handle_incoming(topic, payload));
+        let mut rx = self.rx.borrow_mut();
+        std::iter::from_fn(|| rx.try_recv().ok()).collect()
+    }
+}
+
+/// Every topic class the engine parses, plus one it does not know
+fn topics() -> Vec<String> {
+    let rover: RobotId = "RV-001".parse().unwrap();
+    vec![
+        topics::telemetry(&rover),
+        topics::TELEMETRY_BATCH.to_string(),
+        topics::heartbeat(&rover),
+        topics::commands(&rover),
+        topics::COMMANDS_BROADCAST.to_string(),
+        topics::environment("PIPE-001"),
+        topics::responses(&rover),
+        topics::ALERTS.to_string(),
+        topics::alerts_severity(SeverityLevel::High),
+        topics::triage_results(),
+        "weather/today".to_string(),
+    ]
+}
+
+/// Ways a wire payload gets damaged in transit
+#[derive(Debug, Clone)]
+enum Damage {
+    Truncate(usize),
+    Overwrite(usize, u8),
+    Insert(usize, Vec<u8>),
+    Remove(usize, usize),
+}
+
+impl Damage {
+    fn apply(&self, sample: &[u8]) -> Vec<u8> {
+        let mut bytes = sample.to_vec();
+        let at = |i: usize| i % (bytes.len() + 1);
+        match self {
+            Damage::Truncate(i) => bytes.truncate(at(*i)),
+            Damage::Overwrite(i, byte) => {
+                if let Some(overwritten) = bytes.get_mut(i % sample.len().max(1)) {
+                    *overwritten = *byte;
+                }
+            }
+            Damage::Insert(i, inserted) => {
+                let i = at(*i);
+                bytes.splice(i..i, inserted.iter().copied());
+            }
+            Damage::Remove(i, len) => {
+                let start = at(*i);
+                let end = (start + len).min(bytes.len());
+                bytes.drain(start..end);
+            }
+        }
+        bytes
+    }
+}
+
+fn damage() -> impl Strategy<Value = Damage> {
+    prop_oneof![
+        any::<usize>().prop_map(Damage::Truncate),
+        (any::<usize>(), any::<u8>()).prop_map(|(i, byte)| Damage::Overwrite(i, byte)),
+        (
+            any::<usize>(),
+            prop_oneof![
+                prop::collection::vec(any::<u8>(), 1..16),
+                select(vec![
+                    b"NaN".to_vec(),
+                    b"1e999".to_vec(),
+                    b"-0".to_vec(),
+                    b"null".to_vec(),
+                    b"[[[[[[[[".to_vec(),
+                    b"\"\\ud800\"".to_vec(),
+                    b"\xef\xbb\xbf".to_vec(),
+                ]),
+            ]
+        )
+            .prop_map(|(i, inserted)| Damage::Insert(i, inserted)),
+        (any::<usize>(), 1..32usize).prop_map(|(i, len)| Damage::Remove(i, len)),
+    ]
+}
+
+/// Wire payloads with one or more things wrong with them
+fn damaged_payload() -> impl Strategy<Value = Vec<u8>> {
+    (
+        select(WIRE_SAMPLES.to_vec()),
+        prop::collection::vec(damage(), 1..4),
+    )
+        .prop_map(|(sample, damages)| {
+            damages
+                .iter()
+                .fold(sample.to_vec(), |bytes, damage| damage.apply(&bytes))
+        })
+}
+
+#[test]
+fn test_arbitrary_payloads_never_panic() {
+    let harness = Harness::new();
+    let payload = prop_oneof![
+        prop::collection::vec(any::<u8>(), 0..512),
+        damaged_payload(),
+    ];
+    TestRunner::new(Config::with_cases(2048))
+        .run(&(select(topics()), payload), |(topic, payload)| {
+            harness.feed(&topic, &payload);
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn test_valid_telemetry_is_routed() {
+    let harness = Harness::new();
+    let seq = AtomicU64::new(0);
+    let state = (
+        "RV-[0-9]{3}",
+        (-100.0..100.0, -100.0..100.0, -10.0..10.0),
+        (0.0..=100.0, 0.0..=100.0),
+    )
+        .prop_map(|(id, (x, y, z), (battery, signal))| RobotState {
+            position: Position::new(x, y, z),
+            battery,
+            signal,
+            ..RobotState::new(id.parse().unwrap(), &id, RobotType::Rover)
+        });
+    TestRunner::new(Config::with_cases(256))
+        .run(&state, |state| {
+            let topic = topics::telemetry(&state.id);
+            let msg = MqttMessage::new(
+                state.clone(),
+                state.id.as_str(),
+                seq.fetch_add(1, Ordering::Relaxed) + 1,
+            );
+            let routed = harness.feed(&topic, &serde_json::to_vec(&msg).unwrap());
+            let received = routed.iter().find_map(|message| match message {
+                EngineMessage::TelemetryReceived(received) => Some(received),
+                _ => None,
+            });
+            prop_assert_eq!(received, Some(&state));
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn test_field_payloads_are_refused() {
+    let rover: RobotId = "RV-001".parse().unwrap();
+    let field = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/field");
+    let mut checked = 0;
+    for class in std::fs::read_dir(&field).unwrap() {
+        let class = class.unwrap().path();
+        let name = class.file_name().unwrap().to_str().unwrap();
+        let topic = match name {
+            "telemetry" => topics::telemetry(&rover),
+            "telemetry_batch" => topics::TELEMETRY_BATCH.to_string(),
+            "heartbeat" => topics::heartbeat(&rover),
+            "commands" => topics::commands(&rover),
+            "environment" => topics::environment("PIPE-001"),
+            "responses" => topics::responses(&rover),
+            "alerts" => topics::ALERTS.to_string(),
+            other => panic!("no topic for field fixtures under {other}/"),
+        };
+        for fixture in std::fs::read_dir(&class).unwrap() {
+            let fixture = fixture.unwrap().path();
+            let harness = Harness::new();
+            let routed = harness.feed(&topic, &std::fs::read(&fixture).unwrap());
+            assert!(
+                routed.is_empty(),
+                "{} reached the consumer: {routed:?}",
+                fixture.display()
+            );
+            assert_eq!(
+                harness.mqtt.dead_letters().len(),
+                1,
+                "{} was not dead-lettered",
+                fixture.display()
+            );
+            checked += 1;
+        }
+    }
+    assert!(checked > 0, "no fixtures under {}", field.display());
+}
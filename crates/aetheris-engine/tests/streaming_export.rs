@@ -0,0 +1,100 @@
+//! Streaming export keeps memory flat however many rows a query returns.
+//!
+//! Runs with a counting global allocator, so it lives in its own test binary.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use aetheris_engine::persistence::FleetStore;
+use aetheris_engine::query::{ExportFormat, QueryEngine, QueryLimits};
+
+struct CountingAllocator;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            let live = LIVE.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(live, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Sink that only counts what it is given
+#[derive(Default)]
+struct CountingSink {
+    bytes: usize,
+}
+
+impl Write for CountingSink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.bytes += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+const ROWS: usize = 200_000;
+
+const LARGE_QUERY: &str = "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 200000) \
+     SELECT i AS id, 'RV-' || (i % 50) AS robot_id, i * 0.5 AS battery, \
+     'reading number ' || i || ', padded to look like a real payload' AS detail FROM n";
+
+#[test]
+fn test_streaming_export_memory_is_bounded() {
+    let path = std::env::temp_dir().join(format!("aetheris-export-{}.db", uuid::Uuid::new_v4()));
+    FleetStore::open(&path).unwrap();
+    let engine = QueryEngine::open(
+        &path,
+        QueryLimits {
+            max_rows: ROWS,
+            timeout: Duration::from_secs(30),
+            ..QueryLimits::default()
+        },
+    )
+    .unwrap();
+
+    for format in [ExportFormat::Csv, ExportFormat::JsonLines] {
+        let mut sink = CountingSink::default();
+        let baseline = LIVE.load(Ordering::Relaxed);
+        PEAK.store(baseline, Ordering::Relaxed);
+        let summary = engine.export(LARGE_QUERY, format, &mut sink).unwrap();
+        let peak = PEAK.load(Ordering::Relaxed) - baseline;
+
+        assert_eq!(summary.rows, ROWS);
+        assert!(!summary.truncated);
+        assert!(
+            sink.bytes > 10 * 1024 * 1024,
+            "{format:?} wrote {}",
+            sink.bytes
+        );
+        // Buffering would hold every encoded row; streaming holds one chunk
+        assert!(
+            peak < 2 * 1024 * 1024,
+            "{format:?} peaked at {peak} bytes for {} bytes written",
+            sink.bytes
+        );
+    }
+
+    drop(engine);
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+    }
+}
@@ -2,10 +2,284 @@
 //!
 //! Core data structures for the AETHERIS Digital Twin and Autonomous Inspection System.
 //! These types are shared between the Engine, Brain, and Dashboard components.
+//!
+//! The default `std` feature adds the system clock: [`Timestamp::now`] and
+//! the constructors stamping messages with it. Without it the crate is
+//! `no_std` + `alloc`, for the dashboard through wasm and for robot
+//! microcontrollers; those pass the time to the `*_at` constructors
+//! instead. The `signing` feature, also default, adds envelope signing.
+//! The optional `protobuf` feature adds [`proto`], protobuf messages of the
+//! main types for consumers that generate code from a schema.
+//! `cargo build -p aetheris-shared --no-default-features` checks the core
+//! build.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
+extern crate alloc;
+
+use alloc::collections::{BTreeMap, VecDeque};
+#[cfg(not(any(feature = "std", test)))]
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use base64::prelude::{BASE64_STANDARD, Engine as _};
+use core::time::Duration;
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
 use std::time::SystemTime;
 
+#[cfg(feature = "protobuf")]
+pub mod proto;
+
+/// The float functions `core` lacks, from libm when building without the
+/// standard library; with it, the inherent methods are used as before
+#[cfg(not(any(feature = "std", test)))]
+// Unused when a dependency links std anyway, as the dev-dependencies make
+// `num-traits` (under `rmp`) do
+#[allow(dead_code)]
+trait CoreFloat {
+    fn sqrt(self) -> f64;
+    fn powi(self, n: i32) -> f64;
+    fn hypot(self, other: f64) -> f64;
+    fn atan2(self, other: f64) -> f64;
+    fn sin_cos(self) -> (f64, f64);
+    fn rem_euclid(self, rhs: f64) -> f64;
+}
+
+#[cfg(not(any(feature = "std", test)))]
+impl CoreFloat for f64 {
+    fn sqrt(self) -> f64 {
+        libm::sqrt(self)
+    }
+
+    fn powi(self, n: i32) -> f64 {
+        libm::pow(self, n.into())
+    }
+
+    fn hypot(self, other: f64) -> f64 {
+        libm::hypot(self, other)
+    }
+
+    fn atan2(self, other: f64) -> f64 {
+        libm::atan2(self, other)
+    }
+
+    fn sin_cos(self) -> (f64, f64) {
+        libm::sincos(self)
+    }
+
+    fn rem_euclid(self, rhs: f64) -> f64 {
+        let r = self % rhs;
+        if r < 0.0 { r + rhs.abs() } else { r }
+    }
+}
+
+// ============================================================================
+// LIMITS
+// ============================================================================
+
+/// Physical and protocol limits shared by every component.
+///
+/// Where a limit is configurable, the constant is the documented default of
+/// the corresponding configuration field; code must read the field, not the
+/// constant.
+pub mod limits {
+    /// Lower explosive limit of hydrogen in air (4% by volume)
+    pub const H2_LOWER_EXPLOSIVE_LIMIT_PPM: f64 = 40_000.0;
+
+    /// H2 alert level: 10% of the LEL for a safety margin
+    pub const H2_ALERT_PPM: f64 = H2_LOWER_EXPLOSIVE_LIMIT_PPM / 10.0;
+
+    /// Internal pipe pressure above which a reading is hazardous
+    pub const PRESSURE_ALERT_BAR: f64 = 100.0;
+
+    /// Temperature above which a reading is hazardous
+    pub const TEMPERATURE_ALERT_CELSIUS: f64 = 80.0;
+
+    /// Full battery; battery levels range from 0 to this
+    pub const BATTERY_FULL_PERCENT: f64 = 100.0;
+
+    /// Perfect signal; signal strengths range from 0 to this
+    pub const SIGNAL_FULL_PERCENT: f64 = 100.0;
+
+    /// Time without a heartbeat after which a robot is offline
+    /// (default of the engine's `heartbeat_timeout`)
+    pub const HEARTBEAT_TIMEOUT_SECS: u64 = 15;
+
+    /// Standard unencrypted MQTT port
+    pub const MQTT_PORT: u16 = 1883;
+
+    /// MQTT keep-alive interval (default of `MqttConfig::keep_alive_secs`)
+    pub const MQTT_KEEP_ALIVE_SECS: u64 = 30;
+
+    /// Largest accepted message payload (default of `ParseLimits::max_payload_bytes`)
+    pub const MAX_PAYLOAD_BYTES: usize = 256 * 1024;
+
+    /// Deepest accepted payload nesting (default of `ParseLimits::max_depth`,
+    /// and the fixed limit for CBOR and MessagePack).
+    /// Our deepest message, `MqttMessage<Command::Configure>`, nests 4 levels.
+    pub const MAX_PAYLOAD_DEPTH: usize = 16;
+}
+
+// ============================================================================
+// TIME
+// ============================================================================
+
+/// A point in time, in milliseconds since the Unix epoch. On the wire it is
+/// the plain integer.
+///
+/// Arithmetic saturates: a timestamp from a robot whose clock runs ahead is
+/// zero old, not a wrapped-around age.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Timestamp(u64);
+
+impl Timestamp {
+    pub const EPOCH: Timestamp = Timestamp(0);
+
+    pub const fn from_millis(millis: u64) -> Self {
+        Self(millis)
+    }
+
+    pub const fn as_millis(self) -> u64 {
+        self.0
+    }
+
+    #[cfg(feature = "std")]
+    pub fn now() -> Self {
+        SystemTime::now().into()
+    }
+
+    /// Time passed since `self`, zero if it is in the future
+    #[cfg(feature = "std")]
+    pub fn elapsed(self) -> Duration {
+        Self::now().duration_since(self)
+    }
+
+    /// Whether more than `age` passed since `self`
+    #[cfg(feature = "std")]
+    pub fn is_older_than(self, age: Duration) -> bool {
+        self.elapsed() > age
+    }
+
+    /// Time from `earlier` to `self`, zero if `earlier` is later
+    pub fn duration_since(self, earlier: Timestamp) -> Duration {
+        Duration::from_millis(self.0.saturating_sub(earlier.0))
+    }
+
+    pub fn saturating_add(self, duration: Duration) -> Self {
+        Self(self.0.saturating_add(duration_millis(duration)))
+    }
+
+    pub fn saturating_sub(self, duration: Duration) -> Self {
+        Self(self.0.saturating_sub(duration_millis(duration)))
+    }
+}
+
+fn duration_millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+impl core::ops::Add<Duration> for Timestamp {
+    type Output = Timestamp;
+
+    fn add(self, duration: Duration) -> Self {
+        self.saturating_add(duration)
+    }
+}
+
+impl core::ops::Sub<Duration> for Timestamp {
+    type Output = Timestamp;
+
+    fn sub(self, duration: Duration) -> Self {
+        self.saturating_sub(duration)
+    }
+}
+
+impl core::ops::AddAssign<Duration> for Timestamp {
+    fn add_assign(&mut self, duration: Duration) {
+        *self = *self + duration;
+    }
+}
+
+impl core::ops::SubAssign<Duration> for Timestamp {
+    fn sub_assign(&mut self, duration: Duration) {
+        *self = *self - duration;
+    }
+}
+
+impl From<u64> for Timestamp {
+    fn from(millis: u64) -> Self {
+        Self(millis)
+    }
+}
+
+impl From<Timestamp> for u64 {
+    fn from(timestamp: Timestamp) -> Self {
+        timestamp.0
+    }
+}
+
+impl PartialEq<u64> for Timestamp {
+    fn eq(&self, other: &u64) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialOrd<u64> for Timestamp {
+    fn partial_cmp(&self, other: &u64) -> Option<core::cmp::Ordering> {
+        self.0.partial_cmp(other)
+    }
+}
+
+/// Times before the epoch are the epoch
+#[cfg(feature = "std")]
+impl From<SystemTime> for Timestamp {
+    fn from(time: SystemTime) -> Self {
+        let since_epoch = time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        Self(duration_millis(since_epoch))
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<Timestamp> for SystemTime {
+    fn from(timestamp: Timestamp) -> Self {
+        SystemTime::UNIX_EPOCH + Duration::from_millis(timestamp.0)
+    }
+}
+
+/// Times before the epoch are the epoch
+#[cfg(feature = "chrono")]
+impl From<chrono::DateTime<chrono::Utc>> for Timestamp {
+    fn from(time: chrono::DateTime<chrono::Utc>) -> Self {
+        Self(u64::try_from(time.timestamp_millis()).unwrap_or(0))
+    }
+}
+
+/// Times past the end of chrono's range are its last
+#[cfg(feature = "chrono")]
+impl From<Timestamp> for chrono::DateTime<chrono::Utc> {
+    fn from(timestamp: Timestamp) -> Self {
+        i64::try_from(timestamp.0)
+            .ok()
+            .and_then(chrono::DateTime::from_timestamp_millis)
+            .unwrap_or(chrono::DateTime::<chrono::Utc>::MAX_UTC)
+    }
+}
+
+impl core::fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 // ============================================================================
 // POSITION & SPATIAL TYPES
 // ============================================================================
@@ -27,10 +301,34 @@ impl Position {
         Self::new(0.0, 0.0, 0.0)
     }
 
+    /// Whether every coordinate is a finite number
+    pub fn is_finite(&self) -> bool {
+        self.x.is_finite() && self.y.is_finite() && self.z.is_finite()
+    }
+
     /// Calculate Euclidean distance to another position
     pub fn distance_to(&self, other: &Position) -> f64 {
-        ((self.x - other.x).powi(2) + (self.y - other.y).powi(2) + (self.z - other.z).powi(2))
-            .sqrt()
+        (*other - *self).length()
+    }
+
+    /// Where a robot moving at `velocity` is after `dt_secs` seconds
+    pub fn translate(&self, velocity: &Velocity, dt_secs: f64) -> Position {
+        *self + velocity.displacement(dt_secs)
+    }
+
+    /// The point a fraction `t` of the way to `other`: `self` at 0, `other`
+    /// at 1. `t` is not clamped, so values outside 0..=1 extrapolate.
+    pub fn lerp(&self, other: &Position, t: f64) -> Position {
+        *self + (*other - *self) * t
+    }
+
+    pub fn midpoint(&self, other: &Position) -> Position {
+        self.lerp(other, 0.5)
+    }
+
+    /// Unit vector pointing at `other`, or `None` when the two coincide
+    pub fn direction_to(&self, other: &Position) -> Option<Displacement> {
+        (*other - *self).normalized()
     }
 }
 
@@ -40,6 +338,92 @@ impl Default for Position {
     }
 }
 
+impl core::ops::Add<Displacement> for Position {
+    type Output = Position;
+
+    fn add(self, d: Displacement) -> Position {
+        Position::new(self.x + d.dx, self.y + d.dy, self.z + d.dz)
+    }
+}
+
+impl core::ops::AddAssign<Displacement> for Position {
+    fn add_assign(&mut self, d: Displacement) {
+        *self = *self + d;
+    }
+}
+
+impl core::ops::Sub<Displacement> for Position {
+    type Output = Position;
+
+    fn sub(self, d: Displacement) -> Position {
+        self + -d
+    }
+}
+
+impl core::ops::SubAssign<Displacement> for Position {
+    fn sub_assign(&mut self, d: Displacement) {
+        *self = *self - d;
+    }
+}
+
+/// The displacement that takes `other` to `self`
+impl core::ops::Sub for Position {
+    type Output = Displacement;
+
+    fn sub(self, other: Position) -> Displacement {
+        Displacement::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+/// Offset between two positions, in metres
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+pub struct Displacement {
+    pub dx: f64,
+    pub dy: f64,
+    pub dz: f64,
+}
+
+impl Displacement {
+    pub fn new(dx: f64, dy: f64, dz: f64) -> Self {
+        Self { dx, dy, dz }
+    }
+
+    pub fn length(&self) -> f64 {
+        (self.dx.powi(2) + self.dy.powi(2) + self.dz.powi(2)).sqrt()
+    }
+
+    /// The same direction with length 1, or `None` for a zero (or
+    /// non-finite) displacement, which has no direction
+    pub fn normalized(&self) -> Option<Displacement> {
+        let length = self.length();
+        (length >= f64::EPSILON && length.is_finite()).then(|| *self * (1.0 / length))
+    }
+}
+
+impl core::ops::Add for Displacement {
+    type Output = Displacement;
+
+    fn add(self, other: Displacement) -> Displacement {
+        Displacement::new(self.dx + other.dx, self.dy + other.dy, self.dz + other.dz)
+    }
+}
+
+impl core::ops::Neg for Displacement {
+    type Output = Displacement;
+
+    fn neg(self) -> Displacement {
+        Displacement::new(-self.dx, -self.dy, -self.dz)
+    }
+}
+
+impl core::ops::Mul<f64> for Displacement {
+    type Output = Displacement;
+
+    fn mul(self, factor: f64) -> Displacement {
+        Displacement::new(self.dx * factor, self.dy * factor, self.dz * factor)
+    }
+}
+
 /// 3D velocity vector
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
 pub struct Velocity {
@@ -61,101 +445,591 @@ impl Velocity {
     pub fn zero() -> Self {
         Self::new(0.0, 0.0, 0.0)
     }
+
+    /// Moving along `direction` at `speed` m/s; `direction` need not be a
+    /// unit vector, and a zero one gives no velocity at all
+    pub fn from_direction(direction: &Displacement, speed: f64) -> Self {
+        match direction.normalized() {
+            Some(unit) => Self::new(unit.dx * speed, unit.dy * speed, unit.dz * speed),
+            None => Self::zero(),
+        }
+    }
+
+    /// The same heading at no more than `max` m/s
+    pub fn clamp_speed(&self, max: f64) -> Self {
+        let speed = self.magnitude();
+        if speed <= max {
+            return *self;
+        }
+        *self * (max.max(0.0) / speed)
+    }
+
+    /// Distance covered in `dt_secs` seconds
+    pub fn displacement(&self, dt_secs: f64) -> Displacement {
+        Displacement::new(self.vx * dt_secs, self.vy * dt_secs, self.vz * dt_secs)
+    }
 }
 
-// ============================================================================
-// ROBOT TYPES & STATE
-// ============================================================================
+impl core::ops::Mul<f64> for Velocity {
+    type Output = Velocity;
 
-/// Types of autonomous robots in the AETHERIS fleet
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum RobotType {
-    /// Ground-based patrol rover for external pipeline inspection
-    Rover,
-    /// Aerial drone for overhead surveillance and rapid response
-    Drone,
-    /// Internal crawler for in-pipe inspection (corrosion, cracks, wall thickness)
-    Crawler,
+    fn mul(self, factor: f64) -> Velocity {
+        Velocity::new(self.vx * factor, self.vy * factor, self.vz * factor)
+    }
 }
 
-impl RobotType {
-    pub fn as_str(&self) -> &'static str {
-        match self {
-            RobotType::Rover => "rover",
-            RobotType::Drone => "drone",
-            RobotType::Crawler => "crawler",
-        }
+impl core::ops::MulAssign<f64> for Velocity {
+    fn mul_assign(&mut self, factor: f64) {
+        *self = *self * factor;
     }
 }
 
-/// Operational status of a robot
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum RobotStatus {
-    /// Robot is actively executing a task
-    Active,
-    /// Robot is idle and awaiting commands
-    Idle,
-    /// Robot is in maintenance mode (charging, repairs)
-    Maintenance,
-    /// Robot has encountered an error
-    Error,
-    /// Robot is offline/disconnected
-    Offline,
+/// Wrap an angle in radians into (-π, π]
+pub fn wrap_angle(radians: f64) -> f64 {
+    use core::f64::consts::{PI, TAU};
+    let wrapped = (radians + PI).rem_euclid(TAU) - PI;
+    if wrapped <= -PI {
+        wrapped + TAU
+    } else {
+        wrapped
+    }
 }
 
-/// Current task being executed by a robot
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case", tag = "type", content = "data")]
-pub enum CurrentTask {
-    /// No active task
-    None,
-    /// Patrolling a designated route
-    Patrolling { route_id: String },
-    /// Moving to a specific position
-    MovingTo { target: Position },
-    /// Performing a sensor scan at current location
-    Scanning { scan_type: ScanType },
-    /// Returning to charging station
-    ReturningToBase,
-    /// Investigating a detected anomaly
-    Investigating { anomaly_id: String },
+/// Attitude as intrinsic Z-Y-X Euler angles in radians.
+///
+/// Yaw is the heading about +z measured from +x toward +y, pitch is the nose
+/// up/down about the body y axis (positive raises the nose toward +z), and
+/// roll is about the body x axis. Rovers and crawlers only ever report yaw
+/// and pitch; drones use all three.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+pub struct Orientation {
+    pub yaw: f64,
+    pub pitch: f64,
+    pub roll: f64,
 }
 
-/// Types of sensor scans
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum ScanType {
-    /// Full multi-sensor sweep
-    Full,
-    /// Hydrogen leak detection only
-    LeakDetection,
-    /// Thermal imaging scan
-    Thermal,
-    /// Ultrasonic wall thickness measurement
-    Ultrasonic,
-    /// Visual/camera inspection
-    Visual,
+impl Orientation {
+    pub fn new(yaw: f64, pitch: f64, roll: f64) -> Self {
+        Self { yaw, pitch, roll }
+    }
+
+    /// Level and facing +x
+    pub fn identity() -> Self {
+        Self::default()
+    }
+
+    /// Level orientation facing along the horizontal part of `velocity`, or
+    /// `None` when the robot is not moving horizontally
+    pub fn facing(velocity: &Velocity) -> Option<Self> {
+        let horizontal = velocity.vx.hypot(velocity.vy);
+        if horizontal < f64::EPSILON && velocity.vz.abs() < f64::EPSILON {
+            return None;
+        }
+        Some(Self::new(
+            velocity.vy.atan2(velocity.vx),
+            velocity.vz.atan2(horizontal),
+            0.0,
+        ))
+    }
+
+    /// Every angle wrapped into (-π, π]
+    pub fn normalized(&self) -> Self {
+        Self::new(
+            wrap_angle(self.yaw),
+            wrap_angle(self.pitch),
+            wrap_angle(self.roll),
+        )
+    }
+
+    /// Rotate a body-frame vector into the world frame: roll about x, then
+    /// pitch (nose toward +z), then yaw about z
+    pub fn rotate_vector(&self, v: &Velocity) -> Velocity {
+        let (sr, cr) = self.roll.sin_cos();
+        let (x, y, z) = (v.vx, cr * v.vy - sr * v.vz, sr * v.vy + cr * v.vz);
+        let (sp, cp) = self.pitch.sin_cos();
+        let (x, z) = (cp * x - sp * z, sp * x + cp * z);
+        let (sy, cy) = self.yaw.sin_cos();
+        Velocity::new(cy * x - sy * y, sy * x + cy * y, z)
+    }
+
+    /// Whether every angle is a finite number
+    pub fn is_finite(&self) -> bool {
+        self.yaw.is_finite() && self.pitch.is_finite() && self.roll.is_finite()
+    }
+
+    /// The same attitude as a unit quaternion
+    pub fn to_quaternion(&self) -> Quaternion {
+        Quaternion::from_axis_angle(&Displacement::new(0.0, 0.0, 1.0), self.yaw)
+            * Quaternion::from_axis_angle(&Displacement::new(0.0, 1.0, 0.0), -self.pitch)
+            * Quaternion::from_axis_angle(&Displacement::new(1.0, 0.0, 0.0), self.roll)
+    }
+
+    /// Apply `body`, a rotation expressed in this orientation's own frame,
+    /// on top of this one; yawing a pitched drone keeps its nose up
+    pub fn compose(&self, body: &Orientation) -> Self {
+        (self.to_quaternion() * body.to_quaternion()).to_orientation()
+    }
+
+    /// The rotation that undoes this one
+    pub fn inverse(&self) -> Self {
+        self.to_quaternion().conjugate().to_orientation()
+    }
+
+    /// Smallest rotation in [0, π] radians that turns this attitude into
+    /// `other`, regardless of the axis
+    pub fn angle_to(&self, other: &Orientation) -> f64 {
+        self.to_quaternion().angle_to(&other.to_quaternion())
+    }
 }
 
-/// Health status indicators for robot subsystems
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum HealthStatus {
-    /// All systems nominal
-    Optimal,
-    /// Minor issues detected, operation continues
-    Warning,
-    /// Critical issues, immediate attention required
-    Critical,
+/// Unit quaternion `w + xi + yj + zk` for composing rotations without the
+/// gimbal lock of Euler angles. Orientations stay Euler on the wire; convert
+/// with [`Orientation::to_quaternion`] and [`Quaternion::to_orientation`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Quaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
 }
 
-/// Complete state of a robot unit
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+impl Default for Quaternion {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Quaternion {
+    pub fn new(w: f64, x: f64, y: f64, z: f64) -> Self {
+        Self { w, x, y, z }
+    }
+
+    /// No rotation
+    pub fn identity() -> Self {
+        Self::new(1.0, 0.0, 0.0, 0.0)
+    }
+
+    /// Rotation of `angle` radians counter-clockwise about `axis`; a zero
+    /// axis gives the identity
+    pub fn from_axis_angle(axis: &Displacement, angle: f64) -> Self {
+        match axis.normalized() {
+            Some(unit) => {
+                let (s, c) = (angle / 2.0).sin_cos();
+                Self::new(c, unit.dx * s, unit.dy * s, unit.dz * s)
+            }
+            None => Self::identity(),
+        }
+    }
+
+    pub fn norm(&self) -> f64 {
+        (self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
+    }
+
+    /// Scaled back to unit length to shed accumulated rounding; a zero
+    /// quaternion becomes the identity
+    pub fn normalized(&self) -> Self {
+        let norm = self.norm();
+        if norm < f64::EPSILON {
+            return Self::identity();
+        }
+        Self::new(self.w / norm, self.x / norm, self.y / norm, self.z / norm)
+    }
+
+    /// The inverse rotation of a unit quaternion
+    pub fn conjugate(&self) -> Self {
+        Self::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    /// Rotate a body-frame vector into the world frame
+    pub fn rotate_vector(&self, v: &Velocity) -> Velocity {
+        let p = Self::new(0.0, v.vx, v.vy, v.vz);
+        let r = *self * p * self.conjugate();
+        Velocity::new(r.x, r.y, r.z)
+    }
+
+    /// Smallest rotation in [0, π] radians between the two attitudes
+    pub fn angle_to(&self, other: &Quaternion) -> f64 {
+        let d = self.conjugate() * *other;
+        2.0 * (d.x.hypot(d.y).hypot(d.z)).atan2(d.w.abs())
+    }
+
+    /// Back to Z-Y-X Euler angles. Straight up or down the yaw and roll
+    /// axes coincide, so roll is reported as zero and yaw carries the rest.
+    pub fn to_orientation(&self) -> Orientation {
+        let q = self.normalized();
+        let sin_pitch = (2.0 * (q.x * q.z - q.w * q.y)).clamp(-1.0, 1.0);
+        let pitch = sin_pitch.atan2((1.0 - sin_pitch * sin_pitch).max(0.0).sqrt());
+        if sin_pitch.abs() > 1.0 - 1e-9 {
+            let yaw = (2.0 * (q.w * q.z - q.x * q.y)).atan2(1.0 - 2.0 * (q.x * q.x + q.z * q.z));
+            return Orientation::new(yaw, pitch, 0.0);
+        }
+        Orientation::new(
+            (2.0 * (q.x * q.y + q.w * q.z)).atan2(1.0 - 2.0 * (q.y * q.y + q.z * q.z)),
+            pitch,
+            (2.0 * (q.y * q.z + q.w * q.x)).atan2(1.0 - 2.0 * (q.x * q.x + q.y * q.y)),
+        )
+    }
+
+    /// Whether every component is a finite number
+    pub fn is_finite(&self) -> bool {
+        self.w.is_finite() && self.x.is_finite() && self.y.is_finite() && self.z.is_finite()
+    }
+}
+
+/// Hamilton product: `a * b` applies `b` first, then `a`
+impl core::ops::Mul for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, o: Quaternion) -> Quaternion {
+        Quaternion::new(
+            self.w * o.w - self.x * o.x - self.y * o.y - self.z * o.z,
+            self.w * o.x + self.x * o.w + self.y * o.z - self.z * o.y,
+            self.w * o.y - self.x * o.z + self.y * o.w + self.z * o.x,
+            self.w * o.z + self.x * o.y - self.y * o.x + self.z * o.w,
+        )
+    }
+}
+
+/// Position together with orientation
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+pub struct Pose {
+    pub position: Position,
+    pub orientation: Orientation,
+}
+
+impl Pose {
+    pub fn new(position: Position, orientation: Orientation) -> Self {
+        Self {
+            position,
+            orientation,
+        }
+    }
+
+    /// Signed turn in (-π, π] from the current yaw to face `target`;
+    /// positive turns counter-clockwise (toward +y)
+    pub fn heading_to(&self, target: &Position) -> f64 {
+        let bearing = (target.y - self.position.y).atan2(target.x - self.position.x);
+        wrap_angle(bearing - self.orientation.yaw)
+    }
+}
+
+// ============================================================================
+// ENUM NAMES
+// ============================================================================
+
+/// `ALL`, `as_str`, `Display` and `FromStr` for a unit enum, from the name
+/// serde gives each variant. The `as_str` match is built from the same list
+/// as `ALL`, so a variant missing from it fails to compile; the shared tests
+/// check every name against serde.
+macro_rules! enum_names {
+    ($name:ident { $($variant:ident => $wire:literal),+ $(,)? }) => {
+        impl $name {
+            /// Every variant, in declaration order
+            pub const ALL: [$name; [$($wire),+].len()] = [$($name::$variant),+];
+
+            /// Name on the wire
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $($name::$variant => $wire,)+
+                }
+            }
+        }
+
+        impl core::fmt::Display for $name {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                f.pad(self.as_str())
+            }
+        }
+
+        impl core::str::FromStr for $name {
+            type Err = UnknownName;
+
+            /// Parse the name [`as_str`](Self::as_str) gives
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                $name::ALL
+                    .into_iter()
+                    .find(|variant| variant.as_str() == s)
+                    .ok_or_else(|| UnknownName {
+                        kind: stringify!($name),
+                        name: s.to_string(),
+                    })
+            }
+        }
+    };
+}
+
+/// A name that is none of the variants of enum `kind`
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("unknown {kind} \"{name}\"")]
+pub struct UnknownName {
+    pub kind: &'static str,
+    pub name: String,
+}
+
+// ============================================================================
+// ROBOT TYPES & STATE
+// ============================================================================
+
+/// Types of autonomous robots in the AETHERIS fleet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RobotType {
+    /// Ground-based patrol rover for external pipeline inspection
+    Rover,
+    /// Aerial drone for overhead surveillance and rapid response
+    Drone,
+    /// Internal crawler for in-pipe inspection (corrosion, cracks, wall thickness)
+    Crawler,
+}
+
+enum_names!(RobotType {
+    Rover => "rover",
+    Drone => "drone",
+    Crawler => "crawler",
+});
+
+/// Identifier of a robot: two uppercase letters naming its kind, a dash,
+/// and three digits (`RV-001`). On the wire it is the plain string; one of
+/// another form fails to deserialize.
+///
+/// The one exception is [`RobotId::engine`]: the engine answers the
+/// commands it handles or rejects itself as robots answer theirs.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct RobotId(String);
+
+impl RobotId {
+    const ENGINE: &str = "engine";
+
+    /// Check that `id` is of the `XX-NNN` form, or the engine's
+    pub fn parse(id: impl Into<String>) -> Result<Self, InvalidRobotId> {
+        let id = id.into();
+        let bytes = id.as_bytes();
+        let valid = bytes.len() == 6
+            && bytes[..2].iter().all(u8::is_ascii_uppercase)
+            && bytes[2] == b'-'
+            && bytes[3..].iter().all(u8::is_ascii_digit);
+        if valid || id == Self::ENGINE {
+            Ok(Self(id))
+        } else {
+            Err(InvalidRobotId(id))
+        }
+    }
+
+    /// The engine, answering commands on its own behalf
+    pub fn engine() -> Self {
+        Self(Self::ENGINE.to_string())
+    }
+
+    pub fn is_engine(&self) -> bool {
+        self.0 == Self::ENGINE
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Type of robot the prefix names by convention: `RV` rover, `DR`
+    /// drone, `CR` crawler
+    pub fn robot_type_hint(&self) -> Option<RobotType> {
+        match self.0.get(..3)? {
+            "RV-" => Some(RobotType::Rover),
+            "DR-" => Some(RobotType::Drone),
+            "CR-" => Some(RobotType::Crawler),
+            _ => None,
+        }
+    }
+}
+
+/// A string that is not a robot id
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "invalid robot id {0:?}: expected two uppercase letters, a dash and three digits, like RV-001"
+)]
+pub struct InvalidRobotId(pub String);
+
+impl core::str::FromStr for RobotId {
+    type Err = InvalidRobotId;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl TryFrom<String> for RobotId {
+    type Error = InvalidRobotId;
+
+    fn try_from(id: String) -> Result<Self, Self::Error> {
+        Self::parse(id)
+    }
+}
+
+impl TryFrom<&str> for RobotId {
+    type Error = InvalidRobotId;
+
+    fn try_from(id: &str) -> Result<Self, Self::Error> {
+        Self::parse(id)
+    }
+}
+
+impl From<RobotId> for String {
+    fn from(id: RobotId) -> Self {
+        id.0
+    }
+}
+
+impl From<&RobotId> for String {
+    fn from(id: &RobotId) -> Self {
+        id.0.clone()
+    }
+}
+
+impl core::fmt::Display for RobotId {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl core::ops::Deref for RobotId {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for RobotId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Maps keyed by robot id can be looked up with a plain `&str`
+impl core::borrow::Borrow<str> for RobotId {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for RobotId {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for RobotId {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<String> for RobotId {
+    fn eq(&self, other: &String) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<RobotId> for String {
+    fn eq(&self, other: &RobotId) -> bool {
+        *self == other.0
+    }
+}
+
+impl PartialEq<RobotId> for &str {
+    fn eq(&self, other: &RobotId) -> bool {
+        *self == other.0
+    }
+}
+
+/// Operational status of a robot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RobotStatus {
+    /// Robot is actively executing a task
+    Active,
+    /// Robot is idle and awaiting commands
+    Idle,
+    /// Robot is in maintenance mode (charging, repairs)
+    Maintenance,
+    /// Robot has encountered an error
+    Error,
+    /// Robot is offline/disconnected
+    Offline,
+}
+
+enum_names!(RobotStatus {
+    Active => "active",
+    Idle => "idle",
+    Maintenance => "maintenance",
+    Error => "error",
+    Offline => "offline",
+});
+
+/// Current task being executed by a robot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type", content = "data")]
+pub enum CurrentTask {
+    /// No active task
+    None,
+    /// Patrolling a designated route
+    Patrolling { route_id: String },
+    /// Moving to a specific position
+    MovingTo { target: Position },
+    /// Performing a sensor scan at current location
+    Scanning { scan_type: ScanType },
+    /// Returning to charging station
+    ReturningToBase,
+    /// Investigating a detected anomaly
+    Investigating { anomaly_id: String },
+}
+
+/// Types of sensor scans
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanType {
+    /// Full multi-sensor sweep
+    Full,
+    /// Hydrogen leak detection only
+    LeakDetection,
+    /// Thermal imaging scan
+    Thermal,
+    /// Ultrasonic wall thickness measurement
+    Ultrasonic,
+    /// Visual/camera inspection
+    Visual,
+}
+
+enum_names!(ScanType {
+    Full => "full",
+    LeakDetection => "leak_detection",
+    Thermal => "thermal",
+    Ultrasonic => "ultrasonic",
+    Visual => "visual",
+});
+
+/// Health status indicators for robot subsystems
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    /// All systems nominal
+    Optimal,
+    /// Minor issues detected, operation continues
+    Warning,
+    /// Critical issues, immediate attention required
+    Critical,
+}
+
+enum_names!(HealthStatus {
+    Optimal => "optimal",
+    Warning => "warning",
+    Critical => "critical",
+});
+
+/// Complete state of a robot unit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RobotState {
     /// Unique identifier (e.g., "RV-001", "DR-002", "CR-001")
-    pub id: String,
+    pub id: RobotId,
     /// Human-readable name (e.g., "Rover Alpha", "Crawler Beta")
     pub name: String,
     /// Type of robot
@@ -164,6 +1038,9 @@ pub struct RobotState {
     pub position: Position,
     /// Current velocity vector
     pub velocity: Velocity,
+    /// Current attitude; absent from telemetry sent before it was reported
+    #[serde(default)]
+    pub orientation: Orientation,
     /// Battery level (0.0 - 100.0)
     pub battery: f64,
     /// Signal strength (0.0 - 100.0)
@@ -175,65 +1052,845 @@ pub struct RobotState {
     /// Current executing task
     pub current_task: CurrentTask,
     /// Unix timestamp of last update (milliseconds)
-    pub timestamp: u64,
+    pub timestamp: Timestamp,
 }
 
 impl RobotState {
-    pub fn new(id: impl Into<String>, name: impl Into<String>, robot_type: RobotType) -> Self {
+    #[cfg(feature = "std")]
+    pub fn new(id: RobotId, name: impl Into<String>, robot_type: RobotType) -> Self {
+        Self::new_at(id, name, robot_type, Timestamp::now())
+    }
+
+    /// A robot idle at the origin, reported at `timestamp`
+    pub fn new_at(
+        id: RobotId,
+        name: impl Into<String>,
+        robot_type: RobotType,
+        timestamp: Timestamp,
+    ) -> Self {
         Self {
-            id: id.into(),
+            id,
             name: name.into(),
             robot_type,
             position: Position::origin(),
             velocity: Velocity::zero(),
-            battery: 100.0,
-            signal: 100.0,
+            orientation: Orientation::identity(),
+            battery: limits::BATTERY_FULL_PERCENT,
+            signal: limits::SIGNAL_FULL_PERCENT,
             health: HealthStatus::Optimal,
             status: RobotStatus::Idle,
             current_task: CurrentTask::None,
-            timestamp: current_timestamp_ms(),
+            timestamp,
+        }
+    }
+
+    /// Whether the state was reported more than `max_age` before `now`,
+    /// e.g. retained telemetry of a robot that has since gone away
+    pub fn is_stale(&self, now: Timestamp, max_age: Duration) -> bool {
+        now.duration_since(self.timestamp) > max_age
+    }
+
+    /// The fields that changed since `previous`. Applying the delta to
+    /// `previous` reproduces `self` exactly.
+    pub fn diff(&self, previous: &RobotState) -> RobotStateDelta {
+        fn changed<T: PartialEq + Clone>(current: &T, previous: &T) -> Option<T> {
+            (current != previous).then(|| current.clone())
         }
+        RobotStateDelta {
+            id: self.id.clone(),
+            timestamp: self.timestamp,
+            name: changed(&self.name, &previous.name),
+            robot_type: changed(&self.robot_type, &previous.robot_type),
+            position: changed(&self.position, &previous.position),
+            velocity: changed(&self.velocity, &previous.velocity),
+            orientation: changed(&self.orientation, &previous.orientation),
+            battery: changed(&self.battery, &previous.battery),
+            signal: changed(&self.signal, &previous.signal),
+            health: changed(&self.health, &previous.health),
+            status: changed(&self.status, &previous.status),
+            current_task: changed(&self.current_task, &previous.current_task),
+        }
+    }
+
+    /// Bring the state up to date with a delta of the same robot
+    pub fn apply(&mut self, delta: &RobotStateDelta) {
+        fn set<T: Clone>(field: &mut T, value: &Option<T>) {
+            if let Some(value) = value {
+                *field = value.clone();
+            }
+        }
+        self.timestamp = delta.timestamp;
+        set(&mut self.name, &delta.name);
+        set(&mut self.robot_type, &delta.robot_type);
+        set(&mut self.position, &delta.position);
+        set(&mut self.velocity, &delta.velocity);
+        set(&mut self.orientation, &delta.orientation);
+        set(&mut self.battery, &delta.battery);
+        set(&mut self.signal, &delta.signal);
+        set(&mut self.health, &delta.health);
+        set(&mut self.status, &delta.status);
+        set(&mut self.current_task, &delta.current_task);
     }
 }
 
-// ============================================================================
-// PIPELINE ENVIRONMENT
-// ============================================================================
+/// The fields of a [`RobotState`] that changed since the robot's previous
+/// report; unset fields are unchanged
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RobotStateDelta {
+    pub id: RobotId,
+    /// Unix timestamp of the update (milliseconds)
+    pub timestamp: Timestamp,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub robot_type: Option<RobotType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<Position>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub velocity: Option<Velocity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orientation: Option<Orientation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub battery: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signal: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health: Option<HealthStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<RobotStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_task: Option<CurrentTask>,
+}
 
-/// Environmental sensor readings from pipeline infrastructure
+/// Payload of a telemetry topic: a full state (keyframe) or the changes
+/// since the previous report. A payload without a `kind` is a full state,
+/// as publishers that never send deltas write it.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct PipeEnvironment {
-    /// Unique identifier for the pipeline section
-    pub section_id: String,
-    /// Internal pressure in bar
-    pub pressure: f64,
-    /// Temperature in Celsius
-    pub temperature: f64,
-    /// Hydrogen concentration (ppm - parts per million)
-    pub h2_concentration: f64,
-    /// Wall thickness in millimeters (measured by ultrasonic)
-    pub wall_thickness: f64,
-    /// Flow rate in cubic meters per hour (m³/h)
-    pub flow_rate: f64,
-    /// Humidity percentage (0.0 - 100.0)
-    pub humidity: f64,
-    /// Reading position along the pipeline
-    pub position: Position,
-    /// Unix timestamp of reading (milliseconds)
-    pub timestamp: u64,
+#[serde(tag = "kind", rename_all = "snake_case", from = "TelemetryWire")]
+pub enum TelemetryPayload {
+    Full(RobotState),
+    Delta(RobotStateDelta),
 }
 
-impl PipeEnvironment {
-    /// Check if readings indicate a potentially hazardous condition
-    pub fn is_hazardous(&self) -> bool {
-        // H2 LEL (Lower Explosive Limit) is ~40,000 ppm (4%)
-        // We alert at 10% of LEL = 4,000 ppm for safety margin
-        self.h2_concentration > 4000.0 || self.pressure > 100.0 || self.temperature > 80.0
+impl TelemetryPayload {
+    pub fn robot_id(&self) -> &RobotId {
+        match self {
+            Self::Full(state) => &state.id,
+            Self::Delta(delta) => &delta.id,
+        }
+    }
+
+    /// When the report was made
+    pub fn timestamp(&self) -> Timestamp {
+        match self {
+            Self::Full(state) => state.timestamp,
+            Self::Delta(delta) => delta.timestamp,
+        }
     }
 }
 
-// ============================================================================
-// ANOMALY DETECTION
+/// Accepted forms of a [`TelemetryPayload`]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TelemetryWire {
+    Tagged(TaggedTelemetry),
+    Untagged(RobotState),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum TaggedTelemetry {
+    Full(RobotState),
+    Delta(RobotStateDelta),
+}
+
+impl From<TelemetryWire> for TelemetryPayload {
+    fn from(wire: TelemetryWire) -> Self {
+        match wire {
+            TelemetryWire::Tagged(TaggedTelemetry::Full(state))
+            | TelemetryWire::Untagged(state) => Self::Full(state),
+            TelemetryWire::Tagged(TaggedTelemetry::Delta(delta)) => Self::Delta(delta),
+        }
+    }
+}
+
+/// Engine-smoothed position estimate for one robot, published on
+/// [`topics::telemetry_filtered`] alongside the untouched raw telemetry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FilteredTelemetry {
+    pub robot_id: RobotId,
+    pub position: Position,
+    pub velocity: Velocity,
+    /// The estimate is a prediction across a telemetry gap, not a fresh fix
+    pub coasting: bool,
+    /// Unix timestamp the estimate is for (milliseconds)
+    pub timestamp: u64,
+}
+
+/// Telemetry of several robots in one message, published on
+/// [`topics::TELEMETRY_BATCH`] by a component reporting for many robots at
+/// once (the fleet simulator, a gateway). Receivers apply the states in
+/// order, so a robot listed twice ends up in its later state.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TelemetryBatch {
+    /// Component that assembled the batch
+    pub source: String,
+    /// Unix timestamp the batch was assembled (milliseconds)
+    pub timestamp: u64,
+    pub states: Vec<RobotState>,
+}
+
+impl TelemetryBatch {
+    #[cfg(feature = "std")]
+    pub fn new(source: impl Into<String>, states: Vec<RobotState>) -> Self {
+        Self::new_at(source, states, Timestamp::now())
+    }
+
+    /// A batch assembled at `timestamp`
+    pub fn new_at(
+        source: impl Into<String>,
+        states: Vec<RobotState>,
+        timestamp: Timestamp,
+    ) -> Self {
+        Self {
+            source: source.into(),
+            timestamp: timestamp.as_millis(),
+            states,
+        }
+    }
+}
+
+/// A robot as served to dashboards: the raw reported state plus the
+/// engine's filtered estimate, when it has one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RobotView {
+    #[serde(flatten)]
+    pub state: RobotState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filtered_position: Option<Position>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filtered_velocity: Option<Velocity>,
+    /// Declared in the engine's expected fleet
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub expected: bool,
+}
+
+impl From<RobotState> for RobotView {
+    fn from(state: RobotState) -> Self {
+        Self {
+            state,
+            filtered_position: None,
+            filtered_velocity: None,
+            expected: false,
+        }
+    }
+}
+
+// ============================================================================
+// PATROL ROUTES
+// ============================================================================
+
+/// One stop on a patrol route
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Waypoint {
+    pub position: Position,
+    /// How long to hold position on arrival (seconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dwell_secs: Option<f64>,
+    /// Scan to perform while dwelling
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scan: Option<ScanType>,
+}
+
+impl Waypoint {
+    /// A pass-through waypoint with no dwell and no scan
+    pub fn at(position: Position) -> Self {
+        Self {
+            position,
+            dwell_secs: None,
+            scan: None,
+        }
+    }
+}
+
+/// What a robot does after the last waypoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RouteMode {
+    /// Continue from the first waypoint again
+    Loop,
+    /// Stop at the last waypoint
+    OneShot,
+}
+
+enum_names!(RouteMode {
+    Loop => "loop",
+    OneShot => "one_shot",
+});
+
+/// A named, ordered patrol path referenced by
+/// [`CurrentTask::Patrolling`] and [`Command::StartPatrol`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatrolRoute {
+    /// Unique identifier (e.g., "ROUTE-A1")
+    pub id: String,
+    /// Human-readable name
+    pub name: String,
+    pub waypoints: Vec<Waypoint>,
+    pub mode: RouteMode,
+}
+
+impl PatrolRoute {
+    pub fn new(
+        id: impl Into<String>,
+        name: impl Into<String>,
+        mode: RouteMode,
+        waypoints: Vec<Waypoint>,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            waypoints,
+            mode,
+        }
+    }
+
+    /// Distance travelled over one pass, including the leg from the last
+    /// waypoint back to the first on a looping route
+    pub fn total_length(&self) -> f64 {
+        let legs: f64 = self
+            .waypoints
+            .windows(2)
+            .map(|leg| leg[0].position.distance_to(&leg[1].position))
+            .sum();
+        match (self.mode, self.waypoints.first(), self.waypoints.last()) {
+            (RouteMode::Loop, Some(first), Some(last)) => {
+                legs + last.position.distance_to(&first.position)
+            }
+            _ => legs,
+        }
+    }
+
+    /// Index of the waypoint closest to `position`; `None` for an empty route
+    pub fn nearest_waypoint(&self, position: &Position) -> Option<usize> {
+        self.waypoints
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| {
+                a.position
+                    .distance_to(position)
+                    .total_cmp(&b.position.distance_to(position))
+            })
+            .map(|(index, _)| index)
+    }
+
+    /// Waypoint to head for after `current_index`; `None` once a one-shot
+    /// route is finished
+    pub fn next_waypoint(&self, current_index: usize) -> Option<usize> {
+        let next = current_index + 1;
+        if next < self.waypoints.len() {
+            return Some(next);
+        }
+        match self.mode {
+            RouteMode::Loop if !self.waypoints.is_empty() => Some(0),
+            _ => None,
+        }
+    }
+}
+
+/// Every known patrol route, kept sorted by id. Published retained on
+/// [`topics::ROUTE_LIBRARY`] so a subscriber can learn the whole route
+/// geometry from one message.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct RouteLibrary {
+    pub routes: Vec<PatrolRoute>,
+}
+
+impl RouteLibrary {
+    /// A library of `routes`; a later route replaces an earlier one with the
+    /// same id
+    pub fn new(routes: impl IntoIterator<Item = PatrolRoute>) -> Self {
+        let mut library = Self::default();
+        for route in routes {
+            library.upsert(route);
+        }
+        library
+    }
+
+    pub fn get(&self, route_id: &str) -> Option<&PatrolRoute> {
+        self.routes.iter().find(|route| route.id == route_id)
+    }
+
+    /// Add `route`, or replace the route with its id; returns the replaced
+    /// route
+    pub fn upsert(&mut self, route: PatrolRoute) -> Option<PatrolRoute> {
+        match self.routes.binary_search_by(|r| r.id.cmp(&route.id)) {
+            Ok(index) => Some(core::mem::replace(&mut self.routes[index], route)),
+            Err(index) => {
+                self.routes.insert(index, route);
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, route_id: &str) -> Option<PatrolRoute> {
+        let index = self.routes.iter().position(|route| route.id == route_id)?;
+        Some(self.routes.remove(index))
+    }
+
+    /// Route ids in order
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.routes.iter().map(|route| route.id.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+}
+
+/// Ask whoever owns the route library to republish route definitions, on
+/// [`topics::ROUTE_REQUESTS`]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct RouteRequest {
+    /// Routes to republish on their own topics; empty asks for the whole
+    /// library instead
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub route_ids: Vec<String>,
+    /// Who is asking, for the per-requester rate limit; requests naming
+    /// no one share one allowance
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requester: Option<String>,
+}
+
+impl RouteRequest {
+    /// Request the whole library
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    pub fn routes(route_ids: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            route_ids: route_ids.into_iter().map(Into::into).collect(),
+            requester: None,
+        }
+    }
+
+    pub fn with_requester(mut self, requester: impl Into<String>) -> Self {
+        self.requester = Some(requester.into());
+        self
+    }
+}
+
+// ============================================================================
+// CHARGING STATIONS
+// ============================================================================
+
+/// A dock where robots recharge
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChargingStation {
+    /// Unique identifier (e.g., "CHG-01")
+    pub id: String,
+    pub position: Position,
+    /// Robots that can charge at the same time
+    pub capacity: u32,
+}
+
+impl ChargingStation {
+    pub fn new(id: impl Into<String>, position: Position, capacity: u32) -> Self {
+        Self {
+            id: id.into(),
+            position,
+            capacity,
+        }
+    }
+
+    /// Station closest to `position`; `None` if there are no stations
+    pub fn nearest<'a>(
+        stations: &'a [ChargingStation],
+        position: &Position,
+    ) -> Option<&'a ChargingStation> {
+        stations.iter().min_by(|a, b| {
+            a.position
+                .distance_to(position)
+                .total_cmp(&b.position.distance_to(position))
+        })
+    }
+}
+
+/// How a robot's battery drains, for predicting how far it can still go
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BatteryProfile {
+    /// Battery used per meter travelled (percent)
+    pub drain_per_meter: f64,
+    /// Battery used per second while powered (percent)
+    pub idle_drain_per_sec: f64,
+    /// Speed the robot returns to a station at (m/s)
+    pub return_speed: f64,
+}
+
+impl BatteryProfile {
+    /// Seconds needed to cover `distance` meters at return speed
+    pub fn return_secs(&self, distance: f64) -> f64 {
+        distance / self.return_speed
+    }
+
+    /// Battery needed to cover `distance` meters at return speed (percent)
+    pub fn return_cost(&self, distance: f64) -> f64 {
+        distance * self.drain_per_meter + self.return_secs(distance) * self.idle_drain_per_sec
+    }
+}
+
+// ============================================================================
+// GEOFENCES
+// ============================================================================
+
+/// A volume robots must keep out of, such as the air over a compressor
+/// station: a polygon on the ground plane (x and z), between two heights
+/// (y) when they are given
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Geofence {
+    /// Unique identifier (e.g., "FENCE-COMPRESSOR")
+    pub id: String,
+    /// Corners of the footprint in order; their y is ignored
+    pub polygon: Vec<Position>,
+    /// Heights the fence covers; every height when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub altitude: Option<AltitudeRange>,
+    /// Robot types kept out; every type when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub robot_types: Vec<RobotType>,
+}
+
+impl Geofence {
+    /// A fence over `polygon` at every height, for every robot type
+    pub fn new(id: impl Into<String>, polygon: Vec<Position>) -> Self {
+        Self {
+            id: id.into(),
+            polygon,
+            altitude: None,
+            robot_types: Vec::new(),
+        }
+    }
+
+    /// Whether robots of `robot_type` must keep out
+    pub fn applies_to(&self, robot_type: RobotType) -> bool {
+        self.robot_types.is_empty() || self.robot_types.contains(&robot_type)
+    }
+
+    /// Whether `position` lies inside the fenced volume
+    pub fn contains(&self, position: &Position) -> bool {
+        if self
+            .altitude
+            .is_some_and(|range| !range.contains(position.y))
+        {
+            return false;
+        }
+        // Even-odd rule: a ray towards +x crosses the outline an odd number
+        // of times from inside
+        let mut inside = false;
+        let mut previous = match self.polygon.last() {
+            Some(corner) => corner,
+            None => return false,
+        };
+        for corner in &self.polygon {
+            if (corner.z > position.z) != (previous.z > position.z) {
+                let x = corner.x
+                    + (previous.x - corner.x) * (position.z - corner.z) / (previous.z - corner.z);
+                if position.x < x {
+                    inside = !inside;
+                }
+            }
+            previous = corner;
+        }
+        inside
+    }
+
+    /// Fraction of the straight path from `from` to `to` covered before it
+    /// first enters the fence (0 when it starts inside); `None` if it never
+    /// does
+    pub fn entry(&self, from: &Position, to: &Position) -> Option<f64> {
+        if self.contains(from) {
+            return Some(0.0);
+        }
+        // The path can only go in or out where it crosses an edge of the
+        // footprint or one of the two heights
+        let mut cuts = vec![0.0, 1.0];
+        let (dx, dz) = (to.x - from.x, to.z - from.z);
+        let corners = self.polygon.iter().zip(self.polygon.iter().cycle().skip(1));
+        for (a, b) in corners {
+            let (ex, ez) = (b.x - a.x, b.z - a.z);
+            let denominator = dx * ez - dz * ex;
+            if denominator == 0.0 {
+                continue;
+            }
+            let (ax, az) = (a.x - from.x, a.z - from.z);
+            let t = (ax * ez - az * ex) / denominator;
+            let s = (ax * dz - az * dx) / denominator;
+            if (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&s) {
+                cuts.push(t);
+            }
+        }
+        if let Some(range) = self.altitude
+            && to.y != from.y
+        {
+            for height in [range.min, range.max] {
+                let t = (height - from.y) / (to.y - from.y);
+                if (0.0..=1.0).contains(&t) {
+                    cuts.push(t);
+                }
+            }
+        }
+        cuts.sort_by(f64::total_cmp);
+        cuts.windows(2)
+            .find(|cut| cut[1] > cut[0] && self.contains(&from.lerp(to, (cut[0] + cut[1]) / 2.0)))
+            .map(|cut| cut[0])
+            .or_else(|| self.contains(to).then_some(1.0))
+    }
+}
+
+// ============================================================================
+// PIPELINE ENVIRONMENT
+// ============================================================================
+
+/// Environmental sensor readings from pipeline infrastructure
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PipeEnvironment {
+    /// Unique identifier for the pipeline section
+    pub section_id: String,
+    /// Internal pressure in bar
+    pub pressure: f64,
+    /// Temperature in Celsius
+    pub temperature: f64,
+    /// Hydrogen concentration (ppm - parts per million)
+    pub h2_concentration: f64,
+    /// Wall thickness in millimeters (measured by ultrasonic)
+    pub wall_thickness: f64,
+    /// Flow rate in cubic meters per hour (m³/h)
+    pub flow_rate: f64,
+    /// Humidity percentage (0.0 - 100.0)
+    pub humidity: f64,
+    /// Reading position along the pipeline
+    pub position: Position,
+    /// Unix timestamp of reading (milliseconds)
+    pub timestamp: Timestamp,
+}
+
+/// Levels above which an environment reading is hazardous
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HazardThresholds {
+    pub h2_ppm: f64,
+    pub pressure_bar: f64,
+    pub temperature_celsius: f64,
+}
+
+impl Default for HazardThresholds {
+    fn default() -> Self {
+        Self {
+            h2_ppm: limits::H2_ALERT_PPM,
+            pressure_bar: limits::PRESSURE_ALERT_BAR,
+            temperature_celsius: limits::TEMPERATURE_ALERT_CELSIUS,
+        }
+    }
+}
+
+impl PipeEnvironment {
+    /// Check if readings indicate a potentially hazardous condition
+    pub fn is_hazardous(&self) -> bool {
+        self.exceeds(&HazardThresholds::default())
+    }
+
+    /// Check readings against specific thresholds
+    pub fn exceeds(&self, thresholds: &HazardThresholds) -> bool {
+        self.h2_concentration > thresholds.h2_ppm
+            || self.pressure > thresholds.pressure_bar
+            || self.temperature > thresholds.temperature_celsius
+    }
+}
+
+// ============================================================================
+// PIPELINE TOPOLOGY
+// ============================================================================
+
+/// What a pipeline section is made of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipeMaterial {
+    CarbonSteel,
+    StainlessSteel,
+    Polyethylene,
+    Composite,
+}
+
+enum_names!(PipeMaterial {
+    CarbonSteel => "carbon_steel",
+    StainlessSteel => "stainless_steel",
+    Polyethylene => "polyethylene",
+    Composite => "composite",
+});
+
+/// A straight run of pipe between two points
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PipelineSection {
+    /// Section identifier (e.g., "PIPE-001")
+    pub id: String,
+    pub start: Position,
+    pub end: Position,
+    /// Outer diameter in millimeters
+    pub diameter_mm: f64,
+    pub material: PipeMaterial,
+    /// Maximum allowable operating pressure in bar
+    pub design_pressure_bar: f64,
+    /// Sections joined to this one. Connections need only be listed on one
+    /// side.
+    #[serde(default)]
+    pub neighbors: Vec<String>,
+}
+
+impl PipelineSection {
+    pub fn length(&self) -> f64 {
+        self.start.distance_to(&self.end)
+    }
+
+    /// The point `fraction` (0 to 1) of the way from start to end
+    pub fn point_at(&self, fraction: f64) -> Position {
+        let t = fraction.clamp(0.0, 1.0);
+        Position::new(
+            self.start.x + (self.end.x - self.start.x) * t,
+            self.start.y + (self.end.y - self.start.y) * t,
+            self.start.z + (self.end.z - self.start.z) * t,
+        )
+    }
+
+    /// Shortest distance from `point` to the section's axis
+    pub fn distance_to(&self, point: &Position) -> f64 {
+        let length = self.length();
+        if length == 0.0 {
+            return self.start.distance_to(point);
+        }
+        let along = ((point.x - self.start.x) * (self.end.x - self.start.x)
+            + (point.y - self.start.y) * (self.end.y - self.start.y)
+            + (point.z - self.start.z) * (self.end.z - self.start.z))
+            / (length * length);
+        self.point_at(along).distance_to(point)
+    }
+}
+
+/// The pipeline's sections and how they connect
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PipelineMap {
+    sections: Vec<PipelineSection>,
+    index: BTreeMap<String, usize>,
+    /// Neighbors of each section, both directions of every listed
+    /// connection, in section order
+    adjacency: Vec<Vec<usize>>,
+    /// How far from a section's axis a point still counts as on it (meters)
+    tolerance: f64,
+}
+
+impl PipelineMap {
+    /// Connections to sections not in `sections` are ignored
+    pub fn new(sections: Vec<PipelineSection>, tolerance: f64) -> Self {
+        let index: BTreeMap<String, usize> = sections
+            .iter()
+            .enumerate()
+            .map(|(i, section)| (section.id.clone(), i))
+            .collect();
+        let mut adjacency = vec![Vec::new(); sections.len()];
+        for (i, section) in sections.iter().enumerate() {
+            for neighbor in &section.neighbors {
+                if let Some(&j) = index.get(neighbor)
+                    && i != j
+                {
+                    adjacency[i].push(j);
+                    adjacency[j].push(i);
+                }
+            }
+        }
+        for neighbors in &mut adjacency {
+            neighbors.sort_unstable();
+            neighbors.dedup();
+        }
+        Self {
+            sections,
+            index,
+            adjacency,
+            tolerance,
+        }
+    }
+
+    pub fn get(&self, section_id: &str) -> Option<&PipelineSection> {
+        self.index.get(section_id).map(|&i| &self.sections[i])
+    }
+
+    pub fn sections(&self) -> &[PipelineSection] {
+        &self.sections
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+
+    /// The section nearest `point`, if it lies within the tolerance of any
+    pub fn section_at(&self, point: &Position) -> Option<&PipelineSection> {
+        self.sections
+            .iter()
+            .map(|section| (section, section.distance_to(point)))
+            .filter(|(_, distance)| *distance <= self.tolerance)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(section, _)| section)
+    }
+
+    /// Sections joined to `section_id`; empty for an unknown section
+    pub fn neighbors(&self, section_id: &str) -> Vec<&PipelineSection> {
+        self.index
+            .get(section_id)
+            .map(|&i| {
+                self.adjacency[i]
+                    .iter()
+                    .map(|&j| &self.sections[j])
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The sections from `from` to `to`, both included, through the fewest
+    /// connections; `None` when either is unknown or they are not connected
+    pub fn path_between(&self, from: &str, to: &str) -> Option<Vec<&PipelineSection>> {
+        let start = *self.index.get(from)?;
+        let goal = *self.index.get(to)?;
+        let mut previous = vec![None; self.sections.len()];
+        let mut visited = vec![false; self.sections.len()];
+        let mut queue = VecDeque::from([start]);
+        visited[start] = true;
+        while let Some(current) = queue.pop_front() {
+            if current == goal {
+                let mut path = vec![&self.sections[goal]];
+                let mut at = goal;
+                while let Some(before) = previous[at] {
+                    path.push(&self.sections[before]);
+                    at = before;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for &next in &self.adjacency[current] {
+                if !visited[next] {
+                    visited[next] = true;
+                    previous[next] = Some(current);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+}
+
+// ============================================================================
+// ANOMALY DETECTION
 // ============================================================================
 
 /// Types of anomalies that can be detected
@@ -258,8 +1915,23 @@ pub enum AnomalyType {
     Unknown,
 }
 
-/// Severity levels for detected anomalies
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Ord, PartialOrd, Serialize, Deserialize)]
+enum_names!(AnomalyType {
+    Leak => "leak",
+    Corrosion => "corrosion",
+    Crack => "crack",
+    PressureDrop => "pressure_drop",
+    TemperatureAnomaly => "temperature_anomaly",
+    WallThinning => "wall_thinning",
+    StructuralDamage => "structural_damage",
+    Unknown => "unknown",
+});
+
+/// Severity levels for detected anomalies.
+///
+/// Ordered least severe first, `Info < Low < Medium < High < Critical`:
+/// thresholds, escalation and the most severe of a set are all decided by
+/// comparing levels, so new levels must be declared in their place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum SeverityLevel {
     /// Informational, no action required
@@ -274,6 +1946,26 @@ pub enum SeverityLevel {
     Critical,
 }
 
+enum_names!(SeverityLevel {
+    Info => "info",
+    Low => "low",
+    Medium => "medium",
+    High => "high",
+    Critical => "critical",
+});
+
+impl SeverityLevel {
+    /// One level more severe; Critical stays Critical
+    pub fn raised(self) -> SeverityLevel {
+        match self {
+            SeverityLevel::Info => SeverityLevel::Low,
+            SeverityLevel::Low => SeverityLevel::Medium,
+            SeverityLevel::Medium => SeverityLevel::High,
+            SeverityLevel::High | SeverityLevel::Critical => SeverityLevel::Critical,
+        }
+    }
+}
+
 /// Report of a detected anomaly
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AnomalyReport {
@@ -294,12 +1986,53 @@ pub struct AnomalyReport {
     /// Human-readable description
     pub description: String,
     /// Unix timestamp of detection (milliseconds)
-    pub timestamp: u64,
-    /// Whether the anomaly has been acknowledged
+    pub timestamp: Timestamp,
+    /// Whether the anomaly has been acknowledged; derived from `status` and
+    /// kept for older consumers
     pub acknowledged: bool,
+    /// Where the anomaly is in its operator lifecycle
+    #[serde(default, skip_serializing_if = "AnomalyStatus::is_new")]
+    pub status: AnomalyStatus,
+    /// Lifecycle transitions, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub status_history: Vec<StatusChange>,
+    /// Operator who acknowledged the anomaly
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acknowledged_by: Option<String>,
+    /// Operator who resolved the anomaly or marked it a false positive
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_by: Option<String>,
+    /// Audit trail of the Brain's triage, if the report was triaged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub triage: Option<TriageAudit>,
+    /// Recent commands that may have caused this report
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub correlated_commands: Vec<CorrelatedCommand>,
+    /// How urgently operators should be notified (independent of severity)
+    #[serde(default, skip_serializing_if = "NotificationUrgency::is_normal")]
+    pub urgency: NotificationUrgency,
+    /// The measured quantity that triggered the report, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub measurement: Option<Measurement>,
+    /// Human responder the anomaly is assigned to, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assignment: Option<Assignment>,
+    /// Detections of the same event folded into this report, itself included
+    #[serde(
+        default = "first_occurrence",
+        skip_serializing_if = "is_first_occurrence"
+    )]
+    pub occurrence_count: u32,
+    /// Unix timestamp of the latest folded detection (milliseconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<u64>,
+    /// Times the severity was raised because nobody acknowledged the report
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub escalation_count: u32,
 }
 
 impl AnomalyReport {
+    #[cfg(feature = "std")]
     pub fn new(
         anomaly_type: AnomalyType,
         severity: SeverityLevel,
@@ -309,21 +2042,377 @@ impl AnomalyReport {
         confidence: f64,
         description: impl Into<String>,
     ) -> Self {
-        Self {
-            id: generate_anomaly_id(),
+        Self::new_at(
             anomaly_type,
             severity,
             position,
-            section_id: section_id.into(),
-            detected_by: detected_by.into(),
+            section_id,
+            detected_by,
             confidence,
-            description: description.into(),
-            timestamp: current_timestamp_ms(),
-            acknowledged: false,
+            description,
+            Timestamp::now(),
+        )
+    }
+
+    /// A new report detected at `timestamp`, its id derived from it
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_at(
+        anomaly_type: AnomalyType,
+        severity: SeverityLevel,
+        position: Position,
+        section_id: impl Into<String>,
+        detected_by: impl Into<String>,
+        confidence: f64,
+        description: impl Into<String>,
+        timestamp: Timestamp,
+    ) -> Self {
+        Self {
+            id: generate_anomaly_id(timestamp),
+            anomaly_type,
+            severity,
+            position,
+            section_id: section_id.into(),
+            detected_by: detected_by.into(),
+            confidence,
+            description: description.into(),
+            timestamp,
+            acknowledged: false,
+            status: AnomalyStatus::New,
+            status_history: Vec::new(),
+            acknowledged_by: None,
+            resolved_by: None,
+            triage: None,
+            correlated_commands: Vec::new(),
+            urgency: NotificationUrgency::Normal,
+            measurement: None,
+            assignment: None,
+            occurrence_count: 1,
+            last_seen: None,
+            escalation_count: 0,
+        }
+    }
+
+    /// Move the report to `status`, recording who did it and when. Whether
+    /// the transition is allowed is up to the caller (see
+    /// [`AnomalyStatus::can_become`]).
+    pub fn set_status(&mut self, status: AnomalyStatus, by: &str, at: u64) {
+        self.status = status;
+        self.acknowledged = !status.is_new();
+        match status {
+            AnomalyStatus::Acknowledged => self.acknowledged_by = Some(by.to_string()),
+            AnomalyStatus::Resolved | AnomalyStatus::FalsePositive => {
+                self.resolved_by = Some(by.to_string())
+            }
+            AnomalyStatus::New | AnomalyStatus::Investigating => {}
+        }
+        self.status_history.push(StatusChange {
+            status,
+            by: by.to_string(),
+            at,
+        });
+    }
+}
+
+/// Where an anomaly is in its operator lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyStatus {
+    #[default]
+    New,
+    Acknowledged,
+    /// A robot was sent to investigate
+    Investigating,
+    Resolved,
+    /// Closed as not a real anomaly
+    FalsePositive,
+}
+
+enum_names!(AnomalyStatus {
+    New => "new",
+    Acknowledged => "acknowledged",
+    Investigating => "investigating",
+    Resolved => "resolved",
+    FalsePositive => "false_positive",
+});
+
+impl AnomalyStatus {
+    pub fn is_new(&self) -> bool {
+        *self == Self::New
+    }
+
+    /// Resolved and false-positive anomalies are closed for good
+    pub fn is_closed(&self) -> bool {
+        matches!(self, Self::Resolved | Self::FalsePositive)
+    }
+
+    /// Whether an anomaly in this status may move to `next`. The lifecycle
+    /// only moves forward, and may skip steps.
+    pub fn can_become(&self, next: AnomalyStatus) -> bool {
+        let stage = |status: &AnomalyStatus| match status {
+            Self::New => 0,
+            Self::Acknowledged => 1,
+            Self::Investigating => 2,
+            Self::Resolved | Self::FalsePositive => 3,
+        };
+        !self.is_closed() && stage(&next) > stage(self)
+    }
+}
+
+/// One lifecycle transition of an anomaly
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusChange {
+    pub status: AnomalyStatus,
+    /// Operator (or command source) that made the change
+    pub by: String,
+    /// Unix timestamp of the change (milliseconds)
+    pub at: u64,
+}
+
+/// How an operator closed an anomaly
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Resolution {
+    /// The cause was dealt with
+    #[default]
+    Fixed,
+    /// There was nothing there
+    FalsePositive,
+}
+
+enum_names!(Resolution {
+    Fixed => "fixed",
+    FalsePositive => "false_positive",
+});
+
+impl Resolution {
+    /// Status the anomaly closes with
+    pub fn status(&self) -> AnomalyStatus {
+        match self {
+            Resolution::Fixed => AnomalyStatus::Resolved,
+            Resolution::FalsePositive => AnomalyStatus::FalsePositive,
         }
     }
 }
 
+/// A measured value attached to an anomaly report
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Measurement {
+    /// What was measured (e.g., "battery_discharge_rate")
+    pub name: String,
+    pub value: f64,
+    /// Unit of `value` (e.g., "%/min")
+    pub unit: String,
+}
+
+/// A command issued shortly before an anomaly report that targeted the same
+/// robot or section
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CorrelatedCommand {
+    /// Audit ID of the command
+    pub command_id: String,
+    /// Command variant (e.g., "inject_fault")
+    pub variant: String,
+    /// Component that issued the command
+    pub issued_by: String,
+    /// Seconds between the command and the report
+    pub seconds_before: f64,
+}
+
+/// Notification urgency of an anomaly report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationUrgency {
+    /// Notify according to severity
+    #[default]
+    Normal,
+    /// Likely caused by our own command; notify without paging
+    Reduced,
+}
+
+enum_names!(NotificationUrgency {
+    Normal => "normal",
+    Reduced => "reduced",
+});
+
+impl NotificationUrgency {
+    pub fn is_normal(&self) -> bool {
+        *self == Self::Normal
+    }
+}
+
+// ============================================================================
+// SECTION HEALTH
+// ============================================================================
+
+/// What lowered a section's health score
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthFactorKind {
+    /// Unresolved anomalies on the section, weighted by severity
+    OpenAnomalies,
+    /// A sensor channel close to or above its hazard threshold
+    HazardMargin,
+    /// No recent environment reading
+    StaleReadings,
+}
+
+enum_names!(HealthFactorKind {
+    OpenAnomalies => "open_anomalies",
+    HazardMargin => "hazard_margin",
+    StaleReadings => "stale_readings",
+});
+
+/// One deduction from a section's health score
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthFactor {
+    pub kind: HealthFactorKind,
+    /// Points taken off the score
+    pub penalty: f64,
+    /// Human-readable detail (e.g., "h2_concentration at 90% of threshold")
+    pub detail: String,
+}
+
+/// Rolled-up health of one pipeline section
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SectionHealthReport {
+    pub section_id: String,
+    /// 0 (failing) to 100 (nothing wrong)
+    pub score: u8,
+    /// Unresolved anomalies on the section per severity (every severity
+    /// listed)
+    pub open_anomalies: BTreeMap<SeverityLevel, usize>,
+    /// Latest environment reading of the section
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<PipeEnvironment>,
+    /// Unix timestamp of the latest environment reading or robot report on
+    /// the section (milliseconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_inspection: Option<u64>,
+    /// What lowered the score, largest deduction first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub factors: Vec<HealthFactor>,
+    /// Unix timestamp the score was computed (milliseconds)
+    pub timestamp: u64,
+}
+
+// ============================================================================
+// AI TRIAGE
+// ============================================================================
+
+/// Action the Brain recommends for a triaged anomaly
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriageAction {
+    /// Send a robot to investigate
+    Dispatch,
+    /// Keep watching, no robot needed yet
+    Monitor,
+    /// Escalate to a human operator
+    Escalate,
+    /// Likely a false positive
+    Dismiss,
+}
+
+enum_names!(TriageAction {
+    Dispatch => "dispatch",
+    Monitor => "monitor",
+    Escalate => "escalate",
+    Dismiss => "dismiss",
+});
+
+/// Robot near an anomaly, included as fleet context for triage
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NearbyRobot {
+    pub robot_id: String,
+    pub robot_type: RobotType,
+    pub status: RobotStatus,
+    pub battery: f64,
+    /// Distance to the anomaly in meters
+    pub distance: f64,
+}
+
+/// Request for the Brain to triage an anomaly before robots are dispatched
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TriageRequest {
+    /// The anomaly to triage
+    pub report: AnomalyReport,
+    /// Robots near the anomaly, nearest first
+    pub nearby_robots: Vec<NearbyRobot>,
+    /// Most recent environment reading for the anomaly's section
+    pub latest_environment: Option<PipeEnvironment>,
+    /// Unix timestamp of the request (milliseconds)
+    pub timestamp: u64,
+}
+
+/// The Brain's assessment of a triaged anomaly
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TriageResult {
+    /// Anomaly being assessed
+    pub anomaly_id: String,
+    /// Severity the Brain assigns, if different from the report
+    pub adjusted_severity: Option<SeverityLevel>,
+    /// Confidence the Brain assigns, if different from the report
+    pub adjusted_confidence: Option<f64>,
+    /// Recommended next step
+    pub recommended_action: TriageAction,
+    /// Explanation for operators
+    pub rationale: String,
+    /// Unix timestamp of the assessment (milliseconds)
+    pub timestamp: u64,
+}
+
+/// Progress of an assigned anomaly
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AssignmentState {
+    Assigned,
+    InProgress,
+    Done,
+}
+
+enum_names!(AssignmentState {
+    Assigned => "assigned",
+    InProgress => "in_progress",
+    Done => "done",
+});
+
+impl AssignmentState {
+    /// Whether the responder still has work to do
+    pub fn is_open(&self) -> bool {
+        *self != AssignmentState::Done
+    }
+}
+
+/// Ownership of an anomaly by a named human responder
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Assignment {
+    pub assignee: String,
+    /// Operator who made the assignment
+    pub assigned_by: String,
+    /// Unix timestamp of the (re)assignment (milliseconds)
+    pub assigned_at: u64,
+    /// Unix timestamp the work is due (milliseconds)
+    pub due_at: u64,
+    pub state: AssignmentState,
+}
+
+/// Record of how triage changed a report
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TriageAudit {
+    /// Severity before triage
+    pub original_severity: SeverityLevel,
+    /// Confidence before triage
+    pub original_confidence: f64,
+    /// Recommended action, None if triage timed out
+    pub recommended_action: Option<TriageAction>,
+    /// Brain rationale, or a note that triage timed out
+    pub rationale: String,
+    /// True if no result arrived in time and the report went out untriaged
+    pub timed_out: bool,
+    /// Unix timestamp triage completed (milliseconds)
+    pub completed_at: u64,
+}
+
 // ============================================================================
 // COMMANDS
 // ============================================================================
@@ -351,8 +2440,240 @@ pub enum Command {
     EmergencyStop,
     /// Inject a simulated fault (for testing)
     InjectFault { fault_type: FaultType },
-    /// Update robot configuration
+    /// Clear an injected fault, or every fault when `fault_type` is `None`
+    ClearFault { fault_type: Option<FaultType> },
+    /// Fix the robot's position from an external reference, ending any GPS
+    /// drift
+    CorrectPosition,
+    /// Start a simulated hydrogen leak in a pipeline section (for testing)
+    InjectLeak {
+        section_id: String,
+        severity: SeverityLevel,
+    },
+    /// Seal a simulated leak, or every leak when `section_id` is `None`
+    ClearLeak { section_id: Option<String> },
+    /// Update robot configuration; settings left unset keep their value
     Configure { config: RobotConfig },
+    /// Ask for a robot's effective configuration, answered by the engine
+    /// with a [`CommandResponse`] carrying it
+    GetConfig,
+    /// Register a pipeline section, optionally merging a provisional one into it
+    RegisterSection {
+        section_id: String,
+        position: Position,
+        merge_from: Option<String>,
+    },
+    /// Change the operational mode of a zone, until `until` (Unix ms) if set
+    SetZoneMode {
+        zone_id: String,
+        mode: ZoneMode,
+        until: Option<u64>,
+    },
+    /// Assign (or reassign) an anomaly to a responder, due at `due_at` (Unix ms)
+    AssignAnomaly {
+        anomaly_id: String,
+        assignee: String,
+        due_at: u64,
+    },
+    /// Update the progress of an anomaly's assignment
+    UpdateAssignment {
+        anomaly_id: String,
+        state: AssignmentState,
+    },
+    /// Acknowledge an anomaly
+    AcknowledgeAnomaly { anomaly_id: String },
+    /// Resolve an anomaly; `force` is required while its assignment is open
+    ResolveAnomaly {
+        anomaly_id: String,
+        #[serde(default)]
+        resolution: Resolution,
+        #[serde(default)]
+        force: bool,
+    },
+    /// Send a full telemetry state next, for a receiver that got a delta
+    /// it has nothing to apply to
+    RequestKeyframe,
+    /// Run a sequence of commands on the plan's robot, one step at a time
+    StartMission { plan: MissionPlan },
+    /// Stop a running mission and the robot carrying it out
+    AbortMission { mission_id: String },
+}
+
+impl Command {
+    /// Wire names of every command variant
+    pub const NAMES: [&'static str; 23] = [
+        "move_to",
+        "stop",
+        "perform_scan",
+        "start_patrol",
+        "return_to_base",
+        "investigate",
+        "emergency_stop",
+        "inject_fault",
+        "clear_fault",
+        "inject_leak",
+        "clear_leak",
+        "configure",
+        "get_config",
+        "register_section",
+        "set_zone_mode",
+        "assign_anomaly",
+        "update_assignment",
+        "acknowledge_anomaly",
+        "resolve_anomaly",
+        "request_keyframe",
+        "start_mission",
+        "abort_mission",
+        "correct_position",
+    ];
+
+    /// Wire name of the command variant (e.g., "inject_fault")
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::MoveTo { .. } => "move_to",
+            Command::Stop => "stop",
+            Command::PerformScan { .. } => "perform_scan",
+            Command::StartPatrol { .. } => "start_patrol",
+            Command::ReturnToBase => "return_to_base",
+            Command::Investigate { .. } => "investigate",
+            Command::EmergencyStop => "emergency_stop",
+            Command::InjectFault { .. } => "inject_fault",
+            Command::ClearFault { .. } => "clear_fault",
+            Command::InjectLeak { .. } => "inject_leak",
+            Command::ClearLeak { .. } => "clear_leak",
+            Command::Configure { .. } => "configure",
+            Command::GetConfig => "get_config",
+            Command::RegisterSection { .. } => "register_section",
+            Command::SetZoneMode { .. } => "set_zone_mode",
+            Command::AssignAnomaly { .. } => "assign_anomaly",
+            Command::UpdateAssignment { .. } => "update_assignment",
+            Command::AcknowledgeAnomaly { .. } => "acknowledge_anomaly",
+            Command::ResolveAnomaly { .. } => "resolve_anomaly",
+            Command::RequestKeyframe => "request_keyframe",
+            Command::StartMission { .. } => "start_mission",
+            Command::AbortMission { .. } => "abort_mission",
+            Command::CorrectPosition => "correct_position",
+        }
+    }
+
+    /// Whether the command may carry an expiry; stops are always acted on
+    pub fn expires(&self) -> bool {
+        !matches!(self, Command::Stop | Command::EmergencyStop)
+    }
+
+    /// Robot operation the command performs; `None` for stops and
+    /// administrative commands, which zone modes never block
+    pub fn operation(&self) -> Option<OperationKind> {
+        match self {
+            Command::MoveTo { .. } | Command::ReturnToBase => Some(OperationKind::Movement),
+            Command::PerformScan { .. } => Some(OperationKind::Scan),
+            Command::StartPatrol { .. } => Some(OperationKind::Patrol),
+            Command::Investigate { .. } => Some(OperationKind::Investigation),
+            Command::InjectFault { .. } | Command::ClearFault { .. } => {
+                Some(OperationKind::FaultInjection)
+            }
+            Command::Configure { .. } => Some(OperationKind::Configuration),
+            Command::Stop
+            | Command::EmergencyStop
+            | Command::InjectLeak { .. }
+            | Command::ClearLeak { .. }
+            | Command::RegisterSection { .. }
+            | Command::SetZoneMode { .. }
+            | Command::AssignAnomaly { .. }
+            | Command::UpdateAssignment { .. }
+            | Command::AcknowledgeAnomaly { .. }
+            | Command::ResolveAnomaly { .. }
+            | Command::RequestKeyframe
+            | Command::CorrectPosition
+            | Command::GetConfig
+            | Command::StartMission { .. }
+            | Command::AbortMission { .. } => None,
+        }
+    }
+}
+
+/// Privileges of whoever issues a command, lowest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperatorRole {
+    /// May watch but send nothing
+    Viewer,
+    Operator,
+    Admin,
+}
+
+enum_names!(OperatorRole {
+    Viewer => "viewer",
+    Operator => "operator",
+    Admin => "admin",
+});
+
+/// Identity a command is issued under
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Operator {
+    pub id: String,
+    pub role: OperatorRole,
+}
+
+impl Operator {
+    pub fn new(id: impl Into<String>, role: OperatorRole) -> Self {
+        Self {
+            id: id.into(),
+            role,
+        }
+    }
+}
+
+/// Robot operations a zone mode can disallow
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationKind {
+    Movement,
+    /// Sensor scans, including active (ignition-capable) ones
+    Scan,
+    Patrol,
+    Investigation,
+    FaultInjection,
+    Configuration,
+    /// Any operation by a drone
+    DroneFlight,
+}
+
+enum_names!(OperationKind {
+    Movement => "movement",
+    Scan => "scan",
+    Patrol => "patrol",
+    Investigation => "investigation",
+    FaultInjection => "fault_injection",
+    Configuration => "configuration",
+    DroneFlight => "drone_flight",
+});
+
+/// Operational mode of a zone
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "mode")]
+pub enum ZoneMode {
+    #[default]
+    Normal,
+    /// Robots may operate, except for the listed operations
+    Restricted { disallowed: Vec<OperationKind> },
+    /// No robot may operate in or travel through the zone
+    Excluded,
+}
+
+impl ZoneMode {
+    /// Whether a robot of `robot_type` may perform `operation` in the zone
+    pub fn permits(&self, operation: OperationKind, robot_type: RobotType) -> bool {
+        match self {
+            ZoneMode::Normal => true,
+            ZoneMode::Restricted { disallowed } => {
+                let drone_grounded = robot_type == RobotType::Drone
+                    && disallowed.contains(&OperationKind::DroneFlight);
+                !drone_grounded && !disallowed.contains(&operation)
+            }
+            ZoneMode::Excluded => false,
+        }
+    }
 }
 
 /// Types of faults that can be injected for testing
@@ -371,6 +2692,14 @@ pub enum FaultType {
     GpsDrift,
 }
 
+enum_names!(FaultType {
+    LowBattery => "low_battery",
+    SensorFailure => "sensor_failure",
+    CommDropout => "comm_dropout",
+    MotorFailure => "motor_failure",
+    GpsDrift => "gps_drift",
+});
+
 /// Robot configuration parameters
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct RobotConfig {
@@ -382,190 +2711,2889 @@ pub struct RobotConfig {
     pub heartbeat_interval: Option<u32>,
     /// Low battery threshold percentage
     pub low_battery_threshold: Option<f64>,
+    /// Scans the robot can perform, replacing those of its type
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supported_scans: Option<Vec<ScanType>>,
+    /// Heights the robot can operate at, replacing those of its type
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operating_altitude: Option<AltitudeRange>,
+}
+
+impl RobotConfig {
+    /// Take every setting `update` sets; those it leaves unset keep their
+    /// value
+    pub fn merge(&mut self, update: &RobotConfig) {
+        let RobotConfig {
+            max_speed,
+            scan_interval,
+            heartbeat_interval,
+            low_battery_threshold,
+            supported_scans,
+            operating_altitude,
+        } = update;
+        self.max_speed = max_speed.or(self.max_speed);
+        self.scan_interval = scan_interval.or(self.scan_interval);
+        self.heartbeat_interval = heartbeat_interval.or(self.heartbeat_interval);
+        self.low_battery_threshold = low_battery_threshold.or(self.low_battery_threshold);
+        if supported_scans.is_some() {
+            self.supported_scans = supported_scans.clone();
+        }
+        self.operating_altitude = operating_altitude.or(self.operating_altitude);
+    }
 }
 
 // ============================================================================
-// MQTT MESSAGES
+// MISSIONS
 // ============================================================================
 
-/// Wrapper for all MQTT messages with metadata
+/// What a mission does when a step fails or times out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailurePolicy {
+    /// End the mission as failed
+    #[default]
+    Abort,
+    /// Go on with the next step
+    Continue,
+    /// Send the step again, up to this many more times, then abort
+    Retry(u32),
+}
+
+/// Robot condition a mission step waits for before its command is sent
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct MqttMessage<T> {
-    /// Message payload
-    pub payload: T,
-    /// Source robot/component ID
-    pub source: String,
-    /// Unix timestamp (milliseconds)
-    pub timestamp: u64,
-    /// Message sequence number
-    pub seq: u64,
+#[serde(rename_all = "snake_case")]
+pub enum Precondition {
+    /// Battery at least this percentage
+    MinBattery(f64),
+    /// Robot in this status
+    Status(RobotStatus),
+    /// Robot within `radius` meters of `position`
+    Near { position: Position, radius: f64 },
 }
 
-impl<T> MqttMessage<T> {
-    pub fn new(payload: T, source: impl Into<String>, seq: u64) -> Self {
-        Self {
-            payload,
-            source: source.into(),
-            timestamp: current_timestamp_ms(),
-            seq,
+impl Precondition {
+    /// Whether `state` satisfies the condition
+    pub fn holds(&self, state: &RobotState) -> bool {
+        match self {
+            Precondition::MinBattery(percent) => state.battery >= *percent,
+            Precondition::Status(status) => state.status == *status,
+            Precondition::Near { position, radius } => {
+                state.position.distance_to(position) <= *radius
+            }
         }
     }
 }
 
-/// Heartbeat message for connectivity monitoring
+impl core::fmt::Display for Precondition {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Precondition::MinBattery(percent) => write!(f, "battery at least {percent}%"),
+            Precondition::Status(status) => write!(f, "status {status}"),
+            Precondition::Near { position, radius } => write!(
+                f,
+                "within {radius}m of ({}, {}, {})",
+                position.x, position.y, position.z
+            ),
+        }
+    }
+}
+
+/// One command of a mission
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Heartbeat {
-    /// Robot ID
+pub struct MissionStep {
+    pub command: Command,
+    /// How long the robot has to answer the command, and how long the step
+    /// waits for its preconditions
+    pub timeout_secs: u64,
+    #[serde(default)]
+    pub on_failure: FailurePolicy,
+    /// Conditions the robot must meet before the command is sent
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub preconditions: Vec<Precondition>,
+}
+
+/// An ordered list of commands for one robot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MissionPlan {
+    pub id: String,
     pub robot_id: String,
-    /// Robot type
-    pub robot_type: RobotType,
-    /// Current status
-    pub status: RobotStatus,
-    /// Battery level
-    pub battery: f64,
-    /// Signal strength
-    pub signal: f64,
-    /// Uptime in seconds
-    pub uptime: u64,
+    pub steps: Vec<MissionStep>,
+}
+
+/// Where a mission is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissionState {
+    Running,
+    Completed,
+    /// A step failed under the Abort policy, or ran out of retries
+    Failed,
+    /// Stopped by an AbortMission command
+    Aborted,
+}
+
+enum_names!(MissionState {
+    Running => "running",
+    Completed => "completed",
+    Failed => "failed",
+    Aborted => "aborted",
+});
+
+/// Progress of a mission, published on every step and when it ends
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MissionStatus {
+    pub mission_id: String,
+    pub robot_id: String,
+    pub state: MissionState,
+    /// Index of the current step, or of the last one run once the mission
+    /// ended
+    pub step: usize,
+    /// Number of steps in the plan
+    pub steps: usize,
+    /// Attempt of the current step, from 1
+    pub attempt: u32,
+    /// Steps that failed and were skipped under the Continue policy
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<usize>,
+    /// Why the latest attempt failed, if it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
     /// Unix timestamp (milliseconds)
     pub timestamp: u64,
 }
 
-impl Heartbeat {
-    pub fn new(
-        robot_id: impl Into<String>,
-        robot_type: RobotType,
-        status: RobotStatus,
-        battery: f64,
-        signal: f64,
-        uptime: u64,
-    ) -> Self {
-        Self {
-            robot_id: robot_id.into(),
-            robot_type,
-            status,
-            battery,
-            signal,
-            uptime,
-            timestamp: current_timestamp_ms(),
-        }
+// ============================================================================
+// CAPABILITIES
+// ============================================================================
+
+/// Heights a robot operates at, as position `y` in meters; negative is
+/// below the site datum, e.g. inside buried pipe
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AltitudeRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl AltitudeRange {
+    pub const fn new(min: f64, max: f64) -> Self {
+        Self { min, max }
+    }
+
+    pub fn contains(&self, y: f64) -> bool {
+        (self.min..=self.max).contains(&y)
+    }
+}
+
+impl RobotType {
+    /// Scans the type's sensor package can perform
+    pub fn supported_scans(&self) -> &'static [ScanType] {
+        match self {
+            RobotType::Rover => &[
+                ScanType::Full,
+                ScanType::LeakDetection,
+                ScanType::Thermal,
+                ScanType::Visual,
+            ],
+            RobotType::Drone => &[ScanType::LeakDetection, ScanType::Thermal, ScanType::Visual],
+            // Wall thickness needs contact with the pipe
+            RobotType::Crawler => &[ScanType::Full, ScanType::Ultrasonic, ScanType::Visual],
+        }
+    }
+
+    /// Top speed in m/s
+    pub fn max_speed(&self) -> f64 {
+        match self {
+            RobotType::Rover => 3.0,
+            RobotType::Drone => 15.0,
+            RobotType::Crawler => 0.5,
+        }
+    }
+
+    pub fn operating_altitude(&self) -> AltitudeRange {
+        match self {
+            RobotType::Rover => AltitudeRange::new(-1.0, 10.0),
+            RobotType::Drone => AltitudeRange::new(-20.0, 120.0),
+            RobotType::Crawler => AltitudeRange::new(-5.0, 0.0),
+        }
+    }
+
+    /// Nominal battery drain of the type
+    pub fn battery_profile(&self) -> BatteryProfile {
+        match self {
+            RobotType::Rover => BatteryProfile {
+                drain_per_meter: 0.05,
+                idle_drain_per_sec: 0.005,
+                return_speed: 1.5,
+            },
+            // Hovering costs far more than rolling
+            RobotType::Drone => BatteryProfile {
+                drain_per_meter: 0.02,
+                idle_drain_per_sec: 0.05,
+                return_speed: 8.0,
+            },
+            RobotType::Crawler => BatteryProfile {
+                drain_per_meter: 0.1,
+                idle_drain_per_sec: 0.005,
+                return_speed: 0.3,
+            },
+        }
+    }
+
+    /// Capabilities of a robot of this type without overrides
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            robot_type: *self,
+            scans: self.supported_scans().to_vec(),
+            max_speed: self.max_speed(),
+            altitude: self.operating_altitude(),
+        }
+    }
+
+    pub fn supports_command(&self, command: &Command) -> bool {
+        self.capabilities().check(command).is_ok()
+    }
+}
+
+/// What one robot can do: the defaults of its type, with a special unit's
+/// overrides from its [`RobotConfig`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capabilities {
+    pub robot_type: RobotType,
+    pub scans: Vec<ScanType>,
+    /// m/s
+    pub max_speed: f64,
+    pub altitude: AltitudeRange,
+}
+
+impl Capabilities {
+    /// These capabilities with every override `config` sets
+    pub fn configured(mut self, config: &RobotConfig) -> Self {
+        if let Some(max_speed) = config.max_speed {
+            self.max_speed = max_speed;
+        }
+        if let Some(scans) = &config.supported_scans {
+            self.scans = scans.clone();
+        }
+        if let Some(altitude) = config.operating_altitude {
+            self.altitude = altitude;
+        }
+        self
+    }
+
+    /// Why the robot cannot carry out `command`, if it cannot. Routes are
+    /// only known by id here; see [`Self::check_route`].
+    pub fn check(&self, command: &Command) -> Result<(), Unsupported> {
+        match command {
+            Command::PerformScan { scan_type } => self.check_scan(*scan_type),
+            Command::MoveTo { target, speed } => {
+                if let Some(speed) = speed
+                    && *speed > self.max_speed
+                {
+                    return Err(Unsupported::Speed {
+                        robot_type: self.robot_type,
+                        requested: *speed,
+                        max: self.max_speed,
+                    });
+                }
+                self.check_altitude(target.y)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Why the robot cannot patrol `route`, if it cannot
+    pub fn check_route(&self, route: &PatrolRoute) -> Result<(), Unsupported> {
+        for waypoint in &route.waypoints {
+            self.check_altitude(waypoint.position.y)?;
+            if let Some(scan) = waypoint.scan {
+                self.check_scan(scan)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_scan(&self, scan: ScanType) -> Result<(), Unsupported> {
+        if self.scans.contains(&scan) {
+            Ok(())
+        } else {
+            Err(Unsupported::Scan {
+                robot_type: self.robot_type,
+                scan,
+            })
+        }
+    }
+
+    fn check_altitude(&self, y: f64) -> Result<(), Unsupported> {
+        if self.altitude.contains(y) {
+            Ok(())
+        } else {
+            Err(Unsupported::Altitude {
+                robot_type: self.robot_type,
+                y,
+                range: self.altitude,
+            })
+        }
+    }
+}
+
+/// A command beyond what the target robot can do
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Unsupported {
+    #[error("{} cannot perform {scan:?} scans", .robot_type.as_str())]
+    Scan {
+        robot_type: RobotType,
+        scan: ScanType,
+    },
+    #[error("{} is limited to {max} m/s, {requested} m/s requested", .robot_type.as_str())]
+    Speed {
+        robot_type: RobotType,
+        requested: f64,
+        max: f64,
+    },
+    #[error(
+        "{} operates at y {}..={} m, not {y} m",
+        .robot_type.as_str(),
+        .range.min,
+        .range.max
+    )]
+    Altitude {
+        robot_type: RobotType,
+        y: f64,
+        range: AltitudeRange,
+    },
+}
+
+// ============================================================================
+// VALIDATION
+// ============================================================================
+
+/// A field holding a value no working sender produces
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    /// NaN or infinite
+    NonFinite { field: String, value: f64 },
+    /// Finite, but outside the field's physical range
+    OutOfRange {
+        field: String,
+        value: f64,
+        min: f64,
+        max: f64,
+    },
+    /// An identifier or list left empty
+    Empty { field: String },
+    /// A mission command inside a mission
+    Nested { field: String },
+}
+
+impl ValidationError {
+    /// Path of the offending field (e.g. "position.x")
+    pub fn field(&self) -> &str {
+        match self {
+            Self::NonFinite { field, .. }
+            | Self::OutOfRange { field, .. }
+            | Self::Empty { field }
+            | Self::Nested { field } => field,
+        }
+    }
+
+    /// The same error on a field of the value at `path`
+    fn within(mut self, path: &str) -> Self {
+        let (Self::NonFinite { field, .. }
+        | Self::OutOfRange { field, .. }
+        | Self::Empty { field }
+        | Self::Nested { field }) = &mut self;
+        *field = format!("{path}.command.{field}");
+        self
+    }
+}
+
+impl core::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NonFinite { field, value } => write!(f, "{field} must be finite, got {value}"),
+            Self::OutOfRange {
+                field,
+                value,
+                min,
+                max,
+            } if max.is_infinite() => write!(f, "{field} must be at least {min}, got {value}"),
+            Self::OutOfRange {
+                field,
+                value,
+                min,
+                max,
+            } => write!(f, "{field} must be within {min}..={max}, got {value}"),
+            Self::Empty { field } => write!(f, "{field} must not be empty"),
+            Self::Nested { field } => write!(f, "{field} cannot be a mission command"),
+        }
+    }
+}
+
+impl core::error::Error for ValidationError {}
+
+/// Sanity checks on a received message, run once it has deserialized
+pub trait Validate {
+    /// The first field holding an impossible value, if any
+    fn validate(&self) -> Result<(), ValidationError>;
+}
+
+fn finite(field: &str, value: f64) -> Result<(), ValidationError> {
+    if value.is_finite() {
+        Ok(())
+    } else {
+        Err(ValidationError::NonFinite {
+            field: field.into(),
+            value,
+        })
+    }
+}
+
+fn within(field: &str, value: f64, min: f64, max: f64) -> Result<(), ValidationError> {
+    finite(field, value)?;
+    if (min..=max).contains(&value) {
+        Ok(())
+    } else {
+        Err(ValidationError::OutOfRange {
+            field: field.into(),
+            value,
+            min,
+            max,
+        })
+    }
+}
+
+fn at_least(field: &str, value: f64, min: f64) -> Result<(), ValidationError> {
+    within(field, value, min, f64::INFINITY)
+}
+
+fn percent(field: &str, value: f64) -> Result<(), ValidationError> {
+    within(field, value, 0.0, 100.0)
+}
+
+fn non_empty(field: &str, value: &str) -> Result<(), ValidationError> {
+    if value.trim().is_empty() {
+        Err(ValidationError::Empty {
+            field: field.into(),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+fn finite_position(field: &str, position: &Position) -> Result<(), ValidationError> {
+    finite(&format!("{field}.x"), position.x)?;
+    finite(&format!("{field}.y"), position.y)?;
+    finite(&format!("{field}.z"), position.z)
+}
+
+impl Validate for RobotState {
+    fn validate(&self) -> Result<(), ValidationError> {
+        finite_position("position", &self.position)?;
+        finite("velocity.vx", self.velocity.vx)?;
+        finite("velocity.vy", self.velocity.vy)?;
+        finite("velocity.vz", self.velocity.vz)?;
+        finite("orientation.yaw", self.orientation.yaw)?;
+        finite("orientation.pitch", self.orientation.pitch)?;
+        finite("orientation.roll", self.orientation.roll)?;
+        percent("battery", self.battery)?;
+        percent("signal", self.signal)
+    }
+}
+
+impl Validate for Heartbeat {
+    fn validate(&self) -> Result<(), ValidationError> {
+        percent("battery", self.battery)?;
+        percent("signal", self.signal)
+    }
+}
+
+impl Validate for PipeEnvironment {
+    fn validate(&self) -> Result<(), ValidationError> {
+        non_empty("section_id", &self.section_id)?;
+        at_least("pressure", self.pressure, 0.0)?;
+        // Colder than absolute zero is a broken sensor
+        at_least("temperature", self.temperature, -273.15)?;
+        at_least("h2_concentration", self.h2_concentration, 0.0)?;
+        at_least("wall_thickness", self.wall_thickness, 0.0)?;
+        finite("flow_rate", self.flow_rate)?;
+        percent("humidity", self.humidity)?;
+        finite_position("position", &self.position)
+    }
+}
+
+impl Validate for AnomalyReport {
+    fn validate(&self) -> Result<(), ValidationError> {
+        non_empty("id", &self.id)?;
+        non_empty("section_id", &self.section_id)?;
+        non_empty("detected_by", &self.detected_by)?;
+        within("confidence", self.confidence, 0.0, 1.0)?;
+        at_least("occurrence_count", self.occurrence_count as f64, 1.0)?;
+        finite_position("position", &self.position)
+    }
+}
+
+impl Validate for Command {
+    fn validate(&self) -> Result<(), ValidationError> {
+        match self {
+            Command::MoveTo { target, speed } => {
+                finite_position("target", target)?;
+                if let Some(speed) = speed {
+                    at_least("speed", *speed, 0.0)?;
+                }
+                Ok(())
+            }
+            Command::StartPatrol { route_id } => non_empty("route_id", route_id),
+            Command::Configure { config } => {
+                if let Some(max_speed) = config.max_speed {
+                    at_least("config.max_speed", max_speed, 0.0)?;
+                }
+                if let Some(threshold) = config.low_battery_threshold {
+                    percent("config.low_battery_threshold", threshold)?;
+                }
+                for (field, interval) in [
+                    ("config.scan_interval", config.scan_interval),
+                    ("config.heartbeat_interval", config.heartbeat_interval),
+                ] {
+                    if let Some(interval) = interval {
+                        at_least(field, interval as f64, 1.0)?;
+                    }
+                }
+                if let Some(altitude) = config.operating_altitude {
+                    finite("config.operating_altitude.min", altitude.min)?;
+                    within(
+                        "config.operating_altitude.max",
+                        altitude.max,
+                        altitude.min,
+                        f64::INFINITY,
+                    )?;
+                }
+                Ok(())
+            }
+            Command::RegisterSection {
+                section_id,
+                position,
+                ..
+            } => {
+                non_empty("section_id", section_id)?;
+                finite_position("position", position)
+            }
+            Command::SetZoneMode { zone_id, .. } => non_empty("zone_id", zone_id),
+            Command::InjectLeak { section_id, .. }
+            | Command::ClearLeak {
+                section_id: Some(section_id),
+            } => non_empty("section_id", section_id),
+            Command::AssignAnomaly {
+                anomaly_id,
+                assignee,
+                ..
+            } => {
+                non_empty("anomaly_id", anomaly_id)?;
+                non_empty("assignee", assignee)
+            }
+            Command::StartMission { plan } => plan.validate(),
+            Command::AbortMission { mission_id } => non_empty("mission_id", mission_id),
+            Command::Investigate { anomaly_id }
+            | Command::UpdateAssignment { anomaly_id, .. }
+            | Command::AcknowledgeAnomaly { anomaly_id }
+            | Command::ResolveAnomaly { anomaly_id, .. } => non_empty("anomaly_id", anomaly_id),
+            Command::Stop
+            | Command::PerformScan { .. }
+            | Command::ReturnToBase
+            | Command::EmergencyStop
+            | Command::InjectFault { .. }
+            | Command::ClearFault { .. }
+            | Command::ClearLeak { section_id: None }
+            | Command::RequestKeyframe
+            | Command::CorrectPosition
+            | Command::GetConfig => Ok(()),
+        }
+    }
+}
+
+impl Validate for MissionPlan {
+    fn validate(&self) -> Result<(), ValidationError> {
+        non_empty("plan.id", &self.id)?;
+        non_empty("plan.robot_id", &self.robot_id)?;
+        if self.steps.is_empty() {
+            return Err(ValidationError::Empty {
+                field: "plan.steps".into(),
+            });
+        }
+        for (index, step) in self.steps.iter().enumerate() {
+            let field = format!("plan.steps[{index}]");
+            at_least(
+                &format!("{field}.timeout_secs"),
+                step.timeout_secs as f64,
+                1.0,
+            )?;
+            // Missions do not nest
+            if let Command::StartMission { .. } | Command::AbortMission { .. } = step.command {
+                return Err(ValidationError::Nested {
+                    field: format!("{field}.command"),
+                });
+            }
+            for (i, precondition) in step.preconditions.iter().enumerate() {
+                let field = format!("{field}.preconditions[{i}]");
+                match precondition {
+                    Precondition::MinBattery(value) => percent(&field, *value)?,
+                    Precondition::Status(_) => {}
+                    Precondition::Near { position, radius } => {
+                        finite_position(&format!("{field}.position"), position)?;
+                        at_least(&format!("{field}.radius"), *radius, 0.0)?;
+                    }
+                }
+            }
+            step.command.validate().map_err(|e| e.within(&field))?;
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// SCHEMA VERSIONING
+// ============================================================================
+
+/// Envelope schema version this build writes.
+///
+/// Compatibility policy for every message type:
+///
+/// - Unknown fields are ignored, so older readers accept newer payloads
+///   that only add fields. No type uses `deny_unknown_fields`.
+/// - New fields are optional or carry a serde default, so payloads written
+///   before they existed still parse.
+/// - Anything else (a removed or retyped field, a renamed variant) bumps
+///   this version. Readers refuse envelopes newer than the version they
+///   understand instead of misreading them.
+pub const CURRENT_VERSION: u16 = 1;
+
+/// Oldest envelope schema version still accepted
+pub const MIN_SUPPORTED_VERSION: u16 = 1;
+
+const _: () = assert!(MIN_SUPPORTED_VERSION <= CURRENT_VERSION);
+
+/// Whether an envelope of `version` can be read by this build
+pub fn is_supported_version(version: u16) -> bool {
+    (MIN_SUPPORTED_VERSION..=CURRENT_VERSION).contains(&version)
+}
+
+/// Envelopes from before versioning are version 1
+fn first_version() -> u16 {
+    1
+}
+
+/// The schema version of an envelope, read without parsing its payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct EnvelopeVersion {
+    #[serde(default = "first_version")]
+    pub version: u16,
+}
+
+// ============================================================================
+// MQTT MESSAGES
+// ============================================================================
+
+/// Wrapper for all MQTT messages with metadata
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MqttMessage<T> {
+    /// Message payload
+    pub payload: T,
+    /// Source robot/component ID
+    pub source: String,
+    /// Unix timestamp (milliseconds)
+    pub timestamp: Timestamp,
+    /// Message sequence number
+    pub seq: u64,
+    /// Envelope schema version (see [`CURRENT_VERSION`])
+    #[serde(default = "first_version")]
+    pub version: u16,
+    /// Id of the command carried, echoed in its [`CommandResponse`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command_id: Option<String>,
+    /// Operator who issued the command carried
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operator: Option<Operator>,
+    /// Hex HMAC-SHA256 under the operator's key of
+    /// [`MqttMessage::signing_input`] for commands, under the source's key
+    /// of [`MqttMessage::envelope_signing_input`] for everything else
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    /// Unix timestamp after which the command carried must not be acted on
+    /// (milliseconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+}
+
+impl<T> MqttMessage<T> {
+    #[cfg(feature = "std")]
+    pub fn new(payload: T, source: impl Into<String>, seq: u64) -> Self {
+        Self::new_at(payload, source, seq, Timestamp::now())
+    }
+
+    /// An envelope stamped `timestamp`
+    pub fn new_at(payload: T, source: impl Into<String>, seq: u64, timestamp: Timestamp) -> Self {
+        Self {
+            payload,
+            source: source.into(),
+            timestamp,
+            seq,
+            version: CURRENT_VERSION,
+            command_id: None,
+            operator: None,
+            signature: None,
+            expires_at: None,
+        }
+    }
+
+    /// Tag the message with the id of the command it carries
+    pub fn with_command_id(mut self, command_id: impl Into<String>) -> Self {
+        self.command_id = Some(command_id.into());
+        self
+    }
+
+    /// Tag the message with the operator who issued it
+    pub fn with_operator(mut self, operator: Operator) -> Self {
+        self.operator = Some(operator);
+        self
+    }
+}
+
+impl MqttMessage<Command> {
+    /// Bytes a command signature covers: the compact JSON array
+    /// `[payload, operator, timestamp, command_id, expires_at]`, so a
+    /// signature can't be moved to another command, operator or time
+    pub fn signing_input(&self) -> Vec<u8> {
+        serde_json::to_vec(&(
+            &self.payload,
+            &self.operator,
+            self.timestamp,
+            &self.command_id,
+            self.expires_at,
+        ))
+        .expect("commands serialize to JSON")
+    }
+}
+
+impl<T: Serialize> MqttMessage<T> {
+    /// Bytes an envelope signature covers: the [`canonical_json`] of
+    /// `[payload, source, timestamp, seq]`, so a signature can't be moved
+    /// to another payload, source or position in the stream
+    pub fn envelope_signing_input(&self) -> Vec<u8> {
+        let value = serde_json::to_value((&self.payload, &self.source, self.timestamp, self.seq))
+            .expect("envelopes serialize to JSON");
+        canonical_json(&value)
+    }
+
+    /// Sign the envelope with its source's key. Commands are signed by
+    /// their operator instead, over [`MqttMessage::signing_input`].
+    #[cfg(feature = "signing")]
+    pub fn sign(&mut self, key: &SigningKey) {
+        let tag = ring::hmac::sign(&key.0, &self.envelope_signing_input());
+        self.signature = Some(tag.as_ref().iter().map(|b| format!("{b:02x}")).collect());
+    }
+
+    /// Check the envelope's signature against its source's key, in
+    /// constant time
+    #[cfg(feature = "signing")]
+    pub fn verify(&self, key: &SigningKey) -> Result<(), SignatureError> {
+        let signature = self.signature.as_deref().ok_or(SignatureError::Unsigned)?;
+        let tag = decode_hex(signature).ok_or(SignatureError::Malformed)?;
+        ring::hmac::verify(&key.0, &self.envelope_signing_input(), &tag)
+            .map_err(|_| SignatureError::Mismatch)
+    }
+}
+
+/// HMAC-SHA256 key an envelope source signs with
+#[cfg(feature = "signing")]
+#[derive(Clone)]
+pub struct SigningKey(ring::hmac::Key);
+
+#[cfg(feature = "signing")]
+impl SigningKey {
+    pub fn new(secret: &[u8]) -> Self {
+        Self(ring::hmac::Key::new(ring::hmac::HMAC_SHA256, secret))
+    }
+}
+
+#[cfg(feature = "signing")]
+impl core::fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("SigningKey(***)")
+    }
+}
+
+/// Why an envelope's signature was not accepted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum SignatureError {
+    #[error("envelope is not signed")]
+    Unsigned,
+    #[error("signature is not hex")]
+    Malformed,
+    #[error("signature does not match the source's key")]
+    Mismatch,
+}
+
+/// Compact JSON of `value` with the fields of every object sorted by the
+/// bytes of their names and no whitespace, so that equal values give equal
+/// bytes however their fields were ordered when written or parsed. Strings
+/// and numbers are written as serde_json writes them.
+pub fn canonical_json(value: &serde_json::Value) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_canonical(value, &mut out);
+    out
+}
+
+fn write_canonical(value: &serde_json::Value, out: &mut Vec<u8>) {
+    use serde_json::Value;
+    match value {
+        Value::Array(items) => {
+            out.push(b'[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(b']');
+        }
+        Value::Object(fields) => {
+            let mut fields: Vec<_> = fields.iter().collect();
+            fields.sort_unstable_by(|(a, _), (b, _)| a.as_bytes().cmp(b.as_bytes()));
+            out.push(b'{');
+            for (i, (name, field)) in fields.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                out.extend(serde_json::to_vec(name).expect("strings serialize to JSON"));
+                out.push(b':');
+                write_canonical(field, out);
+            }
+            out.push(b'}');
+        }
+        scalar => out.extend(serde_json::to_vec(scalar).expect("scalars serialize to JSON")),
+    }
+}
+
+#[cfg(feature = "signing")]
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Heartbeat message for connectivity monitoring
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Heartbeat {
+    /// Robot ID
+    pub robot_id: RobotId,
+    /// Robot type
+    pub robot_type: RobotType,
+    /// Current status
+    pub status: RobotStatus,
+    /// Battery level
+    pub battery: f64,
+    /// Signal strength
+    pub signal: f64,
+    /// Uptime in seconds
+    pub uptime: u64,
+    /// Unix timestamp (milliseconds)
+    pub timestamp: Timestamp,
+}
+
+impl Heartbeat {
+    #[cfg(feature = "std")]
+    pub fn new(
+        robot_id: RobotId,
+        robot_type: RobotType,
+        status: RobotStatus,
+        battery: f64,
+        signal: f64,
+        uptime: u64,
+    ) -> Self {
+        Self::new_at(
+            robot_id,
+            robot_type,
+            status,
+            battery,
+            signal,
+            uptime,
+            Timestamp::now(),
+        )
+    }
+
+    /// A heartbeat sent at `timestamp`
+    pub fn new_at(
+        robot_id: RobotId,
+        robot_type: RobotType,
+        status: RobotStatus,
+        battery: f64,
+        signal: f64,
+        uptime: u64,
+        timestamp: Timestamp,
+    ) -> Self {
+        Self {
+            robot_id,
+            robot_type,
+            status,
+            battery,
+            signal,
+            uptime,
+            timestamp,
+        }
+    }
+}
+
+/// Where a command is in its lifecycle, as its robot reports it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandStatus {
+    /// Received and queued by the robot
+    Accepted,
+    /// Being carried out
+    InProgress,
+    Completed,
+    Failed,
+}
+
+enum_names!(CommandStatus {
+    Accepted => "accepted",
+    InProgress => "in_progress",
+    Completed => "completed",
+    Failed => "failed",
+});
+
+impl CommandStatus {
+    /// Whether no further responses to the command will follow
+    pub fn is_final(&self) -> bool {
+        matches!(self, CommandStatus::Completed | CommandStatus::Failed)
+    }
+}
+
+/// Command response from robot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandResponse {
+    /// ID of the command being responded to
+    pub command_id: String,
+    /// Robot ID
+    pub robot_id: RobotId,
+    /// Whether command was accepted; false only with a `Failed` status
+    pub success: bool,
+    /// Error message if failed
+    pub error: Option<String>,
+    /// The robot's effective configuration, answering a `GetConfig`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<RobotConfig>,
+    /// Progress of a long-running command; robots that answer once leave it
+    /// out (see [`CommandResponse::status`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<CommandStatus>,
+    /// Unix timestamp (milliseconds)
+    pub timestamp: u64,
+}
+
+impl CommandResponse {
+    /// The reported status; a response without one is the only answer, so
+    /// `Completed` when successful and `Failed` otherwise
+    pub fn status(&self) -> CommandStatus {
+        match (self.status, self.success) {
+            (Some(status), _) => status,
+            (None, true) => CommandStatus::Completed,
+            (None, false) => CommandStatus::Failed,
+        }
+    }
+
+    /// Whether this is the last response to the command
+    pub fn is_final(&self) -> bool {
+        self.status().is_final()
+    }
+}
+
+/// How the robots online when a command was broadcast answered it, published
+/// on [`topics::BROADCAST_RESULTS`] once all have or the deadline passed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BroadcastResult {
+    /// ID of the broadcast command
+    pub command_id: String,
+    /// Command variant name (see [`Command::name`])
+    pub command: String,
+    /// Robots that carried the command out
+    pub acked: Vec<String>,
+    /// Robots that refused it, or that it could not be sent to
+    pub failed: Vec<String>,
+    /// Robots that never answered, including those that went offline
+    pub non_responding: Vec<String>,
+    /// Unix timestamp the command was broadcast (milliseconds)
+    pub started_at: u64,
+    /// Unix timestamp the result was settled (milliseconds)
+    pub completed_at: u64,
+}
+
+impl BroadcastResult {
+    /// Robots the command was broadcast to
+    pub fn expected(&self) -> usize {
+        self.acked.len() + self.failed.len() + self.non_responding.len()
+    }
+
+    /// Whether every robot carried the command out
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty() && self.non_responding.is_empty()
+    }
+
+    /// One-line summary, e.g. "4/5 robots confirmed emergency_stop"
+    pub fn summary(&self) -> String {
+        format!(
+            "{}/{} robots confirmed {}",
+            self.acked.len(),
+            self.expected(),
+            self.command
+        )
+    }
+}
+
+/// Whether the engine is running
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EngineState {
+    Online,
+    Offline,
+}
+
+enum_names!(EngineState {
+    Online => "online",
+    Offline => "offline",
+});
+
+/// Robots of one type by connectivity
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FleetCount {
+    pub connected: usize,
+    pub offline: usize,
+}
+
+/// A robot's link delay and clock skew, estimated from its heartbeats
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkQuality {
+    /// Median of engine receive time minus heartbeat timestamp
+    /// (milliseconds): the one-way delay plus how far the robot's clock lags;
+    /// negative when its clock runs ahead
+    pub offset_ms: i64,
+    /// Median deviation from the offset (milliseconds)
+    pub jitter_ms: u64,
+    /// Heartbeats the estimate is drawn from
+    pub samples: usize,
+    /// The offset is beyond the engine's skew bound
+    pub drifted: bool,
+}
+
+/// Engine liveness and fleet summary, retained on [`topics::SYSTEM_STATUS`]
+/// and republished periodically. The engine's MQTT Last Will is the offline
+/// form, so the broker announces a crash; the summary is then empty.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SystemStatus {
+    /// MQTT client id of the engine
+    pub engine_id: String,
+    pub state: EngineState,
+    /// Robots not offline
+    pub connected_robots: usize,
+    /// Seconds since the engine started
+    #[serde(default)]
+    pub uptime_secs: u64,
+    /// Connected and offline robots per type, every type listed
+    #[serde(default)]
+    pub fleet: BTreeMap<RobotType, FleetCount>,
+    /// Active anomalies nobody has acknowledged yet, per severity, every
+    /// severity listed
+    #[serde(default)]
+    pub unacknowledged_anomalies: BTreeMap<SeverityLevel, usize>,
+    /// Open anomaly assignments per assignee
+    #[serde(default)]
+    pub open_assignments: BTreeMap<String, usize>,
+    /// Messages received per second since the previous status
+    #[serde(default)]
+    pub messages_per_sec: f64,
+    /// Messages missed per source, from gaps in envelope sequence numbers
+    #[serde(default)]
+    pub missed_messages: BTreeMap<String, u64>,
+    /// Periodic engine messages (telemetry, heartbeats, environment)
+    /// superseded before the engine could process them, per class
+    #[serde(default)]
+    pub dropped_messages: BTreeMap<String, u64>,
+    /// Link delay and clock skew per robot with enough heartbeats
+    #[serde(default)]
+    pub link_quality: BTreeMap<String, LinkQuality>,
+    /// The engine runs on a scaled or virtual clock: timestamps are
+    /// simulated time, not wall-clock time
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub simulated: bool,
+    /// Which engine instance leads, for engines running leader election
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leadership: Option<LeaderStatus>,
+    /// Unix timestamp (milliseconds); for a Last Will, when the engine
+    /// connected
+    pub timestamp: u64,
+}
+
+impl SystemStatus {
+    pub fn online(engine_id: impl Into<String>, connected_robots: usize, timestamp: u64) -> Self {
+        Self {
+            engine_id: engine_id.into(),
+            state: EngineState::Online,
+            connected_robots,
+            uptime_secs: 0,
+            fleet: BTreeMap::new(),
+            unacknowledged_anomalies: BTreeMap::new(),
+            open_assignments: BTreeMap::new(),
+            messages_per_sec: 0.0,
+            missed_messages: BTreeMap::new(),
+            dropped_messages: BTreeMap::new(),
+            link_quality: BTreeMap::new(),
+            simulated: false,
+            leadership: None,
+            timestamp,
+        }
+    }
+
+    pub fn offline(engine_id: impl Into<String>, timestamp: u64) -> Self {
+        Self {
+            engine_id: engine_id.into(),
+            state: EngineState::Offline,
+            connected_robots: 0,
+            uptime_secs: 0,
+            fleet: BTreeMap::new(),
+            unacknowledged_anomalies: BTreeMap::new(),
+            open_assignments: BTreeMap::new(),
+            messages_per_sec: 0.0,
+            missed_messages: BTreeMap::new(),
+            dropped_messages: BTreeMap::new(),
+            link_quality: BTreeMap::new(),
+            simulated: false,
+            leadership: None,
+            timestamp,
+        }
+    }
+}
+
+/// An engine instance's view of the leader election
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeaderStatus {
+    /// Instance id of the engine reporting
+    pub instance_id: String,
+    /// Instance holding the lease, if any is known
+    pub leader: Option<String>,
+    /// Times the leader has changed since the engine started
+    pub changes: u64,
+    /// Unix timestamp (milliseconds) of the last change
+    pub since: u64,
+}
+
+/// Claim to lead the engine instances, retained on
+/// [`topics::SYSTEM_LEADER`] and renewed while the leader lives. A claim not
+/// renewed within `lease_ms` of its arrival has expired; a released claim
+/// hands over at once.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeaderClaim {
+    pub instance_id: String,
+    /// Unix timestamp (milliseconds) the instance took the lead; between
+    /// two leaders the earlier claim wins
+    pub claimed_at: u64,
+    /// Unix timestamp (milliseconds) of this renewal
+    pub renewed_at: u64,
+    /// How long the claim holds without a renewal (milliseconds)
+    pub lease_ms: u64,
+    /// The leader is shutting down
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub released: bool,
+}
+
+impl LeaderClaim {
+    /// Whether this claim beats `other` for the lead: the earlier claim,
+    /// then the higher instance id
+    pub fn outranks(&self, other: &LeaderClaim) -> bool {
+        match self.claimed_at.cmp(&other.claimed_at) {
+            core::cmp::Ordering::Equal => self.instance_id > other.instance_id,
+            ordering => ordering.is_lt(),
+        }
+    }
+}
+
+/// Why a message was quarantined instead of processed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeadLetterReason {
+    /// The envelope's claimed source may not publish on the topic it arrived on
+    SourceMismatch,
+    /// A reported position lies outside the world bounds
+    OutOfBounds,
+    /// A field holds an impossible value (see [`Validate`])
+    Invalid,
+    /// The envelope's source has a signing key and the envelope is unsigned
+    /// or its signature does not verify
+    BadSignature,
+    /// The payload could not be parsed, or was refused before parsing
+    /// (size, depth, schema version)
+    Malformed,
+}
+
+enum_names!(DeadLetterReason {
+    SourceMismatch => "source_mismatch",
+    OutOfBounds => "out_of_bounds",
+    Invalid => "invalid",
+    BadSignature => "bad_signature",
+    Malformed => "malformed",
+});
+
+impl DeadLetterReason {
+    /// Error category the quarantined message falls under
+    pub fn kind(self) -> ErrorKind {
+        match self {
+            Self::SourceMismatch | Self::BadSignature => ErrorKind::Rejected,
+            Self::OutOfBounds | Self::Invalid => ErrorKind::Validation,
+            Self::Malformed => ErrorKind::Serialization,
+        }
+    }
+}
+
+/// A message the engine refused to process, published on `aetheris/deadletter`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    /// Topic the message arrived on
+    pub topic: String,
+    pub reason: DeadLetterReason,
+    /// Human-readable explanation
+    pub detail: String,
+    /// Source claimed by the envelope, if one was parsed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claimed_source: Option<String>,
+    /// Original payload: as text when it is UTF-8 or decodable CBOR or
+    /// MessagePack (shown as JSON), base64 otherwise
+    pub payload: String,
+    /// `payload` is base64
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub payload_base64: bool,
+    /// Unix timestamp the message was received (milliseconds)
+    pub received_at: u64,
+    /// Dead letters from the same source left unpublished since the previous
+    /// one, when the source is rate limited
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub suppressed: u32,
+}
+
+impl DeadLetter {
+    /// Carry `payload` in the letter, base64-encoded where it is neither
+    /// decodable CBOR or MessagePack nor UTF-8
+    pub fn set_payload(&mut self, payload: &[u8]) {
+        let readable_binary = Encoding::binary_value(payload).is_some();
+        self.payload_base64 = !readable_binary && core::str::from_utf8(payload).is_err();
+        self.payload = if self.payload_base64 {
+            BASE64_STANDARD.encode(payload)
+        } else {
+            Encoding::payload_text(payload)
+        };
+    }
+}
+
+// ============================================================================
+// INCIDENT TIMELINE
+// ============================================================================
+
+/// What a [`TimelineEntry`] describes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineEntryKind {
+    Anomaly,
+    Command,
+    Event,
+    /// A summarized robot movement
+    Movement,
+}
+
+enum_names!(TimelineEntryKind {
+    Anomaly => "anomaly",
+    Command => "command",
+    Event => "event",
+    Movement => "movement",
+});
+
+/// Store holding a record behind a timeline entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordStore {
+    Anomalies,
+    Commands,
+    Events,
+    RobotHistory,
+}
+
+enum_names!(RecordStore {
+    Anomalies => "anomalies",
+    Commands => "commands",
+    Events => "events",
+    RobotHistory => "robot_history",
+});
+
+/// Link from a timeline entry to an underlying record
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RecordRef {
+    pub store: RecordStore,
+    /// Record id within the store (robot history records are `{robot_id}@{timestamp}`)
+    pub id: String,
+}
+
+impl RecordRef {
+    pub fn new(store: RecordStore, id: impl Into<String>) -> Self {
+        Self {
+            store,
+            id: id.into(),
+        }
+    }
+}
+
+/// One step of an incident timeline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimelineEntry {
+    /// Unix timestamp (milliseconds)
+    pub timestamp: u64,
+    pub kind: TimelineEntryKind,
+    /// Robot, section, or anomaly the entry is about
+    pub subject: String,
+    /// Human-readable one-liner
+    pub summary: String,
+    /// Every record folded into this entry, the one it was built from first
+    pub records: Vec<RecordRef>,
+}
+
+// ============================================================================
+// MQTT TOPICS
+// ============================================================================
+
+/// MQTT topic definitions for the AETHERIS system
+pub mod topics {
+    #[cfg(not(any(feature = "std", test)))]
+    use alloc::{
+        format,
+        string::{String, ToString},
+    };
+
+    use super::{RobotId, SeverityLevel};
+
+    /// Base topic prefix
+    pub const PREFIX: &str = "aetheris";
+
+    /// Robot telemetry: aetheris/telemetry/{robot_id}
+    pub fn telemetry(robot_id: &RobotId) -> String {
+        format!("{}/telemetry/{}", PREFIX, robot_id)
+    }
+
+    /// Telemetry wildcard subscription: aetheris/telemetry/+
+    pub const TELEMETRY_ALL: &str = "aetheris/telemetry/+";
+
+    /// Telemetry of many robots in one message: aetheris/telemetry_batch
+    pub const TELEMETRY_BATCH: &str = "aetheris/telemetry_batch";
+
+    /// Engine-filtered positions: aetheris/telemetry_filtered/{robot_id}
+    pub fn telemetry_filtered(robot_id: &RobotId) -> String {
+        format!("{}/telemetry_filtered/{}", PREFIX, robot_id)
+    }
+
+    /// Robot heartbeat: aetheris/heartbeat/{robot_id}
+    pub fn heartbeat(robot_id: &RobotId) -> String {
+        format!("{}/heartbeat/{}", PREFIX, robot_id)
+    }
+
+    /// Heartbeat wildcard: aetheris/heartbeat/+
+    pub const HEARTBEAT_ALL: &str = "aetheris/heartbeat/+";
+
+    /// Commands to specific robot: aetheris/commands/{robot_id}
+    pub fn commands(robot_id: &RobotId) -> String {
+        format!("{}/commands/{}", PREFIX, robot_id)
+    }
+
+    /// Broadcast commands to all robots: aetheris/commands/broadcast
+    pub const COMMANDS_BROADCAST: &str = "aetheris/commands/broadcast";
+
+    /// Command wildcard: aetheris/commands/#
+    pub const COMMANDS_ALL: &str = "aetheris/commands/#";
+
+    /// Anomaly alerts: aetheris/alerts
+    pub const ALERTS: &str = "aetheris/alerts";
+
+    /// Alerts of one severity: aetheris/alerts/{severity}
+    pub fn alerts_severity(severity: SeverityLevel) -> String {
+        format!("{}/alerts/{}", PREFIX, severity.as_str())
+    }
+
+    /// Alerts raised a severity level for going unacknowledged:
+    /// aetheris/alerts/escalations
+    pub const ALERT_ESCALATIONS: &str = "aetheris/alerts/escalations";
+
+    /// Environment readings: aetheris/environment/{section_id}
+    pub fn environment(section_id: &str) -> String {
+        format!("{}/environment/{}", PREFIX, section_id)
+    }
+
+    /// Environment wildcard: aetheris/environment/+
+    pub const ENVIRONMENT_ALL: &str = "aetheris/environment/+";
+
+    /// Command responses: aetheris/responses/{robot_id}
+    pub fn responses(robot_id: &RobotId) -> String {
+        format!("{}/responses/{}", PREFIX, robot_id)
+    }
+
+    /// System status: aetheris/system/status
+    pub const SYSTEM_STATUS: &str = "aetheris/system/status";
+
+    /// Engine leader claims: aetheris/system/leader
+    pub const SYSTEM_LEADER: &str = "aetheris/system/leader";
+
+    /// Triage requests to the Brain: aetheris/triage/requests
+    pub fn triage_requests() -> String {
+        format!("{}/triage/requests", PREFIX)
+    }
+
+    /// Triage results from the Brain: aetheris/triage/results
+    pub fn triage_results() -> String {
+        format!("{}/triage/results", PREFIX)
+    }
+
+    /// Quarantined messages: aetheris/deadletter
+    pub const DEADLETTER: &str = "aetheris/deadletter";
+
+    /// Patrol route definitions: aetheris/routes/{route_id}
+    pub fn routes(route_id: &str) -> String {
+        format!("{}/routes/{}", PREFIX, route_id)
+    }
+
+    /// Route definition wildcard: aetheris/routes/+
+    pub const ROUTES_ALL: &str = "aetheris/routes/+";
+
+    /// Every route definition at once: aetheris/routes
+    pub const ROUTE_LIBRARY: &str = "aetheris/routes";
+
+    /// Requests to republish route definitions: aetheris/route_requests
+    pub const ROUTE_REQUESTS: &str = "aetheris/route_requests";
+
+    /// Section health rollups: aetheris/health/{section_id}
+    pub fn health(section_id: &str) -> String {
+        format!("{}/health/{}", PREFIX, section_id)
+    }
+
+    /// Section health wildcard: aetheris/health/+
+    pub const HEALTH_ALL: &str = "aetheris/health/+";
+
+    /// Progress of a robot's mission: aetheris/missions/{robot_id}
+    pub fn missions(robot_id: &str) -> String {
+        format!("{}/missions/{}", PREFIX, robot_id)
+    }
+
+    /// Mission progress wildcard: aetheris/missions/+
+    pub const MISSIONS_ALL: &str = "aetheris/missions/+";
+
+    /// Broadcast command results: aetheris/broadcast_results
+    pub const BROADCAST_RESULTS: &str = "aetheris/broadcast_results";
+
+    /// Who a command topic addresses
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    pub enum CommandTarget {
+        Robot(RobotId),
+        Broadcast,
+    }
+
+    /// A parsed AETHERIS topic
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    pub enum Topic {
+        Telemetry { robot_id: RobotId },
+        TelemetryBatch,
+        TelemetryFiltered { robot_id: RobotId },
+        Heartbeat { robot_id: RobotId },
+        Commands { target: CommandTarget },
+        Environment { section_id: String },
+        Responses { robot_id: RobotId },
+        Alerts,
+        AlertSeverity { severity: SeverityLevel },
+        AlertEscalations,
+        SystemStatus,
+        SystemLeader,
+        TriageRequests,
+        TriageResults,
+        DeadLetter,
+        Routes { route_id: String },
+        RouteLibrary,
+        RouteRequests,
+        Health { section_id: String },
+        Missions { robot_id: String },
+        BroadcastResults,
+    }
+
+    impl Topic {
+        /// Robot or section the topic is about, if any
+        pub fn subject(&self) -> Option<&str> {
+            match self {
+                Topic::Telemetry { robot_id }
+                | Topic::TelemetryFiltered { robot_id }
+                | Topic::Heartbeat { robot_id }
+                | Topic::Responses { robot_id }
+                | Topic::Commands {
+                    target: CommandTarget::Robot(robot_id),
+                } => Some(robot_id.as_str()),
+                Topic::Environment { section_id } | Topic::Health { section_id } => {
+                    Some(section_id)
+                }
+                Topic::Routes { route_id } => Some(route_id),
+                Topic::Missions { robot_id } => Some(robot_id),
+                _ => None,
+            }
+        }
+    }
+
+    impl core::fmt::Display for Topic {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            match self {
+                Topic::Telemetry { robot_id } => f.write_str(&telemetry(robot_id)),
+                Topic::TelemetryBatch => f.write_str(TELEMETRY_BATCH),
+                Topic::TelemetryFiltered { robot_id } => f.write_str(&telemetry_filtered(robot_id)),
+                Topic::Heartbeat { robot_id } => f.write_str(&heartbeat(robot_id)),
+                Topic::Commands {
+                    target: CommandTarget::Robot(robot_id),
+                } => f.write_str(&commands(robot_id)),
+                Topic::Commands {
+                    target: CommandTarget::Broadcast,
+                } => f.write_str(COMMANDS_BROADCAST),
+                Topic::Environment { section_id } => f.write_str(&environment(section_id)),
+                Topic::Responses { robot_id } => f.write_str(&responses(robot_id)),
+                Topic::Alerts => f.write_str(ALERTS),
+                Topic::AlertSeverity { severity } => f.write_str(&alerts_severity(*severity)),
+                Topic::AlertEscalations => f.write_str(ALERT_ESCALATIONS),
+                Topic::SystemStatus => f.write_str(SYSTEM_STATUS),
+                Topic::SystemLeader => f.write_str(SYSTEM_LEADER),
+                Topic::TriageRequests => f.write_str(&triage_requests()),
+                Topic::TriageResults => f.write_str(&triage_results()),
+                Topic::DeadLetter => f.write_str(DEADLETTER),
+                Topic::Routes { route_id } => f.write_str(&routes(route_id)),
+                Topic::RouteLibrary => f.write_str(ROUTE_LIBRARY),
+                Topic::RouteRequests => f.write_str(ROUTE_REQUESTS),
+                Topic::Health { section_id } => f.write_str(&health(section_id)),
+                Topic::Missions { robot_id } => f.write_str(&missions(robot_id)),
+                Topic::BroadcastResults => f.write_str(BROADCAST_RESULTS),
+            }
+        }
+    }
+
+    /// Parse a concrete (non-wildcard) topic. Returns `None` for a wrong
+    /// prefix, an unknown kind, extra or missing segments, an empty id, or
+    /// a robot topic whose id is not a [`RobotId`].
+    pub fn parse(topic: &str) -> Option<Topic> {
+        let mut segments = topic.split('/');
+        if segments.next()? != PREFIX {
+            return None;
+        }
+        let kind = segments.next()?;
+        let id = segments.next();
+        if segments.next().is_some() {
+            return None;
+        }
+        let id = match id {
+            Some(id) if id.is_empty() || id.contains(['+', '#']) => return None,
+            Some(id) => Some(id.to_string()),
+            None => None,
+        };
+        let topic = match (kind, id) {
+            ("telemetry", Some(robot_id)) => Topic::Telemetry {
+                robot_id: robot_id.parse().ok()?,
+            },
+            ("telemetry_batch", None) => Topic::TelemetryBatch,
+            ("telemetry_filtered", Some(robot_id)) => Topic::TelemetryFiltered {
+                robot_id: robot_id.parse().ok()?,
+            },
+            ("heartbeat", Some(robot_id)) => Topic::Heartbeat {
+                robot_id: robot_id.parse().ok()?,
+            },
+            ("commands", Some(target)) if target == "broadcast" => Topic::Commands {
+                target: CommandTarget::Broadcast,
+            },
+            ("commands", Some(robot_id)) => Topic::Commands {
+                target: CommandTarget::Robot(robot_id.parse().ok()?),
+            },
+            ("environment", Some(section_id)) => Topic::Environment { section_id },
+            ("responses", Some(robot_id)) => Topic::Responses {
+                robot_id: robot_id.parse().ok()?,
+            },
+            ("alerts", None) => Topic::Alerts,
+            ("alerts", Some(kind)) if kind == "escalations" => Topic::AlertEscalations,
+            ("alerts", Some(severity)) => Topic::AlertSeverity {
+                severity: severity.parse().ok()?,
+            },
+            ("system", Some(status)) if status == "status" => Topic::SystemStatus,
+            ("system", Some(leader)) if leader == "leader" => Topic::SystemLeader,
+            ("triage", Some(flow)) if flow == "requests" => Topic::TriageRequests,
+            ("triage", Some(flow)) if flow == "results" => Topic::TriageResults,
+            ("deadletter", None) => Topic::DeadLetter,
+            ("routes", Some(route_id)) => Topic::Routes { route_id },
+            ("routes", None) => Topic::RouteLibrary,
+            ("route_requests", None) => Topic::RouteRequests,
+            ("health", Some(section_id)) => Topic::Health { section_id },
+            ("missions", Some(robot_id)) => Topic::Missions { robot_id },
+            ("broadcast_results", None) => Topic::BroadcastResults,
+            _ => return None,
+        };
+        Some(topic)
+    }
+}
+
+// ============================================================================
+// PAYLOAD ENCODING
+// ============================================================================
+
+/// Serialization format of an MQTT payload.
+///
+/// JSON is readable and what the dashboard speaks; CBOR and MessagePack
+/// carry the same structure in roughly half the bytes, for robots on
+/// constrained links. Receivers tell them apart by the first byte (see
+/// [`Encoding::detect`]), so a fleet may mix them freely.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    #[default]
+    Json,
+    Cbor,
+    #[serde(rename = "msgpack")]
+    MessagePack,
+}
+
+enum_names!(Encoding {
+    Json => "json",
+    Cbor => "cbor",
+    MessagePack => "msgpack",
+});
+
+/// A payload that could not be encoded or decoded
+#[derive(Debug)]
+pub enum EncodingError {
+    Json(serde_json::Error),
+    Cbor(String),
+    MessagePack(String),
+    /// CBOR or MessagePack nested deeper than [`limits::MAX_PAYLOAD_DEPTH`]
+    TooDeep,
+}
+
+impl core::fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Json(e) => write!(f, "invalid JSON: {e}"),
+            Self::Cbor(e) => write!(f, "invalid CBOR: {e}"),
+            Self::MessagePack(e) => write!(f, "invalid MessagePack: {e}"),
+            Self::TooDeep => write!(
+                f,
+                "nesting exceeds the depth limit of {}",
+                limits::MAX_PAYLOAD_DEPTH
+            ),
+        }
+    }
+}
+
+impl core::error::Error for EncodingError {}
+
+impl From<serde_json::Error> for EncodingError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
+impl Encoding {
+    /// Format of a received payload. Every message is a JSON object, a
+    /// CBOR map, or a MessagePack map; a JSON document starts with an ASCII
+    /// byte, a MessagePack map with 0x80-0x8F, 0xDE or 0xDF, and a CBOR
+    /// map with 0xA0-0xBF.
+    pub fn detect(payload: &[u8]) -> Self {
+        match payload.first() {
+            Some(0x80..=0x8f | 0xde | 0xdf) => Self::MessagePack,
+            Some(&byte) if byte >= 0x80 => Self::Cbor,
+            _ => Self::Json,
+        }
+    }
+
+    pub fn encode<T: Serialize>(self, value: &T) -> Result<Vec<u8>, EncodingError> {
+        match self {
+            Self::Json => Ok(serde_json::to_vec(value)?),
+            Self::Cbor => {
+                let mut bytes = Vec::new();
+                ciborium::into_writer(value, &mut bytes)
+                    .map_err(|e| EncodingError::Cbor(e.to_string()))?;
+                Ok(bytes)
+            }
+            Self::MessagePack => msgpack::encode(&serde_json::to_value(value)?),
+        }
+    }
+
+    pub fn decode<T: serde::de::DeserializeOwned>(
+        self,
+        payload: &[u8],
+    ) -> Result<T, EncodingError> {
+        match self {
+            Self::Json => Ok(serde_json::from_slice(payload)?),
+            Self::Cbor => {
+                ciborium::de::from_reader_with_recursion_limit(payload, limits::MAX_PAYLOAD_DEPTH)
+                    .map_err(|e| match e {
+                        ciborium::de::Error::RecursionLimitExceeded => EncodingError::TooDeep,
+                        e => EncodingError::Cbor(e.to_string()),
+                    })
+            }
+            Self::MessagePack => serde_json::from_value(msgpack::decode(payload)?)
+                .map_err(|e| EncodingError::MessagePack(e.to_string())),
+        }
+    }
+
+    /// Decode a payload in whichever format it arrived in
+    pub fn decode_detected<T: serde::de::DeserializeOwned>(
+        payload: &[u8],
+    ) -> Result<T, EncodingError> {
+        Self::detect(payload).decode(payload)
+    }
+
+    /// Human-readable form of a payload for logs and dead letters: JSON
+    /// as-is, CBOR and MessagePack rendered as JSON where they decode
+    pub fn payload_text(payload: &[u8]) -> String {
+        if let Some(value) = Self::binary_value(payload) {
+            return value.to_string();
+        }
+        String::from_utf8_lossy(payload).into_owned()
+    }
+
+    /// A CBOR or MessagePack payload as a JSON value, if it decodes
+    fn binary_value(payload: &[u8]) -> Option<serde_json::Value> {
+        match Self::detect(payload) {
+            Self::Json => None,
+            encoding => encoding.decode(payload).ok(),
+        }
+    }
+}
+
+/// MessagePack through the JSON data model: values are converted to a
+/// [`serde_json::Value`] and written with maps keyed by field name, so a
+/// message has the same shape in every encoding.
+mod msgpack {
+    use alloc::format;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    use rmp::Marker;
+    use rmp::decode::{self, RmpRead};
+    use rmp::encode::{self, ByteBuf};
+    use serde_json::{Map, Value};
+
+    use super::{EncodingError, limits};
+
+    fn invalid(reason: impl Into<String>) -> EncodingError {
+        EncodingError::MessagePack(reason.into())
+    }
+
+    /// The codec's errors only implement `Display` for `std::io` streams
+    fn rmp_error(e: impl core::fmt::Debug) -> EncodingError {
+        invalid(format!("{e:?}"))
+    }
+
+    pub(super) fn encode(value: &Value) -> Result<Vec<u8>, EncodingError> {
+        let mut bytes = ByteBuf::new();
+        write(&mut bytes, value)?;
+        Ok(bytes.into_vec())
+    }
+
+    fn write(bytes: &mut ByteBuf, value: &Value) -> Result<(), EncodingError> {
+        match value {
+            Value::Null => encode::write_nil(bytes).map_err(rmp_error)?,
+            Value::Bool(b) => encode::write_bool(bytes, *b).map_err(rmp_error)?,
+            Value::Number(n) => {
+                if let Some(n) = n.as_u64() {
+                    encode::write_uint(bytes, n).map_err(rmp_error)?;
+                } else if let Some(n) = n.as_i64() {
+                    encode::write_sint(bytes, n).map_err(rmp_error)?;
+                } else if let Some(n) = n.as_f64() {
+                    encode::write_f64(bytes, n).map_err(rmp_error)?;
+                }
+            }
+            Value::String(s) => encode::write_str(bytes, s).map_err(rmp_error)?,
+            Value::Array(items) => {
+                encode::write_array_len(bytes, items.len() as u32).map_err(rmp_error)?;
+                for item in items {
+                    write(bytes, item)?;
+                }
+            }
+            Value::Object(fields) => {
+                encode::write_map_len(bytes, fields.len() as u32).map_err(rmp_error)?;
+                for (key, field) in fields {
+                    encode::write_str(bytes, key).map_err(rmp_error)?;
+                    write(bytes, field)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(super) fn decode(payload: &[u8]) -> Result<Value, EncodingError> {
+        let mut rest = payload;
+        let value = read(&mut rest, 0)?;
+        if !rest.is_empty() {
+            return Err(invalid(format!(
+                "{} trailing bytes after the message",
+                rest.len()
+            )));
+        }
+        Ok(value)
+    }
+
+    fn read(rest: &mut &[u8], depth: usize) -> Result<Value, EncodingError> {
+        if depth > limits::MAX_PAYLOAD_DEPTH {
+            return Err(EncodingError::TooDeep);
+        }
+        let Some(&first) = rest.first() else {
+            return Err(invalid("unexpected end of payload"));
+        };
+        let value = match Marker::from_u8(first) {
+            Marker::Null => {
+                decode::read_nil(rest).map_err(rmp_error)?;
+                Value::Null
+            }
+            Marker::True | Marker::False => {
+                Value::Bool(decode::read_bool(rest).map_err(rmp_error)?)
+            }
+            Marker::FixPos(_) | Marker::U8 | Marker::U16 | Marker::U32 | Marker::U64 => {
+                Value::from(decode::read_int::<u64, _>(rest).map_err(rmp_error)?)
+            }
+            Marker::FixNeg(_) | Marker::I8 | Marker::I16 | Marker::I32 | Marker::I64 => {
+                Value::from(decode::read_int::<i64, _>(rest).map_err(rmp_error)?)
+            }
+            Marker::F32 => Value::from(f64::from(decode::read_f32(rest).map_err(rmp_error)?)),
+            Marker::F64 => Value::from(decode::read_f64(rest).map_err(rmp_error)?),
+            Marker::FixStr(_) | Marker::Str8 | Marker::Str16 | Marker::Str32 => {
+                Value::String(read_str(rest)?)
+            }
+            Marker::FixArray(_) | Marker::Array16 | Marker::Array32 => {
+                let len = decode::read_array_len(rest).map_err(rmp_error)? as usize;
+                // Every item takes at least a byte; a forged length allocates no more
+                let mut items = Vec::with_capacity(len.min(rest.len()));
+                for _ in 0..len {
+                    items.push(read(rest, depth + 1)?);
+                }
+                Value::Array(items)
+            }
+            Marker::FixMap(_) | Marker::Map16 | Marker::Map32 => {
+                let len = decode::read_map_len(rest).map_err(rmp_error)?;
+                let mut fields = Map::new();
+                for _ in 0..len {
+                    if !matches!(
+                        rest.first().map(|&byte| Marker::from_u8(byte)),
+                        Some(Marker::FixStr(_) | Marker::Str8 | Marker::Str16 | Marker::Str32)
+                    ) {
+                        return Err(invalid("map keys must be strings"));
+                    }
+                    let key = read_str(rest)?;
+                    fields.insert(key, read(rest, depth + 1)?);
+                }
+                Value::Object(fields)
+            }
+            marker => return Err(invalid(format!("unsupported type {marker:?}"))),
+        };
+        Ok(value)
+    }
+
+    fn read_str(rest: &mut &[u8]) -> Result<String, EncodingError> {
+        let len = decode::read_str_len(rest).map_err(rmp_error)? as usize;
+        if rest.len() < len {
+            return Err(invalid("string runs past the end of the payload"));
+        }
+        let mut bytes = alloc::vec![0; len];
+        rest.read_exact_buf(&mut bytes).map_err(rmp_error)?;
+        String::from_utf8(bytes).map_err(|_| invalid("string is not UTF-8"))
+    }
+}
+
+// ============================================================================
+// ERRORS
+// ============================================================================
+
+/// A failure handling or sending an AETHERIS message.
+///
+/// The variant says what went wrong, and with it what the caller should do
+/// about it (see [`AetherisError::recovery`]): a bad message is dropped, a
+/// broken broker link is reconnected, a closed engine channel means the
+/// engine is shutting down.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum AetherisError {
+    /// A payload that could not be encoded or decoded, or was refused
+    /// before decoding (size, depth, schema version)
+    #[error("unreadable payload: {0}")]
+    Serialization(String),
+    /// A payload that decoded to impossible values
+    #[error("invalid value: {0}")]
+    Validation(#[from] ValidationError),
+    /// A topic outside the AETHERIS topic scheme
+    #[error("unrecognized topic {0}")]
+    Topic(String),
+    /// The MQTT client could not queue a request for the broker
+    #[error("failed to {action}: {reason}")]
+    Transport { action: String, reason: String },
+    /// A well-formed request the current state refuses, e.g. resolving an
+    /// anomaly that is not active
+    #[error("{0}")]
+    Rejected(String),
+    /// The engine's message channel has no receiver left
+    #[error("engine message channel closed")]
+    ChannelClosed,
+}
+
+/// Category of an [`AetherisError`], for tagging dead letters and counters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Serialization,
+    Validation,
+    Topic,
+    Transport,
+    Rejected,
+    ChannelClosed,
+}
+
+enum_names!(ErrorKind {
+    Serialization => "serialization",
+    Validation => "validation",
+    Topic => "topic",
+    Transport => "transport",
+    Rejected => "rejected",
+    ChannelClosed => "channel_closed",
+});
+
+/// What the receiver of an error should do next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recovery {
+    /// Log it and carry on with the next message
+    Skip,
+    /// Drop the broker connection and establish a new one
+    Reconnect,
+    /// Stop: nothing downstream is listening any more
+    Shutdown,
+}
+
+impl AetherisError {
+    /// A transport failure while trying to `action` (e.g. "publish telemetry")
+    pub fn transport(action: impl Into<String>, reason: impl core::fmt::Display) -> Self {
+        Self::Transport {
+            action: action.into(),
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Serialization(_) => ErrorKind::Serialization,
+            Self::Validation(_) => ErrorKind::Validation,
+            Self::Topic(_) => ErrorKind::Topic,
+            Self::Transport { .. } => ErrorKind::Transport,
+            Self::Rejected(_) => ErrorKind::Rejected,
+            Self::ChannelClosed => ErrorKind::ChannelClosed,
+        }
+    }
+
+    pub fn recovery(&self) -> Recovery {
+        match self.kind() {
+            ErrorKind::Serialization
+            | ErrorKind::Validation
+            | ErrorKind::Topic
+            | ErrorKind::Rejected => Recovery::Skip,
+            ErrorKind::Transport => Recovery::Reconnect,
+            ErrorKind::ChannelClosed => Recovery::Shutdown,
+        }
+    }
+}
+
+impl From<EncodingError> for AetherisError {
+    fn from(e: EncodingError) -> Self {
+        Self::Serialization(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for AetherisError {
+    fn from(e: serde_json::Error) -> Self {
+        EncodingError::Json(e).into()
+    }
+}
+
+// ============================================================================
+// WIRE COMPATIBILITY
+// ============================================================================
+
+/// An intentional change to the serialized form of a message type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BreakingChange {
+    /// Wire revision introducing the change; entries sharing a change share a version
+    pub version: u32,
+    /// Fixture name of the affected type (e.g. "robot_state", "command_move_to")
+    pub fixture: &'static str,
+    /// What changed and why
+    pub description: &'static str,
+}
+
+/// Registry of intentional wire format changes.
+///
+/// The `wire_compat` test suite compares every message type against golden
+/// JSON fixtures in `tests/fixtures/wire`. When a change to a shared type
+/// alters its serialized form on purpose:
+///
+/// 1. Add an entry here with the next version number and the fixture name.
+/// 2. Re-bless the fixtures: `AETHERIS_BLESS_FIXTURES=1 cargo test -p aetheris-shared --test wire_compat`.
+/// 3. Commit the registry entry together with the updated fixtures.
+///
+/// Blessing refuses to rewrite a fixture that has no newer entry here, so an
+/// accidental wire break cannot be papered over by regenerating fixtures.
+pub const BREAKING_CHANGES: &[BreakingChange] = &[
+    BreakingChange {
+        version: 1,
+        fixture: "robot_state",
+        description: "RobotState gains `orientation` (yaw/pitch/roll radians); additive, older payloads default to identity",
+    },
+    BreakingChange {
+        version: 1,
+        fixture: "envelope_robot_state",
+        description: "RobotState gains `orientation` (yaw/pitch/roll radians); additive, older payloads default to identity",
+    },
+    BreakingChange {
+        version: 1,
+        fixture: "robot_view",
+        description: "RobotState gains `orientation` (yaw/pitch/roll radians); additive, older payloads default to identity",
+    },
+    BreakingChange {
+        version: 2,
+        fixture: "command_resolve_anomaly",
+        description: "ResolveAnomaly gains `resolution` (fixed or false_positive); older payloads default to fixed",
+    },
+    BreakingChange {
+        version: 3,
+        fixture: "envelope_robot_state",
+        description: "MqttMessage gains the schema `version`; additive, envelopes without it are version 1",
+    },
+    BreakingChange {
+        version: 3,
+        fixture: "envelope_command",
+        description: "MqttMessage gains the schema `version`; additive, envelopes without it are version 1",
+    },
+    BreakingChange {
+        version: 3,
+        fixture: "envelope_command_tracked",
+        description: "MqttMessage gains the schema `version`; additive, envelopes without it are version 1",
+    },
+    BreakingChange {
+        version: 3,
+        fixture: "envelope_anomaly_report",
+        description: "MqttMessage gains the schema `version`; additive, envelopes without it are version 1",
+    },
+    BreakingChange {
+        version: 3,
+        fixture: "envelope_pipe_environment",
+        description: "MqttMessage gains the schema `version`; additive, envelopes without it are version 1",
+    },
+    BreakingChange {
+        version: 4,
+        fixture: "system_status",
+        description: "SystemStatus gains the fleet summary (uptime, robots per type, unacknowledged anomalies per severity, message rate); additive, older payloads default to empty",
+    },
+    BreakingChange {
+        version: 4,
+        fixture: "system_status_offline",
+        description: "SystemStatus gains the fleet summary (uptime, robots per type, unacknowledged anomalies per severity, message rate); additive, older payloads default to empty",
+    },
+    BreakingChange {
+        version: 5,
+        fixture: "system_status",
+        description: "SystemStatus gains `missed_messages` per source from envelope sequence gaps; additive, older payloads default to empty",
+    },
+    BreakingChange {
+        version: 5,
+        fixture: "system_status_offline",
+        description: "SystemStatus gains `missed_messages` per source from envelope sequence gaps; additive, older payloads default to empty",
+    },
+    BreakingChange {
+        version: 6,
+        fixture: "system_status",
+        description: "SystemStatus gains `dropped_messages` per class from engine backpressure; additive, older payloads default to empty",
+    },
+    BreakingChange {
+        version: 6,
+        fixture: "system_status_offline",
+        description: "SystemStatus gains `dropped_messages` per class from engine backpressure; additive, older payloads default to empty",
+    },
+    BreakingChange {
+        version: 7,
+        fixture: "system_status",
+        description: "SystemStatus gains `link_quality` per robot, the clock skew estimated from heartbeats; additive, older payloads default to empty",
+    },
+    BreakingChange {
+        version: 7,
+        fixture: "system_status_offline",
+        description: "SystemStatus gains `link_quality` per robot, the clock skew estimated from heartbeats; additive, older payloads default to empty",
+    },
+    BreakingChange {
+        version: 8,
+        fixture: "system_status",
+        description: "SystemStatus gains `leadership`, the engine's view of the leader election; additive, absent for engines not electing a leader",
+    },
+    BreakingChange {
+        version: 9,
+        fixture: "system_status",
+        description: "SystemStatus gains `open_assignments` per assignee; additive, older payloads default to empty",
+    },
+    BreakingChange {
+        version: 9,
+        fixture: "system_status_offline",
+        description: "SystemStatus gains `open_assignments` per assignee; additive, older payloads default to empty",
+    },
+];
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// Get current Unix timestamp in milliseconds
+#[cfg(feature = "std")]
+pub fn current_timestamp_ms() -> u64 {
+    Timestamp::now().as_millis()
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+fn first_occurrence() -> u32 {
+    1
+}
+
+fn is_first_occurrence(count: &u32) -> bool {
+    *count == 1
+}
+
+fn is_zero(count: &u32) -> bool {
+    *count == 0
+}
+
+/// Generate a unique anomaly ID for a report detected at `timestamp`. The
+/// counter is 32 bits wide, so microcontrollers without 64-bit atomics
+/// can build it.
+fn generate_anomaly_id(timestamp: Timestamp) -> String {
+    use core::sync::atomic::{AtomicU32, Ordering};
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+    let count = COUNTER.fetch_add(1, Ordering::SeqCst);
+    format!("ANM-{:X}-{:04X}", timestamp.as_millis(), count)
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fixed time for messages whose time does not matter
+    const T0: Timestamp = Timestamp::from_millis(1_767_225_600_000);
+
+    fn rover() -> RobotState {
+        RobotState::new_at(
+            "RV-001".parse().unwrap(),
+            "Rover Alpha",
+            RobotType::Rover,
+            T0,
+        )
+    }
+
+    /// Every value in `ok` passes and every value in `bad` is reported on `field`
+    fn assert_bounds<T: Validate + Clone>(
+        base: &T,
+        field: &str,
+        set: fn(&mut T, f64),
+        ok: &[f64],
+        bad: &[f64],
+    ) {
+        for &value in ok {
+            let mut sample = base.clone();
+            set(&mut sample, value);
+            assert_eq!(sample.validate(), Ok(()), "{field} = {value}");
+        }
+        for &value in bad
+            .iter()
+            .chain(&[f64::NAN, f64::INFINITY, f64::NEG_INFINITY])
+        {
+            let mut sample = base.clone();
+            set(&mut sample, value);
+            let error = sample.validate().expect_err(field);
+            assert_eq!(error.field(), field, "{field} = {value}: {error}");
+        }
+    }
+
+    #[test]
+    fn test_robot_state_and_heartbeat_bounds() {
+        let robot = rover();
+        assert_eq!(robot.validate(), Ok(()));
+        assert_bounds(
+            &robot,
+            "battery",
+            |r, v| r.battery = v,
+            &[0.0, 100.0],
+            &[-3.0, 100.1],
+        );
+        assert_bounds(
+            &robot,
+            "signal",
+            |r, v| r.signal = v,
+            &[0.0, 100.0],
+            &[-0.1, 101.0],
+        );
+        assert_bounds(
+            &robot,
+            "position.y",
+            |r, v| r.position.y = v,
+            &[-1e9, 1e9],
+            &[],
+        );
+        assert_bounds(
+            &robot,
+            "velocity.vz",
+            |r, v| r.velocity.vz = v,
+            &[-5.0],
+            &[],
+        );
+        assert_bounds(
+            &robot,
+            "orientation.roll",
+            |r, v| r.orientation.roll = v,
+            &[3.0],
+            &[],
+        );
+        let heartbeat = Heartbeat::new_at(
+            "DR-001".parse().unwrap(),
+            RobotType::Drone,
+            RobotStatus::Active,
+            50.0,
+            80.0,
+            9,
+            T0,
+        );
+        assert_bounds(
+            &heartbeat,
+            "battery",
+            |h, v| h.battery = v,
+            &[0.0, 100.0],
+            &[-0.01, 100.01],
+        );
+        assert_bounds(
+            &heartbeat,
+            "signal",
+            |h, v| h.signal = v,
+            &[0.0, 100.0],
+            &[-1.0, 200.0],
+        );
+    }
+
+    #[test]
+    fn test_environment_and_report_bounds() {
+        let reading = PipeEnvironment {
+            section_id: "PIPE-001".into(),
+            pressure: 50.0,
+            temperature: 25.0,
+            h2_concentration: 100.0,
+            wall_thickness: 10.0,
+            flow_rate: 500.0,
+            humidity: 45.0,
+            position: Position::origin(),
+            timestamp: Timestamp::from_millis(1),
+        };
+        assert_eq!(reading.validate(), Ok(()));
+        assert_bounds(
+            &reading,
+            "pressure",
+            |e, v| e.pressure = v,
+            &[0.0, 900.0],
+            &[-0.5],
+        );
+        assert_bounds(
+            &reading,
+            "temperature",
+            |e, v| e.temperature = v,
+            &[-273.15, 500.0],
+            &[-274.0],
+        );
+        assert_bounds(
+            &reading,
+            "h2_concentration",
+            |e, v| e.h2_concentration = v,
+            &[0.0],
+            &[-1.0],
+        );
+        assert_bounds(
+            &reading,
+            "wall_thickness",
+            |e, v| e.wall_thickness = v,
+            &[0.0],
+            &[-0.1],
+        );
+        assert_bounds(
+            &reading,
+            "flow_rate",
+            |e, v| e.flow_rate = v,
+            &[-20.0, 0.0],
+            &[],
+        );
+        assert_bounds(
+            &reading,
+            "humidity",
+            |e, v| e.humidity = v,
+            &[0.0, 100.0],
+            &[-1.0, 100.5],
+        );
+
+        let report = AnomalyReport::new_at(
+            AnomalyType::Leak,
+            SeverityLevel::High,
+            Position::origin(),
+            "PIPE-001",
+            "RV-001",
+            0.5,
+            "leak",
+            T0,
+        );
+        assert_bounds(
+            &report,
+            "confidence",
+            |r, v| r.confidence = v,
+            &[0.0, 1.0],
+            &[47.0, -0.01],
+        );
+        assert_bounds(&report, "position.x", |r, v| r.position.x = v, &[0.0], &[]);
+        let orphan = AnomalyReport {
+            detected_by: String::new(),
+            ..report
+        };
+        assert_eq!(orphan.validate().unwrap_err().field(), "detected_by");
+    }
+
+    #[test]
+    fn test_error_kind_decides_recovery() {
+        let malformed: AetherisError = Encoding::Json.decode::<Heartbeat>(b"{").unwrap_err().into();
+        assert_eq!(malformed.kind(), ErrorKind::Serialization);
+        assert_eq!(malformed.recovery(), Recovery::Skip);
+
+        let invalid: AetherisError = ValidationError::Empty { field: "id".into() }.into();
+        assert_eq!(invalid.to_string(), "invalid value: id must not be empty");
+        assert_eq!(invalid.recovery(), Recovery::Skip);
+        assert_eq!(
+            AetherisError::Topic("weather/today".into()).recovery(),
+            Recovery::Skip
+        );
+
+        let broken = AetherisError::transport("publish telemetry", "request queue closed");
+        assert_eq!(
+            broken.to_string(),
+            "failed to publish telemetry: request queue closed"
+        );
+        assert_eq!(broken.recovery(), Recovery::Reconnect);
+        assert_eq!(AetherisError::ChannelClosed.recovery(), Recovery::Shutdown);
+
+        // Tags are stable and distinct
+        let tags: std::collections::HashSet<_> =
+            ErrorKind::ALL.iter().map(|kind| kind.as_str()).collect();
+        assert_eq!(tags.len(), ErrorKind::ALL.len());
+        assert_eq!(
+            serde_json::to_string(&ErrorKind::ChannelClosed).unwrap(),
+            "\"channel_closed\""
+        );
+    }
+
+    #[test]
+    fn test_command_bounds() {
+        let move_to = Command::MoveTo {
+            target: Position::origin(),
+            speed: Some(1.0),
+        };
+        let set_speed = |c: &mut Command, v: f64| {
+            if let Command::MoveTo { speed, .. } = c {
+                *speed = Some(v);
+            }
+        };
+        assert_bounds(&move_to, "speed", set_speed, &[0.0, 12.0], &[-1.0]);
+        let set_target = |c: &mut Command, v: f64| {
+            if let Command::MoveTo { target, .. } = c {
+                target.z = v;
+            }
+        };
+        assert_bounds(&move_to, "target.z", set_target, &[-3.0], &[]);
+
+        let configure = Command::Configure {
+            config: RobotConfig::default(),
+        };
+        let set_threshold = |c: &mut Command, v: f64| {
+            if let Command::Configure { config } = c {
+                config.low_battery_threshold = Some(v);
+            }
+        };
+        assert_bounds(
+            &configure,
+            "config.low_battery_threshold",
+            set_threshold,
+            &[0.0, 100.0],
+            &[120.0],
+        );
+        let zero_interval = Command::Configure {
+            config: RobotConfig {
+                scan_interval: Some(0),
+                ..RobotConfig::default()
+            },
+        };
+        assert_eq!(
+            zero_interval.validate().unwrap_err().field(),
+            "config.scan_interval"
+        );
+
+        let resolve = Command::ResolveAnomaly {
+            anomaly_id: String::new(),
+            resolution: Resolution::Fixed,
+            force: false,
+        };
+        assert_eq!(
+            resolve.validate().unwrap_err().to_string(),
+            "anomaly_id must not be empty"
+        );
+        assert_eq!(Command::EmergencyStop.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_mission_plan_checks_every_step() {
+        let step = |command| MissionStep {
+            command,
+            timeout_secs: 30,
+            on_failure: FailurePolicy::Retry(2),
+            preconditions: Vec::new(),
+        };
+        let mut plan = MissionPlan {
+            id: "MSN-1".into(),
+            robot_id: "RV-001".into(),
+            steps: vec![
+                step(Command::MoveTo {
+                    target: Position::new(5.0, 0.0, 0.0),
+                    speed: None,
+                }),
+                step(Command::ReturnToBase),
+            ],
+        };
+        let start = Command::StartMission { plan: plan.clone() };
+        assert_eq!(start.validate(), Ok(()));
+
+        plan.steps[0].command = Command::MoveTo {
+            target: Position::new(f64::NAN, 0.0, 0.0),
+            speed: None,
+        };
+        assert_eq!(
+            plan.validate().unwrap_err().field(),
+            "plan.steps[0].command.target.x"
+        );
+        plan.steps[0] = step(Command::AbortMission {
+            mission_id: "MSN-0".into(),
+        });
+        assert_eq!(
+            plan.validate().unwrap_err().to_string(),
+            "plan.steps[0].command cannot be a mission command"
+        );
+        plan.steps[1].timeout_secs = 0;
+        plan.steps.remove(0);
+        assert_eq!(
+            plan.validate().unwrap_err().field(),
+            "plan.steps[0].timeout_secs"
+        );
+        plan.steps[0].timeout_secs = 30;
+        plan.steps[0].preconditions = vec![
+            Precondition::Status(RobotStatus::Idle),
+            Precondition::MinBattery(120.0),
+        ];
+        assert_eq!(
+            plan.validate().unwrap_err().field(),
+            "plan.steps[0].preconditions[1]"
+        );
+        plan.steps.clear();
+        assert_eq!(
+            plan.validate().unwrap_err().to_string(),
+            "plan.steps must not be empty"
+        );
+
+        let json = serde_json::to_value(step(Command::Stop)).unwrap();
+        assert_eq!(json["on_failure"], serde_json::json!({ "retry": 2 }));
+        let abort: MissionStep =
+            serde_json::from_str(r#"{"command":{"command":"stop"},"timeout_secs":5}"#).unwrap();
+        assert_eq!(abort.on_failure, FailurePolicy::Abort);
+        assert!(abort.preconditions.is_empty());
+        assert!(json.get("preconditions").is_none());
+    }
+
+    #[test]
+    fn test_preconditions_check_robot_state() {
+        let mut state = RobotState::new_at(
+            "RV-001".parse().unwrap(),
+            "Rover",
+            RobotType::Rover,
+            Timestamp::from_millis(0),
+        );
+        state.battery = 40.0;
+        state.status = RobotStatus::Idle;
+        state.position = Position::new(3.0, 0.0, 4.0);
+
+        let near = Precondition::Near {
+            position: Position::new(0.0, 0.0, 0.0),
+            radius: 5.0,
+        };
+        assert!(near.holds(&state));
+        assert!(Precondition::Status(RobotStatus::Idle).holds(&state));
+        assert!(!Precondition::MinBattery(50.0).holds(&state));
+        assert_eq!(
+            Precondition::MinBattery(50.0).to_string(),
+            "battery at least 50%"
+        );
+        assert_eq!(near.to_string(), "within 5m of (0, 0, 0)");
+        assert_eq!(
+            serde_json::to_value(Precondition::Status(RobotStatus::Idle)).unwrap(),
+            serde_json::json!({ "status": "idle" })
+        );
+    }
+
+    #[test]
+    fn test_position_distance() {
+        let p1 = Position::new(0.0, 0.0, 0.0);
+        let p2 = Position::new(3.0, 4.0, 0.0);
+        assert!((p1.distance_to(&p2) - 5.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_position_and_velocity_vector_math() {
+        let a = Position::new(1.0, 2.0, 3.0);
+        let b = Position::new(5.0, -2.0, 5.0);
+        assert_eq!(b - a, Displacement::new(4.0, -4.0, 2.0));
+        assert_eq!(a + (b - a), b);
+        assert_eq!(b - (b - a), a);
+
+        assert_eq!(a.lerp(&b, 0.0), a);
+        assert_eq!(a.lerp(&b, 0.5), Position::new(3.0, 0.0, 4.0));
+        assert_eq!(a.lerp(&b, 1.0), b);
+        assert_eq!(a.midpoint(&b), a.lerp(&b, 0.5));
+
+        let direction = a.direction_to(&b).unwrap();
+        assert!((direction.length() - 1.0).abs() < 1e-12);
+        assert_eq!(direction * 6.0, b - a);
+        // Coincident points have no direction, and give no velocity
+        assert_eq!(a.direction_to(&a), None);
+        assert_eq!(
+            Velocity::from_direction(&Displacement::default(), 2.0),
+            Velocity::zero()
+        );
+
+        // Every axis moves, not just x
+        let velocity = Velocity::from_direction(&(b - a), 3.0);
+        assert!((velocity.magnitude() - 3.0).abs() < 1e-12);
+        let moved = a.translate(&velocity, 2.0);
+        assert!(moved.distance_to(&b) < 1e-12);
+
+        let fast = Velocity::new(6.0, 8.0, 0.0) * 0.5;
+        assert_eq!(fast, Velocity::new(3.0, 4.0, 0.0));
+        assert_eq!(fast.clamp_speed(10.0), fast);
+        assert_eq!(fast.clamp_speed(5.0), fast);
+        assert_eq!(fast.clamp_speed(2.5), Velocity::new(1.5, 2.0, 0.0));
+        assert_eq!(fast.clamp_speed(0.0), Velocity::zero());
     }
-}
 
-/// Command response from robot
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct CommandResponse {
-    /// ID of the command being responded to
-    pub command_id: String,
-    /// Robot ID
-    pub robot_id: String,
-    /// Whether command was accepted
-    pub success: bool,
-    /// Error message if failed
-    pub error: Option<String>,
-    /// Unix timestamp (milliseconds)
-    pub timestamp: u64,
-}
+    #[test]
+    fn test_robot_state_serialization() {
+        let robot = rover();
+        let json = serde_json::to_string(&robot).unwrap();
+        let deserialized: RobotState = serde_json::from_str(&json).unwrap();
+        assert_eq!(robot.id, deserialized.id);
+        assert_eq!(robot.robot_type, deserialized.robot_type);
+    }
 
-// ============================================================================
-// MQTT TOPICS
-// ============================================================================
+    #[test]
+    fn test_robot_state_without_orientation_still_parses() {
+        let mut json = serde_json::to_value(RobotState::new_at(
+            "DR-001".parse().unwrap(),
+            "Drone",
+            RobotType::Drone,
+            T0,
+        ))
+        .unwrap();
+        json.as_object_mut().unwrap().remove("orientation");
+        let parsed: RobotState = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.orientation, Orientation::identity());
+    }
 
-/// MQTT topic definitions for the AETHERIS system
-pub mod topics {
-    /// Base topic prefix
-    pub const PREFIX: &str = "aetheris";
+    #[test]
+    fn test_delta_round_trips_through_the_wire() {
+        let previous = rover();
+        let mut current = previous.clone();
+        current.position = Position::new(0.1 + 0.2, -1e-9, 1.0 / 3.0);
+        current.battery = 100.0 - 0.01 * 7.0;
+        current.health = HealthStatus::Warning;
+        current.timestamp += Duration::from_secs(1);
 
-    /// Robot telemetry: aetheris/telemetry/{robot_id}
-    pub fn telemetry(robot_id: &str) -> String {
-        format!("{}/telemetry/{}", PREFIX, robot_id)
+        let delta = current.diff(&previous);
+        assert_eq!(delta.position, Some(current.position));
+        assert_eq!((delta.name.as_ref(), delta.signal), (None, None));
+        for encoding in Encoding::ALL {
+            let bytes = encoding
+                .encode(&TelemetryPayload::Delta(delta.clone()))
+                .unwrap();
+            let Ok(TelemetryPayload::Delta(decoded)) = encoding.decode(&bytes) else {
+                panic!("{encoding:?} delta did not decode as a delta");
+            };
+            let mut rebuilt = previous.clone();
+            rebuilt.apply(&decoded);
+            assert_eq!(rebuilt, current, "{encoding:?}");
+        }
+
+        // Nothing changed but the time
+        let mut later = current.clone();
+        later.timestamp += Duration::from_secs(1);
+        let idle = later.diff(&current);
+        assert_eq!(
+            idle,
+            RobotStateDelta {
+                timestamp: later.timestamp,
+                ..current.diff(&current)
+            }
+        );
+        assert!(idle.position.is_none() && idle.current_task.is_none());
     }
 
-    /// Telemetry wildcard subscription: aetheris/telemetry/+
-    pub const TELEMETRY_ALL: &str = "aetheris/telemetry/+";
+    #[test]
+    fn test_untagged_telemetry_is_a_full_state() {
+        let state = rover();
+        let json = serde_json::to_string(&state).unwrap();
+        let parsed: TelemetryPayload = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, TelemetryPayload::Full(state.clone()));
+        // Readers expecting a bare state accept a tagged full state
+        let tagged = serde_json::to_string(&TelemetryPayload::Full(state.clone())).unwrap();
+        assert_eq!(serde_json::from_str::<RobotState>(&tagged).unwrap(), state);
+    }
 
-    /// Robot heartbeat: aetheris/heartbeat/{robot_id}
-    pub fn heartbeat(robot_id: &str) -> String {
-        format!("{}/heartbeat/{}", PREFIX, robot_id)
+    #[test]
+    fn test_angles_wrap_at_pi() {
+        use std::f64::consts::PI;
+        assert!((wrap_angle(PI) - PI).abs() < 1e-12);
+        assert!((wrap_angle(-PI) - PI).abs() < 1e-12);
+        assert!((wrap_angle(3.0 * PI / 2.0) + PI / 2.0).abs() < 1e-12);
+        assert!((wrap_angle(-5.0 * PI) - PI).abs() < 1e-9);
+        let wrapped = Orientation::new(7.0, -4.0, 0.5).normalized();
+        assert!((wrapped.yaw - (7.0 - 2.0 * PI)).abs() < 1e-12);
+        assert!((wrapped.pitch - (2.0 * PI - 4.0)).abs() < 1e-12);
+
+        // Facing just short of -π, a target just past +π is a small left turn
+        let pose = Pose::new(Position::origin(), Orientation::new(-PI + 0.1, 0.0, 0.0));
+        let target = Position::new(-10.0, -1.0, 0.0);
+        let turn = pose.heading_to(&target);
+        assert!(turn.abs() < 0.1, "turned {turn}");
     }
 
-    /// Heartbeat wildcard: aetheris/heartbeat/+
-    pub const HEARTBEAT_ALL: &str = "aetheris/heartbeat/+";
+    #[test]
+    fn test_rotate_vector_follows_yaw_and_pitch() {
+        use std::f64::consts::FRAC_PI_2;
+        let forward = Velocity::new(1.0, 0.0, 0.0);
+        let left = Orientation::new(FRAC_PI_2, 0.0, 0.0).rotate_vector(&forward);
+        assert!(left.vx.abs() < 1e-12 && (left.vy - 1.0).abs() < 1e-12);
+        let climbing = Orientation::new(0.0, FRAC_PI_2, 0.0).rotate_vector(&forward);
+        assert!((climbing.vz - 1.0).abs() < 1e-12);
+        let rolled =
+            Orientation::new(0.0, 0.0, FRAC_PI_2).rotate_vector(&Velocity::new(0.0, 1.0, 0.0));
+        assert!((rolled.vz - 1.0).abs() < 1e-12);
 
-    /// Commands to specific robot: aetheris/commands/{robot_id}
-    pub fn commands(robot_id: &str) -> String {
-        format!("{}/commands/{}", PREFIX, robot_id)
+        let facing = Orientation::facing(&Velocity::new(0.0, -2.0, 0.0)).unwrap();
+        assert!((facing.yaw + FRAC_PI_2).abs() < 1e-12);
+        assert!(Orientation::facing(&Velocity::zero()).is_none());
     }
 
-    /// Broadcast commands to all robots: aetheris/commands/broadcast
-    pub const COMMANDS_BROADCAST: &str = "aetheris/commands/broadcast";
+    fn assert_same_vector(a: &Velocity, b: &Velocity) {
+        assert!(
+            (a.vx - b.vx).abs() < 1e-9 && (a.vy - b.vy).abs() < 1e-9 && (a.vz - b.vz).abs() < 1e-9,
+            "{a:?} != {b:?}"
+        );
+    }
 
-    /// Command wildcard: aetheris/commands/#
-    pub const COMMANDS_ALL: &str = "aetheris/commands/#";
+    #[test]
+    fn test_quaternion_matches_euler_rotation_and_round_trips() {
+        let attitude = Orientation::new(2.4, -0.7, 1.1);
+        let q = attitude.to_quaternion();
+        assert!((q.norm() - 1.0).abs() < 1e-12);
+        for v in [
+            Velocity::new(1.0, 0.0, 0.0),
+            Velocity::new(0.0, 1.0, 0.0),
+            Velocity::new(0.3, -2.0, 5.0),
+        ] {
+            assert_same_vector(&q.rotate_vector(&v), &attitude.rotate_vector(&v));
+        }
 
-    /// Anomaly alerts: aetheris/alerts
-    pub const ALERTS: &str = "aetheris/alerts";
+        let back = q.to_orientation();
+        assert!((back.yaw - 2.4).abs() < 1e-9);
+        assert!((back.pitch + 0.7).abs() < 1e-9);
+        assert!((back.roll - 1.1).abs() < 1e-9);
+        assert_eq!(
+            Quaternion::default().to_orientation(),
+            Orientation::identity()
+        );
+    }
 
-    /// Environment readings: aetheris/environment/{section_id}
-    pub fn environment(section_id: &str) -> String {
-        format!("{}/environment/{}", PREFIX, section_id)
+    #[test]
+    fn test_straight_up_folds_roll_into_yaw() {
+        use std::f64::consts::FRAC_PI_2;
+        let vertical = Orientation::new(0.4, FRAC_PI_2, 0.3);
+        let back = vertical.to_quaternion().to_orientation();
+        assert_eq!(back.roll, 0.0);
+        assert!((back.pitch - FRAC_PI_2).abs() < 1e-6);
+        let probe = Velocity::new(0.0, 1.0, 0.0);
+        assert_same_vector(&back.rotate_vector(&probe), &vertical.rotate_vector(&probe));
     }
 
-    /// Environment wildcard: aetheris/environment/+
-    pub const ENVIRONMENT_ALL: &str = "aetheris/environment/+";
+    #[test]
+    fn test_compose_applies_body_rotation_and_inverse_undoes_it() {
+        use std::f64::consts::FRAC_PI_2;
+        let forward = Velocity::new(1.0, 0.0, 0.0);
+        let climbing = Orientation::new(0.0, 0.3, 0.0);
+        let turned = climbing.compose(&Orientation::new(FRAC_PI_2, 0.0, 0.0));
+        assert_same_vector(
+            &turned.rotate_vector(&forward),
+            &climbing.rotate_vector(&Velocity::new(0.0, 1.0, 0.0)),
+        );
 
-    /// Command responses: aetheris/responses/{robot_id}
-    pub fn responses(robot_id: &str) -> String {
-        format!("{}/responses/{}", PREFIX, robot_id)
+        let attitude = Orientation::new(-1.2, 0.5, 2.0);
+        let undone = attitude.compose(&attitude.inverse());
+        assert!(undone.angle_to(&Orientation::identity()) < 1e-9);
+        assert!(
+            (Orientation::identity().angle_to(&Orientation::new(3.0, 0.0, 0.0)) - 3.0).abs() < 1e-9
+        );
+        assert!(Quaternion::new(0.0, 0.0, 0.0, 0.0).normalized() == Quaternion::identity());
     }
 
-    /// System status: aetheris/system/status
-    pub const SYSTEM_STATUS: &str = "aetheris/system/status";
-}
+    fn square_route(mode: RouteMode) -> PatrolRoute {
+        PatrolRoute::new(
+            "ROUTE-SQ",
+            "Square",
+            mode,
+            [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]
+                .into_iter()
+                .map(|(x, y)| Waypoint::at(Position::new(x, y, 0.0)))
+                .collect(),
+        )
+    }
 
-// ============================================================================
-// HELPER FUNCTIONS
-// ============================================================================
+    #[test]
+    fn test_route_length_and_waypoint_order() {
+        let looped = square_route(RouteMode::Loop);
+        let one_shot = square_route(RouteMode::OneShot);
+        assert!((looped.total_length() - 40.0).abs() < 1e-9);
+        assert!((one_shot.total_length() - 30.0).abs() < 1e-9);
 
-/// Get current Unix timestamp in milliseconds
-pub fn current_timestamp_ms() -> u64 {
-    SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_millis() as u64
-}
+        assert_eq!(looped.next_waypoint(1), Some(2));
+        assert_eq!(looped.next_waypoint(3), Some(0));
+        assert_eq!(one_shot.next_waypoint(3), None);
+        assert_eq!(
+            looped.nearest_waypoint(&Position::new(9.0, 8.0, 0.0)),
+            Some(2)
+        );
 
-/// Generate a unique anomaly ID
-fn generate_anomaly_id() -> String {
-    use std::sync::atomic::{AtomicU64, Ordering};
-    static COUNTER: AtomicU64 = AtomicU64::new(0);
-    let count = COUNTER.fetch_add(1, Ordering::SeqCst);
-    let ts = current_timestamp_ms();
-    format!("ANM-{:X}-{:04X}", ts, count)
-}
+        let empty = PatrolRoute::new("ROUTE-E", "Empty", RouteMode::Loop, Vec::new());
+        assert_eq!(empty.total_length(), 0.0);
+        assert_eq!(empty.nearest_waypoint(&Position::origin()), None);
+        assert_eq!(empty.next_waypoint(0), None);
+    }
 
-// ============================================================================
-// TESTS
-// ============================================================================
+    #[test]
+    fn test_return_cost_covers_travel_and_time_powered() {
+        let profile = RobotType::Rover.battery_profile();
+        // 30 m at 1.5 m/s: 1.5% for the distance, 0.1% for the 20 s
+        assert_eq!(profile.return_secs(30.0), 20.0);
+        assert!((profile.return_cost(30.0) - 1.6).abs() < 1e-9);
+        assert_eq!(profile.return_cost(0.0), 0.0);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        let stations = [
+            ChargingStation::new("CHG-01", Position::new(-5.0, 0.0, 1.0), 2),
+            ChargingStation::new("CHG-02", Position::new(5.0, -0.5, 8.0), 1),
+        ];
+        let nearest = ChargingStation::nearest(&stations, &Position::new(4.0, 0.0, 6.0));
+        assert_eq!(nearest.map(|s| s.id.as_str()), Some("CHG-02"));
+        assert!(ChargingStation::nearest(&[], &Position::origin()).is_none());
+    }
 
     #[test]
-    fn test_position_distance() {
-        let p1 = Position::new(0.0, 0.0, 0.0);
-        let p2 = Position::new(3.0, 4.0, 0.0);
-        assert!((p1.distance_to(&p2) - 5.0).abs() < 0.0001);
+    fn test_geofence_covers_its_footprint_between_its_heights() {
+        // An L-shaped station, fenced from the ground up to 30 m
+        let fence = Geofence {
+            altitude: Some(AltitudeRange::new(0.0, 30.0)),
+            robot_types: vec![RobotType::Drone],
+            ..Geofence::new(
+                "FENCE-COMPRESSOR",
+                vec![
+                    Position::new(0.0, 0.0, 0.0),
+                    Position::new(20.0, 0.0, 0.0),
+                    Position::new(20.0, 0.0, 10.0),
+                    Position::new(10.0, 0.0, 10.0),
+                    Position::new(10.0, 0.0, 20.0),
+                    Position::new(0.0, 0.0, 20.0),
+                ],
+            )
+        };
+        assert!(fence.contains(&Position::new(5.0, 10.0, 15.0)));
+        assert!(fence.contains(&Position::new(15.0, 10.0, 5.0)));
+        // The notch of the L, above the fence, and outside it
+        assert!(!fence.contains(&Position::new(15.0, 10.0, 15.0)));
+        assert!(!fence.contains(&Position::new(5.0, 40.0, 15.0)));
+        assert!(!fence.contains(&Position::new(-5.0, 10.0, 5.0)));
+        assert!(fence.applies_to(RobotType::Drone));
+        assert!(!fence.applies_to(RobotType::Rover));
+
+        // Across the station at 10 m, entering a quarter of the way
+        let from = Position::new(-10.0, 10.0, 5.0);
+        let entry = fence.entry(&from, &Position::new(30.0, 10.0, 5.0)).unwrap();
+        assert!((entry - 0.25).abs() < 1e-9);
+        // Descending onto it from above, entering at 30 m
+        let above = Position::new(5.0, 50.0, 5.0);
+        let entry = fence.entry(&above, &Position::new(5.0, 10.0, 5.0)).unwrap();
+        assert!((entry - 0.5).abs() < 1e-9);
+        // Past the notch, and over the top
+        let notch = fence.entry(
+            &Position::new(15.0, 10.0, 25.0),
+            &Position::new(15.0, 10.0, 12.0),
+        );
+        assert_eq!(notch, None);
+        let over = fence.entry(
+            &Position::new(-10.0, 40.0, 5.0),
+            &Position::new(30.0, 40.0, 5.0),
+        );
+        assert_eq!(over, None);
+        assert_eq!(
+            fence.entry(&Position::new(5.0, 10.0, 5.0), &from),
+            Some(0.0)
+        );
     }
 
     #[test]
-    fn test_robot_state_serialization() {
-        let robot = RobotState::new("RV-001", "Rover Alpha", RobotType::Rover);
-        let json = serde_json::to_string(&robot).unwrap();
-        let deserialized: RobotState = serde_json::from_str(&json).unwrap();
-        assert_eq!(robot.id, deserialized.id);
-        assert_eq!(robot.robot_type, deserialized.robot_type);
+    fn test_route_library_keeps_one_route_per_id_in_order() {
+        let mut renamed = square_route(RouteMode::OneShot);
+        renamed.name = "Square, once".into();
+        let mut library = RouteLibrary::new([
+            square_route(RouteMode::Loop),
+            PatrolRoute::new("ROUTE-A1", "Alpha", RouteMode::Loop, Vec::new()),
+        ]);
+        assert_eq!(library.ids().collect::<Vec<_>>(), ["ROUTE-A1", "ROUTE-SQ"]);
+
+        let replaced = library.upsert(renamed).unwrap();
+        assert_eq!(replaced.mode, RouteMode::Loop);
+        assert_eq!(library.len(), 2);
+        assert_eq!(library.get("ROUTE-SQ").unwrap().name, "Square, once");
+
+        assert!(library.remove("ROUTE-A1").is_some());
+        assert!(library.remove("ROUTE-A1").is_none());
+        assert!(library.get("ROUTE-A1").is_none());
+        assert!(!library.is_empty());
     }
 
     #[test]
@@ -579,9 +5607,246 @@ mod tests {
         assert!(json.contains("target"));
     }
 
+    #[test]
+    fn test_canonical_json_sorts_fields_at_every_level() {
+        let parse = |text: &str| serde_json::from_str::<serde_json::Value>(text).unwrap();
+        let written = parse(r#"{"b":1,"a":{"y":[1,{"d":2.5,"c":"x"}],"x":null}}"#);
+        let reordered = parse(r#"{ "a": { "x": null, "y": [1, {"c": "x", "d": 2.5}] }, "b": 1 }"#);
+        assert_eq!(canonical_json(&written), canonical_json(&reordered));
+        assert_eq!(
+            canonical_json(&written),
+            br#"{"a":{"x":null,"y":[1,{"c":"x","d":2.5}]},"b":1}"#
+        );
+        // By bytes: uppercase before lowercase, a prefix before its extensions
+        assert_eq!(
+            canonical_json(&parse(r#"{"b":0,"ab":0,"a":0,"B":"\u00e9"}"#)),
+            r#"{"B":"é","a":0,"ab":0,"b":0}"#.as_bytes()
+        );
+    }
+
+    #[cfg(feature = "signing")]
+    #[test]
+    fn test_envelope_signature_survives_reencoding_but_not_tampering() {
+        let key = SigningKey::new(b"rv-001-key");
+        let state = rover();
+        let mut msg = MqttMessage::new(state, "RV-001", 7);
+        assert_eq!(msg.verify(&key), Err(SignatureError::Unsigned));
+        msg.sign(&key);
+        assert_eq!(msg.verify(&key), Ok(()));
+
+        // As the receiver sees it: untyped, in either encoding
+        for encoding in Encoding::ALL {
+            let bytes = encoding.encode(&msg).unwrap();
+            let received: MqttMessage<serde_json::Value> = encoding.decode(&bytes).unwrap();
+            assert_eq!(received.verify(&key), Ok(()), "{encoding}");
+        }
+
+        let mut replayed = msg.clone();
+        replayed.seq += 1;
+        assert_eq!(replayed.verify(&key), Err(SignatureError::Mismatch));
+        let mut moved = msg.clone();
+        moved.payload.position.x += 1.0;
+        assert_eq!(moved.verify(&key), Err(SignatureError::Mismatch));
+        assert_eq!(
+            msg.verify(&SigningKey::new(b"guessed")),
+            Err(SignatureError::Mismatch)
+        );
+        msg.signature = Some("zz".into());
+        assert_eq!(msg.verify(&key), Err(SignatureError::Malformed));
+    }
+
+    #[test]
+    fn test_topic_parse_round_trips_builders() {
+        use topics::{CommandTarget, Topic};
+        let cases = [
+            Topic::Telemetry {
+                robot_id: "RV-001".parse().unwrap(),
+            },
+            Topic::TelemetryBatch,
+            Topic::TelemetryFiltered {
+                robot_id: "RV-001".parse().unwrap(),
+            },
+            Topic::Heartbeat {
+                robot_id: "DR-002".parse().unwrap(),
+            },
+            Topic::Commands {
+                target: CommandTarget::Robot("CR-001".parse().unwrap()),
+            },
+            Topic::Commands {
+                target: CommandTarget::Broadcast,
+            },
+            Topic::Environment {
+                section_id: "PIPE-003".into(),
+            },
+            Topic::Responses {
+                robot_id: "RV-001".parse().unwrap(),
+            },
+            Topic::Alerts,
+            Topic::AlertSeverity {
+                severity: SeverityLevel::Critical,
+            },
+            Topic::AlertEscalations,
+            Topic::SystemStatus,
+            Topic::SystemLeader,
+            Topic::TriageRequests,
+            Topic::TriageResults,
+            Topic::DeadLetter,
+            Topic::Routes {
+                route_id: "ROUTE-A1".into(),
+            },
+            Topic::RouteLibrary,
+            Topic::RouteRequests,
+            Topic::Health {
+                section_id: "PIPE-002".into(),
+            },
+            Topic::Missions {
+                robot_id: "CR-001".into(),
+            },
+            Topic::BroadcastResults,
+        ];
+        for topic in cases {
+            assert_eq!(topics::parse(&topic.to_string()), Some(topic.clone()));
+        }
+        assert_eq!(
+            topics::parse(&topics::telemetry(&"RV-001".parse().unwrap()))
+                .unwrap()
+                .subject(),
+            Some("RV-001")
+        );
+        assert_eq!(
+            topics::parse(topics::COMMANDS_BROADCAST).unwrap().subject(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_topic_parse_rejects_malformed_topics() {
+        for topic in [
+            "",
+            "aetheris",
+            "aetheris/telemetry",
+            "aetheris/telemetry/",
+            "aetheris/telemetry/RV-001/extra",
+            "aetheris/telemetry/+",
+            "aetheris/commands/#",
+            "other/telemetry/RV-001",
+            "aetheris/alerts/RV-001",
+            "aetheris/alerts/Critical",
+            "aetheris/system/load",
+            "aetheris/route_requests/ROUTE-A1",
+            "aetheris/unknown/RV-001",
+            "/aetheris/telemetry/RV-001",
+        ] {
+            assert_eq!(topics::parse(topic), None, "{topic:?}");
+        }
+    }
+
+    #[test]
+    fn test_severity_names_match_topics_and_order() {
+        for level in SeverityLevel::ALL {
+            assert_eq!(
+                topics::parse(&topics::alerts_severity(level)),
+                Some(topics::Topic::AlertSeverity { severity: level })
+            );
+        }
+        assert!(SeverityLevel::ALL.is_sorted());
+        assert!(SeverityLevel::Info < SeverityLevel::Low);
+        assert!(SeverityLevel::High < SeverityLevel::Critical);
+        assert_eq!(
+            SeverityLevel::ALL.into_iter().max(),
+            Some(SeverityLevel::Critical)
+        );
+    }
+
+    /// Every variant of `all` is named as serde names it, and parses back
+    fn assert_names_round_trip<T>(all: &[T])
+    where
+        T: Copy
+            + PartialEq
+            + std::fmt::Debug
+            + std::fmt::Display
+            + std::str::FromStr<Err = UnknownName>
+            + Serialize
+            + serde::de::DeserializeOwned,
+    {
+        let mut names = std::collections::HashSet::new();
+        for &variant in all {
+            let name = variant.to_string();
+            assert_eq!(serde_json::to_value(variant).unwrap(), name.as_str());
+            assert_eq!(
+                serde_json::from_value::<T>(serde_json::json!(name)).unwrap(),
+                variant
+            );
+            assert_eq!(name.parse::<T>(), Ok(variant), "{name}");
+            assert!(names.insert(name), "{variant:?} named twice");
+        }
+        let err = "no_such_variant".parse::<T>().unwrap_err();
+        assert_eq!(err.name, "no_such_variant");
+    }
+
+    #[test]
+    fn test_enum_names_round_trip_through_serde() {
+        assert_names_round_trip(&RobotType::ALL);
+        assert_names_round_trip(&RobotStatus::ALL);
+        assert_names_round_trip(&ScanType::ALL);
+        assert_names_round_trip(&CommandStatus::ALL);
+        assert_names_round_trip(&HealthStatus::ALL);
+        assert_names_round_trip(&RouteMode::ALL);
+        assert_names_round_trip(&PipeMaterial::ALL);
+        assert_names_round_trip(&AnomalyType::ALL);
+        assert_names_round_trip(&SeverityLevel::ALL);
+        assert_names_round_trip(&AnomalyStatus::ALL);
+        assert_names_round_trip(&Resolution::ALL);
+        assert_names_round_trip(&NotificationUrgency::ALL);
+        assert_names_round_trip(&HealthFactorKind::ALL);
+        assert_names_round_trip(&TriageAction::ALL);
+        assert_names_round_trip(&AssignmentState::ALL);
+        assert_names_round_trip(&OperatorRole::ALL);
+        assert_names_round_trip(&OperationKind::ALL);
+        assert_names_round_trip(&FaultType::ALL);
+        assert_names_round_trip(&MissionState::ALL);
+        assert_names_round_trip(&EngineState::ALL);
+        assert_names_round_trip(&DeadLetterReason::ALL);
+        assert_names_round_trip(&TimelineEntryKind::ALL);
+        assert_names_round_trip(&RecordStore::ALL);
+        assert_names_round_trip(&Encoding::ALL);
+        assert_names_round_trip(&ErrorKind::ALL);
+
+        assert_eq!(
+            "urgent".parse::<SeverityLevel>(),
+            Err(UnknownName {
+                kind: "SeverityLevel",
+                name: "urgent".into()
+            })
+        );
+        assert_eq!(
+            "urgent".parse::<SeverityLevel>().unwrap_err().to_string(),
+            "unknown SeverityLevel \"urgent\""
+        );
+        assert_eq!(format!("[{:<8}]", RobotStatus::Idle), "[idle    ]");
+    }
+
+    #[test]
+    fn test_command_name_matches_wire_tag() {
+        for cmd in [
+            Command::Stop,
+            Command::InjectFault {
+                fault_type: FaultType::CommDropout,
+            },
+            Command::ClearFault { fault_type: None },
+            Command::PerformScan {
+                scan_type: ScanType::Thermal,
+            },
+        ] {
+            let json = serde_json::to_value(&cmd).unwrap();
+            assert_eq!(json["command"], cmd.name());
+            assert!(Command::NAMES.contains(&cmd.name()));
+        }
+    }
+
     #[test]
     fn test_anomaly_report_creation() {
-        let report = AnomalyReport::new(
+        let report = AnomalyReport::new_at(
             AnomalyType::Leak,
             SeverityLevel::High,
             Position::new(5.0, 0.0, 10.0),
@@ -589,11 +5854,91 @@ mod tests {
             "RV-001",
             0.94,
             "Hydrogen leak detected at joint H-7",
+            T0,
         );
         assert!(report.id.starts_with("ANM-"));
         assert_eq!(report.severity, SeverityLevel::High);
     }
 
+    #[test]
+    fn test_assignment_is_optional_on_the_wire() {
+        let mut report = AnomalyReport::new_at(
+            AnomalyType::Leak,
+            SeverityLevel::High,
+            Position::origin(),
+            "PIPE-001",
+            "RV-001",
+            0.9,
+            "Leak",
+            T0,
+        );
+        let mut json = serde_json::to_value(&report).unwrap();
+        assert!(json.get("assignment").is_none());
+        json.as_object_mut().unwrap().remove("assignment");
+        let decoded: AnomalyReport = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.assignment, None);
+
+        report.assignment = Some(Assignment {
+            assignee: "j.ortega".into(),
+            assigned_by: "dashboard".into(),
+            assigned_at: 1_000,
+            due_at: 61_000,
+            state: AssignmentState::InProgress,
+        });
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["assignment"]["state"], "in_progress");
+        let decoded: AnomalyReport = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, report);
+
+        let resolve: Command = serde_json::from_str(
+            r#"{"command":"resolve_anomaly","params":{"anomaly_id":"ANM-1"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            resolve,
+            Command::ResolveAnomaly {
+                anomaly_id: "ANM-1".into(),
+                resolution: Resolution::Fixed,
+                force: false
+            }
+        );
+    }
+
+    #[test]
+    fn test_anomaly_lifecycle_moves_forward_and_derives_acknowledged() {
+        use AnomalyStatus::*;
+        assert!(New.can_become(Acknowledged));
+        assert!(New.can_become(FalsePositive));
+        assert!(Acknowledged.can_become(Investigating));
+        assert!(!Acknowledged.can_become(Acknowledged));
+        assert!(!Investigating.can_become(Acknowledged));
+        assert!(!Resolved.can_become(FalsePositive));
+
+        let mut report = AnomalyReport::new_at(
+            AnomalyType::Leak,
+            SeverityLevel::High,
+            Position::origin(),
+            "PIPE-001",
+            "RV-001",
+            0.9,
+            "Leak",
+            T0,
+        );
+        let json = serde_json::to_value(&report).unwrap();
+        assert!(json.get("status").is_none());
+
+        report.set_status(Acknowledged, "j.ortega", 1_000);
+        report.set_status(Resolution::FalsePositive.status(), "a.chen", 2_000);
+        assert!(report.acknowledged);
+        assert_eq!(report.acknowledged_by.as_deref(), Some("j.ortega"));
+        assert_eq!(report.resolved_by.as_deref(), Some("a.chen"));
+        assert_eq!(report.status_history.len(), 2);
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["status"], "false_positive");
+        let decoded: AnomalyReport = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, report);
+    }
+
     #[test]
     fn test_pipe_environment_hazard() {
         let safe = PipeEnvironment {
@@ -605,7 +5950,7 @@ mod tests {
             flow_rate: 500.0,
             humidity: 45.0,
             position: Position::origin(),
-            timestamp: current_timestamp_ms(),
+            timestamp: T0,
         };
         assert!(!safe.is_hazardous());
 
@@ -614,5 +5959,263 @@ mod tests {
             ..safe.clone()
         };
         assert!(hazardous.is_hazardous());
+        assert!(!hazardous.exceeds(&HazardThresholds {
+            h2_ppm: 6000.0,
+            ..HazardThresholds::default()
+        }));
+    }
+
+    #[test]
+    fn test_default_hazard_thresholds_match_limits() {
+        let thresholds = HazardThresholds::default();
+        assert_eq!(thresholds.h2_ppm, limits::H2_ALERT_PPM);
+        assert_eq!(thresholds.h2_ppm, 4000.0);
+        assert_eq!(thresholds.pressure_bar, limits::PRESSURE_ALERT_BAR);
+        assert_eq!(
+            thresholds.temperature_celsius,
+            limits::TEMPERATURE_ALERT_CELSIUS
+        );
+    }
+
+    fn pipe(id: &str, start: (f64, f64), end: (f64, f64), neighbors: &[&str]) -> PipelineSection {
+        PipelineSection {
+            id: id.into(),
+            start: Position::new(start.0, 0.0, start.1),
+            end: Position::new(end.0, 0.0, end.1),
+            diameter_mm: 508.0,
+            material: PipeMaterial::CarbonSteel,
+            design_pressure_bar: 70.0,
+            neighbors: neighbors.iter().map(|n| n.to_string()).collect(),
+        }
+    }
+
+    /// A trunk with a branch at x = 10, and an isolated section
+    fn junction_map() -> PipelineMap {
+        PipelineMap::new(
+            vec![
+                pipe("TRUNK-1", (0.0, 0.0), (10.0, 0.0), &[]),
+                pipe("TRUNK-2", (10.0, 0.0), (20.0, 0.0), &["TRUNK-1"]),
+                pipe("BRANCH", (10.0, 0.0), (10.0, 10.0), &["TRUNK-1", "TRUNK-2"]),
+                pipe("SPUR", (10.0, 10.0), (15.0, 10.0), &["BRANCH"]),
+                pipe("ISLAND", (50.0, 50.0), (60.0, 50.0), &["NOWHERE"]),
+            ],
+            1.0,
+        )
+    }
+
+    fn ids(sections: Vec<&PipelineSection>) -> Vec<&str> {
+        sections.into_iter().map(|s| s.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_section_at_picks_the_nearest_section_within_tolerance() {
+        let map = junction_map();
+        let at = |x, z| {
+            map.section_at(&Position::new(x, 0.0, z))
+                .map(|s| s.id.as_str())
+        };
+        assert_eq!(at(4.0, 0.5), Some("TRUNK-1"));
+        assert_eq!(at(10.4, 6.0), Some("BRANCH"));
+        assert_eq!(at(12.0, -0.2), Some("TRUNK-2"));
+        // Beyond the end of the trunk and off to the side
+        assert_eq!(at(-1.5, 0.0), None);
+        assert_eq!(at(5.0, 3.0), None);
+        assert!(
+            PipelineMap::default()
+                .section_at(&Position::origin())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_paths_follow_connections_through_junctions() {
+        let map = junction_map();
+        assert_eq!(ids(map.neighbors("TRUNK-1")), ["TRUNK-2", "BRANCH"]);
+        assert_eq!(ids(map.neighbors("BRANCH")), ["TRUNK-1", "TRUNK-2", "SPUR"]);
+        assert_eq!(
+            ids(map.path_between("TRUNK-1", "SPUR").unwrap()),
+            ["TRUNK-1", "BRANCH", "SPUR"]
+        );
+        assert_eq!(ids(map.path_between("SPUR", "SPUR").unwrap()), ["SPUR"]);
+
+        // Disconnected or unknown
+        assert!(map.neighbors("ISLAND").is_empty());
+        assert!(map.path_between("TRUNK-1", "ISLAND").is_none());
+        assert!(map.path_between("TRUNK-1", "NOWHERE").is_none());
+    }
+
+    #[test]
+    fn test_capabilities_follow_type_and_config() {
+        let ultrasonic = Command::PerformScan {
+            scan_type: ScanType::Ultrasonic,
+        };
+        assert!(RobotType::Crawler.supports_command(&ultrasonic));
+        assert!(!RobotType::Rover.supports_command(&ultrasonic));
+        assert!(RobotType::Rover.supports_command(&Command::ReturnToBase));
+
+        let climb = Command::MoveTo {
+            target: Position::new(0.0, 40.0, 0.0),
+            speed: Some(2.0),
+        };
+        assert!(RobotType::Drone.supports_command(&climb));
+        let err = RobotType::Crawler.capabilities().check(&climb).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "crawler is limited to 0.5 m/s, 2 m/s requested"
+        );
+
+        // A special unit carrying an ultrasonic probe
+        let special = RobotType::Rover.capabilities().configured(&RobotConfig {
+            supported_scans: Some(vec![ScanType::Ultrasonic]),
+            ..RobotConfig::default()
+        });
+        assert!(special.check(&ultrasonic).is_ok());
+        assert!(
+            special
+                .check(&Command::PerformScan {
+                    scan_type: ScanType::Thermal
+                })
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_robot_id_format_and_type_hint() {
+        let id = RobotId::parse("DR-002").unwrap();
+        assert_eq!(id.robot_type_hint(), Some(RobotType::Drone));
+        assert_eq!(id, "DR-002");
+        assert_eq!(id.to_string(), "DR-002");
+        assert_eq!(
+            "CR-010".parse::<RobotId>().unwrap().robot_type_hint(),
+            Some(RobotType::Crawler)
+        );
+        // Well formed, but no known kind
+        assert_eq!(RobotId::parse("XX-001").unwrap().robot_type_hint(), None);
+        assert!(RobotId::parse("engine").unwrap().is_engine());
+        assert_eq!(RobotId::engine().robot_type_hint(), None);
+        for bad in [
+            "PIPE-001", "RV-01", "rv-001", "RV001", "RV-0011", "RV-00a", "",
+        ] {
+            assert_eq!(
+                RobotId::parse(bad),
+                Err(InvalidRobotId(bad.into())),
+                "{bad}"
+            );
+        }
+
+        // A plain string on the wire, and a clear error for a bad one
+        assert_eq!(serde_json::to_string(&id).unwrap(), "\"DR-002\"");
+        assert_eq!(serde_json::from_str::<RobotId>("\"DR-002\"").unwrap(), id);
+        let err = serde_json::from_str::<Heartbeat>(
+            r#"{"robot_id":"PIPE-001","robot_type":"rover","status":"active","battery":50.0,"signal":50.0,"uptime":1,"timestamp":0}"#,
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("invalid robot id \"PIPE-001\": expected"),
+            "{err}"
+        );
+        assert_eq!(topics::parse("aetheris/telemetry/PIPE-001"), None);
+
+        // Sorted and looked up like the string
+        let mut ids: Vec<RobotId> = ["RV-002", "CR-001", "RV-001"]
+            .iter()
+            .map(|id| id.parse().unwrap())
+            .collect();
+        ids.sort();
+        assert_eq!(ids, ["CR-001", "RV-001", "RV-002"]);
+        let fleet: std::collections::HashMap<RobotId, u8> =
+            std::collections::HashMap::from([(id, 1)]);
+        assert_eq!(fleet.get("DR-002"), Some(&1));
+    }
+
+    #[test]
+    fn test_timestamp_arithmetic_and_conversions() {
+        let at = Timestamp::from_millis(10_000);
+        assert_eq!(at + Duration::from_secs(5), 15_000);
+        assert_eq!(at - Duration::from_secs(60), Timestamp::EPOCH);
+        assert_eq!(
+            Timestamp::from_millis(u64::MAX) + Duration::from_millis(1),
+            u64::MAX
+        );
+        let later = at + Duration::from_millis(1_500);
+        assert_eq!(later.duration_since(at), Duration::from_millis(1_500));
+        assert_eq!(at.duration_since(later), Duration::ZERO);
+        assert!(at < later && later > 10_000);
+
+        // A plain integer of milliseconds on the wire
+        assert_eq!(serde_json::to_string(&later).unwrap(), "11500");
+        assert_eq!(serde_json::from_str::<Timestamp>("11500").unwrap(), later);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_timestamp_reads_and_converts_the_system_clock() {
+        let at = Timestamp::from_millis(10_000);
+        let now = Timestamp::now();
+        assert!(now.elapsed() < Duration::from_secs(60));
+        assert!(!now.is_older_than(Duration::from_secs(60)));
+        assert!(at.is_older_than(Duration::from_secs(60)));
+
+        // Through SystemTime and back without loss; before the epoch is the epoch
+        let later = at + Duration::from_millis(1_500);
+        assert_eq!(Timestamp::from(SystemTime::from(later)), later);
+        let before = SystemTime::UNIX_EPOCH - Duration::from_secs(1);
+        assert_eq!(Timestamp::from(before), Timestamp::EPOCH);
+    }
+
+    #[test]
+    fn test_constructors_stamp_the_time_they_are_given() {
+        let robot = rover();
+        assert_eq!(robot.timestamp, T0);
+        let heartbeat = Heartbeat::new_at(
+            robot.id.clone(),
+            RobotType::Rover,
+            RobotStatus::Idle,
+            80.0,
+            90.0,
+            5,
+            T0,
+        );
+        assert_eq!(heartbeat.timestamp, T0);
+        assert_eq!(
+            MqttMessage::new_at(heartbeat, "RV-001", 1, T0).timestamp,
+            T0
+        );
+        assert_eq!(
+            TelemetryBatch::new_at("engine", vec![robot], T0).timestamp,
+            T0.as_millis()
+        );
+
+        // Reports at the same instant still get distinct ids
+        let report = |description| {
+            AnomalyReport::new_at(
+                AnomalyType::Leak,
+                SeverityLevel::High,
+                Position::origin(),
+                "PIPE-001",
+                "RV-001",
+                0.9,
+                description,
+                T0,
+            )
+        };
+        let (first, second) = (report("first"), report("second"));
+        assert_eq!(first.timestamp, T0);
+        assert!(first.id.starts_with(&format!("ANM-{:X}-", T0.as_millis())));
+        assert_ne!(first.id, second.id);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_timestamp_converts_to_and_from_chrono() {
+        use chrono::{DateTime, Utc};
+
+        let at = Timestamp::from_millis(1_700_000_000_123);
+        let date_time = DateTime::<Utc>::from(at);
+        assert_eq!(date_time.timestamp_millis(), 1_700_000_000_123);
+        assert_eq!(Timestamp::from(date_time), at);
+        let before = DateTime::<Utc>::from_timestamp_millis(-1).unwrap();
+        assert_eq!(Timestamp::from(before), Timestamp::EPOCH);
     }
 }
@@ -0,0 +1,1470 @@
+//! Protocol Buffers mirror of the shared messages
+//!
+//! For consumers outside Rust that would rather generate code from a schema
+//! than follow the JSON: the messages here match `proto/aetheris.proto`
+//! field for field, written out the way `prost-build` would generate them
+//! so the build needs no `protoc`. `From<&T>` turns a shared value into its
+//! message; `TryFrom` turns a received message back, failing on a missing
+//! field, an enum value this build doesn't know, or an invalid robot id.
+//!
+//! ```ignore
+//! use prost::Message;
+//!
+//! let bytes = proto::RobotState::from(&state).encode_to_vec();
+//! let state: RobotState = proto::RobotState::decode(&bytes[..])?.try_into()?;
+//! ```
+//!
+//! JSON stays the wire default; nothing in the fleet speaks protobuf unless
+//! it is built with the `protobuf` feature and chooses to.
+
+use alloc::{string::String, vec::Vec};
+
+use crate::InvalidRobotId;
+
+/// A message that is not a valid shared value
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ProtoError {
+    #[error("invalid protobuf: {0}")]
+    Decode(#[from] prost::DecodeError),
+    /// A field the shared type requires was absent
+    #[error("missing {0}")]
+    Missing(&'static str),
+    /// An enum value that is unspecified, or from a newer schema
+    #[error("{field} has no value {value}")]
+    UnknownValue { field: &'static str, value: i32 },
+    #[error(transparent)]
+    RobotId(#[from] InvalidRobotId),
+}
+
+fn required<T>(field: &'static str, value: Option<T>) -> Result<T, ProtoError> {
+    value.ok_or(ProtoError::Missing(field))
+}
+
+/// An enum field whose unspecified value stands for the default
+fn defaulted<T: Default>(
+    value: i32,
+    decode: impl FnOnce(i32) -> Result<T, ProtoError>,
+) -> Result<T, ProtoError> {
+    if value == 0 {
+        Ok(T::default())
+    } else {
+        decode(value)
+    }
+}
+
+/// A protobuf enum of the shared one with the same name, numbered from 1
+/// after the unspecified zero value
+macro_rules! proto_enum {
+    ($name:ident { $($variant:ident = $value:literal),+ $(,)? }) => {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+        #[repr(i32)]
+        pub enum $name {
+            Unspecified = 0,
+            $($variant = $value,)+
+        }
+
+        impl From<crate::$name> for $name {
+            fn from(value: crate::$name) -> Self {
+                match value {
+                    $(crate::$name::$variant => Self::$variant,)+
+                }
+            }
+        }
+
+        impl $name {
+            /// The shared value of the received `field`
+            pub fn to_shared(field: &'static str, value: i32) -> Result<crate::$name, ProtoError> {
+                match Self::try_from(value) {
+                    $(Ok(Self::$variant) => Ok(crate::$name::$variant),)+
+                    _ => Err(ProtoError::UnknownValue { field, value }),
+                }
+            }
+        }
+    };
+}
+
+/// Wire value of a shared enum
+fn wire<P: From<T> + Into<i32>, T>(shared: T) -> i32 {
+    P::from(shared).into()
+}
+
+/// A parameterless oneof variant
+#[derive(Clone, Copy, PartialEq, prost::Message)]
+pub struct Empty {}
+
+// ============================================================================
+// GEOMETRY
+// ============================================================================
+
+#[derive(Clone, Copy, PartialEq, prost::Message)]
+pub struct Position {
+    #[prost(double, tag = "1")]
+    pub x: f64,
+    #[prost(double, tag = "2")]
+    pub y: f64,
+    #[prost(double, tag = "3")]
+    pub z: f64,
+}
+
+impl From<crate::Position> for Position {
+    fn from(value: crate::Position) -> Self {
+        Self {
+            x: value.x,
+            y: value.y,
+            z: value.z,
+        }
+    }
+}
+
+impl From<Position> for crate::Position {
+    fn from(value: Position) -> Self {
+        Self::new(value.x, value.y, value.z)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, prost::Message)]
+pub struct Velocity {
+    #[prost(double, tag = "1")]
+    pub vx: f64,
+    #[prost(double, tag = "2")]
+    pub vy: f64,
+    #[prost(double, tag = "3")]
+    pub vz: f64,
+}
+
+impl From<crate::Velocity> for Velocity {
+    fn from(value: crate::Velocity) -> Self {
+        Self {
+            vx: value.vx,
+            vy: value.vy,
+            vz: value.vz,
+        }
+    }
+}
+
+impl From<Velocity> for crate::Velocity {
+    fn from(value: Velocity) -> Self {
+        Self::new(value.vx, value.vy, value.vz)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, prost::Message)]
+pub struct Orientation {
+    #[prost(double, tag = "1")]
+    pub yaw: f64,
+    #[prost(double, tag = "2")]
+    pub pitch: f64,
+    #[prost(double, tag = "3")]
+    pub roll: f64,
+}
+
+impl From<crate::Orientation> for Orientation {
+    fn from(value: crate::Orientation) -> Self {
+        Self {
+            yaw: value.yaw,
+            pitch: value.pitch,
+            roll: value.roll,
+        }
+    }
+}
+
+impl From<Orientation> for crate::Orientation {
+    fn from(value: Orientation) -> Self {
+        Self::new(value.yaw, value.pitch, value.roll)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, prost::Message)]
+pub struct AltitudeRange {
+    #[prost(double, tag = "1")]
+    pub min: f64,
+    #[prost(double, tag = "2")]
+    pub max: f64,
+}
+
+impl From<crate::AltitudeRange> for AltitudeRange {
+    fn from(value: crate::AltitudeRange) -> Self {
+        Self {
+            min: value.min,
+            max: value.max,
+        }
+    }
+}
+
+impl From<AltitudeRange> for crate::AltitudeRange {
+    fn from(value: AltitudeRange) -> Self {
+        Self::new(value.min, value.max)
+    }
+}
+
+// ============================================================================
+// ROBOT STATE
+// ============================================================================
+
+proto_enum!(RobotType {
+    Rover = 1,
+    Drone = 2,
+    Crawler = 3,
+});
+
+proto_enum!(RobotStatus {
+    Active = 1,
+    Idle = 2,
+    Maintenance = 3,
+    Error = 4,
+    Offline = 5,
+});
+
+proto_enum!(HealthStatus {
+    Optimal = 1,
+    Warning = 2,
+    Critical = 3,
+});
+
+proto_enum!(ScanType {
+    Full = 1,
+    LeakDetection = 2,
+    Thermal = 3,
+    Ultrasonic = 4,
+    Visual = 5,
+});
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CurrentTask {
+    #[prost(oneof = "current_task::Task", tags = "1, 2, 3, 4, 5, 6")]
+    pub task: Option<current_task::Task>,
+}
+
+pub mod current_task {
+    use alloc::string::String;
+
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Task {
+        #[prost(message, tag = "1")]
+        None(super::Empty),
+        /// Route id
+        #[prost(string, tag = "2")]
+        Patrolling(String),
+        /// Target position
+        #[prost(message, tag = "3")]
+        MovingTo(super::Position),
+        #[prost(enumeration = "super::ScanType", tag = "4")]
+        Scanning(i32),
+        #[prost(message, tag = "5")]
+        ReturningToBase(super::Empty),
+        /// Anomaly id
+        #[prost(string, tag = "6")]
+        Investigating(String),
+    }
+}
+
+impl From<&crate::CurrentTask> for CurrentTask {
+    fn from(value: &crate::CurrentTask) -> Self {
+        use current_task::Task;
+        let task = match value {
+            crate::CurrentTask::None => Task::None(Empty {}),
+            crate::CurrentTask::Patrolling { route_id } => Task::Patrolling(route_id.clone()),
+            crate::CurrentTask::MovingTo { target } => Task::MovingTo((*target).into()),
+            crate::CurrentTask::Scanning { scan_type } => {
+                Task::Scanning(wire::<ScanType, _>(*scan_type))
+            }
+            crate::CurrentTask::ReturningToBase => Task::ReturningToBase(Empty {}),
+            crate::CurrentTask::Investigating { anomaly_id } => {
+                Task::Investigating(anomaly_id.clone())
+            }
+        };
+        Self { task: Some(task) }
+    }
+}
+
+impl TryFrom<CurrentTask> for crate::CurrentTask {
+    type Error = ProtoError;
+
+    fn try_from(value: CurrentTask) -> Result<Self, ProtoError> {
+        use current_task::Task;
+        Ok(match required("CurrentTask.task", value.task)? {
+            Task::None(_) => Self::None,
+            Task::Patrolling(route_id) => Self::Patrolling { route_id },
+            Task::MovingTo(target) => Self::MovingTo {
+                target: target.into(),
+            },
+            Task::Scanning(scan_type) => Self::Scanning {
+                scan_type: ScanType::to_shared("CurrentTask.scanning", scan_type)?,
+            },
+            Task::ReturningToBase(_) => Self::ReturningToBase,
+            Task::Investigating(anomaly_id) => Self::Investigating { anomaly_id },
+        })
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RobotState {
+    /// e.g. "RV-001"
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "2")]
+    pub name: String,
+    #[prost(enumeration = "RobotType", tag = "3")]
+    pub robot_type: i32,
+    #[prost(message, optional, tag = "4")]
+    pub position: Option<Position>,
+    #[prost(message, optional, tag = "5")]
+    pub velocity: Option<Velocity>,
+    /// Identity when absent
+    #[prost(message, optional, tag = "6")]
+    pub orientation: Option<Orientation>,
+    #[prost(double, tag = "7")]
+    pub battery: f64,
+    #[prost(double, tag = "8")]
+    pub signal: f64,
+    #[prost(enumeration = "HealthStatus", tag = "9")]
+    pub health: i32,
+    #[prost(enumeration = "RobotStatus", tag = "10")]
+    pub status: i32,
+    #[prost(message, optional, tag = "11")]
+    pub current_task: Option<CurrentTask>,
+    /// Unix milliseconds
+    #[prost(uint64, tag = "12")]
+    pub timestamp: u64,
+}
+
+impl From<&crate::RobotState> for RobotState {
+    fn from(value: &crate::RobotState) -> Self {
+        Self {
+            id: value.id.as_str().into(),
+            name: value.name.clone(),
+            robot_type: wire::<RobotType, _>(value.robot_type),
+            position: Some(value.position.into()),
+            velocity: Some(value.velocity.into()),
+            orientation: Some(value.orientation.into()),
+            battery: value.battery,
+            signal: value.signal,
+            health: wire::<HealthStatus, _>(value.health),
+            status: wire::<RobotStatus, _>(value.status),
+            current_task: Some((&value.current_task).into()),
+            timestamp: value.timestamp.as_millis(),
+        }
+    }
+}
+
+impl TryFrom<RobotState> for crate::RobotState {
+    type Error = ProtoError;
+
+    fn try_from(value: RobotState) -> Result<Self, ProtoError> {
+        Ok(Self {
+            id: crate::RobotId::parse(value.id)?,
+            name: value.name,
+            robot_type: RobotType::to_shared("RobotState.robot_type", value.robot_type)?,
+            position: required("RobotState.position", value.position)?.into(),
+            velocity: required("RobotState.velocity", value.velocity)?.into(),
+            orientation: value.orientation.map(Into::into).unwrap_or_default(),
+            battery: value.battery,
+            signal: value.signal,
+            health: HealthStatus::to_shared("RobotState.health", value.health)?,
+            status: RobotStatus::to_shared("RobotState.status", value.status)?,
+            current_task: required("RobotState.current_task", value.current_task)?.try_into()?,
+            timestamp: crate::Timestamp::from_millis(value.timestamp),
+        })
+    }
+}
+
+// ============================================================================
+// ANOMALIES
+// ============================================================================
+
+proto_enum!(AnomalyType {
+    Leak = 1,
+    Corrosion = 2,
+    Crack = 3,
+    PressureDrop = 4,
+    TemperatureAnomaly = 5,
+    WallThinning = 6,
+    StructuralDamage = 7,
+    Unknown = 8,
+});
+
+proto_enum!(SeverityLevel {
+    Info = 1,
+    Low = 2,
+    Medium = 3,
+    High = 4,
+    Critical = 5,
+});
+
+proto_enum!(AnomalyStatus {
+    New = 1,
+    Acknowledged = 2,
+    Investigating = 3,
+    Resolved = 4,
+    FalsePositive = 5,
+});
+
+proto_enum!(NotificationUrgency {
+    Normal = 1,
+    Reduced = 2,
+});
+
+proto_enum!(TriageAction {
+    Dispatch = 1,
+    Monitor = 2,
+    Escalate = 3,
+    Dismiss = 4,
+});
+
+proto_enum!(AssignmentState {
+    Assigned = 1,
+    InProgress = 2,
+    Done = 3,
+});
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StatusChange {
+    #[prost(enumeration = "AnomalyStatus", tag = "1")]
+    pub status: i32,
+    #[prost(string, tag = "2")]
+    pub by: String,
+    #[prost(uint64, tag = "3")]
+    pub at: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TriageAudit {
+    #[prost(enumeration = "SeverityLevel", tag = "1")]
+    pub original_severity: i32,
+    #[prost(double, tag = "2")]
+    pub original_confidence: f64,
+    /// Absent if triage timed out
+    #[prost(enumeration = "TriageAction", optional, tag = "3")]
+    pub recommended_action: Option<i32>,
+    #[prost(string, tag = "4")]
+    pub rationale: String,
+    #[prost(bool, tag = "5")]
+    pub timed_out: bool,
+    #[prost(uint64, tag = "6")]
+    pub completed_at: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CorrelatedCommand {
+    #[prost(string, tag = "1")]
+    pub command_id: String,
+    #[prost(string, tag = "2")]
+    pub variant: String,
+    #[prost(string, tag = "3")]
+    pub issued_by: String,
+    #[prost(double, tag = "4")]
+    pub seconds_before: f64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Measurement {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(double, tag = "2")]
+    pub value: f64,
+    #[prost(string, tag = "3")]
+    pub unit: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Assignment {
+    #[prost(string, tag = "1")]
+    pub assignee: String,
+    #[prost(string, tag = "2")]
+    pub assigned_by: String,
+    #[prost(uint64, tag = "3")]
+    pub assigned_at: u64,
+    #[prost(uint64, tag = "4")]
+    pub due_at: u64,
+    #[prost(enumeration = "AssignmentState", tag = "5")]
+    pub state: i32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct AnomalyReport {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(enumeration = "AnomalyType", tag = "2")]
+    pub anomaly_type: i32,
+    #[prost(enumeration = "SeverityLevel", tag = "3")]
+    pub severity: i32,
+    #[prost(message, optional, tag = "4")]
+    pub position: Option<Position>,
+    #[prost(string, tag = "5")]
+    pub section_id: String,
+    #[prost(string, tag = "6")]
+    pub detected_by: String,
+    #[prost(double, tag = "7")]
+    pub confidence: f64,
+    #[prost(string, tag = "8")]
+    pub description: String,
+    #[prost(uint64, tag = "9")]
+    pub timestamp: u64,
+    #[prost(bool, tag = "10")]
+    pub acknowledged: bool,
+    /// New when unspecified
+    #[prost(enumeration = "AnomalyStatus", tag = "11")]
+    pub status: i32,
+    #[prost(message, repeated, tag = "12")]
+    pub status_history: Vec<StatusChange>,
+    #[prost(string, optional, tag = "13")]
+    pub acknowledged_by: Option<String>,
+    #[prost(string, optional, tag = "14")]
+    pub resolved_by: Option<String>,
+    #[prost(message, optional, tag = "15")]
+    pub triage: Option<TriageAudit>,
+    #[prost(message, repeated, tag = "16")]
+    pub correlated_commands: Vec<CorrelatedCommand>,
+    /// Normal when unspecified
+    #[prost(enumeration = "NotificationUrgency", tag = "17")]
+    pub urgency: i32,
+    #[prost(message, optional, tag = "18")]
+    pub measurement: Option<Measurement>,
+    #[prost(message, optional, tag = "19")]
+    pub assignment: Option<Assignment>,
+    /// 1 when absent
+    #[prost(uint32, optional, tag = "20")]
+    pub occurrence_count: Option<u32>,
+    #[prost(uint64, optional, tag = "21")]
+    pub last_seen: Option<u64>,
+    #[prost(uint32, tag = "22")]
+    pub escalation_count: u32,
+}
+
+impl From<&crate::AnomalyReport> for AnomalyReport {
+    fn from(value: &crate::AnomalyReport) -> Self {
+        Self {
+            id: value.id.clone(),
+            anomaly_type: wire::<AnomalyType, _>(value.anomaly_type),
+            severity: wire::<SeverityLevel, _>(value.severity),
+            position: Some(value.position.into()),
+            section_id: value.section_id.clone(),
+            detected_by: value.detected_by.clone(),
+            confidence: value.confidence,
+            description: value.description.clone(),
+            timestamp: value.timestamp.as_millis(),
+            acknowledged: value.acknowledged,
+            status: wire::<AnomalyStatus, _>(value.status),
+            status_history: value
+                .status_history
+                .iter()
+                .map(|change| StatusChange {
+                    status: wire::<AnomalyStatus, _>(change.status),
+                    by: change.by.clone(),
+                    at: change.at,
+                })
+                .collect(),
+            acknowledged_by: value.acknowledged_by.clone(),
+            resolved_by: value.resolved_by.clone(),
+            triage: value.triage.as_ref().map(|triage| TriageAudit {
+                original_severity: wire::<SeverityLevel, _>(triage.original_severity),
+                original_confidence: triage.original_confidence,
+                recommended_action: triage.recommended_action.map(wire::<TriageAction, _>),
+                rationale: triage.rationale.clone(),
+                timed_out: triage.timed_out,
+                completed_at: triage.completed_at,
+            }),
+            correlated_commands: value
+                .correlated_commands
+                .iter()
+                .map(|command| CorrelatedCommand {
+                    command_id: command.command_id.clone(),
+                    variant: command.variant.clone(),
+                    issued_by: command.issued_by.clone(),
+                    seconds_before: command.seconds_before,
+                })
+                .collect(),
+            urgency: wire::<NotificationUrgency, _>(value.urgency),
+            measurement: value.measurement.as_ref().map(|measurement| Measurement {
+                name: measurement.name.clone(),
+                value: measurement.value,
+                unit: measurement.unit.clone(),
+            }),
+            assignment: value.assignment.as_ref().map(|assignment| Assignment {
+                assignee: assignment.assignee.clone(),
+                assigned_by: assignment.assigned_by.clone(),
+                assigned_at: assignment.assigned_at,
+                due_at: assignment.due_at,
+                state: wire::<AssignmentState, _>(assignment.state),
+            }),
+            occurrence_count: Some(value.occurrence_count),
+            last_seen: value.last_seen,
+            escalation_count: value.escalation_count,
+        }
+    }
+}
+
+impl TryFrom<AnomalyReport> for crate::AnomalyReport {
+    type Error = ProtoError;
+
+    fn try_from(value: AnomalyReport) -> Result<Self, ProtoError> {
+        let status_history = value
+            .status_history
+            .into_iter()
+            .map(|change| {
+                Ok(crate::StatusChange {
+                    status: AnomalyStatus::to_shared("StatusChange.status", change.status)?,
+                    by: change.by,
+                    at: change.at,
+                })
+            })
+            .collect::<Result<_, ProtoError>>()?;
+        let triage = value
+            .triage
+            .map(|triage| {
+                Ok::<_, ProtoError>(crate::TriageAudit {
+                    original_severity: SeverityLevel::to_shared(
+                        "TriageAudit.original_severity",
+                        triage.original_severity,
+                    )?,
+                    original_confidence: triage.original_confidence,
+                    recommended_action: triage
+                        .recommended_action
+                        .map(|action| {
+                            TriageAction::to_shared("TriageAudit.recommended_action", action)
+                        })
+                        .transpose()?,
+                    rationale: triage.rationale,
+                    timed_out: triage.timed_out,
+                    completed_at: triage.completed_at,
+                })
+            })
+            .transpose()?;
+        let assignment = value
+            .assignment
+            .map(|assignment| {
+                Ok::<_, ProtoError>(crate::Assignment {
+                    assignee: assignment.assignee,
+                    assigned_by: assignment.assigned_by,
+                    assigned_at: assignment.assigned_at,
+                    due_at: assignment.due_at,
+                    state: AssignmentState::to_shared("Assignment.state", assignment.state)?,
+                })
+            })
+            .transpose()?;
+        Ok(Self {
+            id: value.id,
+            anomaly_type: AnomalyType::to_shared("AnomalyReport.anomaly_type", value.anomaly_type)?,
+            severity: SeverityLevel::to_shared("AnomalyReport.severity", value.severity)?,
+            position: required("AnomalyReport.position", value.position)?.into(),
+            section_id: value.section_id,
+            detected_by: value.detected_by,
+            confidence: value.confidence,
+            description: value.description,
+            timestamp: crate::Timestamp::from_millis(value.timestamp),
+            acknowledged: value.acknowledged,
+            status: defaulted(value.status, |status| {
+                AnomalyStatus::to_shared("AnomalyReport.status", status)
+            })?,
+            status_history,
+            acknowledged_by: value.acknowledged_by,
+            resolved_by: value.resolved_by,
+            triage,
+            correlated_commands: value
+                .correlated_commands
+                .into_iter()
+                .map(|command| crate::CorrelatedCommand {
+                    command_id: command.command_id,
+                    variant: command.variant,
+                    issued_by: command.issued_by,
+                    seconds_before: command.seconds_before,
+                })
+                .collect(),
+            urgency: defaulted(value.urgency, |urgency| {
+                NotificationUrgency::to_shared("AnomalyReport.urgency", urgency)
+            })?,
+            measurement: value.measurement.map(|measurement| crate::Measurement {
+                name: measurement.name,
+                value: measurement.value,
+                unit: measurement.unit,
+            }),
+            assignment,
+            occurrence_count: value
+                .occurrence_count
+                .unwrap_or_else(crate::first_occurrence),
+            last_seen: value.last_seen,
+            escalation_count: value.escalation_count,
+        })
+    }
+}
+
+// ============================================================================
+// COMMANDS
+// ============================================================================
+
+proto_enum!(FaultType {
+    LowBattery = 1,
+    SensorFailure = 2,
+    CommDropout = 3,
+    MotorFailure = 4,
+    GpsDrift = 5,
+});
+
+proto_enum!(OperationKind {
+    Movement = 1,
+    Scan = 2,
+    Patrol = 3,
+    Investigation = 4,
+    FaultInjection = 5,
+    Configuration = 6,
+    DroneFlight = 7,
+});
+
+proto_enum!(Resolution {
+    Fixed = 1,
+    FalsePositive = 2,
+});
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ZoneMode {
+    #[prost(oneof = "zone_mode::Mode", tags = "1, 2, 3")]
+    pub mode: Option<zone_mode::Mode>,
+}
+
+pub mod zone_mode {
+    use alloc::vec::Vec;
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Restricted {
+        #[prost(enumeration = "super::OperationKind", repeated, tag = "1")]
+        pub disallowed: Vec<i32>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Mode {
+        #[prost(message, tag = "1")]
+        Normal(super::Empty),
+        #[prost(message, tag = "2")]
+        Restricted(Restricted),
+        #[prost(message, tag = "3")]
+        Excluded(super::Empty),
+    }
+}
+
+impl From<&crate::ZoneMode> for ZoneMode {
+    fn from(value: &crate::ZoneMode) -> Self {
+        use zone_mode::{Mode, Restricted};
+        let mode = match value {
+            crate::ZoneMode::Normal => Mode::Normal(Empty {}),
+            crate::ZoneMode::Restricted { disallowed } => Mode::Restricted(Restricted {
+                disallowed: disallowed
+                    .iter()
+                    .map(|&operation| wire::<OperationKind, _>(operation))
+                    .collect(),
+            }),
+            crate::ZoneMode::Excluded => Mode::Excluded(Empty {}),
+        };
+        Self { mode: Some(mode) }
+    }
+}
+
+impl TryFrom<ZoneMode> for crate::ZoneMode {
+    type Error = ProtoError;
+
+    fn try_from(value: ZoneMode) -> Result<Self, ProtoError> {
+        use zone_mode::Mode;
+        Ok(match required("ZoneMode.mode", value.mode)? {
+            Mode::Normal(_) => Self::Normal,
+            Mode::Restricted(restricted) => Self::Restricted {
+                disallowed: restricted
+                    .disallowed
+                    .into_iter()
+                    .map(|operation| OperationKind::to_shared("Restricted.disallowed", operation))
+                    .collect::<Result<_, _>>()?,
+            },
+            Mode::Excluded(_) => Self::Excluded,
+        })
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RobotConfig {
+    #[prost(double, optional, tag = "1")]
+    pub max_speed: Option<f64>,
+    #[prost(uint32, optional, tag = "2")]
+    pub scan_interval: Option<u32>,
+    #[prost(uint32, optional, tag = "3")]
+    pub heartbeat_interval: Option<u32>,
+    #[prost(double, optional, tag = "4")]
+    pub low_battery_threshold: Option<f64>,
+    #[prost(message, optional, tag = "5")]
+    pub supported_scans: Option<robot_config::ScanTypes>,
+    #[prost(message, optional, tag = "6")]
+    pub operating_altitude: Option<AltitudeRange>,
+}
+
+pub mod robot_config {
+    use alloc::vec::Vec;
+
+    /// Scans replacing those of the robot's type; absent keeps them
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ScanTypes {
+        #[prost(enumeration = "super::ScanType", repeated, tag = "1")]
+        pub scans: Vec<i32>,
+    }
+}
+
+impl From<&crate::RobotConfig> for RobotConfig {
+    fn from(value: &crate::RobotConfig) -> Self {
+        Self {
+            max_speed: value.max_speed,
+            scan_interval: value.scan_interval,
+            heartbeat_interval: value.heartbeat_interval,
+            low_battery_threshold: value.low_battery_threshold,
+            supported_scans: value
+                .supported_scans
+                .as_ref()
+                .map(|scans| robot_config::ScanTypes {
+                    scans: scans
+                        .iter()
+                        .map(|&scan| wire::<ScanType, _>(scan))
+                        .collect(),
+                }),
+            operating_altitude: value.operating_altitude.map(Into::into),
+        }
+    }
+}
+
+impl TryFrom<RobotConfig> for crate::RobotConfig {
+    type Error = ProtoError;
+
+    fn try_from(value: RobotConfig) -> Result<Self, ProtoError> {
+        Ok(Self {
+            max_speed: value.max_speed,
+            scan_interval: value.scan_interval,
+            heartbeat_interval: value.heartbeat_interval,
+            low_battery_threshold: value.low_battery_threshold,
+            supported_scans: value
+                .supported_scans
+                .map(|supported| {
+                    supported
+                        .scans
+                        .into_iter()
+                        .map(|scan| ScanType::to_shared("RobotConfig.supported_scans", scan))
+                        .collect()
+                })
+                .transpose()?,
+            operating_altitude: value.operating_altitude.map(Into::into),
+        })
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FailurePolicy {
+    #[prost(oneof = "failure_policy::Policy", tags = "1, 2, 3")]
+    pub policy: Option<failure_policy::Policy>,
+}
+
+pub mod failure_policy {
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Policy {
+        #[prost(message, tag = "1")]
+        Abort(super::Empty),
+        #[prost(message, tag = "2")]
+        Continue(super::Empty),
+        /// Further attempts before aborting
+        #[prost(uint32, tag = "3")]
+        Retry(u32),
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Precondition {
+    #[prost(oneof = "precondition::Condition", tags = "1, 2, 3")]
+    pub condition: Option<precondition::Condition>,
+}
+
+pub mod precondition {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Near {
+        #[prost(message, optional, tag = "1")]
+        pub position: Option<super::Position>,
+        #[prost(double, tag = "2")]
+        pub radius: f64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Condition {
+        /// Percent
+        #[prost(double, tag = "1")]
+        MinBattery(f64),
+        #[prost(enumeration = "super::RobotStatus", tag = "2")]
+        Status(i32),
+        #[prost(message, tag = "3")]
+        Near(Near),
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MissionStep {
+    #[prost(message, optional, tag = "1")]
+    pub command: Option<Command>,
+    #[prost(uint64, tag = "2")]
+    pub timeout_secs: u64,
+    /// Abort when absent
+    #[prost(message, optional, tag = "3")]
+    pub on_failure: Option<FailurePolicy>,
+    #[prost(message, repeated, tag = "4")]
+    pub preconditions: Vec<Precondition>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MissionPlan {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "2")]
+    pub robot_id: String,
+    #[prost(message, repeated, tag = "3")]
+    pub steps: Vec<MissionStep>,
+}
+
+impl From<&crate::MissionPlan> for MissionPlan {
+    fn from(value: &crate::MissionPlan) -> Self {
+        use failure_policy::Policy;
+        use precondition::{Condition, Near};
+        let steps = value.steps.iter().map(|step| MissionStep {
+            command: Some((&step.command).into()),
+            timeout_secs: step.timeout_secs,
+            on_failure: Some(FailurePolicy {
+                policy: Some(match step.on_failure {
+                    crate::FailurePolicy::Abort => Policy::Abort(Empty {}),
+                    crate::FailurePolicy::Continue => Policy::Continue(Empty {}),
+                    crate::FailurePolicy::Retry(attempts) => Policy::Retry(attempts),
+                }),
+            }),
+            preconditions: step
+                .preconditions
+                .iter()
+                .map(|precondition| Precondition {
+                    condition: Some(match precondition {
+                        crate::Precondition::MinBattery(percent) => Condition::MinBattery(*percent),
+                        crate::Precondition::Status(status) => {
+                            Condition::Status(wire::<RobotStatus, _>(*status))
+                        }
+                        crate::Precondition::Near { position, radius } => Condition::Near(Near {
+                            position: Some((*position).into()),
+                            radius: *radius,
+                        }),
+                    }),
+                })
+                .collect(),
+        });
+        Self {
+            id: value.id.clone(),
+            robot_id: value.robot_id.clone(),
+            steps: steps.collect(),
+        }
+    }
+}
+
+impl TryFrom<MissionPlan> for crate::MissionPlan {
+    type Error = ProtoError;
+
+    fn try_from(value: MissionPlan) -> Result<Self, ProtoError> {
+        use failure_policy::Policy;
+        use precondition::Condition;
+        let step = |step: MissionStep| {
+            let on_failure = match step.on_failure.and_then(|policy| policy.policy) {
+                None | Some(Policy::Abort(_)) => crate::FailurePolicy::Abort,
+                Some(Policy::Continue(_)) => crate::FailurePolicy::Continue,
+                Some(Policy::Retry(attempts)) => crate::FailurePolicy::Retry(attempts),
+            };
+            let preconditions = step
+                .preconditions
+                .into_iter()
+                .map(|precondition| {
+                    Ok(
+                        match required("Precondition.condition", precondition.condition)? {
+                            Condition::MinBattery(percent) => {
+                                crate::Precondition::MinBattery(percent)
+                            }
+                            Condition::Status(status) => crate::Precondition::Status(
+                                RobotStatus::to_shared("Precondition.status", status)?,
+                            ),
+                            Condition::Near(near) => crate::Precondition::Near {
+                                position: required("Near.position", near.position)?.into(),
+                                radius: near.radius,
+                            },
+                        },
+                    )
+                })
+                .collect::<Result<_, ProtoError>>()?;
+            Ok(crate::MissionStep {
+                command: required("MissionStep.command", step.command)?.try_into()?,
+                timeout_secs: step.timeout_secs,
+                on_failure,
+                preconditions,
+            })
+        };
+        Ok(Self {
+            id: value.id,
+            robot_id: value.robot_id,
+            steps: value
+                .steps
+                .into_iter()
+                .map(step)
+                .collect::<Result<_, ProtoError>>()?,
+        })
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Command {
+    #[prost(
+        oneof = "command::Kind",
+        tags = "1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23"
+    )]
+    pub kind: Option<command::Kind>,
+}
+
+pub mod command {
+    use alloc::string::String;
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct MoveTo {
+        #[prost(message, optional, tag = "1")]
+        pub target: Option<super::Position>,
+        #[prost(double, optional, tag = "2")]
+        pub speed: Option<f64>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct PerformScan {
+        #[prost(enumeration = "super::ScanType", tag = "1")]
+        pub scan_type: i32,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct StartPatrol {
+        #[prost(string, tag = "1")]
+        pub route_id: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Investigate {
+        #[prost(string, tag = "1")]
+        pub anomaly_id: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct InjectFault {
+        #[prost(enumeration = "super::FaultType", tag = "1")]
+        pub fault_type: i32,
+    }
+
+    /// Every fault when `fault_type` is absent
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ClearFault {
+        #[prost(enumeration = "super::FaultType", optional, tag = "1")]
+        pub fault_type: Option<i32>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct InjectLeak {
+        #[prost(string, tag = "1")]
+        pub section_id: String,
+        #[prost(enumeration = "super::SeverityLevel", tag = "2")]
+        pub severity: i32,
+    }
+
+    /// Every leak when `section_id` is absent
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ClearLeak {
+        #[prost(string, optional, tag = "1")]
+        pub section_id: Option<String>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Configure {
+        #[prost(message, optional, tag = "1")]
+        pub config: Option<super::RobotConfig>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct RegisterSection {
+        #[prost(string, tag = "1")]
+        pub section_id: String,
+        #[prost(message, optional, tag = "2")]
+        pub position: Option<super::Position>,
+        #[prost(string, optional, tag = "3")]
+        pub merge_from: Option<String>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct SetZoneMode {
+        #[prost(string, tag = "1")]
+        pub zone_id: String,
+        #[prost(message, optional, tag = "2")]
+        pub mode: Option<super::ZoneMode>,
+        #[prost(uint64, optional, tag = "3")]
+        pub until: Option<u64>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct AssignAnomaly {
+        #[prost(string, tag = "1")]
+        pub anomaly_id: String,
+        #[prost(string, tag = "2")]
+        pub assignee: String,
+        #[prost(uint64, tag = "3")]
+        pub due_at: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct UpdateAssignment {
+        #[prost(string, tag = "1")]
+        pub anomaly_id: String,
+        #[prost(enumeration = "super::AssignmentState", tag = "2")]
+        pub state: i32,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct AcknowledgeAnomaly {
+        #[prost(string, tag = "1")]
+        pub anomaly_id: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ResolveAnomaly {
+        #[prost(string, tag = "1")]
+        pub anomaly_id: String,
+        /// Fixed when unspecified
+        #[prost(enumeration = "super::Resolution", tag = "2")]
+        pub resolution: i32,
+        #[prost(bool, tag = "3")]
+        pub force: bool,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct StartMission {
+        #[prost(message, optional, tag = "1")]
+        pub plan: Option<super::MissionPlan>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct AbortMission {
+        #[prost(string, tag = "1")]
+        pub mission_id: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Kind {
+        #[prost(message, tag = "1")]
+        MoveTo(MoveTo),
+        #[prost(message, tag = "2")]
+        Stop(super::Empty),
+        #[prost(message, tag = "3")]
+        PerformScan(PerformScan),
+        #[prost(message, tag = "4")]
+        StartPatrol(StartPatrol),
+        #[prost(message, tag = "5")]
+        ReturnToBase(super::Empty),
+        #[prost(message, tag = "6")]
+        Investigate(Investigate),
+        #[prost(message, tag = "7")]
+        EmergencyStop(super::Empty),
+        #[prost(message, tag = "8")]
+        InjectFault(InjectFault),
+        #[prost(message, tag = "9")]
+        ClearFault(ClearFault),
+        #[prost(message, tag = "10")]
+        InjectLeak(InjectLeak),
+        #[prost(message, tag = "11")]
+        ClearLeak(ClearLeak),
+        #[prost(message, tag = "12")]
+        Configure(Configure),
+        #[prost(message, tag = "13")]
+        GetConfig(super::Empty),
+        #[prost(message, tag = "14")]
+        RegisterSection(RegisterSection),
+        #[prost(message, tag = "15")]
+        SetZoneMode(SetZoneMode),
+        #[prost(message, tag = "16")]
+        AssignAnomaly(AssignAnomaly),
+        #[prost(message, tag = "17")]
+        UpdateAssignment(UpdateAssignment),
+        #[prost(message, tag = "18")]
+        AcknowledgeAnomaly(AcknowledgeAnomaly),
+        #[prost(message, tag = "19")]
+        ResolveAnomaly(ResolveAnomaly),
+        #[prost(message, tag = "20")]
+        RequestKeyframe(super::Empty),
+        #[prost(message, tag = "21")]
+        StartMission(StartMission),
+        #[prost(message, tag = "22")]
+        AbortMission(AbortMission),
+        #[prost(message, tag = "23")]
+        CorrectPosition(super::Empty),
+    }
+}
+
+impl From<&crate::Command> for Command {
+    fn from(value: &crate::Command) -> Self {
+        use command::*;
+        let kind = match value {
+            crate::Command::MoveTo { target, speed } => Kind::MoveTo(MoveTo {
+                target: Some((*target).into()),
+                speed: *speed,
+            }),
+            crate::Command::Stop => Kind::Stop(Empty {}),
+            crate::Command::PerformScan { scan_type } => Kind::PerformScan(PerformScan {
+                scan_type: wire::<ScanType, _>(*scan_type),
+            }),
+            crate::Command::StartPatrol { route_id } => Kind::StartPatrol(StartPatrol {
+                route_id: route_id.clone(),
+            }),
+            crate::Command::ReturnToBase => Kind::ReturnToBase(Empty {}),
+            crate::Command::Investigate { anomaly_id } => Kind::Investigate(Investigate {
+                anomaly_id: anomaly_id.clone(),
+            }),
+            crate::Command::EmergencyStop => Kind::EmergencyStop(Empty {}),
+            crate::Command::InjectFault { fault_type } => Kind::InjectFault(InjectFault {
+                fault_type: wire::<FaultType, _>(*fault_type),
+            }),
+            crate::Command::ClearFault { fault_type } => Kind::ClearFault(ClearFault {
+                fault_type: fault_type.map(wire::<FaultType, _>),
+            }),
+            crate::Command::InjectLeak {
+                section_id,
+                severity,
+            } => Kind::InjectLeak(InjectLeak {
+                section_id: section_id.clone(),
+                severity: wire::<SeverityLevel, _>(*severity),
+            }),
+            crate::Command::ClearLeak { section_id } => Kind::ClearLeak(ClearLeak {
+                section_id: section_id.clone(),
+            }),
+            crate::Command::Configure { config } => Kind::Configure(Configure {
+                config: Some(config.into()),
+            }),
+            crate::Command::GetConfig => Kind::GetConfig(Empty {}),
+            crate::Command::RegisterSection {
+                section_id,
+                position,
+                merge_from,
+            } => Kind::RegisterSection(RegisterSection {
+                section_id: section_id.clone(),
+                position: Some((*position).into()),
+                merge_from: merge_from.clone(),
+            }),
+            crate::Command::SetZoneMode {
+                zone_id,
+                mode,
+                until,
+            } => Kind::SetZoneMode(SetZoneMode {
+                zone_id: zone_id.clone(),
+                mode: Some(mode.into()),
+                until: *until,
+            }),
+            crate::Command::AssignAnomaly {
+                anomaly_id,
+                assignee,
+                due_at,
+            } => Kind::AssignAnomaly(AssignAnomaly {
+                anomaly_id: anomaly_id.clone(),
+                assignee: assignee.clone(),
+                due_at: *due_at,
+            }),
+            crate::Command::UpdateAssignment { anomaly_id, state } => {
+                Kind::UpdateAssignment(UpdateAssignment {
+                    anomaly_id: anomaly_id.clone(),
+                    state: wire::<AssignmentState, _>(*state),
+                })
+            }
+            crate::Command::AcknowledgeAnomaly { anomaly_id } => {
+                Kind::AcknowledgeAnomaly(AcknowledgeAnomaly {
+                    anomaly_id: anomaly_id.clone(),
+                })
+            }
+            crate::Command::ResolveAnomaly {
+                anomaly_id,
+                resolution,
+                force,
+            } => Kind::ResolveAnomaly(ResolveAnomaly {
+                anomaly_id: anomaly_id.clone(),
+                resolution: wire::<Resolution, _>(*resolution),
+                force: *force,
+            }),
+            crate::Command::RequestKeyframe => Kind::RequestKeyframe(Empty {}),
+            crate::Command::StartMission { plan } => Kind::StartMission(StartMission {
+                plan: Some(plan.into()),
+            }),
+            crate::Command::AbortMission { mission_id } => Kind::AbortMission(AbortMission {
+                mission_id: mission_id.clone(),
+            }),
+            crate::Command::CorrectPosition => Kind::CorrectPosition(Empty {}),
+        };
+        Self { kind: Some(kind) }
+    }
+}
+
+impl TryFrom<Command> for crate::Command {
+    type Error = ProtoError;
+
+    fn try_from(value: Command) -> Result<Self, ProtoError> {
+        use command::*;
+        Ok(match required("Command.kind", value.kind)? {
+            Kind::MoveTo(MoveTo { target, speed }) => Self::MoveTo {
+                target: required("MoveTo.target", target)?.into(),
+                speed,
+            },
+            Kind::Stop(_) => Self::Stop,
+            Kind::PerformScan(PerformScan { scan_type }) => Self::PerformScan {
+                scan_type: ScanType::to_shared("PerformScan.scan_type", scan_type)?,
+            },
+            Kind::StartPatrol(StartPatrol { route_id }) => Self::StartPatrol { route_id },
+            Kind::ReturnToBase(_) => Self::ReturnToBase,
+            Kind::Investigate(Investigate { anomaly_id }) => Self::Investigate { anomaly_id },
+            Kind::EmergencyStop(_) => Self::EmergencyStop,
+            Kind::InjectFault(InjectFault { fault_type }) => Self::InjectFault {
+                fault_type: FaultType::to_shared("InjectFault.fault_type", fault_type)?,
+            },
+            Kind::ClearFault(ClearFault { fault_type }) => Self::ClearFault {
+                fault_type: fault_type
+                    .map(|fault_type| FaultType::to_shared("ClearFault.fault_type", fault_type))
+                    .transpose()?,
+            },
+            Kind::InjectLeak(InjectLeak {
+                section_id,
+                severity,
+            }) => Self::InjectLeak {
+                section_id,
+                severity: SeverityLevel::to_shared("InjectLeak.severity", severity)?,
+            },
+            Kind::ClearLeak(ClearLeak { section_id }) => Self::ClearLeak { section_id },
+            Kind::Configure(Configure { config }) => Self::Configure {
+                config: required("Configure.config", config)?.try_into()?,
+            },
+            Kind::GetConfig(_) => Self::GetConfig,
+            Kind::RegisterSection(RegisterSection {
+                section_id,
+                position,
+                merge_from,
+            }) => Self::RegisterSection {
+                section_id,
+                position: required("RegisterSection.position", position)?.into(),
+                merge_from,
+            },
+            Kind::SetZoneMode(SetZoneMode {
+                zone_id,
+                mode,
+                until,
+            }) => Self::SetZoneMode {
+                zone_id,
+                mode: required("SetZoneMode.mode", mode)?.try_into()?,
+                until,
+            },
+            Kind::AssignAnomaly(AssignAnomaly {
+                anomaly_id,
+                assignee,
+                due_at,
+            }) => Self::AssignAnomaly {
+                anomaly_id,
+                assignee,
+                due_at,
+            },
+            Kind::UpdateAssignment(UpdateAssignment { anomaly_id, state }) => {
+                Self::UpdateAssignment {
+                    anomaly_id,
+                    state: AssignmentState::to_shared("UpdateAssignment.state", state)?,
+                }
+            }
+            Kind::AcknowledgeAnomaly(AcknowledgeAnomaly { anomaly_id }) => {
+                Self::AcknowledgeAnomaly { anomaly_id }
+            }
+            Kind::ResolveAnomaly(ResolveAnomaly {
+                anomaly_id,
+                resolution,
+                force,
+            }) => Self::ResolveAnomaly {
+                anomaly_id,
+                resolution: defaulted(resolution, |resolution| {
+                    Resolution::to_shared("ResolveAnomaly.resolution", resolution)
+                })?,
+                force,
+            },
+            Kind::RequestKeyframe(_) => Self::RequestKeyframe,
+            Kind::StartMission(StartMission { plan }) => Self::StartMission {
+                plan: required("StartMission.plan", plan)?.try_into()?,
+            },
+            Kind::AbortMission(AbortMission { mission_id }) => Self::AbortMission { mission_id },
+            Kind::CorrectPosition(_) => Self::CorrectPosition,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost::Message;
+
+    fn report() -> crate::AnomalyReport {
+        crate::AnomalyReport::new_at(
+            crate::AnomalyType::Leak,
+            crate::SeverityLevel::High,
+            crate::Position::new(1.0, 2.0, 0.0),
+            "SEC-001",
+            "RV-001",
+            0.9,
+            "Hydrogen above threshold",
+            crate::Timestamp::from_millis(1_700_000_000_000),
+        )
+    }
+
+    #[test]
+    fn test_defaulted_fields_may_be_left_out() {
+        let report = report();
+        let mut message = AnomalyReport::from(&report);
+        message.status = AnomalyStatus::Unspecified as i32;
+        message.urgency = NotificationUrgency::Unspecified as i32;
+        message.occurrence_count = None;
+        let decoded = AnomalyReport::decode(&message.encode_to_vec()[..]).unwrap();
+        assert_eq!(crate::AnomalyReport::try_from(decoded).unwrap(), report);
+    }
+
+    #[test]
+    fn test_required_fields_and_unknown_values_are_refused() {
+        let mut message = AnomalyReport::from(&report());
+        message.position = None;
+        assert_eq!(
+            crate::AnomalyReport::try_from(message).unwrap_err(),
+            ProtoError::Missing("AnomalyReport.position")
+        );
+
+        let mut message = AnomalyReport::from(&report());
+        message.severity = 42;
+        assert_eq!(
+            crate::AnomalyReport::try_from(message).unwrap_err(),
+            ProtoError::UnknownValue {
+                field: "AnomalyReport.severity",
+                value: 42
+            }
+        );
+
+        let command = Command {
+            kind: Some(command::Kind::PerformScan(command::PerformScan {
+                scan_type: ScanType::Unspecified as i32,
+            })),
+        };
+        assert!(matches!(
+            crate::Command::try_from(command),
+            Err(ProtoError::UnknownValue { value: 0, .. })
+        ));
+        assert_eq!(
+            crate::Command::try_from(Command::default()).unwrap_err(),
+            ProtoError::Missing("Command.kind")
+        );
+
+        let mut state = RobotState::from(&crate::RobotState::new_at(
+            crate::RobotId::parse("RV-001").unwrap(),
+            "Rover Alpha",
+            crate::RobotType::Rover,
+            crate::Timestamp::from_millis(0),
+        ));
+        state.id = "rover".into();
+        assert!(matches!(
+            crate::RobotState::try_from(state),
+            Err(ProtoError::RobotId(_))
+        ));
+    }
+}
@@ -0,0 +1,570 @@
+//! Property-based round trips of the messages that arrive off the network
+//!
+//! Arbitrary robot states, commands, anomaly reports, environment readings
+//! and heartbeats, with every enum variant, extreme floats, and empty, long
+//! and non-ASCII strings, must come back unchanged from every [`Encoding`],
+//! bare and in an envelope.
+//!
+//! Floats are finite: JSON has no literal for NaN or infinity, and
+//! validation refuses them before anything reaches the wire.
+//!
+//! With the `protobuf` feature, robot states, commands and anomaly reports
+//! must also come back unchanged through their protobuf messages.
+
+use std::fmt::Debug;
+
+use proptest::prelude::*;
+use proptest::sample::select;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use aetheris_shared::{
+    AltitudeRange, AnomalyReport, AnomalyStatus, AnomalyType, Assignment, AssignmentState, Command,
+    CorrelatedCommand, CurrentTask, Encoding, FailurePolicy, FaultType, HealthStatus, Heartbeat,
+    Measurement, MissionPlan, MissionStep, MqttMessage, NotificationUrgency, OperationKind,
+    Orientation, PipeEnvironment, Position, Precondition, Resolution, RobotConfig, RobotId,
+    RobotState, RobotStatus, RobotType, ScanType, SeverityLevel, StatusChange, Timestamp,
+    TriageAction, TriageAudit, Velocity, ZoneMode,
+};
+
+// ============================================================================
+// STRATEGIES
+// ============================================================================
+
+/// Any finite float, with the extremes drawn more often than chance would
+fn float() -> impl Strategy<Value = f64> {
+    prop_oneof![
+        4 => prop::num::f64::NORMAL | prop::num::f64::SUBNORMAL | prop::num::f64::ZERO,
+        1 => select(vec![
+            f64::MAX,
+            f64::MIN,
+            f64::MIN_POSITIVE,
+            -f64::MIN_POSITIVE,
+            f64::EPSILON,
+            -0.0,
+            1e-308,
+            5e-324,
+        ]),
+    ]
+}
+
+/// Empty, short printable, arbitrary (control characters included) and long
+fn text() -> impl Strategy<Value = String> {
+    prop_oneof![
+        Just(String::new()),
+        "\\PC{1,24}",
+        any::<String>(),
+        "[a-zA-Z0-9 ]{1000,4000}",
+    ]
+}
+
+fn robot_id() -> impl Strategy<Value = RobotId> {
+    prop_oneof![
+        9 => "[A-Z]{2}-[0-9]{3}".prop_map(|id| RobotId::parse(id).unwrap()),
+        1 => Just(RobotId::engine()),
+    ]
+}
+
+fn timestamp() -> impl Strategy<Value = Timestamp> {
+    any::<u64>().prop_map(Timestamp::from_millis)
+}
+
+fn position() -> impl Strategy<Value = Position> {
+    (float(), float(), float()).prop_map(|(x, y, z)| Position { x, y, z })
+}
+
+fn current_task() -> impl Strategy<Value = CurrentTask> {
+    prop_oneof![
+        Just(CurrentTask::None),
+        text().prop_map(|route_id| CurrentTask::Patrolling { route_id }),
+        position().prop_map(|target| CurrentTask::MovingTo { target }),
+        select(ScanType::ALL.to_vec()).prop_map(|scan_type| CurrentTask::Scanning { scan_type }),
+        Just(CurrentTask::ReturningToBase),
+        text().prop_map(|anomaly_id| CurrentTask::Investigating { anomaly_id }),
+    ]
+}
+
+fn robot_state() -> impl Strategy<Value = RobotState> {
+    (
+        (robot_id(), text(), select(RobotType::ALL.to_vec())),
+        (
+            position(),
+            (float(), float(), float()),
+            (float(), float(), float()),
+        ),
+        (float(), float()),
+        (
+            select(HealthStatus::ALL.to_vec()),
+            select(RobotStatus::ALL.to_vec()),
+            current_task(),
+            timestamp(),
+        ),
+    )
+        .prop_map(
+            |(
+                (id, name, robot_type),
+                (position, (vx, vy, vz), (yaw, pitch, roll)),
+                (battery, signal),
+                (health, status, current_task, timestamp),
+            )| RobotState {
+                id,
+                name,
+                robot_type,
+                position,
+                velocity: Velocity { vx, vy, vz },
+                orientation: Orientation { yaw, pitch, roll },
+                battery,
+                signal,
+                health,
+                status,
+                current_task,
+                timestamp,
+            },
+        )
+}
+
+fn robot_config() -> impl Strategy<Value = RobotConfig> {
+    (
+        proptest::option::of(float()),
+        proptest::option::of(any::<u32>()),
+        proptest::option::of(any::<u32>()),
+        proptest::option::of(float()),
+        proptest::option::of(prop::collection::vec(select(ScanType::ALL.to_vec()), 0..5)),
+        proptest::option::of((float(), float()).prop_map(|(min, max)| AltitudeRange { min, max })),
+    )
+        .prop_map(
+            |(
+                max_speed,
+                scan_interval,
+                heartbeat_interval,
+                low_battery_threshold,
+                supported_scans,
+                operating_altitude,
+            )| RobotConfig {
+                max_speed,
+                scan_interval,
+                heartbeat_interval,
+                low_battery_threshold,
+                supported_scans,
+                operating_altitude,
+            },
+        )
+}
+
+fn zone_mode() -> impl Strategy<Value = ZoneMode> {
+    prop_oneof![
+        Just(ZoneMode::Normal),
+        prop::collection::vec(select(OperationKind::ALL.to_vec()), 0..4)
+            .prop_map(|disallowed| ZoneMode::Restricted { disallowed }),
+        Just(ZoneMode::Excluded),
+    ]
+}
+
+/// Every command variant but `StartMission`
+fn single_command() -> impl Strategy<Value = Command> {
+    prop_oneof![
+        (position(), proptest::option::of(float()))
+            .prop_map(|(target, speed)| Command::MoveTo { target, speed }),
+        Just(Command::Stop),
+        select(ScanType::ALL.to_vec()).prop_map(|scan_type| Command::PerformScan { scan_type }),
+        text().prop_map(|route_id| Command::StartPatrol { route_id }),
+        Just(Command::ReturnToBase),
+        text().prop_map(|anomaly_id| Command::Investigate { anomaly_id }),
+        Just(Command::EmergencyStop),
+        select(FaultType::ALL.to_vec()).prop_map(|fault_type| Command::InjectFault { fault_type }),
+        proptest::option::of(select(FaultType::ALL.to_vec()))
+            .prop_map(|fault_type| Command::ClearFault { fault_type }),
+        (text(), select(SeverityLevel::ALL.to_vec())).prop_map(|(section_id, severity)| {
+            Command::InjectLeak {
+                section_id,
+                severity,
+            }
+        }),
+        proptest::option::of(text()).prop_map(|section_id| Command::ClearLeak { section_id }),
+        robot_config().prop_map(|config| Command::Configure { config }),
+        Just(Command::GetConfig),
+        (text(), position(), proptest::option::of(text())).prop_map(
+            |(section_id, position, merge_from)| Command::RegisterSection {
+                section_id,
+                position,
+                merge_from,
+            }
+        ),
+        (text(), zone_mode(), proptest::option::of(any::<u64>())).prop_map(
+            |(zone_id, mode, until)| Command::SetZoneMode {
+                zone_id,
+                mode,
+                until
+            }
+        ),
+        (text(), text(), any::<u64>()).prop_map(|(anomaly_id, assignee, due_at)| {
+            Command::AssignAnomaly {
+                anomaly_id,
+                assignee,
+                due_at,
+            }
+        }),
+        (text(), select(AssignmentState::ALL.to_vec()))
+            .prop_map(|(anomaly_id, state)| Command::UpdateAssignment { anomaly_id, state }),
+        text().prop_map(|anomaly_id| Command::AcknowledgeAnomaly { anomaly_id }),
+        (text(), select(Resolution::ALL.to_vec()), any::<bool>()).prop_map(
+            |(anomaly_id, resolution, force)| Command::ResolveAnomaly {
+                anomaly_id,
+                resolution,
+                force,
+            }
+        ),
+        Just(Command::RequestKeyframe),
+        Just(Command::CorrectPosition),
+        text().prop_map(|mission_id| Command::AbortMission { mission_id }),
+    ]
+}
+
+fn failure_policy() -> impl Strategy<Value = FailurePolicy> {
+    prop_oneof![
+        Just(FailurePolicy::Abort),
+        Just(FailurePolicy::Continue),
+        any::<u32>().prop_map(FailurePolicy::Retry),
+    ]
+}
+
+fn precondition() -> impl Strategy<Value = Precondition> {
+    prop_oneof![
+        float().prop_map(Precondition::MinBattery),
+        select(RobotStatus::ALL.to_vec()).prop_map(Precondition::Status),
+        (position(), float())
+            .prop_map(|(position, radius)| Precondition::Near { position, radius }),
+    ]
+}
+
+/// Every command variant, missions made of single commands
+fn command() -> impl Strategy<Value = Command> {
+    let step = (
+        single_command(),
+        any::<u64>(),
+        failure_policy(),
+        prop::collection::vec(precondition(), 0..3),
+    )
+        .prop_map(
+            |(command, timeout_secs, on_failure, preconditions)| MissionStep {
+                command,
+                timeout_secs,
+                on_failure,
+                preconditions,
+            },
+        );
+    let mission =
+        (text(), text(), prop::collection::vec(step, 0..4)).prop_map(|(id, robot_id, steps)| {
+            Command::StartMission {
+                plan: MissionPlan {
+                    id,
+                    robot_id,
+                    steps,
+                },
+            }
+        });
+    prop_oneof![9 => single_command(), 1 => mission]
+}
+
+fn status_change() -> impl Strategy<Value = StatusChange> {
+    (select(AnomalyStatus::ALL.to_vec()), text(), any::<u64>())
+        .prop_map(|(status, by, at)| StatusChange { status, by, at })
+}
+
+fn triage_audit() -> impl Strategy<Value = TriageAudit> {
+    (
+        select(SeverityLevel::ALL.to_vec()),
+        float(),
+        proptest::option::of(select(TriageAction::ALL.to_vec())),
+        text(),
+        any::<bool>(),
+        any::<u64>(),
+    )
+        .prop_map(
+            |(
+                original_severity,
+                original_confidence,
+                recommended_action,
+                rationale,
+                timed_out,
+                completed_at,
+            )| TriageAudit {
+                original_severity,
+                original_confidence,
+                recommended_action,
+                rationale,
+                timed_out,
+                completed_at,
+            },
+        )
+}
+
+fn anomaly_report() -> impl Strategy<Value = AnomalyReport> {
+    let detection = (
+        (
+            text(),
+            select(AnomalyType::ALL.to_vec()),
+            select(SeverityLevel::ALL.to_vec()),
+        ),
+        (position(), text(), text()),
+        (float(), text(), timestamp()),
+    );
+    let lifecycle = (
+        (
+            any::<bool>(),
+            select(AnomalyStatus::ALL.to_vec()),
+            prop::collection::vec(status_change(), 0..3),
+        ),
+        (proptest::option::of(text()), proptest::option::of(text())),
+        proptest::option::of(triage_audit()),
+        prop::collection::vec(
+            (text(), text(), text(), float()).prop_map(
+                |(command_id, variant, issued_by, seconds_before)| CorrelatedCommand {
+                    command_id,
+                    variant,
+                    issued_by,
+                    seconds_before,
+                },
+            ),
+            0..3,
+        ),
+    );
+    let follow_up = (
+        select(NotificationUrgency::ALL.to_vec()),
+        proptest::option::of(
+            (text(), float(), text()).prop_map(|(name, value, unit)| Measurement {
+                name,
+                value,
+                unit,
+            }),
+        ),
+        proptest::option::of(
+            (
+                text(),
+                text(),
+                any::<u64>(),
+                any::<u64>(),
+                select(AssignmentState::ALL.to_vec()),
+            )
+                .prop_map(|(assignee, assigned_by, assigned_at, due_at, state)| {
+                    Assignment {
+                        assignee,
+                        assigned_by,
+                        assigned_at,
+                        due_at,
+                        state,
+                    }
+                }),
+        ),
+        (
+            any::<u32>(),
+            proptest::option::of(any::<u64>()),
+            any::<u32>(),
+        ),
+    );
+    (detection, lifecycle, follow_up).prop_map(
+        |(
+            (
+                (id, anomaly_type, severity),
+                (position, section_id, detected_by),
+                (confidence, description, timestamp),
+            ),
+            (
+                (acknowledged, status, status_history),
+                (acknowledged_by, resolved_by),
+                triage,
+                correlated_commands,
+            ),
+            (urgency, measurement, assignment, (occurrence_count, last_seen, escalation_count)),
+        )| AnomalyReport {
+            id,
+            anomaly_type,
+            severity,
+            position,
+            section_id,
+            detected_by,
+            confidence,
+            description,
+            timestamp,
+            acknowledged,
+            status,
+            status_history,
+            acknowledged_by,
+            resolved_by,
+            triage,
+            correlated_commands,
+            urgency,
+            measurement,
+            assignment,
+            occurrence_count,
+            last_seen,
+            escalation_count,
+        },
+    )
+}
+
+fn pipe_environment() -> impl Strategy<Value = PipeEnvironment> {
+    (
+        text(),
+        (float(), float(), float()),
+        (float(), float(), float()),
+        position(),
+        timestamp(),
+    )
+        .prop_map(
+            |(
+                section_id,
+                (pressure, temperature, h2_concentration),
+                (wall_thickness, flow_rate, humidity),
+                position,
+                timestamp,
+            )| PipeEnvironment {
+                section_id,
+                pressure,
+                temperature,
+                h2_concentration,
+                wall_thickness,
+                flow_rate,
+                humidity,
+                position,
+                timestamp,
+            },
+        )
+}
+
+fn heartbeat() -> impl Strategy<Value = Heartbeat> {
+    (
+        robot_id(),
+        select(RobotType::ALL.to_vec()),
+        select(RobotStatus::ALL.to_vec()),
+        float(),
+        float(),
+        any::<u64>(),
+        timestamp(),
+    )
+        .prop_map(
+            |(robot_id, robot_type, status, battery, signal, uptime, timestamp)| Heartbeat {
+                robot_id,
+                robot_type,
+                status,
+                battery,
+                signal,
+                uptime,
+                timestamp,
+            },
+        )
+}
+
+/// An envelope around `payload` with every optional field drawn too
+fn envelope<T: Debug>(payload: impl Strategy<Value = T>) -> impl Strategy<Value = MqttMessage<T>> {
+    (
+        payload,
+        text(),
+        timestamp(),
+        any::<u64>(),
+        any::<u16>(),
+        proptest::option::of(text()),
+        proptest::option::of("[0-9a-f]{64}"),
+        proptest::option::of(any::<u64>()),
+    )
+        .prop_map(
+            |(payload, source, timestamp, seq, version, command_id, signature, expires_at)| {
+                let mut msg = MqttMessage::new_at(payload, source, seq, timestamp);
+                msg.version = version;
+                msg.command_id = command_id;
+                msg.signature = signature;
+                msg.expires_at = expires_at;
+                msg
+            },
+        )
+}
+
+// ============================================================================
+// PROPERTIES
+// ============================================================================
+
+/// `value` decodes back to itself from every encoding
+fn assert_round_trips<T>(value: &T) -> Result<(), TestCaseError>
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    for encoding in Encoding::ALL {
+        let bytes = encoding
+            .encode(value)
+            .map_err(|e| TestCaseError::fail(format!("{encoding} encode: {e}")))?;
+        let decoded: T = encoding
+            .decode(&bytes)
+            .map_err(|e| TestCaseError::fail(format!("{encoding} decode: {e}")))?;
+        prop_assert_eq!(&decoded, value, "{} round trip", encoding);
+    }
+    Ok(())
+}
+
+/// `value` decodes back to itself through its protobuf message `P`
+#[cfg(feature = "protobuf")]
+fn assert_proto_round_trips<T, P>(value: &T) -> Result<(), TestCaseError>
+where
+    T: PartialEq + Debug + TryFrom<P, Error = aetheris_shared::proto::ProtoError>,
+    for<'a> P: From<&'a T> + prost::Message + Default,
+{
+    let bytes = P::from(value).encode_to_vec();
+    let message =
+        P::decode(&bytes[..]).map_err(|e| TestCaseError::fail(format!("protobuf decode: {e}")))?;
+    let decoded = T::try_from(message)
+        .map_err(|e| TestCaseError::fail(format!("protobuf conversion: {e}")))?;
+    prop_assert_eq!(&decoded, value, "protobuf round trip");
+    Ok(())
+}
+
+#[cfg(feature = "protobuf")]
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(128))]
+
+    #[test]
+    fn prop_robot_state_round_trips_through_protobuf(state in robot_state()) {
+        assert_proto_round_trips::<_, aetheris_shared::proto::RobotState>(&state)?;
+    }
+
+    #[test]
+    fn prop_command_round_trips_through_protobuf(command in command()) {
+        assert_proto_round_trips::<_, aetheris_shared::proto::Command>(&command)?;
+    }
+
+    #[test]
+    fn prop_anomaly_report_round_trips_through_protobuf(report in anomaly_report()) {
+        assert_proto_round_trips::<_, aetheris_shared::proto::AnomalyReport>(&report)?;
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(128))]
+
+    #[test]
+    fn prop_robot_state_round_trips(msg in envelope(robot_state())) {
+        assert_round_trips(&msg.payload)?;
+        assert_round_trips(&msg)?;
+    }
+
+    #[test]
+    fn prop_command_round_trips(msg in envelope(command())) {
+        assert_round_trips(&msg.payload)?;
+        assert_round_trips(&msg)?;
+    }
+
+    #[test]
+    fn prop_anomaly_report_round_trips(msg in envelope(anomaly_report())) {
+        assert_round_trips(&msg.payload)?;
+        assert_round_trips(&msg)?;
+    }
+
+    #[test]
+    fn prop_pipe_environment_round_trips(msg in envelope(pipe_environment())) {
+        assert_round_trips(&msg.payload)?;
+        assert_round_trips(&msg)?;
+    }
+
+    #[test]
+    fn prop_heartbeat_round_trips(heartbeat in heartbeat()) {
+        assert_round_trips(&heartbeat)?;
+    }
+}
@@ -0,0 +1,80 @@
+//! Schema version compatibility suite
+//!
+//! `tests/fixtures/v1` holds payloads exactly as version 1 publishers sent
+//! them, before fields such as `RobotState::orientation` or the envelope
+//! `version` existed. Unlike the wire fixtures they are frozen: they are
+//! never re-blessed, and must keep deserializing for as long as version 1
+//! is at least `MIN_SUPPORTED_VERSION`.
+
+use std::fs;
+use std::path::PathBuf;
+
+use serde::de::DeserializeOwned;
+
+use aetheris_shared::{
+    AnomalyReport, AnomalyStatus, Command, CommandResponse, EnvelopeVersion, Heartbeat,
+    MqttMessage, Orientation, PipeEnvironment, RobotState, is_supported_version,
+};
+
+fn load<T: DeserializeOwned>(name: &str) -> T {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/v1")
+        .join(format!("{name}.json"));
+    let text = fs::read_to_string(&path).unwrap();
+    serde_json::from_str(&text)
+        .unwrap_or_else(|e| panic!("v1 fixture {name} no longer deserializes: {e}"))
+}
+
+#[test]
+fn test_v1_payloads_still_deserialize() {
+    assert!(is_supported_version(1), "version 1 support was dropped");
+
+    let telemetry: MqttMessage<RobotState> = load("envelope_robot_state");
+    assert_eq!(telemetry.version, 1);
+    assert_eq!(telemetry.payload.id, "RV-001");
+    // Added after v1, defaulted
+    assert_eq!(telemetry.payload.orientation, Orientation::default());
+
+    let alert: MqttMessage<AnomalyReport> = load("envelope_anomaly_report");
+    assert_eq!(alert.version, 1);
+    assert_eq!(alert.payload.status, AnomalyStatus::New);
+    assert!(alert.payload.status_history.is_empty());
+
+    let command: MqttMessage<Command> = load("envelope_command");
+    assert_eq!(command.payload, Command::EmergencyStop);
+    assert_eq!(command.command_id, None);
+
+    let environment: MqttMessage<PipeEnvironment> = load("envelope_pipe_environment");
+    assert_eq!(environment.payload.section_id, "PIPE-001");
+
+    let heartbeat: Heartbeat = load("heartbeat");
+    assert_eq!(heartbeat.robot_id, "DR-001");
+    let response: CommandResponse = load("command_response");
+    assert_eq!(response.error.as_deref(), Some("unknown robot"));
+}
+
+#[test]
+fn test_unknown_fields_are_ignored() {
+    // A newer publisher that added fields without bumping the version
+    let payload = r#"{
+        "payload": {"robot_id": "RV-001", "robot_type": "rover", "status": "active",
+                    "battery": 80.0, "signal": 90.0, "uptime": 5, "timestamp": 1,
+                    "firmware": "2.4.1"},
+        "source": "RV-001", "timestamp": 1, "seq": 7, "version": 1,
+        "trace_id": "abc"
+    }"#;
+    let msg: MqttMessage<Heartbeat> = serde_json::from_str(payload).unwrap();
+    assert_eq!(msg.payload.battery, 80.0);
+}
+
+#[test]
+fn test_newer_versions_are_detected_before_the_payload() {
+    // The payload is unreadable, the version is not
+    let payload = r#"{"payload": {"renamed": true}, "version": 9}"#;
+    let probe: EnvelopeVersion = serde_json::from_str(payload).unwrap();
+    assert_eq!(probe.version, 9);
+    assert!(!is_supported_version(probe.version));
+
+    let unversioned: EnvelopeVersion = serde_json::from_str(r#"{"seq": 1}"#).unwrap();
+    assert_eq!(unversioned.version, 1);
+}
@@ -0,0 +1,1090 @@
+//! Wire compatibility regression suite
+//!
+//! Every public message type has a golden JSON fixture in `tests/fixtures/wire`
+//! captured from a canonical sample value. The suite checks that:
+//!
+//! - serializing the sample today reproduces the fixture byte-for-byte, and
+//! - every fixture still deserializes into the current types, and
+//! - the sample survives a round trip through every [`Encoding`].
+//!
+//! Intentional format changes go through `aetheris_shared::BREAKING_CHANGES`;
+//! see its documentation for the re-blessing procedure.
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use aetheris_shared::{
+    AltitudeRange, AnomalyReport, AnomalyStatus, AnomalyType, Assignment, AssignmentState,
+    BREAKING_CHANGES, BroadcastResult, CURRENT_VERSION, ChargingStation, Command, CommandResponse,
+    CommandStatus, CorrelatedCommand, CurrentTask, DeadLetter, DeadLetterReason, Encoding,
+    FailurePolicy, FaultType, FilteredTelemetry, FleetCount, Geofence, HealthFactor,
+    HealthFactorKind, HealthStatus, Heartbeat, LeaderClaim, LeaderStatus, LinkQuality, Measurement,
+    MissionPlan, MissionState, MissionStatus, MissionStep, MqttMessage, NearbyRobot,
+    NotificationUrgency, OperationKind, Operator, OperatorRole, Orientation, PatrolRoute,
+    PipeEnvironment, PipeMaterial, PipelineSection, Position, Precondition, RecordRef, RecordStore,
+    Resolution, RobotConfig, RobotState, RobotStateDelta, RobotStatus, RobotType, RobotView,
+    RouteLibrary, RouteMode, RouteRequest, ScanType, SectionHealthReport, SeverityLevel,
+    SystemStatus, TelemetryBatch, TelemetryPayload, TimelineEntry, TimelineEntryKind, Timestamp,
+    TriageAction, TriageAudit, TriageRequest, TriageResult, Velocity, Waypoint, ZoneMode,
+};
+
+const BLESS_ENV: &str = "AETHERIS_BLESS_FIXTURES";
+const TIMESTAMP: u64 = 1_767_225_600_000; // 2026-01-01T00:00:00Z
+
+// ============================================================================
+// CANONICAL SAMPLES
+// ============================================================================
+
+fn sample_robot_state() -> RobotState {
+    RobotState {
+        id: "RV-001".parse().unwrap(),
+        name: "Rover Alpha".into(),
+        robot_type: RobotType::Rover,
+        position: Position::new(-2.0, 0.0, 1.5),
+        velocity: Velocity::new(1.2, 0.0, -0.25),
+        orientation: Orientation::new(0.0, 0.05, 0.0),
+        battery: 87.5,
+        signal: 95.0,
+        health: HealthStatus::Warning,
+        status: RobotStatus::Active,
+        current_task: CurrentTask::Patrolling {
+            route_id: "ROUTE-A1".into(),
+        },
+        timestamp: Timestamp::from_millis(TIMESTAMP),
+    }
+}
+
+fn sample_current_tasks() -> Vec<(&'static str, CurrentTask)> {
+    vec![
+        ("current_task_none", CurrentTask::None),
+        (
+            "current_task_patrolling",
+            CurrentTask::Patrolling {
+                route_id: "ROUTE-A1".into(),
+            },
+        ),
+        (
+            "current_task_moving_to",
+            CurrentTask::MovingTo {
+                target: Position::new(10.0, 0.0, -5.0),
+            },
+        ),
+        (
+            "current_task_scanning",
+            CurrentTask::Scanning {
+                scan_type: ScanType::Ultrasonic,
+            },
+        ),
+        (
+            "current_task_returning_to_base",
+            CurrentTask::ReturningToBase,
+        ),
+        (
+            "current_task_investigating",
+            CurrentTask::Investigating {
+                anomaly_id: "ANM-19B2A3C4000-0001".into(),
+            },
+        ),
+    ]
+}
+
+fn sample_commands() -> Vec<(&'static str, Command)> {
+    vec![
+        (
+            "command_move_to",
+            Command::MoveTo {
+                target: Position::new(10.0, 20.0, 0.0),
+                speed: Some(2.5),
+            },
+        ),
+        ("command_stop", Command::Stop),
+        (
+            "command_perform_scan",
+            Command::PerformScan {
+                scan_type: ScanType::LeakDetection,
+            },
+        ),
+        (
+            "command_start_patrol",
+            Command::StartPatrol {
+                route_id: "ROUTE-AIR-1".into(),
+            },
+        ),
+        ("command_return_to_base", Command::ReturnToBase),
+        (
+            "command_investigate",
+            Command::Investigate {
+                anomaly_id: "ANM-19B2A3C4000-0001".into(),
+            },
+        ),
+        ("command_emergency_stop", Command::EmergencyStop),
+        (
+            "command_inject_fault",
+            Command::InjectFault {
+                fault_type: FaultType::GpsDrift,
+            },
+        ),
+        (
+            "command_clear_fault",
+            Command::ClearFault {
+                fault_type: Some(FaultType::GpsDrift),
+            },
+        ),
+        (
+            "command_inject_leak",
+            Command::InjectLeak {
+                section_id: "PIPE-003".into(),
+                severity: SeverityLevel::High,
+            },
+        ),
+        (
+            "command_clear_leak",
+            Command::ClearLeak {
+                section_id: Some("PIPE-003".into()),
+            },
+        ),
+        (
+            "command_configure",
+            Command::Configure {
+                config: RobotConfig {
+                    max_speed: Some(3.0),
+                    scan_interval: None,
+                    heartbeat_interval: Some(5),
+                    low_battery_threshold: Some(20.0),
+                    ..RobotConfig::default()
+                },
+            },
+        ),
+        (
+            "command_configure_capabilities",
+            Command::Configure {
+                config: RobotConfig {
+                    max_speed: Some(1.0),
+                    supported_scans: Some(vec![ScanType::Ultrasonic, ScanType::Visual]),
+                    operating_altitude: Some(AltitudeRange::new(-2.0, 2.0)),
+                    ..RobotConfig::default()
+                },
+            },
+        ),
+        (
+            "command_register_section",
+            Command::RegisterSection {
+                section_id: "PIPE-001".into(),
+                position: Position::new(0.0, -0.5, 5.0),
+                merge_from: Some("PIPE-0O1".into()),
+            },
+        ),
+        (
+            "command_set_zone_mode",
+            Command::SetZoneMode {
+                zone_id: "ZONE-NORTH".into(),
+                mode: ZoneMode::Restricted {
+                    disallowed: vec![OperationKind::Scan, OperationKind::DroneFlight],
+                },
+                until: Some(TIMESTAMP + 3_600_000),
+            },
+        ),
+        (
+            "command_assign_anomaly",
+            Command::AssignAnomaly {
+                anomaly_id: "ANM-19B2A3C4000-0001".into(),
+                assignee: "j.ortega".into(),
+                due_at: TIMESTAMP + 4 * 3_600_000,
+            },
+        ),
+        (
+            "command_update_assignment",
+            Command::UpdateAssignment {
+                anomaly_id: "ANM-19B2A3C4000-0001".into(),
+                state: AssignmentState::Done,
+            },
+        ),
+        (
+            "command_acknowledge_anomaly",
+            Command::AcknowledgeAnomaly {
+                anomaly_id: "ANM-19B2A3C4000-0001".into(),
+            },
+        ),
+        (
+            "command_resolve_anomaly",
+            Command::ResolveAnomaly {
+                anomaly_id: "ANM-19B2A3C4000-0001".into(),
+                resolution: Resolution::FalsePositive,
+                force: true,
+            },
+        ),
+        ("command_request_keyframe", Command::RequestKeyframe),
+        ("command_correct_position", Command::CorrectPosition),
+        (
+            "command_start_mission",
+            Command::StartMission {
+                plan: sample_mission_plan(),
+            },
+        ),
+        (
+            "command_start_mission_with_preconditions",
+            Command::StartMission {
+                plan: MissionPlan {
+                    steps: vec![MissionStep {
+                        preconditions: vec![
+                            Precondition::MinBattery(40.0),
+                            Precondition::Status(RobotStatus::Idle),
+                            Precondition::Near {
+                                position: Position::new(12.0, -0.5, 5.0),
+                                radius: 1.5,
+                            },
+                        ],
+                        ..sample_mission_plan().steps[1].clone()
+                    }],
+                    ..sample_mission_plan()
+                },
+            },
+        ),
+        (
+            "command_abort_mission",
+            Command::AbortMission {
+                mission_id: "MSN-0042".into(),
+            },
+        ),
+    ]
+}
+
+fn sample_mission_plan() -> MissionPlan {
+    MissionPlan {
+        id: "MSN-0042".into(),
+        robot_id: "CR-001".into(),
+        steps: vec![
+            MissionStep {
+                command: Command::MoveTo {
+                    target: Position::new(12.0, -0.5, 5.0),
+                    speed: Some(0.5),
+                },
+                timeout_secs: 60,
+                on_failure: FailurePolicy::Retry(2),
+                preconditions: Vec::new(),
+            },
+            MissionStep {
+                command: Command::PerformScan {
+                    scan_type: ScanType::Ultrasonic,
+                },
+                timeout_secs: 30,
+                on_failure: FailurePolicy::Continue,
+                preconditions: Vec::new(),
+            },
+            MissionStep {
+                command: Command::ReturnToBase,
+                timeout_secs: 120,
+                on_failure: FailurePolicy::Abort,
+                preconditions: Vec::new(),
+            },
+        ],
+    }
+}
+
+fn sample_mission_status() -> MissionStatus {
+    MissionStatus {
+        mission_id: "MSN-0042".into(),
+        robot_id: "CR-001".into(),
+        state: MissionState::Running,
+        step: 2,
+        steps: 3,
+        attempt: 1,
+        skipped: vec![1],
+        error: Some("no response within 30s".into()),
+        timestamp: TIMESTAMP,
+    }
+}
+
+fn sample_anomaly_report() -> AnomalyReport {
+    AnomalyReport {
+        id: "ANM-19B2A3C4000-0001".into(),
+        anomaly_type: AnomalyType::Leak,
+        severity: SeverityLevel::High,
+        position: Position::new(5.0, 0.0, 10.0),
+        section_id: "PIPE-001".into(),
+        detected_by: "RV-001".into(),
+        confidence: 0.94,
+        description: "Hydrogen leak detected at joint H-7".into(),
+        timestamp: Timestamp::from_millis(TIMESTAMP),
+        acknowledged: false,
+        status: AnomalyStatus::New,
+        status_history: Vec::new(),
+        acknowledged_by: None,
+        resolved_by: None,
+        triage: None,
+        correlated_commands: Vec::new(),
+        urgency: NotificationUrgency::Normal,
+        measurement: None,
+        assignment: None,
+        occurrence_count: 1,
+        last_seen: None,
+        escalation_count: 0,
+    }
+}
+
+fn sample_filtered_telemetry() -> FilteredTelemetry {
+    FilteredTelemetry {
+        robot_id: "RV-001".parse().unwrap(),
+        position: Position::new(12.4, -3.1, 0.0),
+        velocity: Velocity::new(0.8, 0.1, 0.0),
+        coasting: false,
+        timestamp: TIMESTAMP,
+    }
+}
+
+fn sample_robot_state_delta() -> RobotStateDelta {
+    RobotStateDelta {
+        id: "RV-001".parse().unwrap(),
+        timestamp: Timestamp::from_millis(TIMESTAMP + 1_000),
+        name: None,
+        robot_type: None,
+        position: Some(Position::new(-0.8, 0.0, 1.25)),
+        velocity: None,
+        orientation: None,
+        battery: Some(87.4),
+        signal: None,
+        health: None,
+        status: None,
+        current_task: None,
+    }
+}
+
+fn sample_telemetry_batch() -> TelemetryBatch {
+    let mut drone = sample_robot_state();
+    drone.id = "DR-001".parse().unwrap();
+    drone.name = "Drone Alpha".into();
+    drone.robot_type = RobotType::Drone;
+    TelemetryBatch {
+        source: "engine".into(),
+        timestamp: TIMESTAMP,
+        states: vec![sample_robot_state(), drone],
+    }
+}
+
+fn sample_patrol_route() -> PatrolRoute {
+    PatrolRoute {
+        id: "ROUTE-A1".into(),
+        name: "East perimeter".into(),
+        waypoints: vec![
+            Waypoint::at(Position::new(-2.0, 0.0, 1.0)),
+            Waypoint {
+                position: Position::new(40.0, 0.0, 1.0),
+                dwell_secs: Some(15.0),
+                scan: Some(ScanType::LeakDetection),
+            },
+            Waypoint::at(Position::new(40.0, 25.0, 1.0)),
+        ],
+        mode: RouteMode::Loop,
+    }
+}
+
+fn sample_route_library() -> RouteLibrary {
+    RouteLibrary::new([
+        sample_patrol_route(),
+        PatrolRoute::new(
+            "ROUTE-B2",
+            "Compressor yard",
+            RouteMode::OneShot,
+            vec![Waypoint::at(Position::new(12.0, -8.0, 0.0))],
+        ),
+    ])
+}
+
+fn sample_pipeline_section() -> PipelineSection {
+    PipelineSection {
+        id: "PIPE-002".into(),
+        start: Position::new(0.0, -0.5, 5.0),
+        end: Position::new(30.0, -0.5, 5.0),
+        diameter_mm: 508.0,
+        material: PipeMaterial::CarbonSteel,
+        design_pressure_bar: 70.0,
+        neighbors: vec!["PIPE-001".into()],
+    }
+}
+
+fn sample_charging_station() -> ChargingStation {
+    ChargingStation::new("CHG-01", Position::new(-5.0, 0.0, 1.0), 2)
+}
+
+fn sample_geofence() -> Geofence {
+    Geofence {
+        altitude: Some(AltitudeRange::new(0.0, 30.0)),
+        robot_types: vec![RobotType::Drone],
+        ..Geofence::new(
+            "FENCE-COMPRESSOR",
+            vec![
+                Position::new(40.0, 0.0, -10.0),
+                Position::new(60.0, 0.0, -10.0),
+                Position::new(60.0, 0.0, 10.0),
+                Position::new(40.0, 0.0, 10.0),
+            ],
+        )
+    }
+}
+
+fn sample_robot_view() -> RobotView {
+    RobotView {
+        filtered_position: Some(Position::new(12.4, -3.1, 0.0)),
+        filtered_velocity: Some(Velocity::new(0.8, 0.1, 0.0)),
+        ..RobotView::from(sample_robot_state())
+    }
+}
+
+fn sample_pipe_environment() -> PipeEnvironment {
+    PipeEnvironment {
+        section_id: "PIPE-001".into(),
+        pressure: 52.5,
+        temperature: 24.0,
+        h2_concentration: 120.0,
+        wall_thickness: 9.75,
+        flow_rate: 480.0,
+        humidity: 45.5,
+        position: Position::new(0.0, -0.5, 5.0),
+        timestamp: Timestamp::from_millis(TIMESTAMP),
+    }
+}
+
+fn sample_section_health() -> SectionHealthReport {
+    SectionHealthReport {
+        section_id: "PIPE-001".into(),
+        score: 70,
+        open_anomalies: SeverityLevel::ALL
+            .map(|level| (level, usize::from(level == SeverityLevel::High)))
+            .into(),
+        environment: Some(sample_pipe_environment()),
+        last_inspection: Some(TIMESTAMP),
+        factors: vec![
+            HealthFactor {
+                kind: HealthFactorKind::OpenAnomalies,
+                penalty: 20.0,
+                detail: "1 high open".into(),
+            },
+            HealthFactor {
+                kind: HealthFactorKind::StaleReadings,
+                penalty: 10.0,
+                detail: "no reading for 12 min".into(),
+            },
+        ],
+        timestamp: TIMESTAMP + 12 * 60_000,
+    }
+}
+
+fn sample_heartbeat() -> Heartbeat {
+    Heartbeat {
+        robot_id: "DR-001".parse().unwrap(),
+        robot_type: RobotType::Drone,
+        status: RobotStatus::Active,
+        battery: 94.0,
+        signal: 99.0,
+        uptime: 3600,
+        timestamp: Timestamp::from_millis(TIMESTAMP),
+    }
+}
+
+fn sample_command_response() -> CommandResponse {
+    CommandResponse {
+        command_id: "CMD-0001".into(),
+        robot_id: "CR-001".parse().unwrap(),
+        success: false,
+        error: Some("unknown robot".into()),
+        config: None,
+        status: None,
+        timestamp: TIMESTAMP,
+    }
+}
+
+fn sample_broadcast_result() -> BroadcastResult {
+    BroadcastResult {
+        command_id: "CMD-6f1c2a9e".into(),
+        command: "emergency_stop".into(),
+        acked: vec!["RV-001".into(), "RV-002".into(), "DR-001".into()],
+        failed: vec!["CR-001".into()],
+        non_responding: vec!["DR-002".into()],
+        started_at: TIMESTAMP,
+        completed_at: TIMESTAMP + 10_000,
+    }
+}
+
+fn sample_triaged_report() -> AnomalyReport {
+    AnomalyReport {
+        severity: SeverityLevel::Medium,
+        confidence: 0.6,
+        triage: Some(TriageAudit {
+            original_severity: SeverityLevel::High,
+            original_confidence: 0.94,
+            recommended_action: Some(TriageAction::Monitor),
+            rationale: "Reading consistent with purge venting".into(),
+            timed_out: false,
+            completed_at: TIMESTAMP + 2_000,
+        }),
+        ..sample_anomaly_report()
+    }
+}
+
+fn sample_correlated_report() -> AnomalyReport {
+    AnomalyReport {
+        anomaly_type: AnomalyType::Unknown,
+        section_id: "SYSTEM".into(),
+        correlated_commands: vec![CorrelatedCommand {
+            command_id: "CMD-0001".into(),
+            variant: "inject_fault".into(),
+            issued_by: "dashboard".into(),
+            seconds_before: 1.5,
+        }],
+        urgency: NotificationUrgency::Reduced,
+        ..sample_anomaly_report()
+    }
+}
+
+fn sample_assigned_report() -> AnomalyReport {
+    AnomalyReport {
+        acknowledged: true,
+        assignment: Some(Assignment {
+            assignee: "j.ortega".into(),
+            assigned_by: "dashboard".into(),
+            assigned_at: TIMESTAMP,
+            due_at: TIMESTAMP + 4 * 3_600_000,
+            state: AssignmentState::InProgress,
+        }),
+        ..sample_anomaly_report()
+    }
+}
+
+fn sample_resolved_report() -> AnomalyReport {
+    let mut report = sample_anomaly_report();
+    report.set_status(AnomalyStatus::Acknowledged, "j.ortega", TIMESTAMP + 60_000);
+    report.set_status(
+        AnomalyStatus::Investigating,
+        "dashboard",
+        TIMESTAMP + 120_000,
+    );
+    report.set_status(AnomalyStatus::Resolved, "j.ortega", TIMESTAMP + 3_600_000);
+    report
+}
+
+fn sample_trend_report() -> AnomalyReport {
+    AnomalyReport {
+        anomaly_type: AnomalyType::Unknown,
+        severity: SeverityLevel::Medium,
+        section_id: "SYSTEM".into(),
+        description: "Robot RV-001 battery discharging at 7.5%/min".into(),
+        measurement: Some(Measurement {
+            name: "battery_discharge_rate".into(),
+            value: 7.5,
+            unit: "%/min".into(),
+        }),
+        ..sample_anomaly_report()
+    }
+}
+
+fn sample_repeated_report() -> AnomalyReport {
+    AnomalyReport {
+        detected_by: "engine".into(),
+        occurrence_count: 7,
+        last_seen: Some(TIMESTAMP + 90_000),
+        ..sample_anomaly_report()
+    }
+}
+
+fn sample_escalated_report() -> AnomalyReport {
+    AnomalyReport {
+        severity: SeverityLevel::Critical,
+        escalation_count: 2,
+        ..sample_anomaly_report()
+    }
+}
+
+fn sample_triage_request() -> TriageRequest {
+    TriageRequest {
+        report: sample_anomaly_report(),
+        nearby_robots: vec![NearbyRobot {
+            robot_id: "CR-001".into(),
+            robot_type: RobotType::Crawler,
+            status: RobotStatus::Idle,
+            battery: 71.0,
+            distance: 4.5,
+        }],
+        latest_environment: Some(sample_pipe_environment()),
+        timestamp: TIMESTAMP,
+    }
+}
+
+fn sample_triage_result() -> TriageResult {
+    TriageResult {
+        anomaly_id: "ANM-19B2A3C4000-0001".into(),
+        adjusted_severity: Some(SeverityLevel::Medium),
+        adjusted_confidence: None,
+        recommended_action: TriageAction::Dispatch,
+        rationale: "Concentration rising in adjacent sections".into(),
+        timestamp: TIMESTAMP,
+    }
+}
+
+fn sample_system_status() -> SystemStatus {
+    let count = |connected, offline| FleetCount { connected, offline };
+    SystemStatus {
+        uptime_secs: 3_600,
+        fleet: BTreeMap::from([
+            (RobotType::Rover, count(3, 1)),
+            (RobotType::Drone, count(1, 0)),
+            (RobotType::Crawler, count(0, 1)),
+        ]),
+        unacknowledged_anomalies: BTreeMap::from([
+            (SeverityLevel::Info, 0),
+            (SeverityLevel::Low, 2),
+            (SeverityLevel::Medium, 0),
+            (SeverityLevel::High, 1),
+            (SeverityLevel::Critical, 0),
+        ]),
+        open_assignments: BTreeMap::from([("j.ortega".into(), 2)]),
+        messages_per_sec: 12.5,
+        missed_messages: BTreeMap::from([("RV-001".into(), 3)]),
+        dropped_messages: BTreeMap::from([("telemetry".into(), 42)]),
+        link_quality: BTreeMap::from([(
+            "RV-001".into(),
+            LinkQuality {
+                offset_ms: 48,
+                jitter_ms: 6,
+                samples: 20,
+                drifted: false,
+            },
+        )]),
+        leadership: Some(LeaderStatus {
+            instance_id: "engine-a".into(),
+            leader: Some("engine-b".into()),
+            changes: 2,
+            since: TIMESTAMP - 60_000,
+        }),
+        ..SystemStatus::online("aetheris-engine-1", 4, TIMESTAMP)
+    }
+}
+
+fn sample_leader_claim() -> LeaderClaim {
+    LeaderClaim {
+        instance_id: "engine-b".into(),
+        claimed_at: TIMESTAMP - 60_000,
+        renewed_at: TIMESTAMP,
+        lease_ms: 10_000,
+        released: false,
+    }
+}
+
+fn sample_dead_letter() -> DeadLetter {
+    DeadLetter {
+        topic: "aetheris/telemetry/RV-001".into(),
+        reason: DeadLetterReason::SourceMismatch,
+        detail: "source \"dashboard\" may not publish on aetheris/telemetry/RV-001".into(),
+        claimed_source: Some("dashboard".into()),
+        payload: r#"{"payload":{},"source":"dashboard","timestamp":0,"seq":0}"#.into(),
+        payload_base64: false,
+        received_at: TIMESTAMP,
+        suppressed: 0,
+    }
+}
+
+fn sample_timeline_entry() -> TimelineEntry {
+    TimelineEntry {
+        timestamp: TIMESTAMP,
+        kind: TimelineEntryKind::Command,
+        subject: "RV-001".into(),
+        summary: "investigate sent to RV-001 by engine".into(),
+        records: vec![
+            RecordRef::new(RecordStore::Commands, "CMD-1"),
+            RecordRef::new(RecordStore::Events, "EVT-1"),
+        ],
+    }
+}
+
+fn envelope<T>(payload: T, source: &str) -> MqttMessage<T> {
+    MqttMessage {
+        payload,
+        source: source.into(),
+        timestamp: Timestamp::from_millis(TIMESTAMP),
+        seq: 42,
+        version: CURRENT_VERSION,
+        command_id: None,
+        operator: None,
+        signature: None,
+        expires_at: None,
+    }
+}
+
+// ============================================================================
+// FIXTURE HARNESS
+// ============================================================================
+
+fn fixture_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/wire")
+}
+
+fn manifest_path() -> PathBuf {
+    fixture_dir().join("manifest.json")
+}
+
+/// Latest registered breaking-change version for a fixture (0 if none)
+fn registered_version(fixture: &str) -> u32 {
+    BREAKING_CHANGES
+        .iter()
+        .filter(|change| change.fixture == fixture)
+        .map(|change| change.version)
+        .max()
+        .unwrap_or(0)
+}
+
+struct Harness {
+    bless: bool,
+    manifest: BTreeMap<String, u32>,
+    failures: Vec<String>,
+    checked: Vec<&'static str>,
+}
+
+impl Harness {
+    fn new() -> Self {
+        let manifest = fs::read_to_string(manifest_path())
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        Self {
+            bless: std::env::var_os(BLESS_ENV).is_some(),
+            manifest,
+            failures: Vec::new(),
+            checked: Vec::new(),
+        }
+    }
+
+    fn check<T>(&mut self, name: &'static str, sample: &T)
+    where
+        T: Serialize + DeserializeOwned + PartialEq + Debug,
+    {
+        self.checked.push(name);
+        let path = fixture_dir().join(format!("{name}.json"));
+        let current = serde_json::to_string_pretty(sample).unwrap() + "\n";
+        let stored = fs::read_to_string(&path).ok();
+        let blessed_at = self.manifest.get(name).copied();
+        let registered = registered_version(name);
+
+        if self.bless {
+            let unchanged = stored.as_deref() == Some(current.as_str());
+            let allowed = stored.is_none() || blessed_at.is_none_or(|v| registered > v);
+            if !unchanged && !allowed {
+                self.failures.push(format!(
+                    "{name}: refusing to re-bless a changed fixture without a new \
+                     BREAKING_CHANGES entry (blessed at version {}, registry has {registered})",
+                    blessed_at.unwrap_or(0)
+                ));
+                return;
+            }
+            fs::write(&path, &current).unwrap();
+            self.manifest.insert(name.to_string(), registered);
+            return;
+        }
+
+        let Some(stored) = stored else {
+            self.failures.push(format!(
+                "{name}: missing fixture; run with {BLESS_ENV}=1 to create it"
+            ));
+            return;
+        };
+
+        // (a) byte-for-byte serialization
+        if stored != current {
+            let hint = if blessed_at.is_some_and(|v| registered > v) {
+                format!("a BREAKING_CHANGES entry exists; re-bless with {BLESS_ENV}=1")
+            } else {
+                "this is a wire break. If intentional, add a BREAKING_CHANGES entry \
+                 for this fixture and re-bless; otherwise restore the old format"
+                    .to_string()
+            };
+            self.failures.push(format!(
+                "{name}: serialization changed\n--- fixture\n{stored}--- current\n{current}{hint}"
+            ));
+        }
+
+        // (b) the stored fixture still deserializes into the current type
+        match serde_json::from_str::<T>(&stored) {
+            Ok(decoded) if stored == current && decoded != *sample => self
+                .failures
+                .push(format!("{name}: fixture decodes to a different value")),
+            Ok(_) => {}
+            Err(e) => self
+                .failures
+                .push(format!("{name}: fixture no longer deserializes: {e}")),
+        }
+
+        // (c) every encoding reproduces the sample
+        for encoding in Encoding::ALL {
+            let decoded = encoding
+                .encode(sample)
+                .and_then(|bytes| Encoding::decode_detected::<T>(&bytes));
+            match decoded {
+                Ok(decoded) if decoded == *sample => {}
+                Ok(decoded) => self.failures.push(format!(
+                    "{name}: {encoding:?} round trip changed the value to {decoded:?}"
+                )),
+                Err(e) => self
+                    .failures
+                    .push(format!("{name}: {encoding:?} round trip failed: {e}")),
+            }
+        }
+    }
+
+    fn finish(mut self) {
+        if self.bless {
+            self.manifest
+                .retain(|name, _| self.checked.contains(&name.as_str()));
+            let text = serde_json::to_string_pretty(&self.manifest).unwrap() + "\n";
+            fs::write(manifest_path(), text).unwrap();
+        } else {
+            for name in self.manifest.keys() {
+                if !self.checked.contains(&name.as_str()) {
+                    self.failures
+                        .push(format!("{name}: fixture in manifest has no sample value"));
+                }
+            }
+        }
+
+        assert!(
+            self.failures.is_empty(),
+            "wire compatibility failures:\n\n{}",
+            self.failures.join("\n\n")
+        );
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[test]
+fn test_wire_format_matches_golden_fixtures() {
+    let mut harness = Harness::new();
+
+    harness.check("robot_state", &sample_robot_state());
+    for (name, task) in sample_current_tasks() {
+        harness.check(name, &task);
+    }
+    for (name, command) in sample_commands() {
+        harness.check(name, &command);
+    }
+    harness.check("anomaly_report", &sample_anomaly_report());
+    harness.check("anomaly_report_triaged", &sample_triaged_report());
+    harness.check("anomaly_report_correlated", &sample_correlated_report());
+    harness.check("anomaly_report_trend", &sample_trend_report());
+    harness.check("anomaly_report_assigned", &sample_assigned_report());
+    harness.check("anomaly_report_resolved", &sample_resolved_report());
+    harness.check("anomaly_report_repeated", &sample_repeated_report());
+    harness.check("anomaly_report_escalated", &sample_escalated_report());
+    harness.check("filtered_telemetry", &sample_filtered_telemetry());
+    harness.check("telemetry_batch", &sample_telemetry_batch());
+    harness.check("robot_state_delta", &sample_robot_state_delta());
+    harness.check(
+        "telemetry_full",
+        &TelemetryPayload::Full(sample_robot_state()),
+    );
+    harness.check(
+        "telemetry_delta",
+        &TelemetryPayload::Delta(sample_robot_state_delta()),
+    );
+    harness.check("robot_view", &sample_robot_view());
+    harness.check("patrol_route", &sample_patrol_route());
+    harness.check("route_library", &sample_route_library());
+    harness.check("route_request", &RouteRequest::routes(["ROUTE-A1"]));
+    harness.check(
+        "route_request_with_requester",
+        &RouteRequest::all().with_requester("dashboard"),
+    );
+    harness.check("charging_station", &sample_charging_station());
+    harness.check("battery_profile", &RobotType::Crawler.battery_profile());
+    harness.check("geofence", &sample_geofence());
+    harness.check("pipeline_section", &sample_pipeline_section());
+    harness.check("pipe_environment", &sample_pipe_environment());
+    harness.check("section_health", &sample_section_health());
+    harness.check(
+        "section_health_unread",
+        &SectionHealthReport {
+            score: 90,
+            open_anomalies: SeverityLevel::ALL.map(|level| (level, 0)).into(),
+            environment: None,
+            last_inspection: None,
+            factors: vec![HealthFactor {
+                kind: HealthFactorKind::StaleReadings,
+                penalty: 10.0,
+                detail: "no environment reading yet".into(),
+            }],
+            ..sample_section_health()
+        },
+    );
+    harness.check("mission_status", &sample_mission_status());
+    harness.check(
+        "mission_status_completed",
+        &MissionStatus {
+            state: MissionState::Completed,
+            skipped: Vec::new(),
+            error: None,
+            ..sample_mission_status()
+        },
+    );
+    harness.check("heartbeat", &sample_heartbeat());
+    harness.check("command_response", &sample_command_response());
+    harness.check(
+        "command_response_in_progress",
+        &CommandResponse {
+            success: true,
+            error: None,
+            status: Some(CommandStatus::InProgress),
+            ..sample_command_response()
+        },
+    );
+    harness.check("broadcast_result", &sample_broadcast_result());
+    harness.check("triage_request", &sample_triage_request());
+    harness.check("triage_result", &sample_triage_result());
+    harness.check("dead_letter", &sample_dead_letter());
+    harness.check("system_status", &sample_system_status());
+    harness.check("leader_claim", &sample_leader_claim());
+    harness.check(
+        "system_status_offline",
+        &SystemStatus::offline("aetheris-engine-1", TIMESTAMP),
+    );
+    harness.check("timeline_entry", &sample_timeline_entry());
+
+    harness.check(
+        "envelope_robot_state",
+        &envelope(sample_robot_state(), "RV-001"),
+    );
+    harness.check(
+        "envelope_command",
+        &envelope(Command::EmergencyStop, "dashboard"),
+    );
+    harness.check(
+        "envelope_command_tracked",
+        &envelope(Command::Stop, "engine").with_command_id("CMD-6f1c2a9e"),
+    );
+    harness.check(
+        "envelope_command_expiring",
+        &MqttMessage {
+            expires_at: Some(TIMESTAMP + 30_000),
+            ..envelope(
+                Command::MoveTo {
+                    target: Position::new(10.0, 0.0, 5.0),
+                    speed: Some(1.5),
+                },
+                "engine",
+            )
+            .with_command_id("CMD-6f1c2a9e")
+        },
+    );
+    harness.check(
+        "envelope_command_signed",
+        &MqttMessage {
+            signature: Some(
+                "4f2b8c0d9e1a7f3b5c6d2e8a0b1c9d7e3f5a4b6c8d0e2f1a3b5c7d9e0f2a4b6c".into(),
+            ),
+            ..envelope(Command::EmergencyStop, "dashboard")
+                .with_command_id("CMD-6f1c2a9e")
+                .with_operator(Operator::new("ops-alice", OperatorRole::Admin))
+        },
+    );
+    harness.check(
+        "envelope_telemetry_delta",
+        &envelope(
+            TelemetryPayload::Delta(sample_robot_state_delta()),
+            "RV-001",
+        ),
+    );
+    harness.check(
+        "envelope_telemetry_batch",
+        &envelope(sample_telemetry_batch(), "engine"),
+    );
+    harness.check(
+        "envelope_anomaly_report",
+        &envelope(sample_anomaly_report(), "RV-001"),
+    );
+    harness.check(
+        "envelope_pipe_environment",
+        &envelope(sample_pipe_environment(), "PIPE-001"),
+    );
+
+    harness.finish();
+}
+
+#[test]
+fn test_cbor_telemetry_is_smaller_than_json() {
+    let telemetry = envelope(sample_robot_state(), "RV-001");
+    let json = Encoding::Json.encode(&telemetry).unwrap();
+    let cbor = Encoding::Cbor.encode(&telemetry).unwrap();
+    assert!(
+        cbor.len() * 10 < json.len() * 9,
+        "CBOR telemetry is {} bytes, JSON {}",
+        cbor.len(),
+        json.len()
+    );
+    assert_eq!(Encoding::detect(&json), Encoding::Json);
+    assert_eq!(Encoding::detect(&cbor), Encoding::Cbor);
+    // Dead letters show CBOR payloads as JSON
+    let text: serde_json::Value = serde_json::from_str(&Encoding::payload_text(&cbor)).unwrap();
+    assert_eq!(text, serde_json::to_value(&telemetry).unwrap());
+
+    // Bytes that are neither travel as base64
+    let mut letter = sample_dead_letter();
+    letter.set_payload(&cbor);
+    assert!(!letter.payload_base64);
+    letter.set_payload(&[0xff, 0xfe, 0x00, 0x41]);
+    assert!(letter.payload_base64);
+    assert_eq!(letter.payload, "//4AQQ==");
+}
+
+#[test]
+fn test_msgpack_telemetry_is_compact_and_told_apart() {
+    let telemetry = envelope(sample_robot_state(), "RV-001");
+    let json = Encoding::Json.encode(&telemetry).unwrap();
+    let msgpack = Encoding::MessagePack.encode(&telemetry).unwrap();
+    assert!(
+        msgpack.len() * 10 < json.len() * 9,
+        "MessagePack telemetry is {} bytes, JSON {}",
+        msgpack.len(),
+        json.len()
+    );
+    assert_eq!(Encoding::detect(&msgpack), Encoding::MessagePack);
+    assert_eq!("msgpack".parse(), Ok(Encoding::MessagePack));
+    let text: serde_json::Value = serde_json::from_str(&Encoding::payload_text(&msgpack)).unwrap();
+    assert_eq!(text, serde_json::to_value(&telemetry).unwrap());
+    let mut letter = sample_dead_letter();
+    letter.set_payload(&msgpack);
+    assert!(!letter.payload_base64);
+
+    // Only what JSON could carry: string keys, one value per payload
+    let decode = |bytes: &[u8]| Encoding::MessagePack.decode::<serde_json::Value>(bytes);
+    assert!(decode(&[0x81, 0x01, 0x02]).is_err());
+    assert!(decode(&[0x80, 0x80]).is_err());
+    assert!(decode(&[0x81, 0xa1, b'a']).is_err());
+    assert_eq!(decode(&[0x81, 0xa1, b'a', 0xc3]).unwrap()["a"], true);
+}
+
+#[test]
+fn test_breaking_change_registry_is_well_formed() {
+    let manifest: BTreeMap<String, u32> =
+        serde_json::from_str(&fs::read_to_string(manifest_path()).unwrap()).unwrap();
+
+    let mut previous = 1;
+    for change in BREAKING_CHANGES {
+        assert!(
+            change.version >= previous,
+            "BREAKING_CHANGES versions must start at 1 and never decrease (found {} after {})",
+            change.version,
+            previous
+        );
+        previous = change.version;
+        assert!(
+            manifest.contains_key(change.fixture),
+            "BREAKING_CHANGES entry v{} names unknown fixture '{}'",
+            change.version,
+            change.fixture
+        );
+        assert!(!change.description.is_empty());
+    }
+}